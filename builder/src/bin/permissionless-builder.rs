@@ -6,6 +6,7 @@ use espresso_types::{eth_signature_key::EthKeyPair, parse_duration, SequencerVer
 use futures::future::pending;
 use hotshot::traits::ValidatedState;
 use hotshot_types::{
+    api_auth::ApiCredentials,
     data::ViewNumber,
     traits::node_implementation::{ConsensusTime, Versions},
 };
@@ -25,6 +26,20 @@ struct NonPermissionedBuilderOptions {
     )]
     hotshot_event_streaming_url: Url,
 
+    /// API key to present to the hotshot events API, if it requires authentication.
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_EVENTS_API_KEY",
+        conflicts_with = "sign_events_api_requests"
+    )]
+    events_api_key: Option<String>,
+
+    /// Authenticate to the hotshot events API by signing requests with the builder account key.
+    ///
+    /// The events API must list the builder account address in its API key configuration.
+    #[clap(long, env = "ESPRESSO_BUILDER_SIGN_EVENTS_API_REQUESTS")]
+    sign_events_api_requests: bool,
+
     /// Mnemonic phrase for builder account.
     ///
     /// This is the address fees will be charged to.
@@ -101,6 +116,12 @@ struct NonPermissionedBuilderOptions {
     #[clap(long, name = "GENESIS_FILE", env = "ESPRESSO_BUILDER_GENESIS_FILE")]
     genesis_file: PathBuf,
 
+    /// File configuring the API keys clients must present to submit transactions to the builder.
+    ///
+    /// If not set, anyone can submit.
+    #[clap(long, env = "ESPRESSO_BUILDER_API_KEYS")]
+    api_keys: Option<PathBuf>,
+
     #[clap(flatten)]
     logging: logging::Config,
 }
@@ -170,12 +191,23 @@ async fn run<V: Versions>(
     };
 
     let builder_key_pair = EthKeyPair::from_mnemonic(&opt.eth_mnemonic, opt.eth_account_index)?;
+    let events_api_credentials = match opt.events_api_key {
+        Some(key) => Some(ApiCredentials::Key(key)),
+        None if opt.sign_events_api_requests => {
+            Some(ApiCredentials::Signer(builder_key_pair.signer()))
+        },
+        None => None,
+    };
     let bootstrapped_view = ViewNumber::new(opt.view_number);
 
     let builder_server_url: Url = format!("http://0.0.0.0:{}", opt.port).parse().unwrap();
 
-    let instance_state =
+    let mut instance_state =
         build_instance_state::<V>(genesis.chain_config, l1_params, opt.state_peers);
+    // Only build blocks the namespace registry in force admits.
+    for registry in genesis.namespace_registries()? {
+        instance_state = instance_state.with_namespace_registry(registry);
+    }
 
     let base_fee = genesis.max_base_fee();
     tracing::info!(?base_fee, "base_fee");
@@ -202,6 +234,8 @@ async fn run<V: Versions>(
         txn_timeout_duration,
         base_fee,
         opt.tx_status_cache_size,
+        opt.api_keys,
+        events_api_credentials,
     )
     .await?;
 
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use espresso_types::SeqTypes;
 use hotshot_builder_api::v0_1::builder::{
     Error as BuilderApiError, Options as HotshotBuilderApiOptions,
@@ -11,7 +13,14 @@ use vbs::version::{StaticVersion, StaticVersionType};
 pub mod non_permissioned;
 
 // It runs the api service for the builder
-pub fn run_builder_api_service(url: Url, source: ProxyGlobalState<SeqTypes>) {
+//
+// If `api_keys` is set, submitting transactions to the private mempool requires one of the keys
+// configured in that file.
+pub fn run_builder_api_service(
+    url: Url,
+    source: ProxyGlobalState<SeqTypes>,
+    api_keys: Option<PathBuf>,
+) {
     // it is to serve hotshot
     let builder_api = hotshot_builder_api::v0_1::builder::define_api::<
         ProxyGlobalState<SeqTypes>,
@@ -24,7 +33,10 @@ pub fn run_builder_api_service(url: Url, source: ProxyGlobalState<SeqTypes>) {
         ProxyGlobalState<SeqTypes>,
         SeqTypes,
         StaticVersion<0, 1>,
-    >(&HotshotBuilderApiOptions::default())
+    >(&HotshotBuilderApiOptions {
+        api_keys,
+        ..Default::default()
+    })
     .expect("Failed to construct the builder API for private mempool txns");
 
     let mut app: App<ProxyGlobalState<SeqTypes>, BuilderApiError> = App::with_state(source);
@@ -53,7 +65,7 @@ pub mod testing {
     use async_lock::RwLock;
     use committable::Committable;
     use espresso_types::{
-        traits::SequencerPersistence, v0_99::ChainConfig, Event, FeeAccount, NamespaceId,
+        traits::SequencerPersistence, v0_4::ChainConfig, Event, FeeAccount, NamespaceId,
         NodeState, PrivKey, PubKey, Transaction, ValidatedState,
     };
     use futures::stream::{Stream, StreamExt};
@@ -143,6 +155,13 @@ pub mod testing {
                 stop_voting_time: 0,
                 epoch_height: 0,
                 epoch_start_block: 0,
+                da_payload_hint_threshold: None,
+                da_payload_hint_urls: vec![],
+                compression: None,
+                bandwidth: Default::default(),
+                local_builder: None,
+                adaptive_view_timeout: None,
+                transaction_dedup: None,
             };
 
             Self {
@@ -373,6 +392,8 @@ pub mod testing {
                 Duration::from_millis(500),
                 ChainConfig::default().base_fee,
                 819200,
+                None,
+                None,
             )
             .await
             .unwrap();
@@ -1,10 +1,10 @@
-use std::{collections::VecDeque, num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{collections::VecDeque, num::NonZeroUsize, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::Context;
 use async_broadcast::broadcast;
 use async_lock::RwLock;
 use espresso_types::{
-    eth_signature_key::EthKeyPair, v0_1::NoStorage, v0_99::ChainConfig, EpochCommittees, FeeAmount,
+    eth_signature_key::EthKeyPair, v0_1::NoStorage, v0_4::ChainConfig, EpochCommittees, FeeAmount,
     NodeState, Payload, SeqTypes, ValidatedState,
 };
 use hotshot::traits::BlockPayload;
@@ -16,6 +16,7 @@ use hotshot_builder_core::{
     },
 };
 use hotshot_types::{
+    api_auth::ApiCredentials,
     data::{fake_commitment, vid_commitment, ViewNumber},
     epoch_membership::EpochMembershipCoordinator,
     traits::{
@@ -93,6 +94,8 @@ impl BuilderConfig {
         maximize_txns_count_timeout_duration: Duration,
         base_fee: FeeAmount,
         tx_status_cache_size: usize,
+        api_keys: Option<PathBuf>,
+        events_api_credentials: Option<ApiCredentials>,
     ) -> anyhow::Result<Self> {
         tracing::info!(
             address = %builder_key_pair.fee_account(),
@@ -197,15 +200,22 @@ impl BuilderConfig {
         );
 
         // start the hotshot api service
-        run_builder_api_service(hotshot_builder_apis_url.clone(), proxy_global_state);
+        run_builder_api_service(
+            hotshot_builder_apis_url.clone(),
+            proxy_global_state,
+            api_keys,
+        );
 
         // spawn the builder service
         let events_url = hotshot_events_api_url.clone();
         let global_state_clone = global_state.clone();
         tracing::info!("Running permissionless builder against hotshot events API at {events_url}",);
 
-        let event_stream =
-            EventServiceStream::<SeqTypes, SequencerApiVersion>::connect(events_url).await?;
+        let event_stream = EventServiceStream::<SeqTypes, SequencerApiVersion>::connect(
+            events_url,
+            events_api_credentials,
+        )
+        .await?;
 
         spawn(async move {
             let res = run_non_permissioned_standalone_builder_service::<_, SequencerApiVersion, _>(
@@ -285,6 +295,7 @@ mod test {
                     )
                     .hotshot_events(HotshotEvents {
                         events_service_port: event_port,
+                        ..Default::default()
                     }),
             )
             .network_config(network_config)
@@ -0,0 +1,303 @@
+//! Light synchronization of the Espresso header chain.
+//!
+//! [`HeaderSyncer`] follows the chain of Espresso headers from a trusted starting point, using the
+//! `availability/header-chain` endpoint of a query service. Each batch of headers comes with the
+//! leaves that contain them and a single quorum certificate for the last leaf. Since every leaf
+//! commits to its parent, checking the hash links from the trusted anchor and that one certificate
+//! is enough to authenticate the whole batch, without downloading payloads or checking a signature
+//! for every block.
+
+use std::cmp::min;
+
+use alloy::primitives::U256;
+use anyhow::{bail, ensure, Context};
+use committable::{Commitment, Committable};
+use espresso_types::{Header, Leaf2, SeqTypes};
+use hotshot_query_service::availability::HeaderChainQueryData;
+use hotshot_types::{
+    message::UpgradeLock,
+    traits::{block_contents::BlockHeader, node_implementation::Versions},
+    PeerConfig, StakeTableEntries,
+};
+
+use crate::SequencerClient;
+
+pub type HeaderChain = HeaderChainQueryData<SeqTypes>;
+
+/// The default number of headers to request at a time.
+///
+/// This should not exceed the `small_object_range_limit` of the query service we are syncing from.
+pub const DEFAULT_BATCH_SIZE: u64 = 100;
+
+/// A client which downloads and verifies Espresso headers.
+///
+/// The syncer starts from a trusted anchor: the height of the next header to sync and the
+/// commitment of the leaf immediately preceding it. Each call to [`sync`](Self::sync) extends the
+/// verified chain by up to one batch of headers and moves the anchor forward.
+///
+/// Certificates are checked against a fixed stake table. If the stake table changes (for example,
+/// at an epoch boundary), the caller is responsible for updating it with
+/// [`set_stake_table`](Self::set_stake_table) before syncing past the change.
+#[derive(Clone, Debug)]
+pub struct HeaderSyncer<V: Versions> {
+    client: SequencerClient,
+    height: u64,
+    parent: Commitment<Leaf2>,
+    stake_table: Vec<PeerConfig<SeqTypes>>,
+    threshold: U256,
+    batch_size: u64,
+    upgrade_lock: UpgradeLock<SeqTypes, V>,
+}
+
+impl<V: Versions> HeaderSyncer<V> {
+    /// Create a syncer which will sync headers starting at `height`.
+    ///
+    /// `parent` must be the commitment of the trusted leaf at `height - 1` (or, when starting from
+    /// genesis, the parent commitment of the genesis leaf).
+    pub fn new(
+        client: SequencerClient,
+        height: u64,
+        parent: Commitment<Leaf2>,
+        stake_table: Vec<PeerConfig<SeqTypes>>,
+        threshold: U256,
+    ) -> Self {
+        Self {
+            client,
+            height,
+            parent,
+            stake_table,
+            threshold,
+            batch_size: DEFAULT_BATCH_SIZE,
+            upgrade_lock: UpgradeLock::new(),
+        }
+    }
+
+    /// Set the maximum number of headers to request at a time.
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Replace the stake table used to verify certificates.
+    pub fn set_stake_table(&mut self, stake_table: Vec<PeerConfig<SeqTypes>>, threshold: U256) {
+        self.stake_table = stake_table;
+        self.threshold = threshold;
+    }
+
+    /// The height of the next header to be synced.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// The commitment of the last verified leaf, which the next header must build on.
+    pub fn parent(&self) -> Commitment<Leaf2> {
+        self.parent
+    }
+
+    /// Download and verify the next batch of headers.
+    ///
+    /// Returns the verified headers in order, or an empty list if there are no new headers yet. If
+    /// verification fails, the anchor is not moved.
+    pub async fn sync(&mut self) -> anyhow::Result<Vec<Header>> {
+        let block_height = self.client.get_height().await?;
+        if block_height <= self.height {
+            return Ok(vec![]);
+        }
+        let until = min(block_height, self.height + self.batch_size);
+        let chain = self
+            .client
+            .get_header_chain(self.height, until)
+            .await
+            .context(format!("fetching header chain {}..{until}", self.height))?;
+        self.verify(chain).await
+    }
+
+    /// Verify a chain of headers which extends the current anchor.
+    ///
+    /// On success, moves the anchor to the end of `chain` and returns the headers it contains.
+    pub async fn verify(&mut self, chain: HeaderChain) -> anyhow::Result<Vec<Header>> {
+        let (leaves, qc) = chain.into_parts();
+        let last = leaves.last().context("header chain is empty")?;
+
+        // Check that the leaves form a chain from our anchor.
+        let mut parent = self.parent;
+        for (i, leaf) in leaves.iter().enumerate() {
+            let height = self.height + i as u64;
+            ensure!(
+                leaf.block_header().block_number() == height,
+                "expected leaf {height}, got leaf {}",
+                leaf.block_header().block_number()
+            );
+            ensure!(
+                leaf.parent_commitment() == parent,
+                "leaf {height} does not extend the verified chain: parent is {}, expected {parent}",
+                leaf.parent_commitment()
+            );
+            parent = leaf.commit();
+        }
+
+        // Check that the certificate signs the last leaf, which attests to the whole chain.
+        ensure!(
+            qc.data.leaf_commit == parent,
+            "certificate is for leaf {}, not the last leaf in the chain {parent}",
+            qc.data.leaf_commit
+        );
+        // The certificate is signed with the version in effect for the last leaf, so check it
+        // with any upgrade the chain decides, and forget the upgrade again if the check fails.
+        let decided_upgrade = self
+            .upgrade_lock
+            .decided_upgrade_certificate
+            .read()
+            .await
+            .clone();
+        if let Some(cert) = last.upgrade_certificate() {
+            *self.upgrade_lock.decided_upgrade_certificate.write().await = Some(cert);
+        }
+        if let Err(err) = qc
+            .verify_untrusted(
+                StakeTableEntries::<SeqTypes>::from(self.stake_table.clone()).0,
+                self.threshold,
+                &self.upgrade_lock,
+            )
+            .await
+        {
+            *self.upgrade_lock.decided_upgrade_certificate.write().await = decided_upgrade;
+            bail!("invalid certificate: {err:#}");
+        }
+
+        self.height += leaves.len() as u64;
+        self.parent = parent;
+        Ok(leaves
+            .into_iter()
+            .map(|leaf| leaf.block_header().clone())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use espresso_types::{sign_qc, MockSequencerVersions, NodeState, ValidatedState};
+    use hotshot_query_service::availability::LeafQueryData;
+    use hotshot_types::{
+        data::ViewNumber, simple_vote::QuorumData2, traits::node_implementation::ConsensusTime,
+        ValidatorConfig,
+    };
+
+    use super::*;
+
+    async fn genesis() -> LeafQueryData<SeqTypes> {
+        LeafQueryData::genesis::<MockSequencerVersions>(
+            &ValidatedState::default(),
+            &NodeState::mock(),
+        )
+        .await
+    }
+
+    fn validators(seed: u8) -> Vec<ValidatorConfig<SeqTypes>> {
+        (0..4)
+            .map(|i| {
+                ValidatorConfig::generated_from_seed_indexed([seed; 32], i, U256::from(1), true)
+            })
+            .collect()
+    }
+
+    /// The genesis leaf, certified by a QC from `validators` in view 1.
+    async fn signed_genesis(validators: &[ValidatorConfig<SeqTypes>]) -> LeafQueryData<SeqTypes> {
+        let genesis = genesis().await;
+        let qc = sign_qc(
+            validators,
+            U256::from(validators.len()),
+            QuorumData2 {
+                leaf_commit: genesis.hash(),
+                epoch: None,
+                block_number: Some(0),
+            },
+            ViewNumber::new(1),
+            &UpgradeLock::<SeqTypes, MockSequencerVersions>::new(),
+        )
+        .await;
+        LeafQueryData::new(genesis.leaf().clone(), qc).unwrap()
+    }
+
+    fn syncer(height: u64, parent: Commitment<Leaf2>) -> HeaderSyncer<MockSequencerVersions> {
+        let stake_table = validators(0)
+            .iter()
+            .map(ValidatorConfig::public_config)
+            .collect::<Vec<_>>();
+        let threshold = U256::from(stake_table.len());
+        HeaderSyncer::new(
+            SequencerClient::new("http://dummy-url:3030".parse().unwrap()),
+            height,
+            parent,
+            stake_table,
+            threshold,
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_header_chain() {
+        let leaf = signed_genesis(&validators(0)).await;
+        let chain = HeaderChain::new([leaf.clone()]).unwrap();
+
+        let mut syncer = syncer(0, leaf.leaf().parent_commitment());
+        let headers = syncer.verify(chain).await.unwrap();
+        assert_eq!(headers, [leaf.header().clone()]);
+        assert_eq!(syncer.height(), 1);
+        assert_eq!(syncer.parent(), leaf.hash());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_header_chain_invalid_qc() {
+        let leaf = signed_genesis(&validators(0)).await;
+        let parent = leaf.leaf().parent_commitment();
+
+        // The genesis QC carries no signatures and must not be trusted.
+        let genesis = genesis().await;
+        let mut syncer = syncer(0, parent);
+        syncer
+            .verify(HeaderChain::new([genesis]).unwrap())
+            .await
+            .unwrap_err();
+
+        // Nor may a QC for a later view with its signatures stripped.
+        let mut qc = leaf.qc().clone();
+        qc.signatures = None;
+        let unsigned = LeafQueryData::new(leaf.leaf().clone(), qc).unwrap();
+        syncer
+            .verify(HeaderChain::new([unsigned]).unwrap())
+            .await
+            .unwrap_err();
+
+        // A QC signed by validators outside the stake table is rejected.
+        let forged = signed_genesis(&validators(1)).await;
+        syncer
+            .verify(HeaderChain::new([forged]).unwrap())
+            .await
+            .unwrap_err();
+
+        // The anchor does not move on failure.
+        assert_eq!(syncer.height(), 0);
+        assert_eq!(syncer.parent(), parent);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_header_chain_wrong_parent() {
+        let leaf = signed_genesis(&validators(0)).await;
+        let chain = HeaderChain::new([leaf.clone()]).unwrap();
+
+        let mut syncer = syncer(0, leaf.hash());
+        syncer.verify(chain).await.unwrap_err();
+        // The anchor does not move on failure.
+        assert_eq!(syncer.height(), 0);
+        assert_eq!(syncer.parent(), leaf.hash());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_header_chain_wrong_height() {
+        let leaf = signed_genesis(&validators(0)).await;
+        let chain = HeaderChain::new([leaf.clone()]).unwrap();
+
+        let mut syncer = syncer(1, leaf.leaf().parent_commitment());
+        syncer.verify(chain).await.unwrap_err();
+    }
+}
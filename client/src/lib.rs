@@ -16,6 +16,9 @@ use surf_disco::{
 use tokio::time::sleep;
 use vbs::version::StaticVersion;
 
+mod header_sync;
+pub use header_sync::{HeaderChain, HeaderSyncer, DEFAULT_BATCH_SIZE};
+
 pub type SequencerApiVersion = StaticVersion<0, 1>;
 
 #[derive(Clone, Debug)]
@@ -71,6 +74,15 @@ impl SequencerClient {
             .context("subscribing to Espresso Blocks")
     }
 
+    /// Get a verifiable chain of headers from `from` up until `until`
+    pub async fn get_header_chain(&self, from: u64, until: u64) -> anyhow::Result<HeaderChain> {
+        self.0
+            .get::<HeaderChain>(&format!("availability/header-chain/{from}/{until}"))
+            .send()
+            .await
+            .context("getting Espresso header chain")
+    }
+
     /// Get the balance for a given account at a given block height, defaulting to current balance.
     pub async fn get_espresso_balance(
         &self,
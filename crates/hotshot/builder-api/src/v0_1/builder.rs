@@ -4,13 +4,17 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use clap::Args;
 use committable::Committable;
 use derive_more::From;
 use futures::FutureExt;
-use hotshot_types::{traits::node_implementation::NodeType, utils::BuilderCommitment};
+use hotshot_types::{
+    api_auth::{ApiKeys, AuthError, Scope},
+    traits::node_implementation::NodeType,
+    utils::BuilderCommitment,
+};
 use serde::{Deserialize, Serialize};
 use tagged_base64::TaggedBase64;
 use thiserror::Error;
@@ -39,6 +43,16 @@ pub struct Options {
         value_delimiter = ','
     )]
     pub extensions: Vec<toml::Value>,
+
+    /// File configuring the API keys clients must present to submit to the builder.
+    ///
+    /// If set, every request to the submit API must carry a key with the `submit-bundles` scope as
+    /// `Authorization: Bearer KEY`, and is counted against the rate limit of the key. See
+    /// [`hotshot_types::api_auth`] for the format. If not set, anyone can submit. Requests for
+    /// blocks are not affected, since they are already authenticated by the signature of the
+    /// leader.
+    #[arg(long = "builder-api-keys", env = "HOTSHOT_BUILDER_API_KEYS")]
+    pub api_keys: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Error, Deserialize, Serialize)]
@@ -82,10 +96,28 @@ pub enum Error {
     BuilderAddress(#[from] BuildError),
     #[error("Error getting transaction status: {0}")]
     TxnStat(BuildError),
+    #[error("Unauthorized: {message}")]
+    Unauthorized { message: String, status: StatusCode },
     #[error("Custom error {status}: {message}")]
     Custom { message: String, status: StatusCode },
 }
 
+impl From<AuthError> for Error {
+    fn from(err: AuthError) -> Self {
+        let status = match err {
+            AuthError::Missing | AuthError::Invalid | AuthError::InvalidSignature(_) => {
+                StatusCode::UNAUTHORIZED
+            },
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        };
+        Error::Unauthorized {
+            message: err.to_string(),
+            status,
+        }
+    }
+}
+
 impl tide_disco::error::Error for Error {
     fn catch_all(status: StatusCode, msg: String) -> Self {
         Error::Custom {
@@ -105,6 +137,7 @@ impl tide_disco::error::Error for Error {
             },
             Error::TxnUnpack { .. } => StatusCode::BAD_REQUEST,
             Error::TxnSubmit { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Unauthorized { status, .. } => *status,
             Error::Custom { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Error::BuilderAddress { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Error::TxnStat { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -112,6 +145,18 @@ impl tide_disco::error::Error for Error {
     }
 }
 
+/// Check that `req` presents a key allowing `scope`, if `keys` are required.
+fn authorize(keys: Option<&ApiKeys>, req: &RequestParams, scope: Scope) -> Result<(), Error> {
+    let Some(keys) = keys else {
+        return Ok(());
+    };
+    let authorization = req
+        .header("Authorization")
+        .map(|values| values.last().as_str());
+    keys.authorize(authorization, scope)?;
+    Ok(())
+}
+
 pub(crate) fn try_extract_param<T: for<'a> TryFrom<&'a TaggedBase64>>(
     params: &RequestParams,
     param_name: &str,
@@ -249,9 +294,23 @@ where
         include_str!("../../api/v0_1/submit.toml"),
         options.extensions.clone(),
     )?;
+    let keys = options
+        .api_keys
+        .as_ref()
+        .map(|path| ApiKeys::load(path))
+        .transpose()
+        .map_err(|err| ApiError::CannotReadToml {
+            reason: format!("{err:#}"),
+        })?
+        .map(Arc::new);
+    let batch_keys = keys.clone();
+    let status_keys = keys.clone();
+
     api.with_version("0.0.1".parse().unwrap())
-        .at("submit_txn", |req: RequestParams, state| {
+        .at("submit_txn", move |req: RequestParams, state| {
+            let auth = authorize(keys.as_deref(), &req, Scope::SubmitBundles);
             async move {
+                auth?;
                 let tx = req
                     .body_auto::<<Types as NodeType>::Transaction, Ver>(Ver::instance())
                     .map_err(Error::TxnUnpack)?;
@@ -264,8 +323,10 @@ where
             }
             .boxed()
         })?
-        .at("submit_batch", |req: RequestParams, state| {
+        .at("submit_batch", move |req: RequestParams, state| {
+            let auth = authorize(batch_keys.as_deref(), &req, Scope::SubmitBundles);
             async move {
+                auth?;
                 let txns = req
                     .body_auto::<Vec<<Types as NodeType>::Transaction>, Ver>(Ver::instance())
                     .map_err(Error::TxnUnpack)?;
@@ -278,8 +339,10 @@ where
             }
             .boxed()
         })?
-        .get("get_status", |req: RequestParams, state| {
+        .get("get_status", move |req: RequestParams, state| {
+            let auth = authorize(status_keys.as_deref(), &req, Scope::SubmitBundles);
             async move {
+                auth?;
                 let tx = req
                     .body_auto::<<Types as NodeType>::Transaction, Ver>(Ver::instance())
                     .map_err(Error::TxnUnpack)?;
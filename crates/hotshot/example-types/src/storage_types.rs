@@ -18,7 +18,7 @@ use hotshot_types::{
         DaProposal, DaProposal2, QuorumProposal, QuorumProposal2, QuorumProposalWrapper,
         VidCommitment,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::HotShotAction,
     message::{convert_proposal, Proposal},
     simple_certificate::{
@@ -60,6 +60,7 @@ pub struct TestStorageState<TYPES: NodeType> {
     epoch: Option<TYPES::Epoch>,
     state_certs: BTreeMap<TYPES::Epoch, LightClientStateUpdateCertificate<TYPES>>,
     drb_results: BTreeMap<TYPES::Epoch, DrbResult>,
+    drb_inputs: BTreeMap<u64, DrbInput>,
     epoch_roots: BTreeMap<TYPES::Epoch, TYPES::BlockHeader>,
 }
 
@@ -80,6 +81,7 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
             epoch: None,
             state_certs: BTreeMap::new(),
             drb_results: BTreeMap::new(),
+            drb_inputs: BTreeMap::new(),
             epoch_roots: BTreeMap::new(),
         }
     }
@@ -392,6 +394,20 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         Ok(())
     }
 
+    async fn store_drb_input(&self, drb_input: DrbInput) -> Result<()> {
+        let mut inner = self.inner.write().await;
+
+        inner.drb_inputs.insert(drb_input.epoch, drb_input);
+
+        Ok(())
+    }
+
+    async fn load_drb_input(&self, epoch: TYPES::Epoch) -> Result<Option<DrbInput>> {
+        let inner = self.inner.read().await;
+
+        Ok(inner.drb_inputs.get(&epoch.u64()).cloned())
+    }
+
     async fn add_epoch_root(
         &self,
         epoch: TYPES::Epoch,
@@ -57,6 +57,7 @@ use hotshot_types::{
     epoch_membership::EpochMembershipCoordinator,
     event::{Event, EventType},
     network::{BuilderType, NetworkConfig, NetworkConfigFile, NetworkConfigSource},
+    signer::Signer,
     traits::{
         block_contents::{BlockHeader, TestableBlock},
         election::Membership,
@@ -390,7 +391,7 @@ pub trait RunDa<
 
         SystemContext::init(
             pk,
-            sk,
+            Signer::Local(sk),
             state_sk,
             config.node_index,
             config.config,
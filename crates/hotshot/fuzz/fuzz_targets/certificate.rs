@@ -0,0 +1,44 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Fuzz the decoding of certificates.
+//!
+//! The first byte of the input selects the kind of certificate, and the rest is decoded as it.
+
+#![no_main]
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_fuzz::{fuzz_all_versions, TrackingAllocator};
+use hotshot_types::simple_certificate::{
+    DaCertificate2, EpochRootQuorumCertificate, LightClientStateUpdateCertificate,
+    NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2, TimeoutCertificate2,
+    UpgradeCertificate, ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2,
+    ViewSyncPreCommitCertificate2,
+};
+use libfuzzer_sys::fuzz_target;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((kind, data)) = data.split_first() else {
+        return;
+    };
+
+    match kind % 11 {
+        0 => fuzz_all_versions::<QuorumCertificate<TestTypes>>(data),
+        1 => fuzz_all_versions::<QuorumCertificate2<TestTypes>>(data),
+        2 => fuzz_all_versions::<NextEpochQuorumCertificate2<TestTypes>>(data),
+        3 => fuzz_all_versions::<DaCertificate2<TestTypes>>(data),
+        4 => fuzz_all_versions::<TimeoutCertificate2<TestTypes>>(data),
+        5 => fuzz_all_versions::<ViewSyncPreCommitCertificate2<TestTypes>>(data),
+        6 => fuzz_all_versions::<ViewSyncCommitCertificate2<TestTypes>>(data),
+        7 => fuzz_all_versions::<ViewSyncFinalizeCertificate2<TestTypes>>(data),
+        8 => fuzz_all_versions::<UpgradeCertificate<TestTypes>>(data),
+        9 => fuzz_all_versions::<LightClientStateUpdateCertificate<TestTypes>>(data),
+        _ => fuzz_all_versions::<EpochRootQuorumCertificate<TestTypes>>(data),
+    }
+});
@@ -0,0 +1,29 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Fuzz the decoding of network messages, as done on every message received from a peer.
+
+#![no_main]
+
+use futures::executor::block_on;
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_fuzz::{assert_bounded_allocation, fuzz_all_versions, TrackingAllocator};
+use hotshot_types::message::{Message, UpgradeLock};
+use libfuzzer_sys::fuzz_target;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+fuzz_target!(|data: &[u8]| {
+    // The version-tagged decoding used by the network task, including the version check
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let _ = assert_bounded_allocation(data, |data| {
+        block_on(upgrade_lock.deserialize::<Message<TestTypes>>(data))
+    });
+
+    // Each version the message may be tagged with, without the check against its view
+    fuzz_all_versions::<Message<TestTypes>>(data);
+});
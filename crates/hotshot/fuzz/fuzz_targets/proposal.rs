@@ -0,0 +1,41 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Fuzz the decoding of proposals.
+//!
+//! The first byte of the input selects the kind of proposal, and the rest is decoded as it.
+
+#![no_main]
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_fuzz::{fuzz_all_versions, TrackingAllocator};
+use hotshot_types::{
+    data::{
+        vid_disperse::VidDisperseShare2, DaProposal, DaProposal2, QuorumProposal, QuorumProposal2,
+        QuorumProposalWrapper, UpgradeProposal,
+    },
+    message::Proposal,
+};
+use libfuzzer_sys::fuzz_target;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((kind, data)) = data.split_first() else {
+        return;
+    };
+
+    match kind % 7 {
+        0 => fuzz_all_versions::<Proposal<TestTypes, QuorumProposal<TestTypes>>>(data),
+        1 => fuzz_all_versions::<Proposal<TestTypes, QuorumProposal2<TestTypes>>>(data),
+        2 => fuzz_all_versions::<Proposal<TestTypes, QuorumProposalWrapper<TestTypes>>>(data),
+        3 => fuzz_all_versions::<Proposal<TestTypes, DaProposal<TestTypes>>>(data),
+        4 => fuzz_all_versions::<Proposal<TestTypes, DaProposal2<TestTypes>>>(data),
+        5 => fuzz_all_versions::<Proposal<TestTypes, UpgradeProposal<TestTypes>>>(data),
+        _ => fuzz_all_versions::<Proposal<TestTypes, VidDisperseShare2<TestTypes>>>(data),
+    }
+});
@@ -0,0 +1,101 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Helpers shared by the fuzz targets.
+//!
+//! Every target feeds arbitrary bytes to the decoders used on input received from peers, and
+//! checks that they never panic and that the memory they allocate is bounded by the size of the
+//! input, so that a malicious peer cannot make a node allocate unbounded memory with a short
+//! message.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use hotshot_example_types::node_types::TestVersions;
+use hotshot_types::traits::node_implementation::Versions;
+use serde::{de::DeserializeOwned, Serialize};
+use vbs::{version::StaticVersionType, BinarySerializer, Serializer};
+
+/// Memory a decoder may allocate regardless of the size of its input
+pub const BASE_ALLOCATION_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Memory a decoder may allocate per byte of input, on top of [`BASE_ALLOCATION_LIMIT`]
+pub const ALLOCATION_PER_INPUT_BYTE: usize = 1024;
+
+/// Bytes currently allocated
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Highest value of [`ALLOCATED`] since the last call to [`assert_bounded_allocation`]
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// An allocator keeping track of the memory in use, so that targets can check how much a decoder
+/// allocated.
+///
+/// Each target installs it with `#[global_allocator]`.
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_ALLOCATED.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+/// Run `decode` on `input`, asserting that the memory it allocates stays within
+/// [`BASE_ALLOCATION_LIMIT`] plus [`ALLOCATION_PER_INPUT_BYTE`] per byte of input.
+///
+/// # Panics
+/// if `decode` allocates more than allowed
+pub fn assert_bounded_allocation<R>(input: &[u8], decode: impl FnOnce(&[u8]) -> R) -> R {
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK_ALLOCATED.store(baseline, Ordering::Relaxed);
+
+    let result = decode(input);
+
+    let allocated = PEAK_ALLOCATED
+        .load(Ordering::Relaxed)
+        .saturating_sub(baseline);
+    let limit = BASE_ALLOCATION_LIMIT + ALLOCATION_PER_INPUT_BYTE * input.len();
+    assert!(
+        allocated <= limit,
+        "Decoding {} bytes allocated {allocated} bytes, more than the limit of {limit}",
+        input.len()
+    );
+
+    result
+}
+
+/// Deserialize `input` as a `M` tagged with version `VER`, checking that allocation is bounded
+/// and that anything which deserializes can be serialized again.
+///
+/// # Panics
+/// if deserialization allocates too much, or a deserialized value fails to serialize
+pub fn fuzz_versioned<VER: StaticVersionType, M: Serialize + DeserializeOwned>(input: &[u8]) {
+    let Ok(message) =
+        assert_bounded_allocation(input, |input| Serializer::<VER>::deserialize::<M>(input))
+    else {
+        return;
+    };
+
+    Serializer::<VER>::serialize(&message).expect("Deserialized value failed to serialize");
+}
+
+/// [`fuzz_versioned`] for every version a `TestVersions` node accepts from its peers
+pub fn fuzz_all_versions<M: Serialize + DeserializeOwned>(input: &[u8]) {
+    fuzz_versioned::<<TestVersions as Versions>::Base, M>(input);
+    fuzz_versioned::<<TestVersions as Versions>::Upgrade, M>(input);
+}
@@ -1,7 +1,62 @@
-use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+use std::sync::OnceLock;
+
+use anyhow::Context;
+use hotshot_types::telemetry;
+use tracing_subscriber::{
+    fmt::format::FmtSpan, layer::SubscriberExt, registry::Registry, reload,
+    util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+mod structured_log;
+pub use structured_log::StructuredJsonLayer;
+
+/// Access to the filter of the log output installed by [`initialize_logging_with_layer`]
+///
+/// The type of the reload handle depends on the additional layer, so it is hidden behind closures.
+struct LogFilterHandle {
+    /// Replace the filter
+    set: Box<dyn Fn(EnvFilter) -> Result<(), reload::Error> + Send + Sync>,
+    /// Render the current filter in `RUST_LOG` syntax
+    get: Box<dyn Fn() -> Option<String> + Send + Sync>,
+}
+
+static LOG_FILTER: OnceLock<LogFilterHandle> = OnceLock::new();
+
+/// The current filter of the log output, in `RUST_LOG` syntax
+///
+/// Returns `None` if logging was not initialized by this module.
+pub fn log_filter() -> Option<String> {
+    (LOG_FILTER.get()?.get)()
+}
+
+/// Replace the filter of the log output, given in `RUST_LOG` syntax
+///
+/// This takes effect immediately, without restarting the process. Additional layers passed to
+/// [`initialize_logging_with_layer`] keep their own filters.
+///
+/// # Errors
+/// Fails if the filter cannot be parsed, or if logging was not initialized by this module.
+pub fn set_log_filter(directives: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_new(directives).context("parsing log filter")?;
+    let handle = LOG_FILTER
+        .get()
+        .context("logging was not initialized with a reloadable filter")?;
+    (handle.set)(filter).context("replacing log filter")
+}
 
 /// Initializes logging
 pub fn initialize_logging() {
+    initialize_logging_with_layer(None::<Box<dyn Layer<Registry> + Send + Sync>>);
+}
+
+/// Initializes logging, with an additional layer (such as a tracing exporter) if given
+///
+/// The additional layer receives all spans and events, regardless of `RUST_LOG`, so it should be
+/// filtered as necessary by the caller.
+pub fn initialize_logging_with_layer<L>(layer: Option<L>)
+where
+    L: Layer<Registry> + Send + Sync,
+{
     // Parse the `RUST_LOG_SPAN_EVENTS` environment variable
     let span_event_filter = match std::env::var("RUST_LOG_SPAN_EVENTS") {
         Ok(val) => val
@@ -19,17 +74,30 @@ pub fn initialize_logging() {
         Err(_) => FmtSpan::NONE,
     };
 
-    // Conditionally initialize in `json` mode
-    if std::env::var("RUST_LOG_FORMAT") == Ok("json".to_string()) {
-        let _ = tracing_subscriber::fmt()
-            .with_env_filter(EnvFilter::from_default_env())
-            .with_span_events(span_event_filter)
-            .json()
-            .try_init();
-    } else {
-        let _ = tracing_subscriber::fmt()
-            .with_env_filter(EnvFilter::from_default_env())
-            .with_span_events(span_event_filter)
-            .try_init();
+    // Conditionally initialize in `json` or `structured` mode
+    let fmt = tracing_subscriber::fmt::layer().with_span_events(span_event_filter);
+    let fmt = match std::env::var("RUST_LOG_FORMAT").as_deref() {
+        Ok("json") => fmt.json().boxed(),
+        Ok("structured") => {
+            // The structured format labels log lines with the view, task and event being handled,
+            // which are recorded on task spans.
+            telemetry::enable_task_spans();
+            StructuredJsonLayer::new(std::io::stdout).boxed()
+        },
+        _ => fmt.boxed(),
     };
+    let (filter, handle) = reload::Layer::new(EnvFilter::from_default_env());
+    let initialized = tracing_subscriber::registry()
+        .with(layer)
+        .with(fmt.with_filter(filter))
+        .try_init();
+    if initialized.is_ok() {
+        let _ = LOG_FILTER.set(LogFilterHandle {
+            set: Box::new({
+                let handle = handle.clone();
+                move |filter| handle.reload(filter)
+            }),
+            get: Box::new(move || handle.with_current(ToString::to_string).ok()),
+        });
+    }
 }
@@ -0,0 +1,348 @@
+//! Structured JSON logging with a stable schema.
+//!
+//! Selected with `RUST_LOG_FORMAT=structured`. Every event is written as a single line containing
+//! a JSON object with exactly the following keys:
+//!
+//! | Key         | Type             | Description                                                  |
+//! |-------------|------------------|--------------------------------------------------------------|
+//! | `timestamp` | string           | RFC 3339 UTC time the event was logged                       |
+//! | `level`     | string           | One of `ERROR`, `WARN`, `INFO`, `DEBUG`, `TRACE`             |
+//! | `target`    | string           | Module which logged the event                                |
+//! | `message`   | string or null   | The log message                                              |
+//! | `view`      | integer or null  | Consensus view the event relates to                          |
+//! | `epoch`     | integer or null  | Epoch the event relates to                                   |
+//! | `task`      | string or null   | Consensus task handling the event                            |
+//! | `event`     | string or null   | Kind of consensus event being handled, e.g. `QuorumVoteRecv` |
+//! | `peer`      | string or null   | Key of the peer the event was received from or sent to       |
+//! | `fields`    | object           | All other fields recorded on the event                       |
+//!
+//! The `view`, `epoch`, `task`, `event` and `peer` keys are filled in from fields of the same name,
+//! either on the event itself or on any span it occurs in, with the innermost value taking
+//! precedence. Consensus tasks record them on a span for each event they handle (see
+//! [`hotshot_types::telemetry::task_span`]), so every log line emitted while handling a consensus
+//! event is labeled, without the log message itself having to repeat them. Since that span may be
+//! filtered out, for example with `RUST_LOG=warn`, the same values are also taken from the
+//! [`TaskContext`] of the consensus event being handled.
+//!
+//! New keys may be added to this schema in the future, but existing keys will not be removed or
+//! change type. Pipelines which need additional context should read it from `fields`.
+
+use std::{fmt::Debug, io::Write};
+
+use chrono::{SecondsFormat, Utc};
+use hotshot_types::telemetry::TaskContext;
+use serde_json::{json, Map, Value};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::MakeWriter,
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+/// A [`Layer`] which writes events as JSON objects with a stable schema.
+///
+/// See the [module-level documentation](self) for the schema.
+pub struct StructuredJsonLayer<W> {
+    make_writer: W,
+}
+
+impl<W> StructuredJsonLayer<W>
+where
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    /// Create a layer which writes to `make_writer`.
+    pub fn new(make_writer: W) -> Self {
+        Self { make_writer }
+    }
+}
+
+/// The fields of the schema which may be inherited from spans.
+#[derive(Clone, Debug, Default)]
+struct SchemaFields {
+    view: Option<u64>,
+    epoch: Option<u64>,
+    task: Option<String>,
+    event: Option<String>,
+    peer: Option<String>,
+}
+
+impl SchemaFields {
+    /// Try to record a field in the schema, returning `false` if it is not a schema field.
+    fn record_u64(&mut self, field: &Field, value: u64) -> bool {
+        match field.name() {
+            "view" => self.view = Some(value),
+            "epoch" => self.epoch = Some(value),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Try to record a field in the schema, returning `false` if it is not a schema field.
+    fn record_str(&mut self, field: &Field, value: String) -> bool {
+        match field.name() {
+            "task" => self.task = Some(value),
+            "event" => self.event = Some(value),
+            "peer" => self.peer = Some(value),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Fill in any fields missing from `self` with values from `other`.
+    fn inherit(&mut self, other: &Self) {
+        self.view = self.view.or(other.view);
+        self.epoch = self.epoch.or(other.epoch);
+        self.task = self.task.take().or_else(|| other.task.clone());
+        self.event = self.event.take().or_else(|| other.event.clone());
+        self.peer = self.peer.take().or_else(|| other.peer.clone());
+    }
+}
+
+impl From<TaskContext> for SchemaFields {
+    fn from(context: TaskContext) -> Self {
+        Self {
+            view: context.view,
+            epoch: context.epoch,
+            task: Some(context.task.to_string()),
+            event: context.event.map(String::from),
+            peer: context.peer,
+        }
+    }
+}
+
+impl Visit for SchemaFields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        SchemaFields::record_u64(self, field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if let Ok(value) = u64::try_from(value) {
+            SchemaFields::record_u64(self, field, value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        SchemaFields::record_str(self, field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        SchemaFields::record_str(self, field, format!("{value:?}"));
+    }
+}
+
+/// Collects the fields of an event.
+#[derive(Default)]
+struct EventFields {
+    schema: SchemaFields,
+    message: Option<String>,
+    fields: Map<String, Value>,
+}
+
+impl Visit for EventFields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if !self.schema.record_u64(field, value) {
+            self.fields.insert(field.name().into(), value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        let is_schema =
+            u64::try_from(value).is_ok_and(|value| self.schema.record_u64(field, value));
+        if !is_schema {
+            self.fields.insert(field.name().into(), value.into());
+        }
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else if !self.schema.record_str(field, value.to_string()) {
+            self.fields.insert(field.name().into(), value.into());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        let value = format!("{value:?}");
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else if !self.schema.record_str(field, value.clone()) {
+            self.fields.insert(field.name().into(), value.into());
+        }
+    }
+}
+
+impl<S, W> Layer<S> for StructuredJsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = SchemaFields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SchemaFields>() {
+            values.record(fields);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        // Inherit schema fields from enclosing spans, innermost first.
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                if let Some(span_fields) = span.extensions().get::<SchemaFields>() {
+                    fields.schema.inherit(span_fields);
+                }
+            }
+        }
+        if let Some(context) = TaskContext::current() {
+            fields.schema.inherit(&context.into());
+        }
+
+        let metadata = event.metadata();
+        let line = json!({
+            "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "message": fields.message,
+            "view": fields.schema.view,
+            "epoch": fields.schema.epoch,
+            "task": fields.schema.task,
+            "event": fields.schema.event,
+            "peer": fields.schema.peer,
+            "fields": fields.fields,
+        });
+        let mut writer = self.make_writer.make_writer_for(metadata);
+        let _ = writeln!(writer, "{line}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_structured_json_schema() {
+        let buffer = Buffer::default();
+        let subscriber =
+            tracing_subscriber::registry().with(StructuredJsonLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "task",
+                task = "QuorumVoteTaskState",
+                view = 5u64,
+                event = tracing::field::Empty
+            );
+            span.record("event", "QuorumProposalValidated");
+            let _enter = span.enter();
+            tracing::info!(epoch = 2u64, latency = 1.5, "voting");
+            tracing::warn!(view = 6u64, "overridden");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines = output
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+
+        let line = &lines[0];
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "voting");
+        assert_eq!(line["view"], 5);
+        assert_eq!(line["epoch"], 2);
+        assert_eq!(line["task"], "QuorumVoteTaskState");
+        assert_eq!(line["event"], "QuorumProposalValidated");
+        assert_eq!(line["peer"], Value::Null);
+        assert_eq!(line["fields"], json!({ "latency": 1.5 }));
+        assert!(line["timestamp"].is_string());
+
+        // Fields on the event take precedence over fields on enclosing spans.
+        let line = &lines[1];
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["view"], 6);
+        assert_eq!(line["epoch"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_structured_json_task_context() {
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(StructuredJsonLayer::new(buffer.clone()).with_filter(LevelFilter::WARN));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let context = TaskContext {
+            task: "QuorumVoteTaskState",
+            view: Some(5),
+            epoch: Some(2),
+            event: Some("QuorumProposalValidated"),
+            peer: None,
+        };
+        context
+            .scope(async {
+                // The task span is filtered out, but the event is still labeled.
+                let span = tracing::info_span!("task", task = "QuorumVoteTaskState", view = 5u64);
+                let _enter = span.enter();
+                tracing::warn!("failed to vote");
+            })
+            .await;
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line = serde_json::from_str::<Value>(output.trim()).unwrap();
+        assert_eq!(line["message"], "failed to vote");
+        assert_eq!(line["view"], 5);
+        assert_eq!(line["epoch"], 2);
+        assert_eq!(line["task"], "QuorumVoteTaskState");
+        assert_eq!(line["event"], "QuorumProposalValidated");
+        assert_eq!(line["peer"], Value::Null);
+    }
+}
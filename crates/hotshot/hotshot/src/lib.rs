@@ -14,14 +14,22 @@ pub mod documentation;
 use committable::Committable;
 use futures::future::{select, Either};
 use hotshot_types::{
+    audit::{MessageAudit, MessageAuditSlot},
+    bandwidth::{BandwidthAccounting, TrafficClass},
+    compression::MessageCompression,
     drb::{DrbResult, INITIAL_DRB_RESULT},
     epoch_membership::EpochMembershipCoordinator,
+    feature_gates::Feature,
     message::UpgradeLock,
     simple_certificate::LightClientStateUpdateCertificate,
     traits::{
-        block_contents::BlockHeader, election::Membership, network::BroadcastDelay,
-        node_implementation::Versions, signature_key::StateSignatureKey,
+        block_contents::{BlockHeader, Transaction},
+        election::Membership,
+        network::BroadcastDelay,
+        node_implementation::Versions,
+        signature_key::StateSignatureKey,
     },
+    transaction_dedup::TransactionDedup,
     utils::epoch_from_block_number,
 };
 use rand::Rng;
@@ -41,7 +49,7 @@ pub mod helpers;
 use std::{
     collections::{BTreeMap, HashMap},
     num::NonZeroUsize,
-    sync::Arc,
+    sync::{Arc, OnceLock},
     time::Duration,
 };
 
@@ -49,7 +57,10 @@ use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::join;
-use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry};
+use hotshot_task::{
+    supervisor::{SupervisorMetrics, TaskSupervisor},
+    task::{ConsensusTaskRegistry, NetworkTaskRegistry},
+};
 use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
 // Internal
 /// Reexport error type
@@ -63,12 +74,13 @@ use hotshot_types::{
     data::Leaf2,
     event::{EventType, LeafInfo},
     message::{DataMessage, Message, MessageKind, Proposal},
+    signer::Signer,
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, UpgradeCertificate},
     traits::{
         consensus_api::ConsensusApi,
+        da_payload_provider::{DaPayloadProvider, DaPayloadProviderSlot},
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
         states::ValidatedState,
     },
     utils::genesis_epoch_from_version,
@@ -106,8 +118,8 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
     /// The public key of this node
     public_key: TYPES::SignatureKey,
 
-    /// The private key of this node
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs with the consensus key of this node
+    signer: Signer<TYPES::SignatureKey>,
 
     /// The private key to sign the light client state
     state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
@@ -163,6 +175,23 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
 
     /// Marketplace config for this instance of HotShot
     pub marketplace_config: MarketplaceConfig<TYPES, I>,
+
+    /// Compression of network messages, shared by the tasks sending and receiving them
+    pub compression: Arc<MessageCompression<TYPES::SignatureKey>>,
+
+    /// Bandwidth accounting and limits of network messages, shared by the tasks sending and
+    /// receiving them
+    pub bandwidth: Arc<BandwidthAccounting>,
+
+    /// Source of DA payloads announced through payload hints, set by the application
+    da_payload_provider: DaPayloadProviderSlot<TYPES>,
+
+    /// Audit log of the proposals and votes sent and received, set by the application
+    message_audit: MessageAuditSlot<TYPES>,
+
+    /// Transactions seen in recent views, shared by the transaction task and transaction
+    /// submission, `None` if deduplication is disabled
+    pub(crate) transaction_dedup: Option<Arc<RwLock<TransactionDedup<TYPES::Transaction>>>>,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     for SystemContext<TYPES, I, V>
@@ -171,7 +200,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     fn clone(&self) -> Self {
         Self {
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
+            signer: self.signer.clone(),
             state_private_key: self.state_private_key.clone(),
             config: self.config.clone(),
             network: Arc::clone(&self.network),
@@ -189,6 +218,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
             storage: Arc::clone(&self.storage),
             upgrade_lock: self.upgrade_lock.clone(),
             marketplace_config: self.marketplace_config.clone(),
+            compression: Arc::clone(&self.compression),
+            bandwidth: Arc::clone(&self.bandwidth),
+            da_payload_provider: Arc::clone(&self.da_payload_provider),
+            message_audit: Arc::clone(&self.message_audit),
+            transaction_dedup: self.transaction_dedup.clone(),
         }
     }
 }
@@ -208,7 +242,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
         nonce: u64,
         config: HotShotConfig<TYPES>,
@@ -224,7 +258,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
 
         Self::new_from_channels(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             nonce,
             config,
@@ -250,7 +284,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub async fn new_from_channels(
         public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
         nonce: u64,
         config: HotShotConfig<TYPES>,
@@ -361,12 +395,24 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         // Our own copy of the receiver is inactive so it doesn't count.
         external_tx.set_await_active(false);
 
+        let compression = Arc::new(MessageCompression::new(
+            config.compression,
+            Feature::MessageCompression.version::<V>(),
+        ));
+        let bandwidth = Arc::new(BandwidthAccounting::new(
+            &config.bandwidth,
+            &*consensus_metrics.bandwidth,
+        ));
+        let transaction_dedup = config
+            .transaction_dedup
+            .map(|config| Arc::new(RwLock::new(TransactionDedup::new(config))));
+
         let inner: Arc<SystemContext<TYPES, I, V>> = Arc::new(SystemContext {
             id: nonce,
             consensus: OuterConsensus::new(consensus),
             instance_state: Arc::new(instance_state),
             public_key,
-            private_key,
+            signer,
             state_private_key,
             config,
             start_view: initializer.start_view,
@@ -381,6 +427,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             storage: Arc::new(RwLock::new(storage)),
             upgrade_lock,
             marketplace_config,
+            compression,
+            bandwidth,
+            da_payload_provider: Arc::new(OnceLock::new()),
+            message_audit: Arc::new(OnceLock::new()),
+            transaction_dedup,
         });
 
         inner
@@ -495,7 +546,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ///
     /// # Errors
     ///
-    /// Always returns Ok; does not return an error if the transaction couldn't be published to the network
+    /// Returns an error if the transaction was already seen in a recent view, with transaction
+    /// deduplication enabled; does not return an error if the transaction couldn't be published to
+    /// the network
     #[instrument(skip(self), err, target = "SystemContext", fields(id = self.id))]
     pub async fn publish_transaction_async(
         &self,
@@ -510,9 +563,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         let epoch = consensus_reader.cur_epoch();
         drop(consensus_reader);
 
+        // Tell the submitter, rather than silently dropping the transaction like the nodes it is
+        // gossiped to would.
+        if let Some(dedup) = &self.transaction_dedup {
+            let commitment = transaction.commit();
+            if dedup.read().await.contains(&commitment) {
+                return Err(HotShotError::DuplicateTransaction(commitment.to_string()));
+            }
+        }
+
         // Wrap up a message
-        let message_kind: DataMessage<TYPES> =
-            DataMessage::SubmitTransaction(transaction.clone(), view_number);
+        let priority = transaction.priority();
+        let message_kind: DataMessage<TYPES> = if priority.is_default() {
+            DataMessage::SubmitTransaction(transaction.clone(), view_number)
+        } else {
+            DataMessage::SubmitPrioritizedTransaction(transaction.clone(), priority, view_number)
+        };
         let message = Message {
             sender: api.public_key.clone(),
             kind: MessageKind::from(message_kind),
@@ -521,6 +587,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         let serialized_message = self.upgrade_lock.serialize(&message).await.map_err(|err| {
             HotShotError::FailedToSerialize(format!("failed to serialize transaction: {err}"))
         })?;
+        let serialized_message = self.compression.compress_broadcast(serialized_message);
+        let throttle = self
+            .bandwidth
+            .reserve_send(TrafficClass::Other, serialized_message.len());
 
         let membership = match api.membership_coordinator.membership_for_epoch(epoch).await {
             Ok(m) => m,
@@ -528,6 +598,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         };
 
         spawn(async move {
+            sleep(throttle).await;
             let memberships_da_committee_members = membership
                 .da_committee_members(view_number)
                 .await
@@ -567,6 +638,43 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         Arc::clone(&self.consensus.inner_consensus)
     }
 
+    /// Create a supervisor for the subtasks of a task, reporting their health under `name`
+    #[must_use]
+    pub fn task_supervisor(&self, name: &'static str) -> TaskSupervisor<TYPES::View> {
+        TaskSupervisor::with_metrics(
+            name,
+            Arc::new(SupervisorMetrics::new(&*self.metrics.subtasks, name)),
+        )
+    }
+
+    /// Set the provider used to fetch DA payloads announced through payload hints.
+    ///
+    /// Takes effect for payload hints received afterwards, so it may be called once the tasks are
+    /// running. Returns `false` if a provider was already set.
+    pub fn set_da_payload_provider(&self, provider: Arc<dyn DaPayloadProvider<TYPES>>) -> bool {
+        self.da_payload_provider.set(provider).is_ok()
+    }
+
+    /// Returns the provider used to fetch DA payloads announced through payload hints, if any
+    #[must_use]
+    pub fn da_payload_provider(&self) -> Option<Arc<dyn DaPayloadProvider<TYPES>>> {
+        self.da_payload_provider.get().cloned()
+    }
+
+    /// Set the audit log of the proposals and votes this node sends and receives.
+    ///
+    /// Takes effect immediately, even once the tasks are running. Returns `false` if an audit log
+    /// was already set.
+    pub fn set_message_audit(&self, audit: Arc<dyn MessageAudit<TYPES>>) -> bool {
+        self.message_audit.set(audit).is_ok()
+    }
+
+    /// Returns the audit log of the proposals and votes this node sends and receives, if any
+    #[must_use]
+    pub fn message_audit(&self) -> Option<Arc<dyn MessageAudit<TYPES>>> {
+        self.message_audit.get().cloned()
+    }
+
     /// Returns a copy of the instance state
     pub fn instance_state(&self) -> Arc<TYPES::InstanceState> {
         Arc::clone(&self.instance_state)
@@ -628,7 +736,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     #[allow(clippy::too_many_arguments)]
     pub async fn init(
         public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
         node_id: u64,
         config: HotShotConfig<TYPES>,
@@ -648,7 +756,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     > {
         let hotshot = Self::new(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             node_id,
             config,
@@ -793,7 +901,7 @@ where
     async fn spawn_twin_handles(
         &'static mut self,
         public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
         nonce: u64,
         config: HotShotConfig<TYPES>,
@@ -810,7 +918,7 @@ where
         let epoch_height = config.epoch_height;
         let left_system_context = SystemContext::new(
             public_key.clone(),
-            private_key.clone(),
+            signer.clone(),
             state_private_key.clone(),
             nonce,
             config.clone(),
@@ -824,7 +932,7 @@ where
         .await;
         let right_system_context = SystemContext::new(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             nonce,
             config,
@@ -997,8 +1105,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusApi<TY
         &self.hotshot.public_key
     }
 
-    fn private_key(&self) -> &<TYPES::SignatureKey as SignatureKey>::PrivateKey {
-        &self.hotshot.private_key
+    fn signer(&self) -> &Signer<TYPES::SignatureKey> {
+        &self.hotshot.signer
     }
 
     fn state_private_key(
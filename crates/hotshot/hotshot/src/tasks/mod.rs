@@ -8,7 +8,7 @@
 
 /// Provides trait to create task states from a `SystemContextHandle`
 pub mod task_state;
-use std::{collections::BTreeMap, fmt::Debug, num::NonZeroUsize, sync::Arc, time::Duration};
+use std::{fmt::Debug, num::NonZeroUsize, sync::Arc, time::Duration};
 
 use async_broadcast::{broadcast, RecvError};
 use async_lock::RwLock;
@@ -17,11 +17,12 @@ use futures::{
     future::{BoxFuture, FutureExt},
     stream, StreamExt,
 };
-use hotshot_task::task::Task;
+use hotshot_task::{supervisor::Backoff, task::Task};
 #[cfg(feature = "rewind")]
 use hotshot_task_impls::rewind::RewindTaskState;
 use hotshot_task_impls::{
     da::DaTaskState,
+    drb::DrbTaskState,
     events::HotShotEvent,
     network::{NetworkEventTaskState, NetworkMessageTaskState},
     request::NetworkRequestState,
@@ -32,11 +33,15 @@ use hotshot_task_impls::{
     view_sync::ViewSyncTaskState,
 };
 use hotshot_types::{
+    audit::has_invalid_signature,
+    bandwidth::TrafficClass,
+    compression::{decompress, is_compressed},
     consensus::{Consensus, OuterConsensus},
     constants::EVENT_CHANNEL_SIZE,
     message::{Message, UpgradeLock},
+    signer::Signer,
     traits::{
-        network::ConnectedNetwork,
+        network::{ConnectedNetwork, PeerOffense},
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
     },
 };
@@ -46,8 +51,8 @@ use vbs::version::StaticVersionType;
 use crate::{
     genesis_epoch_from_version, tasks::task_state::CreateTaskState, types::SystemContextHandle,
     ConsensusApi, ConsensusMetricsValue, ConsensusTaskRegistry, EpochMembershipCoordinator,
-    HotShotConfig, HotShotInitializer, MarketplaceConfig, NetworkTaskRegistry, SignatureKey,
-    StateSignatureKey, SystemContext, Versions,
+    HotShotConfig, HotShotInitializer, MarketplaceConfig, NetworkTaskRegistry, StateSignatureKey,
+    SystemContext, Versions,
 };
 
 /// event for global event stream
@@ -73,7 +78,8 @@ pub async fn add_request_network_task<
         state,
         handle.internal_event_stream.0.clone(),
         handle.internal_event_stream.1.activate_cloned(),
-    );
+    )
+    .with_metrics(&handle.hotshot.metrics);
     handle.consensus_registry.run_task(task);
 }
 
@@ -85,7 +91,7 @@ pub fn add_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
         handle.hotshot.consensus(),
         handle.membership_coordinator.clone(),
         handle.public_key().clone(),
-        handle.private_key().clone(),
+        handle.signer().clone(),
         handle.hotshot.id,
         handle.hotshot.upgrade_lock.clone(),
     );
@@ -133,6 +139,9 @@ pub fn add_network_message_task<
     channel: &Arc<NET>,
 ) {
     let upgrade_lock = handle.hotshot.upgrade_lock.clone();
+    let compression = Arc::clone(&handle.hotshot.compression);
+    let bandwidth = Arc::clone(&handle.hotshot.bandwidth);
+    let membership_coordinator = handle.membership_coordinator.clone();
 
     let network_state: NetworkMessageTaskState<TYPES, V> = NetworkMessageTaskState {
         internal_event_stream: handle.internal_event_stream.0.clone(),
@@ -140,45 +149,93 @@ pub fn add_network_message_task<
         public_key: handle.public_key().clone(),
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
         upgrade_lock: upgrade_lock.clone(),
+        audit: Arc::clone(&handle.hotshot.message_audit),
+        membership_coordinator: handle.membership_coordinator.clone(),
     };
 
     let network = Arc::clone(channel);
-    let mut state = network_state.clone();
-    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
-    let task_handle = spawn(async move {
-        futures::pin_mut!(shutdown_signal);
+    // Shared so that a restarted task still sees a shutdown which happened before it restarted
+    let shutdown_signal = create_shutdown_event_monitor(handle).shared();
+    let mut supervisor = handle.hotshot.task_supervisor("network_message");
+    // A panic while handling one message restarts the task instead of leaving the node deaf.
+    supervisor.spawn_restartable(
+        TYPES::View::genesis(),
+        move || {
+            let network = Arc::clone(&network);
+            let mut state = network_state.clone();
+            let shutdown_signal = shutdown_signal.clone().fuse();
+            let upgrade_lock = upgrade_lock.clone();
+            let compression = Arc::clone(&compression);
+            let bandwidth = Arc::clone(&bandwidth);
+            let membership_coordinator = membership_coordinator.clone();
+            async move {
+                futures::pin_mut!(shutdown_signal);
+
+                loop {
+                    // Wait for one of the following to resolve:
+                    futures::select! {
+                        // Wait for a shutdown signal
+                        () = shutdown_signal => {
+                            tracing::error!("Shutting down network message task");
+                            return;
+                        }
 
-        loop {
-            // Wait for one of the following to resolve:
-            futures::select! {
-                // Wait for a shutdown signal
-                () = shutdown_signal => {
-                    tracing::error!("Shutting down network message task");
-                    return;
-                }
+                        // Wait for a message from the network
+                        message = network.recv_message().fuse() => {
+                            // Make sure the message did not fail
+                            let Ok(message) = message else {
+                                continue;
+                            };
+
+                            // Decompress the message, if the sender compressed it
+                            let compressed = is_compressed(&message);
+                            let decompressed = match decompress(&message) {
+                                Ok(decompressed) => decompressed,
+                                Err(e) => {
+                                    tracing::error!("Failed to decompress message: {:?}", e);
+                                    network.report_message(&message, PeerOffense::MalformedMessage);
+                                    continue;
+                                }
+                            };
+
+                            // Deserialize the message
+                            let deserialized_message: Message<TYPES> = match upgrade_lock.deserialize(&decompressed).await {
+                                Ok(message) => message,
+                                Err(e) => {
+                                    tracing::error!("Failed to deserialize message: {:?}", e);
+                                    network.report_message(&message, PeerOffense::MalformedMessage);
+                                    continue;
+                                }
+                            };
+
+                            bandwidth.record_received(TrafficClass::of(&deserialized_message.kind), message.len());
+
+                            // Drop messages whose signature was not made by their claimed signer
+                            let kind = &deserialized_message.kind;
+                            if has_invalid_signature(kind, &membership_coordinator, &upgrade_lock).await {
+                                tracing::warn!(
+                                    "Dropping message with an invalid signature from {}",
+                                    deserialized_message.sender
+                                );
+                                network.report_message(&message, PeerOffense::InvalidSignature);
+                                continue;
+                            }
 
-                // Wait for a message from the network
-                message = network.recv_message().fuse() => {
-                    // Make sure the message did not fail
-                    let Ok(message) = message else {
-                        continue;
-                    };
-
-                    // Deserialize the message
-                    let deserialized_message: Message<TYPES> = match upgrade_lock.deserialize(&message).await {
-                        Ok(message) => message,
-                        Err(e) => {
-                            tracing::error!("Failed to deserialize message: {:?}", e);
-                            continue;
-                        }
-                    };
+                            // A peer sending compressed messages can decompress the ones we send it
+                            if compressed {
+                                compression.record_capable(&deserialized_message.sender).await;
+                            }
 
-                    // Handle the message
-                    state.handle_message(deserialized_message).await;
+                            // Handle the message
+                            state.handle_message(deserialized_message).await;
+                        }
+                    }
                 }
             }
-        }
-    });
+        },
+        Backoff::default(),
+    );
+    let task_handle = spawn(async move { supervisor.join().await });
     handle.network_registry.register(task_handle);
 }
 
@@ -200,14 +257,18 @@ pub fn add_network_event_task<
         storage: Arc::clone(&handle.storage()),
         consensus: OuterConsensus::new(handle.consensus()),
         upgrade_lock: handle.hotshot.upgrade_lock.clone(),
-        transmit_tasks: BTreeMap::new(),
+        compression: Arc::clone(&handle.hotshot.compression),
+        bandwidth: Arc::clone(&handle.hotshot.bandwidth),
+        transmit_tasks: handle.hotshot.task_supervisor("network_transmit"),
+        audit: Arc::clone(&handle.hotshot.message_audit),
         epoch_height: handle.epoch_height,
     };
     let task = Task::new(
         network_state,
         handle.internal_event_stream.0.clone(),
         handle.internal_event_stream.1.activate_cloned(),
-    );
+    )
+    .with_metrics(&handle.hotshot.metrics);
     handle.consensus_registry.run_task(task);
 }
 
@@ -219,6 +280,7 @@ pub async fn add_consensus_tasks<TYPES: NodeType, I: NodeImplementation<TYPES>,
     handle.add_task(VidTaskState::<TYPES, I, V>::create_from(handle).await);
     handle.add_task(DaTaskState::<TYPES, I, V>::create_from(handle).await);
     handle.add_task(TransactionTaskState::<TYPES, I, V>::create_from(handle).await);
+    handle.add_task(DrbTaskState::<TYPES, I>::create_from(handle).await);
 
     {
         let mut upgrade_certificate_lock = handle
@@ -309,7 +371,7 @@ where
         &mut self,
         event: &HotShotEvent<TYPES>,
         public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>>;
@@ -319,7 +381,7 @@ where
     async fn spawn_handle(
         &'static mut self,
         public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         state_private_key: <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
         nonce: u64,
         config: HotShotConfig<TYPES>,
@@ -334,7 +396,7 @@ where
 
         let hotshot = SystemContext::new(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             nonce,
             config,
@@ -412,7 +474,7 @@ where
         // and broadcast the transformed events to the replacement event stream we just created.
         let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
         let public_key = handle.public_key().clone();
-        let private_key = handle.private_key().clone();
+        let signer = handle.signer().clone();
         let upgrade_lock = handle.hotshot.upgrade_lock.clone();
         let consensus = Arc::clone(&handle.hotshot.consensus());
         let send_handle = spawn(async move {
@@ -444,7 +506,7 @@ where
                                 let mut results = state.send_handler(
                                     &msg,
                                     &public_key,
-                                    &private_key,
+                                    &signer,
                                     &upgrade_lock,
                                     Arc::clone(&consensus)
                                 ).await;
@@ -10,21 +10,33 @@ use std::{
     time::Instant,
 };
 
+use async_lock::Mutex;
 use async_trait::async_trait;
 use chrono::Utc;
 use hotshot_task_impls::{
-    builder::BuilderClient, consensus::ConsensusTaskState, da::DaTaskState,
-    quorum_proposal::QuorumProposalTaskState, quorum_proposal_recv::QuorumProposalRecvTaskState,
-    quorum_vote::QuorumVoteTaskState, request::NetworkRequestState, rewind::RewindTaskState,
-    transactions::TransactionTaskState, upgrade::UpgradeTaskState, vid::VidTaskState,
-    view_sync::ViewSyncTaskState,
+    builder::BuilderClient,
+    consensus::ConsensusTaskState,
+    da::DaTaskState,
+    drb::DrbTaskState,
+    helpers::VidDisperseCache,
+    quorum_proposal::QuorumProposalTaskState,
+    quorum_proposal_recv::{ProposalDependencyTracker, QuorumProposalRecvTaskState},
+    quorum_vote::QuorumVoteTaskState,
+    request::NetworkRequestState,
+    rewind::RewindTaskState,
+    transactions::TransactionTaskState,
+    upgrade::UpgradeTaskState,
+    vid::VidTaskState,
+    view_sync::{ViewSyncRoundTimeout, ViewSyncTaskState},
 };
 use hotshot_types::{
     consensus::OuterConsensus,
+    local_builder::LocalMempool,
     traits::{
         consensus_api::ConsensusApi,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
     },
+    view_timeout::AdaptiveViewTimeout,
 };
 use tokio::spawn;
 
@@ -54,10 +66,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             delay: handle.hotshot.config.data_request_delay,
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             id: handle.hotshot.id,
             shutdown_flag: Arc::new(AtomicBool::new(false)),
-            spawned_tasks: BTreeMap::new(),
+            spawned_tasks: handle.hotshot.task_supervisor("request"),
             epoch_height: handle.epoch_height,
         }
     }
@@ -76,7 +88,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             vote_collectors: BTreeMap::default(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             id: handle.hotshot.id,
             start_proposing_view: handle.hotshot.config.start_proposing_view,
             stop_proposing_view: handle.hotshot.config.stop_proposing_view,
@@ -101,7 +113,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             network: Arc::clone(&handle.hotshot.network),
             vote_collector: None.into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             id: handle.hotshot.id,
             start_proposing_view: 5,
             stop_proposing_view: 10,
@@ -128,10 +140,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             network: Arc::clone(&handle.hotshot.network),
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.epoch_height,
+            vid_disperse_cache: Arc::new(Mutex::new(VidDisperseCache::default())),
+            vid_disperse_tasks: handle.hotshot.task_supervisor("vid"),
         }
     }
 }
@@ -143,6 +157,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         Self {
             consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            instance_state: handle.hotshot.instance_state(),
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             network: Arc::clone(&handle.hotshot.network),
@@ -150,10 +165,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             vote_collectors: BTreeMap::default(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            payload_provider: Arc::clone(&handle.hotshot.da_payload_provider),
+            payload_hint_threshold: handle.hotshot.config.da_payload_hint_threshold,
+            payload_hint_urls: handle.hotshot.config.da_payload_hint_urls.clone(),
+            fetch_tasks: handle.hotshot.task_supervisor("da_payload_fetch"),
+            precomputed_payload_commitments: Arc::default(),
+            precompute_tasks: handle.hotshot.task_supervisor("da_precompute"),
+            proposal_send_times: BTreeMap::default(),
+            votes_received: BTreeMap::default(),
         }
     }
 }
@@ -171,16 +194,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             num_timeouts_tracked: 0,
             replica_task_map: HashMap::default().into(),
             pre_commit_relay_map: HashMap::default().into(),
             commit_relay_map: HashMap::default().into(),
             finalize_relay_map: HashMap::default().into(),
-            view_sync_timeout: handle.hotshot.config.view_sync_timeout,
+            round_timeout: ViewSyncRoundTimeout::new(handle.hotshot.config.view_sync_timeout),
+            view_evidence_requested: None,
             id: handle.hotshot.id,
             last_garbage_collected_view: TYPES::View::new(0),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            consensus_metrics: Arc::clone(&handle.hotshot.metrics),
+            latest_view_evidence: None,
+            last_view_evidence_request: None,
         }
     }
 }
@@ -198,17 +225,19 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             instance_state: handle.hotshot.instance_state(),
             id: handle.hotshot.id,
-            builder_clients: handle
-                .hotshot
-                .config
-                .builder_urls
-                .iter()
-                .cloned()
-                .map(BuilderClient::new)
-                .collect(),
+            builder_clients: Arc::new(
+                handle
+                    .hotshot
+                    .config
+                    .builder_urls
+                    .iter()
+                    .cloned()
+                    .map(BuilderClient::new)
+                    .collect(),
+            ),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             auction_results_provider: Arc::clone(
                 &handle.hotshot.marketplace_config.auction_results_provider,
@@ -219,6 +248,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
                 .fallback_builder_url
                 .clone(),
             epoch_height: handle.epoch_height,
+            prefetch_task: None,
+            local_mempool: handle.hotshot.config.local_builder.map(LocalMempool::new),
+            transaction_dedup: handle.hotshot.transaction_dedup.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for DrbTaskState<TYPES, I>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            membership: Arc::clone(handle.hotshot.membership_coordinator.membership()),
+            storage: Arc::clone(&handle.storage),
+            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            latest_epoch: None,
         }
     }
 }
@@ -235,7 +281,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
 
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             state_private_key: handle.state_private_key().clone(),
             consensus: OuterConsensus::new(consensus),
             instance_state: handle.hotshot.instance_state(),
@@ -263,7 +309,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
         Self {
             latest_proposed_view: handle.cur_view().await,
             cur_epoch: handle.cur_epoch().await,
-            proposal_dependencies: BTreeMap::new(),
+            proposal_dependencies: handle.hotshot.task_supervisor("quorum_proposal"),
             formed_state_cert: BTreeMap::new(),
             formed_quorum_certificates: BTreeMap::new(),
             formed_next_epoch_quorum_certificates: BTreeMap::new(),
@@ -271,7 +317,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             instance_state: handle.hotshot.instance_state(),
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             storage: Arc::clone(&handle.storage),
             timeout: handle.hotshot.config.next_view_timeout,
             id: handle.hotshot.id,
@@ -291,7 +337,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
 
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             consensus: OuterConsensus::new(consensus),
             cur_view: handle.cur_view().await,
             cur_epoch: handle.cur_epoch().await,
@@ -299,10 +345,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             timeout: handle.hotshot.config.next_view_timeout,
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             storage: Arc::clone(&handle.storage),
-            spawned_tasks: BTreeMap::new(),
+            spawned_tasks: handle.hotshot.task_supervisor("quorum_proposal_recv"),
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            dependency_tracker: ProposalDependencyTracker::new(
+                handle.hotshot.task_supervisor("proposal_dependency_fetch"),
+            ),
         }
     }
 }
@@ -313,10 +362,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
 {
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         let consensus = handle.hotshot.consensus();
+        let adaptive_timeout = handle.hotshot.config.adaptive_view_timeout.map(|config| {
+            AdaptiveViewTimeout::new(handle.hotshot.config.next_view_timeout, config)
+        });
 
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer().clone(),
             instance_state: handle.hotshot.instance_state(),
             network: Arc::clone(&handle.hotshot.network),
             membership_coordinator: handle.hotshot.membership_coordinator.clone(),
@@ -329,7 +381,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             timeout_task: spawn(async {}),
-            timeout: handle.hotshot.config.next_view_timeout,
+            timeout: adaptive_timeout.as_ref().map_or(
+                handle.hotshot.config.next_view_timeout,
+                AdaptiveViewTimeout::current,
+            ),
+            adaptive_timeout,
             consensus: OuterConsensus::new(consensus),
             storage: Arc::clone(&handle.storage),
             id: handle.hotshot.id,
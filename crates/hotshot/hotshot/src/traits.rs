@@ -17,7 +17,9 @@ pub use node_implementation::{NodeImplementation, TestableNodeImplementation};
 /// Module for publicly usable implementations of the traits
 pub mod implementations {
     pub use super::networking::{
-        combined_network::{CombinedNetworks, UnderlyingCombinedNetworks},
+        combined_network::{
+            CombinedNetworks, FailoverPolicy, MessageClass, UnderlyingCombinedNetworks,
+        },
         libp2p_network::{
             derive_libp2p_keypair, derive_libp2p_multiaddr, derive_libp2p_peer_id, GossipConfig,
             Libp2pMetricsValue, Libp2pNetwork, PeerInfoVec, RequestResponseConfig,
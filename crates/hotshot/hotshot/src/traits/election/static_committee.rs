@@ -44,6 +44,9 @@ pub struct StaticCommittee<T: NodeType> {
     /// The first epoch which will be encountered. For testing, will panic if an epoch-carrying function is called
     /// when first_epoch is None or is Some greater than that epoch.
     first_epoch: Option<T::Epoch>,
+
+    /// Committees replacing this one from the given epoch onwards, used to simulate stake table changes
+    epoch_committees: BTreeMap<T::Epoch, Box<StaticCommittee<T>>>,
 }
 
 impl<TYPES: NodeType> StaticCommittee<TYPES> {
@@ -56,6 +59,26 @@ impl<TYPES: NodeType> StaticCommittee<TYPES> {
             }
         }
     }
+
+    /// The committee in effect for `epoch`
+    fn committee(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> &Self {
+        epoch
+            .and_then(|epoch| self.epoch_committees.range(..=epoch).next_back())
+            .map_or(self, |(_, committee)| committee)
+    }
+
+    /// Replace the committee from `epoch` onwards, until the next replacement.
+    ///
+    /// Used by tests to change the stake table between epochs.
+    pub fn set_committee_from_epoch(
+        &mut self,
+        epoch: TYPES::Epoch,
+        committee_members: Vec<PeerConfig<TYPES>>,
+        da_members: Vec<PeerConfig<TYPES>>,
+    ) {
+        let committee = <Self as Membership<TYPES>>::new(committee_members, da_members);
+        self.epoch_committees.insert(epoch, Box::new(committee));
+    }
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
@@ -110,19 +133,20 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
             indexed_stake_table,
             indexed_da_stake_table,
             first_epoch: None,
+            epoch_committees: BTreeMap::new(),
         }
     }
 
     /// Get the stake table for the current view
     fn stake_table(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> Vec<PeerConfig<TYPES>> {
         self.check_first_epoch(epoch);
-        self.stake_table.clone()
+        self.committee(epoch).stake_table.clone()
     }
 
     /// Get the stake table for the current view
     fn da_stake_table(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> Vec<PeerConfig<TYPES>> {
         self.check_first_epoch(epoch);
-        self.da_stake_table.clone()
+        self.committee(epoch).da_stake_table.clone()
     }
 
     /// Get all members of the committee for the current view
@@ -132,7 +156,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
         epoch: Option<<TYPES as NodeType>::Epoch>,
     ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         self.check_first_epoch(epoch);
-        self.stake_table
+        self.committee(epoch)
+            .stake_table
             .iter()
             .map(|sc| TYPES::SignatureKey::public_key(&sc.stake_table_entry))
             .collect()
@@ -145,7 +170,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
         epoch: Option<<TYPES as NodeType>::Epoch>,
     ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         self.check_first_epoch(epoch);
-        self.da_stake_table
+        self.committee(epoch)
+            .da_stake_table
             .iter()
             .map(|da| TYPES::SignatureKey::public_key(&da.stake_table_entry))
             .collect()
@@ -159,7 +185,10 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     ) -> Option<PeerConfig<TYPES>> {
         self.check_first_epoch(epoch);
         // Only return the stake if it is above zero
-        self.indexed_stake_table.get(pub_key).cloned()
+        self.committee(epoch)
+            .indexed_stake_table
+            .get(pub_key)
+            .cloned()
     }
 
     /// Get the DA stake table entry for a public key
@@ -170,7 +199,10 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     ) -> Option<PeerConfig<TYPES>> {
         self.check_first_epoch(epoch);
         // Only return the stake if it is above zero
-        self.indexed_da_stake_table.get(pub_key).cloned()
+        self.committee(epoch)
+            .indexed_da_stake_table
+            .get(pub_key)
+            .cloned()
     }
 
     /// Check if a node has stake in the committee
@@ -180,7 +212,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
         epoch: Option<<TYPES as NodeType>::Epoch>,
     ) -> bool {
         self.check_first_epoch(epoch);
-        self.indexed_stake_table
+        self.committee(epoch)
+            .indexed_stake_table
             .get(pub_key)
             .is_some_and(|x| x.stake_table_entry.stake() > U256::ZERO)
     }
@@ -192,7 +225,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
         epoch: Option<<TYPES as NodeType>::Epoch>,
     ) -> bool {
         self.check_first_epoch(epoch);
-        self.indexed_da_stake_table
+        self.committee(epoch)
+            .indexed_da_stake_table
             .get(pub_key)
             .is_some_and(|x| x.stake_table_entry.stake() > U256::ZERO)
     }
@@ -204,46 +238,47 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
         epoch: Option<<TYPES as NodeType>::Epoch>,
     ) -> Result<TYPES::SignatureKey> {
         self.check_first_epoch(epoch);
+        let committee = self.committee(epoch);
         #[allow(clippy::cast_possible_truncation)]
-        let index = *view_number as usize % self.eligible_leaders.len();
-        let res = self.eligible_leaders[index].clone();
+        let index = *view_number as usize % committee.eligible_leaders.len();
+        let res = committee.eligible_leaders[index].clone();
         Ok(TYPES::SignatureKey::public_key(&res.stake_table_entry))
     }
 
     /// Get the total number of nodes in the committee
     fn total_nodes(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> usize {
         self.check_first_epoch(epoch);
-        self.stake_table.len()
+        self.committee(epoch).stake_table.len()
     }
 
     /// Get the total number of DA nodes in the committee
     fn da_total_nodes(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> usize {
         self.check_first_epoch(epoch);
-        self.da_stake_table.len()
+        self.committee(epoch).da_stake_table.len()
     }
 
     /// Get the voting success threshold for the committee
     fn success_threshold(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> U256 {
         self.check_first_epoch(epoch);
-        U256::from(((self.stake_table.len() as u64 * 2) / 3) + 1)
+        U256::from(((self.committee(epoch).stake_table.len() as u64 * 2) / 3) + 1)
     }
 
     /// Get the voting success threshold for the committee
     fn da_success_threshold(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> U256 {
         self.check_first_epoch(epoch);
-        U256::from(((self.da_stake_table.len() as u64 * 2) / 3) + 1)
+        U256::from(((self.committee(epoch).da_stake_table.len() as u64 * 2) / 3) + 1)
     }
 
     /// Get the voting failure threshold for the committee
     fn failure_threshold(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> U256 {
         self.check_first_epoch(epoch);
-        U256::from(((self.stake_table.len() as u64) / 3) + 1)
+        U256::from(((self.committee(epoch).stake_table.len() as u64) / 3) + 1)
     }
 
     /// Get the voting upgrade threshold for the committee
     fn upgrade_threshold(&self, epoch: Option<<TYPES as NodeType>::Epoch>) -> U256 {
         self.check_first_epoch(epoch);
-        let len = self.stake_table.len();
+        let len = self.committee(epoch).stake_table.len();
         U256::from(max((len as u64 * 9) / 10, ((len as u64 * 2) / 3) + 1))
     }
     fn has_stake_table(&self, _epoch: TYPES::Epoch) -> bool {
@@ -7,14 +7,11 @@
 //! Networking Implementation that has a primary and a fallback network.  If the primary
 //! Errors we will use the backup to send or receive
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, VecDeque},
     future::Future,
     num::NonZeroUsize,
-    sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering},
-        Arc,
-    },
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use async_broadcast::{broadcast, InactiveReceiver, Sender};
@@ -29,19 +26,25 @@ use hotshot_types::{
     boxed_sync,
     constants::{
         COMBINED_NETWORK_CACHE_SIZE, COMBINED_NETWORK_DELAY_DURATION,
-        COMBINED_NETWORK_MIN_PRIMARY_FAILURES, COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL,
+        COMBINED_NETWORK_MIN_PRIMARY_FAILURES, COMBINED_NETWORK_NUM_RELAYS,
+        COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL,
     },
     data::ViewNumber,
     epoch_membership::EpochMembershipCoordinator,
     traits::{
-        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        metrics::{Counter, Metrics, NoMetrics},
+        network::{BroadcastDelay, ConnectedNetwork, PeerOffense, PeerReputation, Topic},
         node_implementation::NodeType,
     },
     BoxSyncFuture,
 };
 use lru::LruCache;
-use parking_lot::RwLock as PlRwLock;
-use tokio::{spawn, sync::mpsc::error::TrySendError, time::sleep};
+use parking_lot::{Mutex as PlMutex, RwLock as PlRwLock};
+use tokio::{
+    spawn,
+    sync::mpsc::error::TrySendError,
+    time::{sleep, timeout},
+};
 use tracing::{debug, info, warn};
 
 use super::{push_cdn_network::PushCdnNetwork, NetworkError};
@@ -50,6 +53,242 @@ use crate::traits::implementations::Libp2pNetwork;
 /// Thread-safe ref counted lock to a map of channels to the delayed tasks
 type DelayedTasksChannelsMap = Arc<RwLock<BTreeMap<u64, (Sender<()>, InactiveReceiver<()>)>>>;
 
+/// The kinds of messages whose delivery over the primary network is tracked separately by the
+/// [`FailoverPolicy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageClass {
+    /// Messages broadcast to every node
+    Broadcast,
+    /// Messages broadcast to the DA committee
+    DaBroadcast,
+    /// Messages sent to a single node
+    Direct,
+}
+
+/// Decides when the combined network considers the primary network down for a class of messages,
+/// and sends them on the secondary network without delay.
+///
+/// A send counts as failed when the primary network errors, takes longer than
+/// `max_primary_latency`, or when the view did not progress before the delayed copy of a message
+/// was sent on the secondary. The primary goes down once the error rate over the last `window`
+/// sends exceeds `max_error_rate`, and only comes back up once it is below `recovery_error_rate`
+/// and it has been down for `min_down_duration`, so that it does not flap between the two.
+#[derive(Clone, Copy, Debug)]
+pub struct FailoverPolicy {
+    /// Sends on the primary network taking longer than this count as failures. Direct messages
+    /// are sent on the secondary network instead once this has elapsed.
+    pub max_primary_latency: Duration,
+    /// How long to wait for a direct message to be sent on the secondary network, or handed to
+    /// relays, before falling back to the next route
+    pub direct_message_timeout: Duration,
+    /// Number of most recent sends the error rate is computed over
+    pub window: usize,
+    /// Error rate above which the primary is considered down
+    pub max_error_rate: f64,
+    /// Error rate below which a primary considered down is considered up again
+    pub recovery_error_rate: f64,
+    /// Minimum time the primary is considered down before it can be considered up again
+    pub min_down_duration: Duration,
+    /// While the primary is down, one message in this many is still delayed on the secondary, to
+    /// check whether the primary delivers again
+    pub probe_interval: u64,
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self {
+            max_primary_latency: Duration::from_secs(1),
+            direct_message_timeout: Duration::from_secs(2),
+            window: 2 * COMBINED_NETWORK_MIN_PRIMARY_FAILURES as usize,
+            max_error_rate: 0.5,
+            recovery_error_rate: 0.2,
+            min_down_duration: Duration::from_secs(10),
+            probe_interval: COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL,
+        }
+    }
+}
+
+/// Recent delivery over the primary network, for one class of messages
+#[derive(Debug, Default)]
+struct PrimaryHealth {
+    /// Outcomes of the most recent sends, `true` for failures
+    outcomes: VecDeque<bool>,
+    /// When the primary was last considered down, if it still is
+    down_since: Option<Instant>,
+    /// Messages sent without delay since the last probe of the primary
+    sent_since_probe: u64,
+}
+
+/// The state of the [`FailoverPolicy`] of a combined network
+#[derive(Debug, Default)]
+struct FailoverState {
+    /// The policy to apply
+    policy: FailoverPolicy,
+    /// Health of the primary network, for each class of messages
+    health: PlMutex<HashMap<MessageClass, PrimaryHealth>>,
+}
+
+impl FailoverState {
+    /// Record the outcome of sending a message of class `class` on the primary network
+    fn record(&self, class: MessageClass, failed: bool) {
+        let mut health = self.health.lock();
+        let health = health.entry(class).or_default();
+
+        health.outcomes.push_back(failed);
+        while health.outcomes.len() > self.policy.window {
+            health.outcomes.pop_front();
+        }
+        let failures = health.outcomes.iter().filter(|failed| **failed).count();
+        let error_rate = failures as f64 / self.policy.window.max(1) as f64;
+
+        match health.down_since {
+            None if error_rate > self.policy.max_error_rate => {
+                info!(
+                    ?class,
+                    error_rate,
+                    "Primary network is failing, stop delaying messages on the secondary"
+                );
+                health.down_since = Some(Instant::now());
+                health.sent_since_probe = 0;
+            },
+            Some(since)
+                if error_rate < self.policy.recovery_error_rate
+                    && since.elapsed() >= self.policy.min_down_duration =>
+            {
+                info!(
+                    ?class,
+                    error_rate, "Primary network recovered, delaying messages on the secondary"
+                );
+                health.down_since = None;
+            },
+            _ => {},
+        }
+    }
+
+    /// Whether the next message of class `class` should be delayed on the secondary network.
+    ///
+    /// While the primary is down, only one message in `probe_interval` is.
+    fn should_delay(&self, class: MessageClass) -> bool {
+        let mut health = self.health.lock();
+        let health = health.entry(class).or_default();
+        if health.down_since.is_none() {
+            return true;
+        }
+
+        health.sent_since_probe += 1;
+        if health.sent_since_probe < self.policy.probe_interval {
+            return false;
+        }
+        debug!(
+            ?class,
+            "Sent on secondary without delay {} times, try delaying to check primary",
+            self.policy.probe_interval
+        );
+        health.sent_since_probe = 0;
+        true
+    }
+
+    /// Whether the primary is considered down for any class of messages
+    fn is_down(&self) -> bool {
+        self.health
+            .lock()
+            .values()
+            .any(|health| health.down_since.is_some())
+    }
+}
+
+/// How direct messages sent through the combined network were delivered
+#[derive(Clone, Debug)]
+pub struct DirectDeliveryMetrics {
+    /// Direct messages sent on the primary network
+    pub primary: Box<dyn Counter>,
+    /// Direct messages sent on the secondary network, after failing on the primary
+    pub secondary: Box<dyn Counter>,
+    /// Direct messages relayed through other nodes, after failing on both networks
+    pub relayed: Box<dyn Counter>,
+    /// Direct messages which could not be sent at all
+    pub failed: Box<dyn Counter>,
+}
+
+impl DirectDeliveryMetrics {
+    /// Populate the metrics with the delivery metrics of the combined network
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        let subgroup = metrics.subgroup("combined_network".into());
+        Self {
+            primary: subgroup.create_counter("direct_messages_primary".into(), None),
+            secondary: subgroup.create_counter("direct_messages_secondary".into(), None),
+            relayed: subgroup.create_counter("direct_messages_relayed".into(), None),
+            failed: subgroup.create_counter("direct_messages_failed".into(), None),
+        }
+    }
+}
+
+impl Default for DirectDeliveryMetrics {
+    /// Initialize with empty metrics
+    fn default() -> Self {
+        Self::new(&*NoMetrics::boxed())
+    }
+}
+
+/// Deliver a direct message on the first route which succeeds: the primary network (unless it is
+/// skipped, as `None`), the secondary network, and finally relays through other nodes.
+///
+/// A route which errors or does not complete within the timeout set by `failover`'s policy is
+/// abandoned for the next one. Each route only starts once the previous one has been abandoned.
+async fn deliver_direct(
+    failover: &FailoverState,
+    metrics: &DirectDeliveryMetrics,
+    primary: Option<impl Future<Output = Result<(), NetworkError>>>,
+    secondary: impl Future<Output = Result<(), NetworkError>>,
+    relay: impl Future<Output = Result<(), NetworkError>>,
+) -> Result<(), NetworkError> {
+    let policy = &failover.policy;
+
+    if let Some(primary) = primary {
+        match timeout(policy.max_primary_latency, primary).await {
+            Ok(Ok(())) => {
+                failover.record(MessageClass::Direct, false);
+                metrics.primary.add(1);
+                return Ok(());
+            },
+            Ok(Err(e)) => warn!("Failed to send direct message on primary network: {e}"),
+            Err(_) => warn!(
+                "Timed out sending direct message on primary network after {:?}",
+                policy.max_primary_latency
+            ),
+        }
+        failover.record(MessageClass::Direct, true);
+    }
+
+    let secondary_error = match timeout(policy.direct_message_timeout, secondary).await {
+        Ok(Ok(())) => {
+            metrics.secondary.add(1);
+            return Ok(());
+        },
+        Ok(Err(e)) => e,
+        Err(_) => NetworkError::Timeout(format!(
+            "sending direct message on secondary network took more than {:?}",
+            policy.direct_message_timeout
+        )),
+    };
+    warn!("Failed to send direct message on secondary network: {secondary_error}");
+
+    let relay_error = match timeout(policy.direct_message_timeout, relay).await {
+        Ok(Ok(())) => {
+            metrics.relayed.add(1);
+            return Ok(());
+        },
+        Ok(Err(e)) => e,
+        Err(_) => NetworkError::Timeout(format!(
+            "relaying direct message took more than {:?}",
+            policy.direct_message_timeout
+        )),
+    };
+    warn!("Failed to relay direct message: {relay_error}");
+    metrics.failed.add(1);
+    Err(NetworkError::Multiple(vec![secondary_error, relay_error]))
+}
+
 /// A communication channel with 2 networks, where we can fall back to the slower network if the
 /// primary fails
 #[derive(Clone)]
@@ -60,20 +299,17 @@ pub struct CombinedNetworks<TYPES: NodeType> {
     /// Last n seen messages to prevent processing duplicates
     message_cache: Arc<PlRwLock<LruCache<blake3::Hash, ()>>>,
 
-    /// How many times primary failed to deliver
-    primary_fail_counter: Arc<AtomicU64>,
+    /// When the primary is considered down
+    failover: Arc<FailoverState>,
 
-    /// Whether primary is considered down
-    primary_down: Arc<AtomicBool>,
+    /// How direct messages were delivered
+    direct_metrics: Arc<DirectDeliveryMetrics>,
 
     /// How long to delay
     delay_duration: Arc<RwLock<Duration>>,
 
     /// Channels to the delayed tasks
     delayed_tasks_channels: DelayedTasksChannelsMap,
-
-    /// How many times messages were sent on secondary without delay because primary is down
-    no_delay_counter: Arc<AtomicU64>,
 }
 
 impl<TYPES: NodeType> CombinedNetworks<TYPES> {
@@ -99,16 +335,32 @@ impl<TYPES: NodeType> CombinedNetworks<TYPES> {
             message_cache: Arc::new(PlRwLock::new(LruCache::new(
                 NonZeroUsize::new(COMBINED_NETWORK_CACHE_SIZE).unwrap(),
             ))),
-            primary_fail_counter: Arc::new(AtomicU64::new(0)),
-            primary_down: Arc::new(AtomicBool::new(false)),
+            failover: Arc::default(),
+            direct_metrics: Arc::default(),
             delay_duration: Arc::new(RwLock::new(
                 delay_duration.unwrap_or(Duration::from_millis(COMBINED_NETWORK_DELAY_DURATION)),
             )),
             delayed_tasks_channels: Arc::default(),
-            no_delay_counter: Arc::new(AtomicU64::new(0)),
         }
     }
 
+    /// Use `policy` to decide when the primary network is considered down
+    #[must_use]
+    pub fn with_failover_policy(mut self, policy: FailoverPolicy) -> Self {
+        self.failover = Arc::new(FailoverState {
+            policy,
+            health: PlMutex::default(),
+        });
+        self
+    }
+
+    /// Report how direct messages are delivered in `metrics`
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: &dyn Metrics) -> Self {
+        self.direct_metrics = Arc::new(DirectDeliveryMetrics::new(metrics));
+        self
+    }
+
     /// Get a ref to the primary network
     #[must_use]
     pub fn primary(&self) -> &PushCdnNetwork<TYPES::SignatureKey> {
@@ -125,40 +377,45 @@ impl<TYPES: NodeType> CombinedNetworks<TYPES> {
     async fn send_both_networks(
         &self,
         _message: Vec<u8>,
+        class: MessageClass,
         primary_future: impl Future<Output = Result<(), NetworkError>> + Send + 'static,
         secondary_future: impl Future<Output = Result<(), NetworkError>> + Send + 'static,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
-        // A local variable used to decide whether to delay this message or not
-        let mut primary_failed = false;
-        if self.primary_down.load(Ordering::Relaxed) {
-            // If the primary is considered down, we don't want to delay
-            primary_failed = true;
-        } else if self.primary_fail_counter.load(Ordering::Relaxed)
-            > COMBINED_NETWORK_MIN_PRIMARY_FAILURES
-        {
-            // If the primary failed more than `COMBINED_NETWORK_MIN_PRIMARY_FAILURES` times,
-            // we don't want to delay this message, and from now on we consider the primary as down
-            info!(
-                "View progression is slower than normally, stop delaying messages on the secondary"
-            );
-            self.primary_down.store(true, Ordering::Relaxed);
-            primary_failed = true;
-        }
+        // Only messages tied to a view can be delayed, since the view progressing is how we know
+        // that the primary delivered them
+        let delay_view = match broadcast_delay {
+            BroadcastDelay::View(view) if self.failover.should_delay(class) => Some(view),
+            _ => None,
+        };
 
         // Always send on the primary network
-        if let Err(e) = primary_future.await {
-            // If the primary failed right away, we don't want to delay this message
-            warn!("Error on primary network: {}", e);
-            self.primary_fail_counter.fetch_add(1, Ordering::Relaxed);
-            primary_failed = true;
+        let started = Instant::now();
+        let primary_failed = match primary_future.await {
+            Err(e) => {
+                warn!("Error on primary network: {}", e);
+                true
+            },
+            Ok(()) if started.elapsed() > self.failover.policy.max_primary_latency => {
+                warn!(
+                    "Primary network took {:?} to send, more than {:?}",
+                    started.elapsed(),
+                    self.failover.policy.max_primary_latency
+                );
+                true
+            },
+            Ok(()) => false,
         };
+        // A successful send on the primary only tells us whether the message was delivered if the
+        // message is not delayed on the secondary; otherwise the delayed task records the outcome.
+        if primary_failed || delay_view.is_none() {
+            self.failover.record(class, primary_failed);
+        }
 
-        if let (BroadcastDelay::View(view), false) = (broadcast_delay, primary_failed) {
+        if let (Some(view), false) = (delay_view, primary_failed) {
             // We are delaying this message
             let duration = *self.delay_duration.read().await;
-            let primary_down = Arc::clone(&self.primary_down);
-            let primary_fail_counter = Arc::clone(&self.primary_fail_counter);
+            let failover = Arc::clone(&self.failover);
             // Each delayed task gets its own receiver clone to get a signal cancelling all tasks
             // related to the given view.
             let mut receiver = self
@@ -180,56 +437,17 @@ impl<TYPES: NodeType> CombinedNetworks<TYPES> {
                     debug!(
                         "Not sending on secondary after delay, task was canceled in view update"
                     );
-                    match primary_fail_counter.load(Ordering::Relaxed) {
-                        0u64 => {
-                            // The primary fail counter reached 0, the primary is now considered up
-                            primary_down.store(false, Ordering::Relaxed);
-                            debug!("primary_fail_counter reached zero, primary_down set to false");
-                        },
-                        c => {
-                            // Decrement the primary fail counter
-                            primary_fail_counter.store(c - 1, Ordering::Relaxed);
-                            debug!("primary_fail_counter set to {:?}", c - 1);
-                        },
-                    }
+                    failover.record(class, false);
                     return Ok(());
                 }
                 // The task hasn't been cancelled, the primary probably failed.
-                // Increment the primary fail counter and send the message.
                 debug!("Sending on secondary after delay, message possibly has not reached recipient on primary");
-                primary_fail_counter.fetch_add(1, Ordering::Relaxed);
+                failover.record(class, true);
                 secondary_future.await
             });
             Ok(())
         } else {
-            // We will send without delay
-            if self.primary_down.load(Ordering::Relaxed) {
-                // If the primary is considered down, we want to periodically delay sending
-                // on the secondary to check whether the primary is able to deliver.
-                // This message will be sent without delay but the next might be delayed.
-                match self.no_delay_counter.load(Ordering::Relaxed) {
-                    c if c < COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL => {
-                        // Just increment the 'no delay counter'
-                        self.no_delay_counter.store(c + 1, Ordering::Relaxed);
-                    },
-                    _ => {
-                        // The 'no delay counter' reached the threshold
-                        debug!(
-                            "Sent on secondary without delay more than {} times,\
-                            try delaying to check primary",
-                            COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL
-                        );
-                        // Reset the 'no delay counter'
-                        self.no_delay_counter.store(0u64, Ordering::Relaxed);
-                        // The primary is not considered down for the moment
-                        self.primary_down.store(false, Ordering::Relaxed);
-                        // The primary fail counter is set just below the threshold to delay the next message
-                        self.primary_fail_counter
-                            .store(COMBINED_NETWORK_MIN_PRIMARY_FAILURES, Ordering::Relaxed);
-                    },
-                }
-            }
-            // Send the message
+            // Send the message without delay
             secondary_future.await
         }
     }
@@ -298,12 +516,11 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES> for CombinedNetwor
                 // Combine the two networks with the same cache
                 let combined_network = Self {
                     networks: Arc::new(underlying_combined),
-                    primary_fail_counter: Arc::new(AtomicU64::new(0)),
-                    primary_down: Arc::new(AtomicBool::new(false)),
+                    failover: Arc::default(),
+                    direct_metrics: Arc::default(),
                     message_cache: Arc::clone(&message_cache),
                     delay_duration: Arc::new(RwLock::new(secondary_network_delay)),
                     delayed_tasks_channels: Arc::default(),
-                    no_delay_counter: Arc::new(AtomicU64::new(0)),
                 };
 
                 Arc::new(combined_network)
@@ -322,11 +539,18 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES> for CombinedNetwor
 #[async_trait]
 impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks<TYPES> {
     fn pause(&self) {
-        self.networks.0.pause();
+        self.primary().pause();
+        self.secondary().pause();
     }
 
     fn resume(&self) {
-        self.networks.0.resume();
+        self.primary().resume();
+        self.secondary().resume();
+    }
+
+    /// Messages keep flowing over either network unless both of them are paused.
+    fn can_pause(&self) -> bool {
+        self.primary().can_pause() && self.secondary().can_pause()
     }
 
     async fn wait_for_ready(&self) {
@@ -360,6 +584,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         let topic_clone = topic.clone();
         self.send_both_networks(
             message,
+            MessageClass::Broadcast,
             async move {
                 primary
                     .broadcast_message(primary_message, topic_clone, BroadcastDelay::None)
@@ -388,6 +613,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         let primary_recipients = recipients.clone();
         self.send_both_networks(
             message,
+            MessageClass::DaBroadcast,
             async move {
                 primary
                     .da_broadcast_message(primary_message, primary_recipients, BroadcastDelay::None)
@@ -403,25 +629,30 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
         .await
     }
 
+    /// Send a direct message on the primary network, retrying on the secondary network if that
+    /// fails or times out, and finally relaying it through other nodes if both networks fail.
     async fn direct_message(
         &self,
         message: Vec<u8>,
         recipient: TYPES::SignatureKey,
     ) -> Result<(), NetworkError> {
-        let primary = self.primary().clone();
-        let secondary = self.secondary().clone();
-        let primary_message = message.clone();
-        let secondary_message = message.clone();
-        let primary_recipient = recipient.clone();
-        self.send_both_networks(
-            message,
-            async move {
-                primary
-                    .direct_message(primary_message, primary_recipient)
-                    .await
-            },
-            async move { secondary.direct_message(secondary_message, recipient).await },
-            BroadcastDelay::None,
+        // Skip the primary while it is considered down for direct messages, except to probe it
+        let primary = self.failover.should_delay(MessageClass::Direct).then(|| {
+            self.primary()
+                .direct_message(message.clone(), recipient.clone())
+        });
+        let secondary = self
+            .secondary()
+            .direct_message(message.clone(), recipient.clone());
+        let relay =
+            self.secondary()
+                .relay_direct_message(message, &recipient, COMBINED_NETWORK_NUM_RELAYS);
+        deliver_direct(
+            &self.failover,
+            &self.direct_metrics,
+            primary,
+            secondary,
+            relay,
         )
         .await
     }
@@ -500,6 +731,164 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     }
 
     fn is_primary_down(&self) -> bool {
-        self.primary_down.load(Ordering::Relaxed)
+        self.failover.is_down()
+    }
+
+    fn report_message(&self, message: &[u8], offense: PeerOffense) {
+        // Only the network which delivered the message knows its source
+        self.primary().report_message(message, offense);
+        self.secondary().report_message(message, offense);
+    }
+
+    async fn peer_reputations(&self) -> Vec<PeerReputation> {
+        let mut reputations = self.primary().peer_reputations().await;
+        reputations.extend(self.secondary().peer_reputations().await);
+        reputations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use futures::future::pending;
+
+    use super::*;
+
+    /// A route which records whether it was tried, and then completes with `outcome`
+    async fn route(
+        tried: &AtomicBool,
+        outcome: impl Future<Output = Result<(), NetworkError>>,
+    ) -> Result<(), NetworkError> {
+        tried.store(true, Ordering::SeqCst);
+        outcome.await
+    }
+
+    fn failed() -> Result<(), NetworkError> {
+        Err(NetworkError::MessageSendError("failed".into()))
+    }
+
+    #[tokio::test]
+    async fn test_direct_message_fallback() {
+        let failover = FailoverState {
+            policy: FailoverPolicy {
+                max_primary_latency: Duration::from_millis(50),
+                direct_message_timeout: Duration::from_millis(50),
+                ..FailoverPolicy::default()
+            },
+            health: PlMutex::default(),
+        };
+        let metrics = DirectDeliveryMetrics::default();
+        let outcomes = |failover: &FailoverState| {
+            failover.health.lock()[&MessageClass::Direct]
+                .outcomes
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        // A message delivered on the primary is not sent anywhere else
+        let [primary, secondary, relay] = [(); 3].map(|()| AtomicBool::new(false));
+        deliver_direct(
+            &failover,
+            &metrics,
+            Some(route(&primary, async { Ok(()) })),
+            route(&secondary, async { Ok(()) }),
+            route(&relay, async { Ok(()) }),
+        )
+        .await
+        .unwrap();
+        assert!(primary.load(Ordering::SeqCst));
+        assert!(!secondary.load(Ordering::SeqCst));
+        assert!(!relay.load(Ordering::SeqCst));
+        assert_eq!(outcomes(&failover), [false]);
+
+        // A primary which never completes falls back to the secondary, and counts as a failure
+        let [primary, secondary, relay] = [(); 3].map(|()| AtomicBool::new(false));
+        deliver_direct(
+            &failover,
+            &metrics,
+            Some(route(&primary, pending())),
+            route(&secondary, async { Ok(()) }),
+            route(&relay, async { Ok(()) }),
+        )
+        .await
+        .unwrap();
+        assert!(secondary.load(Ordering::SeqCst));
+        assert!(!relay.load(Ordering::SeqCst));
+        assert_eq!(outcomes(&failover), [false, true]);
+
+        // An erroring primary and a hanging secondary fall back to relays
+        let [primary, secondary, relay] = [(); 3].map(|()| AtomicBool::new(false));
+        deliver_direct(
+            &failover,
+            &metrics,
+            Some(route(&primary, async { failed() })),
+            route(&secondary, pending()),
+            route(&relay, async { Ok(()) }),
+        )
+        .await
+        .unwrap();
+        assert!(secondary.load(Ordering::SeqCst));
+        assert!(relay.load(Ordering::SeqCst));
+        assert_eq!(outcomes(&failover), [false, true, true]);
+
+        // A skipped primary is not tried, and the message fails once every route has failed
+        let [secondary, relay] = [(); 2].map(|()| AtomicBool::new(false));
+        let err = deliver_direct(
+            &failover,
+            &metrics,
+            None::<std::future::Ready<_>>,
+            route(&secondary, async { failed() }),
+            route(&relay, pending()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, NetworkError::Multiple(errors) if errors.len() == 2));
+        assert!(relay.load(Ordering::SeqCst));
+        assert_eq!(outcomes(&failover), [false, true, true]);
+    }
+
+    #[test]
+    fn test_failover_hysteresis() {
+        let failover = FailoverState {
+            policy: FailoverPolicy {
+                window: 10,
+                max_error_rate: 0.5,
+                recovery_error_rate: 0.2,
+                min_down_duration: Duration::ZERO,
+                probe_interval: 3,
+                ..FailoverPolicy::default()
+            },
+            health: PlMutex::default(),
+        };
+
+        // Five failures out of ten is not enough to fail over
+        for _ in 0..5 {
+            failover.record(MessageClass::Broadcast, true);
+        }
+        assert!(!failover.is_down());
+        assert!(failover.should_delay(MessageClass::Broadcast));
+
+        failover.record(MessageClass::Broadcast, true);
+        assert!(failover.is_down());
+
+        // Only one message in `probe_interval` is delayed, and only for the failing class
+        assert!(!failover.should_delay(MessageClass::Broadcast));
+        assert!(!failover.should_delay(MessageClass::Broadcast));
+        assert!(failover.should_delay(MessageClass::Broadcast));
+        assert!(failover.should_delay(MessageClass::Direct));
+
+        // Dropping below the failover threshold is not enough to recover
+        for _ in 0..6 {
+            failover.record(MessageClass::Broadcast, false);
+        }
+        assert!(failover.is_down());
+
+        failover.record(MessageClass::Broadcast, false);
+        failover.record(MessageClass::Broadcast, false);
+        assert!(failover.is_down());
+        failover.record(MessageClass::Broadcast, false);
+        assert!(!failover.is_down());
     }
 }
@@ -55,7 +55,7 @@ use hotshot_types::{
     network::NetworkConfig,
     traits::{
         metrics::{Counter, Gauge, Metrics, NoMetrics},
-        network::{ConnectedNetwork, NetworkError, Topic},
+        network::{ConnectedNetwork, NetworkError, PeerOffense, PeerReputation, Topic},
         node_implementation::{ConsensusTime, NodeType},
         signature_key::{PrivateSignatureKey, SignatureKey},
     },
@@ -65,8 +65,10 @@ use libp2p_identity::{
     ed25519::{self, SecretKey},
     Keypair, PeerId,
 };
+use lru::LruCache;
+use parking_lot::{Mutex as PlMutex, RwLock as PlRwLock};
 use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use tokio::{
     select, spawn,
     sync::{
@@ -75,7 +77,7 @@ use tokio::{
     },
     time::sleep,
 };
-use tracing::{error, info, instrument, trace, warn};
+use tracing::{debug, error, info, instrument, trace, warn};
 
 use crate::{BroadcastDelay, EpochMembershipCoordinator};
 
@@ -119,6 +121,9 @@ pub type BootstrapAddrs = Arc<RwLock<Vec<(PeerId, Multiaddr)>>>;
 /// hardcoded topic of QC used
 pub const QC_TOPIC: &str = "global";
 
+/// Number of recently received messages whose source we remember
+const MESSAGE_SOURCES_CACHE_SIZE: usize = 10_000;
+
 /// Stubbed out Ack
 ///
 /// Note: as part of versioning for upgradability,
@@ -134,6 +139,37 @@ pub struct Empty {
     byte: u8,
 }
 
+/// Bytes at the start of every direct message relayed through another peer. Like the magic of
+/// compressed messages, it can never start a serialized message, whose first bytes are its version.
+const RELAY_MESSAGE_MAGIC: [u8; 4] = [0xff, 0xff, b'r', b'l'];
+
+/// A direct message sent through a relay, because we could not reach its recipient ourselves
+#[derive(Serialize, Deserialize)]
+struct RelayedMessage {
+    /// Serialized public key of the node asking for the message to be relayed
+    sender: Vec<u8>,
+    /// Serialized public key of the recipient
+    recipient: Vec<u8>,
+    /// The message to deliver
+    message: Vec<u8>,
+}
+
+impl RelayedMessage {
+    /// Serialize the message, prefixed with [`RELAY_MESSAGE_MAGIC`]
+    fn encode(&self) -> Result<Vec<u8>, NetworkError> {
+        let mut bytes = RELAY_MESSAGE_MAGIC.to_vec();
+        bincode::serialize_into(&mut bytes, self).map_err(|e| {
+            NetworkError::FailedToSerialize(format!("failed to serialize relayed message: {e}"))
+        })?;
+        Ok(bytes)
+    }
+
+    /// Deserialize a message, if it was encoded by [`Self::encode`]
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::deserialize(bytes.strip_prefix(&RELAY_MESSAGE_MAGIC)?).ok()
+    }
+}
+
 impl<T: NodeType> Debug for Libp2pNetwork<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Libp2p").field("inner", &"inner").finish()
@@ -178,6 +214,12 @@ struct Libp2pNetworkInner<T: NodeType> {
     reliability_config: Option<Box<dyn NetworkReliability>>,
     /// Killswitch sender
     kill_switch: Sender<()>,
+    /// The peer which delivered each recently received message, by hash, so that the peer can be
+    /// held responsible for the message
+    message_sources: PlMutex<LruCache<blake3::Hash, PeerId>>,
+    /// The epoch of the latest view and the keys staked in it, which are the only nodes direct
+    /// messages are relayed for and to. `None` until the first view update.
+    staked_keys: PlRwLock<Option<(Option<u64>, HashSet<T::SignatureKey>)>>,
 }
 
 /// Networking implementation that uses libp2p
@@ -572,6 +614,10 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 #[cfg(feature = "hotshot-testing")]
                 reliability_config,
                 kill_switch: kill_tx,
+                message_sources: PlMutex::new(LruCache::new(
+                    NonZeroUsize::new(MESSAGE_SOURCES_CACHE_SIZE).unwrap(),
+                )),
+                staked_keys: PlRwLock::default(),
             }),
         };
 
@@ -671,6 +717,148 @@ impl<T: NodeType> Libp2pNetwork<T> {
         });
     }
 
+    /// Add `peers` to our bootstrap nodes and bootstrap against them
+    ///
+    /// # Errors
+    /// If the peers could not be handed to the network behaviour
+    pub async fn add_bootstrap_nodes(
+        &self,
+        peers: Vec<(PeerId, Multiaddr)>,
+    ) -> Result<(), NetworkError> {
+        self.inner
+            .bootstrap_addrs
+            .write()
+            .await
+            .extend(peers.iter().cloned());
+        self.inner.handle.add_known_peers(peers)?;
+        self.inner.handle.begin_bootstrap()
+    }
+
+    /// Remember that `pid` delivered `message`
+    fn record_message_source(&self, message: &[u8], pid: PeerId) {
+        self.inner
+            .message_sources
+            .lock()
+            .put(blake3::hash(message), pid);
+    }
+
+    /// Send `message` to `recipient` through up to `num_relays` random peers we are connected to,
+    /// which forward it on our behalf. Used when we cannot reach `recipient` ourselves.
+    ///
+    /// # Errors
+    /// If the message could not be handed to any peer
+    pub async fn relay_direct_message(
+        &self,
+        message: Vec<u8>,
+        recipient: &T::SignatureKey,
+        num_relays: usize,
+    ) -> Result<(), NetworkError> {
+        let relayed = RelayedMessage {
+            sender: self.inner.pk.to_bytes(),
+            recipient: recipient.to_bytes(),
+            message,
+        }
+        .encode()?;
+
+        let relays = self
+            .inner
+            .handle
+            .connected_pids()
+            .await?
+            .into_iter()
+            .choose_multiple(&mut rand::thread_rng(), num_relays);
+        let mut relayed_by_any = false;
+        for pid in relays {
+            match self.inner.handle.direct_request(pid, &relayed) {
+                Ok(()) => relayed_by_any = true,
+                Err(err) => warn!("Failed to relay direct message through {pid}: {err}"),
+            }
+        }
+
+        if relayed_by_any {
+            Ok(())
+        } else {
+            self.inner.metrics.num_failed_messages.add(1);
+            Err(NetworkError::MessageSendError(
+                "no peer to relay the direct message through".to_string(),
+            ))
+        }
+    }
+
+    /// Whether `key` is staked in the epoch of the latest view
+    fn is_staked(&self, key: &T::SignatureKey) -> bool {
+        self.inner
+            .staked_keys
+            .read()
+            .as_ref()
+            .is_some_and(|(_, keys)| keys.contains(key))
+    }
+
+    /// Deliver a direct message relayed to us by `pid`, or forward it to its recipient.
+    ///
+    /// Messages are only relayed between staked nodes, and only forwarded when `pid` is the peer
+    /// of the node which asked for the message to be relayed, so that we cannot be used as an open
+    /// relay.
+    fn handle_relayed_message(
+        &self,
+        relayed: &[u8],
+        pid: PeerId,
+        sender: &Sender<Vec<u8>>,
+    ) -> Result<(), NetworkError> {
+        let Some((origin, recipient, message)) =
+            RelayedMessage::decode(relayed).and_then(|relayed| {
+                let origin = T::SignatureKey::from_bytes(&relayed.sender).ok()?;
+                let recipient = T::SignatureKey::from_bytes(&relayed.recipient).ok()?;
+                Some((origin, recipient, relayed.message))
+            })
+        else {
+            self.report_message(relayed, PeerOffense::MalformedMessage);
+            return Ok(());
+        };
+
+        if self.inner.staked_keys.read().is_none() {
+            debug!("Dropping direct message relayed by {pid} before the stake table is known");
+            return Ok(());
+        }
+        if !self.is_staked(&origin) || !self.is_staked(&recipient) {
+            warn!("Dropping direct message relayed by {pid} for or to an unstaked node");
+            self.report_message(relayed, PeerOffense::Spam);
+            return Ok(());
+        }
+
+        if recipient == self.inner.pk {
+            return sender.try_send(message).map_err(|err| {
+                NetworkError::ChannelSendError(format!("failed to send relayed message: {err}"))
+            });
+        }
+
+        // Forward the message directly, never through another relay, so that it cannot bounce
+        // between relays
+        let network = self.clone();
+        spawn(async move {
+            match network
+                .inner
+                .handle
+                .lookup_node(&origin.to_bytes(), network.inner.dht_timeout)
+                .await
+            {
+                Ok(origin_pid) if origin_pid == pid => {},
+                Ok(origin_pid) => {
+                    return warn!(
+                        "Dropping direct message relayed by {pid} on behalf of {origin_pid}"
+                    );
+                },
+                Err(err) => {
+                    return warn!("Failed to look up the origin of a relayed message: {err}");
+                },
+            }
+            if let Err(err) = network.direct_message(message, recipient).await {
+                warn!("Failed to forward relayed direct message: {err}");
+            }
+        });
+        Ok(())
+    }
+
     /// Handle events
     fn handle_recvd_events(
         &self,
@@ -678,17 +866,23 @@ impl<T: NodeType> Libp2pNetwork<T> {
         sender: &Sender<Vec<u8>>,
     ) -> Result<(), NetworkError> {
         match msg {
-            GossipMsg(msg) => {
+            GossipMsg(msg, pid) => {
+                self.record_message_source(&msg, pid);
                 sender.try_send(msg).map_err(|err| {
                     NetworkError::ChannelSendError(format!("failed to send gossip message: {err}"))
                 })?;
             },
-            DirectRequest(msg, _pid, chan) => {
-                sender.try_send(msg).map_err(|err| {
-                    NetworkError::ChannelSendError(format!(
-                        "failed to send direct request message: {err}"
-                    ))
-                })?;
+            DirectRequest(msg, pid, chan) => {
+                self.record_message_source(&msg, pid);
+                if msg.starts_with(&RELAY_MESSAGE_MAGIC) {
+                    self.handle_relayed_message(&msg, pid, sender)?;
+                } else {
+                    sender.try_send(msg).map_err(|err| {
+                        NetworkError::ChannelSendError(format!(
+                            "failed to send direct request message: {err}"
+                        ))
+                    })?;
+                }
                 if self
                     .inner
                     .handle
@@ -739,7 +933,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
                             NetworkEvent::IsBootstrapped => {
                                 is_bootstrapped.store(true, Ordering::Relaxed);
                             }
-                            GossipMsg(_) | DirectRequest(_, _, _) | DirectResponse(_, _) => {
+                            GossipMsg(_, _) | DirectRequest(_, _, _) | DirectResponse(_, _) => {
                                 let _ = handle.handle_recvd_events(message, &sender);
                             }
                             NetworkEvent::ConnectedPeersUpdate(num_peers) => {
@@ -765,13 +959,11 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         self.wait_for_ready().await;
     }
 
-    fn pause(&self) {
-        unimplemented!("Pausing not implemented for the Libp2p network");
-    }
+    /// Pausing is not supported by the Libp2p network, see [`ConnectedNetwork::can_pause`].
+    fn pause(&self) {}
 
-    fn resume(&self) {
-        unimplemented!("Resuming not implemented for the Libp2p network");
-    }
+    /// Pausing is not supported by the Libp2p network, see [`ConnectedNetwork::can_pause`].
+    fn resume(&self) {}
 
     #[instrument(name = "Libp2pNetwork::shut_down", skip_all)]
     fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
@@ -959,6 +1151,32 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         Ok(result)
     }
 
+    fn report_message(&self, message: &[u8], offense: PeerOffense) {
+        let Some(pid) = self
+            .inner
+            .message_sources
+            .lock()
+            .get(&blake3::hash(message))
+            .copied()
+        else {
+            return;
+        };
+        if let Err(err) = self.inner.handle.report_peer(pid, offense) {
+            warn!("Failed to report peer {pid}: {err}");
+        }
+    }
+
+    async fn peer_reputations(&self) -> Vec<PeerReputation> {
+        self.inner
+            .handle
+            .peer_reputations()
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Failed to get peer reputations: {err}");
+                vec![]
+            })
+    }
+
     #[instrument(name = "Libp2pNetwork::queue_node_lookup", skip_all)]
     #[allow(clippy::type_complexity)]
     fn queue_node_lookup(
@@ -1000,6 +1218,23 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
                 return tracing::warn!(e.message);
             },
         };
+        // Keep track of the stake table of the current epoch, to restrict relaying to stakers
+        let stale = self
+            .inner
+            .staked_keys
+            .read()
+            .as_ref()
+            .is_none_or(|(staked_epoch, _)| *staked_epoch != epoch.map(|epoch| *epoch));
+        if stale {
+            let keys = membership
+                .stake_table()
+                .await
+                .into_iter()
+                .map(|peer| T::SignatureKey::public_key(&peer.stake_table_entry))
+                .collect();
+            *self.inner.staked_keys.write() = Some((epoch.map(|epoch| *epoch), keys));
+        }
+
         let future_leader = match membership.leader(future_view).await {
             Ok(l) => l,
             Err(e) => {
@@ -1100,4 +1335,29 @@ mod test {
             assert!(multiaddr.is_err());
         }
     }
+
+    mod relay {
+        use super::super::*;
+
+        /// Test that relayed messages round trip, and cannot be confused with other messages
+        #[test]
+        fn test_relayed_message_encoding() {
+            let relayed = RelayedMessage {
+                sender: vec![0],
+                recipient: vec![1, 2, 3],
+                message: vec![4, 5, 6],
+            }
+            .encode()
+            .unwrap();
+            assert!(relayed.starts_with(&RELAY_MESSAGE_MAGIC));
+
+            let decoded = RelayedMessage::decode(&relayed).unwrap();
+            assert_eq!(decoded.sender, vec![0]);
+            assert_eq!(decoded.recipient, vec![1, 2, 3]);
+            assert_eq!(decoded.message, vec![4, 5, 6]);
+
+            assert!(RelayedMessage::decode(&relayed[RELAY_MESSAGE_MAGIC.len()..]).is_none());
+            assert!(RelayedMessage::decode(&relayed[..relayed.len() - 1]).is_none());
+        }
+    }
 }
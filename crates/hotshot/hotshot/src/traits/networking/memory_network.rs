@@ -222,13 +222,11 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
     #[instrument(name = "MemoryNetwork::ready_blocking")]
     async fn wait_for_ready(&self) {}
 
-    fn pause(&self) {
-        unimplemented!("Pausing not implemented for the Memory network");
-    }
+    /// Pausing is not supported by the Memory network, see [`ConnectedNetwork::can_pause`].
+    fn pause(&self) {}
 
-    fn resume(&self) {
-        unimplemented!("Resuming not implemented for the Memory network");
-    }
+    /// Pausing is not supported by the Memory network, see [`ConnectedNetwork::can_pause`].
+    fn resume(&self) {}
 
     #[instrument(name = "MemoryNetwork::shut_down")]
     fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
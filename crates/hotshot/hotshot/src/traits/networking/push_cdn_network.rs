@@ -46,7 +46,7 @@ use hotshot_types::{
     BoxSyncFuture,
 };
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 #[cfg(feature = "hotshot-testing")]
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use tokio::{spawn, sync::mpsc::error::TrySendError, time::sleep};
@@ -189,8 +189,12 @@ impl<K: SignatureKey + 'static> RunDef for TestingDef<K> {
 #[derive(Clone)]
 /// Is generic over both the type of key and the network protocol.
 pub struct PushCdnNetwork<K: SignatureKey + 'static> {
-    /// The underlying client
-    client: Client<ClientDef<K>>,
+    /// The underlying client, replaced when the marshal endpoint changes
+    client: Arc<RwLock<Client<ClientDef<K>>>>,
+    /// The topics the client subscribes to
+    topics: Vec<u8>,
+    /// The keypair the client authenticates with
+    keypair: KeyPair<WrappedSignatureKey<K>>,
     /// The CDN-specific metrics
     metrics: Arc<CdnMetricsValue>,
     /// The internal queue for messages to ourselves
@@ -232,9 +236,10 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
         metrics: CdnMetricsValue,
     ) -> anyhow::Result<Self> {
         // Build config
+        let topics: Vec<u8> = topics.into_iter().map(|t| t as u8).collect();
         let config = ClientConfig {
             endpoint: marshal_endpoint,
-            subscribed_topics: topics.into_iter().map(|t| t as u8).collect(),
+            subscribed_topics: topics.clone(),
             keypair: keypair.clone(),
             use_local_authority: true,
         };
@@ -243,7 +248,9 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
         let client = Client::new(config);
 
         Ok(Self {
-            client,
+            client: Arc::new(RwLock::new(client)),
+            topics,
+            keypair: keypair.clone(),
             metrics: Arc::from(metrics),
             internal_queue: Arc::new(Mutex::new(VecDeque::new())),
             public_key: keypair.public_key.0,
@@ -253,6 +260,24 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
         })
     }
 
+    /// The current client
+    fn client(&self) -> Client<ClientDef<K>> {
+        self.client.read().clone()
+    }
+
+    /// Connect to the CDN through the marshal at `marshal_endpoint` from now on, closing the
+    /// connection made through the previous marshal
+    pub fn set_marshal_endpoint(&self, marshal_endpoint: String) {
+        let client = Client::new(ClientConfig {
+            endpoint: marshal_endpoint,
+            subscribed_topics: self.topics.clone(),
+            keypair: self.keypair.clone(),
+            use_local_authority: true,
+        });
+        let previous = std::mem::replace(&mut *self.client.write(), client);
+        spawn(async move { previous.close().await });
+    }
+
     /// Broadcast a message to members of the particular topic. Does not retry.
     ///
     /// # Errors
@@ -267,7 +292,7 @@ impl<K: SignatureKey + 'static> PushCdnNetwork<K> {
 
         // Send the message
         if let Err(err) = self
-            .client
+            .client()
             .send_broadcast_message(vec![topic as u8], message)
             .await
         {
@@ -427,20 +452,23 @@ impl<TYPES: NodeType> TestableNetworkingImplementation<TYPES>
                     };
 
                     // Configure our client
+                    let keypair = KeyPair {
+                        public_key: WrappedSignatureKey(public_key.clone()),
+                        private_key,
+                    };
                     let client_config: ClientConfig<ClientDef<TYPES::SignatureKey>> =
                         ClientConfig {
-                            keypair: KeyPair {
-                                public_key: WrappedSignatureKey(public_key.clone()),
-                                private_key,
-                            },
-                            subscribed_topics: topics,
+                            keypair: keypair.clone(),
+                            subscribed_topics: topics.clone(),
                             endpoint: marshal_endpoint,
                             use_local_authority: true,
                         };
 
                     // Create our client
                     Arc::new(PushCdnNetwork {
-                        client: Client::new(client_config),
+                        client: Arc::new(RwLock::new(Client::new(client_config))),
+                        topics,
+                        keypair,
                         metrics: Arc::new(CdnMetricsValue::default()),
                         internal_queue: Arc::new(Mutex::new(VecDeque::new())),
                         public_key,
@@ -472,9 +500,14 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         self.is_paused.store(false, Ordering::Relaxed);
     }
 
+    /// The PushCDN network can only be paused in builds with the `hotshot-testing` feature.
+    fn can_pause(&self) -> bool {
+        cfg!(feature = "hotshot-testing")
+    }
+
     /// Wait for the client to initialize the connection
     async fn wait_for_ready(&self) {
-        let _ = self.client.ensure_initialized().await;
+        let _ = self.client().ensure_initialized().await;
     }
 
     /// TODO: shut down the networks. Unneeded for testing.
@@ -483,7 +516,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         'a: 'b,
         Self: 'b,
     {
-        boxed_sync(async move { self.client.close().await })
+        boxed_sync(async move { self.client().close().await })
     }
 
     /// Broadcast a message to all members of the quorum.
@@ -551,7 +584,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
 
         // Send the message
         if let Err(e) = self
-            .client
+            .client()
             .send_direct_message(&WrappedSignatureKey(recipient), message)
             .await
         {
@@ -576,7 +609,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         }
 
         // Receive a message from the network
-        let message = self.client.receive_message().await;
+        let message = self.client().receive_message().await;
 
         // If we're paused, receive but don't process messages
         #[cfg(feature = "hotshot-testing")]
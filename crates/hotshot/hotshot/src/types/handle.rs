@@ -26,7 +26,6 @@ use hotshot_types::{
         consensus_api::ConsensusApi,
         network::{BroadcastDelay, ConnectedNetwork, Topic},
         node_implementation::NodeType,
-        signature_key::SignatureKey,
     },
 };
 use tracing::instrument;
@@ -80,7 +79,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
             task_state,
             self.internal_event_stream.0.clone(),
             self.internal_event_stream.1.activate_cloned(),
-        );
+        )
+        .with_metrics(&self.hotshot.metrics);
 
         self.consensus_registry.run_task(task);
     }
@@ -147,10 +147,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         };
 
         // Finally, compute the signature for the payload.
-        let signature = TYPES::SignatureKey::sign(
-            self.private_key(),
-            signed_proposal_request.commit().as_ref(),
-        )?;
+        let signature = self
+            .signer()
+            .sign(signed_proposal_request.commit().as_ref())?;
 
         let mut receiver = self.internal_event_stream.1.activate_cloned();
         let sender = self.internal_event_stream.0.clone();
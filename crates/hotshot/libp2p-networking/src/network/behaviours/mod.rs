@@ -12,3 +12,6 @@ pub mod exponential_backoff;
 
 /// Wrapper around Kademlia
 pub mod dht;
+
+/// Scoring and banning of misbehaving peers
+pub mod peer_reputation;
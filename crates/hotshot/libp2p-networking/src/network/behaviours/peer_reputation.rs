@@ -0,0 +1,253 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use hotshot_types::traits::network::{PeerOffense, PeerReputation};
+use libp2p_identity::PeerId;
+
+/// Configuration of the reputation of peers
+#[derive(Clone, Debug)]
+pub struct PeerReputationConfig {
+    /// Penalty for delivering a message which could not be decoded
+    pub malformed_message_penalty: f64,
+    /// Penalty for delivering a message with an invalid signature
+    pub invalid_signature_penalty: f64,
+    /// Penalty for each message sent beyond `max_messages_per_second`
+    pub spam_penalty: f64,
+    /// Number of messages a peer may send per second
+    pub max_messages_per_second: u32,
+    /// Score below which a peer is deprioritized by gossipsub
+    pub deprioritize_threshold: f64,
+    /// Score below which a peer is banned
+    pub ban_threshold: f64,
+    /// How long a peer stays banned
+    pub ban_duration: Duration,
+    /// Time for a score to decay halfway back to 0
+    pub decay_half_life: Duration,
+}
+
+impl Default for PeerReputationConfig {
+    fn default() -> Self {
+        Self {
+            malformed_message_penalty: 10.0,
+            invalid_signature_penalty: 20.0,
+            spam_penalty: 1.0,
+            max_messages_per_second: 500,
+            deprioritize_threshold: -20.0,
+            ban_threshold: -100.0,
+            ban_duration: Duration::from_secs(3600),
+            decay_half_life: Duration::from_secs(600),
+        }
+    }
+}
+
+/// What to do about a peer whose score changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReputationAction {
+    /// Let gossipsub know about the new score of the peer
+    Rescore(f64),
+    /// Disconnect from the peer and ignore it until its ban expires
+    Ban,
+}
+
+/// The reputation of a single peer
+#[derive(Clone, Debug)]
+struct PeerState {
+    /// Score as of `updated`
+    score: f64,
+    /// When `score` was last decayed
+    updated: Instant,
+    /// Start of the current one second window of messages
+    window_start: Instant,
+    /// Number of messages received in the current window
+    window_messages: u32,
+    /// When the ban on the peer expires, if it is banned
+    banned_until: Option<Instant>,
+}
+
+impl PeerState {
+    /// A peer with a clean record
+    fn new(now: Instant) -> Self {
+        Self {
+            score: 0.0,
+            updated: now,
+            window_start: now,
+            window_messages: 0,
+            banned_until: None,
+        }
+    }
+
+    /// The score of the peer at `now`, decaying towards 0
+    fn score(&self, now: Instant, half_life: Duration) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.score * 0.5f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64())
+    }
+}
+
+/// Scores the peers we receive messages from, based on the offenses they commit
+#[derive(Debug, Default)]
+pub struct PeerReputations {
+    /// Configuration
+    config: PeerReputationConfig,
+    /// State of each peer we have received messages from
+    peers: HashMap<PeerId, PeerState>,
+}
+
+impl PeerReputations {
+    /// Create a new tracker with the given configuration
+    #[must_use]
+    pub fn new(config: PeerReputationConfig) -> Self {
+        Self {
+            config,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Record that `peer` sent us a message, penalizing it if it exceeds its rate
+    pub fn record_message(&mut self, peer: PeerId, now: Instant) -> Option<ReputationAction> {
+        let state = self
+            .peers
+            .entry(peer)
+            .or_insert_with(|| PeerState::new(now));
+        if now.saturating_duration_since(state.window_start) >= Duration::from_secs(1) {
+            state.window_start = now;
+            state.window_messages = 0;
+        }
+        state.window_messages = state.window_messages.saturating_add(1);
+
+        (state.window_messages > self.config.max_messages_per_second)
+            .then(|| self.penalize(peer, PeerOffense::Spam, now))
+            .flatten()
+    }
+
+    /// Lower the score of `peer` for committing `offense`.
+    ///
+    /// Returns the action to take, if the score of the peer changed and it is not already banned.
+    pub fn penalize(
+        &mut self,
+        peer: PeerId,
+        offense: PeerOffense,
+        now: Instant,
+    ) -> Option<ReputationAction> {
+        let penalty = match offense {
+            PeerOffense::MalformedMessage => self.config.malformed_message_penalty,
+            PeerOffense::InvalidSignature => self.config.invalid_signature_penalty,
+            PeerOffense::Spam => self.config.spam_penalty,
+        };
+
+        let state = self
+            .peers
+            .entry(peer)
+            .or_insert_with(|| PeerState::new(now));
+        if state.banned_until.is_some_and(|until| now < until) {
+            return None;
+        }
+        state.score = state.score(now, self.config.decay_half_life) - penalty;
+        state.updated = now;
+
+        if state.score < self.config.ban_threshold {
+            tracing::warn!(%peer, score = state.score, ?offense, "Banning peer");
+            state.banned_until = Some(now + self.config.ban_duration);
+            Some(ReputationAction::Ban)
+        } else {
+            Some(ReputationAction::Rescore(state.score))
+        }
+    }
+
+    /// Whether `peer` is currently banned
+    #[must_use]
+    pub fn is_banned(&self, peer: &PeerId, now: Instant) -> bool {
+        self.peers
+            .get(peer)
+            .and_then(|state| state.banned_until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// Lift the ban on `peer` if it has expired, giving it a clean record.
+    ///
+    /// Returns whether a ban was lifted.
+    pub fn lift_expired_ban(&mut self, peer: &PeerId, now: Instant) -> bool {
+        match self.peers.get(peer).and_then(|state| state.banned_until) {
+            Some(until) if now >= until => {
+                self.peers.insert(*peer, PeerState::new(now));
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// The reputation of every peer we are tracking
+    #[must_use]
+    pub fn reputations(&self, now: Instant) -> Vec<PeerReputation> {
+        self.peers
+            .iter()
+            .map(|(peer, state)| {
+                let score = state.score(now, self.config.decay_half_life);
+                PeerReputation {
+                    peer: peer.to_string(),
+                    score,
+                    deprioritized: score < self.config.deprioritize_threshold,
+                    banned_for: state
+                        .banned_until
+                        .filter(|until| now < *until)
+                        .map(|until| (until - now).as_secs()),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_peer_reputation() {
+        let mut reputations = PeerReputations::new(PeerReputationConfig {
+            max_messages_per_second: 2,
+            ..Default::default()
+        });
+        let peer = PeerId::random();
+        let now = Instant::now();
+
+        // Messages within the rate limit are fine.
+        assert_eq!(reputations.record_message(peer, now), None);
+        assert_eq!(reputations.record_message(peer, now), None);
+        assert_eq!(
+            reputations.record_message(peer, now),
+            Some(ReputationAction::Rescore(-1.0))
+        );
+
+        // Penalties decay over time.
+        let later = now + Duration::from_secs(600);
+        assert_eq!(
+            reputations.penalize(peer, PeerOffense::MalformedMessage, later),
+            Some(ReputationAction::Rescore(-10.5))
+        );
+
+        // Enough offenses get the peer banned, until the ban expires.
+        let mut action = None;
+        for _ in 0..5 {
+            action = reputations.penalize(peer, PeerOffense::InvalidSignature, later);
+        }
+        assert_eq!(action, Some(ReputationAction::Ban));
+        assert!(reputations.is_banned(&peer, later));
+        assert_eq!(
+            reputations.penalize(peer, PeerOffense::InvalidSignature, later),
+            None
+        );
+        assert!(!reputations.lift_expired_ban(&peer, later));
+
+        let expired = later + Duration::from_secs(3600);
+        assert!(!reputations.is_banned(&peer, expired));
+        assert!(reputations.lift_expired_ban(&peer, expired));
+        assert_eq!(reputations.reputations(expired)[0].score, 0.0);
+    }
+}
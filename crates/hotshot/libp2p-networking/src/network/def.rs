@@ -114,6 +114,21 @@ impl<K: SignatureKey + 'static, D: DhtPersistentStorage> NetworkDef<K, D> {
             error!("Failed to unsubscribe from topic {:?}. Error: {:?}", t, e);
         }
     }
+
+    /// Set the application-specific score of a peer, used by gossipsub to select its mesh
+    pub fn set_gossip_score(&mut self, peer_id: &PeerId, score: f64) {
+        self.gossipsub.set_application_score(peer_id, score);
+    }
+
+    /// Ignore all gossip from a peer
+    pub fn blacklist_peer(&mut self, peer_id: &PeerId) {
+        self.gossipsub.blacklist_peer(peer_id);
+    }
+
+    /// Stop ignoring gossip from a peer
+    pub fn remove_blacklisted_peer(&mut self, peer_id: &PeerId) {
+        self.gossipsub.remove_blacklisted_peer(peer_id);
+    }
 }
 
 /// Request/response functions
@@ -20,7 +20,10 @@ use std::{collections::HashSet, fmt::Debug, sync::Arc};
 
 use async_lock::RwLock;
 use futures::channel::oneshot::Sender;
-use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
+use hotshot_types::traits::{
+    network::{NetworkError, PeerOffense, PeerReputation},
+    node_implementation::NodeType,
+};
 use libp2p::{
     build_multiaddr,
     core::{muxing::StreamMuxerBox, transport::Boxed},
@@ -104,14 +107,18 @@ pub enum ClientRequest {
     GetRoutingTable(Sender<()>),
     /// Get address of peer
     LookupPeer(PeerId, Sender<()>),
+    /// Penalize a peer for an offense
+    ReportPeer(PeerId, PeerOffense),
+    /// Request the reputation of the peers we have received messages from
+    GetPeerReputations(Sender<Vec<PeerReputation>>),
 }
 
 /// events generated by the swarm that we wish
 /// to relay to the client
 #[derive(Debug)]
 pub enum NetworkEvent {
-    /// Recv-ed a broadcast
-    GossipMsg(Vec<u8>),
+    /// Recv-ed a broadcast from the given peer
+    GossipMsg(Vec<u8>, PeerId),
     /// Recv-ed a direct message from a node
     DirectRequest(Vec<u8>, PeerId, ResponseChannel<Vec<u8>>),
     /// Recv-ed a direct response from a node (that hopefully was initiated by this node)
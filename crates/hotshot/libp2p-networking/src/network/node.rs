@@ -15,7 +15,7 @@ use std::{
     collections::{HashMap, HashSet},
     iter,
     num::{NonZeroU32, NonZeroUsize},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use futures::{channel::mpsc, SinkExt, StreamExt};
@@ -27,7 +27,8 @@ use libp2p::{
     core::transport::ListenerId,
     gossipsub::{
         Behaviour as Gossipsub, ConfigBuilder as GossipsubConfigBuilder, Event as GossipEvent,
-        Message as GossipsubMessage, MessageAuthenticity, MessageId, Topic, ValidationMode,
+        Message as GossipsubMessage, MessageAuthenticity, MessageId, PeerScoreParams,
+        PeerScoreThresholds, Topic, ValidationMode,
     },
     identify::{
         Behaviour as IdentifyBehaviour, Config as IdentifyConfig, Event as IdentifyEvent,
@@ -72,6 +73,7 @@ use crate::network::behaviours::{
     dht::{DHTBehaviour, DHTProgress, KadPutQuery, NUM_REPLICATED_TO_TRUST},
     direct_message::{DMBehaviour, DMRequest},
     exponential_backoff::ExponentialBackoff,
+    peer_reputation::{PeerReputations, ReputationAction},
 };
 
 /// Maximum size of a message
@@ -98,6 +100,8 @@ pub struct NetworkNode<T: NodeType, D: DhtPersistentStorage> {
     dht_handler: DHTBehaviour<T::SignatureKey, D>,
     /// Channel to resend requests, set to Some when we call `spawn_listeners`
     resend_tx: Option<UnboundedSender<ClientRequest>>,
+    /// Reputation of the peers we receive messages from
+    reputations: PeerReputations,
 }
 
 impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
@@ -226,7 +230,7 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 })?;
 
             // - Build a gossipsub network behavior
-            let gossipsub: Gossipsub = Gossipsub::new(
+            let mut gossipsub: Gossipsub = Gossipsub::new(
                 MessageAuthenticity::Signed(keypair.clone()),
                 gossipsub_config,
             )
@@ -234,6 +238,29 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 NetworkError::ConfigError(format!("error building gossipsub behaviour: {err:?}"))
             })?;
 
+            // Let gossipsub deprioritize peers with a bad reputation. The score of a peer is the
+            // one we assign it, without penalties for sharing an IP with other peers.
+            let reputation_config = &config.peer_reputation_config;
+            gossipsub
+                .with_peer_score(
+                    PeerScoreParams {
+                        app_specific_weight: 1.0,
+                        ip_colocation_factor_weight: 0.0,
+                        ..Default::default()
+                    },
+                    PeerScoreThresholds {
+                        gossip_threshold: reputation_config.deprioritize_threshold,
+                        publish_threshold: reputation_config.deprioritize_threshold,
+                        graylist_threshold: reputation_config.ban_threshold,
+                        ..Default::default()
+                    },
+                )
+                .map_err(|err| {
+                    NetworkError::ConfigError(format!(
+                        "error enabling gossipsub peer scores: {err}"
+                    ))
+                })?;
+
             //   Build a identify network behavior needed for own
             //   node connection information
             //   E.g. this will answer the question: how are other nodes
@@ -337,6 +364,7 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                     .unwrap_or(NonZeroUsize::new(4).unwrap()),
             ),
             resend_tx: None,
+            reputations: PeerReputations::new(config.peer_reputation_config),
         })
     }
 
@@ -493,6 +521,18 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                             warn!("Could not disconnect from {:?}", pid);
                         }
                     },
+                    ClientRequest::ReportPeer(pid, offense) => {
+                        debug!("Peer {:?} reported for {:?}", pid, offense);
+                        let action = self.reputations.penalize(pid, offense, Instant::now());
+                        self.apply_reputation_action(pid, action);
+                    },
+                    ClientRequest::GetPeerReputations(s) => {
+                        if s.send(self.reputations.reputations(Instant::now()))
+                            .is_err()
+                        {
+                            error!("error sending peer reputations to client");
+                        }
+                    },
                 }
             },
             None => {
@@ -502,6 +542,33 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
         Ok(false)
     }
 
+    /// Apply the outcome of a change in the reputation of `peer`
+    fn apply_reputation_action(&mut self, peer: PeerId, action: Option<ReputationAction>) {
+        match action {
+            Some(ReputationAction::Rescore(score)) => {
+                self.swarm.behaviour_mut().set_gossip_score(&peer, score);
+            },
+            Some(ReputationAction::Ban) => {
+                self.swarm.behaviour_mut().blacklist_peer(&peer);
+                if self.swarm.disconnect_peer_id(peer).is_err() {
+                    debug!("Banned peer {:?} was not connected", peer);
+                }
+            },
+            None => {},
+        }
+    }
+
+    /// Account for a message received from `peer`, returning whether it should be delivered
+    fn accept_message_from(&mut self, peer: PeerId) -> bool {
+        let now = Instant::now();
+        if self.reputations.is_banned(&peer, now) {
+            return false;
+        }
+        let action = self.reputations.record_message(peer, now);
+        self.apply_reputation_action(peer, action);
+        !self.reputations.is_banned(&peer, now)
+    }
+
     /// event handler for events emitted from the swarm
     #[allow(clippy::type_complexity)]
     #[instrument(skip(self))]
@@ -535,6 +602,18 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                     );
                 }
 
+                // Refuse connections from banned peers, and give peers whose ban has expired a
+                // fresh start
+                let now = Instant::now();
+                if self.reputations.is_banned(&peer_id, now) {
+                    debug!("Disconnecting from banned peer {:?}", peer_id);
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                } else if self.reputations.lift_expired_ban(&peer_id, now) {
+                    let behaviour = self.swarm.behaviour_mut();
+                    behaviour.remove_blacklisted_peer(&peer_id);
+                    behaviour.set_gossip_score(&peer_id, 0.0);
+                }
+
                 // Send the number of connected peers to the client
                 send_to_client
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
@@ -622,10 +701,15 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                     },
                     NetworkEventInternal::GossipEvent(e) => match *e {
                         GossipEvent::Message {
-                            propagation_source: _peer_id,
+                            propagation_source,
                             message_id: _id,
                             message,
-                        } => Some(NetworkEvent::GossipMsg(message.data)),
+                        } => {
+                            // Messages are signed, so hold their author responsible for them
+                            let source = message.source.unwrap_or(propagation_source);
+                            self.accept_message_from(source)
+                                .then(|| NetworkEvent::GossipMsg(message.data, source))
+                        },
                         GossipEvent::Subscribed { peer_id, topic } => {
                             debug!("Peer {:?} subscribed to topic {:?}", peer_id, topic);
                             None
@@ -667,6 +751,16 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                     },
                 };
 
+                // Drop direct messages from banned peers
+                let maybe_event = match maybe_event {
+                    Some(NetworkEvent::DirectRequest(_, peer, _))
+                        if !self.accept_message_from(peer) =>
+                    {
+                        None
+                    },
+                    event => event,
+                };
+
                 if let Some(event) = maybe_event {
                     // forward messages directly to Client
                     send_to_client
@@ -12,6 +12,7 @@ use libp2p::{identity::Keypair, Multiaddr};
 use libp2p_identity::PeerId;
 
 use super::MAX_GOSSIP_MSG_SIZE;
+use crate::network::behaviours::peer_reputation::PeerReputationConfig;
 
 /// The default Kademlia replication factor
 pub const DEFAULT_REPLICATION_FACTOR: Option<NonZeroUsize> = NonZeroUsize::new(10);
@@ -68,6 +69,10 @@ pub struct NetworkNodeConfig<T: NodeType> {
     #[builder(default)]
     /// The timeout for DHT lookups.
     pub dht_timeout: Option<Duration>,
+
+    #[builder(default)]
+    /// Configuration for scoring and banning misbehaving peers
+    pub peer_reputation_config: PeerReputationConfig,
 }
 
 impl<T: NodeType> Clone for NetworkNodeConfig<T> {
@@ -85,6 +90,7 @@ impl<T: NodeType> Clone for NetworkNodeConfig<T> {
             dht_file_path: self.dht_file_path.clone(),
             auth_message: self.auth_message.clone(),
             dht_timeout: self.dht_timeout,
+            peer_reputation_config: self.peer_reputation_config.clone(),
         }
     }
 }
@@ -6,7 +6,10 @@
 
 use std::{collections::HashSet, fmt::Debug, time::Duration};
 
-use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
+use hotshot_types::traits::{
+    network::{NetworkError, PeerOffense, PeerReputation},
+    node_implementation::NodeType,
+};
 use libp2p::{request_response::ResponseChannel, Multiaddr};
 use libp2p_identity::PeerId;
 use tokio::{
@@ -383,6 +386,14 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         self.send_request(req)
     }
 
+    /// Penalize `pid` for `offense`, deprioritizing or banning it if its reputation drops too low
+    /// # Errors
+    /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
+    pub fn report_peer(&self, pid: PeerId, offense: PeerOffense) -> Result<(), NetworkError> {
+        let req = ClientRequest::ReportPeer(pid, offense);
+        self.send_request(req)
+    }
+
     /// Gossip a message to peers
     /// # Errors
     /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
@@ -450,6 +461,18 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         Ok(r.await.unwrap())
     }
 
+    /// The reputation of the peers we have received messages from
+    /// # Errors
+    /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
+    /// - Will return [`NetworkError::ChannelReceiveError`] when the `NetworkNode` drops the request
+    pub async fn peer_reputations(&self) -> Result<Vec<PeerReputation>, NetworkError> {
+        let (s, r) = futures::channel::oneshot::channel();
+        let req = ClientRequest::GetPeerReputations(s);
+        self.send_request(req)?;
+        r.await
+            .map_err(|err| NetworkError::ChannelReceiveError(err.to_string()))
+    }
+
     /// Get a reference to the network node handle's id.
     #[must_use]
     pub fn id(&self) -> usize {
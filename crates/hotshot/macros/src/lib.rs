@@ -404,6 +404,10 @@ pub fn cross_tests(input: TokenStream) -> TokenStream {
 ///   - repeat until no more output has been generated by any task, and finally
 ///   - proceed to the next entry of inputs.
 ///
+/// Outputs are validated against the `Expectations` of the stage, in order unless the stage is
+/// `unordered`. A stage fails if it has not completed within its timeout, the shortest any script
+/// sets for it.
+///
 /// # Panics
 ///
 /// The macro panics if the input stream cannot be parsed.
@@ -426,9 +430,9 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
 
     let scripts = &inputs[1..];
 
-    let output_index_names: Vec<_> = scripts
+    let progress_names: Vec<_> = scripts
         .iter()
-        .map(|i| format_ident!("{}_output_index", quote::quote!(#i).to_string()))
+        .map(|i| format_ident!("{}_progress", quote::quote!(#i).to_string()))
         .collect();
 
     let task_expectations: Vec<_> = scripts
@@ -444,11 +448,11 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
     let expanded = quote! { {
 
     use hotshot_testing::script::{
-        panic_extra_output_in_script, panic_missing_output_in_script, validate_output_or_panic_in_script,
-        validate_task_state_or_panic_in_script,
+        check_stage_outputs_or_panic_in_script, panic_stage_timeout_in_script,
+        validate_stage_output_or_panic_in_script, validate_task_state_or_panic_in_script,
+        StageProgress,
     };
 
-    use hotshot_testing::{predicates::{Predicate, PredicateResult}};
     use async_broadcast::broadcast;
     use hotshot_task_impls::events::HotShotEvent;
     use tokio::time::timeout;
@@ -469,7 +473,14 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
 
     for (stage_number, input_group) in #test_inputs.into_iter().enumerate() {
 
-    #(let mut #output_index_names = 0;)*
+    let stage_timeout = [#(#task_expectations[stage_number].timeout),*]
+        .into_iter()
+        .min()
+        .expect("a test runs at least one script");
+
+    timeout(stage_timeout, async {
+
+    #(let mut #progress_names = StageProgress::default();)*
 
         for input in &input_group {
             #(
@@ -488,30 +499,17 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
 
                 while from_test.try_recv().is_ok() {}
 
-                let mut result = PredicateResult::Incomplete;
-
                 while let Ok(Ok(received_output)) = timeout(#scripts.timeout, from_task.recv_direct()).await {
                     tracing::debug!("Test received: {:?}", received_output);
 
-                    let output_asserts = &mut #task_expectations[stage_number].output_asserts;
-
-                    if #output_index_names >= output_asserts.len() {
-                            panic_extra_output_in_script(stage_number, #script_names.to_string(), &received_output);
-                    };
-
-                    let assert = &mut output_asserts[#output_index_names];
-
-                    result = validate_output_or_panic_in_script(
+                    validate_stage_output_or_panic_in_script(
                         stage_number,
                         #script_names.to_string(),
+                        &#task_expectations[stage_number],
+                        &mut #progress_names,
                         &received_output,
-                        &**assert
                     )
                     .await;
-
-                    if result == PredicateResult::Pass {
-                        #output_index_names += 1;
-                    }
                 }
             )*
         }
@@ -532,39 +530,28 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
 
                 while from_test.try_recv().is_ok() {}
 
-                let mut result = PredicateResult::Incomplete;
                 while let Ok(Ok(received_output)) = timeout(#scripts.timeout, from_task.recv_direct()).await {
                     tracing::debug!("Test received: {:?}", received_output);
 
-                    let output_asserts = &mut #task_expectations[stage_number].output_asserts;
-
-                    if #output_index_names >= output_asserts.len() {
-                            panic_extra_output_in_script(stage_number, #script_names.to_string(), &received_output);
-                    };
-
-                    let mut assert = &mut output_asserts[#output_index_names];
-
-                    result = validate_output_or_panic_in_script(
+                    validate_stage_output_or_panic_in_script(
                         stage_number,
                         #script_names.to_string(),
+                        &#task_expectations[stage_number],
+                        &mut #progress_names,
                         &received_output,
-                        &**assert
                     )
                     .await;
-
-                    if result == PredicateResult::Pass {
-                        #output_index_names += 1;
-                    }
                 }
             )*
         }
 
         #(
-            let output_asserts = &#task_expectations[stage_number].output_asserts;
-
-            if #output_index_names < output_asserts.len() {
-                panic_missing_output_in_script(stage_number, #script_names.to_string(), &output_asserts[#output_index_names]);
-            }
+            check_stage_outputs_or_panic_in_script(
+                stage_number,
+                #script_names.to_string(),
+                &#task_expectations[stage_number],
+                &#progress_names,
+            );
 
             let task_state_asserts = &mut #task_expectations[stage_number].task_state_asserts;
 
@@ -572,6 +559,9 @@ pub fn test_scripts(input: proc_macro::TokenStream) -> TokenStream {
                 validate_task_state_or_panic_in_script(stage_number, #script_names.to_string(), &#scripts.state, &**assert).await;
             }
         )*
+    })
+    .await
+    .unwrap_or_else(|_| panic_stage_timeout_in_script(stage_number, &[#(#script_names),*], stage_timeout));
     } }
 
     }
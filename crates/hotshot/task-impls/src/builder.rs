@@ -47,7 +47,8 @@ impl From<BuilderApiError> for BuilderClientError {
             BuilderApiError::TxnSubmit(source) | BuilderApiError::BuilderAddress(source) => {
                 Self::Api(source.to_string())
             },
-            BuilderApiError::Custom { message, .. } => Self::Api(message),
+            BuilderApiError::Custom { message, .. }
+            | BuilderApiError::Unauthorized { message, .. } => Self::Api(message),
             BuilderApiError::BlockAvailable { source, .. }
             | BuilderApiError::BlockClaim { source, .. } => match source {
                 BuildError::NotFound => Self::BlockNotFound,
@@ -63,6 +64,8 @@ impl From<BuilderApiError> for BuilderClientError {
 pub struct BuilderClient<TYPES: NodeType, Ver: StaticVersionType> {
     /// Underlying surf_disco::Client for the legacy builder api
     client: Client<BuilderApiError, Ver>,
+    /// Base URL of the builder
+    url: Url,
     /// Marker for [`NodeType`] used here
     _marker: std::marker::PhantomData<TYPES>,
 }
@@ -80,10 +83,16 @@ impl<TYPES: NodeType, Ver: StaticVersionType> BuilderClient<TYPES, Ver> {
             client: Client::builder(url.clone())
                 .set_timeout(Some(Duration::from_secs(2)))
                 .build(),
+            url,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Base URL of the builder
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
     /// Wait for server to become available
     /// Returns `false` if server doesn't respond
     /// with OK healthcheck before `timeout`
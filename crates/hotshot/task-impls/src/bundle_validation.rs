@@ -0,0 +1,209 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Validation of blocks and bundles claimed from builders.
+//!
+//! Before the leader commits to proposing what a builder gave it, the claimed block or bundle is
+//! checked in a blocking task of its own. A malformed or oversized payload can then neither stall
+//! nor crash the transaction task, and a bad block is skipped in favour of the next builder's
+//! instead of surfacing as a failed proposal. Rejections are counted per builder and reason in
+//! [`ConsensusMetricsValue::rejected_builder_bundles`].
+
+use std::fmt;
+
+use hotshot_builder_api::v0_1::block_info::{
+    AvailableBlockData, AvailableBlockHeaderInputV2, AvailableBlockHeaderInputV2Legacy,
+    AvailableBlockInfo,
+};
+use hotshot_types::{
+    bundle::Bundle,
+    consensus::ConsensusMetricsValue,
+    traits::{
+        block_contents::{BlockLimits, BuilderFee, EncodeBytes},
+        metrics::MetricsFamily,
+        node_implementation::NodeType,
+        signature_key::BuilderSignatureKey,
+        BlockPayload,
+    },
+};
+use tokio::task::spawn_blocking;
+
+use crate::transactions::BuilderResponse;
+
+/// Why a block or bundle claimed from a builder was rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BundleRejection {
+    /// The builder's signature over the block or bundle is invalid
+    InvalidSignature,
+    /// The payload does not match the commitment the builder offered
+    CommitmentMismatch,
+    /// The fee signature is invalid
+    InvalidFeeSignature,
+    /// The payload is larger than the size the builder offered
+    SizeExceeded,
+    /// The block metadata, such as the namespace table, is malformed
+    MalformedMetadata,
+    /// The block exceeds the limits on block contents in effect
+    LimitsExceeded,
+    /// The validation task did not complete
+    ValidationFailed,
+}
+
+impl BundleRejection {
+    /// Label for this reason in metrics
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::InvalidSignature => "invalid_signature",
+            Self::CommitmentMismatch => "commitment_mismatch",
+            Self::InvalidFeeSignature => "invalid_fee_signature",
+            Self::SizeExceeded => "size_exceeded",
+            Self::MalformedMetadata => "malformed_metadata",
+            Self::LimitsExceeded => "limits_exceeded",
+            Self::ValidationFailed => "validation_failed",
+        }
+    }
+}
+
+impl fmt::Display for BundleRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A block claimed from a builder, with whichever versions of the header input it returned
+pub struct ClaimedBlock<TYPES: NodeType> {
+    /// The block the builder offered
+    pub info: AvailableBlockInfo<TYPES>,
+    /// The claimed block
+    pub data: AvailableBlockData<TYPES>,
+    /// The claimed header input, if the builder returned one
+    pub header_input: Option<AvailableBlockHeaderInputV2<TYPES>>,
+    /// The claimed legacy header input, if the builder returned one
+    pub legacy_header_input: Option<AvailableBlockHeaderInputV2Legacy<TYPES>>,
+    /// Limits on the contents of the block we are about to propose
+    pub limits: BlockLimits,
+}
+
+impl<TYPES: NodeType> ClaimedBlock<TYPES> {
+    /// Check the claimed block against the block the builder offered.
+    ///
+    /// # Errors
+    /// If the block fails any of the checks.
+    pub fn validate(self) -> Result<BuilderResponse<TYPES>, BundleRejection> {
+        let Self {
+            info,
+            data,
+            header_input,
+            legacy_header_input,
+            limits,
+        } = self;
+
+        // verify the signature over the message
+        if !data.validate_signature() {
+            return Err(BundleRejection::InvalidSignature);
+        }
+
+        // The offer was signed over the builder commitment, so the claimed block must be the one
+        // which was offered.
+        if data.block_payload.builder_commitment(&data.metadata) != info.block_hash {
+            return Err(BundleRejection::CommitmentMismatch);
+        }
+
+        // The fee was offered for a block of at most this size.
+        let block_size = u64::try_from(data.block_payload.encode().len()).unwrap_or(u64::MAX);
+        if block_size > info.block_size {
+            return Err(BundleRejection::SizeExceeded);
+        }
+
+        if !data.block_payload.is_well_formed(&data.metadata) {
+            return Err(BundleRejection::MalformedMetadata);
+        }
+
+        if !data.block_payload.within_limits(&data.metadata, &limits) {
+            return Err(BundleRejection::LimitsExceeded);
+        }
+
+        // Prefer the new header input, falling back to the legacy one.
+        let header_input = header_input
+            .filter(|header_input| {
+                header_input.validate_signature(info.offered_fee, &data.metadata)
+            })
+            .or_else(|| {
+                legacy_header_input
+                    .filter(|legacy_header_input| {
+                        legacy_header_input.validate_signature(info.offered_fee, &data.metadata)
+                    })
+                    .map(|legacy_header_input| AvailableBlockHeaderInputV2 {
+                        fee_signature: legacy_header_input.fee_signature,
+                        sender: legacy_header_input.sender,
+                    })
+            })
+            .ok_or(BundleRejection::InvalidFeeSignature)?;
+
+        // verify the message signature and the fee_signature
+        if !header_input.validate_signature(info.offered_fee, &data.metadata) {
+            return Err(BundleRejection::InvalidFeeSignature);
+        }
+
+        Ok(BuilderResponse {
+            fee: BuilderFee {
+                fee_amount: info.offered_fee,
+                fee_account: header_input.sender,
+                fee_signature: header_input.fee_signature,
+            },
+            block_payload: data.block_payload,
+            metadata: data.metadata,
+        })
+    }
+}
+
+/// Check a bundle returned by a builder for the auction in `view`.
+///
+/// # Errors
+/// If the bundle or its sequencing fee is not signed by the account paying the fee.
+pub fn validate_bundle<TYPES: NodeType>(
+    bundle: Bundle<TYPES>,
+    view: u64,
+) -> Result<Bundle<TYPES>, BundleRejection> {
+    let fee = &bundle.sequencing_fee;
+    if !fee
+        .fee_account
+        .validate_sequencing_fee_signature_marketplace(&fee.fee_signature, fee.fee_amount, view)
+    {
+        return Err(BundleRejection::InvalidFeeSignature);
+    }
+    if !fee.fee_account.validate_bundle_signature(bundle.clone()) {
+        return Err(BundleRejection::InvalidSignature);
+    }
+    Ok(bundle)
+}
+
+/// Run `validate` in a blocking task of its own, treating a panic as a rejection.
+///
+/// # Errors
+/// If `validate` fails or does not complete.
+pub async fn sandboxed<T: Send + 'static>(
+    validate: impl FnOnce() -> Result<T, BundleRejection> + Send + 'static,
+) -> Result<T, BundleRejection> {
+    spawn_blocking(validate).await.unwrap_or_else(|err| {
+        tracing::error!(%err, "Builder bundle validation task failed");
+        Err(BundleRejection::ValidationFailed)
+    })
+}
+
+/// Count a block or bundle from `builder` rejected for `reason`
+pub fn record_rejection<TYPES: NodeType>(
+    metrics: &ConsensusMetricsValue,
+    builder: &TYPES::BuilderSignatureKey,
+    reason: BundleRejection,
+) {
+    tracing::warn!(%builder, %reason, "Rejected block from builder");
+    metrics
+        .rejected_builder_bundles
+        .create(vec![builder.to_string(), reason.as_str().to_string()])
+        .add(1);
+}
@@ -10,16 +10,17 @@ use async_broadcast::{Receiver, Sender};
 use chrono::Utc;
 use hotshot_types::{
     event::{Event, EventType},
+    feature_gates::Feature,
     simple_certificate::EpochRootQuorumCertificate,
     simple_vote::{EpochRootQuorumVote, HasEpoch, QuorumVote2, TimeoutData2, TimeoutVote2},
     traits::node_implementation::{ConsensusTime, NodeImplementation, NodeType},
     utils::{is_epoch_root, is_epoch_transition, is_last_block, EpochTransitionIndicator},
+    view_timeout::AdaptiveViewTimeout,
     vote::{HasViewNumber, Vote},
 };
 use hotshot_utils::anytrace::*;
 use tokio::{spawn, time::sleep};
 use tracing::instrument;
-use vbs::version::StaticVersionType;
 
 use super::ConsensusTaskState;
 use crate::{
@@ -69,12 +70,14 @@ pub(crate) async fn handle_quorum_vote_recv<
     } else {
         EpochTransitionIndicator::NotInTransition
     };
+    let consensus_metrics = Arc::clone(&task_state.consensus.read().await.metrics);
     handle_vote(
         &mut task_state.vote_collectors,
         vote,
         task_state.public_key.clone(),
         &epoch_membership,
         task_state.id,
+        &consensus_metrics,
         &event,
         sender,
         &task_state.upgrade_lock,
@@ -105,6 +108,7 @@ pub(crate) async fn handle_quorum_vote_recv<
                 // I'm not sure this is really necessary, but I've opted not to modify the logic.
                 &epoch_membership.next_epoch().await?.clone(),
                 task_state.id,
+                &consensus_metrics,
                 &event,
                 sender,
                 &task_state.upgrade_lock,
@@ -152,12 +156,14 @@ pub(crate) async fn handle_epoch_root_quorum_vote_recv<
         )
     );
 
+    let consensus_metrics = Arc::clone(&task_state.consensus.read().await.metrics);
     handle_epoch_root_vote(
         &mut task_state.epoch_root_vote_collectors,
         vote,
         task_state.public_key.clone(),
         &epoch_membership,
         task_state.id,
+        &consensus_metrics,
         &event,
         sender,
         &task_state.upgrade_lock,
@@ -192,6 +198,7 @@ pub(crate) async fn handle_timeout_vote_recv<
         )
     );
 
+    let consensus_metrics = Arc::clone(&task_state.consensus.read().await.metrics);
     handle_vote(
         &mut task_state.timeout_vote_collectors,
         vote,
@@ -201,6 +208,7 @@ pub(crate) async fn handle_timeout_vote_recv<
             .membership_for_epoch(vote.data.epoch)
             .await?,
         task_state.id,
+        &consensus_metrics,
         &event,
         sender,
         &task_state.upgrade_lock,
@@ -225,7 +233,7 @@ pub async fn send_high_qc<TYPES: NodeType, V: Versions, I: NodeImplementation<TY
 ) -> Result<()> {
     let version = task_state.upgrade_lock.version(new_view_number).await?;
     ensure!(
-        version >= V::Epochs::VERSION,
+        Feature::Epochs.enabled_in::<V>(version),
         debug!("HotStuff 2 upgrade not yet in effect")
     );
 
@@ -424,6 +432,18 @@ pub(crate) async fn handle_view_change<
         }
     }
 
+    // A view which ended before timing out succeeded, so adapt the timeout to how long it took.
+    if new_view_number == old_view_number + 1 {
+        let duration = task_state.view_start_time.elapsed();
+        if duration < Duration::from_millis(task_state.timeout) {
+            let adjusted = task_state
+                .adaptive_timeout
+                .as_mut()
+                .and_then(|timeout| timeout.observe_view(duration));
+            adjust_view_timeout(adjusted, "a successful view", task_state).await;
+        }
+    }
+
     // Spawn a timeout task if we did actually update view
     let timeout = task_state.timeout;
     let new_timeout_task = spawn({
@@ -458,13 +478,23 @@ pub(crate) async fn handle_view_change<
         .metrics
         .current_view
         .set(usize::try_from(task_state.cur_view.u64()).unwrap());
+    consensus_reader
+        .metrics
+        .view_timeout
+        .set(usize::try_from(timeout).unwrap_or(usize::MAX));
     let cur_view_time = Utc::now().timestamp();
     if old_view_leader_key == task_state.public_key {
         #[allow(clippy::cast_precision_loss)]
         consensus_reader
             .metrics
             .view_duration_as_leader
-            .add_point((cur_view_time - task_state.cur_view_time) as f64);
+            .add_point_with_exemplar(
+                (cur_view_time - task_state.cur_view_time) as f64,
+                vec![
+                    ("view".into(), old_view_number.u64().to_string()),
+                    ("leader".into(), old_view_leader_key.to_string()),
+                ],
+            );
     }
     task_state.cur_view_time = cur_view_time;
 
@@ -508,6 +538,12 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
         "Timeout event is for an old view"
     );
 
+    let adjusted = task_state
+        .adaptive_timeout
+        .as_mut()
+        .and_then(AdaptiveViewTimeout::observe_timeout);
+    adjust_view_timeout(adjusted, "a timeout", task_state).await;
+
     ensure!(
         task_state
             .membership_coordinator
@@ -526,7 +562,7 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
         },
         view_number,
         &task_state.public_key,
-        &task_state.private_key,
+        &task_state.signer,
         &task_state.upgrade_lock,
     )
     .await
@@ -570,3 +606,26 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
 
     Ok(())
 }
+
+/// Use the view timeout `adjusted` by the adaptive view timeout after `reason`, if it changed.
+async fn adjust_view_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    adjusted: Option<u64>,
+    reason: &str,
+    task_state: &mut ConsensusTaskState<TYPES, I, V>,
+) {
+    let Some(timeout) = adjusted else {
+        return;
+    };
+    tracing::info!(
+        "Adjusted view timeout from {}ms to {timeout}ms after {reason}",
+        task_state.timeout
+    );
+    task_state.timeout = timeout;
+    task_state
+        .consensus
+        .read()
+        .await
+        .metrics
+        .view_timeout_adjustments
+        .add(1);
+}
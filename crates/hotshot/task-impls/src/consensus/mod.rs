@@ -14,16 +14,18 @@ use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
     epoch_membership::EpochMembershipCoordinator,
-    event::Event,
+    error::HotShotError,
+    event::{Event, EventType},
     message::UpgradeLock,
+    signer::Signer,
     simple_certificate::{NextEpochQuorumCertificate2, QuorumCertificate2, TimeoutCertificate2},
     simple_vote::{HasEpoch, NextEpochQuorumVote2, QuorumVote2, TimeoutVote2},
     traits::{
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
         storage::Storage,
     },
     utils::{epoch_from_block_number, is_last_block},
+    view_timeout::AdaptiveViewTimeout,
     vote::HasViewNumber,
 };
 use hotshot_utils::anytrace::*;
@@ -35,7 +37,7 @@ use self::handlers::{
 };
 use crate::{
     events::HotShotEvent,
-    helpers::{broadcast_event, validate_qc_and_next_epoch_qc},
+    helpers::{broadcast_error, broadcast_event, validate_qc_and_next_epoch_qc},
     vote_collection::{EpochRootVoteCollectorsMap, VoteCollectorsMap},
 };
 
@@ -48,8 +50,8 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Immutable instance state
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -96,6 +98,9 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// View timeout from config.
     pub timeout: u64,
 
+    /// Adapts `timeout` to recent view durations, if configured
+    pub adaptive_timeout: Option<AdaptiveViewTimeout>,
+
     /// A reference to the metrics trait.
     pub consensus: OuterConsensus<TYPES>,
 
@@ -147,6 +152,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
                     tracing::debug!("Failed to handle TimeoutVoteRecv event; error = {e}");
                 }
             },
+            HotShotEvent::DoubleVoteDetected(evidence) => {
+                broadcast_event(
+                    Event {
+                        view_number: evidence.view,
+                        event: EventType::DoubleVote {
+                            evidence: evidence.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+                broadcast_error(
+                    evidence.view,
+                    HotShotError::ByzantineEvidence(format!(
+                        "{} signed two different votes in view {}",
+                        evidence.key, evidence.view
+                    )),
+                    &self.output_event_stream,
+                )
+                .await;
+            },
             HotShotEvent::SetFirstEpoch(view, epoch) => {
                 self.first_epoch = Some((*view, *epoch));
             },
@@ -4,21 +4,28 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc, time::Instant};
 
 use async_broadcast::{Receiver, Sender};
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::{supervisor::TaskSupervisor, task::TaskState};
 use hotshot_types::{
     consensus::{Consensus, OuterConsensus, PayloadWithMetadata},
-    data::{vid_commitment, vid_disperse::vid_total_weight, DaProposal2, PackedBundle},
+    data::{
+        vid_commitment, vid_disperse::vid_total_weight, DaPayloadHint2, DaProposal2, PackedBundle,
+        VidCommitment,
+    },
     epoch_membership::EpochMembershipCoordinator,
+    error::HotShotError,
     event::{Event, EventType},
     message::{Proposal, UpgradeLock},
+    signature_verifier::SignatureVerifier,
+    signer::Signer,
     simple_certificate::DaCertificate2,
     simple_vote::{DaData2, DaVote2},
     traits::{
+        da_payload_provider::DaPayloadProviderSlot,
         network::ConnectedNetwork,
         node_implementation::{NodeImplementation, NodeType, Versions},
         signature_key::SignatureKey,
@@ -32,13 +39,52 @@ use hotshot_utils::anytrace::*;
 use sha2::{Digest, Sha256};
 use tokio::{spawn, task::spawn_blocking};
 use tracing::instrument;
+use url::Url;
 
 use crate::{
     events::HotShotEvent,
-    helpers::broadcast_event,
+    helpers::{broadcast_error, broadcast_event},
     vote_collection::{handle_vote, VoteCollectorsMap},
 };
 
+/// A payload commitment calculated before the view it is proposed in has started
+pub struct PrecomputedPayloadCommitment<TYPES: NodeType> {
+    /// The transactions the commitment was calculated over
+    pub encoded_transactions: Arc<[u8]>,
+
+    /// The epoch whose stake table the commitment was calculated with
+    pub epoch: Option<TYPES::Epoch>,
+
+    /// The commitment to the payload
+    pub payload_commitment: VidCommitment,
+}
+
+/// Calculate the commitment announced in a payload hint for the given payload.
+///
+/// # Errors
+/// Returns an error if there is no stake table for `epoch` or the calculation panics
+async fn payload_hint_commitment<TYPES: NodeType, V: Versions>(
+    membership_coordinator: &EpochMembershipCoordinator<TYPES>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    encoded_transactions: &Arc<[u8]>,
+    metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    view_number: TYPES::View,
+    epoch: Option<TYPES::Epoch>,
+) -> Result<VidCommitment> {
+    let membership = membership_coordinator
+        .stake_table_for_epoch(epoch)
+        .await
+        .context(warn!("No stake table for epoch"))?;
+    let total_weight = vid_total_weight::<TYPES>(membership.stake_table().await, epoch);
+    let version = upgrade_lock.version_infallible(view_number).await;
+    let txns = Arc::clone(encoded_transactions);
+    let metadata_bytes = metadata.encode();
+    spawn_blocking(move || vid_commitment::<V>(&txns, &metadata_bytes, total_weight, version))
+        .await
+        .wrap()
+        .context(error!("Failed to compute the payload commitment"))
+}
+
 /// Tracks state of a DA task
 pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// Output events to application
@@ -53,6 +99,9 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     /// Reference to consensus. Leader will require a read lock on this.
     pub consensus: OuterConsensus<TYPES>,
 
+    /// InstanceState, for determining the limits on block contents
+    pub instance_state: Arc<TYPES::InstanceState>,
+
     /// Membership for the DA committee and quorum committee.
     /// We need the latter only for calculating the proper VID scheme
     /// from the number of nodes in the quorum.
@@ -67,8 +116,8 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// This Nodes signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// This state's ID
     pub id: u64,
@@ -78,9 +127,101 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Source of payloads announced through DA payload hints, once the application sets one
+    pub payload_provider: DaPayloadProviderSlot<TYPES>,
+
+    /// Payload size above which we announce a payload hint instead of a full DA proposal
+    pub payload_hint_threshold: Option<usize>,
+
+    /// Data providers advertised in our payload hints
+    pub payload_hint_urls: Vec<Url>,
+
+    /// In-flight fetches of payloads announced through payload hints
+    pub fetch_tasks: TaskSupervisor<TYPES::View>,
+
+    /// Payload hint commitments calculated for prefetched blocks, keyed by view
+    pub precomputed_payload_commitments:
+        Arc<Mutex<BTreeMap<TYPES::View, PrecomputedPayloadCommitment<TYPES>>>>,
+
+    /// In-flight payload hint commitment calculations, keyed by view
+    pub precompute_tasks: TaskSupervisor<TYPES::View>,
+
+    /// When we sent the DA proposal for each view we lead, until its certificate is formed
+    pub proposal_send_times: BTreeMap<TYPES::View, Instant>,
+
+    /// Number of DA votes received for each view we lead, until the view is over
+    pub votes_received: BTreeMap<TYPES::View, u64>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYPES, I, V> {
+    /// Whether a payload of `payload_len` bytes for `view` should be announced with a payload hint
+    async fn should_send_payload_hint(&self, payload_len: usize, view: TYPES::View) -> bool {
+        self.payload_hint_threshold
+            .is_some_and(|threshold| payload_len > threshold)
+            && self.upgrade_lock.epochs_enabled(view).await
+    }
+
+    /// Save the payload we are proposing early because we might need it to calculate VID for the next epoch nodes.
+    async fn save_payload(
+        &self,
+        view_number: TYPES::View,
+        encoded_transactions: &Arc<[u8]>,
+        metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    ) {
+        let payload_with_metadata = Arc::new(PayloadWithMetadata {
+            payload: TYPES::BlockPayload::from_bytes(encoded_transactions.as_ref(), metadata),
+            metadata: metadata.clone(),
+        });
+        if let Err(e) = self
+            .consensus
+            .write()
+            .await
+            .update_saved_payloads(view_number, payload_with_metadata)
+        {
+            e.log();
+        }
+    }
+
+    /// Check that the payload of a DA proposal respects the block limits.
+    ///
+    /// The limits are those of the state of our high QC, which the quorum proposal for the same
+    /// view normally extends. If we do not have that state, or cannot determine its limits, the
+    /// check is skipped rather than delaying our vote.
+    async fn validate_limits(&self, proposal: &Proposal<TYPES, DaProposal2<TYPES>>) -> Result<()> {
+        let view = proposal.data.view_number();
+        let parent_state = {
+            let consensus_reader = self.consensus.read().await;
+            let parent_view = consensus_reader.high_qc().view_number();
+            consensus_reader.state(parent_view).cloned()
+        };
+        let Some(parent_state) = parent_state else {
+            tracing::debug!("No parent state for view {view}, not checking block limits");
+            return Ok(());
+        };
+        let limits = match <TYPES::BlockPayload as BlockPayload<TYPES>>::limits(
+            &parent_state,
+            &self.instance_state,
+        )
+        .await
+        {
+            Ok(limits) => limits,
+            Err(e) => {
+                tracing::info!("Failed to determine block limits for view {view}: {e}");
+                return Ok(());
+            },
+        };
+        let payload = TYPES::BlockPayload::from_bytes(
+            &proposal.data.encoded_transactions,
+            &proposal.data.metadata,
+        );
+        ensure!(
+            payload.within_limits(&proposal.data.metadata, &limits),
+            warn!("DA proposal for view {view} exceeds the block limits")
+        );
+        Ok(())
+    }
+
     /// main task event handler
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = self.cur_epoch.map(|x| *x)), name = "DA Main Task", level = "error", target = "DaTaskState")]
     pub async fn handle(
@@ -132,16 +273,110 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 );
 
                 ensure!(
-                    view_leader_key.validate(&proposal.signature, &encoded_transactions_hash),
+                    SignatureVerifier::shared()
+                        .verify(
+                            view_leader_key,
+                            proposal.signature.clone(),
+                            encoded_transactions_hash.as_slice()
+                        )
+                        .await,
                     warn!("Could not verify proposal.")
                 );
 
+                self.validate_limits(proposal).await?;
+
                 broadcast_event(
                     Arc::new(HotShotEvent::DaProposalValidated(proposal.clone(), sender)),
                     &event_stream,
                 )
                 .await;
             },
+            HotShotEvent::DaPayloadHintRecv(hint, sender) => {
+                let view = hint.data.view_number();
+                tracing::debug!("DA payload hint received for view: {view:?}");
+
+                ensure!(
+                    self.cur_view <= view + 1,
+                    "Throwing away DA payload hint that is more than one view older"
+                );
+
+                let view_leader_key = self
+                    .membership_coordinator
+                    .membership_for_epoch(hint.data.epoch)
+                    .await
+                    .context(warn!("No stake table for epoch {:?}", hint.data.epoch))?
+                    .leader(view)
+                    .await?;
+                ensure!(
+                    view_leader_key == *sender,
+                    warn!("DA payload hint doesn't have expected leader key for view {view}")
+                );
+                ensure!(
+                    view_leader_key.validate(&hint.signature, &hint.data.encoded_transactions_hash),
+                    warn!("Could not verify DA payload hint.")
+                );
+
+                let signature = hint.signature.clone();
+                let sender = sender.clone();
+
+                // We may already hold the payload, e.g. from a previous proposal for this view.
+                let saved_payload = self
+                    .consensus
+                    .read()
+                    .await
+                    .saved_payloads()
+                    .get(&view)
+                    .map(|entry| entry.payload.encode());
+                if let Some(encoded_transactions) = saved_payload {
+                    if let Ok(data) = hint.data.clone().into_proposal(encoded_transactions) {
+                        broadcast_event(
+                            Arc::new(HotShotEvent::DaProposalRecv(
+                                Proposal {
+                                    data,
+                                    signature,
+                                    _pd: PhantomData,
+                                },
+                                sender,
+                            )),
+                            &event_stream,
+                        )
+                        .await;
+                        return Ok(());
+                    }
+                }
+
+                let provider = self
+                    .payload_provider
+                    .get()
+                    .cloned()
+                    .context(warn!("Received a DA payload hint for view {view} but no payload provider is configured"))?;
+                let hint = hint.data.clone();
+                self.fetch_tasks.spawn(view, async move {
+                    let Some(encoded_transactions) = provider.fetch_payload(&hint).await else {
+                        tracing::warn!("Failed to fetch DA payload for view {view}");
+                        return;
+                    };
+                    let data = match hint.into_proposal(encoded_transactions) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            tracing::warn!("Fetched invalid DA payload for view {view}: {e}");
+                            return;
+                        },
+                    };
+                    broadcast_event(
+                        Arc::new(HotShotEvent::DaProposalRecv(
+                            Proposal {
+                                data,
+                                signature,
+                                _pd: PhantomData,
+                            },
+                            sender,
+                        )),
+                        &event_stream,
+                    )
+                    .await;
+                });
+            },
             HotShotEvent::DaProposalValidated(proposal, sender) => {
                 let cur_view = self.consensus.read().await.cur_view();
                 let view_number = proposal.data.view_number();
@@ -160,6 +395,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     proposal.data.view_number()
                   )
                 );
+                self.consensus
+                    .read()
+                    .await
+                    .metrics
+                    .payload_size
+                    .add_point(proposal.data.encoded_transactions.len() as f64);
 
                 // Proposal is fresh and valid, notify the application layer
                 broadcast_event(
@@ -226,13 +467,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     None
                 };
 
-                self.storage
+                let stored = self
+                    .storage
                     .write()
                     .await
                     .append_da2(proposal, payload_commitment)
-                    .await
-                    .wrap()
-                    .context(error!("Failed to append DA proposal to storage"))?;
+                    .await;
+                if let Err(e) = stored {
+                    broadcast_error(
+                        view_number,
+                        HotShotError::Storage(format!("failed to append DA proposal: {e}")),
+                        &self.output_event_stream,
+                    )
+                    .await;
+                    return Err(e)
+                        .wrap()
+                        .context(error!("Failed to append DA proposal to storage"));
+                }
                 // Generate and send vote
                 let vote = DaVote2::create_signed_vote(
                     DaData2 {
@@ -242,7 +493,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     },
                     view_number,
                     &self.public_key,
-                    &self.private_key,
+                    &self.signer,
                     &self.upgrade_lock,
                 )
                 .await?;
@@ -257,7 +508,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 if let Err(e) =
                     consensus_writer.update_da_view(view_number, epoch_number, payload_commitment)
                 {
-                    tracing::trace!("{e:?}");
+                    e.log();
                 }
 
                 let payload_with_metadata = Arc::new(PayloadWithMetadata {
@@ -272,7 +523,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 if let Err(e) =
                     consensus_writer.update_saved_payloads(view_number, payload_with_metadata)
                 {
-                    tracing::trace!("{e:?}");
+                    e.log();
                 }
                 drop(consensus_writer);
 
@@ -280,7 +531,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 if self.network.is_primary_down() {
                     let consensus =
                         OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
-                    let pk = self.private_key.clone();
+                    let signer = self.signer.clone();
                     let public_key = self.public_key.clone();
                     let chan = event_stream.clone();
                     let upgrade_lock = self.upgrade_lock.clone();
@@ -306,7 +557,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                             view_number,
                             target_epoch,
                             membership.coordinator.clone(),
-                            &pk,
+                            &signer,
                             &upgrade_lock,
                         )
                         .await;
@@ -349,12 +600,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     )
                 );
 
+                *self.votes_received.entry(view).or_default() += 1;
+
+                let consensus_metrics = Arc::clone(&self.consensus.read().await.metrics);
                 handle_vote(
                     &mut self.vote_collectors,
                     vote,
                     self.public_key.clone(),
                     &membership,
                     self.id,
+                    &consensus_metrics,
                     &event,
                     &event_stream,
                     &self.upgrade_lock,
@@ -362,6 +617,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 )
                 .await?;
             },
+            HotShotEvent::DacSend(cert, _) => {
+                if let Some(sent) = self.proposal_send_times.remove(&cert.view_number()) {
+                    self.consensus
+                        .read()
+                        .await
+                        .metrics
+                        .da_certificate_latency
+                        .add_point(sent.elapsed().as_secs_f64());
+                }
+
+                broadcast_event(
+                    Event {
+                        view_number: cert.view_number(),
+                        event: EventType::DaCertificate {
+                            certificate: cert.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
+            },
             HotShotEvent::ViewChange(view, epoch) => {
                 if *epoch > self.cur_epoch {
                     self.cur_epoch = *epoch;
@@ -377,6 +653,70 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     tracing::info!("View changed by more than 1 going to view {view:?}");
                 }
                 self.cur_view = view;
+
+                // Votes are no longer collected for views more than one view old, so their counts
+                // are final.
+                let votes_received = self.votes_received.split_off(&(view - 1));
+                let finished = std::mem::replace(&mut self.votes_received, votes_received);
+                if !finished.is_empty() {
+                    let metrics = Arc::clone(&self.consensus.read().await.metrics);
+                    for votes in finished.into_values() {
+                        metrics.da_votes_received.add_point(votes as f64);
+                    }
+                }
+                self.proposal_send_times = self.proposal_send_times.split_off(&(view - 1));
+
+                // Proposals more than one view old are discarded, so their payloads are no longer needed.
+                self.fetch_tasks.cancel_before(&(view - 1));
+                self.precompute_tasks.cancel_before(&view);
+                let mut precomputed = self.precomputed_payload_commitments.lock().await;
+                *precomputed = precomputed.split_off(&view);
+            },
+            HotShotEvent::BlockPrefetched(packed_bundle) => {
+                let view_number = packed_bundle.view_number;
+                if !self
+                    .should_send_payload_hint(packed_bundle.encoded_transactions.len(), view_number)
+                    .await
+                {
+                    return Ok(());
+                }
+
+                // The block for our upcoming view is known early, calculate the commitment for
+                // its payload hint while the current view is still in progress.
+                let membership_coordinator = self.membership_coordinator.clone();
+                let upgrade_lock = self.upgrade_lock.clone();
+                let precomputed = Arc::clone(&self.precomputed_payload_commitments);
+                let encoded_transactions = Arc::clone(&packed_bundle.encoded_transactions);
+                let metadata = packed_bundle.metadata.clone();
+                let epoch = self.cur_epoch;
+                self.precompute_tasks.spawn(view_number, async move {
+                    match payload_hint_commitment(
+                        &membership_coordinator,
+                        &upgrade_lock,
+                        &encoded_transactions,
+                        &metadata,
+                        view_number,
+                        epoch,
+                    )
+                    .await
+                    {
+                        Ok(payload_commitment) => {
+                            precomputed.lock().await.insert(
+                                view_number,
+                                PrecomputedPayloadCommitment {
+                                    encoded_transactions,
+                                    epoch,
+                                    payload_commitment,
+                                },
+                            );
+                        },
+                        Err(e) => {
+                            tracing::debug!(
+                                "Failed to precompute the payload commitment for view {view_number}: {e}"
+                            );
+                        },
+                    }
+                });
             },
             HotShotEvent::BlockRecv(packed_bundle) => {
                 let PackedBundle::<TYPES> {
@@ -391,9 +731,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 let encoded_transactions_hash = Sha256::digest(encoded_transactions);
 
                 // sign the encoded transactions as opposed to the VID commitment
-                let signature =
-                    TYPES::SignatureKey::sign(&self.private_key, &encoded_transactions_hash)
-                        .wrap()?;
+                let signature = self.signer.sign(&encoded_transactions_hash).wrap()?;
 
                 let epoch = self.cur_epoch;
                 let leader = self
@@ -409,12 +747,71 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     );
                     return Ok(());
                 }
+                self.proposal_send_times.insert(view_number, Instant::now());
+
                 let epoch_transition_indicator =
                     if self.consensus.read().await.is_high_qc_ge_root_block() {
                         EpochTransitionIndicator::InTransition
                     } else {
                         EpochTransitionIndicator::NotInTransition
                     };
+
+                if self
+                    .should_send_payload_hint(encoded_transactions.len(), view_number)
+                    .await
+                {
+                    let precomputed = self
+                        .precomputed_payload_commitments
+                        .lock()
+                        .await
+                        .remove(&view_number)
+                        .filter(|precomputed| {
+                            precomputed.epoch == epoch
+                                && precomputed.encoded_transactions == *encoded_transactions
+                        });
+                    let payload_commitment = match precomputed {
+                        Some(precomputed) => precomputed.payload_commitment,
+                        None => {
+                            payload_hint_commitment(
+                                &self.membership_coordinator,
+                                &self.upgrade_lock,
+                                encoded_transactions,
+                                metadata,
+                                view_number,
+                                epoch,
+                            )
+                            .await?
+                        },
+                    };
+
+                    let data = DaPayloadHint2 {
+                        encoded_transactions_hash: encoded_transactions_hash.into(),
+                        payload_commitment,
+                        payload_byte_len: encoded_transactions.len() as u64,
+                        metadata: metadata.clone(),
+                        view_number,
+                        epoch,
+                        epoch_transition_indicator,
+                        fetch_hints: self.payload_hint_urls.clone(),
+                    };
+
+                    broadcast_event(
+                        Arc::new(HotShotEvent::DaPayloadHintSend(
+                            Proposal {
+                                data,
+                                signature,
+                                _pd: PhantomData,
+                            },
+                            self.public_key.clone(),
+                        )),
+                        &event_stream,
+                    )
+                    .await;
+                    self.save_payload(view_number, encoded_transactions, metadata)
+                        .await;
+                    return Ok(());
+                }
+
                 let data: DaProposal2<TYPES> = DaProposal2 {
                     encoded_transactions: Arc::clone(encoded_transactions),
                     metadata: metadata.clone(),
@@ -438,22 +835,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     &event_stream,
                 )
                 .await;
-                let payload_with_metadata = Arc::new(PayloadWithMetadata {
-                    payload: TYPES::BlockPayload::from_bytes(
-                        encoded_transactions.as_ref(),
-                        metadata,
-                    ),
-                    metadata: metadata.clone(),
-                });
-                // Save the payload early because we might need it to calculate VID for the next epoch nodes.
-                if let Err(e) = self
-                    .consensus
-                    .write()
-                    .await
-                    .update_saved_payloads(view_number, payload_with_metadata)
-                {
-                    tracing::trace!("{e:?}");
-                }
+                self.save_payload(view_number, encoded_transactions, metadata)
+                    .await;
             },
             _ => {},
         }
@@ -477,5 +860,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
         self.handle(event, sender.clone()).await
     }
 
-    fn cancel_subtasks(&mut self) {}
+    fn cancel_subtasks(&mut self) {
+        self.fetch_tasks.abort_all();
+        self.precompute_tasks.abort_all();
+    }
 }
@@ -0,0 +1,226 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Computation of the DRB results used for leader rotation in upcoming epochs.
+//!
+//! The result for an epoch is seeded by the QC on its epoch root, which is decided two epochs in
+//! advance, and takes a long time to compute by design. The computation runs in the background as
+//! soon as the root is decided, checkpointing its progress to storage as it goes, so that a node
+//! which restarts picks up where it left off instead of starting over. The finished result is
+//! stored and handed to the membership, well before the epoch it is for begins.
+
+use std::sync::Arc;
+
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    consensus::OuterConsensus,
+    drb::{DrbInput, DrbSeedInput, DRB_CHECKPOINT_INTERVAL},
+    traits::{
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        storage::Storage,
+    },
+};
+use hotshot_utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::handle_drb_result};
+
+/// A DRB computation for a single epoch.
+pub struct DrbComputation<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+    /// The epoch the result is for.
+    epoch: TYPES::Epoch,
+
+    /// Progress of the computation.
+    input: DrbInput,
+
+    /// Membership to hand the result to.
+    membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Storage for checkpoints and the result.
+    storage: Arc<RwLock<I::Storage>>,
+
+    /// Consensus state, which records the computations underway.
+    consensus: OuterConsensus<TYPES>,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DrbComputation<TYPES, I> {
+    /// A computation for `epoch` starting from `seed`.
+    pub fn new(
+        epoch: TYPES::Epoch,
+        seed: DrbSeedInput,
+        membership: &Arc<RwLock<TYPES::Membership>>,
+        storage: &Arc<RwLock<I::Storage>>,
+        consensus: &OuterConsensus<TYPES>,
+    ) -> Self {
+        Self::resume(
+            DrbInput::new(epoch.u64(), seed),
+            membership,
+            storage,
+            consensus,
+        )
+    }
+
+    /// A computation continuing from a checkpoint.
+    pub fn resume(
+        input: DrbInput,
+        membership: &Arc<RwLock<TYPES::Membership>>,
+        storage: &Arc<RwLock<I::Storage>>,
+        consensus: &OuterConsensus<TYPES>,
+    ) -> Self {
+        Self {
+            epoch: TYPES::Epoch::new(input.epoch),
+            input,
+            membership: Arc::clone(membership),
+            storage: Arc::clone(storage),
+            consensus: consensus.clone(),
+        }
+    }
+
+    /// Run the computation in the background.
+    ///
+    /// Does nothing if the result is already known, or is already being computed.
+    pub async fn spawn(self) {
+        if !self
+            .consensus
+            .write()
+            .await
+            .drb_results
+            .start_computation(self.epoch)
+        {
+            tracing::debug!("DRB result for epoch {} is already computed", self.epoch);
+            return;
+        }
+        tokio::spawn(self.run());
+    }
+
+    /// Compute the result, checkpointing along the way, and store it.
+    async fn run(mut self) {
+        let epoch = self.epoch;
+
+        // A checkpoint may be further along than the starting point, if the computation was
+        // interrupted by a restart.
+        match self.storage.read().await.load_drb_input(epoch).await {
+            Ok(Some(checkpoint)) if checkpoint.iteration > self.input.iteration => {
+                tracing::info!(
+                    "Resuming DRB computation for epoch {epoch} from iteration {}",
+                    checkpoint.iteration
+                );
+                self.input = checkpoint;
+            },
+            Ok(_) => {},
+            Err(e) => tracing::warn!("Failed to load DRB checkpoint for epoch {epoch}: {e}"),
+        }
+
+        while !self.input.is_complete() {
+            let mut input = self.input.clone();
+            match tokio::task::spawn_blocking(move || {
+                input.iterate(DRB_CHECKPOINT_INTERVAL);
+                input
+            })
+            .await
+            {
+                Ok(input) => self.input = input,
+                Err(e) => {
+                    tracing::error!("DRB computation for epoch {epoch} failed: {e}");
+                    self.consensus
+                        .write()
+                        .await
+                        .drb_results
+                        .abandon_computation(epoch);
+                    return;
+                },
+            }
+
+            if let Err(e) = self
+                .storage
+                .read()
+                .await
+                .store_drb_input(self.input.clone())
+                .await
+            {
+                tracing::warn!("Failed to store DRB checkpoint for epoch {epoch}: {e}");
+            }
+        }
+
+        handle_drb_result::<TYPES, I>(
+            &self.membership,
+            epoch,
+            &self.storage,
+            &self.consensus,
+            self.input.value,
+        )
+        .await;
+    }
+}
+
+/// Task which resumes interrupted DRB computations for upcoming epochs.
+///
+/// New computations are started when epoch roots are decided; this task makes sure those which
+/// were underway when the node stopped are finished before their epochs begin.
+pub struct DrbTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
+    /// Membership to hand results to.
+    pub membership: Arc<RwLock<TYPES::Membership>>,
+
+    /// Storage holding checkpoints of DRB computations.
+    pub storage: Arc<RwLock<I::Storage>>,
+
+    /// Consensus state, which records the computations underway.
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// The latest epoch we have checked for interrupted computations.
+    pub latest_epoch: Option<TYPES::Epoch>,
+}
+
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> DrbTaskState<TYPES, I> {
+    /// Resume any interrupted computations for the two epochs after `epoch`.
+    async fn resume_computations(&mut self, epoch: TYPES::Epoch) {
+        if self.latest_epoch.is_some_and(|latest| latest >= epoch) {
+            return;
+        }
+        self.latest_epoch = Some(epoch);
+
+        for upcoming in [epoch + 1, epoch + 2] {
+            let checkpoint = match self.storage.read().await.load_drb_input(upcoming).await {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    tracing::warn!("Failed to load DRB checkpoint for epoch {upcoming}: {e}");
+                    continue;
+                },
+            };
+            if let Some(input) = checkpoint {
+                DrbComputation::<TYPES, I>::resume(
+                    input,
+                    &self.membership,
+                    &self.storage,
+                    &self.consensus,
+                )
+                .spawn()
+                .await;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for DrbTaskState<TYPES, I> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let HotShotEvent::ViewChange(_, Some(epoch)) = event.as_ref() {
+            self.resume_computations(*epoch).await;
+        }
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -11,26 +11,28 @@ use either::Either;
 use hotshot_task::task::TaskEvent;
 use hotshot_types::{
     data::{
-        DaProposal2, Leaf2, PackedBundle, QuorumProposal2, QuorumProposalWrapper, UpgradeProposal,
-        VidCommitment, VidDisperse, VidDisperseShare,
+        DaPayloadHint2, DaProposal2, Leaf2, PackedBundle, QuorumProposal2, QuorumProposalWrapper,
+        UpgradeProposal, VidCommitment, VidDisperse, VidDisperseShare, ViewChangeEvidence2,
     },
     message::Proposal,
-    request_response::ProposalRequestPayload,
+    request_response::{
+        ProposalFetchRequestPayload, ProposalRequestPayload, ViewEvidenceRequestPayload,
+    },
     simple_certificate::{
         DaCertificate2, EpochRootQuorumCertificate, NextEpochQuorumCertificate2, QuorumCertificate,
         QuorumCertificate2, TimeoutCertificate, TimeoutCertificate2, UpgradeCertificate,
         ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
-        DaVote2, EpochRootQuorumVote, QuorumVote2, TimeoutVote2, UpgradeVote, ViewSyncCommitVote2,
-        ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
+        DaVote2, EpochRootQuorumVote, HasEpoch, QuorumVote2, TimeoutVote2, UpgradeVote,
+        ViewSyncCommitVote2, ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
     },
     traits::{
         block_contents::BuilderFee, network::DataRequest, node_implementation::NodeType,
         signature_key::SignatureKey, BlockPayload,
     },
     utils::BuilderCommitment,
-    vote::HasViewNumber,
+    vote::{DoubleVoteEvidence, HasViewNumber},
 };
 use vec1::Vec1;
 
@@ -40,6 +42,22 @@ impl<TYPES: NodeType> TaskEvent for HotShotEvent<TYPES> {
     fn shutdown_event() -> Self {
         HotShotEvent::Shutdown
     }
+
+    fn view(&self) -> Option<u64> {
+        self.view_number().map(|view| *view)
+    }
+
+    fn epoch(&self) -> Option<u64> {
+        self.epoch_number().map(|epoch| *epoch)
+    }
+
+    fn kind(&self) -> Option<&'static str> {
+        Some(self.into())
+    }
+
+    fn peer(&self) -> Option<String> {
+        self.peer_key().map(ToString::to_string)
+    }
 }
 
 /// Wrapper type for the event to notify tasks that a proposal for a view is missing
@@ -65,7 +83,7 @@ impl<TYPES: NodeType> Eq for ProposalMissing<TYPES> {}
 pub struct HotShotTaskCompleted;
 
 /// All of the possible events that can be passed between Sequencing `HotShot` tasks
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Debug, Clone, strum::IntoStaticStr)]
 #[allow(clippy::large_enum_variant)]
 pub enum HotShotEvent<TYPES: NodeType> {
     /// Shutdown the task
@@ -88,6 +106,8 @@ pub enum HotShotEvent<TYPES: NodeType> {
     DaProposalRecv(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
     /// A DA proposal has been validated; handled by the DA task and VID task
     DaProposalValidated(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
+    /// A DA payload hint has been received from the network; handled by the DA task
+    DaPayloadHintRecv(Proposal<TYPES, DaPayloadHint2<TYPES>>, TYPES::SignatureKey),
     /// A DA vote has been received by the network; handled by the DA task
     DaVoteRecv(DaVote2<TYPES>),
     /// A Data Availability Certificate (DAC) has been received by the network; handled by the consensus task
@@ -129,8 +149,50 @@ pub enum HotShotEvent<TYPES: NodeType> {
     ),
     /// A quorum proposal was requested by a node for a view.
     QuorumProposalResponseRecv(Proposal<TYPES, QuorumProposalWrapper<TYPES>>),
+    /// We received a QC for a proposal we never saw, and ask a specific peer for it.
+    QuorumProposalFetchSend(
+        ProposalFetchRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+    /// A node asked us for a proposal referenced by a QC it received.
+    QuorumProposalFetchRecv(
+        ProposalFetchRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+    /// Send a proposal to a node which fetched it from us.
+    QuorumProposalFetchResponseSend(
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+        Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
+    ),
+    /// We timed out and ask our peers whether the network has already moved past our view.
+    ViewEvidenceRequestSend(
+        ViewEvidenceRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+    /// A node which fell behind asked for evidence that the network has moved past its view.
+    ViewEvidenceRequestRecv(
+        ViewEvidenceRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+    /// Send our latest view change evidence to a node which fell behind.
+    ViewEvidenceResponseSend(
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+        ViewChangeEvidence2<TYPES>,
+    ),
+    /// A peer sent us evidence that the network has moved past our view.
+    ViewEvidenceResponseRecv(ViewChangeEvidence2<TYPES>),
     /// Send a DA proposal to the DA committee; emitted by the DA leader (which is the same node as the leader of view v + 1) in the DA task
     DaProposalSend(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
+    /// Send a DA payload hint to the DA committee in place of the full DA proposal; emitted by the DA leader in the DA task
+    DaPayloadHintSend(Proposal<TYPES, DaPayloadHint2<TYPES>>, TYPES::SignatureKey),
     /// Send a DA vote to the DA leader; emitted by DA committee members in the DA task after seeing a valid DA proposal
     DaVoteSend(DaVote2<TYPES>),
     /// The next leader has collected enough votes to form a QC; emitted by the next leader in the consensus task; an internal event only
@@ -186,6 +248,8 @@ pub enum HotShotEvent<TYPES: NodeType> {
     Timeout(TYPES::View, Option<TYPES::Epoch>),
     /// Receive transactions from the network
     TransactionsRecv(Vec<TYPES::Transaction>),
+    /// Leaves were decided, newest first; emitted by the quorum vote task; internal event only
+    LeavesDecided(Vec<Leaf2<TYPES>>),
     /// Send transactions to the network
     TransactionSend(TYPES::Transaction, TYPES::SignatureKey),
     /// Event to send block payload commitment and metadata from DA leader to the quorum; internal event only
@@ -197,6 +261,9 @@ pub enum HotShotEvent<TYPES: NodeType> {
         Vec1<BuilderFee<TYPES>>,
         Option<TYPES::AuctionResult>,
     ),
+    /// Event when the transactions task has obtained the block for its upcoming view as leader
+    /// before that view started, so the block can be prepared ahead of time
+    BlockPrefetched(PackedBundle<TYPES>),
     /// Event when the transactions task has sequenced transactions. Contains the encoded transactions, the metadata, and the view number
     BlockRecv(PackedBundle<TYPES>),
     /// Send VID shares to VID storage nodes; emitted by the DA leader
@@ -229,6 +296,13 @@ pub enum HotShotEvent<TYPES: NodeType> {
     /// 3. The justify QC is valid
     QuorumProposalPreliminarilyValidated(Proposal<TYPES, QuorumProposalWrapper<TYPES>>),
 
+    /// The missing parent of the buffered proposal for the given view has been fetched
+    ProposalDependenciesFetched(TYPES::View),
+
+    /// A vote collector found a node which signed two different votes in the same view; emitted
+    /// by the vote collection tasks and reported to external listeners by the consensus task
+    DoubleVoteDetected(DoubleVoteEvidence<TYPES>),
+
     /// Send a VID request to the network; emitted to on of the members of DA committee.
     /// Includes the data request, node's public key and signature as well as public key of DA committee who we want to send to.
     VidRequestSend(
@@ -323,6 +397,8 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::DaProposalRecv(proposal, _)
             | HotShotEvent::DaProposalValidated(proposal, _)
             | HotShotEvent::DaProposalSend(proposal, _) => Some(proposal.data.view_number()),
+            HotShotEvent::DaPayloadHintRecv(proposal, _)
+            | HotShotEvent::DaPayloadHintSend(proposal, _) => Some(proposal.data.view_number()),
             HotShotEvent::DaVoteRecv(vote) | HotShotEvent::DaVoteSend(vote) => {
                 Some(vote.view_number())
             },
@@ -355,10 +431,12 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::SendPayloadCommitmentAndMetadata(_, _, _, view_number, ..) => {
                 Some(*view_number)
             },
-            HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
+            HotShotEvent::BlockRecv(packed_bundle)
+            | HotShotEvent::BlockPrefetched(packed_bundle) => Some(packed_bundle.view_number),
             HotShotEvent::Shutdown
             | HotShotEvent::TransactionSend(..)
             | HotShotEvent::TransactionsRecv(_) => None,
+            HotShotEvent::LeavesDecided(leaves) => leaves.first().map(Leaf2::view_number),
             HotShotEvent::VidDisperseSend(proposal, _) => Some(proposal.data.view_number()),
             HotShotEvent::VidShareRecv(_, proposal) | HotShotEvent::VidShareValidated(proposal) => {
                 Some(proposal.data.view_number())
@@ -370,14 +448,25 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             },
             HotShotEvent::QuorumProposalRequestSend(req, _)
             | HotShotEvent::QuorumProposalRequestRecv(req, _) => Some(req.view_number),
+            HotShotEvent::ViewEvidenceRequestSend(req, _)
+            | HotShotEvent::ViewEvidenceRequestRecv(req, _) => Some(req.view_number),
+            HotShotEvent::QuorumProposalFetchSend(req, ..)
+            | HotShotEvent::QuorumProposalFetchRecv(req, _) => Some(req.view_number),
+            HotShotEvent::QuorumProposalFetchResponseSend(_, _, proposal) => {
+                Some(proposal.data.view_number())
+            },
+            HotShotEvent::ViewEvidenceResponseSend(_, _, evidence)
+            | HotShotEvent::ViewEvidenceResponseRecv(evidence) => Some(evidence.view_to_enter()),
             HotShotEvent::ViewChange(view_number, _)
             | HotShotEvent::ViewSyncTimeout(view_number, ..)
             | HotShotEvent::ViewSyncTrigger(view_number)
+            | HotShotEvent::ProposalDependenciesFetched(view_number)
             | HotShotEvent::Timeout(view_number, ..) => Some(*view_number),
             HotShotEvent::DaCertificateRecv(cert) | HotShotEvent::DacSend(cert, _) => {
                 Some(cert.view_number())
             },
             HotShotEvent::DaCertificateValidated(cert) => Some(cert.view_number),
+            HotShotEvent::DoubleVoteDetected(evidence) => Some(evidence.view),
             HotShotEvent::UpgradeCertificateFormed(cert) => Some(cert.view_number()),
             HotShotEvent::VidRequestSend(request, ..)
             | HotShotEvent::VidRequestRecv(request, _) => Some(request.view),
@@ -393,6 +482,48 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::SetFirstEpoch(..) => None,
         }
     }
+
+    /// Return the epoch for a hotshot event, if it is known
+    ///
+    /// This only covers the events which are most useful for correlating logs, such as proposals,
+    /// votes and view changes.
+    pub fn epoch_number(&self) -> Option<TYPES::Epoch> {
+        match self {
+            HotShotEvent::QuorumVoteSend(v)
+            | HotShotEvent::QuorumVoteRecv(v)
+            | HotShotEvent::ExtendedQuorumVoteSend(v) => v.data.epoch(),
+            HotShotEvent::TimeoutVoteRecv(v) | HotShotEvent::TimeoutVoteSend(v) => v.data.epoch(),
+            HotShotEvent::DaVoteRecv(v) | HotShotEvent::DaVoteSend(v) => v.data.epoch(),
+            HotShotEvent::QuorumProposalRecv(proposal, _)
+            | HotShotEvent::QuorumProposalSend(proposal, _)
+            | HotShotEvent::QuorumProposalValidated(proposal, _)
+            | HotShotEvent::QuorumProposalResponseRecv(proposal)
+            | HotShotEvent::QuorumProposalResponseSend(_, proposal)
+            | HotShotEvent::QuorumProposalFetchResponseSend(_, _, proposal)
+            | HotShotEvent::QuorumProposalPreliminarilyValidated(proposal) => proposal.data.epoch(),
+            HotShotEvent::DaProposalRecv(proposal, _)
+            | HotShotEvent::DaProposalValidated(proposal, _)
+            | HotShotEvent::DaProposalSend(proposal, _) => proposal.data.epoch(),
+            HotShotEvent::ViewChange(_, epoch) | HotShotEvent::Timeout(_, epoch) => *epoch,
+            _ => None,
+        }
+    }
+
+    /// Return the key of the peer a hotshot event was received from or is addressed to, if any
+    pub fn peer_key(&self) -> Option<&TYPES::SignatureKey> {
+        match self {
+            HotShotEvent::QuorumProposalRecv(_, sender)
+            | HotShotEvent::DaProposalRecv(_, sender)
+            | HotShotEvent::DaPayloadHintRecv(_, sender)
+            | HotShotEvent::QuorumProposalResponseSend(sender, _)
+            | HotShotEvent::QuorumProposalFetchSend(_, _, sender)
+            | HotShotEvent::QuorumProposalFetchResponseSend(_, sender, _) => Some(sender),
+            HotShotEvent::QuorumVoteRecv(v) => Some(&v.signature.0),
+            HotShotEvent::TimeoutVoteRecv(v) => Some(&v.signature.0),
+            HotShotEvent::DaVoteRecv(v) => Some(&v.signature.0),
+            _ => None,
+        }
+    }
 }
 
 impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
@@ -445,6 +576,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "DaProposalValidated(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::DaPayloadHintRecv(proposal, _) => write!(
+                f,
+                "DaPayloadHintRecv(view_number={:?})",
+                proposal.data.view_number()
+            ),
             HotShotEvent::DaVoteRecv(vote) => {
                 write!(f, "DaVoteRecv(view_number={:?})", vote.view_number())
             },
@@ -474,6 +610,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "DaProposalSend(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::DaPayloadHintSend(proposal, _) => write!(
+                f,
+                "DaPayloadHintSend(view_number={:?})",
+                proposal.data.view_number()
+            ),
             HotShotEvent::DaVoteSend(vote) => {
                 write!(f, "DaVoteSend(view_number={:?})", vote.view_number())
             },
@@ -594,6 +735,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 write!(f, "Timeout(view_number={view_number:?}, epoch={epoch:?})")
             },
             HotShotEvent::TransactionsRecv(_) => write!(f, "TransactionsRecv"),
+            HotShotEvent::LeavesDecided(leaves) => write!(
+                f,
+                "LeavesDecided(view_number={:?})",
+                leaves.first().map(Leaf2::view_number)
+            ),
             HotShotEvent::TransactionSend(..) => write!(f, "TransactionSend"),
             HotShotEvent::SendPayloadCommitmentAndMetadata(_, _, _, view_number, ..) => {
                 write!(
@@ -601,6 +747,13 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     "SendPayloadCommitmentAndMetadata(view_number={view_number:?})"
                 )
             },
+            HotShotEvent::BlockPrefetched(packed_bundle) => {
+                write!(
+                    f,
+                    "BlockPrefetched(view_number={:?})",
+                    packed_bundle.view_number
+                )
+            },
             HotShotEvent::BlockRecv(packed_bundle) => {
                 write!(f, "BlockRecv(view_number={:?})", packed_bundle.view_number)
             },
@@ -660,6 +813,55 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     proposal.data.view_number()
                 )
             },
+            HotShotEvent::QuorumProposalFetchSend(req, ..) => {
+                write!(
+                    f,
+                    "QuorumProposalFetchSend(view_number={:?})",
+                    req.view_number
+                )
+            },
+            HotShotEvent::QuorumProposalFetchRecv(req, _) => {
+                write!(
+                    f,
+                    "QuorumProposalFetchRecv(view_number={:?})",
+                    req.view_number
+                )
+            },
+            HotShotEvent::QuorumProposalFetchResponseSend(_, _, proposal) => {
+                write!(
+                    f,
+                    "QuorumProposalFetchResponseSend(view_number={:?})",
+                    proposal.data.view_number()
+                )
+            },
+            HotShotEvent::ViewEvidenceRequestSend(req, _) => {
+                write!(
+                    f,
+                    "ViewEvidenceRequestSend(view_number={:?})",
+                    req.view_number
+                )
+            },
+            HotShotEvent::ViewEvidenceRequestRecv(req, _) => {
+                write!(
+                    f,
+                    "ViewEvidenceRequestRecv(view_number={:?})",
+                    req.view_number
+                )
+            },
+            HotShotEvent::ViewEvidenceResponseSend(_, _, evidence) => {
+                write!(
+                    f,
+                    "ViewEvidenceResponseSend(view_to_enter={:?})",
+                    evidence.view_to_enter()
+                )
+            },
+            HotShotEvent::ViewEvidenceResponseRecv(evidence) => {
+                write!(
+                    f,
+                    "ViewEvidenceResponseRecv(view_to_enter={:?})",
+                    evidence.view_to_enter()
+                )
+            },
             HotShotEvent::QuorumProposalPreliminarilyValidated(proposal) => {
                 write!(
                     f,
@@ -667,6 +869,12 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     proposal.data.view_number()
                 )
             },
+            HotShotEvent::ProposalDependenciesFetched(view_number) => {
+                write!(
+                    f,
+                    "ProposalDependenciesFetched(view_number={view_number:?})"
+                )
+            },
             HotShotEvent::VidRequestSend(request, ..) => {
                 write!(f, "VidRequestSend(view_number={:?}", request.view)
             },
@@ -712,6 +920,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     view, epoch
                 )
             },
+            HotShotEvent::DoubleVoteDetected(evidence) => write!(
+                f,
+                "DoubleVoteDetected(view_number={:?}, key={})",
+                evidence.view, evidence.key
+            ),
         }
     }
 }
@@ -6,52 +6,82 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 use alloy::primitives::U256;
 use async_broadcast::{Receiver, SendError, Sender};
-use async_lock::RwLock;
+use async_lock::{Mutex, RwLock};
 use committable::{Commitment, Committable};
 use either::Either;
-use hotshot_task::dependency::{Dependency, EventDependency};
+use hotshot_task::{
+    broadcast_time::Timestamped,
+    dependency::{Dependency, EventDependency},
+};
 use hotshot_types::{
-    consensus::OuterConsensus,
-    data::{Leaf2, QuorumProposalWrapper, ViewChangeEvidence2},
-    drb::{DrbResult, DrbSeedInput},
+    consensus::{ConsensusMetricsValue, OuterConsensus},
+    data::{Leaf2, QuorumProposalWrapper, VidDisperse, ViewChangeEvidence2},
+    drb::DrbResult,
     epoch_membership::EpochMembershipCoordinator,
+    error::HotShotError,
     event::{Event, EventType, LeafInfo},
+    feature_gates::Feature,
     message::{Proposal, UpgradeLock},
-    request_response::ProposalRequestPayload,
+    request_response::{ProposalFetchRequestPayload, ProposalRequestPayload},
+    signer::Signer,
     simple_certificate::{
-        LightClientStateUpdateCertificate, NextEpochQuorumCertificate2, QuorumCertificate2,
-        UpgradeCertificate,
+        EpochQuorumCertificate, LightClientStateUpdateCertificate, NextEpochQuorumCertificate2,
+        QuorumCertificate2, UpgradeCertificate,
     },
     simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::{SignatureKey, StakeTableEntryType, StateSignatureKey},
+        signature_key::{StakeTableEntryType, StateSignatureKey},
         storage::Storage,
         BlockPayload, ValidatedState,
     },
     utils::{
         epoch_from_block_number, is_epoch_root, is_epoch_transition, is_transition_block,
-        option_epoch_from_block_number, Terminator, View, ViewInner,
+        option_epoch_from_block_number, BuilderCommitment, Terminator, View, ViewInner,
     },
     vote::{Certificate, HasViewNumber},
     StakeTableEntries,
 };
 use hotshot_utils::anytrace::*;
-use tokio::time::timeout;
+use lru::LruCache;
+use rand::seq::SliceRandom;
+use tokio::time::{timeout, timeout_at};
 use tracing::instrument;
-use vbs::version::StaticVersionType;
+use vbs::version::Version;
+
+use crate::{
+    drb::DrbComputation, events::HotShotEvent, quorum_proposal_recv::ValidationInfo,
+    request::REQUEST_TIMEOUT,
+};
+
+/// How long to wait for a missing proposal from the leader who made it, before asking other peers
+const PROPOSAL_FETCH_LEADER_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long to wait for a missing proposal from each other peer asked for it
+const PROPOSAL_FETCH_PEER_TIMEOUT: Duration = Duration::from_millis(150);
 
-use crate::{events::HotShotEvent, quorum_proposal_recv::ValidationInfo, request::REQUEST_TIMEOUT};
+/// How long to keep fetching a missing proposal before giving up
+const PROPOSAL_FETCH_DEADLINE: Duration = Duration::from_millis(800);
 
-/// Trigger a request to the network for a proposal for a view and wait for the response or timeout.
+/// Fetch the proposal certified by `qc`, which we never received, and wait for the response or
+/// timeout.
+///
+/// Once every node understands proposal fetch requests, the proposal is requested from the leader
+/// who made it first, since it certainly has it, and then from random peers in turn until one
+/// responds or the deadline passes. This recovers the proposal well before the view would time
+/// out. Before that, a single request is broadcast to all peers.
 #[instrument(skip_all)]
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
@@ -61,54 +91,36 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     membership_coordinator: EpochMembershipCoordinator<TYPES>,
     consensus: OuterConsensus<TYPES>,
     sender_public_key: TYPES::SignatureKey,
-    sender_private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    sender_signer: Signer<TYPES::SignatureKey>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
     epoch_height: u64,
 ) -> Result<(Leaf2<TYPES>, View<TYPES>)> {
     let view_number = qc.view_number();
-    let leaf_commit = qc.data.leaf_commit;
-    // We need to be able to sign this request before submitting it to the network. Compute the
-    // payload first.
-    let signed_proposal_request = ProposalRequestPayload {
-        view_number,
-        key: sender_public_key,
-    };
-
-    // Finally, compute the signature for the payload.
-    let signature = TYPES::SignatureKey::sign(
-        &sender_private_key,
-        signed_proposal_request.commit().as_ref(),
-    )
-    .wrap()
-    .context(error!("Failed to sign proposal. This should never happen."))?;
-
-    tracing::info!("Sending proposal request for view {}", view_number);
-
-    // First, broadcast that we need a proposal to the current leader
-    broadcast_event(
-        HotShotEvent::QuorumProposalRequestSend(signed_proposal_request, signature).into(),
-        &event_sender,
-    )
-    .await;
-
-    let mut rx = event_receiver.clone();
-    // Make a background task to await the arrival of the event data.
-    let Ok(Some(proposal)) =
-        // We want to explicitly timeout here so we aren't waiting around for the data.
-        timeout(REQUEST_TIMEOUT, async move {
-            // We want to iterate until the proposal is not None, or until we reach the timeout.
-            while let Ok(event) = rx.recv_direct().await {
-                if let HotShotEvent::QuorumProposalResponseRecv(quorum_proposal) = event.as_ref() {
-                    let leaf = Leaf2::from_quorum_proposal(&quorum_proposal.data);
-                    if leaf.view_number() == view_number && leaf.commit() == leaf_commit {
-                        return Some(quorum_proposal.clone());
-                    }
-                }
-            }
-            None
-        })
+    let proposal = if upgrade_lock
+        .features()
+        .enabled(Feature::ProposalFetch, view_number)
         .await
-    else {
+    {
+        fetch_proposal_from_peers(
+            qc,
+            &event_sender,
+            event_receiver,
+            &membership_coordinator,
+            sender_public_key,
+            &sender_signer,
+        )
+        .await?
+    } else {
+        request_proposal(
+            qc,
+            &event_sender,
+            event_receiver,
+            sender_public_key,
+            &sender_signer,
+        )
+        .await?
+    };
+    let Some(proposal) = proposal else {
         bail!("Request for proposal failed");
     };
 
@@ -139,7 +151,7 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     );
 
     if let Err(e) = consensus_writer.update_leaf(leaf.clone(), Arc::clone(&state), None) {
-        tracing::trace!("{e:?}");
+        e.log();
     }
     let view = View {
         view_inner: ViewInner::Leaf {
@@ -151,6 +163,137 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     };
     Ok((leaf, view))
 }
+
+/// Ask the leader who made the proposal certified by `qc`, then random peers in turn, for it.
+async fn fetch_proposal_from_peers<TYPES: NodeType>(
+    qc: &QuorumCertificate2<TYPES>,
+    event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
+    membership_coordinator: &EpochMembershipCoordinator<TYPES>,
+    sender_public_key: TYPES::SignatureKey,
+    sender_signer: &Signer<TYPES::SignatureKey>,
+) -> Result<Option<Proposal<TYPES, QuorumProposalWrapper<TYPES>>>> {
+    let view_number = qc.view_number();
+    let leaf_commit = qc.data.leaf_commit;
+    let deadline = tokio::time::Instant::now() + PROPOSAL_FETCH_DEADLINE;
+
+    // Subscribe before sending any request, so that no response is missed.
+    let mut rx = event_receiver;
+
+    let request = ProposalFetchRequestPayload {
+        view_number,
+        leaf_commit,
+        key: sender_public_key.clone(),
+    };
+    let signature = sender_signer
+        .sign(request.commit().as_ref())
+        .wrap()
+        .context(error!("Failed to sign proposal. This should never happen."))?;
+
+    let membership = membership_coordinator
+        .membership_for_epoch(qc.data.epoch())
+        .await?;
+    let leader = membership.leader(view_number).await?;
+    let mut peers = membership
+        .stake_table()
+        .await
+        .into_iter()
+        .map(|peer| peer.stake_table_entry.public_key())
+        .filter(|key| *key != leader && *key != sender_public_key)
+        .collect::<Vec<_>>();
+    peers.shuffle(&mut rand::thread_rng());
+
+    let attempts = std::iter::once((leader, PROPOSAL_FETCH_LEADER_TIMEOUT)).chain(
+        peers
+            .into_iter()
+            .map(|peer| (peer, PROPOSAL_FETCH_PEER_TIMEOUT)),
+    );
+    for (peer, wait) in attempts {
+        tracing::info!(%peer, "Fetching proposal for view {view_number}");
+        broadcast_event(
+            HotShotEvent::QuorumProposalFetchSend(request.clone(), signature.clone(), peer).into(),
+            event_sender,
+        )
+        .await;
+
+        // A late response from a peer asked earlier is just as good, so keep listening for any.
+        let attempt_deadline = (tokio::time::Instant::now() + wait).min(deadline);
+        match timeout_at(
+            attempt_deadline,
+            wait_for_proposal(&mut rx, view_number, leaf_commit),
+        )
+        .await
+        {
+            // Either the proposal arrived, or the event stream closed and it never will.
+            Ok(response) => return Ok(response),
+            Err(_) if attempt_deadline >= deadline => break,
+            Err(_) => {},
+        }
+    }
+    Ok(None)
+}
+
+/// Broadcast a request for the proposal certified by `qc` to all peers.
+///
+/// Nodes which do not understand proposal fetch requests yet only answer these.
+async fn request_proposal<TYPES: NodeType>(
+    qc: &QuorumCertificate2<TYPES>,
+    event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
+    sender_public_key: TYPES::SignatureKey,
+    sender_signer: &Signer<TYPES::SignatureKey>,
+) -> Result<Option<Proposal<TYPES, QuorumProposalWrapper<TYPES>>>> {
+    let view_number = qc.view_number();
+    // We need to be able to sign this request before submitting it to the network. Compute the
+    // payload first.
+    let signed_proposal_request = ProposalRequestPayload {
+        view_number,
+        key: sender_public_key,
+    };
+
+    // Finally, compute the signature for the payload.
+    let signature = sender_signer
+        .sign(signed_proposal_request.commit().as_ref())
+        .wrap()
+        .context(error!("Failed to sign proposal. This should never happen."))?;
+
+    // Subscribe before sending the request, so that the response is not missed.
+    let mut rx = event_receiver;
+
+    tracing::info!("Sending proposal request for view {}", view_number);
+
+    broadcast_event(
+        HotShotEvent::QuorumProposalRequestSend(signed_proposal_request, signature).into(),
+        event_sender,
+    )
+    .await;
+
+    // We want to explicitly timeout here so we aren't waiting around for the data.
+    Ok(timeout(
+        REQUEST_TIMEOUT,
+        wait_for_proposal(&mut rx, view_number, qc.data.leaf_commit),
+    )
+    .await
+    .ok()
+    .flatten())
+}
+
+/// Wait for a response with the proposal for `view_number` certifying `leaf_commit`.
+async fn wait_for_proposal<TYPES: NodeType>(
+    rx: &mut Receiver<Arc<HotShotEvent<TYPES>>>,
+    view_number: TYPES::View,
+    leaf_commit: Commitment<Leaf2<TYPES>>,
+) -> Option<Proposal<TYPES, QuorumProposalWrapper<TYPES>>> {
+    while let Ok(event) = rx.recv_direct().await {
+        if let HotShotEvent::QuorumProposalResponseRecv(quorum_proposal) = event.as_ref() {
+            let leaf = Leaf2::from_quorum_proposal(&quorum_proposal.data);
+            if leaf.view_number() == view_number && leaf.commit() == leaf_commit {
+                return Some(quorum_proposal.clone());
+            }
+        }
+    }
+    None
+}
 pub async fn handle_drb_result<TYPES: NodeType, I: NodeImplementation<TYPES>>(
     membership: &Arc<RwLock<TYPES::Membership>>,
     epoch: TYPES::Epoch,
@@ -173,28 +316,6 @@ pub async fn handle_drb_result<TYPES: NodeType, I: NodeImplementation<TYPES>>(
 
     membership.write().await.add_drb_result(epoch, drb_result)
 }
-/// Start the DRB computation task for the next epoch.
-fn start_drb_task<TYPES: NodeType, I: NodeImplementation<TYPES>>(
-    seed: DrbSeedInput,
-    epoch: TYPES::Epoch,
-    membership: &Arc<RwLock<TYPES::Membership>>,
-    storage: &Arc<RwLock<I::Storage>>,
-    consensus: &OuterConsensus<TYPES>,
-) {
-    let membership = membership.clone();
-    let storage = storage.clone();
-    let consensus = consensus.clone();
-    tokio::spawn(async move {
-        let drb_result = tokio::task::spawn_blocking(move || {
-            hotshot_types::drb::compute_drb_result::<TYPES>(seed)
-        })
-        .await
-        .unwrap();
-
-        handle_drb_result::<TYPES, I>(&membership, epoch, &storage, &consensus, drb_result).await;
-        drb_result
-    });
-}
 /// Handles calling add_epoch_root and sync_l1 on Membership if necessary.
 async fn decide_epoch_root<TYPES: NodeType, I: NodeImplementation<TYPES>>(
     decided_leaf: &Leaf2<TYPES>,
@@ -251,13 +372,15 @@ async fn decide_epoch_root<TYPES: NodeType, I: NodeImplementation<TYPES>>(
         let len = drb_seed_input_vec.len().min(32);
         drb_seed_input[..len].copy_from_slice(&drb_seed_input_vec[..len]);
 
-        start_drb_task::<TYPES, I>(
-            drb_seed_input,
+        DrbComputation::<TYPES, I>::new(
             next_epoch_number,
+            drb_seed_input,
             membership,
             storage,
             consensus,
-        );
+        )
+        .spawn()
+        .await;
     }
 }
 
@@ -593,7 +716,7 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
     event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
     membership: EpochMembershipCoordinator<TYPES>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Signer<TYPES::SignatureKey>,
     consensus: OuterConsensus<TYPES>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
     parent_qc: &QuorumCertificate2<TYPES>,
@@ -613,7 +736,7 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
             membership,
             consensus.clone(),
             public_key.clone(),
-            private_key.clone(),
+            signer.clone(),
             upgrade_lock,
             epoch_height,
         )
@@ -833,9 +956,9 @@ pub async fn validate_proposal_safety_and_liveness<
     let mut valid_epoch_transition = false;
     if validation_info
         .upgrade_lock
-        .version(proposal.data.justify_qc().view_number())
+        .features()
+        .enabled(Feature::Epochs, proposal.data.justify_qc().view_number())
         .await
-        .is_ok_and(|v| v >= V::Epochs::VERSION)
     {
         let Some(block_number) = proposal.data.justify_qc().data.block_number else {
             bail!("Quorum Proposal has no block number but it's after the epoch upgrade");
@@ -867,13 +990,13 @@ pub async fn validate_proposal_safety_and_liveness<
     {
         let mut consensus_writer = validation_info.consensus.write().await;
         if let Err(e) = consensus_writer.update_leaf(proposed_leaf.clone(), state, None) {
-            tracing::trace!("{e:?}");
+            e.log();
         }
 
         // Update our internal storage of the proposal. The proposal is valid, so
         // we swallow this error and just log if it occurs.
         if let Err(e) = consensus_writer.update_proposed_view(proposal.clone()) {
-            tracing::debug!("Internal proposal update failed; error = {e:#}");
+            e.log();
         };
     }
 
@@ -950,14 +1073,7 @@ pub async fn validate_proposal_safety_and_liveness<
 
         ensure!(safety_check || liveness_check, {
             if let Err(e) = outcome {
-                broadcast_event(
-                    Event {
-                        view_number,
-                        event: EventType::Error { error: Arc::new(e) },
-                    },
-                    &validation_info.output_event_stream,
-                )
-                .await;
+                broadcast_error(view_number, e, &validation_info.output_event_stream).await;
             }
 
             error!("Failed safety and liveness check \n High QC is {:?}  Proposal QC is {:?}  Locked view is {:?}", consensus_reader.high_qc(), proposal.data.clone(), consensus_reader.locked_view())
@@ -1093,7 +1209,11 @@ pub(crate) async fn validate_proposal_view_and_certs<
 }
 
 /// Helper function to send events and log errors
-pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Sender<E>) {
+pub async fn broadcast_event<E: Clone + std::fmt::Debug + Timestamped>(
+    event: E,
+    sender: &Sender<E>,
+) {
+    event.record_broadcast();
     match sender.broadcast_direct(event).await {
         Ok(None) => (),
         Ok(Some(overflowed)) => {
@@ -1107,6 +1227,24 @@ pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Send
     }
 }
 
+/// Report `error` to the application in an error event for `view_number`
+pub async fn broadcast_error<TYPES: NodeType>(
+    view_number: TYPES::View,
+    error: HotShotError<TYPES>,
+    output_event_stream: &Sender<Event<TYPES>>,
+) {
+    broadcast_event(
+        Event {
+            view_number,
+            event: EventType::Error {
+                error: Arc::new(error),
+            },
+        },
+        output_event_stream,
+    )
+    .await;
+}
+
 /// Gets the next epoch QC corresponding to this epoch QC from the shared consensus state;
 /// if it's not yet available, waits for it with a given timeout.
 pub async fn wait_for_next_epoch_qc<TYPES: NodeType>(
@@ -1171,26 +1309,23 @@ pub async fn validate_qc_and_next_epoch_qc<TYPES: NodeType, V: Versions>(
     upgrade_lock: &UpgradeLock<TYPES, V>,
     epoch_height: u64,
 ) -> Result<()> {
-    let mut epoch_membership = membership_coordinator
+    let certificates =
+        EpochQuorumCertificate::new(qc.clone(), maybe_next_epoch_qc.cloned(), epoch_height)?;
+
+    let epoch_membership = membership_coordinator
         .membership_for_epoch(qc.data.epoch)
         .await?;
 
-    let membership_stake_table = epoch_membership.stake_table().await;
-    let membership_success_threshold = epoch_membership.success_threshold().await;
-
     {
         let consensus_reader = consensus.read().await;
-        qc.is_valid_cert(
-            StakeTableEntries::<TYPES>::from(membership_stake_table).0,
-            membership_success_threshold,
-            upgrade_lock,
-        )
-        .await
-        .context(|e| {
-            consensus_reader.metrics.invalid_qc.update(1);
+        certificates
+            .is_valid(&epoch_membership, upgrade_lock)
+            .await
+            .context(|e| {
+                consensus_reader.metrics.invalid_qc.update(1);
 
-            warn!("Invalid certificate: {e}")
-        })?;
+                warn!("Invalid certificate: {e}")
+            })?;
     }
 
     if upgrade_lock.epochs_enabled(qc.view_number()).await {
@@ -1201,36 +1336,6 @@ pub async fn validate_qc_and_next_epoch_qc<TYPES: NodeType, V: Versions>(
         );
     }
 
-    if qc
-        .data
-        .block_number
-        .is_some_and(|b| is_epoch_transition(b, epoch_height))
-    {
-        ensure!(
-            maybe_next_epoch_qc.is_some(),
-            error!("Received High QC for the transition block but not the next epoch QC")
-        );
-    }
-
-    if let Some(next_epoch_qc) = maybe_next_epoch_qc {
-        // If the next epoch qc exists, make sure it's equal to the qc
-        if qc.view_number() != next_epoch_qc.view_number() || qc.data != *next_epoch_qc.data {
-            bail!("Next epoch qc exists but it's not equal with qc.");
-        }
-        epoch_membership = epoch_membership.next_epoch_stake_table().await?;
-        let membership_next_stake_table = epoch_membership.stake_table().await;
-        let membership_next_success_threshold = epoch_membership.success_threshold().await;
-
-        // Validate the next epoch qc as well
-        next_epoch_qc
-            .is_valid_cert(
-                StakeTableEntries::<TYPES>::from(membership_next_stake_table).0,
-                membership_next_success_threshold,
-                upgrade_lock,
-            )
-            .await
-            .context(|e| warn!("Invalid next epoch certificate: {e}"))?;
-    }
     Ok(())
 }
 
@@ -1289,3 +1394,127 @@ pub(crate) fn check_qc_state_cert_correspondence<TYPES: NodeType>(
         && Some(state_cert.epoch) == qc.data.epoch()
         && qc.view_number().u64() == state_cert.light_client_state.view_number
 }
+
+/// Default number of VID dispersals kept around for reuse by [`VidDisperseCache`]
+pub const VID_DISPERSE_CACHE_SIZE: usize = 16;
+
+/// Key identifying a VID dispersal which can be reused across views
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct VidDisperseCacheKey<TYPES: NodeType> {
+    /// Commitment to the payload and its metadata
+    payload_commitment: BuilderCommitment,
+    /// Number of storage nodes the payload was dispersed to
+    num_nodes: usize,
+    /// Epoch of the recipients of the shares
+    target_epoch: Option<TYPES::Epoch>,
+    /// Epoch the payload belongs to
+    data_epoch: Option<TYPES::Epoch>,
+    /// Version the dispersal was calculated for, determines the VID scheme
+    version: Version,
+}
+
+/// Bounded cache of previously calculated VID dispersals.
+///
+/// When a view times out and the same payload gets proposed again, the leader
+/// can reuse the dispersal instead of recomputing it from scratch.
+pub struct VidDisperseCache<TYPES: NodeType> {
+    /// Cached dispersals, the view number is overwritten on reuse
+    cache: LruCache<VidDisperseCacheKey<TYPES>, VidDisperse<TYPES>>,
+}
+
+impl<TYPES: NodeType> VidDisperseCache<TYPES> {
+    /// Create a new cache holding at most `capacity` dispersals
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            cache: LruCache::new(capacity),
+        }
+    }
+
+    /// Number of dispersals currently cached
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+impl<TYPES: NodeType> Default for VidDisperseCache<TYPES> {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(VID_DISPERSE_CACHE_SIZE).unwrap())
+    }
+}
+
+/// Calculate the VID dispersal for `payload`, reusing a previous computation for the
+/// same payload and recipients if one is present in `cache`.
+///
+/// The time spent on a fresh calculation is recorded in `metrics`. Returns `None` if `cancelled`
+/// is set before the dispersal is ready; a calculation which this stops part way is counted in
+/// `metrics` as cancelled.
+///
+/// # Errors
+/// Returns an error if the membership lookup or the disperse calculation fails
+#[allow(clippy::too_many_arguments)]
+pub async fn calculate_vid_disperse<TYPES: NodeType, V: Versions>(
+    cache: &Mutex<VidDisperseCache<TYPES>>,
+    payload: &TYPES::BlockPayload,
+    membership: &EpochMembershipCoordinator<TYPES>,
+    view: TYPES::View,
+    target_epoch: Option<TYPES::Epoch>,
+    data_epoch: Option<TYPES::Epoch>,
+    metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    metrics: &ConsensusMetricsValue,
+    cancelled: Arc<AtomicBool>,
+) -> Result<Option<VidDisperse<TYPES>>> {
+    let num_nodes = membership
+        .membership_for_epoch(target_epoch)
+        .await?
+        .total_nodes()
+        .await;
+    let key = VidDisperseCacheKey {
+        payload_commitment: payload.builder_commitment(metadata),
+        num_nodes,
+        target_epoch,
+        data_epoch,
+        version: upgrade_lock.version_infallible(view).await,
+    };
+
+    if let Some(cached) = cache.lock().await.cache.get(&key) {
+        tracing::debug!("Reusing cached VID disperse for view {view:?}");
+        let mut vid_disperse = cached.clone();
+        vid_disperse.set_view_number(view);
+        return Ok(Some(vid_disperse));
+    }
+
+    let start = Instant::now();
+    let Some(vid_disperse) = VidDisperse::calculate_vid_disperse_cancellable::<V>(
+        payload,
+        membership,
+        view,
+        target_epoch,
+        data_epoch,
+        metadata,
+        upgrade_lock,
+        Arc::clone(&cancelled),
+    )
+    .await?
+    else {
+        tracing::debug!("Stopped the VID disperse calculation for view {view:?}");
+        metrics.number_of_cancelled_vid_disperse.add(1);
+        return Ok(None);
+    };
+    metrics
+        .vid_disperse_duration
+        .add_point(start.elapsed().as_secs_f64());
+    cache.lock().await.cache.put(key, vid_disperse.clone());
+
+    // The calculation finished before it was cancelled, so it is kept in the cache, but the
+    // dispersal is no longer wanted.
+    if cancelled.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+    Ok(Some(vid_disperse))
+}
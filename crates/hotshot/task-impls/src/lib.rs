@@ -40,13 +40,22 @@ pub mod vote_collection;
 /// Task for handling upgrades
 pub mod upgrade;
 
+/// Translation of consensus messages between protocol versions
+pub mod message_compat;
+
 /// Implementations for builder client
 /// Should contain builder task in the future
 pub mod builder;
 
+/// Validation of blocks and bundles claimed from builders
+pub mod bundle_validation;
+
 /// Helper functions used by any task
 pub mod helpers;
 
+/// Task for computing the DRB results of upcoming epochs
+pub mod drb;
+
 /// Task which responses to requests from the network
 pub mod response;
 
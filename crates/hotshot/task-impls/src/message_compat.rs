@@ -0,0 +1,337 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Translation of consensus messages between protocol versions.
+//!
+//! Consensus messages changed format when epochs were introduced, and each message in the old
+//! format has a counterpart in the new one (`DaData` and `DaData2`, `QuorumProposal` and
+//! `QuorumProposal2`, and so on). Every message received from the network goes through the
+//! [`MessageTranslator`], which up-converts old messages to the new format used internally, and
+//! rejects messages whose format does not match the version in effect in their view.
+
+use hotshot_types::{
+    message::{
+        convert_proposal, DaConsensusMessage, GeneralConsensusMessage, SequencingMessage,
+        UpgradeLock,
+    },
+    traits::node_implementation::{NodeType, Versions},
+    vote::HasViewNumber,
+};
+
+use crate::events::HotShotEvent;
+
+/// Translates consensus messages to the format used internally, according to the version in
+/// effect in their view.
+pub struct MessageTranslator<'a, TYPES: NodeType, V: Versions> {
+    /// Lock holding the decided upgrade, which determines the version in effect in each view
+    upgrade_lock: &'a UpgradeLock<TYPES, V>,
+}
+
+impl<'a, TYPES: NodeType, V: Versions> MessageTranslator<'a, TYPES, V> {
+    /// A translator following the upgrade decided in `upgrade_lock`.
+    pub fn new(upgrade_lock: &'a UpgradeLock<TYPES, V>) -> Self {
+        Self { upgrade_lock }
+    }
+
+    /// Whether a message in the format from before epochs is accepted for `view`.
+    pub async fn accepts_legacy(&self, view: TYPES::View) -> bool {
+        !self.upgrade_lock.epochs_enabled(view).await
+    }
+
+    /// Whether a message in the current format is accepted for `view`.
+    pub async fn accepts_current(&self, view: TYPES::View) -> bool {
+        self.upgrade_lock.epochs_enabled(view).await
+    }
+
+    /// Check that a message in the format from before epochs is accepted for `view`.
+    async fn legacy(&self, kind: &str, view: TYPES::View) -> Option<()> {
+        if self.accepts_legacy(view).await {
+            Some(())
+        } else {
+            tracing::warn!("received {kind} for view {view} but epochs are enabled for that view");
+            None
+        }
+    }
+
+    /// Check that a message in the current format is accepted for `view`.
+    async fn current(&self, kind: &str, view: TYPES::View) -> Option<()> {
+        if self.accepts_current(view).await {
+            Some(())
+        } else {
+            tracing::warn!(
+                "received {kind} for view {view} but epochs are not enabled for that view"
+            );
+            None
+        }
+    }
+
+    /// Translate a consensus message from `sender` into the event for the tasks which handle it.
+    ///
+    /// Returns `None` if the message is not in a format accepted for its view.
+    #[allow(clippy::too_many_lines)]
+    pub async fn translate(
+        &self,
+        message: SequencingMessage<TYPES>,
+        sender: TYPES::SignatureKey,
+    ) -> Option<HotShotEvent<TYPES>> {
+        let event = match message {
+            SequencingMessage::General(general_message) => match general_message {
+                GeneralConsensusMessage::Proposal(proposal) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::Proposal",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::QuorumProposalRecv(convert_proposal(proposal), sender)
+                },
+                GeneralConsensusMessage::Proposal2(proposal) => {
+                    self.current(
+                        "GeneralConsensusMessage::Proposal2",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::QuorumProposalRecv(convert_proposal(proposal), sender)
+                },
+                GeneralConsensusMessage::ProposalRequested(req, sig) => {
+                    HotShotEvent::QuorumProposalRequestRecv(req, sig)
+                },
+                GeneralConsensusMessage::ViewEvidenceRequested(req, sig) => {
+                    HotShotEvent::ViewEvidenceRequestRecv(req, sig)
+                },
+                GeneralConsensusMessage::ViewEvidenceResponse(evidence) => {
+                    HotShotEvent::ViewEvidenceResponseRecv(evidence)
+                },
+                GeneralConsensusMessage::ProposalFetchRequested(req, sig) => {
+                    HotShotEvent::QuorumProposalFetchRecv(req, sig)
+                },
+                GeneralConsensusMessage::ProposalFetchResponse(proposal) => {
+                    HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
+                },
+                GeneralConsensusMessage::ProposalResponse(proposal) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ProposalResponse",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
+                },
+                GeneralConsensusMessage::ProposalResponse2(proposal) => {
+                    self.current(
+                        "GeneralConsensusMessage::ProposalResponse2",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
+                },
+                GeneralConsensusMessage::Vote(vote) => {
+                    self.legacy("GeneralConsensusMessage::Vote", vote.view_number())
+                        .await?;
+                    HotShotEvent::QuorumVoteRecv(vote.to_vote2())
+                },
+                GeneralConsensusMessage::Vote2(vote) => {
+                    self.current("GeneralConsensusMessage::Vote2", vote.view_number())
+                        .await?;
+                    HotShotEvent::QuorumVoteRecv(vote)
+                },
+                GeneralConsensusMessage::ViewSyncPreCommitVote(vote) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncPreCommitVote",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncPreCommitVoteRecv(vote.to_vote2())
+                },
+                GeneralConsensusMessage::ViewSyncPreCommitVote2(vote) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncPreCommitVote2",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncPreCommitVoteRecv(vote)
+                },
+                GeneralConsensusMessage::ViewSyncPreCommitCertificate(cert) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncPreCommitCertificate",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncPreCommitCertificateRecv(cert.to_vsc2())
+                },
+                GeneralConsensusMessage::ViewSyncPreCommitCertificate2(cert) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncPreCommitCertificate2",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncPreCommitCertificateRecv(cert)
+                },
+                GeneralConsensusMessage::ViewSyncCommitVote(vote) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncCommitVote",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncCommitVoteRecv(vote.to_vote2())
+                },
+                GeneralConsensusMessage::ViewSyncCommitVote2(vote) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncCommitVote2",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncCommitVoteRecv(vote)
+                },
+                GeneralConsensusMessage::ViewSyncCommitCertificate(cert) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncCommitCertificate",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncCommitCertificateRecv(cert.to_vsc2())
+                },
+                GeneralConsensusMessage::ViewSyncCommitCertificate2(cert) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncCommitCertificate2",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncCommitCertificateRecv(cert)
+                },
+                GeneralConsensusMessage::ViewSyncFinalizeVote(vote) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncFinalizeVote",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncFinalizeVoteRecv(vote.to_vote2())
+                },
+                GeneralConsensusMessage::ViewSyncFinalizeVote2(vote) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncFinalizeVote2",
+                        vote.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncFinalizeVoteRecv(vote)
+                },
+                GeneralConsensusMessage::ViewSyncFinalizeCertificate(cert) => {
+                    self.legacy(
+                        "GeneralConsensusMessage::ViewSyncFinalizeCertificate",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncFinalizeCertificateRecv(cert.to_vsc2())
+                },
+                GeneralConsensusMessage::ViewSyncFinalizeCertificate2(cert) => {
+                    self.current(
+                        "GeneralConsensusMessage::ViewSyncFinalizeCertificate2",
+                        cert.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::ViewSyncFinalizeCertificateRecv(cert)
+                },
+                GeneralConsensusMessage::TimeoutVote(vote) => {
+                    self.legacy("GeneralConsensusMessage::TimeoutVote", vote.view_number())
+                        .await?;
+                    HotShotEvent::TimeoutVoteRecv(vote.to_vote2())
+                },
+                GeneralConsensusMessage::TimeoutVote2(vote) => {
+                    self.current("GeneralConsensusMessage::TimeoutVote2", vote.view_number())
+                        .await?;
+                    HotShotEvent::TimeoutVoteRecv(vote)
+                },
+                GeneralConsensusMessage::UpgradeProposal(message) => {
+                    HotShotEvent::UpgradeProposalRecv(message, sender)
+                },
+                GeneralConsensusMessage::UpgradeVote(message) => {
+                    tracing::error!("Received upgrade vote!");
+                    HotShotEvent::UpgradeVoteRecv(message)
+                },
+                GeneralConsensusMessage::HighQc(qc, next_qc) => {
+                    HotShotEvent::HighQcRecv(qc, next_qc, sender)
+                },
+                GeneralConsensusMessage::ExtendedQc(qc, next_epoch_qc) => {
+                    HotShotEvent::ExtendedQcRecv(qc, next_epoch_qc, sender)
+                },
+                GeneralConsensusMessage::EpochRootQuorumVote(vote) => {
+                    self.current("GeneralConsensusMessage::EpochRootVote", vote.view_number())
+                        .await?;
+                    HotShotEvent::EpochRootQuorumVoteRecv(vote)
+                },
+                GeneralConsensusMessage::EpochRootQc(root_qc) => {
+                    self.current(
+                        "GeneralConsensusMessage::EpochRootQc",
+                        root_qc.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::EpochRootQcRecv(root_qc, sender)
+                },
+            },
+            SequencingMessage::Da(da_message) => match da_message {
+                DaConsensusMessage::DaProposal(proposal) => {
+                    self.legacy(
+                        "DaConsensusMessage::DaProposal",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::DaProposalRecv(convert_proposal(proposal), sender)
+                },
+                DaConsensusMessage::DaProposal2(proposal) => {
+                    self.current(
+                        "DaConsensusMessage::DaProposal2",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::DaProposalRecv(proposal, sender)
+                },
+                DaConsensusMessage::DaPayloadHint2(proposal) => {
+                    self.current(
+                        "DaConsensusMessage::DaPayloadHint2",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::DaPayloadHintRecv(proposal, sender)
+                },
+                DaConsensusMessage::DaVote(vote) => {
+                    self.legacy("DaConsensusMessage::DaVote", vote.view_number())
+                        .await?;
+                    HotShotEvent::DaVoteRecv(vote.to_vote2())
+                },
+                DaConsensusMessage::DaVote2(vote) => {
+                    self.current("DaConsensusMessage::DaVote2", vote.view_number())
+                        .await?;
+                    HotShotEvent::DaVoteRecv(vote)
+                },
+                DaConsensusMessage::DaCertificate(cert) => {
+                    self.legacy("DaConsensusMessage::DaCertificate", cert.view_number())
+                        .await?;
+                    HotShotEvent::DaCertificateRecv(cert.to_dac2())
+                },
+                DaConsensusMessage::DaCertificate2(cert) => {
+                    self.current("DaConsensusMessage::DaCertificate2", cert.view_number())
+                        .await?;
+                    HotShotEvent::DaCertificateRecv(cert)
+                },
+                DaConsensusMessage::VidDisperseMsg(proposal) => {
+                    self.legacy(
+                        "DaConsensusMessage::VidDisperseMsg",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::VidShareRecv(sender, convert_proposal(proposal))
+                },
+                DaConsensusMessage::VidDisperseMsg2(proposal) => {
+                    self.current(
+                        "DaConsensusMessage::VidDisperseMsg2",
+                        proposal.data.view_number(),
+                    )
+                    .await?;
+                    HotShotEvent::VidShareRecv(sender, convert_proposal(proposal))
+                },
+            },
+        };
+        Some(event)
+    }
+}
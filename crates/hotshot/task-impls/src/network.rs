@@ -5,18 +5,24 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::HashMap,
     hash::{DefaultHasher, Hash, Hasher},
     sync::Arc,
+    time::Duration,
 };
 
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use futures::future::join_all;
+use hotshot_task::{supervisor::TaskSupervisor, task::TaskState};
 use hotshot_types::{
+    audit::{audit_received, audit_sent, MessageAuditSlot},
+    bandwidth::{BandwidthAccounting, TrafficClass},
+    compression::MessageCompression,
     consensus::OuterConsensus,
-    data::{VidDisperse, VidDisperseShare},
+    constants::VID_SHARE_CUSTODIANS,
+    data::{vid_disperse::vid_share_custodians, VidDisperse, VidDisperseShare},
     epoch_membership::EpochMembershipCoordinator,
     event::{Event, EventType, HotShotAction},
     message::{
@@ -25,6 +31,7 @@ use hotshot_types::{
     },
     simple_vote::HasEpoch,
     traits::{
+        block_contents::Transaction,
         network::{
             BroadcastDelay, ConnectedNetwork, RequestKind, ResponseMessage, Topic, TransmitType,
             ViewMessage,
@@ -35,12 +42,13 @@ use hotshot_types::{
     vote::{HasViewNumber, Vote},
 };
 use hotshot_utils::anytrace::*;
-use tokio::{spawn, task::JoinHandle};
+use tokio::{spawn, time::sleep};
 use tracing::instrument;
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
+    message_compat::MessageTranslator,
 };
 
 /// the network message task state
@@ -60,9 +68,29 @@ pub struct NetworkMessageTaskState<TYPES: NodeType, V: Versions> {
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Audit log of the proposals and votes received, if the application set one
+    pub audit: MessageAuditSlot<TYPES>,
+
+    /// Membership, to check the signatures of the messages audited
+    pub membership_coordinator: EpochMembershipCoordinator<TYPES>,
 }
 
 impl<TYPES: NodeType, V: Versions> NetworkMessageTaskState<TYPES, V> {
+    /// Pass on a transaction submitted through a peer, unless we have seen it recently
+    async fn handle_submitted_transaction(&mut self, transaction: TYPES::Transaction) {
+        let mut hasher = DefaultHasher::new();
+        transaction.hash(&mut hasher);
+        if self.transactions_cache.put(hasher.finish(), ()).is_some() {
+            return;
+        }
+        broadcast_event(
+            Arc::new(HotShotEvent::TransactionsRecv(vec![transaction])),
+            &self.internal_event_stream,
+        )
+        .await;
+    }
+
     #[instrument(skip_all, name = "Network message task", level = "trace")]
     /// Handles a (deserialized) message from the network
     pub async fn handle_message(&mut self, message: Message<TYPES>) {
@@ -79,344 +107,24 @@ impl<TYPES: NodeType, V: Versions> NetworkMessageTaskState<TYPES, V> {
             },
         }
 
+        audit_received(
+            &self.audit,
+            &message,
+            &self.membership_coordinator,
+            &self.upgrade_lock,
+        )
+        .await;
+
         // Match the message kind and send the appropriate event to the internal event stream
         let sender = message.sender;
         match message.kind {
             // Handle consensus messages
             MessageKind::Consensus(consensus_message) => {
-                let event = match consensus_message {
-                    SequencingMessage::General(general_message) => match general_message {
-                        GeneralConsensusMessage::Proposal(proposal) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::Proposal for view {} but epochs are enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumProposalRecv(convert_proposal(proposal), sender)
-                        },
-                        GeneralConsensusMessage::Proposal2(proposal) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::Proposal2 for view {} but epochs are not enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumProposalRecv(convert_proposal(proposal), sender)
-                        },
-                        GeneralConsensusMessage::ProposalRequested(req, sig) => {
-                            HotShotEvent::QuorumProposalRequestRecv(req, sig)
-                        },
-                        GeneralConsensusMessage::ProposalResponse(proposal) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ProposalResponse for view {} but epochs are enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
-                        },
-                        GeneralConsensusMessage::ProposalResponse2(proposal) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ProposalResponse2 for view {} but epochs are not enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumProposalResponseRecv(convert_proposal(proposal))
-                        },
-                        GeneralConsensusMessage::Vote(vote) => {
-                            if self.upgrade_lock.epochs_enabled(vote.view_number()).await {
-                                tracing::warn!("received GeneralConsensusMessage::Vote for view {} but epochs are enabled for that view", vote.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumVoteRecv(vote.to_vote2())
-                        },
-                        GeneralConsensusMessage::Vote2(vote) => {
-                            if !self.upgrade_lock.epochs_enabled(vote.view_number()).await {
-                                tracing::warn!("received GeneralConsensusMessage::Vote2 for view {} but epochs are not enabled for that view", vote.view_number());
-                                return;
-                            }
-                            HotShotEvent::QuorumVoteRecv(vote)
-                        },
-                        GeneralConsensusMessage::ViewSyncPreCommitVote(view_sync_message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncPreCommitVote for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncPreCommitVoteRecv(view_sync_message.to_vote2())
-                        },
-                        GeneralConsensusMessage::ViewSyncPreCommitVote2(view_sync_message) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncPreCommitVote2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncPreCommitVoteRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::ViewSyncPreCommitCertificate(
-                            view_sync_message,
-                        ) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncPreCommitCertificate for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncPreCommitCertificateRecv(
-                                view_sync_message.to_vsc2(),
-                            )
-                        },
-                        GeneralConsensusMessage::ViewSyncPreCommitCertificate2(
-                            view_sync_message,
-                        ) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncPreCommitCertificate2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncPreCommitCertificateRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::ViewSyncCommitVote(view_sync_message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncCommitVote for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncCommitVoteRecv(view_sync_message.to_vote2())
-                        },
-                        GeneralConsensusMessage::ViewSyncCommitVote2(view_sync_message) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncCommitVote2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncCommitVoteRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::ViewSyncCommitCertificate(view_sync_message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncCommitCertificate for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncCommitCertificateRecv(view_sync_message.to_vsc2())
-                        },
-                        GeneralConsensusMessage::ViewSyncCommitCertificate2(view_sync_message) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncCommitCertificate2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncCommitCertificateRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::ViewSyncFinalizeVote(view_sync_message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncFinalizeVote for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncFinalizeVoteRecv(view_sync_message.to_vote2())
-                        },
-                        GeneralConsensusMessage::ViewSyncFinalizeVote2(view_sync_message) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncFinalizeVote2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncFinalizeVoteRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::ViewSyncFinalizeCertificate(view_sync_message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncFinalizeCertificate for view {} but epochs are enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncFinalizeCertificateRecv(
-                                view_sync_message.to_vsc2(),
-                            )
-                        },
-                        GeneralConsensusMessage::ViewSyncFinalizeCertificate2(
-                            view_sync_message,
-                        ) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(view_sync_message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::ViewSyncFinalizeCertificate2 for view {} but epochs are not enabled for that view", view_sync_message.view_number());
-                                return;
-                            }
-                            HotShotEvent::ViewSyncFinalizeCertificateRecv(view_sync_message)
-                        },
-                        GeneralConsensusMessage::TimeoutVote(message) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::TimeoutVote for view {} but epochs are enabled for that view", message.view_number());
-                                return;
-                            }
-                            HotShotEvent::TimeoutVoteRecv(message.to_vote2())
-                        },
-                        GeneralConsensusMessage::TimeoutVote2(message) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(message.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::TimeoutVote2 for view {} but epochs are not enabled for that view", message.view_number());
-                                return;
-                            }
-                            HotShotEvent::TimeoutVoteRecv(message)
-                        },
-                        GeneralConsensusMessage::UpgradeProposal(message) => {
-                            HotShotEvent::UpgradeProposalRecv(message, sender)
-                        },
-                        GeneralConsensusMessage::UpgradeVote(message) => {
-                            tracing::error!("Received upgrade vote!");
-                            HotShotEvent::UpgradeVoteRecv(message)
-                        },
-                        GeneralConsensusMessage::HighQc(qc, next_qc) => {
-                            HotShotEvent::HighQcRecv(qc, next_qc, sender)
-                        },
-                        GeneralConsensusMessage::ExtendedQc(qc, next_epoch_qc) => {
-                            HotShotEvent::ExtendedQcRecv(qc, next_epoch_qc, sender)
-                        },
-                        GeneralConsensusMessage::EpochRootQuorumVote(vote) => {
-                            if !self.upgrade_lock.epochs_enabled(vote.view_number()).await {
-                                tracing::warn!("received GeneralConsensusMessage::EpochRootVote for view {} but epochs are not enabled for that view", vote.view_number());
-                                return;
-                            }
-                            HotShotEvent::EpochRootQuorumVoteRecv(vote)
-                        },
-                        GeneralConsensusMessage::EpochRootQc(root_qc) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(root_qc.view_number())
-                                .await
-                            {
-                                tracing::warn!("received GeneralConsensusMessage::EpochRootQc for view {} but epochs are not enabled for that view", root_qc.view_number());
-                                return;
-                            }
-                            HotShotEvent::EpochRootQcRecv(root_qc, sender)
-                        },
-                    },
-                    SequencingMessage::Da(da_message) => match da_message {
-                        DaConsensusMessage::DaProposal(proposal) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received DaConsensusMessage::DaProposal for view {} but epochs are enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaProposalRecv(convert_proposal(proposal), sender)
-                        },
-                        DaConsensusMessage::DaProposal2(proposal) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received DaConsensusMessage::DaProposal2 for view {} but epochs are not enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaProposalRecv(proposal, sender)
-                        },
-                        DaConsensusMessage::DaVote(vote) => {
-                            if self.upgrade_lock.epochs_enabled(vote.view_number()).await {
-                                tracing::warn!("received DaConsensusMessage::DaVote for view {} but epochs are enabled for that view", vote.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaVoteRecv(vote.clone().to_vote2())
-                        },
-                        DaConsensusMessage::DaVote2(vote) => {
-                            if !self.upgrade_lock.epochs_enabled(vote.view_number()).await {
-                                tracing::warn!("received DaConsensusMessage::DaVote2 for view {} but epochs are not enabled for that view", vote.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaVoteRecv(vote.clone())
-                        },
-                        DaConsensusMessage::DaCertificate(cert) => {
-                            if self.upgrade_lock.epochs_enabled(cert.view_number()).await {
-                                tracing::warn!("received DaConsensusMessage::DaCertificate for view {} but epochs are enabled for that view", cert.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaCertificateRecv(cert.to_dac2())
-                        },
-                        DaConsensusMessage::DaCertificate2(cert) => {
-                            if !self.upgrade_lock.epochs_enabled(cert.view_number()).await {
-                                tracing::warn!("received DaConsensusMessage::DaCertificate2 for view {} but epochs are not enabled for that view", cert.view_number());
-                                return;
-                            }
-                            HotShotEvent::DaCertificateRecv(cert)
-                        },
-                        DaConsensusMessage::VidDisperseMsg(proposal) => {
-                            if self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received DaConsensusMessage::VidDisperseMsg for view {} but epochs are enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::VidShareRecv(sender, convert_proposal(proposal))
-                        },
-                        DaConsensusMessage::VidDisperseMsg2(proposal) => {
-                            if !self
-                                .upgrade_lock
-                                .epochs_enabled(proposal.data.view_number())
-                                .await
-                            {
-                                tracing::warn!("received DaConsensusMessage::VidDisperseMsg2 for view {} but epochs are not enabled for that view", proposal.data.view_number());
-                                return;
-                            }
-                            HotShotEvent::VidShareRecv(sender, convert_proposal(proposal))
-                        },
-                    },
+                let Some(event) = MessageTranslator::new(&self.upgrade_lock)
+                    .translate(consensus_message, sender)
+                    .await
+                else {
+                    return;
                 };
                 broadcast_event(Arc::new(event), &self.internal_event_stream).await;
             },
@@ -424,16 +132,11 @@ impl<TYPES: NodeType, V: Versions> NetworkMessageTaskState<TYPES, V> {
             // Handle data messages
             MessageKind::Data(message) => match message {
                 DataMessage::SubmitTransaction(transaction, _) => {
-                    let mut hasher = DefaultHasher::new();
-                    transaction.hash(&mut hasher);
-                    if self.transactions_cache.put(hasher.finish(), ()).is_some() {
-                        return;
-                    }
-                    broadcast_event(
-                        Arc::new(HotShotEvent::TransactionsRecv(vec![transaction])),
-                        &self.internal_event_stream,
-                    )
-                    .await;
+                    self.handle_submitted_transaction(transaction).await;
+                },
+                DataMessage::SubmitPrioritizedTransaction(transaction, priority, _) => {
+                    self.handle_submitted_transaction(transaction.with_priority(priority))
+                        .await;
                 },
                 DataMessage::DataResponse(response) => {
                     if let ResponseMessage::Found(message) = response {
@@ -523,8 +226,17 @@ pub struct NetworkEventTaskState<
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
 
-    /// map view number to transmit tasks
-    pub transmit_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+    /// Compression of the messages we send
+    pub compression: Arc<MessageCompression<TYPES::SignatureKey>>,
+
+    /// Bandwidth accounting and limits of the messages we send
+    pub bandwidth: Arc<BandwidthAccounting>,
+
+    /// Transmit tasks, keyed by view number
+    pub transmit_tasks: TaskSupervisor<TYPES::View>,
+
+    /// Audit log of the proposals and votes sent, if the application set one
+    pub audit: MessageAuditSlot<TYPES>,
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
@@ -585,6 +297,20 @@ impl<
         let epoch = vid_proposal.data.epoch();
         let vid_share_proposals = VidDisperseShare::to_vid_share_proposals(vid_proposal);
         let mut messages = HashMap::new();
+        // Copies of the shares for their custodians, which we cannot send with `vid_broadcast_message`
+        // since a custodian may hold several shares
+        let mut custodian_messages = vec![];
+        let stake_table = match self
+            .membership_coordinator
+            .membership_for_epoch(epoch)
+            .await
+        {
+            Ok(membership) => membership.stake_table().await,
+            Err(e) => {
+                tracing::warn!("Failed to get the stake table for VID share custodians: {e}");
+                vec![]
+            },
+        };
 
         for proposal in vid_share_proposals {
             let recipient = proposal.data.recipient_key().clone();
@@ -640,9 +366,32 @@ impl<
                     continue;
                 },
             };
+            for custodian in
+                vid_share_custodians(&stake_table, view, &recipient, VID_SHARE_CUSTODIANS)
+            {
+                if custodian != *sender {
+                    let serialized_message = self
+                        .compression
+                        .compress_direct(serialized_message.clone(), &custodian)
+                        .await;
+                    custodian_messages.push((custodian, serialized_message));
+                }
+            }
+            let serialized_message = self
+                .compression
+                .compress_direct(serialized_message, &recipient)
+                .await;
 
             messages.insert(recipient, serialized_message);
         }
+        let throttle = self.bandwidth.reserve_send(
+            TrafficClass::Vid,
+            messages
+                .values()
+                .chain(custodian_messages.iter().map(|(_, message)| message))
+                .map(Vec::len)
+                .sum(),
+        );
 
         let net = Arc::clone(&self.network);
         let storage = Arc::clone(&self.storage);
@@ -660,10 +409,22 @@ impl<
             {
                 return;
             }
+            sleep(throttle).await;
             match net.vid_broadcast_message(messages).await {
                 Ok(()) => {},
                 Err(e) => tracing::warn!("Failed to send message from network task: {e:?}"),
             }
+            // Send the copies for the custodians only once every recipient has been sent its own
+            // share
+            let results = join_all(
+                custodian_messages
+                    .into_iter()
+                    .map(|(custodian, message)| net.direct_message(message, custodian)),
+            )
+            .await;
+            for e in results.into_iter().filter_map(std::result::Result::err) {
+                tracing::debug!("Failed to send VID share to custodian: {e:?}");
+            }
         });
 
         None
@@ -703,19 +464,6 @@ impl<
         }
     }
 
-    /// Cancel all tasks for previous views
-    pub fn cancel_tasks(&mut self, view: TYPES::View) {
-        let keep = self.transmit_tasks.split_off(&view);
-
-        while let Some((_, tasks)) = self.transmit_tasks.pop_first() {
-            for task in tasks {
-                task.abort();
-            }
-        }
-
-        self.transmit_tasks = keep;
-    }
-
     /// Parses a `HotShotEvent` and returns a tuple of: (sender's public key, `MessageKind`, `TransmitType`)
     /// which will be used to create a message and transmit on the wire.
     /// Returns `None` if the parsing result should not be sent on the wire.
@@ -841,6 +589,34 @@ impl<
                 )),
                 TransmitType::Broadcast,
             )),
+            HotShotEvent::ViewEvidenceRequestSend(req, signature) => Some((
+                req.key.clone(),
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ViewEvidenceRequested(req, signature),
+                )),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::ViewEvidenceResponseSend(sender, to, evidence) => Some((
+                sender,
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ViewEvidenceResponse(evidence),
+                )),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::QuorumProposalFetchSend(req, signature, to) => Some((
+                req.key.clone(),
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ProposalFetchRequested(req, signature),
+                )),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::QuorumProposalFetchResponseSend(sender, to, proposal) => Some((
+                sender,
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
+                    GeneralConsensusMessage::ProposalFetchResponse(convert_proposal(proposal)),
+                )),
+                TransmitType::Direct(to),
+            )),
             HotShotEvent::QuorumProposalResponseSend(sender_key, proposal) => {
                 let message = if self
                     .upgrade_lock
@@ -885,6 +661,15 @@ impl<
 
                 Some((sender, message, TransmitType::DaCommitteeBroadcast))
             },
+            HotShotEvent::DaPayloadHintSend(proposal, sender) => {
+                *maybe_action = Some(HotShotAction::DaPropose);
+
+                let message = MessageKind::<TYPES>::from_consensus_message(SequencingMessage::Da(
+                    DaConsensusMessage::DaPayloadHint2(proposal),
+                ));
+
+                Some((sender, message, TransmitType::DaCommitteeBroadcast))
+            },
             HotShotEvent::DaVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::DaVote);
                 let view_number = vote.view_number();
@@ -1128,7 +913,7 @@ impl<
                     self.epoch = epoch;
                 }
                 let keep_view = TYPES::View::new(view.saturating_sub(1));
-                self.cancel_tasks(keep_view);
+                self.transmit_tasks.cancel_before(&keep_view);
                 let net = Arc::clone(&self.network);
                 let epoch = self.epoch.map(|x| x.u64());
                 let membership_coordinator = self.membership_coordinator.clone();
@@ -1222,6 +1007,26 @@ impl<
         maybe_action: Option<HotShotAction>,
         transmit: TransmitType<TYPES>,
         sender: TYPES::SignatureKey,
+    ) {
+        self.spawn_transmit_task_after(
+            Duration::ZERO,
+            message_kind,
+            maybe_action,
+            transmit,
+            sender,
+        )
+        .await;
+    }
+
+    /// Creates a network message and spawns a task that transmits it on the wire once `delay`
+    /// has passed.
+    async fn spawn_transmit_task_after(
+        &mut self,
+        delay: Duration,
+        message_kind: MessageKind<TYPES>,
+        maybe_action: Option<HotShotAction>,
+        transmit: TransmitType<TYPES>,
+        sender: TYPES::SignatureKey,
     ) {
         let broadcast_delay = match &message_kind {
             MessageKind::Consensus(
@@ -1249,6 +1054,9 @@ impl<
         let storage = Arc::clone(&self.storage);
         let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
         let upgrade_lock = self.upgrade_lock.clone();
+        let compression = Arc::clone(&self.compression);
+        let bandwidth = Arc::clone(&self.bandwidth);
+        let audit = Arc::clone(&self.audit);
         let handle = spawn(async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 maybe_action,
@@ -1276,6 +1084,7 @@ impl<
                     return;
                 }
             }
+            audit_sent(&audit, &message);
 
             let serialized_message = match upgrade_lock.serialize(&message).await {
                 Ok(serialized) => serialized,
@@ -1285,6 +1094,24 @@ impl<
                 },
             };
 
+            let serialized_message = match &transmit {
+                TransmitType::Direct(recipient) => {
+                    compression
+                        .compress_direct(serialized_message, recipient)
+                        .await
+                },
+                TransmitType::Broadcast | TransmitType::DaCommitteeBroadcast => {
+                    compression.compress_broadcast(serialized_message)
+                },
+            };
+            let throttle =
+                bandwidth.reserve_send(TrafficClass::of(&message.kind), serialized_message.len());
+
+            let delay = delay.max(throttle);
+            if !delay.is_zero() {
+                sleep(delay).await;
+            }
+
             let transmit_result = match transmit {
                 TransmitType::Direct(recipient) => {
                     network.direct_message(serialized_message, recipient).await
@@ -1310,10 +1137,7 @@ impl<
                 Err(e) => tracing::warn!("Failed to send message task: {e:?}"),
             }
         });
-        self.transmit_tasks
-            .entry(view_number)
-            .or_default()
-            .push(handle);
+        self.transmit_tasks.register(view_number, handle);
     }
 }
 
@@ -1324,8 +1148,8 @@ pub mod test {
     use async_trait::async_trait;
 
     use super::{
-        Arc, ConnectedNetwork, HotShotEvent, MessageKind, NetworkEventTaskState, NodeType,
-        Receiver, Result, Sender, Storage, TaskState, TransmitType, Versions,
+        Arc, ConnectedNetwork, Duration, HotShotEvent, MessageKind, NetworkEventTaskState,
+        NodeType, Receiver, Result, Sender, Storage, TaskState, TransmitType, Versions,
     };
 
     /// A dynamic type alias for a function that takes the result of `NetworkEventTaskState::parse_event`
@@ -1338,6 +1162,20 @@ pub mod test {
         ) + Send
         + Sync;
 
+    /// A dynamic type alias for a function that takes the result of `NetworkEventTaskState::parse_event`
+    /// and returns the transmissions to make in its place, each with a delay to wait before sending.
+    ///
+    /// Returning no transmissions drops the message. The epoch passed is the current epoch of the task.
+    pub type TransmitPlanner<TYPES> = dyn Fn(
+            &<TYPES as NodeType>::SignatureKey,
+            &MessageKind<TYPES>,
+            TransmitType<TYPES>,
+            &<TYPES as NodeType>::Membership,
+            Option<<TYPES as NodeType>::Epoch>,
+        ) -> Vec<(TransmitType<TYPES>, Duration)>
+        + Send
+        + Sync;
+
     /// A helper wrapper around `NetworkEventTaskState` that can modify its behaviour for tests
     pub struct NetworkEventTaskStateModifier<
         TYPES: NodeType,
@@ -1428,4 +1266,105 @@ pub mod test {
             &mut self.network_event_task_state
         }
     }
+
+    /// A helper wrapper around `NetworkEventTaskState` that can drop, delay or duplicate its
+    /// messages for tests
+    pub struct NetworkEventTaskStatePlanner<
+        TYPES: NodeType,
+        V: Versions,
+        NET: ConnectedNetwork<TYPES::SignatureKey>,
+        S: Storage<TYPES>,
+    > {
+        /// The real `NetworkEventTaskState`
+        pub network_event_task_state: NetworkEventTaskState<TYPES, V, NET, S>,
+        /// A function that takes the result of `NetworkEventTaskState::parse_event` and
+        /// decides how it is transmitted on the network.
+        pub planner: Arc<TransmitPlanner<TYPES>>,
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES> + 'static,
+        > NetworkEventTaskStatePlanner<TYPES, V, NET, S>
+    {
+        /// Handles the received event, transmitting it as planned.
+        pub async fn handle(&mut self, event: Arc<HotShotEvent<TYPES>>) {
+            let mut maybe_action = None;
+            if let Some((sender, message_kind, transmit)) =
+                self.parse_event(event, &mut maybe_action).await
+            {
+                let plan = (self.planner)(
+                    &sender,
+                    &message_kind,
+                    transmit,
+                    &*self.membership_coordinator.membership().read().await,
+                    self.epoch,
+                );
+
+                for (transmit, delay) in plan {
+                    // Only the first transmission records the action, a repeated one is refused.
+                    self.spawn_transmit_task_after(
+                        delay,
+                        message_kind.clone(),
+                        maybe_action.take(),
+                        transmit,
+                        sender.clone(),
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES> + 'static,
+        > TaskState for NetworkEventTaskStatePlanner<TYPES, V, NET, S>
+    {
+        type Event = HotShotEvent<TYPES>;
+
+        async fn handle_event(
+            &mut self,
+            event: Arc<Self::Event>,
+            _sender: &Sender<Arc<Self::Event>>,
+            _receiver: &Receiver<Arc<Self::Event>>,
+        ) -> Result<()> {
+            self.handle(event).await;
+
+            Ok(())
+        }
+
+        fn cancel_subtasks(&mut self) {}
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES>,
+        > Deref for NetworkEventTaskStatePlanner<TYPES, V, NET, S>
+    {
+        type Target = NetworkEventTaskState<TYPES, V, NET, S>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.network_event_task_state
+        }
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES>,
+        > DerefMut for NetworkEventTaskStatePlanner<TYPES, V, NET, S>
+    {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.network_event_task_state
+        }
+    }
 }
@@ -21,7 +21,9 @@ use hotshot_types::{
     consensus::{CommitmentAndMetadata, OuterConsensus},
     data::{Leaf2, QuorumProposal2, QuorumProposalWrapper, VidDisperse, ViewChangeEvidence2},
     epoch_membership::EpochMembership,
+    feature_gates::Feature,
     message::Proposal,
+    signer::Signer,
     simple_certificate::{
         LightClientStateUpdateCertificate, NextEpochQuorumCertificate2, QuorumCertificate2,
         UpgradeCertificate,
@@ -29,7 +31,6 @@ use hotshot_types::{
     traits::{
         block_contents::BlockHeader,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
         BlockPayload,
     },
     utils::{
@@ -40,7 +41,6 @@ use hotshot_types::{
 };
 use hotshot_utils::anytrace::*;
 use tracing::instrument;
-use vbs::version::StaticVersionType;
 
 use crate::{
     events::HotShotEvent,
@@ -97,8 +97,8 @@ pub struct ProposalDependencyHandle<TYPES: NodeType, V: Versions> {
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Shared consensus task state
     pub consensus: OuterConsensus<TYPES>,
@@ -420,7 +420,7 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             &self.receiver,
             self.membership.coordinator.clone(),
             self.public_key.clone(),
-            self.private_key.clone(),
+            self.signer.clone(),
             OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
             &self.upgrade_lock,
             &parent_qc,
@@ -467,7 +467,7 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
         let builder_commitment = commitment_and_metadata.builder_commitment.clone();
         let metadata = commitment_and_metadata.metadata.clone();
 
-        if version >= V::Epochs::VERSION
+        if Feature::Epochs.enabled_in::<V>(version)
             && parent_qc.view_number()
                 > self
                     .upgrade_lock
@@ -501,7 +501,7 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
                 );
             }
         }
-        let block_header = if version < V::Marketplace::VERSION {
+        let block_header = if !Feature::Marketplace.enabled_in::<V>(version) {
             TYPES::BlockHeader::new_legacy(
                 state.as_ref(),
                 self.instance_state.as_ref(),
@@ -535,7 +535,7 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
         };
 
         let epoch = option_epoch_from_block_number::<TYPES>(
-            version >= V::Epochs::VERSION,
+            Feature::Epochs.enabled_in::<V>(version),
             block_header.block_number(),
             self.epoch_height,
         );
@@ -612,10 +612,11 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             "Proposed leaf parent does not equal high qc"
         );
 
-        let signature =
-            TYPES::SignatureKey::sign(&self.private_key, proposed_leaf.commit().as_ref())
-                .wrap()
-                .context(error!("Failed to compute proposed_leaf.commit()"))?;
+        let signature = self
+            .signer
+            .sign(proposed_leaf.commit().as_ref())
+            .wrap()
+            .context(error!("Failed to compute proposed_leaf.commit()"))?;
 
         let message = Proposal {
             data: proposal,
@@ -725,7 +726,7 @@ impl<TYPES: NodeType, V: Versions> HandleDepOutput for ProposalDependencyHandle<
 
         let (parent_qc, maybe_state_cert) = if let Some(qc) = parent_qc {
             (qc, state_cert)
-        } else if version < V::Epochs::VERSION {
+        } else if !Feature::Epochs.enabled_in::<V>(version) {
             (self.consensus.read().await.high_qc().clone(), None)
         } else if proposal_cert.is_some() {
             // If we have a view change evidence, we need to wait need to propose with the transition QC
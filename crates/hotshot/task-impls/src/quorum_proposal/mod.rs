@@ -13,19 +13,20 @@ use either::Either;
 use hotshot_task::{
     dependency::{AndDependency, EventDependency, OrDependency},
     dependency_task::DependencyTask,
+    supervisor::TaskSupervisor,
     task::TaskState,
 };
 use hotshot_types::{
     consensus::OuterConsensus,
     epoch_membership::EpochMembershipCoordinator,
     message::UpgradeLock,
+    signer::Signer,
     simple_certificate::{
         EpochRootQuorumCertificate, LightClientStateUpdateCertificate, NextEpochQuorumCertificate2,
         QuorumCertificate2, UpgradeCertificate,
     },
     traits::{
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
         storage::Storage,
     },
     utils::{is_epoch_transition, EpochTransitionIndicator},
@@ -33,7 +34,6 @@ use hotshot_types::{
     StakeTableEntries,
 };
 use hotshot_utils::anytrace::*;
-use tokio::task::JoinHandle;
 use tracing::instrument;
 
 use self::handlers::{ProposalDependency, ProposalDependencyHandle};
@@ -50,7 +50,7 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     pub cur_epoch: Option<TYPES::Epoch>,
 
     /// Table for the in-progress proposal dependency tasks.
-    pub proposal_dependencies: BTreeMap<TYPES::View, JoinHandle<()>>,
+    pub proposal_dependencies: TaskSupervisor<TYPES::View>,
 
     /// Formed QCs
     pub formed_quorum_certificates: BTreeMap<TYPES::View, QuorumCertificate2<TYPES>>,
@@ -68,8 +68,8 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// View timeout from config.
     pub timeout: u64,
@@ -379,7 +379,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
         );
 
         ensure!(
-            !self.proposal_dependencies.contains_key(&view_number),
+            !self.proposal_dependencies.contains(&view_number),
             "Task already exists"
         );
 
@@ -395,7 +395,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 receiver: event_receiver,
                 membership: epoch_membership,
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: self.signer.clone(),
                 instance_state: Arc::clone(&self.instance_state),
                 consensus: OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
                 timeout: self.timeout,
@@ -407,7 +407,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
             },
         );
         self.proposal_dependencies
-            .insert(view_number, dependency_task.run());
+            .register(view_number, dependency_task.run());
 
         Ok(())
     }
@@ -424,10 +424,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
 
             // Cancel the old dependency tasks.
             for view in (*self.latest_proposed_view + 1)..=(*new_view) {
-                if let Some(dependency) = self.proposal_dependencies.remove(&TYPES::View::new(view))
-                {
-                    dependency.abort();
-                }
+                self.proposal_dependencies.abort(&TYPES::View::new(view));
             }
 
             self.latest_proposed_view = new_view;
@@ -645,11 +642,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                     self.cur_epoch = *epoch;
                 }
                 let keep_view = TYPES::View::new(view.saturating_sub(1));
-                self.cancel_tasks(keep_view);
+                self.proposal_dependencies.cancel_before(&keep_view);
             },
             HotShotEvent::Timeout(view, ..) => {
                 let keep_view = TYPES::View::new(view.saturating_sub(1));
-                self.cancel_tasks(keep_view);
+                self.proposal_dependencies.cancel_before(&keep_view);
             },
             HotShotEvent::NextEpochQc2Formed(Either::Left(next_epoch_qc)) => {
                 // Only update if the qc is from a newer view
@@ -687,15 +684,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
         }
         Ok(())
     }
-
-    /// Cancel all tasks the consensus tasks has spawned before the given view
-    pub fn cancel_tasks(&mut self, view: TYPES::View) {
-        let keep = self.proposal_dependencies.split_off(&view);
-        while let Some((_, task)) = self.proposal_dependencies.pop_first() {
-            task.abort();
-        }
-        self.proposal_dependencies = keep;
-    }
 }
 
 #[async_trait]
@@ -714,8 +702,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
     }
 
     fn cancel_subtasks(&mut self) {
-        while let Some((_, handle)) = self.proposal_dependencies.pop_first() {
-            handle.abort();
-        }
+        self.proposal_dependencies.abort_all();
     }
 }
@@ -0,0 +1,210 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Buffering of quorum proposals whose parent is not yet known to this node.
+
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_broadcast::{Receiver, Sender};
+use committable::Commitment;
+use hotshot_task::supervisor::TaskSupervisor;
+use hotshot_types::{
+    consensus::{Consensus, OuterConsensus},
+    data::{Leaf2, QuorumProposalWrapper},
+    error::HotShotError,
+    message::Proposal,
+    traits::node_implementation::{NodeImplementation, NodeType, Versions},
+    vote::HasViewNumber,
+};
+
+use super::ValidationInfo;
+use crate::{
+    events::HotShotEvent,
+    helpers::{broadcast_error, broadcast_event, fetch_proposal},
+};
+
+/// A quorum proposal waiting for its parent leaf and state to be fetched.
+struct PendingProposal<TYPES: NodeType> {
+    /// The buffered proposal
+    proposal: Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
+    /// The key of the node which sent us the proposal
+    sender: TYPES::SignatureKey,
+    /// Commitment of the parent leaf the proposal extends
+    parent_commit: Commitment<Leaf2<TYPES>>,
+    /// When the proposal was buffered
+    buffered_at: Instant,
+}
+
+/// A buffered proposal whose dependencies are now all available.
+pub struct ReadyProposal<TYPES: NodeType> {
+    /// The proposal which can now be fully validated
+    pub proposal: Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
+    /// The key of the node which sent us the proposal
+    pub sender: TYPES::SignatureKey,
+    /// The parent leaf of the proposal
+    pub parent_leaf: Leaf2<TYPES>,
+    /// How long the proposal was stalled waiting for its dependencies
+    pub stalled_for: Duration,
+}
+
+/// Tracks quorum proposals whose parent leaf or state is missing.
+///
+/// Instead of dropping such a proposal and relying on a timeout, the proposal is buffered
+/// and a fetch for its parent is issued. Once the parent arrives the proposal is handed back
+/// to the task for full validation, so the node can still vote in that view. The proposal's
+/// own VID share is requested by the request task once the proposal is validated.
+pub struct ProposalDependencyTracker<TYPES: NodeType> {
+    /// Buffered proposals, keyed by their view
+    pending: BTreeMap<TYPES::View, PendingProposal<TYPES>>,
+
+    /// Fetches in flight for the parents of buffered proposals, keyed by the proposal view
+    fetch_tasks: TaskSupervisor<TYPES::View>,
+}
+
+impl<TYPES: NodeType> ProposalDependencyTracker<TYPES> {
+    /// Create a new tracker, running its fetches under `fetch_tasks`
+    #[must_use]
+    pub fn new(fetch_tasks: TaskSupervisor<TYPES::View>) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            fetch_tasks,
+        }
+    }
+
+    /// Number of proposals currently buffered
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no proposals are currently buffered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Buffer `proposal` and fetch its parent leaf and state.
+    ///
+    /// When the fetch succeeds, `HotShotEvent::ProposalDependenciesFetched` is broadcast for
+    /// the proposal's view, and when it fails the application is sent a network error. Nothing is
+    /// done if a proposal for that view is already buffered.
+    pub(crate) fn buffer_and_fetch<I: NodeImplementation<TYPES>, V: Versions>(
+        &mut self,
+        proposal: &Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
+        sender: &TYPES::SignatureKey,
+        validation_info: &ValidationInfo<TYPES, I, V>,
+        event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+        event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let view_number = proposal.data.view_number();
+        if self.pending.contains_key(&view_number) {
+            return;
+        }
+
+        let justify_qc = proposal.data.justify_qc().clone();
+        self.pending.insert(
+            view_number,
+            PendingProposal {
+                proposal: proposal.clone(),
+                sender: sender.clone(),
+                parent_commit: justify_qc.data.leaf_commit,
+                buffered_at: Instant::now(),
+            },
+        );
+
+        let event_sender = event_sender.clone();
+        let event_receiver = event_receiver.clone();
+        let membership = validation_info.membership.coordinator.clone();
+        let consensus = OuterConsensus::new(Arc::clone(&validation_info.consensus.inner_consensus));
+        // Note that we explicitly use the node key here instead of the provided key in the signature.
+        // This is because the key that we receive is for the prior leader, so the payload would be routed
+        // incorrectly.
+        let public_key = validation_info.public_key.clone();
+        let signer = validation_info.signer.clone();
+        let upgrade_lock = validation_info.upgrade_lock.clone();
+        let epoch_height = validation_info.epoch_height;
+        let output_event_stream = validation_info.output_event_stream.clone();
+        self.fetch_tasks.spawn(view_number, async move {
+            match fetch_proposal(
+                &justify_qc,
+                event_sender.clone(),
+                event_receiver,
+                membership,
+                consensus,
+                public_key,
+                signer,
+                &upgrade_lock,
+                epoch_height,
+            )
+            .await
+            {
+                Ok(_) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::ProposalDependenciesFetched(view_number)),
+                        &event_sender,
+                    )
+                    .await;
+                },
+                Err(e) => {
+                    tracing::warn!("Failed to fetch the parent of proposal {view_number}: {e}");
+                    broadcast_error(
+                        view_number,
+                        HotShotError::Network(format!(
+                            "failed to fetch the parent of proposal {view_number}: {e}"
+                        )),
+                        &output_event_stream,
+                    )
+                    .await;
+                },
+            }
+        });
+    }
+
+    /// Remove and return every buffered proposal whose parent leaf and state are now in `consensus`
+    pub fn take_ready(&mut self, consensus: &Consensus<TYPES>) -> Vec<ReadyProposal<TYPES>> {
+        let mut ready = Vec::new();
+        self.pending.retain(|view, pending| {
+            let Some(parent_leaf) = consensus.saved_leaves().get(&pending.parent_commit) else {
+                return true;
+            };
+            if consensus
+                .state_and_delta(parent_leaf.view_number())
+                .0
+                .is_none()
+            {
+                return true;
+            }
+            self.fetch_tasks.abort(view);
+            ready.push(ReadyProposal {
+                proposal: pending.proposal.clone(),
+                sender: pending.sender.clone(),
+                parent_leaf: parent_leaf.clone(),
+                stalled_for: pending.buffered_at.elapsed(),
+            });
+            false
+        });
+        ready
+    }
+
+    /// Drop buffered proposals and cancel fetches for views older than `oldest_view_to_keep`.
+    ///
+    /// Returns the number of proposals dropped.
+    pub fn gc(&mut self, oldest_view_to_keep: &TYPES::View) -> usize {
+        self.fetch_tasks.cancel_before(oldest_view_to_keep);
+        let keep = self.pending.split_off(oldest_view_to_keep);
+        std::mem::replace(&mut self.pending, keep).len()
+    }
+
+    /// Drop every buffered proposal and abort all in-flight fetches
+    pub fn abort_all(&mut self) {
+        self.pending.clear();
+        self.fetch_tasks.abort_all();
+    }
+}
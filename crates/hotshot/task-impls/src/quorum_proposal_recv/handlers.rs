@@ -15,6 +15,8 @@ use hotshot_types::{
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal, QuorumProposalWrapper},
     epoch_membership::EpochMembershipCoordinator,
+    error::HotShotError,
+    feature_gates::Feature,
     message::Proposal,
     simple_certificate::{QuorumCertificate, QuorumCertificate2},
     simple_vote::HasEpoch,
@@ -33,15 +35,13 @@ use hotshot_types::{
     vote::{Certificate, HasViewNumber},
 };
 use hotshot_utils::anytrace::*;
-use tokio::spawn;
 use tracing::instrument;
-use vbs::version::StaticVersionType;
 
-use super::{QuorumProposalRecvTaskState, ValidationInfo};
+use super::{ProposalDependencyTracker, QuorumProposalRecvTaskState, ValidationInfo};
 use crate::{
     events::HotShotEvent,
     helpers::{
-        broadcast_event, check_qc_state_cert_correspondence, fetch_proposal, update_high_qc,
+        broadcast_error, broadcast_event, check_qc_state_cert_correspondence, update_high_qc,
         validate_epoch_transition_qc, validate_light_client_state_update_certificate,
         validate_proposal_safety_and_liveness, validate_proposal_view_and_certs,
         validate_qc_and_next_epoch_qc,
@@ -49,38 +49,6 @@ use crate::{
     quorum_proposal_recv::{UpgradeLock, Versions},
 };
 
-/// Spawn a task which will fire a request to get a proposal, and store it.
-#[allow(clippy::too_many_arguments)]
-fn spawn_fetch_proposal<TYPES: NodeType, V: Versions>(
-    qc: &QuorumCertificate2<TYPES>,
-    event_sender: Sender<Arc<HotShotEvent<TYPES>>>,
-    event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
-    membership: EpochMembershipCoordinator<TYPES>,
-    consensus: OuterConsensus<TYPES>,
-    sender_public_key: TYPES::SignatureKey,
-    sender_private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
-    upgrade_lock: UpgradeLock<TYPES, V>,
-    epoch_height: u64,
-) {
-    let qc = qc.clone();
-    spawn(async move {
-        let lock = upgrade_lock;
-
-        let _ = fetch_proposal(
-            &qc,
-            event_sender,
-            event_receiver,
-            membership,
-            consensus,
-            sender_public_key,
-            sender_private_key,
-            &lock,
-            epoch_height,
-        )
-        .await;
-    });
-}
-
 /// Update states in the event that the parent state is not found for a given `proposal`.
 #[instrument(skip_all)]
 pub async fn validate_proposal_liveness<
@@ -94,9 +62,9 @@ pub async fn validate_proposal_liveness<
     let mut valid_epoch_transition = false;
     if validation_info
         .upgrade_lock
-        .version(proposal.data.view_number())
+        .features()
+        .enabled(Feature::Epochs, proposal.data.view_number())
         .await
-        .is_ok_and(|v| v >= V::Epochs::VERSION)
     {
         let Some(block_number) = proposal.data.justify_qc().data.block_number else {
             bail!("Quorum Proposal has no block number but it's after the epoch upgrade");
@@ -115,7 +83,7 @@ pub async fn validate_proposal_liveness<
     );
 
     if let Err(e) = consensus_writer.update_leaf(leaf.clone(), state, None) {
-        tracing::trace!("{e:?}");
+        e.log();
     }
 
     let liveness_check = proposal.data.justify_qc().view_number() > consensus_writer.locked_view();
@@ -123,9 +91,9 @@ pub async fn validate_proposal_liveness<
     if liveness_check
         && validation_info
             .upgrade_lock
-            .version(leaf.view_number())
+            .features()
+            .enabled(Feature::Epochs, leaf.view_number())
             .await
-            .is_ok_and(|v| v >= V::Epochs::VERSION)
     {
         consensus_writer.update_locked_view(proposal.data.justify_qc().view_number())?;
     }
@@ -261,6 +229,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
     event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
     event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
     validation_info: ValidationInfo<TYPES, I, V>,
+    dependency_tracker: &mut ProposalDependencyTracker<TYPES>,
 ) -> Result<()> {
     proposal
         .data
@@ -270,9 +239,15 @@ pub(crate) async fn handle_quorum_proposal_recv<
     validate_current_epoch(proposal, &validation_info).await?;
     let quorum_proposal_sender_key = quorum_proposal_sender_key.clone();
 
-    validate_proposal_view_and_certs(proposal, &validation_info)
-        .await
-        .context(warn!("Failed to validate proposal view or attached certs"))?;
+    if let Err(e) = validate_proposal_view_and_certs(proposal, &validation_info).await {
+        broadcast_error(
+            proposal.data.view_number(),
+            HotShotError::Validation(format!("invalid proposal view or certificates: {e}")),
+            &validation_info.output_event_stream,
+        )
+        .await;
+        return Err(e).context(warn!("Failed to validate proposal view or attached certs"));
+    }
 
     validate_block_height(proposal).await?;
 
@@ -313,7 +288,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
 
     validate_epoch_transition_block(proposal, &validation_info).await?;
 
-    validate_qc_and_next_epoch_qc(
+    if let Err(e) = validate_qc_and_next_epoch_qc(
         &justify_qc,
         maybe_next_epoch_justify_qc.as_ref(),
         &validation_info.consensus,
@@ -321,7 +296,16 @@ pub(crate) async fn handle_quorum_proposal_recv<
         &validation_info.upgrade_lock,
         validation_info.epoch_height,
     )
-    .await?;
+    .await
+    {
+        broadcast_error(
+            view_number,
+            HotShotError::Validation(format!("invalid justify QC: {e}")),
+            &validation_info.output_event_stream,
+        )
+        .await;
+        return Err(e);
+    }
 
     broadcast_event(
         Arc::new(HotShotEvent::QuorumProposalPreliminarilyValidated(
@@ -341,19 +325,12 @@ pub(crate) async fn handle_quorum_proposal_recv<
         .cloned();
 
     if parent_leaf.is_none() {
-        spawn_fetch_proposal(
-            &justify_qc,
-            event_sender.clone(),
-            event_receiver.clone(),
-            validation_info.membership.coordinator.clone(),
-            OuterConsensus::new(Arc::clone(&validation_info.consensus.inner_consensus)),
-            // Note that we explicitly use the node key here instead of the provided key in the signature.
-            // This is because the key that we receive is for the prior leader, so the payload would be routed
-            // incorrectly.
-            validation_info.public_key.clone(),
-            validation_info.private_key.clone(),
-            validation_info.upgrade_lock.clone(),
-            validation_info.epoch_height,
+        dependency_tracker.buffer_and_fetch(
+            proposal,
+            &quorum_proposal_sender_key,
+            &validation_info,
+            event_sender,
+            event_receiver,
         );
     }
     let consensus_reader = validation_info.consensus.read().await;
@@ -382,7 +359,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
 
     let Some((parent_leaf, _parent_state)) = parent else {
         tracing::warn!(
-            "Proposal's parent missing from storage with commitment: {:?}",
+            "Proposal's parent missing from storage with commitment: {:?}, buffering the proposal until it is fetched",
             justify_qc.data.leaf_commit
         );
         validate_proposal_liveness(proposal, &validation_info).await?;
@@ -6,40 +6,48 @@
 
 #![allow(unused_imports)]
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::sync::Arc;
 
 use async_broadcast::{broadcast, Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use either::Either;
 use futures::future::{err, join_all};
-use hotshot_task::task::{Task, TaskState};
+use hotshot_task::{
+    supervisor::TaskSupervisor,
+    task::{Task, TaskState},
+};
 use hotshot_types::{
     consensus::{Consensus, OuterConsensus},
-    data::{EpochNumber, Leaf, ViewChangeEvidence2},
+    data::{EpochNumber, Leaf, QuorumProposalWrapper, ViewChangeEvidence2},
     epoch_membership::{self, EpochMembership, EpochMembershipCoordinator},
     event::Event,
-    message::UpgradeLock,
+    message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_certificate::UpgradeCertificate,
     simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
     },
     utils::option_epoch_from_block_number,
     vote::{Certificate, HasViewNumber},
 };
 use hotshot_utils::anytrace::{bail, Result};
-use tokio::task::JoinHandle;
 use tracing::{debug, error, info, instrument, warn};
 use vbs::version::Version;
 
+pub use self::dependency_tracker::{ProposalDependencyTracker, ReadyProposal};
 use self::handlers::handle_quorum_proposal_recv;
 use crate::{
     events::{HotShotEvent, ProposalMissing},
-    helpers::{broadcast_event, fetch_proposal, parent_leaf_and_state},
+    helpers::{
+        broadcast_event, fetch_proposal, parent_leaf_and_state,
+        validate_proposal_safety_and_liveness,
+    },
 };
+/// Buffering of proposals with missing dependencies.
+mod dependency_tracker;
 /// Event handlers for this task.
 mod handlers;
 
@@ -49,8 +57,8 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -75,7 +83,7 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 
     /// Spawned tasks related to a specific view, so we can cancel them when
     /// they are stale
-    pub spawned_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+    pub spawned_tasks: TaskSupervisor<TYPES::View>,
 
     /// The node's id
     pub id: u64,
@@ -85,6 +93,9 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Proposals buffered until their missing parent is fetched
+    pub dependency_tracker: ProposalDependencyTracker<TYPES>,
 }
 
 /// all the info we need to validate a proposal.  This makes it easy to spawn an effemeral task to
@@ -96,8 +107,8 @@ pub(crate) struct ValidationInfo<TYPES: NodeType, I: NodeImplementation<TYPES>,
     /// Our public key
     pub(crate) public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub(crate) private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub(crate) signer: Signer<TYPES::SignatureKey>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub(crate) consensus: OuterConsensus<TYPES>,
@@ -121,17 +132,6 @@ pub(crate) struct ValidationInfo<TYPES: NodeType, I: NodeImplementation<TYPES>,
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
     QuorumProposalRecvTaskState<TYPES, I, V>
 {
-    /// Cancel all tasks the consensus tasks has spawned before the given view
-    pub fn cancel_tasks(&mut self, view: TYPES::View) {
-        let keep = self.spawned_tasks.split_off(&view);
-        while let Some((_, tasks)) = self.spawned_tasks.pop_first() {
-            for task in tasks {
-                task.abort();
-            }
-        }
-        self.spawned_tasks = keep;
-    }
-
     /// Handles all consensus events relating to propose and vote-enabling events.
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = self.cur_epoch.map(|x| *x)), name = "Consensus replica task", level = "error")]
     #[allow(unused_variables)]
@@ -156,34 +156,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                     );
                     return;
                 }
-                let proposal_epoch = option_epoch_from_block_number::<TYPES>(
-                    proposal.data.proposal.epoch().is_some(),
-                    proposal.data.block_header().block_number(),
-                    self.epoch_height,
-                );
-                let Ok(epoch_membership) =
-                    self.membership.membership_for_epoch(proposal_epoch).await
-                else {
-                    tracing::warn!("No Stake table for epoch = {proposal_epoch:?}");
+                let Some(validation_info) = self.validation_info(proposal).await else {
                     return;
                 };
-                let validation_info = ValidationInfo::<TYPES, I, V> {
-                    id: self.id,
-                    public_key: self.public_key.clone(),
-                    private_key: self.private_key.clone(),
-                    consensus: self.consensus.clone(),
-                    membership: epoch_membership,
-                    output_event_stream: self.output_event_stream.clone(),
-                    storage: Arc::clone(&self.storage),
-                    upgrade_lock: self.upgrade_lock.clone(),
-                    epoch_height: self.epoch_height,
-                };
                 match handle_quorum_proposal_recv(
                     proposal,
                     sender,
                     &event_sender,
                     &event_receiver,
                     validation_info,
+                    &mut self.dependency_tracker,
                 )
                 .await
                 {
@@ -204,11 +186,86 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 // we might still be processing the proposal from view V which caused us
                 // to enter view V + 1.
                 let oldest_view_to_keep = TYPES::View::new(view.saturating_sub(1));
-                self.cancel_tasks(oldest_view_to_keep);
+                self.spawned_tasks.cancel_before(&oldest_view_to_keep);
+                self.dependency_tracker.gc(&oldest_view_to_keep);
+            },
+            HotShotEvent::ProposalDependenciesFetched(_)
+            | HotShotEvent::QuorumProposalValidated(..) => {
+                if self.dependency_tracker.is_empty() {
+                    return;
+                }
+                self.handle_ready_proposals(&event_sender).await;
             },
             _ => {},
         }
     }
+
+    /// Build the information needed to validate `proposal`, or `None` if we have no stake
+    /// table for the proposal's epoch
+    async fn validation_info(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
+    ) -> Option<ValidationInfo<TYPES, I, V>> {
+        let proposal_epoch = option_epoch_from_block_number::<TYPES>(
+            proposal.data.proposal.epoch().is_some(),
+            proposal.data.block_header().block_number(),
+            self.epoch_height,
+        );
+        let Ok(epoch_membership) = self.membership.membership_for_epoch(proposal_epoch).await
+        else {
+            tracing::warn!("No Stake table for epoch = {proposal_epoch:?}");
+            return None;
+        };
+        Some(ValidationInfo::<TYPES, I, V> {
+            id: self.id,
+            public_key: self.public_key.clone(),
+            signer: self.signer.clone(),
+            consensus: self.consensus.clone(),
+            membership: epoch_membership,
+            output_event_stream: self.output_event_stream.clone(),
+            storage: Arc::clone(&self.storage),
+            upgrade_lock: self.upgrade_lock.clone(),
+            epoch_height: self.epoch_height,
+        })
+    }
+
+    /// Fully validate the buffered proposals whose parent has arrived
+    async fn handle_ready_proposals(&mut self, event_sender: &Sender<Arc<HotShotEvent<TYPES>>>) {
+        let consensus_reader = self.consensus.read().await;
+        let ready = self.dependency_tracker.take_ready(&consensus_reader);
+        let metrics = Arc::clone(&consensus_reader.metrics);
+        drop(consensus_reader);
+
+        for ReadyProposal {
+            proposal,
+            sender,
+            parent_leaf,
+            stalled_for,
+        } in ready
+        {
+            metrics
+                .proposal_dependency_stall_duration
+                .add_point(stalled_for.as_secs_f64());
+            let Some(validation_info) = self.validation_info(&proposal).await else {
+                continue;
+            };
+            tracing::debug!(
+                "Parent of proposal {} arrived after {stalled_for:?}, validating it",
+                proposal.data.view_number()
+            );
+            if let Err(e) = validate_proposal_safety_and_liveness::<TYPES, I, V>(
+                proposal,
+                parent_leaf,
+                &validation_info,
+                event_sender.clone(),
+                sender,
+            )
+            .await
+            {
+                tracing::error!(?e, "Failed to validate the buffered proposal");
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -229,13 +286,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
     }
 
     fn cancel_subtasks(&mut self) {
-        while !self.spawned_tasks.is_empty() {
-            let Some((_, handles)) = self.spawned_tasks.pop_first() else {
-                break;
-            };
-            for handle in handles {
-                handle.abort();
-            }
-        }
+        self.spawned_tasks.abort_all();
+        self.dependency_tracker.abort_all();
     }
 }
@@ -16,14 +16,16 @@ use hotshot_types::{
     drb::{DrbResult, INITIAL_DRB_RESULT},
     epoch_membership::{EpochMembership, EpochMembershipCoordinator},
     event::{Event, EventType},
+    feature_gates::Feature,
     light_client::compute_stake_table_commitment,
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_vote::{EpochRootQuorumVote, LightClientStateUpdateVote, QuorumData2, QuorumVote2},
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::{SignatureKey, StateSignatureKey},
+        signature_key::StateSignatureKey,
         storage::Storage,
         ValidatedState,
     },
@@ -35,7 +37,6 @@ use hotshot_types::{
 };
 use hotshot_utils::anytrace::*;
 use tracing::instrument;
-use vbs::version::StaticVersionType;
 
 use super::QuorumVoteTaskState;
 use crate::{
@@ -68,7 +69,7 @@ async fn get_computed_drb_result<TYPES: NodeType, I: NodeImplementation<TYPES>,
 /// Verify the DRB result from the proposal for the next epoch if this is the last block of the
 /// current epoch.
 ///
-/// Uses the result from `start_drb_task`.
+/// Uses the result from the `DrbComputation` started when the epoch root was decided.
 ///
 /// Returns an error if we should not vote.
 async fn verify_drb_result<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
@@ -176,7 +177,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
         .version(proposal.view_number())
         .await?;
 
-    if version >= V::Epochs::VERSION {
+    if Feature::Epochs.enabled_in::<V>(version) {
         // Don't vote if the DRB result verification fails.
         verify_drb_result(proposal, task_state).await?;
     }
@@ -188,7 +189,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
         leaf_views,
         included_txns,
         decided_upgrade_cert,
-    } = if version >= V::Epochs::VERSION {
+    } = if Feature::Epochs.enabled_in::<V>(version) {
         // Skip the decide rule for the last block of the epoch.  This is so
         // that we do not decide the block with epoch_height -2 before we enter the new epoch
         if !is_last_block(
@@ -200,7 +201,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
                 OuterConsensus::new(Arc::clone(&task_state.consensus.inner_consensus)),
                 Arc::clone(&task_state.upgrade_lock.decided_upgrade_certificate),
                 &task_state.public_key,
-                version >= V::Epochs::VERSION,
+                Feature::Epochs.enabled_in::<V>(version),
                 task_state.membership.membership(),
                 &task_state.storage,
             )
@@ -214,7 +215,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
             OuterConsensus::new(Arc::clone(&task_state.consensus.inner_consensus)),
             Arc::clone(&task_state.upgrade_lock.decided_upgrade_certificate),
             &task_state.public_key,
-            version >= V::Epochs::VERSION,
+            Feature::Epochs.enabled_in::<V>(version),
             task_state.membership.membership(),
             &task_state.storage,
         )
@@ -230,7 +231,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
         *decided_certificate_lock = Some(cert.clone());
         drop(decided_certificate_lock);
 
-        if cert.data.new_version == V::Epochs::VERSION {
+        if cert.data.new_version == Feature::Epochs.version::<V>() {
             let epoch_height = task_state.consensus.read().await.epoch_height;
             let first_epoch_number = TYPES::Epoch::new(epoch_from_block_number(
                 proposal.block_header().block_number(),
@@ -278,11 +279,8 @@ pub(crate) async fn handle_quorum_proposal_validated<
         // Set the new decided view.
         consensus_writer
             .update_last_decided_view(decided_view_number)
-            .context(|e| {
-                warn!(
-                    "`update_last_decided_view` failed; this should never happen. Error: {}",
-                    e
-                )
+            .map_err(|e| {
+                warn!("`update_last_decided_view` failed; this should never happen. Error: {e}")
             })?;
 
         consensus_writer
@@ -334,7 +332,15 @@ pub(crate) async fn handle_quorum_proposal_validated<
             new_decide_qc.as_ref().unwrap().view_number()
         );
 
-        if version >= V::Epochs::VERSION {
+        broadcast_event(
+            Arc::new(HotShotEvent::LeavesDecided(
+                leaf_views.iter().map(|info| info.leaf.clone()).collect(),
+            )),
+            event_sender,
+        )
+        .await;
+
+        if Feature::Epochs.enabled_in::<V>(version) {
             for leaf_view in leaf_views {
                 store_drb_result(task_state, &leaf_view.leaf).await?;
             }
@@ -357,7 +363,7 @@ pub(crate) async fn update_shared_state<
     receiver: InactiveReceiver<Arc<HotShotEvent<TYPES>>>,
     membership: EpochMembershipCoordinator<TYPES>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Signer<TYPES::SignatureKey>,
     upgrade_lock: UpgradeLock<TYPES, V>,
     view_number: TYPES::View,
     instance_state: Arc<TYPES::InstanceState>,
@@ -396,7 +402,7 @@ pub(crate) async fn update_shared_state<
                 membership.clone(),
                 OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
                 public_key.clone(),
-                private_key.clone(),
+                signer.clone(),
                 &upgrade_lock,
                 epoch_height,
             )
@@ -453,7 +459,7 @@ pub(crate) async fn update_shared_state<
         Arc::new(validated_state),
         Some(Arc::new(state_delta)),
     ) {
-        tracing::trace!("{e:?}");
+        e.log();
     }
 
     drop(consensus_writer);
@@ -470,7 +476,7 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
     membership: EpochMembership<TYPES>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Signer<TYPES::SignatureKey>,
     upgrade_lock: UpgradeLock<TYPES, V>,
     view_number: TYPES::View,
     storage: Arc<RwLock<I::Storage>>,
@@ -512,7 +518,7 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
         },
         view_number,
         &public_key,
-        &private_key,
+        &signer,
         &upgrade_lock,
     )
     .await
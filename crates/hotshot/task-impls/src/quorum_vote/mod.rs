@@ -19,8 +19,9 @@ use hotshot_types::{
     consensus::{ConsensusMetricsValue, OuterConsensus},
     data::{vid_disperse::vid_total_weight, Leaf2},
     epoch_membership::EpochMembershipCoordinator,
-    event::Event,
+    event::{Event, EventType},
     message::UpgradeLock,
+    signer::Signer,
     simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
@@ -61,8 +62,8 @@ pub struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     /// Public key.
     pub public_key: TYPES::SignatureKey,
 
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signer for our consensus key.
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -151,8 +152,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
                     parent_view_number = Some(parent_leaf.view_number());
                 },
                 HotShotEvent::DaCertificateValidated(cert) => {
-                    let cert_payload_comm = &cert.data().payload_commit;
-                    let next_epoch_cert_payload_comm = cert.data().next_epoch_payload_commit;
+                    let cert_payload_comms = cert.data().payload_commitments();
+                    let cert_payload_comm = cert_payload_comms.current();
+                    let next_epoch_cert_payload_comm = cert_payload_comms.next_epoch().copied();
                     if let Some(ref comm) = payload_commitment {
                         if cert_payload_comm != comm {
                             tracing::error!("DAC has inconsistent payload commitment with quorum proposal or VID.");
@@ -223,7 +225,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
             self.receiver.clone(),
             self.membership_coordinator.clone(),
             self.public_key.clone(),
-            self.private_key.clone(),
+            self.signer.clone(),
             self.upgrade_lock.clone(),
             self.view_number,
             Arc::clone(&self.instance_state),
@@ -283,7 +285,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
             self.sender.clone(),
             epoch_membership,
             self.public_key.clone(),
-            self.private_key.clone(),
+            self.signer.clone(),
             self.upgrade_lock.clone(),
             self.view_number,
             Arc::clone(&self.storage),
@@ -308,8 +310,8 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// Public key.
     pub public_key: TYPES::SignatureKey,
 
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signer for our consensus key.
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -442,7 +444,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             dependency_chain,
             VoteDependencyHandle::<TYPES, I, V> {
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: self.signer.clone(),
                 consensus: OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
                 instance_state: Arc::clone(&self.instance_state),
                 membership_coordinator: self.membership.clone(),
@@ -567,6 +569,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                     &event_sender.clone(),
                 )
                 .await;
+                broadcast_event(
+                    Event {
+                        view_number: view,
+                        event: EventType::DaCertificate {
+                            certificate: cert.clone(),
+                        },
+                    },
+                    &self.output_event_stream,
+                )
+                .await;
                 self.create_dependency_task_if_new(
                     view,
                     event_receiver,
@@ -625,11 +637,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                     .write()
                     .await
                     .update_vid_shares(view, share.clone());
+                self.consensus_metrics.vid_shares_received.add(1);
 
-                ensure!(
-                    *share.data.recipient_key() == self.public_key,
-                    "Got a Valid VID share but it's not for our key"
-                );
+                // We may be sent the share of another node as one of its custodians, in which
+                // case we only store it so that we can serve it to that node
+                if *share.data.recipient_key() != self.public_key {
+                    return Ok(());
+                }
 
                 broadcast_event(
                     Arc::new(HotShotEvent::VidShareValidated(share.clone())),
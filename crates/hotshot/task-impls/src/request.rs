@@ -5,7 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::BTreeSet,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -17,11 +17,16 @@ use async_broadcast::{Receiver, Sender};
 use async_trait::async_trait;
 use hotshot_task::{
     dependency::{Dependency, EventDependency},
+    supervisor::TaskSupervisor,
     task::TaskState,
 };
 use hotshot_types::{
     consensus::OuterConsensus,
+    constants::VID_SHARE_CUSTODIANS,
+    data::{vid_disperse::vid_share_custodians, VidDisperseShare},
     epoch_membership::EpochMembershipCoordinator,
+    message::Proposal,
+    signer::Signer,
     simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
@@ -72,8 +77,8 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// This nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This nodes private/signing key, used to sign requests.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// This nodes signer, used to sign requests.
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// The node's id
     pub id: u64,
@@ -81,8 +86,8 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// A flag indicating that `HotShotEvent::Shutdown` has been received
     pub shutdown_flag: Arc<AtomicBool>,
 
-    /// A flag indicating that `HotShotEvent::Shutdown` has been received
-    pub spawned_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+    /// Request tasks, keyed by the view they request data for
+    pub spawned_tasks: TaskSupervisor<TYPES::View>,
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
@@ -98,6 +103,37 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> Drop for NetworkRequestState
 type Signature<TYPES> =
     <<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType;
 
+/// The nodes we accept a VID share of a given view from, in response to a request
+#[derive(Clone, Debug)]
+struct VidShareSources<TYPES: NodeType> {
+    /// DA members and the leader, which send shares they signed themselves
+    da_members: BTreeSet<TYPES::SignatureKey>,
+    /// Custodians of our share, which send the share signed by the leader
+    custodians: BTreeSet<TYPES::SignatureKey>,
+    /// The leader of the view
+    leader: Option<TYPES::SignatureKey>,
+}
+
+impl<TYPES: NodeType> VidShareSources<TYPES> {
+    /// The key which signed a share sent by `sender`, if we accept the share from `sender`
+    fn signer(
+        &self,
+        sender: &TYPES::SignatureKey,
+        proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>,
+    ) -> Option<TYPES::SignatureKey> {
+        let signed_by = |key: &TYPES::SignatureKey| {
+            key.validate(&proposal.signature, proposal.data.payload_commitment_ref())
+        };
+        if self.da_members.contains(sender) && signed_by(sender) {
+            Some(sender.clone())
+        } else if self.custodians.contains(sender) {
+            self.leader.clone().filter(signed_by)
+        } else {
+            None
+        }
+    }
+}
+
 #[async_trait]
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequestState<TYPES, I> {
     type Event = HotShotEvent<TYPES>;
@@ -162,15 +198,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
     fn cancel_subtasks(&mut self) {
         self.shutdown_flag.store(true, Ordering::Relaxed);
 
-        while !self.spawned_tasks.is_empty() {
-            let Some((_, handles)) = self.spawned_tasks.pop_first() else {
-                break;
-            };
-
-            for handle in handles {
-                handle.abort();
-            }
-        }
+        self.spawned_tasks.abort_all();
     }
 }
 
@@ -228,13 +256,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                 return;
             },
         };
+        let leader = membership_reader.leader(view).await.ok();
         let mut da_committee_for_view = membership_reader.da_committee_members(view).await;
-        if let Ok(leader) = membership_reader.leader(view).await {
-            da_committee_for_view.insert(leader);
+        if let Some(leader) = &leader {
+            da_committee_for_view.insert(leader.clone());
         }
 
+        // The custodians of our share may hold a copy of it sent by the leader
+        let mut custodians = vid_share_custodians(
+            &membership_reader.stake_table().await,
+            view,
+            &public_key,
+            VID_SHARE_CUSTODIANS,
+        );
+
         // Get committee members for view
-        let mut recipients: Vec<TYPES::SignatureKey> = membership_reader
+        let mut da_members: Vec<TYPES::SignatureKey> = membership_reader
             .da_committee_members(view)
             .await
             .into_iter()
@@ -242,7 +279,23 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
 
         // Randomize the recipients so all replicas don't overload the same 1 recipients
         // and so we don't implicitly rely on the same replica all the time.
-        recipients.shuffle(&mut thread_rng());
+        custodians.shuffle(&mut thread_rng());
+        da_members.shuffle(&mut thread_rng());
+
+        // Ask the custodians first, since they can answer without recalculating the shares
+        let share_sources = VidShareSources {
+            da_members: da_committee_for_view,
+            custodians: custodians.iter().cloned().collect(),
+            leader,
+        };
+        let recipients: Vec<TYPES::SignatureKey> = custodians
+            .into_iter()
+            .chain(
+                da_members
+                    .into_iter()
+                    .filter(|member| !share_sources.custodians.contains(member)),
+            )
+            .collect();
 
         // prepare request
         let data_request = DataRequest::<TYPES> {
@@ -281,7 +334,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                         &receiver,
                         &data_request,
                         recipient,
-                        &da_committee_for_view,
+                        &share_sources,
                         &public_key,
                         view,
                     )
@@ -296,18 +349,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                 }
             }
         });
-        self.spawned_tasks.entry(view).or_default().push(handle);
+        self.spawned_tasks.register(view, handle);
     }
 
     /// Handles main logic for the Request / Response of a vid share
-    /// Make the request to get VID share to a DA member and wait for the response.
+    /// Make the request to get VID share to a DA member or custodian and wait for the response.
     /// Returns true if response received, otherwise false
     async fn handle_vid_request_task(
         sender: &Sender<Arc<HotShotEvent<TYPES>>>,
         receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
         data_request: &DataRequest<TYPES>,
         recipient: &TYPES::SignatureKey,
-        da_committee_for_view: &BTreeSet<<TYPES as NodeType>::SignatureKey>,
+        share_sources: &VidShareSources<TYPES>,
         public_key: &<TYPES as NodeType>::SignatureKey,
         view: TYPES::View,
     ) -> bool {
@@ -326,22 +379,21 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         // Wait for a response
         let result = timeout(
             REQUEST_TIMEOUT,
-            Self::handle_event_dependency(receiver, da_committee_for_view.clone(), view),
+            Self::handle_event_dependency(receiver, share_sources.clone(), view),
         )
         .await;
 
         // Check if we got a result, if not we timed out
         if let Ok(Some(event)) = result {
             if let HotShotEvent::VidResponseRecv(sender_pub_key, proposal) = event.as_ref() {
-                broadcast_event(
-                    Arc::new(HotShotEvent::VidShareRecv(
-                        sender_pub_key.clone(),
-                        proposal.clone(),
-                    )),
-                    sender,
-                )
-                .await;
-                return true;
+                if let Some(signer) = share_sources.signer(sender_pub_key, proposal) {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::VidShareRecv(signer, proposal.clone())),
+                        sender,
+                    )
+                    .await;
+                    return true;
+                }
             }
         }
         false
@@ -351,7 +403,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
     /// Returns an optional with `VidResponseRecv` if received, otherwise None
     async fn handle_event_dependency(
         receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
-        da_members_for_view: BTreeSet<<TYPES as NodeType>::SignatureKey>,
+        share_sources: VidShareSources<TYPES>,
         view: TYPES::View,
     ) -> Option<Arc<HotShotEvent<TYPES>>> {
         EventDependency::new(
@@ -360,9 +412,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
                 let event = event.as_ref();
                 if let HotShotEvent::VidResponseRecv(sender_key, proposal) = event {
                     proposal.data.view_number() == view
-                        && da_members_for_view.contains(sender_key)
-                        && sender_key
-                            .validate(&proposal.signature, proposal.data.payload_commitment_ref())
+                        && share_sources.signer(sender_key, proposal).is_some()
                 } else {
                     false
                 }
@@ -415,8 +465,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             tracing::error!("Failed to serialize request!");
             return None;
         };
-        let Ok(signature) = TYPES::SignatureKey::sign(&self.private_key, &Sha256::digest(data))
-        else {
+        let Ok(signature) = self.signer.sign(&Sha256::digest(data)) else {
             tracing::error!("Failed to sign Data Request");
             return None;
         };
@@ -10,9 +10,10 @@ use async_broadcast::{Receiver, Sender};
 use committable::Committable;
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState, OuterConsensus},
-    data::VidDisperseShare,
+    data::{Leaf2, VidDisperseShare},
     epoch_membership::EpochMembershipCoordinator,
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     traits::{
         network::DataRequest,
         node_implementation::{NodeType, Versions},
@@ -40,8 +41,8 @@ pub struct NetworkResponseState<TYPES: NodeType, V: Versions> {
     /// This replicas public key
     pub_key: TYPES::SignatureKey,
 
-    /// This replicas private key
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// This replicas signer
+    signer: Signer<TYPES::SignatureKey>,
 
     /// The node's id
     id: u64,
@@ -56,7 +57,7 @@ impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
         consensus: LockedConsensusState<TYPES>,
         membership: EpochMembershipCoordinator<TYPES>,
         pub_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Signer<TYPES::SignatureKey>,
         id: u64,
         upgrade_lock: UpgradeLock<TYPES, V>,
     ) -> Self {
@@ -64,7 +65,7 @@ impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
             consensus,
             membership,
             pub_key,
-            private_key,
+            signer,
             id,
             upgrade_lock,
         }
@@ -138,6 +139,38 @@ impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
                                 .await;
                             }
                         },
+                        HotShotEvent::QuorumProposalFetchRecv(req, signature) => {
+                            if !req.key.validate(signature, req.commit().as_ref()) {
+                                tracing::warn!("Invalid signature key on proposal fetch request.");
+                                continue;
+                            }
+
+                            // Only answer with the proposal the requester's QC certifies; any other
+                            // proposal for the view is of no use to it.
+                            let quorum_proposal = self
+                                .consensus
+                                .read()
+                                .await
+                                .last_proposals()
+                                .get(&req.view_number)
+                                .filter(|proposal| {
+                                    Leaf2::from_quorum_proposal(&proposal.data).commit()
+                                        == req.leaf_commit
+                                })
+                                .cloned();
+                            if let Some(quorum_proposal) = quorum_proposal {
+                                broadcast_event(
+                                    HotShotEvent::QuorumProposalFetchResponseSend(
+                                        self.pub_key.clone(),
+                                        req.key.clone(),
+                                        quorum_proposal,
+                                    )
+                                    .into(),
+                                    &event_sender,
+                                )
+                                .await;
+                            }
+                        },
                         HotShotEvent::Shutdown => {
                             return;
                         },
@@ -175,7 +208,7 @@ impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
             view,
             target_epoch,
             self.membership.clone(),
-            &self.private_key,
+            &self.signer,
             &self.upgrade_lock,
         )
         .await
@@ -188,7 +221,7 @@ impl<TYPES: NodeType, V: Versions> NetworkResponseState<TYPES, V> {
                 view,
                 target_epoch,
                 self.membership.clone(),
-                &self.private_key,
+                &self.signer,
                 &self.upgrade_lock,
             )
             .await?;
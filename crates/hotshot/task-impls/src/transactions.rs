@@ -5,60 +5,63 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
+    cmp::Ordering,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::Committable;
 use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
-use hotshot_builder_api::{
-    v0_1::block_info::AvailableBlockInfo, v0_2::block_info::AvailableBlockHeaderInputV2,
-};
+use hotshot_builder_api::v0_1::block_info::AvailableBlockInfo;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
     consensus::OuterConsensus,
     data::{null_block, PackedBundle, VidCommitment},
     epoch_membership::EpochMembershipCoordinator,
     event::{Event, EventType},
+    feature_gates::Feature,
+    local_builder::LocalMempool,
     message::UpgradeLock,
+    signer::Signer,
     traits::{
         auction_results_provider::AuctionResultsProvider,
-        block_contents::{BuilderFee, EncodeBytes},
+        block_contents::{BlockLimits, BuilderFee, EncodeBytes},
         node_implementation::{ConsensusTime, HasUrls, NodeImplementation, NodeType, Versions},
         signature_key::{BuilderSignatureKey, SignatureKey},
         BlockPayload,
     },
+    transaction_dedup::TransactionDedup,
     utils::{is_epoch_transition, is_last_block, ViewInner},
+    vote::HasViewNumber,
 };
 use hotshot_utils::anytrace::*;
-use tokio::time::{sleep, timeout};
+use tokio::{
+    spawn,
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
 use tracing::instrument;
 use url::Url;
-use vbs::version::{StaticVersionType, Version};
+use vbs::version::Version;
 use vec1::Vec1;
 
 use crate::{
     builder::{
         v0_1::BuilderClient as BuilderClientBase, v0_99::BuilderClient as BuilderClientMarketplace,
     },
+    bundle_validation::{
+        record_rejection, sandboxed, validate_bundle, BundleRejection, ClaimedBlock,
+    },
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
 };
 
-// Parameters for builder querying algorithm
-
-/// Proportion of builders queried in first batch, dividend
-const BUILDER_MAIN_BATCH_THRESHOLD_DIVIDEND: usize = 2;
-/// Proportion of builders queried in the first batch, divisor
-const BUILDER_MAIN_BATCH_THRESHOLD_DIVISOR: usize = 3;
-/// Time the first batch of builders has to respond
-const BUILDER_MAIN_BATCH_CUTOFF: Duration = Duration::from_millis(700);
-/// Multiplier for extra time to give to the second batch of builders
-const BUILDER_ADDITIONAL_TIME_MULTIPLIER: f32 = 0.2;
-/// Minimum amount of time allotted to both batches, cannot be cut shorter if the first batch
-/// responds extremely fast.
-const BUILDER_MINIMUM_QUERY_TIME: Duration = Duration::from_millis(300);
+/// Fraction of the builder timeout, as a divisor, that builders have to report their available
+/// blocks. The rest of the timeout is left for claiming the chosen block.
+const BUILDER_QUERY_DEADLINE_DIVISOR: u32 = 2;
 /// Delay between re-tries on unsuccessful calls
 const RETRY_DELAY: Duration = Duration::from_millis(100);
 
@@ -74,6 +77,15 @@ pub struct BuilderResponse<TYPES: NodeType> {
     pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
 }
 
+/// A block requested from the builders before the view it is for has started
+pub struct PrefetchedBlock<TYPES: NodeType> {
+    /// The bundle to propose once the view starts
+    pub bundle: PackedBundle<TYPES>,
+
+    /// Commitment of the parent block the bundle was built on
+    pub parent_commitment: VidCommitment,
+}
+
 /// Tracks state of a Transaction task
 pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// The state's api
@@ -95,13 +107,13 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     pub membership_coordinator: EpochMembershipCoordinator<TYPES>,
 
     /// Builder 0.1 API clients
-    pub builder_clients: Vec<BuilderClientBase<TYPES>>,
+    pub builder_clients: Arc<Vec<BuilderClientBase<TYPES>>>,
 
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// InstanceState
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -120,9 +132,46 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Request for the block of our upcoming view as leader, running while the previous view is
+    /// in progress
+    pub prefetch_task: Option<(TYPES::View, JoinHandle<Option<PrefetchedBlock<TYPES>>>)>,
+
+    /// Transactions for the embedded fallback builder, `None` if it is disabled
+    pub local_mempool: Option<LocalMempool<TYPES::Transaction>>,
+
+    /// Transactions seen in recent views, shared with transaction submission, `None` if
+    /// deduplication is disabled
+    pub transaction_dedup: Option<Arc<RwLock<TransactionDedup<TYPES::Transaction>>>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTaskState<TYPES, I, V> {
+    /// A copy of this state for requesting blocks from a spawned task
+    ///
+    /// The copy has no local mempool, so it cannot build blocks itself.
+    fn detached(&self) -> Self {
+        Self {
+            builder_timeout: self.builder_timeout,
+            output_event_stream: self.output_event_stream.clone(),
+            cur_view: self.cur_view,
+            cur_epoch: self.cur_epoch,
+            consensus: self.consensus.clone(),
+            membership_coordinator: self.membership_coordinator.clone(),
+            builder_clients: Arc::clone(&self.builder_clients),
+            public_key: self.public_key.clone(),
+            signer: self.signer.clone(),
+            instance_state: Arc::clone(&self.instance_state),
+            id: self.id,
+            upgrade_lock: self.upgrade_lock.clone(),
+            auction_results_provider: Arc::clone(&self.auction_results_provider),
+            fallback_builder_url: self.fallback_builder_url.clone(),
+            epoch_height: self.epoch_height,
+            prefetch_task: None,
+            local_mempool: None,
+            transaction_dedup: self.transaction_dedup.clone(),
+        }
+    }
+
     /// handle view change decide legacy or not
     pub async fn handle_view_change(
         &mut self,
@@ -138,7 +187,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             },
         };
 
-        if version < V::Marketplace::VERSION {
+        if !Feature::Marketplace.enabled_in::<V>(version) {
             self.handle_view_change_legacy(event_stream, block_view, block_epoch)
                 .await
         } else {
@@ -165,7 +214,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
 
         // Short circuit if we are in epochs and we are likely proposing a transition block
         // If it's the first view of the upgrade, we don't need to check for transition blocks
-        if version >= V::Epochs::VERSION {
+        if Feature::Epochs.enabled_in::<V>(version) {
             let Some(epoch) = block_epoch else {
                 tracing::error!("Epoch is required for epoch-based view change");
                 return None;
@@ -230,8 +279,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 .is_some_and(|cert| cert.upgrading_in(block_view))
             {
                 None
+            } else if let Some(mut bundle) = self.take_prefetched_block(block_view).await {
+                bundle.epoch_number = block_epoch;
+                broadcast_event(Arc::new(HotShotEvent::BlockRecv(bundle)), event_stream).await;
+                return None;
             } else {
-                self.wait_for_block(block_view).await
+                match self.wait_for_block(block_view).await {
+                    Some((_, response)) => Some(response),
+                    // If no builder responded, build a block ourselves rather than proposing an
+                    // empty one.
+                    None => {
+                        self.build_local_block(block_view, block_epoch, version)
+                            .await
+                    },
+                }
             }
         };
 
@@ -261,6 +322,239 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         return None;
     }
 
+    /// Request the block for `block_view` ahead of time if we are its leader.
+    ///
+    /// This runs once the proposal for the previous view is known, so the builders can be
+    /// queried while that view is still being dispersed and voted on. The request runs in its own
+    /// task so it does not hold up the events of the current view. The bundle is announced with
+    /// `HotShotEvent::BlockPrefetched` so the other leader tasks can begin their own
+    /// precomputation, and is proposed when the view change to `block_view` arrives.
+    #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, block_view = *block_view), name = "Transaction task", level = "error", target = "TransactionTaskState")]
+    async fn prefetch_block(
+        &mut self,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+        block_view: TYPES::View,
+    ) -> Result<()> {
+        ensure!(
+            block_view > self.cur_view,
+            debug!("Not prefetching a block for view {block_view}, which has already started")
+        );
+        ensure!(
+            self.prefetch_task
+                .as_ref()
+                .is_none_or(|(prefetch_view, _)| *prefetch_view < block_view),
+            debug!("Block for view {block_view} was already prefetched")
+        );
+        let version = self.upgrade_lock.version(block_view).await?;
+        ensure!(
+            !Feature::Marketplace.enabled_in::<V>(version),
+            debug!("Blocks are not prefetched for marketplace views")
+        );
+        ensure!(
+            !self
+                .upgrade_lock
+                .decided_upgrade_certificate
+                .read()
+                .await
+                .as_ref()
+                .is_some_and(|cert| cert.upgrading_in(block_view)),
+            debug!("Not prefetching a block for view {block_view} while upgrading")
+        );
+        let leader = self
+            .membership_coordinator
+            .membership_for_epoch(self.cur_epoch)
+            .await?
+            .leader(block_view)
+            .await?;
+        if leader != self.public_key {
+            return Ok(());
+        }
+
+        let state = self.detached();
+        let event_stream = event_stream.clone();
+        let task = spawn(async move {
+            let Some((parent_commitment, response)) = state.wait_for_block(block_view).await else {
+                tracing::info!("Failed to prefetch a block for view {block_view}");
+                return None;
+            };
+            let BuilderResponse {
+                fee,
+                block_payload,
+                metadata,
+            } = response;
+            let bundle = PackedBundle::new(
+                block_payload.encode(),
+                metadata,
+                block_view,
+                state.cur_epoch,
+                vec1::vec1![fee],
+                None,
+            );
+
+            broadcast_event(
+                Arc::new(HotShotEvent::BlockPrefetched(bundle.clone())),
+                &event_stream,
+            )
+            .await;
+            Some(PrefetchedBlock {
+                bundle,
+                parent_commitment,
+            })
+        });
+        if let Some((_, stale)) = self.prefetch_task.replace((block_view, task)) {
+            stale.abort();
+        }
+
+        Ok(())
+    }
+
+    /// Take the prefetched block for `block_view`, if it still builds on the current parent
+    ///
+    /// Waits for the request if it is still in flight, which saves starting over with a new one.
+    async fn take_prefetched_block(
+        &mut self,
+        block_view: TYPES::View,
+    ) -> Option<PackedBundle<TYPES>> {
+        let (_, task) = self
+            .prefetch_task
+            .take_if(|(prefetch_view, _)| *prefetch_view == block_view)?;
+        let prefetched = task.await.ok().flatten()?;
+        match self.last_vid_commitment(block_view).await {
+            Ok((_, parent_commitment)) if parent_commitment == prefetched.parent_commitment => {
+                Some(prefetched.bundle)
+            },
+            _ => {
+                tracing::info!(
+                    "Parent of view {block_view} changed since its block was prefetched, requesting a new one"
+                );
+                None
+            },
+        }
+    }
+
+    /// The state of `parent_view`, or the latest decided state if we do not have it
+    async fn parent_state(&self, parent_view: Option<TYPES::View>) -> Arc<TYPES::ValidatedState> {
+        let consensus = self.consensus.read().await;
+        parent_view
+            .and_then(|view| consensus.state(view).cloned())
+            .unwrap_or_else(|| consensus.decided_state())
+    }
+
+    /// Limits on the contents of blocks built on the state of `parent_view`
+    ///
+    /// Validators check a block against the limits of the state it extends, so a block we propose
+    /// must respect the same. Without a parent, such as for the local mempool, the limits of the
+    /// latest decided state are used. Falls back to no limits if they cannot be determined,
+    /// leaving it to validators to reject a block which exceeds them.
+    async fn block_limits(&self, parent_view: Option<TYPES::View>) -> BlockLimits {
+        let validated_state = self.parent_state(parent_view).await;
+        <TYPES::BlockPayload as BlockPayload<TYPES>>::limits(&validated_state, &self.instance_state)
+            .await
+            .unwrap_or_else(|err| {
+                tracing::warn!("Failed to determine block limits: {err}");
+                BlockLimits::default()
+            })
+    }
+
+    /// Build a block for `block_view` from the local mempool, for when no builder responded
+    ///
+    /// Returns `None` if the embedded builder is disabled, has no transactions, or blocks on the
+    /// parent state must pay a fee.
+    async fn build_local_block(
+        &mut self,
+        block_view: TYPES::View,
+        block_epoch: Option<TYPES::Epoch>,
+        version: Version,
+    ) -> Option<BuilderResponse<TYPES>> {
+        let transactions = {
+            let mempool = self.local_mempool.as_ref()?;
+            if mempool.is_empty() {
+                return None;
+            }
+            mempool.transactions().cloned().collect::<Vec<_>>()
+        };
+
+        let num_storage_nodes = match self
+            .membership_coordinator
+            .stake_table_for_epoch(block_epoch)
+            .await
+        {
+            Ok(epoch_stake_table) => epoch_stake_table.total_nodes().await,
+            Err(e) => {
+                tracing::warn!("Failed to get num_storage_nodes for epoch {block_epoch:?}: {e}");
+                return None;
+            },
+        };
+
+        // Build on the same state as a block from a builder would, see `block_limits`.
+        let parent_view = self
+            .last_vid_commitment(block_view)
+            .await
+            .ok()
+            .map(|(view, _)| view);
+        let validated_state = self.parent_state(parent_view).await;
+
+        // A local block pays no fee, so it would be rejected where blocks must pay one.
+        match <TYPES::BlockPayload as BlockPayload<TYPES>>::requires_fee(
+            &validated_state,
+            &self.instance_state,
+        )
+        .await
+        {
+            Ok(false) => {},
+            Ok(true) => {
+                tracing::debug!(
+                    "Not building a local block for view {block_view}, blocks must pay a fee"
+                );
+                return None;
+            },
+            Err(e) => {
+                tracing::warn!("Failed to determine whether blocks must pay a fee: {e}");
+                return None;
+            },
+        }
+
+        let (block_payload, metadata) =
+            match <TYPES::BlockPayload as BlockPayload<TYPES>>::from_transactions(
+                transactions,
+                &validated_state,
+                &self.instance_state,
+            )
+            .await
+            {
+                Ok(block) => block,
+                Err(e) => {
+                    tracing::warn!("Failed to build a local block for view {block_view}: {e}");
+                    return None;
+                },
+            };
+
+        let fee = null_block::local_builder_fee::<TYPES, V>(
+            num_storage_nodes,
+            version,
+            *block_view,
+            &block_payload,
+            &metadata,
+        )?;
+
+        // The transactions which fit in the block are no longer pending, at least as far as we
+        // are concerned. Those which did not fit stay for the next block.
+        let included = block_payload.transaction_commitments(&metadata);
+        tracing::info!(
+            "No builder responded for view {block_view}, proposing a local block with {} transactions",
+            included.len()
+        );
+        if let Some(mempool) = &mut self.local_mempool {
+            mempool.remove(included);
+        }
+
+        Some(BuilderResponse {
+            fee,
+            block_payload,
+            metadata,
+        })
+    }
+
     /// Send the event to the event stream that we are proposing an empty block
     async fn send_empty_block(
         &self,
@@ -354,7 +648,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         let maybe_auction_result = timeout(
             self.builder_timeout,
             self.auction_results_provider
-                .fetch_auction_result(block_view),
+                .fetch_auction_result(block_view, block_epoch),
         )
         .await
         .wrap()
@@ -395,13 +689,37 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         }
 
+        // Check the bundles in isolation before committing to propose them.
+        let validated = join_all(bundles.into_iter().map(|bundle| {
+            let builder = bundle.sequencing_fee.fee_account.clone();
+            let view = *block_view;
+            async move {
+                (
+                    builder,
+                    sandboxed(move || validate_bundle(bundle, view)).await,
+                )
+            }
+        }))
+        .await;
+
         let mut sequencing_fees = Vec::new();
         let mut transactions: Vec<<TYPES::BlockPayload as BlockPayload<TYPES>>::Transaction> =
             Vec::new();
 
-        for bundle in bundles {
-            sequencing_fees.push(bundle.sequencing_fee);
-            transactions.extend(bundle.transactions);
+        for (builder, bundle) in validated {
+            match bundle {
+                Ok(bundle) => {
+                    sequencing_fees.push(bundle.sequencing_fee);
+                    transactions.extend(bundle.transactions);
+                },
+                Err(reason) => {
+                    record_rejection::<TYPES>(
+                        &self.consensus.read().await.metrics,
+                        &builder,
+                        reason,
+                    );
+                },
+            }
         }
 
         let validated_state = self.consensus.read().await.decided_state();
@@ -523,17 +841,78 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
     ) -> Result<()> {
         match event.as_ref() {
             HotShotEvent::TransactionsRecv(transactions) => {
+                // Drop transactions which are pending or were recently included in a block, so
+                // they are not built into another one.
+                let transactions = match &self.transaction_dedup {
+                    Some(dedup) => {
+                        let mut dedup = dedup.write().await;
+                        let fresh = transactions
+                            .iter()
+                            .filter(|tx| dedup.insert(*self.cur_view, tx.commit()))
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        if fresh.len() < transactions.len() {
+                            tracing::debug!(
+                                "Dropped {} transactions seen in recent views",
+                                transactions.len() - fresh.len()
+                            );
+                        }
+                        drop(dedup);
+                        if fresh.is_empty() {
+                            return Ok(());
+                        }
+                        fresh
+                    },
+                    None => transactions.clone(),
+                };
+
+                if let Some(mempool) = &mut self.local_mempool {
+                    mempool.insert(*self.cur_view, transactions.iter().cloned());
+                }
                 broadcast_event(
                     Event {
                         view_number: self.cur_view,
-                        event: EventType::Transactions {
-                            transactions: transactions.clone(),
-                        },
+                        event: EventType::Transactions { transactions },
                     },
                     &self.output_event_stream,
                 )
                 .await;
             },
+            HotShotEvent::DaProposalValidated(proposal, _) => {
+                // Transactions proposed by other leaders need not be built into our blocks.
+                if let Some(mempool) = &mut self.local_mempool {
+                    let payload = <TYPES::BlockPayload as BlockPayload<TYPES>>::from_bytes(
+                        &proposal.data.encoded_transactions,
+                        &proposal.data.metadata,
+                    );
+                    mempool.remove(payload.transaction_commitments(&proposal.data.metadata));
+                }
+            },
+            HotShotEvent::LeavesDecided(leaves) => {
+                // Only decided transactions are sequenced; those merely proposed may still be lost
+                // in a failed view.
+                if let Some(dedup) = &self.transaction_dedup {
+                    let mut dedup = dedup.write().await;
+                    for leaf in leaves {
+                        if let Some(payload) = leaf.block_payload() {
+                            dedup.include(
+                                *leaf.view_number(),
+                                payload.transaction_commitments(leaf.block_header().metadata()),
+                            );
+                        }
+                    }
+                }
+            },
+            HotShotEvent::Timeout(view, _) => {
+                // Transactions pending in a failed view must be accepted again if resubmitted.
+                if let Some(dedup) = &self.transaction_dedup {
+                    dedup.write().await.fail(**view);
+                }
+            },
+            HotShotEvent::QuorumProposalPreliminarilyValidated(proposal) => {
+                self.prefetch_block(&event_stream, proposal.data.view_number() + 1)
+                    .await?;
+            },
             HotShotEvent::ViewChange(view, epoch) => {
                 let view = TYPES::View::new(std::cmp::max(1, **view));
                 let epoch = if self.upgrade_lock.epochs_enabled(view).await {
@@ -555,6 +934,22 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 );
                 self.cur_view = view;
                 self.cur_epoch = epoch;
+                if let Some((_, stale)) = self
+                    .prefetch_task
+                    .take_if(|(prefetch_view, _)| *prefetch_view < view)
+                {
+                    stale.abort();
+                }
+                if let Some(dedup) = &self.transaction_dedup {
+                    dedup.write().await.expire(*view);
+                }
+                if self.local_mempool.is_some() {
+                    let limits = self.block_limits(None).await;
+                    if let Some(mempool) = &mut self.local_mempool {
+                        mempool.expire(*view);
+                        mempool.set_limits(limits);
+                    }
+                }
 
                 let leader = self
                     .membership_coordinator
@@ -637,8 +1032,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         }
     }
 
+    /// Request a block for `block_view` from the builders, returning it along with the
+    /// commitment of the parent block it was built on.
     #[instrument(skip_all, fields(id = self.id, cur_view = *self.cur_view, block_view = *block_view), name = "wait_for_block", level = "error")]
-    async fn wait_for_block(&self, block_view: TYPES::View) -> Option<BuilderResponse<TYPES>> {
+    async fn wait_for_block(
+        &self,
+        block_view: TYPES::View,
+    ) -> Option<(VidCommitment, BuilderResponse<TYPES>)> {
         let task_start_time = Instant::now();
 
         // Find commitment to the block we want to build upon
@@ -653,10 +1053,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             },
         };
 
-        let parent_comm_sig = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-            &self.private_key,
-            parent_comm.as_ref(),
-        ) {
+        let parent_comm_sig = match self.signer.sign(parent_comm.as_ref()) {
             Ok(sig) => sig,
             Err(err) => {
                 tracing::error!(%err, "Failed to sign block hash");
@@ -674,7 +1071,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             {
                 // We got a block
                 Ok(Ok(block)) => {
-                    return Some(block);
+                    return Some((parent_comm, block));
                 },
 
                 // We failed to get a block
@@ -697,61 +1094,54 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         None
     }
 
-    /// Query the builders for available blocks. Queries only fraction of the builders
-    /// based on the response time.
+    /// Query all the builders concurrently for available blocks.
+    ///
+    /// Returns the blocks offered by every builder which responded before the deadline, with the
+    /// index of the builder which offered each.
     async fn get_available_blocks(
         &self,
         parent_comm: VidCommitment,
         view_number: TYPES::View,
         parent_comm_sig: &<<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     ) -> Vec<(AvailableBlockInfo<TYPES>, usize)> {
+        let metrics = Arc::clone(&self.consensus.read().await.metrics);
+        let metrics = &metrics;
         let tasks = self
             .builder_clients
             .iter()
             .enumerate()
             .map(|(builder_idx, client)| async move {
-                client
+                let start = Instant::now();
+                let result = client
                     .available_blocks(
                         parent_comm,
                         view_number.u64(),
                         self.public_key.clone(),
                         parent_comm_sig,
                     )
-                    .await
-                    .map(move |blocks| {
-                        blocks
-                            .into_iter()
-                            .map(move |block_info| (block_info, builder_idx))
-                    })
+                    .await;
+                metrics
+                    .builder_response_latency
+                    .create(vec![client.url().to_string()])
+                    .add_point(start.elapsed().as_secs_f64());
+                result.map(move |blocks| {
+                    blocks
+                        .into_iter()
+                        .map(move |block_info| (block_info, builder_idx))
+                })
             })
             .collect::<FuturesUnordered<_>>();
-        let mut results = Vec::with_capacity(self.builder_clients.len());
-        let query_start = Instant::now();
-        let threshold = (self.builder_clients.len() * BUILDER_MAIN_BATCH_THRESHOLD_DIVIDEND)
-            .div_ceil(BUILDER_MAIN_BATCH_THRESHOLD_DIVISOR);
-        let mut tasks = tasks.take(threshold);
-        while let Some(result) = tasks.next().await {
-            results.push(result);
-            if query_start.elapsed() > BUILDER_MAIN_BATCH_CUTOFF {
-                break;
-            }
-        }
-        let timeout = sleep(std::cmp::max(
-            query_start
-                .elapsed()
-                .mul_f32(BUILDER_ADDITIONAL_TIME_MULTIPLIER),
-            BUILDER_MINIMUM_QUERY_TIME.saturating_sub(query_start.elapsed()),
-        ));
-        futures::pin_mut!(timeout);
-        let mut tasks = tasks.into_inner().take_until(timeout);
-        while let Some(result) = tasks.next().await {
-            results.push(result);
-        }
-        results
+
+        let deadline = sleep(self.builder_timeout / BUILDER_QUERY_DEADLINE_DIVISOR);
+        futures::pin_mut!(deadline);
+        tasks
+            .take_until(deadline)
+            .filter_map(|result| async move { result.ok() })
+            .collect::<Vec<_>>()
+            .await
             .into_iter()
-            .filter_map(|result| result.ok())
             .flatten()
-            .collect::<Vec<_>>()
+            .collect()
     }
 
     /// Get a block from builder.
@@ -771,18 +1161,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         let mut available_blocks = self
             .get_available_blocks(parent_comm, view_number, parent_comm_sig)
             .await;
+        let limits = self.block_limits(Some(view_number)).await;
 
-        available_blocks.sort_by(|(l, _), (r, _)| {
-            // We want the block with the highest fee per byte of data we're going to have to
-            // process, thus our comparison function is:
-            //      (l.offered_fee / l.block_size) < (r.offered_fee / r.block_size)
-            // To avoid floating point math (which doesn't even have an `Ord` impl) we multiply
-            // through by the denominators to get
-            //      l.offered_fee * r.block_size < r.offered_fee * l.block_size
-            // We cast up to u128 to avoid overflow.
-            (u128::from(l.offered_fee) * u128::from(r.block_size))
-                .cmp(&(u128::from(r.offered_fee) * u128::from(l.block_size)))
-        });
+        available_blocks.sort_by(compare_offers);
 
         if available_blocks.is_empty() {
             tracing::info!("No available blocks");
@@ -798,13 +1179,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 &block_info.block_hash,
             ) {
                 tracing::warn!("Failed to verify available block info response message signature");
+                record_rejection::<TYPES>(
+                    &self.consensus.read().await.metrics,
+                    &block_info.sender,
+                    BundleRejection::InvalidSignature,
+                );
                 continue;
             }
 
-            let request_signature = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-                &self.private_key,
-                block_info.block_hash.as_ref(),
-            ) {
+            let request_signature = match self.signer.sign(block_info.block_hash.as_ref()) {
                 Ok(request_signature) => request_signature,
                 Err(err) => {
                     tracing::error!(%err, "Failed to sign block hash");
@@ -812,114 +1195,80 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 },
             };
 
-            let response = {
-                let client = &self.builder_clients[builder_idx];
+            let client = &self.builder_clients[builder_idx];
 
-                let (block, header_input, legacy_header_input) = futures::join! {
-                    client.claim_block(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature),
-                    client.claim_block_header_input(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature),
-                    client.claim_legacy_block_header_input(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature)
-                };
-
-                let block_data = match block {
-                    Ok(block_data) => block_data,
-                    Err(err) => {
-                        tracing::warn!(%err, "Error claiming block data");
-                        continue;
-                    },
-                };
-
-                let header_input = match (header_input, legacy_header_input) {
-                    (Ok(header_input), Ok(legacy_header_input)) => {
-                        // verify the message signature and the fee_signature
-                        if header_input
-                            .validate_signature(block_info.offered_fee, &block_data.metadata)
-                        {
-                            header_input
-                        } else if legacy_header_input
-                            .validate_signature(block_info.offered_fee, &block_data.metadata)
-                        {
-                            AvailableBlockHeaderInputV2 {
-                                fee_signature: legacy_header_input.fee_signature,
-                                sender: legacy_header_input.sender,
-                            }
-                        } else {
-                            tracing::warn!(
-                              "Failed to verify available new or legacy block header input data response message signature"
-                            );
-                            continue;
-                        }
-                    },
-                    (Ok(header_input), _) => {
-                        // verify the message signature and the fee_signature
-                        if !header_input
-                            .validate_signature(block_info.offered_fee, &block_data.metadata)
-                        {
-                            tracing::warn!(
-                              "Failed to verify available new block header input data response message signature"
-                            );
-                            continue;
-                        }
-
-                        header_input
-                    },
-                    (Err(_), Ok(legacy_header_input)) => {
-                        // verify the message signature and the fee_signature
-                        if !legacy_header_input
-                            .validate_signature(block_info.offered_fee, &block_data.metadata)
-                        {
-                            tracing::warn!(
-                              "Failed to verify available legacy block header input data response message signature"
-                            );
-                            continue;
-                        }
-                        AvailableBlockHeaderInputV2 {
-                            fee_signature: legacy_header_input.fee_signature,
-                            sender: legacy_header_input.sender,
-                        }
-                    },
-                    (Err(err1), Err(err2)) => {
-                        tracing::warn!(%err1, %err2, "Error claiming header input");
-                        continue;
-                    },
-                };
-
-                // verify the signature over the message
-                if !block_data.validate_signature() {
-                    tracing::warn!(
-                        "Failed to verify available block data response message signature"
-                    );
-                    continue;
-                }
+            let (block, header_input, legacy_header_input) = futures::join! {
+                client.claim_block(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature),
+                client.claim_block_header_input(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature),
+                client.claim_legacy_block_header_input(block_info.block_hash.clone(), view_number.u64(), self.public_key.clone(), &request_signature)
+            };
 
-                // verify the message signature and the fee_signature
-                if !header_input.validate_signature(block_info.offered_fee, &block_data.metadata) {
-                    tracing::warn!(
-                        "Failed to verify available block header input data response message signature"
-                    );
+            let block_data = match block {
+                Ok(block_data) => block_data,
+                Err(err) => {
+                    tracing::warn!(%err, "Error claiming block data");
                     continue;
-                }
+                },
+            };
 
-                let fee = BuilderFee {
-                    fee_amount: block_info.offered_fee,
-                    fee_account: header_input.sender,
-                    fee_signature: header_input.fee_signature,
-                };
+            if let (Err(err1), Err(err2)) = (&header_input, &legacy_header_input) {
+                tracing::warn!(%err1, %err2, "Error claiming header input");
+                continue;
+            }
 
-                BuilderResponse {
-                    fee,
-                    block_payload: block_data.block_payload,
-                    metadata: block_data.metadata,
-                }
+            // Check the claimed block in isolation before committing to propose it.
+            let builder = block_info.sender.clone();
+            let claimed = ClaimedBlock {
+                info: block_info,
+                data: block_data,
+                header_input: header_input.ok(),
+                legacy_header_input: legacy_header_input.ok(),
+                limits,
             };
-
-            return Ok(response);
+            match sandboxed(move || claimed.validate()).await {
+                Ok(response) => {
+                    self.consensus
+                        .read()
+                        .await
+                        .metrics
+                        .builder_wins
+                        .create(vec![client.url().to_string()])
+                        .add(1);
+                    return Ok(response);
+                },
+                Err(reason) => {
+                    record_rejection::<TYPES>(
+                        &self.consensus.read().await.metrics,
+                        &builder,
+                        reason,
+                    );
+                },
+            }
         }
 
         bail!("Couldn't claim a block from any of the builders");
     }
 }
 
+/// Order block offers from the best to the worst.
+///
+/// We want the block with the highest fee per byte of data we're going to have to process. Ties are
+/// broken by the highest total fee, then by the order in which the builders are configured, then by
+/// block hash, so that the choice among any set of offers is deterministic.
+fn compare_offers<TYPES: NodeType>(
+    (l, l_idx): &(AvailableBlockInfo<TYPES>, usize),
+    (r, r_idx): &(AvailableBlockInfo<TYPES>, usize),
+) -> Ordering {
+    // To avoid floating point math (which doesn't even have an `Ord` impl) we compare
+    //      r.offered_fee / r.block_size < l.offered_fee / l.block_size
+    // by multiplying through by the denominators. We cast up to u128 to avoid overflow.
+    (u128::from(r.offered_fee) * u128::from(l.block_size))
+        .cmp(&(u128::from(l.offered_fee) * u128::from(r.block_size)))
+        .then_with(|| r.offered_fee.cmp(&l.offered_fee))
+        .then_with(|| l_idx.cmp(r_idx))
+        .then_with(|| l.block_hash.as_ref().cmp(r.block_hash.as_ref()))
+}
+
 #[async_trait]
 /// task state implementation for Transactions Task
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
@@ -936,5 +1285,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
         self.handle(event, sender.clone()).await
     }
 
-    fn cancel_subtasks(&mut self) {}
+    fn cancel_subtasks(&mut self) {
+        if let Some((_, task)) = self.prefetch_task.take() {
+            task.abort();
+        }
+    }
 }
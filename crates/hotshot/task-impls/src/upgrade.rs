@@ -15,14 +15,17 @@ use hotshot_types::{
     data::UpgradeProposal,
     epoch_membership::EpochMembershipCoordinator,
     event::{Event, EventType},
+    feature_gates::Feature,
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_certificate::UpgradeCertificate,
     simple_vote::{UpgradeProposalData, UpgradeVote},
     traits::{
         block_contents::BlockHeader,
         node_implementation::{ConsensusTime, NodeType, Versions},
-        signature_key::SignatureKey,
+        signature_key::StakeTableEntryType,
     },
+    upgrade_readiness::UpgradeReadinessReport,
     utils::{epoch_from_block_number, EpochTransitionIndicator},
     vote::HasViewNumber,
 };
@@ -56,8 +59,8 @@ pub struct UpgradeTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// This Nodes signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// This state's ID
     pub id: u64,
@@ -109,6 +112,71 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
             .is_some()
     }
 
+    /// Check whether the nodes supporting the upgrade hold enough stake to certify it, reporting
+    /// the result to the application.
+    ///
+    /// If no node has reported its supported versions, the network is assumed to be ready. If the
+    /// stake table is not available, it is not, and the upgrade is retried in a later view.
+    async fn ready_to_propose(&self) -> bool {
+        let readiness = self.consensus.read().await.upgrade_readiness.clone();
+        if !readiness.has_reports() {
+            return true;
+        }
+
+        let membership = match self
+            .membership_coordinator
+            .membership_for_epoch(self.cur_epoch)
+            .await
+        {
+            Ok(membership) => membership,
+            Err(err) => {
+                tracing::warn!("Not proposing upgrade: failed to get the stake table: {err}");
+                return false;
+            },
+        };
+        let stake_table = membership.stake_table().await;
+        let mut supporting_stake = readiness.supporting_stake(
+            stake_table.iter().map(|peer| {
+                (
+                    peer.stake_table_entry.public_key(),
+                    peer.stake_table_entry.stake(),
+                )
+            }),
+            V::Upgrade::VERSION,
+        );
+        // This node supports the upgrade it is configured for, whether or not it has heard its own
+        // report.
+        if !readiness.supports(&self.public_key, V::Upgrade::VERSION) {
+            if let Some(peer) = membership.stake(&self.public_key).await {
+                supporting_stake += peer.stake_table_entry.stake();
+            }
+        }
+        let report = UpgradeReadinessReport {
+            version: V::Upgrade::VERSION,
+            supporting_stake,
+            threshold: membership.upgrade_threshold().await,
+        };
+
+        broadcast_event(
+            Event {
+                view_number: self.cur_view,
+                event: EventType::UpgradeReadiness { report },
+            },
+            &self.output_event_stream,
+        )
+        .await;
+
+        if !report.is_ready() {
+            tracing::warn!(
+                "Not proposing upgrade to {}: supporting stake {} is below the threshold {}",
+                report.version,
+                report.supporting_stake,
+                report.threshold
+            );
+        }
+        report.is_ready()
+    }
+
     /// main task event handler
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = self.cur_epoch.map(|x| *x)), name = "Upgrade Task", level = "error")]
     pub async fn handle(
@@ -221,7 +289,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                     proposal.data.upgrade_proposal.clone(),
                     view,
                     &self.public_key,
-                    &self.private_key,
+                    &self.signer,
                     &self.upgrade_lock,
                 )
                 .await?;
@@ -247,12 +315,14 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                     )
                 );
 
+                let consensus_metrics = Arc::clone(&self.consensus.read().await.metrics);
                 handle_vote(
                     &mut self.vote_collectors,
                     vote,
                     self.public_key.clone(),
                     &epoch_membership,
                     self.id,
+                    &consensus_metrics,
                     &event,
                     &tx,
                     &self.upgrade_lock,
@@ -290,7 +360,8 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                 let new_version_first_view = view + TYPES::UPGRADE_CONSTANTS.finish_offset;
                 let decide_by = view + TYPES::UPGRADE_CONSTANTS.decide_by_offset;
 
-                let epoch_upgrade_checks = if V::Upgrade::VERSION == V::Epochs::VERSION {
+                let upgrades_to_epochs = V::Upgrade::VERSION == Feature::Epochs.version::<V>();
+                let epoch_upgrade_checks = if upgrades_to_epochs {
                     let consensus_reader = self.consensus.read().await;
 
                     let (_, last_proposal) = consensus_reader.last_proposals().last_key_value().context(info!("No recent quorum proposals in consensus state -- skipping upgrade proposal."))?;
@@ -321,6 +392,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                     && !self.upgraded().await
                     && epoch_upgrade_checks
                     && leader == self.public_key
+                    && self.ready_to_propose().await
                 {
                     let upgrade_proposal_data = UpgradeProposalData {
                         old_version: V::Base::VERSION,
@@ -338,11 +410,10 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                         ),
                     };
 
-                    let signature = TYPES::SignatureKey::sign(
-                        &self.private_key,
-                        upgrade_proposal_data.commit().as_ref(),
-                    )
-                    .expect("Failed to sign upgrade proposal commitment!");
+                    let signature = self
+                        .signer
+                        .sign(upgrade_proposal_data.commit().as_ref())
+                        .expect("Failed to sign upgrade proposal commitment!");
 
                     tracing::warn!("Sending upgrade proposal:\n\n {upgrade_proposal:?}");
 
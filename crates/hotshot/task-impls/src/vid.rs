@@ -7,28 +7,29 @@
 use std::{marker::PhantomData, sync::Arc};
 
 use async_broadcast::{Receiver, Sender};
+use async_lock::Mutex;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::{supervisor::TaskSupervisor, task::TaskState};
 use hotshot_types::{
     consensus::{OuterConsensus, PayloadWithMetadata},
-    data::{PackedBundle, VidDisperse, VidDisperseShare},
+    data::{PackedBundle, VidDisperseShare},
     epoch_membership::EpochMembershipCoordinator,
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
         node_implementation::{NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
         BlockPayload,
     },
     utils::{is_epoch_transition, option_epoch_from_block_number},
 };
-use hotshot_utils::anytrace::Result;
+use hotshot_utils::anytrace::{Log, Result};
 use tracing::{debug, error, info, instrument};
 
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
-    helpers::broadcast_event,
+    helpers::{broadcast_event, calculate_vid_disperse, VidDisperseCache},
 };
 
 /// Tracks state of a VID task
@@ -51,8 +52,8 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versio
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// This state's ID
     pub id: u64,
@@ -62,9 +63,30 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versio
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Recently calculated dispersals, reused when a payload is proposed again
+    pub vid_disperse_cache: Arc<Mutex<VidDisperseCache<TYPES>>>,
+
+    /// In-flight VID dispersal calculations, keyed by the view they are for
+    pub vid_disperse_tasks: TaskSupervisor<TYPES::View>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> VidTaskState<TYPES, I, V> {
+    /// Cancel the dispersal calculations for views older than the current view.
+    ///
+    /// The calculations are told to stop, which they do at the next point the blocking
+    /// computation checks for cancellation, and no dispersal is sent for the stale views.
+    fn cancel_stale_disperse_tasks(&mut self) {
+        let cancelled = self.vid_disperse_tasks.cancel_before(&self.cur_view);
+
+        if cancelled > 0 {
+            debug!(
+                "Cancelling {cancelled} VID disperse calculations for views before {:?}",
+                self.cur_view
+            );
+        }
+    }
+
     /// main task event handler
     #[instrument(skip_all, fields(id = self.id, view = *self.cur_view, epoch = self.cur_epoch.map(|x| *x)), name = "VID Main Task", level = "error", target = "VidTaskState")]
     pub async fn handle(
@@ -101,73 +123,127 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> VidTaskState<TY
                     );
                     return None;
                 }
-                let vid_disperse = VidDisperse::calculate_vid_disperse::<V>(
-                    &payload,
-                    &self.membership_coordinator,
-                    *view_number,
-                    epoch,
-                    epoch,
-                    metadata,
-                    &self.upgrade_lock,
-                )
-                .await
-                .ok()?;
-                let payload_commitment = vid_disperse.payload_commitment();
-                let shares = VidDisperseShare::from_vid_disperse(vid_disperse.clone());
-                let payload_with_metadata = Arc::new(PayloadWithMetadata {
-                    payload,
-                    metadata: metadata.clone(),
-                });
-
-                let mut consensus_writer = self.consensus.write().await;
-                // Make sure we save the payload; we might need it to send the next epoch VID shares.
-                if let Err(e) =
-                    consensus_writer.update_saved_payloads(*view_number, payload_with_metadata)
-                {
-                    tracing::debug!(error=?e);
-                }
-                for share in shares {
-                    if let Some(share) = share.to_proposal(&self.private_key) {
-                        consensus_writer.update_vid_shares(*view_number, share);
-                    }
-                }
-                drop(consensus_writer);
-
-                // send the commitment and metadata to consensus for block building
-                broadcast_event(
-                    Arc::new(HotShotEvent::SendPayloadCommitmentAndMetadata(
-                        payload_commitment,
-                        builder_commitment,
-                        metadata.clone(),
-                        *view_number,
-                        sequencing_fees.clone(),
-                        auction_result.clone(),
-                    )),
-                    &event_stream,
-                )
-                .await;
 
                 let view_number = *view_number;
-                let Ok(signature) = TYPES::SignatureKey::sign(
-                    &self.private_key,
-                    vid_disperse.payload_commitment_ref(),
-                ) else {
-                    error!("VID: failed to sign dispersal payload");
-                    return None;
-                };
-                debug!("publishing VID disperse for view {view_number} and epoch {epoch:?}");
-                broadcast_event(
-                    Arc::new(HotShotEvent::VidDisperseSend(
-                        Proposal {
-                            signature,
-                            data: vid_disperse,
-                            _pd: PhantomData,
-                        },
-                        self.public_key.clone(),
-                    )),
-                    &event_stream,
-                )
-                .await;
+                let metadata = metadata.clone();
+                let sequencing_fees = sequencing_fees.clone();
+                let auction_result = auction_result.clone();
+                let consensus = self.consensus.clone();
+                let membership_coordinator = self.membership_coordinator.clone();
+                let upgrade_lock = self.upgrade_lock.clone();
+                let cache = Arc::clone(&self.vid_disperse_cache);
+                let public_key = self.public_key.clone();
+                let signer = self.signer.clone();
+                let metrics = Arc::clone(&consensus.read().await.metrics);
+                self.vid_disperse_tasks
+                    .spawn_cancellable(view_number, |cancelled| async move {
+                        let Ok(Some(vid_disperse)) = calculate_vid_disperse::<TYPES, V>(
+                            &cache,
+                            &payload,
+                            &membership_coordinator,
+                            view_number,
+                            epoch,
+                            epoch,
+                            &metadata,
+                            &upgrade_lock,
+                            &metrics,
+                            cancelled,
+                        )
+                        .await
+                        else {
+                            return;
+                        };
+                        let payload_commitment = vid_disperse.payload_commitment();
+                        let shares = VidDisperseShare::from_vid_disperse(vid_disperse.clone());
+                        let payload_with_metadata = Arc::new(PayloadWithMetadata {
+                            payload,
+                            metadata: metadata.clone(),
+                        });
+
+                        let mut consensus_writer = consensus.write().await;
+                        // Make sure we save the payload; we might need it to send the next epoch VID shares.
+                        if let Err(e) = consensus_writer
+                            .update_saved_payloads(view_number, payload_with_metadata)
+                        {
+                            e.log();
+                        }
+                        for share in shares {
+                            if let Some(share) = share.to_proposal(&signer) {
+                                consensus_writer.update_vid_shares(view_number, share);
+                            }
+                        }
+                        drop(consensus_writer);
+
+                        // send the commitment and metadata to consensus for block building
+                        broadcast_event(
+                            Arc::new(HotShotEvent::SendPayloadCommitmentAndMetadata(
+                                payload_commitment,
+                                builder_commitment,
+                                metadata,
+                                view_number,
+                                sequencing_fees,
+                                auction_result,
+                            )),
+                            &event_stream,
+                        )
+                        .await;
+
+                        let Ok(signature) = signer.sign(vid_disperse.payload_commitment_ref())
+                        else {
+                            error!("VID: failed to sign dispersal payload");
+                            return;
+                        };
+                        debug!(
+                            "publishing VID disperse for view {view_number} and epoch {epoch:?}"
+                        );
+                        broadcast_event(
+                            Arc::new(HotShotEvent::VidDisperseSend(
+                                Proposal {
+                                    signature,
+                                    data: vid_disperse,
+                                    _pd: PhantomData,
+                                },
+                                public_key,
+                            )),
+                            &event_stream,
+                        )
+                        .await;
+                    });
+            },
+
+            HotShotEvent::BlockPrefetched(packed_bundle) => {
+                // Calculate the dispersal for our upcoming view ahead of time, so it is
+                // served from the cache once the block is proposed.
+                let view_number = packed_bundle.view_number;
+                let payload = <TYPES as NodeType>::BlockPayload::from_bytes(
+                    &packed_bundle.encoded_transactions,
+                    &packed_bundle.metadata,
+                );
+                let metadata = packed_bundle.metadata.clone();
+                let epoch = self.cur_epoch;
+                let membership_coordinator = self.membership_coordinator.clone();
+                let upgrade_lock = self.upgrade_lock.clone();
+                let cache = Arc::clone(&self.vid_disperse_cache);
+                let metrics = Arc::clone(&self.consensus.read().await.metrics);
+                self.vid_disperse_tasks
+                    .spawn_cancellable(view_number, |cancelled| async move {
+                        if let Err(e) = calculate_vid_disperse::<TYPES, V>(
+                            &cache,
+                            &payload,
+                            &membership_coordinator,
+                            view_number,
+                            epoch,
+                            epoch,
+                            &metadata,
+                            &upgrade_lock,
+                            &metrics,
+                            cancelled,
+                        )
+                        .await
+                        {
+                            debug!("Failed to precompute VID disperse for view {view_number}: {e}");
+                        }
+                    });
             },
 
             HotShotEvent::ViewChange(view, epoch) => {
@@ -184,6 +260,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> VidTaskState<TY
                     info!("View changed by more than 1 going to view {view:?}");
                 }
                 self.cur_view = view;
+                self.cancel_stale_disperse_tasks();
 
                 return None;
             },
@@ -216,41 +293,54 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> VidTaskState<TY
                     return None;
                 };
                 let payload = Arc::clone(payload);
+                let metrics = Arc::clone(&consensus_reader.metrics);
                 drop(consensus_reader);
 
-                let next_epoch_vid_disperse = VidDisperse::calculate_vid_disperse::<V>(
-                    &payload.payload,
-                    &self.membership_coordinator,
-                    proposal_view_number,
-                    target_epoch,
-                    sender_epoch,
-                    &payload.metadata,
-                    &self.upgrade_lock,
-                )
-                .await
-                .ok()?;
-                let Ok(next_epoch_signature) = TYPES::SignatureKey::sign(
-                    &self.private_key,
-                    next_epoch_vid_disperse.payload_commitment().as_ref(),
-                ) else {
-                    error!("VID: failed to sign dispersal payload for the next epoch");
-                    return None;
-                };
-                debug!(
-                    "publishing VID disperse for view {proposal_view_number} and epoch {target_epoch:?}"
-                );
-                broadcast_event(
-                    Arc::new(HotShotEvent::VidDisperseSend(
-                        Proposal {
-                            signature: next_epoch_signature,
-                            data: next_epoch_vid_disperse.clone(),
-                            _pd: PhantomData,
-                        },
-                        self.public_key.clone(),
-                    )),
-                    &event_stream,
-                )
-                .await;
+                let membership_coordinator = self.membership_coordinator.clone();
+                let upgrade_lock = self.upgrade_lock.clone();
+                let cache = Arc::clone(&self.vid_disperse_cache);
+                let public_key = self.public_key.clone();
+                let signer = self.signer.clone();
+                self.vid_disperse_tasks
+                    .spawn_cancellable(proposal_view_number, |cancelled| async move {
+                        let Ok(Some(next_epoch_vid_disperse)) = calculate_vid_disperse::<TYPES, V>(
+                            &cache,
+                            &payload.payload,
+                            &membership_coordinator,
+                            proposal_view_number,
+                            target_epoch,
+                            sender_epoch,
+                            &payload.metadata,
+                            &upgrade_lock,
+                            &metrics,
+                            cancelled,
+                        )
+                        .await
+                        else {
+                            return;
+                        };
+                        let Ok(next_epoch_signature) =
+                            signer.sign(next_epoch_vid_disperse.payload_commitment().as_ref())
+                        else {
+                            error!("VID: failed to sign dispersal payload for the next epoch");
+                            return;
+                        };
+                        debug!(
+                            "publishing VID disperse for view {proposal_view_number} and epoch {target_epoch:?}"
+                        );
+                        broadcast_event(
+                            Arc::new(HotShotEvent::VidDisperseSend(
+                                Proposal {
+                                    signature: next_epoch_signature,
+                                    data: next_epoch_vid_disperse,
+                                    _pd: PhantomData,
+                                },
+                                public_key,
+                            )),
+                            &event_stream,
+                        )
+                        .await;
+                    });
             },
             HotShotEvent::Shutdown => {
                 return Some(HotShotTaskCompleted);
@@ -278,5 +368,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TaskState
         Ok(())
     }
 
-    fn cancel_subtasks(&mut self) {}
+    fn cancel_subtasks(&mut self) {
+        self.vid_disperse_tasks.abort_all();
+    }
 }
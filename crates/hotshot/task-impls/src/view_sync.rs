@@ -7,17 +7,23 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::Debug,
-    sync::Arc,
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::Committable;
+use either::Either;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::ViewChangeEvidence2,
     epoch_membership::{EpochMembership, EpochMembershipCoordinator},
     message::UpgradeLock,
+    request_response::ViewEvidenceRequestPayload,
+    signer::Signer,
     simple_certificate::{
         ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
     },
@@ -57,6 +63,92 @@ pub enum ViewSyncPhase {
     Finalize,
 }
 
+/// Shortest round timeout, however fast the network appears to be
+const MIN_ROUND_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Multiple of the smoothed phase latency allowed for a round before moving to the next relay
+const ROUND_TIMEOUT_MULTIPLIER: u32 = 4;
+
+/// Weight, as a fraction `1 / n`, of each new latency observation in the smoothed latency
+const LATENCY_SMOOTHING: u32 = 4;
+
+/// Timeout for view sync rounds, adapted to the latency recently observed in the network.
+///
+/// A round which has not completed within the timeout moves on to the next relay. With a static
+/// timeout, each unresponsive relay (which is common right after a mass restart) costs the full
+/// configured timeout, even when the network itself is fast. Instead, the timeout follows a
+/// multiple of the smoothed time it has taken to complete recent phases, bounded above by the
+/// configured timeout, which is also used until any latency has been observed.
+///
+/// A round which times out counts as taking the configured timeout, and resets the estimate to it.
+/// Otherwise, unresponsive relays would never raise the estimate, since only rounds which complete
+/// are observed, and nodes would rotate relays at different times depending on what each of them
+/// has seen. After a timeout, every node is back on the configured timeout, so that the relays
+/// which follow are tried in step.
+///
+/// Clones share the same estimate, so that observations made by one replica task speed up the
+/// others.
+#[derive(Clone, Debug)]
+pub struct ViewSyncRoundTimeout {
+    /// The configured view sync timeout, used as the upper bound
+    max: Duration,
+    /// Exponentially weighted moving average of observed latencies
+    smoothed_latency: Arc<Mutex<Option<Duration>>>,
+}
+
+impl ViewSyncRoundTimeout {
+    /// Create a round timeout bounded by the configured view sync timeout `max`
+    #[must_use]
+    pub fn new(max: Duration) -> Self {
+        Self {
+            max,
+            smoothed_latency: Arc::default(),
+        }
+    }
+
+    /// Record the time it took to get a response in one round of the protocol, returning the new
+    /// round timeout
+    pub fn observe(&self, latency: Duration) -> Duration {
+        let mut smoothed = self
+            .smoothed_latency
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        *smoothed = Some(match *smoothed {
+            Some(smoothed) => (smoothed * (LATENCY_SMOOTHING - 1) + latency) / LATENCY_SMOOTHING,
+            None => latency,
+        });
+        self.timeout_for(*smoothed)
+    }
+
+    /// Record a round which timed out without a response, returning the new round timeout
+    pub fn observe_timeout(&self) -> Duration {
+        let mut smoothed = self
+            .smoothed_latency
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        *smoothed = Some(self.max);
+        self.timeout_for(*smoothed)
+    }
+
+    /// The current round timeout
+    #[must_use]
+    pub fn current(&self) -> Duration {
+        let smoothed = *self
+            .smoothed_latency
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        self.timeout_for(smoothed)
+    }
+
+    fn timeout_for(&self, smoothed_latency: Option<Duration>) -> Duration {
+        match smoothed_latency {
+            Some(latency) => (latency * ROUND_TIMEOUT_MULTIPLIER)
+                .clamp(MIN_ROUND_TIMEOUT.min(self.max), self.max),
+            None => self.max,
+        }
+    }
+}
+
 /// Type alias for a map from View Number to Relay to Vote Task
 type RelayMap<TYPES, VOTE, CERT, V> = HashMap<
     (
@@ -91,8 +183,8 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Our node id; for logging
     pub id: u64,
@@ -117,14 +209,26 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
         RelayMap<TYPES, ViewSyncFinalizeVote2<TYPES>, ViewSyncFinalizeCertificate2<TYPES>, V>,
     >,
 
-    /// Timeout duration for view sync rounds
-    pub view_sync_timeout: Duration,
+    /// Timeout for view sync rounds, shared with the replica tasks
+    pub round_timeout: ViewSyncRoundTimeout,
+
+    /// When we last asked our peers for view change evidence, until the first response
+    pub view_evidence_requested: Option<Instant>,
 
     /// Last view we garbage collected old tasks
     pub last_garbage_collected_view: TYPES::View,
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// The consensus metrics
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
+
+    /// The latest valid evidence of a view change we have seen, shared with nodes which fell behind
+    pub latest_view_evidence: Option<ViewChangeEvidence2<TYPES>>,
+
+    /// The latest view we asked our peers for view change evidence in
+    pub last_view_evidence_request: Option<TYPES::View>,
 }
 
 #[async_trait]
@@ -146,7 +250,13 @@ impl<TYPES: NodeType, V: Versions> TaskState for ViewSyncTaskState<TYPES, V> {
 /// State of a view sync replica task
 pub struct ViewSyncReplicaTaskState<TYPES: NodeType, V: Versions> {
     /// Timeout for view sync rounds
-    pub view_sync_timeout: Duration,
+    pub round_timeout: ViewSyncRoundTimeout,
+
+    /// When we sent our vote for the current phase, if we are waiting for its certificate
+    pub phase_started: Option<Instant>,
+
+    /// The consensus metrics
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
 
     /// Current round HotShot is in
     pub cur_view: TYPES::View,
@@ -175,8 +285,8 @@ pub struct ViewSyncReplicaTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Our signer
+    pub signer: Signer<TYPES::SignatureKey>,
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
@@ -249,8 +359,10 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
             timeout_task: None,
             membership_coordinator: self.membership_coordinator.clone(),
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
-            view_sync_timeout: self.view_sync_timeout,
+            signer: self.signer.clone(),
+            round_timeout: self.round_timeout.clone(),
+            phase_started: None,
+            consensus_metrics: Arc::clone(&self.consensus_metrics),
             id: self.id,
             upgrade_lock: self.upgrade_lock.clone(),
             cur_epoch: self.cur_epoch,
@@ -359,6 +471,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     membership: epoch_mem,
                     view: vote_view,
                     id: self.id,
+                    consensus_metrics: Arc::clone(&self.consensus_metrics),
                 };
                 let vote_collector = create_vote_accumulator(
                     &info,
@@ -409,6 +522,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     membership: epoch_mem,
                     view: vote_view,
                     id: self.id,
+                    consensus_metrics: Arc::clone(&self.consensus_metrics),
                 };
 
                 let vote_collector = create_vote_accumulator(
@@ -459,6 +573,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     membership: epoch_mem,
                     view: vote_view,
                     id: self.id,
+                    consensus_metrics: Arc::clone(&self.consensus_metrics),
                 };
                 let vote_collector = create_vote_accumulator(
                     &info,
@@ -540,6 +655,17 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     tracing::error!("Too many consecutive timeouts!  This shouldn't happen");
                 }
 
+                // We may have been partitioned while the network moved on, in which case our
+                // peers can let us jump ahead without running view sync. Only ask once per view,
+                // repeated timeouts for the same view would otherwise flood the network.
+                if self
+                    .last_view_evidence_request
+                    .is_none_or(|requested| requested < view_number)
+                {
+                    self.last_view_evidence_request = Some(view_number);
+                    self.request_view_evidence(view_number, &event_stream).await;
+                }
+
                 if self.num_timeouts_tracked >= 2 {
                     tracing::error!("Starting view sync protocol for view {}", *view_number + 1);
 
@@ -560,11 +686,141 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     .await;
                 }
             },
+            HotShotEvent::Qc2Formed(Either::Right(timeout_cert)) => {
+                self.record_view_evidence(ViewChangeEvidence2::Timeout(timeout_cert.clone()));
+            },
+            HotShotEvent::QuorumProposalValidated(proposal, _) => {
+                if let Some(evidence) = proposal.data.view_change_evidence() {
+                    self.record_view_evidence(evidence.clone());
+                }
+            },
+            HotShotEvent::ViewEvidenceRequestRecv(request, signature) => {
+                ensure!(
+                    request.key.validate(signature, request.commit().as_ref()),
+                    warn!("Invalid signature on view evidence request.")
+                );
+
+                let evidence = self
+                    .latest_view_evidence
+                    .as_ref()
+                    .context(debug!("No view change evidence to share"))?;
+                // A node which timed out in `view` moves to `view + 1` on its own.
+                ensure!(
+                    evidence.view_to_enter() > request.view_number + 1,
+                    debug!(
+                        "Requesting node in view {} is not behind our evidence",
+                        *request.view_number
+                    )
+                );
+
+                broadcast_event(
+                    Arc::new(HotShotEvent::ViewEvidenceResponseSend(
+                        self.public_key.clone(),
+                        request.key.clone(),
+                        evidence.clone(),
+                    )),
+                    &event_stream,
+                )
+                .await;
+            },
+            HotShotEvent::ViewEvidenceResponseRecv(evidence) => {
+                let view = evidence.view_to_enter();
+                ensure!(
+                    view > self.cur_view,
+                    debug!("Already in view {} or later", *view)
+                );
+
+                self.validate_view_evidence(evidence).await?;
+
+                if let Some(requested) = self.view_evidence_requested.take() {
+                    let timeout = self.round_timeout.observe(requested.elapsed());
+                    self.consensus_metrics
+                        .view_sync_round_timeout
+                        .set(timeout.as_millis() as usize);
+                }
+
+                tracing::info!("Catching up to view {} with evidence from a peer", *view);
+                self.record_view_evidence(evidence.clone());
+                broadcast_event(
+                    Arc::new(HotShotEvent::ViewChange(view, evidence.epoch())),
+                    &event_stream,
+                )
+                .await;
+            },
 
             _ => {},
         }
         Ok(())
     }
+
+    /// Ask our peers for evidence that the network has moved past `view`
+    async fn request_view_evidence(
+        &mut self,
+        view: TYPES::View,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let request = ViewEvidenceRequestPayload {
+            view_number: view,
+            key: self.public_key.clone(),
+        };
+        let signature = match self.signer.sign(request.commit().as_ref()) {
+            Ok(signature) => signature,
+            Err(e) => {
+                tracing::error!("Failed to sign view evidence request: {e}");
+                return;
+            },
+        };
+
+        self.view_evidence_requested = Some(Instant::now());
+        broadcast_event(
+            Arc::new(HotShotEvent::ViewEvidenceRequestSend(request, signature)),
+            event_stream,
+        )
+        .await;
+    }
+
+    /// Keep `evidence` if it lets a node enter a later view than what we have
+    fn record_view_evidence(&mut self, evidence: ViewChangeEvidence2<TYPES>) {
+        if self
+            .latest_view_evidence
+            .as_ref()
+            .is_none_or(|latest| latest.view_to_enter() < evidence.view_to_enter())
+        {
+            self.latest_view_evidence = Some(evidence);
+        }
+    }
+
+    /// Check that `evidence` is signed by enough stake in its epoch
+    ///
+    /// # Errors
+    /// If the certificate is invalid or we don't have the stake table for its epoch
+    async fn validate_view_evidence(&self, evidence: &ViewChangeEvidence2<TYPES>) -> Result<()> {
+        let membership = self
+            .membership_coordinator
+            .membership_for_epoch(evidence.epoch())
+            .await?;
+        let stake_table = StakeTableEntries::<TYPES>::from(membership.stake_table().await).0;
+        let success_threshold = membership.success_threshold().await;
+
+        match evidence {
+            ViewChangeEvidence2::Timeout(timeout_cert) => {
+                timeout_cert
+                    .is_valid_cert(stake_table, success_threshold, &self.upgrade_lock)
+                    .await
+            },
+            ViewChangeEvidence2::ViewSync(view_sync_cert) => {
+                view_sync_cert
+                    .is_valid_cert(stake_table, success_threshold, &self.upgrade_lock)
+                    .await
+            },
+        }
+        .context(|e| {
+            warn!(
+                "Invalid view change evidence for view {}: {e}",
+                *evidence.view_to_enter()
+            )
+        })
+    }
 }
 
 impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
@@ -617,6 +873,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.finish_phase("pre_commit");
 
                 let Ok(vote) = ViewSyncCommitVote2::<TYPES>::create_signed_vote(
                     ViewSyncCommitData2 {
@@ -626,7 +883,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     },
                     self.next_view,
                     &self.public_key,
-                    &self.private_key,
+                    &self.signer,
                     &self.upgrade_lock,
                 )
                 .await
@@ -640,6 +897,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     &event_stream,
                 )
                 .await;
+                self.phase_started = Some(Instant::now());
 
                 if let Some(timeout_task) = self.timeout_task.take() {
                     timeout_task.abort();
@@ -650,7 +908,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     let phase = last_seen_certificate;
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.round_timeout.current();
                     async move {
                         sleep(timeout).await;
                         tracing::warn!("Vote sending timed out in ViewSyncPreCommitCertificateRecv, Relay = {relay}");
@@ -709,6 +967,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.finish_phase("commit");
 
                 let Ok(vote) = ViewSyncFinalizeVote2::<TYPES>::create_signed_vote(
                     ViewSyncFinalizeData2 {
@@ -718,7 +977,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     },
                     self.next_view,
                     &self.public_key,
-                    &self.private_key,
+                    &self.signer,
                     &self.upgrade_lock,
                 )
                 .await
@@ -732,6 +991,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     &event_stream,
                 )
                 .await;
+                self.phase_started = Some(Instant::now());
 
                 tracing::info!(
                     "View sync protocol has received view sync evidence to update the view to {}",
@@ -756,7 +1016,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     let phase = last_seen_certificate;
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.round_timeout.current();
                     async move {
                         sleep(timeout).await;
                         tracing::warn!("Vote sending timed out in ViewSyncCommitCertificateRecv, relay = {relay}");
@@ -812,6 +1072,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if certificate.data().relay > self.relay {
                     self.relay = certificate.data().relay;
                 }
+                self.finish_phase("finalize");
 
                 if let Some(timeout_task) = self.timeout_task.take() {
                     timeout_task.abort();
@@ -844,7 +1105,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     },
                     view_number,
                     &self.public_key,
-                    &self.private_key,
+                    &self.signer,
                     &self.upgrade_lock,
                 )
                 .await
@@ -858,12 +1119,14 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     &event_stream,
                 )
                 .await;
+                self.phase_started = Some(Instant::now());
+                self.consensus_metrics.view_sync_rounds.add(1);
 
                 self.timeout_task = Some(spawn({
                     let stream = event_stream.clone();
                     let relay = self.relay;
                     let next_view = self.next_view;
-                    let timeout = self.view_sync_timeout;
+                    let timeout = self.round_timeout.current();
                     async move {
                         sleep(timeout).await;
                         tracing::warn!("Vote sending timed out in ViewSyncTrigger");
@@ -890,6 +1153,12 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                         timeout_task.abort();
                     }
                     self.relay += 1;
+                    self.consensus_metrics.view_sync_relay_changes.add(1);
+                    self.phase_started = None;
+                    let timeout = self.round_timeout.observe_timeout();
+                    self.consensus_metrics
+                        .view_sync_round_timeout
+                        .set(timeout.as_millis() as usize);
                     match last_seen_certificate {
                         ViewSyncPhase::None | ViewSyncPhase::PreCommit | ViewSyncPhase::Commit => {
                             let Ok(vote) = ViewSyncPreCommitVote2::<TYPES>::create_signed_vote(
@@ -900,7 +1169,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                                 },
                                 self.next_view,
                                 &self.public_key,
-                                &self.private_key,
+                                &self.signer,
                                 &self.upgrade_lock,
                             )
                             .await
@@ -914,6 +1183,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                                 &event_stream,
                             )
                             .await;
+                            self.phase_started = Some(Instant::now());
                         },
                         ViewSyncPhase::Finalize => {
                             // This should never occur
@@ -925,7 +1195,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                         let stream = event_stream.clone();
                         let relay = self.relay;
                         let next_view = self.next_view;
-                        let timeout = self.view_sync_timeout;
+                        let timeout = self.round_timeout.current();
                         let last_cert = last_seen_certificate.clone();
                         async move {
                             sleep(timeout).await;
@@ -952,6 +1222,23 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
         None
     }
 
+    /// Record the duration of the phase which just ended with a certificate, if we voted in it
+    fn finish_phase(&mut self, phase: &str) {
+        let Some(started) = self.phase_started.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        self.consensus_metrics
+            .view_sync_phase_duration
+            .create(vec![phase.to_string()])
+            .add_point(elapsed.as_secs_f64());
+
+        let timeout = self.round_timeout.observe(elapsed);
+        self.consensus_metrics
+            .view_sync_round_timeout
+            .set(timeout.as_millis() as usize);
+    }
+
     pub async fn membership_for_epoch(
         &self,
         epoch: Option<TYPES::Epoch>,
@@ -16,6 +16,7 @@ use async_broadcast::Sender;
 use async_trait::async_trait;
 use either::Either::{Left, Right};
 use hotshot_types::{
+    consensus::ConsensusMetricsValue,
     epoch_membership::EpochMembership,
     message::UpgradeLock,
     simple_certificate::{
@@ -65,6 +66,9 @@ pub struct VoteCollectionTaskState<
 
     /// Whether we should check if we are the leader when handling a vote
     pub transition_indicator: EpochTransitionIndicator,
+
+    /// The consensus metrics
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
 }
 
 /// Describes the functions a vote must implement for it to be aggregatable by the generic vote collection task
@@ -127,10 +131,24 @@ impl<
             "No accumulator to handle vote with. This shouldn't happen."
         ))?;
 
-        match accumulator.accumulate(vote, self.membership.clone()).await {
+        let cert = accumulator.accumulate(vote, self.membership.clone()).await;
+        for evidence in accumulator.take_double_votes() {
+            broadcast_event(
+                Arc::new(HotShotEvent::DoubleVoteDetected(evidence)),
+                event_stream,
+            )
+            .await;
+        }
+
+        match cert {
             None => Ok(None),
             Some(cert) => {
                 tracing::debug!("Certificate Formed! {cert:?}");
+                if let Some(elapsed) = accumulator.time_since_first_vote() {
+                    self.consensus_metrics
+                        .vote_time_to_threshold
+                        .add_point(elapsed.as_secs_f64());
+                }
 
                 broadcast_event(
                     Arc::new(VOTE::make_cert_event(cert.clone(), &self.public_key)),
@@ -180,6 +198,9 @@ pub struct AccumulatorInfo<TYPES: NodeType> {
 
     /// This nodes id
     pub id: u64,
+
+    /// The consensus metrics
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
 }
 
 /// Generic function for spawning a vote task.  Returns the event stream id of the spawned task if created
@@ -216,6 +237,8 @@ where
         signers: HashMap::new(),
         phantom: PhantomData,
         upgrade_lock,
+        first_vote_time: None,
+        double_votes: vec![],
     };
 
     let mut state = VoteCollectionTaskState::<TYPES, VOTE, CERT, V> {
@@ -225,6 +248,7 @@ where
         view: info.view,
         id: info.id,
         transition_indicator,
+        consensus_metrics: Arc::clone(&info.consensus_metrics),
     };
 
     state.handle_vote_event(Arc::clone(&event), sender).await?;
@@ -252,6 +276,7 @@ pub async fn handle_vote<
     public_key: TYPES::SignatureKey,
     membership: &EpochMembership<TYPES>,
     id: u64,
+    consensus_metrics: &Arc<ConsensusMetricsValue>,
     event: &Arc<HotShotEvent<TYPES>>,
     event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
@@ -268,6 +293,7 @@ where
                 membership: membership.clone(),
                 view: vote.view_number(),
                 id,
+                consensus_metrics: Arc::clone(consensus_metrics),
             };
             let collector = create_vote_accumulator(
                 &info,
@@ -678,6 +704,9 @@ pub struct EpochRootVoteCollectionTaskState<TYPES: NodeType, V: Versions> {
 
     /// Node id
     pub id: u64,
+
+    /// The consensus metrics
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
 }
 
 // Handlers for extended quorum vote accumulators
@@ -718,14 +747,28 @@ impl<TYPES: NodeType, V: Versions> EpochRootVoteCollectionTaskState<TYPES, V> {
             "No accumulator to handle light client state update vote with. This shouldn't happen."
         ))?;
 
+        let cert = accumulator.accumulate(vote, self.membership.clone()).await;
+        for evidence in accumulator.take_double_votes() {
+            broadcast_event(
+                Arc::new(HotShotEvent::DoubleVoteDetected(evidence)),
+                event_stream,
+            )
+            .await;
+        }
+
         match (
-            accumulator.accumulate(vote, self.membership.clone()).await,
+            cert,
             state_vote_accumulator
                 .accumulate(&vote.signing_key(), state_vote, &self.membership)
                 .await,
         ) {
             (None, None) => Ok(None),
             (Some(cert), Some(state_cert)) => {
+                if let Some(elapsed) = accumulator.time_since_first_vote() {
+                    self.consensus_metrics
+                        .vote_time_to_threshold
+                        .add_point(elapsed.as_secs_f64());
+                }
                 let root_qc = EpochRootQuorumCertificate {
                     qc: cert,
                     state_cert,
@@ -761,6 +804,8 @@ async fn create_epoch_root_vote_collection_task_state<TYPES: NodeType, V: Versio
             signers: HashMap::new(),
             phantom: PhantomData,
             upgrade_lock,
+            first_vote_time: None,
+            double_votes: vec![],
         };
     let state_vote_accumulator = LightClientStateUpdateVoteAccumulator {
         vote_outcomes: HashMap::new(),
@@ -774,6 +819,7 @@ async fn create_epoch_root_vote_collection_task_state<TYPES: NodeType, V: Versio
         view: info.view,
         epoch: info.membership.epoch,
         id: info.id,
+        consensus_metrics: Arc::clone(&info.consensus_metrics),
     };
 
     state.handle_vote_event(Arc::clone(&event), sender).await?;
@@ -792,6 +838,7 @@ pub async fn handle_epoch_root_vote<TYPES: NodeType, V: Versions>(
     public_key: TYPES::SignatureKey,
     membership: &EpochMembership<TYPES>,
     id: u64,
+    consensus_metrics: &Arc<ConsensusMetricsValue>,
     event: &Arc<HotShotEvent<TYPES>>,
     event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
@@ -807,6 +854,7 @@ pub async fn handle_epoch_root_vote<TYPES: NodeType, V: Versions>(
                 membership: membership.clone(),
                 view: vote.view_number(),
                 id,
+                consensus_metrics: Arc::clone(consensus_metrics),
             };
             let collector = create_epoch_root_vote_collection_task_state(
                 &info,
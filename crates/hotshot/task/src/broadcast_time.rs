@@ -0,0 +1,142 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Events are shared between tasks as `Arc`s, so rather than adding a timestamp to every event
+//! type, the time at which each event was broadcast is recorded against its allocation, which all
+//! receivers share. A weak reference to the event is kept alongside the time, so the allocation
+//! cannot be reused for a different event while its time is still recorded.
+//!
+//! Every task records or looks up a time for each event it sends or receives, so the times are
+//! spread over independently locked shards to keep tasks from contending for a single lock.
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    sync::{Arc, LazyLock, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use hotshot_types::{event::Event, traits::node_implementation::NodeType};
+
+/// Number of recent broadcasts whose times are remembered.
+///
+/// This comfortably exceeds the capacity of the internal event stream, so an event is only
+/// forgotten long after every task has received it.
+const CAPACITY: usize = 1 << 14;
+
+/// Number of shards the broadcast times are spread over
+const SHARDS: usize = 64;
+
+/// Recorded broadcast time of a single event
+struct Entry {
+    /// Keeps the allocation of the event from being reused while the entry exists
+    event: Weak<dyn Any + Send + Sync>,
+    /// When the event was broadcast
+    time: Instant,
+}
+
+/// Broadcast times, keyed by event address, with the addresses in the order they were recorded
+#[derive(Default)]
+struct BroadcastTimes {
+    entries: HashMap<usize, Entry>,
+    order: VecDeque<usize>,
+}
+
+impl BroadcastTimes {
+    fn record(&mut self, key: usize, event: Weak<dyn Any + Send + Sync>) {
+        let time = Instant::now();
+        if let Some(entry) = self.entries.get_mut(&key) {
+            // The same event broadcast again, e.g. forwarded to another stream.
+            entry.time = time;
+            return;
+        }
+        self.entries.insert(key, Entry { event, time });
+        self.order.push_back(key);
+
+        // Forget the oldest events once they have been dropped by every receiver, or once there are
+        // too many to remember.
+        while let Some(&oldest) = self.order.front() {
+            let dropped = self
+                .entries
+                .get(&oldest)
+                .is_none_or(|entry| entry.event.strong_count() == 0);
+            if !dropped && self.order.len() <= CAPACITY / SHARDS {
+                break;
+            }
+            self.entries.remove(&oldest);
+            self.order.pop_front();
+        }
+    }
+}
+
+static BROADCAST_TIMES: LazyLock<Vec<Mutex<BroadcastTimes>>> =
+    LazyLock::new(|| (0..SHARDS).map(|_| Mutex::default()).collect());
+
+/// The address identifying `event`
+fn key<T: ?Sized>(event: &Arc<T>) -> usize {
+    Arc::as_ptr(event).cast::<()>() as usize
+}
+
+/// The shard holding the broadcast time of the event at address `key`
+fn shard(key: usize) -> &'static Mutex<BroadcastTimes> {
+    // Allocations are aligned, so the lowest bits of an address do not tell events apart.
+    &BROADCAST_TIMES[(key >> 4) % SHARDS]
+}
+
+/// Record that `event` is being broadcast now.
+pub fn record_broadcast<T: Any + Send + Sync>(event: &Arc<T>) {
+    let weak: Weak<dyn Any + Send + Sync> = Arc::downgrade(event) as _;
+    let key = key(event);
+    if let Ok(mut times) = shard(key).lock() {
+        times.record(key, weak);
+    }
+}
+
+/// Time since `event` was broadcast, if its broadcast was recorded.
+pub fn time_since_broadcast<T: Any + Send + Sync>(event: &Arc<T>) -> Option<Duration> {
+    let key = key(event);
+    let times = shard(key).lock().ok()?;
+    times.entries.get(&key).map(|entry| entry.time.elapsed())
+}
+
+/// Values sent on an event stream whose broadcast time can be recorded
+pub trait Timestamped {
+    /// Record that this value is being broadcast now.
+    fn record_broadcast(&self) {}
+}
+
+impl<T: Any + Send + Sync> Timestamped for Arc<T> {
+    fn record_broadcast(&self) {
+        record_broadcast(self);
+    }
+}
+
+/// External events are consumed by the application, not by tasks, so their delivery is not timed.
+impl<TYPES: NodeType> Timestamped for Event<TYPES> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn broadcast_time_is_per_event() {
+        let a = Arc::new(1u64);
+        let b = Arc::new(1u64);
+        assert_eq!(time_since_broadcast(&a), None);
+
+        record_broadcast(&a);
+        std::thread::sleep(Duration::from_millis(10));
+        record_broadcast(&b);
+
+        let since_a = time_since_broadcast(&a).unwrap();
+        let since_b = time_since_broadcast(&b).unwrap();
+        assert!(since_a >= Duration::from_millis(10));
+        assert!(since_b < since_a);
+
+        // Clones share the allocation, and so the broadcast time.
+        assert!(time_since_broadcast(&Arc::clone(&a)).unwrap() >= since_a);
+    }
+}
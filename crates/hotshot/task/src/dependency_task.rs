@@ -5,9 +5,9 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use futures::Future;
-use tokio::task::{spawn, JoinHandle};
+use tokio::task::JoinHandle;
 
-use crate::dependency::Dependency;
+use crate::{dependency::Dependency, profiling, task::task_name};
 
 /// Defines a type that can handle the result of a dependency
 pub trait HandleDepOutput: Send + Sized + Sync + 'static {
@@ -40,7 +40,7 @@ impl<D: Dependency<H::Output> + Send + 'static, H: HandleDepOutput> DependencyTa
     where
         Self: Sized,
     {
-        spawn(async move {
+        profiling::spawn(task_name::<H>(), async move {
             if let Some(completed) = self.dep.completed().await {
                 self.handle.handle_dep_result(completed).await;
             }
@@ -55,7 +55,7 @@ mod test {
 
     use async_broadcast::{broadcast, Receiver, Sender};
     use futures::{stream::FuturesOrdered, StreamExt};
-    use tokio::time::sleep;
+    use tokio::{task::spawn, time::sleep};
 
     use super::*;
     use crate::dependency::*;
@@ -6,9 +6,15 @@
 
 //! Task primitives for `HotShot`
 
+/// Broadcast times of events on the internal event stream
+pub mod broadcast_time;
 /// Simple Dependency types
 pub mod dependency;
 /// Task which can uses dependencies
 pub mod dependency_task;
+/// Profiling of how tasks use the runtime
+pub mod profiling;
+/// Supervision of spawned subtasks
+pub mod supervisor;
 /// Basic task types
 pub mod task;
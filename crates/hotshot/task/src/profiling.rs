@@ -0,0 +1,136 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Profiling of how tasks use the runtime.
+//!
+//! When built with the `profiling` feature, every [`Task`](crate::task::Task) and
+//! [`DependencyTask`](crate::dependency_task::DependencyTask) is instrumented with a
+//! [`tokio_metrics::TaskMonitor`] shared by all tasks of the same name. The monitors measure how
+//! often each task is polled, how long the polls take, and how long the task waits to be scheduled
+//! once woken, so a task which stalls its worker thread with blocking code shows up as a task with
+//! long or slow polls. Without the feature, tasks are spawned as they are and no profiles are kept.
+//!
+//! When the `profiling` feature is combined with `--cfg tokio_unstable`, tasks are also spawned with
+//! their names, so that they can be told apart in `tokio-console`.
+
+use std::{future::Future, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+/// Whether tasks are being profiled
+pub const ENABLED: bool = cfg!(feature = "profiling");
+
+/// Polls taking at least this long are counted as slow.
+///
+/// Handling an event should only take long when it awaits something; a single poll this long
+/// almost always means blocking code on a runtime thread.
+pub const SLOW_POLL_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Cumulative runtime usage of all tasks with the same name
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskProfile {
+    /// Name of the tasks
+    pub name: String,
+    /// Number of tasks with this name spawned so far
+    pub instances: u64,
+    /// Number of tasks with this name which have finished
+    pub finished: u64,
+    /// Total number of polls
+    pub polls: u64,
+    /// Seconds spent polling the tasks, ie the time they kept a runtime thread busy
+    pub busy_seconds: f64,
+    /// Mean seconds per poll
+    pub mean_poll_seconds: f64,
+    /// Number of polls taking at least [`SLOW_POLL_THRESHOLD`]
+    pub slow_polls: u64,
+    /// Seconds spent in slow polls
+    pub slow_poll_seconds: f64,
+    /// Mean seconds from a task being woken until it is polled
+    pub mean_scheduled_seconds: f64,
+    /// Seconds the tasks spent idle, waiting to be woken
+    pub idle_seconds: f64,
+}
+
+#[cfg(feature = "profiling")]
+mod monitors {
+    use std::{
+        collections::BTreeMap,
+        sync::{LazyLock, Mutex},
+    };
+
+    use tokio_metrics::TaskMonitor;
+
+    use super::{TaskProfile, SLOW_POLL_THRESHOLD};
+
+    /// Monitors of all tasks spawned so far, by task name
+    static MONITORS: LazyLock<Mutex<BTreeMap<&'static str, TaskMonitor>>> =
+        LazyLock::new(Mutex::default);
+
+    pub(super) fn monitor(name: &'static str) -> TaskMonitor {
+        MONITORS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(name)
+            .or_insert_with(|| TaskMonitor::with_slow_poll_threshold(SLOW_POLL_THRESHOLD))
+            .clone()
+    }
+
+    pub(super) fn profiles() -> Vec<TaskProfile> {
+        MONITORS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .map(|(name, monitor)| {
+                let metrics = monitor.cumulative();
+                TaskProfile {
+                    name: name.to_string(),
+                    instances: metrics.instrumented_count,
+                    finished: metrics.dropped_count,
+                    polls: metrics.total_poll_count,
+                    busy_seconds: metrics.total_poll_duration.as_secs_f64(),
+                    mean_poll_seconds: metrics.mean_poll_duration().as_secs_f64(),
+                    slow_polls: metrics.total_slow_poll_count,
+                    slow_poll_seconds: metrics.total_slow_poll_duration.as_secs_f64(),
+                    mean_scheduled_seconds: metrics.mean_scheduled_duration().as_secs_f64(),
+                    idle_seconds: metrics.total_idle_duration.as_secs_f64(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Profiles of all tasks spawned so far, sorted by name.
+///
+/// Empty unless built with the `profiling` feature.
+#[must_use]
+pub fn task_profiles() -> Vec<TaskProfile> {
+    #[cfg(feature = "profiling")]
+    return monitors::profiles();
+    #[cfg(not(feature = "profiling"))]
+    return vec![];
+}
+
+/// Spawn the task `future` named `name`, profiling it if enabled.
+pub(crate) fn spawn<F>(name: &'static str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(feature = "profiling")]
+    let future = monitors::monitor(name).instrument(future);
+
+    #[cfg(all(feature = "profiling", tokio_unstable))]
+    return tokio::task::Builder::new()
+        .name(name)
+        .spawn(future)
+        .expect("failed to spawn task");
+    #[cfg(not(all(feature = "profiling", tokio_unstable)))]
+    {
+        let _ = name;
+        tokio::task::spawn(future)
+    }
+}
@@ -0,0 +1,516 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{future::join_all, Future, FutureExt};
+use hotshot_types::traits::metrics::{Counter, Gauge, Metrics, NoMetrics};
+use tokio::{
+    task::{spawn, AbortHandle, JoinHandle},
+    time::{sleep, timeout},
+};
+
+/// Default time a task is given to finish on its own during a graceful shutdown
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(1);
+
+/// Metrics reported by a [`TaskSupervisor`]
+#[derive(Debug)]
+pub struct SupervisorMetrics {
+    /// Number of tasks currently owned by the supervisor
+    pub running_tasks: Box<dyn Gauge>,
+    /// Number of tasks aborted because they became stale
+    pub cancelled_tasks: Box<dyn Counter>,
+    /// Number of tasks which panicked
+    pub panicked_tasks: Box<dyn Counter>,
+    /// Number of times a panicked task was restarted
+    pub restarted_tasks: Box<dyn Counter>,
+    /// Number of tasks aborted because they missed their shutdown deadline
+    pub shutdown_timeouts: Box<dyn Counter>,
+}
+
+impl SupervisorMetrics {
+    /// Create the supervisor metrics in a subgroup named `name`
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics, name: &str) -> Self {
+        let metrics = metrics.subgroup(name.to_string());
+        Self {
+            running_tasks: metrics.create_gauge(String::from("running"), None),
+            cancelled_tasks: metrics.create_counter(String::from("cancelled"), None),
+            panicked_tasks: metrics.create_counter(String::from("panicked"), None),
+            restarted_tasks: metrics.create_counter(String::from("restarted"), None),
+            shutdown_timeouts: metrics.create_counter(String::from("shutdown_timeouts"), None),
+        }
+    }
+}
+
+impl Default for SupervisorMetrics {
+    fn default() -> Self {
+        Self::new(&*NoMetrics::boxed(), "")
+    }
+}
+
+/// Exponential backoff used when restarting a panicked task
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    /// Delay before the first restart
+    pub initial: Duration,
+    /// Upper bound on the delay between restarts
+    pub max: Duration,
+    /// Number of restarts after which the task is given up on, `None` for unlimited
+    pub max_restarts: Option<usize>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            max_restarts: None,
+        }
+    }
+}
+
+/// A task owned by a [`TaskSupervisor`]
+struct SupervisedTask {
+    /// Handle used to abort the task
+    abort: AbortHandle,
+    /// Handle to the task watching it, which finishes once the task has completed, panicked or
+    /// been aborted
+    watcher: JoinHandle<()>,
+    /// Time the task is given to finish during a graceful shutdown
+    deadline: Duration,
+    /// Flag telling the task to stop, for tasks spawned with
+    /// [`spawn_cancellable`](TaskSupervisor::spawn_cancellable)
+    cancelled: Option<Arc<AtomicBool>>,
+}
+
+impl SupervisedTask {
+    /// Stop the task, by telling it to if it was given a cancellation flag
+    fn cancel(&self) {
+        match &self.cancelled {
+            Some(cancelled) => cancelled.store(true, Ordering::Relaxed),
+            None => self.abort.abort(),
+        }
+    }
+
+    /// Abort the task, also setting its cancellation flag to stop any work it handed off
+    fn abort(&self) {
+        if let Some(cancelled) = &self.cancelled {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        self.abort.abort();
+    }
+}
+
+/// Owns the handles of spawned subtasks, grouped by a key (usually a view number).
+///
+/// Tasks for keys older than a given key can be cancelled at once, panicked tasks are
+/// reported as soon as they panic instead of dying silently, and on shutdown every task is
+/// given its deadline to finish before being aborted.
+pub struct TaskSupervisor<K: Ord> {
+    /// Name of the supervisor, used in logs
+    name: &'static str,
+    /// Tasks owned by this supervisor
+    tasks: BTreeMap<K, Vec<SupervisedTask>>,
+    /// Deadline given to tasks spawned without an explicit one
+    default_deadline: Duration,
+    /// Health metrics of the supervised tasks
+    metrics: Arc<SupervisorMetrics>,
+}
+
+impl<K: Ord + Copy + Debug + Send + 'static> TaskSupervisor<K> {
+    /// Create a new supervisor which does not report metrics
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self::with_metrics(name, Arc::new(SupervisorMetrics::default()))
+    }
+
+    /// Create a new supervisor reporting to `metrics`
+    #[must_use]
+    pub fn with_metrics(name: &'static str, metrics: Arc<SupervisorMetrics>) -> Self {
+        Self {
+            name,
+            tasks: BTreeMap::new(),
+            default_deadline: DEFAULT_SHUTDOWN_DEADLINE,
+            metrics,
+        }
+    }
+
+    /// Number of tasks currently owned by this supervisor
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tasks.values().map(Vec::len).sum()
+    }
+
+    /// Whether this supervisor owns no tasks
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Whether any task is registered for `key`
+    #[must_use]
+    pub fn contains(&self, key: &K) -> bool {
+        self.tasks.contains_key(key)
+    }
+
+    /// Spawn `future` and take ownership of its handle under `key`
+    pub fn spawn<F>(&mut self, key: K, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.register(key, spawn(future));
+    }
+
+    /// Spawn `future` under `key`, giving it `deadline` to finish during a graceful shutdown
+    pub fn spawn_with_deadline<F>(&mut self, key: K, future: F, deadline: Duration)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.register_with_deadline(key, spawn(future), deadline);
+    }
+
+    /// Spawn the future returned by `task` under `key`, passing it a flag which is set once the
+    /// task is cancelled.
+    ///
+    /// Cancelling such a task sets the flag instead of aborting the task, so that work it handed
+    /// off, such as a blocking computation, can be stopped as well. The task is still aborted if
+    /// it misses its shutdown deadline or the supervisor aborts all its tasks.
+    pub fn spawn_cancellable<F, Fut>(&mut self, key: K, task: F)
+    where
+        F: FnOnce(Arc<AtomicBool>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handle = spawn(task(Arc::clone(&cancelled)));
+        self.insert(key, handle, self.default_deadline, Some(cancelled));
+    }
+
+    /// Spawn a task which is restarted with `backoff` whenever it panics.
+    ///
+    /// The task is not restarted once it completes normally or is aborted.
+    pub fn spawn_restartable<F, Fut>(&mut self, key: K, factory: F, backoff: Backoff)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let name = self.name;
+        let metrics = Arc::clone(&self.metrics);
+        self.spawn(key, async move {
+            let mut delay = backoff.initial;
+            let mut restarts = 0;
+            while AssertUnwindSafe(factory()).catch_unwind().await.is_err() {
+                metrics.panicked_tasks.add(1);
+                if backoff.max_restarts.is_some_and(|max| restarts >= max) {
+                    tracing::error!(
+                        "Task {key:?} of {name} panicked {restarts} times, giving up on it"
+                    );
+                    return;
+                }
+                tracing::error!("Task {key:?} of {name} panicked, restarting in {delay:?}");
+                sleep(delay).await;
+                delay = (delay * 2).min(backoff.max);
+                restarts += 1;
+                metrics.restarted_tasks.add(1);
+            }
+        });
+    }
+
+    /// Take ownership of an already spawned task under `key`
+    pub fn register(&mut self, key: K, handle: JoinHandle<()>) {
+        self.register_with_deadline(key, handle, self.default_deadline);
+    }
+
+    /// Take ownership of an already spawned task under `key` with a shutdown deadline
+    pub fn register_with_deadline(&mut self, key: K, handle: JoinHandle<()>, deadline: Duration) {
+        self.insert(key, handle, deadline, None);
+    }
+
+    /// Take ownership of a spawned task, watching it for panics
+    fn insert(
+        &mut self,
+        key: K,
+        handle: JoinHandle<()>,
+        deadline: Duration,
+        cancelled: Option<Arc<AtomicBool>>,
+    ) {
+        let abort = handle.abort_handle();
+        let watcher = spawn(watch(handle, key, self.name, Arc::clone(&self.metrics)));
+        self.tasks.entry(key).or_default().push(SupervisedTask {
+            abort,
+            watcher,
+            deadline,
+            cancelled,
+        });
+        self.update_running();
+    }
+
+    /// Cancel all tasks registered under `key`
+    pub fn abort(&mut self, key: &K) {
+        if let Some(tasks) = self.tasks.remove(key) {
+            self.abort_tasks(tasks);
+        }
+        self.update_running();
+    }
+
+    /// Cancel all tasks registered under keys strictly older than `key`.
+    ///
+    /// Tasks spawned with [`spawn_cancellable`](Self::spawn_cancellable) are told to stop, and
+    /// others are aborted. Returns the number of tasks which were still running.
+    pub fn cancel_before(&mut self, key: &K) -> usize {
+        let keep = self.tasks.split_off(key);
+        let stale = std::mem::replace(&mut self.tasks, keep);
+        let cancelled = stale
+            .into_values()
+            .map(|tasks| self.abort_tasks(tasks))
+            .sum();
+        self.update_running();
+        cancelled
+    }
+
+    /// Abort every task owned by this supervisor without waiting for them
+    pub fn abort_all(&mut self) {
+        while let Some((_, tasks)) = self.tasks.pop_first() {
+            for task in tasks {
+                task.abort();
+            }
+        }
+        self.update_running();
+    }
+
+    /// Gracefully shut down every task owned by this supervisor.
+    ///
+    /// Each task is given its deadline to finish and is aborted if it misses it.
+    pub async fn shutdown(&mut self) {
+        let tasks = std::mem::take(&mut self.tasks);
+        self.update_running();
+        let this = &*self;
+        join_all(tasks.into_iter().flat_map(move |(key, tasks)| {
+            tasks.into_iter().map(move |task| this.shut_down(key, task))
+        }))
+        .await;
+    }
+
+    /// Wait for every task owned by this supervisor to finish on its own
+    pub async fn join(&mut self) {
+        let tasks = std::mem::take(&mut self.tasks);
+        join_all(tasks.into_values().flatten().map(|task| task.watcher)).await;
+        self.update_running();
+    }
+
+    /// Remove the handles of tasks which have finished
+    pub fn reap_finished(&mut self) {
+        for tasks in self.tasks.values_mut() {
+            tasks.retain(|task| !task.abort.is_finished());
+        }
+        self.tasks.retain(|_, tasks| !tasks.is_empty());
+        self.update_running();
+    }
+
+    /// Wait for a task to finish within its deadline, aborting it if it does not
+    async fn shut_down(&self, key: K, mut task: SupervisedTask) {
+        if timeout(task.deadline, &mut task.watcher).await.is_err() {
+            task.abort();
+            self.metrics.shutdown_timeouts.add(1);
+            tracing::warn!(
+                "Task {key:?} of {} did not shut down within {:?}, aborting",
+                self.name,
+                task.deadline
+            );
+        }
+    }
+
+    /// Cancel a group of tasks, returning the number of tasks which were still running
+    fn abort_tasks(&self, tasks: Vec<SupervisedTask>) -> usize {
+        let mut cancelled = 0;
+        for task in tasks {
+            if !task.abort.is_finished() {
+                task.cancel();
+                cancelled += 1;
+            }
+        }
+        self.metrics.cancelled_tasks.add(cancelled);
+        cancelled
+    }
+
+    /// Report the number of tasks currently owned
+    fn update_running(&self) {
+        self.metrics.running_tasks.set(self.len());
+    }
+}
+
+/// Wait for a task to finish, logging and counting its panic as soon as it happens
+async fn watch<K: Debug>(
+    handle: JoinHandle<()>,
+    key: K,
+    name: &'static str,
+    metrics: Arc<SupervisorMetrics>,
+) {
+    if let Err(e) = handle.await {
+        if e.is_panic() {
+            metrics.panicked_tasks.add(1);
+            tracing::error!("Task {key:?} of {name} panicked: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A counter which can be read back
+    #[derive(Clone, Debug, Default)]
+    struct TestCounter(Arc<AtomicUsize>);
+
+    impl Counter for TestCounter {
+        fn add(&self, amount: usize) {
+            self.0.fetch_add(amount, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancel_before_only_aborts_older_keys() {
+        let mut supervisor = TaskSupervisor::<u64>::new("test");
+        for key in 0..4 {
+            supervisor.spawn(key, futures::future::pending());
+        }
+
+        supervisor.cancel_before(&2);
+
+        assert!(!supervisor.contains(&0));
+        assert!(!supervisor.contains(&1));
+        assert!(supervisor.contains(&2));
+        assert!(supervisor.contains(&3));
+        assert_eq!(supervisor.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn abort_only_aborts_its_key() {
+        let mut supervisor = TaskSupervisor::<u64>::new("test");
+        supervisor.spawn(0, futures::future::pending());
+        supervisor.spawn(1, futures::future::pending());
+        supervisor.spawn(1, futures::future::pending());
+
+        supervisor.abort(&1);
+
+        assert!(supervisor.contains(&0));
+        assert!(!supervisor.contains(&1));
+        assert_eq!(supervisor.len(), 1);
+
+        supervisor.abort_all();
+        assert!(supervisor.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cancellable_task_is_told_to_stop() {
+        let mut supervisor = TaskSupervisor::<u64>::new("test");
+        let (stopped_sender, stopped) = tokio::sync::oneshot::channel();
+        supervisor.spawn_cancellable(0, |cancelled| async move {
+            // Stands in for blocking work which checks the flag between chunks.
+            let work = tokio::task::spawn_blocking(move || {
+                while !cancelled.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
+            let _ = work.await;
+            let _ = stopped_sender.send(());
+        });
+
+        assert_eq!(supervisor.cancel_before(&1), 1);
+        assert!(supervisor.is_empty());
+        timeout(Duration::from_secs(1), stopped)
+            .await
+            .expect("cancelled task should stop")
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_aborts_tasks_past_their_deadline() {
+        let timeouts = TestCounter::default();
+        let metrics = Arc::new(SupervisorMetrics {
+            shutdown_timeouts: Box::new(timeouts.clone()),
+            ..SupervisorMetrics::default()
+        });
+        let mut supervisor = TaskSupervisor::<u64>::with_metrics("test", metrics);
+        supervisor.spawn_with_deadline(0, futures::future::pending(), Duration::from_millis(10));
+        supervisor.spawn_with_deadline(1, async {}, Duration::from_millis(10));
+
+        timeout(Duration::from_secs(1), supervisor.shutdown())
+            .await
+            .expect("shutdown should respect the task deadlines");
+        assert!(supervisor.is_empty());
+        assert_eq!(timeouts.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn panicked_task_is_restarted() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let restarts = TestCounter::default();
+        let metrics = Arc::new(SupervisorMetrics {
+            restarted_tasks: Box::new(restarts.clone()),
+            ..SupervisorMetrics::default()
+        });
+        let mut supervisor = TaskSupervisor::<u64>::with_metrics("test", metrics);
+        let task_runs = Arc::clone(&runs);
+        supervisor.spawn_restartable(
+            0,
+            move || {
+                let runs = Arc::clone(&task_runs);
+                async move {
+                    if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                        panic!("task failure");
+                    }
+                }
+            },
+            Backoff {
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(5),
+                max_restarts: None,
+            },
+        );
+
+        timeout(Duration::from_secs(1), supervisor.shutdown())
+            .await
+            .unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(restarts.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn panic_is_reported_when_it_happens() {
+        let panics = TestCounter::default();
+        let metrics = Arc::new(SupervisorMetrics {
+            panicked_tasks: Box::new(panics.clone()),
+            ..SupervisorMetrics::default()
+        });
+        let mut supervisor = TaskSupervisor::<u64>::with_metrics("test", metrics);
+        supervisor.spawn(0, async { panic!("task failure") });
+
+        // The panic is reported without the supervisor cancelling or reaping the task.
+        timeout(Duration::from_secs(1), async {
+            while panics.0.load(Ordering::SeqCst) == 0 {
+                sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("panic should be reported");
+        assert!(supervisor.contains(&0));
+
+        supervisor.reap_finished();
+        assert!(supervisor.is_empty());
+    }
+}
@@ -4,13 +4,21 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Instant};
 
 use async_broadcast::{Receiver, RecvError, Sender};
 use async_trait::async_trait;
 use futures::future::try_join_all;
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    telemetry::{task_span, task_spans_enabled, TaskContext},
+    traits::metrics::{Gauge, Histogram, HistogramFamily, MetricsFamily, NoMetrics},
+};
 use hotshot_utils::anytrace::Result;
-use tokio::task::{spawn, JoinHandle};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+use crate::{broadcast_time::time_since_broadcast, profiling};
 
 /// Trait for events that long-running tasks handle
 pub trait TaskEvent: PartialEq {
@@ -19,6 +27,29 @@ pub trait TaskEvent: PartialEq {
     /// Note that this is necessarily uniform across all tasks.
     /// Exiting the task loop is handled by the task spawner, rather than the task individually.
     fn shutdown_event() -> Self;
+
+    /// The view this event belongs to, if any.
+    ///
+    /// Handling of events which belong to a view is traced in a span correlated with all other
+    /// work on the same view (see [`hotshot_types::telemetry`]).
+    fn view(&self) -> Option<u64> {
+        None
+    }
+
+    /// The epoch this event belongs to, if known.
+    fn epoch(&self) -> Option<u64> {
+        None
+    }
+
+    /// A short, stable name for the kind of this event.
+    fn kind(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// The peer this event was received from or is being sent to, if any.
+    fn peer(&self) -> Option<String> {
+        None
+    }
 }
 
 #[async_trait]
@@ -39,6 +70,61 @@ pub trait TaskState: Send {
     ) -> Result<()>;
 }
 
+/// A short name for the task with state `S`, without its module path or type parameters.
+pub(crate) fn task_name<S>() -> &'static str {
+    let name = std::any::type_name::<S>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Metrics reported by a [`Task`]
+#[derive(Debug)]
+pub struct TaskMetrics {
+    /// Number of events in the channel which some task has yet to receive
+    ///
+    /// The channel is shared by all tasks, and cannot tell how many of its events a particular task
+    /// has yet to receive, so this is an upper bound on the task's own backlog.
+    pub queue_depth: Box<dyn Gauge>,
+    /// Seconds spent handling each event
+    pub event_latency: Box<dyn Histogram>,
+    /// Seconds from the broadcast of each event until the task takes it up, by event kind
+    pub delivery_latency: Box<dyn HistogramFamily>,
+    /// Delivery latency histograms created so far, by event kind
+    delivery_latency_by_kind: HashMap<&'static str, Box<dyn Histogram>>,
+}
+
+impl TaskMetrics {
+    /// Create the metrics for the task named `name`
+    #[must_use]
+    pub fn new(metrics: &ConsensusMetricsValue, name: &str) -> Self {
+        Self {
+            queue_depth: metrics.task_queue_depth.create(vec![name.to_string()]),
+            event_latency: metrics.task_event_latency.create(vec![name.to_string()]),
+            delivery_latency: metrics.event_delivery_latency.clone(),
+            delivery_latency_by_kind: HashMap::new(),
+        }
+    }
+
+    /// Record that an event of kind `kind` was taken up `seconds` after it was broadcast.
+    fn record_delivery(&mut self, kind: &'static str, seconds: f64) {
+        self.delivery_latency_by_kind
+            .entry(kind)
+            .or_insert_with(|| self.delivery_latency.create(vec![kind.to_string()]))
+            .add_point(seconds);
+    }
+}
+
+impl Default for TaskMetrics {
+    fn default() -> Self {
+        Self {
+            queue_depth: Box::new(NoMetrics),
+            event_latency: Box::new(NoMetrics),
+            delivery_latency: Box::new(NoMetrics),
+            delivery_latency_by_kind: HashMap::new(),
+        }
+    }
+}
+
 /// A basic task which loops waiting for events to come from `event_receiver`
 /// and then handles them using its state
 /// It sends events to other `Task`s through `sender`
@@ -52,6 +138,8 @@ pub struct Task<S: TaskState> {
     sender: Sender<Arc<S::Event>>,
     /// Receives events that are broadcast from any task, including itself
     receiver: Receiver<Arc<S::Event>>,
+    /// Metrics on the task's backlog and how long it takes to handle events
+    metrics: TaskMetrics,
 }
 
 impl<S: TaskState + Send + 'static> Task<S> {
@@ -61,9 +149,17 @@ impl<S: TaskState + Send + 'static> Task<S> {
             state,
             sender,
             receiver,
+            metrics: TaskMetrics::default(),
         }
     }
 
+    /// Report queue depth and event handling latency for this task in `metrics`.
+    #[must_use]
+    pub fn with_metrics(mut self, metrics: &ConsensusMetricsValue) -> Self {
+        self.metrics = TaskMetrics::new(metrics, task_name::<S>());
+        self
+    }
+
     /// The state of the task, as a boxed dynamic trait object.
     fn boxed_state(self) -> Box<dyn TaskState<Event = S::Event>> {
         Box::new(self.state) as Box<dyn TaskState<Event = S::Event>>
@@ -72,28 +168,51 @@ impl<S: TaskState + Send + 'static> Task<S> {
     /// Spawn the task loop, consuming self.  Will continue until
     /// the task reaches some shutdown condition
     pub fn run(mut self) -> JoinHandle<Box<dyn TaskState<Event = S::Event>>> {
-        spawn(async move {
+        profiling::spawn(task_name::<S>(), async move {
             loop {
-                match self.receiver.recv_direct().await {
-                    Ok(input) => {
-                        if *input == S::Event::shutdown_event() {
-                            self.state.cancel_subtasks();
-
-                            break self.boxed_state();
-                        }
-
-                        let _ =
-                            S::handle_event(&mut self.state, input, &self.sender, &self.receiver)
-                                .await
-                                .inspect_err(|e| tracing::debug!("{e}"));
-                    },
-                    Err(RecvError::Closed) => {
-                        break self.boxed_state();
-                    },
+                let input = match self.receiver.recv_direct().await {
+                    Ok(input) => input,
+                    Err(RecvError::Closed) => break self.boxed_state(),
                     Err(e) => {
                         tracing::error!("Failed to receive from event stream Error: {}", e);
+                        continue;
                     },
+                };
+                self.metrics.queue_depth.set(self.receiver.len());
+                if let (Some(kind), Some(delay)) = (input.kind(), time_since_broadcast(&input)) {
+                    self.metrics.record_delivery(kind, delay.as_secs_f64());
+                }
+
+                if *input == S::Event::shutdown_event() {
+                    self.state.cancel_subtasks();
+
+                    break self.boxed_state();
+                }
+
+                let context = task_spans_enabled().then(|| TaskContext {
+                    task: task_name::<S>(),
+                    view: input.view(),
+                    epoch: input.epoch(),
+                    event: input.kind(),
+                    peer: input.peer(),
+                });
+                let span = task_span(task_name::<S>(), input.view());
+                if let Some(context) = context.as_ref().filter(|_| !span.is_disabled()) {
+                    span.record("epoch", context.epoch);
+                    span.record("event", context.event);
+                    span.record("peer", context.peer.as_deref());
                 }
+                let start = Instant::now();
+                let handle = S::handle_event(&mut self.state, input, &self.sender, &self.receiver)
+                    .instrument(span);
+                let _ = match context {
+                    Some(context) => context.scope(handle).await,
+                    None => handle.await,
+                }
+                .inspect_err(|e| tracing::debug!("{e}"));
+                self.metrics
+                    .event_latency
+                    .add_point(start.elapsed().as_secs_f64());
             }
         })
     }
@@ -127,9 +246,11 @@ impl<EVENT: Send + Sync + Clone + TaskEvent> ConsensusTaskRegistry<EVENT> {
         let handles = &mut self.task_handles;
 
         while let Some(handle) = handles.pop() {
-            let _ = handle
-                .await
-                .map(|mut task_state| task_state.cancel_subtasks());
+            match handle.await {
+                Ok(mut task_state) => task_state.cancel_subtasks(),
+                Err(e) if e.is_panic() => tracing::error!("Consensus task panicked: {e}"),
+                Err(_) => {},
+            }
         }
     }
     /// Take a task, run it, and register it
@@ -183,3 +304,74 @@ impl NetworkTaskRegistry {
         self.handles.push(handle);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_broadcast::broadcast;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TestEvent {
+        Work,
+        Shutdown,
+    }
+
+    impl TaskEvent for TestEvent {
+        fn shutdown_event() -> Self {
+            Self::Shutdown
+        }
+    }
+
+    struct TestState;
+
+    #[async_trait]
+    impl TaskState for TestState {
+        type Event = TestEvent;
+
+        fn cancel_subtasks(&mut self) {}
+
+        async fn handle_event(
+            &mut self,
+            _event: Arc<TestEvent>,
+            _sender: &Sender<Arc<TestEvent>>,
+            _receiver: &Receiver<Arc<TestEvent>>,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A gauge which remembers the largest value it was set to.
+    #[derive(Clone, Debug, Default)]
+    struct MaxGauge(Arc<AtomicUsize>);
+
+    impl Gauge for MaxGauge {
+        fn set(&self, amount: usize) {
+            self.0.fetch_max(amount, Ordering::SeqCst);
+        }
+
+        fn update(&self, _delta: i64) {}
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn queue_depth_counts_waiting_events() {
+        let (sender, receiver) = broadcast(16);
+        for _ in 0..3 {
+            sender.broadcast(Arc::new(TestEvent::Work)).await.unwrap();
+        }
+        sender
+            .broadcast(Arc::new(TestEvent::Shutdown))
+            .await
+            .unwrap();
+
+        let depth = MaxGauge::default();
+        let mut task = Task::new(TestState, sender, receiver);
+        task.metrics.queue_depth = Box::new(depth.clone());
+        task.run().await.unwrap();
+
+        // While handling the first event, the other two and the shutdown event were waiting.
+        assert_eq!(depth.0.load(Ordering::SeqCst), 3);
+    }
+}
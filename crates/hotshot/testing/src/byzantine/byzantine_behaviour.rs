@@ -1,15 +1,12 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{HashMap, HashSet},
     sync::Arc,
 };
 
 use anyhow::Context;
 use async_lock::RwLock;
 use async_trait::async_trait;
-use hotshot::{
-    tasks::EventTransformerState,
-    types::{SignatureKey, SystemContextHandle},
-};
+use hotshot::{tasks::EventTransformerState, types::SystemContextHandle};
 use hotshot_task_impls::{
     events::HotShotEvent,
     network::{
@@ -21,6 +18,7 @@ use hotshot_types::{
     consensus::{Consensus, OuterConsensus},
     data::QuorumProposalWrapper,
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_vote::QuorumVote2,
     traits::node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
 };
@@ -46,7 +44,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> EventTransforme
         &mut self,
         event: &HotShotEvent<TYPES>,
         _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -89,7 +87,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> EventTransforme
         &mut self,
         event: &HotShotEvent<TYPES>,
         _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -171,7 +169,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         &mut self,
         event: &HotShotEvent<TYPES>,
         _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -215,7 +213,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         &mut self,
         event: &HotShotEvent<TYPES>,
         _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -285,7 +283,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         &mut self,
         event: &HotShotEvent<TYPES>,
         _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -313,7 +311,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         &mut self,
         event: &HotShotEvent<TYPES>,
         public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -323,7 +321,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
                 vote.data.clone(),
                 new_view,
                 public_key,
-                private_key,
+                signer,
                 upgrade_lock,
             )
             .await
@@ -348,7 +346,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
             storage: Arc::clone(&handle.storage()),
             consensus: OuterConsensus::new(handle.consensus()),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
-            transmit_tasks: BTreeMap::new(),
+            compression: Arc::clone(&handle.hotshot.compression),
+            bandwidth: Arc::clone(&handle.hotshot.bandwidth),
+            transmit_tasks: handle.hotshot.task_supervisor("network_transmit"),
+            audit: Arc::default(),
             epoch_height: handle.epoch_height,
         };
         let modified_network_state = NetworkEventTaskStateModifier {
@@ -388,7 +389,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         &mut self,
         event: &HotShotEvent<TYPES>,
         public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -404,7 +405,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
                         self.votes_sent.last().unwrap().data.clone(),
                         event.view_number().unwrap(),
                         public_key,
-                        private_key,
+                        signer,
                         upgrade_lock,
                     )
                     .await
@@ -0,0 +1,297 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! An in-process cluster of full nodes for integration tests.
+//!
+//! [`TestCluster`] launches the nodes of a [`TestDescription`] over their in-memory networks the
+//! same way the [`TestRunner`] does, but instead of running the test tasks to completion it hands
+//! the nodes to the test, which can follow each node's events and consensus state, wait for
+//! decides, and stop and restart nodes as it goes.
+
+#![allow(clippy::panic)]
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::primitives::U256;
+use async_broadcast::broadcast;
+use async_lock::RwLock;
+use futures::{future::join_all, Stream};
+use hotshot::{traits::TestableNodeImplementation, types::SystemContextHandle, HotShotInitializer};
+use hotshot_example_types::{
+    auction_results_provider_types::TestAuctionResultsProvider,
+    block_types::TestBlockHeader,
+    state_types::{TestInstanceState, TestValidatedState},
+    storage_types::TestStorage,
+};
+use hotshot_types::{
+    consensus::Consensus,
+    constants::EVENT_CHANNEL_SIZE,
+    data::Leaf2,
+    event::Event,
+    message::convert_proposal,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        network::ConnectedNetwork,
+        node_implementation::{NodeImplementation, NodeType, Versions},
+    },
+    ValidatorConfig,
+};
+use tide_disco::Url;
+use tokio::{task::JoinHandle, time::sleep};
+
+use crate::{
+    block_builder::TestBuilderImplementation,
+    test_builder::TestDescription,
+    test_launcher::TestLauncher,
+    test_runner::{Node, TestRunner},
+};
+
+/// How long [`TestCluster::wait_for_decide`] waits before failing the test
+const DECIDE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often [`TestCluster::wait_for_decide`] checks the decided leaf of each node
+const DECIDE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A set of full nodes running in-process
+pub struct TestCluster<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> {
+    /// launcher the nodes were built with, used to rebuild them on restart
+    launcher: TestLauncher<TYPES, I, V>,
+    /// the nodes, by index
+    nodes: Vec<Node<TYPES, I, V>>,
+    /// whether each node is running
+    running: Vec<bool>,
+    /// the solver server running for the cluster
+    solver_server: Option<(Url, JoinHandle<()>)>,
+}
+
+impl<
+        TYPES: NodeType<
+            InstanceState = TestInstanceState,
+            ValidatedState = TestValidatedState,
+            BlockHeader = TestBlockHeader,
+        >,
+        I: TestableNodeImplementation<TYPES>,
+        V: Versions,
+    > TestCluster<TYPES, I, V>
+where
+    I: NodeImplementation<
+        TYPES,
+        Storage = TestStorage<TYPES>,
+        AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
+    >,
+{
+    /// Start every node of `description`, with builders of type `B`, and start consensus on all
+    /// of them once their networks are ready.
+    ///
+    /// Spinning changes, completion criteria and the other test tasks of `description` are
+    /// ignored; the test drives the cluster itself.
+    pub async fn start<B: TestBuilderImplementation<TYPES>>(
+        description: TestDescription<TYPES, I, V>,
+    ) -> Self {
+        let mut runner = description.gen_launcher().launch::<I::Network>();
+        let num_nodes = runner.launcher.metadata.test_config.num_nodes_with_stake;
+        runner
+            .add_nodes::<B>(num_nodes.into(), &HashSet::new(), &HashSet::new())
+            .await;
+
+        join_all(
+            runner
+                .nodes
+                .iter()
+                .map(|node| node.network.wait_for_ready()),
+        )
+        .await;
+        for node in &runner.nodes {
+            node.handle.hotshot.start_consensus().await;
+        }
+
+        Self {
+            running: vec![true; runner.nodes.len()],
+            launcher: runner.launcher,
+            nodes: runner.nodes,
+            solver_server: runner.solver_server,
+        }
+    }
+
+    /// Number of nodes in the cluster, running or not
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// The handle of node `index`
+    pub fn handle(&self, index: usize) -> &SystemContextHandle<TYPES, I, V> {
+        &self.nodes[index].handle
+    }
+
+    /// Whether node `index` is running
+    pub fn is_running(&self, index: usize) -> bool {
+        self.running[index]
+    }
+
+    /// A stream of the events of node `index`, from now on.
+    ///
+    /// The stream follows the node across restarts.
+    pub fn events(&self, index: usize) -> impl Stream<Item = Event<TYPES>> {
+        self.handle(index).event_stream()
+    }
+
+    /// The consensus state of node `index`
+    pub fn consensus(&self, index: usize) -> Arc<RwLock<Consensus<TYPES>>> {
+        self.handle(index).consensus()
+    }
+
+    /// Wait until node `index` has decided a leaf at or above `height`, and return its decided leaf.
+    ///
+    /// # Panics
+    /// If the node does not decide such a leaf in time.
+    pub async fn wait_for_node_decide(&self, index: usize, height: u64) -> Leaf2<TYPES> {
+        let handle = self.handle(index);
+        let wait = async {
+            loop {
+                let leaf = handle.decided_leaf().await;
+                if leaf.height() >= height {
+                    return leaf;
+                }
+                sleep(DECIDE_POLL_INTERVAL).await;
+            }
+        };
+        match tokio::time::timeout(DECIDE_TIMEOUT, wait).await {
+            Ok(leaf) => leaf,
+            Err(_) => panic!("node {index} did not decide height {height} in {DECIDE_TIMEOUT:?}"),
+        }
+    }
+
+    /// Wait until every running node has decided a leaf at or above `height`, and return the
+    /// decided leaf of each, in node order.
+    ///
+    /// # Panics
+    /// If a running node does not decide such a leaf in time.
+    pub async fn wait_for_decide(&self, height: u64) -> Vec<Leaf2<TYPES>> {
+        join_all(
+            (0..self.num_nodes())
+                .filter(|&index| self.is_running(index))
+                .map(|index| self.wait_for_node_decide(index, height)),
+        )
+        .await
+    }
+
+    /// Shut down node `index`, keeping its storage so it can be restarted.
+    pub async fn stop_node(&mut self, index: usize) {
+        if self.running[index] {
+            tracing::info!("Node {index} shutting down");
+            self.nodes[index].handle.shut_down().await;
+            self.running[index] = false;
+        }
+    }
+
+    /// Restart node `index` from its storage, shutting it down first if it is running.
+    ///
+    /// The node rejoins with a fresh network connection and resumes from the last view it acted
+    /// in, as a real node would after a crash.
+    pub async fn restart_node(&mut self, index: usize) {
+        let last_decided_leaf = self.handle(index).decided_leaf().await;
+        self.stop_node(index).await;
+
+        let node = &self.nodes[index];
+        let node_id = node.node_id;
+        let network = (self.launcher.resource_generators.channel_generator)(node_id).await;
+        let config = node.handle.hotshot.config.clone();
+        let marketplace_config = node.handle.hotshot.marketplace_config.clone();
+        let memberships = Arc::clone(node.handle.membership_coordinator.membership());
+
+        let storage = node.handle.storage();
+        let storage = storage.read().await;
+        let high_qc = match storage.high_qc_cloned().await {
+            Some(high_qc) => high_qc,
+            None => {
+                QuorumCertificate2::genesis::<V>(
+                    &TestValidatedState::default(),
+                    &TestInstanceState::default(),
+                )
+                .await
+            },
+        };
+        let mut vid_shares = BTreeMap::new();
+        for (view, shares) in storage.vids_cloned().await {
+            vid_shares.insert(
+                view,
+                shares
+                    .into_iter()
+                    .map(|(key, proposal)| (key, convert_proposal(proposal)))
+                    .collect(),
+            );
+        }
+        let initializer = HotShotInitializer::<TYPES>::load(
+            TestInstanceState::new(self.launcher.metadata.async_delay_config.clone()),
+            config.epoch_height,
+            config.epoch_start_block,
+            vec![],
+            last_decided_leaf,
+            (
+                storage.last_actioned_view().await,
+                storage.last_actioned_epoch().await,
+            ),
+            (high_qc, storage.next_epoch_high_qc_cloned().await),
+            storage.proposals_cloned().await,
+            vid_shares,
+            storage.decided_upgrade_certificate().await,
+            storage.state_cert_cloned().await,
+        );
+
+        // We assign node's public key and stake value rather than read from config file since it's a test
+        let validator_config = ValidatorConfig::generated_from_seed_indexed(
+            [0u8; 32],
+            node_id,
+            U256::from(1),
+            // For tests, make the node DA based on its index
+            node_id < config.da_staked_committee_size as u64,
+        );
+        let context = TestRunner::<TYPES, I, V, I::Network>::add_node_with_config_and_channels(
+            node_id,
+            network.clone(),
+            memberships,
+            initializer,
+            config,
+            validator_config,
+            (*storage).clone(),
+            marketplace_config,
+            broadcast(EVENT_CHANNEL_SIZE),
+            // Keep the external channel, so event streams taken before the restart follow the
+            // restarted node
+            (
+                node.handle.external_channel_sender(),
+                node.handle.event_stream_known_impl().new_receiver(),
+            ),
+        )
+        .await;
+        drop(storage);
+
+        let handle = context.run_tasks().await;
+        network.wait_for_ready().await;
+        handle.hotshot.start_consensus().await;
+        tracing::info!("Node {index} restarted");
+
+        self.nodes[index] = Node {
+            node_id,
+            network,
+            handle,
+        };
+        self.running[index] = true;
+    }
+
+    /// Shut down every running node.
+    pub async fn shut_down(mut self) {
+        for index in 0..self.num_nodes() {
+            self.stop_node(index).await;
+        }
+        if let Some((_, solver_server)) = self.solver_server.take() {
+            solver_server.abort();
+        }
+    }
+}
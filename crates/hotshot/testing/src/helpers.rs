@@ -29,17 +29,20 @@ use hotshot_types::{
     consensus::ConsensusMetricsValue,
     data::{vid_commitment, Leaf2, VidCommitment, VidDisperse, VidDisperseShare},
     epoch_membership::{EpochMembership, EpochMembershipCoordinator},
+    light_client::{LightClientState, StakeTableState},
     message::{Proposal, UpgradeLock},
-    simple_certificate::DaCertificate2,
+    signer::Signer,
+    simple_certificate::{DaCertificate2, LightClientStateUpdateCertificate, QuorumCertificate2},
     simple_vote::{DaData2, DaVote2, SimpleVote, VersionedVoteData},
     traits::{
         election::Membership,
         node_implementation::{NodeType, Versions},
+        signature_key::StateSignatureKey,
         EncodeBytes,
     },
     utils::{option_epoch_from_block_number, View, ViewInner},
     vote::{Certificate, HasViewNumber, Vote},
-    StakeTableEntries, ValidatorConfig,
+    PeerConfig, StakeTableEntries, ValidatorConfig,
 };
 use serde::Serialize;
 use vbs::version::Version;
@@ -132,7 +135,7 @@ pub async fn build_system_handle_from_launcher<
 
     let (c, s, r) = SystemContext::init(
         public_key,
-        private_key,
+        Signer::Local(private_key),
         state_private_key,
         node_id,
         hotshot_config,
@@ -178,7 +181,7 @@ pub async fn build_cert<
         data,
         view,
         public_key,
-        private_key,
+        &Signer::Local(private_key.clone()),
         upgrade_lock,
     )
     .await
@@ -240,13 +243,13 @@ pub async fn build_assembled_sig<
     let mut sig_lists = Vec::new();
 
     // assemble the vote
-    for node_id in 0..total_nodes {
-        let (private_key_i, public_key_i) = key_pair_for_id::<TYPES>(node_id.try_into().unwrap());
+    for node_id in node_ids_for_stake_table::<TYPES>(&stake_table) {
+        let (private_key_i, public_key_i) = key_pair_for_id::<TYPES>(node_id);
         let vote: SimpleVote<TYPES, DATAType> = SimpleVote::<TYPES, DATAType>::create_signed_vote(
             data.clone(),
             view,
             &public_key_i,
-            &private_key_i,
+            &Signer::Local(private_key_i),
             upgrade_lock,
         )
         .await
@@ -278,6 +281,93 @@ pub fn key_pair_for_id<TYPES: NodeType>(
     (private_key, public_key)
 }
 
+/// get the state signature keypair for a node id
+#[must_use]
+pub fn state_key_pair_for_id<TYPES: NodeType>(
+    node_id: u64,
+) -> (
+    <TYPES::StateSignatureKey as StateSignatureKey>::StatePrivateKey,
+    TYPES::StateSignatureKey,
+) {
+    let (public_key, private_key) =
+        TYPES::StateSignatureKey::generated_from_seed_indexed([0u8; 32], node_id);
+    (private_key, public_key)
+}
+
+/// Largest node id considered when matching stake table entries to test nodes
+const MAX_TEST_NODE_ID: u64 = 1024;
+
+/// get the ids of the test nodes in `stake_table`, in stake table order
+/// # Panics
+/// if an entry of the stake table does not belong to a node built by [`key_pair_for_id`]
+#[must_use]
+pub fn node_ids_for_stake_table<TYPES: NodeType>(stake_table: &[PeerConfig<TYPES>]) -> Vec<u64> {
+    let mut positions: BTreeMap<TYPES::SignatureKey, usize> = stake_table
+        .iter()
+        .enumerate()
+        .map(|(position, peer)| {
+            (
+                TYPES::SignatureKey::public_key(&peer.stake_table_entry),
+                position,
+            )
+        })
+        .collect();
+    let mut node_ids = vec![0; stake_table.len()];
+    for node_id in 0..MAX_TEST_NODE_ID {
+        if positions.is_empty() {
+            break;
+        }
+        if let Some(position) = positions.remove(&key_pair_for_id::<TYPES>(node_id).1) {
+            node_ids[position] = node_id;
+        }
+    }
+    assert!(
+        positions.is_empty(),
+        "Stake table contains keys which do not belong to any test node"
+    );
+    node_ids
+}
+
+/// Build a light client state update certificate for the epoch root block certified by `qc`,
+/// signed by the state keys of the stake table of `epoch_membership`
+/// # Panics
+/// if `epoch_membership` has no epoch or a light client state cannot be signed
+pub async fn build_state_cert<TYPES: NodeType>(
+    qc: &QuorumCertificate2<TYPES>,
+    epoch_membership: &EpochMembership<TYPES>,
+) -> LightClientStateUpdateCertificate<TYPES> {
+    let light_client_state = LightClientState {
+        view_number: *qc.view_number(),
+        block_height: qc.data.block_number.unwrap_or_default(),
+        block_comm_root: Default::default(),
+    };
+    let next_stake_table_state = StakeTableState::default();
+
+    let stake_table = epoch_membership.stake_table().await;
+    let signatures = node_ids_for_stake_table::<TYPES>(&stake_table)
+        .into_iter()
+        .map(|node_id| {
+            let (private_key, public_key) = state_key_pair_for_id::<TYPES>(node_id);
+            let signature = TYPES::StateSignatureKey::sign_state(
+                &private_key,
+                &light_client_state,
+                &next_stake_table_state,
+            )
+            .expect("Failed to sign light client state!");
+            (public_key, signature)
+        })
+        .collect();
+
+    LightClientStateUpdateCertificate {
+        epoch: epoch_membership
+            .epoch()
+            .expect("State certificates are only built with epochs"),
+        light_client_state,
+        next_stake_table_state,
+        signatures,
+    }
+}
+
 pub async fn da_payload_commitment<TYPES: NodeType, V: Versions>(
     membership: &EpochMembership<TYPES>,
     transactions: Vec<TestTransaction>,
@@ -339,13 +429,14 @@ pub async fn build_vid_proposal<TYPES: NodeType, V: Versions>(
         _pd: PhantomData,
     };
 
+    let signer = Signer::Local(private_key.clone());
     (
         vid_disperse_proposal,
         VidDisperseShare::from_vid_disperse(vid_disperse)
             .into_iter()
             .map(|vid_disperse| {
                 vid_disperse
-                    .to_proposal(private_key)
+                    .to_proposal(&signer)
                     .expect("Failed to sign payload commitment")
             })
             .collect(),
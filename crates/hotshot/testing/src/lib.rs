@@ -18,6 +18,9 @@ pub mod test_launcher;
 /// runner
 pub mod test_runner;
 
+/// in-process cluster of full nodes
+pub mod cluster;
+
 /// task that's consuming events and asserting safety
 pub mod overall_safety_task;
 
@@ -53,3 +56,10 @@ pub mod view_generator;
 
 /// byzantine framework for tests
 pub mod byzantine;
+
+pub mod partition;
+
+pub mod metrics;
+
+/// deterministic simulation runtime for tests
+pub mod simulation;
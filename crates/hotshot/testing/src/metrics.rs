@@ -0,0 +1,310 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Assertions over the consensus metrics of the nodes in a test.
+//!
+//! Each node started by the test runner records its `ConsensusMetricsValue` into a
+//! [`RecordedMetrics`]. Once the test is over, the [`MetricsAssertions`] of the test description
+//! are checked against the metrics of every node, which catches degradation that still lets
+//! views be decided, such as invalid QCs or frequent timeouts.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use hotshot_types::traits::metrics::{
+    Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics, MetricsFamily,
+    TextFamily,
+};
+
+/// Values recorded by a [`RecordedMetrics`] and its subgroups
+#[derive(Debug, Default)]
+struct MetricsValues {
+    /// Sum of each counter
+    counters: HashMap<String, usize>,
+    /// Latest value of each gauge
+    gauges: HashMap<String, usize>,
+    /// Highest value each gauge has been set to
+    gauge_peaks: HashMap<String, usize>,
+    /// Points added to each histogram
+    histograms: HashMap<String, Vec<f64>>,
+}
+
+/// A `Metrics` implementation which keeps every value it is given, so that tests can inspect them.
+///
+/// Metrics in a subgroup are named `<subgroup>-<name>`.
+#[derive(Clone, Debug, Default)]
+pub struct RecordedMetrics {
+    /// Prefix of the names of metrics created from this instance
+    prefix: String,
+    /// Values shared with every metric created from this instance
+    values: Arc<Mutex<MetricsValues>>,
+}
+
+impl RecordedMetrics {
+    /// The value of the counter `name`, or 0 if it was never incremented
+    ///
+    /// # Panics
+    /// if the lock on the values is poisoned
+    #[must_use]
+    pub fn counter(&self, name: &str) -> usize {
+        let values = self.values.lock().unwrap();
+        values.counters.get(name).copied().unwrap_or_default()
+    }
+
+    /// The latest value of the gauge `name`, or 0 if it was never set
+    ///
+    /// # Panics
+    /// if the lock on the values is poisoned
+    #[must_use]
+    pub fn gauge(&self, name: &str) -> usize {
+        let values = self.values.lock().unwrap();
+        values.gauges.get(name).copied().unwrap_or_default()
+    }
+
+    /// The highest value the gauge `name` has had, or 0 if it was never set
+    ///
+    /// # Panics
+    /// if the lock on the values is poisoned
+    #[must_use]
+    pub fn gauge_peak(&self, name: &str) -> usize {
+        let values = self.values.lock().unwrap();
+        values.gauge_peaks.get(name).copied().unwrap_or_default()
+    }
+
+    /// The points added to the histogram `name`
+    ///
+    /// # Panics
+    /// if the lock on the values is poisoned
+    #[must_use]
+    pub fn histogram(&self, name: &str) -> Vec<f64> {
+        let values = self.values.lock().unwrap();
+        values.histograms.get(name).cloned().unwrap_or_default()
+    }
+
+    /// A handle recording into the same values, under `name`
+    fn sub(&self, name: String) -> Self {
+        let prefix = if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}-{name}", self.prefix)
+        };
+        Self {
+            prefix,
+            values: Arc::clone(&self.values),
+        }
+    }
+
+    /// A handle recording into the same values, under the given labels
+    fn family(&self, labels: Vec<String>) -> Self {
+        labels
+            .into_iter()
+            .fold(self.clone(), |metrics, label| metrics.sub(label))
+    }
+
+    /// Set the gauge of this handle to `value`
+    fn set_gauge(&self, values: &mut MetricsValues, value: usize) {
+        values.gauges.insert(self.prefix.clone(), value);
+        let peak = values.gauge_peaks.entry(self.prefix.clone()).or_default();
+        *peak = (*peak).max(value);
+    }
+}
+
+impl Metrics for RecordedMetrics {
+    fn create_counter(&self, name: String, _unit_label: Option<String>) -> Box<dyn Counter> {
+        Box::new(self.sub(name))
+    }
+
+    fn create_gauge(&self, name: String, _unit_label: Option<String>) -> Box<dyn Gauge> {
+        Box::new(self.sub(name))
+    }
+
+    fn create_histogram(&self, name: String, _unit_label: Option<String>) -> Box<dyn Histogram> {
+        Box::new(self.sub(name))
+    }
+
+    fn create_text(&self, name: String) {
+        self.create_gauge(name, None).set(1);
+    }
+
+    fn counter_family(&self, name: String, _: Vec<String>) -> Box<dyn CounterFamily> {
+        Box::new(self.sub(name))
+    }
+
+    fn gauge_family(&self, name: String, _: Vec<String>) -> Box<dyn GaugeFamily> {
+        Box::new(self.sub(name))
+    }
+
+    fn histogram_family(&self, name: String, _: Vec<String>) -> Box<dyn HistogramFamily> {
+        Box::new(self.sub(name))
+    }
+
+    fn text_family(&self, name: String, _: Vec<String>) -> Box<dyn TextFamily> {
+        Box::new(self.sub(name))
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(self.sub(subgroup_name))
+    }
+}
+
+impl Counter for RecordedMetrics {
+    fn add(&self, amount: usize) {
+        *self
+            .values
+            .lock()
+            .unwrap()
+            .counters
+            .entry(self.prefix.clone())
+            .or_default() += amount;
+    }
+}
+
+impl Gauge for RecordedMetrics {
+    fn set(&self, amount: usize) {
+        self.set_gauge(&mut self.values.lock().unwrap(), amount);
+    }
+
+    fn update(&self, delta: i64) {
+        let mut values = self.values.lock().unwrap();
+        let value = values.gauges.get(&self.prefix).copied().unwrap_or_default();
+        let signed_value = i64::try_from(value).unwrap_or(i64::MAX);
+        self.set_gauge(
+            &mut values,
+            usize::try_from(signed_value + delta).unwrap_or(0),
+        );
+    }
+}
+
+impl Histogram for RecordedMetrics {
+    fn add_point(&self, point: f64) {
+        self.values
+            .lock()
+            .unwrap()
+            .histograms
+            .entry(self.prefix.clone())
+            .or_default()
+            .push(point);
+    }
+}
+
+impl MetricsFamily<Box<dyn Counter>> for RecordedMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        Box::new(self.family(labels))
+    }
+}
+
+impl MetricsFamily<Box<dyn Gauge>> for RecordedMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
+        Box::new(self.family(labels))
+    }
+}
+
+impl MetricsFamily<Box<dyn Histogram>> for RecordedMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
+        Box::new(self.family(labels))
+    }
+}
+
+impl MetricsFamily<()> for RecordedMetrics {
+    fn create(&self, labels: Vec<String>) {
+        self.family(labels).set(1);
+    }
+}
+
+/// A check on the metrics of a node, returning whether it holds
+type MetricsCheck = Arc<dyn Fn(&RecordedMetrics) -> bool + Send + Sync>;
+
+/// Assertions checked against the metrics of every node once a test is over
+#[derive(Clone, Default)]
+pub struct MetricsAssertions {
+    /// Description of each assertion, with its check
+    assertions: Vec<(String, MetricsCheck)>,
+}
+
+impl MetricsAssertions {
+    /// No assertions
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether there are no assertions to check
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.assertions.is_empty()
+    }
+
+    /// Assert that `check` holds for the metrics of every node
+    #[must_use]
+    pub fn custom(
+        mut self,
+        description: impl Into<String>,
+        check: impl Fn(&RecordedMetrics) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.assertions.push((description.into(), Arc::new(check)));
+        self
+    }
+
+    /// Assert that the counter `name` is at most `max` on every node
+    #[must_use]
+    pub fn counter_at_most(self, name: &str, max: usize) -> Self {
+        let name = name.to_string();
+        self.custom(format!("counter {name} <= {max}"), move |metrics| {
+            metrics.counter(&name) <= max
+        })
+    }
+
+    /// Assert that the counter `name` is at least `min` on every node
+    #[must_use]
+    pub fn counter_at_least(self, name: &str, min: usize) -> Self {
+        let name = name.to_string();
+        self.custom(format!("counter {name} >= {min}"), move |metrics| {
+            metrics.counter(&name) >= min
+        })
+    }
+
+    /// Assert that the gauge `name` never exceeded `max` on any node
+    #[must_use]
+    pub fn gauge_peak_at_most(self, name: &str, max: usize) -> Self {
+        let name = name.to_string();
+        self.custom(format!("gauge {name} peak <= {max}"), move |metrics| {
+            metrics.gauge_peak(&name) <= max
+        })
+    }
+
+    /// Assert that no node saw an invalid QC
+    #[must_use]
+    pub fn no_invalid_qcs(self) -> Self {
+        self.gauge_peak_at_most("invalid_qc", 0)
+    }
+
+    /// Assert that no node timed out in more than `max` views
+    #[must_use]
+    pub fn timeouts_at_most(self, max: usize) -> Self {
+        self.counter_at_most("number_of_timeouts", max)
+    }
+
+    /// Assert that no node proposed more than `max` empty blocks
+    #[must_use]
+    pub fn empty_blocks_proposed_at_most(self, max: usize) -> Self {
+        self.counter_at_most("number_of_empty_blocks_proposed", max)
+    }
+
+    /// Check the assertions against the metrics of node `node_id`, returning a description of
+    /// each one which does not hold
+    #[must_use]
+    pub fn check(&self, node_id: u64, metrics: &RecordedMetrics) -> Vec<String> {
+        self.assertions
+            .iter()
+            .filter(|(_, check)| !check(metrics))
+            .map(|(description, _)| {
+                format!("Node {node_id} failed metrics assertion: {description}")
+            })
+            .collect()
+    }
+}
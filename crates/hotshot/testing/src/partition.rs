@@ -0,0 +1,218 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Network partitions for tests.
+//!
+//! A [`NetworkPartition`] splits nodes into groups for a range of views. Messages for a view in
+//! the range which cross from one group to another are dropped, delayed or duplicated, and the
+//! network heals once consensus moves past the range. Consensus must then recover within
+//! [`NetworkPartition::recovery_views`] views, which the consistency task checks.
+
+use std::{collections::HashMap, fmt::Debug, ops::Range, sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::{tasks::EventTransformerState, types::SystemContextHandle};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    network::{
+        test::{NetworkEventTaskStatePlanner, TransmitPlanner},
+        NetworkEventTaskState,
+    },
+};
+use hotshot_types::{
+    consensus::{Consensus, OuterConsensus},
+    message::{UpgradeLock, ViewMessage},
+    signer::Signer,
+    traits::{
+        election::Membership,
+        network::TransmitType,
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+        signature_key::StakeTableEntryType,
+    },
+    utils::genesis_epoch_from_version,
+};
+
+use crate::helpers::key_pair_for_id;
+
+/// What happens to a message sent from one group of a partition to another
+#[derive(Clone, Copy, Debug)]
+pub enum CrossPartitionPolicy {
+    /// The message is lost
+    Drop,
+    /// The message arrives after the given delay
+    Delay(Duration),
+    /// The message is delivered the given number of times
+    Duplicate(usize),
+}
+
+/// A split of the network into groups of nodes for a range of views
+///
+/// Since the partition applies to messages by their view, consensus can only move past `views`
+/// with [`CrossPartitionPolicy::Drop`] if some group holds a quorum.
+#[derive(Clone, Debug)]
+pub struct NetworkPartition {
+    /// Ids of the nodes in each group. Nodes in no group reach every node.
+    pub groups: Vec<Vec<u64>>,
+    /// Views for which the partition is in place
+    pub views: Range<u64>,
+    /// What happens to messages between groups
+    pub policy: CrossPartitionPolicy,
+    /// Number of views after the partition heals within which consensus must decide again
+    pub recovery_views: u64,
+}
+
+impl NetworkPartition {
+    /// The group containing `node_id`, if any
+    fn group_of(&self, node_id: u64) -> Option<usize> {
+        self.groups
+            .iter()
+            .position(|group| group.contains(&node_id))
+    }
+
+    /// Whether the partition separates `sender` from `recipient` for a message for `view`
+    fn separates(&self, view: u64, sender: u64, recipient: u64) -> bool {
+        self.views.contains(&view)
+            && matches!(
+                (self.group_of(sender), self.group_of(recipient)),
+                (Some(sender), Some(recipient)) if sender != recipient
+            )
+    }
+
+    /// The views which may fail because of the partition: those it is in place for, and those
+    /// consensus has to recover in once it heals.
+    #[must_use]
+    pub fn possible_view_failures(&self) -> Range<u64> {
+        self.views.start..self.views.end + self.recovery_views
+    }
+}
+
+/// The views which may fail because of any of `partitions`
+#[must_use]
+pub fn possible_view_failures(partitions: &[NetworkPartition]) -> Vec<u64> {
+    partitions
+        .iter()
+        .flat_map(NetworkPartition::possible_view_failures)
+        .collect()
+}
+
+/// Build a [`TransmitPlanner`] applying `partitions` to the messages sent by node `node_id`.
+///
+/// While a partition is in place, broadcasts are sent to each member of the stake table (or DA
+/// committee) directly, so that the policy can be applied per recipient.
+#[must_use]
+pub fn transmit_planner<TYPES: NodeType>(
+    node_id: u64,
+    partitions: Vec<NetworkPartition>,
+) -> Arc<TransmitPlanner<TYPES>> {
+    let node_ids: HashMap<TYPES::SignatureKey, u64> = partitions
+        .iter()
+        .flat_map(|partition| partition.groups.iter().flatten().copied())
+        .map(|id| (key_pair_for_id::<TYPES>(id).1, id))
+        .collect();
+
+    Arc::new(move |_sender, message_kind, transmit, membership, epoch| {
+        let view_number = message_kind.view_number();
+        let Some(partition) = partitions.iter().find(|partition| {
+            partition.views.contains(&view_number.u64()) && partition.group_of(node_id).is_some()
+        }) else {
+            return vec![(transmit, Duration::ZERO)];
+        };
+
+        let recipients: Vec<TYPES::SignatureKey> = match transmit {
+            TransmitType::Direct(recipient) => vec![recipient],
+            TransmitType::Broadcast => membership
+                .stake_table(epoch)
+                .into_iter()
+                .map(|peer| peer.stake_table_entry.public_key())
+                .collect(),
+            TransmitType::DaCommitteeBroadcast => membership
+                .da_committee_members(view_number, epoch)
+                .into_iter()
+                .collect(),
+        };
+
+        recipients
+            .into_iter()
+            .flat_map(|recipient| {
+                let separated = node_ids.get(&recipient).is_some_and(|recipient_id| {
+                    partition.separates(view_number.u64(), node_id, *recipient_id)
+                });
+                let delays = match (separated, partition.policy) {
+                    (false, _) => vec![Duration::ZERO],
+                    (true, CrossPartitionPolicy::Drop) => vec![],
+                    (true, CrossPartitionPolicy::Delay(delay)) => vec![delay],
+                    (true, CrossPartitionPolicy::Duplicate(copies)) => {
+                        vec![Duration::ZERO; copies]
+                    },
+                };
+
+                delays
+                    .into_iter()
+                    .map(move |delay| (TransmitType::Direct(recipient.clone()), delay))
+            })
+            .collect()
+    })
+}
+
+/// An `EventTransformerState` which leaves events untouched, but transmits the messages of the
+/// node through a partitioned network
+pub struct PartitionedNetwork<TYPES: NodeType> {
+    /// A function passed to `NetworkEventTaskStatePlanner` to decide how messages are transmitted
+    pub planner: Arc<TransmitPlanner<TYPES>>,
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> EventTransformerState<TYPES, I, V>
+    for PartitionedNetwork<TYPES>
+{
+    async fn recv_handler(&mut self, event: &HotShotEvent<TYPES>) -> Vec<HotShotEvent<TYPES>> {
+        vec![event.clone()]
+    }
+
+    async fn send_handler(
+        &mut self,
+        event: &HotShotEvent<TYPES>,
+        _public_key: &TYPES::SignatureKey,
+        _signer: &Signer<TYPES::SignatureKey>,
+        _upgrade_lock: &UpgradeLock<TYPES, V>,
+        _consensus: Arc<RwLock<Consensus<TYPES>>>,
+    ) -> Vec<HotShotEvent<TYPES>> {
+        vec![event.clone()]
+    }
+
+    fn add_network_event_task(
+        &self,
+        handle: &mut SystemContextHandle<TYPES, I, V>,
+        network: Arc<<I as NodeImplementation<TYPES>>::Network>,
+    ) {
+        let network_state: NetworkEventTaskState<_, V, _, _> = NetworkEventTaskState {
+            network,
+            view: TYPES::View::genesis(),
+            epoch: genesis_epoch_from_version::<V, TYPES>(),
+            membership_coordinator: handle.membership_coordinator.clone(),
+            storage: Arc::clone(&handle.storage()),
+            consensus: OuterConsensus::new(handle.consensus()),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            compression: Arc::clone(&handle.hotshot.compression),
+            bandwidth: Arc::clone(&handle.hotshot.bandwidth),
+            transmit_tasks: handle.hotshot.task_supervisor("network_transmit"),
+            audit: Arc::default(),
+            epoch_height: handle.epoch_height,
+        };
+        let planned_network_state = NetworkEventTaskStatePlanner {
+            network_event_task_state: network_state,
+            planner: Arc::clone(&self.planner),
+        };
+        handle.add_task(planned_network_state);
+    }
+}
+
+impl<TYPES: NodeType> Debug for PartitionedNetwork<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PartitionedNetwork").finish_non_exhaustive()
+    }
+}
@@ -10,11 +10,13 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use hotshot_task_impls::events::{HotShotEvent, HotShotEvent::*};
 use hotshot_types::{
-    data::null_block,
+    data::{null_block, QuorumProposalWrapper, VidCommitment},
+    simple_vote::HasEpoch,
     traits::{
         block_contents::BlockHeader,
         node_implementation::{NodeType, Versions},
     },
+    vote::HasViewNumber,
 };
 
 use crate::predicates::{Predicate, PredicateResult};
@@ -285,3 +287,147 @@ where
     });
     Box::new(EventPredicate { check, info })
 }
+
+/// A predicate satisfied by the events for which `check` holds, described by `info`
+pub fn event_matching<TYPES, F>(info: impl Into<String>, check: F) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+    F: Fn(&HotShotEvent<TYPES>) -> bool + Send + Sync + 'static,
+{
+    let info = info.into();
+    let check: EventCallback<TYPES> = Arc::new(move |e: Arc<HotShotEvent<TYPES>>| check(&e));
+    Box::new(EventPredicate { check, info })
+}
+
+/// A predicate satisfied by the events matching a pattern, and a guard over the fields it binds if
+/// one is given, so that a test can check only the fields it cares about:
+///
+/// ```ignore
+/// event_matches!(DaVoteSend(vote) if vote.view_number() == ViewNumber::new(2))
+/// ```
+///
+/// The variants named in the pattern must be in scope.
+#[macro_export]
+macro_rules! event_matches {
+    ($pattern:pat $(if $guard:expr)? $(,)?) => {
+        $crate::predicates::event::event_matching(
+            stringify!($pattern $(if $guard)?),
+            move |e| matches!(e, $pattern $(if $guard)?),
+        )
+    };
+}
+
+pub fn da_vote_send_for_view<TYPES>(view_number: TYPES::View) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    event_matching(
+        format!("DaVoteSend for view {view_number:?}"),
+        move |e| matches!(e, DaVoteSend(vote) if vote.view_number() == view_number),
+    )
+}
+
+pub fn quorum_vote_send_for_view<TYPES>(view_number: TYPES::View) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    event_matching(
+        format!("QuorumVoteSend for view {view_number:?}"),
+        move |e| matches!(e, QuorumVoteSend(vote) if vote.view_number() == view_number),
+    )
+}
+
+/// The contents a quorum proposal must have; fields left as `None` are not checked
+pub struct ProposalContents<TYPES: NodeType> {
+    pub view_number: Option<TYPES::View>,
+    pub epoch: Option<Option<TYPES::Epoch>>,
+    pub payload_commitment: Option<VidCommitment>,
+}
+
+impl<TYPES: NodeType> Default for ProposalContents<TYPES> {
+    fn default() -> Self {
+        Self {
+            view_number: None,
+            epoch: None,
+            payload_commitment: None,
+        }
+    }
+}
+
+impl<TYPES: NodeType> std::fmt::Debug for ProposalContents<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut checks = vec![];
+        if let Some(view_number) = &self.view_number {
+            checks.push(format!("view {view_number:?}"));
+        }
+        if let Some(epoch) = &self.epoch {
+            checks.push(format!("epoch {epoch:?}"));
+        }
+        if let Some(payload_commitment) = &self.payload_commitment {
+            checks.push(format!("payload commitment {payload_commitment}"));
+        }
+        if checks.is_empty() {
+            write!(f, "any contents")
+        } else {
+            write!(f, "{}", checks.join(", "))
+        }
+    }
+}
+
+impl<TYPES: NodeType> ProposalContents<TYPES> {
+    /// Whether `proposal` has these contents
+    pub fn matches(&self, proposal: &QuorumProposalWrapper<TYPES>) -> bool {
+        self.view_number
+            .is_none_or(|view_number| proposal.view_number() == view_number)
+            && self.epoch.is_none_or(|epoch| proposal.epoch() == epoch)
+            && self.payload_commitment.is_none_or(|payload_commitment| {
+                proposal.block_header().payload_commitment() == payload_commitment
+            })
+    }
+}
+
+pub fn quorum_proposal_send_with<TYPES>(
+    contents: ProposalContents<TYPES>,
+) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    event_matching(
+        format!("QuorumProposalSend with {contents:?}"),
+        move |e| matches!(e, QuorumProposalSend(proposal, _) if contents.matches(&proposal.data)),
+    )
+}
+
+pub fn quorum_proposal_recv_with<TYPES>(
+    contents: ProposalContents<TYPES>,
+) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    event_matching(
+        format!("QuorumProposalRecv with {contents:?}"),
+        move |e| matches!(e, QuorumProposalRecv(proposal, _) if contents.matches(&proposal.data)),
+    )
+}
+
+pub fn quorum_proposal_validated_with<TYPES>(
+    contents: ProposalContents<TYPES>,
+) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    event_matching(
+        format!("QuorumProposalValidated with {contents:?}"),
+        move |e| matches!(e, QuorumProposalValidated(proposal, _) if contents.matches(&proposal.data)),
+    )
+}
+
+pub fn quorum_proposal_send_for_view<TYPES>(view_number: TYPES::View) -> Box<EventPredicate<TYPES>>
+where
+    TYPES: NodeType,
+{
+    quorum_proposal_send_with(ProposalContents {
+        view_number: Some(view_number),
+        ..Default::default()
+    })
+}
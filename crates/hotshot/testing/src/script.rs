@@ -4,7 +4,11 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use hotshot_task_impls::events::HotShotEvent;
 use hotshot_types::traits::node_implementation::NodeType;
@@ -36,6 +40,9 @@ macro_rules! serial {
     };
 }
 
+/// Time a stage is given to complete, unless it sets its own with [`Expectations::with_timeout`]
+pub const DEFAULT_STAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct TaskScript<TYPES: NodeType, S> {
     /// The time to wait on the receiver for this script.
     pub timeout: Duration,
@@ -43,17 +50,33 @@ pub struct TaskScript<TYPES: NodeType, S> {
     pub expectations: Vec<Expectations<TYPES, S>>,
 }
 
+/// The order in which the outputs of a stage must satisfy its `output_asserts`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// Each output must satisfy the first predicate not yet satisfied
+    #[default]
+    Ordered,
+    /// Each output must satisfy a distinct predicate, in any order. An output satisfying several
+    /// predicates is matched to one of them, and rematched later if another output needs it.
+    Unordered,
+}
+
 pub struct Expectations<TYPES: NodeType, S> {
     pub output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
+    /// Outputs the stage may produce at any point, which are not required to occur.
+    pub optional_output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
+    /// Outputs the stage must not produce at any point.
+    pub forbidden_output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
+    /// The order in which `output_asserts` must be satisfied.
+    pub output_order: OutputOrder,
     pub task_state_asserts: Vec<Box<dyn Predicate<S>>>,
+    /// The time the stage is given to handle its inputs and receive its outputs.
+    pub timeout: Duration,
 }
 
 impl<TYPES: NodeType, S> Expectations<TYPES, S> {
     pub fn from_outputs(output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>) -> Self {
-        Self {
-            output_asserts,
-            task_state_asserts: vec![],
-        }
+        Self::from_outputs_and_task_states(output_asserts, vec![])
     }
     pub fn from_outputs_and_task_states(
         output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
@@ -61,9 +84,192 @@ impl<TYPES: NodeType, S> Expectations<TYPES, S> {
     ) -> Self {
         Self {
             output_asserts,
+            optional_output_asserts: vec![],
+            forbidden_output_asserts: vec![],
+            output_order: OutputOrder::Ordered,
             task_state_asserts,
+            timeout: DEFAULT_STAGE_TIMEOUT,
+        }
+    }
+    /// Expect the outputs of the stage in any order.
+    #[must_use]
+    pub fn unordered(self) -> Self {
+        Self {
+            output_order: OutputOrder::Unordered,
+            ..self
+        }
+    }
+    /// Allow the stage to produce outputs satisfying `optional_output_asserts` without requiring them.
+    #[must_use]
+    pub fn with_optional_outputs(
+        self,
+        optional_output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
+    ) -> Self {
+        Self {
+            optional_output_asserts,
+            ..self
         }
     }
+    /// Fail the stage if it produces any output satisfying one of `forbidden_output_asserts`, e.g.
+    /// `without_outputs(vec![da_vote_send_for_view(view)])` fails it if the task votes in `view`.
+    #[must_use]
+    pub fn without_outputs(
+        self,
+        forbidden_output_asserts: Vec<Box<dyn Predicate<Arc<HotShotEvent<TYPES>>>>>,
+    ) -> Self {
+        Self {
+            forbidden_output_asserts,
+            ..self
+        }
+    }
+    /// Fail the stage if it has not completed within `timeout`, instead of [`DEFAULT_STAGE_TIMEOUT`].
+    #[must_use]
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+/// Progress of a script through the outputs expected in a stage
+#[derive(Debug, Default)]
+pub struct StageProgress {
+    /// For each output matched to the `output_asserts`, the indices of those it satisfies
+    outputs: Vec<Vec<usize>>,
+    /// The output each satisfied `output_assert` is matched to, by index
+    matching: BTreeMap<usize, usize>,
+}
+
+impl StageProgress {
+    /// Whether the `output_assert` at `index` has been matched to an output
+    fn is_satisfied(&self, index: usize) -> bool {
+        self.matching.contains_key(&index)
+    }
+
+    /// Record an output satisfying the `output_asserts` at `indices`, and try to match it to one
+    /// of them, rematching earlier outputs along an augmenting path if necessary.
+    ///
+    /// Returns `false` if the output cannot be matched without leaving an earlier one unmatched.
+    fn match_output(&mut self, indices: Vec<usize>) -> bool {
+        self.outputs.push(indices);
+        let output = self.outputs.len() - 1;
+        if self.augment(output, &mut BTreeSet::new()) {
+            true
+        } else {
+            self.outputs.pop();
+            false
+        }
+    }
+
+    fn augment(&mut self, output: usize, visited: &mut BTreeSet<usize>) -> bool {
+        for index in self.outputs[output].clone() {
+            if !visited.insert(index) {
+                continue;
+            }
+            let rematched = match self.matching.get(&index) {
+                None => true,
+                Some(&other) => self.augment(other, visited),
+            };
+            if rematched {
+                self.matching.insert(index, output);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Validate an output received in a stage against its expectations, panicking if the stage does
+/// not allow for it.
+pub async fn validate_stage_output_or_panic_in_script<TYPES: NodeType, S>(
+    stage_number: usize,
+    script_name: String,
+    expectations: &Expectations<TYPES, S>,
+    progress: &mut StageProgress,
+    output: &Arc<HotShotEvent<TYPES>>,
+) {
+    for assert in &expectations.forbidden_output_asserts {
+        if assert.evaluate(output).await == PredicateResult::Pass {
+            panic!(
+                "Stage {} | Output in {} is forbidden by: {:?}.\n\nReceived:\n\n{:?}",
+                stage_number, script_name, assert, output
+            );
+        }
+    }
+
+    let unsatisfied: Vec<usize> = (0..expectations.output_asserts.len())
+        .filter(|index| !progress.is_satisfied(*index))
+        .collect();
+
+    let matched = match expectations.output_order {
+        OutputOrder::Ordered => match unsatisfied.first() {
+            Some(&index) => match expectations.output_asserts[index].evaluate(output).await {
+                PredicateResult::Pass => progress.match_output(vec![index]),
+                PredicateResult::Incomplete => return,
+                PredicateResult::Fail => false,
+            },
+            None => false,
+        },
+        OutputOrder::Unordered => {
+            // Satisfied predicates are evaluated too, as their outputs may be rematched
+            let mut passed = vec![];
+            let mut incomplete = false;
+            for (index, assert) in expectations.output_asserts.iter().enumerate() {
+                match assert.evaluate(output).await {
+                    PredicateResult::Pass => passed.push(index),
+                    PredicateResult::Incomplete => incomplete = true,
+                    PredicateResult::Fail => {},
+                }
+            }
+            if passed.is_empty() && incomplete {
+                return;
+            }
+            !passed.is_empty() && progress.match_output(passed)
+        },
+    };
+    if matched {
+        return;
+    }
+
+    for assert in &expectations.optional_output_asserts {
+        if assert.evaluate(output).await != PredicateResult::Fail {
+            return;
+        }
+    }
+
+    match (expectations.output_order, unsatisfied.as_slice()) {
+        (_, []) => panic_extra_output_in_script(stage_number, script_name, output),
+        (OutputOrder::Ordered, [index, ..]) => panic!(
+            "Stage {} | Output in {} failed to satisfy: {:?}.\n\nReceived:\n\n{:?}",
+            stage_number, script_name, expectations.output_asserts[*index], output
+        ),
+        (OutputOrder::Unordered, indices) => panic!(
+            "Stage {} | Output in {} failed to satisfy any of: {:?}.\n\nReceived:\n\n{:?}",
+            stage_number,
+            script_name,
+            indices
+                .iter()
+                .map(|index| &expectations.output_asserts[*index])
+                .collect::<Vec<_>>(),
+            output
+        ),
+    }
+}
+
+/// Panic if any output required by the stage has not been received.
+pub fn check_stage_outputs_or_panic_in_script<TYPES: NodeType, S>(
+    stage_number: usize,
+    script_name: String,
+    expectations: &Expectations<TYPES, S>,
+    progress: &StageProgress,
+) {
+    if let Some(index) =
+        (0..expectations.output_asserts.len()).find(|index| !progress.is_satisfied(*index))
+    {
+        panic_missing_output_in_script(
+            stage_number,
+            script_name,
+            &expectations.output_asserts[index],
+        );
+    }
 }
 
 pub fn panic_extra_output_in_script<S>(stage_number: usize, script_name: String, output: &S)
@@ -78,6 +284,19 @@ where
     panic!("{}", extra_output_error);
 }
 
+pub fn panic_stage_timeout_in_script(
+    stage_number: usize,
+    script_names: &[&str],
+    timeout: Duration,
+) -> ! {
+    panic!(
+        "Stage {} | {} did not complete within {:?}",
+        stage_number,
+        script_names.join(", "),
+        timeout
+    );
+}
+
 pub fn panic_missing_output_in_script<S>(stage_number: usize, script_name: String, predicate: &S)
 where
     S: std::fmt::Debug,
@@ -0,0 +1,127 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Deterministic simulation of tests.
+//!
+//! A [`Simulation`] drives a test on a single-threaded tokio runtime with a paused clock: timers
+//! fire in virtual time as soon as every task is idle, and tasks are polled in an order which only
+//! depends on the test itself. Randomness drawn through [`with_rng`] comes from a generator seeded
+//! with the simulation seed, so a failing run can be replayed exactly by setting [`SEED_ENV_VAR`]
+//! to the seed it logged.
+//!
+//! When built with `--cfg tokio_unstable`, the seed is also handed to tokio, which makes the
+//! branch order of `tokio::select!` deterministic as well.
+//!
+//! Running a simulation needs the virtual clock of tokio's `test-util`, so it is only available
+//! with the `simulation` feature. `TestRunner::run_simulation` runs a whole test in one.
+
+use std::{cell::RefCell, future::Future};
+
+use rand::{rngs::StdRng, thread_rng, RngCore, SeedableRng};
+
+/// Environment variable holding the seed to run a simulation with
+pub const SEED_ENV_VAR: &str = "HOTSHOT_SIMULATION_SEED";
+
+thread_local! {
+    /// Random number generator of the simulation running on this thread, if any
+    static SIMULATION_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Call `f` with the random number generator of the simulation running on this thread, or with
+/// the thread's own generator outside of a simulation.
+///
+/// # Panics
+/// if called from within `f`
+pub fn with_rng<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+    SIMULATION_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => f(rng),
+        None => f(&mut thread_rng()),
+    })
+}
+
+/// A seeded, single-threaded runtime driven by a virtual clock
+#[derive(Clone, Copy, Debug)]
+pub struct Simulation {
+    /// Seed of every source of randomness in the simulation
+    seed: u64,
+}
+
+impl Simulation {
+    /// Create a simulation with the given seed
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Create a simulation with the seed in [`SEED_ENV_VAR`], or a random seed if it is not set
+    ///
+    /// # Panics
+    /// if [`SEED_ENV_VAR`] is set to something other than a `u64`
+    #[must_use]
+    pub fn from_env() -> Self {
+        let seed = match std::env::var(SEED_ENV_VAR) {
+            Ok(seed) => seed
+                .parse()
+                .unwrap_or_else(|_| panic!("{SEED_ENV_VAR} must be a u64, got {seed}")),
+            Err(_) => thread_rng().next_u64(),
+        };
+
+        Self::new(seed)
+    }
+
+    /// The seed of the simulation
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Run `future` to completion in the simulation
+    ///
+    /// # Panics
+    /// if the runtime cannot be built, or if `future` panics
+    #[cfg(feature = "simulation")]
+    pub fn run<F: Future>(&self, future: F) -> F::Output {
+        let mut builder = tokio::runtime::Builder::new_current_thread();
+        builder.enable_all().start_paused(true);
+        #[cfg(tokio_unstable)]
+        builder.rng_seed(tokio::runtime::RngSeed::from_bytes(
+            &self.seed.to_le_bytes(),
+        ));
+        let runtime = builder
+            .build()
+            .expect("Failed to build the simulation runtime");
+
+        tracing::info!("Running simulation with seed {}", self.seed);
+        let _guard = SimulationGuard::install(self.seed);
+        runtime.block_on(future)
+    }
+}
+
+/// Installs the generator of a simulation on the current thread for as long as it is alive
+struct SimulationGuard {
+    /// Seed of the simulation, reported if it fails
+    seed: u64,
+}
+
+impl SimulationGuard {
+    /// Install a generator seeded with `seed` on the current thread
+    fn install(seed: u64) -> Self {
+        SIMULATION_RNG.with(|rng| *rng.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+        Self { seed }
+    }
+}
+
+impl Drop for SimulationGuard {
+    fn drop(&mut self) {
+        SIMULATION_RNG.with(|rng| *rng.borrow_mut() = None);
+        if std::thread::panicking() {
+            tracing::error!(
+                "Simulation failed, replay it with {SEED_ENV_VAR}={}",
+                self.seed
+            );
+        }
+    }
+}
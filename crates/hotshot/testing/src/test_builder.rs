@@ -21,6 +21,7 @@ use hotshot_example_types::{
 use hotshot_types::{
     consensus::ConsensusMetricsValue,
     epoch_membership::EpochMembershipCoordinator,
+    signer::Signer,
     traits::node_implementation::{NodeType, Versions},
     HotShotConfig, PeerConfig, ValidatorConfig,
 };
@@ -35,6 +36,8 @@ use super::{
 };
 use crate::{
     helpers::{key_pair_for_id, TestNodeKeyMap},
+    metrics::MetricsAssertions,
+    partition::{transmit_planner, NetworkPartition, PartitionedNetwork},
     spinning_task::SpinningTaskDescription,
     test_launcher::{Network, ResourceGenerators, TestLauncher},
     test_task::TestTaskStateSeed,
@@ -89,6 +92,13 @@ pub fn default_hotshot_config<TYPES: NodeType>(
         stop_voting_time: 0,
         epoch_height,
         epoch_start_block,
+        da_payload_hint_threshold: None,
+        da_payload_hint_urls: vec![],
+        compression: None,
+        bandwidth: Default::default(),
+        local_builder: None,
+        adaptive_view_timeout: None,
+        transaction_dedup: None,
     }
 }
 
@@ -152,6 +162,8 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub solver: FakeSolverApiDescription,
     /// nodes with byzantine behaviour
     pub behaviour: Rc<dyn Fn(u64) -> Behaviour<TYPES, I, V>>,
+    /// partitions of the network between standard nodes
+    pub network_partitions: Vec<NetworkPartition>,
     /// Delay config if any to add delays to asynchronous calls
     pub async_delay_config: DelayConfig,
     /// view in which to propose an upgrade
@@ -160,6 +172,8 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub start_solver: bool,
     /// boxed closure used to validate the resulting transactions
     pub validate_transactions: TransactionValidator,
+    /// assertions over the consensus metrics of each node, checked once the test is over
+    pub metrics_assertions: MetricsAssertions,
 }
 
 pub fn nonempty_block_threshold(threshold: (u64, u64)) -> TransactionValidator {
@@ -239,6 +253,7 @@ pub async fn create_test_handle<
     config: HotShotConfig<TYPES>,
     storage: I::Storage,
     marketplace_config: MarketplaceConfig<TYPES, I>,
+    metrics: ConsensusMetricsValue,
 ) -> SystemContextHandle<TYPES, I, V> {
     let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(
         TestInstanceState::new(metadata.async_delay_config),
@@ -256,26 +271,33 @@ pub async fn create_test_handle<
         ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, U256::from(1), is_da);
 
     // Get key pair for certificate aggregation
-    let private_key = validator_config.private_key.clone();
+    let signer = Signer::Local(validator_config.private_key.clone());
     let public_key = validator_config.public_key.clone();
     let state_private_key = validator_config.state_private_key.clone();
     let membership_coordinator = EpochMembershipCoordinator::new(memberships, config.epoch_height);
 
-    let behaviour = (metadata.behaviour)(node_id);
+    let behaviour = match (metadata.behaviour)(node_id) {
+        Behaviour::Standard if !metadata.network_partitions.is_empty() => {
+            Behaviour::Byzantine(Box::new(PartitionedNetwork {
+                planner: transmit_planner::<TYPES>(node_id, metadata.network_partitions.clone()),
+            }))
+        },
+        behaviour => behaviour,
+    };
     match behaviour {
         Behaviour::ByzantineTwins(state) => {
             let state = Box::leak(state);
             let (left_handle, _right_handle) = state
                 .spawn_twin_handles(
                     public_key,
-                    private_key,
+                    signer,
                     state_private_key,
                     node_id,
                     config,
                     membership_coordinator,
                     network,
                     initializer,
-                    ConsensusMetricsValue::default(),
+                    metrics,
                     storage,
                     marketplace_config,
                 )
@@ -288,14 +310,14 @@ pub async fn create_test_handle<
             state
                 .spawn_handle(
                     public_key,
-                    private_key,
+                    signer,
                     state_private_key,
                     node_id,
                     config,
                     membership_coordinator,
                     network,
                     initializer,
-                    ConsensusMetricsValue::default(),
+                    metrics,
                     storage,
                     marketplace_config,
                 )
@@ -304,14 +326,14 @@ pub async fn create_test_handle<
         Behaviour::Standard => {
             let hotshot = SystemContext::<TYPES, I, V>::new(
                 public_key,
-                private_key,
+                signer,
                 state_private_key,
                 node_id,
                 config,
                 membership_coordinator,
                 network,
                 initializer,
-                ConsensusMetricsValue::default(),
+                metrics,
                 storage,
                 marketplace_config,
             )
@@ -516,10 +538,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Default
                 error_pct: 0.1,
             },
             behaviour: Rc::new(|_| Behaviour::Standard),
+            network_partitions: vec![],
             async_delay_config: DelayConfig::default(),
             upgrade_view: None,
             start_solver: true,
             validate_transactions: Arc::new(|_| Ok(())),
+            metrics_assertions: MetricsAssertions::default(),
         }
     }
 }
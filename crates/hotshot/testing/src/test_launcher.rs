@@ -61,6 +61,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> TestLau
             nodes: Vec::new(),
             solver_server: None,
             late_start: HashMap::new(),
+            node_metrics: HashMap::new(),
             next_node_id: 0,
             _pd: PhantomData,
         }
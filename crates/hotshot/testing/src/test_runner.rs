@@ -34,6 +34,7 @@ use hotshot_types::{
     data::Leaf2,
     drb::INITIAL_DRB_RESULT,
     epoch_membership::EpochMembershipCoordinator,
+    signer::Signer,
     simple_certificate::QuorumCertificate2,
     traits::{
         election::Membership,
@@ -50,9 +51,13 @@ use tracing::info;
 use super::{
     completion_task::CompletionTask, consistency_task::ConsistencyTask, txn_task::TxnTask,
 };
+#[cfg(feature = "simulation")]
+use crate::simulation::Simulation;
 use crate::{
     block_builder::{BuilderTask, TestBuilderImplementation},
     completion_task::CompletionTaskDescription,
+    metrics::RecordedMetrics,
+    partition::possible_view_failures,
     spinning_task::{ChangeNode, NodeAction, SpinningTask},
     test_builder::create_test_handle,
     test_launcher::{Network, TestLauncher},
@@ -83,6 +88,15 @@ where
         AuctionResultsProvider = TestAuctionResultsProvider<TYPES>,
     >,
 {
+    /// execute test in `simulation`, with a virtual clock and seeded randomness
+    ///
+    /// # Panics
+    /// if the test fails
+    #[cfg(feature = "simulation")]
+    pub fn run_simulation<B: TestBuilderImplementation<TYPES>>(self, simulation: &Simulation) {
+        simulation.run(self.run_test::<B>());
+    }
+
     /// execute test
     ///
     /// # Panics
@@ -137,6 +151,7 @@ where
             nodes,
             solver_server,
             late_start,
+            node_metrics,
             next_node_id: _,
             _pd: _,
         } = self;
@@ -208,9 +223,15 @@ where
             test_receiver.clone(),
         );
 
+        let mut safety_properties = launcher.metadata.overall_safety_properties.clone();
+        safety_properties
+            .possible_view_failures
+            .extend(possible_view_failures(
+                &launcher.metadata.network_partitions,
+            ));
         let consistency_task_state = ConsistencyTask {
             consensus_leaves: BTreeMap::new(),
-            safety_properties: launcher.metadata.overall_safety_properties.clone(),
+            safety_properties,
             test_sender: test_sender.clone(),
             errors: vec![],
             ensure_upgrade: launcher.metadata.upgrade_view.is_some(),
@@ -310,6 +331,14 @@ where
 
         completion_handle.abort();
 
+        let mut node_metrics: Vec<_> = node_metrics.into_iter().collect();
+        node_metrics.sort_by_key(|(node_id, _)| *node_id);
+        for (node_id, metrics) in node_metrics {
+            for failure in meta.metrics_assertions.check(node_id, &metrics) {
+                error_list.push(Box::new(failure));
+            }
+        }
+
         assert!(
             error_list.is_empty(),
             "{}",
@@ -550,6 +579,7 @@ where
         for (node_id, network, memberships, config, storage, marketplace_config) in
             uninitialized_nodes
         {
+            let metrics = RecordedMetrics::default();
             let handle = create_test_handle(
                 self.launcher.metadata.clone(),
                 node_id,
@@ -558,8 +588,10 @@ where
                 config.clone(),
                 storage,
                 marketplace_config,
+                ConsensusMetricsValue::new(&metrics),
             )
             .await;
+            self.node_metrics.insert(node_id, metrics);
 
             match node_id.cmp(&(config.da_staked_committee_size as u64 - 1)) {
                 std::cmp::Ordering::Less => {
@@ -601,14 +633,14 @@ where
         marketplace_config: MarketplaceConfig<TYPES, I>,
     ) -> Arc<SystemContext<TYPES, I, V>> {
         // Get key pair for certificate aggregation
-        let private_key = validator_config.private_key.clone();
+        let signer = Signer::Local(validator_config.private_key.clone());
         let public_key = validator_config.public_key.clone();
         let state_private_key = validator_config.state_private_key.clone();
         let epoch_height = config.epoch_height;
 
         SystemContext::new(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             node_id,
             config,
@@ -642,14 +674,14 @@ where
         external_channel: (Sender<Event<TYPES>>, Receiver<Event<TYPES>>),
     ) -> Arc<SystemContext<TYPES, I, V>> {
         // Get key pair for certificate aggregation
-        let private_key = validator_config.private_key.clone();
+        let signer = Signer::Local(validator_config.private_key.clone());
         let public_key = validator_config.public_key.clone();
         let state_private_key = validator_config.state_private_key.clone();
         let epoch_height = config.epoch_height;
 
         SystemContext::new_from_channels(
             public_key,
-            private_key,
+            signer,
             state_private_key,
             node_id,
             config,
@@ -731,6 +763,8 @@ pub struct TestRunner<
     pub(crate) solver_server: Option<(Url, JoinHandle<()>)>,
     /// nodes with a late start
     pub(crate) late_start: HashMap<u64, LateStartNode<TYPES, I, V>>,
+    /// metrics recorded by each node started with the test
+    pub(crate) node_metrics: HashMap<u64, RecordedMetrics>,
     /// the next node unique identifier
     pub(crate) next_node_id: u64,
     /// Phantom for N
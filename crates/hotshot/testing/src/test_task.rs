@@ -14,8 +14,10 @@ use hotshot::{
     traits::TestableNodeImplementation,
     types::{Event, Message},
 };
+use hotshot_task::broadcast_time::Timestamped;
 use hotshot_task_impls::{events::HotShotEvent, network::NetworkMessageTaskState};
 use hotshot_types::{
+    epoch_membership::EpochMembershipCoordinator,
     message::UpgradeLock,
     traits::{
         network::ConnectedNetwork,
@@ -124,6 +126,8 @@ pub enum TestEvent {
     Shutdown,
 }
 
+impl Timestamped for TestEvent {}
+
 impl<S: TestTaskState + Send + 'static> TestTask<S> {
     /// Create a new task
     pub fn new(
@@ -181,6 +185,7 @@ pub async fn add_network_message_test_task<
     upgrade_lock: UpgradeLock<TYPES, V>,
     channel: Arc<NET>,
     public_key: TYPES::SignatureKey,
+    membership_coordinator: EpochMembershipCoordinator<TYPES>,
 ) -> JoinHandle<()> {
     let net = Arc::clone(&channel);
     let network_state: NetworkMessageTaskState<_, _> = NetworkMessageTaskState {
@@ -189,6 +194,8 @@ pub async fn add_network_message_test_task<
         public_key,
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
         upgrade_lock: upgrade_lock.clone(),
+        audit: Arc::default(),
+        membership_coordinator,
     };
 
     let network = Arc::clone(&net);
@@ -10,10 +10,9 @@ use async_broadcast::Receiver;
 use async_lock::RwLock;
 use hotshot::traits::TestableNodeImplementation;
 use hotshot_types::traits::node_implementation::{NodeType, Versions};
-use rand::thread_rng;
 use tokio::{spawn, task::JoinHandle, time::sleep};
 
-use crate::{test_runner::Node, test_task::TestEvent};
+use crate::{simulation::with_rng, test_runner::Node, test_task::TestEvent};
 
 // the obvious idea here is to pass in a "stream" that completes every `n` seconds
 // the stream construction can definitely be fancier but that's the baseline idea
@@ -59,7 +58,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> TxnTask
                     // If they don't match, this is probably fine since
                     // it should be caught by an assertion (and the txn will be rejected anyway)
                     let leaf = node.handle.decided_leaf().await;
-                    let txn = I::leaf_create_random_transaction(&leaf, &mut thread_rng(), 0);
+                    let txn = with_rng(|rng| I::leaf_create_random_transaction(&leaf, rng, 0));
                     node.handle
                         .submit_transaction(txn.clone())
                         .await
@@ -12,6 +12,7 @@ use std::{
     task::{Context, Poll},
 };
 
+use alloy::primitives::U256;
 use committable::Committable;
 use futures::{FutureExt, Stream};
 use hotshot::types::{BLSPubKey, SignatureKey, SystemContextHandle};
@@ -25,32 +26,55 @@ use hotshot_types::{
         DaProposal2, EpochNumber, Leaf2, QuorumProposal2, QuorumProposalWrapper, VidDisperse,
         VidDisperseShare, ViewChangeEvidence2, ViewNumber,
     },
+    drb::INITIAL_DRB_RESULT,
     epoch_membership::{EpochMembership, EpochMembershipCoordinator},
     message::{Proposal, UpgradeLock},
     simple_certificate::{
-        DaCertificate2, QuorumCertificate2, TimeoutCertificate2, UpgradeCertificate,
-        ViewSyncFinalizeCertificate2,
+        DaCertificate2, NextEpochQuorumCertificate2, QuorumCertificate2, TimeoutCertificate2,
+        UpgradeCertificate, ViewSyncFinalizeCertificate2,
     },
     simple_vote::{
-        DaData2, DaVote2, QuorumData2, QuorumVote2, TimeoutData2, TimeoutVote2,
-        UpgradeProposalData, UpgradeVote, ViewSyncFinalizeData2, ViewSyncFinalizeVote2,
+        DaData2, DaVote2, NextEpochQuorumData2, NextEpochQuorumVote2, QuorumData2, QuorumVote2,
+        TimeoutData2, TimeoutVote2, UpgradeProposalData, UpgradeVote, ViewSyncFinalizeData2,
+        ViewSyncFinalizeVote2,
     },
     traits::{
         consensus_api::ConsensusApi,
         node_implementation::{ConsensusTime, NodeType, Versions},
         BlockPayload,
     },
-    utils::{genesis_epoch_from_version, EpochTransitionIndicator},
+    utils::{
+        epoch_from_block_number, genesis_epoch_from_version, is_epoch_root, is_epoch_transition,
+        EpochTransitionIndicator,
+    },
+    ValidatorConfig,
 };
 use rand::{thread_rng, Rng};
 use sha2::{Digest, Sha256};
 
 use crate::helpers::{
-    build_cert, build_da_certificate, build_vid_proposal, da_payload_commitment, TestNodeKeyMap,
+    build_cert, build_da_certificate, build_state_cert, build_vid_proposal, da_payload_commitment,
+    TestNodeKeyMap,
 };
 
+/// What happens in a generated view, see [`TestViewGenerator::next_scripted`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScriptedView {
+    /// The view succeeds: its block is certified by the DA committee, and its proposal is extended
+    /// by the next view
+    #[default]
+    Success,
+    /// The view fails: its proposal is never certified, and the next view extends the last view
+    /// which succeeded, with a timeout certificate for this one
+    Failure,
+    /// The view succeeds, but the DA committee never certifies its block
+    NoDaCertificate,
+    /// The view succeeds with an empty block, whatever transactions were added for it
+    EmptyBlock,
+}
+
 #[derive(Clone)]
-pub struct TestView {
+pub struct TestView<V: Versions = TestVersions> {
     pub da_proposal: Proposal<TestTypes, DaProposal2<TestTypes>>,
     pub quorum_proposal: Proposal<TestTypes, QuorumProposalWrapper<TestTypes>>,
     pub leaf: Leaf2<TestTypes>,
@@ -64,16 +88,20 @@ pub struct TestView {
         <TestTypes as NodeType>::SignatureKey,
     ),
     pub leader_public_key: <TestTypes as NodeType>::SignatureKey,
-    pub da_certificate: DaCertificate2<TestTypes>,
+    /// The DA certificate of this view, if the DA committee certified its block
+    pub da_certificate: Option<DaCertificate2<TestTypes>>,
     pub transactions: Vec<TestTransaction>,
+    /// What happens in this view
+    pub kind: ScriptedView,
     upgrade_data: Option<UpgradeProposalData<TestTypes>>,
     formed_upgrade_certificate: Option<UpgradeCertificate<TestTypes>>,
     view_sync_finalize_data: Option<ViewSyncFinalizeData2<TestTypes>>,
     timeout_cert_data: Option<TimeoutData2<TestTypes>>,
-    upgrade_lock: UpgradeLock<TestTypes, TestVersions>,
+    upgrade_lock: UpgradeLock<TestTypes, V>,
+    epoch_height: u64,
 }
 
-impl TestView {
+impl<V: Versions> TestView<V> {
     async fn find_leader_key_pair(
         membership: &EpochMembership<TestTypes>,
         node_key_map: &Arc<TestNodeKeyMap>,
@@ -94,9 +122,10 @@ impl TestView {
         (sk.clone(), leader)
     }
 
-    pub async fn genesis<V: Versions>(
+    pub async fn genesis(
         membership: &EpochMembershipCoordinator<TestTypes>,
         node_key_map: Arc<TestNodeKeyMap>,
+        epoch_height: u64,
     ) -> Self {
         let genesis_view = ViewNumber::new(1);
         let genesis_epoch = genesis_epoch_from_version::<V, TestTypes>();
@@ -129,7 +158,7 @@ impl TestView {
 
         let genesis_version = upgrade_lock.version_infallible(genesis_view).await;
 
-        let payload_commitment = da_payload_commitment::<TestTypes, TestVersions>(
+        let payload_commitment = da_payload_commitment::<TestTypes, V>(
             &epoch_membership,
             transactions.clone(),
             &metadata,
@@ -137,7 +166,7 @@ impl TestView {
         )
         .await;
 
-        let (vid_disperse, vid_proposal) = build_vid_proposal::<TestTypes, TestVersions>(
+        let (vid_disperse, vid_proposal) = build_vid_proposal::<TestTypes, V>(
             &epoch_membership,
             genesis_view,
             genesis_epoch,
@@ -177,7 +206,7 @@ impl TestView {
                 block_header: block_header.clone(),
                 view_number: genesis_view,
                 epoch: genesis_epoch,
-                justify_qc: QuorumCertificate2::genesis::<TestVersions>(
+                justify_qc: QuorumCertificate2::genesis::<V>(
                     &TestValidatedState::default(),
                     &TestInstanceState::default(),
                 )
@@ -233,15 +262,17 @@ impl TestView {
             node_key_map,
             vid_disperse,
             vid_proposal: (vid_proposal, public_key),
-            da_certificate,
+            da_certificate: Some(da_certificate),
             transactions,
             leader_public_key,
+            kind: ScriptedView::Success,
             upgrade_data: None,
             formed_upgrade_certificate: None,
             view_sync_finalize_data: None,
             timeout_cert_data: None,
             da_proposal,
             upgrade_lock,
+            epoch_height,
         }
     }
 
@@ -250,7 +281,13 @@ impl TestView {
     /// this method can be used to start from an ancestor (whose view is at least one view older
     /// than the current view) and construct valid views without the data structures in the task
     /// failing by expecting views that they has never seen.
-    pub async fn next_view_from_ancestor(&self, ancestor: TestView) -> Self {
+    ///
+    /// If the generator was created with a non-zero epoch height, the epoch of the new view is
+    /// derived from its block number, so that the views cross epoch boundaries. The view is then
+    /// led and certified by the stake table of its epoch, and its proposal carries the next epoch
+    /// QC and the light client state certificate which the protocol requires during epoch
+    /// transitions and after an epoch root.
+    pub async fn next_view_from_ancestor(&self, ancestor: TestView<V>) -> Self {
         let old = ancestor;
         let old_view = old.view_number;
         let old_epoch = old.epoch_number;
@@ -259,6 +296,13 @@ impl TestView {
         // test view here.
         let next_view = max(old_view, self.view_number) + 1;
 
+        let epoch_number = if self.epoch_height == 0 {
+            self.epoch_number
+        } else {
+            self.epoch_number
+                .map(|_| EpochNumber::new(epoch_from_block_number(*next_view, self.epoch_height)))
+        };
+
         let transactions = &self.transactions;
 
         let quorum_data = QuorumData2 {
@@ -267,29 +311,24 @@ impl TestView {
             block_number: Some(old.leaf.height()),
         };
 
+        let old_membership = self
+            .membership
+            .membership_for_epoch(old_epoch)
+            .await
+            .unwrap();
+        let membership = self
+            .membership
+            .membership_for_epoch(epoch_number)
+            .await
+            .unwrap();
+
         //let (old_private_key, old_public_key) = key_pair_for_id::<TestTypes>(*old_view);
-        let (old_private_key, old_public_key) = Self::find_leader_key_pair(
-            &self
-                .membership
-                .membership_for_epoch(old_epoch)
-                .await
-                .unwrap(),
-            &self.node_key_map,
-            old_view,
-        )
-        .await;
+        let (old_private_key, old_public_key) =
+            Self::find_leader_key_pair(&old_membership, &self.node_key_map, old_view).await;
 
         //let (private_key, public_key) = key_pair_for_id::<TestTypes>(*next_view);
-        let (private_key, public_key) = Self::find_leader_key_pair(
-            &self
-                .membership
-                .membership_for_epoch(self.epoch_number)
-                .await
-                .unwrap(),
-            &self.node_key_map,
-            next_view,
-        )
-        .await;
+        let (private_key, public_key) =
+            Self::find_leader_key_pair(&membership, &self.node_key_map, next_view).await;
 
         let leader_public_key = public_key;
 
@@ -307,12 +346,7 @@ impl TestView {
         );
 
         let version = self.upgrade_lock.version_infallible(next_view).await;
-        let membership = self
-            .membership
-            .membership_for_epoch(self.epoch_number)
-            .await
-            .unwrap();
-        let payload_commitment = da_payload_commitment::<TestTypes, TestVersions>(
+        let payload_commitment = da_payload_commitment::<TestTypes, V>(
             &membership,
             transactions.clone(),
             &metadata,
@@ -320,10 +354,10 @@ impl TestView {
         )
         .await;
 
-        let (vid_disperse, vid_proposal) = build_vid_proposal::<TestTypes, TestVersions>(
+        let (vid_disperse, vid_proposal) = build_vid_proposal::<TestTypes, V>(
             &membership,
             next_view,
-            self.epoch_number,
+            epoch_number,
             &block_payload,
             &metadata,
             &private_key,
@@ -331,10 +365,10 @@ impl TestView {
         )
         .await;
 
-        let da_certificate = build_da_certificate::<TestTypes, TestVersions>(
+        let da_certificate = build_da_certificate::<TestTypes, V>(
             &membership,
             next_view,
-            self.epoch_number,
+            epoch_number,
             transactions.clone(),
             &metadata,
             &public_key,
@@ -346,13 +380,13 @@ impl TestView {
 
         let quorum_certificate = build_cert::<
             TestTypes,
-            TestVersions,
+            V,
             QuorumData2<TestTypes>,
             QuorumVote2<TestTypes>,
             QuorumCertificate2<TestTypes>,
         >(
-            quorum_data,
-            &membership,
+            quorum_data.clone(),
+            &old_membership,
             old_view,
             &old_public_key,
             &old_private_key,
@@ -360,10 +394,53 @@ impl TestView {
         )
         .await;
 
+        // Blocks in an epoch transition must also be certified by the next epoch's stake table
+        let next_epoch_justify_qc = if is_epoch_transition(old.leaf.height(), self.epoch_height) {
+            let next_epoch_membership = self
+                .membership
+                .membership_for_epoch(old_epoch.map(|epoch| epoch + 1))
+                .await
+                .unwrap();
+            let cert = build_cert::<
+                TestTypes,
+                V,
+                NextEpochQuorumData2<TestTypes>,
+                NextEpochQuorumVote2<TestTypes>,
+                NextEpochQuorumCertificate2<TestTypes>,
+            >(
+                quorum_data.into(),
+                &next_epoch_membership,
+                old_view,
+                &old_public_key,
+                &old_private_key,
+                &self.upgrade_lock,
+            )
+            .await;
+
+            Some(cert)
+        } else {
+            None
+        };
+
+        // Proposals extending an epoch root must carry the light client state update certificate
+        let state_cert = if is_epoch_root(old.leaf.height(), self.epoch_height) {
+            Some(build_state_cert(&quorum_certificate, &old_membership).await)
+        } else {
+            None
+        };
+
+        let epoch_transition_indicator = if is_epoch_transition(*next_view, self.epoch_height) {
+            EpochTransitionIndicator::InTransition
+        } else {
+            EpochTransitionIndicator::NotInTransition
+        };
+        let next_drb_result =
+            is_epoch_transition(*next_view, self.epoch_height).then_some(INITIAL_DRB_RESULT);
+
         let upgrade_certificate = if let Some(ref data) = self.upgrade_data {
             let cert = build_cert::<
                 TestTypes,
-                TestVersions,
+                V,
                 UpgradeProposalData<TestTypes>,
                 UpgradeVote<TestTypes>,
                 UpgradeCertificate<TestTypes>,
@@ -385,7 +462,7 @@ impl TestView {
         let view_sync_certificate = if let Some(ref data) = self.view_sync_finalize_data {
             let cert = build_cert::<
                 TestTypes,
-                TestVersions,
+                V,
                 ViewSyncFinalizeData2<TestTypes>,
                 ViewSyncFinalizeVote2<TestTypes>,
                 ViewSyncFinalizeCertificate2<TestTypes>,
@@ -407,7 +484,7 @@ impl TestView {
         let timeout_certificate = if let Some(ref data) = self.timeout_cert_data {
             let cert = build_cert::<
                 TestTypes,
-                TestVersions,
+                V,
                 TimeoutData2<TestTypes>,
                 TimeoutVote2<TestTypes>,
                 TimeoutCertificate2<TestTypes>,
@@ -447,13 +524,13 @@ impl TestView {
             proposal: QuorumProposal2::<TestTypes> {
                 block_header: block_header.clone(),
                 view_number: next_view,
-                epoch: old_epoch,
+                epoch: epoch_number,
                 justify_qc: quorum_certificate.clone(),
-                next_epoch_justify_qc: None,
+                next_epoch_justify_qc,
                 upgrade_certificate: upgrade_certificate.clone(),
                 view_change_evidence,
-                next_drb_result: None,
-                state_cert: None,
+                next_drb_result,
+                state_cert,
             },
         };
 
@@ -481,8 +558,8 @@ impl TestView {
             encoded_transactions: encoded_transactions.clone(),
             metadata,
             view_number: next_view,
-            epoch: old_epoch,
-            epoch_transition_indicator: EpochTransitionIndicator::NotInTransition,
+            epoch: epoch_number,
+            epoch_transition_indicator,
         };
 
         let da_proposal = Proposal {
@@ -497,16 +574,17 @@ impl TestView {
             quorum_proposal,
             leaf,
             view_number: next_view,
-            epoch_number: self.epoch_number,
+            epoch_number,
             membership: self.membership.clone(),
             node_key_map: self.node_key_map.clone(),
             vid_disperse,
             vid_proposal: (vid_proposal, public_key),
-            da_certificate,
+            da_certificate: Some(da_certificate),
             leader_public_key,
             // Transactions and upgrade data need to be manually injected each view,
             // so we reset for the next view.
             transactions: Vec::new(),
+            kind: ScriptedView::Success,
             upgrade_data: None,
             // We preserve the upgrade_certificate once formed,
             // and reattach it on every future view until cleared.
@@ -515,6 +593,7 @@ impl TestView {
             timeout_cert_data: None,
             da_proposal,
             upgrade_lock,
+            epoch_height: self.epoch_height,
         }
     }
 
@@ -522,9 +601,14 @@ impl TestView {
         self.next_view_from_ancestor(self.clone()).await
     }
 
+    /// Whether the proposal of this view is certified by a QC.
+    pub fn is_certified(&self) -> bool {
+        self.kind != ScriptedView::Failure
+    }
+
     pub async fn create_quorum_vote(
         &self,
-        handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+        handle: &SystemContextHandle<TestTypes, MemoryImpl, V>,
     ) -> QuorumVote2<TestTypes> {
         QuorumVote2::<TestTypes>::create_signed_vote(
             QuorumData2 {
@@ -534,7 +618,7 @@ impl TestView {
             },
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            handle.signer(),
             &handle.hotshot.upgrade_lock,
         )
         .await
@@ -544,13 +628,13 @@ impl TestView {
     pub async fn create_upgrade_vote(
         &self,
         data: UpgradeProposalData<TestTypes>,
-        handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+        handle: &SystemContextHandle<TestTypes, MemoryImpl, V>,
     ) -> UpgradeVote<TestTypes> {
         UpgradeVote::<TestTypes>::create_signed_vote(
             data,
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            handle.signer(),
             &handle.hotshot.upgrade_lock,
         )
         .await
@@ -560,13 +644,13 @@ impl TestView {
     pub async fn create_da_vote(
         &self,
         data: DaData2<TestTypes>,
-        handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
+        handle: &SystemContextHandle<TestTypes, MemoryImpl, V>,
     ) -> DaVote2<TestTypes> {
         DaVote2::create_signed_vote(
             data,
             self.view_number,
             &handle.public_key(),
-            handle.private_key(),
+            handle.signer(),
             &handle.hotshot.upgrade_lock,
         )
         .await
@@ -575,9 +659,13 @@ impl TestView {
 }
 
 pub struct TestViewGenerator<V: Versions> {
-    pub current_view: Option<TestView>,
+    pub current_view: Option<TestView<V>>,
     pub membership: EpochMembershipCoordinator<TestTypes>,
     pub node_key_map: Arc<TestNodeKeyMap>,
+    /// Number of blocks per epoch, or 0 if the generated views never change epoch
+    pub epoch_height: u64,
+    /// The last view which succeeded, while the current view is a failure
+    certified_ancestor: Option<TestView<V>>,
     pub _pd: PhantomData<fn(V)>,
 }
 
@@ -590,10 +678,61 @@ impl<V: Versions> TestViewGenerator<V> {
             current_view: None,
             membership,
             node_key_map,
+            epoch_height: 0,
+            certified_ancestor: None,
             _pd: PhantomData,
         }
     }
 
+    /// Generate views which move to the next epoch every `epoch_height` blocks.
+    ///
+    /// The membership must have its first epoch set, and `V` must have epochs enabled.
+    pub fn generate_with_epochs(
+        membership: EpochMembershipCoordinator<TestTypes>,
+        node_key_map: Arc<TestNodeKeyMap>,
+        epoch_height: u64,
+    ) -> Self {
+        TestViewGenerator {
+            epoch_height,
+            ..Self::generate(membership, node_key_map)
+        }
+    }
+
+    /// Replace the stake table from `epoch` onwards with the nodes `node_ids`, of which
+    /// `da_node_ids` also form the DA committee. Every node has a stake of 1.
+    ///
+    /// The nodes must be in `node_key_map` to lead views of the new epochs.
+    pub async fn change_stake_table(
+        &self,
+        epoch: EpochNumber,
+        node_ids: &[u64],
+        da_node_ids: &[u64],
+    ) {
+        let peer_config = |node_id: &u64, is_da: bool| {
+            ValidatorConfig::<TestTypes>::generated_from_seed_indexed(
+                [0u8; 32],
+                *node_id,
+                U256::from(1),
+                is_da,
+            )
+            .public_config()
+        };
+        let committee_members = node_ids
+            .iter()
+            .map(|node_id| peer_config(node_id, da_node_ids.contains(node_id)))
+            .collect();
+        let da_members = da_node_ids
+            .iter()
+            .map(|node_id| peer_config(node_id, true))
+            .collect();
+
+        self.membership
+            .membership()
+            .write()
+            .await
+            .set_committee_from_epoch(epoch, committee_members, da_members);
+    }
+
     pub fn add_upgrade(&mut self, upgrade_proposal_data: UpgradeProposalData<TestTypes>) {
         if let Some(ref view) = self.current_view {
             self.current_view = Some(TestView {
@@ -654,32 +793,104 @@ impl<V: Versions> TestViewGenerator<V> {
         }
     }
 
-    pub async fn next_from_ancestor_view(&mut self, ancestor: TestView) {
+    pub async fn next_from_ancestor_view(&mut self, ancestor: TestView<V>) {
         if let Some(ref view) = self.current_view {
-            self.current_view = Some(view.next_view_from_ancestor(ancestor).await)
+            self.current_view = Some(view.next_view_from_ancestor(ancestor).await);
+            self.certified_ancestor = None;
         } else {
             tracing::error!("Cannot attach ancestor to genesis view.");
         }
     }
+
+    /// Generate the next view, in which `kind` happens.
+    ///
+    /// After a [`Failure`](ScriptedView::Failure), the next view generated, scripted or not,
+    /// extends the last view which succeeded and carries a timeout certificate for the failed
+    /// view, so consecutive failures are justified by the same QC.
+    ///
+    /// # Panics
+    /// If the genesis view is scripted to fail, since there is no earlier view to extend.
+    pub async fn next_scripted(&mut self, kind: ScriptedView) -> TestView<V> {
+        let mut view = match self.current_view.clone() {
+            None => {
+                assert_ne!(kind, ScriptedView::Failure, "the genesis view cannot fail");
+                TestView::genesis(
+                    &self.membership,
+                    Arc::clone(&self.node_key_map),
+                    self.epoch_height,
+                )
+                .await
+            },
+            Some(mut current) => {
+                if kind == ScriptedView::EmptyBlock {
+                    current.transactions = Vec::new();
+                }
+                let ancestor = self
+                    .certified_ancestor
+                    .clone()
+                    .unwrap_or_else(|| current.clone());
+                current.next_view_from_ancestor(ancestor).await
+            },
+        };
+        view.kind = kind;
+        if kind == ScriptedView::NoDaCertificate {
+            view.da_certificate = None;
+        }
+
+        if kind == ScriptedView::Failure {
+            if self.certified_ancestor.is_none() {
+                self.certified_ancestor = self.current_view.clone();
+            }
+            self.current_view = Some(TestView {
+                timeout_cert_data: Some(TimeoutData2 {
+                    view: view.view_number,
+                    epoch: view.epoch_number,
+                }),
+                ..view.clone()
+            });
+        } else {
+            self.certified_ancestor = None;
+            self.current_view = Some(view.clone());
+        }
+        view
+    }
+
+    /// Generate a view for each entry of `script`, in order.
+    pub async fn generate_script(&mut self, script: &[ScriptedView]) -> Vec<TestView<V>> {
+        let mut views = Vec::with_capacity(script.len());
+        for kind in script {
+            views.push(self.next_scripted(*kind).await);
+        }
+        views
+    }
 }
 
 impl<V: Versions> Stream for TestViewGenerator<V> {
-    type Item = TestView;
+    type Item = TestView<V>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let epoch_membership = self.membership.clone();
         let nkm = Arc::clone(&self.node_key_map);
+        let epoch_height = self.epoch_height;
         let curr_view = &self.current_view.clone();
+        let ancestor = self.certified_ancestor.clone();
 
         let mut fut = if let Some(ref view) = curr_view {
-            async move { TestView::next_view(view).await }.boxed()
+            async move {
+                match ancestor {
+                    Some(ancestor) => view.next_view_from_ancestor(ancestor).await,
+                    None => TestView::next_view(view).await,
+                }
+            }
+            .boxed()
         } else {
-            async move { TestView::genesis::<V>(&epoch_membership, nkm).await }.boxed()
+            async move { TestView::genesis(&epoch_membership, nkm, epoch_height).await }.boxed()
         };
 
         match fut.as_mut().poll(cx) {
             Poll::Ready(test_view) => {
                 self.current_view = Some(test_view.clone());
+                self.certified_ancestor = None;
                 Poll::Ready(Some(test_view))
             },
             Poll::Pending => Poll::Pending,
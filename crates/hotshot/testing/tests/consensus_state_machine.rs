@@ -0,0 +1,287 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Property based tests for the internal consistency of the `Consensus` state.
+//!
+//! Random sequences of the updates the consensus tasks make are applied to a `Consensus`, and
+//! its invariants are checked after every step.
+
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
+
+use committable::Committable;
+use futures::executor::block_on;
+use hotshot_example_types::{
+    node_types::{TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestStateDelta, TestValidatedState},
+};
+use hotshot_types::{
+    consensus::{CommitmentMap, Consensus, ConsensusMetricsValue},
+    data::{Leaf2, QuorumProposal2, QuorumProposalWrapper, VidCommitment, ViewNumber},
+    simple_certificate::QuorumCertificate2,
+    simple_vote::QuorumData2,
+    utils::{View, ViewInner},
+    vote::HasViewNumber,
+};
+use proptest::{prelude::*, sample::Index};
+
+/// An update the consensus tasks make to the shared consensus state
+#[derive(Clone, Debug)]
+enum Step {
+    /// Move the given number of views ahead
+    AdvanceView(u64),
+    /// Store a leaf for the current view, extending the chosen undecided leaf
+    AddLeaf { parent: Index, with_delta: bool },
+    /// Record the payload commitment of a DA certificate for the current view
+    AddDaView,
+    /// Form a QC for the chosen undecided leaf and update the high QC with it
+    UpdateHighQc(Index),
+    /// Lock the chosen undecided leaf newer than the locked view
+    Lock(Index),
+    /// Decide the chosen undecided leaf which is not newer than the locked view
+    Decide(Index),
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        (1..4u64).prop_map(Step::AdvanceView),
+        (any::<Index>(), any::<bool>())
+            .prop_map(|(parent, with_delta)| Step::AddLeaf { parent, with_delta }),
+        Just(Step::AddDaView),
+        any::<Index>().prop_map(Step::UpdateHighQc),
+        any::<Index>().prop_map(Step::Lock),
+        any::<Index>().prop_map(Step::Decide),
+    ]
+}
+
+/// A QC for `leaf`, without signatures
+fn qc_for(leaf: &Leaf2<TestTypes>) -> QuorumCertificate2<TestTypes> {
+    let data = QuorumData2 {
+        leaf_commit: leaf.commit(),
+        epoch: None,
+        block_number: Some(leaf.height()),
+    };
+    let vote_commitment = data.commit();
+    QuorumCertificate2::new(data, vote_commitment, leaf.view_number(), None, PhantomData)
+}
+
+/// A leaf for `view_number` extending `parent`
+fn child_leaf(parent: &Leaf2<TestTypes>, view_number: ViewNumber) -> Leaf2<TestTypes> {
+    let mut block_header = parent.block_header().clone();
+    block_header.block_number = parent.height() + 1;
+    block_header.timestamp = *view_number;
+
+    Leaf2::from_quorum_proposal(&QuorumProposalWrapper {
+        proposal: QuorumProposal2 {
+            block_header,
+            view_number,
+            epoch: None,
+            justify_qc: qc_for(parent),
+            next_epoch_justify_qc: None,
+            upgrade_certificate: None,
+            view_change_evidence: None,
+            next_drb_result: None,
+            state_cert: None,
+        },
+    })
+}
+
+/// Applies steps to a `Consensus` the way the consensus tasks do, and checks its invariants
+struct Harness {
+    consensus: Consensus<TestTypes>,
+    /// Payload commitment recorded for DA views
+    payload_commitment: VidCommitment,
+    /// View of the high QC after the previous step
+    high_qc_view: ViewNumber,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let validated_state = TestValidatedState::default();
+        let instance_state = TestInstanceState::default();
+        let (genesis_leaf, genesis_qc) = block_on(async {
+            (
+                Leaf2::genesis::<TestVersions>(&validated_state, &instance_state).await,
+                QuorumCertificate2::genesis::<TestVersions>(&validated_state, &instance_state)
+                    .await,
+            )
+        });
+        let genesis_view = genesis_leaf.view_number();
+        let payload_commitment = genesis_leaf.block_header().payload_commitment;
+
+        let validated_state_map = BTreeMap::from([(
+            genesis_view,
+            View {
+                view_inner: ViewInner::Leaf {
+                    leaf: genesis_leaf.commit(),
+                    state: Arc::new(validated_state),
+                    delta: None,
+                    epoch: None,
+                },
+            },
+        )]);
+        let saved_leaves = CommitmentMap::from([(genesis_leaf.commit(), genesis_leaf)]);
+
+        let consensus = Consensus::new(
+            validated_state_map,
+            None,
+            genesis_view,
+            None,
+            genesis_view,
+            genesis_view,
+            genesis_view,
+            BTreeMap::new(),
+            saved_leaves,
+            BTreeMap::new(),
+            genesis_qc,
+            None,
+            Arc::new(ConsensusMetricsValue::default()),
+            0,
+            None,
+        );
+
+        Self {
+            consensus,
+            payload_commitment,
+            high_qc_view: genesis_view,
+        }
+    }
+
+    /// Leaves in the state map which are not older than the last decided view, in view order
+    fn live_leaves(&self) -> Vec<Leaf2<TestTypes>> {
+        self.consensus
+            .validated_state_map()
+            .range(self.consensus.last_decided_view()..)
+            .filter_map(|(_, view)| view.leaf_commitment())
+            .filter_map(|leaf_commit| self.consensus.saved_leaves().get(&leaf_commit).cloned())
+            .collect()
+    }
+
+    /// The live leaf selected by `index` among those matching `filter`
+    fn choose(
+        &self,
+        index: &Index,
+        filter: impl Fn(&Leaf2<TestTypes>) -> bool,
+    ) -> Option<Leaf2<TestTypes>> {
+        let candidates: Vec<_> = self.live_leaves().into_iter().filter(filter).collect();
+        (!candidates.is_empty()).then(|| candidates[index.index(candidates.len())].clone())
+    }
+
+    fn apply(&mut self, step: &Step) -> Result<(), TestCaseError> {
+        let cur_view = self.consensus.cur_view();
+        let locked_view = self.consensus.locked_view();
+        let decided_view = self.consensus.last_decided_view();
+
+        match step {
+            Step::AdvanceView(n) => {
+                prop_assert!(self.consensus.update_view(cur_view + *n).is_ok());
+            },
+            Step::AddLeaf { parent, with_delta } => {
+                if let Some(parent) = self.choose(parent, |leaf| leaf.view_number() < cur_view) {
+                    let delta = with_delta.then(|| Arc::new(TestStateDelta {}));
+                    // A leaf may be rejected as stale if it would replace one with a state delta
+                    if let Err(err) = self.consensus.update_leaf(
+                        child_leaf(&parent, cur_view),
+                        Arc::new(TestValidatedState::default()),
+                        delta,
+                    ) {
+                        prop_assert!(err.is_stale(), "{err}");
+                    }
+                }
+            },
+            Step::AddDaView => {
+                // Rejected as stale if the current view already has a leaf
+                if let Err(err) =
+                    self.consensus
+                        .update_da_view(cur_view, None, self.payload_commitment)
+                {
+                    prop_assert!(err.is_stale(), "{err}");
+                }
+            },
+            Step::UpdateHighQc(index) => {
+                if let Some(leaf) = self.choose(index, |_| true) {
+                    let qc = qc_for(&leaf);
+                    let high_qc = self.consensus.high_qc();
+                    let accepted = qc == *high_qc || qc.view_number() > high_qc.view_number();
+                    match self.consensus.update_high_qc(qc) {
+                        Ok(()) => prop_assert!(accepted),
+                        Err(err) => prop_assert!(!accepted && err.is_stale(), "{err}"),
+                    }
+                }
+            },
+            Step::Lock(index) => {
+                if let Some(leaf) = self.choose(index, |leaf| leaf.view_number() > locked_view) {
+                    prop_assert!(self
+                        .consensus
+                        .update_locked_view(leaf.view_number())
+                        .is_ok());
+                }
+            },
+            Step::Decide(index) => {
+                if let Some(leaf) = self.choose(index, |leaf| {
+                    leaf.view_number() > decided_view && leaf.view_number() <= locked_view
+                }) {
+                    let new_decided_view = leaf.view_number();
+                    self.consensus
+                        .collect_garbage(decided_view, new_decided_view);
+                    prop_assert!(self
+                        .consensus
+                        .update_last_decided_view(new_decided_view)
+                        .is_ok());
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn check_invariants(&mut self) -> Result<(), TestCaseError> {
+        let consensus = &self.consensus;
+        let decided_view = consensus.last_decided_view();
+
+        prop_assert!(decided_view <= consensus.locked_view());
+        prop_assert!(consensus.locked_view() <= consensus.cur_view());
+
+        // The anchor must survive garbage collection: `decided_leaf` and `decided_state` panic
+        // if it is missing
+        prop_assert_eq!(consensus.decided_leaf().view_number(), decided_view);
+        let _ = consensus.decided_state();
+
+        // Every leaf from the anchor onwards must still be resolvable
+        for (view_number, view) in consensus.validated_state_map().range(decided_view..) {
+            if let Some(leaf_commit) = view.leaf_commitment() {
+                prop_assert!(
+                    consensus.saved_leaves().contains_key(&leaf_commit),
+                    "Leaf for view {} is missing from the saved leaves",
+                    **view_number
+                );
+            }
+        }
+        prop_assert!(consensus
+            .validated_state_map()
+            .get(&consensus.locked_view())
+            .is_some_and(|view| view.leaf_commitment().is_some()));
+
+        let high_qc_view = consensus.high_qc().view_number();
+        prop_assert!(high_qc_view >= self.high_qc_view);
+        self.high_qc_view = high_qc_view;
+
+        Ok(())
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn test_consensus_invariants(steps in prop::collection::vec(step(), 1..96)) {
+        let mut harness = Harness::new();
+        harness.check_invariants()?;
+        for step in &steps {
+            harness.apply(step)?;
+            harness.check_invariants()?;
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+#![cfg(feature = "simulation")]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    simulation::{with_rng, Simulation},
+    test_builder::TestDescription,
+};
+use rand::Rng;
+use tokio::time::{sleep, Instant};
+
+/// Spawn tasks which sleep for random durations, and return the order in which they woke up
+async fn wake_up_order() -> Vec<usize> {
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let tasks: Vec<_> = (0..32)
+        .map(|id| {
+            let order = Arc::clone(&order);
+            // Few distinct delays, so that many tasks wake up at the same time
+            let delay = with_rng(|rng| rng.gen_range(0..8));
+            tokio::spawn(async move {
+                sleep(Duration::from_millis(delay)).await;
+                order.lock().unwrap().push(id);
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    Arc::into_inner(order).unwrap().into_inner().unwrap()
+}
+
+#[test]
+fn test_simulation_replays_from_seed() {
+    let simulation = Simulation::from_env();
+
+    assert_eq!(
+        simulation.run(wake_up_order()),
+        simulation.run(wake_up_order())
+    );
+}
+
+#[test]
+fn test_simulation_uses_virtual_time() {
+    Simulation::from_env().run(async {
+        let start = Instant::now();
+        let wall_clock_start = std::time::Instant::now();
+
+        sleep(Duration::from_secs(3600)).await;
+
+        assert!(start.elapsed() >= Duration::from_secs(3600));
+        assert!(wall_clock_start.elapsed() < Duration::from_secs(60));
+    });
+}
+
+#[test]
+fn test_success_in_simulation() {
+    hotshot::helpers::initialize_logging();
+
+    TestDescription::<TestTypes, MemoryImpl, TestVersions>::default()
+        .gen_launcher()
+        .launch()
+        .run_simulation::<SimpleBuilderImplementation>(&Simulation::from_env());
+}
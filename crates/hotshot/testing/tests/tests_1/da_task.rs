@@ -77,7 +77,7 @@ async fn test_da_task() {
             )
             .await,
         );
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
     }
 
@@ -97,7 +97,7 @@ async fn test_da_task() {
             )
             .await,
         );
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
     }
 
@@ -190,7 +190,7 @@ async fn test_da_task_storage_failure() {
             )
             .await,
         );
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
     }
 
@@ -210,7 +210,7 @@ async fn test_da_task_storage_failure() {
             )
             .await,
         );
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
     }
 
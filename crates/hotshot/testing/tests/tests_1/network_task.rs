@@ -10,7 +10,10 @@ use async_broadcast::Sender;
 use async_lock::RwLock;
 use hotshot::traits::implementations::MemoryNetwork;
 use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
-use hotshot_task::task::{ConsensusTaskRegistry, Task};
+use hotshot_task::{
+    supervisor::TaskSupervisor,
+    task::{ConsensusTaskRegistry, Task},
+};
 use hotshot_task_impls::{events::HotShotEvent, network::NetworkEventTaskState};
 use hotshot_testing::{
     helpers::build_system_handle, test_builder::TestDescription,
@@ -33,8 +36,6 @@ use tokio::time::timeout;
 #[tokio::test(flavor = "multi_thread")]
 #[allow(clippy::too_many_lines)]
 async fn test_network_task() {
-    use std::collections::BTreeMap;
-
     use futures::StreamExt;
     use hotshot_types::epoch_membership::EpochMembershipCoordinator;
 
@@ -70,9 +71,12 @@ async fn test_network_task() {
             epoch: None,
             membership_coordinator: coordinator.clone(),
             upgrade_lock: upgrade_lock.clone(),
+            compression: Arc::default(),
+            bandwidth: Arc::default(),
             storage,
             consensus,
-            transmit_tasks: BTreeMap::new(),
+            transmit_tasks: TaskSupervisor::new("network_transmit"),
+            audit: Arc::default(),
             epoch_height: 0u64,
         };
     let (tx, rx) = async_broadcast::broadcast(10);
@@ -81,7 +85,8 @@ async fn test_network_task() {
     let task = Task::new(network_state, tx.clone(), rx);
     task_reg.run_task(task);
 
-    let mut generator = TestViewGenerator::<TestVersions>::generate(coordinator, node_key_map);
+    let mut generator =
+        TestViewGenerator::<TestVersions>::generate(coordinator.clone(), node_key_map);
     let view = generator.next().await.unwrap();
 
     let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(10);
@@ -92,6 +97,7 @@ async fn test_network_task() {
         upgrade_lock,
         network.clone(),
         public_key,
+        coordinator,
     )
     .await;
 
@@ -207,8 +213,6 @@ async fn test_network_external_mnessages() {
 #[cfg(test)]
 #[tokio::test(flavor = "multi_thread")]
 async fn test_network_storage_fail() {
-    use std::collections::BTreeMap;
-
     use futures::StreamExt;
     use hotshot_types::epoch_membership::EpochMembershipCoordinator;
 
@@ -244,9 +248,12 @@ async fn test_network_storage_fail() {
             epoch: None,
             membership_coordinator: coordinator.clone(),
             upgrade_lock: upgrade_lock.clone(),
+            compression: Arc::default(),
+            bandwidth: Arc::default(),
             storage,
             consensus,
-            transmit_tasks: BTreeMap::new(),
+            transmit_tasks: TaskSupervisor::new("network_transmit"),
+            audit: Arc::default(),
             epoch_height: 0u64,
         };
     let (tx, rx) = async_broadcast::broadcast(10);
@@ -255,7 +262,8 @@ async fn test_network_storage_fail() {
     let task = Task::new(network_state, tx.clone(), rx);
     task_reg.run_task(task);
 
-    let mut generator = TestViewGenerator::<TestVersions>::generate(coordinator, node_key_map);
+    let mut generator =
+        TestViewGenerator::<TestVersions>::generate(coordinator.clone(), node_key_map);
     let view = generator.next().await.unwrap();
 
     let (out_tx_internal, mut out_rx_internal): (Sender<Arc<HotShotEvent<TestTypes>>>, _) =
@@ -267,6 +275,7 @@ async fn test_network_storage_fail() {
         upgrade_lock,
         network.clone(),
         public_key,
+        coordinator,
     )
     .await;
 
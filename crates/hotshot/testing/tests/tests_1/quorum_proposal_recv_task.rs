@@ -29,12 +29,8 @@ use hotshot_testing::{
 };
 use hotshot_types::{
     data::{EpochNumber, Leaf2, ViewNumber},
-    request_response::ProposalRequestPayload,
     traits::{
-        consensus_api::ConsensusApi,
-        election::Membership,
-        node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
         ValidatedState,
     },
 };
@@ -68,7 +64,7 @@ async fn test_quorum_proposal_recv_task() {
         proposals.push(view.quorum_proposal.clone());
         leaders.push(view.leader_public_key);
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         leaves.push(view.leaf.clone());
 
@@ -142,7 +138,7 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
         proposals.push(view.quorum_proposal.clone());
         leaders.push(view.leader_public_key);
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         leaves.push(view.leaf.clone());
 
@@ -157,7 +153,8 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
         // We need there to be a DA certificate for us to be able to vote, so we grab
         // this from the generator as well since we don't have the running task that'd
         // insert the value ordinarily.
-        consensus_writer.update_saved_da_certs(inserted_view_number, view.da_certificate.clone());
+        consensus_writer
+            .update_saved_da_certs(inserted_view_number, view.da_certificate.clone().unwrap());
     }
 
     // We can only propose if we've seen a QcFormed event already, so we just insert it
@@ -175,20 +172,20 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
     )]];
 
     // make the request payload
-    let req = ProposalRequestPayload {
+    let req = ProposalFetchRequestPayload {
         view_number: ViewNumber::new(2),
+        leaf_commit: proposals[2].data.justify_qc().data.leaf_commit,
         key: handle.public_key(),
     };
 
     // make the signed commitment
-    let signature =
-        <TestTypes as NodeType>::SignatureKey::sign(handle.private_key(), req.commit().as_ref())
-            .unwrap();
+    let signature = handle.signer().sign(req.commit().as_ref()).unwrap();
 
     let expectations = vec![Expectations::from_outputs(all_predicates![
         exact(QuorumProposalPreliminarilyValidated(proposals[2].clone())),
         exact(ViewChange(ViewNumber::new(3), None)),
-        exact(QuorumProposalRequestSend(req, signature)),
+        // The missing proposal is fetched from its leader first.
+        exact(QuorumProposalFetchSend(req, signature, leaders[1])),
     ])];
 
     let state =
@@ -59,7 +59,7 @@ async fn test_quorum_vote_task_success() {
         leaders.push(view.leader_public_key);
         proposals.push(view.quorum_proposal.clone());
         leaves.push(view.leaf.clone());
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         consensus_writer
             .update_leaf(
@@ -126,7 +126,7 @@ async fn test_quorum_vote_task_miss_dependency() {
         proposals.push(view.quorum_proposal.clone());
         leaders.push(view.leader_public_key);
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         leaves.push(view.leaf.clone());
 
@@ -206,7 +206,7 @@ async fn test_quorum_vote_task_incorrect_dependency() {
         leaders.push(view.leader_public_key);
         proposals.push(view.quorum_proposal.clone());
         leaves.push(view.leaf.clone());
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
     }
 
@@ -94,7 +94,7 @@ async fn test_upgrade_task_with_proposal() {
     for view in (&mut generator).take(1).collect::<Vec<_>>().await {
         proposals.push(view.quorum_proposal.clone());
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vid_dispersals.push(view.vid_disperse.clone());
         leaders.push(view.leader_public_key);
         views.push(view.clone());
@@ -112,7 +112,7 @@ async fn test_upgrade_task_with_proposal() {
     for view in generator.take(4).collect::<Vec<_>>().await {
         proposals.push(view.quorum_proposal.clone());
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vid_dispersals.push(view.vid_disperse.clone());
         leaders.push(view.leader_public_key);
         leaves.push(view.leaf.clone());
@@ -234,10 +234,7 @@ async fn test_upgrade_task_with_proposal() {
         expectations: vec![
             Expectations::from_outputs(vec![]),
             Expectations::from_outputs(vec![]),
-            Expectations {
-                output_asserts: vec![upgrade_certificate_formed::<TestTypes>()],
-                task_state_asserts: vec![],
-            },
+            Expectations::from_outputs(vec![upgrade_certificate_formed::<TestTypes>()]),
             Expectations::from_outputs(vec![]),
         ],
     };
@@ -75,7 +75,7 @@ async fn test_upgrade_task_with_vote() {
     for view in (&mut generator).take(2).collect::<Vec<_>>().await {
         proposals.push(view.quorum_proposal.clone());
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         leaders.push(view.leader_public_key);
         leaves.push(view.leaf.clone());
@@ -94,7 +94,7 @@ async fn test_upgrade_task_with_vote() {
     for view in generator.take(4).collect::<Vec<_>>().await {
         proposals.push(view.quorum_proposal.clone());
         votes.push(view.create_quorum_vote(&handle).await);
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         leaders.push(view.leader_public_key);
         leaves.push(view.leaf.clone());
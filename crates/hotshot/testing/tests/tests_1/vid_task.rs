@@ -6,7 +6,7 @@
 
 use std::{marker::PhantomData, sync::Arc};
 
-use hotshot::{tasks::task_state::CreateTaskState, types::SignatureKey};
+use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::{
     block_types::{TestBlockPayload, TestMetadata, TestTransaction},
     node_types::{MemoryImpl, TestTypes, TestVersions},
@@ -25,13 +25,14 @@ use hotshot_types::{
     message::UpgradeLock,
     traits::{
         consensus_api::ConsensusApi,
-        node_implementation::{ConsensusTime, NodeType, Versions},
+        node_implementation::{ConsensusTime, Versions},
         BlockPayload,
     },
 };
 use vbs::version::StaticVersionType;
 use vec1::vec1;
 
+#[cfg(test)]
 #[tokio::test(flavor = "multi_thread")]
 async fn test_vid_task() {
     use hotshot_types::message::Proposal;
@@ -82,11 +83,10 @@ async fn test_vid_task() {
     let encoded_transactions: Arc<[u8]> = Arc::from(TestTransaction::encode(&transactions));
     let payload_commitment = vid_disperse.payload_commitment();
 
-    let signature = <TestTypes as NodeType>::SignatureKey::sign(
-        handle.private_key(),
-        payload_commitment.as_ref(),
-    )
-    .expect("Failed to sign block payload!");
+    let signature = handle
+        .signer()
+        .sign(payload_commitment.as_ref())
+        .expect("Failed to sign block payload!");
     let proposal: DaProposal<TestTypes> = DaProposal {
         encoded_transactions: encoded_transactions.clone(),
         metadata: TestMetadata {
@@ -144,7 +144,8 @@ async fn test_vid_task() {
                 None,
             )),
             exact(VidDisperseSend(vid_proposal.clone(), pub_key)),
-        ]),
+        ])
+        .unordered(),
     ];
 
     let vid_state = VidTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
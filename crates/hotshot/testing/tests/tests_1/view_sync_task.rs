@@ -4,6 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
+use committable::Committable;
 use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
 use hotshot_task_impls::{
@@ -11,8 +12,10 @@ use hotshot_task_impls::{
 };
 use hotshot_testing::helpers::build_system_handle;
 use hotshot_types::{
-    data::ViewNumber, simple_vote::ViewSyncPreCommitData2,
-    traits::node_implementation::ConsensusTime,
+    data::ViewNumber,
+    request_response::ViewEvidenceRequestPayload,
+    simple_vote::ViewSyncPreCommitData2,
+    traits::{consensus_api::ConsensusApi, node_implementation::ConsensusTime},
 };
 
 #[cfg(test)]
@@ -34,7 +37,7 @@ async fn test_view_sync_task() {
         vote_data,
         <TestTypes as hotshot_types::traits::node_implementation::NodeType>::View::new(4),
         hotshot_types::traits::consensus_api::ConsensusApi::public_key(&handle),
-        hotshot_types::traits::consensus_api::ConsensusApi::private_key(&handle),
+        hotshot_types::traits::consensus_api::ConsensusApi::signer(&handle),
         &handle.hotshot.upgrade_lock,
     )
     .await
@@ -47,11 +50,22 @@ async fn test_view_sync_task() {
 
     input.push(HotShotEvent::Timeout(ViewNumber::new(2), None));
     input.push(HotShotEvent::Timeout(ViewNumber::new(3), None));
+    // Timing out again in the same view restarts view sync, but asks for no more evidence.
+    input.push(HotShotEvent::Timeout(ViewNumber::new(3), None));
 
     input.push(HotShotEvent::Shutdown);
 
+    for view in [2, 3] {
+        let request = ViewEvidenceRequestPayload {
+            view_number: ViewNumber::new(view),
+            key: handle.public_key(),
+        };
+        let signature = handle.signer().sign(request.commit().as_ref()).unwrap();
+        output.push(HotShotEvent::ViewEvidenceRequestSend(request, signature));
+    }
     output.push(HotShotEvent::ViewChange(ViewNumber::new(3), None));
     output.push(HotShotEvent::ViewSyncPreCommitVoteSend(vote.clone()));
+    output.push(HotShotEvent::ViewSyncPreCommitVoteSend(vote.clone()));
 
     let view_sync_state = ViewSyncTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
     run_harness(input, output, view_sync_state, false).await;
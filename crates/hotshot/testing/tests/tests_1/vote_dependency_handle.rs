@@ -48,7 +48,7 @@ async fn test_vote_dependency_handle() {
     for view in (&mut generator).take(3).collect::<Vec<_>>().await {
         proposals.push(view.quorum_proposal.clone());
         leaves.push(view.leaf.clone());
-        dacs.push(view.da_certificate.clone());
+        dacs.push(view.da_certificate.clone().unwrap());
         vids.push(view.vid_proposal.clone());
         consensus_writer
             .update_leaf(
@@ -84,7 +84,7 @@ async fn test_vote_dependency_handle() {
         let vote_dependency_handle_state =
             VoteDependencyHandle::<TestTypes, MemoryImpl, TestVersions> {
                 public_key: handle.public_key(),
-                private_key: handle.private_key().clone(),
+                signer: handle.signer().clone(),
                 consensus: OuterConsensus::new(consensus.clone()),
                 consensus_metrics: Arc::clone(&consensus.read().await.metrics),
                 instance_state: handle.hotshot.instance_state(),
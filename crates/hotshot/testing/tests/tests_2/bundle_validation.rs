@@ -0,0 +1,176 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::marker::PhantomData;
+
+use hotshot_builder_api::v0_1::block_info::{
+    AvailableBlockData, AvailableBlockHeaderInputV2, AvailableBlockInfo,
+};
+use hotshot_example_types::{
+    block_types::{TestBlockPayload, TestTransaction},
+    node_types::TestTypes,
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_task_impls::bundle_validation::{
+    record_rejection, sandboxed, BundleRejection, ClaimedBlock,
+};
+use hotshot_testing::metrics::RecordedMetrics;
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    traits::{
+        block_contents::{BlockLimits, EncodeBytes},
+        node_implementation::NodeType,
+        signature_key::BuilderSignatureKey,
+        BlockPayload,
+    },
+};
+
+type BuilderKey = <TestTypes as NodeType>::BuilderSignatureKey;
+
+const OFFERED_FEE: u64 = 123;
+
+fn builder_key() -> (
+    BuilderKey,
+    <BuilderKey as BuilderSignatureKey>::BuilderPrivateKey,
+) {
+    <BuilderKey as BuilderSignatureKey>::generated_from_seed_indexed([0; 32], 0)
+}
+
+/// A block of `transactions`, correctly signed by the builder
+async fn claimed_block(transactions: Vec<TestTransaction>) -> ClaimedBlock<TestTypes> {
+    let (sender, key) = builder_key();
+    let (block_payload, metadata) = TestBlockPayload::from_transactions(
+        transactions,
+        &TestValidatedState::default(),
+        &TestInstanceState::default(),
+    )
+    .await
+    .unwrap();
+    let block_hash = block_payload.builder_commitment(&metadata);
+    let block_size = block_payload.encode().len() as u64;
+
+    ClaimedBlock {
+        info: AvailableBlockInfo {
+            signature: BuilderKey::sign_block_info(&key, block_size, OFFERED_FEE, &block_hash)
+                .unwrap(),
+            block_hash: block_hash.clone(),
+            block_size,
+            offered_fee: OFFERED_FEE,
+            sender: sender.clone(),
+            _phantom: PhantomData,
+        },
+        header_input: Some(AvailableBlockHeaderInputV2 {
+            fee_signature: BuilderKey::sign_fee(&key, OFFERED_FEE, &metadata).unwrap(),
+            sender: sender.clone(),
+        }),
+        data: AvailableBlockData {
+            block_payload,
+            metadata,
+            signature: BuilderKey::sign_builder_message(&key, block_hash.as_ref()).unwrap(),
+            sender,
+        },
+        legacy_header_input: None,
+        limits: BlockLimits::default(),
+    }
+}
+
+fn transactions() -> Vec<TestTransaction> {
+    vec![
+        TestTransaction::new(vec![1, 2, 3]),
+        TestTransaction::new(vec![4, 5]),
+    ]
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_claimed_block_accepted() {
+    let claimed = claimed_block(transactions()).await;
+    let sender = claimed.info.sender.clone();
+
+    let response = sandboxed(move || claimed.validate()).await.unwrap();
+    assert_eq!(response.fee.fee_amount, OFFERED_FEE);
+    assert_eq!(response.fee.fee_account, sender);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_claimed_block_rejected() {
+    // A payload other than the one the builder signed.
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.data.block_payload = claimed_block(vec![]).await.data.block_payload;
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::InvalidSignature)
+    );
+
+    // A correctly signed block, but not the one which was offered.
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.data = claimed_block(vec![]).await.data;
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::CommitmentMismatch)
+    );
+
+    // A block larger than the size the fee was offered for.
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.info.block_size -= 1;
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::SizeExceeded)
+    );
+
+    // A block with more transactions than a block may have.
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.limits.max_transactions = Some(1);
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::LimitsExceeded)
+    );
+
+    // A fee signature over a fee other than the one offered.
+    let mut claimed = claimed_block(transactions()).await;
+    let (_, key) = builder_key();
+    let fee_signature =
+        BuilderKey::sign_fee(&key, OFFERED_FEE + 1, &claimed.data.metadata).unwrap();
+    claimed.header_input.as_mut().unwrap().fee_signature = fee_signature;
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::InvalidFeeSignature)
+    );
+
+    // No fee signature at all.
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.header_input = None;
+    assert_eq!(
+        claimed.validate().err(),
+        Some(BundleRejection::InvalidFeeSignature)
+    );
+
+    // Validation which does not complete is a rejection too.
+    let result = sandboxed::<()>(|| panic!("malformed payload")).await;
+    assert_eq!(result, Err(BundleRejection::ValidationFailed));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_claimed_block_rejection_recorded() {
+    let metrics = RecordedMetrics::default();
+    let consensus_metrics = ConsensusMetricsValue::new(&metrics);
+
+    let mut claimed = claimed_block(transactions()).await;
+    claimed.info.block_size -= 1;
+    let builder = claimed.info.sender.clone();
+    let reason = sandboxed(move || claimed.validate()).await.err().unwrap();
+    record_rejection::<TestTypes>(&consensus_metrics, &builder, reason);
+
+    assert_eq!(
+        metrics.counter(&format!("rejected_builder_bundles-{builder}-size_exceeded")),
+        1
+    );
+    assert_eq!(
+        metrics.counter(&format!(
+            "rejected_builder_bundles-{builder}-invalid_signature"
+        )),
+        0
+    );
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::{da::DaTaskState, events::HotShotEvent};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{DaPayloadHint2, ViewNumber},
+    message::Proposal,
+    traits::{da_payload_provider::DaPayloadProvider, node_implementation::ConsensusTime},
+    vote::HasViewNumber,
+};
+use sha2::{Digest, Sha256};
+use tokio::time::timeout;
+
+/// Serves a single payload, whatever is asked for
+struct StaticPayloadProvider(Arc<[u8]>);
+
+#[async_trait]
+impl DaPayloadProvider<TestTypes> for StaticPayloadProvider {
+    async fn fetch_payload(&self, _hint: &DaPayloadHint2<TestTypes>) -> Option<Arc<[u8]>> {
+        Some(Arc::clone(&self.0))
+    }
+}
+
+/// A DA member which receives a payload hint instead of a DA proposal fetches the payload from the
+/// provider the application sets, even once its tasks are running, and votes on it.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_vote_on_payload_hint() {
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2).await;
+    let mut state = DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+
+    let mut generator = TestViewGenerator::<TestVersions>::generate(
+        handle.hotshot.membership_coordinator.clone(),
+        node_key_map,
+    );
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+    let proposal = views[1].da_proposal.clone();
+    let leader = views[1].leader_public_key;
+
+    let hint = Proposal {
+        data: DaPayloadHint2 {
+            encoded_transactions_hash: Sha256::digest(&proposal.data.encoded_transactions).into(),
+            payload_commitment: views[1].vid_disperse.data.payload_commitment(),
+            payload_byte_len: proposal.data.encoded_transactions.len() as u64,
+            metadata: proposal.data.metadata,
+            view_number: proposal.data.view_number,
+            epoch: proposal.data.epoch,
+            epoch_transition_indicator: proposal.data.epoch_transition_indicator.clone(),
+            fetch_hints: vec![],
+        },
+        signature: proposal.signature.clone(),
+        _pd: PhantomData,
+    };
+    assert!(handle
+        .hotshot
+        .set_da_payload_provider(Arc::new(StaticPayloadProvider(Arc::clone(
+            &proposal.data.encoded_transactions
+        )))));
+
+    let (sender, mut receiver) = async_broadcast::broadcast(1024);
+    for event in [
+        HotShotEvent::ViewChange(ViewNumber::new(1), None),
+        HotShotEvent::ViewChange(ViewNumber::new(2), None),
+        HotShotEvent::QuorumProposalValidated(
+            views[1].quorum_proposal.clone(),
+            views[0].leaf.clone(),
+        ),
+        HotShotEvent::DaPayloadHintRecv(hint, leader),
+    ] {
+        state.handle(Arc::new(event), sender.clone()).await.unwrap();
+    }
+
+    // Feed the events the task emits back into it, as the event stream would, until it votes.
+    let vote = timeout(Duration::from_secs(10), async {
+        loop {
+            let event = receiver.recv_direct().await.unwrap();
+            if let HotShotEvent::DaVoteSend(vote) = event.as_ref() {
+                break vote.clone();
+            }
+            state.handle(event, sender.clone()).await.unwrap();
+        }
+    })
+    .await
+    .expect("no DA vote was cast for the payload hint");
+    assert_eq!(vote.view_number(), ViewNumber::new(2));
+}
@@ -0,0 +1,296 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use alloy::primitives::U256;
+use committable::Commitment;
+use hotshot_example_types::node_types::{EpochsTestVersions, MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::{build_cert, build_system_handle, key_pair_for_id};
+use hotshot_types::{
+    data::{EpochNumber, Leaf2, ViewNumber},
+    drb::INITIAL_DRB_RESULT,
+    simple_certificate::{
+        DoubleQuorumCertificate, EpochQuorumCertificate, NextEpochQuorumCertificate2,
+        QuorumCertificate2,
+    },
+    simple_vote::{NextEpochQuorumData2, NextEpochQuorumVote2, QuorumData2, QuorumVote2},
+    traits::{election::Membership, node_implementation::ConsensusTime},
+    utils::is_epoch_transition,
+    StakeTableEntries, ValidatorConfig,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_double_quorum_certificates_across_epoch_boundaries() {
+    hotshot::helpers::initialize_logging();
+
+    const EPOCH_HEIGHT: u64 = 10;
+
+    let (handle, ..) = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1).await;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let membership = handle
+        .hotshot
+        .membership_coordinator
+        .membership_for_epoch(None)
+        .await
+        .unwrap();
+    let stake_table = StakeTableEntries::<TestTypes>::from(membership.stake_table().await).0;
+    let threshold = membership.success_threshold().await;
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(1);
+    let leaf_commit = Commitment::<Leaf2<TestTypes>>::from_raw([1; 32]);
+
+    // Simulate the certificates for every block of two epochs and the boundaries around them.
+    for block_number in 1..=2 * EPOCH_HEIGHT {
+        let view = ViewNumber::new(block_number);
+        let data = QuorumData2 {
+            leaf_commit,
+            epoch: None,
+            block_number: Some(block_number),
+        };
+        let qc = build_cert::<
+            TestTypes,
+            TestVersions,
+            QuorumData2<TestTypes>,
+            QuorumVote2<TestTypes>,
+            QuorumCertificate2<TestTypes>,
+        >(
+            data.clone(),
+            &membership,
+            view,
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await;
+        let next_epoch_qc = build_cert::<
+            TestTypes,
+            TestVersions,
+            NextEpochQuorumData2<TestTypes>,
+            NextEpochQuorumVote2<TestTypes>,
+            NextEpochQuorumCertificate2<TestTypes>,
+        >(
+            data.into(),
+            &membership,
+            view,
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await;
+
+        // A block in the transition cannot be justified by the outgoing epoch's quorum alone.
+        let single = EpochQuorumCertificate::new(qc.clone(), None, EPOCH_HEIGHT);
+        if is_epoch_transition(block_number, EPOCH_HEIGHT) {
+            assert!(single.is_err(), "block {block_number}");
+        } else {
+            let single = single.unwrap();
+            assert_eq!(single.next_epoch_qc(), None);
+            single.is_valid(&membership, upgrade_lock).await.unwrap();
+        }
+
+        let certificates =
+            EpochQuorumCertificate::new(qc.clone(), Some(next_epoch_qc.clone()), EPOCH_HEIGHT)
+                .unwrap();
+        let EpochQuorumCertificate::Double(double) = certificates else {
+            panic!("block {block_number} should have a double quorum certificate");
+        };
+        assert_eq!(double.qc(), &qc);
+        assert_eq!(double.next_epoch_qc(), &next_epoch_qc);
+
+        double
+            .is_valid_cert(
+                stake_table.clone(),
+                threshold,
+                stake_table.clone(),
+                threshold,
+                upgrade_lock,
+            )
+            .await
+            .unwrap();
+        // Each certificate must be checked against the stake table of its own epoch.
+        double
+            .is_valid_cert(
+                stake_table.clone(),
+                threshold,
+                stake_table[..1].to_vec(),
+                threshold,
+                upgrade_lock,
+            )
+            .await
+            .unwrap_err();
+        double
+            .is_valid_cert(
+                stake_table[..1].to_vec(),
+                threshold,
+                stake_table.clone(),
+                threshold,
+                upgrade_lock,
+            )
+            .await
+            .unwrap_err();
+    }
+
+    // Certificates from the two quorums must be for the same view and leaf.
+    let data = |block_number: u64| QuorumData2 {
+        leaf_commit,
+        epoch: None,
+        block_number: Some(block_number),
+    };
+    let qc = build_cert::<
+        TestTypes,
+        TestVersions,
+        QuorumData2<TestTypes>,
+        QuorumVote2<TestTypes>,
+        QuorumCertificate2<TestTypes>,
+    >(
+        data(8),
+        &membership,
+        ViewNumber::new(8),
+        &public_key,
+        &private_key,
+        upgrade_lock,
+    )
+    .await;
+    for (block_number, view) in [(9, 8), (8, 9)] {
+        let next_epoch_qc = build_cert::<
+            TestTypes,
+            TestVersions,
+            NextEpochQuorumData2<TestTypes>,
+            NextEpochQuorumVote2<TestTypes>,
+            NextEpochQuorumCertificate2<TestTypes>,
+        >(
+            data(block_number).into(),
+            &membership,
+            ViewNumber::new(view),
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await;
+        DoubleQuorumCertificate::new(qc.clone(), next_epoch_qc).unwrap_err();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_double_quorum_certificates_with_changing_stake_table() {
+    hotshot::helpers::initialize_logging();
+
+    const EPOCH_HEIGHT: u64 = 10;
+
+    let (handle, ..) = build_system_handle::<TestTypes, MemoryImpl, EpochsTestVersions>(1).await;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let coordinator = handle.hotshot.membership_coordinator.clone();
+
+    // Only the first five nodes have stake from the second epoch onwards.
+    let peer_config = |node_id: u64| {
+        ValidatorConfig::<TestTypes>::generated_from_seed_indexed(
+            [0u8; 32],
+            node_id,
+            U256::from(1),
+            node_id < 3,
+        )
+        .public_config()
+    };
+    {
+        let mut membership = coordinator.membership().write().await;
+        membership.set_first_epoch(EpochNumber::new(1), INITIAL_DRB_RESULT);
+        membership.set_committee_from_epoch(
+            EpochNumber::new(2),
+            (0..5).map(peer_config).collect(),
+            (0..3).map(peer_config).collect(),
+        );
+    }
+    let membership = coordinator
+        .membership_for_epoch(Some(EpochNumber::new(1)))
+        .await
+        .unwrap();
+    let next_membership = coordinator
+        .membership_for_epoch(Some(EpochNumber::new(2)))
+        .await
+        .unwrap();
+    let stake_table = StakeTableEntries::<TestTypes>::from(membership.stake_table().await).0;
+    let threshold = membership.success_threshold().await;
+    let next_stake_table =
+        StakeTableEntries::<TestTypes>::from(next_membership.stake_table().await).0;
+    let next_threshold = next_membership.success_threshold().await;
+    assert_ne!(stake_table.len(), next_stake_table.len());
+
+    // The last block of the first epoch is certified by both quorums.
+    let block_number = EPOCH_HEIGHT - 1;
+    assert!(is_epoch_transition(block_number, EPOCH_HEIGHT));
+    let view = ViewNumber::new(block_number);
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(1);
+    let data = QuorumData2 {
+        leaf_commit: Commitment::<Leaf2<TestTypes>>::from_raw([1; 32]),
+        epoch: Some(EpochNumber::new(1)),
+        block_number: Some(block_number),
+    };
+    let qc = build_cert::<
+        TestTypes,
+        EpochsTestVersions,
+        QuorumData2<TestTypes>,
+        QuorumVote2<TestTypes>,
+        QuorumCertificate2<TestTypes>,
+    >(
+        data.clone(),
+        &membership,
+        view,
+        &public_key,
+        &private_key,
+        upgrade_lock,
+    )
+    .await;
+    let next_epoch_qc = build_cert::<
+        TestTypes,
+        EpochsTestVersions,
+        NextEpochQuorumData2<TestTypes>,
+        NextEpochQuorumVote2<TestTypes>,
+        NextEpochQuorumCertificate2<TestTypes>,
+    >(
+        data.into(),
+        &next_membership,
+        view,
+        &public_key,
+        &private_key,
+        upgrade_lock,
+    )
+    .await;
+
+    let certificates = EpochQuorumCertificate::new(qc, Some(next_epoch_qc), EPOCH_HEIGHT).unwrap();
+    let EpochQuorumCertificate::Double(double) = &certificates else {
+        panic!("block {block_number} should have a double quorum certificate");
+    };
+
+    double
+        .is_valid_cert(
+            stake_table.clone(),
+            threshold,
+            next_stake_table.clone(),
+            next_threshold,
+            upgrade_lock,
+        )
+        .await
+        .unwrap();
+    // Swapping the stake tables of the two epochs invalidates both certificates.
+    double
+        .is_valid_cert(
+            next_stake_table,
+            next_threshold,
+            stake_table,
+            threshold,
+            upgrade_lock,
+        )
+        .await
+        .unwrap_err();
+
+    // The stake table of the next epoch is looked up from the epoch of the leaf.
+    certificates
+        .is_valid(&membership, upgrade_lock)
+        .await
+        .unwrap();
+    certificates
+        .is_valid(&next_membership, upgrade_lock)
+        .await
+        .unwrap_err();
+}
@@ -0,0 +1,195 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use committable::Commitment;
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::{da::DaTaskState, events::HotShotEvent::DaProposalValidated};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    error::{ErrorCategory, ErrorReport, HotShotError, RoundTimedoutState},
+    event::EventType,
+    traits::node_implementation::ConsensusTime,
+};
+use tokio::time::timeout;
+use vbs::{version::StaticVersion, BinarySerializer, Serializer};
+
+type TestSerializer = Serializer<StaticVersion<0, 1>>;
+
+/// Error events carry the code and category of the error, so they survive serialization.
+#[test]
+fn test_error_event_serialization() {
+    let error = HotShotError::<TestTypes>::ViewTimedOut {
+        view_number: ViewNumber::new(1),
+        state: RoundTimedoutState::ReplicaWaitingForDecide,
+    };
+    assert_eq!(error.code(), 1001);
+    assert_eq!(error.category(), ErrorCategory::Network);
+
+    let event = EventType::Error {
+        error: Arc::new(error),
+    };
+    for deserialized in [
+        serde_json::from_str(&serde_json::to_string(&event).unwrap()).unwrap(),
+        TestSerializer::deserialize(&TestSerializer::serialize(&event).unwrap()).unwrap(),
+    ] {
+        let EventType::<TestTypes>::Error { error } = deserialized else {
+            panic!("expected an error event");
+        };
+        assert_eq!(
+            error.report(),
+            ErrorReport {
+                code: 1001,
+                category: ErrorCategory::Network,
+                message: "View 1 timed out: ReplicaWaitingForDecide".into(),
+            }
+        );
+    }
+}
+
+/// The codes and categories of errors are part of the events API, and must never change.
+#[test]
+fn test_error_codes_are_stable() {
+    let errors: Vec<(HotShotError<TestTypes>, u16, ErrorCategory)> = vec![
+        (
+            HotShotError::Network("".into()),
+            1000,
+            ErrorCategory::Network,
+        ),
+        (
+            HotShotError::ViewTimedOut {
+                view_number: ViewNumber::new(1),
+                state: RoundTimedoutState::ReplicaWaitingForDecide,
+            },
+            1001,
+            ErrorCategory::Network,
+        ),
+        (
+            HotShotError::Storage("".into()),
+            2000,
+            ErrorCategory::Storage,
+        ),
+        (
+            HotShotError::MissingLeaf(Commitment::from_raw([0; 32])),
+            2001,
+            ErrorCategory::Storage,
+        ),
+        (
+            HotShotError::Validation("".into()),
+            3000,
+            ErrorCategory::Validation,
+        ),
+        (
+            HotShotError::DuplicateTransaction("".into()),
+            3001,
+            ErrorCategory::Validation,
+        ),
+        (
+            HotShotError::ByzantineEvidence("".into()),
+            4000,
+            ErrorCategory::ByzantineEvidence,
+        ),
+        (
+            HotShotError::InvalidState("".into()),
+            5000,
+            ErrorCategory::Internal,
+        ),
+        (
+            HotShotError::FailedToSerialize("".into()),
+            5001,
+            ErrorCategory::Internal,
+        ),
+        (
+            HotShotError::FailedToDeserialize("".into()),
+            5002,
+            ErrorCategory::Internal,
+        ),
+    ];
+    for (error, code, category) in errors {
+        assert_eq!(error.code(), code, "{error}");
+        assert_eq!(error.category(), category, "{error}");
+        assert_eq!(ErrorCategory::from_code(code), Some(category));
+        assert_eq!(category as u16, code / 1000);
+    }
+
+    // Categories are serialized by name.
+    assert_eq!(
+        serde_json::to_string(&ErrorCategory::ByzantineEvidence).unwrap(),
+        "\"byzantine-evidence\""
+    );
+}
+
+/// Error events from nodes which serialized errors as plain messages can still be read.
+#[test]
+fn test_legacy_error_event_deserialization() {
+    // Older versions encoded the `Error` variant with just the message of the error.
+    #[derive(serde::Serialize)]
+    enum LegacyEventType {
+        Error { error: String },
+    }
+    let legacy = LegacyEventType::Error {
+        error: "Invalid state: no parent".into(),
+    };
+
+    for deserialized in [
+        serde_json::from_str(&serde_json::to_string(&legacy).unwrap()).unwrap(),
+        TestSerializer::deserialize(&TestSerializer::serialize(&legacy).unwrap()).unwrap(),
+    ] {
+        let EventType::<TestTypes>::Error { error } = deserialized else {
+            panic!("expected an error event");
+        };
+        assert!(matches!(
+            &*error,
+            HotShotError::FailedToDeserialize(message) if message == "Invalid state: no parent"
+        ));
+    }
+}
+
+/// A DA proposal which cannot be stored is reported to the application as a storage error.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_storage_failure_error_event() {
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2).await;
+    handle.storage().write().await.should_return_err = true;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    let mut generator = TestViewGenerator::<TestVersions>::generate(membership, node_key_map);
+    let view = generator.next().await.unwrap();
+
+    let mut events = handle.event_stream_known_impl();
+    let mut da_state =
+        DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let (sender, receiver) = async_broadcast::broadcast(16);
+    assert!(da_state
+        .handle_event(
+            Arc::new(DaProposalValidated(
+                view.da_proposal.clone(),
+                view.leader_public_key
+            )),
+            &sender,
+            &receiver,
+        )
+        .await
+        .is_err());
+
+    let error = timeout(Duration::from_secs(1), async {
+        loop {
+            if let EventType::Error { error } = events.recv().await.unwrap().event {
+                return error;
+            }
+        }
+    })
+    .await
+    .expect("no error event was sent");
+    assert_eq!(error.code(), 2000);
+    assert_eq!(error.category(), ErrorCategory::Storage);
+}
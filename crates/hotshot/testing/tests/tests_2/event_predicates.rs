@@ -0,0 +1,192 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::events::HotShotEvent::*;
+use hotshot_testing::{
+    event_matches,
+    helpers::build_system_handle,
+    predicates::{
+        event::{
+            da_vote_send_for_view, event_matching, quorum_proposal_send,
+            quorum_proposal_send_for_view, quorum_proposal_send_with, EventPredicate,
+            ProposalContents,
+        },
+        Predicate, PredicateResult,
+    },
+    script::{
+        check_stage_outputs_or_panic_in_script, validate_stage_output_or_panic_in_script,
+        Expectations, StageProgress,
+    },
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    simple_vote::DaData2,
+    traits::{block_contents::BlockHeader, node_implementation::ConsensusTime},
+    vote::HasViewNumber,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_event_predicates() {
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2).await;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    let mut generator = TestViewGenerator::<TestVersions>::generate(membership, node_key_map);
+    let views = (&mut generator).take(2).collect::<Vec<_>>().await;
+
+    let proposals: Vec<_> = views
+        .iter()
+        .map(|view| {
+            Arc::new(QuorumProposalSend(
+                view.quorum_proposal.clone(),
+                view.leader_public_key,
+            ))
+        })
+        .collect();
+    let mut votes = vec![];
+    for view in &views {
+        let vote = view
+            .create_da_vote(
+                DaData2 {
+                    payload_commit: view
+                        .quorum_proposal
+                        .data
+                        .block_header()
+                        .payload_commitment(),
+                    next_epoch_payload_commit: None,
+                    epoch: view.da_proposal.data.epoch,
+                },
+                &handle,
+            )
+            .await;
+        votes.push(Arc::new(DaVoteSend(vote)));
+    }
+
+    // Proposals are matched by their contents, not by the whole event.
+    let view_number = views[1].view_number;
+    let payload_commitment = views[1]
+        .quorum_proposal
+        .data
+        .block_header()
+        .payload_commitment();
+    let predicate = quorum_proposal_send_with(ProposalContents {
+        view_number: Some(view_number),
+        epoch: Some(None),
+        payload_commitment: Some(payload_commitment),
+    });
+    assert_eq!(
+        predicate.evaluate(&proposals[1]).await,
+        PredicateResult::Pass
+    );
+    assert_eq!(
+        predicate.evaluate(&proposals[0]).await,
+        PredicateResult::Fail
+    );
+    assert_eq!(predicate.evaluate(&votes[1]).await, PredicateResult::Fail);
+    assert_eq!(
+        quorum_proposal_send_for_view(views[0].view_number)
+            .evaluate(&proposals[0])
+            .await,
+        PredicateResult::Pass
+    );
+
+    // Patterns can extract fields and check them.
+    let predicate: Box<EventPredicate<TestTypes>> =
+        event_matches!(DaVoteSend(vote) if vote.view_number() == view_number);
+    assert_eq!(predicate.evaluate(&votes[1]).await, PredicateResult::Pass);
+    assert_eq!(predicate.evaluate(&votes[0]).await, PredicateResult::Fail);
+    let predicate: Box<EventPredicate<TestTypes>> = event_matches!(QuorumProposalSend(..));
+    assert_eq!(
+        predicate.evaluate(&proposals[0]).await,
+        PredicateResult::Pass
+    );
+
+    // A stage which forbids an output still allows the others.
+    let expectations = forbid_da_vote(view_number);
+    let mut progress = StageProgress::default();
+    for output in [
+        &votes[0],
+        &proposals[1],
+        &Arc::new(ViewChange(ViewNumber::new(3), None)),
+    ] {
+        validate_stage_output_or_panic_in_script(
+            0,
+            "test".into(),
+            &expectations,
+            &mut progress,
+            output,
+        )
+        .await;
+    }
+
+    // An unordered stage rematches an output satisfying several predicates if a later output
+    // needs its predicate, rather than failing on the later output.
+    let expectations: Expectations<TestTypes, ()> = Expectations::from_outputs(vec![
+        quorum_proposal_send(),
+        quorum_proposal_send_for_view(views[0].view_number),
+    ])
+    .unordered();
+    let mut progress = StageProgress::default();
+    for output in [&proposals[0], &proposals[1]] {
+        validate_stage_output_or_panic_in_script(
+            0,
+            "test".into(),
+            &expectations,
+            &mut progress,
+            output,
+        )
+        .await;
+    }
+    check_stage_outputs_or_panic_in_script(0, "test".into(), &expectations, &progress);
+}
+
+/// A stage in which any output is allowed, except a DA vote in `view_number`
+fn forbid_da_vote(view_number: ViewNumber) -> Expectations<TestTypes, ()> {
+    Expectations::from_outputs(vec![])
+        .with_optional_outputs(vec![event_matching("any event", |_| true)])
+        .without_outputs(vec![da_vote_send_for_view(view_number)])
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[should_panic(expected = "is forbidden by")]
+async fn test_forbidden_stage_output() {
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2).await;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    let mut generator = TestViewGenerator::<TestVersions>::generate(membership, node_key_map);
+    let view = generator.next().await.unwrap();
+    let vote = view
+        .create_da_vote(
+            DaData2 {
+                payload_commit: view
+                    .quorum_proposal
+                    .data
+                    .block_header()
+                    .payload_commitment(),
+                next_epoch_payload_commit: None,
+                epoch: view.da_proposal.data.epoch,
+            },
+            &handle,
+        )
+        .await;
+
+    validate_stage_output_or_panic_in_script(
+        0,
+        "test".into(),
+        &forbid_da_vote(view.view_number),
+        &mut StageProgress::default(),
+        &Arc::new(DaVoteSend(vote)),
+    )
+    .await;
+}
@@ -0,0 +1,27 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{MarketplaceTestVersions, TestTypes, TestVersions};
+use hotshot_types::{
+    data::ViewNumber, feature_gates::Feature, message::UpgradeLock,
+    traits::node_implementation::ConsensusTime,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_feature_gates() {
+    let view = ViewNumber::new(10);
+
+    // Without an upgrade, only the features of the base version are enabled.
+    let gates = UpgradeLock::<TestTypes, TestVersions>::new().features();
+    assert!(!gates.enabled(Feature::Marketplace, view).await);
+    assert!(!gates.enabled(Feature::Epochs, view).await);
+    assert!(gates.active(view).await.is_empty());
+
+    let gates = UpgradeLock::<TestTypes, MarketplaceTestVersions>::new().features();
+    assert!(gates.enabled(Feature::Marketplace, view).await);
+    assert!(!gates.enabled(Feature::Epochs, view).await);
+    assert_eq!(gates.active(view).await, vec![Feature::Marketplace]);
+}
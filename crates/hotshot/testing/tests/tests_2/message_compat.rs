@@ -0,0 +1,66 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::marker::PhantomData;
+
+use committable::Committable;
+use hotshot_example_types::node_types::{
+    EpochUpgradeTestVersions, EpochsTestVersions, TestTypes, TestVersions,
+};
+use hotshot_task_impls::message_compat::MessageTranslator;
+use hotshot_types::{
+    data::ViewNumber,
+    message::UpgradeLock,
+    simple_certificate::UpgradeCertificate,
+    simple_vote::UpgradeProposalData,
+    traits::node_implementation::{ConsensusTime, Versions},
+};
+use vbs::version::Version;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_message_translator_formats() {
+    let view = ViewNumber::new(10);
+
+    // Before epochs, only messages in the old format are accepted.
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let translator = MessageTranslator::new(&upgrade_lock);
+    assert!(translator.accepts_legacy(view).await);
+    assert!(!translator.accepts_current(view).await);
+
+    // Without an upgrade to epochs, there is no window for messages in the old format.
+    let upgrade_lock = UpgradeLock::<TestTypes, EpochsTestVersions>::new();
+    let translator = MessageTranslator::new(&upgrade_lock);
+    assert!(!translator.accepts_legacy(view).await);
+    assert!(translator.accepts_current(view).await);
+
+    // After an upgrade to epochs, messages in the old format are rejected from the first view of
+    // the new version.
+    let upgrade_lock = UpgradeLock::<TestTypes, EpochUpgradeTestVersions>::new();
+    let upgrade_data = UpgradeProposalData {
+        old_version: Version { major: 0, minor: 3 },
+        new_version: Version { major: 0, minor: 4 },
+        decide_by: ViewNumber::new(4),
+        new_version_hash: EpochUpgradeTestVersions::UPGRADE_HASH.to_vec(),
+        old_version_last_view: ViewNumber::new(5),
+        new_version_first_view: ViewNumber::new(7),
+    };
+    *upgrade_lock.decided_upgrade_certificate.write().await = Some(UpgradeCertificate::new(
+        upgrade_data.clone(),
+        upgrade_data.commit(),
+        ViewNumber::new(3),
+        None,
+        PhantomData,
+    ));
+    let translator = MessageTranslator::new(&upgrade_lock);
+    for view in [5, 6] {
+        assert!(translator.accepts_legacy(ViewNumber::new(view)).await);
+        assert!(!translator.accepts_current(ViewNumber::new(view)).await);
+    }
+    for view in [7, 8] {
+        assert!(!translator.accepts_legacy(ViewNumber::new(view)).await);
+        assert!(translator.accepts_current(ViewNumber::new(view)).await);
+    }
+}
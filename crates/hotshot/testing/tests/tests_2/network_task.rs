@@ -0,0 +1,120 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use futures::StreamExt;
+use hotshot::traits::implementations::MemoryNetwork;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task::supervisor::TaskSupervisor;
+use hotshot_task_impls::{events::HotShotEvent, network::NetworkEventTaskState};
+use hotshot_testing::{
+    helpers::build_system_handle, test_builder::TestDescription,
+    test_task::add_network_message_test_task, view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::OuterConsensus,
+    data::ViewNumber,
+    epoch_membership::EpochMembershipCoordinator,
+    message::UpgradeLock,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    vote::HasViewNumber,
+};
+use tokio::time::timeout;
+
+// Test that the event task hands each message to a transmit task owned by its supervisor, which
+// delivers the message, and that the supervisor drops the task once its view is stale
+#[tokio::test(flavor = "multi_thread")]
+async fn test_network_task_supervises_transmit_tasks() {
+    hotshot::helpers::initialize_logging();
+
+    let builder: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_multiple_rounds();
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let node_id = 1;
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id).await;
+    let launcher = builder.gen_launcher();
+
+    let network = (launcher.resource_generators.channel_generator)(node_id).await;
+
+    let storage = Arc::new(RwLock::new((launcher.resource_generators.storage)(node_id)));
+    let consensus = OuterConsensus::new(handle.hotshot.consensus());
+    let config = (launcher.resource_generators.hotshot_config)(node_id);
+    let validator_config = (launcher.resource_generators.validator_config)(node_id);
+    let public_key = validator_config.public_key;
+
+    let all_nodes = config.known_nodes_with_stake.clone();
+
+    let membership = Arc::new(RwLock::new(<TestTypes as NodeType>::Membership::new(
+        all_nodes.clone(),
+        all_nodes,
+    )));
+    let coordinator = EpochMembershipCoordinator::new(membership, config.epoch_height);
+    let mut network_state: NetworkEventTaskState<TestTypes, TestVersions, MemoryNetwork<_>, _> =
+        NetworkEventTaskState {
+            network: network.clone(),
+            view: ViewNumber::new(0),
+            epoch: None,
+            membership_coordinator: coordinator.clone(),
+            upgrade_lock: upgrade_lock.clone(),
+            compression: Arc::default(),
+            bandwidth: Arc::default(),
+            storage,
+            consensus,
+            transmit_tasks: TaskSupervisor::new("network_transmit"),
+            audit: Arc::default(),
+            epoch_height: 0u64,
+        };
+
+    let mut generator =
+        TestViewGenerator::<TestVersions>::generate(coordinator.clone(), node_key_map);
+    let view = generator.next().await.unwrap();
+    let view_number = view.quorum_proposal.data.view_number();
+
+    let (out_tx_internal, mut out_rx_internal) = async_broadcast::broadcast(10);
+    let (out_tx_external, _) = async_broadcast::broadcast(10);
+    add_network_message_test_task(
+        out_tx_internal.clone(),
+        out_tx_external.clone(),
+        upgrade_lock,
+        network.clone(),
+        public_key,
+        coordinator,
+    )
+    .await;
+
+    network_state
+        .handle(Arc::new(HotShotEvent::QuorumProposalSend(
+            view.quorum_proposal,
+            public_key,
+        )))
+        .await;
+    assert!(network_state.transmit_tasks.contains(&view_number));
+
+    let res: Arc<HotShotEvent<TestTypes>> =
+        timeout(Duration::from_millis(100), out_rx_internal.recv_direct())
+            .await
+            .expect("timed out waiting for response")
+            .expect("channel closed");
+    assert!(matches!(
+        res.as_ref(),
+        HotShotEvent::QuorumProposalRecv(_, _)
+    ));
+
+    // Moving on past the view lets the supervisor drop its transmit task.
+    network_state
+        .handle(Arc::new(HotShotEvent::ViewChange(
+            ViewNumber::new(*view_number + 2),
+            None,
+        )))
+        .await;
+    assert!(network_state.transmit_tasks.is_empty());
+}
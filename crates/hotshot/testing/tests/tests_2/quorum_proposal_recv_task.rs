@@ -0,0 +1,163 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{EpochsTestVersions, MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::run_test;
+use hotshot_task_impls::{
+    events::HotShotEvent::*, quorum_proposal_recv::QuorumProposalRecvTaskState,
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, build_system_handle_from_launcher},
+    predicates::event::{all_predicates, exact},
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+    test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    drb::INITIAL_DRB_RESULT,
+    request_response::{ProposalFetchRequestPayload, ProposalRequestPayload},
+    traits::{
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
+    },
+    vote::HasViewNumber,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quorum_proposal_recv_task_fetches_missing_parent() {
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(4).await;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+
+    let mut generator = TestViewGenerator::<TestVersions>::generate(membership, node_key_map);
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    for view in (&mut generator).take(4).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+
+        let inserted_view_number = view.quorum_proposal.data.view_number();
+        consensus_writer.update_vid_shares(inserted_view_number, view.vid_proposal.0[2].clone());
+        consensus_writer
+            .update_saved_da_certs(inserted_view_number, view.da_certificate.clone().unwrap());
+    }
+    consensus_writer
+        .update_high_qc(proposals[3].data.justify_qc().clone())
+        .unwrap();
+    drop(consensus_writer);
+
+    // The parent of the proposal for view 3 is unknown, so it is buffered while the parent
+    // is requested, and the response to that request resolves the dependency.
+    let inputs = vec![
+        serial![QuorumProposalRecv(proposals[2].clone(), leaders[2])],
+        serial![QuorumProposalResponseRecv(proposals[1].clone())],
+    ];
+
+    // Before the version which introduces proposal fetch requests, the parent is requested from
+    // all peers at once.
+    let req = ProposalRequestPayload {
+        view_number: ViewNumber::new(2),
+        key: handle.public_key(),
+    };
+    let signature = handle.signer().sign(req.commit().as_ref()).unwrap();
+
+    let expectations = vec![
+        Expectations::from_outputs(all_predicates![
+            exact(QuorumProposalPreliminarilyValidated(proposals[2].clone())),
+            exact(ViewChange(ViewNumber::new(3), None)),
+            exact(QuorumProposalRequestSend(req, signature)),
+        ]),
+        Expectations::from_outputs(vec![exact(ProposalDependenciesFetched(ViewNumber::new(3)))]),
+    ];
+
+    let state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle)
+            .await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+
+    assert!(consensus
+        .read()
+        .await
+        .saved_leaves()
+        .contains_key(&proposals[2].data.justify_qc().data.leaf_commit));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quorum_proposal_recv_task_fetches_missing_parent_from_leader() {
+    hotshot::helpers::initialize_logging();
+
+    // Proposal fetch requests are introduced with epochs.
+    let launcher =
+        TestDescription::<TestTypes, MemoryImpl, EpochsTestVersions>::default_multiple_rounds()
+            .gen_launcher();
+    let epoch_height = launcher.metadata.test_config.epoch_height;
+    let (handle, _, _, node_key_map) = build_system_handle_from_launcher(4, &launcher).await;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    membership
+        .membership()
+        .write()
+        .await
+        .set_first_epoch(EpochNumber::new(1), INITIAL_DRB_RESULT);
+
+    let mut generator = TestViewGenerator::<EpochsTestVersions>::generate_with_epochs(
+        membership,
+        node_key_map,
+        epoch_height,
+    );
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+    }
+
+    // The parent of the proposal for view 3 is unknown, so it is fetched from the leader who
+    // proposed it, rather than requested from all peers.
+    let inputs = vec![serial![QuorumProposalRecv(
+        proposals[2].clone(),
+        leaders[2]
+    )]];
+
+    let req = ProposalFetchRequestPayload {
+        view_number: ViewNumber::new(2),
+        leaf_commit: proposals[2].data.justify_qc().data.leaf_commit,
+        key: handle.public_key(),
+    };
+    let signature = handle.signer().sign(req.commit().as_ref()).unwrap();
+
+    let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(QuorumProposalPreliminarilyValidated(proposals[2].clone())),
+        exact(ViewChange(ViewNumber::new(3), Some(EpochNumber::new(1)))),
+        exact(QuorumProposalFetchSend(req, signature, leaders[1])),
+    ])];
+
+    let state =
+        QuorumProposalRecvTaskState::<TestTypes, MemoryImpl, EpochsTestVersions>::create_from(
+            &handle,
+        )
+        .await;
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
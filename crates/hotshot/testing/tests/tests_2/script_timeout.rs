@@ -0,0 +1,66 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_macros::{run_test, test_scripts};
+use hotshot_task::task::TaskState;
+use hotshot_task_impls::events::HotShotEvent::{self, *};
+use hotshot_testing::{
+    script::{Expectations, InputOrder, TaskScript},
+    serial,
+};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+use hotshot_utils::anytrace::Result;
+
+/// A task which never finishes handling a view change past view 1
+struct StallingTask;
+
+#[async_trait]
+impl TaskState for StallingTask {
+    type Event = HotShotEvent<TestTypes>;
+
+    fn cancel_subtasks(&mut self) {}
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        _sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        if let ViewChange(view, _) = event.as_ref() {
+            if **view > 1 {
+                futures::future::pending::<()>().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[should_panic(expected = "did not complete within 100ms")]
+async fn test_stage_timeout() {
+    hotshot::helpers::initialize_logging();
+
+    let inputs: Vec<InputOrder<TestTypes>> = vec![
+        serial![ViewChange(ViewNumber::new(1), None)],
+        serial![ViewChange(ViewNumber::new(2), None)],
+    ];
+    let expectations: Vec<Expectations<TestTypes, StallingTask>> = vec![
+        Expectations::from_outputs(vec![]),
+        Expectations::from_outputs(vec![]).with_timeout(Duration::from_millis(100)),
+    ];
+    let mut script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: StallingTask,
+        expectations,
+    };
+
+    run_test![inputs, script].await;
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot::types::EventType;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation, cluster::TestCluster, test_builder::TestDescription,
+};
+use hotshot_types::data::Leaf2;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cluster_restart_node() {
+    hotshot::helpers::initialize_logging();
+
+    let mut description = TestDescription::<TestTypes, MemoryImpl, TestVersions>::default();
+    description.test_config.epoch_height = 0;
+    let mut cluster = TestCluster::start::<SimpleBuilderImplementation>(description).await;
+    let mut events = cluster.events(2);
+
+    // Every node decides.
+    let leaves = cluster.wait_for_decide(3).await;
+    assert_eq!(leaves.len(), cluster.num_nodes());
+    let min_height = leaves.iter().map(Leaf2::height).min().unwrap();
+    let leaves = cluster.wait_for_decide(min_height + 1).await;
+    assert!(leaves.iter().all(|leaf| leaf.height() > min_height));
+
+    // The rest of the cluster keeps deciding while a node is down.
+    cluster.stop_node(2).await;
+    assert!(!cluster.is_running(2));
+    let height = cluster.handle(0).decided_leaf().await.height();
+    assert_eq!(
+        cluster.wait_for_decide(height + 3).await.len(),
+        cluster.num_nodes() - 1
+    );
+
+    // A restarted node catches up, and streams taken before the restart follow it. Decides from
+    // before the node stopped may still be buffered in the stream, so only count later views.
+    let restart_view = cluster.handle(0).cur_view().await;
+    cluster.restart_node(2).await;
+    loop {
+        let event = events.next().await.expect("event stream ended");
+        if matches!(event.event, EventType::Decide { .. }) && event.view_number > restart_view {
+            break;
+        }
+    }
+    let height = cluster.handle(0).decided_leaf().await.height();
+    cluster.wait_for_node_decide(2, height).await;
+
+    cluster.shut_down().await;
+}
@@ -0,0 +1,92 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use hotshot::{tasks::task_state::CreateTaskState, HotShotError};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{events::HotShotEvent, transactions::TransactionTaskState};
+use hotshot_testing::{
+    helpers::build_system_handle_from_launcher, test_builder::TestDescription,
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    data::ViewNumber, traits::node_implementation::ConsensusTime,
+    transaction_dedup::TransactionDedupConfig,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_dedup_follows_decides() {
+    hotshot::helpers::initialize_logging();
+
+    let launcher =
+        TestDescription::<TestTypes, MemoryImpl, TestVersions>::default_multiple_rounds()
+            .gen_launcher()
+            .map_hotshot_config(|config| {
+                config.epoch_height = 0;
+                config.transaction_dedup = Some(TransactionDedupConfig::default());
+            });
+    let (handle, sender, _receiver, node_key_map) =
+        build_system_handle_from_launcher(2, &launcher).await;
+    let mut state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+
+    let tx = TestTransaction::new(vec![1]);
+    let mut generator = TestViewGenerator::<TestVersions>::generate(
+        handle.hotshot.membership_coordinator.clone(),
+        node_key_map,
+    );
+    generator.next().await.unwrap();
+    generator.add_transactions(vec![tx.clone()]);
+    let decided = generator.next().await.unwrap();
+
+    // A transaction received from the network is rejected when it is submitted again.
+    state
+        .handle(
+            Arc::new(HotShotEvent::TransactionsRecv(vec![tx.clone()])),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        handle.submit_transaction(tx.clone()).await,
+        Err(HotShotError::DuplicateTransaction(_))
+    ));
+
+    // Once a view it may have been proposed in fails, it is accepted again.
+    state
+        .handle(
+            Arc::new(HotShotEvent::Timeout(ViewNumber::new(1), None)),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    handle.submit_transaction(tx.clone()).await.unwrap();
+
+    // Once it is decided, it stays rejected even when a later view fails.
+    state
+        .handle(
+            Arc::new(HotShotEvent::LeavesDecided(vec![decided.leaf.clone()])),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    state
+        .handle(
+            Arc::new(HotShotEvent::Timeout(decided.view_number + 1, None)),
+            sender.clone(),
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        handle.submit_transaction(tx).await,
+        Err(HotShotError::DuplicateTransaction(_))
+    ));
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use alloy::primitives::U256;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::{
+    block_types::{TestMetadata, TestTransaction},
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{events::HotShotEvent::*, vid::VidTaskState};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    data::{null_block, vid_disperse::vid_share_custodians, PackedBundle, ViewNumber},
+    traits::node_implementation::{ConsensusTime, Versions},
+    ValidatorConfig,
+};
+use vbs::version::StaticVersionType;
+use vec1::vec1;
+
+#[test]
+fn test_vid_share_custodians() {
+    // Nine staked nodes, and one without stake which can never be a custodian
+    let validators: Vec<_> = (0..10)
+        .map(|i| {
+            let stake = if i == 9 { 0 } else { 1 };
+            ValidatorConfig::<TestTypes>::generated_from_seed_indexed(
+                [0u8; 32],
+                i,
+                U256::from(stake),
+                false,
+            )
+        })
+        .collect();
+    let stake_table: Vec<_> = validators
+        .iter()
+        .map(ValidatorConfig::public_config)
+        .collect();
+    let recipient = &validators[0].public_key;
+    let view = ViewNumber::new(7);
+
+    let custodians = vid_share_custodians(&stake_table, view, recipient, 3);
+    assert_eq!(custodians.len(), 3);
+    assert!(!custodians.contains(recipient));
+    assert!(!custodians.contains(&validators[9].public_key));
+
+    // The leader and the recipient agree on the custodians
+    assert_eq!(
+        vid_share_custodians(&stake_table, view, recipient, 3),
+        custodians
+    );
+
+    // There cannot be more custodians than other staked nodes
+    assert_eq!(
+        vid_share_custodians(&stake_table, view, recipient, 20).len(),
+        8
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_vid_task_cancels_stale_disperse() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 2 is the leader of view 2.
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let encoded_transactions: Arc<[u8]> = Arc::from(TestTransaction::encode(&transactions));
+    let (sender, _receiver) = async_broadcast::broadcast(10);
+
+    let mut vid_state =
+        VidTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    vid_state
+        .handle(
+            Arc::new(ViewChange(ViewNumber::new(2), None)),
+            sender.clone(),
+        )
+        .await;
+    vid_state
+        .handle(
+            Arc::new(BlockRecv(PackedBundle::new(
+                encoded_transactions,
+                TestMetadata {
+                    num_transactions: transactions.len() as u64,
+                },
+                ViewNumber::new(2),
+                None,
+                vec1![null_block::builder_fee::<TestTypes, TestVersions>(
+                    <TestVersions as Versions>::Base::VERSION,
+                    *ViewNumber::new(2),
+                )
+                .unwrap()],
+                None,
+            ))),
+            sender.clone(),
+        )
+        .await;
+    assert!(vid_state.vid_disperse_tasks.contains(&ViewNumber::new(2)));
+
+    // Moving past the view cancels its dispersal calculation.
+    vid_state
+        .handle(Arc::new(ViewChange(ViewNumber::new(3), None)), sender)
+        .await;
+    assert!(vid_state.vid_disperse_tasks.is_empty());
+}
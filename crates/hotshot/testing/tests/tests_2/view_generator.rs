@@ -0,0 +1,145 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{EpochsTestVersions, MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_testing::{
+    helpers::{build_system_handle, key_pair_for_id},
+    view_generator::{ScriptedView, TestViewGenerator},
+};
+use hotshot_types::{
+    data::{EpochNumber, ViewChangeEvidence2},
+    drb::INITIAL_DRB_RESULT,
+    traits::{election::Membership, node_implementation::ConsensusTime},
+    utils::{epoch_from_block_number, is_epoch_root, is_epoch_transition},
+    vote::HasViewNumber,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_generator_script() {
+    use ScriptedView::*;
+
+    hotshot::helpers::initialize_logging();
+
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2).await;
+    let mut generator = TestViewGenerator::<TestVersions>::generate(
+        handle.hotshot.membership_coordinator.clone(),
+        node_key_map,
+    );
+
+    let mut views = generator
+        .generate_script(&[Success, Failure, Failure, Success, NoDaCertificate])
+        .await;
+    generator.add_transactions(vec![TestTransaction::new(vec![1])]);
+    views.push(generator.next_scripted(EmptyBlock).await);
+    // Unscripted views succeed.
+    generator.add_transactions(vec![TestTransaction::new(vec![1])]);
+    views.extend((&mut generator).take(1).collect::<Vec<_>>().await);
+
+    let view_numbers = views
+        .iter()
+        .map(|view| *view.view_number)
+        .collect::<Vec<_>>();
+    assert_eq!(view_numbers, [1, 2, 3, 4, 5, 6, 7]);
+    let certified = views
+        .iter()
+        .map(|view| view.is_certified())
+        .collect::<Vec<_>>();
+    assert_eq!(certified, [true, false, false, true, true, true, true]);
+
+    // Failed views, and the view after them, extend the last view which succeeded. Each one after
+    // the first carries a timeout certificate for the view before.
+    for (i, view) in views[1..4].iter().enumerate() {
+        let proposal = &view.quorum_proposal.data;
+        assert_eq!(proposal.justify_qc().view_number(), views[0].view_number);
+        match proposal.view_change_evidence() {
+            Some(ViewChangeEvidence2::Timeout(tc)) => {
+                assert!(i > 0);
+                assert_eq!(tc.data.view, view.view_number - 1);
+            },
+            Some(ViewChangeEvidence2::ViewSync(_)) => panic!("unexpected view sync certificate"),
+            None => assert_eq!(i, 0),
+        }
+    }
+    for (parent, view) in views[3..].iter().zip(&views[4..]) {
+        let proposal = &view.quorum_proposal.data;
+        assert_eq!(proposal.justify_qc().view_number(), parent.view_number);
+        assert!(proposal.view_change_evidence().is_none());
+    }
+
+    // Views without a DA certificate are otherwise ordinary.
+    assert!(views[4].da_certificate.is_none());
+    assert_eq!(
+        views
+            .iter()
+            .filter(|view| view.da_certificate.is_some())
+            .count(),
+        6
+    );
+
+    // Empty blocks drop the transactions added for them.
+    assert!(views[5].da_proposal.data.encoded_transactions.is_empty());
+    assert!(!views[6].da_proposal.data.encoded_transactions.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_generator_crosses_epoch_boundary() {
+    hotshot::helpers::initialize_logging();
+
+    let epoch_height = 10;
+    let (handle, _, _, node_key_map) =
+        build_system_handle::<TestTypes, MemoryImpl, EpochsTestVersions>(2).await;
+    let membership = handle.hotshot.membership_coordinator.clone();
+    membership
+        .membership()
+        .write()
+        .await
+        .set_first_epoch(EpochNumber::new(1), INITIAL_DRB_RESULT);
+
+    let mut generator = TestViewGenerator::<EpochsTestVersions>::generate_with_epochs(
+        membership,
+        node_key_map,
+        epoch_height,
+    );
+    // Only the first five nodes have stake from the second epoch onwards
+    let new_stake_table = [0, 1, 2, 3, 4];
+    generator
+        .change_stake_table(EpochNumber::new(2), &new_stake_table, &[0, 1, 2])
+        .await;
+    let new_leaders: Vec<_> = new_stake_table
+        .iter()
+        .map(|node_id| key_pair_for_id::<TestTypes>(*node_id).1)
+        .collect();
+
+    let views = (&mut generator).take(13).collect::<Vec<_>>().await;
+    for (parent, view) in views.iter().zip(views.iter().skip(1)) {
+        let block_number = view.leaf.height();
+        let parent_block_number = parent.leaf.height();
+        let proposal = &view.quorum_proposal.data;
+
+        let epoch = EpochNumber::new(epoch_from_block_number(block_number, epoch_height));
+        assert_eq!(view.epoch_number, Some(epoch));
+        assert_eq!(proposal.proposal.epoch, Some(epoch));
+        assert_eq!(proposal.justify_qc().data.epoch, parent.epoch_number);
+
+        assert_eq!(
+            proposal.next_epoch_justify_qc().is_some(),
+            is_epoch_transition(parent_block_number, epoch_height)
+        );
+        assert_eq!(
+            proposal.state_cert().is_some(),
+            is_epoch_root(parent_block_number, epoch_height)
+        );
+
+        if *epoch > 1 {
+            assert!(new_leaders.contains(&view.leader_public_key));
+        }
+    }
+}
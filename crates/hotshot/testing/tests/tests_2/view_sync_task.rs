@@ -0,0 +1,164 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use committable::Committable;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    harness::run_harness,
+    view_sync::{ViewSyncRoundTimeout, ViewSyncTaskState},
+};
+use hotshot_testing::helpers::{build_cert, build_system_handle, key_pair_for_id};
+use hotshot_types::{
+    data::{ViewChangeEvidence2, ViewNumber},
+    request_response::ViewEvidenceRequestPayload,
+    simple_certificate::TimeoutCertificate2,
+    simple_vote::{TimeoutData2, TimeoutVote2, ViewSyncPreCommitData2, ViewSyncPreCommitVote2},
+    traits::{consensus_api::ConsensusApi, node_implementation::ConsensusTime},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_task_catches_up_with_peer_evidence() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(5)
+        .await
+        .0;
+    let membership = handle
+        .hotshot
+        .membership_coordinator
+        .membership_for_epoch(None)
+        .await
+        .unwrap();
+
+    // The network timed out in view 6 while we were partitioned.
+    let timeout_cert = build_cert::<
+        TestTypes,
+        TestVersions,
+        TimeoutData2<TestTypes>,
+        TimeoutVote2<TestTypes>,
+        TimeoutCertificate2<TestTypes>,
+    >(
+        TimeoutData2 {
+            view: ViewNumber::new(6),
+            epoch: None,
+        },
+        &membership,
+        ViewNumber::new(7),
+        &handle.public_key(),
+        &key_pair_for_id::<TestTypes>(5).0,
+        &handle.hotshot.upgrade_lock,
+    )
+    .await;
+    let evidence = ViewChangeEvidence2::Timeout(timeout_cert);
+
+    // Another node stuck in view 2 asks for evidence.
+    let request = ViewEvidenceRequestPayload {
+        view_number: ViewNumber::new(2),
+        key: handle.public_key(),
+    };
+    let signature = handle.signer().sign(request.commit().as_ref()).unwrap();
+
+    let input = vec![
+        HotShotEvent::ViewEvidenceResponseRecv(evidence.clone()),
+        HotShotEvent::ViewEvidenceRequestRecv(request, signature),
+        HotShotEvent::Shutdown,
+    ];
+    let output = vec![
+        HotShotEvent::ViewChange(ViewNumber::new(7), None),
+        HotShotEvent::ViewEvidenceResponseSend(handle.public_key(), handle.public_key(), evidence),
+    ];
+
+    let view_sync_state = ViewSyncTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    run_harness(input, output, view_sync_state, false).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_view_sync_task_requests_evidence_once_per_view() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(5)
+        .await
+        .0;
+
+    let vote = ViewSyncPreCommitVote2::<TestTypes>::create_signed_vote(
+        ViewSyncPreCommitData2 {
+            relay: 0,
+            round: ViewNumber::new(4),
+            epoch: None,
+        },
+        ViewNumber::new(4),
+        &handle.public_key(),
+        handle.signer(),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .expect("Failed to create a ViewSyncPreCommitVote!");
+
+    let input = vec![
+        HotShotEvent::Timeout(ViewNumber::new(2), None),
+        HotShotEvent::Timeout(ViewNumber::new(3), None),
+        // Timing out again in the same view restarts view sync, but asks for no more evidence.
+        HotShotEvent::Timeout(ViewNumber::new(3), None),
+        HotShotEvent::Shutdown,
+    ];
+    let mut output = Vec::new();
+    for view in [2, 3] {
+        let request = ViewEvidenceRequestPayload {
+            view_number: ViewNumber::new(view),
+            key: handle.public_key(),
+        };
+        let signature = handle.signer().sign(request.commit().as_ref()).unwrap();
+        output.push(HotShotEvent::ViewEvidenceRequestSend(request, signature));
+    }
+    output.push(HotShotEvent::ViewChange(ViewNumber::new(3), None));
+    output.push(HotShotEvent::ViewSyncPreCommitVoteSend(vote.clone()));
+    output.push(HotShotEvent::ViewSyncPreCommitVoteSend(vote));
+
+    let view_sync_state = ViewSyncTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    run_harness(input, output, view_sync_state, false).await;
+}
+
+#[test]
+fn test_view_sync_round_timeout() {
+    let max = Duration::from_secs(2);
+    let timeout = ViewSyncRoundTimeout::new(max);
+    // Without observations, the configured timeout is used.
+    assert_eq!(timeout.current(), max);
+
+    // A fast network shortens the timeout, down to the floor.
+    assert_eq!(
+        timeout.observe(Duration::from_millis(100)),
+        Duration::from_millis(400)
+    );
+    assert_eq!(
+        timeout.observe(Duration::from_millis(20)),
+        Duration::from_millis(320)
+    );
+    for _ in 0..20 {
+        timeout.observe(Duration::from_millis(1));
+    }
+    assert_eq!(timeout.current(), Duration::from_millis(250));
+
+    // Clones share the estimate, and slow phases lengthen the timeout up to the configured one.
+    let clone = timeout.clone();
+    for _ in 0..20 {
+        clone.observe(Duration::from_secs(1));
+    }
+    assert_eq!(timeout.current(), max);
+
+    // A round which times out resets the estimate to the configured timeout, however fast the
+    // rounds before it were.
+    for _ in 0..20 {
+        timeout.observe(Duration::from_millis(1));
+    }
+    assert_eq!(timeout.observe_timeout(), max);
+    // A single fast round after it is not enough to shorten the timeout again.
+    assert_eq!(timeout.observe(Duration::from_millis(100)), max);
+}
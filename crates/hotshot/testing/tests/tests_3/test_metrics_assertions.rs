@@ -0,0 +1,71 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    metrics::{MetricsAssertions, RecordedMetrics},
+    test_builder::TestDescription,
+};
+use hotshot_types::consensus::ConsensusMetricsValue;
+
+#[test]
+fn test_metrics_assertions_report_failures() {
+    let metrics = RecordedMetrics::default();
+    let consensus_metrics = ConsensusMetricsValue::new(&metrics);
+
+    consensus_metrics.number_of_timeouts.add(3);
+    // The gauge is reset after a decide, but the peak is kept
+    consensus_metrics.invalid_qc.update(1);
+    consensus_metrics.invalid_qc.update(-1);
+
+    assert_eq!(metrics.counter("number_of_timeouts"), 3);
+    assert_eq!(metrics.gauge("invalid_qc"), 0);
+    assert_eq!(metrics.gauge_peak("invalid_qc"), 1);
+
+    let assertions = MetricsAssertions::new()
+        .timeouts_at_most(3)
+        .empty_blocks_proposed_at_most(0);
+    assert!(assertions.check(0, &metrics).is_empty());
+
+    let assertions = MetricsAssertions::new()
+        .timeouts_at_most(2)
+        .no_invalid_qcs();
+    assert_eq!(assertions.check(0, &metrics).len(), 2);
+}
+
+cross_tests!(
+    TestName: test_success_with_metrics_assertions,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            metrics_assertions: MetricsAssertions::new()
+                .no_invalid_qcs()
+                .timeouts_at_most(2)
+                .custom("every node decided", |metrics| {
+                    metrics.gauge("last_decided_view") > 0
+                }),
+            ..TestDescription::default()
+        };
+
+        metadata.test_config.epoch_height = 0;
+
+        metadata
+    },
+);
@@ -13,6 +13,7 @@ use hotshot_testing::{
     test_builder::{BuilderChange, BuilderDescription, TestDescription},
     txn_task::TxnTaskDescription,
 };
+use hotshot_types::local_builder::LocalBuilderConfig;
 
 // Test one node leaving the network.
 cross_tests!(
@@ -76,3 +77,26 @@ cross_tests!(
         metadata
     }
 );
+
+// Test that blocks keep including transactions while every builder is down, when nodes run the
+// embedded fallback builder.
+cross_tests!(
+    TestName: test_with_local_builder,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription::default_multiple_rounds();
+        metadata.test_config.epoch_height = 0;
+        metadata.test_config.local_builder = Some(LocalBuilderConfig::default());
+        metadata.overall_safety_properties.transaction_threshold = 1;
+        metadata.txn_description = TxnTaskDescription::RoundRobinTimeBased(Duration::from_millis(1));
+
+        // The only builder is down for the whole test.
+        metadata.builders = vec1::vec1![BuilderDescription {
+            changes: [(0, BuilderChange::Down)].into_iter().collect(),
+        }];
+        metadata
+    }
+);
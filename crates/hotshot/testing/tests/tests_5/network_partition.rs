@@ -0,0 +1,105 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestVersions},
+    state_types::TestTypes,
+};
+use hotshot_macros::cross_tests;
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    partition::{CrossPartitionPolicy, NetworkPartition},
+    test_builder::TestDescription,
+};
+
+// Cut two of seven nodes off from the rest, then heal the network.
+cross_tests!(
+    TestName: test_partition_minority_heals,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(120),
+                },
+            ),
+            network_partitions: vec![NetworkPartition {
+                groups: vec![vec![0, 1, 2, 3, 4], vec![5, 6]],
+                views: 5..15,
+                policy: CrossPartitionPolicy::Drop,
+                recovery_views: 5,
+            }],
+            ..TestDescription::default()
+        };
+        metadata.test_config.epoch_height = 0;
+        metadata.overall_safety_properties.num_successful_views = 25;
+        metadata.overall_safety_properties.decide_timeout = Duration::from_secs(20);
+        metadata
+    }
+);
+
+// Split the network so that no group holds a quorum, while slowing down messages between groups.
+cross_tests!(
+    TestName: test_partition_without_quorum_delay_heals,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(120),
+                },
+            ),
+            network_partitions: vec![NetworkPartition {
+                groups: vec![vec![0, 1, 2, 3], vec![4, 5, 6]],
+                views: 5..10,
+                policy: CrossPartitionPolicy::Delay(Duration::from_millis(1500)),
+                recovery_views: 5,
+            }],
+            ..TestDescription::default()
+        };
+        metadata.test_config.epoch_height = 0;
+        metadata.overall_safety_properties.num_successful_views = 20;
+        metadata.overall_safety_properties.decide_timeout = Duration::from_secs(20);
+        metadata
+    }
+);
+
+// Deliver every message between groups twice.
+cross_tests!(
+    TestName: test_partition_duplicate_messages,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let mut metadata = TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(120),
+                },
+            ),
+            network_partitions: vec![NetworkPartition {
+                groups: vec![vec![0, 1, 2], vec![3, 4, 5, 6]],
+                views: 5..15,
+                policy: CrossPartitionPolicy::Duplicate(2),
+                recovery_views: 0,
+            }],
+            ..TestDescription::default()
+        };
+        metadata.test_config.epoch_height = 0;
+        metadata.overall_safety_properties.num_successful_views = 20;
+        metadata
+    }
+);
@@ -0,0 +1,564 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! API keys for the public interfaces of a node: the events API and the builder.
+//!
+//! Operators exposing these interfaces publicly can require clients to authenticate, either by
+//! presenting an API key as `Authorization: Bearer KEY`, or by signing each request with the
+//! Ethereum key of a configured address as `Authorization: Signature ADDRESS TIMESTAMP SIGNATURE`
+//! (see [`ApiCredentials`]). Each key is granted a set of [`Scope`]s and may be limited to a number
+//! of requests per minute. API keys are configured by their SHA-256 hash, so the configuration
+//! does not contain usable keys, and presented keys are looked up by hash, so response timings
+//! reveal nothing about the configured keys.
+//!
+//! Keys are configured in a TOML file like
+//!
+//! ```toml
+//! [[keys]]
+//! name = "searcher"
+//! # echo -n KEY | sha256sum
+//! key_hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+//! scopes = ["read-events", "submit-bundles"]
+//! requests_per_minute = 600
+//!
+//! [[keys]]
+//! name = "builder"
+//! address = "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f"
+//! scopes = ["read-events"]
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Display},
+    fs,
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    hex,
+    primitives::{Address, PrimitiveSignature},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// What a key allows its holder to do
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Subscribe to the events API
+    ReadEvents,
+    /// Submit transactions and bundles to the builder
+    SubmitBundles,
+    /// Submit transactions to a sequencer node
+    SubmitTransactions,
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReadEvents => write!(f, "read events"),
+            Self::SubmitBundles => write!(f, "submit bundles"),
+            Self::SubmitTransactions => write!(f, "submit transactions"),
+        }
+    }
+}
+
+impl Scope {
+    /// Name of the scope in the configuration
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ReadEvents => "read-events",
+            Self::SubmitBundles => "submit-bundles",
+            Self::SubmitTransactions => "submit-transactions",
+        }
+    }
+}
+
+/// How far the timestamp of a signed request may be from the time it is received
+///
+/// A signature can be replayed within this window, but only for the scope it was made for.
+pub const MAX_SIGNATURE_AGE: Duration = Duration::from_secs(60);
+
+/// The message signed to authorize a request for `scope` at `timestamp` (in seconds since the Unix
+/// epoch)
+fn signed_message(scope: Scope, timestamp: u64) -> String {
+    format!("espresso-api-auth:{}:{timestamp}", scope.name())
+}
+
+/// Configuration of a single API key
+///
+/// Exactly one of `key_hash` and `address` must be set.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Name of the key, used in logs
+    pub name: String,
+    /// Hex-encoded SHA-256 hash of a bearer key
+    #[serde(default)]
+    pub key_hash: Option<String>,
+    /// Ethereum address whose holder authenticates by signing each request
+    #[serde(default)]
+    pub address: Option<Address>,
+    /// What the key allows
+    pub scopes: Vec<Scope>,
+    /// Most requests allowed with this key per minute, unlimited if not set
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+}
+
+/// Configuration of all API keys
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiKeysConfig {
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+/// Reasons a request is refused
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum AuthError {
+    /// The request did not present an API key
+    #[error("missing API key")]
+    Missing,
+    /// The request presented a key which is not configured
+    #[error("invalid API key")]
+    Invalid,
+    /// The request presented a malformed or stale signature
+    #[error("invalid signature: {0}")]
+    InvalidSignature(String),
+    /// The key does not have the scope required for the request
+    #[error("API key is not allowed to {0}")]
+    Forbidden(Scope),
+    /// The key has used up its requests for now
+    #[error("API key rate limit exceeded, retry in {}s", .retry_after.as_secs().max(1))]
+    RateLimited { retry_after: Duration },
+}
+
+/// Rate limit of a key, as a token bucket holding up to a minute's worth of requests
+#[derive(Debug)]
+struct RateLimiter {
+    per_minute: u32,
+    /// Requests which can be made without waiting, as of `updated`
+    tokens: f64,
+    /// When `tokens` was last refilled
+    updated: Instant,
+}
+
+impl RateLimiter {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        Self {
+            per_minute,
+            tokens: per_minute as f64,
+            updated: now,
+        }
+    }
+
+    /// Take a request out of the bucket, or say how long until one is available.
+    fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let rate = self.per_minute.max(1) as f64 / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.per_minute as f64);
+        self.updated = now;
+
+        if self.tokens < 1.0 {
+            return Err(Duration::from_secs_f64((1.0 - self.tokens) / rate));
+        }
+        self.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ApiKey {
+    name: String,
+    scopes: Vec<Scope>,
+    limiter: Option<Mutex<RateLimiter>>,
+}
+
+/// The API keys accepted by an interface, and their rate limits
+#[derive(Debug)]
+pub struct ApiKeys {
+    /// Bearer keys by their SHA-256 hash
+    keys: HashMap<[u8; 32], ApiKey>,
+    /// Keys authenticated by signature, by address
+    signers: HashMap<Address, ApiKey>,
+}
+
+impl ApiKeys {
+    /// Accept the keys in `config`.
+    ///
+    /// # Errors
+    /// If a key hash is malformed, a key has neither or both of a hash and an address, or a key is
+    /// configured twice.
+    pub fn new(config: ApiKeysConfig) -> anyhow::Result<Self> {
+        let now = Instant::now();
+        let mut keys = HashMap::new();
+        let mut signers = HashMap::new();
+        for key in config.keys {
+            let name = key.name.clone();
+            let api_key = ApiKey {
+                name: key.name,
+                scopes: key.scopes,
+                limiter: key
+                    .requests_per_minute
+                    .map(|limit| Mutex::new(RateLimiter::new(limit, now))),
+            };
+            let prev = match (key.key_hash, key.address) {
+                (Some(hash), None) => {
+                    let hash = hex::decode(&hash)
+                        .ok()
+                        .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                        .with_context(|| format!("key hash of {name} is not a hex SHA-256 hash"))?;
+                    keys.insert(hash, api_key)
+                },
+                (None, Some(address)) => signers.insert(address, api_key),
+                _ => bail!("key {name} must have exactly one of key_hash and address"),
+            };
+            ensure!(prev.is_none(), "key {name} is configured more than once");
+        }
+        Ok(Self { keys, signers })
+    }
+
+    /// Read the key configuration file at `path`.
+    ///
+    /// # Errors
+    /// If the file cannot be read or parsed, or the configuration is invalid.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let toml = fs::read_to_string(path)
+            .with_context(|| format!("reading API keys {}", path.display()))?;
+        let config = toml::from_str(&toml)
+            .with_context(|| format!("parsing API keys {}", path.display()))?;
+        Self::new(config)
+    }
+
+    /// Check that a request with the `Authorization` header `authorization` may do `scope`.
+    ///
+    /// Counts the request against the rate limit of its key, and returns the name of the key.
+    ///
+    /// # Errors
+    /// If the request does not present a configured key with the scope, or the key is over its
+    /// rate limit.
+    pub fn authorize(&self, authorization: Option<&str>, scope: Scope) -> Result<&str, AuthError> {
+        self.authorize_at(authorization, scope, SystemTime::now())
+    }
+
+    fn authorize_at(
+        &self,
+        authorization: Option<&str>,
+        scope: Scope,
+        now: SystemTime,
+    ) -> Result<&str, AuthError> {
+        let authorization = authorization.ok_or(AuthError::Missing)?;
+        let key = if let Some(key) = authorization.strip_prefix("Bearer ") {
+            self.keys
+                .get(&<[u8; 32]>::from(Sha256::digest(key.trim())))
+                .ok_or(AuthError::Invalid)?
+        } else if let Some(signature) = authorization.strip_prefix("Signature ") {
+            let address = verify_signature(signature, scope, now)?;
+            self.signers.get(&address).ok_or(AuthError::Invalid)?
+        } else {
+            return Err(AuthError::Missing);
+        };
+        if !key.scopes.contains(&scope) {
+            return Err(AuthError::Forbidden(scope));
+        }
+        if let Some(limiter) = &key.limiter {
+            limiter
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take(Instant::now())
+                .map_err(|retry_after| AuthError::RateLimited { retry_after })?;
+        }
+        Ok(&key.name)
+    }
+}
+
+/// The address which signed a request with the `Authorization` header `authorization` for `scope`.
+///
+/// This authenticates the signer without checking it against configured keys, for interfaces which
+/// decide what each address may do themselves.
+///
+/// # Errors
+/// If the header is not a valid, current `Signature` for `scope`.
+pub fn request_signer(authorization: &str, scope: Scope) -> Result<Address, AuthError> {
+    let signature = authorization
+        .strip_prefix("Signature ")
+        .ok_or(AuthError::Missing)?;
+    verify_signature(signature, scope, SystemTime::now())
+}
+
+/// Check a signature presented as `ADDRESS TIMESTAMP SIGNATURE` for `scope`, and return the
+/// address which signed it.
+fn verify_signature(signature: &str, scope: Scope, now: SystemTime) -> Result<Address, AuthError> {
+    let invalid = |reason: &str| AuthError::InvalidSignature(reason.into());
+    let [address, timestamp, signature] = signature
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| invalid("expected ADDRESS TIMESTAMP SIGNATURE"))?;
+    let address: Address = address.parse().map_err(|_| invalid("malformed address"))?;
+    let timestamp: u64 = timestamp
+        .parse()
+        .map_err(|_| invalid("malformed timestamp"))?;
+    let signature = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| PrimitiveSignature::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| invalid("malformed signature"))?;
+
+    let signed_at = UNIX_EPOCH + Duration::from_secs(timestamp);
+    let age = now
+        .duration_since(signed_at)
+        .unwrap_or_else(|err| err.duration());
+    if age > MAX_SIGNATURE_AGE {
+        return Err(invalid("timestamp is too far from the current time"));
+    }
+
+    let signer = signature
+        .recover_address_from_msg(signed_message(scope, timestamp))
+        .map_err(|_| invalid("cannot recover signer"))?;
+    if signer != address {
+        return Err(invalid("signed by a different address"));
+    }
+    Ok(address)
+}
+
+/// Credentials a client presents to an interface which requires API keys
+#[derive(Clone)]
+pub enum ApiCredentials {
+    /// Present a bearer key
+    Key(String),
+    /// Sign each request with the key of a configured address
+    Signer(PrivateKeySigner),
+}
+
+impl Debug for ApiCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(_) => f.debug_tuple("Key").finish_non_exhaustive(),
+            Self::Signer(signer) => f.debug_tuple("Signer").field(&signer.address()).finish(),
+        }
+    }
+}
+
+impl ApiCredentials {
+    /// The `Authorization` header for a request which does `scope`.
+    ///
+    /// Signatures are only valid for [`MAX_SIGNATURE_AGE`], so this must be called for each
+    /// request rather than once per client.
+    ///
+    /// # Errors
+    /// If the request cannot be signed.
+    pub fn authorization(&self, scope: Scope) -> anyhow::Result<String> {
+        self.authorization_at(scope, SystemTime::now())
+    }
+
+    fn authorization_at(&self, scope: Scope, now: SystemTime) -> anyhow::Result<String> {
+        match self {
+            Self::Key(key) => Ok(format!("Bearer {key}")),
+            Self::Signer(signer) => {
+                let timestamp = now.duration_since(UNIX_EPOCH)?.as_secs();
+                let signature = signer
+                    .sign_message_sync(signed_message(scope, timestamp).as_bytes())
+                    .context("signing API request")?;
+                Ok(format!(
+                    "Signature {} {timestamp} {}",
+                    signer.address(),
+                    hex::encode_prefixed(signature.as_bytes())
+                ))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key_hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key))
+    }
+
+    #[test]
+    fn test_authorize() {
+        let keys = ApiKeys::new(ApiKeysConfig {
+            keys: vec![
+                ApiKeyConfig {
+                    name: "reader".into(),
+                    key_hash: Some(key_hash("read-key")),
+                    address: None,
+                    scopes: vec![Scope::ReadEvents],
+                    requests_per_minute: Some(2),
+                },
+                ApiKeyConfig {
+                    name: "searcher".into(),
+                    key_hash: Some(key_hash("search-key")),
+                    address: None,
+                    scopes: vec![Scope::ReadEvents, Scope::SubmitBundles],
+                    requests_per_minute: None,
+                },
+            ],
+        })
+        .unwrap();
+
+        assert_eq!(
+            keys.authorize(None, Scope::ReadEvents),
+            Err(AuthError::Missing)
+        );
+        assert_eq!(
+            keys.authorize(Some("read-key"), Scope::ReadEvents),
+            Err(AuthError::Missing)
+        );
+        assert_eq!(
+            keys.authorize(Some("Bearer wrong-key"), Scope::ReadEvents),
+            Err(AuthError::Invalid)
+        );
+        assert_eq!(
+            keys.authorize(Some("Bearer read-key"), Scope::SubmitBundles),
+            Err(AuthError::Forbidden(Scope::SubmitBundles))
+        );
+
+        // The reader can make two requests before hitting its limit.
+        assert_eq!(
+            keys.authorize(Some("Bearer read-key"), Scope::ReadEvents),
+            Ok("reader")
+        );
+        assert_eq!(
+            keys.authorize(Some("Bearer read-key"), Scope::ReadEvents),
+            Ok("reader")
+        );
+        let Err(AuthError::RateLimited { retry_after }) =
+            keys.authorize(Some("Bearer read-key"), Scope::ReadEvents)
+        else {
+            panic!("reader should be rate limited");
+        };
+        assert!(retry_after <= Duration::from_secs(30));
+
+        // The searcher is unlimited.
+        for _ in 0..10 {
+            assert_eq!(
+                keys.authorize(Some("Bearer search-key"), Scope::SubmitBundles),
+                Ok("searcher")
+            );
+        }
+    }
+
+    #[test]
+    fn test_config() {
+        let config: ApiKeysConfig = toml::from_str(&format!(
+            r#"
+            [[keys]]
+            name = "searcher"
+            key_hash = "{}"
+            scopes = ["read-events", "submit-bundles"]
+            requests_per_minute = 600
+            "#,
+            key_hash("key")
+        ))
+        .unwrap();
+        assert_eq!(config.keys[0].requests_per_minute, Some(600));
+        ApiKeys::new(config.clone()).unwrap();
+
+        // The same key cannot be configured twice.
+        let mut duplicate = config.clone();
+        duplicate.keys.push(config.keys[0].clone());
+        ApiKeys::new(duplicate).unwrap_err();
+
+        // Nor can a malformed hash.
+        let mut malformed = config;
+        malformed.keys[0].key_hash = Some("not a hash".into());
+        ApiKeys::new(malformed.clone()).unwrap_err();
+
+        // Nor a key with both a hash and an address.
+        let mut both = malformed;
+        both.keys[0].key_hash = Some(key_hash("key"));
+        both.keys[0].address = Some(Address::repeat_byte(1));
+        ApiKeys::new(both).unwrap_err();
+    }
+
+    #[test]
+    fn test_authorize_signature() {
+        let signer = PrivateKeySigner::from_slice(&[1; 32]).unwrap();
+        let keys = ApiKeys::new(ApiKeysConfig {
+            keys: vec![ApiKeyConfig {
+                name: "builder".into(),
+                key_hash: None,
+                address: Some(signer.address()),
+                scopes: vec![Scope::ReadEvents],
+                requests_per_minute: None,
+            }],
+        })
+        .unwrap();
+        let credentials = ApiCredentials::Signer(signer);
+        let now = SystemTime::now();
+
+        let authorization = credentials
+            .authorization_at(Scope::ReadEvents, now)
+            .unwrap();
+        assert_eq!(
+            keys.authorize_at(Some(&authorization), Scope::ReadEvents, now),
+            Ok("builder")
+        );
+
+        // The signature is only valid for a while.
+        assert!(matches!(
+            keys.authorize_at(
+                Some(&authorization),
+                Scope::ReadEvents,
+                now + MAX_SIGNATURE_AGE + Duration::from_secs(1)
+            ),
+            Err(AuthError::InvalidSignature(_))
+        ));
+
+        // A signature for one scope cannot be used for another.
+        let authorization = credentials
+            .authorization_at(Scope::SubmitBundles, now)
+            .unwrap();
+        assert!(matches!(
+            keys.authorize_at(Some(&authorization), Scope::ReadEvents, now),
+            Err(AuthError::InvalidSignature(_))
+        ));
+        assert_eq!(
+            keys.authorize_at(Some(&authorization), Scope::SubmitBundles, now),
+            Err(AuthError::Forbidden(Scope::SubmitBundles))
+        );
+
+        // Signers which are not configured are refused.
+        let other = ApiCredentials::Signer(PrivateKeySigner::from_slice(&[2; 32]).unwrap());
+        let authorization = other.authorization_at(Scope::ReadEvents, now).unwrap();
+        assert_eq!(
+            keys.authorize_at(Some(&authorization), Scope::ReadEvents, now),
+            Err(AuthError::Invalid)
+        );
+
+        // Interfaces which decide what each address may do themselves can still authenticate it.
+        let authorization = other.authorization(Scope::SubmitTransactions).unwrap();
+        let ApiCredentials::Signer(other) = other else {
+            unreachable!()
+        };
+        assert_eq!(
+            request_signer(&authorization, Scope::SubmitTransactions),
+            Ok(other.address())
+        );
+        assert!(matches!(
+            request_signer(&authorization, Scope::SubmitBundles),
+            Err(AuthError::InvalidSignature(_))
+        ));
+        assert_eq!(
+            request_signer("Bearer key", Scope::SubmitTransactions),
+            Err(AuthError::Missing)
+        );
+
+        assert_eq!(
+            ApiCredentials::Key("key".into())
+                .authorization(Scope::ReadEvents)
+                .unwrap(),
+            "Bearer key"
+        );
+    }
+}
@@ -0,0 +1,410 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Audit records of the proposals and votes a node sends and receives.
+//!
+//! Storage only keeps what consensus needs, so after an incident there is usually no record of the
+//! proposals and votes which were never decided, which are exactly the ones needed to investigate
+//! a stall or show that a node equivocated. When the application sets a [`MessageAudit`], the
+//! network tasks hand it every proposal and vote as it is sent or received, signature included, so
+//! each record can be checked independently of the node which kept it.
+//!
+//! The sender of a received message is not authenticated, so a received message is only recorded
+//! once its signature is found to be valid, and is attributed to the key which signed it: the
+//! signer of a vote, which must be in the stake table, or the leader of the view of a proposal.
+//! Otherwise any peer could forge records, or flood the log to rotate real records out of it.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use committable::Committable;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    data::{Leaf, Leaf2, QuorumProposalWrapper},
+    epoch_membership::EpochMembershipCoordinator,
+    message::{
+        DaConsensusMessage, GeneralConsensusMessage, Message, MessageKind, SequencingMessage,
+        UpgradeLock,
+    },
+    simple_vote::{HasEpoch, VersionedVoteData},
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::{HasViewNumber, Vote},
+};
+
+/// The audit log of a node, once the application sets one
+pub type MessageAuditSlot<TYPES> = Arc<OnceLock<Arc<dyn MessageAudit<TYPES>>>>;
+
+/// Whether a message was sent or received by the node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditDirection {
+    Sent,
+    Received,
+}
+
+/// What an audited message is
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditKind {
+    /// A quorum, DA or upgrade proposal, or a DA payload hint, sent by its leader
+    Proposal,
+    /// A vote of any kind, sent by its signer
+    Vote,
+}
+
+impl AuditKind {
+    /// The kind of `message`, or `None` if it is neither a proposal nor a vote.
+    ///
+    /// Proposals relayed in response to a request are not audited, since they are not sent by
+    /// their signer; the original proposal is.
+    #[must_use]
+    pub fn of<TYPES: NodeType>(message: &MessageKind<TYPES>) -> Option<Self> {
+        let MessageKind::Consensus(message) = message else {
+            return None;
+        };
+        match message {
+            SequencingMessage::General(message) => match message {
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::Proposal2(_)
+                | GeneralConsensusMessage::UpgradeProposal(_) => Some(Self::Proposal),
+                GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::Vote2(_)
+                | GeneralConsensusMessage::EpochRootQuorumVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote2(_)
+                | GeneralConsensusMessage::TimeoutVote(_)
+                | GeneralConsensusMessage::TimeoutVote2(_)
+                | GeneralConsensusMessage::UpgradeVote(_) => Some(Self::Vote),
+                _ => None,
+            },
+            SequencingMessage::Da(message) => match message {
+                DaConsensusMessage::DaProposal(_)
+                | DaConsensusMessage::DaProposal2(_)
+                | DaConsensusMessage::DaPayloadHint2(_) => Some(Self::Proposal),
+                DaConsensusMessage::DaVote(_) | DaConsensusMessage::DaVote2(_) => Some(Self::Vote),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A proposal or vote sent or received by the node
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct AuditRecord<TYPES: NodeType> {
+    /// When the message was sent or received, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub direction: AuditDirection,
+    pub kind: AuditKind,
+    /// The view of the message
+    pub view: u64,
+    /// The node which signed the message
+    pub signer: TYPES::SignatureKey,
+    /// The message as sent
+    pub message: Message<TYPES>,
+}
+
+impl<TYPES: NodeType> AuditRecord<TYPES> {
+    /// Record `message`, signed by `signer`, as of now, or `None` if it is neither a proposal nor
+    /// a vote.
+    #[must_use]
+    pub fn new(
+        direction: AuditDirection,
+        signer: TYPES::SignatureKey,
+        message: &Message<TYPES>,
+    ) -> Option<Self> {
+        let kind = AuditKind::of(&message.kind)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| u64::try_from(now.as_millis()).unwrap_or(u64::MAX));
+        Some(Self {
+            timestamp,
+            direction,
+            kind,
+            view: *message.kind.view_number(),
+            signer,
+            message: message.clone(),
+        })
+    }
+}
+
+/// Which audit records to retrieve
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct AuditFilter<TYPES: NodeType> {
+    /// First view to retrieve records of
+    pub from_view: u64,
+    /// View to retrieve records until, exclusive
+    pub until_view: u64,
+    /// Only retrieve the messages of this signer
+    pub signer: Option<TYPES::SignatureKey>,
+    /// Most records to retrieve
+    pub limit: usize,
+}
+
+impl<TYPES: NodeType> AuditFilter<TYPES> {
+    /// Whether `record` is selected by this filter, not counting the limit
+    #[must_use]
+    pub fn matches(&self, record: &AuditRecord<TYPES>) -> bool {
+        (self.from_view..self.until_view).contains(&record.view)
+            && self
+                .signer
+                .as_ref()
+                .is_none_or(|signer| *signer == record.signer)
+    }
+}
+
+/// An append-only log of the proposals and votes a node sends and receives
+#[async_trait]
+pub trait MessageAudit<TYPES: NodeType>: Send + Sync + 'static {
+    /// Append `record` to the log.
+    ///
+    /// Called from the network tasks for every proposal and vote, so it must not block; records
+    /// should be queued and written in the background.
+    fn record(&self, record: AuditRecord<TYPES>);
+
+    /// The records selected by `filter`, oldest first.
+    async fn records(&self, filter: &AuditFilter<TYPES>)
+        -> anyhow::Result<Vec<AuditRecord<TYPES>>>;
+}
+
+/// Hand `message`, sent by this node, to the audit log in `audit`, if one is set and the message
+/// is audited.
+pub fn audit_sent<TYPES: NodeType>(audit: &MessageAuditSlot<TYPES>, message: &Message<TYPES>) {
+    if let Some(audit) = audit.get() {
+        if let Some(record) =
+            AuditRecord::new(AuditDirection::Sent, message.sender.clone(), message)
+        {
+            audit.record(record);
+        }
+    }
+}
+
+/// Hand `message`, received from a peer, to the audit log in `audit`, if one is set, the message
+/// is audited and it is validly signed.
+pub async fn audit_received<TYPES: NodeType, V: Versions>(
+    audit: &MessageAuditSlot<TYPES>,
+    message: &Message<TYPES>,
+    membership: &EpochMembershipCoordinator<TYPES>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) {
+    let Some(audit) = audit.get() else {
+        return;
+    };
+    if AuditKind::of(&message.kind).is_none() {
+        return;
+    }
+    let Some(signer) = verified_signer(&message.kind, membership, upgrade_lock).await else {
+        tracing::debug!(
+            sender = %message.sender,
+            "not auditing a message without a valid signature"
+        );
+        return;
+    };
+    if let Some(record) = AuditRecord::new(AuditDirection::Received, signer, message) {
+        audit.record(record);
+    }
+}
+
+/// The key which signed the proposal or vote `message`, if its signature is valid.
+///
+/// The signer of a proposal is the leader of its view, and the signer of a vote must be in the
+/// stake table, or the DA committee for a DA vote, of the epoch of the vote.
+pub async fn verified_signer<TYPES: NodeType, V: Versions>(
+    message: &MessageKind<TYPES>,
+    membership: &EpochMembershipCoordinator<TYPES>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) -> Option<TYPES::SignatureKey> {
+    let signed = signed_data(message, upgrade_lock).await?;
+    let membership = membership
+        .membership_for_epoch(message.epoch())
+        .await
+        .ok()?;
+    let key = match signed.signer {
+        Signer::Leader => membership.leader(message.view_number()).await.ok()?,
+        Signer::Voter { key, da } => {
+            let member = if da {
+                membership.da_stake(&key).await.is_some()
+            } else {
+                membership.stake(&key).await.is_some()
+            };
+            if !member {
+                return None;
+            }
+            key
+        },
+    };
+    key.validate(&signed.signature, &signed.data).then_some(key)
+}
+
+/// Whether the proposal or vote `message` carries a signature which its claimed signer did not
+/// make.
+///
+/// Unlike [`verified_signer`], this does not require a vote to come from a member of the stake
+/// table, and a proposal whose leader is not known yet is not considered invalid, so that only
+/// messages which no honest node could have sent are rejected.
+pub async fn has_invalid_signature<TYPES: NodeType, V: Versions>(
+    message: &MessageKind<TYPES>,
+    membership: &EpochMembershipCoordinator<TYPES>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) -> bool {
+    let Some(signed) = signed_data(message, upgrade_lock).await else {
+        return false;
+    };
+    let key = match signed.signer {
+        Signer::Leader => {
+            let Ok(membership) = membership.membership_for_epoch(message.epoch()).await else {
+                return false;
+            };
+            let Ok(leader) = membership.leader(message.view_number()).await else {
+                return false;
+            };
+            leader
+        },
+        Signer::Voter { key, .. } => key,
+    };
+    !key.validate(&signed.signature, &signed.data)
+}
+
+/// Who must have signed a proposal or vote
+enum Signer<TYPES: NodeType> {
+    /// The leader of the view of the proposal
+    Leader,
+    /// The key which signed the vote, a member of the DA committee if `da`
+    Voter { key: TYPES::SignatureKey, da: bool },
+}
+
+/// The signature of a proposal or vote, with the data it signs
+struct SignedData<TYPES: NodeType> {
+    /// Who must have signed the data
+    signer: Signer<TYPES>,
+    /// The signature carried by the message
+    signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    /// The signed data
+    data: Vec<u8>,
+}
+
+/// The signature of the proposal or vote `message`, if it is one
+async fn signed_data<TYPES: NodeType, V: Versions>(
+    message: &MessageKind<TYPES>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) -> Option<SignedData<TYPES>> {
+    let MessageKind::Consensus(consensus_message) = message else {
+        return None;
+    };
+    match consensus_message {
+        SequencingMessage::General(message) => match message {
+            GeneralConsensusMessage::Proposal(proposal) => {
+                let leaf = Leaf::from_quorum_proposal(&proposal.data);
+                let commitment = leaf.commit(upgrade_lock).await;
+                Some(leader_signed(&proposal.signature, commitment.as_ref()))
+            },
+            GeneralConsensusMessage::Proposal2(proposal) => {
+                let wrapper = QuorumProposalWrapper::from(proposal.data.clone());
+                let commitment = Leaf2::from_quorum_proposal(&wrapper).commit();
+                Some(leader_signed(&proposal.signature, commitment.as_ref()))
+            },
+            GeneralConsensusMessage::UpgradeProposal(proposal) => {
+                let commitment = proposal.data.upgrade_proposal.commit();
+                Some(leader_signed(&proposal.signature, commitment.as_ref()))
+            },
+            GeneralConsensusMessage::Vote(vote) => vote_signed(vote, upgrade_lock, false).await,
+            GeneralConsensusMessage::Vote2(vote) => vote_signed(vote, upgrade_lock, false).await,
+            GeneralConsensusMessage::EpochRootQuorumVote(vote) => {
+                vote_signed(&vote.vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncPreCommitVote(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncCommitVote(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncFinalizeVote(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncPreCommitVote2(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncCommitVote2(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::ViewSyncFinalizeVote2(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::TimeoutVote(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::TimeoutVote2(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            GeneralConsensusMessage::UpgradeVote(vote) => {
+                vote_signed(vote, upgrade_lock, false).await
+            },
+            _ => None,
+        },
+        SequencingMessage::Da(message) => match message {
+            DaConsensusMessage::DaProposal(proposal) => {
+                let hash = Sha256::digest(&proposal.data.encoded_transactions);
+                Some(leader_signed(&proposal.signature, hash.as_slice()))
+            },
+            DaConsensusMessage::DaProposal2(proposal) => {
+                let hash = Sha256::digest(&proposal.data.encoded_transactions);
+                Some(leader_signed(&proposal.signature, hash.as_slice()))
+            },
+            DaConsensusMessage::DaPayloadHint2(hint) => {
+                let hash = &hint.data.encoded_transactions_hash;
+                Some(leader_signed(&hint.signature, hash.as_slice()))
+            },
+            DaConsensusMessage::DaVote(vote) => vote_signed(vote, upgrade_lock, true).await,
+            DaConsensusMessage::DaVote2(vote) => vote_signed(vote, upgrade_lock, true).await,
+            _ => None,
+        },
+    }
+}
+
+/// `data` signed with `signature` by the leader of the view
+fn leader_signed<TYPES: NodeType>(
+    signature: &<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    data: &[u8],
+) -> SignedData<TYPES> {
+    SignedData {
+        signer: Signer::Leader,
+        signature: signature.clone(),
+        data: data.to_vec(),
+    }
+}
+
+/// The signature of `vote`, which must come from the DA committee if `da`
+async fn vote_signed<TYPES: NodeType, V: Versions, VOTE: Vote<TYPES>>(
+    vote: &VOTE,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    da: bool,
+) -> Option<SignedData<TYPES>> {
+    let commitment = VersionedVoteData::new(vote.date().clone(), vote.view_number(), upgrade_lock)
+        .await
+        .ok()?
+        .commit();
+    Some(SignedData {
+        signer: Signer::Voter {
+            key: vote.signing_key(),
+            da,
+        },
+        signature: vote.signature(),
+        data: commitment.as_ref().to_vec(),
+    })
+}
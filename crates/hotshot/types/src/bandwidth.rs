@@ -0,0 +1,367 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Accounting and rate limiting of network bandwidth, by class of message.
+//!
+//! Every message a node sends or receives is counted against its [`TrafficClass`], in bytes as
+//! handed to or received from the network, after compression. Sending is additionally throttled
+//! by a token bucket for each class with a configured [`RateLimit`], so that bulky classes (VID
+//! shares in particular) cannot use up the link and starve small, latency-sensitive ones (votes).
+//!
+//! A message larger than the burst of its class is still sent, once the bucket has refilled
+//! enough to pay for it, so limits delay messages but never drop them.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    message::{DaConsensusMessage, GeneralConsensusMessage, MessageKind, SequencingMessage},
+    traits::{
+        metrics::{Counter, Metrics, NoMetrics},
+        node_implementation::NodeType,
+    },
+};
+
+/// Classes of messages whose bandwidth is accounted, and limited, separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrafficClass {
+    /// Quorum and upgrade proposals, certificates, and requests and responses for them
+    Proposal,
+    /// Quorum, timeout and upgrade votes
+    Vote,
+    /// VID shares
+    Vid,
+    /// DA proposals, votes and certificates
+    Da,
+    /// View sync votes and certificates
+    ViewSync,
+    /// Transactions, data requests and responses, and external messages
+    Other,
+}
+
+impl TrafficClass {
+    /// Every class
+    pub const ALL: [TrafficClass; 6] = [
+        TrafficClass::Proposal,
+        TrafficClass::Vote,
+        TrafficClass::Vid,
+        TrafficClass::Da,
+        TrafficClass::ViewSync,
+        TrafficClass::Other,
+    ];
+
+    /// The class of a message
+    #[must_use]
+    pub fn of<TYPES: NodeType>(kind: &MessageKind<TYPES>) -> Self {
+        match kind {
+            MessageKind::Consensus(SequencingMessage::General(message)) => match message {
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::Proposal2(_)
+                | GeneralConsensusMessage::ProposalRequested(..)
+                | GeneralConsensusMessage::ProposalResponse(_)
+                | GeneralConsensusMessage::ProposalResponse2(_)
+                | GeneralConsensusMessage::UpgradeProposal(_)
+                | GeneralConsensusMessage::HighQc(..)
+                | GeneralConsensusMessage::ExtendedQc(..)
+                | GeneralConsensusMessage::EpochRootQc(_)
+                | GeneralConsensusMessage::ViewEvidenceRequested(..)
+                | GeneralConsensusMessage::ViewEvidenceResponse(_)
+                | GeneralConsensusMessage::ProposalFetchRequested(..)
+                | GeneralConsensusMessage::ProposalFetchResponse(_) => Self::Proposal,
+                GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::Vote2(_)
+                | GeneralConsensusMessage::EpochRootQuorumVote(_)
+                | GeneralConsensusMessage::TimeoutVote(_)
+                | GeneralConsensusMessage::TimeoutVote2(_)
+                | GeneralConsensusMessage::UpgradeVote(_) => Self::Vote,
+                GeneralConsensusMessage::ViewSyncPreCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote2(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate2(_) => Self::ViewSync,
+            },
+            MessageKind::Consensus(SequencingMessage::Da(message)) => match message {
+                DaConsensusMessage::VidDisperseMsg(_) | DaConsensusMessage::VidDisperseMsg2(_) => {
+                    Self::Vid
+                },
+                DaConsensusMessage::DaProposal(_)
+                | DaConsensusMessage::DaProposal2(_)
+                | DaConsensusMessage::DaVote(_)
+                | DaConsensusMessage::DaVote2(_)
+                | DaConsensusMessage::DaCertificate(_)
+                | DaConsensusMessage::DaCertificate2(_)
+                | DaConsensusMessage::DaPayloadHint2(_) => Self::Da,
+            },
+            MessageKind::Data(_) | MessageKind::External(_) => Self::Other,
+        }
+    }
+
+    /// Name of the class, as used in metric names and configuration
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Proposal => "proposal",
+            Self::Vote => "vote",
+            Self::Vid => "vid",
+            Self::Da => "da",
+            Self::ViewSync => "view_sync",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Rate limit on the bytes sent for a class of messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Sustained rate, in bytes per second
+    pub bytes_per_second: u64,
+    /// Bytes which can be sent at once after the class has been idle
+    pub burst: u64,
+}
+
+/// Configuration of bandwidth limits
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthConfig {
+    /// Limit on the bytes sent for each class of messages, classes without a limit are unlimited
+    #[serde(default)]
+    pub limits: HashMap<TrafficClass, RateLimit>,
+}
+
+/// A token bucket enforcing a [`RateLimit`].
+///
+/// The bucket may go into debt to send a message larger than what it holds, in which case the
+/// debt is repaid before any later message is sent.
+#[derive(Debug)]
+struct TokenBucket {
+    /// The limit enforced
+    limit: RateLimit,
+    /// Bytes which can be sent without waiting, as of `updated`; negative while in debt
+    tokens: f64,
+    /// When `tokens` was last refilled
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket enforcing `limit`
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    /// Take `bytes` out of the bucket, returning how long to wait before sending them
+    fn reserve(&mut self, bytes: usize, now: Instant) -> Duration {
+        let rate = self.limit.bytes_per_second.max(1) as f64;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.limit.burst as f64);
+        self.updated = now;
+
+        // Wait until the bucket is out of debt, then take the whole message at once
+        let wait = Duration::from_secs_f64((-self.tokens).max(0.0) / rate);
+        self.tokens -= bytes as f64;
+        wait
+    }
+}
+
+/// Bandwidth metrics of one class of messages
+#[derive(Debug)]
+struct ClassMetrics {
+    /// Bytes handed to the network
+    bytes_sent: Box<dyn Counter>,
+    /// Bytes received from the network
+    bytes_received: Box<dyn Counter>,
+    /// Messages delayed by the rate limit of the class
+    throttled: Box<dyn Counter>,
+}
+
+/// Accounts the bandwidth used by each class of messages, and enforces the configured limits
+#[derive(Debug)]
+pub struct BandwidthAccounting {
+    /// Metrics, for every class
+    metrics: HashMap<TrafficClass, ClassMetrics>,
+    /// Token buckets of the classes with a limit
+    buckets: Mutex<HashMap<TrafficClass, TokenBucket>>,
+}
+
+impl Default for BandwidthAccounting {
+    fn default() -> Self {
+        Self::new(&BandwidthConfig::default(), &NoMetrics)
+    }
+}
+
+impl BandwidthAccounting {
+    /// Create the accounting of a node with the given limits, reporting to `metrics`
+    #[must_use]
+    pub fn new(config: &BandwidthConfig, metrics: &dyn Metrics) -> Self {
+        let now = Instant::now();
+        Self {
+            metrics: TrafficClass::ALL
+                .into_iter()
+                .map(|class| {
+                    let name = class.as_str();
+                    let metrics = ClassMetrics {
+                        bytes_sent: metrics
+                            .create_counter(format!("{name}_bytes_sent"), Some("bytes".into())),
+                        bytes_received: metrics
+                            .create_counter(format!("{name}_bytes_received"), Some("bytes".into())),
+                        throttled: metrics.create_counter(format!("{name}_throttled"), None),
+                    };
+                    (class, metrics)
+                })
+                .collect(),
+            buckets: Mutex::new(
+                config
+                    .limits
+                    .iter()
+                    .map(|(class, limit)| (*class, TokenBucket::new(*limit, now)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Replace the limits with those of `config`.
+    ///
+    /// Classes whose limit changes keep the bytes they have already used, capped to their new
+    /// burst; classes without a limit in `config` become unlimited.
+    pub fn set_limits(&self, config: &BandwidthConfig) {
+        let now = Instant::now();
+        let mut buckets = self.buckets();
+        buckets.retain(|class, _| config.limits.contains_key(class));
+        for (class, limit) in &config.limits {
+            match buckets.get_mut(class) {
+                Some(bucket) => {
+                    bucket.limit = *limit;
+                    bucket.tokens = bucket.tokens.min(limit.burst as f64);
+                },
+                None => {
+                    buckets.insert(*class, TokenBucket::new(*limit, now));
+                },
+            }
+        }
+    }
+
+    /// The token buckets of the classes with a limit
+    fn buckets(&self) -> MutexGuard<'_, HashMap<TrafficClass, TokenBucket>> {
+        self.buckets.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Account `bytes` about to be sent for a message of class `class`.
+    ///
+    /// Returns how long to wait before sending them to respect the limit of the class.
+    pub fn reserve_send(&self, class: TrafficClass, bytes: usize) -> Duration {
+        let metrics = &self.metrics[&class];
+        metrics.bytes_sent.add(bytes);
+
+        let Some(wait) = self
+            .buckets()
+            .get_mut(&class)
+            .map(|bucket| bucket.reserve(bytes, Instant::now()))
+        else {
+            return Duration::ZERO;
+        };
+        if !wait.is_zero() {
+            metrics.throttled.add(1);
+        }
+        wait
+    }
+
+    /// Account `bytes` received for a message of class `class`
+    pub fn record_received(&self, class: TrafficClass, bytes: usize) {
+        self.metrics[&class].bytes_received.add(bytes);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(
+            RateLimit {
+                bytes_per_second: 1000,
+                burst: 500,
+            },
+            now,
+        );
+
+        // The burst can be sent at once, and so can a message overdrawing the bucket.
+        assert_eq!(bucket.reserve(400, now), Duration::ZERO);
+        assert_eq!(bucket.reserve(600, now), Duration::ZERO);
+
+        // The next message waits until the debt is repaid.
+        assert_eq!(bucket.reserve(100, now), Duration::from_millis(500));
+
+        // The bucket refills over time, up to the burst.
+        let later = now + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(500, later), Duration::ZERO);
+        assert_eq!(bucket.reserve(1, later), Duration::ZERO);
+        assert!(bucket.reserve(1, later) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bandwidth_config() {
+        let config: BandwidthConfig = toml::from_str(
+            r#"
+            [limits.vid]
+            bytes_per_second = 1000000
+            burst = 2000000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.limits[&TrafficClass::Vid],
+            RateLimit {
+                bytes_per_second: 1_000_000,
+                burst: 2_000_000,
+            }
+        );
+
+        // Unlimited classes are never throttled.
+        let accounting = BandwidthAccounting::new(&config, &NoMetrics);
+        assert_eq!(
+            accounting.reserve_send(TrafficClass::Vote, usize::MAX),
+            Duration::ZERO
+        );
+
+        // Until they are given a limit.
+        accounting.set_limits(&BandwidthConfig {
+            limits: [(
+                TrafficClass::Vote,
+                RateLimit {
+                    bytes_per_second: 1000,
+                    burst: 1000,
+                },
+            )]
+            .into(),
+        });
+        assert_eq!(
+            accounting.reserve_send(TrafficClass::Vote, 2000),
+            Duration::ZERO
+        );
+        assert!(accounting.reserve_send(TrafficClass::Vote, 1) > Duration::ZERO);
+        assert_eq!(
+            accounting.reserve_send(TrafficClass::Vid, usize::MAX),
+            Duration::ZERO
+        );
+    }
+}
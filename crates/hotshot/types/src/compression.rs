@@ -0,0 +1,303 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compression of large network messages.
+//!
+//! A compressed message is framed as [`COMPRESSED_MESSAGE_MAGIC`], followed by the length of the
+//! uncompressed message as a little-endian `u32`, followed by the zstd-compressed message. The
+//! magic can never start a serialized [`Message`](crate::message::Message), whose first bytes are
+//! its version, so uncompressed messages are passed through unchanged and nodes with compression
+//! disabled stay compatible with every peer that can decompress.
+//!
+//! Every node decompresses the messages it receives. Nodes running a protocol version from before
+//! [`Feature::MessageCompression`](crate::feature_gates::Feature::MessageCompression) may not be
+//! able to, so a message is only compressed if the version it is serialized with, which is the
+//! version in effect in its view, enables the feature. Messages are therefore sent uncompressed
+//! until an upgrade to such a version takes effect, which keeps rolling upgrades safe. Beyond
+//! that, which messages a node compresses depends on its [`CompressionConfig`]:
+//! * broadcasts larger than the threshold (DA proposals in particular) are compressed as soon as
+//!   compression is enabled, since they cannot be tailored to each recipient;
+//! * direct messages larger than the threshold (VID shares in particular) are only compressed for
+//!   peers which have been seen sending compressed messages themselves, and are therefore known to
+//!   be able to decompress them.
+
+use std::{borrow::Cow, collections::HashSet, hash::Hash, io::Read};
+
+use async_lock::RwLock;
+use hotshot_utils::anytrace::*;
+use serde::{Deserialize, Serialize};
+use vbs::version::Version;
+
+/// Bytes at the start of every compressed message
+pub const COMPRESSED_MESSAGE_MAGIC: [u8; 4] = [0xff, 0xff, b'z', b's'];
+
+/// Length of the header of a compressed message: the magic and the uncompressed length
+const HEADER_LEN: usize = COMPRESSED_MESSAGE_MAGIC.len() + size_of::<u32>();
+
+/// Largest uncompressed message we accept, to bound the memory a peer can make us allocate
+pub const MAX_DECOMPRESSED_SIZE: usize = 1 << 30;
+
+/// Configuration of message compression
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// Size in bytes of the serialized message above which it is compressed
+    #[serde(default = "default_threshold")]
+    pub threshold: usize,
+    /// zstd compression level
+    #[serde(default = "default_level")]
+    pub level: i32,
+}
+
+/// Default [`CompressionConfig::threshold`]
+fn default_threshold() -> usize {
+    128 * 1024
+}
+
+/// Default [`CompressionConfig::level`]
+fn default_level() -> i32 {
+    3
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_threshold(),
+            level: default_level(),
+        }
+    }
+}
+
+/// Compresses outgoing messages and decompresses incoming ones, keeping track of the peers known
+/// to support compression.
+#[derive(Debug)]
+pub struct MessageCompression<K> {
+    /// Configuration, `None` if this node does not compress the messages it sends
+    config: Option<CompressionConfig>,
+    /// Earliest protocol version whose messages may be compressed
+    min_version: Version,
+    /// Peers which have sent us compressed messages
+    capable_peers: RwLock<HashSet<K>>,
+}
+
+impl<K: Clone + Eq + Hash> Default for MessageCompression<K> {
+    fn default() -> Self {
+        Self::new(None, Version { major: 0, minor: 0 })
+    }
+}
+
+impl<K: Clone + Eq + Hash> MessageCompression<K> {
+    /// Create the compression state of a node with the given configuration, compressing only
+    /// messages serialized with `min_version` or later
+    pub fn new(config: Option<CompressionConfig>, min_version: Version) -> Self {
+        Self {
+            config,
+            min_version,
+            capable_peers: RwLock::new(HashSet::new()),
+        }
+    }
+
+    /// The compression level to compress a serialized message with, if compression is enabled,
+    /// the message is large enough, and its version allows it to be compressed
+    fn level(&self, message: &[u8]) -> Option<i32> {
+        let config = self.config?;
+        let (version, _) = Version::deserialize(message).ok()?;
+        (message.len() > config.threshold && version >= self.min_version).then_some(config.level)
+    }
+
+    /// Compress a serialized message to be broadcast, if compression is enabled, the message is
+    /// large enough, and its version allows it
+    pub fn compress_broadcast(&self, message: Vec<u8>) -> Vec<u8> {
+        match self.level(&message) {
+            Some(level) => compress(message, level),
+            None => message,
+        }
+    }
+
+    /// Compress a serialized message to be sent to `recipient`, if compression is enabled, the
+    /// message is large enough, its version allows it, and `recipient` is known to support
+    /// compression
+    pub async fn compress_direct(&self, message: Vec<u8>, recipient: &K) -> Vec<u8> {
+        match self.level(&message) {
+            Some(level) if self.capable_peers.read().await.contains(recipient) => {
+                compress(message, level)
+            },
+            _ => message,
+        }
+    }
+
+    /// Record that `peer` sent us a compressed message
+    pub async fn record_capable(&self, peer: &K) {
+        if self.capable_peers.read().await.contains(peer) {
+            return;
+        }
+        self.capable_peers.write().await.insert(peer.clone());
+    }
+}
+
+/// Whether `message` is a compressed message
+pub fn is_compressed(message: &[u8]) -> bool {
+    message.starts_with(&COMPRESSED_MESSAGE_MAGIC)
+}
+
+/// Compress a serialized message, falling back to the uncompressed message if compression fails
+/// or does not make it smaller
+fn compress(message: Vec<u8>, level: i32) -> Vec<u8> {
+    let Ok(len) = u32::try_from(message.len()) else {
+        return message;
+    };
+    let compressed = match zstd::bulk::compress(&message, level) {
+        Ok(compressed) => compressed,
+        Err(err) => {
+            tracing::warn!("Failed to compress message: {err}");
+            return message;
+        },
+    };
+    if HEADER_LEN + compressed.len() >= message.len() {
+        return message;
+    }
+
+    let mut framed = Vec::with_capacity(HEADER_LEN + compressed.len());
+    framed.extend_from_slice(&COMPRESSED_MESSAGE_MAGIC);
+    framed.extend_from_slice(&len.to_le_bytes());
+    framed.extend_from_slice(&compressed);
+    framed
+}
+
+/// Decompress a message received from the network, passing uncompressed messages through
+///
+/// # Errors
+/// if the message is compressed but malformed, or larger than [`MAX_DECOMPRESSED_SIZE`]
+pub fn decompress(message: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !is_compressed(message) {
+        return Ok(Cow::Borrowed(message));
+    }
+    ensure!(
+        message.len() >= HEADER_LEN,
+        "Compressed message is truncated"
+    );
+
+    let mut len_bytes = [0; size_of::<u32>()];
+    len_bytes.copy_from_slice(&message[COMPRESSED_MESSAGE_MAGIC.len()..HEADER_LEN]);
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    ensure!(
+        len <= MAX_DECOMPRESSED_SIZE,
+        "Compressed message claims to be {len} bytes, more than the limit of {MAX_DECOMPRESSED_SIZE}"
+    );
+
+    // Read at most one byte more than announced, so that the buffer only grows as the message is
+    // actually decompressed and a lying header is detected.
+    let mut decompressed = Vec::new();
+    zstd::stream::read::Decoder::new(&message[HEADER_LEN..])
+        .wrap()
+        .context(warn!("Failed to initialize decompression"))?
+        .take(len as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .wrap()
+        .context(warn!("Failed to decompress message"))?;
+    ensure!(
+        decompressed.len() == len,
+        "Compressed message decompressed to {} bytes instead of {len}",
+        decompressed.len()
+    );
+
+    Ok(Cow::Owned(decompressed))
+}
+
+#[cfg(test)]
+mod test {
+    use vbs::{version::StaticVersion, BinarySerializer, Serializer};
+
+    use super::*;
+
+    fn compression() -> MessageCompression<u64> {
+        MessageCompression::new(
+            Some(CompressionConfig {
+                threshold: 1024,
+                level: 3,
+            }),
+            Version { major: 0, minor: 3 },
+        )
+    }
+
+    /// A compressible message of about `len` bytes, serialized with version 0.`MINOR`
+    fn serialized<const MINOR: u16>(len: usize) -> Vec<u8> {
+        Serializer::<StaticVersion<0, MINOR>>::serialize(&vec![7u8; len]).unwrap()
+    }
+
+    #[test]
+    fn test_compression_roundtrip() {
+        let compression = compression();
+        let message = serialized::<3>(4096);
+
+        let compressed = compression.compress_broadcast(message.clone());
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < message.len());
+        assert_eq!(decompress(&compressed).unwrap(), message);
+
+        // Small messages, and uncompressed messages, are passed through.
+        let small = serialized::<3>(512);
+        assert_eq!(compression.compress_broadcast(small.clone()), small);
+        assert_eq!(decompress(&small).unwrap(), small);
+
+        // Nothing is compressed when compression is disabled.
+        let disabled = MessageCompression::<u64>::default();
+        assert_eq!(disabled.compress_broadcast(message.clone()), message);
+    }
+
+    #[tokio::test]
+    async fn test_compression_version_gate() {
+        let compression = compression();
+        compression.record_capable(&1).await;
+
+        // Messages of versions from before compression was introduced are never compressed, so
+        // that nodes which have not upgraded yet can read them.
+        let old = serialized::<2>(4096);
+        assert_eq!(compression.compress_broadcast(old.clone()), old);
+        assert_eq!(compression.compress_direct(old.clone(), &1).await, old);
+
+        let new = serialized::<4>(4096);
+        assert!(is_compressed(&compression.compress_broadcast(new.clone())));
+        assert!(is_compressed(&compression.compress_direct(new, &1).await));
+    }
+
+    #[tokio::test]
+    async fn test_compression_negotiation() {
+        let compression = compression();
+        let message = serialized::<3>(4096);
+
+        // Direct messages are only compressed for peers known to support it.
+        assert_eq!(
+            compression.compress_direct(message.clone(), &1).await,
+            message
+        );
+        compression.record_capable(&1).await;
+        assert!(is_compressed(
+            &compression.compress_direct(message.clone(), &1).await
+        ));
+        assert_eq!(
+            compression.compress_direct(message.clone(), &2).await,
+            message
+        );
+    }
+
+    #[test]
+    fn test_decompression_limits() {
+        let compressed = compression().compress_broadcast(serialized::<3>(4096));
+
+        // A header announcing the wrong length is rejected.
+        let mut lying = compressed.clone();
+        lying[COMPRESSED_MESSAGE_MAGIC.len()..HEADER_LEN].copy_from_slice(&100u32.to_le_bytes());
+        decompress(&lying).unwrap_err();
+
+        // So is one announcing more than the limit.
+        let mut huge = compressed.clone();
+        huge[COMPRESSED_MESSAGE_MAGIC.len()..HEADER_LEN].copy_from_slice(&u32::MAX.to_le_bytes());
+        decompress(&huge).unwrap_err();
+
+        // And a truncated message.
+        decompress(&compressed[..HEADER_LEN - 1]).unwrap_err();
+    }
+}
@@ -11,6 +11,7 @@ use std::{
     mem::ManuallyDrop,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::Instant,
 };
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
@@ -27,17 +28,21 @@ use crate::{
     error::HotShotError,
     event::{HotShotAction, LeafInfo},
     message::{Proposal, UpgradeLock},
+    signer::Signer,
     simple_certificate::{
         DaCertificate2, LightClientStateUpdateCertificate, NextEpochQuorumCertificate2,
         QuorumCertificate2,
     },
     traits::{
         block_contents::{BlockHeader, BuilderFee},
-        metrics::{Counter, Gauge, Histogram, Metrics, NoMetrics},
+        metrics::{
+            Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics,
+            NoMetrics,
+        },
         node_implementation::{ConsensusTime, NodeType, Versions},
-        signature_key::SignatureKey,
         BlockPayload, ValidatedState,
     },
+    upgrade_readiness::UpgradeReadiness,
     utils::{
         epoch_from_block_number, is_epoch_root, is_epoch_transition, is_ge_epoch_root,
         is_last_block, is_transition_block, option_epoch_from_block_number, BuilderCommitment,
@@ -55,6 +60,56 @@ pub type VidShares<TYPES> = BTreeMap<
     HashMap<<TYPES as NodeType>::SignatureKey, Proposal<TYPES, VidDisperseShare<TYPES>>>,
 >;
 
+/// Why an update of the [`Consensus`] state was not applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ConsensusUpdateError {
+    /// The update carries nothing newer than the state it would replace.
+    ///
+    /// This is benign and common: the same view, QC or payload often arrives more than once, or
+    /// after something newer.
+    #[error("stale update: {0}")]
+    Stale(&'static str),
+    /// The update is inconsistent with itself or with the state, which points to a bug or a
+    /// misbehaving peer.
+    #[error("inconsistent update: {0}")]
+    Inconsistent(&'static str),
+}
+
+impl ConsensusUpdateError {
+    /// Whether the update was rejected only because it was stale
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        matches!(self, Self::Stale(_))
+    }
+}
+
+/// Logs an update which was not applied, for callers which carry on regardless: stale updates at
+/// trace level, inconsistent ones as warnings.
+impl Log for ConsensusUpdateError {
+    fn log(&self) {
+        match self {
+            Self::Stale(_) => tracing::trace!("{self}"),
+            Self::Inconsistent(_) => tracing::warn!("{self}"),
+        }
+    }
+}
+
+/// Stale updates are propagated at debug level, inconsistent ones at error level.
+impl From<ConsensusUpdateError> for Error {
+    fn from(err: ConsensusUpdateError) -> Self {
+        Self {
+            level: match err {
+                ConsensusUpdateError::Stale(_) => Level::Debug,
+                ConsensusUpdateError::Inconsistent(_) => Level::Error,
+            },
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Result of an update of the [`Consensus`] state
+pub type UpdateResult = std::result::Result<(), ConsensusUpdateError>;
+
 /// Type alias for consensus state wrapped in a lock.
 pub type LockedConsensusState<TYPES> = Arc<RwLock<Consensus<TYPES>>>;
 
@@ -76,31 +131,31 @@ impl<TYPES: NodeType> OuterConsensus<TYPES> {
     /// Locks inner consensus for reading and leaves debug traces
     #[instrument(skip_all, target = "OuterConsensus")]
     pub async fn read(&self) -> ConsensusReadLockGuard<'_, TYPES> {
-        tracing::trace!("Trying to acquire read lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Trying to acquire read lock on consensus");
         let ret = self.inner_consensus.read().await;
-        tracing::trace!("Acquired read lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Acquired read lock on consensus");
         ConsensusReadLockGuard::new(ret)
     }
 
     /// Locks inner consensus for writing and leaves debug traces
     #[instrument(skip_all, target = "OuterConsensus")]
     pub async fn write(&self) -> ConsensusWriteLockGuard<'_, TYPES> {
-        tracing::trace!("Trying to acquire write lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Trying to acquire write lock on consensus");
         let ret = self.inner_consensus.write().await;
-        tracing::trace!("Acquired write lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Acquired write lock on consensus");
         ConsensusWriteLockGuard::new(ret)
     }
 
     /// Tries to acquire write lock on inner consensus and leaves debug traces
     #[instrument(skip_all, target = "OuterConsensus")]
     pub fn try_write(&self) -> Option<ConsensusWriteLockGuard<'_, TYPES>> {
-        tracing::trace!("Trying to acquire write lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Trying to acquire write lock on consensus");
         let ret = self.inner_consensus.try_write();
         if let Some(guard) = ret {
-            tracing::trace!("Acquired write lock on consensus");
+            tracing::trace!(target: "OuterConsensus", "Acquired write lock on consensus");
             Some(ConsensusWriteLockGuard::new(guard))
         } else {
-            tracing::trace!("Failed to acquire write lock");
+            tracing::trace!(target: "OuterConsensus", "Failed to acquire write lock");
             None
         }
     }
@@ -108,22 +163,22 @@ impl<TYPES: NodeType> OuterConsensus<TYPES> {
     /// Acquires upgradable read lock on inner consensus and leaves debug traces
     #[instrument(skip_all, target = "OuterConsensus")]
     pub async fn upgradable_read(&self) -> ConsensusUpgradableReadLockGuard<'_, TYPES> {
-        tracing::trace!("Trying to acquire upgradable read lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Trying to acquire upgradable read lock on consensus");
         let ret = self.inner_consensus.upgradable_read().await;
-        tracing::trace!("Acquired upgradable read lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Acquired upgradable read lock on consensus");
         ConsensusUpgradableReadLockGuard::new(ret)
     }
 
     /// Tries to acquire read lock on inner consensus and leaves debug traces
     #[instrument(skip_all, target = "OuterConsensus")]
     pub fn try_read(&self) -> Option<ConsensusReadLockGuard<'_, TYPES>> {
-        tracing::trace!("Trying to acquire read lock on consensus");
+        tracing::trace!(target: "OuterConsensus", "Trying to acquire read lock on consensus");
         let ret = self.inner_consensus.try_read();
         if let Some(guard) = ret {
-            tracing::trace!("Acquired read lock on consensus");
+            tracing::trace!(target: "OuterConsensus", "Acquired read lock on consensus");
             Some(ConsensusReadLockGuard::new(guard))
         } else {
-            tracing::trace!("Failed to acquire read lock");
+            tracing::trace!(target: "OuterConsensus", "Failed to acquire read lock");
             None
         }
     }
@@ -153,7 +208,7 @@ impl<TYPES: NodeType> Deref for ConsensusReadLockGuard<'_, TYPES> {
 impl<TYPES: NodeType> Drop for ConsensusReadLockGuard<'_, TYPES> {
     #[instrument(skip_all, target = "ConsensusReadLockGuard")]
     fn drop(&mut self) {
-        tracing::trace!("Read lock on consensus dropped");
+        tracing::trace!(target: "OuterConsensus", "Read lock on consensus dropped");
     }
 }
 
@@ -187,7 +242,7 @@ impl<TYPES: NodeType> DerefMut for ConsensusWriteLockGuard<'_, TYPES> {
 impl<TYPES: NodeType> Drop for ConsensusWriteLockGuard<'_, TYPES> {
     #[instrument(skip_all, target = "ConsensusWriteLockGuard")]
     fn drop(&mut self) {
-        tracing::debug!("Write lock on consensus dropped");
+        tracing::debug!(target: "OuterConsensus", "Write lock on consensus dropped");
     }
 }
 
@@ -214,9 +269,9 @@ impl<'a, TYPES: NodeType> ConsensusUpgradableReadLockGuard<'a, TYPES> {
     pub async fn upgrade(mut guard: Self) -> ConsensusWriteLockGuard<'a, TYPES> {
         let inner_guard = unsafe { ManuallyDrop::take(&mut guard.lock_guard) };
         guard.taken = true;
-        tracing::debug!("Trying to upgrade upgradable read lock on consensus");
+        tracing::debug!(target: "OuterConsensus", "Trying to upgrade upgradable read lock on consensus");
         let ret = RwLockUpgradableReadGuard::upgrade(inner_guard).await;
-        tracing::debug!("Upgraded upgradable read lock on consensus");
+        tracing::debug!(target: "OuterConsensus", "Upgraded upgradable read lock on consensus");
         ConsensusWriteLockGuard::new(ret)
     }
 }
@@ -234,7 +289,7 @@ impl<TYPES: NodeType> Drop for ConsensusUpgradableReadLockGuard<'_, TYPES> {
     fn drop(&mut self) {
         if !self.taken {
             unsafe { ManuallyDrop::drop(&mut self.lock_guard) }
-            tracing::debug!("Upgradable read lock on consensus dropped");
+            tracing::debug!(target: "OuterConsensus", "Upgradable read lock on consensus dropped");
         }
     }
 }
@@ -335,6 +390,9 @@ pub struct Consensus<TYPES: NodeType> {
     /// Tables for the DRB seeds and results.
     pub drb_results: DrbResults<TYPES>,
 
+    /// The protocol versions other nodes have reported supporting.
+    pub upgrade_readiness: UpgradeReadiness<TYPES::SignatureKey>,
+
     /// The transition QC for the current epoch
     transition_qc: Option<(
         QuorumCertificate2<TYPES>,
@@ -387,8 +445,98 @@ pub struct ConsensusMetricsValue {
     pub number_of_empty_blocks_proposed: Box<dyn Counter>,
     /// Number of events in the hotshot event queue
     pub internal_event_queue_len: Box<dyn Gauge>,
+    /// Number of events waiting to be handled, by consensus task
+    pub task_queue_depth: Box<dyn GaugeFamily>,
+    /// Seconds spent handling each event, by consensus task
+    pub task_event_latency: Box<dyn HistogramFamily>,
+    /// Seconds from the broadcast of each internal event until a consensus task takes it up, by
+    /// event kind
+    pub event_delivery_latency: Box<dyn HistogramFamily>,
+    /// Number of VID disperse calculations stopped before finishing because their view became stale
+    pub number_of_cancelled_vid_disperse: Box<dyn Counter>,
+    /// Metrics subgroup for the health of supervised subtasks
+    pub subtasks: Box<dyn Metrics>,
+    /// Seconds from the first valid vote to reaching the certificate threshold, as a leader
+    pub vote_time_to_threshold: Box<dyn Histogram>,
+    /// Seconds a quorum proposal was buffered waiting for its missing parent to be fetched
+    pub proposal_dependency_stall_duration: Box<dyn Histogram>,
+    /// Metrics subgroup for the bandwidth used by each class of network messages
+    pub bandwidth: Box<dyn Metrics>,
+    /// Number of blocks and bundles claimed from builders which failed validation, by builder and
+    /// reason
+    pub rejected_builder_bundles: Box<dyn CounterFamily>,
+    /// Seconds each builder took to report its available blocks, by builder
+    pub builder_response_latency: Box<dyn HistogramFamily>,
+    /// Number of blocks claimed from each builder to be proposed, by builder
+    pub builder_wins: Box<dyn CounterFamily>,
+    /// Number of DA votes received for each view, as DA leader
+    pub da_votes_received: Box<dyn Histogram>,
+    /// Seconds from sending a DA proposal to forming its certificate, as DA leader
+    pub da_certificate_latency: Box<dyn Histogram>,
+    /// Seconds spent calculating each VID dispersal
+    pub vid_disperse_duration: Box<dyn Histogram>,
+    /// Number of valid VID shares received
+    pub vid_shares_received: Box<dyn Counter>,
+    /// Size in bytes of each valid block payload received for DA, including our own
+    pub payload_size: Box<dyn Histogram>,
+    /// Number of view sync rounds this node has started
+    pub view_sync_rounds: Box<dyn Counter>,
+    /// Seconds from sending a view sync vote to receiving the certificate for its phase, by phase
+    pub view_sync_phase_duration: Box<dyn HistogramFamily>,
+    /// Number of times a view sync round timed out and moved on to the next relay
+    pub view_sync_relay_changes: Box<dyn Counter>,
+    /// Current view sync round timeout in milliseconds, adapted to the observed network latency
+    pub view_sync_round_timeout: Box<dyn Gauge>,
+    /// Current view timeout in milliseconds
+    pub view_timeout: Box<dyn Gauge>,
+    /// Number of times the view timeout was adapted to recent view durations or timeouts
+    pub view_timeout_adjustments: Box<dyn Counter>,
 }
 
+/// Bucket boundaries, in seconds, for view duration histograms.
+///
+/// View durations are measured in whole seconds and are usually a few seconds long, but can be as
+/// long as the view timeout when something goes wrong, so the default buckets (which top out at 10
+/// seconds) put every interesting outlier in the overflow bucket.
+const VIEW_DURATION_BUCKETS: [f64; 12] = [
+    1.0, 2.0, 3.0, 4.0, 5.0, 7.5, 10.0, 15.0, 20.0, 30.0, 60.0, 120.0,
+];
+
+/// Bucket boundaries, in seconds, for the time consensus tasks spend handling a single event.
+///
+/// Most events are handled in well under a millisecond, while the slow ones (signature aggregation,
+/// VID, storage) take tens or hundreds of milliseconds, so the buckets start much finer than the
+/// default.
+const TASK_EVENT_LATENCY_BUCKETS: [f64; 12] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// Bucket boundaries, in seconds, for the time internal events wait before a task takes them up.
+///
+/// Events are usually taken up almost immediately, but wait behind slow events in a busy task, so
+/// the buckets cover both the handling latencies above and delays of several seconds.
+const EVENT_DELIVERY_LATENCY_BUCKETS: [f64; 15] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Bucket boundaries for the number of DA votes received in a view.
+///
+/// The count is bounded by the size of the DA committee, which ranges from a handful of nodes in
+/// tests to hundreds in production.
+const DA_VOTES_BUCKETS: [f64; 10] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+/// Bucket boundaries, in bytes, for block payload sizes, from 1 KiB to 64 MiB.
+const PAYLOAD_SIZE_BUCKETS: [f64; 9] = [
+    1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0, 67108864.0,
+];
+
+/// Bucket boundaries, in seconds, for the duration of view sync phases.
+///
+/// A phase takes a couple of network round trips when its relay is up, and lasts until the round
+/// timeout when it is not, so the buckets run from tens of milliseconds to the default timeout.
+const VIEW_SYNC_PHASE_BUCKETS: [f64; 11] =
+    [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
 impl ConsensusMetricsValue {
     /// Create a new instance of this [`ConsensusMetricsValue`] struct, setting all the counters and gauges
     #[must_use]
@@ -404,8 +552,11 @@ impl ConsensusMetricsValue {
                 .create_gauge(String::from("number_of_views_since_last_decide"), None),
             number_of_views_per_decide_event: metrics
                 .create_histogram(String::from("number_of_views_per_decide_event"), None),
-            view_duration_as_leader: metrics
-                .create_histogram(String::from("view_duration_as_leader"), None),
+            view_duration_as_leader: metrics.create_histogram_with_buckets(
+                String::from("view_duration_as_leader"),
+                Some(String::from("seconds")),
+                VIEW_DURATION_BUCKETS.to_vec(),
+            ),
             invalid_qc: metrics.create_gauge(String::from("invalid_qc"), None),
             outstanding_transactions: metrics
                 .create_gauge(String::from("outstanding_transactions"), None),
@@ -418,6 +569,71 @@ impl ConsensusMetricsValue {
                 .create_counter(String::from("number_of_empty_blocks_proposed"), None),
             internal_event_queue_len: metrics
                 .create_gauge(String::from("internal_event_queue_len"), None),
+            task_queue_depth: metrics
+                .gauge_family(String::from("task_queue_depth"), vec![String::from("task")]),
+            task_event_latency: metrics.histogram_family_with_buckets(
+                String::from("task_event_latency"),
+                vec![String::from("task")],
+                TASK_EVENT_LATENCY_BUCKETS.to_vec(),
+            ),
+            event_delivery_latency: metrics.histogram_family_with_buckets(
+                String::from("event_delivery_latency"),
+                vec![String::from("event")],
+                EVENT_DELIVERY_LATENCY_BUCKETS.to_vec(),
+            ),
+            number_of_cancelled_vid_disperse: metrics
+                .create_counter(String::from("number_of_cancelled_vid_disperse"), None),
+            subtasks: metrics.subgroup(String::from("subtasks")),
+            vote_time_to_threshold: metrics
+                .create_histogram(String::from("vote_time_to_threshold"), None),
+            proposal_dependency_stall_duration: metrics
+                .create_histogram(String::from("proposal_dependency_stall_duration"), None),
+            bandwidth: metrics.subgroup(String::from("bandwidth")),
+            rejected_builder_bundles: metrics.counter_family(
+                String::from("rejected_builder_bundles"),
+                vec![String::from("builder"), String::from("reason")],
+            ),
+            builder_response_latency: metrics.histogram_family(
+                String::from("builder_response_latency"),
+                vec![String::from("builder")],
+            ),
+            builder_wins: metrics
+                .counter_family(String::from("builder_wins"), vec![String::from("builder")]),
+            da_votes_received: metrics.create_histogram_with_buckets(
+                String::from("da_votes_received"),
+                None,
+                DA_VOTES_BUCKETS.to_vec(),
+            ),
+            da_certificate_latency: metrics.create_histogram(
+                String::from("da_certificate_latency"),
+                Some(String::from("seconds")),
+            ),
+            vid_disperse_duration: metrics.create_histogram(
+                String::from("vid_disperse_duration"),
+                Some(String::from("seconds")),
+            ),
+            vid_shares_received: metrics.create_counter(String::from("vid_shares_received"), None),
+            payload_size: metrics.create_histogram_with_buckets(
+                String::from("payload_size"),
+                Some(String::from("bytes")),
+                PAYLOAD_SIZE_BUCKETS.to_vec(),
+            ),
+            view_sync_rounds: metrics.create_counter(String::from("view_sync_rounds"), None),
+            view_sync_phase_duration: metrics.histogram_family_with_buckets(
+                String::from("view_sync_phase_duration"),
+                vec![String::from("phase")],
+                VIEW_SYNC_PHASE_BUCKETS.to_vec(),
+            ),
+            view_sync_relay_changes: metrics
+                .create_counter(String::from("view_sync_relay_changes"), None),
+            view_sync_round_timeout: metrics.create_gauge(
+                String::from("view_sync_round_timeout"),
+                Some(String::from("ms")),
+            ),
+            view_timeout: metrics
+                .create_gauge(String::from("view_timeout"), Some(String::from("ms"))),
+            view_timeout_adjustments: metrics
+                .create_counter(String::from("view_timeout_adjustments"), None),
         }
     }
 }
@@ -483,6 +699,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             metrics,
             epoch_height,
             drb_results: DrbResults::new(),
+            upgrade_readiness: UpgradeReadiness::default(),
             transition_qc,
             highest_block: 0,
             state_cert,
@@ -607,12 +824,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Update the current view.
     /// # Errors
-    /// Can return an error when the new view_number is not higher than the existing view number.
-    pub fn update_view(&mut self, view_number: TYPES::View) -> Result<()> {
-        ensure!(
-            view_number > self.cur_view,
-            debug!("New view isn't newer than the current view.")
-        );
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view_number is not higher than the
+    /// existing view number.
+    pub fn update_view(&mut self, view_number: TYPES::View) -> UpdateResult {
+        if view_number <= self.cur_view {
+            return Err(ConsensusUpdateError::Stale(
+                "new view isn't newer than the current view",
+            ));
+        }
         self.cur_view = view_number;
         Ok(())
     }
@@ -665,12 +884,17 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Update the current epoch.
     /// # Errors
-    /// Can return an error when the new epoch_number is not higher than the existing epoch number.
-    pub fn update_epoch(&mut self, epoch_number: TYPES::Epoch) -> Result<()> {
-        ensure!(
-            self.cur_epoch.is_none() || Some(epoch_number) > self.cur_epoch,
-            debug!("New epoch isn't newer than the current epoch.")
-        );
+    /// Returns [`ConsensusUpdateError::Stale`] when the new epoch_number is not higher than the
+    /// existing epoch number.
+    pub fn update_epoch(&mut self, epoch_number: TYPES::Epoch) -> UpdateResult {
+        if self
+            .cur_epoch
+            .is_some_and(|cur_epoch| epoch_number <= cur_epoch)
+        {
+            return Err(ConsensusUpdateError::Stale(
+                "new epoch isn't newer than the current epoch",
+            ));
+        }
         tracing::trace!(
             "Updating epoch from {:?} to {}",
             self.cur_epoch,
@@ -715,19 +939,22 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the last proposal.
     ///
     /// # Errors
-    /// Can return an error when the new view_number is not higher than the existing proposed view number.
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view_number is not higher than the
+    /// existing proposed view number.
     pub fn update_proposed_view(
         &mut self,
         proposal: Proposal<TYPES, QuorumProposalWrapper<TYPES>>,
-    ) -> Result<()> {
-        ensure!(
-            proposal.data.view_number()
-                > self
-                    .last_proposals
-                    .last_key_value()
-                    .map_or(TYPES::View::genesis(), |(k, _)| { *k }),
-            debug!("New view isn't newer than the previously proposed view.")
-        );
+    ) -> UpdateResult {
+        if proposal.data.view_number()
+            <= self
+                .last_proposals
+                .last_key_value()
+                .map_or(TYPES::View::genesis(), |(k, _)| *k)
+        {
+            return Err(ConsensusUpdateError::Stale(
+                "new view isn't newer than the previously proposed view",
+            ));
+        }
         self.last_proposals
             .insert(proposal.data.view_number(), proposal);
         Ok(())
@@ -736,12 +963,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the last decided view.
     ///
     /// # Errors
-    /// Can return an error when the new view_number is not higher than the existing decided view number.
-    pub fn update_last_decided_view(&mut self, view_number: TYPES::View) -> Result<()> {
-        ensure!(
-            view_number > self.last_decided_view,
-            debug!("New view isn't newer than the previously decided view.")
-        );
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view_number is not higher than the
+    /// existing decided view number.
+    pub fn update_last_decided_view(&mut self, view_number: TYPES::View) -> UpdateResult {
+        if view_number <= self.last_decided_view {
+            return Err(ConsensusUpdateError::Stale(
+                "new view isn't newer than the previously decided view",
+            ));
+        }
         self.last_decided_view = view_number;
         Ok(())
     }
@@ -749,12 +978,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the locked view.
     ///
     /// # Errors
-    /// Can return an error when the new view_number is not higher than the existing locked view number.
-    pub fn update_locked_view(&mut self, view_number: TYPES::View) -> Result<()> {
-        ensure!(
-            view_number > self.locked_view,
-            debug!("New view isn't newer than the previously locked view.")
-        );
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view_number is not higher than the
+    /// existing locked view number.
+    pub fn update_locked_view(&mut self, view_number: TYPES::View) -> UpdateResult {
+        if view_number <= self.locked_view {
+            return Err(ConsensusUpdateError::Stale(
+                "new view isn't newer than the previously locked view",
+            ));
+        }
         self.locked_view = view_number;
         Ok(())
     }
@@ -762,14 +993,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the validated state map with a new view_number/view combo.
     ///
     /// # Errors
-    /// Can return an error when the new view contains less information than the existing view
-    /// with the same view number.
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view contains less information than
+    /// the existing view with the same view number.
     pub fn update_da_view(
         &mut self,
         view_number: TYPES::View,
         epoch: Option<TYPES::Epoch>,
         payload_commitment: VidCommitment,
-    ) -> Result<()> {
+    ) -> UpdateResult {
         let view = View {
             view_inner: ViewInner::Da {
                 payload_commitment,
@@ -782,14 +1013,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the validated state map with a new view_number/view combo.
     ///
     /// # Errors
-    /// Can return an error when the new view contains less information than the existing view
-    /// with the same view number.
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view contains less information than
+    /// the existing view with the same view number.
     pub fn update_leaf(
         &mut self,
         leaf: Leaf2<TYPES>,
         state: Arc<TYPES::ValidatedState>,
         delta: Option<Arc<<TYPES::ValidatedState as ValidatedState<TYPES>>::Delta>>,
-    ) -> Result<()> {
+    ) -> UpdateResult {
         let view_number = leaf.view_number();
         let epoch = option_epoch_from_block_number::<TYPES>(
             leaf.with_epoch,
@@ -812,13 +1043,13 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the validated state map with a new view_number/view combo.
     ///
     /// # Errors
-    /// Can return an error when the new view contains less information than the existing view
-    /// with the same view number.
+    /// Returns [`ConsensusUpdateError::Stale`] when the new view contains less information than
+    /// the existing view with the same view number.
     fn update_validated_state_map(
         &mut self,
         view_number: TYPES::View,
         new_view: View<TYPES>,
-    ) -> Result<()> {
+    ) -> UpdateResult {
         if let Some(existing_view) = self.validated_state_map().get(&view_number) {
             if let ViewInner::Leaf {
                 delta: ref existing_delta,
@@ -830,12 +1061,15 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                     ..
                 } = new_view.view_inner
                 {
-                    ensure!(
-                         new_delta.is_some() || existing_delta.is_none(),
-                         debug!("Skipping the state update to not override a `Leaf` view with `Some` state delta.")
-                     );
+                    if new_delta.is_none() && existing_delta.is_some() {
+                        return Err(ConsensusUpdateError::Stale(
+                            "not overriding a `Leaf` view with `Some` state delta",
+                        ));
+                    }
                 } else {
-                    bail!("Skipping the state update to not override a `Leaf` view with a non-`Leaf` view.");
+                    return Err(ConsensusUpdateError::Stale(
+                        "not overriding a `Leaf` view with a non-`Leaf` view",
+                    ));
                 }
             }
         }
@@ -851,32 +1085,36 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// Update the saved payloads with a new encoded transaction.
     ///
     /// # Errors
-    /// Can return an error when there's an existing payload corresponding to the same view number.
+    /// Returns [`ConsensusUpdateError::Stale`] when there's an existing payload corresponding to
+    /// the same view number.
     pub fn update_saved_payloads(
         &mut self,
         view_number: TYPES::View,
         payload: Arc<PayloadWithMetadata<TYPES>>,
-    ) -> Result<()> {
-        ensure!(
-            !self.saved_payloads.contains_key(&view_number),
-            "Payload with the same view already exists."
-        );
+    ) -> UpdateResult {
+        if self.saved_payloads.contains_key(&view_number) {
+            return Err(ConsensusUpdateError::Stale(
+                "payload with the same view already exists",
+            ));
+        }
         self.saved_payloads.insert(view_number, payload);
         Ok(())
     }
 
     /// Update the high QC if given a newer one.
     /// # Errors
-    /// Can return an error when the provided high_qc is not newer than the existing entry.
-    pub fn update_high_qc(&mut self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+    /// Returns [`ConsensusUpdateError::Stale`] when the provided high_qc is not newer than the
+    /// existing entry.
+    pub fn update_high_qc(&mut self, high_qc: QuorumCertificate2<TYPES>) -> UpdateResult {
         if self.high_qc == high_qc {
             return Ok(());
         }
         // make sure the we don't update the high QC unless is't a higher view
-        ensure!(
-            high_qc.view_number > self.high_qc.view_number,
-            debug!("High QC with an equal or higher view exists.")
-        );
+        if high_qc.view_number <= self.high_qc.view_number {
+            return Err(ConsensusUpdateError::Stale(
+                "high QC with an equal or higher view exists",
+            ));
+        }
         tracing::debug!("Updating high QC");
         self.high_qc = high_qc;
 
@@ -885,21 +1123,21 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Update the next epoch high QC if given a newer one.
     /// # Errors
-    /// Can return an error when the provided high_qc is not newer than the existing entry.
-    /// # Panics
-    /// It can't actually panic. If the option is None, we will not call unwrap on it.
+    /// Returns [`ConsensusUpdateError::Stale`] when the provided high_qc is not newer than the
+    /// existing entry.
     pub fn update_next_epoch_high_qc(
         &mut self,
         high_qc: NextEpochQuorumCertificate2<TYPES>,
-    ) -> Result<()> {
+    ) -> UpdateResult {
         if self.next_epoch_high_qc.as_ref() == Some(&high_qc) {
             return Ok(());
         }
         if let Some(next_epoch_high_qc) = self.next_epoch_high_qc() {
-            ensure!(
-                high_qc.view_number > next_epoch_high_qc.view_number,
-                debug!("Next epoch high QC with an equal or higher view exists.")
-            );
+            if high_qc.view_number <= next_epoch_high_qc.view_number {
+                return Err(ConsensusUpdateError::Stale(
+                    "next epoch high QC with an equal or higher view exists",
+                ));
+            }
         }
         tracing::debug!("Updating next epoch high QC");
         self.next_epoch_high_qc = Some(high_qc);
@@ -909,16 +1147,18 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Resets high qc and next epoch qc to the provided transition qc.
     /// # Errors
-    /// Can return an error when the provided high_qc is not newer than the existing entry.
+    /// Returns [`ConsensusUpdateError::Inconsistent`] when the QCs are for different leaves, or
+    /// are not transition QCs of the same epoch as the existing high QC.
     pub fn reset_high_qc(
         &mut self,
         high_qc: QuorumCertificate2<TYPES>,
         next_epoch_qc: NextEpochQuorumCertificate2<TYPES>,
-    ) -> Result<()> {
-        ensure!(
-            high_qc.data.leaf_commit == next_epoch_qc.data.leaf_commit,
-            error!("High QC's and next epoch QC's leaf commits do not match.")
-        );
+    ) -> UpdateResult {
+        if high_qc.data.leaf_commit != next_epoch_qc.data.leaf_commit {
+            return Err(ConsensusUpdateError::Inconsistent(
+                "high QC's and next epoch QC's leaf commits do not match",
+            ));
+        }
         if self.high_qc == high_qc {
             return Ok(());
         }
@@ -930,14 +1170,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             epoch_from_block_number(bn + 1, self.epoch_height)
                 == epoch_from_block_number(high_bn + 1, self.epoch_height)
         });
-        ensure!(
-            high_qc
-                .data
-                .block_number
-                .is_some_and(|bn| is_transition_block(bn, self.epoch_height))
-                && same_epoch,
-            error!("Provided QC is not a transition QC.")
-        );
+        if !(high_qc
+            .data
+            .block_number
+            .is_some_and(|bn| is_transition_block(bn, self.epoch_height))
+            && same_epoch)
+        {
+            return Err(ConsensusUpdateError::Inconsistent(
+                "provided QC is not a transition QC",
+            ));
+        }
         tracing::debug!("Resetting high QC and next epoch high QC");
         self.high_qc = high_qc;
         self.next_epoch_high_qc = Some(next_epoch_qc);
@@ -947,18 +1189,18 @@ impl<TYPES: NodeType> Consensus<TYPES> {
 
     /// Update the light client state update certificate if given a newer one.
     /// # Errors
-    /// Can return an error when the provided state_cert is not newer than the existing entry.
+    /// Returns [`ConsensusUpdateError::Stale`] when the provided state_cert is not newer than the
+    /// existing entry.
     pub fn update_state_cert(
         &mut self,
         state_cert: LightClientStateUpdateCertificate<TYPES>,
-    ) -> Result<()> {
+    ) -> UpdateResult {
         if let Some(existing_state_cert) = &self.state_cert {
-            ensure!(
-                state_cert.epoch > existing_state_cert.epoch,
-                debug!(
-                    "Light client state update certification with an equal or higher epoch exists."
-                )
-            );
+            if state_cert.epoch <= existing_state_cert.epoch {
+                return Err(ConsensusUpdateError::Stale(
+                    "light client state update certificate with an equal or higher epoch exists",
+                ));
+            }
         }
         tracing::debug!("Updating light client state update certification");
         self.state_cert = Some(state_cert);
@@ -1141,7 +1383,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         view: <TYPES as NodeType>::View,
         target_epoch: Option<<TYPES as NodeType>::Epoch>,
         membership_coordinator: EpochMembershipCoordinator<TYPES>,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Option<()> {
         let payload_with_metadata = Arc::clone(consensus.read().await.saved_payloads().get(&view)?);
@@ -1153,6 +1395,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             .view_inner
             .epoch()?;
 
+        let start = Instant::now();
         let vid = VidDisperse::calculate_vid_disperse::<V>(
             &payload_with_metadata.payload,
             &membership_coordinator,
@@ -1164,11 +1407,17 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         )
         .await
         .ok()?;
+        consensus
+            .read()
+            .await
+            .metrics
+            .vid_disperse_duration
+            .add_point(start.elapsed().as_secs_f64());
 
         let shares = VidDisperseShare::from_vid_disperse(vid);
         let mut consensus_writer = consensus.write().await;
         for share in shares {
-            if let Some(prop) = share.to_proposal(private_key) {
+            if let Some(prop) = share.to_proposal(signer) {
                 consensus_writer.update_vid_shares(view, prop);
             }
         }
@@ -34,6 +34,12 @@ pub const COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL: u64 = 50;
 /// the default delay duration value in milliseconds of sending on the secondary in the combined networks
 pub const COMBINED_NETWORK_DELAY_DURATION: u64 = 5000;
 
+/// the number of random peers a direct message is relayed through when it cannot be sent on either network of the combined networks
+pub const COMBINED_NETWORK_NUM_RELAYS: usize = 3;
+
+/// the number of nodes, besides its recipient, the leader sends each VID share to, so that the recipient can pull its share from them if it misses it
+pub const VID_SHARE_CUSTODIANS: usize = 2;
+
 /// The default network data request delay in milliseconds
 pub const REQUEST_DATA_DELAY: u64 = 5000;
 
@@ -13,7 +13,7 @@ use std::{
     fmt::{Debug, Display},
     hash::Hash,
     marker::PhantomData,
-    sync::Arc,
+    sync::{atomic::AtomicBool, Arc},
 };
 
 use async_lock::RwLock;
@@ -23,8 +23,10 @@ use hotshot_utils::anytrace::*;
 use jf_vid::VidScheme;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tagged_base64::TaggedBase64;
 use thiserror::Error;
+use url::Url;
 use vbs::version::{StaticVersionType, Version};
 use vec1::Vec1;
 use vid_disperse::{ADVZDisperse, ADVZDisperseShare, AvidMDisperse, VidDisperseShare2};
@@ -32,8 +34,10 @@ use vid_disperse::{ADVZDisperse, ADVZDisperseShare, AvidMDisperse, VidDisperseSh
 use crate::{
     drb::DrbResult,
     epoch_membership::EpochMembershipCoordinator,
+    feature_gates::Feature,
     impl_has_epoch, impl_has_none_epoch,
     message::{convert_proposal, Proposal, UpgradeLock},
+    signer::Signer,
     simple_certificate::{
         LightClientStateUpdateCertificate, NextEpochQuorumCertificate2, QuorumCertificate,
         QuorumCertificate2, TimeoutCertificate, TimeoutCertificate2, UpgradeCertificate,
@@ -201,6 +205,60 @@ impl<TYPES: NodeType> From<DaProposal2<TYPES>> for DaProposal<TYPES> {
     }
 }
 
+/// A compact announcement of a DA proposal whose payload is fetched out of band.
+///
+/// Like [`DaProposal2`], but instead of the encoded transactions it carries their commitment
+/// and a list of data providers committee members can fetch them from. It is signed with the
+/// same signature as the full proposal, so a fetched payload can be verified against it.
+#[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(bound = "TYPES: NodeType")]
+pub struct DaPayloadHint2<TYPES: NodeType> {
+    /// Sha256 digest of the encoded transactions, signed by the leader
+    pub encoded_transactions_hash: [u8; 32],
+    /// VID commitment of the payload, used to fetch it from data providers
+    pub payload_commitment: VidCommitment,
+    /// Length of the encoded transactions in bytes
+    pub payload_byte_len: u64,
+    /// Metadata of the block to be applied.
+    pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    /// View this proposal applies to
+    pub view_number: TYPES::View,
+    /// Epoch this proposal applies to
+    pub epoch: Option<TYPES::Epoch>,
+    /// Indicates whether we are in epoch transition
+    pub epoch_transition_indicator: EpochTransitionIndicator,
+    /// Data providers which are expected to serve the payload
+    pub fetch_hints: Vec<Url>,
+}
+
+impl<TYPES: NodeType> DaPayloadHint2<TYPES> {
+    /// Build the full DA proposal from a payload fetched for this hint.
+    ///
+    /// # Errors
+    /// Returns an error if the payload does not match the announced length or hash
+    pub fn into_proposal(self, encoded_transactions: Arc<[u8]>) -> Result<DaProposal2<TYPES>> {
+        ensure!(
+            encoded_transactions.len() as u64 == self.payload_byte_len,
+            "Fetched payload has length {} but {} was announced",
+            encoded_transactions.len(),
+            self.payload_byte_len
+        );
+        let hash: [u8; 32] = Sha256::digest(&encoded_transactions).into();
+        ensure!(
+            hash == self.encoded_transactions_hash,
+            "Fetched payload does not match the announced hash"
+        );
+
+        Ok(DaProposal2 {
+            encoded_transactions,
+            metadata: self.metadata,
+            view_number: self.view_number,
+            epoch: self.epoch,
+            epoch_transition_indicator: self.epoch_transition_indicator,
+        })
+    }
+}
+
 /// A proposal to upgrade the network
 #[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 #[serde(bound = "TYPES: NodeType")]
@@ -344,7 +402,7 @@ pub fn vid_commitment<V: Versions>(
     total_weight: usize,
     version: Version,
 ) -> VidCommitment {
-    if version < V::Epochs::VERSION {
+    if !Feature::Epochs.enabled_in::<V>(version) {
         let encoded_tx_len = encoded_transactions.len();
         advz_scheme(total_weight).commit_only(encoded_transactions).map(VidCommitment::V0).unwrap_or_else(|err| panic!("VidScheme::commit_only failure:(total_weight,payload_byte_len)=({total_weight},{encoded_tx_len}) error: {err}"))
     } else {
@@ -443,28 +501,60 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
         metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Result<Self> {
+        Self::calculate_vid_disperse_cancellable(
+            payload,
+            membership,
+            view,
+            target_epoch,
+            data_epoch,
+            metadata,
+            upgrade_lock,
+            Arc::default(),
+        )
+        .await?
+        .context(error!("VID disperse calculation cancelled"))
+    }
+
+    /// Same as [`calculate_vid_disperse`](Self::calculate_vid_disperse), but stops the calculation
+    /// and returns `None` once `cancelled` is set.
+    ///
+    /// # Errors
+    /// Returns an error if the disperse or commitment calculation fails
+    #[allow(clippy::too_many_arguments)]
+    pub async fn calculate_vid_disperse_cancellable<V: Versions>(
+        payload: &TYPES::BlockPayload,
+        membership: &EpochMembershipCoordinator<TYPES>,
+        view: TYPES::View,
+        target_epoch: Option<TYPES::Epoch>,
+        data_epoch: Option<TYPES::Epoch>,
+        metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<Option<Self>> {
         let version = upgrade_lock.version_infallible(view).await;
-        if version < V::Epochs::VERSION {
-            ADVZDisperse::calculate_vid_disperse(
+        if !Feature::Epochs.enabled_in::<V>(version) {
+            ADVZDisperse::calculate_vid_disperse_cancellable(
                 payload,
                 membership,
                 view,
                 target_epoch,
                 data_epoch,
+                cancelled,
             )
             .await
-            .map(|disperse| Self::V0(disperse))
+            .map(|disperse| disperse.map(Self::V0))
         } else {
-            AvidMDisperse::calculate_vid_disperse(
+            AvidMDisperse::calculate_vid_disperse_cancellable(
                 payload,
                 membership,
                 view,
                 target_epoch,
                 data_epoch,
                 metadata,
+                cancelled,
             )
             .await
-            .map(|disperse| Self::V1(disperse))
+            .map(|disperse| disperse.map(Self::V1))
         }
     }
 
@@ -525,13 +615,13 @@ impl<TYPES: NodeType> VidDisperseShare<TYPES> {
     /// Consume `self` and return a `Proposal`
     pub fn to_proposal(
         self,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
     ) -> Option<Proposal<TYPES, Self>> {
         let payload_commitment_ref: &[u8] = match &self {
             Self::V0(share) => share.payload_commitment.as_ref(),
             Self::V1(share) => share.payload_commitment.as_ref(),
         };
-        let Ok(signature) = TYPES::SignatureKey::sign(private_key, payload_commitment_ref) else {
+        let Ok(signature) = signer.sign(payload_commitment_ref) else {
             tracing::error!("VID: failed to sign dispersal share payload");
             return None;
         };
@@ -707,6 +797,14 @@ impl<TYPES: NodeType> ViewChangeEvidence2<TYPES> {
         }
     }
 
+    /// The view this evidence allows a node to enter.
+    pub fn view_to_enter(&self) -> TYPES::View {
+        match self {
+            ViewChangeEvidence2::Timeout(timeout_cert) => timeout_cert.data().view + 1,
+            ViewChangeEvidence2::ViewSync(view_sync_cert) => view_sync_cert.view_number,
+        }
+    }
+
     /// Convert to ViewChangeEvidence
     pub fn to_evidence(self) -> ViewChangeEvidence<TYPES> {
         match self {
@@ -720,6 +818,15 @@ impl<TYPES: NodeType> ViewChangeEvidence2<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> HasEpoch<TYPES> for ViewChangeEvidence2<TYPES> {
+    fn epoch(&self) -> Option<TYPES::Epoch> {
+        match self {
+            ViewChangeEvidence2::Timeout(timeout_cert) => timeout_cert.data().epoch(),
+            ViewChangeEvidence2::ViewSync(view_sync_cert) => view_sync_cert.data().epoch(),
+        }
+    }
+}
+
 /// Proposal to append a block.
 #[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
 #[serde(bound(deserialize = ""))]
@@ -956,6 +1063,12 @@ impl<TYPES: NodeType> HasViewNumber<TYPES> for DaProposal2<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> HasViewNumber<TYPES> for DaPayloadHint2<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
 impl<TYPES: NodeType> HasViewNumber<TYPES> for QuorumProposal<TYPES> {
     fn view_number(&self) -> TYPES::View {
         self.view_number
@@ -980,7 +1093,11 @@ impl<TYPES: NodeType> HasViewNumber<TYPES> for UpgradeProposal<TYPES> {
     }
 }
 
-impl_has_epoch!(QuorumProposal2<TYPES>, DaProposal2<TYPES>);
+impl_has_epoch!(
+    QuorumProposal2<TYPES>,
+    DaProposal2<TYPES>,
+    DaPayloadHint2<TYPES>
+);
 
 impl_has_none_epoch!(
     QuorumProposal<TYPES>,
@@ -1135,7 +1252,7 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
             metadata,
         );
 
-        let block_number = if V::Base::VERSION < V::Epochs::VERSION {
+        let block_number = if !Feature::Epochs.enabled_in::<V>(V::Base::VERSION) {
             None
         } else {
             Some(0u64)
@@ -1836,12 +1953,12 @@ pub mod null_block {
     #![allow(missing_docs)]
 
     use jf_vid::VidScheme;
-    use vbs::version::StaticVersionType;
 
     use crate::{
         data::VidCommitment,
+        feature_gates::Feature,
         traits::{
-            block_contents::BuilderFee,
+            block_contents::{BuilderFee, EncodeBytes},
             node_implementation::{NodeType, Versions},
             signature_key::BuilderSignatureKey,
             BlockPayload,
@@ -1867,6 +1984,66 @@ pub mod null_block {
         }
     }
 
+    /// The well-known key which signs the fees of blocks not built by a builder: null blocks, and
+    /// blocks built by the node itself (see [`local_builder_fee`])
+    #[must_use]
+    pub fn builder_key<TYPES: NodeType>() -> (
+        TYPES::BuilderSignatureKey,
+        <TYPES::BuilderSignatureKey as BuilderSignatureKey>::BuilderPrivateKey,
+    ) {
+        <TYPES::BuilderSignatureKey as BuilderSignatureKey>::generated_from_seed_indexed(
+            [0_u8; 32], 0,
+        )
+    }
+
+    /// Builder fee data for a block built by the node itself, from its local mempool
+    ///
+    /// Like a null block, such a block pays no fee, and its fee is signed with [`builder_key`].
+    /// It is thus only valid where blocks need not pay a fee (see
+    /// [`BlockPayload::requires_fee`]).
+    #[must_use]
+    pub fn local_builder_fee<TYPES: NodeType, V: Versions>(
+        num_storage_nodes: usize,
+        version: vbs::version::Version,
+        view_number: u64,
+        payload: &TYPES::BlockPayload,
+        metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    ) -> Option<BuilderFee<TYPES>> {
+        /// Fee amount, this block doesn't come from a builder
+        const FEE_AMOUNT: u64 = 0;
+
+        let (pub_key, priv_key) = builder_key::<TYPES>();
+
+        let fee_signature = if Feature::Marketplace.enabled_in::<V>(version) {
+            TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(
+                &priv_key,
+                FEE_AMOUNT,
+                view_number,
+            )
+        } else if Feature::Epochs.enabled_in::<V>(version) {
+            TYPES::BuilderSignatureKey::sign_fee(&priv_key, FEE_AMOUNT, metadata)
+        } else {
+            let commitment = super::vid_commitment::<V>(
+                &payload.encode(),
+                &metadata.encode(),
+                num_storage_nodes,
+                version,
+            );
+            TYPES::BuilderSignatureKey::sign_fee_with_vid_commitment(
+                &priv_key,
+                FEE_AMOUNT,
+                metadata,
+                &commitment,
+            )
+        };
+
+        Some(BuilderFee {
+            fee_amount: FEE_AMOUNT,
+            fee_account: pub_key,
+            fee_signature: fee_signature.ok()?,
+        })
+    }
+
     /// Builder fee data for a null block payload
     #[must_use]
     pub fn builder_fee<TYPES: NodeType, V: Versions>(
@@ -1877,12 +2054,9 @@ pub mod null_block {
         /// Arbitrary fee amount, this block doesn't actually come from a builder
         const FEE_AMOUNT: u64 = 0;
 
-        let (pub_key, priv_key) =
-            <TYPES::BuilderSignatureKey as BuilderSignatureKey>::generated_from_seed_indexed(
-                [0_u8; 32], 0,
-            );
+        let (pub_key, priv_key) = builder_key::<TYPES>();
 
-        if version >= V::Marketplace::VERSION {
+        if Feature::Marketplace.enabled_in::<V>(version) {
             match TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(
                 &priv_key,
                 FEE_AMOUNT,
@@ -1895,7 +2069,7 @@ pub mod null_block {
                 }),
                 Err(_) => None,
             }
-        } else if version >= V::Epochs::VERSION {
+        } else if Feature::Epochs.enabled_in::<V>(version) {
             let (_null_block, null_block_metadata) =
                 <TYPES::BlockPayload as BlockPayload<TYPES>>::empty();
 
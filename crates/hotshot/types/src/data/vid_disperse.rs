@@ -6,23 +6,37 @@
 
 //! This module provides types for VID disperse related data structures.
 
-use std::{collections::BTreeMap, fmt::Debug, hash::Hash, marker::PhantomData};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    hash::Hash,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use alloy::primitives::U256;
 use hotshot_utils::anytrace::*;
 use jf_vid::{VidDisperse as JfVidDisperse, VidScheme};
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::task::spawn_blocking;
+use vid::VidError;
 
 use super::ns_table::parse_ns_table;
 use crate::{
     epoch_membership::{EpochMembership, EpochMembershipCoordinator},
     impl_has_epoch,
     message::Proposal,
+    signer::Signer,
     simple_vote::HasEpoch,
     traits::{
         block_contents::EncodeBytes,
-        node_implementation::NodeType,
+        node_implementation::{ConsensusTime, NodeType},
         signature_key::{SignatureKey, StakeTableEntryType},
         BlockPayload,
     },
@@ -108,6 +122,34 @@ impl<TYPES: NodeType> ADVZDisperse<TYPES> {
         target_epoch: Option<TYPES::Epoch>,
         data_epoch: Option<TYPES::Epoch>,
     ) -> Result<Self> {
+        Self::calculate_vid_disperse_cancellable(
+            payload,
+            membership,
+            view,
+            target_epoch,
+            data_epoch,
+            Arc::default(),
+        )
+        .await?
+        .context(error!("VID disperse calculation cancelled"))
+    }
+
+    /// Same as [`calculate_vid_disperse`](Self::calculate_vid_disperse), but returns `None`
+    /// without calculating the dispersal if `cancelled` is set before the calculation starts.
+    ///
+    /// The ADVZ dispersal is a single call into the VID library, so it cannot be stopped once it
+    /// has started.
+    ///
+    /// # Errors
+    /// Returns an error if the disperse or commitment calculation fails
+    pub async fn calculate_vid_disperse_cancellable(
+        payload: &TYPES::BlockPayload,
+        membership: &EpochMembershipCoordinator<TYPES>,
+        view: TYPES::View,
+        target_epoch: Option<TYPES::Epoch>,
+        data_epoch: Option<TYPES::Epoch>,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<Option<Self>> {
         let num_nodes = membership
             .membership_for_epoch(target_epoch)
             .await?
@@ -116,14 +158,24 @@ impl<TYPES: NodeType> ADVZDisperse<TYPES> {
 
         let txns = payload.encode();
 
-        let vid_disperse = spawn_blocking(move || advz_scheme(num_nodes).disperse(&txns))
-            .await
-            .wrap()
-            .context(error!("Join error"))?
-            .wrap()
-            .context(|err| error!("Failed to calculate VID disperse. Error: {}", err))?;
+        let vid_disperse = spawn_blocking(move || {
+            if cancelled.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            advz_scheme(num_nodes).disperse(&txns).map(Some)
+        })
+        .await
+        .wrap()
+        .context(error!("Join error"))?
+        .wrap()
+        .context(|err| error!("Failed to calculate VID disperse. Error: {}", err))?;
+        let Some(vid_disperse) = vid_disperse else {
+            return Ok(None);
+        };
 
-        Ok(Self::from_membership(view, vid_disperse, membership, target_epoch, data_epoch).await)
+        Ok(Some(
+            Self::from_membership(view, vid_disperse, membership, target_epoch, data_epoch).await,
+        ))
     }
 
     /// Returns the payload length in bytes.
@@ -172,11 +224,9 @@ impl<TYPES: NodeType> ADVZDisperseShare<TYPES> {
     /// Consume `self` and return a `Proposal`
     pub fn to_proposal(
         self,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
     ) -> Option<Proposal<TYPES, Self>> {
-        let Ok(signature) =
-            TYPES::SignatureKey::sign(private_key, self.payload_commitment.as_ref())
-        else {
+        let Ok(signature) = signer.sign(self.payload_commitment.as_ref()) else {
             tracing::error!("VID: failed to sign dispersal share payload");
             return None;
         };
@@ -307,6 +357,43 @@ pub fn vid_total_weight<TYPES: NodeType>(
     }
 }
 
+/// The nodes holding a redundant copy of the VID share of `recipient` for `view`, from which
+/// `recipient` can pull its share if it misses the one sent by the leader.
+///
+/// Up to `count` custodians are sampled from `stake_table`, without replacement and with
+/// probability proportional to their stake. The randomness is seeded from `view` and `recipient`,
+/// so the leader and `recipient` agree on the custodians without exchanging messages.
+pub fn vid_share_custodians<TYPES: NodeType>(
+    stake_table: &[PeerConfig<TYPES>],
+    view: TYPES::View,
+    recipient: &TYPES::SignatureKey,
+    count: usize,
+) -> Vec<TYPES::SignatureKey> {
+    let candidates: Vec<_> = stake_table
+        .iter()
+        .map(|peer| {
+            (
+                peer.stake_table_entry.public_key(),
+                peer.stake_table_entry.stake().saturating_to::<u128>() as f64,
+            )
+        })
+        .filter(|(key, stake)| key != recipient && *stake > 0.0)
+        .collect();
+
+    let mut seed = Sha256::new();
+    seed.update(view.u64().to_le_bytes());
+    seed.update(recipient.to_bytes());
+    let mut rng = ChaCha20Rng::from_seed(seed.finalize().into());
+
+    match candidates.choose_multiple_weighted(&mut rng, count, |(_, stake)| *stake) {
+        Ok(custodians) => custodians.map(|(key, _)| key.clone()).collect(),
+        Err(err) => {
+            tracing::warn!("Failed to sample VID share custodians: {err}");
+            vec![]
+        },
+    }
+}
+
 fn approximate_weights<TYPES: NodeType>(stake_table: Vec<PeerConfig<TYPES>>) -> Weights {
     let total_stake = stake_table.iter().fold(U256::ZERO, |acc, entry| {
         acc + entry.stake_table_entry.stake()
@@ -397,7 +484,6 @@ impl<TYPES: NodeType> AvidMDisperse<TYPES> {
     /// # Errors
     /// Returns an error if the disperse or commitment calculation fails
     #[allow(clippy::panic)]
-    #[allow(clippy::single_range_in_vec_init)]
     pub async fn calculate_vid_disperse(
         payload: &TYPES::BlockPayload,
         membership: &EpochMembershipCoordinator<TYPES>,
@@ -406,6 +492,37 @@ impl<TYPES: NodeType> AvidMDisperse<TYPES> {
         data_epoch: Option<TYPES::Epoch>,
         metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
     ) -> Result<Self> {
+        Self::calculate_vid_disperse_cancellable(
+            payload,
+            membership,
+            view,
+            target_epoch,
+            data_epoch,
+            metadata,
+            Arc::default(),
+        )
+        .await?
+        .context(error!("VID disperse calculation cancelled"))
+    }
+
+    /// Same as [`calculate_vid_disperse`](Self::calculate_vid_disperse), but returns `None` once
+    /// `cancelled` is set, without finishing the calculation.
+    ///
+    /// The flag is checked between the namespaces of the payload, and between encoding each
+    /// namespace and distributing its shares among the nodes.
+    ///
+    /// # Errors
+    /// Returns an error if the disperse or commitment calculation fails
+    #[allow(clippy::single_range_in_vec_init)]
+    pub async fn calculate_vid_disperse_cancellable(
+        payload: &TYPES::BlockPayload,
+        membership: &EpochMembershipCoordinator<TYPES>,
+        view: TYPES::View,
+        target_epoch: Option<TYPES::Epoch>,
+        data_epoch: Option<TYPES::Epoch>,
+        metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+        cancelled: Arc<AtomicBool>,
+    ) -> Result<Option<Self>> {
         let target_mem = membership.membership_for_epoch(target_epoch).await?;
         let stake_table = target_mem.stake_table().await;
         let approximate_weights = approximate_weights(stake_table);
@@ -418,30 +535,40 @@ impl<TYPES: NodeType> AvidMDisperse<TYPES> {
 
         let ns_table = parse_ns_table(num_txns, &metadata.encode());
         let ns_table_clone = ns_table.clone();
-        let (commit, shares) = spawn_blocking(move || {
-            AvidMScheme::ns_disperse(
+        let disperse = spawn_blocking(move || {
+            match AvidMScheme::ns_disperse_cancellable(
                 &avidm_param,
                 &approximate_weights.weights,
                 &txns,
                 ns_table_clone,
-            )
+                || cancelled.load(Ordering::Relaxed),
+            ) {
+                Ok(disperse) => Ok(Some(disperse)),
+                Err(VidError::Cancelled) => Ok(None),
+                Err(err) => Err(err),
+            }
         })
         .await
         .wrap()
         .context(error!("Join error"))?
         .wrap()
         .context(|err| error!("Failed to calculate VID disperse. Error: {}", err))?;
+        let Some((commit, shares)) = disperse else {
+            return Ok(None);
+        };
 
-        Ok(Self::from_membership(
-            view,
-            commit,
-            &shares,
-            common,
-            &target_mem,
-            target_epoch,
-            data_epoch,
-        )
-        .await)
+        Ok(Some(
+            Self::from_membership(
+                view,
+                commit,
+                &shares,
+                common,
+                &target_mem,
+                target_epoch,
+                data_epoch,
+            )
+            .await,
+        ))
     }
 
     /// Returns the payload length in bytes.
@@ -496,11 +623,9 @@ impl<TYPES: NodeType> VidDisperseShare2<TYPES> {
     /// Consume `self` and return a `Proposal`
     pub fn to_proposal(
         self,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
     ) -> Option<Proposal<TYPES, Self>> {
-        let Ok(signature) =
-            TYPES::SignatureKey::sign(private_key, self.payload_commitment.as_ref())
-        else {
+        let Ok(signature) = signer.sign(self.payload_commitment.as_ref()) else {
             tracing::error!("VID: failed to sign dispersal share payload");
             return None;
         };
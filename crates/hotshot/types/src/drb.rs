@@ -4,8 +4,9 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 use crate::traits::node_implementation::{ConsensusTime, NodeType};
@@ -37,6 +38,49 @@ pub type DrbResult = [u8; 32];
 /// Number of previous results and seeds to keep
 pub const KEEP_PREVIOUS_RESULT_COUNT: u64 = 8;
 
+/// Number of hash iterations between checkpoints of an ongoing DRB computation.
+pub const DRB_CHECKPOINT_INTERVAL: u64 = 1_000_000;
+
+/// Progress of a DRB computation, which can be stored and resumed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DrbInput {
+    /// The epoch the result is for.
+    pub epoch: u64,
+    /// The number of hash iterations completed so far.
+    pub iteration: u64,
+    /// The hash after `iteration` iterations, starting from the seed.
+    pub value: [u8; 32],
+}
+
+impl DrbInput {
+    /// The input for a new computation starting from `seed`.
+    #[must_use]
+    pub fn new(epoch: u64, seed: DrbSeedInput) -> Self {
+        Self {
+            epoch,
+            iteration: 0,
+            value: seed,
+        }
+    }
+
+    /// Whether all the iterations have been completed, so that `value` is the result.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.iteration >= DIFFICULTY_LEVEL
+    }
+
+    /// Perform up to `max_iterations` further iterations of the computation.
+    pub fn iterate(&mut self, max_iterations: u64) {
+        let target = DIFFICULTY_LEVEL.min(self.iteration.saturating_add(max_iterations));
+        let mut hash = self.value;
+        while self.iteration < target {
+            hash = Sha256::digest(hash).into();
+            self.iteration += 1;
+        }
+        self.value = hash;
+    }
+}
+
 // TODO: Use `HASHES_PER_SECOND` * `VIEW_TIMEOUT` * `DRB_CALCULATION_NUM_VIEW` to calculate this
 // once we bench the hash time.
 // <https://github.com/EspressoSystems/HotShot/issues/3880>
@@ -56,17 +100,9 @@ pub fn difficulty_level() -> u64 {
 /// * `drb_seed_input` - Serialized QC signature.
 #[must_use]
 pub fn compute_drb_result<TYPES: NodeType>(drb_seed_input: DrbSeedInput) -> DrbResult {
-    let mut hash = drb_seed_input.to_vec();
-    for _iter in 0..DIFFICULTY_LEVEL {
-        // TODO: This may be optimized to avoid memcopies after we bench the hash time.
-        // <https://github.com/EspressoSystems/HotShot/issues/3880>
-        hash = Sha256::digest(hash).to_vec();
-    }
-
-    // Convert the hash to the DRB result.
-    let mut drb_result = [0u8; 32];
-    drb_result.copy_from_slice(&hash);
-    drb_result
+    let mut input = DrbInput::new(0, drb_seed_input);
+    input.iterate(DIFFICULTY_LEVEL);
+    input.value
 }
 
 /// Seeds for DRB computation and computed results.
@@ -74,6 +110,8 @@ pub fn compute_drb_result<TYPES: NodeType>(drb_seed_input: DrbSeedInput) -> DrbR
 pub struct DrbResults<TYPES: NodeType> {
     /// Stored results from computations
     pub results: BTreeMap<TYPES::Epoch, DrbResult>,
+    /// Epochs whose results are being computed
+    pub in_progress: BTreeSet<TYPES::Epoch>,
 }
 
 impl<TYPES: NodeType> DrbResults<TYPES> {
@@ -85,13 +123,27 @@ impl<TYPES: NodeType> DrbResults<TYPES> {
                 (TYPES::Epoch::new(1), INITIAL_DRB_RESULT),
                 (TYPES::Epoch::new(2), INITIAL_DRB_RESULT),
             ]),
+            in_progress: BTreeSet::new(),
         }
     }
 
     pub fn store_result(&mut self, epoch: TYPES::Epoch, result: DrbResult) {
+        self.in_progress.remove(&epoch);
         self.results.insert(epoch, result);
     }
 
+    /// Claim the computation of the result for `epoch`.
+    ///
+    /// Returns `false` if the result is already known or another task is computing it.
+    pub fn start_computation(&mut self, epoch: TYPES::Epoch) -> bool {
+        !self.results.contains_key(&epoch) && self.in_progress.insert(epoch)
+    }
+
+    /// Give up the computation of the result for `epoch`, so it can be started again.
+    pub fn abandon_computation(&mut self, epoch: TYPES::Epoch) {
+        self.in_progress.remove(&epoch);
+    }
+
     /// Garbage collects internal data structures
     pub fn garbage_collect(&mut self, epoch: TYPES::Epoch) {
         if epoch.u64() < KEEP_PREVIOUS_RESULT_COUNT {
@@ -103,6 +155,7 @@ impl<TYPES: NodeType> DrbResults<TYPES> {
 
         // Remove result entries older than EPOCH
         self.results = self.results.split_off(&retain_epoch);
+        self.in_progress = self.in_progress.split_off(&retain_epoch);
     }
 }
 
@@ -229,4 +282,38 @@ pub mod election {
         /// DRB result
         drb: [u8; 32],
     }
+
+    impl<Entry> RandomizedCommittee<Entry> {
+        /// The DRB result the committee was randomized with
+        #[must_use]
+        pub fn drb(&self) -> [u8; 32] {
+            self.drb
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resumed_drb_computation() {
+        let seed = [7u8; 32];
+        let mut expected = seed;
+        for _ in 0..DIFFICULTY_LEVEL {
+            expected = Sha256::digest(expected).into();
+        }
+
+        // Computing in steps, as when resuming from a checkpoint, gives the same result.
+        let mut input = DrbInput::new(3, seed);
+        while !input.is_complete() {
+            input.iterate(3);
+        }
+        assert_eq!(input.iteration, DIFFICULTY_LEVEL);
+        assert_eq!(input.value, expected);
+
+        // Further iterations have no effect once the computation is complete.
+        input.iterate(1);
+        assert_eq!(input.value, expected);
+    }
 }
@@ -8,6 +8,10 @@
 //!
 //! This module provides [`HotShotError`], which is an enum representing possible faults that can
 //! occur while interacting with this crate.
+//!
+//! Every error belongs to an [`ErrorCategory`] and has a numeric [code](HotShotError::code),
+//! which are stable across releases, so that consumers of error events can react to errors without
+//! parsing their messages. Error events are serialized as an [`ErrorReport`] carrying both.
 
 use committable::Commitment;
 use serde::{Deserialize, Serialize};
@@ -15,10 +19,59 @@ use thiserror::Error;
 
 use crate::{data::Leaf2, traits::node_implementation::NodeType};
 
+/// Broad class of a [`HotShotError`]
+///
+/// The numeric value of each category is the thousands digit of the codes of its errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCategory {
+    /// Communicating with other nodes failed, or they did not respond in time
+    Network = 1,
+    /// Data which should have been stored locally is missing or unreadable
+    Storage = 2,
+    /// Data received by this node is invalid
+    Validation = 3,
+    /// Another node provably misbehaved
+    ByzantineEvidence = 4,
+    /// A fault within this node
+    Internal = 5,
+}
+
+impl ErrorCategory {
+    /// The category of errors with `code`, if any
+    #[must_use]
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code / 1000 {
+            1 => Some(Self::Network),
+            2 => Some(Self::Storage),
+            3 => Some(Self::Validation),
+            4 => Some(Self::ByzantineEvidence),
+            5 => Some(Self::Internal),
+            _ => None,
+        }
+    }
+}
+
 /// Error type for `HotShot`
 #[derive(Debug, Error)]
 #[non_exhaustive]
 pub enum HotShotError<TYPES: NodeType> {
+    /// Communicating with other nodes failed
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// Data which should have been stored locally is missing or unreadable
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// Data received from another node failed validation
+    #[error("Validation failed: {0}")]
+    Validation(String),
+
+    /// Another node provably misbehaved, for instance by signing conflicting messages
+    #[error("Byzantine behavior: {0}")]
+    ByzantineEvidence(String),
+
     /// The consensus state machine is in an invalid state
     #[error("Invalid state: {0}")]
     InvalidState(String),
@@ -35,6 +88,10 @@ pub enum HotShotError<TYPES: NodeType> {
     #[error("Failed to deserialize: {0}")]
     FailedToDeserialize(String),
 
+    /// A submitted transaction was already seen in a recent view
+    #[error("Transaction {0} was already seen in a recent view")]
+    DuplicateTransaction(String),
+
     /// The view timed out
     #[error("View {view_number} timed out: {state:?}")]
     ViewTimedOut {
@@ -43,6 +100,67 @@ pub enum HotShotError<TYPES: NodeType> {
         /// The state that the round was in when it timed out
         state: RoundTimedoutState,
     },
+
+    /// An error reported by another process, such as a node whose events API this was received
+    /// from
+    #[error("{}", .0.message)]
+    Reported(ErrorReport),
+}
+
+impl<TYPES: NodeType> HotShotError<TYPES> {
+    /// The stable numeric code of this error.
+    ///
+    /// Codes are never reused or reassigned; new errors get new codes. The thousands digit is the
+    /// [`ErrorCategory`] of the error.
+    #[must_use]
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Network(_) => 1000,
+            Self::ViewTimedOut { .. } => 1001,
+            Self::Storage(_) => 2000,
+            Self::MissingLeaf(_) => 2001,
+            Self::Validation(_) => 3000,
+            Self::DuplicateTransaction(_) => 3001,
+            Self::ByzantineEvidence(_) => 4000,
+            Self::InvalidState(_) => 5000,
+            Self::FailedToSerialize(_) => 5001,
+            Self::FailedToDeserialize(_) => 5002,
+            Self::Reported(report) => report.code,
+        }
+    }
+
+    /// The category of this error
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Reported(report) => report.category,
+            _ => ErrorCategory::from_code(self.code()).unwrap_or(ErrorCategory::Internal),
+        }
+    }
+
+    /// A serializable description of this error
+    #[must_use]
+    pub fn report(&self) -> ErrorReport {
+        match self {
+            Self::Reported(report) => report.clone(),
+            _ => ErrorReport {
+                code: self.code(),
+                category: self.category(),
+                message: self.to_string(),
+            },
+        }
+    }
+}
+
+/// Description of a [`HotShotError`], as serialized in error events
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ErrorReport {
+    /// The [code](HotShotError::code) of the error
+    pub code: u16,
+    /// The category of the error
+    pub category: ErrorCategory,
+    /// Human-readable description of the error
+    pub message: String,
 }
 
 /// Contains information about what the state of the hotshot-consensus was when a round timed out
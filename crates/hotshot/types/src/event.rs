@@ -14,8 +14,10 @@ use crate::{
     data::{DaProposal2, Leaf2, QuorumProposalWrapper, UpgradeProposal, VidDisperseShare},
     error::HotShotError,
     message::Proposal,
-    simple_certificate::{LightClientStateUpdateCertificate, QuorumCertificate2},
+    simple_certificate::{DaCertificate2, LightClientStateUpdateCertificate, QuorumCertificate2},
     traits::{node_implementation::NodeType, ValidatedState},
+    upgrade_readiness::UpgradeReadinessReport,
+    vote::DoubleVoteEvidence,
 };
 
 /// A status event emitted by a `HotShot` instance
@@ -69,13 +71,41 @@ impl<TYPES: NodeType> LeafInfo<TYPES> {
 /// The chain of decided leaves with its corresponding state and VID info.
 pub type LeafChain<TYPES> = Vec<LeafInfo<TYPES>>;
 
-/// Utilities for converting between HotShotError and a string.
+/// Utilities for converting between HotShotError and an [`ErrorReport`](crate::error::ErrorReport).
+///
+/// Errors used to be serialized as just their message, and nodes and consumers of the events API
+/// are not upgraded in lockstep, so plain messages are still accepted. Self-describing formats
+/// encode the report as a structure; binary formats cannot tell a string from a structure, so
+/// they encode it as a JSON string, which older consumers can still read as a message.
 pub mod error_adaptor {
-    use serde::{de::Deserializer, ser::Serializer};
+    use serde::{
+        de::Deserializer,
+        ser::{Error as _, Serializer},
+    };
+
+    use super::{Arc, Deserialize, HotShotError, NodeType, Serialize};
+    use crate::error::ErrorReport;
+
+    /// An error as encoded by either a current or a legacy node
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum EncodedError {
+        /// An error with its code and category
+        Report(ErrorReport),
+        /// Just the message of an error, as serialized by older versions
+        Message(String),
+    }
 
-    use super::{Arc, Deserialize, HotShotError, NodeType};
+    impl<TYPES: NodeType> From<EncodedError> for HotShotError<TYPES> {
+        fn from(encoded: EncodedError) -> Self {
+            match encoded {
+                EncodedError::Report(report) => Self::Reported(report),
+                EncodedError::Message(message) => Self::FailedToDeserialize(message),
+            }
+        }
+    }
 
-    /// Convert a HotShotError into a string
+    /// Convert a HotShotError into an [`ErrorReport`]
     ///
     /// # Errors
     /// Returns `Err` if the serializer fails.
@@ -83,18 +113,31 @@ pub mod error_adaptor {
         elem: &Arc<HotShotError<TYPES>>,
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
-        serializer.serialize_str(&format!("{elem}"))
+        if serializer.is_human_readable() {
+            elem.report().serialize(serializer)
+        } else {
+            let report = serde_json::to_string(&elem.report()).map_err(S::Error::custom)?;
+            serializer.serialize_str(&report)
+        }
     }
 
-    /// Convert a string into a HotShotError
+    /// Convert an [`ErrorReport`], or the message of an error from an older version, into a
+    /// HotShotError
     ///
     /// # Errors
-    /// Returns `Err` if the string cannot be deserialized.
+    /// Returns `Err` if the error cannot be deserialized.
     pub fn deserialize<'de, D: Deserializer<'de>, TYPES: NodeType>(
         deserializer: D,
     ) -> Result<Arc<HotShotError<TYPES>>, D::Error> {
-        let str = String::deserialize(deserializer)?;
-        Ok(Arc::new(HotShotError::FailedToDeserialize(str)))
+        let encoded = if deserializer.is_human_readable() {
+            EncodedError::deserialize(deserializer)?
+        } else {
+            let str = String::deserialize(deserializer)?;
+            serde_json::from_str(&str)
+                .map(EncodedError::Report)
+                .unwrap_or(EncodedError::Message(str))
+        };
+        Ok(Arc::new(encoded.into()))
     }
 }
 
@@ -160,6 +203,12 @@ pub enum EventType<TYPES: NodeType> {
         /// Public key of the leader submitting the proposal
         sender: TYPES::SignatureKey,
     },
+    /// The DA committee certified the block proposed for a view, either in a certificate received
+    /// from the network or in one formed by us
+    DaCertificate {
+        /// The certificate
+        certificate: DaCertificate2<TYPES>,
+    },
     /// Quorum proposal was received from the network
     /// or submitted to the network by us
     QuorumProposal {
@@ -177,6 +226,19 @@ pub enum EventType<TYPES: NodeType> {
         sender: TYPES::SignatureKey,
     },
 
+    /// A node was caught signing two different votes of the same kind in the same view
+    DoubleVote {
+        /// The conflicting votes
+        evidence: DoubleVoteEvidence<TYPES>,
+    },
+
+    /// The leader checked whether the network is ready for the configured upgrade, before
+    /// proposing it
+    UpgradeReadiness {
+        /// Support for the upgrade among the nodes of the current epoch
+        report: UpgradeReadinessReport,
+    },
+
     /// A message destined for external listeners was received
     ExternalMessageReceived {
         /// Public Key of the message sender
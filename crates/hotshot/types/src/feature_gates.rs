@@ -0,0 +1,93 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Protocol features gated on the version in effect.
+//!
+//! Each feature is introduced by a protocol version, and is enabled from the view in which that
+//! version takes effect, as determined by the decided upgrade certificate. Tasks should ask the
+//! [`FeatureGates`] whether a feature is enabled, rather than comparing versions themselves.
+
+use serde::{Deserialize, Serialize};
+use vbs::version::{StaticVersionType, Version};
+
+use crate::{
+    message::UpgradeLock,
+    traits::node_implementation::{NodeType, Versions},
+};
+
+/// A protocol feature introduced by an upgrade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// Solver-based block building
+    Marketplace,
+    /// Proof of stake, with stake tables changing each epoch
+    Epochs,
+    /// Fetching a missing proposal directly from its leader and then other peers
+    ProposalFetch,
+    /// Compression of large network messages
+    MessageCompression,
+}
+
+impl Feature {
+    /// All the features, in the order they were introduced.
+    pub const ALL: [Feature; 4] = [
+        Feature::Marketplace,
+        Feature::Epochs,
+        Feature::ProposalFetch,
+        Feature::MessageCompression,
+    ];
+
+    /// The protocol version which introduces this feature.
+    pub fn version<V: Versions>(self) -> Version {
+        match self {
+            Feature::Marketplace => V::Marketplace::VERSION,
+            Feature::Epochs | Feature::ProposalFetch | Feature::MessageCompression => {
+                V::Epochs::VERSION
+            },
+        }
+    }
+
+    /// Whether this feature is enabled in protocol `version`.
+    pub fn enabled_in<V: Versions>(self, version: Version) -> bool {
+        version >= self.version::<V>()
+    }
+}
+
+/// Registry of which features are enabled in each view.
+#[derive(Clone, Debug)]
+pub struct FeatureGates<TYPES: NodeType, V: Versions> {
+    /// The lock holding the decided upgrade, which determines the version in each view
+    upgrade_lock: UpgradeLock<TYPES, V>,
+}
+
+impl<TYPES: NodeType, V: Versions> FeatureGates<TYPES, V> {
+    /// Feature gates following the upgrade decided in `upgrade_lock`.
+    pub fn new(upgrade_lock: UpgradeLock<TYPES, V>) -> Self {
+        Self { upgrade_lock }
+    }
+
+    /// Whether `feature` is enabled in `view`.
+    ///
+    /// No feature is enabled in a view running a version we do not support.
+    pub async fn enabled(&self, feature: Feature, view: TYPES::View) -> bool {
+        self.upgrade_lock
+            .version(view)
+            .await
+            .is_ok_and(|version| feature.enabled_in::<V>(version))
+    }
+
+    /// The features enabled in `view`.
+    pub async fn active(&self, view: TYPES::View) -> Vec<Feature> {
+        let Ok(version) = self.upgrade_lock.version(view).await else {
+            return Vec::new();
+        };
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| feature.enabled_in::<V>(version))
+            .collect()
+    }
+}
@@ -11,8 +11,10 @@ use url::Url;
 use vec1::Vec1;
 
 use crate::{
-    constants::REQUEST_DATA_DELAY, upgrade_config::UpgradeConfig, HotShotConfig, NodeType,
-    PeerConfig, ValidatorConfig,
+    bandwidth::BandwidthConfig, compression::CompressionConfig, constants::REQUEST_DATA_DELAY,
+    local_builder::LocalBuilderConfig, transaction_dedup::TransactionDedupConfig,
+    upgrade_config::UpgradeConfig, view_timeout::AdaptiveViewTimeoutConfig, HotShotConfig,
+    NodeType, PeerConfig, ValidatorConfig,
 };
 
 /// Default builder URL, used as placeholder
@@ -58,6 +60,24 @@ pub struct HotShotConfigFile<TYPES: NodeType> {
     pub epoch_height: u64,
     /// Epoch start block
     pub epoch_start_block: u64,
+    /// Compression of large messages, `None` to send every message uncompressed
+    #[serde(default)]
+    pub compression: Option<CompressionConfig>,
+    /// Limits on the bandwidth used to send each class of messages
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    /// Fallback builder embedded in the node, `None` to propose an empty block whenever no builder
+    /// responds in time
+    #[serde(default)]
+    pub local_builder: Option<LocalBuilderConfig>,
+    /// Adaptation of the view timeout to recent view durations, `None` to always use
+    /// `next_view_timeout`
+    #[serde(default)]
+    pub adaptive_view_timeout: Option<AdaptiveViewTimeoutConfig>,
+    /// Rejection of transactions seen in recent views, `None` to forward every transaction
+    /// received
+    #[serde(default)]
+    pub transaction_dedup: Option<TransactionDedupConfig>,
 }
 
 impl<TYPES: NodeType> From<HotShotConfigFile<TYPES>> for HotShotConfig<TYPES> {
@@ -87,6 +107,13 @@ impl<TYPES: NodeType> From<HotShotConfigFile<TYPES>> for HotShotConfig<TYPES> {
             stop_voting_time: val.upgrade.stop_voting_time,
             epoch_height: val.epoch_height,
             epoch_start_block: val.epoch_start_block,
+            da_payload_hint_threshold: None,
+            da_payload_hint_urls: vec![],
+            compression: val.compression,
+            bandwidth: val.bandwidth,
+            local_builder: val.local_builder,
+            adaptive_view_timeout: val.adaptive_view_timeout,
+            transaction_dedup: val.transaction_dedup,
         }
     }
 }
@@ -138,6 +165,11 @@ impl<TYPES: NodeType> HotShotConfigFile<TYPES> {
             upgrade: UpgradeConfig::default(),
             epoch_height: 0,
             epoch_start_block: 0,
+            compression: None,
+            bandwidth: BandwidthConfig::default(),
+            local_builder: None,
+            adaptive_view_timeout: None,
+            transaction_dedup: None,
         }
     }
 }
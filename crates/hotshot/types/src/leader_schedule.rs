@@ -0,0 +1,208 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Leader schedules, so that builders and other clients can find upcoming leaders in advance.
+//!
+//! Before epochs, leadership rotates through the eligible leaders in stake table order. With
+//! epochs, the leader of each view is drawn from the epoch's eligible leaders, weighted by stake,
+//! using the epoch's DRB result, which is known once the DRB computation for the epoch completes,
+//! some time before the epoch starts. Given these, a [`LeaderSchedule`] computes the leader of any
+//! view exactly as the committee does. Which views fall in which epoch depends on how many views it
+//! takes to decide the blocks of an epoch, so it cannot be known in advance.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    drb::{
+        election::{generate_stake_cdf, select_randomized_leader},
+        DrbResult,
+    },
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    PeerConfig,
+};
+
+/// How leadership rotates among the eligible leaders
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LeaderRotation {
+    /// Each view is led by the next eligible leader in order, as before epochs
+    RoundRobin,
+    /// Each view is led by an eligible leader drawn by stake, using the DRB result of the epoch
+    Randomized(DrbResult),
+}
+
+/// Everything needed to compute the leaders of an epoch
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct LeaderSchedule<TYPES: NodeType> {
+    /// The epoch, `None` before epochs
+    pub epoch: Option<TYPES::Epoch>,
+    /// The nodes eligible to lead, in stake table order
+    pub eligible_leaders: Vec<PeerConfig<TYPES>>,
+    /// How leadership rotates among them
+    pub rotation: LeaderRotation,
+}
+
+impl<TYPES: NodeType> LeaderSchedule<TYPES> {
+    /// The schedule before epochs, rotating through the nodes of `stake_table` which have stake.
+    ///
+    /// This is the schedule of a network given the stake table from its startup info.
+    #[must_use]
+    pub fn round_robin(stake_table: Vec<PeerConfig<TYPES>>) -> Self {
+        Self {
+            epoch: None,
+            eligible_leaders: stake_table
+                .into_iter()
+                .filter(|peer| !peer.stake_table_entry.stake().is_zero())
+                .collect(),
+            rotation: LeaderRotation::RoundRobin,
+        }
+    }
+
+    /// The schedule of `epoch`, drawing leaders from `eligible_leaders` with `drb_result`.
+    #[must_use]
+    pub fn randomized(
+        epoch: TYPES::Epoch,
+        eligible_leaders: Vec<PeerConfig<TYPES>>,
+        drb_result: DrbResult,
+    ) -> Self {
+        Self {
+            epoch: Some(epoch),
+            eligible_leaders,
+            rotation: LeaderRotation::Randomized(drb_result),
+        }
+    }
+
+    /// The leader of `view`, or `None` if no node is eligible to lead.
+    #[must_use]
+    pub fn leader(&self, view: TYPES::View) -> Option<TYPES::SignatureKey> {
+        self.leaders(*view..*view + 1)
+            .pop()
+            .map(|(_, leader)| leader)
+    }
+
+    /// The leaders of `views`, or nothing if no node is eligible to lead.
+    #[must_use]
+    pub fn leaders(&self, views: Range<u64>) -> Vec<(TYPES::View, TYPES::SignatureKey)> {
+        let entries = self
+            .eligible_leaders
+            .iter()
+            .map(|peer| peer.stake_table_entry.clone())
+            .collect::<Vec<_>>();
+        leaders::<TYPES::SignatureKey>(entries, self.rotation, views.clone())
+            .into_iter()
+            .zip(views)
+            .map(|(leader, view)| (TYPES::View::new(view), leader))
+            .collect()
+    }
+}
+
+/// The leaders of `views` among `eligible_leaders`
+fn leaders<K: SignatureKey>(
+    eligible_leaders: Vec<K::StakeTableEntry>,
+    rotation: LeaderRotation,
+    views: Range<u64>,
+) -> Vec<K> {
+    if eligible_leaders.is_empty() {
+        return vec![];
+    }
+    match rotation {
+        LeaderRotation::RoundRobin => views
+            .map(|view| {
+                let index = usize::try_from(view % eligible_leaders.len() as u64).unwrap();
+                K::public_key(&eligible_leaders[index])
+            })
+            .collect(),
+        LeaderRotation::Randomized(drb_result) => {
+            if eligible_leaders.iter().all(|entry| entry.stake().is_zero()) {
+                return vec![];
+            }
+            let committee = generate_stake_cdf(eligible_leaders, drb_result);
+            views
+                .map(|view| K::public_key(&select_randomized_leader(&committee, view)))
+                .collect()
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::primitives::U256;
+
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    fn stake_table(stakes: &[u64]) -> Vec<<BLSPubKey as SignatureKey>::StakeTableEntry> {
+        stakes
+            .iter()
+            .enumerate()
+            .map(|(i, stake)| {
+                BLSPubKey::generated_from_seed_indexed([0; 32], i as u64)
+                    .0
+                    .stake_table_entry(U256::from(*stake))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_schedule() {
+        let entries = stake_table(&[1, 1, 1]);
+        let keys = entries
+            .iter()
+            .map(BLSPubKey::public_key)
+            .collect::<Vec<_>>();
+        let schedule = leaders::<BLSPubKey>(entries, LeaderRotation::RoundRobin, 4..8);
+        assert_eq!(schedule, [keys[1], keys[2], keys[0], keys[1]]);
+
+        assert!(leaders::<BLSPubKey>(vec![], LeaderRotation::RoundRobin, 0..10).is_empty());
+    }
+
+    #[test]
+    fn test_randomized_schedule() {
+        let drb = [3; 32];
+        let entries = stake_table(&[1, 0, 1000]);
+        let keys = entries
+            .iter()
+            .map(BLSPubKey::public_key)
+            .collect::<Vec<_>>();
+
+        // The schedule is the one the committee computes, for any range of views.
+        let committee = generate_stake_cdf(entries.clone(), drb);
+        let schedule =
+            leaders::<BLSPubKey>(entries.clone(), LeaderRotation::Randomized(drb), 0..200);
+        for (view, leader) in schedule.iter().enumerate() {
+            assert_eq!(
+                *leader,
+                BLSPubKey::public_key(&select_randomized_leader(&committee, view as u64))
+            );
+        }
+        assert_eq!(
+            leaders::<BLSPubKey>(entries.clone(), LeaderRotation::Randomized(drb), 100..150),
+            schedule[100..150]
+        );
+
+        // Nodes without stake never lead, and leadership follows stake.
+        assert!(!schedule.contains(&keys[1]));
+        let heavy = schedule.iter().filter(|leader| **leader == keys[2]).count();
+        assert!(heavy > 190, "{heavy}");
+
+        // Another DRB result gives another schedule.
+        let entries = stake_table(&[1, 1, 1]);
+        assert_ne!(
+            leaders::<BLSPubKey>(entries.clone(), LeaderRotation::Randomized(drb), 0..200),
+            leaders::<BLSPubKey>(entries, LeaderRotation::Randomized([4; 32]), 0..200),
+        );
+
+        assert!(
+            leaders::<BLSPubKey>(stake_table(&[0, 0]), LeaderRotation::Randomized(drb), 0..10)
+                .is_empty()
+        );
+    }
+}
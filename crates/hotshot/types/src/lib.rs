@@ -18,8 +18,16 @@ use traits::{
 use url::Url;
 use vec1::Vec1;
 
-use crate::utils::bincode_opts;
+use crate::{
+    bandwidth::BandwidthConfig, compression::CompressionConfig, local_builder::LocalBuilderConfig,
+    transaction_dedup::TransactionDedupConfig, utils::bincode_opts,
+    view_timeout::AdaptiveViewTimeoutConfig,
+};
+pub mod api_auth;
+pub mod audit;
+pub mod bandwidth;
 pub mod bundle;
+pub mod compression;
 pub mod consensus;
 pub mod constants;
 pub mod data;
@@ -29,9 +37,12 @@ pub mod drb;
 pub mod epoch_membership;
 pub mod error;
 pub mod event;
+pub mod feature_gates;
 /// Holds the configuration file specification for a HotShot node.
 pub mod hotshot_config_file;
+pub mod leader_schedule;
 pub mod light_client;
+pub mod local_builder;
 pub mod message;
 
 /// Holds the network configuration specification for HotShot nodes.
@@ -39,15 +50,21 @@ pub mod network;
 pub mod qc;
 pub mod request_response;
 pub mod signature_key;
+pub mod signature_verifier;
+pub mod signer;
 pub mod simple_certificate;
 pub mod simple_vote;
 pub mod stake_table;
+pub mod telemetry;
 pub mod traits;
+pub mod transaction_dedup;
 
 /// Holds the upgrade configuration specification for HotShot nodes.
 pub mod upgrade_config;
+pub mod upgrade_readiness;
 pub mod utils;
 pub mod vid;
+pub mod view_timeout;
 pub mod vote;
 
 /// Pinned future that is Send and Sync
@@ -205,7 +222,7 @@ pub struct HotShotConfig<TYPES: NodeType> {
     pub fixed_leader_for_gpuvid: usize,
     /// Base duration for next-view timeout, in milliseconds
     pub next_view_timeout: u64,
-    /// Duration of view sync round timeouts
+    /// Longest duration of view sync rounds; shorter rounds are used when the network is fast
     pub view_sync_timeout: Duration,
     /// Number of network bootstrap nodes
     pub num_bootstrap: usize,
@@ -236,6 +253,31 @@ pub struct HotShotConfig<TYPES: NodeType> {
     /// Epoch start block   
     #[serde(default = "default_epoch_start_block")]
     pub epoch_start_block: u64,
+    /// Payload size in bytes above which the DA leader announces a payload hint instead of
+    /// broadcasting the payload to the DA committee, `None` to always broadcast it
+    #[serde(default)]
+    pub da_payload_hint_threshold: Option<usize>,
+    /// Data providers advertised in DA payload hints
+    #[serde(default)]
+    pub da_payload_hint_urls: Vec<Url>,
+    /// Compression of large messages, `None` to send every message uncompressed
+    #[serde(default)]
+    pub compression: Option<CompressionConfig>,
+    /// Limits on the bandwidth used to send each class of messages
+    #[serde(default)]
+    pub bandwidth: BandwidthConfig,
+    /// Fallback builder embedded in the node, `None` to propose an empty block whenever no builder
+    /// responds in time
+    #[serde(default)]
+    pub local_builder: Option<LocalBuilderConfig>,
+    /// Adaptation of the view timeout to recent view durations, `None` to always use
+    /// `next_view_timeout`
+    #[serde(default)]
+    pub adaptive_view_timeout: Option<AdaptiveViewTimeoutConfig>,
+    /// Rejection of transactions seen in recent views, `None` to forward every transaction
+    /// received
+    #[serde(default)]
+    pub transaction_dedup: Option<TransactionDedupConfig>,
 }
 
 fn default_epoch_start_block() -> u64 {
@@ -0,0 +1,556 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Mempool for the fallback builder embedded in the node.
+//!
+//! When a leader gets no block from any external builder within the builder timeout, it would
+//! normally propose an empty block, so the chain makes no progress on transactions for as long as
+//! the builders are unavailable. With a [`LocalBuilderConfig`], the node instead keeps the
+//! transactions it sees gossiped in a [`LocalMempool`], and builds a block from them itself.
+//!
+//! A locally built block pays no fee. It is signed with the same well-known key as null blocks
+//! (see [`null_block::builder_key`](crate::data::null_block::builder_key)), and validators hold it
+//! to the same fee rules as any other block. The node thus only builds one where blocks need not
+//! pay a fee (see [`BlockPayload::requires_fee`](crate::traits::BlockPayload::requires_fee)), and
+//! proposes an empty block otherwise.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::block_contents::{BlockLimits, Transaction};
+
+/// Configuration of the fallback builder embedded in the node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalBuilderConfig {
+    /// Maximum number of transactions kept in the local mempool
+    #[serde(default = "default_max_transactions")]
+    pub max_transactions: usize,
+    /// Maximum total size in bytes of the transactions kept in the local mempool
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    /// Maximum number of transactions a single fairness group, such as a namespace, may keep in
+    /// the local mempool, unlimited if absent
+    #[serde(default)]
+    pub max_transactions_per_group: Option<usize>,
+    /// Number of views after which a transaction which has not been sequenced is dropped
+    #[serde(default = "default_max_age")]
+    pub max_age: u64,
+}
+
+/// Default [`LocalBuilderConfig::max_transactions`]
+fn default_max_transactions() -> usize {
+    10_000
+}
+
+/// Default [`LocalBuilderConfig::max_bytes`]
+fn default_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// Default [`LocalBuilderConfig::max_age`]
+fn default_max_age() -> u64 {
+    100
+}
+
+impl Default for LocalBuilderConfig {
+    fn default() -> Self {
+        Self {
+            max_transactions: default_max_transactions(),
+            max_bytes: default_max_bytes(),
+            max_transactions_per_group: None,
+            max_age: default_max_age(),
+        }
+    }
+}
+
+/// A transaction in the local mempool
+#[derive(Debug)]
+struct Pending<T> {
+    /// The view in which the transaction was received
+    view: u64,
+    /// Position of the transaction in the order received
+    seq: u64,
+    fee: u64,
+    group: u64,
+    size: u64,
+    replacement_key: Option<Vec<u8>>,
+    transaction: T,
+}
+
+/// Transactions seen by this node which may not have been sequenced yet
+///
+/// Transactions are ordered by the [priority fee](Transaction::priority_fee) they offer, highest
+/// first, and then in the order they were received.
+///
+/// Once the mempool is full, a new transaction only gets in by offering a higher fee than the
+/// lowest one in the mempool, evicting the oldest transaction offering that fee. So flooding the
+/// node with transactions cannot push out transactions which offer as much and have been waiting
+/// longer. The same applies within each [fairness group](Transaction::fairness_group) which has
+/// reached [`max_transactions_per_group`](LocalBuilderConfig::max_transactions_per_group).
+///
+/// A transaction with a [replacement key](Transaction::replacement_key) replaces the pending
+/// transaction with the same key if it offers a higher fee, and is dropped otherwise.
+///
+/// Transactions which could not be included in any block under the current [`BlockLimits`] are
+/// dropped rather than kept until they expire.
+#[derive(Debug)]
+pub struct LocalMempool<T: Transaction> {
+    /// Configuration
+    config: LocalBuilderConfig,
+    /// Limits on the contents of the blocks we build
+    limits: BlockLimits,
+    /// The transactions in the mempool
+    transactions: HashMap<Commitment<T>, Pending<T>>,
+    /// Transactions by priority: highest fee first, then first received
+    by_priority: BTreeMap<(Reverse<u64>, u64), Commitment<T>>,
+    /// Transactions in the order they were received
+    by_arrival: BTreeMap<u64, Commitment<T>>,
+    /// Transactions by replacement key
+    by_replacement_key: HashMap<Vec<u8>, Commitment<T>>,
+    /// Number of transactions in each fairness group
+    group_sizes: HashMap<u64, usize>,
+    /// Total size of the transactions
+    bytes: u64,
+    /// Position of the next transaction received
+    next_seq: u64,
+}
+
+impl<T: Transaction> LocalMempool<T> {
+    /// Create an empty mempool
+    #[must_use]
+    pub fn new(config: LocalBuilderConfig) -> Self {
+        Self {
+            config,
+            limits: BlockLimits::default(),
+            transactions: HashMap::new(),
+            by_priority: BTreeMap::new(),
+            by_arrival: BTreeMap::new(),
+            by_replacement_key: HashMap::new(),
+            group_sizes: HashMap::new(),
+            bytes: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// Number of transactions in the mempool
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Whether the mempool is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Update the limits on the contents of blocks, dropping transactions which no longer fit
+    pub fn set_limits(&mut self, limits: BlockLimits) {
+        if limits == self.limits {
+            return;
+        }
+        self.limits = limits;
+        let excluded = self
+            .transactions
+            .iter()
+            .filter(|(_, pending)| !limits.admits(&pending.transaction))
+            .map(|(commitment, _)| *commitment)
+            .collect::<Vec<_>>();
+        if !excluded.is_empty() {
+            tracing::debug!(
+                count = excluded.len(),
+                "dropping transactions which exceed the block limits from the local mempool"
+            );
+        }
+        self.remove(excluded);
+    }
+
+    /// Add transactions received in `view`, skipping duplicates
+    pub fn insert(&mut self, view: u64, transactions: impl IntoIterator<Item = T>) {
+        for transaction in transactions {
+            self.insert_one(view, transaction);
+        }
+    }
+
+    /// Add a transaction received in `view`, if it is not a duplicate and there is room for it
+    fn insert_one(&mut self, view: u64, transaction: T) {
+        let commitment = transaction.commit();
+        if self.transactions.contains_key(&commitment) {
+            return;
+        }
+        let fee = transaction.priority_fee();
+        let group = transaction.fairness_group();
+        let size = transaction.minimum_block_size();
+        let replacement_key = transaction.replacement_key();
+        if size > self.config.max_bytes {
+            tracing::debug!(
+                size,
+                "transaction too large for the local mempool, dropping it"
+            );
+            return;
+        }
+        if !self.limits.admits(&transaction) {
+            tracing::debug!(size, "transaction exceeds the block limits, dropping it");
+            return;
+        }
+
+        let replaced = replacement_key
+            .as_ref()
+            .and_then(|key| self.by_replacement_key.get(key))
+            .copied();
+        if let Some(replaced) = &replaced {
+            if fee <= self.transactions[replaced].fee {
+                tracing::debug!("replacement transaction does not raise the fee, dropping it");
+                return;
+            }
+        }
+        // Nothing is removed until we know the transaction gets in, so a replacement which is
+        // refused leaves the transaction it would have replaced in place.
+        let Some(evicted) = self.make_room(fee, group, size, replaced) else {
+            return;
+        };
+        for commitment in evicted {
+            self.remove_one(&commitment);
+        }
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.by_priority.insert((Reverse(fee), seq), commitment);
+        self.by_arrival.insert(seq, commitment);
+        if let Some(key) = &replacement_key {
+            self.by_replacement_key.insert(key.clone(), commitment);
+        }
+        *self.group_sizes.entry(group).or_default() += 1;
+        self.bytes += size;
+        self.transactions.insert(
+            commitment,
+            Pending {
+                view,
+                seq,
+                fee,
+                group,
+                size,
+                replacement_key,
+                transaction,
+            },
+        );
+    }
+
+    /// The transactions to remove to make room for one offering `fee` in `group` and taking up
+    /// `size` bytes, replacing `replaced` if given
+    ///
+    /// Besides the replaced transaction, the transactions evicted are the oldest of those offering
+    /// the lowest fee, which must be lower than `fee`. Returns `None` if the transaction cannot
+    /// get in.
+    fn make_room(
+        &self,
+        fee: u64,
+        group: u64,
+        size: u64,
+        replaced: Option<Commitment<T>>,
+    ) -> Option<Vec<Commitment<T>>> {
+        let mut evicted = Vec::from_iter(replaced);
+        let evicted_from = |evicted: &[Commitment<T>], group: Option<u64>| {
+            evicted
+                .iter()
+                .map(|commitment| &self.transactions[commitment])
+                .filter(|pending| group.is_none_or(|group| pending.group == group))
+                .fold((0, 0), |(count, bytes), pending| {
+                    (count + 1, bytes + pending.size)
+                })
+        };
+
+        // Candidates for eviction, lowest fee first and then oldest first.
+        let mut candidates = self
+            .by_priority
+            .iter()
+            .filter(|((Reverse(candidate_fee), _), _)| *candidate_fee < fee)
+            .map(|((Reverse(candidate_fee), seq), commitment)| (*candidate_fee, *seq, *commitment))
+            .filter(|(_, _, commitment)| Some(*commitment) != replaced)
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(candidate_fee, seq, _)| (*candidate_fee, *seq));
+        let mut candidates = candidates.into_iter().map(|(_, _, commitment)| commitment);
+
+        if let Some(max) = self.config.max_transactions_per_group {
+            let group_size = self.group_sizes.get(&group).copied().unwrap_or(0);
+            if group_size - evicted_from(&evicted, Some(group)).0 >= max {
+                let Some(commitment) = candidates
+                    .clone()
+                    .find(|commitment| self.transactions[commitment].group == group)
+                else {
+                    tracing::debug!(
+                        group,
+                        "group is using its share of the local mempool, dropping transaction"
+                    );
+                    return None;
+                };
+                evicted.push(commitment);
+            }
+        }
+        loop {
+            let (count, bytes) = evicted_from(&evicted, None);
+            if self.transactions.len() - count < self.config.max_transactions
+                && self.bytes - bytes + size <= self.config.max_bytes
+            {
+                break;
+            }
+            let Some(commitment) = candidates.find(|commitment| !evicted.contains(commitment))
+            else {
+                tracing::debug!("local mempool is full, dropping transaction");
+                return None;
+            };
+            evicted.push(commitment);
+        }
+
+        if evicted.len() > usize::from(replaced.is_some()) {
+            tracing::debug!(
+                count = evicted.len() - usize::from(replaced.is_some()),
+                "evicting transactions from the local mempool"
+            );
+        }
+        Some(evicted)
+    }
+
+    /// Remove a transaction, if it is in the mempool
+    fn remove_one(&mut self, commitment: &Commitment<T>) {
+        let Some(pending) = self.transactions.remove(commitment) else {
+            return;
+        };
+        self.by_priority
+            .remove(&(Reverse(pending.fee), pending.seq));
+        self.by_arrival.remove(&pending.seq);
+        if let Some(key) = &pending.replacement_key {
+            if self.by_replacement_key.get(key) == Some(commitment) {
+                self.by_replacement_key.remove(key);
+            }
+        }
+        if let Some(group_size) = self.group_sizes.get_mut(&pending.group) {
+            *group_size -= 1;
+            if *group_size == 0 {
+                self.group_sizes.remove(&pending.group);
+            }
+        }
+        self.bytes -= pending.size;
+    }
+
+    /// Remove transactions which have been included in a block
+    pub fn remove(&mut self, included: impl IntoIterator<Item = Commitment<T>>) {
+        for commitment in included {
+            self.remove_one(&commitment);
+        }
+    }
+
+    /// Drop transactions which were received too long before `view`
+    pub fn expire(&mut self, view: u64) {
+        let oldest = view.saturating_sub(self.config.max_age);
+        while let Some((_, commitment)) = self.by_arrival.first_key_value() {
+            if self.transactions[commitment].view >= oldest {
+                break;
+            }
+            let commitment = *commitment;
+            self.remove_one(&commitment);
+        }
+    }
+
+    /// The transactions in the mempool, in priority order
+    pub fn transactions(&self) -> impl Iterator<Item = &T> {
+        self.by_priority
+            .values()
+            .map(|commitment| &self.transactions[commitment].transaction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use committable::RawCommitmentBuilder;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    struct TestTx {
+        id: u64,
+        fee: u64,
+        group: u64,
+        key: Option<u8>,
+    }
+
+    impl TestTx {
+        fn new(id: u64, fee: u64) -> Self {
+            Self {
+                id,
+                fee,
+                group: 0,
+                key: None,
+            }
+        }
+    }
+
+    impl Committable for TestTx {
+        fn commit(&self) -> Commitment<Self> {
+            RawCommitmentBuilder::new("TestTx")
+                .u64_field("id", self.id)
+                .u64_field("fee", self.fee)
+                .finalize()
+        }
+    }
+
+    impl Transaction for TestTx {
+        fn minimum_block_size(&self) -> u64 {
+            10
+        }
+
+        fn priority_fee(&self) -> u64 {
+            self.fee
+        }
+
+        fn fairness_group(&self) -> u64 {
+            self.group
+        }
+
+        fn replacement_key(&self) -> Option<Vec<u8>> {
+            self.key.map(|key| vec![key])
+        }
+    }
+
+    fn ids(mempool: &LocalMempool<TestTx>) -> Vec<u64> {
+        mempool.transactions().map(|tx| tx.id).collect()
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig::default());
+        mempool.insert(1, [TestTx::new(0, 1), TestTx::new(1, 5), TestTx::new(2, 1)]);
+        mempool.insert(2, [TestTx::new(3, 3), TestTx::new(0, 1)]);
+
+        // Highest fee first, and the order received among equal fees.
+        assert_eq!(ids(&mempool), [1, 3, 0, 2]);
+
+        mempool.remove([TestTx::new(1, 5).commit()]);
+        assert_eq!(ids(&mempool), [3, 0, 2]);
+
+        mempool.expire(2 + mempool.config.max_age);
+        assert_eq!(ids(&mempool), [3]);
+    }
+
+    #[test]
+    fn test_lowest_fee_eviction() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig {
+            max_transactions: 3,
+            ..Default::default()
+        });
+        mempool.insert(1, [TestTx::new(0, 1), TestTx::new(1, 5), TestTx::new(2, 1)]);
+        assert_eq!(ids(&mempool), [1, 0, 2]);
+
+        // A full mempool does not take a transaction offering no more than the lowest fee.
+        mempool.insert(2, [TestTx::new(3, 1)]);
+        assert_eq!(ids(&mempool), [1, 0, 2]);
+
+        // A higher fee evicts the oldest of the lowest fee transactions.
+        mempool.insert(2, [TestTx::new(4, 2)]);
+        assert_eq!(ids(&mempool), [1, 4, 2]);
+    }
+
+    #[test]
+    fn test_memory_pressure() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig {
+            max_bytes: 20,
+            ..Default::default()
+        });
+        mempool.insert(1, [TestTx::new(0, 1), TestTx::new(1, 1)]);
+        mempool.insert(1, [TestTx::new(2, 3)]);
+        assert_eq!(ids(&mempool), [2, 1]);
+        assert_eq!(mempool.bytes, 20);
+    }
+
+    #[test]
+    fn test_block_limits() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig::default());
+        mempool.insert(1, [TestTx::new(0, 1), TestTx::new(1, 1)]);
+
+        // Transactions which no longer fit in a block are dropped, and new ones are refused.
+        let limits = BlockLimits {
+            max_group_size: Some(5),
+            ..Default::default()
+        };
+        mempool.set_limits(limits);
+        assert!(mempool.is_empty());
+        assert_eq!(mempool.bytes, 0);
+        mempool.insert(1, [TestTx::new(2, 1)]);
+        assert!(mempool.is_empty());
+
+        mempool.set_limits(BlockLimits::default());
+        mempool.insert(1, [TestTx::new(2, 1)]);
+        assert_eq!(ids(&mempool), [2]);
+    }
+
+    #[test]
+    fn test_group_cap() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig {
+            max_transactions_per_group: Some(2),
+            ..Default::default()
+        });
+        let tx = |id, fee, group| TestTx {
+            group,
+            ..TestTx::new(id, fee)
+        };
+        mempool.insert(1, [tx(0, 1, 0), tx(1, 2, 0), tx(2, 1, 1)]);
+
+        // The group is at its cap, so a transaction only gets in by outbidding one of the group's
+        // own transactions.
+        mempool.insert(1, [tx(3, 1, 0)]);
+        assert_eq!(ids(&mempool), [1, 0, 2]);
+        mempool.insert(1, [tx(4, 3, 0)]);
+        assert_eq!(ids(&mempool), [4, 1, 2]);
+    }
+
+    #[test]
+    fn test_replace_by_fee() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig::default());
+        let tx = |id, fee| TestTx {
+            key: Some(7),
+            ..TestTx::new(id, fee)
+        };
+        mempool.insert(1, [tx(0, 2), TestTx::new(1, 1)]);
+
+        // A replacement which does not raise the fee is dropped...
+        mempool.insert(1, [tx(2, 2)]);
+        assert_eq!(ids(&mempool), [0, 1]);
+
+        // ...and one which does takes the place of the pending transaction.
+        mempool.insert(1, [tx(3, 3)]);
+        assert_eq!(ids(&mempool), [3, 1]);
+        assert_eq!(mempool.by_replacement_key.len(), 1);
+    }
+
+    #[test]
+    fn test_refused_replacement_keeps_original() {
+        let mut mempool = LocalMempool::new(LocalBuilderConfig {
+            max_transactions_per_group: Some(1),
+            ..Default::default()
+        });
+        let tx = |id, fee, group, key| TestTx {
+            group,
+            key,
+            ..TestTx::new(id, fee)
+        };
+        mempool.insert(1, [tx(0, 1, 0, Some(7)), tx(1, 5, 1, None)]);
+
+        // The replacement raises the fee, but its group is full of transactions offering more, so
+        // it is refused and the transaction it would have replaced stays.
+        mempool.insert(1, [tx(2, 2, 1, Some(7))]);
+        assert_eq!(ids(&mempool), [1, 0]);
+        assert_eq!(mempool.by_replacement_key.len(), 1);
+
+        // Replacing within the same group frees the replaced transaction's share.
+        mempool.insert(1, [tx(3, 2, 0, Some(7))]);
+        assert_eq!(ids(&mempool), [1, 3]);
+    }
+}
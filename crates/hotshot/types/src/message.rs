@@ -27,11 +27,15 @@ use vbs::{
 use crate::{
     data::{
         vid_disperse::{ADVZDisperseShare, VidDisperseShare2},
-        DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2,
-        QuorumProposalWrapper, UpgradeProposal,
+        DaPayloadHint2, DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2,
+        QuorumProposalWrapper, UpgradeProposal, ViewChangeEvidence2,
     },
     epoch_membership::EpochMembership,
-    request_response::ProposalRequestPayload,
+    feature_gates::{Feature, FeatureGates},
+    request_response::{
+        ProposalFetchRequestPayload, ProposalRequestPayload, ViewEvidenceRequestPayload,
+    },
+    signature_verifier::SignatureVerifier,
     simple_certificate::{
         DaCertificate, DaCertificate2, EpochRootQuorumCertificate, NextEpochQuorumCertificate2,
         QuorumCertificate2, UpgradeCertificate, ViewSyncCommitCertificate,
@@ -44,6 +48,7 @@ use crate::{
         ViewSyncFinalizeVote2, ViewSyncPreCommitVote, ViewSyncPreCommitVote2,
     },
     traits::{
+        block_contents::TransactionPriority,
         election::Membership,
         network::{DataRequest, ResponseMessage, ViewMessage},
         node_implementation::{ConsensusTime, NodeType, Versions},
@@ -158,7 +163,10 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
     fn view_number(&self) -> TYPES::View {
         match &self {
             MessageKind::Consensus(message) => message.view_number(),
-            MessageKind::Data(DataMessage::SubmitTransaction(_, v)) => *v,
+            MessageKind::Data(
+                DataMessage::SubmitTransaction(_, v)
+                | DataMessage::SubmitPrioritizedTransaction(_, _, v),
+            ) => *v,
             MessageKind::Data(DataMessage::RequestData(msg)) => msg.view,
             MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
                 ResponseMessage::Found(m) => m.view_number(),
@@ -173,7 +181,11 @@ impl<TYPES: NodeType> HasEpoch<TYPES> for MessageKind<TYPES> {
     fn epoch(&self) -> Option<TYPES::Epoch> {
         match &self {
             MessageKind::Consensus(message) => message.epoch_number(),
-            MessageKind::Data(DataMessage::SubmitTransaction(..) | DataMessage::RequestData(_))
+            MessageKind::Data(
+                DataMessage::SubmitTransaction(..)
+                | DataMessage::SubmitPrioritizedTransaction(..)
+                | DataMessage::RequestData(_),
+            )
             | MessageKind::External(_) => None,
             MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
                 ResponseMessage::Found(m) => m.epoch_number(),
@@ -276,6 +288,24 @@ pub enum GeneralConsensusMessage<TYPES: NodeType> {
 
     /// Message with a Timeout vote
     TimeoutVote2(TimeoutVote2<TYPES>),
+
+    /// A node which fell behind needs evidence that the network has moved past its view.
+    ViewEvidenceRequested(
+        ViewEvidenceRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+
+    /// A peer has responded with the latest evidence that it has seen for a view change.
+    ViewEvidenceResponse(ViewChangeEvidence2<TYPES>),
+
+    /// A node which received a QC for a proposal it never saw is fetching the proposal from us.
+    ProposalFetchRequested(
+        ProposalFetchRequestPayload<TYPES>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    ),
+
+    /// A peer has responded to a proposal fetch request with the proposal.
+    ProposalFetchResponse(Proposal<TYPES, QuorumProposal2<TYPES>>),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -309,6 +339,9 @@ pub enum DaConsensusMessage<TYPES: NodeType> {
     ///
     /// Like [`DaProposal`]. Use `Msg` suffix to distinguish from `VidDisperse`.
     VidDisperseMsg2(Proposal<TYPES, VidDisperseShare2<TYPES>>),
+
+    /// Proposal for data availability committee whose payload is fetched out of band
+    DaPayloadHint2(Proposal<TYPES, DaPayloadHint2<TYPES>>),
 }
 
 /// Messages for sequencing consensus.
@@ -340,6 +373,14 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                         p.data.view_number()
                     },
                     GeneralConsensusMessage::ProposalRequested(req, _) => req.view_number,
+                    GeneralConsensusMessage::ViewEvidenceRequested(req, _) => req.view_number,
+                    GeneralConsensusMessage::ProposalFetchRequested(req, _) => req.view_number,
+                    GeneralConsensusMessage::ProposalFetchResponse(proposal) => {
+                        proposal.data.view_number()
+                    },
+                    GeneralConsensusMessage::ViewEvidenceResponse(evidence) => {
+                        evidence.view_to_enter()
+                    },
                     GeneralConsensusMessage::ProposalResponse(proposal) => {
                         proposal.data.view_number()
                     },
@@ -406,6 +447,7 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     DaConsensusMessage::DaVote2(vote_message) => vote_message.view_number(),
                     DaConsensusMessage::DaCertificate2(cert) => cert.view_number,
                     DaConsensusMessage::VidDisperseMsg2(disperse) => disperse.data.view_number(),
+                    DaConsensusMessage::DaPayloadHint2(p) => p.data.view_number(),
                 }
             },
         }
@@ -427,6 +469,12 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                         p.data.epoch()
                     },
                     GeneralConsensusMessage::ProposalRequested(..) => None,
+                    GeneralConsensusMessage::ViewEvidenceRequested(..) => None,
+                    GeneralConsensusMessage::ProposalFetchRequested(..) => None,
+                    GeneralConsensusMessage::ProposalFetchResponse(proposal) => {
+                        proposal.data.epoch()
+                    },
+                    GeneralConsensusMessage::ViewEvidenceResponse(evidence) => evidence.epoch(),
                     GeneralConsensusMessage::ProposalResponse(proposal) => proposal.data.epoch(),
                     GeneralConsensusMessage::ProposalResponse2(proposal) => proposal.data.epoch(),
                     GeneralConsensusMessage::Vote(vote_message) => vote_message.epoch(),
@@ -479,6 +527,7 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     },
                     DaConsensusMessage::DaVote2(vote_message) => vote_message.epoch(),
                     DaConsensusMessage::DaCertificate2(cert) => cert.epoch(),
+                    DaConsensusMessage::DaPayloadHint2(p) => p.data.epoch(),
                 }
             },
         }
@@ -499,6 +548,14 @@ pub enum DataMessage<TYPES: NodeType> {
     RequestData(DataRequest<TYPES>),
     /// A response to a data request
     DataResponse(ResponseMessage<TYPES>),
+    /// Contains a transaction to be submitted along with the priority its submitter chose
+    ///
+    /// The priority is not part of the transaction's binary encoding, so it travels separately.
+    /// Transactions offering the default priority are sent as [`SubmitTransaction`] instead,
+    /// which nodes without this variant understand.
+    ///
+    /// [`SubmitTransaction`]: DataMessage::SubmitTransaction
+    SubmitPrioritizedTransaction(TYPES::Transaction, TransactionPriority, TYPES::View),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -576,7 +633,13 @@ where
         let proposed_leaf = Leaf2::from_quorum_proposal(&self.data);
 
         ensure!(
-            view_leader_key.validate(&self.signature, proposed_leaf.commit().as_ref()),
+            SignatureVerifier::shared()
+                .verify(
+                    view_leader_key,
+                    self.signature.clone(),
+                    proposed_leaf.commit().as_ref()
+                )
+                .await,
             "Proposal signature is invalid."
         );
 
@@ -663,9 +726,14 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
         }
     }
 
+    /// The features enabled by the versions in effect in each view
+    pub fn features(&self) -> FeatureGates<TYPES, V> {
+        FeatureGates::new(self.clone())
+    }
+
     /// Return whether epochs are enabled in the given view
     pub async fn epochs_enabled(&self, view: TYPES::View) -> bool {
-        self.version_infallible(view).await >= V::Epochs::VERSION
+        Feature::Epochs.enabled_in::<V>(self.version_infallible(view).await)
     }
 
     /// Serialize a message with a version number, using `message.view_number()` and an optional decided upgrade certificate to determine the message's version.
@@ -74,34 +74,7 @@ where
         signers: &BitSlice,
         sigs: &[A::Signature],
     ) -> Result<Self::Qc, SignatureError> {
-        if signers.len() != qc_pp.stake_entries.len() {
-            return Err(SignatureError::ParameterError(format!(
-                "bit vector len {} != the number of stake entries {}",
-                signers.len(),
-                qc_pp.stake_entries.len(),
-            )));
-        }
-        let total_weight: U256 =
-            qc_pp
-                .stake_entries
-                .iter()
-                .zip(signers.iter())
-                .fold(
-                    U256::ZERO,
-                    |acc, (entry, b)| {
-                        if *b {
-                            acc + entry.stake_amount
-                        } else {
-                            acc
-                        }
-                    },
-                );
-        if total_weight < qc_pp.threshold {
-            return Err(SignatureError::ParameterError(format!(
-                "total_weight {} less than threshold {}",
-                total_weight, qc_pp.threshold,
-            )));
-        }
+        check_signers_weight(qc_pp, signers)?;
         let mut ver_keys = vec![];
         for (entry, b) in qc_pp.stake_entries.iter().zip(signers.iter()) {
             if *b {
@@ -120,6 +93,34 @@ where
         Ok((sig, signers.into()))
     }
 
+    fn aggregate_partial(
+        agg_sig_pp: &A::PublicParameter,
+        aggregate: Option<&A::Signature>,
+        ver_key: &A::VerificationKey,
+        sig: &A::Signature,
+    ) -> Result<A::Signature, SignatureError> {
+        match aggregate {
+            None => Ok(sig.clone()),
+            // The aggregated BLS signature only depends on the partial signatures, the keys just
+            // have to line up with them.
+            Some(aggregate) => A::aggregate(
+                agg_sig_pp,
+                &[ver_key.clone(), ver_key.clone()],
+                &[aggregate.clone(), sig.clone()],
+            ),
+        }
+    }
+
+    fn assemble_aggregated(
+        qc_pp: &Self::QcProverParams,
+        signers: &BitSlice,
+        aggregate: A::Signature,
+    ) -> Result<Self::Qc, SignatureError> {
+        check_signers_weight(qc_pp, signers)?;
+
+        Ok((aggregate, signers.into()))
+    }
+
     fn check(
         qc_vp: &Self::QcVerifierParams,
         message: &GenericArray<A::MessageUnit, Self::MessageLength>,
@@ -192,6 +193,44 @@ where
     }
 }
 
+/// Checks that `signers` matches the stake table and that their accumulated weight reaches the
+/// threshold.
+fn check_signers_weight<K: SignatureKey, P: for<'a> Deserialize<'a>>(
+    qc_pp: &QcParams<K, P>,
+    signers: &BitSlice,
+) -> Result<U256, SignatureError> {
+    if signers.len() != qc_pp.stake_entries.len() {
+        return Err(SignatureError::ParameterError(format!(
+            "bit vector len {} != the number of stake entries {}",
+            signers.len(),
+            qc_pp.stake_entries.len(),
+        )));
+    }
+    let total_weight: U256 =
+        qc_pp
+            .stake_entries
+            .iter()
+            .zip(signers.iter())
+            .fold(
+                U256::ZERO,
+                |acc, (entry, b)| {
+                    if *b {
+                        acc + entry.stake_amount
+                    } else {
+                        acc
+                    }
+                },
+            );
+    if total_weight < qc_pp.threshold {
+        return Err(SignatureError::ParameterError(format!(
+            "total_weight {} less than threshold {}",
+            total_weight, qc_pp.threshold,
+        )));
+    }
+
+    Ok(total_weight)
+}
+
 #[cfg(test)]
 mod tests {
     use jf_signature::{
@@ -252,6 +291,37 @@ mod tests {
                 vec![key_pair2.ver_key(), key_pair3.ver_key()],
             );
 
+            // incremental aggregation yields the same QC
+            let aggregate = [(&key_pair2, &sig2), (&key_pair3, &sig3)].into_iter().fold(
+                None,
+                |aggregate, (key_pair, sig)| {
+                    Some(
+                        BitVectorQc::<$aggsig>::aggregate_partial(
+                            &agg_sig_pp,
+                            aggregate.as_ref(),
+                            &key_pair.ver_key(),
+                            sig,
+                        )
+                        .unwrap(),
+                    )
+                },
+            );
+            let aggregated_qc = BitVectorQc::<$aggsig>::assemble_aggregated(
+                &qc_pp,
+                signers.as_bitslice(),
+                aggregate.unwrap(),
+            )
+            .unwrap();
+            assert_eq!(qc, aggregated_qc);
+            assert!(BitVectorQc::<$aggsig>::check(&qc_pp, &msg.into(), &aggregated_qc).is_ok());
+            // aggregated signatures under threshold
+            assert!(BitVectorQc::<$aggsig>::assemble_aggregated(
+                &qc_pp,
+                bitvec![0, 0, 1].as_bitslice(),
+                sig3.clone(),
+            )
+            .is_err());
+
             // Check the QC and the QcParams can be serialized / deserialized
             assert_eq!(
                 qc,
@@ -7,10 +7,13 @@
 //! Types for the request/response implementations. This module incorporates all
 //! of the shared types for all of the network backends.
 
-use committable::{Committable, RawCommitmentBuilder};
+use committable::{Commitment, Committable, RawCommitmentBuilder};
 use serde::{Deserialize, Serialize};
 
-use crate::traits::{node_implementation::NodeType, signature_key::SignatureKey};
+use crate::{
+    data::Leaf2,
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 /// A signed request for a proposal.
@@ -31,3 +34,50 @@ impl<TYPES: NodeType> Committable for ProposalRequestPayload<TYPES> {
             .finalize()
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+/// A signed request for evidence that the network has moved past a view, so that a node which
+/// fell behind can catch up without running view sync.
+pub struct ViewEvidenceRequestPayload<TYPES: NodeType> {
+    /// The view the requesting node is stuck in.
+    pub view_number: TYPES::View,
+
+    /// Our public key. The ensures that the recipient can reply to
+    /// us directly.
+    pub key: TYPES::SignatureKey,
+}
+
+impl<TYPES: NodeType> Committable for ViewEvidenceRequestPayload<TYPES> {
+    fn commit(&self) -> committable::Commitment<Self> {
+        RawCommitmentBuilder::new("signed view evidence request commitment")
+            .u64_field("view number", *self.view_number)
+            .var_size_bytes(&self.key.to_bytes())
+            .finalize()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(bound(deserialize = ""))]
+/// A signed request to a specific peer for a proposal referenced by a QC we received, when we never
+/// saw the proposal itself.
+pub struct ProposalFetchRequestPayload<TYPES: NodeType> {
+    /// The view of the missing proposal.
+    pub view_number: TYPES::View,
+
+    /// The leaf the QC certifies, so that the peer can return the matching proposal.
+    pub leaf_commit: Commitment<Leaf2<TYPES>>,
+
+    /// Our public key. The ensures that the recipient can reply to
+    /// us directly.
+    pub key: TYPES::SignatureKey,
+}
+
+impl<TYPES: NodeType> Committable for ProposalFetchRequestPayload<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("signed proposal fetch request commitment")
+            .u64_field("view number", *self.view_number)
+            .field("leaf commitment", self.leaf_commit)
+            .var_size_bytes(&self.key.to_bytes())
+            .finalize()
+    }
+}
@@ -6,16 +6,23 @@
 
 //! Types and structs for the hotshot signature keys
 
+use std::collections::HashMap;
+
 use alloy::primitives::U256;
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::Zero;
 use ark_serialize::SerializationError;
 use bitvec::{slice::BitSlice, vec::BitVec};
 use digest::generic_array::GenericArray;
 use jf_signature::{
-    bls_over_bn254::{BLSOverBN254CurveSignatureScheme, KeyPair, SignKey, VerKey},
+    bls_over_bn254::{hash_to_curve, BLSOverBN254CurveSignatureScheme, KeyPair, SignKey, VerKey},
+    constants::CS_ID_BLS_BN254,
     SignatureError, SignatureScheme,
 };
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha20Rng;
+use sha3::Keccak256;
 use tracing::instrument;
 
 use crate::{
@@ -68,6 +75,40 @@ impl SignatureKey for BLSPubKey {
         BLSOverBN254CurveSignatureScheme::verify(&(), self, data, signature).is_ok()
     }
 
+    fn batch_validate(batch: &[(&Self, &Self::PureAssembledSignatureType, &[u8])]) -> bool {
+        // A signature `s` by `k` on `m` is valid if `e(s, g) = e(H(m), k)`, where `H` hashes `m`
+        // followed by the ciphersuite ID, as `BLSOverBN254CurveSignatureScheme` does. Each
+        // signature and its key are weighted by a random scalar, unknown to the signers, so that
+        // invalid signatures cannot be chosen to cancel each other out, and the whole batch is
+        // checked at once as `e(sum(s), g) = prod_m e(H(m), sum(k))`, summing the keys of each
+        // message separately. This is sound as long as no key was chosen to cancel out another,
+        // which proofs of possession rule out.
+        if let [(key, signature, data)] = batch {
+            return key.validate(signature, data);
+        }
+        let mut rng = rand::thread_rng();
+        let mut sigma = G1Projective::zero();
+        let mut by_message = HashMap::<&[u8], G2Projective>::new();
+        for (key, signature, data) in batch {
+            let weight = Fr::from(rng.gen_range(1..=u128::MAX));
+            sigma += signature.sigma * weight;
+            *by_message.entry(*data).or_default() += key.to_affine() * weight;
+        }
+        // Check `e(-sum(s), g) * prod_m e(H(m), sum(k)) = 1` with a single final exponentiation.
+        let (g1, g2): (Vec<G1Affine>, Vec<G2Affine>) = by_message
+            .into_iter()
+            .map(|(data, key)| {
+                (
+                    hash_to_curve::<Keccak256>(&[data, CS_ID_BLS_BN254.as_bytes()].concat())
+                        .into_affine(),
+                    key.into_affine(),
+                )
+            })
+            .chain([((-sigma).into_affine(), G2Affine::generator())])
+            .unzip();
+        Bn254::multi_pairing(g1, g2).is_zero()
+    }
+
     fn sign(
         sk: &Self::PrivateKey,
         data: &[u8],
@@ -148,6 +189,31 @@ impl SignatureKey for BLSPubKey {
             .expect("this assembling shouldn't fail")
     }
 
+    fn aggregate_partial(
+        aggregate: Option<&Self::PureAssembledSignatureType>,
+        signer: &Self,
+        sig: &Self::PureAssembledSignatureType,
+    ) -> Self::PureAssembledSignatureType {
+        BitVectorQc::<BLSOverBN254CurveSignatureScheme>::aggregate_partial(
+            &(),
+            aggregate,
+            signer,
+            sig,
+        )
+        .expect("this aggregation shouldn't fail")
+    }
+
+    fn assemble_aggregated(
+        real_qc_pp: &Self::QcParams,
+        signers: &BitSlice,
+        aggregate: Self::PureAssembledSignatureType,
+    ) -> Self::QcType {
+        BitVectorQc::<BLSOverBN254CurveSignatureScheme>::assemble_aggregated(
+            real_qc_pp, signers, aggregate,
+        )
+        .expect("this assembling shouldn't fail")
+    }
+
     fn genesis_proposer_pk() -> Self {
         let kp = KeyPair::generate(&mut ChaCha20Rng::from_seed([0u8; 32]));
         kp.ver_key()
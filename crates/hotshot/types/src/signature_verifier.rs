@@ -0,0 +1,372 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Batched signature verification shared by all tasks.
+//!
+//! Verifying votes and proposals one at a time is a CPU hot spot with large committees. Instead,
+//! tasks submit verifications to a shared pool of worker threads. Each worker takes whatever
+//! verifications are pending when it becomes free and checks them as one batch with
+//! [`SignatureKey::batch_validate`], so batches grow with load without delaying verification when
+//! the node is idle. Signatures which are known together, such as the votes of a certificate, can be
+//! submitted at once with [`SignatureVerifier::verify_all`], so that they land in the same batch. If
+//! a batch fails, its signatures are checked individually to find the invalid ones.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, LazyLock, Mutex,
+    },
+    thread,
+};
+
+use futures::future::join_all;
+use tokio::sync::oneshot;
+
+use crate::traits::signature_key::SignatureKey;
+
+/// Most verifications checked in a single batch, unless a single submission is larger
+const MAX_BATCH_SIZE: usize = 256;
+
+/// A pending verification
+struct Request<K: SignatureKey> {
+    key: K,
+    signature: K::PureAssembledSignatureType,
+    data: Arc<[u8]>,
+    respond: oneshot::Sender<bool>,
+}
+
+/// Handle to a pool of threads verifying signatures of type `K` in batches
+#[derive(Debug)]
+pub struct SignatureVerifier<K: SignatureKey> {
+    requests: Sender<Vec<Request<K>>>,
+}
+
+impl<K: SignatureKey> Clone for SignatureVerifier<K> {
+    fn clone(&self) -> Self {
+        Self {
+            requests: self.requests.clone(),
+        }
+    }
+}
+
+impl<K: SignatureKey + 'static> SignatureVerifier<K> {
+    /// Start a pool of `threads` verification threads.
+    ///
+    /// The threads exit once every handle to the pool has been dropped.
+    #[must_use]
+    pub fn new(threads: usize) -> Self {
+        let (requests, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..threads.max(1) {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name(format!("signature-verifier-{i}"))
+                .spawn(move || run_worker(&receiver))
+                .expect("failed to spawn signature verification thread");
+        }
+        Self { requests }
+    }
+
+    /// The pool shared by all tasks in this process, started on first use.
+    ///
+    /// The pool has one thread for every other available CPU, leaving the rest for the tasks.
+    #[must_use]
+    pub fn shared() -> Self {
+        static POOLS: LazyLock<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>> =
+            LazyLock::new(Mutex::default);
+
+        let mut pools = POOLS.lock().unwrap_or_else(|err| err.into_inner());
+        pools
+            .entry(TypeId::of::<K>())
+            .or_insert_with(|| {
+                let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+                Box::new(Self::new(cpus / 2))
+            })
+            .downcast_ref::<Self>()
+            .expect("pools are keyed by signature key type")
+            .clone()
+    }
+
+    /// Check that `signature` is a valid signature by `key` on `data`.
+    ///
+    /// `key` must be a stake table key; see [`SignatureKey::batch_validate`].
+    pub async fn verify(
+        &self,
+        key: K,
+        signature: K::PureAssembledSignatureType,
+        data: impl Into<Vec<u8>>,
+    ) -> bool {
+        self.verify_all(vec![(key, signature)], data).await[0]
+    }
+
+    /// Check each of `signatures`, by the paired keys, on the same `data`.
+    ///
+    /// This is meant for signatures which are received together, such as the votes of a
+    /// certificate: they are submitted at once, so they are checked in the same batch. Returns
+    /// whether each signature is valid, in order. Keys must be stake table keys; see
+    /// [`SignatureKey::batch_validate`].
+    pub async fn verify_all(
+        &self,
+        signatures: Vec<(K, K::PureAssembledSignatureType)>,
+        data: impl Into<Vec<u8>>,
+    ) -> Vec<bool> {
+        let data: Arc<[u8]> = data.into().into();
+        let (requests, responses): (Vec<_>, Vec<_>) = signatures
+            .into_iter()
+            .map(|(key, signature)| {
+                let (respond, response) = oneshot::channel();
+                let request = Request {
+                    key,
+                    signature,
+                    data: Arc::clone(&data),
+                    respond,
+                };
+                (request, response)
+            })
+            .unzip();
+        if let Err(mpsc::SendError(requests)) = self.requests.send(requests) {
+            // The pool only stops once all handles are gone, but verify inline rather than
+            // failing if it ever does.
+            return requests
+                .iter()
+                .map(|request| request.key.validate(&request.signature, &request.data))
+                .collect();
+        }
+        join_all(responses)
+            .await
+            .into_iter()
+            .map(|response| response.unwrap_or(false))
+            .collect()
+    }
+}
+
+/// Verify batches of pending requests until all senders are dropped.
+fn run_worker<K: SignatureKey>(receiver: &Mutex<Receiver<Vec<Request<K>>>>) {
+    while let Some(batch) = next_batch(receiver) {
+        verify_batch(batch);
+    }
+}
+
+/// Wait for a submission, and take it together with whatever else is pending, up to
+/// [`MAX_BATCH_SIZE`] verifications. Submissions are never split between batches.
+///
+/// Returns [`None`] once all senders are dropped.
+fn next_batch<K: SignatureKey>(
+    receiver: &Mutex<Receiver<Vec<Request<K>>>>,
+) -> Option<Vec<Request<K>>> {
+    let receiver = receiver.lock().unwrap_or_else(|err| err.into_inner());
+    let mut batch = receiver.recv().ok()?;
+    while batch.len() < MAX_BATCH_SIZE {
+        let Ok(requests) = receiver.try_recv() else {
+            break;
+        };
+        batch.extend(requests);
+    }
+    Some(batch)
+}
+
+/// Verify a batch of requests, falling back to individual verification if the batch fails.
+fn verify_batch<K: SignatureKey>(batch: Vec<Request<K>>) {
+    if batch.len() > 1 {
+        let items = batch
+            .iter()
+            .map(|req| (&req.key, &req.signature, &*req.data))
+            .collect::<Vec<_>>();
+        if K::batch_validate(&items) {
+            for req in batch {
+                let _ = req.respond.send(true);
+            }
+            return;
+        }
+        tracing::debug!(
+            size = batch.len(),
+            "signature batch failed, verifying individually"
+        );
+    }
+    for req in batch {
+        let valid = req.key.validate(&req.signature, &req.data);
+        let _ = req.respond.send(valid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    /// Keys which each sign `message`, except for the key at `forged`, which signs something else.
+    fn signatures(
+        n: u64,
+        forged: u64,
+        message: &[u8],
+    ) -> Vec<(
+        BLSPubKey,
+        <BLSPubKey as SignatureKey>::PureAssembledSignatureType,
+    )> {
+        (0..n)
+            .map(|i| {
+                let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], i);
+                let signed = if i == forged {
+                    b"forged".as_slice()
+                } else {
+                    message
+                };
+                (key, BLSPubKey::sign(&private_key, signed).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_submissions_form_one_batch() {
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Mutex::new(receiver);
+        let data: Arc<[u8]> = Arc::from(b"message".as_slice());
+        let mut responses = vec![];
+        for submission in [signatures(4, 4, &data), signatures(3, 3, &data)] {
+            let requests = submission
+                .into_iter()
+                .map(|(key, signature)| {
+                    let (respond, response) = oneshot::channel();
+                    responses.push(response);
+                    Request {
+                        key,
+                        signature,
+                        data: Arc::clone(&data),
+                        respond,
+                    }
+                })
+                .collect();
+            sender.send(requests).unwrap();
+        }
+
+        // Everything pending is taken as one batch, which verifies as a whole.
+        let batch = next_batch(&receiver).unwrap();
+        assert_eq!(batch.len(), 7);
+        verify_batch(batch);
+        for mut response in responses {
+            assert_eq!(response.try_recv(), Ok(true));
+        }
+
+        drop(sender);
+        assert!(next_batch(&receiver).is_none());
+    }
+
+    #[test]
+    fn test_invalid_signature_isolated_in_batch() {
+        let data: Arc<[u8]> = Arc::from(b"message".as_slice());
+        let (batch, responses): (Vec<_>, Vec<_>) = signatures(8, 5, &data)
+            .into_iter()
+            .map(|(key, signature)| {
+                let (respond, response) = oneshot::channel();
+                let request = Request {
+                    key,
+                    signature,
+                    data: Arc::clone(&data),
+                    respond,
+                };
+                (request, response)
+            })
+            .unzip();
+
+        // The batch fails as a whole, and only the invalid signature is rejected on the fallback.
+        verify_batch(batch);
+        for (i, mut response) in responses.into_iter().enumerate() {
+            assert_eq!(response.try_recv(), Ok(i != 5), "signature {i}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_all() {
+        let verifier = SignatureVerifier::<BLSPubKey>::new(2);
+        let results = verifier
+            .verify_all(signatures(16, 9, b"message"), b"message".as_slice())
+            .await;
+        assert_eq!(results.len(), 16);
+        for (i, valid) in results.into_iter().enumerate() {
+            assert_eq!(valid, i != 9, "signature {i}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_batch_with_invalid_signature() {
+        let verifier = SignatureVerifier::<BLSPubKey>::new(2);
+        let keys = (0..8)
+            .map(|i| BLSPubKey::generated_from_seed_indexed([0; 32], i))
+            .collect::<Vec<_>>();
+
+        // Every key signs the same message, except that one signature is on another message.
+        let checks = keys.iter().enumerate().map(|(i, (key, private_key))| {
+            let signed = if i == 3 {
+                b"forged".as_slice()
+            } else {
+                b"message"
+            };
+            let signature = BLSPubKey::sign(private_key, signed).unwrap();
+            verifier.verify(*key, signature, b"message".as_slice())
+        });
+        let results = join_all(checks).await;
+        for (i, valid) in results.into_iter().enumerate() {
+            assert_eq!(valid, i != 3, "signature {i}");
+        }
+    }
+
+    #[test]
+    fn test_batch_validate() {
+        let (key_a, private_a) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+        let (key_b, private_b) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+        let sig_a = BLSPubKey::sign(&private_a, b"one").unwrap();
+        let sig_b = BLSPubKey::sign(&private_b, b"one").unwrap();
+        let sig_c = BLSPubKey::sign(&private_b, b"two").unwrap();
+
+        let one = b"one".as_slice();
+        let two = b"two".as_slice();
+        assert!(BLSPubKey::batch_validate(&[
+            (&key_a, &sig_a, one),
+            (&key_b, &sig_b, one),
+            (&key_b, &sig_c, two),
+        ]));
+        // A signature on the wrong message fails the whole batch.
+        assert!(!BLSPubKey::batch_validate(&[
+            (&key_a, &sig_a, one),
+            (&key_b, &sig_c, one),
+        ]));
+        assert!(!BLSPubKey::batch_validate(&[
+            (&key_a, &sig_a, one),
+            (&key_b, &sig_b, one),
+            (&key_b, &sig_b, two),
+        ]));
+    }
+
+    #[test]
+    fn test_batch_validate_offsetting_signatures() {
+        let (key_a, private_a) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+        let (key_b, private_b) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+        let sig_a = BLSPubKey::sign(&private_a, b"one").unwrap();
+        let sig_b = BLSPubKey::sign(&private_b, b"one").unwrap();
+
+        // Shift one signature by some point and the other back by the same point, so that the
+        // invalid signatures still sum to the aggregate of the valid ones.
+        let offset = BLSPubKey::sign(&private_a, b"offset").unwrap().sigma;
+        let mut forged_a = sig_a.clone();
+        forged_a.sigma += offset;
+        let mut forged_b = sig_b.clone();
+        forged_b.sigma -= offset;
+
+        let one = b"one".as_slice();
+        assert!(!key_a.validate(&forged_a, one));
+        assert!(!key_b.validate(&forged_b, one));
+        assert!(!BLSPubKey::batch_validate(&[
+            (&key_a, &forged_a, one),
+            (&key_b, &forged_b, one),
+        ]));
+        assert!(BLSPubKey::batch_validate(&[
+            (&key_a, &sig_a, one),
+            (&key_b, &sig_b, one),
+        ]));
+    }
+}
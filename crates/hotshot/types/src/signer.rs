@@ -0,0 +1,120 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! The signer a node signs consensus messages with.
+//!
+//! A node usually holds its consensus key itself, but it may instead delegate signing to a signer
+//! running outside of the process. Tasks sign through a [`Signer`], which covers both cases, rather
+//! than through the private key directly.
+//!
+//! Votes of which an honest node casts at most one per view are signed with their [`VoteSlot`], so
+//! that a remote signer can refuse to sign a second, conflicting vote in the same slot.
+
+use std::{fmt::Debug, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::traits::signature_key::SignatureKey;
+
+/// A kind of vote of which an honest node casts at most one per view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum VoteKind {
+    /// A vote for a quorum proposal
+    Quorum,
+    /// A vote for a quorum proposal by a member of the next epoch's stake table
+    NextEpochQuorum,
+    /// A vote for a DA proposal
+    Da,
+    /// A vote to time out a view
+    Timeout,
+}
+
+/// The kind of a vote and the view it is cast in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct VoteSlot {
+    /// The kind of the vote
+    pub kind: VoteKind,
+    /// The view the vote is cast in
+    pub view: u64,
+}
+
+/// A consensus key held outside of this process, such as by a remote signing service
+pub trait RemoteSigner<K: SignatureKey>: Debug + Send + Sync {
+    /// The public key of the key held by the signer.
+    fn public_key(&self) -> K;
+
+    /// Sign `data` with the key held by the signer.
+    /// # Errors
+    /// If the signer cannot be reached, or refuses to sign `data`
+    fn sign(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, K::SignError>;
+
+    /// Sign the vote `data` cast in `slot` with the key held by the signer.
+    /// # Errors
+    /// If the signer cannot be reached, or refuses to sign `data`, for instance because it already
+    /// signed a different vote in `slot`
+    fn sign_vote(
+        &self,
+        slot: VoteSlot,
+        data: &[u8],
+    ) -> Result<K::PureAssembledSignatureType, K::SignError> {
+        let _ = slot;
+        self.sign(data)
+    }
+}
+
+/// Signs consensus messages with the consensus key of a node
+#[derive(Clone)]
+pub enum Signer<K: SignatureKey> {
+    /// The private key is held by this process.
+    Local(K::PrivateKey),
+    /// The private key is held by a remote signer.
+    Remote(Arc<dyn RemoteSigner<K>>),
+}
+
+impl<K: SignatureKey> Signer<K> {
+    /// The public key matching the key this signs with.
+    #[must_use]
+    pub fn public_key(&self) -> K {
+        match self {
+            Self::Local(private_key) => K::from_private(private_key),
+            Self::Remote(signer) => signer.public_key(),
+        }
+    }
+
+    /// Sign `data`.
+    /// # Errors
+    /// If unable to sign the data
+    pub fn sign(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, K::SignError> {
+        match self {
+            Self::Local(private_key) => K::sign(private_key, data),
+            Self::Remote(signer) => signer.sign(data),
+        }
+    }
+
+    /// Sign the vote `data` cast in `slot`.
+    /// # Errors
+    /// If unable to sign the data, or a remote signer refuses to sign a second vote in `slot`
+    pub fn sign_vote(
+        &self,
+        slot: VoteSlot,
+        data: &[u8],
+    ) -> Result<K::PureAssembledSignatureType, K::SignError> {
+        match self {
+            Self::Local(private_key) => K::sign(private_key, data),
+            Self::Remote(signer) => signer.sign_vote(slot, data),
+        }
+    }
+}
+
+impl<K: SignatureKey> Debug for Signer<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // Don't print the private key.
+            Self::Local(_) => f.debug_tuple("Local").finish_non_exhaustive(),
+            Self::Remote(signer) => f.debug_tuple("Remote").field(signer).finish(),
+        }
+    }
+}
@@ -33,6 +33,7 @@ use crate::{
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::{SignatureKey, StateSignatureKey},
     },
+    utils::is_epoch_transition,
     vote::{Certificate, HasViewNumber},
     PeerConfig, StakeTableEntries,
 };
@@ -384,6 +385,37 @@ impl<
     }
 }
 
+impl<TYPES: NodeType, VOTEABLE: Voteable<TYPES> + 'static, THRESHOLD: Threshold<TYPES>>
+    SimpleCertificate<TYPES, VOTEABLE, THRESHOLD>
+where
+    Self: Certificate<TYPES, VOTEABLE>,
+{
+    /// Check a certificate received from an untrusted source, such as a peer or an API client.
+    ///
+    /// [`is_valid_cert`](Certificate::is_valid_cert) accepts any certificate for the genesis view
+    /// without checking it, and panics on a certificate without signatures, since consensus only
+    /// ever checks certificates of its own or which passed its own validation. A certificate from
+    /// anywhere else may be either, so this rejects both instead.
+    ///
+    /// # Errors
+    /// If the certificate is for the genesis view, has no signatures, or its signatures do not meet
+    /// `threshold` in `stake_table`.
+    pub async fn verify_untrusted<V: Versions>(
+        &self,
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        threshold: U256,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        ensure!(
+            self.view_number != TYPES::View::genesis(),
+            "certificate for the genesis view cannot be verified"
+        );
+        ensure!(self.signatures.is_some(), "certificate has no signatures");
+        self.is_valid_cert(stake_table, threshold, upgrade_lock)
+            .await
+    }
+}
+
 impl<TYPES: NodeType> Display for QuorumCertificate<TYPES> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "view: {:?}", self.view_number)
@@ -732,6 +764,170 @@ pub type ViewSyncFinalizeCertificate2<TYPES> =
 pub type UpgradeCertificate<TYPES> =
     SimpleCertificate<TYPES, UpgradeProposalData<TYPES>, UpgradeThreshold>;
 
+/// A pair of quorum certificates on the same leaf, from the quorums of consecutive epochs.
+///
+/// Blocks in the epoch transition must be voted for by the nodes of both the outgoing and the
+/// incoming epoch, so they are justified by a certificate from each quorum.
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub struct DoubleQuorumCertificate<TYPES: NodeType> {
+    /// Certificate from the quorum of the outgoing epoch
+    qc: QuorumCertificate2<TYPES>,
+    /// Certificate from the quorum of the incoming epoch
+    next_epoch_qc: NextEpochQuorumCertificate2<TYPES>,
+}
+
+impl<TYPES: NodeType> DoubleQuorumCertificate<TYPES> {
+    /// Pair the certificates of the outgoing and incoming epochs' quorums.
+    ///
+    /// # Errors
+    /// If the certificates are not for the same view and leaf.
+    pub fn new(
+        qc: QuorumCertificate2<TYPES>,
+        next_epoch_qc: NextEpochQuorumCertificate2<TYPES>,
+    ) -> Result<Self> {
+        ensure!(
+            qc.view_number() == next_epoch_qc.view_number() && qc.data == *next_epoch_qc.data,
+            "Next epoch QC for view {:?} does not match QC for view {:?}",
+            next_epoch_qc.view_number(),
+            qc.view_number()
+        );
+        Ok(Self { qc, next_epoch_qc })
+    }
+
+    /// The certificate from the quorum of the outgoing epoch
+    pub fn qc(&self) -> &QuorumCertificate2<TYPES> {
+        &self.qc
+    }
+
+    /// The certificate from the quorum of the incoming epoch
+    pub fn next_epoch_qc(&self) -> &NextEpochQuorumCertificate2<TYPES> {
+        &self.next_epoch_qc
+    }
+
+    /// Split into the certificates of the outgoing and incoming epochs' quorums.
+    pub fn into_parts(
+        self,
+    ) -> (
+        QuorumCertificate2<TYPES>,
+        NextEpochQuorumCertificate2<TYPES>,
+    ) {
+        (self.qc, self.next_epoch_qc)
+    }
+
+    /// Check each certificate against the stake table of its epoch.
+    ///
+    /// # Errors
+    /// If either certificate is not signed by a quorum of its epoch.
+    pub async fn is_valid_cert<V: Versions>(
+        &self,
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        threshold: U256,
+        next_epoch_stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        next_epoch_threshold: U256,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        self.qc
+            .is_valid_cert(stake_table, threshold, upgrade_lock)
+            .await
+            .context(|e| warn!("Invalid certificate: {e}"))?;
+        self.next_epoch_qc
+            .is_valid_cert(next_epoch_stake_table, next_epoch_threshold, upgrade_lock)
+            .await
+            .context(|e| warn!("Invalid next epoch certificate: {e}"))
+    }
+}
+
+/// The quorum certificates justifying a leaf.
+///
+/// A leaf in the epoch transition is justified by the quorums of both the outgoing and incoming
+/// epochs; any other leaf by the quorum of its own epoch alone.
+#[derive(Eq, Hash, PartialEq, Debug, Clone)]
+pub enum EpochQuorumCertificate<TYPES: NodeType> {
+    /// A certificate formed within an epoch
+    Single(QuorumCertificate2<TYPES>),
+    /// Certificates formed at an epoch boundary
+    Double(DoubleQuorumCertificate<TYPES>),
+}
+
+impl<TYPES: NodeType> EpochQuorumCertificate<TYPES> {
+    /// Determine the certificates justifying the leaf of `qc`, given the certificate of the next
+    /// epoch's quorum if one was received with it.
+    ///
+    /// # Errors
+    /// If `qc` is for a block in the epoch transition but no next epoch certificate is given, or
+    /// the two certificates do not match.
+    pub fn new(
+        qc: QuorumCertificate2<TYPES>,
+        next_epoch_qc: Option<NextEpochQuorumCertificate2<TYPES>>,
+        epoch_height: u64,
+    ) -> Result<Self> {
+        match next_epoch_qc {
+            Some(next_epoch_qc) => Ok(Self::Double(DoubleQuorumCertificate::new(
+                qc,
+                next_epoch_qc,
+            )?)),
+            None => {
+                ensure!(
+                    !qc.data
+                        .block_number
+                        .is_some_and(|b| is_epoch_transition(b, epoch_height)),
+                    error!("Received High QC for the transition block but not the next epoch QC")
+                );
+                Ok(Self::Single(qc))
+            },
+        }
+    }
+
+    /// The certificate from the quorum of the leaf's epoch
+    pub fn qc(&self) -> &QuorumCertificate2<TYPES> {
+        match self {
+            Self::Single(qc) => qc,
+            Self::Double(double) => double.qc(),
+        }
+    }
+
+    /// The certificate from the quorum of the next epoch, if the leaf is at an epoch boundary
+    pub fn next_epoch_qc(&self) -> Option<&NextEpochQuorumCertificate2<TYPES>> {
+        match self {
+            Self::Single(_) => None,
+            Self::Double(double) => Some(double.next_epoch_qc()),
+        }
+    }
+
+    /// Check the certificates against the stake table of `membership`'s epoch and, at an epoch
+    /// boundary, the stake table of the next epoch.
+    ///
+    /// # Errors
+    /// If any certificate is not signed by a quorum of its epoch, or the next epoch's stake table
+    /// is not available.
+    pub async fn is_valid<V: Versions>(
+        &self,
+        membership: &EpochMembership<TYPES>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        let stake_table = StakeTableEntries::<TYPES>::from(membership.stake_table().await).0;
+        let threshold = membership.success_threshold().await;
+        match self {
+            Self::Single(qc) => qc
+                .is_valid_cert(stake_table, threshold, upgrade_lock)
+                .await
+                .context(|e| warn!("Invalid certificate: {e}")),
+            Self::Double(double) => {
+                let next_epoch = membership.next_epoch_stake_table().await?;
+                double
+                    .is_valid_cert(
+                        stake_table,
+                        threshold,
+                        StakeTableEntries::<TYPES>::from(next_epoch.stake_table().await).0,
+                        next_epoch.success_threshold().await,
+                        upgrade_lock,
+                    )
+                    .await
+            },
+        }
+    }
+}
+
 /// Type for light client state update certificate
 #[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Debug, Clone)]
 pub struct LightClientStateUpdateCertificate<TYPES: NodeType> {
@@ -7,6 +7,7 @@
 //! Implementations of the simple vote types.
 
 use std::{
+    any::TypeId,
     fmt::Debug,
     hash::Hash,
     marker::PhantomData,
@@ -22,6 +23,7 @@ use crate::{
     data::{Leaf, Leaf2, VidCommitment},
     light_client::{LightClientState, StakeTableState},
     message::UpgradeLock,
+    signer::{Signer, VoteKind, VoteSlot},
     traits::{
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::{SignatureKey, StateSignatureKey},
@@ -70,6 +72,53 @@ pub struct DaData2<TYPES: NodeType> {
     /// Epoch number
     pub epoch: Option<TYPES::Epoch>,
 }
+/// The payload commitments attested to by a DA vote or certificate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaPayloadCommitments {
+    /// A payload dispersed among the nodes of its epoch
+    Single(VidCommitment),
+    /// A payload in the epoch transition, dispersed among the nodes of both the outgoing and the
+    /// incoming epoch with a commitment for each
+    Transition {
+        /// Commitment for the outgoing epoch
+        current: VidCommitment,
+        /// Commitment for the incoming epoch
+        next_epoch: VidCommitment,
+    },
+}
+
+impl DaPayloadCommitments {
+    /// The commitment for the payload's own epoch
+    #[must_use]
+    pub fn current(&self) -> &VidCommitment {
+        match self {
+            Self::Single(current) | Self::Transition { current, .. } => current,
+        }
+    }
+
+    /// The commitment for the next epoch, if the payload is in the epoch transition
+    #[must_use]
+    pub fn next_epoch(&self) -> Option<&VidCommitment> {
+        match self {
+            Self::Single(_) => None,
+            Self::Transition { next_epoch, .. } => Some(next_epoch),
+        }
+    }
+}
+
+impl<TYPES: NodeType> DaData2<TYPES> {
+    /// The payload commitments this data attests to
+    #[must_use]
+    pub fn payload_commitments(&self) -> DaPayloadCommitments {
+        match self.next_epoch_payload_commit {
+            Some(next_epoch) => DaPayloadCommitments::Transition {
+                current: self.payload_commit,
+                next_epoch,
+            },
+            None => DaPayloadCommitments::Single(self.payload_commit),
+        }
+    }
+}
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
 /// Data used for a timeout vote.
 pub struct TimeoutData<TYPES: NodeType> {
@@ -260,18 +309,26 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES> + 'static> SimpleVote<TYPES, DATA> {
         data: DATA,
         view: TYPES::View,
         pub_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> Result<Self> {
         let commit = VersionedVoteData::new(data.clone(), view, upgrade_lock)
             .await?
             .commit();
 
+        let signature = match vote_kind::<TYPES, DATA>() {
+            Some(kind) => signer.sign_vote(
+                VoteSlot {
+                    kind,
+                    view: view.u64(),
+                },
+                commit.as_ref(),
+            ),
+            None => signer.sign(commit.as_ref()),
+        };
         let signature = (
             pub_key.clone(),
-            TYPES::SignatureKey::sign(private_key, commit.as_ref())
-                .wrap()
-                .context(error!("Failed to sign vote"))?,
+            signature.wrap().context(error!("Failed to sign vote"))?,
         );
 
         Ok(Self {
@@ -282,6 +339,24 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES> + 'static> SimpleVote<TYPES, DATA> {
     }
 }
 
+/// The kind of a vote on `DATA`, if an honest node casts at most one such vote per view
+fn vote_kind<TYPES: NodeType, DATA: 'static>() -> Option<VoteKind> {
+    let data = TypeId::of::<DATA>();
+    if data == TypeId::of::<QuorumData<TYPES>>() || data == TypeId::of::<QuorumData2<TYPES>>() {
+        Some(VoteKind::Quorum)
+    } else if data == TypeId::of::<NextEpochQuorumData2<TYPES>>() {
+        Some(VoteKind::NextEpochQuorum)
+    } else if data == TypeId::of::<DaData>() || data == TypeId::of::<DaData2<TYPES>>() {
+        Some(VoteKind::Da)
+    } else if data == TypeId::of::<TimeoutData<TYPES>>()
+        || data == TypeId::of::<TimeoutData2<TYPES>>()
+    {
+        Some(VoteKind::Timeout)
+    } else {
+        None
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
 /// A wrapper for vote data that carries a view number and an `upgrade_lock`, allowing switching the commitment calculation dynamically depending on the version
 pub struct VersionedVoteData<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> {
@@ -0,0 +1,187 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Correlation of tracing spans by view.
+//!
+//! Work on a single view is spread across many tasks, which communicate only by events, and across
+//! many nodes, which communicate only by network messages. To follow a view from proposal receipt
+//! through decide in a distributed tracing backend, every span belonging to the view must share a
+//! trace ID. Rather than threading an explicit trace context through every event and message, we
+//! derive the trace ID deterministically from the view number, which every event and message
+//! already carries. Spans created with [`task_span`] in any task join the same trace.
+//!
+//! The derivation is salted by a trace namespace. By default the namespace is random, so traces are
+//! correlated across the tasks of a single node but not across nodes. Nodes configured with the
+//! same namespace (see [`set_trace_namespace`]) derive the same trace ID for each view, so their
+//! spans for a given view are collected into a single trace.
+//!
+//! Trace IDs are only derived with the `otlp` feature. Without it, task spans are still created,
+//! for the structured log format, but are not correlated in a trace.
+
+#[cfg(feature = "otlp")]
+use std::sync::OnceLock;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "otlp")]
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+#[cfg(feature = "otlp")]
+use rand::RngCore;
+#[cfg(feature = "otlp")]
+use sha2::{Digest, Sha256};
+use tracing::Span;
+#[cfg(feature = "otlp")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Whether task spans are enabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The salt used to derive trace IDs from view numbers.
+#[cfg(feature = "otlp")]
+static NAMESPACE: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// Enable task spans.
+///
+/// This should be called when a tracing exporter or a structured log format which makes use of
+/// task spans is installed. Until it is called, [`task_span`] returns a disabled span, so that task
+/// spans cost nothing and do not clutter log output when they are not being used.
+pub fn enable_task_spans() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether task spans, and [task contexts](TaskContext), are enabled.
+pub fn task_spans_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// The event a task is handling, as recorded on its [task span](task_span).
+///
+/// Task spans are filtered out like any other span at their level, so with `RUST_LOG=warn`, say,
+/// the warnings logged while handling an event would lose the fields of the span. The context is
+/// therefore also kept in a task-local variable while the event is handled, so that log layers can
+/// attach it to every log event, whichever spans are enabled.
+#[derive(Clone, Debug)]
+pub struct TaskContext {
+    /// The task handling the event
+    pub task: &'static str,
+    /// The view the event belongs to, if any
+    pub view: Option<u64>,
+    /// The epoch the event belongs to, if known
+    pub epoch: Option<u64>,
+    /// The kind of the event
+    pub event: Option<&'static str>,
+    /// The peer the event was received from or is being sent to, if any
+    pub peer: Option<String>,
+}
+
+tokio::task_local! {
+    static TASK_CONTEXT: TaskContext;
+}
+
+impl TaskContext {
+    /// Run `fut` with this as the [current](Self::current) context.
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        TASK_CONTEXT.scope(self, fut).await
+    }
+
+    /// The context of the event being handled by the current task, if any.
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        TASK_CONTEXT.try_with(Clone::clone).ok()
+    }
+}
+
+/// Set the namespace used to derive per-view trace IDs.
+///
+/// Nodes which share a namespace produce the same trace ID for the same view, so that a tracing
+/// backend collecting spans from all of them can show a single trace for each view. This must be
+/// called before the first call to [`task_span`]; later calls have no effect.
+#[cfg(feature = "otlp")]
+pub fn set_trace_namespace(namespace: &str) {
+    let _ = NAMESPACE.set(Sha256::digest(namespace.as_bytes()).into());
+}
+
+/// The trace ID for spans belonging to `view`.
+#[cfg(feature = "otlp")]
+#[must_use]
+pub fn view_trace_id(view: u64) -> TraceId {
+    let namespace = NAMESPACE.get_or_init(|| {
+        let mut salt = [0; 32];
+        rand::thread_rng().fill_bytes(&mut salt);
+        salt
+    });
+    let digest = Sha256::new()
+        .chain_update(namespace)
+        .chain_update(view.to_le_bytes())
+        .finalize();
+    let mut id = [0; 16];
+    id.copy_from_slice(&digest[..16]);
+    TraceId::from_bytes(id)
+}
+
+/// Create a span for an event handled by `task`, on behalf of `view` if the event belongs to one.
+///
+/// With the `otlp` feature, if there is a view, the span is parented to a remote span context whose
+/// trace ID is derived from the view, so all spans for the same view are part of the same trace,
+/// regardless of which task (or which node, with a shared namespace) created them.
+///
+/// The span also declares the fields `epoch`, `event` and `peer`, which the caller may
+/// [record](Span::record) if they are known.
+#[must_use]
+pub fn task_span(task: &'static str, view: Option<u64>) -> Span {
+    if !task_spans_enabled() {
+        return Span::none();
+    }
+
+    let span = tracing::info_span!(
+        "task",
+        task,
+        view,
+        epoch = tracing::field::Empty,
+        event = tracing::field::Empty,
+        peer = tracing::field::Empty,
+    );
+    #[cfg(feature = "otlp")]
+    if let Some(view) = view {
+        set_view_parent(&span, view);
+    }
+    span
+}
+
+/// Parent `span` to a remote span context whose trace ID is derived from `view`
+#[cfg(feature = "otlp")]
+fn set_view_parent(span: &Span, view: u64) {
+    let trace_id = view_trace_id(view);
+    // The root of the trace is a virtual span for the view itself, which never gets exported. Its
+    // ID only needs to be non-zero and the same for every span in the view.
+    let mut root = [0; 8];
+    root.copy_from_slice(&trace_id.to_bytes()[8..]);
+    let root = SpanContext::new(
+        trace_id,
+        SpanId::from_bytes(root),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    span.set_parent(Context::new().with_remote_span_context(root));
+}
+
+#[cfg(all(test, feature = "otlp"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_view_trace_id() {
+        assert_eq!(view_trace_id(1), view_trace_id(1));
+        assert_ne!(view_trace_id(1), view_trace_id(2));
+        assert_ne!(view_trace_id(1), TraceId::INVALID);
+    }
+}
@@ -8,6 +8,7 @@
 pub mod auction_results_provider;
 pub mod block_contents;
 pub mod consensus_api;
+pub mod da_payload_provider;
 pub mod election;
 pub mod metrics;
 pub mod network;
@@ -18,7 +18,11 @@ use super::node_implementation::NodeType;
 /// type has the requisite fields available.
 #[async_trait]
 pub trait AuctionResultsProvider<TYPES: NodeType>: Send + Sync + Clone {
-    /// Fetches the auction result for a view. Does not cache the result,
+    /// Fetches the auction result for a view in `epoch`. Does not cache the result,
     /// subsequent calls will invoke additional wasted calls.
-    async fn fetch_auction_result(&self, view_number: TYPES::View) -> Result<TYPES::AuctionResult>;
+    async fn fetch_auction_result(
+        &self,
+        view_number: TYPES::View,
+        epoch: Option<TYPES::Epoch>,
+    ) -> Result<TYPES::AuctionResult>;
 }
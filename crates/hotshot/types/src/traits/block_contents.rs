@@ -45,6 +45,87 @@ pub trait Transaction:
     /// Since each new namespace adds overhead
     /// just ignore this parameter by default and use it when needed
     fn minimum_block_size(&self) -> u64;
+
+    /// Fee offered for including this transaction ahead of others by the node's local builder
+    ///
+    /// Transactions offering no such fee have priority 0, and are otherwise ordered by arrival.
+    fn priority_fee(&self) -> u64 {
+        0
+    }
+
+    /// Group sharing a fair portion of the local builder's mempool, such as the namespace
+    fn fairness_group(&self) -> u64 {
+        0
+    }
+
+    /// Key shared with the pending transactions this transaction may replace
+    ///
+    /// A pending transaction with the same key is replaced if this one offers a higher
+    /// [`priority_fee`](Self::priority_fee).
+    fn replacement_key(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// The [`priority_fee`](Self::priority_fee) and [`replacement_key`](Self::replacement_key)
+    /// offered by this transaction
+    fn priority(&self) -> TransactionPriority {
+        TransactionPriority {
+            fee: self.priority_fee(),
+            replacement_key: self.replacement_key(),
+        }
+    }
+
+    /// This transaction offering `priority` instead
+    ///
+    /// Used to restore the priority of a transaction received from a peer, as the priority is not
+    /// part of the transaction's binary encoding. Transactions which cannot offer a priority ignore
+    /// it.
+    #[must_use]
+    fn with_priority(self, _priority: TransactionPriority) -> Self {
+        self
+    }
+}
+
+/// Priority a transaction offers to the local builder, chosen by whoever submitted it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TransactionPriority {
+    /// See [`Transaction::priority_fee`]
+    pub fee: u64,
+    /// See [`Transaction::replacement_key`]
+    pub replacement_key: Option<Vec<u8>>,
+}
+
+impl TransactionPriority {
+    /// Whether this is the priority of a transaction offering no fee or replacement key
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Limits on the contents of a block
+///
+/// The limits may change from one view to the next, for example at an upgrade, so they are
+/// determined for each block by [`BlockPayload::limits`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockLimits {
+    /// Maximum size in bytes of a block payload, unlimited if absent
+    pub max_block_size: Option<u64>,
+    /// Maximum number of transactions in a block, unlimited if absent
+    pub max_transactions: Option<u64>,
+    /// Maximum size in bytes of the transactions of any one fairness group, such as a namespace,
+    /// in a block, unlimited if absent
+    pub max_group_size: Option<u64>,
+}
+
+impl BlockLimits {
+    /// Whether `transaction` could be included in a block on its own
+    pub fn admits(&self, transaction: &impl Transaction) -> bool {
+        let size = transaction.minimum_block_size();
+        self.max_transactions != Some(0)
+            && self.max_block_size.is_none_or(|max| size <= max)
+            && self.max_group_size.is_none_or(|max| size <= max)
+    }
 }
 
 /// Abstraction over the full contents of a block
@@ -122,6 +203,59 @@ pub trait BlockPayload<TYPES: NodeType>:
     /// Generate commitment that builders use to sign block options.
     fn builder_commitment(&self, metadata: &Self::Metadata) -> BuilderCommitment;
 
+    /// Whether `metadata` is consistent with this payload.
+    ///
+    /// Checked by the leader before proposing a block claimed from a builder. The default
+    /// implementation accepts any metadata.
+    fn is_well_formed(&self, _metadata: &Self::Metadata) -> bool {
+        true
+    }
+
+    /// Limits on the contents of a block built on `validated_state`.
+    ///
+    /// The default implementation imposes no limits.
+    ///
+    /// # Errors
+    /// If the limits cannot be determined, for example because the configuration they are part of
+    /// is not available.
+    async fn limits(
+        _validated_state: &Self::ValidatedState,
+        _instance_state: &Self::Instance,
+    ) -> Result<BlockLimits, Self::Error> {
+        Ok(BlockLimits::default())
+    }
+
+    /// Whether a block built on `validated_state` must pay a fee to be valid.
+    ///
+    /// Blocks built by the node itself pay no fee (see
+    /// [`null_block::local_builder_fee`](crate::data::null_block::local_builder_fee)), so they are
+    /// only proposed when this is `false`. The default implementation requires no fee.
+    ///
+    /// # Errors
+    /// If the fee rules cannot be determined, for example because the configuration they are part
+    /// of is not available.
+    async fn requires_fee(
+        _validated_state: &Self::ValidatedState,
+        _instance_state: &Self::Instance,
+    ) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    /// Whether this payload respects `limits`.
+    ///
+    /// Checked by the leader before proposing a block claimed from a builder, and by every node
+    /// before accepting a DA proposal. The default implementation checks the size of the payload
+    /// and the number of transactions, but not the size of each fairness group, which depends on
+    /// the encoding of the payload.
+    fn within_limits(&self, metadata: &Self::Metadata, limits: &BlockLimits) -> bool {
+        let size = u64::try_from(self.encode().len()).unwrap_or(u64::MAX);
+        let num_transactions = u64::try_from(self.num_transactions(metadata)).unwrap_or(u64::MAX);
+        limits.max_block_size.is_none_or(|max| size <= max)
+            && limits
+                .max_transactions
+                .is_none_or(|max| num_transactions <= max)
+    }
+
     /// Get the transactions in the payload.
     fn transactions<'a>(
         &'a self,
@@ -12,9 +12,10 @@ use async_trait::async_trait;
 
 use crate::{
     event::Event,
+    signer::Signer,
     traits::{
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::{SignatureKey, StateSignatureKey},
+        signature_key::StateSignatureKey,
     },
 };
 
@@ -31,8 +32,8 @@ pub trait ConsensusApi<TYPES: NodeType, I: NodeImplementation<TYPES>>: Send + Sy
     /// Get a reference to the public key.
     fn public_key(&self) -> &TYPES::SignatureKey;
 
-    /// Get a reference to the private key.
-    fn private_key(&self) -> &<TYPES::SignatureKey as SignatureKey>::PrivateKey;
+    /// Get a reference to the signer for the consensus key.
+    fn signer(&self) -> &Signer<TYPES::SignatureKey>;
 
     /// Get a reference to the light client signing key.
     fn state_private_key(
@@ -0,0 +1,28 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! This module defines the [`DaPayloadProvider`] trait, which DA committee members use to fetch
+//! payloads the leader announced with a [`DaPayloadHint2`] instead of broadcasting them inline.
+
+use std::sync::{Arc, OnceLock};
+
+use async_trait::async_trait;
+
+use super::node_implementation::NodeType;
+use crate::data::DaPayloadHint2;
+
+/// The source of DA payloads of a node, once the application sets one
+pub type DaPayloadProviderSlot<TYPES> = Arc<OnceLock<Arc<dyn DaPayloadProvider<TYPES>>>>;
+
+/// A source of DA payloads which were not broadcast inline by the DA leader.
+///
+/// The fetched payload does not need to be trusted, it is checked against the hash signed by
+/// the leader before it is voted on.
+#[async_trait]
+pub trait DaPayloadProvider<TYPES: NodeType>: Send + Sync + 'static {
+    /// Fetch the encoded transactions announced by `hint`, or `None` if they are not available.
+    async fn fetch_payload(&self, hint: &DaPayloadHint2<TYPES>) -> Option<Arc<[u8]>>;
+}
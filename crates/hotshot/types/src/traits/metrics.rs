@@ -30,6 +30,21 @@ pub trait Metrics: Send + Sync + DynClone + Debug {
     ///
     /// The `unit_label` can be used to indicate what the unit of the value is, e.g. "kb" or "seconds"
     fn create_histogram(&self, name: String, unit_label: Option<String>) -> Box<dyn Histogram>;
+    /// Create a [`Histogram`] with custom bucket boundaries.
+    ///
+    /// `buckets` are the upper bounds of the buckets, in increasing order. They should be chosen to
+    /// cover the expected range of values, so that high quantiles can be estimated accurately.
+    /// Implementations which do not bucket their points may ignore them, which is what the default
+    /// implementation does.
+    fn create_histogram_with_buckets(
+        &self,
+        name: String,
+        unit_label: Option<String>,
+        buckets: Vec<f64>,
+    ) -> Box<dyn Histogram> {
+        let _ = buckets;
+        self.create_histogram(name, unit_label)
+    }
 
     /// Create a text metric.
     ///
@@ -48,6 +63,19 @@ pub trait Metrics: Send + Sync + DynClone + Debug {
     /// Create a family of related histograms, partitioned by their label values.
     fn histogram_family(&self, name: String, labels: Vec<String>) -> Box<dyn HistogramFamily>;
 
+    /// Create a family of related histograms with custom bucket boundaries.
+    ///
+    /// See [`create_histogram_with_buckets`](Self::create_histogram_with_buckets).
+    fn histogram_family_with_buckets(
+        &self,
+        name: String,
+        labels: Vec<String>,
+        buckets: Vec<f64>,
+    ) -> Box<dyn HistogramFamily> {
+        let _ = buckets;
+        self.histogram_family(name, labels)
+    }
+
     /// Create a family of related text metricx, partitioned by their label values.
     fn text_family(&self, name: String, labels: Vec<String>) -> Box<dyn TextFamily>;
 
@@ -226,12 +254,27 @@ pub trait Gauge: Send + Sync + Debug + DynClone {
 pub trait Histogram: Send + Sync + Debug + DynClone {
     /// Add a point to this histogram.
     fn add_point(&self, point: f64);
+
+    /// Add a point to this histogram, with an exemplar identifying the event it came from.
+    ///
+    /// The exemplar is a list of key-value pairs, such as the view number and leader key of a slow
+    /// view, which make it possible to go from an outlier in the histogram to the logs for the
+    /// specific event that caused it. Implementations which do not support exemplars may discard
+    /// them, which is what the default implementation does.
+    fn add_point_with_exemplar(&self, point: f64, exemplar: Vec<(String, String)>) {
+        let _ = exemplar;
+        self.add_point(point);
+    }
 }
 
 dyn_clone::clone_trait_object!(Metrics);
 dyn_clone::clone_trait_object!(Gauge);
 dyn_clone::clone_trait_object!(Counter);
 dyn_clone::clone_trait_object!(Histogram);
+dyn_clone::clone_trait_object!(CounterFamily);
+dyn_clone::clone_trait_object!(GaugeFamily);
+dyn_clone::clone_trait_object!(HistogramFamily);
+dyn_clone::clone_trait_object!(TextFamily);
 
 #[cfg(test)]
 mod test {
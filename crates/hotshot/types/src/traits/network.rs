@@ -172,6 +172,30 @@ pub enum BroadcastDelay {
     View(u64),
 }
 
+/// Misbehaviour of the peer which delivered a message, lowering its reputation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PeerOffense {
+    /// The message could not be decoded
+    MalformedMessage,
+    /// The message carried an invalid signature
+    InvalidSignature,
+    /// The peer sent more messages than it is allowed to
+    Spam,
+}
+
+/// The reputation of a peer, as tracked by a network implementation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerReputation {
+    /// Identifier of the peer in the underlying network
+    pub peer: String,
+    /// Current score of the peer, 0 for a well-behaved peer and negative for a misbehaving one
+    pub score: f64,
+    /// Whether messages from the peer are deprioritized
+    pub deprioritized: bool,
+    /// Seconds until the peer is no longer banned, if it is banned
+    pub banned_for: Option<u64>,
+}
+
 #[async_trait]
 /// represents a networking implmentration
 /// exposes low level API for interacting with a network
@@ -184,6 +208,11 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     /// Resumes the underlying network
     fn resume(&self);
 
+    /// Whether [`pause`](Self::pause) actually stops sending and receiving on this network
+    fn can_pause(&self) -> bool {
+        false
+    }
+
     /// Blocks until the network is successfully initialized
     async fn wait_for_ready(&self);
 
@@ -269,6 +298,17 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     fn is_primary_down(&self) -> bool {
         false
     }
+
+    /// Report that `message`, as returned by `recv_message`, shows `offense` on the part of the
+    /// peer which delivered it.
+    ///
+    /// Networks which do not track the reputation of their peers ignore reports.
+    fn report_message(&self, _message: &[u8], _offense: PeerOffense) {}
+
+    /// The reputation of the peers we have received messages from
+    async fn peer_reputations(&self) -> Vec<PeerReputation> {
+        vec![]
+    }
 }
 
 /// A channel generator for types that need asynchronous execution
@@ -72,6 +72,37 @@ pub trait QuorumCertificateScheme<
         sigs: &[A::Signature],
     ) -> Result<Self::Qc, SignatureError>;
 
+    /// Folds a partial signature into a running aggregated signature, so that the QC can be
+    /// assembled as soon as enough weight is collected
+    /// * `agg_sig_pp` - public parameters for aggregate signature
+    /// * `aggregate` - signature aggregated so far, `None` for the first partial signature
+    /// * `ver_key` - verification key of the signer of `sig`
+    /// * `sig` - partial signature to add
+    ///
+    /// # Errors
+    ///
+    /// Will return error if the underlying signature scheme fails to aggregate.
+    fn aggregate_partial(
+        agg_sig_pp: &A::PublicParameter,
+        aggregate: Option<&A::Signature>,
+        ver_key: &A::VerificationKey,
+        sig: &A::Signature,
+    ) -> Result<A::Signature, SignatureError>;
+
+    /// Builds a QC from a signature aggregated with [`QuorumCertificateScheme::aggregate_partial`]
+    /// * `qc_pp` - public parameters for generating the QC
+    /// * `signers` - a bool vector indicating the verification keys of the aggregated partial signatures
+    /// * `aggregate` - the aggregated signature
+    ///
+    /// # Errors
+    ///
+    /// Will return error if the signers don't match the stake table or don't reach the threshold.
+    fn assemble_aggregated(
+        qc_pp: &Self::QcProverParams,
+        signers: &BitSlice,
+        aggregate: A::Signature,
+    ) -> Result<Self::Qc, SignatureError>;
+
     /// Checks an aggregated signature over some message provided as input
     /// * `qc_vp` - public parameters for validating the QC
     /// * `message` - message to check the aggregated signature against
@@ -125,6 +125,18 @@ pub trait SignatureKey:
     /// Validate a signature
     fn validate(&self, signature: &Self::PureAssembledSignatureType, data: &[u8]) -> bool;
 
+    /// Validate a batch of signatures, returning `true` only if every signature is valid.
+    ///
+    /// Implementations may verify the batch more cheaply than one signature at a time, but a
+    /// failed batch does not identify which signatures are invalid. Keys in the batch must have
+    /// proven possession of their private keys (as stake table keys have), since batching may
+    /// combine signatures from different keys.
+    fn batch_validate(batch: &[(&Self, &Self::PureAssembledSignatureType, &[u8])]) -> bool {
+        batch
+            .iter()
+            .all(|(key, signature, data)| key.validate(signature, data))
+    }
+
     /// Produce a signature
     /// # Errors
     /// If unable to sign the data with the key
@@ -178,6 +190,20 @@ pub trait SignatureKey:
         sigs: &[Self::PureAssembledSignatureType],
     ) -> Self::QcType;
 
+    /// Fold the partial signature of `signer` into the signature aggregated so far.
+    fn aggregate_partial(
+        aggregate: Option<&Self::PureAssembledSignatureType>,
+        signer: &Self,
+        sig: &Self::PureAssembledSignatureType,
+    ) -> Self::PureAssembledSignatureType;
+
+    /// Assemble the QC from a signature aggregated with [`SignatureKey::aggregate_partial`].
+    fn assemble_aggregated(
+        real_qc_pp: &Self::QcParams,
+        signers: &BitSlice,
+        aggregate: Self::PureAssembledSignatureType,
+    ) -> Self::QcType;
+
     /// generates the genesis public key. Meant to be dummy/filler
     #[must_use]
     fn genesis_proposer_pk() -> Self;
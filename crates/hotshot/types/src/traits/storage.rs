@@ -19,7 +19,7 @@ use crate::{
         DaProposal, DaProposal2, QuorumProposal, QuorumProposal2, QuorumProposalWrapper,
         VidCommitment, VidDisperseShare,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::HotShotAction,
     message::{convert_proposal, Proposal},
     simple_certificate::{
@@ -141,6 +141,14 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
     }
     /// Add a drb result
     async fn add_drb_result(&self, epoch: TYPES::Epoch, drb_result: DrbResult) -> Result<()>;
+    /// Store the progress of an ongoing drb computation, so it can be resumed after a restart
+    async fn store_drb_input(&self, _drb_input: DrbInput) -> Result<()> {
+        Ok(())
+    }
+    /// Load the progress of the drb computation for `epoch`, if any was stored
+    async fn load_drb_input(&self, _epoch: TYPES::Epoch) -> Result<Option<DrbInput>> {
+        Ok(None)
+    }
     /// Add an epoch block header
     async fn add_epoch_root(
         &self,
@@ -0,0 +1,264 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Rejection of transactions the node has recently seen.
+//!
+//! Transactions are gossiped to every node and forwarded to the builders, so a transaction
+//! submitted again, by accident or to bloat blocks, is built into another block unless someone
+//! notices it was already sequenced. With a [`TransactionDedupConfig`], the node remembers the
+//! transactions it received and those included in recently decided blocks for a window of views,
+//! and drops any it receives again within the window before they reach the builders or the local
+//! mempool.
+//!
+//! A transaction which is pending, rather than decided, may have been lost in a view which failed,
+//! so pending transactions are forgotten when a view fails and may then be submitted again.
+
+use std::collections::{BTreeMap, HashMap};
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of transaction deduplication
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionDedupConfig {
+    /// Number of views for which a transaction is remembered after it was last seen
+    #[serde(default = "default_window")]
+    pub window: u64,
+    /// Maximum number of transactions remembered; beyond this, the oldest are forgotten early
+    #[serde(default = "default_max_transactions")]
+    pub max_transactions: usize,
+}
+
+/// Default [`TransactionDedupConfig::window`]
+fn default_window() -> u64 {
+    100
+}
+
+/// Default [`TransactionDedupConfig::max_transactions`]
+fn default_max_transactions() -> usize {
+    1_000_000
+}
+
+impl Default for TransactionDedupConfig {
+    fn default() -> Self {
+        Self {
+            window: default_window(),
+            max_transactions: default_max_transactions(),
+        }
+    }
+}
+
+/// Exact set of the transactions seen in a rolling window of views
+///
+/// Memory is bounded by [`max_transactions`](TransactionDedupConfig::max_transactions), so a flood
+/// of distinct transactions can only shorten the window, never exhaust memory.
+#[derive(Debug)]
+pub struct TransactionDedup<T: Committable> {
+    /// Configuration
+    config: TransactionDedupConfig,
+    /// The transactions remembered, with the key they are remembered under in `by_view` and
+    /// whether they were decided
+    seen: HashMap<Commitment<T>, ((u64, u64), bool)>,
+    /// Transactions by the view they were last seen in, then by the order they were seen in
+    by_view: BTreeMap<(u64, u64), Commitment<T>>,
+    /// Position of the next transaction seen
+    next_seq: u64,
+}
+
+impl<T: Committable> TransactionDedup<T> {
+    /// Create an empty set
+    #[must_use]
+    pub fn new(config: TransactionDedupConfig) -> Self {
+        Self {
+            config,
+            seen: HashMap::new(),
+            by_view: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Number of transactions remembered
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Whether no transactions are remembered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Whether the transaction with `commitment` has been seen within the window
+    #[must_use]
+    pub fn contains(&self, commitment: &Commitment<T>) -> bool {
+        self.seen.contains_key(commitment)
+    }
+
+    /// Record a transaction received in `view`.
+    ///
+    /// Returns `false`, and leaves the set unchanged, if the transaction was already seen within
+    /// the window, in which case it should be dropped.
+    pub fn insert(&mut self, view: u64, commitment: Commitment<T>) -> bool {
+        if self.contains(&commitment) {
+            return false;
+        }
+        self.remember(view, commitment);
+        true
+    }
+
+    /// Record transactions included in the decided block of `view`.
+    ///
+    /// Transactions already seen are remembered for a window from `view`, so a transaction stays
+    /// rejected for a full window after it is sequenced, however long it was pending.
+    pub fn include(&mut self, view: u64, commitments: impl IntoIterator<Item = Commitment<T>>) {
+        for commitment in commitments {
+            if let Some(&(key, decided)) = self.seen.get(&commitment) {
+                if decided && key.0 >= view {
+                    continue;
+                }
+                self.by_view.remove(&key);
+            }
+            self.remember(view, commitment);
+            if let Some((_, decided)) = self.seen.get_mut(&commitment) {
+                *decided = true;
+            }
+        }
+    }
+
+    /// Forget the pending transactions received up to `view`, which has failed.
+    ///
+    /// These transactions may have been built into the block of the failed view, in which case
+    /// they have not been sequenced and must be accepted again. Decided transactions are kept.
+    pub fn fail(&mut self, view: u64) {
+        let failed = self
+            .by_view
+            .range(..=(view, u64::MAX))
+            .filter(|(_, commitment)| matches!(self.seen.get(commitment), Some((_, false))))
+            .map(|(&key, _)| key)
+            .collect::<Vec<_>>();
+        for key in failed {
+            if let Some(commitment) = self.by_view.remove(&key) {
+                self.seen.remove(&commitment);
+            }
+        }
+    }
+
+    /// Forget the transactions last seen more than a window before `view`.
+    pub fn expire(&mut self, view: u64) {
+        let cutoff = view.saturating_sub(self.config.window);
+        while let Some(entry) = self.by_view.first_entry() {
+            if entry.key().0 >= cutoff {
+                break;
+            }
+            self.seen.remove(&entry.remove());
+        }
+    }
+
+    fn remember(&mut self, view: u64, commitment: Commitment<T>) {
+        let key = (view, self.next_seq);
+        self.next_seq += 1;
+        self.seen.insert(commitment, (key, false));
+        self.by_view.insert(key, commitment);
+
+        while self.seen.len() > self.config.max_transactions {
+            let Some((_, oldest)) = self.by_view.pop_first() else {
+                break;
+            };
+            self.seen.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use committable::RawCommitmentBuilder;
+
+    use super::*;
+
+    struct TestTx(u64);
+
+    impl Committable for TestTx {
+        fn commit(&self) -> Commitment<Self> {
+            RawCommitmentBuilder::new("TestTx")
+                .u64_field("id", self.0)
+                .finalize()
+        }
+    }
+
+    fn tx(id: u64) -> Commitment<TestTx> {
+        TestTx(id).commit()
+    }
+
+    #[test]
+    fn test_rejects_transactions_seen_within_window() {
+        let mut dedup = TransactionDedup::new(TransactionDedupConfig {
+            window: 10,
+            ..Default::default()
+        });
+
+        // Pending transactions are rejected when resubmitted.
+        assert!(dedup.insert(1, tx(1)));
+        assert!(dedup.insert(1, tx(2)));
+        assert!(!dedup.insert(2, tx(1)));
+
+        // Decided transactions are remembered for a window from the view of their block.
+        dedup.include(8, [tx(2), tx(3)]);
+        dedup.expire(12);
+        assert!(dedup.insert(12, tx(1)));
+        assert!(!dedup.insert(12, tx(2)));
+        assert!(!dedup.insert(12, tx(3)));
+
+        dedup.expire(19);
+        assert!(!dedup.contains(&tx(2)));
+        assert!(dedup.contains(&tx(1)));
+        assert!(dedup.insert(19, tx(3)));
+        assert_eq!(dedup.len(), 2);
+    }
+
+    #[test]
+    fn test_forgets_pending_transactions_of_failed_views() {
+        let mut dedup = TransactionDedup::new(TransactionDedupConfig {
+            window: 10,
+            ..Default::default()
+        });
+        assert!(dedup.insert(1, tx(1)));
+        assert!(dedup.insert(2, tx(2)));
+        assert!(dedup.insert(2, tx(3)));
+        assert!(dedup.insert(4, tx(4)));
+        dedup.include(3, [tx(3)]);
+
+        // Pending transactions received up to the failed view can be submitted again, decided
+        // transactions and those received later cannot.
+        dedup.fail(2);
+        assert!(dedup.insert(5, tx(1)));
+        assert!(dedup.insert(5, tx(2)));
+        assert!(!dedup.insert(5, tx(3)));
+        assert!(!dedup.insert(5, tx(4)));
+    }
+
+    #[test]
+    fn test_bounded_memory() {
+        let mut dedup = TransactionDedup::new(TransactionDedupConfig {
+            window: 10,
+            max_transactions: 3,
+        });
+        for id in 0..5 {
+            assert!(dedup.insert(id, tx(id)));
+        }
+
+        // The oldest transactions are forgotten first.
+        assert_eq!(dedup.len(), 3);
+        assert!(!dedup.contains(&tx(1)));
+        assert!(dedup.contains(&tx(2)));
+
+        // Including a transaction refreshes it.
+        dedup.include(5, [tx(2)]);
+        assert!(dedup.insert(6, tx(6)));
+        assert!(dedup.contains(&tx(2)));
+        assert!(!dedup.contains(&tx(3)));
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Readiness of the network for a protocol upgrade.
+//!
+//! Nodes periodically report the highest protocol version they support. Before proposing an
+//! upgrade, the leader checks that the nodes supporting the new version hold enough stake to
+//! certify it, so that an upgrade which cannot reach quorum is never started.
+
+use std::{collections::HashMap, hash::Hash};
+
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+use vbs::version::Version;
+
+/// The protocol versions nodes have reported supporting.
+#[derive(Clone, Debug)]
+pub struct UpgradeReadiness<K> {
+    /// The highest version supported by each node which has reported.
+    supported: HashMap<K, Version>,
+}
+
+impl<K> Default for UpgradeReadiness<K> {
+    fn default() -> Self {
+        Self {
+            supported: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> UpgradeReadiness<K> {
+    /// Record that `key` supports protocol versions up to `version`.
+    pub fn report(&mut self, key: K, version: Version) {
+        self.supported.insert(key, version);
+    }
+
+    /// Whether any node has reported its supported version.
+    ///
+    /// Networks whose nodes do not report are assumed ready for any upgrade they are configured
+    /// for, as they were before readiness was tracked.
+    pub fn has_reports(&self) -> bool {
+        !self.supported.is_empty()
+    }
+
+    /// Whether `key` has reported supporting `version`.
+    pub fn supports(&self, key: &K, version: Version) -> bool {
+        self.supported
+            .get(key)
+            .is_some_and(|supported| *supported >= version)
+    }
+
+    /// The total stake of the nodes in `stake_table` which support `version`.
+    pub fn supporting_stake(
+        &self,
+        stake_table: impl IntoIterator<Item = (K, U256)>,
+        version: Version,
+    ) -> U256 {
+        stake_table
+            .into_iter()
+            .filter(|(key, _)| self.supports(key, version))
+            .fold(U256::ZERO, |acc, (_, stake)| acc + stake)
+    }
+}
+
+/// Whether the network is ready to upgrade to a new version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeReadinessReport {
+    /// The version being upgraded to.
+    pub version: Version,
+    /// The stake of the nodes reporting support for `version`.
+    pub supporting_stake: U256,
+    /// The stake required to certify the upgrade.
+    pub threshold: U256,
+}
+
+impl UpgradeReadinessReport {
+    /// Whether enough stake supports the upgrade for it to be certified.
+    pub fn is_ready(&self) -> bool {
+        self.supporting_stake >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_supporting_stake() {
+        let old = Version { major: 0, minor: 2 };
+        let new = Version { major: 0, minor: 3 };
+        let stake_table = || {
+            [
+                (1, U256::from(10)),
+                (2, U256::from(20)),
+                (3, U256::from(30)),
+            ]
+        };
+
+        let mut readiness = UpgradeReadiness::default();
+        assert!(!readiness.has_reports());
+        assert_eq!(readiness.supporting_stake(stake_table(), new), U256::ZERO);
+
+        readiness.report(1, new);
+        readiness.report(2, old);
+        // Nodes outside the stake table do not count.
+        readiness.report(4, new);
+        assert!(readiness.has_reports());
+        assert_eq!(
+            readiness.supporting_stake(stake_table(), new),
+            U256::from(10)
+        );
+        assert_eq!(
+            readiness.supporting_stake(stake_table(), old),
+            U256::from(30)
+        );
+
+        // A later report replaces an earlier one.
+        readiness.report(2, new);
+        assert_eq!(
+            readiness.supporting_stake(stake_table(), new),
+            U256::from(30)
+        );
+
+        let report = UpgradeReadinessReport {
+            version: new,
+            supporting_stake: U256::from(30),
+            threshold: U256::from(41),
+        };
+        assert!(!report.is_ready());
+    }
+}
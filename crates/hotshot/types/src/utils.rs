@@ -32,6 +32,7 @@ use vbs::version::StaticVersionType;
 
 use crate::{
     data::{Leaf2, VidCommitment},
+    feature_gates::Feature,
     traits::{
         node_implementation::{ConsensusTime, NodeType, Versions},
         ValidatedState,
@@ -391,7 +392,7 @@ pub fn option_epoch_from_block_number<TYPES: NodeType>(
 /// Returns Some(1) if epochs are enabled by V::Base, otherwise returns None
 #[must_use]
 pub fn genesis_epoch_from_version<V: Versions, TYPES: NodeType>() -> Option<TYPES::Epoch> {
-    (V::Base::VERSION >= V::Epochs::VERSION).then(|| TYPES::Epoch::new(1))
+    (Feature::Epochs.enabled_in::<V>(V::Base::VERSION)).then(|| TYPES::Epoch::new(1))
 }
 
 /// A function for generating a cute little user mnemonic from a hash
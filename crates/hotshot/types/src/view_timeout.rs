@@ -0,0 +1,209 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! View timeouts adapted to how long views have recently taken.
+//!
+//! With a static view timeout, the timeout must be long enough for the slowest conditions the
+//! network is expected to see, so every failed leader stalls the chain for that long even when
+//! views normally complete in a fraction of it. With an [`AdaptiveViewTimeoutConfig`], the view
+//! timeout instead follows a multiple of a percentile of the durations of recent successful views,
+//! within configured bounds. A view which times out doubles the timeout, backing off when the
+//! network is congested, and the timeout then shrinks gradually as views succeed again.
+
+use std::{collections::VecDeque, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Configuration of adaptive view timeouts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdaptiveViewTimeoutConfig {
+    /// Shortest view timeout, in milliseconds
+    pub min_timeout: u64,
+    /// Longest view timeout, in milliseconds
+    pub max_timeout: u64,
+    /// Percentile of recent view durations the timeout is based on
+    #[serde(default = "default_percentile")]
+    pub percentile: u8,
+    /// Multiple of the percentile view duration allowed before a view times out
+    #[serde(default = "default_multiplier")]
+    pub multiplier: u32,
+    /// Number of recent successful views whose durations are considered
+    #[serde(default = "default_window")]
+    pub window: usize,
+}
+
+/// Default [`AdaptiveViewTimeoutConfig::percentile`]
+fn default_percentile() -> u8 {
+    95
+}
+
+/// Default [`AdaptiveViewTimeoutConfig::multiplier`]
+fn default_multiplier() -> u32 {
+    3
+}
+
+/// Default [`AdaptiveViewTimeoutConfig::window`]
+fn default_window() -> usize {
+    100
+}
+
+/// Fewest view durations on which the timeout is based; until then the base timeout is kept
+const MIN_SAMPLES: usize = 10;
+
+/// View timeout controller
+///
+/// Starts from the configured base view timeout, and adjusts it as views succeed or time out.
+#[derive(Clone, Debug)]
+pub struct AdaptiveViewTimeout {
+    config: AdaptiveViewTimeoutConfig,
+    /// Durations of recent successful views, oldest first
+    durations: VecDeque<Duration>,
+    /// Current view timeout, in milliseconds
+    current: u64,
+}
+
+impl AdaptiveViewTimeout {
+    /// Adapt the view timeout, starting from `base` milliseconds.
+    #[must_use]
+    pub fn new(base: u64, mut config: AdaptiveViewTimeoutConfig) -> Self {
+        config.max_timeout = config.max_timeout.max(config.min_timeout);
+        config.percentile = config.percentile.clamp(1, 100);
+        config.multiplier = config.multiplier.max(1);
+        config.window = config.window.max(1);
+        Self {
+            durations: VecDeque::with_capacity(config.window),
+            current: base.clamp(config.min_timeout, config.max_timeout),
+            config,
+        }
+    }
+
+    /// The current view timeout, in milliseconds
+    #[must_use]
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Record a view which succeeded after `duration`.
+    ///
+    /// Returns the new view timeout if it changed. The timeout grows immediately if views are
+    /// getting slower, but shrinks by at most a quarter per view.
+    pub fn observe_view(&mut self, duration: Duration) -> Option<u64> {
+        if self.durations.len() == self.config.window {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+        if self.durations.len() < MIN_SAMPLES.min(self.config.window) {
+            return None;
+        }
+
+        let target = self.target();
+        let new = if target >= self.current {
+            target
+        } else {
+            target.max(self.current - self.current / 4)
+        };
+        self.set(new)
+    }
+
+    /// Record a view which timed out.
+    ///
+    /// Returns the new view timeout if it changed.
+    pub fn observe_timeout(&mut self) -> Option<u64> {
+        self.set(self.current.saturating_mul(2).min(self.config.max_timeout))
+    }
+
+    /// The view timeout called for by recent view durations
+    fn target(&self) -> u64 {
+        let mut durations = self.durations.iter().copied().collect::<Vec<_>>();
+        durations.sort_unstable();
+        // Nearest-rank percentile
+        let rank = (durations.len() * usize::from(self.config.percentile)).div_ceil(100);
+        let duration = durations[rank.saturating_sub(1)];
+        let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        millis
+            .saturating_mul(self.config.multiplier.into())
+            .clamp(self.config.min_timeout, self.config.max_timeout)
+    }
+
+    fn set(&mut self, new: u64) -> Option<u64> {
+        if new == self.current {
+            return None;
+        }
+        self.current = new;
+        Some(new)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> AdaptiveViewTimeoutConfig {
+        AdaptiveViewTimeoutConfig {
+            min_timeout: 1000,
+            max_timeout: 30000,
+            percentile: 90,
+            multiplier: 3,
+            window: 20,
+        }
+    }
+
+    #[test]
+    fn test_adaptive_view_timeout() {
+        let mut timeout = AdaptiveViewTimeout::new(10000, config());
+        assert_eq!(timeout.current(), 10000);
+
+        // The base timeout is kept until enough views have been seen.
+        for _ in 0..MIN_SAMPLES - 1 {
+            assert_eq!(timeout.observe_view(Duration::from_millis(500)), None);
+        }
+
+        // A fast network shrinks the timeout gradually, down to the floor.
+        assert_eq!(timeout.observe_view(Duration::from_millis(500)), Some(7500));
+        assert_eq!(timeout.observe_view(Duration::from_millis(500)), Some(5625));
+        for _ in 0..20 {
+            timeout.observe_view(Duration::from_millis(100));
+        }
+        assert_eq!(timeout.current(), 1000);
+
+        // Timeouts back off up to the ceiling.
+        assert_eq!(timeout.observe_timeout(), Some(2000));
+        for _ in 0..10 {
+            timeout.observe_timeout();
+        }
+        assert_eq!(timeout.current(), 30000);
+        assert_eq!(timeout.observe_timeout(), None);
+
+        // Slow views raise the timeout immediately, based on the percentile of the window.
+        let mut timeout = AdaptiveViewTimeout::new(1000, config());
+        for i in 1..=20 {
+            timeout.observe_view(Duration::from_millis(i * 100));
+        }
+        assert_eq!(timeout.current(), 5400);
+    }
+
+    #[test]
+    fn test_adaptive_view_timeout_bounds() {
+        // The base timeout is clamped to the bounds.
+        assert_eq!(AdaptiveViewTimeout::new(100, config()).current(), 1000);
+        assert_eq!(AdaptiveViewTimeout::new(100000, config()).current(), 30000);
+
+        // Inconsistent bounds do not panic.
+        let mut timeout = AdaptiveViewTimeout::new(
+            5000,
+            AdaptiveViewTimeoutConfig {
+                min_timeout: 2000,
+                max_timeout: 1000,
+                percentile: 0,
+                multiplier: 0,
+                window: 0,
+            },
+        );
+        assert_eq!(timeout.current(), 2000);
+        assert_eq!(timeout.observe_view(Duration::from_millis(10)), None);
+        assert_eq!(timeout.observe_timeout(), None);
+    }
+}
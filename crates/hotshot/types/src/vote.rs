@@ -10,18 +10,21 @@ use std::{
     collections::{BTreeMap, HashMap},
     future::Future,
     marker::PhantomData,
+    time::{Duration, Instant},
 };
 
 use alloy::primitives::U256;
 use bitvec::{bitvec, vec::BitVec};
 use committable::{Commitment, Committable};
 use hotshot_utils::anytrace::*;
+use serde::{Deserialize, Serialize};
 use tracing::error;
 
 use crate::{
     epoch_membership::EpochMembership,
     light_client::{LightClientState, StakeTableState},
     message::UpgradeLock,
+    signature_verifier::SignatureVerifier,
     simple_certificate::{LightClientStateUpdateCertificate, Threshold},
     simple_vote::{LightClientStateUpdateVote, VersionedVoteData, Voteable},
     traits::{
@@ -107,12 +110,30 @@ pub trait Certificate<TYPES: NodeType, T>: HasViewNumber<TYPES> {
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> impl std::future::Future<Output = Result<Commitment<VersionedVoteData<TYPES, Self::Voteable, V>>>>;
 }
-/// Mapping of vote commitment to signatures and bitvec
+/// Evidence that a node signed two different votes of the same kind in the same view
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct DoubleVoteEvidence<TYPES: NodeType> {
+    /// The view both votes were cast in
+    pub view: TYPES::View,
+    /// The key which signed both votes
+    pub key: TYPES::SignatureKey,
+    /// Commitment to the versioned data of the first vote
+    pub first_commitment: [u8; 32],
+    /// Signature on the first vote
+    pub first_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    /// Commitment to the versioned data of the second vote
+    pub second_commitment: [u8; 32],
+    /// Signature on the second vote
+    pub second_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+/// Mapping of vote commitment to the signers bitvec and the signatures aggregated so far
 type SignersMap<COMMITMENT, KEY> = HashMap<
     COMMITMENT,
     (
         BitVec,
-        Vec<<KEY as SignatureKey>::PureAssembledSignatureType>,
+        Option<<KEY as SignatureKey>::PureAssembledSignatureType>,
     ),
 >;
 
@@ -131,7 +152,7 @@ pub struct VoteAccumulator<
         <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
     >,
     /// A bitvec to indicate which node is active and send out a valid signature for certificate aggregation, this automatically do uniqueness check
-    /// And a list of valid signatures for certificate aggregation
+    /// And the aggregate of the valid signatures, folded in as votes arrive
     pub signers: SignersMap<
         Commitment<VersionedVoteData<TYPES, <VOTE as Vote<TYPES>>::Commitment, V>>,
         TYPES::SignatureKey,
@@ -140,6 +161,10 @@ pub struct VoteAccumulator<
     pub phantom: PhantomData<(TYPES, VOTE, CERT)>,
     /// version information
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+    /// When the first valid vote was accumulated
+    pub first_vote_time: Option<Instant>,
+    /// Evidence of double votes found since it was last taken
+    pub double_votes: Vec<DoubleVoteEvidence<TYPES>>,
 }
 
 impl<
@@ -173,12 +198,16 @@ impl<
             },
         };
 
-        if !key.validate(&vote.signature(), vote_commitment.as_ref()) {
+        // Only stake table keys may be verified in a batch, so check membership first.
+        let stake_table_entry = CERT::stake_table_entry(&membership, &key).await?;
+        if !SignatureVerifier::shared()
+            .verify(key.clone(), vote.signature(), vote_commitment.as_ref())
+            .await
+        {
             error!("Invalid vote! Vote Data {:?}", vote.date());
             return None;
         }
 
-        let stake_table_entry = CERT::stake_table_entry(&membership, &key).await?;
         let stake_table = CERT::stake_table(&membership).await;
         let total_nodes = CERT::total_nodes(&membership).await;
         let threshold = CERT::threshold(&membership).await;
@@ -190,6 +219,27 @@ impl<
         let original_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType =
             vote.signature();
 
+        // A valid vote for something else in this view from the same key is a double vote. Only
+        // the first vote counts towards a certificate.
+        let first_vote = self
+            .vote_outcomes
+            .values()
+            .find_map(|(_, votes)| votes.get(&key))
+            .filter(|(_, commitment)| *commitment != vote_commitment)
+            .cloned();
+        if let Some((first_signature, first_commitment)) = first_vote {
+            tracing::warn!(%key, view = ?vote.view_number(), "Double vote detected");
+            self.double_votes.push(DoubleVoteEvidence {
+                view: vote.view_number(),
+                key: key.clone(),
+                first_commitment: first_commitment.into(),
+                first_signature,
+                second_commitment: vote_commitment.into(),
+                second_signature: original_signature,
+            });
+            return None;
+        }
+
         let (total_stake_casted, total_vote_map) = self
             .vote_outcomes
             .entry(vote_commitment)
@@ -199,16 +249,21 @@ impl<
         if total_vote_map.contains_key(&key) {
             return None;
         }
-        let (signers, sig_list) = self
+        let (signers, aggregate) = self
             .signers
             .entry(vote_commitment)
-            .or_insert((bitvec![0; total_nodes], Vec::new()));
+            .or_insert((bitvec![0; total_nodes], None));
         if signers.get(vote_node_id).as_deref() == Some(&true) {
             error!("Node id is already in signers list");
             return None;
         }
         signers.set(vote_node_id, true);
-        sig_list.push(original_signature);
+        *aggregate = Some(<TYPES::SignatureKey as SignatureKey>::aggregate_partial(
+            aggregate.as_ref(),
+            &key,
+            &original_signature,
+        ));
+        self.first_vote_time.get_or_insert_with(Instant::now);
 
         *total_stake_casted += stake_table_entry.stake_table_entry.stake();
         total_vote_map.insert(key, (vote.signature(), vote_commitment));
@@ -221,10 +276,10 @@ impl<
                     threshold,
                 );
 
-            let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(
+            let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble_aggregated(
                 &real_qc_pp,
                 signers.as_bitslice(),
-                &sig_list[..],
+                aggregate.clone()?,
             );
 
             let cert = CERT::create_signed_certificate::<V>(
@@ -237,6 +292,17 @@ impl<
         }
         None
     }
+
+    /// Take the evidence of double votes found since the last call
+    pub fn take_double_votes(&mut self) -> Vec<DoubleVoteEvidence<TYPES>> {
+        std::mem::take(&mut self.double_votes)
+    }
+
+    /// Time elapsed between the first valid vote and now, e.g. once a certificate is formed
+    #[must_use]
+    pub fn time_since_first_vote(&self) -> Option<Duration> {
+        self.first_vote_time.map(|time| time.elapsed())
+    }
 }
 
 /// Mapping of commitments to vote tokens by key.
@@ -205,7 +205,11 @@ where
 
             match event.event {
                 EventType::Error { error } => {
-                    error!("Error event in HotShot: {:?}", error);
+                    error!(
+                        code = error.code(),
+                        category = ?error.category(),
+                        "Error event in HotShot: {error}"
+                    );
                 },
                 EventType::Transactions { transactions } => {
                     let this = Arc::clone(&self);
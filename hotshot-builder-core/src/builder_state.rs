@@ -16,7 +16,7 @@ use hotshot_types::{
     data::{DaProposal2, Leaf2, QuorumProposalWrapper},
     message::Proposal,
     traits::{
-        block_contents::{BlockHeader, BlockPayload},
+        block_contents::{BlockHeader, BlockPayload, Transaction},
         node_implementation::{ConsensusTime, NodeType, Versions},
         EncodeBytes,
     },
@@ -1086,6 +1086,33 @@ impl<Types: NodeType, V: Versions> BuilderState<Types, V> {
         }
     }
 
+    /// Queue a transaction behind those offering at least the same priority fee
+    ///
+    /// A transaction with a replacement key takes the place of the queued transaction with the
+    /// same key if it offers a higher fee, and is dropped otherwise.
+    fn enqueue(&mut self, tx: Arc<ReceivedTransaction<Types>>) {
+        let fee = tx.tx.priority_fee();
+        if let Some(key) = tx.tx.replacement_key() {
+            if let Some(index) = self
+                .tx_queue
+                .iter()
+                .position(|queued| queued.tx.replacement_key().as_ref() == Some(&key))
+            {
+                if self.tx_queue[index].tx.priority_fee() >= fee {
+                    return;
+                }
+                if let Some(replaced) = self.tx_queue.remove(index) {
+                    self.txns_in_queue.remove(&replaced.commit);
+                }
+            }
+        }
+        let index = self
+            .tx_queue
+            .partition_point(|queued| queued.tx.priority_fee() >= fee);
+        self.txns_in_queue.insert(tx.commit);
+        self.tx_queue.insert(index, tx);
+    }
+
     // collect outstanding transactions
     async fn collect_txns(&mut self, timeout_after: Instant) {
         while Instant::now() <= timeout_after {
@@ -1098,8 +1125,7 @@ impl<Types: NodeType, V: Versions> BuilderState<Types, V> {
                     {
                         continue;
                     }
-                    self.txns_in_queue.insert(tx.commit);
-                    self.tx_queue.push_back(tx);
+                    self.enqueue(tx);
                 },
                 Err(async_broadcast::TryRecvError::Empty)
                 | Err(async_broadcast::TryRecvError::Closed) => {
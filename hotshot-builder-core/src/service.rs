@@ -1110,7 +1110,11 @@ pub async fn run_non_permissioned_standalone_builder_service<
 
         match event.event {
             EventType::Error { error } => {
-                tracing::error!("Error event in HotShot: {:?}", error);
+                tracing::error!(
+                    code = error.code(),
+                    category = ?error.category(),
+                    "Error event in HotShot: {error}"
+                );
             },
             // tx event
             EventType::Transactions { transactions } => {
@@ -1,15 +1,18 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
 use clap::Args;
 use derive_more::From;
 use futures::{FutureExt, StreamExt, TryFutureExt};
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{
+    api_auth::{ApiKeys, AuthError, Scope},
+    traits::node_implementation::NodeType,
+};
 use serde::{Deserialize, Serialize};
 use snafu::Snafu;
-use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, StatusCode};
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, RequestParams, StatusCode};
 use vbs::version::StaticVersionType;
 
-use crate::{api::load_api, events_source::EventsSource};
+use crate::{api::load_api, events_source::EventsSource, schema::VersionedEvent};
 
 #[derive(Args, Default, Debug)]
 pub struct Options {
@@ -29,6 +32,17 @@ pub struct Options {
         value_delimiter = ','
     )]
     pub extensions: Vec<toml::Value>,
+
+    /// File configuring the API keys clients must present to use the events API.
+    ///
+    /// If set, every request must carry a key with the `read-events` scope as
+    /// `Authorization: Bearer KEY`, and is counted against the rate limit of the key. See
+    /// [`hotshot_types::api_auth`] for the format. If not set, the API is open to everyone.
+    #[arg(
+        long = "hotshot-events-service-api-keys",
+        env = "HOTSHOT_EVENTS_SERVICE_API_KEYS"
+    )]
+    pub api_keys: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug, Snafu, Deserialize, Serialize)]
@@ -55,12 +69,34 @@ pub enum Error {
         source: EventError,
         resource: String,
     },
+    #[snafu(display("{message}"))]
+    #[from(ignore)]
+    Unauthorized {
+        message: String,
+        status: StatusCode,
+    },
     Custom {
         message: String,
         status: StatusCode,
     },
 }
 
+impl From<AuthError> for Error {
+    fn from(err: AuthError) -> Self {
+        let status = match err {
+            AuthError::Missing | AuthError::Invalid | AuthError::InvalidSignature(_) => {
+                StatusCode::UNAUTHORIZED
+            },
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        };
+        Error::Unauthorized {
+            message: err.to_string(),
+            status,
+        }
+    }
+}
+
 impl tide_disco::error::Error for Error {
     fn catch_all(status: StatusCode, msg: String) -> Self {
         Error::Custom {
@@ -76,11 +112,25 @@ impl tide_disco::error::Error for Error {
                 EventError::Missing => StatusCode::NOT_FOUND,
                 EventError::Error { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             },
+            Error::Unauthorized { status, .. } => *status,
             Error::Custom { .. } => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+/// Check that `req` presents a key allowing `scope`, if `keys` are required.
+fn authorize(keys: Option<&ApiKeys>, req: &RequestParams, scope: Scope) -> Result<(), Error> {
+    let Some(keys) = keys else {
+        return Ok(());
+    };
+    let authorization = req
+        .header("Authorization")
+        .map(|values| values.last().as_str());
+    let key = keys.authorize(authorization, scope)?;
+    tracing::debug!(key, %scope, "authorized request");
+    Ok(())
+}
+
 pub fn define_api<State, Types, Ver>(options: &Options) -> Result<Api<State, Error, Ver>, ApiError>
 where
     State: 'static + Send + Sync + ReadState,
@@ -93,9 +143,23 @@ where
         include_str!("../api/hotshot_events.toml"),
         options.extensions.clone(),
     )?;
+    let keys = options
+        .api_keys
+        .as_ref()
+        .map(|path| ApiKeys::load(path))
+        .transpose()
+        .map_err(|err| ApiError::CannotReadToml {
+            reason: format!("{err:#}"),
+        })?
+        .map(Arc::new);
+    let versioned_events_keys = keys.clone();
+    let startup_info_keys = keys.clone();
+
     api.with_version("0.1.0".parse().unwrap())
-        .stream("events", move |_, state| {
+        .stream("events", move |req, state| {
+            let auth = authorize(keys.as_deref(), &req, Scope::ReadEvents);
             async move {
+                auth?;
                 tracing::info!("client subscribed to events");
                 state
                     .read(|state| {
@@ -106,8 +170,35 @@ where
             .try_flatten_stream()
             .boxed()
         })?
-        .get("startup_info", |_, state| {
-            async move { Ok(state.get_startup_info().await) }.boxed()
+        .stream("versioned_events", move |req, state| {
+            let auth = authorize(versioned_events_keys.as_deref(), &req, Scope::ReadEvents);
+            async move {
+                auth?;
+                tracing::info!("client subscribed to versioned events");
+                state
+                    .read(|state| {
+                        async move {
+                            Ok(state.get_event_stream(None).await.map(|event| {
+                                VersionedEvent::encode(&*event).map_err(|err| Error::Custom {
+                                    message: err.to_string(),
+                                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                                })
+                            }))
+                        }
+                        .boxed()
+                    })
+                    .await
+            }
+            .try_flatten_stream()
+            .boxed()
+        })?
+        .get("startup_info", move |req, state| {
+            let auth = authorize(startup_info_keys.as_deref(), &req, Scope::ReadEvents);
+            async move {
+                auth?;
+                Ok(state.get_startup_info().await)
+            }
+            .boxed()
         })?;
 
     Ok(api)
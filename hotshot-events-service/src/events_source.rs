@@ -8,6 +8,7 @@ use futures::{
 };
 use hotshot_types::{
     event::{Event, EventType},
+    leader_schedule::LeaderSchedule,
     traits::node_implementation::NodeType,
     PeerConfig,
 };
@@ -32,6 +33,16 @@ pub struct StartupInfo<Types: NodeType> {
     pub non_staked_node_count: usize,
 }
 
+impl<Types: NodeType> StartupInfo<Types> {
+    /// The leader schedule of the network before epochs, computed from its initial stake table.
+    ///
+    /// With epochs, leaders are drawn using randomness which is only known as the network runs;
+    /// the leader schedule of each epoch is published by the node API instead.
+    pub fn leader_schedule(&self) -> LeaderSchedule<Types> {
+        LeaderSchedule::round_robin(self.known_node_with_stake.clone())
+    }
+}
+
 #[async_trait]
 pub trait EventConsumer<Types>
 where
@@ -105,6 +116,7 @@ impl<Types: NodeType> EventFilterSet<Types> {
             EventType::ViewTimeout { .. } => filter.contains(&EventFilter::ViewTimeout),
             EventType::Transactions { .. } => filter.contains(&EventFilter::Transactions),
             EventType::DaProposal { .. } => filter.contains(&EventFilter::DaProposal),
+            EventType::DaCertificate { .. } => filter.contains(&EventFilter::DaCertificate),
             EventType::QuorumProposal { .. } => filter.contains(&EventFilter::QuorumProposal),
             EventType::UpgradeProposal { .. } => filter.contains(&EventFilter::UpgradeProposal),
             _ => false,
@@ -123,6 +135,7 @@ pub enum EventFilter<Types: NodeType> {
     ViewTimeout,
     Transactions,
     DaProposal,
+    DaCertificate,
     QuorumProposal,
     UpgradeProposal,
     Pd(PhantomData<Types>),
@@ -1,4 +1,5 @@
 mod api;
 pub mod events;
 pub mod events_source;
+pub mod schema;
 mod test;
@@ -0,0 +1,156 @@
+//! Versioned JSON encoding of events.
+//!
+//! The `events` endpoint sends [`Event`]s in the encoding of the internal consensus types, so a
+//! consumer breaks as soon as the node it follows moves to types which encode differently. The
+//! `versioned_events` endpoint instead sends each event as a [`VersionedEvent`]: the JSON encoding
+//! of the event, labelled with the version of the encoding and the kind of event. Consumers decode
+//! it with [`VersionedEvent::decode`], which
+//! * upgrades events encoded with an older version, using the upgrades registered in [`SCHEMAS`],
+//! * ignores fields unknown to the consumer, which newer producers may send, and
+//! * skips events from newer producers which the consumer cannot decode at all, such as new kinds
+//!   of event, instead of failing.
+//!
+//! Whenever a change to the consensus types changes the encoding of events such that events
+//! encoded before the change no longer decode, for example by adding a field without a default or
+//! renaming a field, a new version must be registered in [`SCHEMAS`], with an upgrade which
+//! rewrites events encoded with the previous version. The examples pinned in `data/event-schema`
+//! catch such changes.
+
+use hotshot_types::{event::Event, traits::node_implementation::NodeType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::{ensure, ResultExt, Snafu};
+
+/// A version of the JSON encoding of events.
+#[derive(Clone, Copy, Debug)]
+pub struct Schema {
+    pub version: u16,
+    /// What changed since the previous version
+    pub changes: &'static str,
+    /// Rewrite an event encoded with the previous version so that it decodes as this version
+    pub upgrade: fn(&mut Value),
+}
+
+/// Every version of the JSON encoding of events, oldest first.
+pub const SCHEMAS: &[Schema] = &[Schema {
+    version: 1,
+    changes: "first versioned encoding of events",
+    upgrade: no_upgrade,
+}];
+
+/// The version of the JSON encoding of events produced by this build.
+pub const EVENT_SCHEMA_VERSION: u16 = SCHEMAS[SCHEMAS.len() - 1].version;
+
+fn no_upgrade(_: &mut Value) {}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum SchemaError {
+    #[snafu(display("unknown event schema version {version}"))]
+    UnknownVersion { version: u16 },
+    #[snafu(display("failed to encode event: {source}"))]
+    Encode { source: serde_json::Error },
+    #[snafu(display("failed to decode event of schema version {version}: {source}"))]
+    Decode {
+        version: u16,
+        source: serde_json::Error,
+    },
+}
+
+/// An [`Event`] in the JSON encoding of a specific schema version.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionedEvent {
+    /// The version of the encoding of `event`
+    pub schema_version: u16,
+    /// The kind of event, i.e. the name of its [`EventType`](hotshot_types::event::EventType)
+    ///
+    /// Consumers can use this to skip kinds of event they are not interested in without decoding
+    /// them.
+    pub kind: String,
+    /// The JSON encoding of the event
+    #[serde(with = "json_payload")]
+    pub event: Value,
+}
+
+impl VersionedEvent {
+    /// Encode `event` with the current schema version.
+    pub fn encode<Types: NodeType>(event: &Event<Types>) -> Result<Self, SchemaError> {
+        let event = serde_json::to_value(event).context(EncodeSnafu)?;
+        let kind = event["event"]
+            .as_object()
+            .and_then(|variant| variant.keys().next())
+            .cloned()
+            .unwrap_or_default();
+        Ok(Self {
+            schema_version: EVENT_SCHEMA_VERSION,
+            kind,
+            event,
+        })
+    }
+
+    /// Decode the event, upgrading it from an older schema version if necessary.
+    ///
+    /// Returns `None` if the event was encoded with a newer schema version and cannot be decoded
+    /// by this build, in which case consumers should skip it.
+    pub fn decode<Types: NodeType>(self) -> Result<Option<Event<Types>>, SchemaError> {
+        self.decode_with(SCHEMAS)
+    }
+
+    pub(crate) fn decode_with<Types: NodeType>(
+        self,
+        schemas: &[Schema],
+    ) -> Result<Option<Event<Types>>, SchemaError> {
+        let Self {
+            schema_version: version,
+            kind,
+            mut event,
+        } = self;
+
+        let current = schemas.last().map_or(0, |schema| schema.version);
+        if version > current {
+            // Fields added since our version are ignored, but the event may still be beyond us.
+            return match serde_json::from_value(event) {
+                Ok(event) => Ok(Some(event)),
+                Err(err) => {
+                    tracing::debug!(version, kind, "skipping event of newer schema: {err}");
+                    Ok(None)
+                },
+            };
+        }
+
+        ensure!(
+            schemas.iter().any(|schema| schema.version == version),
+            UnknownVersionSnafu { version }
+        );
+        for schema in schemas.iter().filter(|schema| schema.version > version) {
+            (schema.upgrade)(&mut event);
+        }
+        serde_json::from_value(event)
+            .map(Some)
+            .context(DecodeSnafu { version })
+    }
+}
+
+/// Encodes the payload of a [`VersionedEvent`] as nested JSON in JSON, but as a string in binary
+/// formats, which cannot decode arbitrary JSON values.
+mod json_payload {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    pub fn serialize<S: Serializer>(value: &Value, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            value.serialize(serializer)
+        } else {
+            value.to_string().serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        if deserializer.is_human_readable() {
+            Value::deserialize(deserializer)
+        } else {
+            let json = String::deserialize(deserializer)?;
+            serde_json::from_str(&json).map_err(D::Error::custom)
+        }
+    }
+}
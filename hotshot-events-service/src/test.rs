@@ -1,31 +1,44 @@
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{marker::PhantomData, sync::Arc};
 
     use alloy::primitives::U256;
     use async_lock::RwLock;
     use futures::stream::StreamExt;
-    use hotshot_example_types::node_types::TestTypes;
+    use hotshot_example_types::{
+        block_types::{TestMetadata, TestTransaction},
+        node_types::{TestTypes, TestVersions},
+        state_types::{TestInstanceState, TestValidatedState},
+    };
     use hotshot_types::{
-        data::ViewNumber,
-        event::{Event, EventType},
+        data::{DaProposal2, Leaf2, QuorumProposal2, QuorumProposalWrapper, ViewNumber},
+        error::HotShotError,
+        event::{Event, EventType, LeafInfo},
         light_client::StateKeyPair,
+        message::Proposal,
         signature_key::BLSPubKey,
+        simple_certificate::QuorumCertificate2,
         traits::{
             node_implementation::{ConsensusTime, NodeType},
             signature_key::SignatureKey,
         },
+        utils::EpochTransitionIndicator,
         PeerConfig,
     };
+    use serde_json::{json, Value};
     use surf_disco::Client;
     use tide_disco::{App, Url};
     use tokio::spawn;
     use tracing_test::traced_test;
-    use vbs::version::{StaticVersion, StaticVersionType};
+    use vbs::{
+        version::{StaticVersion, StaticVersionType},
+        BinarySerializer, Serializer,
+    };
 
     //use crate::fetch::Fetch;
     use crate::events::{define_api, Error, Options};
     use crate::events_source::{EventConsumer, EventsStreamer, StartupInfo}; // EventsUpdater};
+    use crate::schema::{Schema, VersionedEvent, EVENT_SCHEMA_VERSION, SCHEMAS};
 
     // return a empty transaction event
     fn generate_event<Types: NodeType<View = ViewNumber>>(view_number: u64) -> Event<Types> {
@@ -270,4 +283,268 @@ mod tests {
         receive_handle_1.await.unwrap();
         receive_handle_2.await.unwrap();
     }
+
+    /// The events pinned in `data/event-schema`, by file name.
+    ///
+    /// Besides the simple events, these include the events builders consume, whose encoding
+    /// follows the internal consensus types. All of them are deterministic, so that their encoding
+    /// only changes with the types.
+    async fn pinned_events() -> Vec<(&'static str, Event<TestTypes>)> {
+        let view_number = ViewNumber::new(6);
+        let (sender, private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+        let signature = BLSPubKey::sign(&private_key, b"pinned event").unwrap();
+        let validated_state = TestValidatedState::default();
+        let instance_state = TestInstanceState::default();
+        let leaf =
+            Leaf2::<TestTypes>::genesis::<TestVersions>(&validated_state, &instance_state).await;
+        let qc = QuorumCertificate2::<TestTypes>::genesis::<TestVersions>(
+            &validated_state,
+            &instance_state,
+        )
+        .await;
+        let quorum_proposal = QuorumProposalWrapper {
+            proposal: QuorumProposal2 {
+                block_header: leaf.block_header().clone(),
+                view_number,
+                epoch: None,
+                justify_qc: qc.clone(),
+                next_epoch_justify_qc: None,
+                upgrade_certificate: None,
+                view_change_evidence: None,
+                next_drb_result: None,
+                state_cert: None,
+            },
+        };
+        let da_proposal = DaProposal2 {
+            encoded_transactions: Arc::from([1, 2, 3].as_slice()),
+            metadata: TestMetadata {
+                num_transactions: 1,
+            },
+            view_number,
+            epoch: None,
+            epoch_transition_indicator: EpochTransitionIndicator::NotInTransition,
+        };
+
+        [
+            (
+                "decide",
+                EventType::Decide {
+                    leaf_chain: Arc::new(vec![LeafInfo::new(
+                        leaf,
+                        Arc::new(validated_state),
+                        None,
+                        None,
+                        None,
+                    )]),
+                    qc: Arc::new(qc),
+                    block_size: Some(0),
+                },
+            ),
+            (
+                "da_proposal",
+                EventType::DaProposal {
+                    proposal: Proposal {
+                        data: da_proposal,
+                        signature: signature.clone(),
+                        _pd: PhantomData,
+                    },
+                    sender,
+                },
+            ),
+            (
+                "quorum_proposal",
+                EventType::QuorumProposal {
+                    proposal: Proposal {
+                        data: quorum_proposal,
+                        signature,
+                        _pd: PhantomData,
+                    },
+                    sender,
+                },
+            ),
+            (
+                "error",
+                EventType::Error {
+                    error: Arc::new(HotShotError::InvalidState(
+                        "quorum proposal is missing".into(),
+                    )),
+                },
+            ),
+            ("view_finished", EventType::ViewFinished { view_number }),
+            ("view_timeout", EventType::ViewTimeout { view_number }),
+            (
+                "replica_view_timeout",
+                EventType::ReplicaViewTimeout { view_number },
+            ),
+            (
+                "transactions",
+                EventType::Transactions {
+                    transactions: vec![
+                        TestTransaction::new(vec![1, 2, 3]),
+                        TestTransaction::new(vec![]),
+                    ],
+                },
+            ),
+        ]
+        .into_iter()
+        .map(|(name, event)| {
+            let event = Event {
+                view_number: ViewNumber::new(7),
+                event,
+            };
+            (name, event)
+        })
+        .collect()
+    }
+
+    fn pinned_path(version: u16, name: &str) -> String {
+        format!(
+            "{}/data/event-schema/v{version}/{name}.json",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    }
+
+    fn load_pinned(version: u16, name: &str) -> VersionedEvent {
+        let path = pinned_path(version, name);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_versioned_event_pinned_schemas() {
+        for (name, event) in pinned_events().await {
+            // Events still encode as pinned; if not, the encoding has changed and a new schema
+            // version must be registered.
+            let path = pinned_path(EVENT_SCHEMA_VERSION, name);
+            let encoded = VersionedEvent::encode(&event).unwrap();
+            let pinned = std::fs::read_to_string(&path)
+                .ok()
+                .map(|pinned| serde_json::from_str::<VersionedEvent>(&pinned).unwrap());
+            if pinned.as_ref() != Some(&encoded) {
+                // Write the actual encoding next to the pinned one, to make it easier to compare
+                // with or, once a new schema version is registered, pin.
+                let actual_path = pinned_path(EVENT_SCHEMA_VERSION, &format!("{name}-actual"));
+                std::fs::write(
+                    &actual_path,
+                    serde_json::to_string_pretty(&encoded).unwrap(),
+                )
+                .unwrap();
+                panic!(
+                    "encoding of {name} does not match {path}, actual encoding written to \
+                     {actual_path}"
+                );
+            }
+
+            // Events pinned with every version still decode to the same event.
+            for schema in SCHEMAS {
+                let decoded = load_pinned(schema.version, name)
+                    .decode::<TestTypes>()
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(VersionedEvent::encode(&decoded).unwrap(), encoded);
+            }
+
+            // Versioned events also survive binary encodings.
+            let bytes = Serializer::<StaticVersion<0, 1>>::serialize(&encoded).unwrap();
+            let decoded: VersionedEvent =
+                Serializer::<StaticVersion<0, 1>>::deserialize(&bytes).unwrap();
+            assert_eq!(decoded, encoded);
+        }
+    }
+
+    #[test]
+    fn test_versioned_event_compatibility() {
+        // Suppose version 2 made the transactions of a transactions event required.
+        fn require_transactions(event: &mut Value) {
+            if let Some(Value::Object(payload)) = event.pointer_mut("/event/Transactions") {
+                payload.entry("transactions").or_insert(json!([]));
+            }
+        }
+        let schemas = [
+            SCHEMAS[0],
+            Schema {
+                version: 2,
+                changes: "transactions are required",
+                upgrade: require_transactions,
+            },
+        ];
+
+        // Events of older versions are upgraded.
+        let old = VersionedEvent {
+            schema_version: 1,
+            kind: "Transactions".into(),
+            event: json!({ "view_number": 7, "event": { "Transactions": {} } }),
+        };
+        old.clone().decode::<TestTypes>().unwrap_err();
+        let event = old.decode_with::<TestTypes>(&schemas).unwrap().unwrap();
+        assert!(matches!(
+            event.event,
+            EventType::Transactions { transactions } if transactions.is_empty()
+        ));
+
+        // Versions which were never registered are rejected.
+        let mut unknown = load_pinned(EVENT_SCHEMA_VERSION, "view_finished");
+        unknown.schema_version = 0;
+        unknown.decode::<TestTypes>().unwrap_err();
+
+        // Events of newer versions decode if they can, ignoring new fields...
+        let mut newer = load_pinned(EVENT_SCHEMA_VERSION, "view_finished");
+        newer.schema_version = EVENT_SCHEMA_VERSION + 1;
+        newer.event["event"]["ViewFinished"]["leader"] = json!("someone");
+        let event = newer.decode::<TestTypes>().unwrap().unwrap();
+        assert_eq!(event.view_number, ViewNumber::new(7));
+
+        // ...and are skipped otherwise.
+        let newer = VersionedEvent {
+            schema_version: EVENT_SCHEMA_VERSION + 1,
+            kind: "NewKind".into(),
+            event: json!({ "view_number": 7, "event": { "NewKind": {} } }),
+        };
+        assert!(newer.decode::<TestTypes>().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_versioned_event_stream() {
+        let port = portpicker::pick_unused_port().expect("Could not find an open port");
+        let api_url = Url::parse(format!("http://localhost:{port}").as_str()).unwrap();
+
+        let events_streamer = Arc::new(RwLock::new(EventsStreamer::new(vec![], 0)));
+        let mut app = App::<_, Error>::with_state(events_streamer.clone());
+        let hotshot_events_api =
+            define_api::<Arc<RwLock<EventsStreamer<TestTypes>>>, TestTypes, StaticVersion<0, 1>>(
+                &Options::default(),
+            )
+            .expect("Failed to define hotshot eventsAPI");
+        app.register_module("hotshot_events", hotshot_events_api)
+            .expect("Failed to register hotshot events API");
+        spawn(app.serve(api_url, StaticVersion::<0, 1>::instance()));
+
+        let client = Client::<Error, StaticVersion<0, 1>>::new(
+            format!("http://localhost:{port}/hotshot_events")
+                .parse()
+                .unwrap(),
+        );
+        client.connect(None).await;
+        let mut events = client
+            .socket("versioned_events")
+            .subscribe::<VersionedEvent>()
+            .await
+            .unwrap();
+
+        let total_count = 3;
+        for view in 0..total_count {
+            events_streamer
+                .write()
+                .await
+                .handle_event(generate_event(view))
+                .await;
+        }
+        for view in 0..total_count {
+            let event = events.next().await.unwrap().unwrap();
+            assert_eq!(event.schema_version, EVENT_SCHEMA_VERSION);
+            assert_eq!(event.kind, "Transactions");
+            let event = event.decode::<TestTypes>().unwrap().unwrap();
+            assert_eq!(event.view_number, ViewNumber::new(view));
+        }
+    }
 }
@@ -0,0 +1,77 @@
+//! Benchmark of the availability data source under typical query service load
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hotshot_query_service::{
+    data_source::FileSystemDataSource,
+    fetching::provider::NoFetching,
+    testing::{
+        consensus::DataSourceLifeCycle,
+        mocks::MockTypes,
+        perf::{fan_out, populate, scan_blocks, MockChain},
+    },
+};
+use tokio::runtime::Runtime;
+
+type D = FileSystemDataSource<MockTypes, NoFetching>;
+
+const TXS_PER_BLOCK: usize = 10;
+const TX_SIZE: usize = 1024;
+
+fn range_scan_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let num_blocks = 1000;
+    let (_storage, ds) = rt.block_on(async {
+        let storage = D::create(0).await;
+        let ds = D::connect(&storage).await;
+        let mut chain = MockChain::new(TXS_PER_BLOCK, TX_SIZE).await;
+        populate(&ds, &mut chain, num_blocks).await;
+        (storage, ds)
+    });
+
+    let mut group = c.benchmark_group("BlockRangeScan");
+    for len in [10, 100, 1000] {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &len, |b, &len| {
+            b.to_async(&rt).iter(|| scan_blocks(&ds, 0..len))
+        });
+    }
+    group.finish();
+}
+
+fn fan_out_benchmark(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let num_blocks = 10;
+
+    let mut group = c.benchmark_group("StreamFanOut");
+    group.sample_size(10);
+    for subscribers in [1, 10, 100, 1000] {
+        let (_storage, ds, mut chain) = rt.block_on(async {
+            let storage = D::create(0).await;
+            let ds = D::connect(&storage).await;
+            let chain = MockChain::new(TXS_PER_BLOCK, TX_SIZE).await;
+            (storage, ds, chain)
+        });
+        group.throughput(Throughput::Elements((subscribers * num_blocks) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                // Each iteration appends new blocks, so the chain is threaded through iterations.
+                b.iter_custom(|iters| {
+                    rt.block_on(async {
+                        let start = Instant::now();
+                        for _ in 0..iters {
+                            fan_out(&ds, &mut chain, subscribers, num_blocks).await;
+                        }
+                        start.elapsed()
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, range_scan_benchmark, fan_out_benchmark);
+criterion_main!(benches);
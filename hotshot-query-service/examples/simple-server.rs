@@ -48,6 +48,7 @@ use hotshot_types::{
     epoch_membership::EpochMembershipCoordinator,
     light_client::StateKeyPair,
     signature_key::BLSPubKey,
+    signer::Signer,
     traits::{election::Membership, network::Topic},
     HotShotConfig, PeerConfig,
 };
@@ -221,6 +222,13 @@ async fn init_consensus(
         stop_voting_time: 0,
         epoch_height: 0,
         epoch_start_block: 0,
+        da_payload_hint_threshold: None,
+        da_payload_hint_urls: vec![],
+        compression: None,
+        bandwidth: Default::default(),
+        local_builder: None,
+        adaptive_view_timeout: None,
+        transaction_dedup: None,
     };
 
     let nodes = join_all(priv_keys.into_iter().zip(data_sources).enumerate().map(
@@ -250,7 +258,7 @@ async fn init_consensus(
 
                 SystemContext::init(
                     pub_keys[node_id],
-                    priv_key,
+                    Signer::Local(priv_key),
                     state_private_keys[node_id].clone(),
                     node_id as u64,
                     config,
@@ -0,0 +1,8 @@
+-- Progress of long-running data migrations registered in code. Each migration processes rows in
+-- batches ordered by some integer key, and records the key to resume from after each batch, so
+-- that a migration interrupted by a restart picks up where it left off.
+CREATE TABLE data_migrations (
+    name      VARCHAR PRIMARY KEY,
+    next_key  BIGINT  NOT NULL DEFAULT 0,
+    completed BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,42 @@
+-- Cumulative statistics for the block explorer, as of each block height. Statistics over a window
+-- of time can be computed from the difference between two rows, without scanning the blocks in
+-- between.
+CREATE TABLE explorer_stats (
+    height BIGINT PRIMARY KEY REFERENCES header (height) ON DELETE CASCADE,
+    timestamp BIGINT NOT NULL,
+    num_transactions BIGINT NOT NULL,
+    payload_size BIGINT NOT NULL,
+    num_empty_blocks BIGINT NOT NULL,
+    -- Total fees collected, or NULL if none have been collected yet.
+    fees JSONB
+);
+CREATE INDEX explorer_stats_timestamp ON explorer_stats (timestamp);
+
+-- Cumulative statistics as of the last pruned block. Totals only cover blocks which have not been
+-- pruned, so these are subtracted from the latest statistics. There is at most one row, with id 1.
+CREATE TABLE explorer_stats_pruned (
+    id INT PRIMARY KEY,
+    height BIGINT NOT NULL,
+    timestamp BIGINT NOT NULL,
+    num_transactions BIGINT NOT NULL,
+    payload_size BIGINT NOT NULL,
+    num_empty_blocks BIGINT NOT NULL,
+    fees JSONB
+);
+
+-- Total transaction volume for each namespace, over blocks which have not been pruned.
+CREATE TABLE explorer_namespace_stats (
+    namespace JSONB PRIMARY KEY,
+    num_transactions BIGINT NOT NULL,
+    payload_size BIGINT NOT NULL
+);
+
+-- Transaction volume for each namespace in each block, so that it can be subtracted from the
+-- totals when the block is pruned.
+CREATE TABLE explorer_namespace_block_stats (
+    height BIGINT NOT NULL,
+    namespace JSONB NOT NULL,
+    num_transactions BIGINT NOT NULL,
+    payload_size BIGINT NOT NULL,
+    PRIMARY KEY (height, namespace)
+);
@@ -272,6 +272,27 @@ where
         .await
 }
 
+async fn get_header_chain_handler<Types, State>(
+    req: tide_disco::RequestParams,
+    state: &State,
+    timeout: Duration,
+    small_object_range_limit: usize,
+) -> Result<HeaderChainQueryData<Types>, Error>
+where
+    State: 'static + Send + Sync + ReadState,
+    <State as ReadState>::State: Send + Sync + AvailabilityDataSource<Types>,
+    Types: NodeType,
+    Payload<Types>: QueryablePayload<Types>,
+{
+    let from = req.integer_param::<_, usize>("from")?;
+    let until = req.integer_param::<_, usize>("until")?;
+    let leaves = get_leaf_range_handler(req, state, timeout, small_object_range_limit).await?;
+    HeaderChainQueryData::new(leaves).ok_or_else(|| Error::Custom {
+        message: format!("header chain {from}..{until} is empty"),
+        status: StatusCode::BAD_REQUEST,
+    })
+}
+
 fn downgrade_vid_common_query_data<Types: NodeType>(
     data: VidCommonQueryData<Types>,
 ) -> Option<ADVZCommonQueryData<Types>> {
@@ -404,6 +425,12 @@ where
         })?;
     }
 
+    // Header chains were introduced after the upgrade to `Leaf2`, so there is no legacy format to
+    // downgrade to and we serve the same type in every API version.
+    api.at("get_header_chain", move |req, state| {
+        get_header_chain_handler(req, state, timeout, small_object_range_limit).boxed()
+    })?;
+
     // VIDCommon data is version gated after the VID upgrade.
     // We keep the old struct and data in the API version V0. Starting from V1 we are returning version gated structs.
     if api_ver.major == 0 {
@@ -991,6 +1018,22 @@ mod test {
                 .unwrap();
 
             assert_eq!(header_range.len() as u64, i);
+
+            if i > 0 {
+                let chain: HeaderChainQueryData<MockTypes> = client
+                    .get(&format!("header-chain/{}/{}", 0, i))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(chain.leaves().len() as u64, i);
+                for (leaf, expected) in chain.leaves().iter().zip(&leaf_range) {
+                    assert_eq!(leaf, expected.leaf());
+                }
+                for pair in chain.leaves().windows(2) {
+                    assert_eq!(pair[1].parent_commitment(), pair[0].commit());
+                }
+                assert_eq!(chain.qc(), leaf_range.last().unwrap().qc());
+            }
         }
     }
 
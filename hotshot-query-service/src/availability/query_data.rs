@@ -382,6 +382,55 @@ impl<Types: NodeType> HeightIndexed for LeafQueryDataLegacy<Types> {
     }
 }
 
+/// A contiguous chain of leaves, together with the certificate for the last one.
+///
+/// Each leaf commits to its parent, so a quorum certificate for the last leaf in the chain attests
+/// to every leaf before it. A client that trusts the parent of the first leaf can therefore verify
+/// the whole chain by checking the hash links and a single certificate, without downloading any
+/// payloads or checking a signature for every leaf.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(bound = "")]
+pub struct HeaderChainQueryData<Types: NodeType> {
+    pub(crate) leaves: Vec<Leaf2<Types>>,
+    pub(crate) qc: QuorumCertificate2<Types>,
+}
+
+impl<Types: NodeType> HeaderChainQueryData<Types> {
+    /// Build a header chain from a contiguous range of leaves.
+    ///
+    /// Returns [`None`] if `leaves` is empty.
+    pub fn new(leaves: impl IntoIterator<Item = LeafQueryData<Types>>) -> Option<Self> {
+        let mut chain = vec![];
+        let mut qc = None;
+        for leaf in leaves {
+            chain.push(leaf.leaf);
+            qc = Some(leaf.qc);
+        }
+        Some(Self {
+            leaves: chain,
+            qc: qc?,
+        })
+    }
+
+    /// The leaves in the chain, in increasing order of height, with payloads removed.
+    pub fn leaves(&self) -> &[Leaf2<Types>] {
+        &self.leaves
+    }
+
+    /// The certificate for the last leaf in the chain.
+    pub fn qc(&self) -> &QuorumCertificate2<Types> {
+        &self.qc
+    }
+
+    pub fn headers(&self) -> impl Iterator<Item = &Header<Types>> {
+        self.leaves.iter().map(|leaf| leaf.block_header())
+    }
+
+    pub fn into_parts(self) -> (Vec<Leaf2<Types>>, QuorumCertificate2<Types>) {
+        (self.leaves, self.qc)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, serde::Deserialize, PartialEq, Eq)]
 #[serde(bound = "")]
 pub struct HeaderQueryData<Types: NodeType> {
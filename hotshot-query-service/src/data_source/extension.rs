@@ -32,7 +32,10 @@ use crate::{
         UpdateStateData,
     },
     metrics::PrometheusMetrics,
-    node::{NodeDataSource, SyncStatus, TimeWindowQueryData, WindowStart},
+    node::{
+        BackfillStatus, NodeDataSource, PruningHorizon, SyncStatus, TimeWindowQueryData,
+        WindowStart,
+    },
     status::{HasMetrics, StatusDataSource},
     Header, Payload, QueryResult, Transaction,
 };
@@ -350,6 +353,12 @@ where
     async fn sync_status(&self) -> QueryResult<SyncStatus> {
         self.data_source.sync_status().await
     }
+    async fn pruning_horizon(&self) -> QueryResult<PruningHorizon> {
+        self.data_source.pruning_horizon().await
+    }
+    async fn backfill_status(&self) -> QueryResult<Option<BackfillStatus>> {
+        self.data_source.backfill_status().await
+    }
     async fn get_header_window(
         &self,
         start: impl Into<WindowStart<Types>> + Send + Sync,
@@ -491,6 +500,16 @@ where
         self.data_source.get_explorer_summary().await
     }
 
+    async fn get_explorer_stats(
+        &self,
+        window: u64,
+    ) -> Result<
+        explorer::query_data::ExplorerStats<Types>,
+        explorer::query_data::GetExplorerStatsError,
+    > {
+        self.data_source.get_explorer_stats(window).await
+    }
+
     async fn get_search_results(
         &self,
         query: TaggedBase64,
@@ -84,7 +84,7 @@ use std::{
 };
 
 use anyhow::{bail, Context};
-use async_lock::Semaphore;
+use async_lock::{RwLock, Semaphore};
 use async_trait::async_trait;
 use backoff::{backoff::Backoff, ExponentialBackoff, ExponentialBackoffBuilder};
 use derivative::Derivative;
@@ -133,13 +133,17 @@ use crate::{
         MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot,
     },
     metrics::PrometheusMetrics,
-    node::{NodeDataSource, SyncStatus, TimeWindowQueryData, WindowStart},
+    node::{
+        BackfillStatus, NodeDataSource, PruningHorizon, SyncStatus, TimeWindowQueryData,
+        WindowStart,
+    },
     status::{HasMetrics, StatusDataSource},
     task::BackgroundTask,
     types::HeightIndexed,
     Header, Payload, QueryError, QueryResult,
 };
 
+mod backfill;
 mod block;
 mod header;
 mod leaf;
@@ -147,7 +151,9 @@ mod state_cert;
 mod transaction;
 mod vid;
 
+pub use self::backfill::BackfillCfg;
 use self::{
+    backfill::BackfillMetrics,
     block::PayloadFetcher,
     leaf::LeafFetcher,
     transaction::TransactionRequest,
@@ -172,6 +178,7 @@ pub struct Builder<Types, S, P> {
     aggregator_chunk_size: Option<usize>,
     types_migration_batch_size: u64,
     leaf_only: bool,
+    archival_backfill: Option<BackfillCfg>,
     _types: PhantomData<Types>,
 }
 
@@ -210,6 +217,7 @@ impl<Types, S, P> Builder<Types, S, P> {
             aggregator_chunk_size: None,
             types_migration_batch_size: 10000,
             leaf_only: false,
+            archival_backfill: None,
             _types: Default::default(),
         }
     }
@@ -379,6 +387,16 @@ impl<Types, S, P> Builder<Types, S, P> {
         self
     }
 
+    /// Run an archival backfill when the data source starts.
+    ///
+    /// The backfill fetches every leaf, block and VID common object missing between the configured
+    /// start height and the block height at startup, and reports its progress via metrics and the
+    /// `backfill-status` endpoint of the node API. It has no effect in leaf-only mode.
+    pub fn with_archival_backfill(mut self, cfg: BackfillCfg) -> Self {
+        self.archival_backfill = Some(cfg);
+        self
+    }
+
     pub fn is_leaf_only(&self) -> bool {
         self.leaf_only
     }
@@ -432,6 +450,9 @@ where
     scanner: Option<BackgroundTask>,
     // The aggregator task, which derives aggregate statistics from a block stream.
     aggregator: Option<BackgroundTask>,
+    // The archival backfill task, if enabled, and its progress.
+    backfill: Option<BackgroundTask>,
+    backfill_status: Option<Arc<RwLock<BackfillStatus>>>,
     pruner: Pruner<Types, S>,
 }
 
@@ -527,6 +548,8 @@ where
             .proactive_range_chunk_size
             .unwrap_or(builder.range_chunk_size);
         let migration_batch_size = builder.types_migration_batch_size;
+        let archival_backfill = builder.archival_backfill;
+        let backfill_metrics = BackfillMetrics::new(builder.storage.metrics());
         let scanner_metrics = ScannerMetrics::new(builder.storage.metrics());
         let aggregator_metrics = AggregatorMetrics::new(builder.storage.metrics());
 
@@ -564,6 +587,20 @@ where
             None
         };
 
+        let (backfill, backfill_status) = match archival_backfill {
+            Some(cfg) if !leaf_only => {
+                let status = Arc::new(RwLock::new(BackfillStatus::default()));
+                let task = BackgroundTask::spawn(
+                    "archival backfill",
+                    fetcher
+                        .clone()
+                        .archival_backfill(cfg, status.clone(), backfill_metrics),
+                );
+                (Some(task), Some(status))
+            },
+            _ => (None, None),
+        };
+
         let storage = fetcher.storage.clone();
 
         let pruner = Pruner::new(storage).await;
@@ -572,6 +609,8 @@ where
             scanner,
             pruner,
             aggregator,
+            backfill,
+            backfill_status,
         };
 
         Ok(ds)
@@ -1881,6 +1920,20 @@ where
         tx.sync_status().await
     }
 
+    async fn pruning_horizon(&self) -> QueryResult<PruningHorizon> {
+        let mut tx = self.read().await.map_err(|err| QueryError::Error {
+            message: err.to_string(),
+        })?;
+        tx.pruning_horizon().await
+    }
+
+    async fn backfill_status(&self) -> QueryResult<Option<BackfillStatus>> {
+        match &self.backfill_status {
+            Some(status) => Ok(Some(status.read().await.clone())),
+            None => Ok(None),
+        }
+    }
+
     async fn get_header_window(
         &self,
         start: impl Into<WindowStart<Types>> + Send + Sync,
@@ -1967,6 +2020,19 @@ where
         tx.get_explorer_summary().await
     }
 
+    async fn get_explorer_stats(
+        &self,
+        window: u64,
+    ) -> Result<
+        explorer::query_data::ExplorerStats<Types>,
+        explorer::query_data::GetExplorerStatsError,
+    > {
+        let mut tx = self.read().await.map_err(|err| QueryError::Error {
+            message: err.to_string(),
+        })?;
+        tx.get_explorer_stats(window).await
+    }
+
     async fn get_search_results(
         &self,
         query: TaggedBase64,
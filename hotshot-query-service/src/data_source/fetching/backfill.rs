@@ -0,0 +1,293 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+//! Backfilling of historical data for archive nodes.
+//!
+//! Proactive scanning keeps a node caught up with the chain, but it is tuned to be a low priority
+//! background task, and it only looks back as far as the previous scan except during infrequent
+//! major scans. A node being stood up as an archive needs the opposite: a single, deliberate pass
+//! over the whole history which fetches everything that is missing as fast as its peers allow,
+//! and which reports how far along it is.
+//!
+//! An archival backfill run walks the chain from the configured start height up to the block height
+//! at the time the run started, once for each kind of object (leaves, then blocks, then VID common
+//! data). Every object which is missing from local storage is fetched from the provider, verified
+//! and stored by the usual fetching machinery. The number of objects fetched per second is limited,
+//! so that the run does not overwhelm the peers it is fetching from.
+
+use std::{cmp::max, ops::Bound, sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use futures::stream::StreamExt;
+use hotshot_types::traits::{
+    metrics::{Gauge, Metrics},
+    node_implementation::NodeType,
+};
+use tokio::time::sleep;
+use tracing::Instrument;
+
+use super::{AvailabilityProvider, Fetcher, Heights, RangedFetchable};
+use crate::{
+    availability::{LeafQueryData, PayloadMetadata, QueryablePayload, VidCommonMetadata},
+    data_source::{
+        storage::{
+            pruning::PrunedHeightStorage, AvailabilityStorage, NodeStorage,
+            UpdateAvailabilityStorage,
+        },
+        VersionedDataSource,
+    },
+    metrics::PrometheusMetrics,
+    node::BackfillStatus,
+    Payload,
+};
+
+/// Configuration for an archival backfill run.
+#[derive(Clone, Copy, Debug)]
+pub struct BackfillCfg {
+    start: u64,
+    chunk_size: usize,
+    rate_limit: usize,
+    fetch_timeout: Duration,
+}
+
+impl Default for BackfillCfg {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            chunk_size: 100,
+            rate_limit: 50,
+            fetch_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl BackfillCfg {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_start(mut self, start: u64) -> Self {
+        self.start = start;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: usize) -> Self {
+        self.rate_limit = rate_limit;
+        self
+    }
+
+    pub fn with_fetch_timeout(mut self, fetch_timeout: Duration) -> Self {
+        self.fetch_timeout = fetch_timeout;
+        self
+    }
+
+    /// The oldest block to backfill.
+    ///
+    /// Blocks below the pruned height are never backfilled, regardless of this setting.
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Number of objects to load from storage at a time while looking for gaps.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Maximum number of missing objects to fetch per second (0 for unlimited).
+    pub fn rate_limit(&self) -> usize {
+        self.rate_limit
+    }
+
+    /// How long to wait for a single missing object before counting it as failed.
+    pub fn fetch_timeout(&self) -> Duration {
+        self.fetch_timeout
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct BackfillMetrics {
+    /// Whether a backfill is currently running (1) or not (0).
+    running: Box<dyn Gauge>,
+    /// Block height where the backfill started.
+    start: Box<dyn Gauge>,
+    /// Block height where the backfill will end.
+    end: Box<dyn Gauge>,
+    /// Number of objects checked so far.
+    scanned: Box<dyn Gauge>,
+    /// Number of missing objects found so far.
+    missing: Box<dyn Gauge>,
+    /// Number of missing objects fetched so far.
+    fetched: Box<dyn Gauge>,
+    /// Number of missing objects which could not be fetched.
+    failed: Box<dyn Gauge>,
+}
+
+impl BackfillMetrics {
+    pub(super) fn new(metrics: &PrometheusMetrics) -> Self {
+        let group = metrics.subgroup("backfill".into());
+        Self {
+            running: group.create_gauge("running".into(), None),
+            start: group.create_gauge("start".into(), None),
+            end: group.create_gauge("end".into(), None),
+            scanned: group.create_gauge("scanned".into(), None),
+            missing: group.create_gauge("missing".into(), None),
+            fetched: group.create_gauge("fetched".into(), None),
+            failed: group.create_gauge("failed".into(), None),
+        }
+    }
+}
+
+impl<Types, S, P> Fetcher<Types, S, P>
+where
+    Types: NodeType,
+    Payload<Types>: QueryablePayload<Types>,
+    S: VersionedDataSource + 'static,
+    for<'a> S::Transaction<'a>: UpdateAvailabilityStorage<Types>,
+    for<'a> S::ReadOnly<'a>: AvailabilityStorage<Types> + NodeStorage<Types> + PrunedHeightStorage,
+    P: AvailabilityProvider<Types>,
+{
+    /// Fetch every leaf, block and VID common object missing from storage, once.
+    ///
+    /// This function runs until the backfill is complete, reporting progress through `status` and
+    /// `metrics`. It is meant to be spawned as a background task.
+    pub(super) async fn archival_backfill(
+        self: Arc<Self>,
+        cfg: BackfillCfg,
+        status: Arc<RwLock<BackfillStatus>>,
+        metrics: BackfillMetrics,
+    ) {
+        // We can't start until we know which blocks exist, so retry until we do.
+        let heights = loop {
+            let heights = match self.read().await {
+                Ok(mut tx) => Heights::load(&mut tx).await,
+                Err(err) => Err(err),
+            };
+            match heights {
+                Ok(heights) => break heights,
+                Err(err) => {
+                    tracing::error!("unable to load heights for backfill: {err:#}");
+                    sleep(Duration::from_secs(10)).await;
+                },
+            }
+        };
+        let start = max(cfg.start, heights.pruned_height.map_or(0, |h| h + 1));
+        let end = heights.height;
+
+        tracing::warn!(start, end, "starting archival backfill");
+        {
+            let mut status = status.write().await;
+            status.start = start;
+            status.end = end;
+        }
+        metrics.running.set(1);
+        metrics.start.set(start as usize);
+        metrics.end.set(end as usize);
+
+        // Leaves go first, since blocks and VID common can only be fetched once we have the
+        // corresponding leaf. Within each stage we iterate in reverse, since we cannot fetch a leaf
+        // until we have the subsequent leaf, which tells us what the hash of the parent should be.
+        self.clone()
+            .backfill_stage::<LeafQueryData<Types>>("leaf", cfg, start, end, &status, &metrics)
+            .await;
+        self.clone()
+            .backfill_stage::<PayloadMetadata<Types>>("block", cfg, start, end, &status, &metrics)
+            .await;
+        self.clone()
+            .backfill_stage::<VidCommonMetadata<Types>>(
+                "vid_common",
+                cfg,
+                start,
+                end,
+                &status,
+                &metrics,
+            )
+            .await;
+
+        let status = {
+            let mut status = status.write().await;
+            status.complete = true;
+            status.clone()
+        };
+        metrics.running.set(0);
+        tracing::warn!(?status, "archival backfill complete");
+    }
+
+    async fn backfill_stage<T>(
+        self: Arc<Self>,
+        stage: &'static str,
+        cfg: BackfillCfg,
+        start: u64,
+        end: u64,
+        status: &RwLock<BackfillStatus>,
+        metrics: &BackfillMetrics,
+    ) where
+        T: RangedFetchable<Types>,
+    {
+        if start >= end {
+            return;
+        }
+        status.write().await.stage = stage.into();
+
+        let span = tracing::info_span!("backfill", stage, start, end);
+        async {
+            let delay =
+                (cfg.rate_limit > 0).then(|| Duration::from_secs(1) / cfg.rate_limit as u32);
+            let mut objects = self.get_range_with_chunk_size_rev::<T>(
+                cfg.chunk_size,
+                Bound::Included(start as usize),
+                (end - 1) as usize,
+            );
+            while let Some(fetch) = objects.next().await {
+                if fetch.is_pending() {
+                    {
+                        let mut status = status.write().await;
+                        match stage {
+                            "leaf" => status.missing_leaves += 1,
+                            "block" => status.missing_blocks += 1,
+                            _ => status.missing_vid_common += 1,
+                        }
+                        metrics.missing.update(1);
+                    }
+
+                    // Wait for the object to be fetched before moving on, so that we never have
+                    // more fetches in flight than our peers can serve.
+                    let fetched = fetch.with_timeout(cfg.fetch_timeout).await.is_some();
+                    {
+                        let mut status = status.write().await;
+                        if fetched {
+                            status.fetched += 1;
+                            metrics.fetched.update(1);
+                        } else {
+                            tracing::warn!(stage, "timed out fetching missing object");
+                            status.failed += 1;
+                            metrics.failed.update(1);
+                        }
+                    }
+
+                    if let Some(delay) = delay {
+                        sleep(delay).await;
+                    }
+                }
+
+                status.write().await.scanned += 1;
+                metrics.scanned.update(1);
+            }
+        }
+        .instrument(span)
+        .await
+    }
+}
@@ -18,7 +18,9 @@ use async_trait::async_trait;
 use derivative::Derivative;
 use derive_more::From;
 use futures::future::{BoxFuture, FutureExt};
-use hotshot_types::traits::{block_contents::BlockHeader, node_implementation::NodeType};
+use hotshot_types::traits::{
+    block_contents::BlockHeader, node_implementation::NodeType, BlockPayload, EncodeBytes,
+};
 
 use super::{
     header::{fetch_header_and_then, HeaderCallback},
@@ -298,6 +300,13 @@ where
 {
     async fn run(self, payload: Payload<Types>) {
         tracing::info!("fetched payload {:?}", self.header.payload_commitment());
+        // Providers only vouch for the payload bytes, which they check against the commitment in
+        // the header. Interpret the bytes with the metadata from our own header, rather than
+        // trusting the metadata a provider attached.
+        let payload = <Types::BlockPayload as BlockPayload<Types>>::from_bytes(
+            &payload.encode(),
+            self.header.metadata(),
+        );
         let block = BlockQueryData::new(self.header, payload);
         self.fetcher.store_and_notify(block).await;
     }
@@ -73,16 +73,17 @@ use crate::{
     },
     explorer::{
         query_data::{
-            BlockDetail, BlockIdentifier, BlockSummary, ExplorerSummary, GetBlockDetailError,
-            GetBlockSummariesError, GetBlockSummariesRequest, GetExplorerSummaryError,
-            GetSearchResultsError, GetTransactionDetailError, GetTransactionSummariesError,
+            BlockDetail, BlockIdentifier, BlockSummary, ExplorerStats, ExplorerSummary,
+            GetBlockDetailError, GetBlockSummariesError, GetBlockSummariesRequest,
+            GetExplorerStatsError, GetExplorerSummaryError, GetSearchResultsError,
+            GetTransactionDetailError, GetTransactionSummariesError,
             GetTransactionSummariesRequest, SearchResult, TransactionDetailResponse,
             TransactionIdentifier, TransactionSummary,
         },
         traits::{ExplorerHeader, ExplorerTransaction},
     },
     merklized_state::{MerklizedState, Snapshot},
-    node::{SyncStatus, TimeWindowQueryData, WindowStart},
+    node::{PruningHorizon, SyncStatus, TimeWindowQueryData, WindowStart},
     Header, Payload, QueryResult, Transaction,
 };
 
@@ -245,6 +246,11 @@ pub trait NodeStorage<Types: NodeType> {
 
     /// Search the database for missing objects and generate a report.
     async fn sync_status(&mut self) -> QueryResult<SyncStatus>;
+
+    /// Get the range of blocks which have been pruned from the database.
+    async fn pruning_horizon(&mut self) -> QueryResult<PruningHorizon> {
+        Ok(PruningHorizon::default())
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -276,6 +282,23 @@ where
     ) -> impl Future<Output = anyhow::Result<Aggregate>> + Send;
 }
 
+/// Incrementally maintained statistics for the block explorer.
+pub trait UpdateExplorerStatsStorage<Types>
+where
+    Types: NodeType,
+{
+    /// The height of the next block which needs to be included in explorer statistics.
+    fn explorer_stats_height(&mut self) -> impl Future<Output = anyhow::Result<usize>> + Send;
+
+    /// Update explorer statistics based on a chunk of new blocks, in increasing order of height.
+    ///
+    /// Blocks which have already been included in the statistics are ignored.
+    fn update_explorer_stats(
+        &mut self,
+        blocks: &[BlockQueryData<Types>],
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
 /// An interface for querying Data and Statistics from the HotShot Blockchain.
 ///
 /// This interface provides methods that allows the enabling of querying data
@@ -332,6 +355,14 @@ where
         &mut self,
     ) -> Result<ExplorerSummary<Types>, GetExplorerSummaryError>;
 
+    /// `get_explorer_stats` is a method that retrieves aggregate statistics
+    /// about the blockchain, such as throughput over the last `window`
+    /// seconds and transaction volume per namespace.
+    async fn get_explorer_stats(
+        &mut self,
+        window: u64,
+    ) -> Result<ExplorerStats<Types>, GetExplorerStatsError>;
+
     /// `get_search_results` is a method that retrieves the results of a search
     /// query against the blockchain.  The results are generated from the given
     /// query string.
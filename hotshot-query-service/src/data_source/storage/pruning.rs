@@ -10,10 +10,26 @@
 // You should have received a copy of the GNU General Public License along with this program. If not,
 // see <https://www.gnu.org/licenses/>.
 
-use std::{fmt::Debug, time::Duration};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use anyhow::bail;
 use async_trait::async_trait;
+use hotshot_types::data::VidShare;
+
+/// Source of the VID shares peers still hold of a block
+///
+/// Pruning VID data which is not backed up anywhere else makes a block unreconstructable. Before
+/// deleting VID data, the pruner collects the shares peers still hold of the block, and keeps the
+/// data unless the shares which verify against the VID commitment of the block carry enough stake
+/// weight to recover its payload. A peer serving a share is its attestation to holding it.
+#[async_trait]
+pub trait ShareAttestor: Debug + Send + Sync {
+    /// The VID shares peers serve for the block at `height`.
+    ///
+    /// Shares need not be verified: the pruner checks them against its own copy of the VID
+    /// commitment.
+    async fn shares(&self, height: u64) -> Vec<VidShare>;
+}
 
 #[derive(Clone, Debug)]
 pub struct PrunerCfg {
@@ -24,6 +40,10 @@ pub struct PrunerCfg {
     max_usage: u16,
     interval: Duration,
     state_tables: Vec<String>,
+    retention_blocks: Option<u64>,
+    preserve_headers: bool,
+    share_retention: Option<Duration>,
+    share_attestor: Option<Arc<dyn ShareAttestor>>,
 }
 
 #[async_trait]
@@ -72,6 +92,10 @@ impl PrunerCfg {
             }
         }
 
+        if self.retention_blocks == Some(0) {
+            bail!("retention_blocks must be greater than 0 or set to None")
+        }
+
         if self.max_usage > 10000 {
             bail!("max_usage must be less than or equal to 10000")
         }
@@ -84,6 +108,26 @@ impl PrunerCfg {
         self
     }
 
+    pub fn with_retention_blocks(mut self, retention_blocks: u64) -> Self {
+        self.retention_blocks = Some(retention_blocks);
+        self
+    }
+
+    pub fn with_preserve_headers(mut self, preserve_headers: bool) -> Self {
+        self.preserve_headers = preserve_headers;
+        self
+    }
+
+    pub fn with_share_retention(mut self, share_retention: Duration) -> Self {
+        self.share_retention = Some(share_retention);
+        self
+    }
+
+    pub fn with_share_attestor(mut self, share_attestor: Arc<dyn ShareAttestor>) -> Self {
+        self.share_attestor = Some(share_attestor);
+        self
+    }
+
     pub fn with_pruning_threshold(mut self, pruning_threshold: u64) -> Self {
         self.pruning_threshold = Some(pruning_threshold);
         self
@@ -161,6 +205,43 @@ impl PrunerCfg {
     pub fn state_tables(&self) -> Vec<String> {
         self.state_tables.clone()
     }
+
+    /// Number of most recent blocks to retain
+    ///
+    /// Blocks further than this from the head of the chain are pruned, even if they are younger
+    /// than the target retention period. Blocks younger than `MINIMUM_RETENTION` are never pruned.
+    pub fn retention_blocks(&self) -> Option<u64> {
+        self.retention_blocks
+    }
+
+    /// Whether to keep headers, leaves and merklized state when pruning
+    ///
+    /// In this mode only payloads, transactions and VID data are deleted, so that the node can
+    /// still serve everything a light client needs to verify the chain.
+    pub fn preserve_headers(&self) -> bool {
+        self.preserve_headers
+    }
+
+    /// VID share retention period
+    ///
+    /// The VID shares of blocks older than this are deleted, even if the rest of the blocks are
+    /// retained. Shares are only deleted once the block can be reconstructed without them: either
+    /// its full payload is archived locally, or peers attest to holding enough of its shares (see
+    /// [`share_attestor`](Self::share_attestor)).
+    pub fn share_retention(&self) -> Option<Duration> {
+        self.share_retention
+    }
+
+    /// Source of the VID shares peers hold, which must suffice to recover a block before the VID
+    /// data of the block may be pruned
+    ///
+    /// This applies whenever VID data is deleted without the full payload remaining in local
+    /// storage. Pruning stops at the first block peers cannot recover, so that no pruning
+    /// configuration can make old blocks unreconstructable network-wide. Without an attestor, the
+    /// check is disabled.
+    pub fn share_attestor(&self) -> Option<&Arc<dyn ShareAttestor>> {
+        self.share_attestor.as_ref()
+    }
 }
 
 impl Default for PrunerCfg {
@@ -178,6 +259,10 @@ impl Default for PrunerCfg {
             // 1.5 hour
             interval: Duration::from_secs(5400),
             state_tables: Vec::new(),
+            retention_blocks: None,
+            preserve_headers: false,
+            share_retention: None,
+            share_attestor: None,
         }
     }
 }
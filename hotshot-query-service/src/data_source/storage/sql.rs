@@ -11,7 +11,14 @@
 // see <https://www.gnu.org/licenses/>.
 
 #![cfg(feature = "sql-data-source")]
-use std::{cmp::min, fmt::Debug, str::FromStr, time::Duration};
+use std::{
+    cmp::{max, min},
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -19,11 +26,12 @@ use chrono::Utc;
 use committable::Committable;
 #[cfg(not(feature = "embedded-db"))]
 use futures::future::FutureExt;
+use futures::stream::{self, StreamExt};
 use hotshot_types::{
-    data::{Leaf, Leaf2, VidShare},
+    data::{Leaf, Leaf2, VidCommitment, VidShare},
     simple_certificate::{QuorumCertificate, QuorumCertificate2},
     traits::{
-        metrics::Metrics,
+        metrics::{Counter, Gauge, Metrics},
         node_implementation::{ConsensusTime, NodeType},
     },
     vid::advz::{ADVZCommon, ADVZShare},
@@ -41,10 +49,13 @@ use sqlx::{
 
 use crate::{
     data_source::{
-        storage::pruning::{PruneStorage, PrunerCfg, PrunerConfig},
+        storage::pruning::{
+            PruneStorage, PrunedHeightStorage, PrunerCfg, PrunerConfig, ShareAttestor,
+        },
         update::Transaction as _,
         VersionedDataSource,
     },
+    fetching::provider::VidShares,
     metrics::PrometheusMetrics,
     status::HasMetrics,
     QueryError, QueryResult, VidCommon,
@@ -52,6 +63,9 @@ use crate::{
 pub extern crate sqlx;
 pub use sqlx::{Database, Sqlite};
 
+/// Number of blocks whose VID shares are collected from peers at once while pruning
+const SHARE_ATTESTATION_CONCURRENCY: usize = 16;
+
 mod db;
 mod migrate;
 mod queries;
@@ -64,7 +78,11 @@ pub use queries::QueryBuilder;
 pub use refinery::Migration;
 pub use transaction::*;
 
-use self::{migrate::Migrator, transaction::PoolMetrics};
+pub use self::migrate::{DataMigration, MigrationPlan, PendingDataMigration};
+use self::{
+    migrate::{load_data_migration, run_data_migration, Migrator},
+    transaction::PoolMetrics,
+};
 // This needs to be reexported so that we can reference it by absolute path relative to this crate
 // in the expansion of `include_migrations`, even when `include_migrations` is invoked from another
 // crate which doesn't have `include_dir` as a dependency.
@@ -221,9 +239,18 @@ pub struct Config {
     pool_opt: PoolOptions<Db>,
     #[cfg(not(feature = "embedded-db"))]
     schema: String,
+    #[cfg(not(feature = "embedded-db"))]
+    read_replica: Option<PgConnectOptions>,
+    #[cfg(not(feature = "embedded-db"))]
+    read_replica_pool_opt: PoolOptions<Db>,
+    #[cfg(not(feature = "embedded-db"))]
+    statement_timeout: Option<Duration>,
     reset: bool,
     migrations: Vec<Migration>,
     no_migrations: bool,
+    data_migrations: Vec<Arc<dyn DataMigration>>,
+    data_migration_batch_size: u64,
+    plan_only: bool,
     pruner_cfg: Option<PrunerCfg>,
     archive: bool,
     pool: Option<Pool<Db>>,
@@ -262,6 +289,9 @@ impl From<SqliteConnectOptions> for Config {
             reset: false,
             migrations: vec![],
             no_migrations: false,
+            data_migrations: vec![],
+            data_migration_batch_size: 1000,
+            plan_only: false,
             pruner_cfg: None,
             archive: false,
             pool: None,
@@ -276,9 +306,15 @@ impl From<PgConnectOptions> for Config {
             db_opt,
             pool_opt: PoolOptions::default(),
             schema: "hotshot".into(),
+            read_replica: None,
+            read_replica_pool_opt: PoolOptions::default(),
+            statement_timeout: None,
             reset: false,
             migrations: vec![],
             no_migrations: false,
+            data_migrations: vec![],
+            data_migration_batch_size: 1000,
+            plan_only: false,
             pruner_cfg: None,
             archive: false,
             pool: None,
@@ -370,6 +406,36 @@ impl Config {
         self.schema = schema.into();
         self
     }
+
+    /// Route read-only transactions to a read replica.
+    ///
+    /// Read-only transactions opened with [`read`](crate::data_source::VersionedDataSource::read)
+    /// use a separate connection pool connected to `replica`, so that heavy read traffic does not
+    /// compete with writes for connections to the primary database. Writes, and any reads made
+    /// while connecting (such as running migrations), always go to the primary database.
+    ///
+    /// Note that a replica may lag slightly behind the primary, so a read following a write may
+    /// not immediately observe the effects of the write.
+    pub fn read_replica(mut self, replica: PgConnectOptions) -> Self {
+        self.read_replica = Some(replica);
+        self
+    }
+
+    /// Set the maximum number of connections to the [read replica](Self::read_replica).
+    pub fn read_replica_max_connections(mut self, max: u32) -> Self {
+        self.read_replica_pool_opt = self.read_replica_pool_opt.max_connections(max);
+        self
+    }
+
+    /// Abort any SQL statement in a read-only transaction which runs for longer than `timeout`.
+    ///
+    /// This applies to read-only transactions on the primary database and on the
+    /// [read replica](Self::read_replica). It is set per transaction rather than per connection, so
+    /// writes, migrations and other users of a shared [pool](Self::pool) are not affected.
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
 }
 
 impl Config {
@@ -402,11 +468,27 @@ impl Config {
     }
 
     /// Skip all migrations when connecting to the database.
+    ///
+    /// Connecting will fail if any SQL or data migration has not been applied yet.
     pub fn no_migrations(mut self) -> Self {
         self.no_migrations = true;
         self
     }
 
+    /// Register a data migration to run when connecting to the database.
+    ///
+    /// Data migrations run after all SQL migrations, in the order they were registered.
+    pub fn data_migration(mut self, migration: impl DataMigration + 'static) -> Self {
+        self.data_migrations.push(Arc::new(migration));
+        self
+    }
+
+    /// Set the number of rows migrated per transaction by data migrations.
+    pub fn data_migration_batch_size(mut self, batch_size: u64) -> Self {
+        self.data_migration_batch_size = batch_size;
+        self
+    }
+
     /// Enable pruning with a given configuration.
     ///
     /// If [`archive`](Self::archive) was previously specified, this will override it.
@@ -485,8 +567,14 @@ impl Config {
 #[derive(Clone, Debug)]
 pub struct SqlStorage {
     pool: Pool<Db>,
+    /// Pool for read-only transactions, if reads are routed to a replica.
+    read_pool: Option<Pool<Db>>,
+    /// Timeout for statements in read-only transactions.
+    #[cfg(not(feature = "embedded-db"))]
+    statement_timeout: Option<Duration>,
     metrics: PrometheusMetrics,
     pool_metrics: PoolMetrics,
+    pruner_metrics: PrunerMetrics,
     pruner_cfg: Option<PrunerCfg>,
 }
 
@@ -495,6 +583,26 @@ pub struct Pruner {
     pruned_height: Option<u64>,
     target_height: Option<u64>,
     minimum_retention_height: Option<u64>,
+    share_target_height: Option<u64>,
+    /// Whether this run has reached a block whose VID data must be kept
+    blocked: bool,
+    /// Whether this run is done pruning VID shares
+    shares_done: bool,
+}
+
+#[derive(Clone, Debug)]
+struct PrunerMetrics {
+    pruned_bytes: Box<dyn Counter>,
+    pruned_height: Box<dyn Gauge>,
+}
+
+impl PrunerMetrics {
+    fn new(metrics: &(impl Metrics + ?Sized)) -> Self {
+        Self {
+            pruned_bytes: metrics.create_counter("pruned_bytes".into(), Some("bytes".into())),
+            pruned_height: metrics.create_gauge("pruned_height".into(), None),
+        }
+    }
 }
 
 impl SqlStorage {
@@ -505,14 +613,20 @@ impl SqlStorage {
     pub async fn connect(mut config: Config) -> Result<Self, Error> {
         let metrics = PrometheusMetrics::default();
         let pool_metrics = PoolMetrics::new(&*metrics.subgroup("sql".into()));
+        let pruner_metrics = PrunerMetrics::new(&*metrics.subgroup("pruner".into()));
         let pool = config.pool_opt.clone();
         let pruner_cfg = config.pruner_cfg;
+        let read_pool = Self::read_replica_pool(&config);
 
         // re-use the same pool if present and return early
         if let Some(pool) = config.pool {
             return Ok(Self {
+                read_pool: Self::check_read_replica(read_pool).await?,
+                #[cfg(not(feature = "embedded-db"))]
+                statement_timeout: config.statement_timeout,
                 metrics,
                 pool_metrics,
+                pruner_metrics,
                 pool,
                 pruner_cfg,
             });
@@ -537,7 +651,7 @@ impl SqlStorage {
             std::fs::remove_file(config.db_opt.get_filename())?;
         }
 
-        let pool = pool.connect_with(config.db_opt).await?;
+        let pool = pool.connect_with(config.db_opt.clone()).await?;
 
         // Create or connect to the schema for this query service.
         let mut conn = pool.acquire().await?;
@@ -563,7 +677,20 @@ impl SqlStorage {
         // the migrations or just check if the database is up to date.
         let runner = refinery::Runner::new(&migrations).set_grouped(true);
 
-        if config.no_migrations {
+        // Refuse to touch a database which has been migrated by a newer version of this software.
+        // Rolling back a release must not silently run against a schema it does not understand.
+        // In a fresh database the migrations table does not exist yet, and there is nothing to
+        // check.
+        if let Ok(last_applied) = runner
+            .get_last_applied_migration_async(&mut Migrator::from(&mut conn))
+            .await
+        {
+            check_schema_version(last_applied.as_ref(), &migrations)?;
+        }
+
+        if config.plan_only {
+            // We are only connecting to report which migrations would run; leave the DB alone.
+        } else if config.no_migrations {
             // We've been asked not to run any migrations. Abort if the DB is not already up to
             // date.
             let last_applied = runner
@@ -588,6 +715,37 @@ impl SqlStorage {
             }
         }
 
+        let mut storage = Self {
+            pool,
+            read_pool: None,
+            #[cfg(not(feature = "embedded-db"))]
+            statement_timeout: config.statement_timeout,
+            pool_metrics,
+            pruner_metrics,
+            metrics,
+            pruner_cfg,
+        };
+        if config.plan_only {
+            conn.close().await?;
+            return Ok(storage);
+        }
+
+        for migration in &config.data_migrations {
+            if config.no_migrations {
+                let mut tx = storage.read().await?;
+                let progress = load_data_migration(&mut tx, migration.name()).await?;
+                if !matches!(progress, Some((_, true))) {
+                    return Err(Error::msg(format!(
+                        "DB is out of date: data migration {} has not completed",
+                        migration.name()
+                    )));
+                }
+            } else {
+                run_data_migration(&storage, &**migration, config.data_migration_batch_size)
+                    .await?;
+            }
+        }
+
         if config.archive {
             // If running in archive mode, ensure the pruned height is set to 0, so the fetcher will
             // reconstruct previously pruned data.
@@ -598,11 +756,129 @@ impl SqlStorage {
 
         conn.close().await?;
 
-        Ok(Self {
-            pool,
-            pool_metrics,
-            metrics,
-            pruner_cfg,
+        // Only now that the database is fully migrated do we start routing reads to the replica.
+        storage.read_pool = Self::check_read_replica(read_pool).await?;
+
+        Ok(storage)
+    }
+
+    /// Create a connection pool for the read replica, if one is configured.
+    ///
+    /// The pool connects lazily, so that it can be created before `config` is consumed, but it is
+    /// not used until it has been checked by [`check_read_replica`](Self::check_read_replica).
+    #[cfg(not(feature = "embedded-db"))]
+    fn read_replica_pool(config: &Config) -> Option<Pool<Db>> {
+        let replica = config.read_replica.clone()?;
+        let schema = config.schema.clone();
+        let pool = config
+            .read_replica_pool_opt
+            .clone()
+            .after_connect(move |conn, _| {
+                let schema = schema.clone();
+                async move {
+                    query(&format!("SET search_path TO {schema}"))
+                        .execute(conn)
+                        .await?;
+                    Ok(())
+                }
+                .boxed()
+            })
+            .connect_lazy_with(replica);
+        Some(pool)
+    }
+
+    #[cfg(feature = "embedded-db")]
+    fn read_replica_pool(_config: &Config) -> Option<Pool<Db>> {
+        None
+    }
+
+    /// Make sure we can connect to the read replica before routing reads to it.
+    async fn check_read_replica(pool: Option<Pool<Db>>) -> Result<Option<Pool<Db>>, Error> {
+        let Some(pool) = pool else {
+            return Ok(None);
+        };
+        pool.acquire().await?;
+        tracing::info!("routing read-only transactions to read replica");
+        Ok(Some(pool))
+    }
+}
+
+/// Fail if the database has applied a migration this software does not know about.
+fn check_schema_version(
+    last_applied: Option<&Migration>,
+    migrations: &[Migration],
+) -> Result<(), Error> {
+    let Some(last_applied) = last_applied else {
+        return Ok(());
+    };
+    let last_known = migrations.last().map(|m| m.version()).unwrap_or_default();
+    if last_applied.version() > last_known {
+        return Err(Error::msg(format!(
+            "DB schema is newer than this software: last applied migration is V{}__{}, but the \
+             latest known migration is V{last_known}; refusing to start",
+            last_applied.version(),
+            last_applied.name(),
+        )));
+    }
+    Ok(())
+}
+
+impl SqlStorage {
+    /// Report the changes that connecting with `config` would make to the database, without
+    /// making them.
+    ///
+    /// This still creates the schema if it does not exist, but applies no migrations.
+    pub async fn plan_migrations(config: Config) -> Result<MigrationPlan, Error> {
+        let data_migrations = config.data_migrations.clone();
+        let mut migrations = config.migrations.clone();
+        let storage = Self::connect(Config {
+            reset: false,
+            plan_only: true,
+            ..config
+        })
+        .await?;
+        let mut conn = storage.pool.acquire().await?;
+
+        validate_migrations(&mut migrations)?;
+        let migrations =
+            add_custom_migrations(default_migrations(), migrations).collect::<Vec<_>>();
+        let runner = refinery::Runner::new(&migrations).set_grouped(true);
+        // In a fresh database the migrations table does not exist yet, so nothing has been applied.
+        let applied = runner
+            .get_applied_migrations_async(&mut Migrator::from(&mut conn))
+            .await
+            .unwrap_or_default();
+        conn.close().await?;
+        let last_applied = applied.last().map(|m| m.version());
+
+        let pending = migrations
+            .iter()
+            .filter(|m| !applied.iter().any(|a| a.version() == m.version()))
+            .map(|m| (m.version(), m.name().to_string()))
+            .collect::<Vec<_>>();
+
+        let mut pending_data = vec![];
+        for migration in data_migrations {
+            // The tables needed to load progress and count rows may not exist until the pending
+            // SQL migrations have run, in which case we can't say how much work there is yet.
+            let mut tx = storage.read().await?;
+            let next_key = match load_data_migration(&mut tx, migration.name()).await {
+                Ok(Some((_, true))) => continue,
+                Ok(Some((next_key, false))) => next_key,
+                Ok(None) | Err(_) => 0,
+            };
+            let remaining = migration.remaining(&mut tx, next_key).await.ok();
+            pending_data.push(PendingDataMigration {
+                name: migration.name().to_string(),
+                next_key,
+                remaining,
+            });
+        }
+
+        Ok(MigrationPlan {
+            last_applied,
+            pending,
+            pending_data,
         })
     }
 }
@@ -638,6 +914,214 @@ impl SqlStorage {
         Ok(Some(height as u64))
     }
 
+    async fn get_maximum_height(&self) -> QueryResult<Option<u64>> {
+        let mut tx = self.read().await.map_err(|err| QueryError::Error {
+            message: err.to_string(),
+        })?;
+        let (Some(height),) =
+            query_as::<(Option<i64>,)>("SELECT MAX(height) as height FROM header")
+                .fetch_one(tx.as_mut())
+                .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(height as u64))
+    }
+
+    /// The height up to which data should be pruned according to the target retention period and
+    /// the block retention window, whichever is more aggressive.
+    ///
+    /// The block retention window never prunes data younger than the minimum retention period.
+    async fn get_target_height(&self, cfg: &PrunerCfg) -> QueryResult<Option<u64>> {
+        let now = Utc::now().timestamp();
+        let target_height = self
+            .get_height_by_timestamp(now - (cfg.target_retention().as_secs()) as i64)
+            .await?;
+
+        let Some(retention_blocks) = cfg.retention_blocks() else {
+            return Ok(target_height);
+        };
+        let Some(window_height) = self
+            .get_maximum_height()
+            .await?
+            .and_then(|height| height.checked_sub(retention_blocks))
+        else {
+            return Ok(target_height);
+        };
+        let Some(minimum_retention_height) = self
+            .get_height_by_timestamp(now - (cfg.minimum_retention().as_secs()) as i64)
+            .await?
+        else {
+            return Ok(target_height);
+        };
+
+        Ok(max(
+            target_height,
+            Some(min(window_height, minimum_retention_height)),
+        ))
+    }
+
+    /// Delete one batch of data, up to and including `height`.
+    async fn prune_batch(&self, cfg: &PrunerCfg, height: u64) -> anyhow::Result<()> {
+        let mut tx = self.write().await?;
+        let bytes = tx.payload_bytes_up_to(height).await?;
+        if cfg.preserve_headers() {
+            tx.delete_payload_batch(height).await?;
+        } else {
+            tx.delete_batch(cfg.state_tables(), height).await?;
+        }
+        tx.commit().await.map_err(|e| QueryError::Error {
+            message: format!("failed to commit {e}"),
+        })?;
+
+        tracing::info!(height, bytes, "pruned batch");
+        self.pruner_metrics.pruned_bytes.add(bytes as usize);
+        self.pruner_metrics.pruned_height.set(height as usize);
+        Ok(())
+    }
+
+    /// Delete one batch of data, from `height` up to at most `to`.
+    ///
+    /// The batch stops short of the first block whose VID data must be kept (see
+    /// [`reconstructable_height`](Self::reconstructable_height)). Returns the new pruned height, or
+    /// `None` if nothing could be pruned.
+    async fn prune_reconstructable_batch(
+        &self,
+        cfg: &PrunerCfg,
+        pruner: &mut Pruner,
+        height: u64,
+        to: u64,
+    ) -> anyhow::Result<Option<u64>> {
+        if pruner.blocked {
+            return Ok(None);
+        }
+        let Some(to) = self.reconstructable_height(cfg, height, to, false).await? else {
+            pruner.blocked = true;
+            return Ok(None);
+        };
+        self.prune_batch(cfg, to).await?;
+        pruner.pruned_height = Some(to);
+        Ok(Some(to))
+    }
+
+    /// Delete one batch of VID shares older than the share retention period.
+    ///
+    /// Returns the height up to which shares were deleted, or `None` if there were none to delete.
+    async fn prune_shares(
+        &self,
+        cfg: &PrunerCfg,
+        pruner: &mut Pruner,
+    ) -> anyhow::Result<Option<u64>> {
+        let Some(share_retention) = cfg.share_retention() else {
+            return Ok(None);
+        };
+        if pruner.shares_done {
+            return Ok(None);
+        }
+        if pruner.share_target_height.is_none() {
+            pruner.share_target_height = self
+                .get_height_by_timestamp(Utc::now().timestamp() - share_retention.as_secs() as i64)
+                .await?;
+        }
+        let Some(target_height) = pruner.share_target_height else {
+            pruner.shares_done = true;
+            return Ok(None);
+        };
+
+        let mut tx = self.read().await?;
+        let (from,) = query_as::<(Option<i64>,)>(
+            "SELECT MIN(height) FROM vid2 WHERE share IS NOT NULL AND height <= $1",
+        )
+        .bind(target_height as i64)
+        .fetch_one(tx.as_mut())
+        .await?;
+        drop(tx);
+        let Some(from) = from.map(|height| height as u64) else {
+            pruner.shares_done = true;
+            return Ok(None);
+        };
+
+        let to = min(from + cfg.batch_size(), target_height);
+        let Some(to) = self.reconstructable_height(cfg, from, to, true).await? else {
+            pruner.shares_done = true;
+            return Ok(None);
+        };
+        let mut tx = self.write().await?;
+        tx.delete_share_batch(to).await?;
+        tx.commit().await?;
+        tracing::info!(height = to, "pruned VID shares");
+        Ok(Some(to))
+    }
+
+    /// The highest height in `from..=to` up to which VID data can be deleted without making any
+    /// block unreconstructable, or `None` if not even the block at `from` can be.
+    ///
+    /// The VID data of a block can be deleted if the full payload of the block stays in local
+    /// storage (which is only the case if `payload_kept`), or if the shares peers serve for the
+    /// block (see [`share_attestor`](PrunerCfg::share_attestor)) verify against its VID commitment
+    /// and carry enough stake weight to recover the payload.
+    async fn reconstructable_height(
+        &self,
+        cfg: &PrunerCfg,
+        from: u64,
+        to: u64,
+        payload_kept: bool,
+    ) -> anyhow::Result<Option<u64>> {
+        let Some(attestor) = cfg.share_attestor() else {
+            return Ok(Some(to));
+        };
+
+        let archived = if payload_kept {
+            let mut tx = self.read().await?;
+            query_as::<(i64,)>(
+                "SELECT height FROM payload WHERE height >= $1 AND height <= $2 AND data IS NOT NULL",
+            )
+            .bind(from as i64)
+            .bind(to as i64)
+            .fetch_all(tx.as_mut())
+            .await?
+            .into_iter()
+            .map(|(height,)| height as u64)
+            .collect()
+        } else {
+            HashSet::new()
+        };
+
+        // Shares are checked against our own copy of the VID commitment and common data.
+        let mut tx = self.read().await?;
+        let vid: HashMap<u64, (String, Vec<u8>)> = query_as::<(i64, String, Vec<u8>)>(
+            "SELECT h.height, h.payload_hash, v.common
+               FROM header AS h
+               JOIN vid2 AS v ON h.height = v.height
+              WHERE h.height >= $1 AND h.height <= $2",
+        )
+        .bind(from as i64)
+        .bind(to as i64)
+        .fetch_all(tx.as_mut())
+        .await?
+        .into_iter()
+        .map(|(height, commit, common)| (height as u64, (commit, common)))
+        .collect();
+        drop(tx);
+
+        // Collect the shares of several blocks at once, but stop at the first block which cannot
+        // be recovered.
+        let (archived, vid) = (&archived, &vid);
+        let mut recoverable = stream::iter(from..=to)
+            .map(|height| async move {
+                let recoverable = archived.contains(&height)
+                    || peers_can_recover(&**attestor, height, vid.get(&height)).await;
+                (height, recoverable)
+            })
+            .buffered(SHARE_ATTESTATION_CONCURRENCY);
+
+        let mut safe_height = None;
+        while let Some((height, true)) = recoverable.next().await {
+            safe_height = Some(height);
+        }
+        Ok(safe_height)
+    }
+
     async fn get_height_by_timestamp(&self, timestamp: i64) -> QueryResult<Option<u64>> {
         let mut tx = self.read().await.map_err(|err| QueryError::Error {
             message: err.to_string(),
@@ -665,6 +1149,45 @@ impl SqlStorage {
     }
 }
 
+/// Whether the VID shares peers serve for the block at `height` verify against its VID commitment
+/// and common data (`vid`, as stored locally) and suffice to recover its payload.
+async fn peers_can_recover(
+    attestor: &dyn ShareAttestor,
+    height: u64,
+    vid: Option<&(String, Vec<u8>)>,
+) -> bool {
+    let Some((commit, common)) = vid else {
+        tracing::warn!(height, "missing VID common data, keeping VID data of block");
+        return false;
+    };
+    let (Ok(commit), Ok(common)) = (
+        commit.parse::<VidCommitment>(),
+        bincode::deserialize::<VidCommon>(common),
+    ) else {
+        tracing::warn!(
+            height,
+            "malformed VID common data, keeping VID data of block"
+        );
+        return false;
+    };
+
+    let mut shares = VidShares::default();
+    for share in attestor.shares(height).await {
+        if shares.add(share, &common, commit).is_none() {
+            tracing::warn!(height, "peer served invalid VID share");
+        }
+    }
+    if !shares.sufficient(&common) {
+        tracing::warn!(
+            height,
+            shares = shares.len(),
+            "peers do not hold enough VID shares to recover block, keeping its VID data"
+        );
+        return false;
+    }
+    true
+}
+
 #[async_trait]
 impl PruneStorage for SqlStorage {
     type Pruner = Pruner;
@@ -695,7 +1218,6 @@ impl PruneStorage for SqlStorage {
         })?;
         let batch_size = cfg.batch_size();
         let max_usage = cfg.max_usage();
-        let state_tables = cfg.state_tables();
 
         // If a pruner run was already in progress, some variables may already be set,
         // depending on whether a batch was deleted and which batch it was (target or minimum retention).
@@ -703,7 +1225,7 @@ impl PruneStorage for SqlStorage {
         // If any of these values are not set, they can be loaded from the database if necessary.
         let mut minimum_retention_height = pruner.minimum_retention_height;
         let mut target_height = pruner.target_height;
-        let mut height = match pruner.pruned_height {
+        let height = match pruner.pruned_height {
             Some(h) => h,
             None => {
                 let Some(height) = self.get_minimum_height().await? else {
@@ -711,35 +1233,40 @@ impl PruneStorage for SqlStorage {
                     return Ok(None);
                 };
 
-                height
+                // When headers are preserved, the oldest header says nothing about how much has
+                // already been pruned, so resume from the recorded pruned height instead.
+                if cfg.preserve_headers() {
+                    let mut tx = self.read().await?;
+                    max(height, tx.load_pruned_height().await?.unwrap_or(0))
+                } else {
+                    height
+                }
             },
         };
 
-        // Prune data exceeding target retention in batches
+        // Prune data exceeding target retention or the block retention window in batches
         if pruner.target_height.is_none() {
-            let th = self
-                .get_height_by_timestamp(
-                    Utc::now().timestamp() - (cfg.target_retention().as_secs()) as i64,
-                )
-                .await?;
-            target_height = th;
+            target_height = self.get_target_height(&cfg).await?;
             pruner.target_height = target_height;
         };
 
         if let Some(target_height) = target_height {
             if height < target_height {
-                height = min(height + batch_size, target_height);
-                let mut tx = self.write().await?;
-                tx.delete_batch(state_tables, height).await?;
-                tx.commit().await.map_err(|e| QueryError::Error {
-                    message: format!("failed to commit {e}"),
-                })?;
-
-                pruner.pruned_height = Some(height);
-                return Ok(Some(height));
+                let to = min(height + batch_size, target_height);
+                if let Some(height) = self
+                    .prune_reconstructable_batch(&cfg, pruner, height, to)
+                    .await?
+                {
+                    return Ok(Some(height));
+                }
             }
         }
 
+        // Prune VID shares exceeding the share retention period in batches
+        if let Some(height) = self.prune_shares(&cfg, pruner).await? {
+            return Ok(Some(height));
+        }
+
         #[cfg(feature = "embedded-db")]
         {
             let mut conn = self.pool().acquire().await?;
@@ -774,23 +1301,20 @@ impl PruneStorage for SqlStorage {
                     if (usage as f64 / threshold as f64) > (f64::from(max_usage) / 10000.0)
                         && height < min_retention_height
                     {
-                        height = min(height + batch_size, min_retention_height);
-                        let mut tx = self.write().await?;
-                        tx.delete_batch(state_tables, height).await?;
-                        tx.commit().await.map_err(|e| QueryError::Error {
-                            message: format!("failed to commit {e}"),
-                        })?;
-
-                        #[cfg(feature = "embedded-db")]
+                        let to = min(height + batch_size, min_retention_height);
+                        if let Some(height) = self
+                            .prune_reconstructable_batch(&cfg, pruner, height, to)
+                            .await?
                         {
-                            let mut conn = self.pool().acquire().await?;
-                            query("VACUUM").execute(conn.as_mut()).await?;
-                            conn.close().await?;
+                            #[cfg(feature = "embedded-db")]
+                            {
+                                let mut conn = self.pool().acquire().await?;
+                                query("VACUUM").execute(conn.as_mut()).await?;
+                                conn.close().await?;
+                            }
+
+                            return Ok(Some(height));
                         }
-
-                        pruner.pruned_height = Some(height);
-
-                        return Ok(Some(height));
                     }
                 }
             }
@@ -815,7 +1339,21 @@ impl VersionedDataSource for SqlStorage {
     }
 
     async fn read(&self) -> anyhow::Result<Transaction<Read>> {
-        Transaction::new(&self.pool, self.pool_metrics.clone()).await
+        #[allow(unused_mut)]
+        let mut tx = match &self.read_pool {
+            Some(pool) => Transaction::replica(pool, self.pool_metrics.clone()).await?,
+            None => Transaction::new(&self.pool, self.pool_metrics.clone()).await?,
+        };
+        #[cfg(not(feature = "embedded-db"))]
+        if let Some(timeout) = self.statement_timeout {
+            query(&format!(
+                "SET LOCAL statement_timeout = {}",
+                timeout.as_millis()
+            ))
+            .execute(tx.as_mut())
+            .await?;
+        }
+        Ok(tx)
     }
 }
 
@@ -1324,7 +1862,7 @@ mod test {
             node_implementation::{ConsensusTime, Versions},
             EncodeBytes,
         },
-        vid::advz::advz_scheme,
+        vid::{advz::advz_scheme, avidm::AvidMScheme},
     };
     use jf_merkle_tree::{
         prelude::UniversalMerkleTree, MerkleTreeScheme, ToTraversalPath, UniversalMerkleTreeScheme,
@@ -1335,8 +1873,11 @@ mod test {
 
     use super::{testing::TmpDb, *};
     use crate::{
-        availability::{LeafQueryData, QueryableHeader},
-        data_source::storage::{pruning::PrunedHeightStorage, UpdateAvailabilityStorage},
+        availability::{LeafQueryData, QueryableHeader, VidCommonQueryData},
+        data_source::storage::{
+            pruning::{PrunedHeightStorage, ShareAttestor},
+            NodeStorage, UpdateAvailabilityStorage,
+        },
         merklized_state::{MerklizedState, UpdateStateData},
         testing::{
             mocks::{MockHeader, MockMerkleTree, MockPayload, MockTypes, MockVersions},
@@ -1399,6 +1940,79 @@ mod test {
         connect(true, migrations).await.unwrap();
     }
 
+    /// A data migration which migrates the keys `0..10` without touching any data.
+    #[derive(Debug)]
+    struct CountingMigration {
+        batches: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl DataMigration for CountingMigration {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn remaining(&self, _tx: &mut Transaction<Read>, from: i64) -> anyhow::Result<u64> {
+            Ok(10u64.saturating_sub(from as u64))
+        }
+
+        async fn migrate_batch(
+            &self,
+            _tx: &mut Transaction<Write>,
+            from: i64,
+            batch_size: u64,
+        ) -> anyhow::Result<Option<i64>> {
+            self.batches
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let next = from + batch_size as i64;
+            Ok((next < 10).then_some(next))
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_data_migrations() {
+        setup_test();
+
+        let db = TmpDb::init().await;
+        let batches = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let cfg = db
+            .config()
+            .data_migration(CountingMigration {
+                batches: batches.clone(),
+            })
+            .data_migration_batch_size(3);
+
+        // A dry run on a fresh database reports every migration, and changes nothing.
+        let plan = SqlStorage::plan_migrations(cfg.clone()).await.unwrap();
+        assert_eq!(plan.last_applied, None);
+        assert_eq!(plan.pending.len(), default_migrations().len());
+        assert_eq!(
+            plan.pending_data,
+            vec![PendingDataMigration {
+                name: "counting".into(),
+                next_key: 0,
+                remaining: Some(10),
+            }]
+        );
+        assert_eq!(batches.load(std::sync::atomic::Ordering::SeqCst), 0);
+        SqlStorage::connect(cfg.clone().no_migrations())
+            .await
+            .unwrap_err();
+
+        // Connecting runs the data migration in batches.
+        SqlStorage::connect(cfg.clone()).await.unwrap();
+        assert_eq!(batches.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        // Now there is nothing left to do, and the migration does not run again.
+        let plan = SqlStorage::plan_migrations(cfg.clone()).await.unwrap();
+        assert!(plan.is_up_to_date(), "{plan}");
+        SqlStorage::connect(cfg.clone().no_migrations())
+            .await
+            .unwrap();
+        SqlStorage::connect(cfg).await.unwrap();
+        assert_eq!(batches.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
     #[test]
     #[cfg(not(feature = "embedded-db"))]
     fn test_config_from_str() {
@@ -1517,6 +2131,162 @@ mod test {
         )
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retention_blocks_pruning_preserves_headers() {
+        setup_test();
+
+        let db = TmpDb::init().await;
+        let cfg = db.config();
+
+        let mut storage = SqlStorage::connect(cfg).await.unwrap();
+        let mut leaf = LeafQueryData::<MockTypes>::genesis::<TestVersions>(
+            &TestValidatedState::default(),
+            &TestInstanceState::default(),
+        )
+        .await;
+        // insert some mock data
+        for i in 0..20 {
+            leaf.leaf.block_header_mut().block_number = i;
+            leaf.leaf.block_header_mut().timestamp = Utc::now().timestamp() as u64;
+            let mut tx = storage.write().await.unwrap();
+            tx.insert_leaf(leaf.clone()).await.unwrap();
+            tx.commit().await.unwrap();
+        }
+
+        // Retain only the 5 most recent blocks. None of the data is older than the target
+        // retention, so only the block window triggers pruning.
+        storage.set_pruning_config(
+            PrunerCfg::new()
+                .with_minimum_retention(Duration::ZERO)
+                .with_retention_blocks(5)
+                .with_preserve_headers(true),
+        );
+        let pruned_height = storage.prune(&mut Default::default()).await.unwrap();
+        assert_eq!(pruned_height, Some(14));
+
+        // All the headers are still there.
+        let header_rows = storage
+            .read()
+            .await
+            .unwrap()
+            .fetch_one("select count(*) as count from header")
+            .await
+            .unwrap()
+            .get::<i64, _>("count");
+        assert_eq!(header_rows, 20);
+
+        // But the payloads of the pruned blocks are gone.
+        let payload_rows = storage
+            .read()
+            .await
+            .unwrap()
+            .fetch_one("select count(*) as count from payload where height <= 14")
+            .await
+            .unwrap()
+            .get::<i64, _>("count");
+        assert_eq!(payload_rows, 0);
+
+        let mut tx = storage.read().await.unwrap();
+        let horizon = NodeStorage::<MockTypes>::pruning_horizon(&mut tx)
+            .await
+            .unwrap();
+        drop(tx);
+        assert_eq!(horizon.pruned_height, Some(14));
+        assert_eq!(horizon.oldest_header, Some(0));
+
+        // A second run resumes from the pruned height rather than the oldest header, and finds
+        // nothing left to do.
+        let pruned_height = storage.prune(&mut Default::default()).await.unwrap();
+        assert_eq!(pruned_height, None);
+    }
+
+    /// Serves a single share with enough stake to recover every block below `height`. For other
+    /// blocks, it serves a share with too little stake and a share which does not verify.
+    #[derive(Debug)]
+    struct MockAttestor {
+        height: u64,
+        shares: Vec<VidShare>,
+        invalid: VidShare,
+    }
+
+    #[async_trait]
+    impl ShareAttestor for MockAttestor {
+        async fn shares(&self, height: u64) -> Vec<VidShare> {
+            if height < self.height {
+                vec![self.shares[0].clone()]
+            } else {
+                vec![self.shares[1].clone(), self.invalid.clone()]
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pruning_requires_share_attestations() {
+        setup_test();
+
+        let db = TmpDb::init().await;
+        let cfg = db.config();
+
+        // Disperse a payload among three nodes, the first of which has half of the stake, so that
+        // its share alone is enough to recover the payload.
+        let param = AvidMScheme::setup(2, 4).unwrap();
+        let payload = [1; 64];
+        let (commit, shares) =
+            AvidMScheme::ns_disperse(&param, &[2, 1, 1], &payload, [0..payload.len()]).unwrap();
+        let (_, other_shares) =
+            AvidMScheme::ns_disperse(&param, &[2, 1, 1], &[2; 64], [0..64]).unwrap();
+        let attestor = MockAttestor {
+            height: 10,
+            shares: shares.into_iter().map(VidShare::V1).collect(),
+            // Counted towards the stake, this share would make up for the one served with it.
+            invalid: VidShare::V1(other_shares[2].clone()),
+        };
+
+        let mut storage = SqlStorage::connect(cfg).await.unwrap();
+        let mut leaf = LeafQueryData::<MockTypes>::genesis::<TestVersions>(
+            &TestValidatedState::default(),
+            &TestInstanceState::default(),
+        )
+        .await;
+        for i in 0..20 {
+            leaf.leaf.block_header_mut().block_number = i;
+            leaf.leaf.block_header_mut().timestamp = Utc::now().timestamp() as u64;
+            leaf.leaf.block_header_mut().payload_commitment = VidCommitment::V1(commit);
+            let common =
+                VidCommonQueryData::new(leaf.header().clone(), VidCommon::V1(param.clone()));
+            let mut tx = storage.write().await.unwrap();
+            tx.insert_leaf(leaf.clone()).await.unwrap();
+            tx.insert_vid(common, None).await.unwrap();
+            tx.commit().await.unwrap();
+        }
+
+        // The block window allows pruning up to height 14, but peers only hold enough of the
+        // shares of the first 10 blocks.
+        storage.set_pruning_config(
+            PrunerCfg::new()
+                .with_minimum_retention(Duration::ZERO)
+                .with_retention_blocks(5)
+                .with_preserve_headers(true)
+                .with_share_attestor(Arc::new(attestor)),
+        );
+        let mut pruner = Default::default();
+        let pruned_height = storage.prune(&mut pruner).await.unwrap();
+        assert_eq!(pruned_height, Some(9));
+        // The run stops at the first block peers cannot reconstruct.
+        let pruned_height = storage.prune(&mut pruner).await.unwrap();
+        assert_eq!(pruned_height, None);
+
+        let payload_rows = storage
+            .read()
+            .await
+            .unwrap()
+            .fetch_one("select count(*) as count from payload where height > 9")
+            .await
+            .unwrap()
+            .get::<i64, _>("count");
+        assert_eq!(payload_rows, 10);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_merklized_state_pruning() {
         setup_test();
@@ -1717,6 +2487,60 @@ mod test {
         }
     }
 
+    #[cfg(not(feature = "embedded-db"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_read_replica() {
+        setup_test();
+
+        let db = TmpDb::init().await;
+        let cfg = db.config();
+        // Use the primary as its own replica, so that reads observe writes immediately.
+        let replica = cfg.db_opt.clone();
+        let cfg = cfg
+            .read_replica(replica)
+            .read_replica_max_connections(2)
+            .statement_timeout(Duration::from_secs(10));
+
+        let storage = SqlStorage::connect(cfg).await.unwrap();
+        assert!(storage.read_pool.is_some());
+
+        let mut tx = storage.write().await.unwrap();
+        tx.save_pruned_height(10).await.unwrap();
+        tx.commit().await.unwrap();
+        assert_eq!(
+            storage
+                .read()
+                .await
+                .unwrap()
+                .load_pruned_height()
+                .await
+                .unwrap(),
+            Some(10)
+        );
+
+        // The statement timeout applies to reads only.
+        let mut tx = storage.read().await.unwrap();
+        let (timeout,) = query_as::<(String,)>("SHOW statement_timeout")
+            .fetch_one(tx.as_mut())
+            .await
+            .unwrap();
+        assert_eq!(timeout, "10s");
+        let mut tx = storage.write().await.unwrap();
+        let (timeout,) = query_as::<(String,)>("SHOW statement_timeout")
+            .fetch_one(tx.as_mut())
+            .await
+            .unwrap();
+        assert_eq!(timeout, "0");
+        drop(tx);
+
+        // Read-only transactions on the replica cannot write.
+        let mut tx = storage.read().await.unwrap();
+        query("INSERT INTO pruned_height (id, last_height) VALUES (2, 20)")
+            .execute(tx.as_mut())
+            .await
+            .unwrap_err();
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_types_migration() {
         setup_test();
@@ -1,3 +1,6 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+use anyhow::Context;
 use async_trait::async_trait;
 use derive_more::From;
 use futures::stream::StreamExt;
@@ -5,10 +8,16 @@ use refinery_core::{
     traits::r#async::{AsyncMigrate, AsyncQuery, AsyncTransaction},
     Migration,
 };
+use serde::{Deserialize, Serialize};
 use sqlx::{pool::PoolConnection, Acquire, Executor, Row};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
-use super::{queries::DecodeError, Db};
+use super::{
+    queries::DecodeError,
+    transaction::{query_as, Read, Transaction, TransactionMode, Write},
+    Db,
+};
+use crate::data_source::{update::Transaction as _, VersionedDataSource};
 
 /// Run migrations using a sqlx connection.
 ///
@@ -70,3 +79,153 @@ impl AsyncQuery<Vec<Migration>> for Migrator<'_> {
 }
 
 impl AsyncMigrate for Migrator<'_> {}
+
+/// A long-running migration of existing data, registered in code.
+///
+/// Schema changes are made by [SQL migrations](super::Migration), but some changes require
+/// computation that cannot easily be expressed in SQL, such as recomputing a commitment for every
+/// row of a table. A data migration processes such rows in batches, ordered by some integer key.
+/// After each batch, the key to resume from is recorded in the same transaction as the migrated
+/// rows, so that a migration interrupted by a restart resumes where it left off rather than
+/// starting over.
+///
+/// Data migrations run after all SQL migrations have been applied, in the order they were
+/// registered with [`Config::data_migration`](super::Config::data_migration).
+#[async_trait]
+pub trait DataMigration: Debug + Send + Sync {
+    /// A unique name identifying this migration in the database.
+    ///
+    /// This must never change once the migration has been released.
+    fn name(&self) -> &'static str;
+
+    /// Count the rows which still need to be migrated, starting from key `from`.
+    async fn remaining(&self, tx: &mut Transaction<Read>, from: i64) -> anyhow::Result<u64>;
+
+    /// Migrate up to `batch_size` rows, starting from key `from`.
+    ///
+    /// Returns the key to resume from in the next batch, or [`None`] if there are no more rows to
+    /// migrate.
+    async fn migrate_batch(
+        &self,
+        tx: &mut Transaction<Write>,
+        from: i64,
+        batch_size: u64,
+    ) -> anyhow::Result<Option<i64>>;
+}
+
+/// The changes that would be made to the database by connecting with a given
+/// [`Config`](super::Config).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationPlan {
+    /// The version of the most recent SQL migration applied to the database.
+    pub last_applied: Option<i32>,
+    /// SQL migrations which have not been applied yet, as `(version, name)`.
+    pub pending: Vec<(i32, String)>,
+    /// Data migrations which have not completed yet.
+    pub pending_data: Vec<PendingDataMigration>,
+}
+
+impl MigrationPlan {
+    /// Whether connecting would leave the database unchanged.
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty() && self.pending_data.is_empty()
+    }
+}
+
+impl Display for MigrationPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_up_to_date() {
+            return write!(
+                f,
+                "database is up to date (last applied {:?})",
+                self.last_applied
+            );
+        }
+        writeln!(f, "last applied migration: {:?}", self.last_applied)?;
+        for (version, name) in &self.pending {
+            writeln!(f, "would apply V{version}__{name}")?;
+        }
+        for data in &self.pending_data {
+            match data.remaining {
+                Some(remaining) => writeln!(
+                    f,
+                    "would migrate {remaining} rows for {} (resuming from key {})",
+                    data.name, data.next_key
+                )?,
+                None => writeln!(f, "would run data migration {}", data.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A data migration which has not completed yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingDataMigration {
+    pub name: String,
+    /// The key the migration will resume from.
+    pub next_key: i64,
+    /// The number of rows left to migrate, if it can be determined before the SQL migrations run.
+    pub remaining: Option<u64>,
+}
+
+/// Load the progress of a data migration.
+///
+/// Returns `(next_key, completed)`, or [`None`] if the migration has never run.
+pub(super) async fn load_data_migration<Mode: TransactionMode>(
+    tx: &mut Transaction<Mode>,
+    name: &str,
+) -> anyhow::Result<Option<(i64, bool)>> {
+    Ok(
+        query_as::<(i64, bool)>("SELECT next_key, completed FROM data_migrations WHERE name = $1")
+            .bind(name)
+            .fetch_optional(tx.as_mut())
+            .await?,
+    )
+}
+
+/// Run a data migration to completion, committing after each batch.
+pub(super) async fn run_data_migration(
+    storage: &super::SqlStorage,
+    migration: &dyn DataMigration,
+    batch_size: u64,
+) -> anyhow::Result<()> {
+    let name = migration.name();
+    let mut tx = storage.read().await?;
+    let (mut next_key, completed) = load_data_migration(&mut tx, name)
+        .await?
+        .unwrap_or_default();
+    drop(tx);
+    if completed {
+        tracing::debug!(name, "data migration already complete");
+        return Ok(());
+    }
+
+    tracing::warn!(name, next_key, "running data migration");
+    loop {
+        let mut tx = storage.write().await?;
+        let next = migration
+            .migrate_batch(&mut tx, next_key, batch_size)
+            .await
+            .with_context(|| format!("data migration {name} failed at key {next_key}"))?;
+        tx.upsert(
+            "data_migrations",
+            ["name", "next_key", "completed"],
+            ["name"],
+            [(name.to_string(), next.unwrap_or(next_key), next.is_none())],
+        )
+        .await?;
+        tx.commit().await?;
+
+        match next {
+            Some(key) => {
+                tracing::info!(name, key, "data migration progress");
+                next_key = key;
+            },
+            None => {
+                tracing::warn!(name, "data migration complete");
+                return Ok(());
+            },
+        }
+    }
+}
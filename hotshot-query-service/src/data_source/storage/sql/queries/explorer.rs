@@ -12,7 +12,10 @@
 
 //! Explorer storage implementation for a database query engine.
 
-use std::{collections::VecDeque, num::NonZeroUsize};
+use std::{
+    collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
+};
 
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
@@ -23,24 +26,28 @@ use sqlx::{types::Json, FromRow, Row};
 use tagged_base64::{Tagged, TaggedBase64};
 
 use super::{
-    super::transaction::{query, Transaction, TransactionMode},
+    super::transaction::{query, query_as, Transaction, TransactionMode, Write},
     Database, Db, DecodeError, BLOCK_COLUMNS,
 };
 use crate::{
     availability::{BlockQueryData, QueryableHeader, QueryablePayload, TransactionIndex},
-    data_source::storage::{ExplorerStorage, NodeStorage},
+    data_source::storage::{
+        pruning::PrunedHeightStorage, ExplorerStorage, NodeStorage, UpdateExplorerStatsStorage,
+    },
     explorer::{
         self,
         errors::{self, NotFound},
         query_data::TransactionDetailResponse,
-        traits::ExplorerHeader,
+        traits::{ExplorerHeader, ExplorerTransaction},
         BalanceAmount, BlockDetail, BlockIdentifier, BlockRange, BlockSummary, ExplorerHistograms,
-        ExplorerSummary, GenesisOverview, GetBlockDetailError, GetBlockSummariesError,
-        GetBlockSummariesRequest, GetExplorerSummaryError, GetSearchResultsError,
-        GetTransactionDetailError, GetTransactionSummariesError, GetTransactionSummariesRequest,
-        MonetaryValue, SearchResult, TransactionIdentifier, TransactionRange, TransactionSummary,
-        TransactionSummaryFilter,
+        ExplorerStats, ExplorerSummary, GenesisOverview, GetBlockDetailError,
+        GetBlockSummariesError, GetBlockSummariesRequest, GetExplorerStatsError,
+        GetExplorerSummaryError, GetSearchResultsError, GetTransactionDetailError,
+        GetTransactionSummariesError, GetTransactionSummariesRequest, MonetaryValue,
+        NamespaceStats, SearchResult, TransactionIdentifier, TransactionNamespaceId,
+        TransactionRange, TransactionSummary, TransactionSummaryFilter,
     },
+    types::HeightIndexed,
     Header, Payload, QueryError, QueryResult, Transaction as HotshotTransaction,
 };
 
@@ -74,6 +81,12 @@ impl From<sqlx::Error> for GetBlockSummariesError {
     }
 }
 
+impl From<sqlx::Error> for GetExplorerStatsError {
+    fn from(err: sqlx::Error) -> Self {
+        Self::from(QueryError::from(err))
+    }
+}
+
 impl From<sqlx::Error> for GetSearchResultsError {
     fn from(err: sqlx::Error) -> Self {
         Self::from(QueryError::from(err))
@@ -265,6 +278,72 @@ const EXPLORER_SUMMARY_NUM_BLOCKS: usize = 10;
 /// to return in our explorer summary.
 const EXPLORER_SUMMARY_NUM_TRANSACTIONS: usize = 10;
 
+const EXPLORER_STATS_COLUMNS: &str =
+    "height, timestamp, num_transactions, payload_size, num_empty_blocks, fees";
+
+/// A row of the `explorer_stats` table: cumulative statistics as of a given block.
+#[derive(Clone, Debug, Default)]
+struct ExplorerStatsRow {
+    height: u64,
+    timestamp: u64,
+    num_transactions: u64,
+    payload_size: u64,
+    num_empty_blocks: u64,
+    fees: Option<MonetaryValue>,
+}
+
+impl<'r> FromRow<'r, <Db as Database>::Row> for ExplorerStatsRow {
+    fn from_row(row: &'r <Db as Database>::Row) -> sqlx::Result<Self> {
+        let height: i64 = row.try_get("height")?;
+        let timestamp: i64 = row.try_get("timestamp")?;
+        let num_transactions: i64 = row.try_get("num_transactions")?;
+        let payload_size: i64 = row.try_get("payload_size")?;
+        let num_empty_blocks: i64 = row.try_get("num_empty_blocks")?;
+        let fees: Option<Json<MonetaryValue>> = row.try_get("fees")?;
+        Ok(Self {
+            height: height as u64,
+            timestamp: timestamp as u64,
+            num_transactions: num_transactions as u64,
+            payload_size: payload_size as u64,
+            num_empty_blocks: num_empty_blocks as u64,
+            fees: fees.map(|Json(fees)| fees),
+        })
+    }
+}
+
+impl ExplorerStatsRow {
+    /// The statistics of the blocks after `base`, up to and including this one.
+    fn since(&self, base: &Self) -> Result<Self, QueryError> {
+        let fees = match (&self.fees, &base.fees) {
+            (Some(fees), Some(base)) => {
+                Some(
+                    (fees.clone() - base.clone()).map_err(|err| QueryError::Error {
+                        message: err.to_string(),
+                    })?,
+                )
+            },
+            (fees, _) => fees.clone(),
+        };
+        Ok(Self {
+            height: self.height,
+            timestamp: self.timestamp,
+            num_transactions: self.num_transactions.saturating_sub(base.num_transactions),
+            payload_size: self.payload_size.saturating_sub(base.payload_size),
+            num_empty_blocks: self.num_empty_blocks.saturating_sub(base.num_empty_blocks),
+            fees,
+        })
+    }
+}
+
+/// The fraction `num / den`, or 0 if the denominator is 0.
+fn ratio(num: u64, den: u64) -> f64 {
+    if den == 0 {
+        0.0
+    } else {
+        num as f64 / den as f64
+    }
+}
+
 #[async_trait]
 impl<Mode, Types> ExplorerStorage<Types> for Transaction<Mode>
 where
@@ -574,6 +653,94 @@ where
         })
     }
 
+    async fn get_explorer_stats(
+        &mut self,
+        window: u64,
+    ) -> Result<ExplorerStats<Types>, GetExplorerStatsError> {
+        let Some(latest) = self.latest_explorer_stats().await? else {
+            return Err(QueryError::NotFound.into());
+        };
+
+        // The window starts at the last block at or before the start time. If statistics do not go
+        // back that far, the window starts at the earliest block we have statistics for.
+        let start = match query_as::<ExplorerStatsRow>(&format!(
+            "SELECT {EXPLORER_STATS_COLUMNS}
+               FROM explorer_stats
+              WHERE timestamp <= $1
+              ORDER BY timestamp DESC, height DESC
+              LIMIT 1"
+        ))
+        .bind(latest.timestamp.saturating_sub(window) as i64)
+        .fetch_optional(self.as_mut())
+        .await?
+        {
+            Some(start) => start,
+            None => {
+                query_as(&format!(
+                    "SELECT {EXPLORER_STATS_COLUMNS} FROM explorer_stats ORDER BY height LIMIT 1"
+                ))
+                .fetch_one(self.as_mut())
+                .await?
+            },
+        };
+
+        let elapsed = latest.timestamp.saturating_sub(start.timestamp);
+        let window_blocks = latest.height - start.height;
+        let window_fees = match (&latest.fees, start.fees) {
+            (Some(latest), Some(start)) => {
+                vec![(latest.clone() - start).map_err(|err| QueryError::Error {
+                    message: err.to_string(),
+                })?]
+            },
+            (Some(latest), None) => vec![latest.clone()],
+            (None, _) => vec![],
+        };
+
+        // Totals only cover blocks which have not been pruned.
+        let totals = match self.pruned_explorer_stats().await? {
+            Some(pruned) => latest.since(&pruned)?,
+            None => latest.clone(),
+        };
+
+        let namespaces = query_as::<(Json<TransactionNamespaceId<Types>>, i64, i64)>(
+            "SELECT namespace, num_transactions, payload_size
+               FROM explorer_namespace_stats
+              ORDER BY num_transactions DESC",
+        )
+        .fetch_all(self.as_mut())
+        .await?
+        .into_iter()
+        .map(
+            |(Json(namespace), num_transactions, payload_size)| NamespaceStats {
+                namespace,
+                num_transactions: num_transactions as u64,
+                payload_size: payload_size as u64,
+            },
+        )
+        .collect();
+
+        Ok(ExplorerStats {
+            block_height: latest.height,
+            window,
+            window_blocks,
+            transactions_per_second: ratio(
+                latest.num_transactions - start.num_transactions,
+                elapsed,
+            ),
+            bytes_per_second: ratio(latest.payload_size - start.payload_size, elapsed),
+            empty_block_ratio: ratio(
+                latest.num_empty_blocks - start.num_empty_blocks,
+                window_blocks,
+            ),
+            window_fees,
+            total_transactions: totals.num_transactions,
+            total_payload_size: totals.payload_size,
+            total_empty_blocks: totals.num_empty_blocks,
+            total_fees: totals.fees.into_iter().collect(),
+            namespaces,
+        })
+    }
+
     async fn get_search_results(
         &mut self,
         search_query: TaggedBase64,
@@ -648,3 +815,282 @@ where
         }
     }
 }
+
+impl<Mode: TransactionMode> Transaction<Mode> {
+    async fn latest_explorer_stats(&mut self) -> sqlx::Result<Option<ExplorerStatsRow>> {
+        query_as(&format!(
+            "SELECT {EXPLORER_STATS_COLUMNS} FROM explorer_stats ORDER BY height DESC LIMIT 1"
+        ))
+        .fetch_optional(self.as_mut())
+        .await
+    }
+
+    /// The cumulative statistics as of the last pruned block which was included in them, if any.
+    async fn pruned_explorer_stats(&mut self) -> sqlx::Result<Option<ExplorerStatsRow>> {
+        query_as(&format!(
+            "SELECT {EXPLORER_STATS_COLUMNS} FROM explorer_stats_pruned WHERE id = 1"
+        ))
+        .fetch_optional(self.as_mut())
+        .await
+    }
+}
+
+impl Transaction<Write> {
+    /// Remove blocks up to and including `height` from the explorer statistics totals.
+    ///
+    /// The cumulative statistics as of the last pruned block are saved, so that they can be
+    /// subtracted from the latest statistics, and the volume of the pruned blocks is subtracted
+    /// from the total for each namespace. This must run before the pruned blocks are deleted.
+    pub(in crate::data_source::storage::sql) async fn prune_explorer_stats(
+        &mut self,
+        height: u64,
+    ) -> anyhow::Result<()> {
+        let pruned = query_as::<ExplorerStatsRow>(&format!(
+            "SELECT {EXPLORER_STATS_COLUMNS}
+               FROM explorer_stats
+              WHERE height <= $1
+              ORDER BY height DESC
+              LIMIT 1"
+        ))
+        .bind(height as i64)
+        .fetch_optional(self.as_mut())
+        .await?;
+        if let Some(stats) = pruned {
+            self.upsert(
+                "explorer_stats_pruned",
+                [
+                    "id",
+                    "height",
+                    "timestamp",
+                    "num_transactions",
+                    "payload_size",
+                    "num_empty_blocks",
+                    "fees",
+                ],
+                ["id"],
+                [(
+                    1i32,
+                    stats.height as i64,
+                    stats.timestamp as i64,
+                    stats.num_transactions as i64,
+                    stats.payload_size as i64,
+                    stats.num_empty_blocks as i64,
+                    stats.fees.map(Json),
+                )],
+            )
+            .await?;
+        }
+
+        let pruned = query_as::<(Json<serde_json::Value>, i64, i64)>(
+            "SELECT namespace,
+                    CAST(SUM(num_transactions) AS BIGINT),
+                    CAST(SUM(payload_size) AS BIGINT)
+               FROM explorer_namespace_block_stats
+              WHERE height <= $1
+              GROUP BY namespace",
+        )
+        .bind(height as i64)
+        .fetch_all(self.as_mut())
+        .await?;
+        if pruned.is_empty() {
+            return Ok(());
+        }
+        let mut totals = query_as::<(Json<serde_json::Value>, i64, i64)>(
+            "SELECT namespace, num_transactions, payload_size FROM explorer_namespace_stats",
+        )
+        .fetch_all(self.as_mut())
+        .await?
+        .into_iter()
+        .map(|(Json(namespace), num_transactions, payload_size)| {
+            (
+                namespace.to_string(),
+                (namespace, num_transactions, payload_size),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+        for (Json(namespace), num_transactions, payload_size) in pruned {
+            if let Some(entry) = totals.get_mut(&namespace.to_string()) {
+                entry.1 -= num_transactions;
+                entry.2 -= payload_size;
+            }
+        }
+
+        // Namespaces with no transactions left are removed entirely.
+        let (emptied, remaining): (Vec<_>, Vec<_>) = totals
+            .into_values()
+            .partition(|(_, num_transactions, _)| *num_transactions <= 0);
+        for (namespace, ..) in emptied {
+            query("DELETE FROM explorer_namespace_stats WHERE namespace = $1")
+                .bind(Json(namespace))
+                .execute(self.as_mut())
+                .await?;
+        }
+        if !remaining.is_empty() {
+            self.upsert(
+                "explorer_namespace_stats",
+                ["namespace", "num_transactions", "payload_size"],
+                ["namespace"],
+                remaining
+                    .into_iter()
+                    .map(|(namespace, num_transactions, payload_size)| {
+                        (Json(namespace), num_transactions, payload_size)
+                    }),
+            )
+            .await?;
+        }
+        query("DELETE FROM explorer_namespace_block_stats WHERE height <= $1")
+            .bind(height as i64)
+            .execute(self.as_mut())
+            .await?;
+        Ok(())
+    }
+}
+
+impl<Types> UpdateExplorerStatsStorage<Types> for Transaction<Write>
+where
+    Types: NodeType,
+    Payload<Types>: QueryablePayload<Types>,
+    Header<Types>: QueryableHeader<Types> + ExplorerHeader<Types>,
+    crate::Transaction<Types>: ExplorerTransaction,
+    BalanceAmount<Types>: Into<MonetaryValue>,
+{
+    async fn explorer_stats_height(&mut self) -> anyhow::Result<usize> {
+        let next = self
+            .latest_explorer_stats()
+            .await?
+            .map_or(0, |stats| stats.height + 1);
+        // There is no point waiting for blocks which have been pruned, since we will never get
+        // them.
+        let unpruned = self.load_pruned_height().await?.map_or(0, |h| h + 1);
+        Ok(next.max(unpruned) as usize)
+    }
+
+    async fn update_explorer_stats(
+        &mut self,
+        blocks: &[BlockQueryData<Types>],
+    ) -> anyhow::Result<()> {
+        // If every block we have statistics for has been pruned, carry on from the statistics as of
+        // the last pruned block, which are subtracted from the totals.
+        let prev = match self.latest_explorer_stats().await? {
+            Some(prev) => Some(prev),
+            None => self.pruned_explorer_stats().await?,
+        };
+        let mut next_height = prev.as_ref().map_or(0, |stats| stats.height + 1);
+        let mut stats = prev.unwrap_or_default();
+
+        // Cumulatively sum up statistics for each new block, skipping any blocks we have already
+        // counted so that replaying a chunk does not count it twice.
+        let mut rows = vec![];
+        let mut namespaces = HashMap::<String, (serde_json::Value, u64, u64)>::new();
+        let mut block_namespaces = vec![];
+        for block in blocks {
+            if block.height() < next_height {
+                continue;
+            }
+            next_height = block.height() + 1;
+
+            let num_transactions = block.num_transactions();
+            stats.height = block.height();
+            stats.timestamp = block.header().timestamp();
+            stats.num_transactions += num_transactions;
+            stats.payload_size += block.size();
+            if num_transactions == 0 {
+                stats.num_empty_blocks += 1;
+            }
+            let fee: MonetaryValue = block.header().fee_info_balance().into();
+            stats.fees = Some(match stats.fees {
+                Some(total) => (total + fee).map_err(|err| anyhow::anyhow!("{err}"))?,
+                None => fee,
+            });
+            rows.push(stats.clone());
+
+            let mut volume = HashMap::<String, (serde_json::Value, u64, u64)>::new();
+            for (_, txn) in block.enumerate() {
+                let namespace = serde_json::to_value(txn.namespace_id())?;
+                let entry = volume
+                    .entry(namespace.to_string())
+                    .or_insert((namespace, 0, 0));
+                entry.1 += 1;
+                entry.2 += txn.payload_size();
+            }
+            for (key, (namespace, num_transactions, payload_size)) in volume {
+                let entry = namespaces.entry(key).or_insert((namespace.clone(), 0, 0));
+                entry.1 += num_transactions;
+                entry.2 += payload_size;
+                block_namespaces.push((
+                    block.height() as i64,
+                    Json(namespace),
+                    num_transactions as i64,
+                    payload_size as i64,
+                ));
+            }
+        }
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        self.upsert(
+            "explorer_stats",
+            [
+                "height",
+                "timestamp",
+                "num_transactions",
+                "payload_size",
+                "num_empty_blocks",
+                "fees",
+            ],
+            ["height"],
+            rows.into_iter().map(|stats| {
+                (
+                    stats.height as i64,
+                    stats.timestamp as i64,
+                    stats.num_transactions as i64,
+                    stats.payload_size as i64,
+                    stats.num_empty_blocks as i64,
+                    stats.fees.map(Json),
+                )
+            }),
+        )
+        .await?;
+
+        if namespaces.is_empty() {
+            return Ok(());
+        }
+        // Keep the volume of each block, to subtract it from the totals when the block is pruned.
+        self.upsert(
+            "explorer_namespace_block_stats",
+            ["height", "namespace", "num_transactions", "payload_size"],
+            ["height", "namespace"],
+            block_namespaces,
+        )
+        .await?;
+
+        // Add the new volume to the existing totals for each namespace.
+        let totals = query_as::<(Json<serde_json::Value>, i64, i64)>(
+            "SELECT namespace, num_transactions, payload_size FROM explorer_namespace_stats",
+        )
+        .fetch_all(self.as_mut())
+        .await?;
+        for (Json(namespace), num_transactions, payload_size) in totals {
+            if let Some(entry) = namespaces.get_mut(&namespace.to_string()) {
+                entry.1 += num_transactions as u64;
+                entry.2 += payload_size as u64;
+            }
+        }
+        self.upsert(
+            "explorer_namespace_stats",
+            ["namespace", "num_transactions", "payload_size"],
+            ["namespace"],
+            namespaces
+                .into_values()
+                .map(|(namespace, num_transactions, payload_size)| {
+                    (
+                        Json(namespace),
+                        num_transactions as i64,
+                        payload_size as i64,
+                    )
+                }),
+        )
+        .await
+    }
+}
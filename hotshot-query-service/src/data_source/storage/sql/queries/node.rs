@@ -32,7 +32,7 @@ use crate::{
     data_source::storage::{
         Aggregate, AggregatesStorage, NodeStorage, PayloadMetadata, UpdateAggregatesStorage,
     },
-    node::{BlockId, SyncStatus, TimeWindowQueryData, WindowStart},
+    node::{BlockId, PruningHorizon, SyncStatus, TimeWindowQueryData, WindowStart},
     types::HeightIndexed,
     Header, MissingSnafu, NotFoundSnafu, QueryError, QueryResult,
 };
@@ -204,6 +204,20 @@ where
         })
     }
 
+    async fn pruning_horizon(&mut self) -> QueryResult<PruningHorizon> {
+        let (pruned_height, oldest_header) = query_as::<(Option<i64>, Option<i64>)>(
+            "SELECT
+                (SELECT last_height FROM pruned_height ORDER BY id DESC LIMIT 1),
+                (SELECT MIN(height) FROM header)",
+        )
+        .fetch_one(self.as_mut())
+        .await?;
+        Ok(PruningHorizon {
+            pruned_height: pruned_height.map(|h| h as u64),
+            oldest_header: oldest_header.map(|h| h as u64),
+        })
+    }
+
     async fn get_header_window(
         &mut self,
         start: impl Into<WindowStart<Types>> + Send + Sync,
@@ -238,6 +238,24 @@ impl<Mode: TransactionMode> Transaction<Mode> {
     }
 }
 
+impl Transaction<Read> {
+    /// Begin a read-only transaction on a read replica.
+    ///
+    /// Postgres does not allow serializable transactions on a hot standby, so replica transactions
+    /// use repeatable read isolation instead. This still gives each transaction a consistent
+    /// snapshot of the replica.
+    pub(super) async fn replica(pool: &Pool<Db>, metrics: PoolMetrics) -> anyhow::Result<Self> {
+        let mut inner = pool.begin().await?;
+        let metrics = TransactionMetricsGuard::begin(metrics);
+        #[cfg(not(feature = "embedded-db"))]
+        inner
+            .as_mut()
+            .execute("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .await?;
+        Ok(Self { inner, metrics })
+    }
+}
+
 impl<Mode: TransactionMode> update::Transaction for Transaction<Mode> {
     async fn commit(mut self) -> anyhow::Result<()> {
         self.inner.commit().await?;
@@ -421,6 +439,7 @@ impl Transaction<Write> {
         state_tables: Vec<String>,
         height: u64,
     ) -> anyhow::Result<()> {
+        self.prune_explorer_stats(height).await?;
         self.execute(query("DELETE FROM header WHERE height <= $1").bind(height as i64))
             .await?;
 
@@ -446,6 +465,51 @@ impl Transaction<Write> {
         Ok(())
     }
 
+    /// Delete the payloads, transactions and VID data of all blocks up to and including `height`.
+    ///
+    /// Unlike [`delete_batch`](Self::delete_batch), headers, leaves and merklized state are left
+    /// in place, so that the pruned blocks can still be verified by light clients.
+    pub(super) async fn delete_payload_batch(&mut self, height: u64) -> anyhow::Result<()> {
+        self.prune_explorer_stats(height).await?;
+        self.execute(
+            query("DELETE FROM transactions WHERE block_height <= $1").bind(height as i64),
+        )
+        .await?;
+        for table in ["payload", "vid", "vid2"] {
+            self.execute(
+                query(&format!("DELETE FROM {table} WHERE height <= $1")).bind(height as i64),
+            )
+            .await?;
+        }
+
+        self.save_pruned_height(height).await?;
+        Ok(())
+    }
+
+    /// Delete the VID shares of all blocks up to and including `height`.
+    ///
+    /// The VID common data is kept, along with the rest of the blocks. This does not change the
+    /// pruned height, since the blocks themselves are still available.
+    pub(super) async fn delete_share_batch(&mut self, height: u64) -> anyhow::Result<()> {
+        self.execute(query("UPDATE vid2 SET share = NULL WHERE height <= $1").bind(height as i64))
+            .await?;
+        Ok(())
+    }
+
+    /// The number of bytes of payload and VID data stored for blocks up to and including `height`.
+    pub(super) async fn payload_bytes_up_to(&mut self, height: u64) -> anyhow::Result<u64> {
+        let (bytes,) = query_as::<(i64,)>(
+            "SELECT
+                (SELECT COALESCE(SUM(LENGTH(data)), 0) FROM payload WHERE height <= $1) +
+                (SELECT COALESCE(SUM(LENGTH(common) + COALESCE(LENGTH(share), 0)), 0)
+                   FROM vid2 WHERE height <= $1)",
+        )
+        .bind(height as i64)
+        .fetch_one(self.as_mut())
+        .await?;
+        Ok(bytes as u64)
+    }
+
     /// Record the height of the latest pruned header.
     pub(super) async fn save_pruned_height(&mut self, height: u64) -> anyhow::Result<()> {
         // id is set to 1 so that there is only one row in the table.
@@ -15,6 +15,7 @@ pub(crate) mod data_source;
 pub(crate) mod errors;
 pub(crate) mod monetary_value;
 pub(crate) mod query_data;
+pub(crate) mod stats;
 pub(crate) mod traits;
 
 use std::{fmt::Display, num::NonZeroUsize, path::Path};
@@ -26,6 +27,7 @@ use hotshot_types::traits::node_implementation::NodeType;
 pub use monetary_value::*;
 pub use query_data::*;
 use serde::{Deserialize, Serialize};
+pub use stats::update_explorer_stats_loop;
 use tide_disco::{api::ApiError, method::ReadState, Api, StatusCode};
 pub use traits::*;
 use vbs::version::StaticVersionType;
@@ -47,6 +49,7 @@ pub enum Error {
     GetTransactionDetail(GetTransactionDetailError),
     GetTransactionSummaries(GetTransactionSummariesError),
     GetExplorerSummary(GetExplorerSummaryError),
+    GetExplorerStats(GetExplorerStatsError),
     GetSearchResults(GetSearchResultsError),
 }
 
@@ -58,6 +61,7 @@ impl Error {
             Error::GetTransactionDetail(e) => e.status(),
             Error::GetTransactionSummaries(e) => e.status(),
             Error::GetExplorerSummary(e) => e.status(),
+            Error::GetExplorerStats(e) => e.status(),
             Error::GetSearchResults(e) => e.status(),
         }
     }
@@ -71,6 +75,7 @@ impl Display for Error {
             Error::GetTransactionDetail(e) => e.fmt(f),
             Error::GetTransactionSummaries(e) => e.fmt(f),
             Error::GetExplorerSummary(e) => e.fmt(f),
+            Error::GetExplorerStats(e) => e.fmt(f),
             Error::GetSearchResults(e) => e.fmt(f),
         }
     }
@@ -84,6 +89,7 @@ impl std::error::Error for Error {
             Error::GetTransactionDetail(e) => Some(e),
             Error::GetTransactionSummaries(e) => Some(e),
             Error::GetExplorerSummary(e) => Some(e),
+            Error::GetExplorerStats(e) => Some(e),
             Error::GetSearchResults(e) => Some(e),
         }
     }
@@ -191,6 +197,26 @@ where
     }
 }
 
+/// [ExplorerStatsResponse] is a struct that represents the response from the
+/// `get_explorer_stats` endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ExplorerStatsResponse<Types: NodeType>
+where
+    Transaction<Types>: ExplorerTransaction,
+{
+    pub explorer_stats: ExplorerStats<Types>,
+}
+
+impl<Types: NodeType> From<ExplorerStats<Types>> for ExplorerStatsResponse<Types>
+where
+    Transaction<Types>: ExplorerTransaction,
+{
+    fn from(explorer_stats: ExplorerStats<Types>) -> Self {
+        Self { explorer_stats }
+    }
+}
+
 /// [SearchResultResponse] is a struct that represents the response from the
 /// `get_search_result` endpoint.
 #[derive(Debug, Serialize, Deserialize)]
@@ -233,6 +259,10 @@ fn validate_limit(
     Ok(num_blocks)
 }
 
+/// The window over which explorer statistics are computed, in seconds, if the
+/// client does not request a specific window.
+const DEFAULT_STATS_WINDOW: u64 = 60 * 60;
+
 /// `define_api` is a function that defines the API endpoints for the Explorer
 /// module of the HotShot Query Service. It implements the specification
 /// defined in the `explorer.toml` file.
@@ -371,6 +401,26 @@ where
             }
             .boxed()
         })?
+        .get("get_explorer_stats", move |req, state| {
+            async move {
+                let window = match req.opt_integer_param::<str, u64>("window") {
+                    Ok(Some(0)) | Err(_) => {
+                        return Err(Error::GetExplorerStats(
+                            GetExplorerStatsError::InvalidQuery(errors::BadQuery {}),
+                        ))
+                    },
+                    Ok(Some(window)) => window,
+                    Ok(None) => DEFAULT_STATS_WINDOW,
+                };
+
+                state
+                    .get_explorer_stats(window)
+                    .await
+                    .map(ExplorerStatsResponse::from)
+                    .map_err(Error::GetExplorerStats)
+            }
+            .boxed()
+        })?
         .get("get_search_result", move |req, state| {
             async move {
                 let query = req
@@ -394,12 +444,13 @@ where
 
 #[cfg(test)]
 mod test {
-    use std::{cmp::min, time::Duration};
+    use std::{cmp::min, sync::Arc, time::Duration};
 
     use futures::StreamExt;
     use portpicker::pick_unused_port;
     use surf_disco::Client;
     use tide_disco::App;
+    use tokio::time::sleep;
 
     use super::*;
     use crate::{
@@ -409,6 +460,7 @@ mod test {
             mocks::{mock_transaction, MockBase, MockTypes, MockVersions},
             setup_test,
         },
+        types::HeightIndexed,
         ApiState, Error,
     };
 
@@ -853,6 +905,50 @@ mod test {
         test_api_helper().await;
     }
 
+    async fn validate_stats(client: &Client<Error, MockBase>, height: u64) {
+        // Statistics are updated asynchronously, so wait for them to catch up.
+        let stats = loop {
+            let stats = client
+                .get::<ExplorerStatsResponse<MockTypes>>("stats")
+                .send()
+                .await
+                .unwrap()
+                .explorer_stats;
+            if stats.block_height >= height {
+                break stats;
+            }
+            sleep(Duration::from_millis(100)).await;
+        };
+
+        assert!(stats.total_transactions > 0);
+        assert!(stats.total_payload_size > 0);
+        assert!(stats.total_empty_blocks <= stats.block_height + 1);
+        assert!(stats.window_blocks <= stats.block_height);
+        assert!((0.0..=1.0).contains(&stats.empty_block_ratio));
+
+        // All mock transactions belong to the same namespace.
+        assert_eq!(stats.namespaces.len(), 1);
+        assert_eq!(
+            stats.namespaces[0].num_transactions,
+            stats.total_transactions
+        );
+
+        // A window covering the whole chain includes every block but the first.
+        let all: ExplorerStatsResponse<MockTypes> =
+            client.get("stats/1000000").send().await.unwrap();
+        assert_eq!(
+            all.explorer_stats.window_blocks,
+            all.explorer_stats.block_height
+        );
+
+        // An empty window is rejected.
+        client
+            .get::<ExplorerStatsResponse<MockTypes>>("stats/0")
+            .send()
+            .await
+            .unwrap_err();
+    }
+
     fn num_blocks() -> usize {
         10
     }
@@ -891,6 +987,10 @@ mod test {
             "server",
             app.serve(format!("0.0.0.0:{}", port), MockBase::instance()),
         );
+        network.spawn(
+            "explorer statistics",
+            update_explorer_stats_loop(Arc::new(network.data_source())),
+        );
 
         // Start a client.
         let availability_client = Client::<Error, MockBase>::new(
@@ -918,6 +1018,7 @@ mod test {
 
         let n_blocks = num_blocks();
         let n_txns = num_txns_per_block();
+        let mut last_height = 0;
         for b in 0..n_blocks {
             for t in 0..n_txns {
                 let nonce = b * n_txns + t;
@@ -930,6 +1031,7 @@ mod test {
             for _ in 0..10 {
                 let block = blocks.next().await.unwrap();
                 let block = block.unwrap();
+                last_height = block.height();
 
                 if !block.is_empty() {
                     break;
@@ -941,6 +1043,7 @@ mod test {
 
         // sleep a little bit to give some chance for blocks to be generated.
         validate(&explorer_client).await;
+        validate_stats(&explorer_client, last_height).await;
         network.shut_down().await;
     }
 }
@@ -16,11 +16,11 @@ use tagged_base64::TaggedBase64;
 
 use super::{
     query_data::{
-        BlockDetail, BlockIdentifier, BlockSummary, ExplorerSummary, GetBlockDetailError,
-        GetBlockSummariesError, GetBlockSummariesRequest, GetExplorerSummaryError,
-        GetSearchResultsError, GetTransactionDetailError, GetTransactionSummariesError,
-        GetTransactionSummariesRequest, SearchResult, TransactionDetailResponse,
-        TransactionIdentifier, TransactionSummary,
+        BlockDetail, BlockIdentifier, BlockSummary, ExplorerStats, ExplorerSummary,
+        GetBlockDetailError, GetBlockSummariesError, GetBlockSummariesRequest,
+        GetExplorerStatsError, GetExplorerSummaryError, GetSearchResultsError,
+        GetTransactionDetailError, GetTransactionSummariesError, GetTransactionSummariesRequest,
+        SearchResult, TransactionDetailResponse, TransactionIdentifier, TransactionSummary,
     },
     traits::{ExplorerHeader, ExplorerTransaction},
 };
@@ -84,6 +84,14 @@ where
     async fn get_explorer_summary(&self)
         -> Result<ExplorerSummary<Types>, GetExplorerSummaryError>;
 
+    /// `get_explorer_stats` is a method that retrieves aggregate statistics
+    /// about the blockchain, such as throughput over the last `window`
+    /// seconds and transaction volume per namespace.
+    async fn get_explorer_stats(
+        &self,
+        window: u64,
+    ) -> Result<ExplorerStats<Types>, GetExplorerStatsError>;
+
     /// `get_search_results` is a method that retrieves the results of a search
     /// query against the blockchain.  The results are generated from the given
     /// query string.
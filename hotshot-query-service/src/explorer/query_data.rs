@@ -502,6 +502,51 @@ where
     pub histograms: ExplorerHistograms,
 }
 
+/// [NamespaceStats] represents the total volume of transactions that have
+/// been sequenced in a single namespace.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(bound = "")]
+pub struct NamespaceStats<Types: NodeType>
+where
+    Transaction<Types>: ExplorerTransaction,
+{
+    pub namespace: TransactionNamespaceId<Types>,
+    pub num_transactions: u64,
+    pub payload_size: u64,
+}
+
+/// [ExplorerStats] provides aggregate statistics about the block chain, both
+/// over a recent window of time and in total.
+///
+/// These statistics are materialized incrementally as blocks are decided, so
+/// retrieving them does not require scanning the chain.  The totals include
+/// every block that has been processed since statistics were first collected,
+/// which excludes any blocks that had already been pruned at that time.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ExplorerStats<Types: NodeType>
+where
+    Transaction<Types>: ExplorerTransaction,
+{
+    /// The height of the latest block included in these statistics.
+    pub block_height: u64,
+    /// The requested length of the window, in seconds.
+    pub window: u64,
+    /// The number of blocks in the window.
+    pub window_blocks: u64,
+    pub transactions_per_second: f64,
+    pub bytes_per_second: f64,
+    /// The fraction of blocks in the window which contain no transactions.
+    pub empty_block_ratio: f64,
+    pub window_fees: Vec<MonetaryValue>,
+    pub total_transactions: u64,
+    pub total_payload_size: u64,
+    pub total_empty_blocks: u64,
+    pub total_fees: Vec<MonetaryValue>,
+    /// The total volume of each namespace, ordered from most to least active.
+    pub namespaces: Vec<NamespaceStats<Types>>,
+}
+
 /// [SearchResult] is a struct that represents the results of executing a
 /// search query against the chain.  It contains a list of blocks and
 /// transactions that match the search query.
@@ -846,6 +891,62 @@ impl From<GetTransactionSummariesError> for GetExplorerSummaryError {
     }
 }
 
+/// [GetExplorerStatsError] represents an error that has occurred in response
+/// to the `get_explorer_stats` request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GetExplorerStatsError {
+    Unimplemented(Unimplemented),
+    QueryError(QueryError),
+    InvalidQuery(BadQuery),
+}
+
+impl GetExplorerStatsError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            GetExplorerStatsError::QueryError(err) => err.status(),
+            GetExplorerStatsError::Unimplemented(err) => err.status(),
+            GetExplorerStatsError::InvalidQuery(err) => err.status(),
+        }
+    }
+}
+
+impl Display for GetExplorerStatsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GetExplorerStatsError::QueryError(err) => write!(f, "{err}"),
+            GetExplorerStatsError::Unimplemented(err) => write!(f, "{err}"),
+            GetExplorerStatsError::InvalidQuery(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl ExplorerAPIError for GetExplorerStatsError {
+    fn code(&self) -> &str {
+        match self {
+            GetExplorerStatsError::QueryError(err) => err.code(),
+            GetExplorerStatsError::Unimplemented(err) => err.code(),
+            GetExplorerStatsError::InvalidQuery(err) => err.code(),
+        }
+    }
+}
+
+impl std::error::Error for GetExplorerStatsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GetExplorerStatsError::Unimplemented(err) => Some(err),
+            GetExplorerStatsError::QueryError(err) => Some(err),
+            GetExplorerStatsError::InvalidQuery(err) => Some(err),
+        }
+    }
+}
+
+impl From<crate::QueryError> for GetExplorerStatsError {
+    fn from(value: crate::QueryError) -> Self {
+        GetExplorerStatsError::QueryError(QueryError { error: value })
+    }
+}
+
 /// [GetSearchResultsError] represents an error that has occurred in response
 /// to the `get_search_results` request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
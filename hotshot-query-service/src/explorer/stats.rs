@@ -0,0 +1,100 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+//! Incremental aggregation of block explorer statistics.
+//!
+//! Explorers want to display statistics such as throughput and per-namespace volume, which are
+//! expensive to compute from scratch. Instead, we follow the stream of decided blocks and fold each
+//! new block into cumulative totals in storage, so that statistics over any window of time can be
+//! read back as the difference between two stored totals.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context;
+use futures::stream::StreamExt;
+use hotshot_types::traits::node_implementation::NodeType;
+use tokio::time::sleep;
+
+use super::{
+    monetary_value::MonetaryValue,
+    query_data::BalanceAmount,
+    traits::{ExplorerHeader, ExplorerTransaction},
+};
+use crate::{
+    availability::{AvailabilityDataSource, QueryableHeader, QueryablePayload},
+    data_source::{storage::UpdateExplorerStatsStorage, Transaction as _, VersionedDataSource},
+    types::HeightIndexed,
+    Header, Payload, Transaction,
+};
+
+/// The maximum number of blocks to fold into the statistics in a single database transaction.
+const CHUNK_SIZE: usize = 100;
+
+/// Keep explorer statistics up to date as new blocks are decided.
+///
+/// This task resumes from wherever the statistics in `data_source` left off, and then runs forever,
+/// updating the statistics as each new block becomes available. It should be spawned in the
+/// background alongside the explorer API.
+pub async fn update_explorer_stats_loop<Types, D>(data_source: Arc<D>)
+where
+    Types: NodeType,
+    Header<Types>: ExplorerHeader<Types> + QueryableHeader<Types>,
+    Transaction<Types>: ExplorerTransaction,
+    Payload<Types>: QueryablePayload<Types>,
+    BalanceAmount<Types>: Into<MonetaryValue>,
+    D: AvailabilityDataSource<Types> + VersionedDataSource + 'static,
+    for<'a> D::Transaction<'a>: UpdateExplorerStatsStorage<Types>,
+{
+    let start = loop {
+        let res = async {
+            let mut tx = data_source.write().await.context("opening transaction")?;
+            tx.explorer_stats_height().await
+        }
+        .await;
+        match res {
+            Ok(height) => break height,
+            Err(err) => {
+                tracing::warn!("unable to load explorer statistics height: {err:#}");
+                sleep(Duration::from_secs(5)).await;
+            },
+        }
+    };
+    tracing::info!(start, "updating explorer statistics");
+
+    let mut blocks = data_source
+        .subscribe_blocks(start)
+        .await
+        .ready_chunks(CHUNK_SIZE);
+    while let Some(chunk) = blocks.next().await {
+        let Some(last) = chunk.last() else {
+            continue;
+        };
+        let height = last.height();
+        loop {
+            let res = async {
+                let mut tx = data_source.write().await.context("opening transaction")?;
+                tx.update_explorer_stats(&chunk).await?;
+                tx.commit().await.context("committing transaction")
+            }
+            .await;
+            match res {
+                Ok(()) => break,
+                Err(err) => {
+                    tracing::warn!(height, "failed to update explorer statistics: {err:#}");
+                    sleep(Duration::from_secs(1)).await;
+                },
+            }
+        }
+        tracing::debug!(height, "updated explorer statistics");
+    }
+    tracing::warn!("block stream ended; explorer statistics will no longer be updated");
+}
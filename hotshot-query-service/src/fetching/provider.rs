@@ -29,6 +29,9 @@
 //! data availability provider, as well as various implementations for different data sources,
 //! including:
 //! * [`QueryServiceProvider`]
+//! * [`VidReconstructionProvider`]
+//!
+//! [`DaPayloadFetcher`] adapts any [`Provider`] of payloads to serve DA payload hints in HotShot.
 //!
 //! We also provide combinators for modularly adding functionality to existing fetchers:
 //! * [`AnyProvider`]
@@ -42,13 +45,18 @@ use async_trait::async_trait;
 use super::Request;
 
 mod any;
+mod da_payload;
 mod query_service;
 mod testing;
+mod vid_reconstruction;
 
 pub use any::AnyProvider;
+pub use da_payload::DaPayloadFetcher;
 pub use query_service::QueryServiceProvider;
 #[cfg(any(test, feature = "testing"))]
 pub use testing::TestProvider;
+pub use vid_reconstruction::VidReconstructionProvider;
+pub(crate) use vid_reconstruction::VidShares;
 
 /// A provider which is able to satisfy requests for data of type `T`.
 ///
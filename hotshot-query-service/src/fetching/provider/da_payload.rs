@@ -0,0 +1,53 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use hotshot_types::{
+    data::DaPayloadHint2,
+    traits::{da_payload_provider::DaPayloadProvider, node_implementation::NodeType, EncodeBytes},
+};
+
+use super::Provider;
+use crate::fetching::request::PayloadRequest;
+
+/// Adaptor serving DA payload hints from a data availability [`Provider`].
+///
+/// HotShot DA committee members which receive a payload hint instead of a full DA proposal use this
+/// to fetch the announced payload by its VID commitment. The payload is checked against the hash
+/// signed by the DA leader before it is used, so any provider can be plugged in.
+#[derive(Clone, Debug)]
+pub struct DaPayloadFetcher<P> {
+    provider: P,
+}
+
+impl<P> DaPayloadFetcher<P> {
+    pub fn new(provider: P) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<Types, P> DaPayloadProvider<Types> for DaPayloadFetcher<P>
+where
+    Types: NodeType,
+    P: Provider<Types, PayloadRequest> + 'static,
+{
+    async fn fetch_payload(&self, hint: &DaPayloadHint2<Types>) -> Option<Arc<[u8]>> {
+        let payload = self
+            .provider
+            .fetch(PayloadRequest(hint.payload_commitment))
+            .await?;
+        Some(payload.encode())
+    }
+}
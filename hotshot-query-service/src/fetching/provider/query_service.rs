@@ -393,6 +393,7 @@ mod test {
             TransactionQueryData, UpdateAvailabilityData,
         },
         data_source::{
+            fetching::BackfillCfg,
             sql::{self, SqlDataSource},
             storage::{
                 fail_storage::{FailStorage, FailableAction},
@@ -1828,6 +1829,83 @@ mod test {
         assert_eq!(vid.block_hash(), leaf.block_hash());
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_archival_backfill() {
+        setup_test();
+
+        // Create the consensus network.
+        let mut network = MockNetwork::<MockDataSource, MockVersions>::init().await;
+
+        // Start a web server that the non-consensus node can use to fetch blocks.
+        let port = pick_unused_port().unwrap();
+        let mut app = App::<_, Error>::with_state(ApiState::from(network.data_source()));
+        app.register_module(
+            "availability",
+            define_api(
+                &Default::default(),
+                MockBase::instance(),
+                "1.0.0".parse().unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        network.spawn(
+            "server",
+            app.serve(format!("0.0.0.0:{port}"), MockBase::instance()),
+        );
+
+        // Start consensus and wait until a few blocks are produced.
+        network.start().await;
+        let leaves = network.data_source().subscribe_leaves(1).await;
+        let leaves = leaves.take(5).collect::<Vec<_>>().await;
+        let last_leaf = leaves.last().unwrap();
+
+        // Give a data source which is not receiving events from consensus only the last leaf, so
+        // that it knows the block height but is missing all the history.
+        let db = TmpDb::init().await;
+        let provider = Provider::new(QueryServiceProvider::new(
+            format!("http://localhost:{port}").parse().unwrap(),
+            MockBase::instance(),
+        ));
+        {
+            let data_source = data_source(&db, &provider).await;
+            data_source.append(last_leaf.clone().into()).await.unwrap();
+        }
+
+        // Restart with archival backfill enabled.
+        let data_source = builder(&db, &provider)
+            .await
+            .with_archival_backfill(BackfillCfg::new().with_rate_limit(0))
+            .build()
+            .await
+            .unwrap();
+        let status = loop {
+            let status = data_source.backfill_status().await.unwrap().unwrap();
+            if status.complete {
+                break status;
+            }
+            tracing::info!(?status, "waiting for backfill");
+            sleep(Duration::from_secs(1)).await;
+        };
+        assert_eq!(status.end, last_leaf.height() + 1);
+        assert_eq!(status.failed, 0);
+
+        // Read the backfilled data directly from storage, so that this does not trigger a fetch.
+        let mut tx = data_source.read().await.unwrap();
+        for leaf in &leaves {
+            let id = BlockId::<MockTypes>::from(leaf.height() as usize);
+            assert_eq!(
+                tx.get_leaf((leaf.height() as usize).into()).await.unwrap(),
+                *leaf
+            );
+            assert_eq!(tx.get_block(id).await.unwrap().hash(), leaf.block_hash());
+            assert_eq!(
+                tx.get_vid_common(id).await.unwrap().block_hash(),
+                leaf.block_hash()
+            );
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_fetch_begin_failure() {
         setup_test();
@@ -0,0 +1,380 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use hotshot_types::{
+    data::{ns_table, VidCommitment, VidShare},
+    traits::{
+        block_contents::BlockHeader, node_implementation::NodeType, BlockPayload, EncodeBytes,
+    },
+    vid::{
+        advz::{advz_scheme, ADVZScheme, ADVZShare},
+        avidm::{AvidMScheme, AvidMShare},
+    },
+};
+use jf_vid::VidScheme;
+use surf_disco::{Client, Url};
+use vbs::version::StaticVersionType;
+
+use super::Provider;
+use crate::{
+    availability::VidCommonQueryData, fetching::request::PayloadRequest, Error, Header, Payload,
+    VidCommon,
+};
+
+/// Data availability provider which reconstructs payloads from the VID shares of its peers.
+///
+/// Every node keeps its own VID share of each block, even if it does not store the full payload.
+/// This provider requests the shares for a missing payload from a set of peer query services,
+/// verifying each against the payload commitment, until it has enough to recover the payload. The
+/// recovered payload is checked against the commitment before it is returned, so peers need not be
+/// trusted. It is an alternative to [`QueryServiceProvider`](super::QueryServiceProvider) when no
+/// peer has stored the full block.
+///
+/// The namespace table needed to interpret the recovered bytes comes from a peer's header, which
+/// this provider cannot authenticate. The fetcher therefore only keeps the bytes of the payload,
+/// and reads them with the metadata of the header it already trusts.
+#[derive(Clone, Debug)]
+pub struct VidReconstructionProvider<Ver: StaticVersionType> {
+    peers: Vec<Client<Error, Ver>>,
+}
+
+impl<Ver: StaticVersionType> VidReconstructionProvider<Ver> {
+    pub fn new(peers: impl IntoIterator<Item = Url>, _: Ver) -> Self {
+        Self {
+            peers: peers.into_iter().map(Client::new).collect(),
+        }
+    }
+
+    /// Fetch the header of the block with payload commitment `commit` from any peer.
+    async fn fetch_header<Types: NodeType>(&self, commit: VidCommitment) -> Option<Header<Types>> {
+        for peer in &self.peers {
+            match peer
+                .get::<Header<Types>>(&format!("availability/header/payload-hash/{commit}"))
+                .send()
+                .await
+            {
+                Ok(header) if header.payload_commitment() == commit => return Some(header),
+                Ok(header) => {
+                    tracing::warn!(%commit, ?header, "peer returned header for wrong payload");
+                },
+                Err(err) => tracing::debug!(%commit, "failed to fetch header from peer: {err}"),
+            }
+        }
+        None
+    }
+
+    /// Fetch the VID common data for payload commitment `commit` from any peer.
+    async fn fetch_common<Types: NodeType>(&self, commit: VidCommitment) -> Option<VidCommon> {
+        for peer in &self.peers {
+            match peer
+                .get::<VidCommonQueryData<Types>>(&format!(
+                    "availability/vid/common/payload-hash/{commit}"
+                ))
+                .send()
+                .await
+            {
+                Ok(common) => {
+                    let consistent = match (common.common(), commit) {
+                        (VidCommon::V0(common), VidCommitment::V0(commit)) => {
+                            ADVZScheme::is_consistent(&commit, common).is_ok()
+                        },
+                        (VidCommon::V1(_), VidCommitment::V1(_)) => true,
+                        _ => false,
+                    };
+                    if consistent {
+                        return Some(common.common().clone());
+                    }
+                    tracing::warn!(%commit, "peer returned inconsistent VID common data");
+                },
+                Err(err) => {
+                    tracing::debug!(%commit, "failed to fetch VID common from peer: {err}")
+                },
+            }
+        }
+        None
+    }
+
+    /// Recover the payload with commitment `commit` from the shares of as many peers as needed.
+    async fn reconstruct(
+        &self,
+        commit: VidCommitment,
+        common: &VidCommon,
+        ns_table: &[u8],
+    ) -> Option<Vec<u8>> {
+        let mut responses = self
+            .peers
+            .iter()
+            .map(|peer| async move {
+                peer.get::<VidShare>(&format!("node/vid/share/payload-hash/{commit}"))
+                    .send()
+                    .await
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut shares = VidShares::default();
+        while let Some(res) = responses.next().await {
+            let share = match res {
+                Ok(share) => share,
+                Err(err) => {
+                    tracing::debug!(%commit, "failed to fetch VID share from peer: {err}");
+                    continue;
+                },
+            };
+            match shares.add(share, common, commit) {
+                Some(true) => {},
+                Some(false) => {
+                    tracing::debug!(%commit, "peer returned a VID share we already have");
+                    continue;
+                },
+                None => {
+                    tracing::warn!(%commit, "peer returned invalid VID share");
+                    continue;
+                },
+            }
+            // Recovery fails cheaply until there are enough shares.
+            if let Some(payload) = shares.recover(common, commit, ns_table) {
+                return Some(payload);
+            }
+        }
+        tracing::warn!(
+            %commit,
+            shares = shares.len(),
+            "not enough VID shares to reconstruct payload"
+        );
+        None
+    }
+}
+
+/// Verified VID shares collected for a single payload.
+#[derive(Default)]
+pub(crate) struct VidShares {
+    advz: Vec<ADVZShare>,
+    avidm: Vec<AvidMShare>,
+}
+
+impl VidShares {
+    pub(crate) fn len(&self) -> usize {
+        self.advz.len() + self.avidm.len()
+    }
+
+    /// Add `share` if it is valid for `commit`.
+    ///
+    /// Returns whether the share was new, or `None` if it is invalid. Recovery fails if the same
+    /// share is given twice, so a share already collected, say from a peer sharing its storage with
+    /// another, is not added again.
+    pub(crate) fn add(
+        &mut self,
+        share: VidShare,
+        common: &VidCommon,
+        commit: VidCommitment,
+    ) -> Option<bool> {
+        match (share, common, commit) {
+            (VidShare::V0(share), VidCommon::V0(common), VidCommitment::V0(commit)) => {
+                let num_storage_nodes = ADVZScheme::get_num_storage_nodes(common) as usize;
+                if !matches!(
+                    advz_scheme(num_storage_nodes).verify_share(&share, common, &commit),
+                    Ok(Ok(()))
+                ) {
+                    return None;
+                }
+                // A valid share is determined by its index, so equal shares have equal indices.
+                if self.advz.contains(&share) {
+                    return Some(false);
+                }
+                self.advz.push(share);
+                Some(true)
+            },
+            (VidShare::V1(share), VidCommon::V1(param), VidCommitment::V1(commit)) => {
+                if !matches!(
+                    AvidMScheme::verify_share(param, &commit, &share),
+                    Ok(Ok(()))
+                ) {
+                    return None;
+                }
+                // The index of a share is the range of evaluations it holds, the same in every
+                // namespace.
+                if self
+                    .avidm
+                    .iter()
+                    .any(|collected| collected.ns_ranges().eq(share.ns_ranges()))
+                {
+                    return Some(false);
+                }
+                self.avidm.push(share);
+                Some(true)
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the shares collected so far carry enough weight to recover the payload.
+    ///
+    /// AVID-M shares are weighted by stake, so this checks that the distinct parts of the encoded
+    /// payload they hold reach the recovery threshold in every namespace. ADVZ shares carry no
+    /// weight, so for them the only way to tell is to attempt the recovery.
+    pub(crate) fn sufficient(&self, common: &VidCommon) -> bool {
+        match common {
+            VidCommon::V0(common) => {
+                let num_storage_nodes = ADVZScheme::get_num_storage_nodes(common) as usize;
+                advz_scheme(num_storage_nodes)
+                    .recover_payload(&self.advz, common)
+                    .is_ok()
+            },
+            VidCommon::V1(param) => {
+                let Some(first) = self.avidm.first() else {
+                    return false;
+                };
+                let mut held = vec![HashSet::new(); first.ns_ranges().count()];
+                for share in &self.avidm {
+                    for (held, range) in held.iter_mut().zip(share.ns_ranges()) {
+                        held.extend(range);
+                    }
+                }
+                held.iter()
+                    .all(|held| held.len() >= param.recovery_threshold)
+            },
+        }
+    }
+
+    /// Recover the payload from the shares collected so far, if there are enough of them and the
+    /// recovered payload matches `commit`.
+    fn recover(
+        &self,
+        common: &VidCommon,
+        commit: VidCommitment,
+        ns_table: &[u8],
+    ) -> Option<Vec<u8>> {
+        let (payload, recomputed) = match common {
+            VidCommon::V0(common) => {
+                let num_storage_nodes = ADVZScheme::get_num_storage_nodes(common) as usize;
+                let mut scheme = advz_scheme(num_storage_nodes);
+                let payload = scheme.recover_payload(&self.advz, common).ok()?;
+                let recomputed = VidCommitment::V0(scheme.commit_only(&payload).ok()?);
+                (payload, recomputed)
+            },
+            VidCommon::V1(param) => {
+                let payload = AvidMScheme::recover(param, &self.avidm).ok()?;
+                let recomputed = VidCommitment::V1(
+                    AvidMScheme::commit(
+                        param,
+                        &payload,
+                        ns_table::parse_ns_table(payload.len(), ns_table),
+                    )
+                    .ok()?,
+                );
+                (payload, recomputed)
+            },
+        };
+        if recomputed != commit {
+            tracing::error!(%commit, %recomputed, "reconstructed payload is inconsistent");
+            return None;
+        }
+        Some(payload)
+    }
+}
+
+#[async_trait]
+impl<Types, Ver: StaticVersionType> Provider<Types, PayloadRequest>
+    for VidReconstructionProvider<Ver>
+where
+    Types: NodeType,
+{
+    async fn fetch(&self, req: PayloadRequest) -> Option<Payload<Types>> {
+        let commit = req.0;
+        // The header gives us the metadata needed to interpret the recovered bytes as a payload.
+        let header = self.fetch_header::<Types>(commit).await?;
+        let common = self.fetch_common::<Types>(commit).await?;
+        let metadata = header.metadata();
+        let bytes = self
+            .reconstruct(commit, &common, &metadata.encode())
+            .await?;
+        tracing::info!(%commit, "reconstructed payload from VID shares");
+        Some(<Types::BlockPayload as BlockPayload<Types>>::from_bytes(
+            &bytes, metadata,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::{
+        data::ns_table::parse_ns_table,
+        vid::avidm::{init_avidm_param, AvidMScheme},
+    };
+
+    use super::*;
+
+    /// A namespace table with namespaces ending at each of `ends`
+    fn ns_table(ends: &[u32]) -> Vec<u8> {
+        let mut bytes = (ends.len() as u32).to_le_bytes().to_vec();
+        for (id, end) in ends.iter().enumerate() {
+            bytes.extend((id as u32).to_le_bytes());
+            bytes.extend(end.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_recover_avidm() {
+        let param = init_avidm_param(9).unwrap();
+        let payload = (0..48u8).collect::<Vec<_>>();
+        let table = ns_table(&[15, 48]);
+        let disperse = |payload: &[u8]| {
+            AvidMScheme::ns_disperse(
+                &param,
+                &[1; 9],
+                payload,
+                parse_ns_table(payload.len(), &table),
+            )
+            .unwrap()
+        };
+        let (commit, shares) = disperse(&payload);
+        let commit = VidCommitment::V1(commit);
+        let common = VidCommon::V1(param.clone());
+
+        let mut collected = VidShares::default();
+        assert_eq!(
+            collected.add(VidShare::V1(shares[0].clone()), &common, commit),
+            Some(true)
+        );
+        // The same share is only counted once.
+        assert_eq!(
+            collected.add(VidShare::V1(shares[0].clone()), &common, commit),
+            Some(false)
+        );
+        // A share of another payload is rejected.
+        let (_, other) = disperse(&[1; 48]);
+        assert_eq!(
+            collected.add(VidShare::V1(other[1].clone()), &common, commit),
+            None
+        );
+        assert_eq!(collected.len(), 1);
+
+        // Recovery needs a third of the shares.
+        collected.add(VidShare::V1(shares[1].clone()), &common, commit);
+        assert!(!collected.sufficient(&common));
+        assert_eq!(collected.recover(&common, commit, &table), None);
+        collected.add(VidShare::V1(shares[2].clone()), &common, commit);
+        assert!(collected.sufficient(&common));
+        assert_eq!(
+            collected.recover(&common, commit, &table),
+            Some(payload.clone())
+        );
+
+        // The recovered payload must match the commitment under the header's namespace table.
+        assert_eq!(collected.recover(&common, commit, &ns_table(&[48])), None);
+    }
+}
@@ -14,14 +14,16 @@
 
 use std::{
     collections::HashMap,
+    fmt::{self, Display, Formatter, Write},
     sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use hotshot_types::traits::metrics;
 use itertools::Itertools;
 use prometheus::{
     core::{AtomicU64, GenericCounter, GenericCounterVec, GenericGauge, GenericGaugeVec},
-    Encoder, HistogramVec, Opts, Registry, TextEncoder,
+    Encoder, HistogramOpts, HistogramVec, Opts, Registry, TextEncoder, DEFAULT_BUCKETS,
 };
 use snafu::Snafu;
 
@@ -75,9 +77,31 @@ pub struct PrometheusMetrics {
     counter_families: Arc<RwLock<HashMap<String, CounterFamily>>>,
     gauge_families: Arc<RwLock<HashMap<String, GaugeFamily>>>,
     histogram_families: Arc<RwLock<HashMap<String, HistogramFamily>>>,
+    exemplars: ExemplarRegistry,
 }
 
 impl PrometheusMetrics {
+    /// Export all metrics, with the latest exemplar in each histogram bucket.
+    ///
+    /// This is the same as [export](tide_disco::metrics::Metrics::export), except that each
+    /// histogram bucket line which has an exemplar is annotated with it in OpenMetrics syntax, e.g.
+    /// `latency_bucket{le="5"} 2 # {view="2"} 4 1700000000.5`. The classic Prometheus text format
+    /// does not allow anything after the sample value, so this output is only suitable for
+    /// consumers which understand exemplars.
+    pub fn export_with_exemplars(&self) -> Result<String, MetricsError> {
+        let text = tide_disco::metrics::Metrics::export(self)?;
+        let exemplars = self.exemplars.read().unwrap();
+        let mut output = String::with_capacity(text.len());
+        for line in text.lines() {
+            output.push_str(line);
+            if let Some(exemplar) = bucket_exemplar(line, &exemplars) {
+                write!(output, " # {exemplar}").unwrap();
+            }
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
     /// Get a counter in this sub-group by name.
     pub fn get_counter(&self, name: &str) -> Result<Counter, MetricsError> {
         self.get_metric(&self.counters, name)
@@ -202,7 +226,21 @@ impl metrics::Metrics for PrometheusMetrics {
         name: String,
         unit_label: Option<String>,
     ) -> Box<dyn metrics::Histogram> {
-        let histogram = Histogram::new(&self.metrics, self.metric_opts(name.clone(), unit_label));
+        self.create_histogram_with_buckets(name, unit_label, DEFAULT_BUCKETS.to_vec())
+    }
+
+    fn create_histogram_with_buckets(
+        &self,
+        name: String,
+        unit_label: Option<String>,
+        buckets: Vec<f64>,
+    ) -> Box<dyn metrics::Histogram> {
+        let histogram = Histogram::new(
+            &self.metrics,
+            &self.exemplars,
+            self.metric_opts(name.clone(), unit_label),
+            buckets,
+        );
         self.histograms
             .write()
             .unwrap()
@@ -238,8 +276,22 @@ impl metrics::Metrics for PrometheusMetrics {
         name: String,
         labels: Vec<String>,
     ) -> Box<dyn metrics::HistogramFamily> {
-        let family =
-            HistogramFamily::new(&self.metrics, self.metric_opts(name.clone(), None), &labels);
+        self.histogram_family_with_buckets(name, labels, DEFAULT_BUCKETS.to_vec())
+    }
+
+    fn histogram_family_with_buckets(
+        &self,
+        name: String,
+        labels: Vec<String>,
+        buckets: Vec<f64>,
+    ) -> Box<dyn metrics::HistogramFamily> {
+        let family = HistogramFamily::new(
+            &self.metrics,
+            &self.exemplars,
+            self.metric_opts(name.clone(), None),
+            &labels,
+            buckets,
+        );
         self.histogram_families
             .write()
             .unwrap()
@@ -263,6 +315,7 @@ impl metrics::Metrics for PrometheusMetrics {
                 .entry(subgroup_name.clone())
                 .or_insert_with(|| Self {
                     metrics: self.metrics.clone(),
+                    exemplars: self.exemplars.clone(),
                     namespace: {
                         let mut namespace = self.namespace.clone();
                         namespace.push(subgroup_name);
@@ -327,33 +380,205 @@ impl metrics::Gauge for Gauge {
     }
 }
 
+/// An example of an observation recorded in a [Histogram].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exemplar {
+    /// Key-value pairs identifying the event which produced this observation.
+    pub labels: Vec<(String, String)>,
+    /// The observed value.
+    pub value: f64,
+    /// When the value was observed.
+    pub timestamp: SystemTime,
+}
+
+/// OpenMetrics limits the combined length of the label names and values of an exemplar.
+const MAX_EXEMPLAR_LABEL_CHARS: usize = 128;
+
+impl Display for Exemplar {
+    /// Format this exemplar in OpenMetrics syntax: `{labels} value timestamp`.
+    ///
+    /// Labels which would push the exemplar past the OpenMetrics length limit are left out, so that
+    /// the output stays parseable.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut chars = 0;
+        let mut labels = vec![];
+        for (key, value) in &self.labels {
+            chars += key.chars().count() + value.chars().count();
+            if chars > MAX_EXEMPLAR_LABEL_CHARS {
+                break;
+            }
+            labels.push(format!("{key}=\"{}\"", escape_label_value(value)));
+        }
+        let timestamp = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        write!(f, "{{{}}} {} {timestamp}", labels.join(","), self.value)
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Parse the labels of a sample in the Prometheus text format, e.g. `a="x",le="5"`.
+fn parse_labels(mut labels: &str) -> Option<Vec<(String, String)>> {
+    let mut parsed = vec![];
+    while !labels.is_empty() {
+        let (key, rest) = labels.split_once("=\"")?;
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next()? {
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (i, '"') => break i,
+                (_, c) => value.push(c),
+            }
+        };
+        parsed.push((key.to_string(), value));
+        let rest = &rest[end + 1..];
+        labels = rest.strip_prefix(',').unwrap_or(rest);
+    }
+    Some(parsed)
+}
+
+/// The exemplar to attach to an exported line, if it is a histogram bucket with an exemplar.
+fn bucket_exemplar(line: &str, exemplars: &HashMap<ExemplarKey, Exemplars>) -> Option<Exemplar> {
+    if line.starts_with('#') {
+        return None;
+    }
+    let (series, _) = line.rsplit_once(' ')?;
+    let (name, labels) = series.strip_suffix('}')?.split_once('{')?;
+    let name = name.strip_suffix("_bucket")?;
+    let mut labels = parse_labels(labels)?;
+    let le = labels.iter().position(|(key, _)| key == "le")?;
+    let bound = labels.remove(le).1.parse().ok()?;
+    labels.sort();
+    exemplars.get(&(name.to_string(), labels))?.in_bucket(bound)
+}
+
+/// Identifies a histogram by its fully qualified name and its sorted labels.
+type ExemplarKey = (String, Vec<(String, String)>);
+
+/// The exemplars of every histogram in a tree of [PrometheusMetrics].
+type ExemplarRegistry = Arc<RwLock<HashMap<ExemplarKey, Exemplars>>>;
+
+/// The most recent [Exemplar] in each bucket of a histogram.
+///
+/// The Prometheus text format has no way to represent exemplars, so they are not included in
+/// [export](tide_disco::metrics::Metrics::export). They are rendered by
+/// [export_with_exemplars](PrometheusMetrics::export_with_exemplars), and can also be inspected at
+/// run-time to find the events behind outliers in a histogram.
+#[derive(Clone, Debug, Default)]
+struct Exemplars {
+    buckets: Arc<Vec<f64>>,
+    exemplars: Arc<RwLock<Vec<Option<Exemplar>>>>,
+}
+
+impl Exemplars {
+    fn new(buckets: Arc<Vec<f64>>) -> Self {
+        // One slot per bucket, plus the implicit `+Inf` bucket.
+        let exemplars = vec![None; buckets.len() + 1];
+        Self {
+            buckets,
+            exemplars: Arc::new(RwLock::new(exemplars)),
+        }
+    }
+
+    fn record(&self, value: f64, labels: Vec<(String, String)>) {
+        let bucket = self.buckets.partition_point(|&bound| bound < value);
+        self.exemplars.write().unwrap()[bucket] = Some(Exemplar {
+            labels,
+            value,
+            timestamp: SystemTime::now(),
+        });
+    }
+
+    fn in_bucket(&self, bound: f64) -> Option<Exemplar> {
+        let bucket = if bound == f64::INFINITY {
+            self.buckets.len()
+        } else {
+            self.buckets.iter().position(|&b| b == bound)?
+        };
+        self.exemplars.read().unwrap()[bucket].clone()
+    }
+
+    fn get(&self) -> Vec<(f64, Exemplar)> {
+        self.buckets
+            .iter()
+            .copied()
+            .chain([f64::INFINITY])
+            .zip(self.exemplars.read().unwrap().iter())
+            .filter_map(|(bound, exemplar)| Some((bound, exemplar.clone()?)))
+            .collect()
+    }
+}
+
 /// A [Histogram](metrics::Histogram) metric.
 #[derive(Clone, Debug)]
-pub struct Histogram(prometheus::Histogram);
+pub struct Histogram {
+    inner: prometheus::Histogram,
+    exemplars: Exemplars,
+}
 
 impl Histogram {
-    fn new(registry: &Registry, opts: Opts) -> Self {
-        let histogram = prometheus::Histogram::with_opts(opts.into()).unwrap();
+    fn new(
+        registry: &Registry,
+        exemplars: &ExemplarRegistry,
+        opts: Opts,
+        buckets: Vec<f64>,
+    ) -> Self {
+        let name = opts.fq_name();
+        let opts = HistogramOpts::from(opts).buckets(buckets.clone());
+        let histogram = prometheus::Histogram::with_opts(opts).unwrap();
         registry.register(Box::new(histogram.clone())).unwrap();
-        Self(histogram)
+        let histogram_exemplars = Exemplars::new(Arc::new(buckets));
+        exemplars
+            .write()
+            .unwrap()
+            .insert((name, vec![]), histogram_exemplars.clone());
+        Self {
+            inner: histogram,
+            exemplars: histogram_exemplars,
+        }
     }
 
     pub fn sample_count(&self) -> usize {
-        self.0.get_sample_count() as usize
+        self.inner.get_sample_count() as usize
     }
 
     pub fn sum(&self) -> f64 {
-        self.0.get_sample_sum()
+        self.inner.get_sample_sum()
     }
 
     pub fn mean(&self) -> f64 {
         self.sum() / (self.sample_count() as f64)
     }
+
+    /// The most recent exemplar recorded in each bucket of this histogram.
+    ///
+    /// Each exemplar is paired with the upper bound of its bucket. Buckets with no exemplars are
+    /// omitted.
+    pub fn exemplars(&self) -> Vec<(f64, Exemplar)> {
+        self.exemplars.get()
+    }
 }
 
 impl metrics::Histogram for Histogram {
     fn add_point(&self, point: f64) {
-        self.0.observe(point);
+        self.inner.observe(point);
+    }
+
+    fn add_point_with_exemplar(&self, point: f64, exemplar: Vec<(String, String)>) {
+        self.inner.observe(point);
+        self.exemplars.record(point, exemplar);
     }
 }
 
@@ -407,19 +632,57 @@ impl metrics::MetricsFamily<Box<dyn metrics::Gauge>> for GaugeFamily {
 
 /// A [HistogramFamily](metrics::HistogramFamily) metric.
 #[derive(Clone, Debug)]
-pub struct HistogramFamily(HistogramVec);
+pub struct HistogramFamily {
+    inner: HistogramVec,
+    name: String,
+    label_names: Vec<String>,
+    buckets: Arc<Vec<f64>>,
+    exemplars: ExemplarRegistry,
+}
 
 impl HistogramFamily {
-    fn new(registry: &Registry, opts: Opts, labels: &[String]) -> Self {
+    fn new(
+        registry: &Registry,
+        exemplars: &ExemplarRegistry,
+        opts: Opts,
+        labels: &[String],
+        buckets: Vec<f64>,
+    ) -> Self {
+        let name = opts.fq_name();
+        let label_names = labels.to_vec();
         let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
-        let family = HistogramVec::new(opts.into(), &labels).unwrap();
+        let opts = HistogramOpts::from(opts).buckets(buckets.clone());
+        let family = HistogramVec::new(opts, &labels).unwrap();
         registry.register(Box::new(family.clone())).unwrap();
-        Self(family)
+        Self {
+            inner: family,
+            name,
+            label_names,
+            buckets: Arc::new(buckets),
+            exemplars: exemplars.clone(),
+        }
     }
 
     pub fn get(&self, label_values: &[impl AsRef<str>]) -> Histogram {
         let labels = label_values.iter().map(AsRef::as_ref).collect::<Vec<_>>();
-        Histogram(self.0.get_metric_with_label_values(&labels).unwrap())
+        let mut key = self
+            .label_names
+            .iter()
+            .cloned()
+            .zip(labels.iter().map(|label| label.to_string()))
+            .collect::<Vec<_>>();
+        key.sort();
+        let exemplars = self
+            .exemplars
+            .write()
+            .unwrap()
+            .entry((self.name.clone(), key))
+            .or_insert_with(|| Exemplars::new(self.buckets.clone()))
+            .clone();
+        Histogram {
+            inner: self.inner.get_metric_with_label_values(&labels).unwrap(),
+            exemplars,
+        }
     }
 }
 
@@ -447,7 +710,7 @@ impl metrics::MetricsFamily<()> for TextFamily {
 
 #[cfg(test)]
 mod test {
-    use metrics::Metrics;
+    use metrics::{Metrics, MetricsFamily};
     use tide_disco::metrics::Metrics as _;
 
     use super::*;
@@ -576,6 +839,121 @@ mod test {
             .contains(&"subgroup1_subgroup2_text 1"));
     }
 
+    #[test]
+    fn test_histogram_buckets_and_exemplars() {
+        setup_test();
+
+        let metrics = PrometheusMetrics::default();
+        let histogram =
+            metrics.create_histogram_with_buckets("latency".into(), None, vec![1.0, 5.0, 10.0]);
+        histogram.add_point_with_exemplar(3.0, vec![("view".into(), "1".into())]);
+        histogram.add_point_with_exemplar(4.0, vec![("view".into(), "2".into())]);
+        histogram.add_point_with_exemplar(30.0, vec![("view".into(), "3".into())]);
+        histogram.add_point(0.5);
+
+        // Check that the custom buckets are used.
+        let string = metrics.export().unwrap();
+        let lines = string.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"latency_bucket{le=\"1\"} 1"), "{lines:?}");
+        assert!(lines.contains(&"latency_bucket{le=\"5\"} 3"), "{lines:?}");
+        assert!(lines.contains(&"latency_bucket{le=\"10\"} 3"), "{lines:?}");
+        assert!(
+            lines.contains(&"latency_bucket{le=\"+Inf\"} 4"),
+            "{lines:?}"
+        );
+        assert!(!lines.iter().any(|line| line.contains("le=\"0.005\"")));
+
+        // Check that the latest exemplar in each bucket is retained.
+        let exemplars = metrics.get_histogram("latency").unwrap().exemplars();
+        assert_eq!(exemplars.len(), 2);
+        assert_eq!(exemplars[0].0, 5.0);
+        assert_eq!(exemplars[0].1.value, 4.0);
+        assert_eq!(
+            exemplars[0].1.labels,
+            [("view".to_string(), "2".to_string())]
+        );
+        assert_eq!(exemplars[1].0, f64::INFINITY);
+        assert_eq!(exemplars[1].1.value, 30.0);
+        assert_eq!(
+            exemplars[1].1.labels,
+            [("view".to_string(), "3".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_export_with_exemplars() {
+        setup_test();
+
+        let metrics = PrometheusMetrics::default();
+        let consensus = metrics.subgroup("consensus".into());
+        let histogram =
+            consensus.create_histogram_with_buckets("latency".into(), None, vec![1.0, 5.0, 10.0]);
+        let family = consensus.histogram_family_with_buckets(
+            "latency_by_node".into(),
+            vec!["node".into()],
+            vec![1.0, 5.0],
+        );
+        histogram.add_point(0.5);
+        histogram.add_point_with_exemplar(
+            4.0,
+            vec![
+                ("view".into(), "2".into()),
+                ("leader".into(), "BLS_VER_KEY~abc".into()),
+            ],
+        );
+        family
+            .create(vec!["a".into()])
+            .add_point_with_exemplar(30.0, vec![("view".into(), "3".into())]);
+
+        // The plain export must stay parseable as the classic text format.
+        let plain = metrics.export().unwrap();
+        assert!(!plain.contains(" # {"), "{plain}");
+
+        let string = metrics.export_with_exemplars().unwrap();
+        let lines = string.lines().collect::<Vec<_>>();
+
+        // Buckets without exemplars are unchanged.
+        assert!(
+            lines.contains(&"consensus_latency_bucket{le=\"1\"} 1"),
+            "{lines:?}"
+        );
+
+        // The exemplar is rendered on the bucket it fell into.
+        let line = lines
+            .iter()
+            .find(|line| line.starts_with("consensus_latency_bucket{le=\"5\"} 2 # "))
+            .unwrap_or_else(|| panic!("{lines:?}"));
+        assert!(
+            line.contains(" # {view=\"2\",leader=\"BLS_VER_KEY~abc\"} 4 "),
+            "{line}"
+        );
+        assert!(!lines.iter().any(
+            |line| line.starts_with("consensus_latency_bucket{le=\"10\"}") && line.contains(" # ")
+        ));
+
+        // Exemplars of a histogram family are matched by label.
+        let line = lines
+            .iter()
+            .find(|line| {
+                line.starts_with("consensus_latency_by_node_bucket{node=\"a\",le=\"+Inf\"}")
+            })
+            .unwrap_or_else(|| panic!("{lines:?}"));
+        assert!(line.contains(" # {view=\"3\"} 30 "), "{line}");
+    }
+
+    #[test]
+    fn test_exemplar_label_limit() {
+        let exemplar = Exemplar {
+            labels: vec![
+                ("view".into(), "1".into()),
+                ("leader".into(), "x".repeat(MAX_EXEMPLAR_LABEL_CHARS)),
+            ],
+            value: 1.0,
+            timestamp: UNIX_EPOCH,
+        };
+        assert_eq!(exemplar.to_string(), "{view=\"1\"} 1 0");
+    }
+
     #[test]
     fn test_labels() {
         setup_test();
@@ -175,6 +175,12 @@ where
         .get("sync_status", |_req, state| {
             async move { state.sync_status().await.context(QuerySnafu) }.boxed()
         })?
+        .get("pruning_horizon", |_req, state| {
+            async move { state.pruning_horizon().await.context(QuerySnafu) }.boxed()
+        })?
+        .get("backfill_status", |_req, state| {
+            async move { state.backfill_status().await.context(QuerySnafu) }.boxed()
+        })?
         .get("get_header_window", move |req, state| {
             async move {
                 let start = if let Some(height) = req.opt_integer_param("height")? {
@@ -31,7 +31,9 @@ use derivative::Derivative;
 use derive_more::From;
 use hotshot_types::{data::VidShare, traits::node_implementation::NodeType};
 
-use super::query_data::{BlockHash, BlockId, SyncStatus, TimeWindowQueryData};
+use super::query_data::{
+    BackfillStatus, BlockHash, BlockId, PruningHorizon, SyncStatus, TimeWindowQueryData,
+};
 use crate::{Header, QueryResult};
 
 #[derive(Derivative, From)]
@@ -74,6 +76,16 @@ pub trait NodeDataSource<Types: NodeType> {
     /// Search the database for missing objects and generate a report.
     async fn sync_status(&self) -> QueryResult<SyncStatus>;
 
+    /// Get the range of blocks for which this node has pruned data.
+    async fn pruning_horizon(&self) -> QueryResult<PruningHorizon> {
+        Ok(PruningHorizon::default())
+    }
+
+    /// Get the progress of the archival backfill, if one is configured.
+    async fn backfill_status(&self) -> QueryResult<Option<BackfillStatus>> {
+        Ok(None)
+    }
+
     async fn count_transactions(&self) -> QueryResult<usize> {
         self.count_transactions_in_range(0..).await
     }
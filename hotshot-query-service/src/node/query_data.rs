@@ -41,6 +41,46 @@ impl SyncStatus {
     }
 }
 
+/// The range of blocks for which this node has pruned data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PruningHorizon {
+    /// The height of the most recent block whose payload and VID data have been pruned.
+    pub pruned_height: Option<u64>,
+    /// The height of the oldest header still stored.
+    ///
+    /// When the pruner is configured to preserve headers, this may be well below `pruned_height`,
+    /// since headers, leaves and merklized state are kept for pruned blocks.
+    pub oldest_header: Option<u64>,
+}
+
+/// Progress of an archival backfill run.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct BackfillStatus {
+    /// The oldest block being backfilled.
+    pub start: u64,
+    /// The block height when the run started; blocks from `start` up to (but not including) this
+    /// height are backfilled.
+    pub end: u64,
+    /// The kind of object currently being backfilled (`leaf`, `block` or `vid_common`).
+    pub stage: String,
+    /// The number of objects checked so far.
+    pub scanned: u64,
+    /// The number of missing leaves found.
+    pub missing_leaves: u64,
+    /// The number of missing blocks found.
+    pub missing_blocks: u64,
+    /// The number of missing VID common objects found.
+    pub missing_vid_common: u64,
+    /// The number of missing objects which were fetched, verified and stored.
+    pub fetched: u64,
+    /// The number of missing objects which could not be fetched in time.
+    ///
+    /// These will be retried by the next run, or picked up by proactive scanning.
+    pub failed: u64,
+    /// Whether the run has finished.
+    pub complete: bool,
+}
+
 /// Response to a `/:resource/window` query.
 #[derive(Clone, Debug, Derivative, PartialEq, Eq, Serialize, Deserialize)]
 #[derivative(Default(bound = ""))]
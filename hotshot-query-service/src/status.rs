@@ -35,8 +35,10 @@ use vbs::version::StaticVersionType;
 use crate::api::load_api;
 
 pub(crate) mod data_source;
+pub(crate) mod health;
 
 pub use data_source::*;
+pub use health::*;
 
 #[derive(Default)]
 pub struct Options {
@@ -47,12 +49,24 @@ pub struct Options {
     /// These optional files may contain route definitions for application-specific routes that have
     /// been added as extensions to the basic status API.
     pub extensions: Vec<toml::Value>,
+
+    /// Thresholds used by the `health` endpoints to decide whether the node is healthy.
+    pub health: HealthThresholds,
 }
 
 #[derive(Clone, Debug, From, Snafu, Deserialize, Serialize)]
 pub enum Error {
-    Request { source: RequestError },
-    Internal { reason: String },
+    Request {
+        source: RequestError,
+    },
+    Internal {
+        reason: String,
+    },
+    /// The node failed a health check. The full report is included so that callers can see which
+    /// subsystem is at fault.
+    Unhealthy {
+        report: Box<HealthReport>,
+    },
 }
 
 impl Error {
@@ -60,6 +74,7 @@ impl Error {
         match self {
             Self::Request { .. } => StatusCode::BAD_REQUEST,
             Self::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Unhealthy { .. } => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
 }
@@ -83,6 +98,8 @@ where
         include_str!("../api/status.toml"),
         options.extensions.clone(),
     )?;
+    let health = options.health.clone();
+    let liveness = options.health.clone();
     api.with_version("0.0.1".parse().unwrap())
         .get("block_height", |_, state| {
             async { state.block_height().await.map_err(internal) }.boxed()
@@ -99,6 +116,34 @@ where
             }
             .boxed()
         })?
+        .get("health", move |_, state| {
+            let thresholds = health.clone();
+            async move {
+                let report = state.health(&thresholds).await;
+                if report.ready {
+                    Ok(report)
+                } else {
+                    Err(Error::Unhealthy {
+                        report: Box::new(report),
+                    })
+                }
+            }
+            .boxed()
+        })?
+        .get("liveness", move |_, state| {
+            let thresholds = liveness.clone();
+            async move {
+                let report = state.health(&thresholds).await;
+                if report.live {
+                    Ok(report)
+                } else {
+                    Err(Error::Unhealthy {
+                        report: Box::new(report),
+                    })
+                }
+            }
+            .boxed()
+        })?
         .metrics("metrics", |_, state| {
             async { Ok(Cow::Borrowed(state.metrics())) }.boxed()
         })?;
@@ -199,6 +244,21 @@ mod test {
         // We know at least some views have been successful, since we finalized a block.
         assert!(success_rate > 0.0, "{success_rate}");
 
+        // Now that consensus is deciding, the node reports itself healthy.
+        let report = client.get::<HealthReport>("health").send().await.unwrap();
+        assert!(report.live);
+        assert!(report.ready);
+        assert_eq!(report.consensus.status, HealthStatus::Healthy);
+        assert_eq!(report.storage.status, HealthStatus::Healthy);
+        assert!(
+            client
+                .get::<HealthReport>("health/live")
+                .send()
+                .await
+                .unwrap()
+                .live
+        );
+
         network.shut_down().await;
     }
 
@@ -14,6 +14,7 @@ use async_trait::async_trait;
 use chrono::Utc;
 use hotshot_types::traits::metrics::Metrics;
 
+use super::{HealthReport, HealthThresholds};
 use crate::{
     metrics::{MetricsError, PrometheusMetrics},
     QueryError, QueryResult,
@@ -58,6 +59,11 @@ pub trait StatusDataSource: HasMetrics {
         // By definition, a successful view is any which committed a block.
         Ok(self.block_height().await? as f64 / total_views)
     }
+
+    /// Assess the health of each subsystem of this node.
+    async fn health(&self, thresholds: &HealthThresholds) -> HealthReport {
+        HealthReport::collect(self, thresholds).await
+    }
 }
 
 pub trait UpdateStatusData {
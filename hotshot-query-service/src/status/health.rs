@@ -0,0 +1,439 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+//! Aggregated health of a node.
+//!
+//! A [`HealthReport`] summarizes the state of each subsystem of a node in a single machine-readable
+//! object, suitable for load balancer health checks and alerting. Each subsystem is assessed from
+//! the metrics it already publishes, so a subsystem which is not running in this node (for example,
+//! the L1 client in a standalone query service) is simply reported as
+//! [`Unknown`](HealthStatus::Unknown) and does not affect the overall result.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::StatusDataSource;
+use crate::metrics::PrometheusMetrics;
+
+/// Thresholds used to decide whether a node is healthy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthThresholds {
+    /// The maximum number of views which may pass without a decide before consensus is considered
+    /// stalled.
+    pub max_views_since_decide: u64,
+
+    /// The maximum time which may pass without a decide before consensus is considered stalled.
+    pub max_time_since_decide: Duration,
+
+    /// The minimum number of libp2p peers required for the network to be considered healthy.
+    pub min_libp2p_peers: u64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            max_views_since_decide: 100,
+            max_time_since_decide: Duration::from_secs(300),
+            min_libp2p_peers: 1,
+        }
+    }
+}
+
+/// The health of a single subsystem.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// The subsystem is working normally.
+    Healthy,
+    /// The subsystem is working, but something may need attention.
+    Degraded,
+    /// The subsystem is not working.
+    Unhealthy,
+    /// The subsystem is not running in this node, or has not published enough information to
+    /// assess it.
+    Unknown,
+}
+
+/// Health of consensus.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConsensusHealth {
+    pub status: HealthStatus,
+    pub current_view: Option<u64>,
+    pub last_decided_view: Option<u64>,
+    pub views_since_last_decide: Option<u64>,
+    /// Seconds since the last decide, or [`None`] if this node has not decided anything yet.
+    pub time_since_last_decide: Option<u64>,
+}
+
+/// Health of a single network transport.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportHealth {
+    /// The number of peers this node is connected to, if the transport reports it.
+    pub connected_peers: Option<u64>,
+    /// Whether the transport has finished bootstrapping, if the transport reports it.
+    pub ready: Option<bool>,
+    /// The total number of messages which have failed to send.
+    pub failed_messages: Option<u64>,
+}
+
+/// Health of the consensus network.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkHealth {
+    pub status: HealthStatus,
+    pub libp2p: Option<TransportHealth>,
+    pub cdn: Option<TransportHealth>,
+}
+
+/// Health of persistent storage.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageHealth {
+    pub status: HealthStatus,
+    pub block_height: Option<u64>,
+    pub open_transactions: Option<u64>,
+    /// The error encountered while querying storage, if any.
+    pub error: Option<String>,
+}
+
+/// Health of the fetcher, which fills in missing data from peers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetcherHealth {
+    pub status: HealthStatus,
+    pub scanner_running: Option<bool>,
+    /// Retries of the current scan for missing data.
+    pub scanner_retries: Option<u64>,
+    /// Blocks found to be missing in the most recent scans.
+    pub missing_blocks: Option<u64>,
+    /// VID common data found to be missing in the most recent scans.
+    pub missing_vid: Option<u64>,
+    /// Objects the backfill task was unable to fetch.
+    pub backfill_failed: Option<u64>,
+}
+
+/// Sync status of the L1 client.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Health {
+    pub status: HealthStatus,
+    pub head: Option<u64>,
+    pub finalized: Option<u64>,
+}
+
+/// Aggregated health of a node.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Whether the node is making progress.
+    ///
+    /// This is false only when consensus has stalled, and is suitable for liveness probes.
+    pub live: bool,
+    /// Whether the node is able to serve requests.
+    ///
+    /// This is false when any subsystem is [`Unhealthy`](HealthStatus::Unhealthy), and is suitable
+    /// for readiness probes and load balancer health checks.
+    pub ready: bool,
+    pub consensus: ConsensusHealth,
+    pub network: NetworkHealth,
+    pub storage: StorageHealth,
+    pub fetcher: FetcherHealth,
+    pub l1: L1Health,
+}
+
+impl HealthReport {
+    /// Assess the health of each subsystem of `state`.
+    pub async fn collect<S>(state: &S, thresholds: &HealthThresholds) -> Self
+    where
+        S: StatusDataSource + ?Sized,
+    {
+        let metrics = state.metrics();
+        let consensus = consensus_health(metrics, thresholds);
+        let network = network_health(metrics, thresholds);
+        let storage = match state.block_height().await {
+            Ok(height) => StorageHealth {
+                status: HealthStatus::Healthy,
+                block_height: Some(height as u64),
+                open_transactions: gauge(metrics, &["sql"], "open_transactions"),
+                error: None,
+            },
+            Err(err) => StorageHealth {
+                status: HealthStatus::Unhealthy,
+                block_height: None,
+                open_transactions: gauge(metrics, &["sql"], "open_transactions"),
+                error: Some(err.to_string()),
+            },
+        };
+        let fetcher = fetcher_health(metrics);
+        let l1 = l1_health(metrics);
+
+        let statuses = [
+            consensus.status,
+            network.status,
+            storage.status,
+            fetcher.status,
+            l1.status,
+        ];
+        Self {
+            live: consensus.status != HealthStatus::Unhealthy,
+            ready: !statuses.contains(&HealthStatus::Unhealthy),
+            consensus,
+            network,
+            storage,
+            fetcher,
+            l1,
+        }
+    }
+}
+
+fn consensus_health(metrics: &PrometheusMetrics, thresholds: &HealthThresholds) -> ConsensusHealth {
+    let current_view = gauge(metrics, &["consensus"], "current_view");
+    let last_decided_view = gauge(metrics, &["consensus"], "last_decided_view");
+    let views_since_last_decide =
+        gauge(metrics, &["consensus"], "number_of_views_since_last_decide");
+    // A decide time of zero means we have not decided anything yet.
+    let time_since_last_decide = gauge(metrics, &["consensus"], "last_decided_time")
+        .filter(|time| *time > 0)
+        .map(|time| (Utc::now().timestamp() as u64).saturating_sub(time));
+
+    let status = if current_view.is_none() {
+        HealthStatus::Unknown
+    } else if views_since_last_decide.unwrap_or(0) > thresholds.max_views_since_decide
+        || time_since_last_decide.unwrap_or(0) > thresholds.max_time_since_decide.as_secs()
+    {
+        HealthStatus::Unhealthy
+    } else if time_since_last_decide.is_none() {
+        // Still starting up.
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    };
+    ConsensusHealth {
+        status,
+        current_view,
+        last_decided_view,
+        views_since_last_decide,
+        time_since_last_decide,
+    }
+}
+
+fn network_health(metrics: &PrometheusMetrics, thresholds: &HealthThresholds) -> NetworkHealth {
+    let libp2p = metrics
+        .get_subgroup(["consensus", "libp2p"])
+        .ok()
+        .map(|_| TransportHealth {
+            connected_peers: gauge(metrics, &["consensus", "libp2p"], "num_connected_peers"),
+            ready: gauge(metrics, &["consensus", "libp2p"], "is_ready").map(|ready| ready != 0),
+            failed_messages: counter(metrics, &["consensus", "libp2p"], "num_failed_messages"),
+        });
+    let cdn = metrics
+        .get_subgroup(["consensus", "cdn"])
+        .ok()
+        .map(|_| TransportHealth {
+            connected_peers: None,
+            ready: None,
+            failed_messages: counter(metrics, &["consensus", "cdn"], "num_failed_messages"),
+        });
+
+    let status = match (&libp2p, &cdn) {
+        (None, None) => HealthStatus::Unknown,
+        (Some(libp2p), cdn) => {
+            let connected = libp2p.ready != Some(false)
+                && libp2p.connected_peers.unwrap_or(0) >= thresholds.min_libp2p_peers;
+            if connected {
+                HealthStatus::Healthy
+            } else if cdn.is_some() {
+                // We can still reach the network through the CDN.
+                HealthStatus::Degraded
+            } else {
+                HealthStatus::Unhealthy
+            }
+        },
+        // The CDN does not report connectivity, so as long as it is configured we assume it works.
+        (None, Some(_)) => HealthStatus::Healthy,
+    };
+    NetworkHealth {
+        status,
+        libp2p,
+        cdn,
+    }
+}
+
+fn fetcher_health(metrics: &PrometheusMetrics) -> FetcherHealth {
+    let sum = |a: Option<u64>, b: Option<u64>| match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    };
+    let scanner_running = gauge(metrics, &["scanner"], "running").map(|running| running != 0);
+    let scanner_retries = gauge(metrics, &["scanner"], "retries");
+    let missing_blocks = sum(
+        gauge(metrics, &["scanner"], "major_missing_blocks"),
+        gauge(metrics, &["scanner"], "minor_missing_blocks"),
+    );
+    let missing_vid = sum(
+        gauge(metrics, &["scanner"], "major_missing_vid"),
+        gauge(metrics, &["scanner"], "minor_missing_vid"),
+    );
+    let backfill_failed = gauge(metrics, &["backfill"], "failed");
+
+    let status = if scanner_running.is_none() && backfill_failed.is_none() {
+        HealthStatus::Unknown
+    } else if scanner_retries.unwrap_or(0) > 0 || backfill_failed.unwrap_or(0) > 0 {
+        // Missing data will be retried, but repeated failures suggest we cannot reach any peers.
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    };
+    FetcherHealth {
+        status,
+        scanner_running,
+        scanner_retries,
+        missing_blocks,
+        missing_vid,
+        backfill_failed,
+    }
+}
+
+fn l1_health(metrics: &PrometheusMetrics) -> L1Health {
+    let head = gauge(metrics, &["consensus", "l1"], "head");
+    let finalized = gauge(metrics, &["consensus", "l1"], "finalized");
+    let status = match head {
+        None => HealthStatus::Unknown,
+        // The L1 client has not yet received a block from any provider.
+        Some(0) => HealthStatus::Unhealthy,
+        Some(_) => HealthStatus::Healthy,
+    };
+    L1Health {
+        status,
+        head,
+        finalized,
+    }
+}
+
+fn gauge(metrics: &PrometheusMetrics, path: &[&str], name: &str) -> Option<u64> {
+    let gauge = metrics.get_subgroup(path).ok()?.get_gauge(name).ok()?;
+    Some(gauge.get() as u64)
+}
+
+fn counter(metrics: &PrometheusMetrics, path: &[&str], name: &str) -> Option<u64> {
+    let counter = metrics.get_subgroup(path).ok()?.get_counter(name).ok()?;
+    Some(counter.get() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use hotshot_types::traits::metrics::Metrics;
+
+    use super::*;
+    use crate::{status::HasMetrics, QueryError, QueryResult};
+
+    #[derive(Default)]
+    struct MockStatus {
+        metrics: PrometheusMetrics,
+        storage_error: bool,
+    }
+
+    impl HasMetrics for MockStatus {
+        fn metrics(&self) -> &PrometheusMetrics {
+            &self.metrics
+        }
+    }
+
+    #[async_trait]
+    impl StatusDataSource for MockStatus {
+        async fn block_height(&self) -> QueryResult<usize> {
+            if self.storage_error {
+                Err(QueryError::Error {
+                    message: "database unavailable".into(),
+                })
+            } else {
+                Ok(10)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_report() {
+        let status = MockStatus::default();
+        let thresholds = HealthThresholds::default();
+
+        // With no metrics, only storage can be assessed.
+        let report = HealthReport::collect(&status, &thresholds).await;
+        assert!(report.live);
+        assert!(report.ready);
+        assert_eq!(report.consensus.status, HealthStatus::Unknown);
+        assert_eq!(report.network.status, HealthStatus::Unknown);
+        assert_eq!(report.storage.status, HealthStatus::Healthy);
+        assert_eq!(report.storage.block_height, Some(10));
+        assert_eq!(report.fetcher.status, HealthStatus::Unknown);
+        assert_eq!(report.l1.status, HealthStatus::Unknown);
+
+        // A running node which has decided recently.
+        let consensus = status.metrics.subgroup("consensus".into());
+        consensus.create_gauge("current_view".into(), None).set(20);
+        consensus
+            .create_gauge("last_decided_view".into(), None)
+            .set(19);
+        consensus
+            .create_gauge("number_of_views_since_last_decide".into(), None)
+            .set(1);
+        let last_decided_time = consensus.create_gauge("last_decided_time".into(), None);
+        last_decided_time.set(Utc::now().timestamp() as usize);
+        let libp2p = consensus.subgroup("libp2p".into());
+        let peers = libp2p.create_gauge("num_connected_peers".into(), None);
+        peers.set(5);
+        libp2p.create_gauge("is_ready".into(), None).set(1);
+        consensus
+            .subgroup("l1".into())
+            .create_gauge("head".into(), None)
+            .set(100);
+
+        let report = HealthReport::collect(&status, &thresholds).await;
+        assert!(report.live);
+        assert!(report.ready);
+        assert_eq!(report.consensus.status, HealthStatus::Healthy);
+        assert_eq!(report.consensus.views_since_last_decide, Some(1));
+        assert_eq!(report.network.status, HealthStatus::Healthy);
+        assert_eq!(
+            report.network.libp2p.as_ref().unwrap().connected_peers,
+            Some(5)
+        );
+        assert_eq!(report.network.cdn, None);
+        assert_eq!(report.l1.status, HealthStatus::Healthy);
+        assert_eq!(report.l1.head, Some(100));
+
+        // Losing all peers makes the node unready, but it is still live.
+        peers.set(0);
+        let report = HealthReport::collect(&status, &thresholds).await;
+        assert!(report.live);
+        assert!(!report.ready);
+        assert_eq!(report.network.status, HealthStatus::Unhealthy);
+        peers.set(5);
+
+        // A stalled node is neither live nor ready.
+        let stalled = thresholds.max_time_since_decide.as_secs() as usize + 10;
+        last_decided_time.set(Utc::now().timestamp() as usize - stalled);
+        let report = HealthReport::collect(&status, &thresholds).await;
+        assert!(!report.live);
+        assert!(!report.ready);
+        assert_eq!(report.consensus.status, HealthStatus::Unhealthy);
+
+        // Storage failures make the node unready.
+        let status = MockStatus {
+            storage_error: true,
+            ..Default::default()
+        };
+        let report = HealthReport::collect(&status, &thresholds).await;
+        assert!(report.live);
+        assert!(!report.ready);
+        assert_eq!(report.storage.status, HealthStatus::Unhealthy);
+        assert!(report.storage.error.is_some());
+    }
+}
@@ -17,6 +17,7 @@ use tracing_subscriber::EnvFilter;
 
 pub mod consensus;
 pub mod mocks;
+pub mod perf;
 
 pub async fn sleep(dur: Duration) {
     tokio::time::sleep(dur).await;
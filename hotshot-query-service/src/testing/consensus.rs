@@ -36,6 +36,7 @@ use hotshot_types::{
     epoch_membership::EpochMembershipCoordinator,
     light_client::StateKeyPair,
     signature_key::BLSPubKey,
+    signer::Signer,
     traits::{
         election::Membership,
         network::Topic,
@@ -159,6 +160,13 @@ impl<D: DataSourceLifeCycle + UpdateStatusData, V: Versions> MockNetwork<D, V> {
             stop_voting_time: 0,
             epoch_height: EPOCH_HEIGHT,
             epoch_start_block: 0,
+            da_payload_hint_threshold: None,
+            da_payload_hint_urls: vec![],
+            compression: None,
+            bandwidth: Default::default(),
+            local_builder: None,
+            adaptive_view_timeout: None,
+            transaction_dedup: None,
         };
         update_config(&mut config);
 
@@ -203,7 +211,7 @@ impl<D: DataSourceLifeCycle + UpdateStatusData, V: Versions> MockNetwork<D, V> {
 
                         let hotshot = SystemContext::init(
                             pub_keys[node_id],
-                            priv_key,
+                            Signer::Local(priv_key),
                             state_priv_keys[node_id].clone(),
                             node_id as u64,
                             config,
@@ -0,0 +1,205 @@
+// Copyright (c) 2022 Espresso Systems (espressosys.com)
+// This file is part of the HotShot Query Service library.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the GNU
+// General Public License as published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// General Public License for more details.
+// You should have received a copy of the GNU General Public License along with this program. If not,
+// see <https://www.gnu.org/licenses/>.
+
+//! Load drivers for measuring query service performance.
+//!
+//! These drive a data source through the workloads which dominate the load on a production query
+//! service: scanning ranges of blocks, and streaming new blocks to many subscribers at once. They
+//! are shared by the criterion benchmarks in `benches/` and by the budget tests below, which fail
+//! if a workload starts waiting far longer than expected.
+
+use std::ops::Range;
+
+use futures::{future::join_all, stream::StreamExt};
+use hotshot_example_types::{
+    node_types::TestVersions,
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_types::vid::advz::{advz_scheme, ADVZCommon};
+use jf_vid::VidScheme;
+
+use super::{
+    consensus::TestableDataSource,
+    mocks::{mock_transaction, MockPayload, MockTypes},
+};
+use crate::{
+    availability::{BlockInfo, BlockQueryData, LeafQueryData, VidCommonQueryData},
+    VidCommon,
+};
+
+/// Generator of mock blocks with non-trivial payloads.
+#[derive(Clone, Debug)]
+pub struct MockChain {
+    leaf: LeafQueryData<MockTypes>,
+    payload: MockPayload,
+    common: ADVZCommon,
+    height: u64,
+}
+
+impl MockChain {
+    /// A chain whose blocks each contain `txs_per_block` transactions of `tx_size` bytes.
+    pub async fn new(txs_per_block: usize, tx_size: usize) -> Self {
+        let leaf = LeafQueryData::<MockTypes>::genesis::<TestVersions>(
+            &TestValidatedState::default(),
+            &TestInstanceState::default(),
+        )
+        .await;
+        let payload = MockPayload {
+            transactions: (0..txs_per_block)
+                .map(|i| mock_transaction(vec![i as u8; tx_size]))
+                .collect(),
+        };
+        // We reuse the same VID common data for every block; it is stored but never verified.
+        let common = advz_scheme(2).disperse([]).unwrap().common;
+        Self {
+            leaf,
+            payload,
+            common,
+            height: 0,
+        }
+    }
+
+    /// The height of the next block to be generated.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Generate the next block.
+    pub fn next_block(&mut self) -> BlockInfo<MockTypes> {
+        let mut leaf = self.leaf.clone();
+        leaf.leaf.block_header_mut().block_number = self.height;
+        self.height += 1;
+
+        let block = BlockQueryData::new(leaf.header().clone(), self.payload.clone());
+        let common =
+            VidCommonQueryData::new(leaf.header().clone(), VidCommon::V0(self.common.clone()));
+        BlockInfo::new(leaf, Some(block), Some(common), None, None)
+    }
+}
+
+/// Append `num_blocks` blocks from `chain` to `ds`.
+pub async fn populate(ds: &impl TestableDataSource, chain: &mut MockChain, num_blocks: usize) {
+    for _ in 0..num_blocks {
+        ds.append(chain.next_block()).await.unwrap();
+    }
+}
+
+/// Read the blocks in `range`, returning the total number of transactions read.
+pub async fn scan_blocks(ds: &impl TestableDataSource, range: Range<usize>) -> u64 {
+    let expected = range.len();
+    let blocks = ds
+        .get_block_range(range)
+        .await
+        .then(|fetch| fetch.resolve())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(blocks.len(), expected);
+    blocks.iter().map(|block| block.num_transactions()).sum()
+}
+
+/// Stream the next `num_blocks` blocks from `chain` to `subscribers` concurrent subscribers.
+///
+/// This returns once every subscriber has received every block.
+pub async fn fan_out(
+    ds: &impl TestableDataSource,
+    chain: &mut MockChain,
+    subscribers: usize,
+    num_blocks: usize,
+) {
+    let from = chain.height() as usize;
+    let streams = join_all((0..subscribers).map(|_| ds.subscribe_blocks(from))).await;
+    let readers = streams
+        .into_iter()
+        .map(|blocks| tokio::spawn(blocks.take(num_blocks).count()))
+        .collect::<Vec<_>>();
+
+    populate(ds, chain, num_blocks).await;
+    for received in join_all(readers).await {
+        assert_eq!(received.unwrap(), num_blocks);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::{
+        data_source::FileSystemDataSource,
+        fetching::provider::NoFetching,
+        testing::{consensus::DataSourceLifeCycle, setup_test},
+    };
+
+    // These tests run with the clock paused, so the time they measure is only the time each
+    // workload spends waiting on timers, such as the delays between fetching chunks of a range,
+    // and does not depend on the speed of the machine. The budgets are several times the expected
+    // wait. They are not meant to track performance, which is what the benchmarks are for, but to
+    // catch accidental regressions, such as a range scan which starts waiting once per block.
+
+    type D = FileSystemDataSource<MockTypes, NoFetching>;
+
+    const NUM_BLOCKS: usize = 200;
+    const TXS_PER_BLOCK: usize = 10;
+    const TX_SIZE: usize = 256;
+
+    const SCAN_BUDGET: Duration = Duration::from_secs(5);
+    const FAN_OUT_BUDGET: Duration = Duration::from_secs(10);
+
+    #[tokio::test(start_paused = true)]
+    async fn test_block_range_scan_budget() {
+        setup_test();
+
+        let storage = D::create(0).await;
+        let ds = D::connect(&storage).await;
+        let mut chain = MockChain::new(TXS_PER_BLOCK, TX_SIZE).await;
+        populate(&ds, &mut chain, NUM_BLOCKS).await;
+
+        let start = Instant::now();
+        let txs = scan_blocks(&ds, 0..NUM_BLOCKS).await;
+        let elapsed = start.elapsed();
+        tracing::info!(?elapsed, "scanned {NUM_BLOCKS} blocks");
+
+        assert_eq!(txs, (NUM_BLOCKS * TXS_PER_BLOCK) as u64);
+        assert!(
+            elapsed < SCAN_BUDGET,
+            "scanning {NUM_BLOCKS} blocks took {elapsed:?}, budget is {SCAN_BUDGET:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stream_fan_out_budget() {
+        setup_test();
+
+        const SUBSCRIBERS: usize = 50;
+        const BLOCKS: usize = 50;
+
+        let storage = D::create(0).await;
+        let ds = D::connect(&storage).await;
+        let mut chain = MockChain::new(TXS_PER_BLOCK, TX_SIZE).await;
+
+        let start = Instant::now();
+        fan_out(&ds, &mut chain, SUBSCRIBERS, BLOCKS).await;
+        let elapsed = start.elapsed();
+        tracing::info!(
+            ?elapsed,
+            "streamed {BLOCKS} blocks to {SUBSCRIBERS} subscribers"
+        );
+
+        assert!(
+            elapsed < FAN_OUT_BUDGET,
+            "streaming {BLOCKS} blocks to {SUBSCRIBERS} subscribers took {elapsed:?}, budget is \
+             {FAN_OUT_BUDGET:?}"
+        );
+    }
+}
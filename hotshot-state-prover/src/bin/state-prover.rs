@@ -48,6 +48,14 @@ struct Args {
     )]
     max_retries: u64,
 
+    /// Gas price in wei above which state updates are held back and batched into later submissions
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_GAS_PRICE_THRESHOLD")]
+    gas_price_threshold: Option<u128>,
+
+    /// Longest time a state update may be held back because of the gas price
+    #[clap(long, value_parser = parse_duration, default_value = "1h", env = "ESPRESSO_STATE_PROVER_MAX_UPDATE_DELAY")]
+    max_update_delay: Duration,
+
     /// URL of layer 1 Ethereum JSON-RPC provider.
     #[clap(
         long,
@@ -147,6 +155,8 @@ async fn main() {
         blocks_per_epoch,
         epoch_start_block,
         max_retries: args.max_retries,
+        gas_price_threshold: args.gas_price_threshold,
+        max_update_delay: args.max_update_delay,
     };
 
     // validate that the light client contract is a proxy, panics otherwise
@@ -2,6 +2,7 @@
 
 use std::{
     collections::HashMap,
+    future::Future,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -82,6 +83,11 @@ pub struct StateProverConfig {
     pub epoch_start_block: u64,
     /// Maximum number of retires for one-shot prover
     pub max_retries: u64,
+    /// Gas price in wei above which state updates are held back, so that several finalized states
+    /// are covered by a single proof submission
+    pub gas_price_threshold: Option<u128>,
+    /// Longest time a state update may be held back because of the gas price
+    pub max_update_delay: Duration,
 }
 
 #[derive(Debug, Clone)]
@@ -94,6 +100,8 @@ pub struct ProverServiceState {
     pub stake_table: Vec<PeerConfig<SeqTypes>>,
     /// The current stake table state
     pub st_state: StakeTableState,
+    /// When the oldest state update not yet submitted was first held back
+    pub deferred_since: Option<Instant>,
 }
 
 impl ProverServiceState {
@@ -107,6 +115,7 @@ impl ProverServiceState {
             epoch: None,
             stake_table,
             st_state,
+            deferred_since: None,
         })
     }
 
@@ -124,6 +133,65 @@ impl ProverServiceState {
         }
         Ok(())
     }
+
+    /// Whether to submit a state update now, or hold it back to batch it with later updates while
+    /// gas is expensive.
+    async fn ready_to_submit(&mut self, oracle: &impl GasOracle) -> bool {
+        let Some(threshold) = self.config.gas_price_threshold else {
+            return true;
+        };
+        let deferred_for = self
+            .deferred_since
+            .get_or_insert_with(Instant::now)
+            .elapsed();
+        let gas_price = match oracle.gas_price().await {
+            Ok(price) => Some(price),
+            Err(err) => {
+                tracing::warn!("Failed to fetch the gas price, submitting anyway: {err:#}");
+                None
+            },
+        };
+        let ready = should_submit(
+            gas_price,
+            threshold,
+            deferred_for,
+            self.config.max_update_delay,
+        );
+        if !ready {
+            tracing::info!(
+                ?gas_price,
+                threshold,
+                ?deferred_for,
+                "Gas price above threshold, deferring state update"
+            );
+        }
+        ready
+    }
+}
+
+/// Source of the current gas price, used to decide when to submit state updates
+pub trait GasOracle {
+    /// The current gas price, in wei
+    fn gas_price(&self) -> impl Future<Output = Result<u128>> + Send;
+}
+
+impl<P: Provider> GasOracle for P {
+    async fn gas_price(&self) -> Result<u128> {
+        Ok(self.get_gas_price().await?)
+    }
+}
+
+/// Whether to submit a state update which has been held back for `deferred_for`.
+///
+/// Updates are submitted right away while gas is at or below the threshold, or if the gas price is
+/// unknown, and otherwise once they have been held back for `max_delay`.
+fn should_submit(
+    gas_price: Option<u128>,
+    threshold: u128,
+    deferred_for: Duration,
+    max_delay: Duration,
+) -> bool {
+    deferred_for >= max_delay || gas_price.is_none_or(|price| price <= threshold)
 }
 
 impl StateProverConfig {
@@ -583,6 +651,9 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
 
     if !epoch_enabled {
         // If epoch hasn't been enabled, directly update the contract.
+        if !state.ready_to_submit(&provider).await {
+            return Ok(());
+        }
         let (proof, public_input) = generate_proof(
             state,
             bundle.state,
@@ -594,6 +665,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
         .await?;
 
         submit_state_and_proof(&provider, light_client_address, proof, public_input).await?;
+        state.deferred_since = None;
 
         tracing::info!("Successfully synced light client state.");
     } else {
@@ -661,7 +733,11 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
             )
             .await?;
         } else {
-            // Otherwise process the bundle update information as usual
+            // Otherwise process the bundle update information as usual, unless gas is too
+            // expensive right now, in which case a later update will cover this one.
+            if !state.ready_to_submit(&provider).await {
+                return Ok(());
+            }
             let (proof, public_input) = generate_proof(
                 state,
                 bundle.state,
@@ -673,6 +749,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
             .await?;
 
             submit_state_and_proof(&provider, light_client_address, proof, public_input).await?;
+            state.deferred_since = None;
 
             tracing::info!("Successfully synced light client state.");
         }
@@ -747,9 +824,11 @@ pub async fn run_prover_service<ApiVer: StaticVersionType + 'static>(
 
 /// Run light client state prover once
 pub async fn run_prover_once<ApiVer: StaticVersionType>(
-    config: StateProverConfig,
+    mut config: StateProverConfig,
     _: ApiVer,
 ) -> Result<()> {
+    // A one-off run is a request to update the contract now, whatever the gas price.
+    config.gas_price_threshold = None;
     let mut state = ProverServiceState::new_genesis(config).await?;
 
     let stake_table_capacity = state.config.stake_table_capacity;
@@ -825,6 +904,20 @@ mod test {
         STAKE_TABLE_CAPACITY_FOR_TEST,
     };
 
+    #[test]
+    fn test_should_submit() {
+        let max_delay = Duration::from_secs(600);
+        let early = Duration::from_secs(60);
+
+        // Cheap gas, or an unknown gas price, does not hold updates back.
+        assert!(should_submit(Some(10), 10, early, max_delay));
+        assert!(should_submit(None, 10, early, max_delay));
+
+        // Expensive gas holds updates back, but only up to the maximum delay.
+        assert!(!should_submit(Some(11), 10, early, max_delay));
+        assert!(should_submit(Some(11), 10, max_delay, max_delay));
+    }
+
     // const MAX_HISTORY_SECONDS: u32 = 864000;
     const NUM_INIT_VALIDATORS: usize = STAKE_TABLE_CAPACITY_FOR_TEST / 2;
 
@@ -188,7 +188,11 @@ where
 
             match event.event {
                 EventType::Error { error } => {
-                    tracing::error!("Error event in HotShot: {:?}", error);
+                    tracing::error!(
+                        code = error.code(),
+                        category = ?error.category(),
+                        "Error event in HotShot: {error}"
+                    );
                 },
                 EventType::Transactions { transactions } => {
                     let hooks = Arc::clone(&hooks);
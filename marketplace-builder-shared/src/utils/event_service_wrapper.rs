@@ -5,7 +5,10 @@ use either::Either::{self, Left, Right};
 use futures::{stream::unfold, Stream, StreamExt};
 use hotshot::types::Event;
 use hotshot_events_service::events::Error as EventStreamError;
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{
+    api_auth::{ApiCredentials, Scope},
+    traits::node_implementation::NodeType,
+};
 use surf_disco::{client::HealthStatus, Client};
 use tokio::time::{sleep, timeout};
 use tracing::{error, warn};
@@ -26,6 +29,7 @@ type EventServiceReconnect<Types, ApiVer> = Pin<
 /// A wrapper around event streaming API that provides auto-reconnection capability
 pub struct EventServiceStream<Types: NodeType, V: StaticVersionType> {
     api_url: Url,
+    credentials: Option<ApiCredentials>,
     connection: Either<EventServiceConnection<Types, V>, EventServiceReconnect<Types, V>>,
 }
 
@@ -38,6 +42,7 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
 
     async fn connect_inner(
         url: Url,
+        credentials: Option<ApiCredentials>,
     ) -> anyhow::Result<
         surf_disco::socket::Connection<
             Event<Types>,
@@ -64,18 +69,29 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
 
         tracing::info!("Builder client connected to the hotshot events API");
 
-        Ok(client
-            .socket("hotshot-events/events")
-            .subscribe::<Event<Types>>()
-            .await?)
+        let mut socket = client.socket("hotshot-events/events");
+        if let Some(credentials) = &credentials {
+            // Signed credentials expire, so every connection is authorized afresh.
+            socket = socket.header(
+                "Authorization",
+                credentials.authorization(Scope::ReadEvents)?.as_str(),
+            );
+        }
+        Ok(socket.subscribe::<Event<Types>>().await?)
     }
 
     /// Establish initial connection to the events service at `api_url`
-    pub async fn connect(api_url: Url) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
-        let connection = Self::connect_inner(api_url.clone()).await?;
+    ///
+    /// If the service requires API keys, `credentials` are presented on every connection.
+    pub async fn connect(
+        api_url: Url,
+        credentials: Option<ApiCredentials>,
+    ) -> anyhow::Result<impl Stream<Item = Event<Types>> + Unpin> {
+        let connection = Self::connect_inner(api_url.clone(), credentials.clone()).await?;
 
         let this = Self {
             api_url,
+            credentials,
             connection: Left(connection),
         };
 
@@ -93,7 +109,10 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
                             },
                             Ok(None) => {
                                 warn!("Event stream ended, attempting reconnection");
-                                let fut = Self::connect_inner(this.api_url.clone());
+                                let fut = Self::connect_inner(
+                                    this.api_url.clone(),
+                                    this.credentials.clone(),
+                                );
                                 let _ =
                                     std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
                                 continue;
@@ -101,7 +120,10 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
                             Err(_) => {
                                 // Timeout occurred, reconnect
                                 warn!("Timeout waiting for next event; reconnecting");
-                                let fut = Self::connect_inner(this.api_url.clone());
+                                let fut = Self::connect_inner(
+                                    this.api_url.clone(),
+                                    this.credentials.clone(),
+                                );
                                 let _ =
                                     std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
                                 continue;
@@ -116,7 +138,8 @@ impl<Types: NodeType, ApiVer: StaticVersionType + 'static> EventServiceStream<Ty
                         Err(err) => {
                             error!(?err, "Error while reconnecting, will retry in a while");
                             sleep(Self::RETRY_PERIOD).await;
-                            let fut = Self::connect_inner(this.api_url.clone());
+                            let fut =
+                                Self::connect_inner(this.api_url.clone(), this.credentials.clone());
                             let _ = std::mem::replace(&mut this.connection, Right(Box::pin(fut)));
                             continue;
                         },
@@ -226,7 +249,7 @@ mod tests {
 
         let app_handle = run_app("hotshot-events", url.clone());
 
-        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone())
+        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone(), None)
             .await
             .unwrap();
 
@@ -270,7 +293,7 @@ mod tests {
 
         let app_handle = run_app("hotshot-events", url.clone());
 
-        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone())
+        let mut stream = EventServiceStream::<TestTypes, MockVersion>::connect(url.clone(), None)
             .await
             .unwrap();
 
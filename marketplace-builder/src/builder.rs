@@ -8,7 +8,8 @@ use async_lock::RwLock;
 use espresso_types::{
     eth_signature_key::EthKeyPair,
     v0_1::NoStorage,
-    v0_99::{ChainConfig, RollupRegistration},
+    v0_4::ChainConfig,
+    v0_99::RollupRegistration,
     EpochCommittees, FeeAmount, L1Client, MarketplaceVersion, MockSequencerVersions, NamespaceId,
     NodeState, Payload, SeqTypes, SequencerVersions, ValidatedState, V0_1,
 };
@@ -22,6 +23,7 @@ use hotshot_events_service::{
     events_source::{EventConsumer, EventsStreamer},
 };
 use hotshot_types::{
+    api_auth::ApiCredentials,
     data::{fake_commitment, Leaf, ViewNumber},
     epoch_membership::EpochMembershipCoordinator,
     traits::{
@@ -98,6 +100,7 @@ impl BuilderConfig {
     async fn start_service(
         global_state: Arc<GlobalState<SeqTypes, DynamicHooks>>,
         events_api_url: Url,
+        events_api_credentials: Option<ApiCredentials>,
         builder_api_url: Url,
     ) -> anyhow::Result<()> {
         // create the proxy global state it will server the builder apis
@@ -117,8 +120,11 @@ impl BuilderConfig {
         // spawn the builder service
         tracing::info!("Running builder against hotshot events API at {events_api_url}",);
 
-        let stream =
-            EventServiceStream::<SeqTypes, SequencerApiVersion>::connect(events_api_url).await?;
+        let stream = EventServiceStream::<SeqTypes, SequencerApiVersion>::connect(
+            events_api_url,
+            events_api_credentials,
+        )
+        .await?;
 
         spawn(async move {
             let res = global_state.start_event_loop(stream).await;
@@ -140,6 +146,7 @@ impl BuilderConfig {
         event_channel_capacity: NonZeroUsize,
         instance_state: NodeState,
         events_api_url: Url,
+        events_api_credentials: Option<ApiCredentials>,
         builder_api_url: Url,
         api_timeout: Duration,
         maximize_txns_count_timeout_duration: Duration,
@@ -160,6 +167,17 @@ impl BuilderConfig {
 
         let hooks: DynamicHooks = if is_reserve {
             let bid_config = bid_config.expect("Missing bid config for the reserve builder.");
+            // The solver only accepts bids from registered builders. If registration fails, bids
+            // will be rejected, but the builder can still serve bundles.
+            if let Err(err) = hooks::register_builder(
+                solver_base_url.clone(),
+                &builder_key_pair,
+                builder_api_url.clone(),
+            )
+            .await
+            {
+                tracing::error!("Failed to register the builder with the solver: {err:#}.");
+            }
             Box::new(hooks::EspressoReserveHooks {
                 namespaces: bid_config.namespaces.into_iter().collect(),
                 solver_base_url,
@@ -194,6 +212,7 @@ impl BuilderConfig {
         Self::start_service(
             Arc::clone(&global_state),
             events_api_url.clone(),
+            events_api_credentials,
             builder_api_url.clone(),
         )
         .await?;
@@ -552,6 +571,7 @@ mod test {
                     )
                     .hotshot_events(HotshotEvents {
                         events_service_port: ports.event,
+                        ..Default::default()
                     }),
             )
             .network_config(network_config)
@@ -574,6 +594,7 @@ mod test {
             NonZeroUsize::new(1024).unwrap(),
             NodeState::default(),
             urls.event.clone(),
+            None,
             urls.builder.clone(),
             Duration::from_secs(2),
             Duration::from_secs(2),
@@ -674,6 +695,7 @@ mod test {
                     )
                     .hotshot_events(HotshotEvents {
                         events_service_port: ports.event,
+                        ..Default::default()
                     }),
             )
             .network_config(network_config)
@@ -697,6 +719,7 @@ mod test {
             NonZeroUsize::new(1024).unwrap(),
             NodeState::default(),
             urls.event.clone(),
+            None,
             urls.builder.clone(),
             Duration::from_secs(2),
             Duration::from_secs(2),
@@ -4,7 +4,7 @@ use async_lock::RwLock;
 use async_trait::async_trait;
 use espresso_types::{
     eth_signature_key::EthKeyPair,
-    v0_99::{BidTxBody, RollupRegistration},
+    v0_99::{BidTxBody, BuilderRegistration, BuilderRegistrationBody, RollupRegistration},
     FeeAmount, MarketplaceVersion, NamespaceId, SeqTypes,
 };
 use hotshot::types::{Event, EventType};
@@ -57,6 +57,34 @@ pub async fn fetch_namespaces_to_skip(solver_base_url: Url) -> Option<HashSet<Na
     }
 }
 
+/// Register the builder account with the solver, so that bids signed by it are accepted.
+///
+/// Registering again is harmless, and updates the builder URL known to the solver.
+pub async fn register_builder(
+    solver_base_url: Url,
+    key_pair: &EthKeyPair,
+    builder_api_base_url: Url,
+) -> anyhow::Result<()> {
+    let registration = BuilderRegistrationBody {
+        account: key_pair.fee_account(),
+        url: builder_api_base_url,
+    }
+    .signed(key_pair)?;
+
+    let solver_client = connect_to_solver(solver_base_url);
+    solver_client
+        .post::<BuilderRegistration>("register_builder")
+        .body_json(&registration)?
+        .send()
+        .await?;
+
+    info!(
+        "Registered builder {} with the solver",
+        key_pair.fee_account()
+    );
+    Ok(())
+}
+
 /// Reserve builder hooks for espresso sequencer.
 ///
 /// Provides bidding and transaction filtering on top of base builder functionality.
@@ -0,0 +1,4 @@
+CREATE TABLE builder_registrations (
+    account TEXT PRIMARY KEY,
+    data BYTEA NOT NULL
+);
@@ -4,8 +4,8 @@ use std::{
 };
 
 use espresso_types::{
-    v0_99::{BidTx, RollupRegistration, RollupUpdate},
-    NamespaceId,
+    v0_99::{BidTx, BuilderRegistration, BundleReservation, RollupRegistration, RollupUpdate},
+    FeeAccount, NamespaceId,
 };
 use futures::FutureExt;
 use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
@@ -31,6 +31,14 @@ pub enum SolverError {
     SignatureKeysMismatch(String),
     #[error("Signature key {0} does not match signatures in the database")]
     SignatureDatabaseKeysMismatch(String),
+    #[error("builder not registered: {0}")]
+    BuilderNotRegistered(FeeAccount),
+    #[error("auction for view {0} is closed")]
+    AuctionClosed(u64),
+    #[error("auction for view {0} is still open")]
+    AuctionOpen(u64),
+    #[error("bundle slot for namespace {0} in view {1} is already reserved")]
+    BundleSlotReserved(NamespaceId, u64),
     #[error("bincode err: {0}")]
     BincodeError(String),
     #[error("database err: {0}")]
@@ -101,6 +109,20 @@ where
         }
         .boxed()
     })?
+    .post("register_builder", |req, state| {
+        async move {
+            let body = req.body_json::<BuilderRegistration>()?;
+            state.register_builder(body).await
+        }
+        .boxed()
+    })?
+    .post("reserve_bundle", |req, state| {
+        async move {
+            let reservation = req.body_json::<BundleReservation>()?;
+            state.reserve_bundle(reservation).await
+        }
+        .boxed()
+    })?
     .get("auction_results", |req, state| {
         async move {
             let view_num: u64 = req.integer_param("view_number")?;
@@ -5,6 +5,7 @@ use async_lock::RwLock;
 use clap::Parser;
 use espresso_types::MarketplaceVersion;
 use hotshot::helpers::initialize_logging;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
 use marketplace_solver::{
     define_api, handle_events,
     state::{GlobalState, SolverState, StakeTable},
@@ -49,6 +50,8 @@ async fn main() -> anyhow::Result<()> {
             known_nodes_with_stake: startup_info.known_node_with_stake,
         },
         bid_txs: Default::default(),
+        reservations: Default::default(),
+        latest_view: ViewNumber::genesis(),
     };
 
     let global_state = Arc::new(RwLock::new(GlobalState::new(database, solver_state)?));
@@ -1,17 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use async_trait::async_trait;
 use committable::Committable;
 use espresso_types::{
     v0_99::{
-        BidTx, RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody,
-        SolverAuctionResults,
+        BidTx, BuilderRegistration, BundleReservation, RollupRegistration, RollupRegistrationBody,
+        RollupUpdate, RollupUpdatebody, SolverAuctionResults,
     },
-    SeqTypes,
+    FeeAccount, FeeAmount, NamespaceId, SeqTypes,
     Update::Set,
 };
 use hotshot::types::SignatureKey;
-use hotshot_types::{data::ViewNumber, traits::node_implementation::NodeType, PeerConfig};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::node_implementation::{ConsensusTime, NodeType},
+    PeerConfig,
+};
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 
@@ -28,6 +32,10 @@ impl GlobalState {
         &self.solver
     }
 
+    pub fn solver_mut(&mut self) -> &mut SolverState {
+        &mut self.solver
+    }
+
     pub fn database(&self) -> &PgPool {
         self.database.pool()
     }
@@ -42,9 +50,105 @@ impl GlobalState {
     }
 }
 
+/// Number of views before the latest finished view for which bids and reservations are retained.
+const RETAINED_VIEWS: u64 = 100;
+
+/// Number of views ahead of a view that its auction closes.
+///
+/// The auction for a view closes when the view this many views earlier finishes, so that its leader
+/// can fetch the results in time to build its block. Until then, the bids are kept secret.
+pub const AUCTION_LEAD_VIEWS: u64 = 2;
+
 pub struct SolverState {
     pub stake_table: StakeTable,
     pub bid_txs: HashMap<ViewNumber, HashMap<<SeqTypes as NodeType>::BuilderSignatureKey, BidTx>>,
+    /// Bundle slots reserved by builders, by view and namespace.
+    pub reservations: HashMap<ViewNumber, HashMap<NamespaceId, BundleReservation>>,
+    /// The latest view known to have finished.
+    ///
+    /// Bids and reservations are only accepted for views whose auction is still open, and results
+    /// are only served once it has closed (see [`AUCTION_LEAD_VIEWS`]).
+    pub latest_view: ViewNumber,
+}
+
+impl SolverState {
+    /// Record that `view` has finished, closing the auction [`AUCTION_LEAD_VIEWS`] views ahead.
+    ///
+    /// Bids and reservations for views long past are dropped.
+    pub fn view_finished(&mut self, view: ViewNumber) {
+        self.latest_view = self.latest_view.max(view);
+
+        let horizon = ViewNumber::new(self.latest_view.u64().saturating_sub(RETAINED_VIEWS));
+        self.bid_txs.retain(|view, _| *view >= horizon);
+        self.reservations.retain(|view, _| *view >= horizon);
+    }
+
+    /// Whether the auction for `view` is still open.
+    fn auction_open(&self, view: ViewNumber) -> bool {
+        view.u64() > self.latest_view.u64().saturating_add(AUCTION_LEAD_VIEWS)
+    }
+
+    /// Check that the auction for `view` is still open.
+    fn ensure_auction_open(&self, view: ViewNumber) -> SolverResult<()> {
+        if !self.auction_open(view) {
+            return Err(SolverError::AuctionClosed(view.u64()));
+        }
+        Ok(())
+    }
+
+    /// Check that the auction for `view` has closed, so its bids can be revealed.
+    fn ensure_auction_closed(&self, view: ViewNumber) -> SolverResult<()> {
+        if self.auction_open(view) {
+            return Err(SolverError::AuctionOpen(view.u64()));
+        }
+        Ok(())
+    }
+
+    /// Select the winning bids for `view`.
+    ///
+    /// Bids are considered from highest to lowest. A bid wins if none of its namespaces has been
+    /// won by a higher bid and it meets the sum of the reserve prices of the registered rollups it
+    /// bids for.
+    fn winning_bids(
+        &self,
+        view: ViewNumber,
+        rollups: &HashMap<NamespaceId, RollupRegistrationBody>,
+    ) -> Vec<BidTx> {
+        let Some(bids) = self.bid_txs.get(&view) else {
+            return Vec::new();
+        };
+
+        let mut bids = bids.values().collect::<Vec<_>>();
+        // Break ties by account so that every query for the same view gets the same results.
+        bids.sort_by(|a, b| {
+            b.amount()
+                .cmp(&a.amount())
+                .then_with(|| a.account().cmp(&b.account()))
+        });
+
+        let mut won = HashSet::new();
+        let mut winners = Vec::new();
+        for bid in bids {
+            let namespaces = bid.namespaces();
+            if namespaces.is_empty() || namespaces.iter().any(|ns| won.contains(ns)) {
+                continue;
+            }
+
+            let reserve_price = namespaces
+                .iter()
+                .filter_map(|ns| rollups.get(ns))
+                .fold(FeeAmount::default(), |sum, rollup| {
+                    sum + rollup.reserve_price
+                });
+            if bid.amount() < reserve_price {
+                continue;
+            }
+
+            won.extend(namespaces.iter().copied());
+            winners.push(bid.clone());
+        }
+        winners
+    }
 }
 
 pub struct StakeTable {
@@ -55,6 +159,13 @@ pub struct StakeTable {
 pub trait UpdateSolverState {
     async fn submit_bid_tx(&mut self, bid_tx: BidTx) -> SolverResult<()>;
 
+    async fn register_builder(
+        &self,
+        registration: BuilderRegistration,
+    ) -> SolverResult<BuilderRegistration>;
+
+    async fn reserve_bundle(&mut self, reservation: BundleReservation) -> SolverResult<()>;
+
     async fn register_rollup(
         &self,
         registration: RollupRegistration,
@@ -84,11 +195,65 @@ impl UpdateSolverState for GlobalState {
         let view = bid_tx.view();
         let builder_key = bid_tx.account();
 
+        if bid_tx.verify().is_err() {
+            return Err(SolverError::InvalidSignature(builder_key.to_string()));
+        }
+        self.solver.ensure_auction_open(view)?;
+        self.ensure_builder_registered(builder_key).await?;
+
         let bid_txs = &mut self.solver.bid_txs;
         bid_txs.entry(view).or_default().insert(builder_key, bid_tx);
         Ok(())
     }
 
+    async fn register_builder(
+        &self,
+        registration: BuilderRegistration,
+    ) -> SolverResult<BuilderRegistration> {
+        let account = registration.body.account;
+
+        if !registration.verify() {
+            return Err(SolverError::InvalidSignature(account.to_string()));
+        }
+
+        // Registrations are signed by the builder itself, so a builder may re-register to update
+        // its URL.
+        let bytes = bincode::serialize(&registration)?;
+        sqlx::query(
+            "INSERT INTO builder_registrations VALUES ($1, $2)
+             ON CONFLICT (account) DO UPDATE SET data = excluded.data;",
+        )
+        .bind(account.to_string())
+        .bind(&bytes)
+        .execute(self.database())
+        .await
+        .map_err(SolverError::from)?;
+
+        Ok(registration)
+    }
+
+    async fn reserve_bundle(&mut self, reservation: BundleReservation) -> SolverResult<()> {
+        let account = reservation.body.account;
+        let view = reservation.body.view;
+
+        if !reservation.verify() {
+            return Err(SolverError::InvalidSignature(account.to_string()));
+        }
+        self.solver.ensure_auction_open(view)?;
+        self.ensure_builder_registered(account).await?;
+
+        let slots = self.solver.reservations.entry(view).or_default();
+        for ns in &reservation.body.namespaces {
+            if slots.get(ns).is_some_and(|r| r.body.account != account) {
+                return Err(SolverError::BundleSlotReserved(*ns, view.u64()));
+            }
+        }
+        for ns in &reservation.body.namespaces {
+            slots.insert(*ns, reservation.clone());
+        }
+        Ok(())
+    }
+
     async fn register_rollup(
         &self,
         registration: RollupRegistration,
@@ -263,42 +428,92 @@ impl UpdateSolverState for GlobalState {
         &self,
         view_number: ViewNumber,
     ) -> SolverResult<SolverAuctionResults> {
-        // todo (ab): actual logic needs to implemented
-        // for we, we just return some default results
-
-        let rollups = self.get_all_rollup_registrations().await?;
-
-        let results = SolverAuctionResults::new(
-            view_number,
-            Vec::new(),
-            rollups
-                .into_iter()
-                .filter_map(|r| Some((r.body.namespace_id, r.body.reserve_url?)))
-                .collect(),
-        );
-
-        Ok(results)
+        self.auction_results(view_number).await
     }
+
     async fn calculate_auction_results_permissioned(
         &self,
         view_number: ViewNumber,
         _signauture: <SeqTypes as NodeType>::SignatureKey,
     ) -> SolverResult<SolverAuctionResults> {
-        // todo (ab): actual logic needs to implemented
-        // for we, we just return some default results
+        self.auction_results(view_number).await
+    }
+}
 
-        let rollups = self.get_all_rollup_registrations().await?;
+impl GlobalState {
+    /// Look up the registration of a builder account.
+    pub async fn builder_registration(
+        &self,
+        account: FeeAccount,
+    ) -> SolverResult<Option<BuilderRegistration>> {
+        let data: Option<Vec<u8>> =
+            sqlx::query_scalar("SELECT data FROM builder_registrations WHERE account = $1;")
+                .bind(account.to_string())
+                .fetch_optional(self.database())
+                .await
+                .map_err(SolverError::from)?;
 
-        let results = SolverAuctionResults::new(
-            view_number,
-            Vec::new(),
-            rollups
-                .into_iter()
-                .filter_map(|r| Some((r.body.namespace_id, r.body.reserve_url?)))
-                .collect(),
-        );
+        data.map(|data| bincode::deserialize(&data).map_err(SolverError::from))
+            .transpose()
+    }
+
+    async fn ensure_builder_registered(&self, account: FeeAccount) -> SolverResult<()> {
+        match self.builder_registration(account).await? {
+            Some(_) => Ok(()),
+            None => Err(SolverError::BuilderNotRegistered(account)),
+        }
+    }
 
-        Ok(results)
+    /// Compute the results of the auction for `view_number`, once it has closed.
+    ///
+    /// Each namespace not covered by a winning bid is served by the builder which reserved a
+    /// bundle slot for it, if any, and otherwise by the rollup's reserve builder.
+    async fn auction_results(&self, view_number: ViewNumber) -> SolverResult<SolverAuctionResults> {
+        self.solver.ensure_auction_closed(view_number)?;
+
+        let rollups = self
+            .get_all_rollup_registrations()
+            .await?
+            .into_iter()
+            .map(|r| (r.body.namespace_id, r.body))
+            .collect::<HashMap<_, _>>();
+
+        let winning_bids = self.solver.winning_bids(view_number, &rollups);
+        let won = winning_bids
+            .iter()
+            .flat_map(|bid| bid.namespaces().iter().copied())
+            .collect::<HashSet<_>>();
+
+        let reservations = self.solver.reservations.get(&view_number);
+        let mut namespaces = rollups
+            .keys()
+            .chain(reservations.into_iter().flat_map(|r| r.keys()))
+            .copied()
+            .filter(|ns| !won.contains(ns))
+            .collect::<Vec<_>>();
+        namespaces.sort();
+        namespaces.dedup();
+
+        let mut reserve_bids = Vec::new();
+        for ns in namespaces {
+            let reserved = match reservations.and_then(|r| r.get(&ns)) {
+                Some(reservation) => self
+                    .builder_registration(reservation.body.account)
+                    .await?
+                    .map(|registration| registration.body.url),
+                None => None,
+            };
+            let url = reserved.or_else(|| rollups.get(&ns)?.reserve_url.clone());
+            if let Some(url) = url {
+                reserve_bids.push((ns, url));
+            }
+        }
+
+        Ok(SolverAuctionResults::new(
+            view_number,
+            winning_bids,
+            reserve_bids,
+        ))
     }
 }
 
@@ -348,6 +563,8 @@ impl SolverState {
                 known_nodes_with_stake: crate::mock::generate_stake_table(),
             },
             bid_txs: Default::default(),
+            reservations: Default::default(),
+            latest_view: ViewNumber::genesis(),
         }
     }
 }
@@ -9,6 +9,7 @@ use std::sync::Arc;
 use async_lock::RwLock;
 use espresso_types::MarketplaceVersion;
 use hotshot_query_service::data_source::sql::testing::TmpDb;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
 use portpicker::pick_unused_port;
 use tide_disco::{App, Url};
 use tokio::{spawn, task::JoinHandle};
@@ -70,6 +71,8 @@ impl MockSolver {
                 known_nodes_with_stake: startup_info.known_node_with_stake,
             },
             bid_txs: Default::default(),
+            reservations: Default::default(),
+            latest_view: ViewNumber::genesis(),
         };
 
         let state = Arc::new(RwLock::new(
@@ -126,17 +129,29 @@ mod test {
 
     use committable::Committable;
     use espresso_types::{
+        eth_signature_key::EthKeyPair,
         v0_99::{
-            BidTx, RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody,
+            BidTx, BidTxBody, BuilderRegistration, BuilderRegistrationBody, BundleReservationBody,
+            RollupRegistration, RollupRegistrationBody, RollupUpdate, RollupUpdatebody,
+            SolverAuctionResults,
         },
-        FeeAccount, MarketplaceVersion, SeqTypes,
+        FeeAccount, MarketplaceVersion, NamespaceId, SeqTypes,
         Update::{Set, Skip},
     };
     use hotshot::types::{BLSPubKey, SignatureKey};
-    use hotshot_types::traits::node_implementation::NodeType;
+    use hotshot_types::{
+        data::ViewNumber,
+        traits::{
+            node_implementation::{ConsensusTime, NodeType},
+            signature_key::BuilderSignatureKey,
+        },
+    };
     use tide_disco::Url;
 
-    use crate::{testing::MockSolver, SolverError};
+    use crate::{state::AUCTION_LEAD_VIEWS, testing::MockSolver, SolverError};
+
+    /// A view far enough ahead that its auction is still open for the duration of a test.
+    const FUTURE_VIEW: u64 = 1_000_000;
 
     async fn register_rollup_helper(
         namespace_id: u64,
@@ -500,6 +515,41 @@ mod test {
             .unwrap_err();
     }
 
+    fn bid_helper(key: &EthKeyPair, amount: u64, view: u64, namespaces: &[u64]) -> BidTx {
+        BidTxBody::new(
+            key.fee_account(),
+            amount.into(),
+            ViewNumber::new(view),
+            namespaces.iter().map(|ns| (*ns).into()).collect(),
+            Url::from_str("http://localhost:3939").unwrap(),
+            Default::default(),
+        )
+        .signed(key)
+        .unwrap()
+    }
+
+    async fn register_builder_helper(
+        client: &surf_disco::Client<SolverError, MarketplaceVersion>,
+        key: &EthKeyPair,
+        url: &str,
+    ) {
+        let registration = BuilderRegistrationBody {
+            account: key.fee_account(),
+            url: Url::from_str(url).unwrap(),
+        }
+        .signed(key)
+        .unwrap();
+
+        let result: BuilderRegistration = client
+            .post("register_builder")
+            .body_json(&registration)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(result, registration);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_bid_submission() {
         let mock_solver = MockSolver::init().await;
@@ -510,7 +560,19 @@ mod test {
         client.connect(None).await;
 
         let key = FeeAccount::test_key_pair();
-        let tx = BidTx::mock(key);
+        let tx = bid_helper(&key, 10, FUTURE_VIEW, &[1]);
+
+        // Bids from unregistered builders are rejected.
+        let err = client
+            .post::<()>("submit_bid")
+            .body_json(&tx)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SolverError::BuilderNotRegistered(_)), "{err}");
+
+        register_builder_helper(&client, &key, "http://localhost:3939").await;
 
         client
             .post::<()>("submit_bid")
@@ -519,6 +581,161 @@ mod test {
             .send()
             .await
             .unwrap();
+
+        // Bids signed by a key other than the bidding account are rejected.
+        let other_key = FeeAccount::generated_from_seed_indexed([0u8; 32], 1).1;
+        let forged = BidTxBody::new(
+            key.fee_account(),
+            10u64.into(),
+            ViewNumber::new(FUTURE_VIEW),
+            vec![1u64.into()],
+            Url::from_str("http://localhost:3939").unwrap(),
+            Default::default(),
+        )
+        .signed(&other_key)
+        .unwrap();
+        let err = client
+            .post::<()>("submit_bid")
+            .body_json(&forged)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SolverError::InvalidSignature(_)), "{err}");
+
+        // Bids for views which have already finished are rejected.
+        let err = client
+            .post::<()>("submit_bid")
+            .body_json(&bid_helper(&key, 10, 0, &[1]))
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SolverError::AuctionClosed(0)), "{err}");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_auction_results() {
+        let mock_solver = MockSolver::init().await;
+        let solver_api = mock_solver.solver_api();
+        let client = surf_disco::Client::<SolverError, MarketplaceVersion>::new(solver_api);
+        client.connect(None).await;
+
+        // Rollups 1 and 2 have reserve builders, rollup 3 does not.
+        for (ns, reserve_url) in [
+            (1, Some("http://reserve1")),
+            (2, Some("http://reserve2")),
+            (3, None),
+        ] {
+            let (reg, ..) = register_rollup_helper(ns, reserve_url, 100, true, "test").await;
+            let _: RollupRegistration = client
+                .post("register_rollup")
+                .body_json(&reg)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let keys = (1..=3)
+            .map(|i| FeeAccount::generated_from_seed_indexed([1u8; 32], i).1)
+            .collect::<Vec<_>>();
+        for (i, key) in keys.iter().enumerate() {
+            register_builder_helper(&client, key, &format!("http://builder{i}")).await;
+        }
+
+        // Builder 0 outbids builder 1 for namespace 1. Builder 2 bids below the reserve price.
+        let winner = bid_helper(&keys[0], 200, FUTURE_VIEW, &[1]);
+        for bid in [
+            winner.clone(),
+            bid_helper(&keys[1], 150, FUTURE_VIEW, &[1, 2]),
+            bid_helper(&keys[2], 50, FUTURE_VIEW, &[3]),
+        ] {
+            client
+                .post::<()>("submit_bid")
+                .body_json(&bid)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+        }
+
+        // Builder 2 reserves the bundle slot for namespace 2.
+        let reservation = BundleReservationBody {
+            account: keys[2].fee_account(),
+            view: ViewNumber::new(FUTURE_VIEW),
+            namespaces: vec![2u64.into()],
+        }
+        .signed(&keys[2])
+        .unwrap();
+        client
+            .post::<()>("reserve_bundle")
+            .body_json(&reservation)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Another builder cannot take the same slot.
+        let conflicting = BundleReservationBody {
+            account: keys[1].fee_account(),
+            view: ViewNumber::new(FUTURE_VIEW),
+            namespaces: vec![2u64.into()],
+        }
+        .signed(&keys[1])
+        .unwrap();
+        let err = client
+            .post::<()>("reserve_bundle")
+            .body_json(&conflicting)
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SolverError::BundleSlotReserved(..)), "{err}");
+
+        // The bids are not revealed while the auction is open.
+        let err = client
+            .get::<SolverAuctionResults>(&format!("auction_results/{FUTURE_VIEW}"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SolverError::AuctionOpen(FUTURE_VIEW)),
+            "{err}"
+        );
+
+        // Close the auction.
+        mock_solver
+            .state()
+            .write()
+            .await
+            .solver_mut()
+            .view_finished(ViewNumber::new(FUTURE_VIEW - AUCTION_LEAD_VIEWS));
+        let err = client
+            .post::<()>("submit_bid")
+            .body_json(&bid_helper(&keys[1], 300, FUTURE_VIEW, &[1]))
+            .unwrap()
+            .send()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SolverError::AuctionClosed(FUTURE_VIEW)),
+            "{err}"
+        );
+
+        let results: SolverAuctionResults = client
+            .get(&format!("auction_results/{FUTURE_VIEW}"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(results.winning_bids(), [winner]);
+        assert_eq!(
+            results.reserve_bids(),
+            [(
+                NamespaceId::from(2u64),
+                Url::from_str("http://builder2").unwrap()
+            )]
+        );
     }
 
     #[tokio::test(flavor = "multi_thread")]
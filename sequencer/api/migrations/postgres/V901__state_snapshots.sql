@@ -0,0 +1,11 @@
+CREATE TABLE state_diff (
+    height BIGINT PRIMARY KEY,
+    data BYTEA NOT NULL
+);
+
+CREATE TABLE state_snapshot (
+    height BIGINT NOT NULL,
+    chunk INTEGER NOT NULL,
+    data BYTEA NOT NULL,
+    PRIMARY KEY (height, chunk)
+);
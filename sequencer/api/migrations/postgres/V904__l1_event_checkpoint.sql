@@ -0,0 +1,6 @@
+CREATE TABLE l1_event_checkpoint (
+  contract TEXT PRIMARY KEY,
+  l1_block BIGINT NOT NULL,
+  last_event_block BIGINT,
+  last_event_index BIGINT
+);
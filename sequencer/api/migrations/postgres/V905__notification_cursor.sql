@@ -0,0 +1,9 @@
+CREATE TABLE notification_cursor (
+  sink TEXT PRIMARY KEY,
+  height BIGINT NOT NULL
+);
+
+CREATE TABLE notification_queue (
+  height BIGINT PRIMARY KEY,
+  data BYTEA NOT NULL
+);
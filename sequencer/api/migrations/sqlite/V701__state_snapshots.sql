@@ -0,0 +1,11 @@
+CREATE TABLE state_diff (
+    height BIGINT PRIMARY KEY,
+    data BLOB NOT NULL
+);
+
+CREATE TABLE state_snapshot (
+    height BIGINT NOT NULL,
+    chunk INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (height, chunk)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE pending_transaction (
+  hash TEXT PRIMARY KEY,
+  data BLOB NOT NULL,
+  submitted INTEGER NOT NULL
+);
+
+CREATE INDEX pending_transaction_submitted_idx ON pending_transaction (submitted);
@@ -0,0 +1,5 @@
+CREATE TABLE drb_input (
+  epoch BIGINT PRIMARY KEY,
+  iteration BIGINT NOT NULL,
+  value BLOB NOT NULL
+);
@@ -0,0 +1,557 @@
+//! Alerting on the node's own metrics.
+//!
+//! Operators running a single node often do not run a Prometheus server and Alertmanager alongside
+//! it. For them, the node can evaluate a few alert rules over its own metrics and notify them
+//! through webhooks (including Slack incoming webhooks) when a rule starts or stops firing.
+//!
+//! Rules are read from a TOML file given by `ESPRESSO_SEQUENCER_ALERT_CONFIG`, for example:
+//!
+//! ```toml
+//! # Seconds between evaluations of the rules
+//! interval = 15
+//! # Seconds after startup during which no alert fires, while the node catches up
+//! startup_grace = 300
+//! # Name of this node, included in notifications
+//! node = "my-node"
+//!
+//! [[webhook]]
+//! url = "https://hooks.slack.com/services/..."
+//! format = "slack"
+//!
+//! # No decide for 2 minutes
+//! [[rule]]
+//! name = "no-decide"
+//! metric = "consensus_last_decided_time"
+//! kind = "age"
+//! above = 120
+//!
+//! # More than one timeout every 10 seconds, sustained for 5 minutes
+//! [[rule]]
+//! name = "timeouts"
+//! metric = "consensus_number_of_timeouts"
+//! kind = "rate"
+//! above = 0.1
+//! for = 300
+//!
+//! # L1 head more than 100 blocks ahead of the finalized block
+//! [[rule]]
+//! name = "l1-lag"
+//! metric = "consensus_l1_head"
+//! minus = "consensus_l1_finalized"
+//! above = 100
+//! ```
+//!
+//! Metric names are the fully qualified names served by the `status/metrics` endpoint.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Context};
+use hotshot_query_service::metrics::PrometheusMetrics;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tide_disco::metrics::Metrics as _;
+use tokio::{spawn, time::sleep};
+use url::Url;
+
+use crate::cdn_metrics::{parse_samples, Sample};
+
+/// Time allowed for a webhook to accept a notification
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Alert rules and where to send notifications
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    /// Seconds between evaluations of the rules
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    /// Seconds after startup during which no alert fires, while the node catches up
+    #[serde(default = "default_startup_grace")]
+    pub startup_grace: u64,
+    /// Name of this node, included in notifications
+    pub node: Option<String>,
+    #[serde(default, rename = "webhook")]
+    pub webhooks: Vec<Webhook>,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<AlertRule>,
+}
+
+fn default_interval() -> u64 {
+    15
+}
+
+fn default_startup_grace() -> u64 {
+    300
+}
+
+impl AlertConfig {
+    /// Load and validate the alert configuration in the TOML file at `path`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path).context("reading alert configuration")?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(text).context("malformed alert configuration")?;
+        ensure!(config.interval > 0, "alert interval must be positive");
+        for rule in &config.rules {
+            ensure!(
+                rule.above.is_some() || rule.below.is_some(),
+                "alert rule {} has neither `above` nor `below` threshold",
+                rule.name
+            );
+        }
+        Ok(config)
+    }
+}
+
+/// A destination for notifications
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: Url,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+/// The body of the request sent to a webhook
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// A JSON object describing the alert
+    #[default]
+    Json,
+    /// A Slack incoming webhook message
+    Slack,
+}
+
+/// How the value compared against a rule's thresholds is derived from its metric
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// The current value of the metric
+    #[default]
+    Value,
+    /// The per-second increase of the metric since the previous evaluation
+    Rate,
+    /// Seconds elapsed since the time held by the metric, as a Unix timestamp
+    Age,
+}
+
+/// A condition on the node's metrics which should notify the operator
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    pub name: String,
+    /// The metric the rule is evaluated over
+    pub metric: String,
+    /// Only consider samples of the metric with these labels
+    ///
+    /// If several samples match, the largest value is used.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Another metric whose value is subtracted from `metric`
+    pub minus: Option<String>,
+    #[serde(default)]
+    pub kind: AlertKind,
+    /// Fire when the value is above this threshold
+    pub above: Option<f64>,
+    /// Fire when the value is below this threshold
+    pub below: Option<f64>,
+    /// Seconds the condition must hold before the rule fires
+    #[serde(default, rename = "for")]
+    pub for_secs: u64,
+}
+
+impl AlertRule {
+    /// The value of `metric` (with our labels) in `samples`, if present
+    fn lookup(&self, samples: &[Sample], metric: &str) -> Option<f64> {
+        samples
+            .iter()
+            .filter(|sample| {
+                sample.name == metric
+                    && self.labels.iter().all(|(name, value)| {
+                        sample.labels.iter().any(|(n, v)| n == name && v == value)
+                    })
+            })
+            .map(|sample| sample.value)
+            .reduce(f64::max)
+    }
+
+    /// The raw value of the rule's expression, before applying `kind`
+    fn raw_value(&self, samples: &[Sample]) -> Option<f64> {
+        let value = self.lookup(samples, &self.metric)?;
+        match &self.minus {
+            Some(minus) => Some(value - self.lookup(samples, minus)?),
+            None => Some(value),
+        }
+    }
+
+    fn is_violated(&self, value: f64) -> bool {
+        self.above.is_some_and(|above| value > above)
+            || self.below.is_some_and(|below| value < below)
+    }
+}
+
+/// Whether an alert started or stopped firing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// A change in the status of an alert, to be sent to the webhooks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub rule: String,
+    pub status: AlertStatus,
+    pub value: Option<f64>,
+}
+
+/// Evaluation state of a single rule
+#[derive(Clone, Debug, Default)]
+struct RuleState {
+    /// Time and raw value at the previous evaluation, for computing rates
+    previous: Option<(f64, f64)>,
+    /// Time since which the condition has held, if it currently holds
+    violated_since: Option<f64>,
+    firing: bool,
+}
+
+/// Periodically evaluates alert rules over the node's metrics and sends notifications
+#[derive(Debug)]
+pub struct AlertManager {
+    config: AlertConfig,
+    metrics: PrometheusMetrics,
+    states: Vec<RuleState>,
+    /// Time of the first evaluation, from which the startup grace period runs
+    started: Option<f64>,
+}
+
+impl AlertManager {
+    pub fn new(config: AlertConfig, metrics: PrometheusMetrics) -> Self {
+        let states = vec![RuleState::default(); config.rules.len()];
+        Self {
+            config,
+            metrics,
+            states,
+            started: None,
+        }
+    }
+
+    /// Evaluate the rules forever
+    pub async fn run(mut self) {
+        let client = reqwest::Client::new();
+        let interval = Duration::from_secs(self.config.interval);
+        tracing::info!(
+            rules = self.config.rules.len(),
+            webhooks = self.config.webhooks.len(),
+            "evaluating alert rules every {interval:?}"
+        );
+        loop {
+            match self.metrics.export() {
+                Ok(text) => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs_f64();
+                    for notification in self.evaluate(&parse_samples(&text), now) {
+                        self.notify(&client, &notification);
+                    }
+                },
+                Err(err) => tracing::warn!("failed to read metrics for alerting: {err}"),
+            }
+            sleep(interval).await;
+        }
+    }
+
+    /// Evaluate each rule over `samples`, taken at Unix time `now`.
+    ///
+    /// Returns a notification for each rule which started or stopped firing.
+    fn evaluate(&mut self, samples: &[Sample], now: f64) -> Vec<Notification> {
+        let started = *self.started.get_or_insert(now);
+        let in_grace = now - started < self.config.startup_grace as f64;
+        let mut notifications = vec![];
+        for (rule, state) in self.config.rules.iter().zip(&mut self.states) {
+            let raw = rule.raw_value(samples);
+            let value = match (rule.kind, raw) {
+                (_, None) => None,
+                (AlertKind::Value, Some(raw)) => Some(raw),
+                (AlertKind::Age, Some(raw)) => Some(now - raw),
+                (AlertKind::Rate, Some(raw)) => state
+                    .previous
+                    .filter(|(time, _)| now > *time)
+                    .map(|(time, prev)| (raw - prev) / (now - time)),
+            };
+            if let Some(raw) = raw {
+                state.previous = Some((now, raw));
+            }
+
+            // A missing metric neither fires nor resolves an alert.
+            let Some(value) = value else {
+                continue;
+            };
+            if rule.is_violated(value) {
+                let since = *state.violated_since.get_or_insert(now);
+                if !state.firing && !in_grace && now - since >= rule.for_secs as f64 {
+                    state.firing = true;
+                    notifications.push(Notification {
+                        rule: rule.name.clone(),
+                        status: AlertStatus::Firing,
+                        value: Some(value),
+                    });
+                }
+            } else {
+                state.violated_since = None;
+                if state.firing {
+                    state.firing = false;
+                    notifications.push(Notification {
+                        rule: rule.name.clone(),
+                        status: AlertStatus::Resolved,
+                        value: Some(value),
+                    });
+                }
+            }
+        }
+        notifications
+    }
+
+    /// Send `notification` to every webhook
+    ///
+    /// The requests are sent from a separate task, so a slow webhook does not delay the evaluation
+    /// of the rules.
+    fn notify(&self, client: &reqwest::Client, notification: &Notification) {
+        match notification.status {
+            AlertStatus::Firing => {
+                tracing::warn!(
+                    rule = notification.rule,
+                    value = notification.value,
+                    "alert firing"
+                )
+            },
+            AlertStatus::Resolved => {
+                tracing::info!(
+                    rule = notification.rule,
+                    value = notification.value,
+                    "alert resolved"
+                )
+            },
+        }
+        let requests = self
+            .config
+            .webhooks
+            .iter()
+            .map(|webhook| {
+                (
+                    webhook.url.clone(),
+                    self.webhook_body(webhook.format, notification),
+                )
+            })
+            .collect::<Vec<_>>();
+        let client = client.clone();
+        spawn(async move {
+            for (url, body) in requests {
+                let res = client
+                    .post(url.clone())
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .json(&body)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status());
+                if let Err(err) = res {
+                    tracing::warn!(%url, "failed to send alert notification: {err:#}");
+                }
+            }
+        });
+    }
+
+    fn webhook_body(
+        &self,
+        format: WebhookFormat,
+        notification: &Notification,
+    ) -> serde_json::Value {
+        match format {
+            WebhookFormat::Json => json!({
+                "node": self.config.node,
+                "rule": notification.rule,
+                "status": notification.status,
+                "value": notification.value,
+            }),
+            WebhookFormat::Slack => {
+                let status = match notification.status {
+                    AlertStatus::Firing => "firing",
+                    AlertStatus::Resolved => "resolved",
+                };
+                let node = match &self.config.node {
+                    Some(node) => format!(" on {node}"),
+                    None => String::new(),
+                };
+                let value = match notification.value {
+                    Some(value) => format!(" (value {value})"),
+                    None => String::new(),
+                };
+                json!({ "text": format!("Alert {} {status}{node}{value}", notification.rule) })
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample(name: &str, value: f64) -> Sample {
+        Sample {
+            name: name.into(),
+            labels: vec![],
+            value,
+        }
+    }
+
+    const CONFIG: &str = r#"
+        node = "test"
+        startup_grace = 0
+
+        [[webhook]]
+        url = "http://localhost:1234/alerts"
+
+        [[rule]]
+        name = "no-decide"
+        metric = "consensus_last_decided_time"
+        kind = "age"
+        above = 120
+
+        [[rule]]
+        name = "timeouts"
+        metric = "consensus_number_of_timeouts"
+        kind = "rate"
+        above = 0.1
+        for = 20
+
+        [[rule]]
+        name = "l1-lag"
+        metric = "consensus_l1_head"
+        minus = "consensus_l1_finalized"
+        above = 100
+    "#;
+
+    #[test]
+    fn test_parse_alert_config() {
+        let config = AlertConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.interval, 15);
+        assert_eq!(config.startup_grace, 0);
+        assert_eq!(config.webhooks[0].format, WebhookFormat::Json);
+        assert_eq!(config.rules.len(), 3);
+        assert_eq!(config.rules[1].kind, AlertKind::Rate);
+        assert_eq!(config.rules[1].for_secs, 20);
+
+        // A rule must have a threshold.
+        AlertConfig::parse(
+            r#"
+            [[rule]]
+            name = "bad"
+            metric = "consensus_current_view"
+            "#,
+        )
+        .unwrap_err();
+
+        // Rules must be evaluated at some interval.
+        AlertConfig::parse("interval = 0").unwrap_err();
+    }
+
+    #[test]
+    fn test_startup_grace() {
+        let config = AlertConfig::parse(
+            r#"
+            [[rule]]
+            name = "no-decide"
+            metric = "consensus_last_decided_time"
+            kind = "age"
+            above = 120
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.startup_grace, 300);
+        let mut manager = AlertManager::new(config, PrometheusMetrics::default());
+        let samples = [sample("consensus_last_decided_time", 0.0)];
+
+        // Nothing has been decided yet, but the node has only just started.
+        assert!(manager.evaluate(&samples, 1000.0).is_empty());
+        assert!(manager.evaluate(&samples, 1299.0).is_empty());
+
+        // The grace period is over.
+        assert_eq!(
+            manager.evaluate(&samples, 1300.0),
+            [Notification {
+                rule: "no-decide".into(),
+                status: AlertStatus::Firing,
+                value: Some(1300.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_alerts() {
+        let config = AlertConfig::parse(CONFIG).unwrap();
+        let mut manager = AlertManager::new(config, PrometheusMetrics::default());
+        let firing = |rule: &str, value| Notification {
+            rule: rule.into(),
+            status: AlertStatus::Firing,
+            value: Some(value),
+        };
+        let resolved = |rule: &str, value| Notification {
+            rule: rule.into(),
+            status: AlertStatus::Resolved,
+            value: Some(value),
+        };
+
+        // Everything healthy.
+        let samples = |decided: f64, timeouts: f64, head: f64| {
+            vec![
+                sample("consensus_last_decided_time", decided),
+                sample("consensus_number_of_timeouts", timeouts),
+                sample("consensus_l1_head", head),
+                sample("consensus_l1_finalized", 1000.0),
+            ]
+        };
+        assert!(manager
+            .evaluate(&samples(1000.0, 0.0, 1010.0), 1000.0)
+            .is_empty());
+
+        // No decide for too long, and the L1 lags.
+        assert_eq!(
+            manager.evaluate(&samples(1000.0, 0.0, 1200.0), 1130.0),
+            [firing("no-decide", 130.0), firing("l1-lag", 200.0)]
+        );
+        // Alerts only fire once.
+        assert!(manager
+            .evaluate(&samples(1000.0, 0.0, 1200.0), 1140.0)
+            .is_empty());
+
+        // Timeouts start; the rate rule must hold for 20 seconds before firing.
+        assert_eq!(
+            manager.evaluate(&samples(1150.0, 5.0, 1010.0), 1150.0),
+            [resolved("no-decide", 0.0), resolved("l1-lag", 10.0)]
+        );
+        assert!(manager
+            .evaluate(&samples(1150.0, 10.0, 1010.0), 1160.0)
+            .is_empty());
+        assert_eq!(
+            manager.evaluate(&samples(1150.0, 15.0, 1010.0), 1170.0),
+            [firing("timeouts", 0.5)]
+        );
+        assert_eq!(
+            manager.evaluate(&samples(1180.0, 15.0, 1010.0), 1180.0),
+            [resolved("timeouts", 0.0)]
+        );
+
+        // Missing metrics do not change the state of an alert.
+        assert!(manager.evaluate(&[], 2000.0).is_empty());
+    }
+}
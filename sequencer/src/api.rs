@@ -1,13 +1,15 @@
-use std::{pin::Pin, sync::Arc};
+use std::{collections::BTreeSet, pin::Pin, sync::Arc};
 
 use alloy::primitives::Address;
 use anyhow::{bail, Context};
 use async_lock::RwLock;
 use async_once_cell::Lazy;
 use async_trait::async_trait;
-use committable::Commitment;
+use committable::{Commitment, Committable};
 use data_source::{
-    CatchupDataSource, StakeTableDataSource, StakeTableWithEpochNumber, SubmitDataSource,
+    CatchupDataSource, CurrentLeaderSchedule, EpochLeaders, FeatureDataSource,
+    PeerReputationDataSource, RewardDistributionDataSource, StakeTableDataSource,
+    StakeTableWithEpochNumber, SubmitDataSource, UPCOMING_LEADER_VIEWS,
 };
 use derivative::Derivative;
 use espresso_types::{
@@ -16,9 +18,10 @@ use espresso_types::{
     v0::traits::SequencerPersistence,
     v0_1::{RewardAccount, RewardAccountProof, RewardMerkleTree},
     v0_3::Validator,
-    v0_99::ChainConfig,
-    AccountQueryData, BlockMerkleTree, FeeAccount, FeeAccountProof, FeeMerkleTree, Leaf2,
-    NodeState, PubKey, Transaction, ValidatedState,
+    v0_4::ChainConfig,
+    AccountQueryData, BlockMerkleTree, EpochCommittees, FeeAccount, FeeAccountProof, FeeMerkleTree,
+    Leaf2, NamespaceRegistry, NodeState, PubKey, RewardDistribution, ThresholdEncryptionKey,
+    Transaction, TransactionStatus, ValidatedState,
 };
 use futures::{
     future::{BoxFuture, Future, FutureExt},
@@ -28,14 +31,18 @@ use hotshot::types::BLSPubKey;
 use hotshot_events_service::events_source::{
     EventFilterSet, EventsSource, EventsStreamer, StartupInfo,
 };
-use hotshot_query_service::data_source::ExtensibleDataSource;
+use hotshot_query_service::{
+    availability::AvailabilityDataSource, data_source::ExtensibleDataSource, node::NodeDataSource,
+};
 use hotshot_types::{
-    data::ViewNumber,
+    data::{EpochNumber, ViewNumber},
     event::Event,
+    feature_gates::Feature,
+    leader_schedule::LeaderSchedule,
     light_client::StateSignatureRequestBody,
     network::NetworkConfig,
     traits::{
-        network::ConnectedNetwork,
+        network::{ConnectedNetwork, PeerReputation},
         node_implementation::{NodeType, Versions},
         ValidatedState as _,
     },
@@ -52,14 +59,24 @@ use jf_merkle_tree::{
 
 use self::data_source::{HotShotConfigDataSource, NodeStateDataSource, StateSignatureDataSource};
 use crate::{
-    catchup::CatchupStorage, context::Consensus, state_signature::StateSigner, SeqTypes,
-    SequencerApiVersion, SequencerContext,
+    catchup::CatchupStorage,
+    context::Consensus,
+    encryption::Decryptor,
+    pending_transactions::unix_timestamp,
+    state_signature::StateSigner,
+    state_sync::{StateDiff, StateSnapshotInfo},
+    transaction_status::TransactionStatusTracker,
+    SeqTypes, SequencerApiVersion, SequencerContext,
 };
 
 pub mod data_source;
 pub mod endpoints;
 pub mod fs;
+pub mod log_filter;
+pub mod network_pause;
+pub mod ns_proof_cache;
 pub mod options;
+pub mod rate_limit;
 pub mod sql;
 mod update;
 
@@ -76,6 +93,8 @@ struct ConsensusState<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: V
     event_streamer: Arc<RwLock<EventsStreamer<SeqTypes>>>,
     node_state: NodeState,
     network_config: NetworkConfig<SeqTypes>,
+    decryptor: Option<Arc<Decryptor>>,
+    transaction_status: Arc<TransactionStatusTracker>,
 
     #[derivative(Debug = "ignore")]
     handle: Arc<RwLock<Consensus<N, P, V>>>,
@@ -90,6 +109,8 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions>
             event_streamer: ctx.event_streamer(),
             node_state: ctx.node_state(),
             network_config: ctx.network_config(),
+            decryptor: ctx.decryptor(),
+            transaction_status: ctx.transaction_status(),
             handle: ctx.consensus(),
         }
     }
@@ -125,6 +146,50 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> ApiState
         Arc::clone(&self.consensus.as_ref().get().await.get_ref().handle)
     }
 
+    async fn decryptor(&self) -> Option<&Arc<Decryptor>> {
+        self.consensus
+            .as_ref()
+            .get()
+            .await
+            .get_ref()
+            .decryptor
+            .as_ref()
+    }
+
+    async fn transaction_status(&self) -> &TransactionStatusTracker {
+        &self
+            .consensus
+            .as_ref()
+            .get()
+            .await
+            .get_ref()
+            .transaction_status
+    }
+
+    /// The chain config as of the latest decided state.
+    async fn decided_chain_config(&self) -> ChainConfig {
+        // Fetch full chain config from the validated state, if present.
+        // This is necessary because we support chain config upgrades,
+        // so the updated chain config is found in the validated state.
+        let cf = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .decided_state()
+            .await
+            .chain_config
+            .resolve();
+
+        // Use the chain config from the validated state if available,
+        // otherwise, use the node state's chain config
+        // The node state's chain config is the node's base version chain config
+        match cf {
+            Some(cf) => cf,
+            None => self.node_state().await.chain_config,
+        }
+    }
+
     async fn network_config(&self) -> NetworkConfig<SeqTypes> {
         self.consensus
             .as_ref()
@@ -171,6 +236,29 @@ impl<N: ConnectedNetwork<PubKey>, D: Send + Sync, V: Versions, P: SequencerPersi
     async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
         self.as_ref().submit(tx).await
     }
+
+    async fn encryption_key(&self) -> Option<ThresholdEncryptionKey> {
+        self.as_ref().encryption_key().await
+    }
+
+    async fn decrypted(&self, hash: Commitment<Transaction>) -> Option<Transaction> {
+        self.as_ref().decrypted(hash).await
+    }
+
+    async fn transaction_status(&self, hash: Commitment<Transaction>) -> Option<TransactionStatus> {
+        self.as_ref().transaction_status(hash).await
+    }
+
+    async fn subscribe_transaction_status(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> BoxStream<'static, TransactionStatus> {
+        self.as_ref().subscribe_transaction_status(hash).await
+    }
+
+    async fn namespace_registry(&self) -> anyhow::Result<Option<Arc<NamespaceRegistry>>> {
+        self.as_ref().namespace_registry().await
+    }
 }
 
 impl<N: ConnectedNetwork<PubKey>, D: Sync, V: Versions, P: SequencerPersistence>
@@ -189,6 +277,14 @@ impl<N: ConnectedNetwork<PubKey>, D: Sync, V: Versions, P: SequencerPersistence>
         self.as_ref().get_stake_table_current().await
     }
 
+    /// Get the stake table recorded for a given epoch
+    async fn get_stake_table_at(
+        &self,
+        epoch: <SeqTypes as NodeType>::Epoch,
+    ) -> anyhow::Result<Option<Vec<PeerConfig<SeqTypes>>>> {
+        self.as_ref().get_stake_table_at(epoch).await
+    }
+
     /// Get all the validators
     async fn get_validators(
         &self,
@@ -196,6 +292,19 @@ impl<N: ConnectedNetwork<PubKey>, D: Sync, V: Versions, P: SequencerPersistence>
     ) -> anyhow::Result<IndexMap<Address, Validator<BLSPubKey>>> {
         self.as_ref().get_validators(epoch).await
     }
+
+    /// Get the leader schedule for a given epoch
+    async fn get_leader_schedule(
+        &self,
+        epoch: Option<<SeqTypes as NodeType>::Epoch>,
+    ) -> Option<LeaderSchedule<SeqTypes>> {
+        self.as_ref().get_leader_schedule(epoch).await
+    }
+
+    /// Get the leader schedules for the current and next epochs
+    async fn get_leader_schedule_current(&self) -> CurrentLeaderSchedule<SeqTypes> {
+        self.as_ref().get_leader_schedule_current().await
+    }
 }
 
 impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence>
@@ -230,6 +339,22 @@ impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence>
         }
     }
 
+    /// Get the stake table recorded for a given epoch
+    async fn get_stake_table_at(
+        &self,
+        epoch: <SeqTypes as NodeType>::Epoch,
+    ) -> anyhow::Result<Option<Vec<PeerConfig<SeqTypes>>>> {
+        let membership = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .membership_coordinator
+            .membership()
+            .clone();
+        EpochCommittees::stake_table_at(&membership, epoch).await
+    }
+
     /// Get the whole validators map
     async fn get_validators(
         &self,
@@ -248,32 +373,150 @@ impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence>
         let r = mem.coordinator.membership().read().await;
         r.validators(&epoch)
     }
+
+    /// Get the leader schedule for a given epoch
+    async fn get_leader_schedule(
+        &self,
+        epoch: Option<<SeqTypes as NodeType>::Epoch>,
+    ) -> Option<LeaderSchedule<SeqTypes>> {
+        let membership = self
+            .consensus()
+            .await
+            .read()
+            .await
+            .membership_coordinator
+            .membership()
+            .clone();
+        let membership = membership.read().await;
+        membership.leader_schedule(epoch)
+    }
+
+    /// Get the leader schedules for the current and next epochs, and their upcoming leaders
+    async fn get_leader_schedule_current(&self) -> CurrentLeaderSchedule<SeqTypes> {
+        let (view, epoch) = {
+            let handle = self.consensus().await;
+            let handle = handle.read().await;
+            (handle.cur_view().await, handle.cur_epoch().await)
+        };
+        let upcoming = *view..*view + UPCOMING_LEADER_VIEWS;
+        let leaders = |schedule: LeaderSchedule<SeqTypes>| EpochLeaders {
+            upcoming: schedule.leaders(upcoming.clone()),
+            schedule,
+        };
+
+        let current = self.get_leader_schedule(epoch).await.map(leaders);
+        let next = match epoch {
+            Some(epoch) => self.get_leader_schedule(Some(epoch + 1)).await.map(leaders),
+            None => None,
+        };
+        CurrentLeaderSchedule {
+            view,
+            epoch,
+            current,
+            next,
+        }
+    }
 }
 
-impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence> SubmitDataSource<N, P>
+impl<N, P, D, V> RewardDistributionDataSource for StorageState<N, P, D, V>
+where
+    N: ConnectedNetwork<PubKey>,
+    V: Versions,
+    P: SequencerPersistence,
+    D: CatchupStorage + AvailabilityDataSource<SeqTypes> + NodeDataSource<SeqTypes> + Send + Sync,
+{
+    async fn get_reward_distribution(
+        &self,
+        epoch: EpochNumber,
+    ) -> anyhow::Result<Option<RewardDistribution>> {
+        let instance = self.node_state().await;
+        let epoch_height = instance
+            .epoch_height
+            .filter(|height| *height > 0)
+            .context("epochs are not enabled")?;
+        if *epoch == 0 {
+            return Ok(None);
+        }
+        let last = *epoch * epoch_height;
+        if (self.block_height().await? as u64) <= last {
+            return Ok(None);
+        }
+
+        // Rewards are credited to the leader of each block and its delegators, so only the
+        // validators of the epoch and their delegators can have earned anything.
+        let validators = self.get_validators(epoch).await?;
+        let accounts = validators
+            .values()
+            .flat_map(|validator| {
+                std::iter::once(validator.account).chain(validator.delegators.keys().copied())
+            })
+            .collect::<BTreeSet<_>>();
+        let reward_accounts = accounts
+            .iter()
+            .map(|account| RewardAccount(*account))
+            .collect::<Vec<_>>();
+
+        // Read the balances credited by consensus at the end of the previous epoch and at the end
+        // of this one.
+        let mut trees = vec![];
+        for height in [last - epoch_height, last] {
+            let view = self
+                .get_leaf(height as usize)
+                .await
+                .await
+                .leaf()
+                .view_number();
+            let tree = self
+                .get_reward_accounts(&instance, height, view, &reward_accounts)
+                .await
+                .with_context(|| format!("failed to read reward balances at height {height}"))?;
+            trees.push(tree);
+        }
+        RewardDistribution::from_balances(epoch, &trees[0], &trees[1], accounts).map(Some)
+    }
+}
+
+impl<N: ConnectedNetwork<PubKey>, D: Sync, V: Versions, P: SequencerPersistence>
+    PeerReputationDataSource for StorageState<N, P, D, V>
+{
+    async fn get_peer_reputations(&self) -> Vec<PeerReputation> {
+        self.as_ref().get_peer_reputations().await
+    }
+}
+
+impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence> PeerReputationDataSource
     for ApiState<N, P, V>
 {
-    async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
-        let handle = self.consensus().await;
+    async fn get_peer_reputations(&self) -> Vec<PeerReputation> {
+        let network = Arc::clone(&self.consensus().await.read().await.network);
+        network.peer_reputations().await
+    }
+}
 
-        let consensus_read_lock = handle.read().await;
+impl<N: ConnectedNetwork<PubKey>, D: Sync, V: Versions, P: SequencerPersistence> FeatureDataSource
+    for StorageState<N, P, D, V>
+{
+    async fn get_active_features(&self) -> Vec<Feature> {
+        self.as_ref().get_active_features().await
+    }
+}
 
-        // Fetch full chain config from the validated state, if present.
-        // This is necessary because we support chain config upgrades,
-        // so the updated chain config is found in the validated state.
-        let cf = consensus_read_lock
-            .decided_state()
-            .await
-            .chain_config
-            .resolve();
+impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence> FeatureDataSource
+    for ApiState<N, P, V>
+{
+    async fn get_active_features(&self) -> Vec<Feature> {
+        let handle = self.consensus().await;
+        let handle = handle.read().await;
+        let view = handle.cur_view().await;
+        handle.hotshot.upgrade_lock.features().active(view).await
+    }
+}
 
-        // Use the chain config from the validated state if available,
-        // otherwise, use the node state's chain config
-        // The node state's chain config is the node's base version chain config
-        let cf = match cf {
-            Some(cf) => cf,
-            None => self.node_state().await.chain_config,
-        };
+impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence> SubmitDataSource<N, P>
+    for ApiState<N, P, V>
+{
+    async fn submit(&self, tx: Transaction) -> anyhow::Result<()> {
+        let cf = self.decided_chain_config().await;
 
         let max_block_size: u64 = cf.max_block_size.into();
         let txn_size = tx.payload().len() as u64;
@@ -283,9 +526,47 @@ impl<N: ConnectedNetwork<PubKey>, V: Versions, P: SequencerPersistence> SubmitDa
             bail!("transaction size ({txn_size}) is greater than max_block_size ({max_block_size})")
         }
 
-        consensus_read_lock.submit_transaction(tx).await?;
+        let handle = self.consensus().await;
+        let consensus_read_lock = handle.read().await;
+        consensus_read_lock.submit_transaction(tx.clone()).await?;
+        self.transaction_status().await.received(tx.commit()).await;
+
+        // Keep the transaction until it is sequenced, so it is not lost if the node restarts.
+        let storage = consensus_read_lock.storage();
+        if let Err(err) = storage
+            .read()
+            .await
+            .add_pending_transaction(&tx, unix_timestamp())
+            .await
+        {
+            tracing::warn!(hash = %tx.commit(), "failed to persist pending transaction: {err:#}");
+        }
         Ok(())
     }
+
+    async fn encryption_key(&self) -> Option<ThresholdEncryptionKey> {
+        Some(self.decryptor().await?.key().clone())
+    }
+
+    async fn decrypted(&self, hash: Commitment<Transaction>) -> Option<Transaction> {
+        self.decryptor().await?.decrypted(hash).await
+    }
+
+    async fn transaction_status(&self, hash: Commitment<Transaction>) -> Option<TransactionStatus> {
+        self.transaction_status().await.status(hash).await
+    }
+
+    async fn subscribe_transaction_status(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> BoxStream<'static, TransactionStatus> {
+        self.transaction_status().await.subscribe(hash).await
+    }
+
+    async fn namespace_registry(&self) -> anyhow::Result<Option<Arc<NamespaceRegistry>>> {
+        let cf = self.decided_chain_config().await;
+        Ok(self.node_state().await.namespace_registry(&cf)?)
+    }
 }
 
 impl<N, P, D, V> NodeStateDataSource for StorageState<N, P, D, V>
@@ -372,8 +653,12 @@ impl<
                 (Arc::new(state), None)
             },
         };
-        if let Err(err) = consensus.update_leaf(leaf, Arc::clone(&state), delta) {
-            tracing::warn!(?view, "cannot update fetched account state: {err:#}");
+        match consensus.update_leaf(leaf, Arc::clone(&state), delta) {
+            Err(err) if err.is_stale() => {
+                tracing::debug!(?view, "not updating fetched account state: {err}")
+            },
+            Err(err) => tracing::warn!(?view, "cannot update fetched account state: {err}"),
+            Ok(()) => {},
         }
         tracing::info!(?view, "updated with fetched account state");
 
@@ -492,13 +777,29 @@ impl<
                 (Arc::new(state), None)
             },
         };
-        if let Err(err) = consensus.update_leaf(leaf, Arc::clone(&state), delta) {
-            tracing::warn!(?view, "cannot update fetched account state: {err:#}");
+        match consensus.update_leaf(leaf, Arc::clone(&state), delta) {
+            Err(err) if err.is_stale() => {
+                tracing::debug!(?view, "not updating fetched account state: {err}")
+            },
+            Err(err) => tracing::warn!(?view, "cannot update fetched account state: {err}"),
+            Ok(()) => {},
         }
         tracing::info!(?view, "updated with fetched account state");
 
         Ok(tree)
     }
+
+    async fn get_state_snapshot_info(&self) -> anyhow::Result<StateSnapshotInfo> {
+        self.inner().get_state_snapshot_info().await
+    }
+
+    async fn get_state_snapshot_chunk(&self, height: u64, chunk: u64) -> anyhow::Result<Vec<u8>> {
+        self.inner().get_state_snapshot_chunk(height, chunk).await
+    }
+
+    async fn get_state_diffs(&self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>> {
+        self.inner().get_state_diffs(from, to).await
+    }
 }
 
 // #[async_trait]
@@ -1445,6 +1746,7 @@ mod api_tests {
         },
         event::LeafInfo,
         message::Proposal,
+        signer::Signer,
         simple_certificate::QuorumCertificate2,
         traits::{node_implementation::ConsensusTime, signature_key::SignatureKey, EncodeBytes},
         utils::EpochTransitionIndicator,
@@ -1462,7 +1764,7 @@ mod api_tests {
 
     use super::{update::ApiEventConsumer, *};
     use crate::{
-        api::endpoints::NamespaceProofQueryData,
+        api::endpoints::{NamespaceBlockQueryData, NamespaceProofQueryData},
         network,
         persistence::no_storage::NoStorage,
         testing::{wait_for_decide_on_handle, TestConfigBuilder},
@@ -1583,6 +1885,80 @@ mod api_tests {
         assert!(found_empty_block);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    pub(crate) async fn test_namespace_stream<D: TestableSequencerDataSource>() {
+        setup_test();
+
+        let ns_id = NamespaceId::from(42_u32);
+        let txn = Transaction::new(ns_id, vec![1, 2, 3, 4]);
+
+        // Start query service.
+        let port = pick_unused_port().expect("No ports free");
+        let storage = D::create_storage().await;
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint_url();
+        let network_config = TestConfigBuilder::default().l1_url(l1).build();
+        let config = TestNetworkConfigBuilder::default()
+            .api_config(D::options(&storage, Options::with_port(port)).submit(Default::default()))
+            .network_config(network_config)
+            .build();
+        let network = TestNetwork::new(config, MockSequencerVersions::new()).await;
+
+        // Connect client.
+        let client: Client<ServerError, StaticVersion<0, 1>> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        let mut blocks = client
+            .socket(&format!("availability/stream/blocks/0/namespace/{ns_id}"))
+            .subscribe::<NamespaceBlockQueryData>()
+            .await
+            .unwrap();
+
+        let hash = client
+            .post("submit/submit")
+            .body_json(&txn)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        // Follow the namespace until our transaction shows up, checking the proof of every block.
+        let mut height = 0;
+        loop {
+            let block = blocks.next().await.unwrap().unwrap();
+            assert_eq!(block.header.height(), height);
+            height += 1;
+
+            let Some(proof) = block.namespace.proof else {
+                assert!(block.header.ns_table().find_ns_id(&ns_id).is_none());
+                assert!(block.namespace.transactions.is_empty());
+                continue;
+            };
+            let vid_common: VidCommonQueryData<SeqTypes> = client
+                .get(&format!(
+                    "availability/vid/common/{}",
+                    block.header.height()
+                ))
+                .send()
+                .await
+                .unwrap();
+            let (txs, _) = proof
+                .verify(
+                    block.header.ns_table(),
+                    &block.header.payload_commitment(),
+                    vid_common.common(),
+                )
+                .unwrap();
+            assert_eq!(txs, block.namespace.transactions);
+
+            if txs.iter().any(|tx| tx.commit() == hash) {
+                break;
+            }
+        }
+        drop(network);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     pub(crate) async fn catchup_test_with_query_module<D: TestableSequencerDataSource>() {
         let storage = D::create_storage().await;
@@ -1689,7 +2065,7 @@ mod api_tests {
                 common: avidm_param.clone(),
             };
             persistence
-                .append_vid2(&share.to_proposal(&privkey).unwrap())
+                .append_vid2(&share.to_proposal(&Signer::Local(privkey.clone())).unwrap())
                 .await
                 .unwrap();
 
@@ -3069,6 +3445,7 @@ mod test {
 
         let hotshot_events = HotshotEvents {
             events_service_port: hotshot_event_streaming_port,
+            ..Default::default()
         };
 
         let client: Client<ServerError, SequencerApiVersion> = Client::new(url);
@@ -3140,6 +3517,7 @@ mod test {
 
         let hotshot_events = HotshotEvents {
             events_service_port: hotshot_event_streaming_port,
+            ..Default::default()
         };
 
         let client: Client<ServerError, SequencerApiVersion> = Client::new(hotshot_url);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use alloy::primitives::Address;
 use anyhow::Context;
 use async_trait::async_trait;
@@ -7,23 +9,26 @@ use espresso_types::{
     v0::traits::{PersistenceOptions, SequencerPersistence},
     v0_1::{RewardAccount, RewardAccountProof, RewardAccountQueryData, RewardMerkleTree},
     v0_3::Validator,
-    v0_99::ChainConfig,
-    FeeAccount, FeeAccountProof, FeeMerkleTree, Leaf2, NodeState, PubKey, Transaction,
+    v0_4::ChainConfig,
+    FeeAccount, FeeAccountProof, FeeMerkleTree, Leaf2, NamespaceRegistry, NodeState, PubKey,
+    RewardDistribution, ThresholdEncryptionKey, Transaction, TransactionStatus,
 };
-use futures::future::Future;
+use futures::{future::Future, stream::BoxStream};
 use hotshot::types::BLSPubKey;
 use hotshot_query_service::{
     availability::AvailabilityDataSource,
     data_source::{UpdateDataSource, VersionedDataSource},
-    fetching::provider::{AnyProvider, QueryServiceProvider},
+    fetching::provider::{AnyProvider, QueryServiceProvider, VidReconstructionProvider},
     node::NodeDataSource,
     status::StatusDataSource,
 };
 use hotshot_types::{
     data::{EpochNumber, ViewNumber},
+    feature_gates::Feature,
+    leader_schedule::LeaderSchedule,
     light_client::StateSignatureRequestBody,
     traits::{
-        network::ConnectedNetwork,
+        network::{ConnectedNetwork, PeerReputation},
         node_implementation::{NodeType, Versions},
     },
     PeerConfig,
@@ -39,11 +44,15 @@ use super::{
 };
 use crate::{
     persistence::{self},
+    state_sync::{StateDiff, StateSnapshotInfo},
     SeqTypes, SequencerApiVersion,
 };
 
 pub trait DataSourceOptions: PersistenceOptions {
-    type DataSource: SequencerDataSource<Options = Self>;
+    type DataSource: SequencerDataSource;
+
+    /// Options for the query service storage which accompanies this persistence.
+    fn data_source_options(&self) -> <Self::DataSource as SequencerDataSource>::Options;
 
     fn enable_query_module(&self, opt: Options, query: Query) -> Options;
 }
@@ -51,6 +60,10 @@ pub trait DataSourceOptions: PersistenceOptions {
 impl DataSourceOptions for persistence::sql::Options {
     type DataSource = sql::DataSource;
 
+    fn data_source_options(&self) -> Self {
+        self.clone()
+    }
+
     fn enable_query_module(&self, opt: Options, query: Query) -> Options {
         opt.query_sql(query, self.clone())
     }
@@ -59,11 +72,29 @@ impl DataSourceOptions for persistence::sql::Options {
 impl DataSourceOptions for persistence::fs::Options {
     type DataSource = fs::DataSource;
 
+    fn data_source_options(&self) -> Self {
+        self.clone()
+    }
+
     fn enable_query_module(&self, opt: Options, query: Query) -> Options {
         opt.query_fs(query, self.clone())
     }
 }
 
+/// Embedded persistence has no query service storage of its own, so query data is stored in the
+/// file system, next to the database file.
+impl DataSourceOptions for persistence::embedded::Options {
+    type DataSource = fs::DataSource;
+
+    fn data_source_options(&self) -> persistence::fs::Options {
+        persistence::fs::Options::new(self.query_path())
+    }
+
+    fn enable_query_module(&self, opt: Options, query: Query) -> Options {
+        opt.query_fs(query, self.data_source_options())
+    }
+}
+
 /// A data source with sequencer-specific functionality.
 ///
 /// This trait extends the generic [`AvailabilityDataSource`] with some additional data needed to
@@ -87,8 +118,12 @@ pub trait SequencerDataSource:
 pub type Provider = AnyProvider<SeqTypes>;
 
 /// Create a provider for fetching missing data from a list of peer query services.
+///
+/// Payloads which none of `peers` can provide are reconstructed from the VID shares of `vid_peers`,
+/// if any.
 pub fn provider<V: Versions>(
     peers: impl IntoIterator<Item = Url>,
+    vid_peers: Vec<Url>,
     bind_version: SequencerApiVersion,
 ) -> Provider {
     let mut provider = Provider::default();
@@ -96,11 +131,42 @@ pub fn provider<V: Versions>(
         tracing::info!("will fetch missing data from {peer}");
         provider = provider.with_provider(QueryServiceProvider::new(peer, bind_version));
     }
+    if !vid_peers.is_empty() {
+        tracing::info!(peers = ?vid_peers, "will reconstruct missing payloads from VID shares");
+        provider =
+            provider.with_block_provider(VidReconstructionProvider::new(vid_peers, bind_version));
+    }
     provider
 }
 
 pub(crate) trait SubmitDataSource<N: ConnectedNetwork<PubKey>, P: SequencerPersistence> {
     fn submit(&self, tx: Transaction) -> impl Send + Future<Output = anyhow::Result<()>>;
+
+    /// The threshold encryption key, if the network has one
+    fn encryption_key(&self) -> impl Send + Future<Output = Option<ThresholdEncryptionKey>>;
+
+    /// The decryption of the encrypted transaction with commitment `hash`, if it is available
+    fn decrypted(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> impl Send + Future<Output = Option<Transaction>>;
+
+    /// How far the transaction with commitment `hash` has made it through consensus, if known
+    fn transaction_status(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> impl Send + Future<Output = Option<TransactionStatus>>;
+
+    /// Follow the status of the transaction with commitment `hash` until it is decided
+    fn subscribe_transaction_status(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> impl Send + Future<Output = BoxStream<'static, TransactionStatus>>;
+
+    /// The namespace registry the latest decided chain config commits to, if any
+    fn namespace_registry(
+        &self,
+    ) -> impl Send + Future<Output = anyhow::Result<Option<Arc<NamespaceRegistry>>>>;
 }
 
 pub(crate) trait HotShotConfigDataSource {
@@ -123,6 +189,29 @@ pub struct StakeTableWithEpochNumber<T: NodeType> {
     pub stake_table: Vec<PeerConfig<T>>,
 }
 
+/// Number of upcoming views whose leaders are listed in a [`CurrentLeaderSchedule`]
+pub const UPCOMING_LEADER_VIEWS: u64 = 100;
+
+/// The leader schedule of an epoch, and the leaders it gives for upcoming views
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: NodeType")]
+pub struct EpochLeaders<T: NodeType> {
+    pub schedule: LeaderSchedule<T>,
+    /// Leaders of the views from the current one on, if they are in this epoch
+    pub upcoming: Vec<(T::View, T::SignatureKey)>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: NodeType")]
+pub struct CurrentLeaderSchedule<T: NodeType> {
+    pub view: T::View,
+    pub epoch: Option<EpochNumber>,
+    /// Leaders of the current epoch, if its randomness is known
+    pub current: Option<EpochLeaders<T>>,
+    /// Leaders of the next epoch, if its randomness is known
+    pub next: Option<EpochLeaders<T>>,
+}
+
 pub(crate) trait StakeTableDataSource<T: NodeType> {
     /// Get the stake table for a given epoch
     fn get_stake_table(
@@ -133,11 +222,50 @@ pub(crate) trait StakeTableDataSource<T: NodeType> {
     /// Get the stake table for the current epoch if not provided
     fn get_stake_table_current(&self) -> impl Send + Future<Output = StakeTableWithEpochNumber<T>>;
 
+    /// Get the stake table recorded for a given epoch, which may be long past.
+    ///
+    /// Returns `None` if no stake table is known for the epoch.
+    fn get_stake_table_at(
+        &self,
+        epoch: <T as NodeType>::Epoch,
+    ) -> impl Send + Future<Output = anyhow::Result<Option<Vec<PeerConfig<T>>>>>;
+
     /// Get all the validators
     fn get_validators(
         &self,
         epoch: <T as NodeType>::Epoch,
     ) -> impl Send + Future<Output = anyhow::Result<IndexMap<Address, Validator<BLSPubKey>>>>;
+
+    /// Get the leader schedule for a given epoch, or before epochs if not provided.
+    ///
+    /// Returns `None` if the randomness for the epoch is not known yet.
+    fn get_leader_schedule(
+        &self,
+        epoch: Option<<T as NodeType>::Epoch>,
+    ) -> impl Send + Future<Output = Option<LeaderSchedule<T>>>;
+
+    /// Get the leader schedules for the current and next epochs, as far as they are known
+    fn get_leader_schedule_current(&self) -> impl Send + Future<Output = CurrentLeaderSchedule<T>>;
+}
+
+pub(crate) trait RewardDistributionDataSource {
+    /// Read the distribution of rewards for a completed epoch from the reward state.
+    ///
+    /// Returns `None` if the epoch is not complete yet.
+    fn get_reward_distribution(
+        &self,
+        epoch: EpochNumber,
+    ) -> impl Send + Future<Output = anyhow::Result<Option<RewardDistribution>>>;
+}
+
+pub(crate) trait PeerReputationDataSource {
+    /// Get the reputation of the peers we have received messages from
+    fn get_peer_reputations(&self) -> impl Send + Future<Output = Vec<PeerReputation>>;
+}
+
+pub(crate) trait FeatureDataSource {
+    /// Get the protocol features enabled in the current view
+    fn get_active_features(&self) -> impl Send + Future<Output = Vec<Feature>>;
 }
 
 pub(crate) trait CatchupDataSource: Sync {
@@ -234,6 +362,31 @@ pub(crate) trait CatchupDataSource: Sync {
         view: ViewNumber,
         accounts: &[RewardAccount],
     ) -> impl Send + Future<Output = anyhow::Result<RewardMerkleTree>>;
+
+    /// Get the height and size of the most recent merklized state snapshot.
+    fn get_state_snapshot_info(
+        &self,
+    ) -> impl Send + Future<Output = anyhow::Result<StateSnapshotInfo>> {
+        async { anyhow::bail!("state snapshots are not supported for this data source") }
+    }
+
+    /// Get one chunk of the merklized state snapshot at `height`.
+    fn get_state_snapshot_chunk(
+        &self,
+        _height: u64,
+        _chunk: u64,
+    ) -> impl Send + Future<Output = anyhow::Result<Vec<u8>>> {
+        async { anyhow::bail!("state snapshots are not supported for this data source") }
+    }
+
+    /// Get the changes to the merklized state made by the blocks in `from..=to`.
+    fn get_state_diffs(
+        &self,
+        _from: u64,
+        _to: u64,
+    ) -> impl Send + Future<Output = anyhow::Result<Vec<StateDiff>>> {
+        async { anyhow::bail!("state snapshots are not supported for this data source") }
+    }
 }
 
 #[cfg(any(test, feature = "testing"))]
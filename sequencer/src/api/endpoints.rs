@@ -3,17 +3,25 @@
 use std::{
     collections::{BTreeSet, HashMap},
     env,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::Duration,
 };
 
 use anyhow::Result;
-use committable::Committable;
+use committable::{Commitment, Committable};
 use espresso_types::{
     v0_1::{ADVZNsProof, RewardAccount, RewardMerkleTree},
-    FeeAccount, FeeMerkleTree, NamespaceId, NsProof, PubKey, Transaction,
+    FeeAccount, FeeMerkleTree, Header, LeafProof, NamespaceAdmission, NamespaceId,
+    NamespaceRegistry, NsProof, PubKey, RewardDistribution, SubmissionReceipt, Transaction,
+    TransactionStatus,
 };
-use futures::{try_join, FutureExt};
+use futures::{stream::BoxStream, try_join, FutureExt, StreamExt, TryFutureExt};
 use hotshot_query_service::{
-    availability::{self, AvailabilityDataSource, CustomSnafu, FetchBlockSnafu},
+    availability::{
+        self, AvailabilityDataSource, BlockQueryData, CustomSnafu, FetchBlockSnafu,
+        VidCommonQueryData,
+    },
     explorer::{self, ExplorerDataSource},
     merklized_state::{
         self, MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot,
@@ -21,7 +29,10 @@ use hotshot_query_service::{
     node::{self, NodeDataSource},
     ApiState, Error, VidCommon,
 };
+use hotshot_task::profiling;
 use hotshot_types::{
+    api_auth::{request_signer, Scope},
+    audit::{AuditFilter, MessageAudit},
     data::{EpochNumber, ViewNumber},
     traits::{
         network::ConnectedNetwork,
@@ -29,20 +40,30 @@ use hotshot_types::{
     },
 };
 use jf_merkle_tree::MerkleTreeScheme;
+use lru::LruCache;
+use parking_lot::Mutex;
 use serde::{de::Error as _, Deserialize, Serialize};
 use snafu::OptionExt;
 use tagged_base64::TaggedBase64;
-use tide_disco::{method::ReadState, Api, Error as _, StatusCode};
+use tide_disco::{method::ReadState, Api, Error as _, RequestParams, StatusCode};
 use vbs::version::{StaticVersion, StaticVersionType};
 
 use super::{
     data_source::{
-        CatchupDataSource, HotShotConfigDataSource, NodeStateDataSource, SequencerDataSource,
+        CatchupDataSource, FeatureDataSource, HotShotConfigDataSource, NodeStateDataSource,
+        PeerReputationDataSource, RewardDistributionDataSource, SequencerDataSource,
         StakeTableDataSource, StateSignatureDataSource, SubmitDataSource,
     },
+    log_filter::{LogFilterChange, LogFilterControl},
+    network_pause::{NetworkPause, NetworkPauseControl},
+    ns_proof_cache::{NsProofCache, Prover},
+    rate_limit::SubmitLimiter,
     StorageState,
 };
-use crate::{SeqTypes, SequencerApiVersion, SequencerPersistence};
+use crate::{
+    audit::AuditLog, state_sync::MAX_STATE_DIFFS_PER_REQUEST, SeqTypes, SequencerApiVersion,
+    SequencerPersistence,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NamespaceProofQueryData {
@@ -56,6 +77,13 @@ pub struct ADVZNamespaceProofQueryData {
     pub transactions: Vec<Transaction>,
 }
 
+/// A block header along with the transactions of one namespace in that block and their proof
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NamespaceBlockQueryData<T = NamespaceProofQueryData> {
+    pub header: Header,
+    pub namespace: T,
+}
+
 pub(super) fn fee<State, Ver>() -> Result<Api<State, merklized_state::Error, Ver>>
 where
     State: 'static + Send + Sync + ReadState,
@@ -156,6 +184,7 @@ type AvailabilityApi<N, P, D, V, ApiVer> = Api<AvailState<N, P, D, V>, availabil
 // However, the query service still uses snafu
 pub(super) fn availability<N, P, D, V: Versions>(
     api_ver: semver::Version,
+    caches: &NsProofCaches,
 ) -> Result<AvailabilityApi<N, P, D, V, SequencerApiVersion>>
 where
     N: ConnectedNetwork<PubKey>,
@@ -174,110 +203,71 @@ where
     )?;
 
     if api_ver.major == 1 {
+        let cache = caches.v1.clone();
         api.get("getnamespaceproof", move |req, state| {
+            let cache = cache.clone();
             async move {
                 let height: usize = req.integer_param("height")?;
                 let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
-                let (block, common) = try_join!(
-                    async move {
-                        state
-                            .get_block(height)
-                            .await
-                            .with_timeout(timeout)
-                            .await
-                            .context(FetchBlockSnafu {
-                                resource: height.to_string(),
-                            })
-                    },
-                    async move {
-                        state
-                            .get_vid_common(height)
-                            .await
-                            .with_timeout(timeout)
-                            .await
-                            .context(FetchBlockSnafu {
-                                resource: height.to_string(),
-                            })
-                    }
-                )?;
-
-                if let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) {
-                    let proof = NsProof::new(block.payload(), &ns_index, common.common()).context(
-                        CustomSnafu {
-                            message: format!("failed to make proof for namespace {ns_id}"),
-                            status: StatusCode::NOT_FOUND,
-                        },
-                    )?;
-
-                    Ok(NamespaceProofQueryData {
-                        transactions: proof.export_all_txs(&ns_id),
-                        proof: Some(proof),
-                    })
-                } else {
-                    // ns_id not found in ns_table
-                    Ok(NamespaceProofQueryData {
-                        proof: None,
-                        transactions: Vec::new(),
+                cached_ns_proof(state, &cache, height, ns_id, timeout, ns_proof_query_data).await
+            }
+            .boxed()
+        })?
+        .stream("stream_namespace", move |req, state| {
+            async move {
+                let height = req.integer_param("height")?;
+                let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+                state
+                    .read(|state| {
+                        async move {
+                            Ok(
+                                subscribe_namespace(state, height, ns_id, ns_proof_query_data)
+                                    .await,
+                            )
+                        }
+                        .boxed()
                     })
-                }
+                    .await
             }
+            .try_flatten_stream()
             .boxed()
         })?;
     } else {
+        let cache = caches.v0.clone();
         api.get("getnamespaceproof", move |req, state| {
+            let cache = cache.clone();
             async move {
                 let height: usize = req.integer_param("height")?;
                 let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
-                let (block, common) = try_join!(
-                    async move {
-                        state
-                            .get_block(height)
-                            .await
-                            .with_timeout(timeout)
-                            .await
-                            .context(FetchBlockSnafu {
-                                resource: height.to_string(),
-                            })
-                    },
-                    async move {
-                        state
-                            .get_vid_common(height)
-                            .await
-                            .with_timeout(timeout)
-                            .await
-                            .context(FetchBlockSnafu {
-                                resource: height.to_string(),
-                            })
-                    }
-                )?;
-
-                if let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) {
-                    let VidCommon::V0(common) = &common.common().clone() else {
-                        return Err(availability::Error::Custom {
-                            message: "Unsupported VID version, use new API version instead."
-                                .to_string(),
-                            status: StatusCode::NOT_FOUND,
-                        });
-                    };
-                    let proof = ADVZNsProof::new(block.payload(), &ns_index, common).context(
-                        CustomSnafu {
-                            message: format!("failed to make proof for namespace {ns_id}"),
-                            status: StatusCode::NOT_FOUND,
-                        },
-                    )?;
-
-                    Ok(ADVZNamespaceProofQueryData {
-                        transactions: proof.export_all_txs(&ns_id),
-                        proof: Some(proof),
-                    })
-                } else {
-                    // ns_id not found in ns_table
-                    Ok(ADVZNamespaceProofQueryData {
-                        proof: None,
-                        transactions: Vec::new(),
+                cached_ns_proof(
+                    state,
+                    &cache,
+                    height,
+                    ns_id,
+                    timeout,
+                    advz_ns_proof_query_data,
+                )
+                .await
+            }
+            .boxed()
+        })?
+        .stream("stream_namespace", move |req, state| {
+            async move {
+                let height = req.integer_param("height")?;
+                let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+                state
+                    .read(|state| {
+                        async move {
+                            Ok(
+                                subscribe_namespace(state, height, ns_id, advz_ns_proof_query_data)
+                                    .await,
+                            )
+                        }
+                        .boxed()
                     })
-                }
+                    .await
             }
+            .try_flatten_stream()
             .boxed()
         })?;
     }
@@ -285,6 +275,152 @@ where
     Ok(api)
 }
 
+/// Fetch a block along with the VID common data needed to prove its namespaces
+async fn fetch_block_and_vid_common<D>(
+    state: &D,
+    height: usize,
+    timeout: Duration,
+) -> Result<(BlockQueryData<SeqTypes>, VidCommonQueryData<SeqTypes>), availability::Error>
+where
+    D: AvailabilityDataSource<SeqTypes> + Sync,
+{
+    try_join!(
+        async move {
+            state
+                .get_block(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })
+        },
+        async move {
+            state
+                .get_vid_common(height)
+                .await
+                .with_timeout(timeout)
+                .await
+                .context(FetchBlockSnafu {
+                    resource: height.to_string(),
+                })
+        }
+    )
+}
+
+/// Namespace proof caches for each version of the availability API
+#[derive(Debug, Default)]
+pub(super) struct NsProofCaches {
+    pub(super) v0: Arc<NsProofCache<ADVZNamespaceProofQueryData>>,
+    pub(super) v1: Arc<NsProofCache<NamespaceProofQueryData>>,
+}
+
+/// Get the proof for namespace `ns_id` in the block at `height`, from `cache` if possible
+async fn cached_ns_proof<D, T>(
+    state: &D,
+    cache: &NsProofCache<T>,
+    height: usize,
+    ns_id: NamespaceId,
+    timeout: Duration,
+    prove: Prover<T>,
+) -> Result<T, availability::Error>
+where
+    D: AvailabilityDataSource<SeqTypes> + Sync,
+    T: Clone,
+{
+    cache
+        .get_or_compute(height as u64, ns_id, || async {
+            let (block, common) = fetch_block_and_vid_common(state, height, timeout).await?;
+            prove(&block, &common, ns_id)
+        })
+        .await
+}
+
+pub(super) fn ns_proof_query_data(
+    block: &BlockQueryData<SeqTypes>,
+    common: &VidCommonQueryData<SeqTypes>,
+    ns_id: NamespaceId,
+) -> Result<NamespaceProofQueryData, availability::Error> {
+    let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) else {
+        // ns_id not found in ns_table
+        return Ok(NamespaceProofQueryData {
+            proof: None,
+            transactions: Vec::new(),
+        });
+    };
+    let proof = NsProof::new(block.payload(), &ns_index, common.common()).context(CustomSnafu {
+        message: format!("failed to make proof for namespace {ns_id}"),
+        status: StatusCode::NOT_FOUND,
+    })?;
+
+    Ok(NamespaceProofQueryData {
+        transactions: proof.export_all_txs(&ns_id),
+        proof: Some(proof),
+    })
+}
+
+/// Whether namespace proofs of the v0 availability API can be made for blocks with `common`
+pub(super) fn advz_ns_proof_supported(common: &VidCommon) -> bool {
+    matches!(common, VidCommon::V0(_))
+}
+
+pub(super) fn advz_ns_proof_query_data(
+    block: &BlockQueryData<SeqTypes>,
+    common: &VidCommonQueryData<SeqTypes>,
+    ns_id: NamespaceId,
+) -> Result<ADVZNamespaceProofQueryData, availability::Error> {
+    let Some(ns_index) = block.payload().ns_table().find_ns_id(&ns_id) else {
+        // ns_id not found in ns_table
+        return Ok(ADVZNamespaceProofQueryData {
+            proof: None,
+            transactions: Vec::new(),
+        });
+    };
+    let VidCommon::V0(common) = common.common() else {
+        return Err(availability::Error::Custom {
+            message: "Unsupported VID version, use new API version instead.".to_string(),
+            status: StatusCode::NOT_FOUND,
+        });
+    };
+    let proof = ADVZNsProof::new(block.payload(), &ns_index, common).context(CustomSnafu {
+        message: format!("failed to make proof for namespace {ns_id}"),
+        status: StatusCode::NOT_FOUND,
+    })?;
+
+    Ok(ADVZNamespaceProofQueryData {
+        transactions: proof.export_all_txs(&ns_id),
+        proof: Some(proof),
+    })
+}
+
+/// Stream the transactions of namespace `ns_id`, with a proof, for each block starting at `from`
+async fn subscribe_namespace<D, T>(
+    state: &D,
+    from: usize,
+    ns_id: NamespaceId,
+    prove: fn(
+        &BlockQueryData<SeqTypes>,
+        &VidCommonQueryData<SeqTypes>,
+        NamespaceId,
+    ) -> Result<T, availability::Error>,
+) -> BoxStream<'static, Result<NamespaceBlockQueryData<T>, availability::Error>>
+where
+    D: AvailabilityDataSource<SeqTypes> + Sync,
+    T: Send + 'static,
+{
+    let blocks = state.subscribe_blocks(from).await;
+    let common = state.subscribe_vid_common(from).await;
+    blocks
+        .zip(common)
+        .map(move |(block, common)| {
+            Ok(NamespaceBlockQueryData {
+                header: block.header().clone(),
+                namespace: prove(&block, &common, ns_id)?,
+            })
+        })
+        .boxed()
+}
+
 type ExplorerApi<N, P, D, V, ApiVer> = Api<AvailState<N, P, D, V>, explorer::Error, ApiVer>;
 
 pub(super) fn explorer<N, P, D, V: Versions>(
@@ -300,11 +436,18 @@ where
     Ok(api)
 }
 
-pub(super) fn node<S>() -> Result<Api<S, node::Error, StaticVersion<0, 1>>>
+pub(super) fn node<S>(
+    admin_token: Option<String>,
+) -> Result<Api<S, node::Error, StaticVersion<0, 1>>>
 where
     S: 'static + Send + Sync + ReadState,
-    <S as ReadState>::State:
-        Send + Sync + StakeTableDataSource<SeqTypes> + NodeDataSource<SeqTypes>,
+    <S as ReadState>::State: Send
+        + Sync
+        + StakeTableDataSource<SeqTypes>
+        + RewardDistributionDataSource
+        + PeerReputationDataSource
+        + FeatureDataSource
+        + NodeDataSource<SeqTypes>,
 {
     // Extend the base API
     let mut options = node::Options::default();
@@ -314,6 +457,13 @@ where
     // Create the base API with our extensions
     let mut api = node::define_api::<S, SeqTypes, _>(&options, SequencerApiVersion::instance())?;
 
+    let distributions = Arc::new(RewardDistributionCache::new(
+        NonZeroUsize::new(REWARD_DISTRIBUTION_CACHE_SIZE).unwrap(),
+    ));
+    let claim_distributions = distributions.clone();
+    let claim_admin_token = admin_token.clone();
+    let profiles_admin_token = admin_token.clone();
+
     // Tack on the application logic
     api.at("stake_table", |req, state| {
         async move {
@@ -341,6 +491,57 @@ where
         }
         .boxed()
     })?
+    .at("leader_schedule_current", |_, state| {
+        async move {
+            Ok(state
+                .read(|state| state.get_leader_schedule_current().boxed())
+                .await)
+        }
+        .boxed()
+    })?
+    .at("leader_schedule", |req, state| {
+        async move {
+            let epoch = req
+                .opt_integer_param("epoch_number")
+                .map_err(|_| hotshot_query_service::node::Error::Custom {
+                    message: "Invalid epoch number".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                })?
+                .map(EpochNumber::new);
+
+            state
+                .read(|state| state.get_leader_schedule(epoch).boxed())
+                .await
+                .ok_or_else(|| hotshot_query_service::node::Error::Custom {
+                    message: format!("the leader schedule for epoch {epoch:?} is not known yet"),
+                    status: StatusCode::NOT_FOUND,
+                })
+        }
+        .boxed()
+    })?
+    .at("stake_table_history", |req, state| {
+        async move {
+            let epoch = req.integer_param::<_, u64>("epoch_number").map_err(|_| {
+                hotshot_query_service::node::Error::Custom {
+                    message: "Epoch number is required".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+
+            state
+                .read(|state| state.get_stake_table_at(EpochNumber::new(epoch)).boxed())
+                .await
+                .map_err(|err| hotshot_query_service::node::Error::Custom {
+                    message: format!("failed to load stake table: {err:#}"),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                })?
+                .ok_or_else(|| hotshot_query_service::node::Error::Custom {
+                    message: format!("no stake table is known for epoch {epoch}"),
+                    status: StatusCode::NOT_FOUND,
+                })
+        }
+        .boxed()
+    })?
     .at("get_validators", |req, state| {
         async move {
             let epoch = req.integer_param::<_, u64>("epoch_number").map_err(|_| {
@@ -359,11 +560,153 @@ where
                 })
         }
         .boxed()
+    })?
+    .at("reward_distribution", move |req, state| {
+        let distributions = distributions.clone();
+        let admin_token = admin_token.clone();
+        async move {
+            authorize_node_admin(&req, admin_token.as_deref())?;
+            let epoch = req.integer_param::<_, u64>("epoch_number").map_err(|_| {
+                hotshot_query_service::node::Error::Custom {
+                    message: "Epoch number is required".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+            let offset = req
+                .opt_integer_param::<_, usize>("offset")
+                .map_err(|_| hotshot_query_service::node::Error::Custom {
+                    message: "failed to parse offset".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                })?
+                .unwrap_or(0);
+            let limit = req
+                .opt_integer_param::<_, usize>("limit")
+                .map_err(|_| hotshot_query_service::node::Error::Custom {
+                    message: "failed to parse limit".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                })?
+                .unwrap_or(MAX_REWARD_DISTRIBUTION_PAGE)
+                .min(MAX_REWARD_DISTRIBUTION_PAGE);
+            Ok(reward_distribution(state, &distributions, epoch)
+                .await?
+                .page(offset, limit))
+        }
+        .boxed()
+    })?
+    .at("reward_claim", move |req, state| {
+        let distributions = claim_distributions.clone();
+        let admin_token = claim_admin_token.clone();
+        async move {
+            authorize_node_admin(&req, admin_token.as_deref())?;
+            let epoch = req.integer_param::<_, u64>("epoch_number").map_err(|_| {
+                hotshot_query_service::node::Error::Custom {
+                    message: "Epoch number is required".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                }
+            })?;
+            let address = req
+                .string_param("address")
+                .map_err(|_| hotshot_query_service::node::Error::Custom {
+                    message: "Address is required".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                })?
+                .parse()
+                .map_err(|_| hotshot_query_service::node::Error::Custom {
+                    message: "failed to parse reward address".to_string(),
+                    status: StatusCode::BAD_REQUEST,
+                })?;
+            reward_distribution(state, &distributions, epoch)
+                .await?
+                .claim(address)
+                .ok_or_else(|| hotshot_query_service::node::Error::Custom {
+                    message: format!("{address} earned no reward in epoch {epoch}"),
+                    status: StatusCode::NOT_FOUND,
+                })
+        }
+        .boxed()
+    })?
+    .at("peer_reputations", |_, state| {
+        async move {
+            Ok(state
+                .read(|state| state.get_peer_reputations().boxed())
+                .await)
+        }
+        .boxed()
+    })?
+    .at("task_profiles", move |req, _| {
+        let admin_token = profiles_admin_token.clone();
+        async move {
+            authorize_node_admin(&req, admin_token.as_deref())?;
+            if !profiling::ENABLED {
+                return Err(hotshot_query_service::node::Error::Custom {
+                    message: "node was built without the task-profiling feature".to_string(),
+                    status: StatusCode::NOT_IMPLEMENTED,
+                });
+            }
+            Ok(profiling::task_profiles())
+        }
+        .boxed()
+    })?
+    .at("features", |_, state| {
+        async move {
+            Ok(state
+                .read(|state| state.get_active_features().boxed())
+                .await)
+        }
+        .boxed()
     })?;
 
     Ok(api)
 }
-pub(super) fn submit<N, P, S, ApiVer: StaticVersionType + 'static>() -> Result<Api<S, Error, ApiVer>>
+
+/// Number of epochs whose reward distributions are kept in memory
+const REWARD_DISTRIBUTION_CACHE_SIZE: usize = 16;
+
+/// Most accounts returned by a single reward distribution request
+const MAX_REWARD_DISTRIBUTION_PAGE: usize = 1_000;
+
+/// Reward distributions of completed epochs, by epoch number
+type RewardDistributionCache = Mutex<LruCache<u64, RewardDistribution>>;
+
+/// Get the reward distribution for `epoch`, from `cache` if possible
+///
+/// Reading a distribution looks up the reward balance of every validator and delegator of the epoch
+/// twice, and claims for many accounts are requested against the same epoch, so completed
+/// distributions are cached.
+async fn reward_distribution<S>(
+    state: &S,
+    cache: &RewardDistributionCache,
+    epoch: u64,
+) -> Result<RewardDistribution, node::Error>
+where
+    S: ReadState,
+    S::State: Send + Sync + RewardDistributionDataSource,
+{
+    if let Some(distribution) = cache.lock().get(&epoch) {
+        return Ok(distribution.clone());
+    }
+    let distribution = state
+        .read(|state| {
+            state
+                .get_reward_distribution(EpochNumber::new(epoch))
+                .boxed()
+        })
+        .await
+        .map_err(|err| node::Error::Custom {
+            message: format!("failed to compute reward distribution: {err:#}"),
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+        })?
+        .ok_or_else(|| node::Error::Custom {
+            message: format!("epoch {epoch} is not complete"),
+            status: StatusCode::NOT_FOUND,
+        })?;
+    cache.lock().put(epoch, distribution.clone());
+    Ok(distribution)
+}
+
+pub(super) fn submit<N, P, S, ApiVer: StaticVersionType + 'static>(
+    limiter: SubmitLimiter,
+) -> Result<Api<S, Error, ApiVer>>
 where
     N: ConnectedNetwork<PubKey>,
     S: 'static + Send + Sync + ReadState,
@@ -372,19 +715,100 @@ where
 {
     let toml = toml::from_str::<toml::Value>(include_str!("../../api/submit.toml"))?;
     let mut api = Api::<S, Error, ApiVer>::new(toml)?;
+    let limiter = Arc::new(limiter);
 
-    api.at("submit", |req, state| {
+    let submit_limiter = limiter.clone();
+    api.at("submit", move |req, state| {
+        let limiter = submit_limiter.clone();
+        async move { submit_transaction::<N, P, S, ApiVer>(req, state, &limiter).await }.boxed()
+    })?
+    .at("submit_with_receipt", move |req, state| {
+        let limiter = limiter.clone();
+        async move {
+            let hash = submit_transaction::<N, P, S, ApiVer>(req, state, &limiter).await?;
+            let status = state
+                .read(|state| state.transaction_status(hash).boxed())
+                .await
+                .unwrap_or(TransactionStatus::Received);
+            Ok(SubmissionReceipt { hash, status })
+        }
+        .boxed()
+    })?
+    .get("status", |req, state| {
         async move {
-            let tx = req
-                .body_auto::<Transaction, ApiVer>(ApiVer::instance())
+            let hash = req.blob_param("hash").map_err(Error::from_request_error)?;
+            state.transaction_status(hash).await.ok_or_else(|| {
+                Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("transaction {hash} is unknown"),
+                )
+            })
+        }
+        .boxed()
+    })?
+    .stream("stream_status", |req, state| {
+        async move {
+            let hash = req.blob_param("hash").map_err(Error::from_request_error)?;
+            state
+                .read(|state| {
+                    async move { Ok(state.subscribe_transaction_status(hash).await.map(Ok)) }
+                        .boxed()
+                })
+                .await
+        }
+        .try_flatten_stream()
+        .boxed()
+    })?
+    .get("encryption_key", |_, state| {
+        async move {
+            state.encryption_key().await.ok_or_else(|| {
+                Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    "no encryption key configured".to_string(),
+                )
+            })
+        }
+        .boxed()
+    })?
+    .get("namespace_registry", |_, state| {
+        async move {
+            let registry = state
+                .namespace_registry()
+                .await
+                .map_err(|err| Error::internal(format!("{err:#}")))?;
+            Ok(registry.map(|registry| (*registry).clone()))
+        }
+        .boxed()
+    })?
+    .get("namespace_registration", |req, state| {
+        async move {
+            let namespace = req
+                .integer_param::<_, u64>("namespace")
                 .map_err(Error::from_request_error)?;
-
-            let hash = tx.commit();
+            let namespace = NamespaceId::from(namespace);
             state
-                .read(|state| state.submit(tx).boxed())
+                .namespace_registry()
                 .await
-                .map_err(|err| Error::internal(err.to_string()))?;
-            Ok(hash)
+                .map_err(|err| Error::internal(format!("{err:#}")))?
+                .and_then(|registry| registry.get(namespace).cloned())
+                .ok_or_else(|| {
+                    Error::catch_all(
+                        StatusCode::NOT_FOUND,
+                        format!("namespace {namespace} is not registered"),
+                    )
+                })
+        }
+        .boxed()
+    })?
+    .get("decrypted", |req, state| {
+        async move {
+            let hash = req.blob_param("hash").map_err(Error::from_request_error)?;
+            state.decrypted(hash).await.ok_or_else(|| {
+                Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("transaction {hash} has not been decrypted"),
+                )
+            })
         }
         .boxed()
     })?;
@@ -392,6 +816,74 @@ where
     Ok(api)
 }
 
+/// Check a submitted transaction against the submission limits and pass it to consensus
+async fn submit_transaction<N, P, S, ApiVer>(
+    req: RequestParams,
+    state: &S,
+    limiter: &SubmitLimiter,
+) -> Result<Commitment<Transaction>, Error>
+where
+    N: ConnectedNetwork<PubKey>,
+    S: 'static + Send + Sync + ReadState,
+    P: SequencerPersistence,
+    S::State: Send + Sync + SubmitDataSource<N, P>,
+    ApiVer: StaticVersionType + 'static,
+{
+    let tx = req
+        .body_auto::<Transaction, ApiVer>(ApiVer::instance())
+        .map_err(Error::from_request_error)?;
+    limiter
+        .check(tx.namespace(), tx.payload().len(), limiter.client(&req))
+        .map_err(|reason| Error::catch_all(reason.status(), reason.to_string()))?;
+
+    let registry = state
+        .read(|state| state.namespace_registry().boxed())
+        .await
+        .map_err(|err| Error::internal(format!("{err:#}")))?;
+    if let Some(registry) = registry {
+        check_namespace_registry(&registry, &tx, &req)?;
+    }
+
+    let hash = tx.commit();
+    state
+        .read(|state| state.submit(tx).boxed())
+        .await
+        .map_err(|err| Error::internal(err.to_string()))?;
+    Ok(hash)
+}
+
+/// Check a submitted transaction against the namespace registry in force
+fn check_namespace_registry(
+    registry: &NamespaceRegistry,
+    tx: &Transaction,
+    req: &RequestParams,
+) -> Result<(), Error> {
+    // Namespaces with an owner only accept submissions the owner signed.
+    let owned = registry
+        .get(tx.namespace())
+        .is_some_and(|registration| registration.owner.is_some());
+    let signer = match req.header("Authorization") {
+        Some(values) if owned => Some(
+            request_signer(values.last().as_str(), Scope::SubmitTransactions)
+                .map_err(|err| Error::catch_all(StatusCode::UNAUTHORIZED, err.to_string()))?
+                .into(),
+        ),
+        _ => None,
+    };
+    match registry.authorize_submission(tx.namespace(), signer) {
+        Ok(NamespaceAdmission::Flagged) => {
+            tracing::warn!(
+                namespace = %tx.namespace(),
+                remote = ?req.remote(),
+                "transaction submitted to unregistered namespace"
+            );
+            Ok(())
+        },
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::catch_all(StatusCode::FORBIDDEN, err.to_string())),
+    }
+}
+
 pub(super) fn state_signature<N, S, ApiVer: StaticVersionType + 'static>(
     _: ApiVer,
 ) -> Result<Api<S, Error, ApiVer>>
@@ -598,6 +1090,67 @@ where
                 .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
         }
         .boxed()
+    })?
+    .get("leaf_proof", |req, state| {
+        async move {
+            let height = req
+                .integer_param("height")
+                .map_err(Error::from_request_error)?;
+            let mut chain = state
+                .get_leaf_chain(height)
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))?;
+            chain.sort_by_key(|leaf| leaf.view_number());
+            LeafProof::from_leaf_chain(chain)
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("state_snapshot", |_, state| {
+        async move {
+            state
+                .get_state_snapshot_info()
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("state_snapshot_chunk", |req, state| {
+        async move {
+            let height = req
+                .integer_param("height")
+                .map_err(Error::from_request_error)?;
+            let chunk = req
+                .integer_param("chunk")
+                .map_err(Error::from_request_error)?;
+            state
+                .get_state_snapshot_chunk(height, chunk)
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
+    })?
+    .get("state_diffs", |req, state| {
+        async move {
+            let from: u64 = req
+                .integer_param("from")
+                .map_err(Error::from_request_error)?;
+            let to: u64 = req.integer_param("to").map_err(Error::from_request_error)?;
+            if to < from || to - from >= MAX_STATE_DIFFS_PER_REQUEST {
+                return Err(Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "invalid range {from}..={to}: at most {MAX_STATE_DIFFS_PER_REQUEST} diffs \
+                         can be requested at once"
+                    ),
+                ));
+            }
+            state
+                .get_state_diffs(from, to)
+                .await
+                .map_err(|err| Error::catch_all(StatusCode::NOT_FOUND, format!("{err:#}")))
+        }
+        .boxed()
     })?;
 
     Ok(api)
@@ -677,3 +1230,157 @@ fn get_public_env_vars() -> Result<Vec<String>> {
 
     Ok(public_env_vars)
 }
+
+/// Most audit records returned by a single request
+const MAX_AUDIT_RECORDS: usize = 10_000;
+
+pub(super) fn admin<S, ApiVer: StaticVersionType + 'static>(
+    token: String,
+    log_filter: LogFilterControl,
+    network_pause: NetworkPauseControl,
+    audit: Option<AuditLog>,
+) -> Result<Api<S, Error, ApiVer>>
+where
+    S: 'static + Send + Sync + ReadState,
+{
+    let toml = toml::from_str::<toml::Value>(include_str!("../../api/admin.toml"))?;
+    let mut api = Api::<S, Error, ApiVer>::new(toml)?;
+    let token = Arc::new(token);
+
+    let get_token = token.clone();
+    let get_log_filter = log_filter.clone();
+    api.at("log_filter", move |req, _| {
+        let res = authorize_admin(&req, &get_token).and_then(|()| {
+            get_log_filter
+                .status()
+                .map_err(|err| Error::internal(format!("{err:#}")))
+        });
+        async move { res }.boxed()
+    })?
+    .at("set_log_filter", {
+        let token = token.clone();
+        move |req, _| {
+            let res = authorize_admin(&req, &token).and_then(|()| {
+                let change = req
+                    .body_auto::<LogFilterChange, ApiVer>(ApiVer::instance())
+                    .map_err(Error::from_request_error)?;
+                log_filter
+                    .set(change)
+                    .map_err(|err| Error::catch_all(StatusCode::BAD_REQUEST, format!("{err:#}")))
+            });
+            async move { res }.boxed()
+        }
+    })?
+    .at("network", {
+        let token = token.clone();
+        let network_pause = network_pause.clone();
+        move |req, _| {
+            let res = authorize_admin(&req, &token).map(|()| network_pause.status());
+            async move { res }.boxed()
+        }
+    })?
+    .at("pause_network", {
+        let token = token.clone();
+        let network_pause = network_pause.clone();
+        move |req, _| {
+            let res = authorize_admin(&req, &token).and_then(|()| {
+                let pause = req
+                    .body_auto::<NetworkPause, ApiVer>(ApiVer::instance())
+                    .map_err(Error::from_request_error)?;
+                network_pause.pause(pause).map_err(|err| {
+                    Error::catch_all(StatusCode::SERVICE_UNAVAILABLE, format!("{err:#}"))
+                })
+            });
+            async move { res }.boxed()
+        }
+    })?
+    .at("resume_network", {
+        let token = token.clone();
+        move |req, _| {
+            let res = authorize_admin(&req, &token).and_then(|()| {
+                network_pause.resume().map_err(|err| {
+                    Error::catch_all(StatusCode::SERVICE_UNAVAILABLE, format!("{err:#}"))
+                })
+            });
+            async move { res }.boxed()
+        }
+    })?
+    .at("audit_records", move |req, _| {
+        let filter = authorize_admin(&req, &token).and_then(|()| {
+            Ok(AuditFilter {
+                from_view: req
+                    .integer_param("from_view")
+                    .map_err(Error::from_request_error)?,
+                until_view: req
+                    .integer_param("until_view")
+                    .map_err(Error::from_request_error)?,
+                signer: req
+                    .opt_blob_param("signer")
+                    .map_err(Error::from_request_error)?,
+                limit: MAX_AUDIT_RECORDS,
+            })
+        });
+        let audit = audit.clone();
+        async move {
+            let filter = filter?;
+            let audit = audit.ok_or_else(|| {
+                Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    "audit mode is not enabled on this node".into(),
+                )
+            })?;
+            audit
+                .records(&filter)
+                .await
+                .map_err(|err| Error::internal(format!("failed to read audit log: {err:#}")))
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+/// Check that `req` carries the admin token `token` as a bearer token
+fn authorize_admin(req: &RequestParams, token: &str) -> Result<(), Error> {
+    if admin_token_matches(req, token) {
+        Ok(())
+    } else {
+        Err(Error::catch_all(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid admin token".into(),
+        ))
+    }
+}
+
+/// Check the admin token on an endpoint of the node API, which is only served when the admin API
+/// is enabled.
+fn authorize_node_admin(req: &RequestParams, token: Option<&str>) -> Result<(), node::Error> {
+    let token = token.ok_or_else(|| node::Error::Custom {
+        message: "admin API is not enabled".into(),
+        status: StatusCode::NOT_FOUND,
+    })?;
+    if admin_token_matches(req, token) {
+        Ok(())
+    } else {
+        Err(node::Error::Custom {
+            message: "missing or invalid admin token".into(),
+            status: StatusCode::UNAUTHORIZED,
+        })
+    }
+}
+
+fn admin_token_matches(req: &RequestParams, token: &str) -> bool {
+    let provided = req
+        .header("Authorization")
+        .and_then(|values| values.last().as_str().strip_prefix("Bearer "));
+    // Compare in constant time, so the token cannot be guessed a byte at a time from response
+    // timings.
+    provided.is_some_and(|provided| {
+        provided.len() == token.len()
+            && provided
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    })
+}
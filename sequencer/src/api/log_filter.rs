@@ -0,0 +1,167 @@
+//! Runtime control of the log filter.
+//!
+//! Debugging a live node often calls for more verbose logs from one component, such as the
+//! `OuterConsensus` lock traces, but restarting the node to change `RUST_LOG` loses the state being
+//! debugged. The admin API replaces the filter in place instead, optionally only for a limited
+//! time, after which the filter in effect before the change is restored.
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use hotshot::helpers::{log_filter, set_log_filter};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// A requested change to the log filter
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogFilterChange {
+    /// The new filter, in `RUST_LOG` syntax
+    pub filter: String,
+    /// Seconds after which to restore the previous filter
+    ///
+    /// If not provided, the change is permanent (until the next change or restart).
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+}
+
+/// The log filter in effect
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFilterStatus {
+    /// The current filter, in `RUST_LOG` syntax
+    pub filter: String,
+    /// The filter which will be restored when a temporary change expires
+    pub revert_to: Option<String>,
+    /// When the temporary change expires, in seconds since the Unix epoch
+    pub revert_at: Option<u64>,
+}
+
+/// A pending restoration of the log filter
+#[derive(Clone, Debug)]
+struct Revert {
+    to: String,
+    at: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Incremented on every change, so that a pending revert is abandoned once superseded
+    generation: u64,
+    revert: Option<Revert>,
+}
+
+/// Changes the log filter on behalf of the admin API.
+#[derive(Clone, Debug, Default)]
+pub struct LogFilterControl {
+    state: Arc<Mutex<State>>,
+}
+
+impl LogFilterControl {
+    /// The log filter currently in effect.
+    pub fn status(&self) -> anyhow::Result<LogFilterStatus> {
+        let filter =
+            log_filter().context("logging was not initialized with a reloadable filter")?;
+        let revert = self.state.lock().revert.clone();
+        Ok(LogFilterStatus {
+            filter,
+            revert_at: revert.as_ref().map(|revert| unix_secs(revert.at)),
+            revert_to: revert.map(|revert| revert.to),
+        })
+    }
+
+    /// Replace the log filter, restoring the previous one after `duration` if given.
+    pub fn set(&self, change: LogFilterChange) -> anyhow::Result<LogFilterStatus> {
+        let previous =
+            log_filter().context("logging was not initialized with a reloadable filter")?;
+        set_log_filter(&change.filter)?;
+
+        let mut state = self.state.lock();
+        state.generation += 1;
+        match change.duration_secs.map(Duration::from_secs) {
+            Some(duration) => {
+                // If a temporary change is already in effect, keep the filter it would restore, so
+                // that overlapping temporary changes never leave a temporary filter behind.
+                let to = state.revert.take().map_or(previous, |revert| revert.to);
+                state.revert = Some(Revert {
+                    to,
+                    at: SystemTime::now() + duration,
+                });
+                tokio::spawn(self.clone().revert_after(state.generation, duration));
+            },
+            None => state.revert = None,
+        }
+        drop(state);
+
+        tracing::warn!(filter = %change.filter, duration = ?change.duration_secs, "log filter changed");
+        self.status()
+    }
+
+    /// Restore the previous filter after `duration`, unless it has been changed again since.
+    async fn revert_after(self, generation: u64, duration: Duration) {
+        tokio::time::sleep(duration).await;
+
+        let mut state = self.state.lock();
+        if state.generation != generation {
+            return;
+        }
+        let Some(revert) = state.revert.take() else {
+            return;
+        };
+        match set_log_filter(&revert.to) {
+            Ok(()) => tracing::warn!(filter = %revert.to, "temporary log filter expired"),
+            Err(err) => tracing::error!("failed to restore log filter {}: {err:#}", revert.to),
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use sequencer_utils::test_utils::setup_test;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_temporary_log_filter() {
+        setup_test();
+
+        let control = LogFilterControl::default();
+        let original = control.status().unwrap();
+        assert_eq!(original.revert_to, None);
+
+        // Nested temporary changes both revert to the original filter.
+        control
+            .set(LogFilterChange {
+                filter: "info,OuterConsensus=trace".into(),
+                duration_secs: Some(1),
+            })
+            .unwrap();
+        let status = control
+            .set(LogFilterChange {
+                filter: "debug,OuterConsensus=trace".into(),
+                duration_secs: Some(1),
+            })
+            .unwrap();
+        assert!(status.filter.contains("OuterConsensus=trace"));
+        assert_eq!(status.revert_to, Some(original.filter.clone()));
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(control.status().unwrap(), original);
+
+        // Invalid filters are rejected and leave the filter unchanged.
+        control
+            .set(LogFilterChange {
+                filter: "OuterConsensus=loud".into(),
+                duration_secs: None,
+            })
+            .unwrap_err();
+        assert_eq!(control.status().unwrap(), original);
+    }
+}
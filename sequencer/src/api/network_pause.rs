@@ -0,0 +1,229 @@
+//! Runtime pausing of the consensus network.
+//!
+//! Resilience drills need to take a node off the network without stopping it, so that it rejoins
+//! with all of its state once the drill is over. The admin API pauses the consensus network of the
+//! node, optionally only for a limited time, after which it is resumed.
+//!
+//! Only networks which support pausing can be paused; the CDN does so in builds with the `testing`
+//! feature, and the combined network only when both of its networks do. For other networks,
+//! requests to pause fail rather than reporting a pause which has no effect.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::bail;
+use espresso_types::PubKey;
+use hotshot_types::traits::network::ConnectedNetwork;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// A requested pause of the consensus network
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NetworkPause {
+    /// Seconds after which to resume the network
+    ///
+    /// If not provided, the network stays paused until it is resumed through the admin API.
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+}
+
+/// Whether the consensus network is paused
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPauseStatus {
+    pub paused: bool,
+    /// When a temporary pause expires, in seconds since the Unix epoch
+    pub resume_at: Option<u64>,
+}
+
+type SetPaused = Arc<dyn Fn(bool) + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct State {
+    /// Incremented on every change, so that a pending resume is abandoned once superseded
+    generation: u64,
+    paused: bool,
+    resume_at: Option<SystemTime>,
+}
+
+/// Pauses and resumes the consensus network on behalf of the admin API.
+///
+/// The API starts before consensus, so the network is attached once the node has initialized;
+/// until then, requests to pause it fail.
+#[derive(Clone, Default)]
+pub struct NetworkPauseControl {
+    /// The attached network, or `None` if it is attached but cannot be paused
+    network: Arc<OnceLock<Option<SetPaused>>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for NetworkPauseControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NetworkPauseControl")
+            .field("attached", &self.network.get().is_some())
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl NetworkPauseControl {
+    /// Attach the consensus network of the node.
+    pub fn attach<N: ConnectedNetwork<PubKey>>(&self, network: Arc<N>) {
+        let set_paused = network.can_pause().then(|| -> SetPaused {
+            Arc::new(move |paused| {
+                if paused {
+                    network.pause();
+                } else {
+                    network.resume();
+                }
+            })
+        });
+        if self.network.set(set_paused).is_err() {
+            tracing::warn!("consensus network attached to the admin API more than once");
+        }
+    }
+
+    /// Whether the network is currently paused.
+    pub fn status(&self) -> NetworkPauseStatus {
+        let state = self.state.lock();
+        NetworkPauseStatus {
+            paused: state.paused,
+            resume_at: state.resume_at.map(unix_secs),
+        }
+    }
+
+    /// Pause the network, resuming it after `duration_secs` if given.
+    pub fn pause(&self, pause: NetworkPause) -> anyhow::Result<NetworkPauseStatus> {
+        let set_paused = self.set_paused()?;
+
+        let mut state = self.state.lock();
+        state.generation += 1;
+        state.paused = true;
+        set_paused(true);
+        match pause.duration_secs.map(Duration::from_secs) {
+            Some(duration) => {
+                state.resume_at = Some(SystemTime::now() + duration);
+                tokio::spawn(self.clone().resume_after(state.generation, duration));
+            },
+            None => state.resume_at = None,
+        }
+        drop(state);
+
+        tracing::warn!(duration = ?pause.duration_secs, "consensus network paused");
+        Ok(self.status())
+    }
+
+    /// Resume the network, if it is paused.
+    pub fn resume(&self) -> anyhow::Result<NetworkPauseStatus> {
+        let set_paused = self.set_paused()?;
+
+        let mut state = self.state.lock();
+        state.generation += 1;
+        if state.paused {
+            set_paused(false);
+            tracing::warn!("consensus network resumed");
+        }
+        state.paused = false;
+        state.resume_at = None;
+        drop(state);
+
+        Ok(self.status())
+    }
+
+    /// The control of the attached network, if it can be paused.
+    fn set_paused(&self) -> anyhow::Result<&SetPaused> {
+        match self.network.get() {
+            Some(Some(set_paused)) => Ok(set_paused),
+            Some(None) => bail!("the consensus network of this node cannot be paused"),
+            None => bail!("the node has not joined the network yet"),
+        }
+    }
+
+    /// Resume the network after `duration`, unless it has been paused or resumed again since.
+    async fn resume_after(self, generation: u64, duration: Duration) {
+        tokio::time::sleep(duration).await;
+
+        if self.state.lock().generation != generation {
+            return;
+        }
+        if let Err(err) = self.resume() {
+            tracing::error!("failed to resume consensus network: {err:#}");
+        }
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use sequencer_utils::test_utils::setup_test;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_temporary_network_pause() {
+        setup_test();
+
+        let control = NetworkPauseControl::default();
+        control.pause(NetworkPause::default()).unwrap_err();
+
+        // Attach a stand-in for the network which records whether it is paused.
+        let paused = Arc::new(AtomicBool::new(false));
+        let set_paused: SetPaused = {
+            let paused = paused.clone();
+            Arc::new(move |value| paused.store(value, Ordering::SeqCst))
+        };
+        control.network.set(Some(set_paused)).ok().unwrap();
+
+        // A temporary pause expires on its own.
+        let status = control
+            .pause(NetworkPause {
+                duration_secs: Some(1),
+            })
+            .unwrap();
+        assert!(status.paused);
+        assert!(status.resume_at.is_some());
+        assert!(paused.load(Ordering::SeqCst));
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(!control.status().paused);
+        assert!(!paused.load(Ordering::SeqCst));
+
+        // A later indefinite pause is not cut short by an earlier temporary one.
+        control
+            .pause(NetworkPause {
+                duration_secs: Some(1),
+            })
+            .unwrap();
+        control.pause(NetworkPause::default()).unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(
+            control.status(),
+            NetworkPauseStatus {
+                paused: true,
+                resume_at: None
+            }
+        );
+
+        control.resume().unwrap();
+        assert!(!paused.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_network_pause_unsupported() {
+        setup_test();
+
+        // A network which cannot be paused is never reported as paused.
+        let control = NetworkPauseControl::default();
+        control.network.set(None).ok().unwrap();
+        control.pause(NetworkPause::default()).unwrap_err();
+        control.resume().unwrap_err();
+        assert!(!control.status().paused);
+    }
+}
@@ -0,0 +1,286 @@
+//! Caching of namespace proofs.
+//!
+//! Rollup nodes tend to request the proof for their namespace in each block, often several times,
+//! and generating a proof walks the whole block payload. Proofs are therefore kept in an LRU cache
+//! keyed by block height and namespace. The cache also counts requests for each namespace, and
+//! proofs for the most requested namespaces are computed in the background as soon as each block
+//! becomes available, so that the first request for them is served from the cache as well.
+//!
+//! Concurrent requests for a proof which is not cached wait for a single computation of it.
+
+use std::{collections::HashMap, future::Future, num::NonZeroUsize, sync::Arc};
+
+use espresso_types::{NamespaceId, SeqTypes};
+use futures::StreamExt;
+use hotshot_query_service::{
+    availability::{self, AvailabilityDataSource, BlockQueryData, VidCommonQueryData},
+    node::NodeDataSource,
+    types::HeightIndexed,
+    VidCommon,
+};
+use lru::LruCache;
+use parking_lot::Mutex;
+
+/// Number of proofs kept in each cache
+const CAPACITY: usize = 1_000;
+
+/// Number of namespaces whose requests are counted
+const TRACKED_NAMESPACES: usize = 10_000;
+
+/// Number of namespaces whose proofs are computed in the background
+const HOT_NAMESPACES: usize = 10;
+
+/// Number of blocks after which request counts are halved, so that hotness follows recent demand
+const DECAY_INTERVAL: u64 = 100;
+
+/// Function generating a proof for one namespace of a block
+pub(super) type Prover<T> = fn(
+    &BlockQueryData<SeqTypes>,
+    &VidCommonQueryData<SeqTypes>,
+    NamespaceId,
+) -> Result<T, availability::Error>;
+
+/// Cache of namespace proofs of type `T`, by block height and namespace.
+#[derive(Debug)]
+pub struct NsProofCache<T> {
+    proofs: Mutex<LruCache<(u64, NamespaceId), T>>,
+    requests: Mutex<LruCache<NamespaceId, u64>>,
+    /// Proofs being computed, locked for the duration of the computation
+    in_flight: Mutex<HashMap<(u64, NamespaceId), Arc<async_lock::Mutex<()>>>>,
+}
+
+impl<T> Default for NsProofCache<T> {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(CAPACITY).unwrap())
+    }
+}
+
+impl<T> NsProofCache<T> {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            proofs: Mutex::new(LruCache::new(capacity)),
+            requests: Mutex::new(LruCache::new(
+                NonZeroUsize::new(TRACKED_NAMESPACES).unwrap(),
+            )),
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Look up the proof for `namespace` in the block at `height`, counting a request for it.
+    pub fn get(&self, height: u64, namespace: NamespaceId) -> Option<T>
+    where
+        T: Clone,
+    {
+        *self.requests.lock().get_or_insert_mut(namespace, || 0) += 1;
+        self.proofs.lock().get(&(height, namespace)).cloned()
+    }
+
+    /// Look up the proof for `namespace` in the block at `height`, computing it with `compute` if
+    /// it is not cached.
+    ///
+    /// If the proof is already being computed for another request, this waits for that
+    /// computation instead of repeating it.
+    pub async fn get_or_compute<F, Fut, E>(
+        &self,
+        height: u64,
+        namespace: NamespaceId,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Clone,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(proof) = self.get(height, namespace) {
+            return Ok(proof);
+        }
+        let claim = InFlight::new(self, (height, namespace));
+        let _guard = claim.lock.lock().await;
+        if let Some(proof) = self.proofs.lock().get(&(height, namespace)).cloned() {
+            return Ok(proof);
+        }
+        let proof = compute().await?;
+        self.insert(height, namespace, proof.clone());
+        Ok(proof)
+    }
+
+    pub fn insert(&self, height: u64, namespace: NamespaceId, proof: T) {
+        self.proofs.lock().put((height, namespace), proof);
+    }
+
+    /// The `n` most requested namespaces, most requested first.
+    pub fn hot_namespaces(&self, n: usize) -> Vec<NamespaceId> {
+        let mut counts = self
+            .requests
+            .lock()
+            .iter()
+            .map(|(ns, count)| (*ns, *count))
+            .collect::<Vec<_>>();
+        counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+        counts.into_iter().take(n).map(|(ns, _)| ns).collect()
+    }
+
+    /// Halve the request counts, forgetting namespaces which are no longer requested.
+    fn decay(&self) {
+        let mut requests = self.requests.lock();
+        let forgotten = requests
+            .iter_mut()
+            .filter_map(|(ns, count)| {
+                *count /= 2;
+                (*count == 0).then_some(*ns)
+            })
+            .collect::<Vec<_>>();
+        for ns in forgotten {
+            requests.pop(&ns);
+        }
+    }
+}
+
+/// A claim on the computation of one proof, released when dropped
+struct InFlight<'a, T> {
+    cache: &'a NsProofCache<T>,
+    key: (u64, NamespaceId),
+    lock: Arc<async_lock::Mutex<()>>,
+}
+
+impl<'a, T> InFlight<'a, T> {
+    fn new(cache: &'a NsProofCache<T>, key: (u64, NamespaceId)) -> Self {
+        let lock = cache.in_flight.lock().entry(key).or_default().clone();
+        Self { cache, key, lock }
+    }
+}
+
+impl<T> Drop for InFlight<'_, T> {
+    fn drop(&mut self) {
+        // Forget the lock once no other request holds or waits on it.
+        let mut in_flight = self.cache.in_flight.lock();
+        if Arc::strong_count(&self.lock) == 2 {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Compute proofs for the hot namespaces of each new block, as it becomes available.
+///
+/// Only blocks whose VID common data satisfies `supported` are considered, since `prove` fails on
+/// any other.
+pub(super) async fn precompute<D, T>(
+    cache: Arc<NsProofCache<T>>,
+    ds: Arc<D>,
+    prove: Prover<T>,
+    supported: fn(&VidCommon) -> bool,
+) where
+    D: AvailabilityDataSource<SeqTypes> + NodeDataSource<SeqTypes> + Send + Sync,
+    T: Send + 'static,
+{
+    let from = match ds.block_height().await {
+        Ok(height) => height,
+        Err(err) => {
+            tracing::warn!(
+                "unable to get block height, precomputing namespace proofs from genesis: {err:#}"
+            );
+            0
+        },
+    };
+    let blocks = ds.subscribe_blocks(from).await;
+    let common = ds.subscribe_vid_common(from).await;
+    let mut blocks = blocks.zip(common);
+    while let Some((block, common)) = blocks.next().await {
+        let height = block.height();
+        if height % DECAY_INTERVAL == 0 {
+            cache.decay();
+        }
+        if !supported(common.common()) {
+            continue;
+        }
+        let namespaces = cache
+            .hot_namespaces(HOT_NAMESPACES)
+            .into_iter()
+            .filter(|ns| block.payload().ns_table().find_ns_id(ns).is_some())
+            .collect::<Vec<_>>();
+        if !namespaces.is_empty() {
+            // Proof generation is CPU bound; keep it off the async runtime.
+            let res = tokio::task::spawn_blocking(move || {
+                namespaces
+                    .into_iter()
+                    .map(|ns| (ns, prove(&block, &common, ns)))
+                    .collect::<Vec<_>>()
+            })
+            .await;
+            match res {
+                Ok(proofs) => {
+                    for (ns, proof) in proofs {
+                        match proof {
+                            Ok(proof) => cache.insert(height, ns, proof),
+                            Err(err) => {
+                                tracing::warn!(height, %ns, "failed to precompute namespace proof: {err:#}")
+                            },
+                        }
+                    }
+                },
+                Err(err) => tracing::error!(height, "namespace proof task panicked: {err}"),
+            }
+        }
+    }
+    tracing::warn!("block stream ended, no longer precomputing namespace proofs");
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_ns_proof_cache() {
+        let cache = NsProofCache::new(NonZeroUsize::new(2).unwrap());
+        let (a, b, c) = (
+            NamespaceId::from(1u32),
+            NamespaceId::from(2u32),
+            NamespaceId::from(3u32),
+        );
+
+        assert_eq!(cache.get(0, a), None);
+        cache.insert(0, a, "a0");
+        cache.insert(0, b, "b0");
+        assert_eq!(cache.get(0, a), Some("a0"));
+        assert_eq!(cache.get(0, b), Some("b0"));
+
+        // Touch `a` so that `b` is least recently used, then evict it.
+        cache.get(0, a);
+        cache.insert(1, a, "a1");
+        assert_eq!(cache.get(0, b), None);
+        assert_eq!(cache.get(0, a), Some("a0"));
+        assert_eq!(cache.get(1, a), Some("a1"));
+
+        // `a` has had 5 requests, `b` 2 and `c` 1.
+        cache.get(0, c);
+        assert_eq!(cache.hot_namespaces(2), vec![a, b]);
+        assert_eq!(cache.hot_namespaces(5), vec![a, b, c]);
+
+        // After decay, namespaces with a single request are forgotten.
+        cache.decay();
+        assert_eq!(cache.hot_namespaces(5), vec![a, b]);
+    }
+
+    #[tokio::test]
+    async fn test_ns_proof_single_flight() {
+        let cache = NsProofCache::<&str>::default();
+        let ns = NamespaceId::from(1u32);
+        let computations = AtomicUsize::new(0);
+        let compute = || async {
+            computations.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Ok::<_, ()>("proof")
+        };
+
+        let (a, b) = futures::join!(
+            cache.get_or_compute(0, ns, compute),
+            cache.get_or_compute(0, ns, compute),
+        );
+        assert_eq!(a, Ok("proof"));
+        assert_eq!(b, Ok("proof"));
+        assert_eq!(computations.load(Ordering::SeqCst), 1);
+        assert!(cache.in_flight.lock().is_empty());
+    }
+}
@@ -1,10 +1,11 @@
 //! Sequencer-specific API options and initialization.
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use anyhow::{bail, Context};
 use clap::Parser;
 use espresso_types::{
+    parse_size,
     v0::traits::{EventConsumer, NullEventConsumer, PersistenceOptions, SequencerPersistence},
     BlockMerkleTree, PubKey,
 };
@@ -15,8 +16,10 @@ use futures::{
 use hotshot_events_service::events::Error as EventStreamingError;
 use hotshot_query_service::{
     data_source::{ExtensibleDataSource, MetricsDataSource},
-    fetching::provider::QueryServiceProvider,
-    status::{self, UpdateStatusData},
+    explorer::update_explorer_stats_loop,
+    fetching::provider::{QueryServiceProvider, VidReconstructionProvider},
+    metrics::PrometheusMetrics,
+    status::{self, HasMetrics, UpdateStatusData},
     ApiState as AppState, Error,
 };
 use hotshot_types::traits::{
@@ -32,11 +35,18 @@ use super::{
         provider, CatchupDataSource, HotShotConfigDataSource, NodeStateDataSource, Provider,
         SequencerDataSource, StateSignatureDataSource, SubmitDataSource,
     },
-    endpoints, fs, sql,
+    endpoints, fs,
+    log_filter::LogFilterControl,
+    network_pause::NetworkPauseControl,
+    ns_proof_cache,
+    rate_limit::{NamespaceLimit, SubmitLimiter},
+    sql,
     update::ApiEventConsumer,
     ApiState, StorageState,
 };
 use crate::{
+    alerts::{AlertConfig, AlertManager},
+    audit::AuditLog,
     catchup::CatchupStorage,
     context::{SequencerContext, TaskList},
     persistence,
@@ -54,8 +64,13 @@ pub struct Options {
     pub config: Option<Config>,
     pub hotshot_events: Option<HotshotEvents>,
     pub explorer: Option<Explorer>,
+    pub admin: Option<Admin>,
     pub storage_fs: Option<persistence::fs::Options>,
     pub storage_sql: Option<persistence::sql::Options>,
+    /// Pauses the consensus network on behalf of the admin API, once consensus has started
+    network_pause: NetworkPauseControl,
+    /// Audit log served by the admin API, if the node runs in audit mode
+    audit: Option<AuditLog>,
 }
 
 impl From<Http> for Options {
@@ -69,8 +84,11 @@ impl From<Http> for Options {
             config: None,
             hotshot_events: None,
             explorer: None,
+            admin: None,
             storage_fs: None,
             storage_sql: None,
+            network_pause: NetworkPauseControl::default(),
+            audit: None,
         }
     }
 }
@@ -131,6 +149,18 @@ impl Options {
         self
     }
 
+    /// Add an admin API module.
+    pub fn admin(mut self, opt: Admin) -> Self {
+        self.admin = Some(opt);
+        self
+    }
+
+    /// Serve the records of an audit log through the admin API.
+    pub fn audit(mut self, log: AuditLog) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
     /// Whether these options will run the query API.
     pub fn has_query_module(&self) -> bool {
         self.query.is_some() && (self.storage_fs.is_some() || self.storage_sql.is_some())
@@ -195,6 +225,7 @@ impl Options {
                 // storage.
                 let ds = MetricsDataSource::default();
                 let metrics = ds.populate_metrics();
+                self.init_and_spawn_alerts(ds.metrics(), &mut tasks)?;
                 let mut app = App::<_, Error>::with_state(AppState::from(
                     ExtensibleDataSource::new(ds, state.clone()),
                 ));
@@ -204,7 +235,7 @@ impl Options {
                     status::define_api(&Default::default(), SequencerApiVersion::instance())?;
                 app.register_module("status", status_api)?;
 
-                self.init_hotshot_modules(&mut app)?;
+                self.init_hotshot_modules(&mut app, &*metrics)?;
 
                 if self.hotshot_events.is_some() {
                     self.init_and_spawn_hotshot_event_streaming_module(state, &mut tasks)?;
@@ -225,7 +256,7 @@ impl Options {
                 // so we better have been provided the leaf ahead of time if we want it at all.
                 let mut app = App::<_, Error>::with_state(AppState::from(state.clone()));
 
-                self.init_hotshot_modules(&mut app)?;
+                self.init_hotshot_modules(&mut app, &NoMetrics)?;
 
                 if self.hotshot_events.is_some() {
                     self.init_and_spawn_hotshot_event_streaming_module(state, &mut tasks)?;
@@ -240,6 +271,10 @@ impl Options {
             };
 
         let ctx = init_context(metrics, consumer).await?;
+        if self.admin.is_some() {
+            let network = ctx.consensus().read().await.hotshot.network.clone();
+            self.network_pause.attach(network);
+        }
         send_ctx
             .send(super::ConsensusState::from(&ctx))
             .ok()
@@ -251,6 +286,7 @@ impl Options {
         &self,
         ds: D,
         state: ApiState<N, P, V>,
+        tasks: &mut TaskList,
         bind_version: SequencerApiVersion,
     ) -> anyhow::Result<(
         Box<dyn Metrics>,
@@ -282,25 +318,50 @@ impl Options {
 
         // initialize the availability module for API version V0.
         // This ensures compatibility for nodes that expect `Leaf1` for leaf endpoints
+        let ns_proof_caches = endpoints::NsProofCaches::default();
         app.register_module(
             "availability",
-            endpoints::availability("0.0.1".parse().unwrap())?,
+            endpoints::availability("0.0.1".parse().unwrap(), &ns_proof_caches)?,
         )?;
 
         // initialize the availability module for API version V1.
         // This enables support for the new `Leaf2` type
         app.register_module(
             "availability",
-            endpoints::availability("1.0.0".parse().unwrap())?,
+            endpoints::availability("1.0.0".parse().unwrap(), &ns_proof_caches)?,
         )?;
 
-        app.register_module("node", endpoints::node()?)?;
+        // Keep proofs for the most requested namespaces ready ahead of requests.
+        tasks.spawn(
+            "namespace proof precomputation (v0)",
+            ns_proof_cache::precompute(
+                ns_proof_caches.v0,
+                ds.clone(),
+                endpoints::advz_ns_proof_query_data,
+                endpoints::advz_ns_proof_supported,
+            ),
+        );
+        tasks.spawn(
+            "namespace proof precomputation (v1)",
+            ns_proof_cache::precompute(
+                ns_proof_caches.v1,
+                ds.clone(),
+                endpoints::ns_proof_query_data,
+                |_| true,
+            ),
+        );
+
+        app.register_module(
+            "node",
+            endpoints::node(self.admin.as_ref().map(|admin| admin.token.clone()))?,
+        )?;
 
         // Initialize submit API
-        if self.submit.is_some() {
+        if let Some(submit) = &self.submit {
+            let limiter = SubmitLimiter::new(submit, &*metrics);
             app.register_module(
                 "submit",
-                endpoints::submit::<_, _, _, SequencerApiVersion>()?,
+                endpoints::submit::<_, _, _, SequencerApiVersion>(limiter)?,
             )?;
         }
 
@@ -312,6 +373,7 @@ impl Options {
         if self.config.is_some() {
             app.register_module("config", endpoints::config(bind_version)?)?;
         }
+        self.init_admin_module(&mut app)?;
         Ok((metrics, ds, app))
     }
 
@@ -329,14 +391,15 @@ impl Options {
     {
         let ds = <fs::DataSource as SequencerDataSource>::create(
             mod_opt,
-            provider::<V>(query_opt.peers, bind_version),
+            provider::<V>(query_opt.peers, query_opt.vid_peers, bind_version),
             false,
         )
         .await?;
 
         let (metrics, ds, app) = self
-            .init_app_modules(ds, state.clone(), bind_version)
+            .init_app_modules(ds, state.clone(), tasks, bind_version)
             .await?;
+        self.init_and_spawn_alerts(ds.metrics(), tasks)?;
 
         if self.hotshot_events.is_some() {
             self.init_and_spawn_hotshot_event_streaming_module(state, tasks)?;
@@ -368,14 +431,27 @@ impl Options {
             tracing::info!("will fetch missing data from {peer}");
             provider = provider.with_provider(QueryServiceProvider::new(peer, bind_version));
         }
+        // As a last resort, reconstruct missing payloads from VID shares.
+        if !query_opt.vid_peers.is_empty() {
+            tracing::info!(peers = ?query_opt.vid_peers, "will reconstruct missing payloads from VID shares");
+            provider = provider.with_block_provider(VidReconstructionProvider::new(
+                query_opt.vid_peers,
+                bind_version,
+            ));
+        }
 
         let ds = sql::DataSource::create(mod_opt.clone(), provider, false).await?;
         let (metrics, ds, mut app) = self
-            .init_app_modules(ds, state.clone(), bind_version)
+            .init_app_modules(ds, state.clone(), tasks, bind_version)
             .await?;
+        self.init_and_spawn_alerts(ds.metrics(), tasks)?;
 
         if self.explorer.is_some() {
             app.register_module("explorer", endpoints::explorer()?)?;
+            tasks.spawn(
+                "explorer statistics update loop",
+                update_explorer_stats_loop(ds.clone()),
+            );
         }
 
         // Initialize merklized state module for block merkle tree
@@ -397,7 +473,7 @@ impl Options {
         };
         tasks.spawn(
             "merklized state storage update loop",
-            update_state_storage_loop(ds.clone(), get_node_state),
+            update_state_storage_loop(ds.clone(), get_node_state, mod_opt.state_snapshot_interval),
         );
         if self.hotshot_events.is_some() {
             self.init_and_spawn_hotshot_event_streaming_module(state, tasks)?;
@@ -410,12 +486,35 @@ impl Options {
         Ok((metrics, Box::new(ApiEventConsumer::from(ds))))
     }
 
+    /// Start evaluating alert rules over `metrics`, if the status module is configured with them.
+    fn init_and_spawn_alerts(
+        &self,
+        metrics: &PrometheusMetrics,
+        tasks: &mut TaskList,
+    ) -> anyhow::Result<()> {
+        let Some(path) = self
+            .status
+            .as_ref()
+            .and_then(|opt| opt.alert_config.as_ref())
+        else {
+            return Ok(());
+        };
+        let config = AlertConfig::load(path)
+            .with_context(|| format!("loading alert rules from {}", path.display()))?;
+        tasks.spawn("alerts", AlertManager::new(config, metrics.clone()).run());
+        Ok(())
+    }
+
     /// Initialize the modules for interacting with HotShot.
     ///
     /// This function adds the `submit`, `state`, and `state_signature` API modules to the given
     /// app. These modules only require a HotShot handle as state, and thus they work with any data
     /// source, so initialization is the same no matter what mode the service is running in.
-    fn init_hotshot_modules<N, P, S>(&self, app: &mut App<S, Error>) -> anyhow::Result<()>
+    fn init_hotshot_modules<N, P, S>(
+        &self,
+        app: &mut App<S, Error>,
+        metrics: &dyn Metrics,
+    ) -> anyhow::Result<()>
     where
         S: 'static + Send + Sync + ReadState,
         P: SequencerPersistence,
@@ -430,8 +529,9 @@ impl Options {
     {
         let bind_version = SequencerApiVersion::instance();
         // Initialize submit API
-        if self.submit.is_some() {
-            let submit_api = endpoints::submit::<_, _, _, SequencerApiVersion>()?;
+        if let Some(submit) = &self.submit {
+            let limiter = SubmitLimiter::new(submit, metrics);
+            let submit_api = endpoints::submit::<_, _, _, SequencerApiVersion>(limiter)?;
             app.register_module("submit", submit_api)?;
         }
 
@@ -448,10 +548,31 @@ impl Options {
         if self.config.is_some() {
             app.register_module("config", endpoints::config(bind_version)?)?;
         }
+        self.init_admin_module(app)?;
 
         Ok(())
     }
 
+    /// Add the admin API module to `app`, if it is enabled.
+    fn init_admin_module<S>(&self, app: &mut App<S, Error>) -> anyhow::Result<()>
+    where
+        S: 'static + Send + Sync + ReadState,
+    {
+        if let Some(admin) = &self.admin {
+            tracing::info!("initializing admin API");
+            app.register_module(
+                "admin",
+                endpoints::admin::<_, SequencerApiVersion>(
+                    admin.token.clone(),
+                    LogFilterControl::default(),
+                    self.network_pause.clone(),
+                    self.audit.clone(),
+                )?,
+            )?;
+        }
+        Ok(())
+    }
+
     // Enable the events streaming api module
     fn init_and_spawn_hotshot_event_streaming_module<
         N,
@@ -473,16 +594,19 @@ impl Options {
         let mut app = App::<_, EventStreamingError>::with_state(AppState::from(state));
 
         tracing::info!("initializing hotshot events API");
-        let hotshot_events_api = hotshot_events_service::events::define_api(
-            &hotshot_events_service::events::Options::default(),
-        )?;
+        let opt = self.hotshot_events.as_ref().unwrap();
+        let hotshot_events_api =
+            hotshot_events_service::events::define_api(&hotshot_events_service::events::Options {
+                api_keys: opt.events_service_api_keys.clone(),
+                ..Default::default()
+            })?;
 
         app.register_module::<_, SequencerApiVersion>("hotshot-events", hotshot_events_api)?;
 
         tasks.spawn(
             "Hotshot Events Streaming API server",
             self.listen(
-                self.hotshot_events.unwrap().events_service_port,
+                opt.events_service_port,
                 app,
                 SequencerApiVersion::instance(),
             ),
@@ -546,12 +670,65 @@ impl Http {
 }
 
 /// Options for the submission API module.
-#[derive(Parser, Clone, Copy, Debug, Default)]
-pub struct Submit;
+#[derive(Parser, Clone, Debug, Default)]
+pub struct Submit {
+    /// Maximum size of a submitted transaction.
+    ///
+    /// Transactions are always limited to the maximum block size. This sets a lower limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_MAX_TRANSACTION_SIZE", value_parser = parse_size)]
+    pub max_transaction_size: Option<u64>,
+
+    /// Transactions per second accepted for each namespace.
+    ///
+    /// Leave unset for no per-namespace limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_NAMESPACE_RATE")]
+    pub namespace_rate: Option<f64>,
+
+    /// Transactions which may be submitted at once for a namespace which has been quiet.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SUBMIT_NAMESPACE_BURST",
+        default_value = "100"
+    )]
+    pub namespace_burst: u32,
+
+    /// Rate limits for particular namespaces, overriding the default namespace limit.
+    ///
+    /// Each limit has the form NAMESPACE:RATE:BURST.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SUBMIT_NAMESPACE_LIMITS",
+        value_delimiter = ','
+    )]
+    pub namespace_limits: Vec<NamespaceLimit>,
+
+    /// Transactions per second accepted from each client IP address.
+    ///
+    /// Leave unset for no per-client limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_IP_RATE")]
+    pub ip_rate: Option<f64>,
+
+    /// Transactions which may be submitted at once by a client which has been quiet.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_IP_BURST", default_value = "20")]
+    pub ip_burst: u32,
+
+    /// Header holding the client IP address, such as X-Forwarded-For.
+    ///
+    /// Only set this behind a reverse proxy which sets the header, since clients can otherwise
+    /// choose their own address.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SUBMIT_IP_HEADER")]
+    pub ip_header: Option<String>,
+}
 
 /// Options for the status API module.
-#[derive(Parser, Clone, Copy, Debug, Default)]
-pub struct Status;
+#[derive(Parser, Clone, Debug, Default)]
+pub struct Status {
+    /// TOML file holding alert rules to evaluate over the metrics of this node
+    ///
+    /// When a rule starts or stops firing, the node notifies the webhooks listed in the file.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ALERT_CONFIG")]
+    pub alert_config: Option<PathBuf>,
+}
 
 /// Options for the catchup API module.
 #[derive(Parser, Clone, Copy, Debug, Default)]
@@ -567,6 +744,14 @@ pub struct Query {
     /// Peers for fetching missing data for the query service.
     #[clap(long, env = "ESPRESSO_SEQUENCER_API_PEERS", value_delimiter = ',')]
     pub peers: Vec<Url>,
+
+    /// Peers for reconstructing missing payloads from their VID shares.
+    ///
+    /// If no peer query service has stored a missing payload, it can be recovered from the VID
+    /// shares of enough other nodes. This should list the query services of nodes holding at least a
+    /// third of the stake.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_API_VID_PEERS", value_delimiter = ',')]
+    pub vid_peers: Vec<Url>,
 }
 
 /// Options for the state API module.
@@ -574,13 +759,34 @@ pub struct Query {
 pub struct State;
 
 /// Options for the Hotshot events streaming API module.
-#[derive(Parser, Clone, Copy, Debug, Default)]
+#[derive(Parser, Clone, Debug, Default)]
 pub struct HotshotEvents {
     /// Port that the HTTP Hotshot Event streaming API will use.
     #[clap(long, env = "ESPRESSO_SEQUENCER_HOTSHOT_EVENT_STREAMING_API_PORT")]
     pub events_service_port: u16,
+
+    /// File configuring the API keys clients must present to use the events streaming API.
+    ///
+    /// If not set, the API is open to everyone.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_HOTSHOT_EVENT_STREAMING_API_KEYS")]
+    pub events_service_api_keys: Option<PathBuf>,
 }
 
 /// Options for the explorer API module.
 #[derive(Parser, Clone, Copy, Debug, Default)]
 pub struct Explorer;
+
+/// Options for the admin API module.
+#[derive(Parser, Clone)]
+pub struct Admin {
+    /// Token which requests to the admin API must present as `Authorization: Bearer TOKEN`.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ADMIN_TOKEN", hide_env_values = true)]
+    pub token: String,
+}
+
+// Options are logged at startup, so keep the token out of the debug output.
+impl std::fmt::Debug for Admin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Admin").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,438 @@
+//! Rate limits on transaction submission.
+//!
+//! Each namespace and each client IP address gets a token bucket. A transaction spends one token
+//! from the bucket of its namespace and one from the bucket of the client which submitted it, and
+//! is turned away with `429 Too Many Requests` if either bucket is empty. Buckets refill at a
+//! steady rate up to their burst allowance, so one busy rollup cannot crowd out the others.
+//!
+//! Behind a reverse proxy every request comes from the proxy's address, so clients are then told
+//! apart by a header the proxy sets to the address it received the request from.
+
+use std::{collections::HashMap, fmt, hash::Hash, num::NonZeroUsize, str::FromStr, time::Instant};
+
+use anyhow::{ensure, Context};
+use espresso_types::NamespaceId;
+use hotshot_types::traits::metrics::{Counter, CounterFamily, Metrics};
+use lru::LruCache;
+use parking_lot::Mutex;
+use tide_disco::{RequestParams, StatusCode};
+
+use super::options::Submit;
+
+/// Number of buckets kept for each kind of client, beyond which the least recently used is dropped
+const MAX_BUCKETS: usize = 10_000;
+
+/// A sustained rate with a burst allowance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    /// Transactions per second
+    pub rate: f64,
+    /// Transactions which may be submitted at once after a quiet period
+    pub burst: u32,
+}
+
+impl Limit {
+    fn capacity(&self) -> f64 {
+        self.burst.max(1) as f64
+    }
+}
+
+/// A rate limit for one particular namespace, overriding the default namespace limit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NamespaceLimit {
+    pub namespace: NamespaceId,
+    pub limit: Limit,
+}
+
+impl FromStr for NamespaceLimit {
+    type Err = anyhow::Error;
+
+    /// Parse a limit of the form `NAMESPACE:RATE:BURST`
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parts = s.split(':');
+        let (Some(namespace), Some(rate), Some(burst), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("namespace limit must be of the form NAMESPACE:RATE:BURST");
+        };
+        let namespace = namespace
+            .parse::<u64>()
+            .context("malformed namespace")?
+            .into();
+        let rate = rate.parse::<f64>().context("malformed rate")?;
+        ensure!(rate.is_finite() && rate >= 0.0, "rate must be non-negative");
+        let burst = burst.parse().context("malformed burst")?;
+        Ok(Self {
+            namespace,
+            limit: Limit { rate, burst },
+        })
+    }
+}
+
+/// Why a submitted transaction was turned away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// The transaction is larger than the configured maximum
+    TooLarge,
+    /// The namespace of the transaction is over its rate limit
+    NamespaceLimited,
+    /// The client is over its rate limit
+    ClientLimited,
+}
+
+impl Rejection {
+    /// Label for this reason in metrics
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TooLarge => "too_large",
+            Self::NamespaceLimited => "namespace_rate_limited",
+            Self::ClientLimited => "client_rate_limited",
+        }
+    }
+
+    /// The HTTP status to respond with
+    pub fn status(self) -> StatusCode {
+        match self {
+            Self::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::NamespaceLimited | Self::ClientLimited => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge => write!(f, "transaction is too large"),
+            Self::NamespaceLimited => write!(f, "namespace is over its submission rate limit"),
+            Self::ClientLimited => write!(f, "client is over its submission rate limit"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    limit: Limit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: Limit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.capacity(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.rate).min(self.limit.capacity());
+        self.updated = now;
+    }
+
+    #[cfg(test)]
+    fn is_full(&self) -> bool {
+        self.tokens >= self.limit.capacity()
+    }
+}
+
+/// Token buckets for one kind of client, by key
+#[derive(Debug)]
+struct Buckets<K: Eq + Hash> {
+    buckets: LruCache<K, Bucket>,
+}
+
+impl<K: Eq + Hash> Buckets<K> {
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            buckets: LruCache::new(capacity),
+        }
+    }
+
+    /// The bucket for `key`, refilled as of `now`
+    ///
+    /// When there are too many buckets, the one used least recently, and so the most likely to
+    /// have refilled, is forgotten.
+    fn get(&mut self, key: K, limit: Limit, now: Instant) -> &mut Bucket {
+        let bucket = self
+            .buckets
+            .get_or_insert_mut(key, || Bucket::full(limit, now));
+        bucket.refill(now);
+        bucket
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    namespaces: Buckets<NamespaceId>,
+    clients: Buckets<String>,
+}
+
+#[derive(Debug)]
+struct SubmitMetrics {
+    accepted: Box<dyn Counter>,
+    rejected: Box<dyn CounterFamily>,
+}
+
+/// Enforces the size and rate limits on submitted transactions
+#[derive(Debug)]
+pub struct SubmitLimiter {
+    max_transaction_size: Option<u64>,
+    namespace_limit: Option<Limit>,
+    namespace_overrides: HashMap<NamespaceId, Limit>,
+    client_limit: Option<Limit>,
+    /// Header holding the client address, set by a trusted proxy
+    client_header: Option<String>,
+    state: Mutex<State>,
+    metrics: SubmitMetrics,
+}
+
+impl SubmitLimiter {
+    pub fn new(opt: &Submit, metrics: &dyn Metrics) -> Self {
+        let metrics = metrics.subgroup("submit".into());
+        Self {
+            max_transaction_size: opt.max_transaction_size,
+            namespace_limit: opt.namespace_rate.map(|rate| Limit {
+                rate,
+                burst: opt.namespace_burst,
+            }),
+            namespace_overrides: opt
+                .namespace_limits
+                .iter()
+                .map(|limit| (limit.namespace, limit.limit))
+                .collect(),
+            client_limit: opt.ip_rate.map(|rate| Limit {
+                rate,
+                burst: opt.ip_burst,
+            }),
+            client_header: opt.ip_header.clone(),
+            state: Mutex::new(State {
+                namespaces: Buckets::new(NonZeroUsize::new(MAX_BUCKETS).unwrap()),
+                clients: Buckets::new(NonZeroUsize::new(MAX_BUCKETS).unwrap()),
+            }),
+            metrics: SubmitMetrics {
+                accepted: metrics.create_counter("accepted_transactions".into(), None),
+                rejected: metrics
+                    .counter_family("rejected_transactions".into(), vec!["reason".into()]),
+            },
+        }
+    }
+
+    /// The address of the client which made `req`
+    ///
+    /// This is the last address in the trusted proxy header, which the proxy itself added, or the
+    /// remote address of the request if there is no such header.
+    pub fn client<'a>(&self, req: &'a RequestParams) -> Option<&'a str> {
+        self.client_header
+            .as_deref()
+            .and_then(|header| req.header(header))
+            .and_then(|values| values.last().as_str().rsplit(',').next())
+            .map(str::trim)
+            .or_else(|| req.remote())
+    }
+
+    /// Admit a transaction of `size` bytes in `namespace` submitted by `client`, if no limit
+    /// forbids it.
+    ///
+    /// # Errors
+    /// If the transaction is too large, or its namespace or client is over its rate limit.
+    pub fn check(
+        &self,
+        namespace: NamespaceId,
+        size: usize,
+        client: Option<&str>,
+    ) -> Result<(), Rejection> {
+        let res = self.check_at(namespace, size, client, Instant::now());
+        match res {
+            Ok(()) => self.metrics.accepted.add(1),
+            Err(reason) => self
+                .metrics
+                .rejected
+                .create(vec![reason.as_str().into()])
+                .add(1),
+        }
+        res
+    }
+
+    fn check_at(
+        &self,
+        namespace: NamespaceId,
+        size: usize,
+        client: Option<&str>,
+        now: Instant,
+    ) -> Result<(), Rejection> {
+        if self
+            .max_transaction_size
+            .is_some_and(|max| size as u64 > max)
+        {
+            return Err(Rejection::TooLarge);
+        }
+
+        let mut state = self.state.lock();
+        let State {
+            namespaces,
+            clients,
+        } = &mut *state;
+
+        // Check both buckets before spending from either, so a rejected transaction costs nothing.
+        let namespace_limit = self
+            .namespace_overrides
+            .get(&namespace)
+            .or(self.namespace_limit.as_ref())
+            .copied();
+        let namespace_bucket = namespace_limit.map(|limit| namespaces.get(namespace, limit, now));
+        if namespace_bucket
+            .as_ref()
+            .is_some_and(|bucket| bucket.tokens < 1.0)
+        {
+            return Err(Rejection::NamespaceLimited);
+        }
+        let client_bucket = match (self.client_limit, client) {
+            (Some(limit), Some(client)) => Some(clients.get(client.to_string(), limit, now)),
+            _ => None,
+        };
+        if client_bucket
+            .as_ref()
+            .is_some_and(|bucket| bucket.tokens < 1.0)
+        {
+            return Err(Rejection::ClientLimited);
+        }
+
+        for bucket in namespace_bucket.into_iter().chain(client_bucket) {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use hotshot_types::traits::metrics::NoMetrics;
+
+    use super::*;
+
+    fn limiter(opt: Submit) -> SubmitLimiter {
+        SubmitLimiter::new(&opt, &NoMetrics)
+    }
+
+    #[test]
+    fn test_parse_namespace_limit() {
+        assert_eq!(
+            "10:2.5:20".parse::<NamespaceLimit>().unwrap(),
+            NamespaceLimit {
+                namespace: 10u64.into(),
+                limit: Limit {
+                    rate: 2.5,
+                    burst: 20
+                },
+            }
+        );
+        "10:2.5".parse::<NamespaceLimit>().unwrap_err();
+        "10:-1:20".parse::<NamespaceLimit>().unwrap_err();
+        "ns:1:20".parse::<NamespaceLimit>().unwrap_err();
+    }
+
+    #[test]
+    fn test_max_transaction_size() {
+        let limiter = limiter(Submit {
+            max_transaction_size: Some(100),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        limiter.check_at(1u64.into(), 100, None, now).unwrap();
+        assert_eq!(
+            limiter.check_at(1u64.into(), 101, None, now),
+            Err(Rejection::TooLarge)
+        );
+    }
+
+    #[test]
+    fn test_namespace_rate_limit() {
+        let limiter = limiter(Submit {
+            namespace_rate: Some(1.0),
+            namespace_burst: 2,
+            namespace_limits: vec![NamespaceLimit {
+                namespace: 2u64.into(),
+                limit: Limit {
+                    rate: 10.0,
+                    burst: 5,
+                },
+            }],
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let check = |ns: u64, at: Instant| limiter.check_at(ns.into(), 1, None, at);
+
+        // The burst allowance is available up front.
+        check(1, now).unwrap();
+        check(1, now).unwrap();
+        assert_eq!(check(1, now), Err(Rejection::NamespaceLimited));
+
+        // Other namespaces are unaffected, and overrides apply.
+        for _ in 0..5 {
+            check(2, now).unwrap();
+        }
+        assert_eq!(check(2, now), Err(Rejection::NamespaceLimited));
+        check(3, now).unwrap();
+
+        // Buckets refill at the configured rate.
+        let later = now + Duration::from_secs(1);
+        check(1, later).unwrap();
+        assert_eq!(check(1, later), Err(Rejection::NamespaceLimited));
+        for _ in 0..5 {
+            check(2, later).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_buckets_bounded() {
+        let limit = Limit {
+            rate: 1.0,
+            burst: 1,
+        };
+        let now = Instant::now();
+        let mut buckets = Buckets::new(NonZeroUsize::new(2).unwrap());
+
+        buckets.get(1, limit, now).tokens -= 1.0;
+        buckets.get(2, limit, now).tokens -= 1.0;
+        assert!(!buckets.get(1, limit, now).is_full());
+
+        // The bucket used least recently is dropped to make room.
+        buckets.get(3, limit, now);
+        assert_eq!(buckets.buckets.len(), 2);
+        assert!(!buckets.get(1, limit, now).is_full());
+        assert!(buckets.get(2, limit, now).is_full());
+    }
+
+    #[test]
+    fn test_client_rate_limit() {
+        let limiter = limiter(Submit {
+            namespace_rate: Some(1.0),
+            namespace_burst: 1,
+            ip_rate: Some(1.0),
+            ip_burst: 1,
+            ..Default::default()
+        });
+        let now = Instant::now();
+
+        limiter
+            .check_at(1u64.into(), 1, Some("1.1.1.1"), now)
+            .unwrap();
+        assert_eq!(
+            limiter.check_at(2u64.into(), 1, Some("1.1.1.1"), now),
+            Err(Rejection::ClientLimited)
+        );
+
+        // A rejection by the client limit does not spend from the namespace bucket.
+        limiter
+            .check_at(2u64.into(), 1, Some("2.2.2.2"), now)
+            .unwrap();
+        assert_eq!(
+            limiter.check_at(2u64.into(), 1, Some("3.3.3.3"), now),
+            Err(Rejection::NamespaceLimited)
+        );
+    }
+}
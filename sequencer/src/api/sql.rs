@@ -6,16 +6,18 @@ use committable::{Commitment, Committable};
 use espresso_types::{
     get_l1_deposits,
     v0_1::{RewardAccount, RewardMerkleTree, REWARD_MERKLE_TREE_HEIGHT},
-    v0_99::{ChainConfig, IterableFeeInfo},
+    v0_4::ChainConfig,
+    v0_99::IterableFeeInfo,
     BlockMerkleTree, EpochVersion, FeeAccount, FeeMerkleTree, Leaf2, NodeState, ValidatedState,
 };
 use hotshot::traits::ValidatedState as _;
 use hotshot_query_service::{
     availability::LeafId,
     data_source::{
+        fetching::BackfillCfg,
         sql::{Config, SqlDataSource, Transaction},
         storage::{
-            sql::{query_as, Db, TransactionMode, Write},
+            sql::{query, query_as, Db, TransactionMode, Write},
             AvailabilityStorage, MerklizedStateStorage, NodeStorage, SqlStorage,
         },
         VersionedDataSource,
@@ -43,8 +45,9 @@ use super::{
 };
 use crate::{
     catchup::{CatchupStorage, NullStateCatchup},
-    persistence::{sql::Options, ChainConfigPersistence},
+    persistence::{sql::Options, ChainConfigPersistence, StateSnapshotPersistence},
     state::compute_state_update,
+    state_sync::{StateDiff, StateSnapshot, StateSnapshotInfo, STATE_SNAPSHOT_CHUNK_SIZE},
     SeqTypes,
 };
 
@@ -64,6 +67,20 @@ impl SequencerDataSource for DataSource {
             cfg = cfg.reset_schema();
         }
 
+        // Only the query service reads from the replica and has its reads time out. Consensus
+        // storage must always observe its own writes and shares the connection pool, so it is
+        // configured without either.
+        #[cfg(not(feature = "embedded-db"))]
+        if let Some(uri) = &opt.read_replica_uri {
+            cfg = cfg
+                .read_replica(uri.parse().context("invalid read replica URI")?)
+                .read_replica_max_connections(opt.read_replica_max_connections);
+        }
+        #[cfg(not(feature = "embedded-db"))]
+        if let Some(timeout) = opt.statement_timeout {
+            cfg = cfg.statement_timeout(timeout);
+        }
+
         let mut builder = cfg.builder(provider).await?;
 
         if let Some(limit) = fetch_limit {
@@ -86,6 +103,17 @@ impl SequencerDataSource for DataSource {
             builder = builder.with_types_migration_batch_size(batch_size);
         }
 
+        if opt.archival_backfill {
+            let mut backfill = BackfillCfg::new();
+            if let Some(start) = opt.archival_backfill_start {
+                backfill = backfill.with_start(start);
+            }
+            if let Some(limit) = opt.archival_backfill_rate_limit {
+                backfill = backfill.with_rate_limit(limit);
+            }
+            builder = builder.with_archival_backfill(backfill);
+        }
+
         builder.build().await
     }
 }
@@ -253,6 +281,45 @@ impl CatchupStorage for SqlStorage {
 
         Ok(chain)
     }
+
+    async fn get_state_snapshot_info(&self) -> anyhow::Result<StateSnapshotInfo> {
+        let mut tx = self
+            .read()
+            .await
+            .context("opening transaction to fetch state snapshot")?;
+        load_latest_state_snapshot_info(&mut tx)
+            .await?
+            .context("no state snapshot available")
+    }
+
+    async fn get_state_snapshot_chunk(&self, height: u64, chunk: u64) -> anyhow::Result<Vec<u8>> {
+        let mut tx = self.read().await.context(format!(
+            "opening transaction to fetch state snapshot {height} chunk {chunk}"
+        ))?;
+        let (data,) = query_as::<(Vec<u8>,)>(
+            "SELECT data FROM state_snapshot WHERE height = $1 AND chunk = $2",
+        )
+        .bind(height as i64)
+        .bind(chunk as i32)
+        .fetch_optional(tx.as_mut())
+        .await?
+        .context(format!(
+            "state snapshot {height} chunk {chunk} not available"
+        ))?;
+        Ok(data)
+    }
+
+    async fn get_state_diffs(&self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>> {
+        let mut tx = self.read().await.context(format!(
+            "opening transaction to fetch state diffs {from}..={to}"
+        ))?;
+        let diffs = load_state_diffs(&mut tx, from, to).await?;
+        ensure!(
+            diffs.len() as u64 == to + 1 - from,
+            "state diffs {from}..={to} not available"
+        );
+        Ok(diffs)
+    }
 }
 
 impl CatchupStorage for DataSource {
@@ -298,6 +365,18 @@ impl CatchupStorage for DataSource {
     async fn get_leaf_chain(&self, height: u64) -> anyhow::Result<Vec<Leaf2>> {
         self.as_ref().get_leaf_chain(height).await
     }
+
+    async fn get_state_snapshot_info(&self) -> anyhow::Result<StateSnapshotInfo> {
+        self.as_ref().get_state_snapshot_info().await
+    }
+
+    async fn get_state_snapshot_chunk(&self, height: u64, chunk: u64) -> anyhow::Result<Vec<u8>> {
+        self.as_ref().get_state_snapshot_chunk(height, chunk).await
+    }
+
+    async fn get_state_diffs(&self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>> {
+        self.as_ref().get_state_diffs(from, to).await
+    }
 }
 
 #[async_trait]
@@ -315,6 +394,119 @@ impl ChainConfigPersistence for Transaction<Write> {
     }
 }
 
+#[async_trait]
+impl StateSnapshotPersistence for Transaction<Write> {
+    async fn insert_state_diff(&mut self, diff: &StateDiff) -> anyhow::Result<()> {
+        let data = bincode::serialize(diff)?;
+        self.upsert(
+            "state_diff",
+            ["height", "data"],
+            ["height"],
+            [(diff.height as i64, data)],
+        )
+        .await
+    }
+
+    async fn load_state_diffs(&mut self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>> {
+        load_state_diffs(self, from, to).await
+    }
+
+    async fn load_latest_state_snapshot(&mut self) -> anyhow::Result<Option<StateSnapshot>> {
+        let Some(info) = load_latest_state_snapshot_info(self).await? else {
+            return Ok(None);
+        };
+        let chunks = query_as::<(Vec<u8>,)>(
+            "SELECT data FROM state_snapshot WHERE height = $1 ORDER BY chunk",
+        )
+        .bind(info.height as i64)
+        .fetch_all(self.as_mut())
+        .await?;
+        StateSnapshot::from_chunks(chunks.into_iter().map(|(data,)| data)).map(Some)
+    }
+
+    async fn latest_state_snapshot_height(&mut self) -> anyhow::Result<Option<u64>> {
+        Ok(load_latest_state_snapshot_info(self)
+            .await?
+            .map(|info| info.height))
+    }
+
+    async fn delete_state_snapshots(&mut self) -> anyhow::Result<()> {
+        query("DELETE FROM state_snapshot")
+            .execute(self.as_mut())
+            .await?;
+        query("DELETE FROM state_diff")
+            .execute(self.as_mut())
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_state_snapshot(&mut self, snapshot: &StateSnapshot) -> anyhow::Result<()> {
+        let previous = load_latest_state_snapshot_info(self).await?;
+
+        let height = snapshot.height as i64;
+        let chunks = snapshot.into_chunks(STATE_SNAPSHOT_CHUNK_SIZE)?;
+        query("DELETE FROM state_snapshot WHERE height = $1")
+            .bind(height)
+            .execute(self.as_mut())
+            .await?;
+        self.upsert(
+            "state_snapshot",
+            ["height", "chunk", "data"],
+            ["height", "chunk"],
+            chunks
+                .into_iter()
+                .enumerate()
+                .map(|(i, data)| (height, i as i32, data)),
+        )
+        .await?;
+
+        // Keep the previous snapshot, and the diffs after it, for nodes which are still
+        // downloading it.
+        if let Some(previous) = previous.filter(|previous| previous.height < snapshot.height) {
+            query("DELETE FROM state_snapshot WHERE height < $1")
+                .bind(previous.height as i64)
+                .execute(self.as_mut())
+                .await?;
+            query("DELETE FROM state_diff WHERE height <= $1")
+                .bind(previous.height as i64)
+                .execute(self.as_mut())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+async fn load_state_diffs<Mode: TransactionMode>(
+    tx: &mut Transaction<Mode>,
+    from: u64,
+    to: u64,
+) -> anyhow::Result<Vec<StateDiff>> {
+    let rows = query_as::<(Vec<u8>,)>(
+        "SELECT data FROM state_diff WHERE height >= $1 AND height <= $2 ORDER BY height",
+    )
+    .bind(from as i64)
+    .bind(to as i64)
+    .fetch_all(tx.as_mut())
+    .await?;
+    rows.into_iter()
+        .map(|(data,)| bincode::deserialize(&data).context("failed to deserialize state diff"))
+        .collect()
+}
+
+async fn load_latest_state_snapshot_info<Mode: TransactionMode>(
+    tx: &mut Transaction<Mode>,
+) -> anyhow::Result<Option<StateSnapshotInfo>> {
+    let row = query_as::<(i64, i64)>(
+        "SELECT height, count(*) FROM state_snapshot GROUP BY height ORDER BY height DESC LIMIT 1",
+    )
+    .fetch_optional(tx.as_mut())
+    .await?;
+    Ok(row.map(|(height, num_chunks)| StateSnapshotInfo {
+        height: height as u64,
+        num_chunks: num_chunks as u64,
+    }))
+}
+
 async fn load_frontier<Mode: TransactionMode>(
     tx: &mut Transaction<Mode>,
     height: u64,
@@ -0,0 +1,556 @@
+//! Audit mode: an append-only log of every proposal and vote the node sends or receives.
+//!
+//! Consensus storage only keeps what the node needs to make progress, and is garbage collected as
+//! views are decided. For post-incident forensics, and to investigate equivocation, operators can
+//! enable audit mode, in which consensus hands every proposal and vote to the [`AuditLog`] as it is
+//! sent or received, signature included.
+//!
+//! Records are appended as JSON lines to numbered segment files in the audit directory. A new
+//! segment is started whenever the current one reaches the configured size, and each time the node
+//! starts, so existing segments are never rewritten; once there are more than the configured
+//! number of segments, the oldest are deleted. Records are written by a background thread, so
+//! consensus never waits on the disk. If the writer falls far behind, records are dropped rather
+//! than buffered without bound, and the drop is logged.
+//!
+//! The range of views recorded in each segment is kept in memory, and in a small index file next
+//! to the segment once it is closed, so that reads only open the segments which may hold the views
+//! requested. Segments left without an index, by a node which stopped while writing them, are
+//! scanned once when the log is opened.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use clap::Parser;
+use espresso_types::{parse_size, SeqTypes};
+use hotshot_types::audit::{AuditFilter, AuditRecord, MessageAudit};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::spawn_blocking};
+
+/// Records queued for the writer before new records are dropped
+const QUEUE_SIZE: usize = 100_000;
+
+/// Prefix of the names of segment files
+const SEGMENT_PREFIX: &str = "audit-";
+
+/// Extension of segment files
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// Extension of the index files of closed segments
+const INDEX_EXTENSION: &str = "views";
+
+/// The views recorded in a segment
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct ViewRange {
+    first: u64,
+    last: u64,
+}
+
+impl ViewRange {
+    fn of(view: u64) -> Self {
+        Self {
+            first: view,
+            last: view,
+        }
+    }
+
+    fn add(&mut self, view: u64) {
+        self.first = self.first.min(view);
+        self.last = self.last.max(view);
+    }
+
+    /// Whether any view in `from..until` is in this range
+    fn overlaps(&self, from: u64, until: u64) -> bool {
+        self.first < until && from <= self.last
+    }
+}
+
+/// The views recorded in each segment which has any records, by segment index
+type ViewIndex = Arc<Mutex<BTreeMap<u64, ViewRange>>>;
+
+/// Options for audit mode.
+#[derive(Parser, Clone, Debug)]
+pub struct AuditOptions {
+    /// Keep an audit log of every proposal and vote sent or received in this directory.
+    ///
+    /// Audit mode is off unless this is set.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_AUDIT_DIR")]
+    pub audit_dir: Option<PathBuf>,
+
+    /// Size at which a segment of the audit log is closed and a new one started.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_AUDIT_SEGMENT_SIZE",
+        default_value = "64mb",
+        value_parser = parse_size
+    )]
+    pub audit_segment_size: u64,
+
+    /// Number of audit log segments to keep; older segments are deleted.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_AUDIT_MAX_SEGMENTS",
+        default_value = "32"
+    )]
+    pub audit_max_segments: usize,
+}
+
+impl AuditOptions {
+    /// Open the audit log, if audit mode is enabled.
+    pub fn open(&self) -> anyhow::Result<Option<AuditLog>> {
+        let Some(dir) = &self.audit_dir else {
+            return Ok(None);
+        };
+        let segments = Segments::open(
+            dir.clone(),
+            self.audit_segment_size,
+            self.audit_max_segments.max(1),
+        )?;
+        tracing::info!(dir = %dir.display(), "audit mode enabled");
+        Ok(Some(AuditLog::start(segments)))
+    }
+}
+
+/// Append-only audit log of proposals and votes, kept in rotating segment files.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    dir: PathBuf,
+    index: ViewIndex,
+    sender: mpsc::Sender<AuditRecord<SeqTypes>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Write records to `segments` in the background.
+    fn start(segments: Segments) -> Self {
+        let dir = segments.dir.clone();
+        let index = segments.index.clone();
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        thread::Builder::new()
+            .name("audit-log".into())
+            .spawn(move || segments.run(receiver))
+            .expect("failed to spawn audit log writer");
+        Self {
+            dir,
+            index,
+            sender,
+            dropped: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageAudit<SeqTypes> for AuditLog {
+    fn record(&self, record: AuditRecord<SeqTypes>) {
+        if self.sender.try_send(record).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped == 1 || dropped % 10_000 == 0 {
+                tracing::warn!(dropped, "audit log writer is behind, dropping records");
+            }
+        }
+    }
+
+    async fn records(
+        &self,
+        filter: &AuditFilter<SeqTypes>,
+    ) -> anyhow::Result<Vec<AuditRecord<SeqTypes>>> {
+        let dir = self.dir.clone();
+        let filter = filter.clone();
+        // Only the segments which may hold the requested views are read.
+        let segments = self
+            .index
+            .lock()
+            .iter()
+            .filter(|(_, views)| views.overlaps(filter.from_view, filter.until_view))
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+        spawn_blocking(move || read_records(&dir, segments, &filter)).await?
+    }
+}
+
+/// The segment files of the audit log, and the one being written
+#[derive(Debug)]
+struct Segments {
+    dir: PathBuf,
+    segment_size: u64,
+    max_segments: usize,
+    /// Writer, index and size of the current segment
+    current: Option<(BufWriter<File>, u64, u64)>,
+    /// Index of the next segment to start
+    next: u64,
+    /// The views recorded in each segment
+    index: ViewIndex,
+}
+
+impl Segments {
+    fn open(dir: PathBuf, segment_size: u64, max_segments: usize) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating audit directory {}", dir.display()))?;
+        let indices = segment_indices(&dir)?;
+        let mut views = BTreeMap::new();
+        for index in &indices {
+            if let Some(range) = load_view_range(&dir, *index)? {
+                views.insert(*index, range);
+            }
+        }
+        Ok(Self {
+            segment_size,
+            max_segments,
+            current: None,
+            next: indices.last().map_or(0, |last| last + 1),
+            index: Arc::new(Mutex::new(views)),
+            dir,
+        })
+    }
+
+    /// Write records as they are received, until the log is dropped.
+    fn run(mut self, mut receiver: mpsc::Receiver<AuditRecord<SeqTypes>>) {
+        while let Some(record) = receiver.blocking_recv() {
+            let mut result = self.append(&record);
+            // Write whatever else is queued before flushing.
+            while result.is_ok() {
+                let Ok(record) = receiver.try_recv() else {
+                    break;
+                };
+                result = self.append(&record);
+            }
+            if let Err(err) = result.and_then(|()| self.flush()) {
+                tracing::error!("failed to write audit log: {err:#}");
+                // Start a fresh segment, in case the current one is what failed.
+                self.close();
+            }
+        }
+        self.close();
+    }
+
+    fn append(&mut self, record: &AuditRecord<SeqTypes>) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        if self
+            .current
+            .as_ref()
+            .is_none_or(|(_, _, size)| *size > 0 && size + line.len() as u64 > self.segment_size)
+        {
+            self.rotate()?;
+        }
+        let (writer, index, size) = self.current.as_mut().unwrap();
+        writer.write_all(&line)?;
+        *size += line.len() as u64;
+        self.index
+            .lock()
+            .entry(*index)
+            .and_modify(|views| views.add(record.view))
+            .or_insert_with(|| ViewRange::of(record.view));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if let Some((writer, ..)) = &mut self.current {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Stop writing the current segment, saving the views it holds next to it.
+    fn close(&mut self) {
+        let Some((mut writer, index, _)) = self.current.take() else {
+            return;
+        };
+        if let Err(err) = writer.flush() {
+            tracing::warn!("failed to flush audit segment: {err:#}");
+        }
+        let Some(views) = self.index.lock().get(&index).copied() else {
+            return;
+        };
+        let path = index_path(&self.dir, index);
+        if let Err(err) = serde_json::to_vec(&views)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(fs::write(&path, bytes)?))
+        {
+            tracing::warn!(path = %path.display(), "failed to write audit index: {err:#}");
+        }
+    }
+
+    /// Start a new segment and delete the oldest ones beyond the limit.
+    fn rotate(&mut self) -> anyhow::Result<()> {
+        self.close();
+        let path = segment_path(&self.dir, self.next);
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("creating audit segment {}", path.display()))?;
+        self.current = Some((BufWriter::new(file), self.next, 0));
+        self.next += 1;
+
+        let indices = segment_indices(&self.dir)?;
+        for index in &indices[..indices.len().saturating_sub(self.max_segments)] {
+            self.index.lock().remove(index);
+            let path = segment_path(&self.dir, *index);
+            tracing::info!(path = %path.display(), "deleting old audit segment");
+            if let Err(err) = fs::remove_file(&path) {
+                tracing::warn!(path = %path.display(), "failed to delete audit segment: {err:#}");
+            }
+            let _ = fs::remove_file(index_path(&self.dir, *index));
+        }
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{SEGMENT_PREFIX}{index:010}.{SEGMENT_EXTENSION}"))
+}
+
+fn index_path(dir: &Path, index: u64) -> PathBuf {
+    dir.join(format!("{SEGMENT_PREFIX}{index:010}.{INDEX_EXTENSION}"))
+}
+
+/// The views recorded in segment `index`, from its index file or, failing that, its records
+fn load_view_range(dir: &Path, index: u64) -> anyhow::Result<Option<ViewRange>> {
+    if let Ok(bytes) = fs::read(index_path(dir, index)) {
+        if let Ok(views) = serde_json::from_slice(&bytes) {
+            return Ok(Some(views));
+        }
+    }
+
+    let path = segment_path(dir, index);
+    tracing::info!(path = %path.display(), "indexing audit segment");
+    let file = File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+    let mut views: Option<ViewRange> = None;
+    for line in BufReader::new(file).lines() {
+        let Ok(record) = serde_json::from_str::<AuditRecord<SeqTypes>>(&line?) else {
+            continue;
+        };
+        match &mut views {
+            Some(views) => views.add(record.view),
+            None => views = Some(ViewRange::of(record.view)),
+        }
+    }
+    if let Some(views) = views {
+        let path = index_path(dir, index);
+        fs::write(&path, serde_json::to_vec(&views)?)
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
+    Ok(views)
+}
+
+/// The indices of the segments in `dir`, in ascending order
+fn segment_indices(dir: &Path) -> anyhow::Result<Vec<u64>> {
+    let mut indices = vec![];
+    for entry in fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let name = entry?.file_name();
+        let Some(index) = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(SEGMENT_PREFIX))
+            .and_then(|name| name.strip_suffix(&format!(".{SEGMENT_EXTENSION}")))
+            .and_then(|index| index.parse().ok())
+        else {
+            continue;
+        };
+        indices.push(index);
+    }
+    indices.sort_unstable();
+    Ok(indices)
+}
+
+/// Read the records selected by `filter` from `segments` in `dir`, oldest first.
+fn read_records(
+    dir: &Path,
+    segments: Vec<u64>,
+    filter: &AuditFilter<SeqTypes>,
+) -> anyhow::Result<Vec<AuditRecord<SeqTypes>>> {
+    let mut records = vec![];
+    for index in segments {
+        let path = segment_path(dir, index);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            // The segment was deleted since it was listed.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("opening {}", path.display()));
+            },
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            // The last line of a segment may be cut short if the node stopped while writing it.
+            let record: AuditRecord<SeqTypes> = match serde_json::from_str(&line) {
+                Ok(record) => record,
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), "skipping malformed audit record: {err:#}");
+                    continue;
+                },
+            };
+            if filter.matches(&record) {
+                records.push(record);
+                if records.len() >= filter.limit {
+                    return Ok(records);
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use espresso_types::PubKey;
+    use hotshot_example_types::node_types::TestVersions;
+    use hotshot_types::{
+        audit::{AuditDirection, AuditKind},
+        data::ViewNumber,
+        message::{GeneralConsensusMessage, Message, MessageKind, SequencingMessage, UpgradeLock},
+        signer::Signer,
+        simple_vote::{TimeoutData2, TimeoutVote2},
+        traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    };
+    use tempfile::TempDir;
+    use tokio::time::sleep;
+
+    use super::*;
+
+    fn signer(i: u64) -> PubKey {
+        PubKey::generated_from_seed_indexed([0; 32], i).0
+    }
+
+    async fn timeout_vote(view: u64, i: u64) -> Message<SeqTypes> {
+        let (key, priv_key) = PubKey::generated_from_seed_indexed([0; 32], i);
+        let vote = TimeoutVote2::<SeqTypes>::create_signed_vote(
+            TimeoutData2 {
+                view: ViewNumber::new(view),
+                epoch: None,
+            },
+            ViewNumber::new(view),
+            &key,
+            &Signer::Local(priv_key),
+            &UpgradeLock::<SeqTypes, TestVersions>::new(),
+        )
+        .await
+        .unwrap();
+        Message {
+            sender: key,
+            kind: MessageKind::Consensus(SequencingMessage::General(
+                GeneralConsensusMessage::TimeoutVote2(vote),
+            )),
+        }
+    }
+
+    /// Wait until the record of `view` and `signer` is written.
+    async fn wait_for(log: &AuditLog, view: u64, signer: PubKey) {
+        let filter = AuditFilter {
+            from_view: view,
+            until_view: view + 1,
+            signer: Some(signer),
+            limit: 1,
+        };
+        for _ in 0..100 {
+            if !log.records(&filter).await.unwrap().is_empty() {
+                return;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+        panic!("audit records were not written");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_audit_log_rotation_and_filters() {
+        let dir = TempDir::new().unwrap();
+        let options = AuditOptions {
+            audit_dir: Some(dir.path().into()),
+            audit_segment_size: 1,
+            audit_max_segments: 3,
+        };
+        let log = options.open().unwrap().unwrap();
+
+        for view in 0..5 {
+            for i in 0..2 {
+                let message = timeout_vote(view, i).await;
+                let record =
+                    AuditRecord::new(AuditDirection::Received, signer(i), &message).unwrap();
+                assert_eq!(record.kind, AuditKind::Vote);
+                log.record(record);
+            }
+        }
+        let all = AuditFilter {
+            from_view: 0,
+            until_view: u64::MAX,
+            signer: None,
+            limit: usize::MAX,
+        };
+        wait_for(&log, 4, signer(1)).await;
+
+        // Each record filled a segment, so only the last three are kept.
+        let records = log.records(&all).await.unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| (record.view, record.signer))
+                .collect::<Vec<_>>(),
+            [(3, signer(1)), (4, signer(0)), (4, signer(1))]
+        );
+        assert_eq!(records[2].message, timeout_vote(4, 1).await);
+
+        // Filter by view and signer.
+        let records = log
+            .records(&AuditFilter {
+                from_view: 4,
+                until_view: 5,
+                signer: Some(signer(0)),
+                limit: 10,
+            })
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].signer, signer(0));
+
+        // And limit the number of records.
+        let records = log
+            .records(&AuditFilter {
+                limit: 2,
+                ..all.clone()
+            })
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 2);
+
+        // A restarted node starts a new segment instead of appending to an old one.
+        drop(log);
+        let log = options.open().unwrap().unwrap();
+        let message = timeout_vote(5, 0).await;
+        log.record(AuditRecord::new(AuditDirection::Sent, signer(0), &message).unwrap());
+        wait_for(&log, 5, signer(0)).await;
+        let records = log.records(&all).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].direction, AuditDirection::Sent);
+        assert_eq!(segment_indices(dir.path()).unwrap(), [8, 9, 10]);
+
+        // Segments are indexed by the views they hold, so reads only open those which match.
+        let index = BTreeMap::from([
+            (8, ViewRange::of(4)),
+            (9, ViewRange::of(4)),
+            (10, ViewRange::of(5)),
+        ]);
+        assert_eq!(*log.index.lock(), index);
+        assert!(!index_path(dir.path(), 7).exists());
+
+        // A segment whose index is missing is indexed from its records when the log is opened.
+        drop(log);
+        fs::remove_file(index_path(dir.path(), 8)).unwrap();
+        let log = options.open().unwrap().unwrap();
+        assert_eq!(*log.index.lock(), index);
+        assert!(index_path(dir.path(), 8).exists());
+    }
+}
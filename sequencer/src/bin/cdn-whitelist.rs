@@ -1,19 +1,40 @@
 //! The whitelist is an adaptor that is able to update the allowed public keys for
-//! all brokers. Right now, we do this by asking the orchestrator for the list of
-//! allowed public keys. In the future, we will pull the stake table from the L1.
+//! all brokers. The allowed public keys are either taken once from the orchestrator, or from the
+//! stake table served by a sequencer node, which in daemon mode is watched so that the whitelist
+//! follows the stake table through every epoch change.
+//!
+//! Rather than blindly overwriting the whitelist, the updater checks which keys the discovery
+//! endpoint currently allows, only writes the whitelist when it differs from the desired one, and
+//! records every change in an audit log.
 
-use std::{str::FromStr, sync::Arc};
+use std::{
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    future::Future,
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{Context, Result};
 use cdn_broker::reexports::discovery::{DiscoveryClient, Embedded, Redis};
 use clap::Parser;
-use espresso_types::SeqTypes;
+use espresso_types::{parse_duration, PubKey, SeqTypes};
 use hotshot_orchestrator::client::OrchestratorClient;
-use hotshot_types::{network::NetworkConfig, traits::signature_key::SignatureKey};
-use surf_disco::Url;
+use hotshot_query_service::Error;
+use hotshot_types::{
+    data::EpochNumber, network::NetworkConfig, traits::signature_key::SignatureKey, PeerConfig,
+};
+use sequencer::{api::data_source::StakeTableWithEpochNumber, SequencerApiVersion};
+use serde::{Deserialize, Serialize};
+use surf_disco::{Client, Url};
+use tokio::time::sleep;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group(clap::ArgGroup::new("source").required(true).args(["orchestrator_url", "stake_table_url"])))]
 /// Whitelist is a service that updates the allowed public keys for the CDN.
 struct Args {
     /// The discovery client endpoint (including scheme) to connect to.
@@ -24,13 +45,101 @@ struct Args {
 
     /// The URL the orchestrator is running on. This should be something like `http://localhost:5555`
     #[arg(short, long, env = "ESPRESSO_SEQUENCER_ORCHESTRATOR_URL")]
-    orchestrator_url: String,
+    orchestrator_url: Option<String>,
+
+    /// The URL of a sequencer node serving the stake table API. This should be something like
+    /// `http://localhost:24000/v0`
+    #[arg(
+        short,
+        long,
+        env = "ESPRESSO_CDN_WHITELIST_STAKE_TABLE_URL",
+        conflicts_with = "orchestrator_url"
+    )]
+    stake_table_url: Option<Url>,
+
+    /// Keep running, updating the whitelist whenever the stake table changes.
+    ///
+    /// Requires `--stake-table-url`.
+    #[arg(
+        long,
+        env = "ESPRESSO_CDN_WHITELIST_DAEMON",
+        requires = "stake_table_url"
+    )]
+    daemon: bool,
+
+    /// How often to check the stake table for changes in daemon mode
+    #[arg(
+        long,
+        env = "ESPRESSO_CDN_WHITELIST_POLL_INTERVAL",
+        default_value = "30s",
+        value_parser = parse_duration
+    )]
+    poll_interval: Duration,
+
+    /// Delay before the first retry of a failed request. It doubles with every attempt, up to
+    /// `--max-retry-delay`.
+    #[arg(
+        long,
+        env = "ESPRESSO_CDN_WHITELIST_BASE_RETRY_DELAY",
+        default_value = "1s",
+        value_parser = parse_duration
+    )]
+    base_retry_delay: Duration,
+
+    /// Longest delay between retries of a failed request
+    #[arg(
+        long,
+        env = "ESPRESSO_CDN_WHITELIST_MAX_RETRY_DELAY",
+        default_value = "1m",
+        value_parser = parse_duration
+    )]
+    max_retry_delay: Duration,
+
+    /// Compute and log the changes to the whitelist without applying them
+    #[arg(long, env = "ESPRESSO_CDN_WHITELIST_DRY_RUN")]
+    dry_run: bool,
+
+    /// File to which every change to the whitelist is appended, one JSON object per line.
+    ///
+    /// It is also read on startup to find keys which may have to be removed from the whitelist.
+    #[arg(long, env = "ESPRESSO_CDN_WHITELIST_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
 
     /// Whether or not to use the local discovery client
     #[arg(short, long)]
     local_discovery: bool,
 }
 
+/// Where the desired whitelist was taken from
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum WhitelistSource {
+    /// The config of the orchestrator
+    Orchestrator,
+    /// The stake table of a sequencer node
+    StakeTable,
+}
+
+/// A change to the whitelist, as recorded in the audit log
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditEntry {
+    /// Seconds since the Unix epoch at which the change was made
+    timestamp: u64,
+    /// Where the desired whitelist was taken from
+    source: WhitelistSource,
+    /// Epoch of the stake table the desired whitelist was taken from, if any
+    epoch: Option<EpochNumber>,
+    /// Keys added to the whitelist
+    added: Vec<PubKey>,
+    /// Keys removed from the whitelist
+    removed: Vec<PubKey>,
+    /// The whole whitelist, if it replaced a whitelist whose keys were not known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    replaced_with: Option<Vec<PubKey>>,
+    /// Whether the change was only computed, and not applied
+    dry_run: bool,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Parse the command line arguments
@@ -39,15 +148,43 @@ async fn main() -> Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
+    let known = read_audit_log(&args)?;
+
+    match (&args.orchestrator_url, &args.stake_table_url) {
+        (Some(orchestrator_url), _) => {
+            whitelist_from_orchestrator(&args, &known, orchestrator_url).await?;
+        },
+        (None, Some(stake_table_url)) if args.daemon => {
+            watch_stake_table(&args, known, stake_table_url).await?;
+        },
+        (None, Some(stake_table_url)) => {
+            let stake_table = fetch_stake_table(&args, stake_table_url).await?;
+            update_whitelist(
+                &args,
+                &known,
+                WhitelistSource::StakeTable,
+                stake_table.epoch,
+                &stake_table.stake_table,
+            )
+            .await?;
+        },
+        (None, None) => unreachable!("clap requires a source for the whitelist"),
+    }
+
+    Ok(())
+}
+
+/// Set the whitelist once, from the config of the orchestrator
+async fn whitelist_from_orchestrator(
+    args: &Args,
+    known: &Option<BTreeSet<PubKey>>,
+    orchestrator_url: &str,
+) -> Result<()> {
     // Create a new `OrchestratorClient` from the supplied URL
-    let orchestrator_client = OrchestratorClient::new(
-        Url::from_str(&args.orchestrator_url).with_context(|| "Invalid URL")?,
-    );
+    let orchestrator_client =
+        OrchestratorClient::new(Url::from_str(orchestrator_url).with_context(|| "Invalid URL")?);
 
-    tracing::info!(
-        "Waiting for config from orchestrator on {}",
-        args.orchestrator_url
-    );
+    tracing::info!("Waiting for config from orchestrator on {orchestrator_url}");
 
     // Attempt to get the config from the orchestrator.
     // Loops internally until the config is received.
@@ -55,27 +192,246 @@ async fn main() -> Result<()> {
 
     tracing::info!("Received config from orchestrator");
 
-    // Extrapolate the state_ver_keys from the config and convert them to a compatible format
-    let whitelist = config
-        .config
-        .known_nodes_with_stake
+    update_whitelist(
+        args,
+        known,
+        WhitelistSource::Orchestrator,
+        None,
+        &config.config.known_nodes_with_stake,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Keep the whitelist in sync with the stake table, updating it on every epoch change
+async fn watch_stake_table(
+    args: &Args,
+    mut known: Option<BTreeSet<PubKey>>,
+    stake_table_url: &Url,
+) -> Result<()> {
+    let mut current: Option<(Option<EpochNumber>, BTreeSet<PubKey>)> = None;
+
+    loop {
+        let stake_table = fetch_stake_table(args, stake_table_url).await?;
+        let desired = stake_keys(&stake_table.stake_table);
+
+        if current.as_ref() != Some(&(stake_table.epoch, desired.clone())) {
+            tracing::info!(
+                epoch = ?stake_table.epoch,
+                nodes = desired.len(),
+                "Stake table changed, checking whitelist"
+            );
+            known = with_retries(args, "update whitelist", || {
+                update_whitelist(
+                    args,
+                    &known,
+                    WhitelistSource::StakeTable,
+                    stake_table.epoch,
+                    &stake_table.stake_table,
+                )
+            })
+            .await?;
+            current = Some((stake_table.epoch, desired));
+        }
+
+        sleep(args.poll_interval).await;
+    }
+}
+
+/// Fetch the stake table for the current epoch from a sequencer node
+async fn fetch_stake_table(
+    args: &Args,
+    stake_table_url: &Url,
+) -> Result<StakeTableWithEpochNumber<SeqTypes>> {
+    let client = &Client::<Error, SequencerApiVersion>::new(stake_table_url.clone());
+
+    with_retries(args, "fetch stake table", || async move {
+        client
+            .get::<StakeTableWithEpochNumber<SeqTypes>>("node/stake-table/current")
+            .send()
+            .await
+            .context("Failed to fetch the current stake table")
+    })
+    .await
+}
+
+/// Run `f` until it succeeds, with an exponential backoff between attempts.
+///
+/// Outside of daemon mode, the first failure is returned instead.
+async fn with_retries<T, F: Future<Output = Result<T>>>(
+    args: &Args,
+    operation: &str,
+    f: impl Fn() -> F,
+) -> Result<T> {
+    let mut delay = args.base_retry_delay;
+    loop {
+        match f().await {
+            Ok(res) => return Ok(res),
+            Err(err) if !args.daemon => return Err(err),
+            Err(err) => {
+                tracing::warn!("Failed to {operation}, will retry after {delay:?}: {err:#}");
+                sleep(delay).await;
+                delay = (delay * 2).min(args.max_retry_delay);
+            },
+        }
+    }
+}
+
+/// The stake keys of the nodes in `stake_table`
+fn stake_keys(stake_table: &[PeerConfig<SeqTypes>]) -> BTreeSet<PubKey> {
+    stake_table
         .iter()
-        .map(|k| Arc::from(k.stake_table_entry.stake_key.to_bytes()))
-        .collect();
+        .map(|k| k.stake_table_entry.stake_key)
+        .collect()
+}
+
+/// Make the whitelist contain exactly the stake keys of the nodes in `stake_table`.
+///
+/// `known` holds the keys which may currently be whitelisted besides the desired ones, if they are
+/// known. Returns the keys which are whitelisted once the update is done, if they are known.
+async fn update_whitelist(
+    args: &Args,
+    known: &Option<BTreeSet<PubKey>>,
+    source: WhitelistSource,
+    epoch: Option<EpochNumber>,
+    stake_table: &[PeerConfig<SeqTypes>],
+) -> Result<Option<BTreeSet<PubKey>>> {
+    let desired = stake_keys(stake_table);
 
     if args.local_discovery {
-        <Embedded as DiscoveryClient>::new(args.discovery_endpoint, None)
-            .await?
-            .set_whitelist(whitelist)
-            .await?;
+        let client =
+            <Embedded as DiscoveryClient>::new(args.discovery_endpoint.clone(), None).await?;
+        apply_diff(args, client, known, source, epoch, desired).await
     } else {
-        <Redis as DiscoveryClient>::new(args.discovery_endpoint, None)
-            .await?
-            .set_whitelist(whitelist)
-            .await?;
+        let client = <Redis as DiscoveryClient>::new(args.discovery_endpoint.clone(), None).await?;
+        apply_diff(args, client, known, source, epoch, desired).await
+    }
+}
+
+/// Compare the whitelist of `client` with `desired`, and write it only if they differ.
+///
+/// The discovery client cannot list the whitelist, so added keys are found by checking each
+/// desired key, and removed keys by checking each of the `known` keys which is not desired. If the
+/// keys which may be whitelisted are not known, stale keys can only be removed by replacing the
+/// whole whitelist, so it is written regardless.
+async fn apply_diff<D: DiscoveryClient>(
+    args: &Args,
+    mut client: D,
+    known: &Option<BTreeSet<PubKey>>,
+    source: WhitelistSource,
+    epoch: Option<EpochNumber>,
+    desired: BTreeSet<PubKey>,
+) -> Result<Option<BTreeSet<PubKey>>> {
+    let mut added = vec![];
+    for key in &desired {
+        if !client.check_whitelist(&Arc::from(key.to_bytes())).await? {
+            added.push(*key);
+        }
+    }
+
+    let mut removed = vec![];
+    for key in known.iter().flat_map(|known| known.difference(&desired)) {
+        if client.check_whitelist(&Arc::from(key.to_bytes())).await? {
+            removed.push(*key);
+        }
+    }
+
+    let replaced_with = known
+        .is_none()
+        .then(|| desired.iter().copied().collect::<Vec<_>>());
+    if added.is_empty() && removed.is_empty() && replaced_with.is_none() {
+        tracing::info!(?epoch, "Whitelist is up to date");
+        return Ok(Some(desired));
     }
 
-    tracing::info!("Posted config to discovery endpoint");
+    tracing::info!(
+        ?epoch,
+        added = ?added.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        removed = ?removed.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        replaced = replaced_with.is_some(),
+        dry_run = args.dry_run,
+        "Whitelist changed"
+    );
+
+    let whitelisted = if args.dry_run {
+        known
+            .as_ref()
+            .map(|known| known.union(&desired).copied().collect())
+    } else {
+        // Convert the stake keys to a format compatible with the discovery client
+        let whitelist = desired
+            .iter()
+            .map(|key| Arc::from(key.to_bytes()))
+            .collect();
+        client.set_whitelist(whitelist).await?;
+
+        tracing::info!("Posted whitelist to discovery endpoint");
+        Some(desired)
+    };
+
+    append_audit_log(
+        args,
+        &AuditEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            source,
+            epoch,
+            added,
+            removed,
+            replaced_with,
+            dry_run: args.dry_run,
+        },
+    )?;
+
+    Ok(whitelisted)
+}
+
+/// The keys which the audit log records as added to the whitelist, and not removed since.
+///
+/// Without an audit log, the keys which may be whitelisted are not known.
+fn read_audit_log(args: &Args) -> Result<Option<BTreeSet<PubKey>>> {
+    let Some(path) = &args.audit_log else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut known = BTreeSet::new();
+    let log = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read audit log {}", path.display()))?;
+    for (i, line) in log.lines().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let entry: AuditEntry = serde_json::from_str(line)
+            .with_context(|| format!("Invalid entry on line {} of the audit log", i + 1))?;
+        if entry.dry_run {
+            continue;
+        }
+        if let Some(whitelist) = entry.replaced_with {
+            known = whitelist.into_iter().collect();
+            continue;
+        }
+        for key in entry.removed {
+            known.remove(&key);
+        }
+        known.extend(entry.added);
+    }
+
+    Ok(Some(known))
+}
+
+/// Append `entry` to the audit log, if there is one
+fn append_audit_log(args: &Args, entry: &AuditEntry) -> Result<()> {
+    let Some(path) = &args.audit_log else {
+        return Ok(());
+    };
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)
+        .with_context(|| format!("Failed to write to audit log {}", path.display()))?;
 
     Ok(())
 }
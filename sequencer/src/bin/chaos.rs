@@ -0,0 +1,579 @@
+//! Chaos drills against a running network.
+//!
+//! The chaos tool applies a schedule of disruptions to a deployed test network, each for a limited
+//! time, and records when each disruption started and ended in a timeline, so that the behavior of
+//! the network during the drill can be correlated with the metrics of its nodes. It can
+//!
+//! * pause the consensus network of a node through its admin API,
+//! * revoke the CDN whitelist entries of some nodes, and
+//! * add latency to the connections through a TCP proxy it runs for the duration of the drill.
+//!
+//! The schedule is a TOML file:
+//!
+//! ```toml
+//! # Proxies the tool runs; nodes must be configured to connect through them.
+//! [[proxy]]
+//! name = "node-1-cdn"
+//! listen = "0.0.0.0:9100"
+//! upstream = "node-1:1740"
+//!
+//! [[disruption]]
+//! kind = "pause-node"
+//! start = "1m"
+//! duration = "2m"
+//! node = "http://node-1:24000/v0"
+//!
+//! [[disruption]]
+//! kind = "revoke-whitelist"
+//! start = "5m"
+//! duration = "1m"
+//! keys = ["BLS_VER_KEY~..."]
+//!
+//! [[disruption]]
+//! kind = "latency"
+//! start = "10m"
+//! duration = "3m"
+//! proxy = "node-1-cdn"
+//! delay = "500ms"
+//! ```
+//!
+//! Disruptions still in effect when the tool is interrupted (Ctrl-C) are reverted before it exits.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use cdn_broker::reexports::discovery::{DiscoveryClient, Embedded, Redis};
+use clap::Parser;
+use espresso_types::{parse_duration, PubKey, SeqTypes};
+use futures::future::{join_all, try_join};
+use hotshot_query_service::Error;
+use hotshot_types::traits::signature_key::SignatureKey;
+use parking_lot::Mutex;
+use sequencer::{
+    api::{data_source::StakeTableWithEpochNumber, network_pause::NetworkPause},
+    SequencerApiVersion,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use surf_disco::{Client, Url};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpStream,
+    },
+    sync::mpsc,
+    time::{sleep, sleep_until, Instant},
+};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+/// Applies a schedule of disruptions to a running network.
+struct Args {
+    /// The schedule of the drill, as a TOML file
+    #[arg(long, env = "ESPRESSO_CHAOS_SCHEDULE")]
+    schedule: PathBuf,
+
+    /// File to which the timeline of the drill is appended, one JSON object per line
+    #[arg(long, env = "ESPRESSO_CHAOS_TIMELINE")]
+    timeline: Option<PathBuf>,
+
+    /// Admin token of the nodes, required to pause them
+    #[arg(long, env = "ESPRESSO_CHAOS_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// The CDN discovery endpoint, required to revoke whitelist entries.
+    /// With the local discovery feature, this is a file path.
+    /// With the remote (redis) discovery feature, this is a redis URL (e.g. `redis://127.0.0.1:6789`).
+    #[arg(long, env = "ESPRESSO_CHAOS_DISCOVERY_ENDPOINT")]
+    discovery_endpoint: Option<String>,
+
+    /// Whether or not to use the local discovery client
+    #[arg(long)]
+    local_discovery: bool,
+
+    /// The URL of a sequencer node serving the stake table API, required to revoke whitelist
+    /// entries. This should be something like `http://localhost:24000/v0`
+    #[arg(long, env = "ESPRESSO_CHAOS_STAKE_TABLE_URL")]
+    stake_table_url: Option<Url>,
+}
+
+/// The schedule of a drill
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Schedule {
+    #[serde(default)]
+    proxy: Vec<ProxyConfig>,
+    #[serde(default)]
+    disruption: Vec<ScheduledDisruption>,
+}
+
+/// A TCP proxy run by the tool, through which latency can be added
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProxyConfig {
+    name: String,
+    /// Address to accept connections on
+    listen: String,
+    /// Address to forward connections to
+    upstream: String,
+}
+
+/// A disruption, and when to apply it
+#[derive(Clone, Debug, Deserialize)]
+struct ScheduledDisruption {
+    /// Time from the start of the drill at which the disruption is applied
+    #[serde(deserialize_with = "deserialize_duration")]
+    start: Duration,
+    /// Time for which the disruption is in effect
+    #[serde(deserialize_with = "deserialize_duration")]
+    duration: Duration,
+    #[serde(flatten)]
+    disruption: Disruption,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum Disruption {
+    /// Pause the consensus network of a node
+    PauseNode {
+        /// Base URL of the node API, like `http://localhost:24000/v0`
+        node: Url,
+    },
+    /// Remove nodes from the CDN whitelist
+    RevokeWhitelist { keys: Vec<PubKey> },
+    /// Delay the data forwarded by a proxy
+    Latency {
+        proxy: String,
+        #[serde(deserialize_with = "deserialize_duration")]
+        delay: Duration,
+    },
+}
+
+impl Disruption {
+    /// The node or proxy disrupted, if disruptions of it must not overlap
+    fn target(&self) -> Option<String> {
+        match self {
+            Self::PauseNode { node } => Some(node.to_string()),
+            // Revocations are counted per key, so they can overlap.
+            Self::RevokeWhitelist { .. } => None,
+            Self::Latency { proxy, .. } => Some(format!("proxy {proxy}")),
+        }
+    }
+}
+
+impl fmt::Display for Disruption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PauseNode { node } => write!(f, "pause node {node}"),
+            Self::RevokeWhitelist { keys } => write!(f, "revoke whitelist of {} nodes", keys.len()),
+            Self::Latency { proxy, delay } => write!(f, "add {delay:?} latency to proxy {proxy}"),
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s).map_err(serde::de::Error::custom)
+}
+
+/// What happened to a disruption, as recorded in the timeline
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum TimelineEvent {
+    Applied,
+    Reverted,
+    Failed,
+}
+
+/// An entry of the timeline
+#[derive(Debug, Serialize, Deserialize)]
+struct TimelineEntry {
+    /// Milliseconds since the Unix epoch, to correlate with node metrics
+    timestamp: u64,
+    /// Milliseconds since the start of the drill
+    offset: u64,
+    /// Index of the disruption in the schedule
+    disruption: usize,
+    event: TimelineEvent,
+    description: String,
+    /// Why the disruption could not be applied or reverted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// State of a drill, shared by the tasks applying each disruption
+struct Drill {
+    args: Args,
+    started: Instant,
+    http: reqwest::Client,
+    /// Added latency of each proxy, in milliseconds
+    proxies: HashMap<String, Arc<AtomicU64>>,
+    /// Number of revocations in effect for each revoked key
+    revoked: tokio::sync::Mutex<BTreeMap<PubKey, usize>>,
+    /// Disruptions currently in effect, by index in the schedule
+    active: Mutex<BTreeMap<usize, Disruption>>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt::init();
+
+    let schedule = load_schedule(&args.schedule)?;
+    validate(&args, &schedule)?;
+
+    let mut proxies = HashMap::new();
+    for proxy in &schedule.proxy {
+        let delay = Arc::new(AtomicU64::new(0));
+        let listener = TcpListener::bind(&proxy.listen)
+            .await
+            .with_context(|| format!("binding proxy {} to {}", proxy.name, proxy.listen))?;
+        tracing::info!(
+            name = %proxy.name,
+            listen = %proxy.listen,
+            upstream = %proxy.upstream,
+            "proxy started"
+        );
+        tokio::spawn(run_proxy(listener, proxy.clone(), delay.clone()));
+        proxies.insert(proxy.name.clone(), delay);
+    }
+
+    let drill = Arc::new(Drill {
+        args,
+        started: Instant::now(),
+        http: reqwest::Client::new(),
+        proxies,
+        revoked: Default::default(),
+        active: Default::default(),
+    });
+
+    let tasks = schedule
+        .disruption
+        .into_iter()
+        .enumerate()
+        .map(|(index, scheduled)| tokio::spawn(drill.clone().run(index, scheduled)));
+
+    tokio::select! {
+        _ = join_all(tasks) => {
+            tracing::info!("drill complete");
+        },
+        _ = tokio::signal::ctrl_c() => {
+            tracing::warn!("interrupted, reverting disruptions in effect");
+            let active: Vec<_> = drill.active.lock().keys().copied().collect();
+            join_all(active.into_iter().map(|index| drill.finish(index))).await;
+        },
+    }
+
+    Ok(())
+}
+
+fn load_schedule(path: &Path) -> Result<Schedule> {
+    let schedule = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read schedule {}", path.display()))?;
+    toml::from_str(&schedule).with_context(|| format!("Invalid schedule {}", path.display()))
+}
+
+/// Check that the schedule can be carried out before starting the drill.
+fn validate(args: &Args, schedule: &Schedule) -> Result<()> {
+    let proxies: BTreeSet<_> = schedule.proxy.iter().map(|proxy| &proxy.name).collect();
+    ensure!(
+        proxies.len() == schedule.proxy.len(),
+        "proxy names must be unique"
+    );
+
+    let mut windows = BTreeMap::<String, Vec<(Duration, Duration)>>::new();
+    for (index, scheduled) in schedule.disruption.iter().enumerate() {
+        match &scheduled.disruption {
+            Disruption::PauseNode { .. } => {
+                ensure!(
+                    args.admin_token.is_some(),
+                    "disruption {index} pauses a node, which requires --admin-token"
+                );
+            },
+            Disruption::RevokeWhitelist { .. } => {
+                ensure!(
+                    args.discovery_endpoint.is_some() && args.stake_table_url.is_some(),
+                    "disruption {index} revokes whitelist entries, which requires \
+                     --discovery-endpoint and --stake-table-url"
+                );
+            },
+            Disruption::Latency { proxy, .. } => {
+                ensure!(
+                    proxies.contains(proxy),
+                    "disruption {index} adds latency to unknown proxy {proxy}"
+                );
+            },
+        }
+        if let Some(target) = scheduled.disruption.target() {
+            windows
+                .entry(target)
+                .or_default()
+                .push((scheduled.start, scheduled.start + scheduled.duration));
+        }
+    }
+
+    // Reverting one of two overlapping disruptions of the same target would revert both.
+    for (target, mut windows) in windows {
+        windows.sort();
+        for pair in windows.windows(2) {
+            if pair[1].0 < pair[0].1 {
+                bail!("disruptions of {target} overlap");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Drill {
+    /// Apply `scheduled` at its start time and revert it once its duration has passed.
+    async fn run(self: Arc<Self>, index: usize, scheduled: ScheduledDisruption) {
+        sleep_until(self.started + scheduled.start).await;
+        let disruption = &scheduled.disruption;
+
+        if let Err(err) = self.apply(disruption, scheduled.duration).await {
+            self.record(index, TimelineEvent::Failed, disruption, Some(err));
+            return;
+        }
+        self.active.lock().insert(index, disruption.clone());
+        self.record(index, TimelineEvent::Applied, disruption, None);
+
+        sleep(scheduled.duration).await;
+        self.finish(index).await;
+    }
+
+    /// Revert disruption `index`, unless it has already been reverted.
+    async fn finish(&self, index: usize) {
+        let Some(disruption) = self.active.lock().remove(&index) else {
+            return;
+        };
+        match self.revert(&disruption).await {
+            Ok(()) => self.record(index, TimelineEvent::Reverted, &disruption, None),
+            Err(err) => self.record(index, TimelineEvent::Failed, &disruption, Some(err)),
+        }
+    }
+
+    async fn apply(&self, disruption: &Disruption, duration: Duration) -> Result<()> {
+        match disruption {
+            Disruption::PauseNode { node } => {
+                // Ask the node to resume by itself, in case the drill does not get to it.
+                let pause = NetworkPause {
+                    duration_secs: Some(duration.as_secs_f64().ceil() as u64),
+                };
+                self.admin_request(node, "admin/network/pause", &pause)
+                    .await
+            },
+            Disruption::RevokeWhitelist { keys } => {
+                let mut revoked = self.revoked.lock().await;
+                for key in keys {
+                    *revoked.entry(*key).or_default() += 1;
+                }
+                self.update_whitelist(&revoked).await
+            },
+            Disruption::Latency { proxy, delay } => {
+                self.proxies[proxy].store(delay.as_millis() as u64, Ordering::Relaxed);
+                Ok(())
+            },
+        }
+    }
+
+    async fn revert(&self, disruption: &Disruption) -> Result<()> {
+        match disruption {
+            Disruption::PauseNode { node } => {
+                self.admin_request(node, "admin/network/resume", &()).await
+            },
+            Disruption::RevokeWhitelist { keys } => {
+                let mut revoked = self.revoked.lock().await;
+                for key in keys {
+                    if let Some(count) = revoked.get_mut(key) {
+                        *count -= 1;
+                        if *count == 0 {
+                            revoked.remove(key);
+                        }
+                    }
+                }
+                self.update_whitelist(&revoked).await
+            },
+            Disruption::Latency { proxy, .. } => {
+                self.proxies[proxy].store(0, Ordering::Relaxed);
+                Ok(())
+            },
+        }
+    }
+
+    /// Send `body` to the admin API of `node`.
+    async fn admin_request<T: Serialize>(&self, node: &Url, route: &str, body: &T) -> Result<()> {
+        let token = self
+            .args
+            .admin_token
+            .as_ref()
+            .context("an admin token is required to pause nodes")?;
+        let url = format!("{}/{route}", node.as_str().trim_end_matches('/'));
+        self.http
+            .post(&url)
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .with_context(|| format!("POST {url}"))?
+            .error_for_status()
+            .with_context(|| format!("POST {url}"))?;
+        Ok(())
+    }
+
+    /// Whitelist the current stake table, except for the `revoked` keys.
+    async fn update_whitelist(&self, revoked: &BTreeMap<PubKey, usize>) -> Result<()> {
+        let (Some(endpoint), Some(stake_table_url)) =
+            (&self.args.discovery_endpoint, &self.args.stake_table_url)
+        else {
+            bail!("revoking whitelist entries requires a discovery endpoint and a stake table URL");
+        };
+
+        let client = Client::<Error, SequencerApiVersion>::new(stake_table_url.clone());
+        let stake_table = client
+            .get::<StakeTableWithEpochNumber<SeqTypes>>("node/stake-table/current")
+            .send()
+            .await
+            .context("Failed to fetch the current stake table")?;
+        let whitelist = stake_table
+            .stake_table
+            .iter()
+            .map(|peer| peer.stake_table_entry.stake_key)
+            .filter(|key| !revoked.contains_key(key))
+            .map(|key| Arc::from(key.to_bytes()))
+            .collect();
+
+        if self.args.local_discovery {
+            let mut client = <Embedded as DiscoveryClient>::new(endpoint.clone(), None).await?;
+            client.set_whitelist(whitelist).await?;
+        } else {
+            let mut client = <Redis as DiscoveryClient>::new(endpoint.clone(), None).await?;
+            client.set_whitelist(whitelist).await?;
+        }
+        Ok(())
+    }
+
+    /// Log `event` and append it to the timeline.
+    fn record(
+        &self,
+        index: usize,
+        event: TimelineEvent,
+        disruption: &Disruption,
+        error: Option<anyhow::Error>,
+    ) {
+        let entry = TimelineEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            offset: self.started.elapsed().as_millis() as u64,
+            disruption: index,
+            event,
+            description: disruption.to_string(),
+            error: error.map(|err| format!("{err:#}")),
+        };
+        match &entry.error {
+            Some(err) => tracing::error!(index, ?event, %disruption, "disruption failed: {err}"),
+            None => tracing::warn!(index, ?event, %disruption, "disruption {event:?}"),
+        }
+
+        if let Some(path) = &self.args.timeline {
+            let res = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| {
+                    writeln!(
+                        file,
+                        "{}",
+                        serde_json::to_string(&entry).unwrap_or_default()
+                    )
+                });
+            if let Err(err) = res {
+                tracing::error!("Failed to write to timeline {}: {err:#}", path.display());
+            }
+        }
+    }
+}
+
+/// Accept connections on `listener` and forward them to the upstream of `proxy`, delayed by
+/// `delay` milliseconds.
+async fn run_proxy(listener: TcpListener, proxy: ProxyConfig, delay: Arc<AtomicU64>) {
+    loop {
+        let (inbound, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!(proxy = %proxy.name, "failed to accept connection: {err:#}");
+                continue;
+            },
+        };
+        let proxy = proxy.clone();
+        let delay = delay.clone();
+        tokio::spawn(async move {
+            if let Err(err) = proxy_connection(inbound, &proxy.upstream, delay).await {
+                tracing::debug!(proxy = %proxy.name, %peer, "connection closed: {err:#}");
+            }
+        });
+    }
+}
+
+async fn proxy_connection(inbound: TcpStream, upstream: &str, delay: Arc<AtomicU64>) -> Result<()> {
+    let outbound = TcpStream::connect(upstream)
+        .await
+        .with_context(|| format!("connecting to {upstream}"))?;
+    let (inbound_read, inbound_write) = inbound.into_split();
+    let (outbound_read, outbound_write) = outbound.into_split();
+    try_join(
+        forward(inbound_read, outbound_write, delay.clone()),
+        forward(outbound_read, inbound_write, delay),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Copy data from `from` to `to`, delivering each chunk `delay` milliseconds after it was read.
+///
+/// Chunks are queued rather than forwarded one at a time, so latency does not limit throughput.
+async fn forward(
+    mut from: OwnedReadHalf,
+    mut to: OwnedWriteHalf,
+    delay: Arc<AtomicU64>,
+) -> Result<()> {
+    let (send, mut recv) = mpsc::channel::<(Instant, Vec<u8>)>(1024);
+    let read = async move {
+        let mut buf = vec![0; 16 * 1024];
+        loop {
+            let n = from.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            let due = Instant::now() + Duration::from_millis(delay.load(Ordering::Relaxed));
+            if send.send((due, buf[..n].to_vec())).await.is_err() {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    };
+    let write = async move {
+        while let Some((due, chunk)) = recv.recv().await {
+            sleep_until(due).await;
+            to.write_all(&chunk).await?;
+        }
+        to.shutdown().await?;
+        anyhow::Ok(())
+    };
+    try_join(read, write).await?;
+    Ok(())
+}
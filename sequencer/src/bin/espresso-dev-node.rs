@@ -15,7 +15,7 @@ use alloy::{
 use async_trait::async_trait;
 use clap::Parser;
 use espresso_types::{
-    parse_duration, v0_99::ChainConfig, EpochVersion, SequencerVersions, ValidatedState,
+    parse_duration, v0_4::ChainConfig, EpochVersion, SequencerVersions, ValidatedState,
 };
 use futures::{future::BoxFuture, stream::FuturesUnordered, FutureExt, StreamExt};
 use hotshot_contract_adapter::sol_types::LightClientV2Mock::{self, LightClientV2MockInstance};
@@ -356,6 +356,8 @@ async fn main() -> anyhow::Result<()> {
             blocks_per_epoch,
             epoch_start_block,
             max_retries: 0,
+            gas_price_threshold: None,
+            max_update_delay: Duration::ZERO,
         };
 
         // spawn off prover service for this chain
@@ -7,14 +7,18 @@ use std::{
 };
 
 use alloy::hex;
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use clap::{Parser, ValueEnum};
 use derive_more::Display;
+use espresso_types::ThresholdEncryptionKey;
 use hotshot::types::SignatureKey;
 use hotshot_types::{light_client::StateKeyPair, signature_key::BLSPubKey};
 use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use sequencer::keystore::{self, Keystore};
 use sequencer_utils::logging;
 use tracing::info_span;
+use zeroize::Zeroizing;
 
 #[derive(Clone, Copy, Debug, Display, Default, ValueEnum)]
 enum Scheme {
@@ -99,6 +103,21 @@ struct Options {
     #[clap(short, long, name = "OUT")]
     out: PathBuf,
 
+    /// Also split a threshold encryption key among the N setups, with threshold T.
+    ///
+    /// Each .env file gets a share of the key. The public parameters of the key are written to
+    /// encryption.toml in DIR, as an `[encryption]` section to add to the genesis file.
+    #[clap(long, name = "T")]
+    encryption_threshold: Option<usize>,
+
+    /// Encrypt each setup under the passphrase in this file.
+    ///
+    /// Instead of .env files, private key setups are written to encrypted keystores named
+    /// 0.keystore, 1.keystore, etc., which can be used to configure a sequencer node with
+    /// ESPRESSO_SEQUENCER_KEYSTORE.
+    #[clap(long)]
+    passphrase_file: Option<PathBuf>,
+
     #[clap(flatten)]
     logging: logging::Config,
 }
@@ -112,7 +131,7 @@ fn parse_seed(s: &str) -> Result<[u8; 32], anyhow::Error> {
 
 fn gen_default_seed() -> [u8; 32] {
     let mut seed = [0u8; 32];
-    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let mut rng = ChaChaRng::from_entropy();
     rng.fill_bytes(&mut seed);
 
     seed
@@ -137,18 +156,65 @@ fn main() -> anyhow::Result<()> {
     });
     fs::write(opts.out.join(".seed"), hex::encode(seed))?;
 
+    let encryption_key_shares = match opts.encryption_threshold {
+        Some(threshold) => {
+            if threshold == 0 || threshold > opts.num {
+                bail!("encryption threshold must be between 1 and N");
+            }
+            // Each setup holds the share with its own index, registered to its staking key.
+            let holders = (0..opts.num as u64)
+                .map(|index| BLSPubKey::generated_from_seed_indexed(seed, index).0)
+                .collect();
+            let (key, shares) = ThresholdEncryptionKey::generate(
+                threshold,
+                holders,
+                &mut ChaChaRng::from_seed(seed),
+            );
+            let mut genesis = toml::Table::new();
+            genesis.insert("encryption".into(), toml::Value::try_from(&key)?);
+            let path = opts.out.join("encryption.toml");
+            fs::write(&path, genesis.to_string())?;
+            tracing::info!("encryption key written to {}", path.display());
+            shares
+        },
+        None => vec![],
+    };
+
+    let passphrase = opts
+        .passphrase_file
+        .as_deref()
+        .map(keystore::read_passphrase)
+        .transpose()?;
+
     for index in 0..opts.num {
         let span = info_span!("gen", index);
         let _enter = span.enter();
         tracing::info!("generating new key set");
 
-        let path = opts.out.join(format!("{index}.env"));
-        let mut file = File::options()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&path)?;
-        opts.scheme.gen(seed, index as u64, &mut file)?;
+        let mut env = Zeroizing::new(vec![]);
+        opts.scheme.gen(seed, index as u64, &mut *env)?;
+        if let Some(share) = encryption_key_shares.get(index) {
+            writeln!(env, "ESPRESSO_SEQUENCER_ENCRYPTION_KEY_SHARE={share}")?;
+        }
+
+        let path = match &passphrase {
+            Some(passphrase) => {
+                let path = opts.out.join(format!("{index}.keystore"));
+                Keystore::encrypt(&env, passphrase.as_bytes(), keystore::DEFAULT_ROUNDS)?
+                    .save(&path)?;
+                path
+            },
+            None => {
+                let path = opts.out.join(format!("{index}.env"));
+                File::options()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&path)?
+                    .write_all(&env)?;
+                path
+            },
+        };
 
         tracing::info!("private keys written to {}", path.display());
     }
@@ -0,0 +1,49 @@
+use clap::{Parser, Subcommand};
+use hotshot_query_service::data_source::storage::sql::{Config, SqlStorage};
+use sequencer::persistence;
+use sequencer_utils::logging;
+
+/// Migrate the SQL storage of a sequencer to the schema expected by this version.
+///
+/// Migrations are also applied automatically when the sequencer starts. This program allows the
+/// migrations to be inspected with `--dry-run` before they are applied, and long-running data
+/// migrations to be run to completion ahead of an upgrade. Do not run this program while the
+/// sequencer is running.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    #[clap(flatten)]
+    logging: logging::Config,
+
+    /// Report the migrations which would be applied, without changing the database.
+    #[clap(long)]
+    dry_run: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum Command {
+    /// Migrate SQL storage.
+    Sql(Box<persistence::sql::Options>),
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let opt = Options::parse();
+    opt.logging.init();
+
+    let Command::Sql(sql_opt) = opt.command;
+    let cfg = Config::try_from(&*sql_opt)?;
+
+    let plan = SqlStorage::plan_migrations(cfg.clone()).await?;
+    println!("{plan}");
+    if opt.dry_run || plan.is_up_to_date() {
+        return Ok(());
+    }
+
+    tracing::warn!("migrating SQL storage {sql_opt:?}");
+    SqlStorage::connect(cfg).await?;
+    tracing::warn!("migration complete");
+    Ok(())
+}
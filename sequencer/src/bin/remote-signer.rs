@@ -0,0 +1,133 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use clap::Parser;
+use futures::future::select_all;
+use hotshot_types::{light_client::StateSignKey, signature_key::BLSPrivKey};
+use sequencer::{
+    keystore::{self, KeyFileVars, Keystore},
+    remote_signer::{serve_http, serve_unix, Signer, SignerSecret},
+    SequencerApiVersion,
+};
+use sequencer_utils::logging;
+use tagged_base64::TaggedBase64;
+use vbs::version::StaticVersionType;
+
+/// Sign consensus messages and light client states on behalf of a sequencer node.
+///
+/// The node is pointed at this signer with ESPRESSO_SEQUENCER_REMOTE_SIGNER_URL, and both must be
+/// given the same secret. The signer serves over HTTP, over a Unix domain socket, or both.
+#[derive(Parser)]
+struct Args {
+    /// Address to bind the HTTP server to.
+    #[clap(long, env = "ESPRESSO_REMOTE_SIGNER_HOST", default_value = "127.0.0.1")]
+    host: IpAddr,
+
+    /// Port to run the HTTP server on.
+    #[clap(short, long, env = "ESPRESSO_REMOTE_SIGNER_PORT")]
+    port: Option<u16>,
+
+    /// Path of a Unix domain socket to serve on.
+    #[clap(long, env = "ESPRESSO_REMOTE_SIGNER_SOCKET")]
+    socket: Option<PathBuf>,
+
+    /// Path to a file containing the secret shared with the node.
+    #[clap(long, env = "ESPRESSO_REMOTE_SIGNER_SECRET_FILE")]
+    secret_file: PathBuf,
+
+    /// Path to the file recording the last light client state and the recent votes signed.
+    ///
+    /// The signer refuses to sign a state older than the last one, or a different state at the same
+    /// height, and refuses to sign two different votes of the same kind in the same view, so this
+    /// must persist across restarts of the signer.
+    #[clap(long, env = "ESPRESSO_REMOTE_SIGNER_HIGH_WATER_MARK")]
+    high_water_mark: PathBuf,
+
+    /// Path to the node's key file.
+    #[clap(long, name = "KEY_FILE", env = "ESPRESSO_SEQUENCER_KEY_FILE")]
+    key_file: Option<PathBuf>,
+
+    /// Path to the node's encrypted keystore, as an alternative to KEY_FILE.
+    #[clap(
+        long,
+        name = "KEYSTORE",
+        env = "ESPRESSO_SEQUENCER_KEYSTORE",
+        conflicts_with = "KEY_FILE",
+        requires = "KEYSTORE_PASSPHRASE_FILE"
+    )]
+    keystore: Option<PathBuf>,
+
+    /// Path to a file containing the passphrase for KEYSTORE.
+    #[clap(
+        long,
+        name = "KEYSTORE_PASSPHRASE_FILE",
+        env = "ESPRESSO_SEQUENCER_KEYSTORE_PASSPHRASE_FILE"
+    )]
+    keystore_passphrase_file: Option<PathBuf>,
+
+    #[clap(flatten)]
+    logging: logging::Config,
+}
+
+impl Args {
+    fn private_keys(&self) -> anyhow::Result<(BLSPrivKey, StateSignKey)> {
+        let vars = match (
+            &self.keystore,
+            &self.keystore_passphrase_file,
+            &self.key_file,
+        ) {
+            (Some(path), Some(passphrase_file), _) => {
+                let passphrase = keystore::read_passphrase(passphrase_file)?;
+                Keystore::open(path, passphrase.as_bytes())?
+            },
+            (None, _, Some(path)) => KeyFileVars::read(path)?,
+            _ => anyhow::bail!("neither key file nor keystore was provided"),
+        };
+        let consensus_key = vars
+            .get("ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")
+            .context("key file missing ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")?;
+        let state_key = vars
+            .get("ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY")
+            .context("key file missing ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY")?;
+        Ok((
+            TaggedBase64::parse(consensus_key)?.try_into()?,
+            TaggedBase64::parse(state_key)?.try_into()?,
+        ))
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    args.logging.init();
+
+    let (consensus_key, state_key) = args.private_keys()?;
+    let signer = Signer::new(
+        consensus_key,
+        state_key,
+        SignerSecret::read(&args.secret_file)?,
+        args.high_water_mark.clone(),
+    )?;
+
+    let mut servers = vec![];
+    if let Some(port) = args.port {
+        servers.push(tokio::spawn(serve_http(
+            format!("http://{}", SocketAddr::new(args.host, port)).parse()?,
+            signer.clone(),
+            SequencerApiVersion::instance(),
+        )));
+    }
+    if let Some(socket) = args.socket {
+        servers.push(tokio::spawn(serve_unix(socket, signer)));
+    }
+    anyhow::ensure!(
+        !servers.is_empty(),
+        "neither a port nor a socket was provided"
+    );
+    // The servers run until they fail.
+    let (result, ..) = select_all(servers).await;
+    result?
+}
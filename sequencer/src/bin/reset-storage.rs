@@ -24,6 +24,8 @@ enum Command {
     Fs(persistence::fs::Options),
     /// Reset SQL storage.
     Sql(Box<persistence::sql::Options>),
+    /// Reset embedded storage.
+    Embedded(persistence::embedded::Options),
 }
 
 #[tokio::main]
@@ -40,12 +42,16 @@ async fn main() -> anyhow::Result<()> {
             tracing::warn!("resetting SQL storage {opt:?}");
             reset_storage(*opt).await
         },
+        Command::Embedded(opt) => {
+            tracing::warn!("resetting embedded storage {opt:?}");
+            reset_storage(opt).await
+        },
     }
 }
 
 async fn reset_storage<O: DataSourceOptions>(opt: O) -> anyhow::Result<()> {
     // Reset query service storage.
-    O::DataSource::create(opt.clone(), Default::default(), true).await?;
+    O::DataSource::create(opt.data_source_options(), Default::default(), true).await?;
     // Reset consensus storage.
     opt.reset().await?;
 
@@ -0,0 +1,169 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context};
+use clap::{Args, Parser, Subcommand};
+use espresso_types::v0::traits::PersistenceOptions;
+use hotshot_query_service::node::NodeDataSource;
+use sequencer::{
+    api::data_source::{DataSourceOptions, SequencerDataSource},
+    persistence::{self, snapshot::Snapshot},
+};
+use sequencer_utils::logging;
+
+/// Export or import the persistent state of a sequencer.
+///
+/// `export` writes a consistent archive of consensus storage, the network config and (optionally)
+/// the genesis file, along with a manifest recording the height of the query service storage.
+/// `import` restores such an archive into empty storage, which may use a different backend than
+/// the storage it was exported from. Together, these move a node between machines without losing
+/// its place in consensus. Do not run this program while the sequencer is running.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    #[clap(flatten)]
+    logging: logging::Config,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum Command {
+    /// Write the state of a node to an archive.
+    Export(Transfer),
+    /// Restore the state of a node from an archive.
+    ///
+    /// The target storage must not hold any consensus state; use `reset-storage` first if needed.
+    /// The query service storage is not restored: it fetches missing data from peers once the node
+    /// is running.
+    Import(Transfer),
+}
+
+#[derive(Clone, Debug, Args)]
+struct Transfer {
+    /// Path of the archive.
+    #[clap(long)]
+    archive: PathBuf,
+
+    /// Genesis file of the node.
+    ///
+    /// On export, the file is included in the archive. On import, the archived genesis file is
+    /// written to this path, which must not exist yet.
+    #[clap(long)]
+    genesis_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    storage: Storage,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum Storage {
+    /// File system storage.
+    Fs(persistence::fs::Options),
+    /// SQL storage.
+    Sql(Box<persistence::sql::Options>),
+    /// Embedded storage.
+    Embedded(persistence::embedded::Options),
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let opt = Options::parse();
+    opt.logging.init();
+
+    match opt.command {
+        Command::Export(transfer) => match transfer.storage.clone() {
+            Storage::Fs(storage) => export(storage, transfer).await,
+            Storage::Sql(storage) => export(*storage, transfer).await,
+            Storage::Embedded(storage) => export(storage, transfer).await,
+        },
+        Command::Import(transfer) => match transfer.storage.clone() {
+            Storage::Fs(storage) => import(storage, transfer).await,
+            Storage::Sql(storage) => import(*storage, transfer).await,
+            Storage::Embedded(storage) => import(storage, transfer).await,
+        },
+    }
+}
+
+async fn export<O: DataSourceOptions>(mut opt: O, transfer: Transfer) -> anyhow::Result<()> {
+    tracing::warn!(
+        "exporting storage {opt:?} to {}",
+        transfer.archive.display()
+    );
+
+    let persistence = opt.create().await?;
+    let mut snapshot = Snapshot::export(&persistence).await?;
+    if let Some(path) = &transfer.genesis_file {
+        snapshot.genesis = Some(
+            std::fs::read_to_string(path)
+                .with_context(|| format!("reading genesis file {}", path.display()))?,
+        );
+    }
+
+    let query_block_height = query_block_height(&opt).await;
+    let manifest = snapshot.write(&transfer.archive, query_block_height)?;
+    println!("{manifest:#?}");
+    Ok(())
+}
+
+async fn import<O: DataSourceOptions>(mut opt: O, transfer: Transfer) -> anyhow::Result<()> {
+    let (manifest, snapshot) = Snapshot::read(&transfer.archive)?;
+    println!("{manifest:#?}");
+    tracing::warn!(
+        "importing {} into storage {opt:?}",
+        transfer.archive.display()
+    );
+
+    // Write the genesis file before touching storage, so that a conflicting path does not leave
+    // storage partially imported.
+    match (&snapshot.genesis, &transfer.genesis_file) {
+        (Some(genesis), Some(path)) => {
+            ensure!(
+                !path.exists(),
+                "genesis file {} already exists",
+                path.display()
+            );
+            std::fs::write(path, genesis)
+                .with_context(|| format!("writing genesis file {}", path.display()))?;
+        },
+        (None, Some(_)) => tracing::warn!("archive does not include a genesis file"),
+        (Some(_), None) => {
+            tracing::warn!("archive includes a genesis file, but no path was given to write it to")
+        },
+        (None, None) => {},
+    }
+
+    let persistence = opt.create().await?;
+    snapshot.import(&persistence).await?;
+
+    if let Some(expected) = manifest.query_block_height {
+        match query_block_height(&opt).await {
+            Some(height) if height >= expected => {},
+            height => tracing::warn!(
+                ?height,
+                expected,
+                "query service storage is behind the exported node; missing blocks will be \
+                 fetched from peers"
+            ),
+        }
+    }
+
+    tracing::warn!("import complete");
+    Ok(())
+}
+
+/// The height of the query service storage accompanying `opt`, if it can be opened.
+async fn query_block_height<O: DataSourceOptions>(opt: &O) -> Option<u64> {
+    let res = async {
+        let ds =
+            O::DataSource::create(opt.data_source_options(), Default::default(), false).await?;
+        anyhow::Ok(ds.block_height().await? as u64)
+    }
+    .await;
+    match res {
+        Ok(height) => Some(height),
+        Err(err) => {
+            tracing::warn!("unable to read query service storage: {err:#}");
+            None
+        },
+    }
+}
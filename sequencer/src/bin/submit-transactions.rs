@@ -20,6 +20,7 @@ use futures::{
     stream::StreamExt,
 };
 use hotshot_query_service::{availability::BlockQueryData, types::HeightIndexed, Error};
+use hotshot_types::api_auth::{ApiCredentials, Scope};
 use rand::{Rng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
 use rand_distr::Distribution;
@@ -125,6 +126,14 @@ struct Options {
     #[clap(long, env = "ESPRESSO_SUBMIT_TRANSACTIONS_SUBMIT_URL")]
     submit_url: Option<Url>,
 
+    /// API key to present to the builder when submitting to a private mempool.
+    #[clap(
+        long,
+        env = "ESPRESSO_SUBMIT_TRANSACTIONS_SUBMIT_API_KEY",
+        requires = "submit_url"
+    )]
+    submit_api_key: Option<String>,
+
     /// URL of the query service.
     #[clap(env = "ESPRESSO_SEQUENCER_URL")]
     url: Url,
@@ -389,6 +398,11 @@ async fn submit_transactions<ApiVer: StaticVersionType>(
     let url = opt.submit_url();
     tracing::info!(%url, "starting load generator task");
     let client = Client::<Error, ApiVer>::new(url);
+    let authorization = opt.submit_api_key.clone().map(|key| {
+        ApiCredentials::Key(key)
+            .authorization(Scope::SubmitBundles)
+            .unwrap()
+    });
 
     // Create an exponential distribution for sampling delay times. The distribution should have
     // mean `opt.delay`, or parameter `\lambda = 1 / opt.delay`.
@@ -414,22 +428,17 @@ async fn submit_transactions<ApiVer: StaticVersionType>(
         };
         let txns_batch_count = txns.len() as u64;
         if randomized_batch_size <= txns_batch_count {
-            if let Err(err) = if txns_batch_count == 1 {
+            let req = if txns_batch_count == 1 {
                 // occasionally test the 'submit' endpoint, just for coverage
-                client
-                    .post::<()>("submit")
-                    .body_binary(&txns[0])
-                    .unwrap()
-                    .send()
-                    .await
+                client.post::<()>("submit").body_binary(&txns[0]).unwrap()
             } else {
-                client
-                    .post::<()>("batch")
-                    .body_binary(&txns)
-                    .unwrap()
-                    .send()
-                    .await
-            } {
+                client.post::<()>("batch").body_binary(&txns).unwrap()
+            };
+            let req = match &authorization {
+                Some(authorization) => req.header("Authorization", authorization.as_str()),
+                None => req,
+            };
+            if let Err(err) = req.send().await {
                 tracing::error!(
                     ?err,
                     "failed to submit batch of {txns_batch_count} transactions"
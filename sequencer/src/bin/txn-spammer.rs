@@ -0,0 +1,472 @@
+//! Load-testing tool which submits transactions to Espresso Sequencer nodes at a fixed rate.
+//!
+//! Transactions are submitted round-robin to one or more submit endpoints for a fixed duration.
+//! The end-to-end inclusion latency of each transaction, from submission until it appears in a
+//! decided leaf on the HotShot events stream, is collected and reported as a histogram once the
+//! run is over.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::{error::ErrorKind, Parser, ValueEnum};
+use committable::{Commitment, Committable};
+use espresso_types::{parse_duration, parse_size, SeqTypes, Transaction};
+use futures::stream::StreamExt;
+use hotshot_query_service::Error;
+use hotshot_types::{
+    event::{Event, EventType},
+    traits::{block_contents::BlockHeader, BlockPayload},
+};
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use rand_distr::Distribution;
+use sequencer::SequencerApiVersion;
+use sequencer_utils::logging;
+use surf_disco::{Client, Url};
+use tokio::{
+    sync::{mpsc, Semaphore},
+    task::spawn,
+    time::{interval, timeout, MissedTickBehavior},
+};
+
+/// Upper bounds of the buckets of the latency histogram.
+const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(4),
+    Duration::from_secs(8),
+    Duration::from_secs(15),
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+    Duration::from_secs(120),
+];
+
+/// Distribution of the sizes of submitted transactions.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+enum SizeDistribution {
+    /// Every transaction has size MAX_SIZE.
+    Fixed,
+    /// Sizes are chosen uniformly between MIN_SIZE and MAX_SIZE.
+    #[default]
+    Uniform,
+    /// Sizes are sampled from an exponential distribution with mean MEAN_SIZE, clamped between
+    /// MIN_SIZE and MAX_SIZE.
+    Exponential,
+}
+
+/// Submit transactions to Espresso Sequencer nodes at a configurable rate and report how long
+/// they take to be decided.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    /// Number of transactions to submit per second, across all submit endpoints.
+    #[clap(long, default_value = "10", env = "ESPRESSO_TXN_SPAMMER_RATE")]
+    rate: f64,
+
+    /// How long to keep submitting transactions for.
+    #[clap(long, value_parser = parse_duration, default_value = "1m", env = "ESPRESSO_TXN_SPAMMER_DURATION")]
+    duration: Duration,
+
+    /// How long to wait for submitted transactions to be decided once submission has stopped.
+    #[clap(long, value_parser = parse_duration, default_value = "30s", env = "ESPRESSO_TXN_SPAMMER_DRAIN_TIMEOUT")]
+    drain_timeout: Duration,
+
+    /// Distribution of transaction sizes.
+    #[clap(
+        long,
+        value_enum,
+        default_value_t,
+        env = "ESPRESSO_TXN_SPAMMER_SIZE_DISTRIBUTION"
+    )]
+    size_distribution: SizeDistribution,
+
+    /// Minimum size of transaction to submit.
+    #[clap(long, name = "MIN_SIZE", default_value = "1", value_parser = parse_size, env = "ESPRESSO_TXN_SPAMMER_MIN_SIZE")]
+    min_size: u64,
+
+    /// Maximum size of transaction to submit.
+    #[clap(long, name = "MAX_SIZE", default_value = "1kb", value_parser = parse_size, env = "ESPRESSO_TXN_SPAMMER_MAX_SIZE")]
+    max_size: u64,
+
+    /// Mean size of transaction to submit, for the exponential size distribution.
+    #[clap(long, name = "MEAN_SIZE", default_value = "256", value_parser = parse_size, env = "ESPRESSO_TXN_SPAMMER_MEAN_SIZE")]
+    mean_size: u64,
+
+    /// Minimum namespace ID to submit to.
+    #[clap(
+        long,
+        default_value = "10000",
+        env = "ESPRESSO_TXN_SPAMMER_MIN_NAMESPACE"
+    )]
+    min_namespace: u32,
+
+    /// Maximum namespace ID to submit to.
+    #[clap(
+        long,
+        default_value = "10010",
+        env = "ESPRESSO_TXN_SPAMMER_MAX_NAMESPACE"
+    )]
+    max_namespace: u32,
+
+    /// Maximum number of submissions in flight at once.
+    ///
+    /// When this many submissions are outstanding, further transactions are skipped rather than
+    /// queued, so that a slow endpoint shows up as a lower achieved rate.
+    #[clap(
+        long,
+        default_value = "1000",
+        env = "ESPRESSO_TXN_SPAMMER_MAX_IN_FLIGHT"
+    )]
+    max_in_flight: usize,
+
+    /// Seed for reproducible randomness.
+    #[clap(long, env = "ESPRESSO_TXN_SPAMMER_SEED")]
+    seed: Option<u64>,
+
+    /// URL of a node serving the HotShot events API, used to observe decided transactions.
+    ///
+    /// The node should be a DA committee member, so that decided leaves carry their payloads.
+    #[clap(long, env = "ESPRESSO_TXN_SPAMMER_EVENTS_URL")]
+    events_url: Url,
+
+    /// Base URLs of the nodes to submit transactions to.
+    ///
+    /// Transactions are posted to the `submit/submit` endpoint of each node in turn.
+    #[clap(
+        long = "submit-url",
+        env = "ESPRESSO_TXN_SPAMMER_SUBMIT_URLS",
+        value_delimiter = ',',
+        required = true
+    )]
+    submit_urls: Vec<Url>,
+
+    #[clap(flatten)]
+    logging: logging::Config,
+}
+
+impl Options {
+    /// Check that the options describe transactions which can be generated.
+    fn validate(&self) -> Result<(), clap::Error> {
+        let invalid = |msg: &str| Err(clap::Error::raw(ErrorKind::ValueValidation, msg));
+        if self.rate <= 0f64 {
+            return invalid("RATE must be positive\n");
+        }
+        if self.min_size > self.max_size {
+            return invalid("MIN_SIZE must not be greater than MAX_SIZE\n");
+        }
+        if self.min_namespace > self.max_namespace {
+            return invalid("MIN_NAMESPACE must not be greater than MAX_NAMESPACE\n");
+        }
+        Ok(())
+    }
+
+    /// Sample the size of the next transaction.
+    fn transaction_size(&self, rng: &mut ChaChaRng) -> u64 {
+        match self.size_distribution {
+            SizeDistribution::Fixed => self.max_size,
+            SizeDistribution::Uniform => rng.gen_range(self.min_size..=self.max_size),
+            SizeDistribution::Exponential => {
+                let distr =
+                    rand_distr::Exp::<f64>::new(1f64 / self.mean_size.max(1) as f64).unwrap();
+                (distr.sample(rng) as u64).clamp(self.min_size, self.max_size)
+            },
+        }
+    }
+
+    fn random_transaction(&self, rng: &mut ChaChaRng) -> Transaction {
+        let namespace = rng.gen_range(self.min_namespace..=self.max_namespace);
+
+        let mut payload = vec![0; self.transaction_size(rng) as usize];
+        rng.fill_bytes(&mut payload);
+
+        Transaction::new(namespace.into(), payload)
+    }
+}
+
+struct SubmittedTransaction {
+    hash: Commitment<Transaction>,
+    submitted_at: Instant,
+}
+
+/// Counters shared between the submission tasks and the report.
+#[derive(Debug, Default)]
+struct SubmissionStats {
+    submitted: AtomicUsize,
+    failed: AtomicUsize,
+    skipped: AtomicUsize,
+}
+
+#[tokio::main]
+async fn main() {
+    let opt = Options::parse();
+    if let Err(err) = opt.validate() {
+        err.exit();
+    }
+    opt.logging.init();
+
+    let seed = opt
+        .seed
+        .unwrap_or_else(|| ChaChaRng::from_entropy().next_u64());
+    tracing::info!("PRNG seed: {seed}");
+
+    // Subscribe before submitting anything, so that no decide is missed.
+    let mut events = Client::<Error, SequencerApiVersion>::new(opt.events_url.clone())
+        .socket("hotshot-events/events")
+        .subscribe::<Event<SeqTypes>>()
+        .await
+        .expect("failed to subscribe to the HotShot events stream");
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let stats = Arc::new(SubmissionStats::default());
+    let started_at = Instant::now();
+    let mut generator = spawn(generate_load(
+        opt.clone(),
+        ChaChaRng::seed_from_u64(seed),
+        sender,
+        Arc::clone(&stats),
+    ));
+
+    let mut pending = HashMap::new();
+    let mut latencies = Vec::new();
+    let mut generating = true;
+    let mut drain_deadline = None;
+    loop {
+        let event = if generating {
+            tokio::select! {
+                _ = &mut generator => {
+                    tracing::warn!("submission finished, waiting for pending transactions");
+                    generating = false;
+                    drain_deadline = Some(Instant::now() + opt.drain_timeout);
+                    continue;
+                },
+                event = events.next() => event,
+            }
+        } else {
+            // Transactions submitted before the channel closed are all known by now.
+            while let Ok(tx) = receiver.try_recv() {
+                pending.insert(tx.hash, tx.submitted_at);
+            }
+            let remaining = drain_deadline
+                .unwrap()
+                .saturating_duration_since(Instant::now());
+            if pending.is_empty() || remaining.is_zero() {
+                break;
+            }
+            match timeout(remaining, events.next()).await {
+                Ok(event) => event,
+                Err(_) => break,
+            }
+        };
+
+        let event = match event {
+            Some(Ok(event)) => event,
+            Some(Err(err)) => {
+                tracing::warn!("error getting event: {err}");
+                continue;
+            },
+            None => {
+                tracing::error!("events stream ended");
+                break;
+            },
+        };
+        let EventType::Decide { leaf_chain, .. } = event.event else {
+            continue;
+        };
+        let decided_at = Instant::now();
+
+        // Get all transactions which were submitted before this decide.
+        while let Ok(tx) = receiver.try_recv() {
+            pending.insert(tx.hash, tx.submitted_at);
+        }
+
+        for leaf_info in leaf_chain.iter() {
+            let leaf = &leaf_info.leaf;
+            let Some(payload) = leaf.block_payload() else {
+                tracing::warn!(
+                    height = leaf.height(),
+                    "decided leaf has no payload, cannot check for transactions"
+                );
+                continue;
+            };
+            for tx in payload.transactions(leaf.block_header().metadata()) {
+                if let Some(submitted_at) = pending.remove(&tx.commit()) {
+                    let latency = decided_at - submitted_at;
+                    tracing::debug!(
+                        "transaction {} decided in block {} after {latency:?}",
+                        tx.commit(),
+                        leaf.height()
+                    );
+                    latencies.push(latency);
+                }
+            }
+        }
+        tracing::info!(
+            "{} transactions decided, {} pending",
+            latencies.len(),
+            pending.len()
+        );
+    }
+
+    report(&stats, started_at.elapsed(), &mut latencies, pending.len());
+}
+
+/// Submit transactions at the configured rate until the configured duration has passed.
+async fn generate_load(
+    opt: Options,
+    mut rng: ChaChaRng,
+    sender: mpsc::UnboundedSender<SubmittedTransaction>,
+    stats: Arc<SubmissionStats>,
+) {
+    let clients: Vec<_> = opt
+        .submit_urls
+        .iter()
+        .map(|url| Client::<Error, SequencerApiVersion>::new(url.join("submit").unwrap()))
+        .collect();
+    let in_flight = Arc::new(Semaphore::new(opt.max_in_flight));
+
+    let mut ticks = interval(Duration::from_secs_f64(1f64 / opt.rate));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let deadline = Instant::now() + opt.duration;
+
+    for client in clients.iter().cycle() {
+        ticks.tick().await;
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        let Ok(permit) = Arc::clone(&in_flight).try_acquire_owned() else {
+            stats.skipped.fetch_add(1, Ordering::Relaxed);
+            continue;
+        };
+        let tx = opt.random_transaction(&mut rng);
+        let client = client.clone();
+        let sender = sender.clone();
+        let stats = Arc::clone(&stats);
+        spawn(async move {
+            let hash = tx.commit();
+            let submitted_at = Instant::now();
+            match client
+                .post::<()>("submit")
+                .body_binary(&tx)
+                .unwrap()
+                .send()
+                .await
+            {
+                Ok(()) => {
+                    stats.submitted.fetch_add(1, Ordering::Relaxed);
+                    sender
+                        .send(SubmittedTransaction { hash, submitted_at })
+                        .ok();
+                },
+                Err(err) => {
+                    tracing::warn!("failed to submit transaction {hash}: {err}");
+                    stats.failed.fetch_add(1, Ordering::Relaxed);
+                },
+            }
+            drop(permit);
+        });
+    }
+
+    // Wait for the submissions still in flight.
+    let _ = in_flight
+        .acquire_many(opt.max_in_flight as u32)
+        .await
+        .unwrap();
+}
+
+/// Print a summary of the run and a histogram of the inclusion latencies.
+fn report(stats: &SubmissionStats, elapsed: Duration, latencies: &mut [Duration], pending: usize) {
+    let submitted = stats.submitted.load(Ordering::Relaxed);
+    println!("run time:           {elapsed:?}");
+    println!(
+        "submitted:          {submitted} ({:.2}/s)",
+        submitted as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "failed submissions: {}",
+        stats.failed.load(Ordering::Relaxed)
+    );
+    println!(
+        "skipped (in flight limit): {}",
+        stats.skipped.load(Ordering::Relaxed)
+    );
+    println!("decided:            {}", latencies.len());
+    println!("never decided:      {pending}");
+
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+    println!(
+        "latency: min {:?} p50 {:?} p90 {:?} p99 {:?} max {:?}",
+        latencies[0],
+        percentile(0.5),
+        percentile(0.9),
+        percentile(0.99),
+        latencies[latencies.len() - 1]
+    );
+
+    let mut counts = [0usize; LATENCY_BUCKETS.len() + 1];
+    for latency in latencies.iter() {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| latency <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        counts[bucket] += 1;
+    }
+    let widest = counts.iter().max().copied().unwrap_or(1).max(1);
+    for (bucket, count) in counts.iter().enumerate() {
+        let label = match LATENCY_BUCKETS.get(bucket) {
+            Some(bound) => format!("<= {bound:?}"),
+            None => format!("> {:?}", LATENCY_BUCKETS[LATENCY_BUCKETS.len() - 1]),
+        };
+        println!(
+            "{label:>10} | {count:>8} {}",
+            "#".repeat(count * 50 / widest)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        Options::try_parse_from(
+            [
+                "txn-spammer",
+                "--events-url",
+                "http://localhost:1",
+                "--submit-url",
+                "http://localhost:2",
+            ]
+            .iter()
+            .chain(args),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_options() {
+        options(&[]).validate().unwrap();
+        options(&["--min-size", "1kb", "--max-size", "1kb"])
+            .validate()
+            .unwrap();
+
+        options(&["--rate", "0"]).validate().unwrap_err();
+        options(&["--min-size", "2kb", "--max-size", "1kb"])
+            .validate()
+            .unwrap_err();
+        options(&["--min-namespace", "2", "--max-namespace", "1"])
+            .validate()
+            .unwrap_err();
+    }
+}
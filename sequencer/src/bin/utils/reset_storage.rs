@@ -23,6 +23,8 @@ pub enum SequencerStorage {
     Fs(persistence::fs::Options),
     /// Reset SQL storage.
     Sql(Box<persistence::sql::Options>),
+    /// Reset embedded storage.
+    Embedded(persistence::embedded::Options),
 }
 
 pub async fn run(opt: Commands) -> anyhow::Result<()> {
@@ -36,6 +38,10 @@ pub async fn run(opt: Commands) -> anyhow::Result<()> {
                 tracing::warn!("resetting sequencer SQL storage {opt:?}");
                 reset_storage(*opt).await
             },
+            SequencerStorage::Embedded(opt) => {
+                tracing::warn!("resetting sequencer embedded storage {opt:?}");
+                reset_storage(opt).await
+            },
         },
 
         Commands::Solver(opt) => {
@@ -50,7 +56,7 @@ pub async fn run(opt: Commands) -> anyhow::Result<()> {
 
 async fn reset_storage<O: DataSourceOptions>(opt: O) -> anyhow::Result<()> {
     // Reset query service storage.
-    O::DataSource::create(opt.clone(), Default::default(), true).await?;
+    O::DataSource::create(opt.data_source_options(), Default::default(), true).await?;
     // Reset consensus storage.
     opt.reset().await?;
 
@@ -0,0 +1,289 @@
+//! Bootstrapping a node without an orchestrator.
+//!
+//! Instead of registering with the orchestrator and waiting for it to hand out the network config,
+//! a node can derive the config from a [`BootstrapDocument`] signed by a key it trusts, and from
+//! the stake table contract on L1. The document holds everything the orchestrator would otherwise
+//! decide (timeouts, DA committee, Libp2p bootstrap nodes, ...) and the L1 block at which the
+//! initial stake table is read, so that every node derives the same config.
+//!
+//! The document is a TOML file, and its signature is kept next to it in a file of the same name
+//! with `.sig` appended. The signature covers the bytes of the document file exactly as they were
+//! written, so it does not depend on how the document is parsed or encoded.
+
+use std::{
+    ffi::OsString,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use alloy::primitives::Address;
+use anyhow::{ensure, Context};
+use espresso_types::{v0_3::StakeTableFetcher, L1Client, PubKey, SeqTypes};
+use hotshot_types::{
+    network::{Libp2pConfig, NetworkConfig, NetworkConfigFile},
+    signature_key::BLSPrivKey,
+    traits::signature_key::{SignatureKey, StakeTableEntryType},
+    PeerConfig, ValidatorConfig,
+};
+use libp2p::Multiaddr;
+use serde::{Deserialize, Serialize};
+
+use crate::network::libp2p::split_off_peer_id;
+
+/// Signature over a [`BootstrapDocument`] file
+pub type BootstrapSignature = <PubKey as SignatureKey>::PureAssembledSignatureType;
+
+/// The network-wide parameters a node needs to join the network without an orchestrator
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BootstrapDocument {
+    /// Parameters of the network, in the format of the orchestrator config file.
+    ///
+    /// The stake table and DA committee given here are ignored, and replaced by those derived from
+    /// L1 and [`Self::da_nodes`].
+    pub network: NetworkConfigFile<SeqTypes>,
+    /// Address of the stake table contract on L1
+    pub stake_table_contract: Address,
+    /// L1 block at which the initial stake table is read
+    pub l1_block: u64,
+    /// Keys of the members of the DA committee, all of which must be in the stake table
+    pub da_nodes: Vec<PubKey>,
+    /// Libp2p addresses, including peer ID, of the nodes to connect to on startup
+    #[serde(default)]
+    pub libp2p_bootstrap_nodes: Vec<Multiaddr>,
+}
+
+impl BootstrapDocument {
+    /// Load the document at `path`, if its signature was made by `trusted_signer`.
+    ///
+    /// The signature is read from [`signature_path`], and checked before the document is parsed.
+    pub fn load(path: impl AsRef<Path>, trusted_signer: &PubKey) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("failed to read bootstrap document {}", path.display()))?;
+        BootstrapDocumentSignature::from_file(signature_path(path))?
+            .verify(&bytes, trusted_signer)?;
+        let text = std::str::from_utf8(&bytes).context("bootstrap document is not UTF-8")?;
+        toml::from_str(text).context("malformed bootstrap document")
+    }
+
+    /// Derive the network config of the node with key `validator_config`.
+    ///
+    /// The stake table is the active validator set read from the stake table contract at
+    /// [`Self::l1_block`].
+    pub async fn network_config(
+        &self,
+        l1_client: &L1Client,
+        validator_config: &ValidatorConfig<SeqTypes>,
+    ) -> anyhow::Result<NetworkConfig<SeqTypes>> {
+        let validators = StakeTableFetcher::fetch_active_validators(
+            l1_client.clone(),
+            self.stake_table_contract,
+            self.l1_block,
+        )
+        .await
+        .context("failed to fetch the initial stake table from L1")?;
+
+        let stake_table: Vec<PeerConfig<SeqTypes>> = validators
+            .into_values()
+            .map(|v| PeerConfig {
+                stake_table_entry: PubKey::stake_table_entry(&v.stake_table_key, v.stake),
+                state_ver_key: v.state_ver_key,
+            })
+            .collect();
+        self.apply_stake_table(stake_table, &validator_config.public_key)
+    }
+
+    /// Build the network config of the node with key `public_key`, given the initial stake table
+    fn apply_stake_table(
+        &self,
+        stake_table: Vec<PeerConfig<SeqTypes>>,
+        public_key: &PubKey,
+    ) -> anyhow::Result<NetworkConfig<SeqTypes>> {
+        let node_index = stake_table
+            .iter()
+            .position(|peer| peer.stake_table_entry.public_key() == *public_key)
+            .context("this node is not in the initial stake table")?;
+
+        let da_nodes = self
+            .da_nodes
+            .iter()
+            .map(|key| {
+                stake_table
+                    .iter()
+                    .find(|peer| peer.stake_table_entry.public_key() == *key)
+                    .cloned()
+                    .with_context(|| format!("DA node {key} is not in the initial stake table"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let bootstrap_nodes = self
+            .libp2p_bootstrap_nodes
+            .iter()
+            .cloned()
+            .map(split_off_peer_id)
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("failed to parse peer ID from bootstrap node")?;
+
+        let mut config: NetworkConfig<SeqTypes> = self.network.clone().into();
+        config.node_index = node_index as u64;
+        config.libp2p_config = Some(Libp2pConfig { bootstrap_nodes });
+        config.config.num_nodes_with_stake =
+            NonZeroUsize::new(stake_table.len()).context("initial stake table is empty")?;
+        config.config.da_staked_committee_size = da_nodes.len();
+        config.config.known_nodes_with_stake = stake_table;
+        config.config.known_da_nodes = da_nodes;
+
+        Ok(config)
+    }
+}
+
+/// Path of the signature of the bootstrap document at `path`
+pub fn signature_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut path = OsString::from(path.as_ref());
+    path.push(".sig");
+    path.into()
+}
+
+/// Signature over the bytes of a bootstrap document file, with the key that issued it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BootstrapDocumentSignature {
+    /// Key which signed the document
+    pub signer: PubKey,
+    /// Signature of `signer` over the document file
+    pub signature: BootstrapSignature,
+}
+
+impl BootstrapDocumentSignature {
+    /// Sign the bytes of a document file with `key`
+    pub fn sign(document: &[u8], key: &BLSPrivKey) -> anyhow::Result<Self> {
+        let signature = PubKey::sign(key, document).context("failed to sign bootstrap document")?;
+        Ok(Self {
+            signer: PubKey::from_private(key),
+            signature,
+        })
+    }
+
+    /// Load a signature from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "failed to read bootstrap document signature {}",
+                path.display()
+            )
+        })?;
+        toml::from_str(&text).context("malformed bootstrap document signature")
+    }
+
+    /// Check that this is a signature of `trusted_signer` over the bytes of a document file
+    pub fn verify(&self, document: &[u8], trusted_signer: &PubKey) -> anyhow::Result<()> {
+        ensure!(
+            self.signer == *trusted_signer,
+            "bootstrap document is signed by {}, not by the trusted signer {trusted_signer}",
+            self.signer
+        );
+        ensure!(
+            self.signer.validate(&self.signature, document),
+            "invalid signature on bootstrap document"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::primitives::U256;
+    use hotshot_types::light_client::StateKeyPair;
+
+    use super::*;
+
+    fn document(da_nodes: Vec<PubKey>) -> BootstrapDocument {
+        BootstrapDocument {
+            network: toml::from_str::<NetworkConfigFile<SeqTypes>>("").unwrap(),
+            stake_table_contract: Address::ZERO,
+            l1_block: 0,
+            da_nodes,
+            libp2p_bootstrap_nodes: vec![],
+        }
+    }
+
+    fn stake_table(keys: &[PubKey]) -> Vec<PeerConfig<SeqTypes>> {
+        keys.iter()
+            .map(|key| PeerConfig {
+                stake_table_entry: PubKey::stake_table_entry(key, U256::from(1)),
+                state_ver_key: StateKeyPair::generate().ver_key(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bootstrap_document_signature() {
+        let (signer, signer_key) = PubKey::generated_from_seed_indexed([0; 32], 0);
+        let (other, other_key) = PubKey::generated_from_seed_indexed([0; 32], 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bootstrap.toml");
+        let text = "stake_table_contract = \"0x0000000000000000000000000000000000000000\"\n\
+                    l1_block = 0\n\
+                    da_nodes = []\n\
+                    [network]\n";
+        let write = |text: &str, signature: &BootstrapDocumentSignature| {
+            std::fs::write(&path, text).unwrap();
+            std::fs::write(signature_path(&path), toml::to_string(signature).unwrap()).unwrap();
+        };
+
+        let signature = BootstrapDocumentSignature::sign(text.as_bytes(), &signer_key).unwrap();
+        write(text, &signature);
+        let document = BootstrapDocument::load(&path, &signer).unwrap();
+        assert_eq!(document.l1_block, 0);
+        BootstrapDocument::load(&path, &other).unwrap_err();
+
+        // A document signed by an untrusted key is rejected.
+        let forged = BootstrapDocumentSignature::sign(text.as_bytes(), &other_key).unwrap();
+        write(text, &forged);
+        BootstrapDocument::load(&path, &signer).unwrap_err();
+
+        // A tampered document is rejected.
+        write(&text.replace("l1_block = 0", "l1_block = 1"), &signature);
+        BootstrapDocument::load(&path, &signer).unwrap_err();
+
+        // So is any change to the file, even one which parses to the same document.
+        write(&text.replace("l1_block = 0", "l1_block =  0"), &signature);
+        BootstrapDocument::load(&path, &signer).unwrap_err();
+
+        // As is a document without a signature.
+        std::fs::remove_file(signature_path(&path)).unwrap();
+        BootstrapDocument::load(&path, &signer).unwrap_err();
+    }
+
+    #[test]
+    fn test_bootstrap_network_config() {
+        let keys: Vec<PubKey> = (0..4)
+            .map(|i| PubKey::generated_from_seed_indexed([0; 32], i).0)
+            .collect();
+
+        let stake_table = stake_table(&keys);
+
+        let config = document(vec![keys[0], keys[2]])
+            .apply_stake_table(stake_table.clone(), &keys[3])
+            .unwrap();
+        assert_eq!(config.node_index, 3);
+        assert_eq!(config.config.num_nodes_with_stake.get(), 4);
+        assert_eq!(config.config.known_nodes_with_stake, stake_table);
+        assert_eq!(
+            config.config.known_da_nodes,
+            vec![stake_table[0].clone(), stake_table[2].clone()]
+        );
+
+        // The node must be in the stake table.
+        let (outsider, _) = PubKey::generated_from_seed_indexed([1; 32], 0);
+        document(vec![])
+            .apply_stake_table(stake_table.clone(), &outsider)
+            .unwrap_err();
+
+        // So must every DA node.
+        document(vec![outsider])
+            .apply_stake_table(stake_table, &keys[0])
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,103 @@
+//! Load the registry of builders the node accepts marketplace bundles from.
+//!
+//! The registry is read from a TOML file when the node starts, and read again each time the node
+//! receives `SIGHUP`, so builders can be registered, removed or given new quotas without
+//! restarting the node. A file which fails to load leaves the current registry in place.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use async_lock::RwLock;
+use espresso_types::BuilderRegistry;
+use tokio::signal::unix::{signal, SignalKind};
+
+fn parse(text: &str) -> anyhow::Result<BuilderRegistry> {
+    toml::from_str(text).context("malformed builder registry")
+}
+
+/// Load the builder registry from the file at `path`
+pub fn load(path: &Path) -> anyhow::Result<BuilderRegistry> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("reading builder registry {}", path.display()))?;
+    parse(&text)
+}
+
+/// Reloads the builder registry from the file at `path` each time the node receives `SIGHUP`
+pub struct BuilderRegistryReloader {
+    path: PathBuf,
+    registry: Arc<RwLock<BuilderRegistry>>,
+}
+
+impl BuilderRegistryReloader {
+    pub fn new(path: PathBuf, registry: Arc<RwLock<BuilderRegistry>>) -> Self {
+        Self { path, registry }
+    }
+
+    pub async fn run(self) {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(err) => {
+                tracing::error!("failed to install SIGHUP handler, builder registry will not be reloaded: {err:#}");
+                return;
+            },
+        };
+        while hangups.recv().await.is_some() {
+            tracing::info!(path = %self.path.display(), "reloading builder registry");
+            match load(&self.path) {
+                Ok(registry) => {
+                    tracing::info!(
+                        builders = registry.builders.len(),
+                        "updated builder registry"
+                    );
+                    *self.registry.write().await = registry;
+                },
+                Err(err) => {
+                    tracing::error!(path = %self.path.display(), "failed to reload builder registry: {err:#}");
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{FeeAccount, NamespaceId};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_builder_registry() {
+        assert_eq!(parse("").unwrap(), BuilderRegistry::default());
+
+        let registry = parse(
+            r#"
+            [[builders]]
+            account = "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f"
+            url = "http://builder.example.com"
+            namespaces = [1, 2]
+            reserve = true
+            quota = 1
+            "#,
+        )
+        .unwrap();
+        let builder = &registry.builders[0];
+        assert_eq!(
+            builder.account,
+            "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f"
+                .parse::<FeeAccount>()
+                .unwrap()
+        );
+        assert_eq!(
+            builder.namespaces,
+            [NamespaceId::from(1u64), NamespaceId::from(2u64)]
+        );
+        assert!(builder.reserve);
+        assert_eq!(builder.quota, Some(1));
+
+        parse("[[bulders]]").unwrap_err();
+    }
+}
@@ -1,4 +1,6 @@
-use std::{cmp::Ordering, collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+use std::{
+    cmp::Ordering, collections::HashMap, fmt::Display, ops::Range, sync::Arc, time::Duration,
+};
 
 use anyhow::{anyhow, bail, ensure, Context};
 use async_lock::RwLock;
@@ -9,11 +11,12 @@ use espresso_types::{
     traits::SequencerPersistence,
     v0::traits::StateCatchup,
     v0_1::{RewardAccount, RewardAccountProof, RewardMerkleCommitment, RewardMerkleTree},
-    v0_99::ChainConfig,
+    v0_4::ChainConfig,
     BackoffParams, BlockMerkleTree, FeeAccount, FeeAccountProof, FeeMerkleCommitment,
     FeeMerkleTree, Leaf2, NodeState, SeqTypes,
 };
 use futures::future::{Future, FutureExt, TryFuture, TryFutureExt};
+use hotshot_query_service::availability::LeafQueryData;
 use hotshot_types::{
     data::ViewNumber,
     network::NetworkConfig,
@@ -33,7 +36,10 @@ use tokio::time::timeout;
 use url::Url;
 use vbs::version::StaticVersionType;
 
-use crate::api::BlocksFrontier;
+use crate::{
+    api::BlocksFrontier,
+    state_sync::{StateDiff, StateSnapshotInfo},
+};
 
 // This newtype is probably not worth having. It's only used to be able to log
 // URLs before doing requests.
@@ -243,6 +249,40 @@ impl<ApiVer: StaticVersionType> StatePeers<ApiVer> {
             })
             .await
     }
+
+    /// Try to fetch the number of blocks in the chain, failing without retrying if unable.
+    pub(crate) async fn try_fetch_block_height(&self, retry: usize) -> anyhow::Result<u64> {
+        self.fetch(retry, |client| async move {
+            let height = client.get::<u64>("status/block-height").send().await?;
+            anyhow::Ok(height)
+        })
+        .await
+    }
+
+    /// Try to fetch the leaves at the heights in `range`, each with the QC certifying it, failing
+    /// without retrying if unable.
+    ///
+    /// The leaves are not verified.
+    pub(crate) async fn try_fetch_leaf_range(
+        &self,
+        retry: usize,
+        range: Range<u64>,
+    ) -> anyhow::Result<Vec<LeafQueryData<SeqTypes>>> {
+        self.fetch(retry, |client| {
+            let range = range.clone();
+            async move {
+                let leaves = client
+                    .get::<Vec<LeafQueryData<SeqTypes>>>(&format!(
+                        "availability/leaf/{}/{}",
+                        range.start, range.end
+                    ))
+                    .send()
+                    .await?;
+                anyhow::Ok(leaves)
+            }
+        })
+        .await
+    }
 }
 
 #[async_trait]
@@ -465,6 +505,37 @@ pub(crate) trait CatchupStorage: Sync {
             bail!("leaf chain catchup is not supported for this data source");
         }
     }
+
+    /// Get the height and size of the most recent merklized state snapshot.
+    fn get_state_snapshot_info(
+        &self,
+    ) -> impl Send + Future<Output = anyhow::Result<StateSnapshotInfo>> {
+        async {
+            bail!("state snapshots are not supported for this data source");
+        }
+    }
+
+    /// Get one chunk of the merklized state snapshot at `height`.
+    fn get_state_snapshot_chunk(
+        &self,
+        _height: u64,
+        _chunk: u64,
+    ) -> impl Send + Future<Output = anyhow::Result<Vec<u8>>> {
+        async {
+            bail!("state snapshots are not supported for this data source");
+        }
+    }
+
+    /// Get the changes to the merklized state made by the blocks in `from..=to`.
+    fn get_state_diffs(
+        &self,
+        _from: u64,
+        _to: u64,
+    ) -> impl Send + Future<Output = anyhow::Result<Vec<StateDiff>>> {
+        async {
+            bail!("state snapshots are not supported for this data source");
+        }
+    }
 }
 
 impl CatchupStorage for hotshot_query_service::data_source::MetricsDataSource {}
@@ -516,6 +587,18 @@ where
     async fn get_leaf_chain(&self, height: u64) -> anyhow::Result<Vec<Leaf2>> {
         self.inner().get_leaf_chain(height).await
     }
+
+    async fn get_state_snapshot_info(&self) -> anyhow::Result<StateSnapshotInfo> {
+        self.inner().get_state_snapshot_info().await
+    }
+
+    async fn get_state_snapshot_chunk(&self, height: u64, chunk: u64) -> anyhow::Result<Vec<u8>> {
+        self.inner().get_state_snapshot_chunk(height, chunk).await
+    }
+
+    async fn get_state_diffs(&self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>> {
+        self.inner().get_state_diffs(from, to).await
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,403 @@
+//! Re-export the metrics of the CDN through the metrics of the node.
+//!
+//! CDN brokers and marshals serve their own Prometheus metrics (connected clients, message rates,
+//! whitelist rejections, ...). The bridge periodically scrapes those endpoints and mirrors every
+//! sample in the `cdn` subgroup of the node's metrics, labelled with the endpoint it came from, so
+//! that operators can monitor the CDN from the node's metrics endpoint. Histograms are mirrored as
+//! histograms with the same buckets, and every other sample as a gauge.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use anyhow::Context;
+use hotshot_types::traits::metrics::{Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics};
+use tokio::time::sleep;
+use url::Url;
+
+/// Label added to every re-exported sample, holding the URL it was scraped from
+const ENDPOINT_LABEL: &str = "endpoint";
+
+/// Label holding the upper bound of a histogram bucket
+const BUCKET_LABEL: &str = "le";
+
+/// A sample from the Prometheus text exposition format
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sample {
+    pub(crate) name: String,
+    pub(crate) labels: Vec<(String, String)>,
+    pub(crate) value: f64,
+}
+
+/// Parse the samples out of a page in the Prometheus text exposition format.
+///
+/// Comments, and lines which cannot be parsed, are skipped.
+pub(crate) fn parse_samples(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(parse_sample)
+        .collect()
+}
+
+/// Parse the names of the histograms declared in a page in the Prometheus text exposition format,
+/// by lines of the form `# TYPE name histogram`.
+pub(crate) fn parse_histograms(text: &str) -> HashSet<String> {
+    text.lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["#", "TYPE", name, "histogram"] => Some(name.to_string()),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// The histogram in `histograms` which the series `name` is part of, if any
+fn histogram_of<'a>(name: &'a str, histograms: &HashSet<String>) -> Option<&'a str> {
+    ["_bucket", "_sum", "_count"]
+        .into_iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .filter(|base| histograms.contains(*base))
+}
+
+/// Parse a line of the form `name{label="value",...} value [timestamp]`
+fn parse_sample(line: &str) -> Option<Sample> {
+    let (series, rest) = match line.find('{') {
+        Some(_) => {
+            let close = line.rfind('}')?;
+            (&line[..close + 1], &line[close + 1..])
+        },
+        None => line.split_at(line.find(char::is_whitespace)?),
+    };
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+
+    let (name, labels) = match series.split_once('{') {
+        Some((name, labels)) => (name, parse_labels(labels.strip_suffix('}')?)?),
+        None => (series, vec![]),
+    };
+
+    Some(Sample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+/// Parse the labels of a sample, of the form `label="value",...`
+fn parse_labels(text: &str) -> Option<Vec<(String, String)>> {
+    let mut labels = vec![];
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let (name, value) = rest.split_once('=')?;
+        let value = value.trim_start().strip_prefix('"')?;
+
+        // Find the closing quote, skipping escaped characters
+        let mut escaped = false;
+        let end = value.char_indices().find_map(|(i, c)| match c {
+            _ if escaped => {
+                escaped = false;
+                None
+            },
+            '\\' => {
+                escaped = true;
+                None
+            },
+            '"' => Some(i),
+            _ => None,
+        })?;
+
+        labels.push((name.trim().to_string(), value[..end].replace("\\\"", "\"")));
+        rest = value[end + 1..].trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+    Some(labels)
+}
+
+/// Periodically mirrors the metrics of CDN brokers and marshals into the node's metrics
+#[derive(Debug)]
+pub struct CdnMetricsBridge {
+    /// Prometheus endpoints of the CDN components
+    endpoints: Vec<Url>,
+    /// Time between scrapes
+    interval: Duration,
+    /// Metrics of the node, under which the CDN metrics are exported
+    metrics: Box<dyn Metrics>,
+    /// The label names and family of each metric name seen so far
+    families: HashMap<String, (Vec<String>, Box<dyn GaugeFamily>)>,
+    /// A gauge for each sample seen so far, by metric name and label values
+    gauges: HashMap<(String, Vec<String>), Box<dyn Gauge>>,
+    /// The label names, finite bucket bounds and family of each histogram name seen so far
+    histogram_families: HashMap<String, (Vec<String>, Vec<f64>, Box<dyn HistogramFamily>)>,
+    /// A histogram for each series seen so far, by histogram name and label values, with the
+    /// number of observations last scraped in each of its buckets
+    histograms: HashMap<(String, Vec<String>), (Box<dyn Histogram>, Vec<u64>)>,
+}
+
+impl CdnMetricsBridge {
+    pub fn new(endpoints: Vec<Url>, interval: Duration, metrics: &dyn Metrics) -> Self {
+        Self {
+            endpoints,
+            interval,
+            metrics: metrics.subgroup("cdn".into()),
+            families: HashMap::new(),
+            gauges: HashMap::new(),
+            histogram_families: HashMap::new(),
+            histograms: HashMap::new(),
+        }
+    }
+
+    /// Scrape the CDN endpoints forever
+    pub async fn run(mut self) {
+        let client = reqwest::Client::new();
+        loop {
+            for endpoint in self.endpoints.clone() {
+                match scrape(&client, &endpoint).await {
+                    Ok((samples, histograms)) => self.export(&endpoint, samples, &histograms),
+                    Err(err) => {
+                        tracing::warn!(%endpoint, "failed to scrape CDN metrics: {err:#}");
+                    },
+                }
+            }
+            sleep(self.interval).await;
+        }
+    }
+
+    /// Mirror the samples scraped from `endpoint` into the node's metrics.
+    ///
+    /// Since gauges hold integers, values are rounded down, and negative values are exported as 0.
+    /// Samples of the `histograms` are exported through
+    /// [`export_histogram`](Self::export_histogram).
+    fn export(&mut self, endpoint: &Url, samples: Vec<Sample>, histograms: &HashSet<String>) {
+        // The cumulative bucket counts of each histogram series, by histogram name and labels.
+        let mut buckets: HashMap<(String, Vec<(String, String)>), Vec<(f64, f64)>> = HashMap::new();
+
+        for sample in samples {
+            if let Some(histogram) = histogram_of(&sample.name, histograms) {
+                // The sum and count of a histogram follow from its buckets.
+                if sample.name.ends_with("_bucket") {
+                    let (bound, labels): (Vec<_>, Vec<_>) = sample
+                        .labels
+                        .into_iter()
+                        .partition(|(name, _)| name == BUCKET_LABEL);
+                    if let Some(bound) = bound.first().and_then(|(_, le)| le.parse().ok()) {
+                        buckets
+                            .entry((histogram.to_string(), labels))
+                            .or_default()
+                            .push((bound, sample.value));
+                    }
+                }
+                continue;
+            }
+
+            let (label_names, label_values): (Vec<_>, Vec<_>) = sample
+                .labels
+                .into_iter()
+                .chain([(ENDPOINT_LABEL.to_string(), endpoint.to_string())])
+                .unzip();
+
+            let (family_labels, family) =
+                self.families.entry(sample.name.clone()).or_insert_with(|| {
+                    let family = self
+                        .metrics
+                        .gauge_family(sample.name.clone(), label_names.clone());
+                    (label_names.clone(), family)
+                });
+            // A family can only be registered once, with a fixed set of labels
+            if *family_labels != label_names {
+                tracing::debug!(
+                    name = sample.name,
+                    "skipping CDN metric with unexpected labels"
+                );
+                continue;
+            }
+
+            let gauge = self
+                .gauges
+                .entry((sample.name, label_values.clone()))
+                .or_insert_with(|| family.create(label_values));
+            gauge.set(sample.value.max(0.0) as usize);
+        }
+
+        for ((name, labels), series) in buckets {
+            self.export_histogram(endpoint, name, labels, series);
+        }
+    }
+
+    /// Mirror a histogram series scraped from `endpoint`, given the cumulative count of each of its
+    /// `buckets` by upper bound.
+    ///
+    /// The observations made since the last scrape are added to the mirrored histogram at the upper
+    /// bound of their bucket, so the mirrored buckets match the scraped ones while the sum is an
+    /// upper bound. Observations above the largest finite bound are added just above it.
+    fn export_histogram(
+        &mut self,
+        endpoint: &Url,
+        name: String,
+        labels: Vec<(String, String)>,
+        mut buckets: Vec<(f64, f64)>,
+    ) {
+        buckets.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        let bounds = buckets
+            .iter()
+            .map(|(bound, _)| *bound)
+            .filter(|bound| bound.is_finite())
+            .collect::<Vec<_>>();
+        let (label_names, label_values): (Vec<_>, Vec<_>) = labels
+            .into_iter()
+            .chain([(ENDPOINT_LABEL.to_string(), endpoint.to_string())])
+            .unzip();
+
+        let (family_labels, family_bounds, family) = self
+            .histogram_families
+            .entry(name.clone())
+            .or_insert_with(|| {
+                let family = self.metrics.histogram_family_with_buckets(
+                    name.clone(),
+                    label_names.clone(),
+                    bounds.clone(),
+                );
+                (label_names.clone(), bounds.clone(), family)
+            });
+        // A family can only be registered once, with fixed labels and buckets
+        if *family_labels != label_names || *family_bounds != bounds {
+            tracing::debug!(
+                name,
+                "skipping CDN histogram with unexpected labels or buckets"
+            );
+            return;
+        }
+
+        let (histogram, seen) = self
+            .histograms
+            .entry((name, label_values.clone()))
+            .or_insert_with(|| (family.create(label_values), vec![0; buckets.len()]));
+        let overflow = bounds
+            .last()
+            .map_or(1.0, |bound| bound + bound.abs().max(1.0));
+        let mut below = 0.0;
+        for ((bound, cumulative), seen) in buckets.into_iter().zip(seen) {
+            let count = (cumulative - below).max(0.0) as u64;
+            below = cumulative;
+            // Counts only go down when the CDN component restarts, and then start from zero.
+            let new = count.checked_sub(*seen).unwrap_or(count);
+            *seen = count;
+
+            let point = if bound.is_finite() { bound } else { overflow };
+            for _ in 0..new {
+                histogram.add_point(point);
+            }
+        }
+    }
+}
+
+/// Fetch and parse the metrics served at `endpoint`, and the names of the histograms among them
+async fn scrape(
+    client: &reqwest::Client,
+    endpoint: &Url,
+) -> anyhow::Result<(Vec<Sample>, HashSet<String>)> {
+    let text = client
+        .get(endpoint.clone())
+        .send()
+        .await
+        .context("request failed")?
+        .error_for_status()?
+        .text()
+        .await
+        .context("failed to read response")?;
+    Ok((parse_samples(&text), parse_histograms(&text)))
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_query_service::metrics::PrometheusMetrics;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_samples() {
+        let text = r#"
+# HELP num_users_connected The number of users connected
+# TYPE num_users_connected gauge
+num_users_connected 12
+messages_sent{topic="global",kind="broadcast"} 1027 1712345678000
+whitelist_rejections{reason="not \"staked\""} 3
+malformed{topic="global" 1
+latency_seconds_sum 0.25
+"#;
+
+        assert_eq!(
+            parse_samples(text),
+            vec![
+                Sample {
+                    name: "num_users_connected".into(),
+                    labels: vec![],
+                    value: 12.0,
+                },
+                Sample {
+                    name: "messages_sent".into(),
+                    labels: vec![
+                        ("topic".into(), "global".into()),
+                        ("kind".into(), "broadcast".into()),
+                    ],
+                    value: 1027.0,
+                },
+                Sample {
+                    name: "whitelist_rejections".into(),
+                    labels: vec![("reason".into(), "not \"staked\"".into())],
+                    value: 3.0,
+                },
+                Sample {
+                    name: "latency_seconds_sum".into(),
+                    labels: vec![],
+                    value: 0.25,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_histogram() {
+        let text = r#"
+# TYPE latency_seconds histogram
+latency_seconds_bucket{topic="global",le="0.1"} 2
+latency_seconds_bucket{topic="global",le="1"} 3
+latency_seconds_bucket{topic="global",le="+Inf"} 4
+latency_seconds_sum{topic="global"} 7.5
+latency_seconds_count{topic="global"} 4
+# TYPE num_users_connected gauge
+num_users_connected 12
+"#;
+        let endpoint: Url = "http://broker:9090/metrics".parse().unwrap();
+        let metrics = PrometheusMetrics::default();
+        let mut bridge = CdnMetricsBridge::new(vec![], Duration::from_secs(1), &metrics);
+
+        let histograms = parse_histograms(text);
+        assert_eq!(histograms, HashSet::from(["latency_seconds".to_string()]));
+        bridge.export(&endpoint, parse_samples(text), &histograms);
+
+        let cdn = metrics.get_subgroup(["cdn"]).unwrap();
+        let labels = ["global", endpoint.as_str()];
+        let histogram = cdn
+            .get_histogram_family("latency_seconds")
+            .unwrap()
+            .get(&labels);
+        assert_eq!(histogram.sample_count(), 4);
+        assert_eq!(histogram.sum(), 2.0 * 0.1 + 1.0 + 2.0);
+        assert_eq!(
+            cdn.gauge_family("num_users_connected")
+                .unwrap()
+                .get(&[endpoint.as_str()])
+                .get(),
+            12
+        );
+        // The parts of the histogram are not exported as gauges.
+        assert!(cdn.gauge_family("latency_seconds_count").is_err());
+
+        // Only the observations made since the last scrape are added.
+        let text = text.replace("\"+Inf\"} 4", "\"+Inf\"} 5");
+        bridge.export(&endpoint, parse_samples(&text), &histograms);
+        assert_eq!(histogram.sample_count(), 5);
+    }
+}
@@ -9,7 +9,7 @@ use async_lock::RwLock;
 use derivative::Derivative;
 use espresso_types::{
     v0::traits::{EventConsumer as PersistenceEventConsumer, SequencerPersistence},
-    NodeState, PubKey, Transaction, ValidatedState,
+    KeyShare, NodeState, PubKey, ThresholdEncryptionKey, Transaction, ValidatedState,
 };
 use futures::{
     future::{join_all, Future},
@@ -27,6 +27,7 @@ use hotshot_types::{
     epoch_membership::EpochMembershipCoordinator,
     light_client::compute_stake_table_commitment,
     network::NetworkConfig,
+    signer::Signer,
     traits::{metrics::Metrics, network::ConnectedNetwork, node_implementation::Versions},
     PeerConfig, ValidatorConfig,
 };
@@ -41,14 +42,17 @@ use tracing::{Instrument, Level};
 use url::Url;
 
 use crate::{
+    encryption::Decryptor,
     external_event_handler::ExternalEventHandler,
     proposal_fetcher::ProposalFetcherConfig,
+    remote_signer::RemoteSigner,
     request_response::{
         data_source::DataSource, network::Sender as RequestResponseSender,
         recipient_source::RecipientSource, request::Request,
     },
     state_signature::StateSigner,
-    Node, SeqTypes, SequencerApiVersion,
+    transaction_status::TransactionStatusTracker,
+    upgrade_readiness, Node, SeqTypes, SequencerApiVersion,
 };
 
 /// The consensus handle
@@ -95,6 +99,12 @@ pub struct SequencerContext<N: ConnectedNetwork<PubKey>, P: SequencerPersistence
 
     #[derivative(Debug = "ignore")]
     validator_config: ValidatorConfig<SeqTypes>,
+
+    /// Decrypts encrypted transactions, if an encryption key is configured
+    decryptor: Option<Arc<Decryptor>>,
+
+    /// Tracks the progress of recent transactions through consensus
+    transaction_status: Arc<TransactionStatusTracker>,
 }
 
 impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> SequencerContext<N, P, V> {
@@ -108,6 +118,7 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
         persistence: P,
         network: Arc<N>,
         state_relay_server: Option<Url>,
+        remote_signer: Option<Arc<RemoteSigner<SequencerApiVersion>>>,
         metrics: &dyn Metrics,
         stake_table_capacity: u64,
         event_consumer: impl PersistenceEventConsumer + 'static,
@@ -148,9 +159,17 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
         let persistence = Arc::new(persistence);
         let membership = coordinator.membership().clone();
 
+        // Sign consensus messages through the remote signer if it holds our consensus key, which
+        // it no longer does once this node has rotated to new keys.
+        let signer = match &remote_signer {
+            Some(signer) if signer.consensus_key() == validator_config.public_key => {
+                Signer::Remote(signer.clone())
+            },
+            _ => Signer::Local(validator_config.private_key.clone()),
+        };
         let handle = SystemContext::init(
             validator_config.public_key,
-            validator_config.private_key.clone(),
+            signer,
             validator_config.state_private_key.clone(),
             instance_state.node_id,
             config.clone(),
@@ -174,6 +193,12 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
         if let Some(url) = state_relay_server {
             state_signer = state_signer.with_relay_server(url);
         }
+        // Likewise for light client states.
+        if let Some(signer) =
+            remote_signer.filter(|signer| signer.state_key() == validator_config.state_public_key)
+        {
+            state_signer = state_signer.with_remote_signer(signer);
+        }
 
         // Create the channel for sending outbound messages from the external event handler
         let (outbound_message_sender, outbound_message_receiver) = channel(10);
@@ -257,6 +282,8 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
         metrics: &dyn Metrics,
     ) -> Self {
         let events = handle.event_stream();
+        let status_events = handle.event_stream();
+        let readiness_events = handle.event_stream();
 
         let node_id = node_state.node_id;
         let mut ctx = Self {
@@ -270,8 +297,33 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
             node_state,
             network_config,
             validator_config,
+            decryptor: None,
+            transaction_status: Default::default(),
         };
 
+        // Spawn transaction status tracking.
+        ctx.spawn(
+            "transaction status tracker",
+            ctx.transaction_status.clone().run(status_events),
+        );
+
+        // Spawn reporting of supported versions, for upgrade readiness checks.
+        ctx.spawn(
+            "upgrade readiness",
+            upgrade_readiness::run(
+                readiness_events,
+                ctx.handle.clone(),
+                ctx.validator_config.private_key.clone(),
+                metrics.subgroup("upgrade_readiness".into()),
+            ),
+        );
+
+        // Spawn rollback of state scanned from L1 blocks which are replaced by reorgs.
+        ctx.spawn(
+            "L1 reorg rollback",
+            ctx.node_state.clone().roll_back_l1_reorgs(),
+        );
+
         // Spawn proposal fetching tasks.
         proposal_fetcher_cfg.spawn(
             &mut ctx.tasks,
@@ -305,6 +357,37 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, V: Versions> Sequence
         self
     }
 
+    /// Decrypt transactions encrypted to `key` once they are decided, contributing decryption shares
+    /// with `key_share` if this node holds one.
+    pub async fn enable_decryption(
+        &mut self,
+        key: ThresholdEncryptionKey,
+        key_share: Option<KeyShare>,
+    ) {
+        let decryptor = Arc::new(Decryptor::new(
+            key,
+            key_share,
+            self.validator_config.public_key,
+            self.validator_config.private_key.clone(),
+        ));
+        let events = self.event_stream().await;
+        self.spawn(
+            "decryptor",
+            Arc::clone(&decryptor).run(events, self.consensus()),
+        );
+        self.decryptor = Some(decryptor);
+    }
+
+    /// The decryptor of encrypted transactions, if decryption is enabled
+    pub fn decryptor(&self) -> Option<Arc<Decryptor>> {
+        self.decryptor.clone()
+    }
+
+    /// The tracker of transactions through consensus
+    pub fn transaction_status(&self) -> Arc<TransactionStatusTracker> {
+        self.transaction_status.clone()
+    }
+
     /// Add a list of tasks to the given context.
     pub(crate) fn with_task_list(mut self, tasks: TaskList) -> Self {
         self.tasks.extend(tasks);
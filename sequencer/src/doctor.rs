@@ -0,0 +1,498 @@
+//! Checking the configuration of a node before it joins consensus.
+//!
+//! `sequencer config doctor` takes exactly the options the node itself would run with, checks them
+//! and prints a diagnosis for each check, with a hint on how to fix anything that is wrong. This
+//! catches misconfigurations (unparseable keys, keys missing from the stake table, a genesis file
+//! different from the rest of the network, ports already in use, unreachable services) before the
+//! node starts, rather than from its logs once it fails to make progress. Nothing is changed by the
+//! checks, except that connecting to an empty database creates its schema.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    future::Future,
+    net::SocketAddr,
+    time::Duration,
+};
+
+use alloy::providers::{Provider, ProviderBuilder};
+use anyhow::{bail, Context};
+use espresso_types::{v0_4::ResolvableChainConfig, Header, PubKey, SeqTypes};
+use hotshot_query_service::data_source::storage::sql::{Config, SqlStorage};
+use hotshot_types::{
+    light_client::{StateKeyPair, StateVerKey},
+    traits::signature_key::{SignatureKey, StakeTableEntryType},
+};
+use surf_disco::Client;
+use tide_disco::error::ServerError;
+use tokio::{
+    net::{lookup_host, TcpListener, UdpSocket},
+    time::timeout,
+};
+use url::Url;
+
+use crate::{
+    api::data_source::StakeTableWithEpochNumber,
+    options::{Modules, Options},
+    Genesis, SequencerApiVersion,
+};
+
+/// How long to wait for any single remote service before declaring it unreachable
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The check passed
+    Ok,
+    /// The node can run, but something looks wrong or could not be checked
+    Warning,
+    /// The node will not work until this is fixed
+    Error,
+}
+
+/// The outcome of a single check
+#[derive(Clone, Debug)]
+pub struct Diagnosis {
+    pub check: String,
+    pub severity: Severity,
+    pub message: String,
+    /// What to do about a failed check
+    pub hint: Option<String>,
+}
+
+/// The outcomes of all checks
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub diagnoses: Vec<Diagnosis>,
+}
+
+impl Report {
+    /// Whether the node is expected to work, ie no check failed with an error.
+    pub fn is_healthy(&self) -> bool {
+        self.diagnoses
+            .iter()
+            .all(|diagnosis| diagnosis.severity != Severity::Error)
+    }
+
+    fn ok(&mut self, check: impl Into<String>, message: impl Into<String>) {
+        self.push(check, Severity::Ok, message, None::<String>);
+    }
+
+    fn warn(
+        &mut self,
+        check: impl Into<String>,
+        message: impl Into<String>,
+        hint: impl Into<String>,
+    ) {
+        self.push(check, Severity::Warning, message, Some(hint));
+    }
+
+    fn error(
+        &mut self,
+        check: impl Into<String>,
+        message: impl Into<String>,
+        hint: impl Into<String>,
+    ) {
+        self.push(check, Severity::Error, message, Some(hint));
+    }
+
+    fn push(
+        &mut self,
+        check: impl Into<String>,
+        severity: Severity,
+        message: impl Into<String>,
+        hint: Option<impl Into<String>>,
+    ) {
+        self.diagnoses.push(Diagnosis {
+            check: check.into(),
+            severity,
+            message: message.into(),
+            hint: hint.map(Into::into),
+        });
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for diagnosis in &self.diagnoses {
+            let tag = match diagnosis.severity {
+                Severity::Ok => "ok",
+                Severity::Warning => "warn",
+                Severity::Error => "FAIL",
+            };
+            writeln!(f, "[{tag:>4}] {}: {}", diagnosis.check, diagnosis.message)?;
+            if let Some(hint) = &diagnosis.hint {
+                writeln!(f, "       hint: {hint}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the configuration in `opt`, print the diagnoses, and fail if any check failed.
+pub async fn run(opt: Options) -> anyhow::Result<()> {
+    let report = diagnose(&opt).await;
+    print!("{report}");
+    if !report.is_healthy() {
+        bail!("configuration has errors");
+    }
+    println!("configuration looks good");
+    Ok(())
+}
+
+/// Run all checks on the configuration in `opt`.
+pub async fn diagnose(opt: &Options) -> Report {
+    let mut report = Report::default();
+    let modules = opt.modules();
+
+    let keys = check_keys(opt, &mut report);
+    let genesis = check_genesis(opt, &mut report);
+    check_ports(opt, &modules, &mut report).await;
+    check_storage(&modules, &mut report).await;
+    check_l1(opt, &mut report).await;
+    check_services(opt, &mut report).await;
+    check_peers(opt, keys.as_ref(), genesis.as_ref(), &mut report).await;
+
+    report
+}
+
+/// Check that the private keys can be loaded and parsed.
+fn check_keys(opt: &Options, report: &mut Report) -> Option<(PubKey, StateVerKey)> {
+    let keys = match opt.private_keys() {
+        Ok((staking, state)) => {
+            let key = PubKey::from_private(&staking);
+            let state_key = StateKeyPair::from_sign_key(state).ver_key();
+            report.ok("keys", format!("staking key {key}, state key {state_key}"));
+            Some((key, state_key))
+        },
+        Err(err) => {
+            report.error(
+                "keys",
+                format!("failed to load private keys: {err:#}"),
+                "provide the keys with --key-file, --keystore and --keystore-passphrase-file, or \
+                 ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY and ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY; \
+                 keys can be generated with the keygen utility",
+            );
+            None
+        },
+    };
+    match opt.next_private_keys() {
+        Ok(Some((staking, _))) => report.ok(
+            "next keys",
+            format!(
+                "will rotate to staking key {}",
+                PubKey::from_private(&staking)
+            ),
+        ),
+        Ok(None) => {},
+        Err(err) => report.error(
+            "next keys",
+            format!("failed to load next private keys: {err:#}"),
+            "check ESPRESSO_SEQUENCER_NEXT_PRIVATE_STAKING_KEY and \
+             ESPRESSO_SEQUENCER_NEXT_PRIVATE_STATE_KEY, or remove them if no key rotation is \
+             planned",
+        ),
+    }
+    keys
+}
+
+/// Check that the genesis file can be read and is well formed.
+fn check_genesis(opt: &Options, report: &mut Report) -> Option<Genesis> {
+    let path = opt.genesis_file.display();
+    let genesis = Genesis::from_file(&opt.genesis_file).and_then(|genesis| {
+        genesis.validate()?;
+        Ok(genesis)
+    });
+    match genesis {
+        Ok(genesis) => {
+            report.ok(
+                "genesis",
+                format!(
+                    "{path}: base version {}, upgrade version {}",
+                    genesis.base_version, genesis.upgrade_version
+                ),
+            );
+            Some(genesis)
+        },
+        Err(err) => {
+            report.error(
+                "genesis",
+                format!("{path}: {err:#}"),
+                "point --genesis-file at the genesis file published for the network you are \
+                 joining",
+            );
+            None
+        },
+    }
+}
+
+/// Check that the ports the node listens on are free.
+async fn check_ports(opt: &Options, modules: &Modules, report: &mut Report) {
+    // Libp2p runs over QUIC, so its port is a UDP port.
+    let check = "libp2p port";
+    match lookup_host(&opt.libp2p_bind_address)
+        .await
+        .map(|mut addrs| addrs.next())
+    {
+        Ok(Some(addr)) => match UdpSocket::bind(addr).await {
+            Ok(_) => report.ok(check, format!("UDP {addr} is free")),
+            Err(err) => report.error(
+                check,
+                format!("cannot bind UDP {addr}: {err}"),
+                "stop the process using the port or change --libp2p-bind-address",
+            ),
+        },
+        Ok(None) | Err(_) => report.error(
+            check,
+            format!("invalid bind address {}", opt.libp2p_bind_address),
+            "--libp2p-bind-address must be in host:port form",
+        ),
+    }
+
+    if let Some(http) = &modules.http {
+        let addr = SocketAddr::from(([0, 0, 0, 0], http.port));
+        match TcpListener::bind(addr).await {
+            Ok(_) => report.ok("http port", format!("TCP {addr} is free")),
+            Err(err) => report.error(
+                "http port",
+                format!("cannot bind TCP {addr}: {err}"),
+                "stop the process using the port or change the port of the http module",
+            ),
+        }
+    }
+}
+
+/// Check that the storage the node is configured with can be used.
+async fn check_storage(modules: &Modules, report: &mut Report) {
+    if let Some(sql) = &modules.storage_sql {
+        let res = async {
+            let config = Config::try_from(sql)?;
+            with_timeout(async { Ok(SqlStorage::plan_migrations(config).await?) }).await
+        }
+        .await;
+        match res {
+            Ok(plan) if plan.is_up_to_date() => {
+                report.ok("database", "connected, schema is current")
+            },
+            Ok(plan) => report.ok(
+                "database",
+                format!("connected, migrations will run on startup:\n{plan}"),
+            ),
+            Err(err) => report.error(
+                "database",
+                format!("cannot connect: {err:#}"),
+                "check the options of the storage-sql module and that the database is running",
+            ),
+        }
+    } else if let Some(fs) = &modules.storage_fs {
+        let path = fs.path();
+        match path.metadata() {
+            Ok(metadata) if metadata.is_dir() && !metadata.permissions().readonly() => report.ok(
+                "storage",
+                format!("{} is a writable directory", path.display()),
+            ),
+            Ok(_) => report.error(
+                "storage",
+                format!("{} is not a writable directory", path.display()),
+                "point the storage-fs module at a writable directory",
+            ),
+            Err(_) => report.warn(
+                "storage",
+                format!("{} does not exist", path.display()),
+                "it will be created on startup; if this node has run before, the path is probably \
+                 wrong and the node will start from scratch",
+            ),
+        }
+    } else if modules.storage_embedded.is_none() {
+        report.warn(
+            "storage",
+            "no storage module given, using file system storage in the default location",
+            "add a storage module (storage-fs, storage-sql or storage-embedded) to keep state in a \
+             known place",
+        );
+    }
+}
+
+/// Check that the L1 providers respond.
+async fn check_l1(opt: &Options, report: &mut Report) {
+    for url in &opt.l1_provider_url {
+        let provider = ProviderBuilder::new().on_http(url.clone());
+        match with_timeout(async { Ok(provider.get_block_number().await?) }).await {
+            Ok(block) => report.ok("L1 provider", format!("{url} is at block {block}")),
+            Err(err) => report.error(
+                "L1 provider",
+                format!("{url}: {err:#}"),
+                "check --l1-provider-url and that the provider accepts requests from this host",
+            ),
+        }
+    }
+}
+
+/// Check that the auxiliary services the node talks to are reachable.
+async fn check_services(opt: &Options, report: &mut Report) {
+    if opt.bootstrap_document.is_none() {
+        check_reachable(
+            "orchestrator",
+            &opt.orchestrator_url,
+            "check --orchestrator-url, or join without an orchestrator using a bootstrap document",
+            report,
+        )
+        .await;
+    }
+    check_reachable(
+        "state relay server",
+        &opt.state_relay_server_url,
+        "check --state-relay-server-url; the node runs without it, but its light client state \
+         signatures will be lost",
+        report,
+    )
+    .await;
+}
+
+/// Check that `url` answers HTTP requests, with any status.
+async fn check_reachable(check: &str, url: &Url, hint: &str, report: &mut Report) {
+    let res = with_timeout(async { Ok(reqwest::get(url.clone()).await?) }).await;
+    match res {
+        Ok(_) => report.ok(check, format!("{url} is reachable")),
+        Err(err) => report.error(check, format!("{url}: {err:#}"), hint),
+    }
+}
+
+/// Check the node's keys and genesis against its peers.
+async fn check_peers(
+    opt: &Options,
+    keys: Option<&(PubKey, StateVerKey)>,
+    genesis: Option<&Genesis>,
+    report: &mut Report,
+) {
+    if opt.state_peers.is_empty() {
+        report.warn(
+            "peers",
+            "no state peers given, cannot compare configuration with the network",
+            "set --state-peers to the query services of other nodes; the node needs them to catch \
+             up",
+        );
+        return;
+    }
+
+    for peer in &opt.state_peers {
+        let client = Client::<ServerError, SequencerApiVersion>::new(peer.clone());
+
+        if let Some(genesis) = genesis {
+            let check = format!("genesis ({peer})");
+            match with_timeout(async {
+                Ok(client.get::<Header>("availability/header/0").send().await?)
+            })
+            .await
+            {
+                Ok(header) => {
+                    let mismatches = genesis_mismatches(genesis, &header);
+                    if mismatches.is_empty() {
+                        report.ok(check, "matches the peer's genesis block");
+                    } else {
+                        report.error(
+                            check,
+                            format!(
+                                "differs from the peer's genesis block in {}",
+                                mismatches.join(", ")
+                            ),
+                            "make sure the genesis file is the one published for this network",
+                        );
+                    }
+                },
+                Err(err) => report.warn(
+                    check,
+                    format!("cannot fetch the peer's genesis block: {err:#}"),
+                    "check --state-peers; the peer must run the query module",
+                ),
+            }
+        }
+
+        if let Some((key, state_key)) = keys {
+            let check = format!("stake table ({peer})");
+            match with_timeout(async {
+                Ok(client
+                    .get::<StakeTableWithEpochNumber<SeqTypes>>("node/stake-table/current")
+                    .send()
+                    .await?)
+            })
+            .await
+            {
+                Ok(stake_table) => {
+                    match stake_table
+                        .stake_table
+                        .iter()
+                        .find(|entry| entry.stake_table_entry.public_key() == *key)
+                    {
+                        Some(entry) if entry.state_ver_key == *state_key => report.ok(
+                            check,
+                            format!(
+                                "staked with {} in epoch {:?}",
+                                entry.stake_table_entry.stake(),
+                                stake_table.epoch
+                            ),
+                        ),
+                        Some(_) => report.error(
+                            check,
+                            "the registered state key does not match the private state key",
+                            "use the state key registered with the stake table, or update the \
+                             registration",
+                        ),
+                        None => report.warn(
+                            check,
+                            format!(
+                                "{key} is not in the stake table of epoch {:?}",
+                                stake_table.epoch
+                            ),
+                            "register the staking key with the stake table contract; the node \
+                             will only follow the chain until it is staked",
+                        ),
+                    }
+                },
+                Err(err) => report.warn(
+                    check,
+                    format!("cannot fetch the peer's stake table: {err:#}"),
+                    "check --state-peers; the peer must run the node API",
+                ),
+            }
+        }
+    }
+}
+
+/// The parts of the genesis block implied by `genesis` which differ from `header`.
+fn genesis_mismatches(genesis: &Genesis, header: &Header) -> Vec<&'static str> {
+    let mut mismatches = vec![];
+    if header.version() != genesis.base_version {
+        mismatches.push("version");
+    }
+    if header.chain_config().commit() != ResolvableChainConfig::from(genesis.chain_config).commit()
+    {
+        mismatches.push("chain config");
+    }
+    if header.timestamp() != genesis.header.timestamp.unix_timestamp() {
+        mismatches.push("timestamp");
+    }
+    mismatches
+}
+
+async fn with_timeout<T>(f: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    timeout(CHECK_TIMEOUT, f).await.context("timed out")?
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_report_health() {
+        let mut report = Report::default();
+        report.ok("keys", "loaded");
+        report.warn("peers", "none given", "set --state-peers");
+        assert!(report.is_healthy());
+
+        report.error("genesis", "missing", "set --genesis-file");
+        assert!(!report.is_healthy());
+        let text = report.to_string();
+        assert!(text.contains("[  ok] keys: loaded"));
+        assert!(text.contains("[FAIL] genesis: missing\n       hint: set --genesis-file"));
+    }
+}
@@ -0,0 +1,459 @@
+//! Decryption of encrypted transactions once they have been sequenced.
+//!
+//! When the genesis file configures a threshold encryption key, every node follows the decided
+//! chain looking for encrypted transactions. A node holding a share of the key broadcasts its
+//! decryption shares for each valid one, signed with its staking key, and every node collects the
+//! shares broadcast by the holders of the other key shares until it has enough to decrypt.
+//! Decrypted transactions are kept for a while, to be served by the submit API.
+//!
+//! Shares can arrive before the transaction they decrypt has been decided locally, when they cannot
+//! be verified yet. Each holder gets a bounded buffer of its own for such shares, so that no node
+//! can crowd out the shares of the others.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Context;
+use async_lock::RwLock;
+use committable::{Commitment, Committable};
+use espresso_types::{
+    v0::traits::SequencerPersistence, DecryptionShare, EncryptedPayload, KeyShare, NamespaceId,
+    PrivKey, PubKey, SeqTypes, ThresholdEncryptionKey, Transaction,
+};
+use futures::stream::{Stream, StreamExt};
+use hotshot::types::{Event, EventType};
+use hotshot_types::{
+    event::LeafInfo,
+    message::RecipientList,
+    traits::{
+        block_contents::BlockHeader, network::ConnectedNetwork, node_implementation::Versions,
+        signature_key::SignatureKey, BlockPayload,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{bounded_map::BoundedMap, context::Consensus, external_event_handler::ExternalMessage};
+
+/// Maximum number of encrypted transactions awaiting decryption shares
+const MAX_PENDING: usize = 10_000;
+
+/// Maximum number of decrypted transactions kept
+const MAX_DECRYPTED: usize = 10_000;
+
+/// Maximum number of shares kept from each holder for transactions not decided locally yet
+const MAX_EARLY_SHARES_PER_HOLDER: usize = 1_000;
+
+/// Decryption shares for a batch of encrypted transactions, identified by their commitments
+pub type DecryptionShares = Vec<(Commitment<Transaction>, DecryptionShare)>;
+
+/// Decryption shares broadcast by the holder of a key share, signed with its staking key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedDecryptionShares {
+    pub holder: PubKey,
+    pub shares: DecryptionShares,
+    pub signature: <PubKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl SignedDecryptionShares {
+    fn sign(
+        holder: PubKey,
+        private_key: &PrivKey,
+        shares: DecryptionShares,
+    ) -> anyhow::Result<Self> {
+        let bytes = bincode::serialize(&shares)?;
+        let signature = PubKey::sign(private_key, &bytes).context("signing decryption shares")?;
+        Ok(Self {
+            holder,
+            shares,
+            signature,
+        })
+    }
+
+    fn verify(&self) -> bool {
+        bincode::serialize(&self.shares)
+            .is_ok_and(|bytes| self.holder.validate(&self.signature, &bytes))
+    }
+}
+
+/// Decrypts sequenced transactions with the committee's threshold encryption key
+#[derive(Debug)]
+pub struct Decryptor {
+    key: ThresholdEncryptionKey,
+    key_share: Option<KeyShare>,
+    public_key: PubKey,
+    private_key: PrivKey,
+    state: RwLock<DecryptionState>,
+}
+
+#[derive(Debug)]
+struct DecryptionState {
+    /// Decided encrypted transactions which have not been decrypted yet, with shares received for
+    /// them
+    pending: BoundedMap<Commitment<Transaction>, Pending>,
+    /// Shares for transactions which have not been decided locally yet, by key share index
+    early: HashMap<u32, BoundedMap<Commitment<Transaction>, DecryptionShare>>,
+    /// Decrypted transactions, by the commitment of the encrypted transaction
+    decrypted: BoundedMap<Commitment<Transaction>, Transaction>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    namespace: NamespaceId,
+    transaction: EncryptedPayload,
+    /// Valid shares, by index
+    shares: HashMap<u32, DecryptionShare>,
+}
+
+impl Decryptor {
+    /// Decrypt transactions encrypted to `key`, broadcasting decryption shares with `key_share` if
+    /// this node holds one, signed with its staking key.
+    pub fn new(
+        key: ThresholdEncryptionKey,
+        key_share: Option<KeyShare>,
+        public_key: PubKey,
+        private_key: PrivKey,
+    ) -> Self {
+        if let Some(key_share) = &key_share {
+            if key.holder_index(&public_key) != Some(key_share.index()) {
+                tracing::warn!(
+                    index = key_share.index(),
+                    "encryption key share is not registered to this node, other nodes will ignore \
+                     its decryption shares"
+                );
+            }
+        }
+        Self {
+            key,
+            key_share,
+            public_key,
+            private_key,
+            state: RwLock::new(DecryptionState {
+                pending: BoundedMap::new(MAX_PENDING),
+                early: HashMap::new(),
+                decrypted: BoundedMap::new(MAX_DECRYPTED),
+            }),
+        }
+    }
+
+    /// The key transactions are encrypted to
+    pub fn key(&self) -> &ThresholdEncryptionKey {
+        &self.key
+    }
+
+    /// The decryption of the encrypted transaction with commitment `hash`, if it is available
+    pub async fn decrypted(&self, hash: Commitment<Transaction>) -> Option<Transaction> {
+        self.state.read().await.decrypted.get(&hash).cloned()
+    }
+
+    /// Follow consensus events, exchanging decryption shares for decided encrypted transactions.
+    pub async fn run<N, P, V>(
+        self: Arc<Self>,
+        mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+        consensus: Arc<RwLock<Consensus<N, P, V>>>,
+    ) where
+        N: ConnectedNetwork<PubKey>,
+        P: SequencerPersistence,
+        V: Versions,
+    {
+        while let Some(event) = events.next().await {
+            match event.event {
+                EventType::Decide { leaf_chain, .. } => {
+                    let shares = self.handle_decide(&leaf_chain).await;
+                    if shares.is_empty() {
+                        continue;
+                    }
+                    let message =
+                        SignedDecryptionShares::sign(self.public_key, &self.private_key, shares)
+                            .and_then(|shares| {
+                                Ok(bincode::serialize(&ExternalMessage::DecryptionShares(
+                                    shares,
+                                ))?)
+                            });
+                    let message = match message {
+                        Ok(message) => message,
+                        Err(err) => {
+                            tracing::warn!("failed to sign decryption shares: {err:#}");
+                            continue;
+                        },
+                    };
+                    if let Err(err) = consensus
+                        .read()
+                        .await
+                        .send_external_message(message, RecipientList::Broadcast)
+                        .await
+                    {
+                        tracing::warn!("failed to broadcast decryption shares: {err:#}");
+                    }
+                },
+                EventType::ExternalMessageReceived { data, .. } => {
+                    if let Ok(ExternalMessage::DecryptionShares(shares)) =
+                        bincode::deserialize(&data)
+                    {
+                        self.add_shares(shares).await;
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// Start decrypting the encrypted transactions in newly decided leaves.
+    ///
+    /// Returns this node's decryption shares for them, to broadcast to the other nodes.
+    async fn handle_decide(&self, leaf_chain: &[LeafInfo<SeqTypes>]) -> DecryptionShares {
+        let mut own_shares = vec![];
+        let mut state = self.state.write().await;
+        for LeafInfo { leaf, .. } in leaf_chain.iter().rev() {
+            let Some(payload) = leaf.block_payload() else {
+                continue;
+            };
+            let ns_table = leaf.block_header().metadata();
+            for tx in payload.transactions(ns_table) {
+                let Some(encrypted) = EncryptedPayload::decode(tx.payload()) else {
+                    continue;
+                };
+                if let Some(share) = self.track(&mut state, tx.commit(), tx.namespace(), encrypted)
+                {
+                    own_shares.push((tx.commit(), share));
+                }
+            }
+        }
+        own_shares
+    }
+
+    /// Start decrypting a decided encrypted transaction.
+    ///
+    /// Returns this node's decryption share for it, unless it is already being decrypted or is not
+    /// a valid ciphertext.
+    fn track(
+        &self,
+        state: &mut DecryptionState,
+        hash: Commitment<Transaction>,
+        namespace: NamespaceId,
+        encrypted: EncryptedPayload,
+    ) -> Option<DecryptionShare> {
+        if state.decrypted.get(&hash).is_some() || state.pending.get(&hash).is_some() {
+            return None;
+        }
+        if !encrypted.verify(namespace) {
+            tracing::info!(%hash, "encrypted transaction has an invalid ciphertext");
+            for early in state.early.values_mut() {
+                early.remove(&hash);
+            }
+            return None;
+        }
+
+        let mut shares = HashMap::new();
+        for early in state.early.values_mut() {
+            if let Some(share) = early.remove(&hash) {
+                if self.key.verify_share(&encrypted, &share) {
+                    shares.insert(share.index, share);
+                }
+            }
+        }
+        let own_share = self.key_share.as_ref().and_then(|key_share| {
+            let share =
+                key_share.decryption_share(namespace, &encrypted, &mut rand::thread_rng())?;
+            shares.insert(share.index, share);
+            Some(share)
+        });
+        state.pending.insert(
+            hash,
+            Pending {
+                namespace,
+                transaction: encrypted,
+                shares,
+            },
+        );
+        self.try_decrypt(state, hash);
+        own_share
+    }
+
+    /// Add decryption shares broadcast by another node
+    async fn add_shares(&self, signed: SignedDecryptionShares) {
+        // Only the holder of a key share may send shares computed with it.
+        let Some(index) = self.key.holder_index(&signed.holder) else {
+            tracing::debug!(holder = %signed.holder, "decryption shares from a non-holder");
+            return;
+        };
+        if !signed.verify() {
+            tracing::debug!(holder = %signed.holder, "invalid signature on decryption shares");
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        for (hash, share) in signed.shares {
+            if share.index != index {
+                tracing::debug!(%hash, index = share.index, "share for another key share");
+                continue;
+            }
+            if state.decrypted.get(&hash).is_some() {
+                continue;
+            }
+            let Some(pending) = state.pending.get_mut(&hash) else {
+                // The transaction has not been decided here yet, so the share cannot be verified.
+                state
+                    .early
+                    .entry(index)
+                    .or_insert_with(|| BoundedMap::new(MAX_EARLY_SHARES_PER_HOLDER))
+                    .insert(hash, share);
+                continue;
+            };
+            if !self.key.verify_share(&pending.transaction, &share) {
+                tracing::debug!(%hash, index, "invalid decryption share");
+                continue;
+            }
+            pending.shares.insert(index, share);
+            self.try_decrypt(&mut state, hash);
+        }
+    }
+
+    /// Decrypt the transaction with commitment `hash`, if enough valid shares have been collected
+    fn try_decrypt(&self, state: &mut DecryptionState, hash: Commitment<Transaction>) {
+        let Some(Pending {
+            namespace,
+            transaction,
+            shares,
+        }) = state.pending.get(&hash)
+        else {
+            return;
+        };
+        if shares.len() < self.key.threshold {
+            return;
+        }
+
+        let namespace = *namespace;
+        let shares = shares.values().copied().collect::<Vec<_>>();
+        match self.key.decrypt(namespace, transaction, &shares) {
+            Ok(plaintext) => {
+                tracing::debug!(%hash, "decrypted transaction");
+                let transaction = Transaction::new(namespace, plaintext);
+                state.pending.remove(&hash);
+                state.decrypted.insert(hash, transaction);
+            },
+            Err(err) => {
+                // The ciphertext and all the shares were verified, so this cannot happen.
+                tracing::error!(%hash, "encrypted transaction cannot be decrypted: {err:#}");
+                state.pending.remove(&hash);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(i: u64) -> (PubKey, PrivKey) {
+        PubKey::generated_from_seed_indexed([0; 32], i)
+    }
+
+    fn decryptor(key: &ThresholdEncryptionKey) -> Decryptor {
+        let (public_key, private_key) = node(100);
+        Decryptor::new(key.clone(), None, public_key, private_key)
+    }
+
+    #[tokio::test]
+    async fn test_collect_decryption_shares() {
+        let mut rng = rand::thread_rng();
+        let holders = (0..3).map(|i| node(i).0).collect();
+        let (key, key_shares) = ThresholdEncryptionKey::generate(2, holders, &mut rng);
+        let decryptor = decryptor(&key);
+
+        let namespace = NamespaceId::from(1u64);
+        let encrypted = EncryptedPayload::encrypt(&key, namespace, b"secret", &mut rng);
+        let tx = Transaction::new(namespace, encrypted.encode());
+        let hash = tx.commit();
+        let signed = |i: usize, shares: DecryptionShares| {
+            let (public_key, private_key) = node(i as u64);
+            SignedDecryptionShares::sign(public_key, &private_key, shares).unwrap()
+        };
+        let share = |i: usize| {
+            let share = key_shares[i]
+                .decryption_share(namespace, &encrypted, &mut rand::thread_rng())
+                .unwrap();
+            signed(i, vec![(hash, share)])
+        };
+
+        // A share arriving before the transaction is decided is held until it can be verified.
+        decryptor.add_shares(share(0)).await;
+        let own_share = decryptor.track(
+            &mut *decryptor.state.write().await,
+            hash,
+            namespace,
+            encrypted.clone(),
+        );
+        assert_eq!(own_share, None);
+        assert!(decryptor.decrypted(hash).await.is_none());
+
+        // Shares signed by a node other than the holder of their key share are discarded.
+        let mut forged = share(1);
+        forged.holder = node(0).0;
+        decryptor.add_shares(forged).await;
+        let (outsider, outsider_key) = node(3);
+        let mut relayed = share(1);
+        relayed.holder = outsider;
+        relayed.signature =
+            PubKey::sign(&outsider_key, &bincode::serialize(&relayed.shares).unwrap()).unwrap();
+        decryptor.add_shares(relayed).await;
+        let SignedDecryptionShares { shares, .. } = share(1);
+        decryptor.add_shares(signed(0, shares)).await;
+        assert!(decryptor.decrypted(hash).await.is_none());
+
+        // Invalid shares are discarded.
+        let other = EncryptedPayload::encrypt(&key, namespace, b"other", &mut rng);
+        let bad_share = key_shares[1]
+            .decryption_share(namespace, &other, &mut rng)
+            .unwrap();
+        decryptor
+            .add_shares(signed(1, vec![(hash, bad_share)]))
+            .await;
+        assert!(decryptor.decrypted(hash).await.is_none());
+
+        decryptor.add_shares(share(1)).await;
+        assert_eq!(
+            decryptor.decrypted(hash).await,
+            Some(Transaction::new(namespace, b"secret".to_vec()))
+        );
+        assert!(decryptor.state.read().await.pending.get(&hash).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_early_shares_bounded_per_holder() {
+        let mut rng = rand::thread_rng();
+        let holders = (0..3).map(|i| node(i).0).collect();
+        let (key, key_shares) = ThresholdEncryptionKey::generate(2, holders, &mut rng);
+        let decryptor = decryptor(&key);
+
+        let namespace = NamespaceId::from(1u64);
+        let encrypted = EncryptedPayload::encrypt(&key, namespace, b"secret", &mut rng);
+        let hash = Transaction::new(namespace, encrypted.encode()).commit();
+        let (public_key, private_key) = node(0);
+        let share = key_shares[0]
+            .decryption_share(namespace, &encrypted, &mut rng)
+            .unwrap();
+        decryptor
+            .add_shares(
+                SignedDecryptionShares::sign(public_key, &private_key, vec![(hash, share)])
+                    .unwrap(),
+            )
+            .await;
+
+        // Another holder flooding shares for transactions which do not exist does not evict it.
+        let (public_key, private_key) = node(1);
+        let junk = (0..=MAX_EARLY_SHARES_PER_HOLDER as u64)
+            .map(|i| {
+                let tx = Transaction::new(namespace, i.to_le_bytes().to_vec());
+                (tx.commit(), DecryptionShare { index: 2, ..share })
+            })
+            .collect::<DecryptionShares>();
+        let first_junk = junk[0].0;
+        decryptor
+            .add_shares(SignedDecryptionShares::sign(public_key, &private_key, junk).unwrap())
+            .await;
+
+        // Its own buffer is bounded instead.
+        let state = decryptor.state.read().await;
+        assert_eq!(state.early[&1].get(&hash), Some(&share));
+        assert_eq!(state.early[&2].get(&first_junk), None);
+    }
+}
@@ -16,12 +16,20 @@ use request_response::network::Bytes;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::{Receiver, Sender};
 
-use crate::context::TaskList;
+use crate::{
+    context::TaskList, encryption::SignedDecryptionShares,
+    upgrade_readiness::SignedSupportedVersion,
+};
 
 /// An external message that can be sent to or received from a node
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum ExternalMessage {
     RequestResponse(Vec<u8>),
+    /// Decryption shares for encrypted transactions, for the
+    /// [`Decryptor`](crate::encryption::Decryptor)
+    DecryptionShares(SignedDecryptionShares),
+    /// The protocol version a node supports, for upgrade readiness checks
+    SupportedVersion(SignedSupportedVersion),
 }
 
 /// The external event handler
@@ -86,6 +94,8 @@ impl<V: Versions> ExternalEventHandler<V> {
                     .send(request_response.into())
                     .await?;
             },
+            // The decryptor and the upgrade readiness reporter follow the event stream themselves.
+            ExternalMessage::DecryptionShares(_) | ExternalMessage::SupportedVersion(_) => {},
         }
         Ok(())
     }
@@ -0,0 +1,670 @@
+//! Fast sync for nodes far behind the tip of the chain.
+//!
+//! A node which has been offline for a long time would otherwise catch up by processing every
+//! block it missed through the consensus task, one after the other. With fast sync, before
+//! consensus starts, the node instead downloads the leaves it missed, each with the QC certifying
+//! it, from its state peers in parallel batches. Each QC is checked against the stake table of its
+//! epoch, and the leaves must form a chain from the anchor leaf of the node to a leaf which is
+//! proven decided, so every leaf in between is decided too. The verified leaves are then stored as
+//! decided, so consensus starts from near the tip, and the payloads of the skipped blocks are
+//! filled in lazily, by the query service fetching them from peers when it processes the decide.
+//!
+//! Sync proceeds in rounds of at most [`FastSyncConfig::round_size`] blocks, which bounds the
+//! number of leaves held in memory, until the node is less than [`FastSyncConfig::min_lag`]
+//! blocks behind the tip. The rest is caught up through consensus as usual.
+
+use std::{cmp::min, collections::HashMap, fmt::Display, future::Future, ops::Range, sync::Arc};
+
+use alloy::primitives::U256;
+use anyhow::{bail, ensure, Context};
+use async_lock::RwLock;
+use clap::Parser;
+use committable::Committable;
+use espresso_types::{
+    success_threshold_of,
+    v0::traits::{EventConsumer, SequencerPersistence, StateCatchup},
+    EpochCommittees, EpochNumber, Leaf2, LeafProof, LeafProofVerifier, PubKey, SeqTypes,
+    ValidatedState,
+};
+use futures::{
+    future::FutureExt,
+    stream::{self, StreamExt, TryStreamExt},
+};
+use hotshot::traits::ValidatedState as _;
+use hotshot_query_service::availability::LeafQueryData;
+use hotshot_types::{
+    epoch_membership::{EpochMembership, EpochMembershipCoordinator},
+    event::LeafInfo,
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        node_implementation::{ConsensusTime, Versions},
+        signature_key::SignatureKey,
+    },
+    utils::{epoch_from_block_number, is_epoch_root, is_transition_block},
+    vote::HasViewNumber,
+    PeerConfig, StakeTableEntries,
+};
+use vbs::version::StaticVersionType;
+
+use crate::catchup::StatePeers;
+
+/// Number of times a batch or proof is fetched before fast sync gives up
+const MAX_ATTEMPTS: usize = 5;
+
+/// Number of blocks below the tip left to consensus.
+///
+/// Proving a leaf decided takes its descendants up to the next decide, so the peers must have
+/// decided a few blocks past any leaf fast sync stops at.
+const TIP_MARGIN: u64 = 100;
+
+/// The stake table of an epoch, and the stake needed to form a QC with it
+#[derive(Debug)]
+struct QuorumStakeTable {
+    peers: Vec<PeerConfig<SeqTypes>>,
+    entries: Vec<<PubKey as SignatureKey>::StakeTableEntry>,
+    success_threshold: U256,
+}
+
+#[derive(Clone, Copy, Debug, Parser)]
+pub struct FastSyncConfig {
+    /// Sync missed blocks in parallel batches before starting consensus, when far behind the tip.
+    #[clap(long = "fast-sync", env = "ESPRESSO_SEQUENCER_FAST_SYNC")]
+    pub enabled: bool,
+
+    /// Only fast sync when at least this many blocks behind the tip.
+    #[clap(
+        long = "fast-sync-min-lag",
+        env = "ESPRESSO_SEQUENCER_FAST_SYNC_MIN_LAG",
+        default_value = "10000"
+    )]
+    pub min_lag: u64,
+
+    /// Number of leaves fetched from a peer in a single request.
+    ///
+    /// This must not exceed the range limit of the availability API of the peers.
+    #[clap(
+        long = "fast-sync-batch-size",
+        env = "ESPRESSO_SEQUENCER_FAST_SYNC_BATCH_SIZE",
+        default_value = "500"
+    )]
+    pub batch_size: u64,
+
+    /// Number of batches fetched and verified concurrently.
+    #[clap(
+        long = "fast-sync-parallelism",
+        env = "ESPRESSO_SEQUENCER_FAST_SYNC_PARALLELISM",
+        default_value = "8"
+    )]
+    pub parallelism: usize,
+
+    /// Number of blocks verified before they are stored.
+    #[clap(
+        long = "fast-sync-round-size",
+        env = "ESPRESSO_SEQUENCER_FAST_SYNC_ROUND_SIZE",
+        default_value = "50000"
+    )]
+    pub round_size: u64,
+}
+
+impl Default for FastSyncConfig {
+    fn default() -> Self {
+        Self::parse_from(std::iter::empty::<String>())
+    }
+}
+
+/// Fetches and verifies the leaves a node missed.
+pub struct FastSync<ApiVer: StaticVersionType, V: Versions> {
+    config: FastSyncConfig,
+    peers: StatePeers<ApiVer>,
+    coordinator: EpochMembershipCoordinator<SeqTypes>,
+    upgrade_lock: UpgradeLock<SeqTypes, V>,
+    /// Stake tables of the epochs seen so far
+    stake_tables: RwLock<HashMap<Option<EpochNumber>, Arc<QuorumStakeTable>>>,
+}
+
+impl<ApiVer: StaticVersionType, V: Versions> FastSync<ApiVer, V> {
+    pub fn new(
+        config: FastSyncConfig,
+        peers: StatePeers<ApiVer>,
+        coordinator: EpochMembershipCoordinator<SeqTypes>,
+        upgrade_lock: UpgradeLock<SeqTypes, V>,
+    ) -> Self {
+        Self {
+            config,
+            peers,
+            coordinator,
+            upgrade_lock,
+            stake_tables: Default::default(),
+        }
+    }
+
+    /// Sync the chain from `anchor` to near the tip, storing the verified leaves in `persistence`.
+    ///
+    /// Returns the height of the last leaf stored, or `None` if the node was not far enough behind
+    /// to fast sync.
+    pub async fn run(
+        &self,
+        mut anchor: Leaf2,
+        persistence: &impl SequencerPersistence,
+        consumer: &(impl EventConsumer + 'static),
+    ) -> anyhow::Result<Option<u64>> {
+        let mut synced = None;
+        loop {
+            let block_height = self
+                .peers
+                .backoff()
+                .retry(self, |sync, retry| {
+                    sync.peers.try_fetch_block_height(retry).boxed()
+                })
+                .await?;
+            let tip = block_height.saturating_sub(1);
+            let target = min(
+                tip.saturating_sub(TIP_MARGIN),
+                anchor.height() + self.config.round_size,
+            );
+            if tip.saturating_sub(anchor.height()) < self.config.min_lag
+                || target <= anchor.height()
+            {
+                return Ok(synced);
+            }
+
+            tracing::info!(from = anchor.height(), target, tip, "fast syncing");
+            let leaves = self.sync_round(&anchor, target).await?;
+
+            store_round(
+                persistence,
+                consumer,
+                &leaves,
+                self.coordinator.epoch_height,
+            )
+            .await
+            .context("storing fast synced leaves")?;
+            let (last, _) = leaves.last().context("fast sync round is empty")?;
+            anchor = last.leaf.clone();
+            synced = Some(target);
+            tracing::info!(height = target, view = ?anchor.view_number(), "fast synced");
+        }
+    }
+
+    /// Fetch and verify the leaves after `anchor`, up to and including height `target`.
+    async fn sync_round(
+        &self,
+        anchor: &Leaf2,
+        target: u64,
+    ) -> anyhow::Result<Vec<(LeafInfo<SeqTypes>, QuorumCertificate2<SeqTypes>)>> {
+        // Prove the leaf at `target` decided first; the leaves chained to it are then decided too.
+        let decided = self.decided_leaf(target).await?;
+
+        let mut parent = anchor.commit();
+        let mut leaves = Vec::with_capacity((target - anchor.height()) as usize);
+        let mut batches = stream::iter(batches(
+            anchor.height() + 1..target + 1,
+            self.config.batch_size,
+        ))
+        .map(|range| self.fetch_batch(range))
+        .buffered(self.config.parallelism);
+        while let Some(batch) = batches.try_next().await? {
+            // Each batch is chained internally; check it extends the one before.
+            let first = batch[0].leaf();
+            ensure!(
+                first.parent_commitment() == parent,
+                "leaf at height {} does not extend its parent",
+                first.height()
+            );
+            for data in batch {
+                let leaf = data.leaf().clone();
+                parent = leaf.commit();
+                let state = Arc::new(ValidatedState::from_header(leaf.block_header()));
+                leaves.push((
+                    LeafInfo::new(leaf, state, None, None, None),
+                    data.qc().clone(),
+                ));
+            }
+        }
+        ensure!(
+            parent == decided.commit(),
+            "leaf at height {target} is not the decided leaf"
+        );
+        Ok(leaves)
+    }
+
+    /// Fetch the leaf at `height`, with a proof that it is decided.
+    async fn decided_leaf(&self, height: u64) -> anyhow::Result<Leaf2> {
+        attempts(
+            format!("decided leaf at height {height}"),
+            |retry| async move {
+                let mut chain = self.peers.try_fetch_leaves(retry, height).await?;
+                chain.sort_by_key(|leaf| leaf.view_number());
+                let proof = LeafProof::from_leaf_chain(chain)?;
+                ensure!(
+                    proof.height() == height,
+                    "expected proof for height {height}, got height {}",
+                    proof.height()
+                );
+                let stake_table = self.stake_table(proof.qc.data.epoch).await?;
+                LeafProofVerifier::new(stake_table.peers.clone(), stake_table.success_threshold)
+                    .with_upgrade_lock(self.upgrade_lock.clone())
+                    .verify(&proof)
+                    .await?;
+                anyhow::Ok(proof.leaf)
+            },
+        )
+        .await
+    }
+
+    /// Fetch and verify the leaves in `range`.
+    async fn fetch_batch(&self, range: Range<u64>) -> anyhow::Result<Vec<LeafQueryData<SeqTypes>>> {
+        attempts(format!("leaves {range:?}"), |retry| {
+            let range = range.clone();
+            async move {
+                let batch = self
+                    .peers
+                    .try_fetch_leaf_range(retry, range.clone())
+                    .await?;
+                self.verify_batch(range, &batch).await?;
+                anyhow::Ok(batch)
+            }
+        })
+        .await
+    }
+
+    /// Check that `batch` is a chain of certified leaves at the heights in `range`.
+    async fn verify_batch(
+        &self,
+        range: Range<u64>,
+        batch: &[LeafQueryData<SeqTypes>],
+    ) -> anyhow::Result<()> {
+        ensure!(
+            batch.len() as u64 == range.end - range.start,
+            "expected {} leaves, got {}",
+            range.end - range.start,
+            batch.len()
+        );
+        for (height, data) in range.zip(batch) {
+            let leaf = data.leaf();
+            ensure!(
+                leaf.height() == height,
+                "expected leaf at height {height}, got height {}",
+                leaf.height()
+            );
+            ensure!(
+                data.qc().data.leaf_commit == leaf.commit(),
+                "QC does not certify leaf at height {height}"
+            );
+            if let Some(epoch) = data.qc().data.epoch {
+                ensure!(
+                    *epoch == epoch_from_block_number(height, self.coordinator.epoch_height),
+                    "QC for leaf at height {height} is from the wrong epoch {epoch}"
+                );
+            }
+        }
+        for pair in batch.windows(2) {
+            ensure!(
+                pair[1].leaf().parent_commitment() == pair[0].leaf().commit(),
+                "leaf at height {} does not extend its parent",
+                pair[1].leaf().height()
+            );
+        }
+
+        // Checking signatures is the expensive part, so check all the QCs of the batch at once.
+        stream::iter(batch)
+            .map(anyhow::Ok)
+            .try_for_each_concurrent(None, |data| async move {
+                let qc = data.qc();
+                let stake_table = self.stake_table(qc.data.epoch).await?;
+                qc.verify_untrusted(
+                    stake_table.entries.clone(),
+                    stake_table.success_threshold,
+                    &self.upgrade_lock,
+                )
+                .await
+                .with_context(|| format!("invalid QC for view {:?}", qc.view_number()))
+            })
+            .await
+    }
+
+    /// The stake table QCs of `epoch` are checked against.
+    async fn stake_table(
+        &self,
+        epoch: Option<EpochNumber>,
+    ) -> anyhow::Result<Arc<QuorumStakeTable>> {
+        if let Some(stake_table) = self.stake_tables.read().await.get(&epoch) {
+            return Ok(stake_table.clone());
+        }
+        // Stake tables of old epochs are no longer held in memory, but are recorded in storage.
+        let recorded = match epoch {
+            Some(epoch) => {
+                EpochCommittees::stake_table_at(self.coordinator.membership(), epoch).await?
+            },
+            None => None,
+        };
+        let (peers, success_threshold) = match recorded {
+            Some(peers) => {
+                let success_threshold = success_threshold_of(&peers);
+                (peers, success_threshold)
+            },
+            None => {
+                let membership = self.membership(epoch).await?;
+                (
+                    membership.stake_table().await,
+                    membership.success_threshold().await,
+                )
+            },
+        };
+        let stake_table = Arc::new(QuorumStakeTable {
+            entries: StakeTableEntries::<SeqTypes>::from(peers.clone()).0,
+            peers,
+            success_threshold,
+        });
+        self.stake_tables
+            .write()
+            .await
+            .insert(epoch, stake_table.clone());
+        Ok(stake_table)
+    }
+
+    /// The membership of `epoch`, catching up on its stake table if necessary.
+    async fn membership(
+        &self,
+        epoch: Option<EpochNumber>,
+    ) -> anyhow::Result<EpochMembership<SeqTypes>> {
+        if let Ok(membership) = self.coordinator.membership_for_epoch(epoch).await {
+            return Ok(membership);
+        }
+        let epoch = epoch.context("no membership before epochs")?;
+        let membership = self
+            .coordinator
+            .wait_for_catchup(epoch)
+            .await
+            .with_context(|| format!("catching up on stake table for epoch {epoch}"))?;
+        Ok(membership)
+    }
+}
+
+/// Store the verified leaves of a round as decided.
+///
+/// When consensus decides an epoch root or a transition block, it also stores the root header or
+/// the DRB result it carries, from which the stake tables of later epochs are computed. The leaves
+/// fast sync skips are never decided by consensus, so the same data is stored here, before the
+/// leaves themselves, and consensus loads it into the membership when it starts.
+async fn store_round(
+    persistence: &impl SequencerPersistence,
+    consumer: &(impl EventConsumer + 'static),
+    leaves: &[(LeafInfo<SeqTypes>, QuorumCertificate2<SeqTypes>)],
+    epoch_height: u64,
+) -> anyhow::Result<()> {
+    let (last, _) = leaves.last().context("fast sync round is empty")?;
+
+    for (info, _) in leaves {
+        let leaf = &info.leaf;
+        let height = leaf.height();
+        if epoch_height == 0 || !leaf.with_epoch {
+            continue;
+        }
+        let epoch = EpochNumber::new(epoch_from_block_number(height, epoch_height));
+        if is_epoch_root(height, epoch_height) {
+            persistence
+                .add_epoch_root(epoch + 2, leaf.block_header().clone())
+                .await
+                .with_context(|| format!("storing epoch root for epoch {}", epoch + 2))?;
+        }
+        if is_transition_block(height, epoch_height) {
+            let drb_result = leaf
+                .next_drb_result
+                .with_context(|| format!("transition block {height} has no DRB result"))?;
+            persistence
+                .add_drb_result(epoch + 1, drb_result)
+                .await
+                .with_context(|| format!("storing DRB result for epoch {}", epoch + 1))?;
+        }
+    }
+
+    persistence
+        .append_decided_leaves(
+            last.leaf.view_number(),
+            leaves.iter().map(|(info, qc)| (info, qc.clone())),
+            consumer,
+        )
+        .await
+}
+
+/// Run `f` up to [`MAX_ATTEMPTS`] times, until it succeeds.
+///
+/// Each attempt is passed its index, which [`StatePeers`] uses to lengthen its timeouts.
+async fn attempts<T, F>(what: impl Display, f: impl Fn(usize) -> F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    for retry in 0..MAX_ATTEMPTS {
+        match f(retry).await {
+            Ok(res) => return Ok(res),
+            Err(err) => tracing::warn!(retry, "failed to fetch {what}: {err:#}"),
+        }
+    }
+    bail!("failed to fetch {what} after {MAX_ATTEMPTS} attempts");
+}
+
+/// Split `range` into consecutive batches of at most `batch_size` heights.
+fn batches(range: Range<u64>, batch_size: u64) -> impl Iterator<Item = Range<u64>> {
+    let batch_size = batch_size.max(1);
+    range
+        .clone()
+        .step_by(batch_size as usize)
+        .map(move |start| start..min(start + batch_size, range.end))
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{
+        child_leaf, sign_qc, traits::NullEventConsumer, v0::traits::PersistenceOptions,
+        BackoffParams, EpochCommittees, MockSequencerVersions, NodeState,
+    };
+    use hotshot::InitializerEpochInfo;
+    use hotshot_types::{
+        data::ViewNumber, simple_vote::QuorumData2, traits::metrics::NoMetrics, ValidatorConfig,
+    };
+    use sequencer_utils::test_utils::setup_test;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        persistence::{fs, no_storage::NoStorage},
+        SequencerApiVersion,
+    };
+
+    const EPOCH_HEIGHT: u64 = 10;
+
+    fn validators() -> Vec<ValidatorConfig<SeqTypes>> {
+        (0..4)
+            .map(|i| ValidatorConfig::generated_from_seed_indexed([0; 32], i, U256::from(1), true))
+            .collect()
+    }
+
+    fn fast_sync() -> FastSync<SequencerApiVersion, MockSequencerVersions> {
+        let node_state = NodeState::mock();
+        let peers = validators()
+            .iter()
+            .map(ValidatorConfig::public_config)
+            .collect::<Vec<_>>();
+        let membership = EpochCommittees::new_stake(
+            peers,
+            vec![],
+            node_state.l1_client,
+            node_state.chain_config,
+            node_state.peers,
+            NoStorage,
+        );
+        FastSync::new(
+            FastSyncConfig::default(),
+            StatePeers::from_urls(
+                vec!["http://localhost:1".parse().unwrap()],
+                BackoffParams::default(),
+                &NoMetrics,
+            ),
+            EpochMembershipCoordinator::new(Arc::new(RwLock::new(membership)), EPOCH_HEIGHT),
+            UpgradeLock::new(),
+        )
+    }
+
+    /// The genesis leaf and `len` leaves extending it, each with a QC signed by [`validators`].
+    ///
+    /// With `epochs`, the leaves and QCs carry their epoch, and each transition block carries a DRB
+    /// result.
+    async fn chain(len: u64, epochs: bool) -> Vec<LeafQueryData<SeqTypes>> {
+        let validators = validators();
+        let threshold = U256::from(validators.len());
+        let upgrade_lock = UpgradeLock::<SeqTypes, MockSequencerVersions>::new();
+        let certify = |leaf: Leaf2| {
+            let validators = &validators;
+            let upgrade_lock = &upgrade_lock;
+            async move {
+                let data = QuorumData2 {
+                    leaf_commit: leaf.commit(),
+                    epoch: epochs.then(|| {
+                        EpochNumber::new(epoch_from_block_number(leaf.height(), EPOCH_HEIGHT))
+                    }),
+                    block_number: Some(leaf.height()),
+                };
+                let qc = sign_qc(
+                    validators,
+                    threshold,
+                    data,
+                    leaf.view_number(),
+                    upgrade_lock,
+                )
+                .await;
+                LeafQueryData::new(leaf, qc).unwrap()
+            }
+        };
+
+        let genesis = LeafQueryData::<SeqTypes>::genesis::<MockSequencerVersions>(
+            &Default::default(),
+            &NodeState::mock(),
+        )
+        .await;
+        let mut parent = certify(genesis.leaf().clone()).await;
+        let mut chain = vec![genesis];
+        for view in 1..=len {
+            let mut leaf = child_leaf(parent.header(), ViewNumber::new(view), parent.qc().clone());
+            leaf.with_epoch = epochs;
+            if epochs && is_transition_block(leaf.height(), EPOCH_HEIGHT) {
+                leaf.next_drb_result = Some([leaf.height() as u8; 32]);
+            }
+            parent = certify(leaf).await;
+            chain.push(parent.clone());
+        }
+        chain
+    }
+
+    #[test]
+    fn test_fast_sync_batches() {
+        assert_eq!(batches(1..11, 4).collect::<Vec<_>>(), [1..5, 5..9, 9..11]);
+        assert_eq!(batches(1..9, 4).collect::<Vec<_>>(), [1..5, 5..9]);
+        assert_eq!(batches(5..5, 4).count(), 0);
+
+        // A zero batch size still makes progress.
+        assert_eq!(batches(0..2, 0).collect::<Vec<_>>(), [0..1, 1..2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fast_sync_verify_batch() {
+        setup_test();
+
+        let sync = fast_sync();
+        let chain = chain(4, false).await;
+        sync.verify_batch(1..5, &chain[1..]).await.unwrap();
+
+        // The batch must cover exactly the requested heights.
+        sync.verify_batch(2..6, &chain[1..]).await.unwrap_err();
+        sync.verify_batch(1..4, &chain[1..]).await.unwrap_err();
+
+        // The leaves must form a chain.
+        let gapped = [chain[1].clone(), chain[3].clone()];
+        sync.verify_batch(1..3, &gapped).await.unwrap_err();
+
+        // The genesis QC is not signed and must not be trusted.
+        sync.verify_batch(0..1, &chain[..1]).await.unwrap_err();
+
+        // A QC without signatures is rejected, rather than crashing the node.
+        let mut batch = chain[1..].to_vec();
+        let mut qc = batch[2].qc().clone();
+        qc.signatures = None;
+        batch[2] = LeafQueryData::new(batch[2].leaf().clone(), qc).unwrap();
+        sync.verify_batch(1..5, &batch).await.unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fast_sync_store_round() {
+        setup_test();
+
+        let tmp = TempDir::new().unwrap();
+        let persistence = fs::Options::new(tmp.path().into()).create().await.unwrap();
+
+        let chain = chain(2 * EPOCH_HEIGHT, true).await;
+        let leaves = chain[1..]
+            .iter()
+            .map(|data| {
+                let leaf = data.leaf().clone();
+                let state = Arc::new(ValidatedState::from_header(leaf.block_header()));
+                (
+                    LeafInfo::new(leaf, state, None, None, None),
+                    data.qc().clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        store_round(&persistence, &NullEventConsumer, &leaves, EPOCH_HEIGHT)
+            .await
+            .unwrap();
+
+        // The last leaf is the new anchor.
+        let last = chain.last().unwrap();
+        assert_eq!(
+            persistence.load_anchor_leaf().await.unwrap(),
+            Some((last.leaf().clone(), last.qc().clone()))
+        );
+
+        // The DRB results of the transition blocks at heights 7 and 17 are stored for the epochs
+        // after theirs, along with the roots at height 5 for epoch 3 and height 15 for epoch 4.
+        let root = |height: usize| chain[height].header().clone();
+        let info = persistence.load_start_epoch_info().await.unwrap();
+        assert_eq!(
+            info,
+            [
+                InitializerEpochInfo::<SeqTypes> {
+                    epoch: EpochNumber::new(2),
+                    drb_result: [7; 32],
+                    block_header: None,
+                },
+                InitializerEpochInfo::<SeqTypes> {
+                    epoch: EpochNumber::new(3),
+                    drb_result: [17; 32],
+                    block_header: Some(root(5)),
+                },
+            ]
+        );
+
+        // The root for epoch 4 waits for its DRB result, which a later round stores.
+        let mut transition = chain(2 * EPOCH_HEIGHT + 7, true).await;
+        let transition = transition.split_off(2 * EPOCH_HEIGHT as usize + 1);
+        let leaves = transition
+            .iter()
+            .map(|data| {
+                let leaf = data.leaf().clone();
+                let state = Arc::new(ValidatedState::from_header(leaf.block_header()));
+                (
+                    LeafInfo::new(leaf, state, None, None, None),
+                    data.qc().clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        store_round(&persistence, &NullEventConsumer, &leaves, EPOCH_HEIGHT)
+            .await
+            .unwrap();
+        let info = persistence.load_start_epoch_info().await.unwrap();
+        assert_eq!(info.len(), 3);
+        assert_eq!(info[2].epoch, EpochNumber::new(4));
+        assert_eq!(info[2].drb_result, [27; 32]);
+        assert_eq!(info[2].block_header, Some(root(15)));
+    }
+}
@@ -1,16 +1,20 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    cmp::max,
+    collections::{BTreeMap, HashMap, HashSet},
+    iter,
     path::Path,
 };
 
 use alloy::primitives::Address;
-use anyhow::{Context, Ok};
+use anyhow::{bail, ensure, Context, Ok};
+use committable::Committable;
 use espresso_types::{
-    v0_99::ChainConfig, FeeAccount, FeeAmount, GenesisHeader, L1BlockInfo, L1Client, Timestamp,
-    Upgrade,
+    v0_4::ChainConfig, BlockLimitsVersion, EpochVersion, FeeAccount, FeeAmount, FeeVersion, GenesisHeader,
+    L1BlockInfo, L1Client, MarketplaceVersion, NamespaceRegistry, NamespaceRegistryConfig,
+    ThresholdEncryptionKey, Timestamp, Upgrade, UpgradeMode, UpgradeType,
 };
 use serde::{Deserialize, Serialize};
-use vbs::version::Version;
+use vbs::version::{StaticVersionType, Version};
 
 /// Initial configuration of an Espresso stake table.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
@@ -62,6 +66,19 @@ pub struct Genesis {
     #[serde(rename = "upgrade", with = "upgrade_ser")]
     #[serde(default)]
     pub upgrades: BTreeMap<Version, Upgrade>,
+    /// Key held by the DA committee which transactions can be encrypted to.
+    ///
+    /// If absent, encrypted transactions are sequenced like any other, but never decrypted.
+    #[serde(default)]
+    pub encryption: Option<ThresholdEncryptionKey>,
+    /// Registries of the rollups owning each namespace.
+    ///
+    /// A registry is in force while the chain config commits to it, so each registry must be
+    /// committed to by the genesis chain config or the chain config of an upgrade. Nodes keep every
+    /// registry, so they can validate blocks on both sides of an upgrade which changes it. Without
+    /// a registry, transactions are accepted in every namespace.
+    #[serde(rename = "namespace_registry", default)]
+    pub namespace_registries: Vec<NamespaceRegistryConfig>,
 }
 
 impl Genesis {
@@ -302,8 +319,88 @@ mod upgrade_ser {
 
 impl Genesis {
     pub fn to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
-        let toml = toml::to_string_pretty(self)?;
-        std::fs::write(path, toml.as_bytes())?;
+        std::fs::write(path, self.to_toml()?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Parse and validate a genesis document.
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        let genesis: Self = toml::from_str(text).context("malformed genesis")?;
+        genesis.validate()?;
+        Ok(genesis)
+    }
+
+    /// Check the consistency of the genesis document, without consulting the L1.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.base_version <= self.upgrade_version,
+            "base version {} is after upgrade version {}",
+            self.base_version,
+            self.upgrade_version,
+        );
+        ensure!(
+            self.stake_table.capacity > 0,
+            "stake table capacity must be positive"
+        );
+        match (self.epoch_height, self.epoch_start_block) {
+            (Some(0), _) => bail!("epoch height must be positive"),
+            (None, Some(_)) => bail!("epoch start block is set without an epoch height"),
+            _ => {},
+        }
+        if self.upgrade_version >= EpochVersion::version() {
+            ensure!(
+                self.epoch_height.is_some(),
+                "epoch height is required for version {}",
+                self.upgrade_version,
+            );
+        }
+        if let Some(key) = &self.encryption {
+            key.validate().context("invalid encryption key")?;
+        }
+        self.namespace_registries()?;
+        validate_chain_config(self.base_version, &self.chain_config)?;
+
+        let mut previous: Option<(&Version, &Upgrade)> = None;
+        for (version, upgrade) in &self.upgrades {
+            ensure!(
+                *version > self.base_version && *version <= self.upgrade_version,
+                "upgrade to {version} is outside the range from base version {} to upgrade version {}",
+                self.base_version,
+                self.upgrade_version,
+            );
+            let expected = match upgrade.upgrade_type {
+                UpgradeType::Fee { .. } => FeeVersion::version(),
+                UpgradeType::Epoch { .. } => EpochVersion::version(),
+                UpgradeType::Marketplace { .. } => MarketplaceVersion::version(),
+                UpgradeType::BlockLimits { .. } => BlockLimitsVersion::version(),
+            };
+            ensure!(
+                *version == expected,
+                "upgrade to {version} has the type of the upgrade to {expected}",
+            );
+            if let Some(chain_config) = upgrade.upgrade_type.chain_config() {
+                ensure!(
+                    chain_config.chain_id == self.chain_config.chain_id,
+                    "upgrade to {version} changes the chain ID",
+                );
+                validate_chain_config(*version, &chain_config)?;
+            }
+            validate_upgrade_mode(&upgrade.mode)
+                .context(format!("invalid schedule for upgrade to {version}"))?;
+
+            // Upgrades must be scheduled in the order of their versions.
+            if let Some((previous_version, previous)) = previous {
+                ensure!(
+                    upgrade_starts_after(&upgrade.mode, &previous.mode),
+                    "upgrade to {version} is not scheduled after the upgrade to {previous_version}",
+                );
+            }
+            previous = Some((version, upgrade));
+        }
         Ok(())
     }
 
@@ -312,7 +409,215 @@ impl Genesis {
         let bytes = std::fs::read(path).context(format!("genesis file {}", path.display()))?;
         let text = std::str::from_utf8(&bytes).context("genesis file must be UTF-8")?;
 
-        toml::from_str(text).context("malformed genesis file")
+        let genesis: Self = toml::from_str(text).context("malformed genesis file")?;
+        if let Some(key) = &genesis.encryption {
+            key.validate()
+                .context("invalid encryption key in genesis file")?;
+        }
+        genesis.namespace_registries().context("in genesis file")?;
+        Ok(genesis)
+    }
+
+    /// The namespace registries the chain configs of this genesis may commit to.
+    ///
+    /// # Errors
+    /// If a registry is invalid, a chain config commits to a registry which is not configured or a
+    /// registry is not committed to by any chain config, or a chain config commits to a registry in
+    /// a version whose headers cannot carry it.
+    pub fn namespace_registries(&self) -> anyhow::Result<Vec<NamespaceRegistry>> {
+        let registries = self
+            .namespace_registries
+            .iter()
+            .map(|config| NamespaceRegistry::new(config.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid namespace registry")?;
+        let configured = registries
+            .iter()
+            .map(|registry| registry.commit())
+            .collect::<HashSet<_>>();
+
+        let chain_configs = iter::once((self.base_version, self.chain_config)).chain(
+            self.upgrades.iter().filter_map(|(version, upgrade)| {
+                Some((*version, upgrade.upgrade_type.chain_config()?))
+            }),
+        );
+        let mut committed = HashSet::new();
+        for (version, chain_config) in chain_configs {
+            let Some(commitment) = chain_config.namespace_registry else {
+                continue;
+            };
+            // Only v0.4 headers carry chain configs with a namespace registry.
+            ensure!(
+                version == BlockLimitsVersion::version(),
+                "chain config of version {version} commits to a namespace registry, which requires \
+                 version {}",
+                BlockLimitsVersion::version(),
+            );
+            ensure!(
+                configured.contains(&commitment),
+                "chain config of version {version} commits to namespace registry {commitment}, \
+                 which is not configured",
+            );
+            committed.insert(commitment);
+        }
+        for commitment in configured {
+            ensure!(
+                committed.contains(&commitment),
+                "namespace registry {commitment} is not committed to by any chain config; set \
+                 `namespace_registry = \"{commitment}\"` in the chain config which should \
+                 enforce it",
+            );
+        }
+        Ok(registries)
+    }
+}
+
+/// Check that headers of `version` can carry `chain_config`.
+fn validate_chain_config(version: Version, chain_config: &ChainConfig) -> anyhow::Result<()> {
+    ensure!(
+        chain_config.supported_in(version),
+        "chain config of version {version} sets block limits or a namespace registry, which are \
+         only supported in version {}",
+        BlockLimitsVersion::version(),
+    );
+    Ok(())
+}
+
+/// Check that the windows for proposing and voting on an upgrade are well formed.
+fn validate_upgrade_mode(mode: &UpgradeMode) -> anyhow::Result<()> {
+    match mode {
+        UpgradeMode::View(mode) => {
+            ensure!(
+                mode.start_proposing_view < mode.stop_proposing_view,
+                "proposing window ends before it starts"
+            );
+            if let (Some(start), Some(stop)) = (mode.start_voting_view, mode.stop_voting_view) {
+                ensure!(start < stop, "voting window ends before it starts");
+            }
+        },
+        UpgradeMode::Time(mode) => {
+            ensure!(
+                mode.start_proposing_time.unix_timestamp()
+                    < mode.stop_proposing_time.unix_timestamp(),
+                "proposing window ends before it starts"
+            );
+            if let (Some(start), Some(stop)) = (mode.start_voting_time, mode.stop_voting_time) {
+                ensure!(
+                    start.unix_timestamp() < stop.unix_timestamp(),
+                    "voting window ends before it starts"
+                );
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Whether an upgrade is scheduled strictly after an earlier one.
+///
+/// Upgrades scheduled in different modes cannot be compared, and are allowed in either order.
+fn upgrade_starts_after(mode: &UpgradeMode, earlier: &UpgradeMode) -> bool {
+    match (mode, earlier) {
+        (UpgradeMode::View(mode), UpgradeMode::View(earlier)) => {
+            mode.start_proposing_view > earlier.start_proposing_view
+        },
+        (UpgradeMode::Time(mode), UpgradeMode::Time(earlier)) => {
+            mode.start_proposing_time.unix_timestamp()
+                > earlier.start_proposing_time.unix_timestamp()
+        },
+        _ => true,
+    }
+}
+
+/// Programmatic construction of a [`Genesis`].
+///
+/// The document is validated when it is built, so that mistakes surface when it is written rather
+/// than when a node starts from it.
+#[derive(Clone, Debug)]
+pub struct GenesisBuilder {
+    genesis: Genesis,
+}
+
+impl GenesisBuilder {
+    /// Start a genesis at `base_version`, with no upgrades scheduled.
+    pub fn new(
+        base_version: Version,
+        chain_config: ChainConfig,
+        l1_finalized: L1Finalized,
+    ) -> Self {
+        Self {
+            genesis: Genesis {
+                base_version,
+                upgrade_version: base_version,
+                epoch_height: None,
+                epoch_start_block: None,
+                chain_config,
+                stake_table: StakeTableConfig { capacity: 10 },
+                accounts: Default::default(),
+                l1_finalized,
+                header: Default::default(),
+                upgrades: Default::default(),
+                encryption: None,
+                namespace_registries: Default::default(),
+            },
+        }
+    }
+
+    pub fn stake_table_capacity(mut self, capacity: u64) -> Self {
+        self.genesis.stake_table.capacity = capacity;
+        self
+    }
+
+    pub fn epochs(mut self, epoch_height: u64, epoch_start_block: Option<u64>) -> Self {
+        self.genesis.epoch_height = Some(epoch_height);
+        self.genesis.epoch_start_block = epoch_start_block;
+        self
+    }
+
+    /// Give `account` an initial balance of `amount`.
+    pub fn account(mut self, account: FeeAccount, amount: FeeAmount) -> Self {
+        self.genesis.accounts.insert(account, amount);
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.genesis.header.timestamp = timestamp;
+        self
+    }
+
+    pub fn encryption_key(mut self, key: ThresholdEncryptionKey) -> Self {
+        self.genesis.encryption = Some(key);
+        self
+    }
+
+    pub fn namespace_registry(mut self, registry: NamespaceRegistryConfig) -> Self {
+        self.genesis.namespace_registries.push(registry);
+        self
+    }
+
+    /// Schedule an upgrade to `version`.
+    ///
+    /// The upgrade version of the genesis becomes the latest version scheduled.
+    pub fn upgrade(mut self, version: Version, upgrade: Upgrade) -> Self {
+        self.genesis.upgrade_version = max(self.genesis.upgrade_version, version);
+        self.genesis.upgrades.insert(version, upgrade);
+        self
+    }
+
+    /// Set the version the node will upgrade to, without scheduling the upgrade in the genesis.
+    pub fn upgrade_version(mut self, version: Version) -> Self {
+        self.genesis.upgrade_version = version;
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Genesis> {
+        self.genesis.validate()?;
+        Ok(self.genesis)
+    }
+}
+
+impl From<Genesis> for GenesisBuilder {
+    fn from(genesis: Genesis) -> Self {
+        Self { genesis }
     }
 }
 
@@ -326,8 +631,10 @@ mod test {
         providers::{layers::AnvilProvider, ProviderBuilder},
     };
     use espresso_types::{
-        L1BlockInfo, TimeBasedUpgrade, Timestamp, UpgradeMode, UpgradeType, ViewBasedUpgrade,
+        L1BlockInfo, NamespaceId, PubKey, TimeBasedUpgrade, Timestamp, UnregisteredNamespacePolicy,
+        UpgradeMode, UpgradeType, ViewBasedUpgrade, V0_1,
     };
+    use hotshot_types::traits::signature_key::SignatureKey;
     use sequencer_utils::{
         deployer::{self, Contracts},
         ser::FromStringOrInteger,
@@ -378,7 +685,10 @@ mod test {
                 fee_recipient: FeeAccount::default(),
                 fee_contract: Some(Address::default()),
                 bid_recipient: None,
-                stake_table_contract: None
+                stake_table_contract: None,
+                max_block_transactions: None,
+                max_namespace_size: None,
+                namespace_registry: None,
             }
         );
         assert_eq!(
@@ -452,6 +762,9 @@ mod test {
                 bid_recipient: None,
                 fee_contract: None,
                 stake_table_contract: None,
+                max_block_transactions: None,
+                max_namespace_size: None,
+                namespace_registry: None,
             }
         );
         assert_eq!(
@@ -462,6 +775,140 @@ mod test {
         );
         assert_eq!(genesis.accounts, HashMap::default());
         assert_eq!(genesis.l1_finalized, L1Finalized::Number { number: 0 });
+        assert_eq!(genesis.encryption, None);
+    }
+
+    #[test]
+    fn test_genesis_encryption_key() {
+        let holders = (0..3)
+            .map(|i| PubKey::generated_from_seed_indexed([0; 32], i).0)
+            .collect();
+        let (key, _) = ThresholdEncryptionKey::generate(2, holders, &mut rand::thread_rng());
+        let mut toml = toml! {
+            base_version = "0.1"
+            upgrade_version = "0.2"
+
+            [stake_table]
+            capacity = 10
+
+            [chain_config]
+            chain_id = 12345
+            max_block_size = 30000
+            base_fee = 1
+            fee_recipient = "0x0000000000000000000000000000000000000000"
+
+            [header]
+            timestamp = 123456
+
+            [l1_finalized]
+            number = 0
+        };
+        toml.as_table_mut()
+            .unwrap()
+            .insert("encryption".into(), toml::Value::try_from(&key).unwrap());
+
+        let genesis: Genesis = toml::from_str(&toml.to_string()).unwrap();
+        assert_eq!(genesis.encryption, Some(key.clone()));
+
+        // A key with an unreachable threshold is rejected when loading the file.
+        let mut invalid = key;
+        invalid.threshold = 4;
+        toml.as_table_mut().unwrap().insert(
+            "encryption".into(),
+            toml::Value::try_from(&invalid).unwrap(),
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), toml.to_string()).unwrap();
+        Genesis::from_file(file.path()).unwrap_err();
+    }
+
+    #[test]
+    fn test_genesis_namespace_registry() {
+        let mut toml = toml! {
+            base_version = "0.4"
+            upgrade_version = "0.4"
+            epoch_height = 10
+
+            [stake_table]
+            capacity = 10
+
+            [chain_config]
+            chain_id = 12345
+            max_block_size = 30000
+            base_fee = 1
+            fee_recipient = "0x0000000000000000000000000000000000000000"
+
+            [header]
+            timestamp = 123456
+
+            [l1_finalized]
+            number = 0
+
+            [[namespace_registry]]
+            unregistered = "reject"
+
+            [[namespace_registry.namespaces]]
+            namespace = 10
+            rollup = "alpha"
+            owner = "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f"
+
+            [[namespace_registry.namespaces]]
+            namespace = 11
+            rollup = "beta"
+        };
+
+        // The registry is only in force once a chain config commits to it.
+        let genesis: Genesis = toml::from_str(&toml.to_string()).unwrap();
+        genesis.validate().unwrap_err();
+        let commitment = NamespaceRegistry::new(genesis.namespace_registries[0].clone())
+            .unwrap()
+            .commit();
+        toml.get_mut("chain_config")
+            .and_then(|chain_config| chain_config.as_table_mut())
+            .unwrap()
+            .insert("namespace_registry".into(), commitment.to_string().into());
+
+        let genesis = Genesis::from_toml(&toml.to_string()).unwrap();
+        assert_eq!(genesis.chain_config.namespace_registry, Some(commitment));
+        let [registry] = <[_; 1]>::try_from(genesis.namespace_registries().unwrap()).unwrap();
+        assert_eq!(
+            registry.unregistered_policy(),
+            UnregisteredNamespacePolicy::Reject
+        );
+        let alpha = registry.get(NamespaceId::from(10u64)).unwrap();
+        assert_eq!(alpha.rollup, "alpha");
+        assert_eq!(
+            alpha.owner,
+            Some(
+                "0x23618e81e3f5cdf7f54c3d65f7fbc0abf5b21e8f"
+                    .parse::<FeeAccount>()
+                    .unwrap()
+            )
+        );
+        registry.admit(NamespaceId::from(12u64)).unwrap_err();
+
+        // Headers of other versions cannot carry the registry.
+        for version in ["0.3", "0.99"] {
+            let mut other = toml.clone();
+            other.insert("base_version".into(), version.into());
+            other.insert("upgrade_version".into(), version.into());
+            Genesis::from_toml(&other.to_string()).unwrap_err();
+        }
+
+        // A namespace registered twice is rejected when collisions are.
+        let registry = toml
+            .get_mut("namespace_registry")
+            .and_then(|registries| registries.as_array_mut())
+            .and_then(|registries| registries[0].as_table_mut())
+            .unwrap();
+        registry.insert("collisions".into(), "reject".into());
+        registry
+            .get_mut("namespaces")
+            .and_then(|namespaces| namespaces.as_array_mut())
+            .and_then(|namespaces| namespaces[1].as_table_mut())
+            .unwrap()
+            .insert("namespace".into(), 10.into());
+        Genesis::from_toml(&toml.to_string()).unwrap_err();
     }
 
     #[test]
@@ -1256,4 +1703,132 @@ mod test {
 
         toml::from_str::<Genesis>(&toml).unwrap();
     }
+
+    #[test]
+    fn test_genesis_builder() {
+        let chain_config = ChainConfig::default();
+        let upgrade = |start_proposing_view, upgrade_type| Upgrade {
+            mode: UpgradeMode::View(ViewBasedUpgrade {
+                start_proposing_view,
+                stop_proposing_view: start_proposing_view + 10,
+                start_voting_view: None,
+                stop_voting_view: None,
+            }),
+            upgrade_type,
+        };
+        let builder = GenesisBuilder::new(
+            V0_1::version(),
+            chain_config,
+            L1Finalized::Number { number: 5 },
+        )
+        .stake_table_capacity(20)
+        .account(FeeAccount::default(), 100.into())
+        .epochs(20, Some(1));
+
+        let genesis = builder
+            .clone()
+            .upgrade(
+                FeeVersion::version(),
+                upgrade(5, UpgradeType::Fee { chain_config }),
+            )
+            .upgrade(
+                EpochVersion::version(),
+                upgrade(20, UpgradeType::Epoch { chain_config }),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(genesis.upgrade_version, EpochVersion::version());
+
+        // The document round trips through TOML.
+        let toml = genesis.to_toml().unwrap();
+        let parsed = Genesis::from_toml(&toml).unwrap();
+        assert_eq!(parsed.to_toml().unwrap(), toml);
+        assert_eq!(parsed.stake_table, StakeTableConfig { capacity: 20 });
+        assert_eq!(parsed.l1_finalized, L1Finalized::Number { number: 5 });
+        assert_eq!(parsed.upgrades, genesis.upgrades);
+
+        // Upgrades must be scheduled in order.
+        builder
+            .clone()
+            .upgrade(
+                FeeVersion::version(),
+                upgrade(20, UpgradeType::Fee { chain_config }),
+            )
+            .upgrade(
+                EpochVersion::version(),
+                upgrade(5, UpgradeType::Epoch { chain_config }),
+            )
+            .build()
+            .unwrap_err();
+
+        // Upgrades must match their versions.
+        builder
+            .clone()
+            .upgrade(
+                FeeVersion::version(),
+                upgrade(5, UpgradeType::Epoch { chain_config }),
+            )
+            .build()
+            .unwrap_err();
+
+        // Upgrades may not change the chain ID.
+        let mut other_chain = chain_config;
+        other_chain.chain_id = (chain_config.chain_id.0 + U256::from(1)).into();
+        builder
+            .clone()
+            .upgrade(
+                FeeVersion::version(),
+                upgrade(
+                    5,
+                    UpgradeType::Fee {
+                        chain_config: other_chain,
+                    },
+                ),
+            )
+            .build()
+            .unwrap_err();
+
+        // Block limits can only be set in the version which introduced them.
+        let limited = ChainConfig {
+            max_block_transactions: Some(100),
+            ..chain_config
+        };
+        builder
+            .clone()
+            .upgrade(
+                EpochVersion::version(),
+                upgrade(
+                    5,
+                    UpgradeType::Epoch {
+                        chain_config: limited,
+                    },
+                ),
+            )
+            .build()
+            .unwrap_err();
+        builder
+            .clone()
+            .upgrade(
+                EpochVersion::version(),
+                upgrade(5, UpgradeType::Epoch { chain_config }),
+            )
+            .upgrade(
+                BlockLimitsVersion::version(),
+                upgrade(
+                    20,
+                    UpgradeType::BlockLimits {
+                        chain_config: limited,
+                    },
+                ),
+            )
+            .build()
+            .unwrap();
+
+        // Epoch versions require an epoch height.
+        let mut genesis = genesis;
+        genesis.epoch_height = None;
+        genesis.epoch_start_block = None;
+        genesis.validate().unwrap_err();
+        Genesis::from_toml(&genesis.to_toml().unwrap()).unwrap_err();
+    }
 }
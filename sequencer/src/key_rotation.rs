@@ -0,0 +1,86 @@
+//! Rotation of this node's consensus keys.
+//!
+//! A validator rotates its consensus keys by registering new ones on the L1 stake table, without
+//! exiting and re-registering its stake. The new keys take effect from the first epoch whose stake
+//! table includes them, and the membership identifies the validator by either key around the
+//! transition. A node configured with its next keys watches for the start of that epoch, and then
+//! restarts consensus under the new keys. After a restart of the process, the node picks whichever
+//! keys are active in the stake table of the latest decided epoch.
+
+use std::{pin::pin, sync::Arc};
+
+use async_lock::RwLock;
+use espresso_types::{
+    traits::{MembershipPersistence, SequencerPersistence},
+    EpochCommittees, SeqTypes,
+};
+use futures::stream::{Stream, StreamExt};
+use hotshot::types::{BLSPubKey, Event, EventType};
+use hotshot_types::{
+    data::EpochNumber, traits::node_implementation::ConsensusTime, utils::epoch_from_block_number,
+};
+
+/// Whether `key` is in the stake table for the epoch of the latest leaf decided before the node
+/// last stopped.
+pub async fn key_active<P>(storage: &P, epoch_height: u64, key: BLSPubKey) -> anyhow::Result<bool>
+where
+    P: SequencerPersistence + MembershipPersistence,
+{
+    if epoch_height == 0 {
+        return Ok(false);
+    }
+    let Some((leaf, _)) = storage.load_anchor_leaf().await? else {
+        return Ok(false);
+    };
+    let epoch = EpochNumber::new(epoch_from_block_number(leaf.height(), epoch_height));
+    let Some(validators) = storage.load_stake(epoch).await? else {
+        return Ok(false);
+    };
+    Ok(validators
+        .values()
+        .any(|validator| validator.stake_table_key == key))
+}
+
+/// Wait for `key` to become active, that is, for a leaf to be decided in an epoch whose stake table
+/// includes it.
+///
+/// Returns `false` if the event stream ends first.
+pub async fn wait_for_activation(
+    events: impl Stream<Item = Event<SeqTypes>>,
+    membership: Arc<RwLock<EpochCommittees>>,
+    epoch_height: u64,
+    key: BLSPubKey,
+) -> bool {
+    if epoch_height == 0 {
+        // Without epochs, the stake table never changes.
+        return false;
+    }
+
+    let mut events = pin!(events);
+    while let Some(event) = events.next().await {
+        let EventType::Decide { leaf_chain, .. } = event.event else {
+            continue;
+        };
+        // The leaf chain is ordered from newest to oldest.
+        let Some(info) = leaf_chain.first() else {
+            continue;
+        };
+        let epoch = EpochNumber::new(epoch_from_block_number(info.leaf.height(), epoch_height));
+        // Check the stake table as registered, since the membership also recognizes a rotated key
+        // in the epoch before it takes effect.
+        let registered = membership
+            .read()
+            .await
+            .validators(&epoch)
+            .is_ok_and(|validators| {
+                validators
+                    .values()
+                    .any(|validator| validator.stake_table_key == key)
+            });
+        if registered {
+            tracing::warn!(%epoch, %key, "next consensus key is active");
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,260 @@
+//! Encrypted storage of private keys.
+//!
+//! A keystore holds the contents of a key file (the private keys of a node, in .env format),
+//! encrypted under a passphrase, so that the keys do not sit on the host unencrypted. The encryption
+//! key is derived from the passphrase with PBKDF2-HMAC-SHA256, and the key file is encrypted with
+//! AES-256-GCM, which also authenticates it, so a wrong passphrase is detected rather than yielding
+//! garbage keys. The keystore itself is a JSON document recording the parameters needed to decrypt
+//! it.
+
+use std::{collections::HashMap, fs, io::Write, os::unix::fs::OpenOptionsExt, path::Path};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use alloy::hex;
+use anyhow::{ensure, Context};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Version of the keystore format written by this module
+const VERSION: u32 = 1;
+
+/// PBKDF2 iterations for new keystores, following current OWASP guidance for HMAC-SHA256
+pub const DEFAULT_ROUNDS: u32 = 600_000;
+
+/// Fewest PBKDF2 iterations accepted when reading a keystore, so that a tampered keystore cannot
+/// make a weak passphrase cheap to guess from the ciphertext
+const MIN_ROUNDS: u32 = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum KdfAlgorithm {
+    Pbkdf2HmacSha256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum CipherAlgorithm {
+    Aes256Gcm,
+}
+
+/// Parameters for deriving the encryption key from the passphrase
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Kdf {
+    algorithm: KdfAlgorithm,
+    rounds: u32,
+    /// Hex-encoded salt
+    salt: String,
+}
+
+/// Parameters for decrypting the key file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Cipher {
+    algorithm: CipherAlgorithm,
+    /// Hex-encoded nonce
+    nonce: String,
+}
+
+/// A key file encrypted under a passphrase
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Keystore {
+    version: u32,
+    kdf: Kdf,
+    cipher: Cipher,
+    /// Hex-encoded ciphertext, including the authentication tag
+    ciphertext: String,
+}
+
+impl Keystore {
+    /// Encrypt the key file `contents` under `passphrase`.
+    pub fn encrypt(contents: &[u8], passphrase: &[u8], rounds: u32) -> anyhow::Result<Self> {
+        let mut rng = rand::thread_rng();
+        let mut salt = [0; 16];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0; 12];
+        rng.fill_bytes(&mut nonce);
+
+        let ciphertext = cipher(passphrase, &salt, rounds)
+            .encrypt(Nonce::from_slice(&nonce), contents)
+            .ok()
+            .context("encrypting keystore")?;
+        Ok(Self {
+            version: VERSION,
+            kdf: Kdf {
+                algorithm: KdfAlgorithm::Pbkdf2HmacSha256,
+                rounds,
+                salt: hex::encode(salt),
+            },
+            cipher: Cipher {
+                algorithm: CipherAlgorithm::Aes256Gcm,
+                nonce: hex::encode(nonce),
+            },
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the key file in this keystore.
+    ///
+    /// Fails if `passphrase` is wrong or the keystore has been tampered with.
+    pub fn decrypt(&self, passphrase: &[u8]) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+        ensure!(
+            self.version == VERSION,
+            "unsupported keystore version {}",
+            self.version
+        );
+        ensure!(
+            self.kdf.rounds >= MIN_ROUNDS,
+            "keystore uses too few key derivation rounds ({})",
+            self.kdf.rounds
+        );
+        let salt = hex::decode(&self.kdf.salt).context("malformed salt")?;
+        let nonce = hex::decode(&self.cipher.nonce).context("malformed nonce")?;
+        ensure!(nonce.len() == 12, "malformed nonce");
+        let ciphertext = hex::decode(&self.ciphertext).context("malformed ciphertext")?;
+
+        let contents = cipher(passphrase, &salt, self.kdf.rounds)
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .ok()
+            .context("failed to decrypt keystore: wrong passphrase or corrupted keystore")?;
+        Ok(Zeroizing::new(contents))
+    }
+
+    /// Read a keystore from `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("reading keystore {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("parsing keystore {}", path.display()))
+    }
+
+    /// Write this keystore to `path`.
+    ///
+    /// A new file is only readable and writable by its owner.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .with_context(|| format!("writing keystore {}", path.display()))
+    }
+
+    /// Decrypt the keystore at `path` and parse the key file inside.
+    pub fn open(path: &Path, passphrase: &[u8]) -> anyhow::Result<KeyFileVars> {
+        let contents = Self::load(path)?.decrypt(passphrase)?;
+        KeyFileVars::parse(&contents).context("parsing decrypted key file")
+    }
+}
+
+/// The variables in a key file.
+///
+/// The values are private keys, so they are wiped from memory when this is dropped.
+#[derive(Default)]
+pub struct KeyFileVars(HashMap<String, String>);
+
+impl KeyFileVars {
+    /// Parse the contents of a key file in .env format.
+    pub fn parse(contents: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self(
+            dotenvy::from_read_iter(contents).collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Read the unencrypted key file at `path`.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = Zeroizing::new(
+            fs::read(path).with_context(|| format!("reading key file {}", path.display()))?,
+        );
+        Self::parse(&contents).with_context(|| format!("parsing key file {}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+impl Drop for KeyFileVars {
+    fn drop(&mut self) {
+        for (mut name, mut value) in self.0.drain() {
+            name.zeroize();
+            value.zeroize();
+        }
+    }
+}
+
+/// Read a passphrase from the file at `path`, ignoring a trailing newline.
+pub fn read_passphrase(path: &Path) -> anyhow::Result<Zeroizing<String>> {
+    let mut passphrase = Zeroizing::new(
+        fs::read_to_string(path)
+            .with_context(|| format!("reading passphrase file {}", path.display()))?,
+    );
+    let len = passphrase.trim_end_matches(['\r', '\n']).len();
+    passphrase.truncate(len);
+    ensure!(!passphrase.is_empty(), "passphrase is empty");
+    Ok(passphrase)
+}
+
+fn cipher(passphrase: &[u8], salt: &[u8], rounds: u32) -> Aes256Gcm {
+    let mut key = Zeroizing::new([0; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, rounds, &mut *key);
+    Aes256Gcm::new((&*key).into())
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let contents = b"ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY=BLS_SIGNING_KEY~abc\n\
+                         ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY=SCHNORR_SIGNING_KEY~def\n";
+        let keystore = Keystore::encrypt(contents, b"hunter2", MIN_ROUNDS).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        keystore.save(&path).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let vars = Keystore::open(&path, b"hunter2").unwrap();
+        assert_eq!(
+            vars.get("ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY"),
+            Some("BLS_SIGNING_KEY~abc")
+        );
+        assert_eq!(
+            vars.get("ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY"),
+            Some("SCHNORR_SIGNING_KEY~def")
+        );
+
+        // A wrong passphrase is detected.
+        Keystore::open(&path, b"hunter3").unwrap_err();
+
+        // So is tampering with the key derivation parameters.
+        let mut weak = keystore.clone();
+        weak.kdf.rounds = 1;
+        weak.decrypt(b"hunter2").unwrap_err();
+        let mut tampered = keystore;
+        tampered.kdf.rounds += 1;
+        tampered.decrypt(b"hunter2").unwrap_err();
+    }
+
+    #[test]
+    fn test_read_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passphrase");
+        fs::write(&path, "correct horse\n").unwrap();
+        assert_eq!(read_passphrase(&path).unwrap().as_str(), "correct horse");
+
+        fs::write(&path, "\n").unwrap();
+        read_passphrase(&path).unwrap_err();
+    }
+}
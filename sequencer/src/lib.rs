@@ -1,13 +1,32 @@
+mod alerts;
 pub mod api;
+pub mod audit;
+pub mod bootstrap;
+mod bounded_map;
+mod builder_registry;
 pub mod catchup;
+mod cdn_metrics;
 pub mod context;
+pub mod doctor;
+pub mod encryption;
+pub mod fast_sync;
 pub mod genesis;
+pub mod key_rotation;
+pub mod keystore;
+mod network_reload;
+pub mod notification;
+pub mod pending_transactions;
 mod proposal_fetcher;
+pub mod remote_signer;
 mod request_response;
+#[cfg(feature = "slashing")]
+pub mod slashing;
 
 mod external_event_handler;
 pub mod options;
 pub mod state_signature;
+pub mod transaction_status;
+mod upgrade_readiness;
 
 mod restart_tests;
 
@@ -18,26 +37,33 @@ use std::sync::Arc;
 use alloy::primitives::U256;
 use anyhow::Context;
 use async_lock::RwLock;
+use bootstrap::BootstrapDocument;
 use catchup::StatePeers;
+use cdn_metrics::CdnMetricsBridge;
+use committable::Committable;
 use context::SequencerContext;
 use espresso_types::{
     traits::{EventConsumer, MembershipPersistence},
-    BackoffParams, EpochCommittees, L1ClientOptions, NodeState, PubKey, SeqTypes,
+    BackoffParams, EpochCommittees, KeyShare, L1ClientOptions, NodeState, PubKey, SeqTypes,
     SolverAuctionResultsProvider, ValidatedState,
 };
+use fast_sync::{FastSync, FastSyncConfig};
 use genesis::L1Finalized;
 // Should move `STAKE_TABLE_CAPACITY` in the sequencer repo when we have variate stake table support
 use hotshot_libp2p_networking::network::behaviours::dht::store::persistent::DhtNoPersistence;
 use libp2p::Multiaddr;
-use network::libp2p::split_off_peer_id;
+use network::{libp2p::split_off_peer_id, FailoverConfig};
+use network_reload::NetworkReloader;
 use options::Identity;
 use proposal_fetcher::ProposalFetcherConfig;
+use remote_signer::RemoteSigner;
 use tokio::select;
 use tracing::info;
 use url::Url;
 pub mod persistence;
 pub mod state;
-use std::{fmt::Debug, marker::PhantomData, time::Duration};
+pub mod state_sync;
+use std::{fmt::Debug, marker::PhantomData, path::PathBuf, time::Duration};
 
 use derivative::Derivative;
 use espresso_types::v0::traits::SequencerPersistence;
@@ -53,14 +79,16 @@ use hotshot::{
 };
 use hotshot_orchestrator::client::{get_complete_config, OrchestratorClient};
 use hotshot_types::{
-    data::ViewNumber,
+    data::{Leaf2, ViewNumber},
     epoch_membership::EpochMembershipCoordinator,
     light_client::{StateKeyPair, StateSignKey},
+    message::UpgradeLock,
     signature_key::{BLSPrivKey, BLSPubKey},
     traits::{
         metrics::{Metrics, NoMetrics},
         network::ConnectedNetwork,
         node_implementation::{NodeImplementation, NodeType, Versions},
+        ValidatedState as _,
     },
     utils::BuilderCommitment,
     ValidatorConfig,
@@ -107,13 +135,28 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence> NodeImplementation<Se
 pub struct NetworkParams {
     /// The address where a CDN marshal is located
     pub cdn_endpoint: String,
+    /// Prometheus endpoints of CDN components whose metrics are re-exported by the node
+    pub cdn_metrics_urls: Vec<Url>,
+    /// Time between scrapes of the CDN metrics endpoints
+    pub cdn_metrics_interval: Duration,
+    /// File holding network configuration to reload on SIGHUP
+    pub network_reload_config: Option<PathBuf>,
     pub orchestrator_url: Url,
     pub state_relay_server_url: Url,
+    /// Remote signer to sign consensus messages and light client states with, if any
+    pub remote_signer: Option<Arc<RemoteSigner<SequencerApiVersion>>>,
     pub private_staking_key: BLSPrivKey,
     pub private_state_key: StateSignKey,
+    /// This node's share of the threshold encryption key, if it has one
+    pub encryption_key_share: Option<KeyShare>,
     pub state_peers: Vec<Url>,
     pub config_peers: Option<Vec<Url>>,
+    /// Verified document from which to derive the network config of a fresh network, instead of
+    /// the orchestrator
+    pub bootstrap_document: Option<BootstrapDocument>,
     pub catchup_backoff: BackoffParams,
+    /// Syncing of missed blocks from `state_peers` before consensus starts
+    pub fast_sync: FastSyncConfig,
     /// The address to advertise as our public API's URL
     pub public_api_url: Option<Url>,
 
@@ -183,6 +226,9 @@ pub struct NetworkParams {
 
     /// Minimum number of Libp2p peers to emit gossip to during a heartbeat
     pub libp2p_gossip_lazy: usize,
+
+    /// When the combined network falls back from the CDN to Libp2p
+    pub failover: FailoverConfig,
 }
 
 pub struct L1Params {
@@ -284,6 +330,9 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
     info!("Libp2p bind address: {}", libp2p_bind_address);
     info!("Libp2p advertise address: {}", libp2p_advertise_address);
 
+    let encryption_key = genesis.encryption.clone();
+    let namespace_registries = genesis.namespace_registries()?;
+
     // Orchestrator client
     let orchestrator_client = OrchestratorClient::new(network_params.orchestrator_url);
     let state_key_pair = StateKeyPair::from_sign_key(network_params.private_state_key);
@@ -305,16 +354,23 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
     // Print the libp2p public key
     info!("Starting Libp2p with PeerID: {}", libp2p_public_key);
 
+    let l1_client = l1_params
+        .options
+        .with_metrics(metrics)
+        .connect(l1_params.urls)
+        .with_context(|| "failed to create L1 client")?;
+
     let (mut network_config, wait_for_orchestrator) = match (
         persistence.load_config().await?,
         network_params.config_peers,
+        network_params.bootstrap_document,
     ) {
-        (Some(config), _) => {
+        (Some(config), ..) => {
             tracing::info!("loaded network config from storage, rejoining existing network");
             (config, false)
         },
         // If we were told to fetch the config from an already-started peer, do so.
-        (None, Some(peers)) => {
+        (None, Some(peers), _) => {
             tracing::info!(?peers, "loading network config from peers");
             let peers = StatePeers::<SequencerApiVersion>::from_urls(
                 peers,
@@ -331,8 +387,27 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
             persistence.save_config(&config).await?;
             (config, false)
         },
+        // If we were given a bootstrap document, derive the config from it and the L1 stake table.
+        (None, None, Some(document)) => {
+            tracing::info!(
+                contract = %document.stake_table_contract,
+                l1_block = document.l1_block,
+                "deriving network config from bootstrap document"
+            );
+            let config = document
+                .network_config(&l1_client, &validator_config)
+                .await?;
+
+            tracing::info!(
+                node_id = config.node_index,
+                stake_table = ?config.config.known_nodes_with_stake,
+                "loaded config",
+            );
+            persistence.save_config(&config).await?;
+            (config, false)
+        },
         // Otherwise, this is a fresh network; load from the orchestrator.
-        (None, None) => {
+        (None, None, None) => {
             tracing::info!("loading network config from orchestrator");
             tracing::error!(
                 "waiting for other nodes to connect, DO NOT RESTART until fully connected"
@@ -443,11 +518,6 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
         response_size_maximum: network_params.libp2p_max_direct_transmit_size,
     };
 
-    let l1_client = l1_params
-        .options
-        .with_metrics(metrics)
-        .connect(l1_params.urls)
-        .with_context(|| "failed to create L1 client")?;
     genesis.validate_fee_contract(&l1_client).await?;
 
     l1_client.spawn_tasks().await;
@@ -470,6 +540,7 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
         genesis_state.prefund_account(address, amount);
     }
 
+    let fast_sync_peers = network_params.state_peers.clone();
     let peers = catchup::local_and_remote(
         persistence.clone(),
         StatePeers::<SequencerApiVersion>::from_urls(
@@ -506,10 +577,44 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
         epoch_height: Some(epoch_height),
         peers,
         coordinator: coordinator.clone(),
+        namespace_registries: namespace_registries
+            .into_iter()
+            .map(|registry| (registry.commit(), Arc::new(registry)))
+            .collect(),
     };
 
+    // If we are far behind, sync the blocks we missed before starting consensus.
+    if network_params.fast_sync.enabled {
+        let anchor = match persistence.load_anchor_leaf().await? {
+            Some((leaf, _)) => leaf,
+            None => {
+                let genesis_state = ValidatedState::genesis(&instance_state).0;
+                Leaf2::genesis::<V>(&genesis_state, &instance_state).await
+            },
+        };
+        let upgrade_lock =
+            UpgradeLock::from_certificate(&persistence.load_upgrade_certificate().await?);
+        let fast_sync = FastSync::new(
+            network_params.fast_sync,
+            StatePeers::<SequencerApiVersion>::from_urls(
+                fast_sync_peers,
+                network_params.catchup_backoff,
+                &NoMetrics,
+            ),
+            coordinator.clone(),
+            upgrade_lock,
+        );
+        match fast_sync.run(anchor, &persistence, &event_consumer).await {
+            Ok(Some(height)) => tracing::info!(height, "fast sync complete"),
+            Ok(None) => tracing::info!("not far enough behind to fast sync"),
+            Err(err) => {
+                tracing::warn!("fast sync failed, catching up through consensus: {err:#}")
+            },
+        }
+    }
+
     // Initialize the Libp2p network
-    let network = {
+    let network: Arc<CombinedNetworks<SeqTypes>> = {
         let p2p_network = Libp2pNetwork::from_config(
             network_config.clone(),
             DhtNoPersistence,
@@ -542,13 +647,17 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
         };
 
         // Combine the CDN and P2P networks
-        Arc::from(CombinedNetworks::new(
-            cdn_network,
-            p2p_network,
-            Some(Duration::from_secs(1)),
-        ))
+        Arc::from(
+            CombinedNetworks::new(cdn_network, p2p_network, Some(Duration::from_secs(1)))
+                .with_failover_policy(network_params.failover.into())
+                .with_metrics(metrics),
+        )
     };
 
+    let reloader_network = network_params
+        .network_reload_config
+        .map(|path| (path, Arc::clone(&network)));
+
     let mut ctx = SequencerContext::init(
         network_config,
         validator_config,
@@ -557,6 +666,7 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
         persistence,
         network,
         Some(network_params.state_relay_server_url),
+        network_params.remote_signer,
         metrics,
         genesis.stake_table.capacity,
         event_consumer,
@@ -568,6 +678,23 @@ pub async fn init_node<P: SequencerPersistence + MembershipPersistence, V: Versi
     if wait_for_orchestrator {
         ctx = ctx.wait_for_orchestrator(orchestrator_client);
     }
+    if !network_params.cdn_metrics_urls.is_empty() {
+        let bridge = CdnMetricsBridge::new(
+            network_params.cdn_metrics_urls,
+            network_params.cdn_metrics_interval,
+            metrics,
+        );
+        ctx.spawn("CDN metrics bridge", bridge.run());
+    }
+    if let Some((path, network)) = reloader_network {
+        let bandwidth = Arc::clone(&ctx.consensus().read().await.hotshot.bandwidth);
+        let reloader = NetworkReloader::new(path, network, bandwidth);
+        ctx.spawn("network config reloader", reloader.run());
+    }
+    if let Some(key) = encryption_key {
+        ctx.enable_decryption(key, network_params.encryption_key_share)
+            .await;
+    }
     Ok(ctx)
 }
 
@@ -925,6 +1052,13 @@ pub mod testing {
                 stop_voting_time: 0,
                 epoch_height: 300,
                 epoch_start_block: 1,
+                da_payload_hint_threshold: None,
+                da_payload_hint_urls: vec![],
+                compression: None,
+                bandwidth: Default::default(),
+                local_builder: None,
+                adaptive_view_timeout: None,
+                transaction_dedup: None,
             };
 
             Self {
@@ -1147,6 +1281,7 @@ pub mod testing {
                 persistence,
                 network,
                 self.state_relay_url.clone(),
+                None,
                 metrics,
                 stake_table_capacity,
                 event_consumer,
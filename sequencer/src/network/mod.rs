@@ -1,4 +1,6 @@
-use espresso_types::PubKey;
+use clap::Parser;
+use espresso_types::{parse_duration, PubKey};
+use hotshot::traits::implementations::FailoverPolicy;
 
 use super::*;
 
@@ -8,3 +10,87 @@ pub mod libp2p;
 pub type Production = CombinedNetworks<SeqTypes>;
 
 pub type Memory = MemoryNetwork<PubKey>;
+
+/// When the combined network considers the CDN down for a class of messages and sends them over
+/// Libp2p without delay.
+#[derive(Clone, Copy, Debug, Parser)]
+pub struct FailoverConfig {
+    /// Sends over the CDN taking longer than this count as failures.
+    #[clap(
+        long = "failover-max-primary-latency",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_MAX_PRIMARY_LATENCY",
+        default_value = "1s",
+        value_parser = parse_duration,
+    )]
+    pub max_primary_latency: Duration,
+
+    /// Time to wait for a direct message to be sent before falling back to the next route.
+    #[clap(
+        long = "failover-direct-message-timeout",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_DIRECT_MESSAGE_TIMEOUT",
+        default_value = "2s",
+        value_parser = parse_duration,
+    )]
+    pub direct_message_timeout: Duration,
+
+    /// Number of most recent sends the CDN error rate is computed over.
+    #[clap(
+        long = "failover-window",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_WINDOW",
+        default_value = "10"
+    )]
+    pub window: usize,
+
+    /// Error rate above which the CDN is considered down.
+    #[clap(
+        long = "failover-max-error-rate",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_MAX_ERROR_RATE",
+        default_value = "0.5"
+    )]
+    pub max_error_rate: f64,
+
+    /// Error rate below which the CDN is considered up again.
+    #[clap(
+        long = "failover-recovery-error-rate",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_RECOVERY_ERROR_RATE",
+        default_value = "0.2"
+    )]
+    pub recovery_error_rate: f64,
+
+    /// Minimum time the CDN is considered down before it can be considered up again.
+    #[clap(
+        long = "failover-min-down-duration",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_MIN_DOWN_DURATION",
+        default_value = "10s",
+        value_parser = parse_duration,
+    )]
+    pub min_down_duration: Duration,
+
+    /// While the CDN is down, one message in this many is still sent over it first.
+    #[clap(
+        long = "failover-probe-interval",
+        env = "ESPRESSO_SEQUENCER_FAILOVER_PROBE_INTERVAL",
+        default_value = "50"
+    )]
+    pub probe_interval: u64,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self::parse_from(std::iter::empty::<String>())
+    }
+}
+
+impl From<FailoverConfig> for FailoverPolicy {
+    fn from(config: FailoverConfig) -> Self {
+        Self {
+            max_primary_latency: config.max_primary_latency,
+            direct_message_timeout: config.direct_message_timeout,
+            window: config.window,
+            max_error_rate: config.max_error_rate,
+            recovery_error_rate: config.recovery_error_rate,
+            min_down_duration: config.min_down_duration,
+            probe_interval: config.probe_interval,
+        }
+    }
+}
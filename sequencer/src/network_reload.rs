@@ -0,0 +1,149 @@
+//! Reload part of the network configuration of a running node.
+//!
+//! On `SIGHUP`, the node re-reads a TOML file holding the subset of its network configuration
+//! which can safely change at runtime: additional Libp2p bootstrap nodes, the endpoint of the CDN
+//! marshal and the per-class bandwidth limits. Settings absent from the file are left as they are.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use hotshot::traits::implementations::CombinedNetworks;
+use hotshot_types::bandwidth::{BandwidthAccounting, BandwidthConfig};
+use libp2p::Multiaddr;
+use serde::Deserialize;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{network::libp2p::split_off_peer_id, SeqTypes};
+
+/// The network configuration which can be reloaded at runtime
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReloadableNetworkConfig {
+    /// Libp2p bootstrap nodes to add, including their peer IDs
+    #[serde(default)]
+    pub libp2p_bootstrap_nodes: Vec<Multiaddr>,
+    /// The address of the CDN marshal to connect through
+    pub cdn_endpoint: Option<String>,
+    /// The bandwidth limits, replacing the current ones
+    pub bandwidth: Option<BandwidthConfig>,
+}
+
+impl ReloadableNetworkConfig {
+    fn parse(text: &str) -> anyhow::Result<Self> {
+        toml::from_str(text).context("malformed network configuration")
+    }
+}
+
+/// Applies the configuration file at `path` to the network each time the node receives `SIGHUP`
+pub struct NetworkReloader {
+    path: PathBuf,
+    network: Arc<CombinedNetworks<SeqTypes>>,
+    bandwidth: Arc<BandwidthAccounting>,
+}
+
+impl NetworkReloader {
+    pub fn new(
+        path: PathBuf,
+        network: Arc<CombinedNetworks<SeqTypes>>,
+        bandwidth: Arc<BandwidthAccounting>,
+    ) -> Self {
+        Self {
+            path,
+            network,
+            bandwidth,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(err) => {
+                tracing::error!("failed to install SIGHUP handler, network config will not be reloaded: {err:#}");
+                return;
+            },
+        };
+        while hangups.recv().await.is_some() {
+            tracing::info!(path = %self.path.display(), "reloading network config");
+            if let Err(err) = self.reload().await {
+                tracing::error!(path = %self.path.display(), "failed to reload network config: {err:#}");
+            }
+        }
+    }
+
+    async fn reload(&self) -> anyhow::Result<()> {
+        let text = fs::read_to_string(&self.path).context("reading network configuration")?;
+        let config = ReloadableNetworkConfig::parse(&text)?;
+
+        // Validate everything before applying anything, so a bad file changes nothing
+        let bootstrap_nodes = config
+            .libp2p_bootstrap_nodes
+            .into_iter()
+            .map(split_off_peer_id)
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to parse peer ID from bootstrap node")?;
+
+        if let Some(bandwidth) = &config.bandwidth {
+            self.bandwidth.set_limits(bandwidth);
+            tracing::info!(?bandwidth, "updated bandwidth limits");
+        }
+        if let Some(endpoint) = config.cdn_endpoint {
+            tracing::info!(endpoint, "switching CDN marshal");
+            self.network.primary().set_marshal_endpoint(endpoint);
+        }
+        if !bootstrap_nodes.is_empty() {
+            tracing::info!(?bootstrap_nodes, "adding Libp2p bootstrap nodes");
+            self.network
+                .secondary()
+                .add_bootstrap_nodes(bootstrap_nodes)
+                .await
+                .context("adding Libp2p bootstrap nodes")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::bandwidth::TrafficClass;
+    use libp2p::PeerId;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_reloadable_network_config() {
+        assert_eq!(
+            ReloadableNetworkConfig::parse("").unwrap(),
+            ReloadableNetworkConfig::default()
+        );
+
+        let peer_id = PeerId::random();
+        let config = ReloadableNetworkConfig::parse(&format!(
+            r#"
+            libp2p_bootstrap_nodes = ["/ip4/127.0.0.1/udp/3000/quic-v1/p2p/{peer_id}"]
+            cdn_endpoint = "marshal.example.com:1737"
+
+            [bandwidth.limits.vid]
+            bytes_per_second = 1000000
+            burst = 4000000
+            "#
+        ))
+        .unwrap();
+        assert_eq!(
+            split_off_peer_id(config.libp2p_bootstrap_nodes[0].clone())
+                .unwrap()
+                .0,
+            peer_id
+        );
+        assert_eq!(
+            config.cdn_endpoint.as_deref(),
+            Some("marshal.example.com:1737")
+        );
+        assert!(config
+            .bandwidth
+            .unwrap()
+            .limits
+            .contains_key(&TrafficClass::Vid));
+
+        ReloadableNetworkConfig::parse("cdn_endpont = \"typo\"").unwrap_err();
+    }
+}
@@ -3,17 +3,18 @@
 use core::fmt::Display;
 use std::{
     cmp::Ordering,
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     fmt::{self, Formatter},
     iter::once,
     path::PathBuf,
+    sync::{Arc, OnceLock},
     time::Duration,
 };
 
 use anyhow::{bail, Context};
-use clap::{error::ErrorKind, Args, FromArgMatches, Parser};
+use clap::{error::ErrorKind, Args, FromArgMatches, Parser, Subcommand};
 use derivative::Derivative;
-use espresso_types::{parse_duration, BackoffParams, L1ClientOptions};
+use espresso_types::{parse_duration, BackoffParams, KeyShare, L1ClientOptions, PubKey};
 use hotshot_types::{light_client::StateSignKey, signature_key::BLSPrivKey};
 use jf_signature::{bls_over_bn254, schnorr};
 use libp2p::Multiaddr;
@@ -21,7 +22,19 @@ use sequencer_utils::logging;
 use tagged_base64::TaggedBase64;
 use url::Url;
 
-use crate::{api, persistence, proposal_fetcher::ProposalFetcherConfig};
+use crate::{
+    api,
+    audit::AuditOptions,
+    bootstrap::BootstrapDocument,
+    fast_sync::FastSyncConfig,
+    keystore::{self, KeyFileVars, Keystore},
+    network::FailoverConfig,
+    notification::NotificationOptions,
+    persistence,
+    proposal_fetcher::ProposalFetcherConfig,
+    remote_signer::{RemoteSigner, SignerSecret},
+    SequencerApiVersion,
+};
 
 // This options struct is a bit unconventional. The sequencer has multiple optional modules which
 // can be added, in any combination, to the service. These include, for example, the API server.
@@ -248,6 +261,33 @@ pub struct Options {
     #[derivative(Debug(format_with = "Display::fmt"))]
     pub state_relay_server_url: Url,
 
+    /// URL of a remote signer holding the private keys of this node.
+    ///
+    /// If set, consensus messages and light client states are signed by this signer instead of
+    /// locally. The private keys must still be provided, since the node authenticates itself to
+    /// the network with its consensus key. The URL is either an HTTP URL or
+    /// `unix://` followed by the path of a Unix domain socket. Such a signer can be run with the
+    /// `remote-signer` utility program.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_REMOTE_SIGNER_URL",
+        requires = "remote_signer_secret_file"
+    )]
+    pub remote_signer_url: Option<Url>,
+
+    /// Path to a file containing the secret shared with the remote signer.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_REMOTE_SIGNER_SECRET_FILE")]
+    pub remote_signer_secret_file: Option<PathBuf>,
+
+    /// How long to wait for the remote signer to answer a request before giving up on it.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_REMOTE_SIGNER_TIMEOUT",
+        default_value = "5s",
+        value_parser = parse_duration
+    )]
+    pub remote_signer_timeout: Duration,
+
     /// URL of the Auction Results Solver
     #[clap(
         long,
@@ -279,6 +319,30 @@ pub struct Options {
     #[derivative(Debug(format_with = "Display::fmt"))]
     pub fallback_builder_url: Url,
 
+    /// TOML file holding the registry of builders the node accepts marketplace bundles from
+    ///
+    /// The registry is re-read when the node receives SIGHUP. Without it, the node accepts bundles
+    /// from any builder which wins the auction.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_BUILDER_REGISTRY")]
+    pub builder_registry: Option<PathBuf>,
+
+    /// How long transactions submitted to this node are kept in storage waiting to be sequenced.
+    ///
+    /// Transactions which were submitted but not sequenced when the node stopped are submitted
+    /// again when it restarts, unless they were sequenced while the node was down or are older than
+    /// this.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PENDING_TRANSACTION_RETENTION",
+        default_value = "10m",
+        value_parser = parse_duration
+    )]
+    pub pending_transaction_retention: Duration,
+
+    /// Brokers to publish decided blocks to.
+    #[clap(flatten)]
+    pub notifications: NotificationOptions,
+
     /// Path to TOML file containing genesis state.
     #[clap(
         long,
@@ -298,13 +362,41 @@ pub struct Options {
     #[clap(long, name = "KEY_FILE", env = "ESPRESSO_SEQUENCER_KEY_FILE")]
     pub key_file: Option<PathBuf>,
 
+    /// Path to an encrypted keystore containing private keys.
+    ///
+    /// This can be used as an alternative to KEY_FILE, to avoid keeping private keys unencrypted
+    /// on disk. The keystore holds a key file encrypted under a passphrase, which is read from
+    /// KEYSTORE_PASSPHRASE_FILE. Keystores can be generated with the `keygen` utility program.
+    #[clap(
+        long,
+        name = "KEYSTORE",
+        env = "ESPRESSO_SEQUENCER_KEYSTORE",
+        conflicts_with = "KEY_FILE",
+        requires = "KEYSTORE_PASSPHRASE_FILE"
+    )]
+    pub keystore: Option<PathBuf>,
+
+    /// Path to a file containing the passphrase for KEYSTORE.
+    #[clap(
+        long,
+        name = "KEYSTORE_PASSPHRASE_FILE",
+        env = "ESPRESSO_SEQUENCER_KEYSTORE_PASSPHRASE_FILE"
+    )]
+    pub keystore_passphrase_file: Option<PathBuf>,
+
+    /// The contents of KEY_FILE or KEYSTORE, loaded on first use so that the keystore is only
+    /// decrypted once.
+    #[clap(skip)]
+    #[derivative(Debug = "ignore")]
+    loaded_key_file: Arc<OnceLock<Option<Arc<KeyFileVars>>>>,
+
     /// Private staking key.
     ///
     /// This can be used as an alternative to KEY_FILE.
     #[clap(
         long,
         env = "ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY",
-        conflicts_with = "KEY_FILE"
+        conflicts_with_all = ["KEY_FILE", "KEYSTORE"]
     )]
     #[derivative(Debug = "ignore")]
     pub private_staking_key: Option<TaggedBase64>,
@@ -315,11 +407,46 @@ pub struct Options {
     #[clap(
         long,
         env = "ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY",
-        conflicts_with = "KEY_FILE"
+        conflicts_with_all = ["KEY_FILE", "KEYSTORE"]
     )]
     #[derivative(Debug = "ignore")]
     pub private_state_key: Option<TaggedBase64>,
 
+    /// Private staking key this node will rotate to.
+    ///
+    /// Once the corresponding public key has been registered on the L1 stake table, the node
+    /// switches to this key at the start of the first epoch whose stake table includes it.
+    ///
+    /// This can also be given as ESPRESSO_SEQUENCER_NEXT_PRIVATE_STAKING_KEY in the KEY_FILE.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_NEXT_PRIVATE_STAKING_KEY",
+        requires = "next_private_state_key"
+    )]
+    #[derivative(Debug = "ignore")]
+    pub next_private_staking_key: Option<TaggedBase64>,
+
+    /// Private state signing key this node will rotate to, along with the next staking key.
+    ///
+    /// This can also be given as ESPRESSO_SEQUENCER_NEXT_PRIVATE_STATE_KEY in the KEY_FILE.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_NEXT_PRIVATE_STATE_KEY",
+        requires = "next_private_staking_key"
+    )]
+    #[derivative(Debug = "ignore")]
+    pub next_private_state_key: Option<TaggedBase64>,
+
+    /// Share of the DA committee's threshold encryption key held by this node.
+    ///
+    /// Only needed if the genesis file configures an encryption key. Nodes without a share still
+    /// decrypt encrypted transactions, using the decryption shares broadcast by the committee.
+    ///
+    /// This can also be given as ESPRESSO_SEQUENCER_ENCRYPTION_KEY_SHARE in the KEY_FILE.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ENCRYPTION_KEY_SHARE")]
+    #[derivative(Debug = "ignore")]
+    pub encryption_key_share: Option<KeyShare>,
+
     /// Add optional modules to the service.
     ///
     /// Modules are added by specifying the name of the module followed by it's arguments, as in
@@ -351,6 +478,15 @@ pub struct Options {
     #[clap(flatten)]
     pub l1_options: L1ClientOptions,
 
+    /// Submission of double vote evidence to the L1 slashing contract.
+    #[cfg(feature = "slashing")]
+    #[clap(flatten)]
+    pub slashing: crate::slashing::SlashingConfig,
+
+    /// Audit log of every proposal and vote sent or received.
+    #[clap(flatten)]
+    pub audit: AuditOptions,
+
     /// Whether or not we are a DA node.
     #[clap(long, env = "ESPRESSO_SEQUENCER_IS_DA", action)]
     pub is_da: bool,
@@ -360,6 +496,33 @@ pub struct Options {
     #[derivative(Debug(format_with = "fmt_urls"))]
     pub state_peers: Vec<Url>,
 
+    /// Prometheus metrics endpoints of CDN brokers and marshals
+    ///
+    /// The metrics scraped from these endpoints are re-exported through the metrics of this node,
+    /// under the `cdn` prefix.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CDN_METRICS_URLS",
+        value_delimiter = ','
+    )]
+    #[derivative(Debug(format_with = "fmt_urls"))]
+    pub cdn_metrics_urls: Vec<Url>,
+
+    /// Time between scrapes of the CDN metrics endpoints
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CDN_METRICS_INTERVAL",
+        default_value = "15s",
+        value_parser = parse_duration
+    )]
+    pub cdn_metrics_interval: Duration,
+
+    /// TOML file holding network configuration to apply when the node receives SIGHUP
+    ///
+    /// The file may set `libp2p_bootstrap_nodes`, `cdn_endpoint` and `bandwidth`.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_NETWORK_RELOAD_CONFIG")]
+    pub network_reload_config: Option<PathBuf>,
+
     /// Peer nodes use to fetch missing config
     ///
     /// Typically, the network-wide config is fetched from the orchestrator on startup and then
@@ -370,6 +533,23 @@ pub struct Options {
     #[derivative(Debug(format_with = "fmt_opt_urls"))]
     pub config_peers: Option<Vec<Url>>,
 
+    /// Signed document from which to derive the network config, instead of the orchestrator
+    ///
+    /// When joining a fresh network, the node reads the initial stake table from the L1 stake
+    /// table contract named in the document, instead of registering with the orchestrator and
+    /// waiting for it to hand out the config. The document must be signed by BOOTSTRAP_SIGNER,
+    /// with the signature in a file next to it of the same name with `.sig` appended.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_BOOTSTRAP_DOCUMENT",
+        requires = "bootstrap_signer"
+    )]
+    pub bootstrap_document: Option<PathBuf>,
+
+    /// Public key trusted to sign the bootstrap document
+    #[clap(long, env = "ESPRESSO_SEQUENCER_BOOTSTRAP_SIGNER")]
+    pub bootstrap_signer: Option<TaggedBase64>,
+
     /// Exponential backoff for fetching missing state from peers.
     #[clap(flatten)]
     pub catchup_backoff: BackoffParams,
@@ -382,6 +562,39 @@ pub struct Options {
 
     #[clap(flatten)]
     pub proposal_fetcher_config: ProposalFetcherConfig,
+
+    #[clap(flatten)]
+    pub fast_sync: FastSyncConfig,
+
+    #[clap(flatten)]
+    pub failover: FailoverConfig,
+}
+
+/// Command line of the sequencer.
+///
+/// Without a command, the node runs with the given options.
+#[derive(Parser, Debug)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(flatten)]
+    pub options: Options,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect the configuration of the node.
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Check the configuration given by the same options the node would run with, instead of
+    /// running the node.
+    Doctor(Options),
 }
 
 impl Options {
@@ -389,9 +602,31 @@ impl Options {
         ModuleArgs(self.modules.clone()).parse()
     }
 
+    /// The variables in the key file or keystore, if either was provided.
+    fn key_file_vars(&self) -> anyhow::Result<Option<Arc<KeyFileVars>>> {
+        if let Some(vars) = self.loaded_key_file.get() {
+            return Ok(vars.clone());
+        }
+        let vars = if let Some(path) = &self.keystore {
+            let passphrase_file = self
+                .keystore_passphrase_file
+                .as_ref()
+                .context("keystore passphrase file not provided")?;
+            let passphrase = keystore::read_passphrase(passphrase_file)?;
+            Some(Keystore::open(path, passphrase.as_bytes())?)
+        } else if let Some(path) = &self.key_file {
+            Some(KeyFileVars::read(path)?)
+        } else {
+            None
+        };
+        Ok(self
+            .loaded_key_file
+            .get_or_init(|| vars.map(Arc::new))
+            .clone())
+    }
+
     pub fn private_keys(&self) -> anyhow::Result<(BLSPrivKey, StateSignKey)> {
-        if let Some(path) = &self.key_file {
-            let vars = dotenvy::from_path_iter(path)?.collect::<Result<HashMap<_, _>, _>>()?;
+        if let Some(vars) = self.key_file_vars()? {
             let staking = TaggedBase64::parse(
                 vars.get("ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")
                     .context("key file missing ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")?,
@@ -414,9 +649,73 @@ impl Options {
 
             Ok((staking, state))
         } else {
-            bail!("neither key file nor full set of private keys was provided")
+            bail!("neither key file, keystore nor full set of private keys was provided")
         }
     }
+
+    /// Connect to the remote signer holding the keys of this node, if there is one.
+    pub async fn remote_signer(
+        &self,
+    ) -> anyhow::Result<Option<Arc<RemoteSigner<SequencerApiVersion>>>> {
+        let (Some(url), Some(secret_file)) =
+            (&self.remote_signer_url, &self.remote_signer_secret_file)
+        else {
+            return Ok(None);
+        };
+        let signer = RemoteSigner::connect(
+            url.clone(),
+            SignerSecret::read(secret_file)?,
+            self.remote_signer_timeout,
+        )
+        .await
+        .context("connecting to remote signer")?;
+        Ok(Some(Arc::new(signer)))
+    }
+
+    /// The keys this node will rotate to, if any.
+    pub fn next_private_keys(&self) -> anyhow::Result<Option<(BLSPrivKey, StateSignKey)>> {
+        let (staking, state) = match (
+            self.next_private_staking_key.clone(),
+            self.next_private_state_key.clone(),
+        ) {
+            (Some(staking), Some(state)) => (staking, state),
+            _ => {
+                let Some(vars) = self.key_file_vars()? else {
+                    return Ok(None);
+                };
+                let (Some(staking), Some(state)) = (
+                    vars.get("ESPRESSO_SEQUENCER_NEXT_PRIVATE_STAKING_KEY"),
+                    vars.get("ESPRESSO_SEQUENCER_NEXT_PRIVATE_STATE_KEY"),
+                ) else {
+                    return Ok(None);
+                };
+                (TaggedBase64::parse(staking)?, TaggedBase64::parse(state)?)
+            },
+        };
+        let staking = bls_over_bn254::SignKey::try_from(staking)?;
+        let state = schnorr::SignKey::try_from(state)?;
+        Ok(Some((staking, state)))
+    }
+
+    pub fn encryption_key_share(&self) -> anyhow::Result<Option<KeyShare>> {
+        if let Some(share) = &self.encryption_key_share {
+            return Ok(Some(share.clone()));
+        }
+        let Some(vars) = self.key_file_vars()? else {
+            return Ok(None);
+        };
+        vars.get("ESPRESSO_SEQUENCER_ENCRYPTION_KEY_SHARE")
+            .map(|share| share.parse())
+            .transpose()
+    }
+
+    pub fn bootstrap_document(&self) -> anyhow::Result<Option<BootstrapDocument>> {
+        let (Some(path), Some(signer)) = (&self.bootstrap_document, &self.bootstrap_signer) else {
+            return Ok(None);
+        };
+        let signer = PubKey::try_from(signer)?;
+        Ok(Some(BootstrapDocument::load(path, &signer)?))
+    }
 }
 
 /// Identity represents identifying information concerning the sequencer node.
@@ -543,6 +842,9 @@ impl ModuleArgs {
                 SequencerModule::StorageSql(m) => {
                     curr = m.add(&mut modules.storage_sql, &mut provided)?
                 },
+                SequencerModule::StorageEmbedded(m) => {
+                    curr = m.add(&mut modules.storage_embedded, &mut provided)?
+                },
                 SequencerModule::Http(m) => curr = m.add(&mut modules.http, &mut provided)?,
                 SequencerModule::Query(m) => curr = m.add(&mut modules.query, &mut provided)?,
                 SequencerModule::Submit(m) => curr = m.add(&mut modules.submit, &mut provided)?,
@@ -555,6 +857,7 @@ impl ModuleArgs {
                 SequencerModule::Explorer(m) => {
                     curr = m.add(&mut modules.explorer, &mut provided)?
                 },
+                SequencerModule::Admin(m) => curr = m.add(&mut modules.admin, &mut provided)?,
             }
         }
 
@@ -581,6 +884,7 @@ macro_rules! module {
 
 module!("storage-fs", persistence::fs::Options);
 module!("storage-sql", persistence::sql::Options);
+module!("storage-embedded", persistence::embedded::Options);
 module!("http", api::options::Http);
 module!("query", api::options::Query, requires: "http");
 module!("submit", api::options::Submit, requires: "http");
@@ -589,6 +893,7 @@ module!("catchup", api::options::Catchup, requires: "http");
 module!("config", api::options::Config, requires: "http");
 module!("hotshot-events", api::options::HotshotEvents, requires: "http");
 module!("explorer", api::options::Explorer, requires: "http", "storage-sql");
+module!("admin", api::options::Admin, requires: "http");
 
 #[derive(Clone, Debug, Args)]
 struct Module<Options: ModuleInfo> {
@@ -642,6 +947,11 @@ enum SequencerModule {
     StorageFs(Module<persistence::fs::Options>),
     /// Use a Postgres database for persistent storage.
     StorageSql(Module<persistence::sql::Options>),
+    /// Use an embedded database file for persistent storage.
+    ///
+    /// This does not require a separate database server, which makes it suitable for operators
+    /// running a single node.
+    StorageEmbedded(Module<persistence::embedded::Options>),
     /// Run the query API module.
     ///
     /// This module requires the http module to be started.
@@ -669,12 +979,17 @@ enum SequencerModule {
     ///
     /// This module requires the http and storage-sql modules to be started.
     Explorer(Module<api::options::Explorer>),
+    /// Run the admin API module, for changing the log filter of a running node.
+    ///
+    /// This module requires the http module to be started.
+    Admin(Module<api::options::Admin>),
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct Modules {
     pub storage_fs: Option<persistence::fs::Options>,
     pub storage_sql: Option<persistence::sql::Options>,
+    pub storage_embedded: Option<persistence::embedded::Options>,
     pub http: Option<api::options::Http>,
     pub query: Option<api::options::Query>,
     pub submit: Option<api::options::Submit>,
@@ -683,4 +998,5 @@ pub struct Modules {
     pub config: Option<api::options::Config>,
     pub hotshot_events: Option<api::options::HotshotEvents>,
     pub explorer: Option<api::options::Explorer>,
+    pub admin: Option<api::options::Admin>,
 }
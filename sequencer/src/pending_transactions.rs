@@ -0,0 +1,229 @@
+//! Recovery of submitted transactions which had not been sequenced when the node stopped.
+//!
+//! Every transaction submitted through the API is kept in storage until it appears in a decided
+//! block, or until the retention window passes. When the node restarts, the transactions still in
+//! storage may or may not have been sequenced while the node was down. Once the node has caught up
+//! with consensus, it asks its peers about each of them, and submits again those which no peer
+//! knows to have been sequenced.
+
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_lock::RwLock;
+use committable::{Commitment, Committable};
+use espresso_types::{v0::traits::SequencerPersistence, PubKey, SeqTypes, Transaction};
+use futures::{
+    future::join_all,
+    stream::{Stream, StreamExt},
+};
+use hotshot::types::{Event, EventType};
+use hotshot_query_service::availability::TransactionQueryData;
+use hotshot_types::{
+    event::LeafInfo,
+    traits::{
+        block_contents::BlockHeader, network::ConnectedNetwork, node_implementation::Versions,
+        BlockPayload,
+    },
+};
+use surf_disco::Client;
+use tide_disco::error::ServerError;
+use tokio::time::timeout;
+use url::Url;
+
+use crate::{context::Consensus, SequencerApiVersion};
+
+/// Time to wait for a peer to say whether it knows of a transaction
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The current time, in seconds since the Unix epoch
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Keeps persisted pending transactions up to date, and resubmits them after a restart
+pub struct PendingTransactions<P> {
+    storage: Arc<P>,
+    peers: Vec<Url>,
+    retention: Duration,
+}
+
+impl<P: SequencerPersistence> PendingTransactions<P> {
+    pub fn new(storage: Arc<P>, peers: Vec<Url>, retention: Duration) -> Self {
+        Self {
+            storage,
+            peers,
+            retention,
+        }
+    }
+
+    /// The earliest submission time of transactions still within the retention window
+    fn cutoff(&self) -> u64 {
+        unix_timestamp().saturating_sub(self.retention.as_secs())
+    }
+
+    /// Follow decided blocks, forgetting pending transactions once they are sequenced.
+    ///
+    /// The transactions which were pending when the node started are resubmitted after the first
+    /// decide, unless they have been sequenced in the meantime.
+    pub async fn run<N, V>(
+        self,
+        mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+        consensus: Arc<RwLock<Consensus<N, P, V>>>,
+    ) where
+        N: ConnectedNetwork<PubKey>,
+        V: Versions,
+    {
+        let mut recovered = match self.recover().await {
+            Ok(recovered) => recovered,
+            Err(err) => {
+                tracing::warn!("failed to load pending transactions: {err:#}");
+                vec![]
+            },
+        };
+
+        while let Some(event) = events.next().await {
+            let EventType::Decide { leaf_chain, .. } = event.event else {
+                continue;
+            };
+            let decided = decided_transactions(&leaf_chain);
+            if !decided.is_empty() {
+                if let Err(err) = self
+                    .storage
+                    .remove_pending_transactions(&decided.iter().copied().collect::<Vec<_>>())
+                    .await
+                {
+                    tracing::warn!("failed to remove sequenced pending transactions: {err:#}");
+                }
+            }
+            if let Err(err) = self.storage.prune_pending_transactions(self.cutoff()).await {
+                tracing::warn!("failed to prune pending transactions: {err:#}");
+            }
+
+            if recovered.is_empty() {
+                continue;
+            }
+            let unsequenced = self
+                .unsequenced(std::mem::take(&mut recovered), &decided)
+                .await;
+            tracing::info!(
+                count = unsequenced.len(),
+                "resubmitting pending transactions"
+            );
+            let consensus = consensus.read().await;
+            for tx in unsequenced {
+                if let Err(err) = consensus.submit_transaction(tx.clone()).await {
+                    tracing::warn!(hash = %tx.commit(), "failed to resubmit pending transaction: {err:#}");
+                }
+            }
+        }
+    }
+
+    /// Load the transactions which were pending when the node stopped and are still within the
+    /// retention window.
+    async fn recover(&self) -> anyhow::Result<Vec<Transaction>> {
+        let cutoff = self.cutoff();
+        self.storage.prune_pending_transactions(cutoff).await?;
+        let recovered = self
+            .storage
+            .load_pending_transactions()
+            .await?
+            .into_iter()
+            .filter(|(_, submitted)| *submitted >= cutoff)
+            .map(|(tx, _)| tx)
+            .collect::<Vec<_>>();
+        if !recovered.is_empty() {
+            tracing::info!(count = recovered.len(), "recovered pending transactions");
+        }
+        Ok(recovered)
+    }
+
+    /// The recovered transactions which have not been sequenced.
+    ///
+    /// Transactions which have just been `decided` are sequenced, as are transactions which any peer
+    /// finds in its copy of the chain. The latter are forgotten.
+    async fn unsequenced(
+        &self,
+        recovered: Vec<Transaction>,
+        decided: &HashSet<Commitment<Transaction>>,
+    ) -> Vec<Transaction> {
+        let recovered = recovered
+            .into_iter()
+            .filter(|tx| !decided.contains(&tx.commit()))
+            .collect::<Vec<_>>();
+        let clients = self
+            .peers
+            .iter()
+            .map(|url| Client::<ServerError, SequencerApiVersion>::new(url.clone()))
+            .collect::<Vec<_>>();
+
+        let sequenced = join_all(recovered.iter().map(|tx| sequenced_by_peers(&clients, tx))).await;
+        let (sequenced, unsequenced): (Vec<_>, Vec<_>) = recovered
+            .into_iter()
+            .zip(sequenced)
+            .partition(|(_, sequenced)| *sequenced);
+
+        let sequenced = sequenced
+            .into_iter()
+            .map(|(tx, _)| tx.commit())
+            .collect::<Vec<_>>();
+        if !sequenced.is_empty() {
+            tracing::info!(
+                count = sequenced.len(),
+                "pending transactions were sequenced while the node was down"
+            );
+            if let Err(err) = self.storage.remove_pending_transactions(&sequenced).await {
+                tracing::warn!("failed to remove sequenced pending transactions: {err:#}");
+            }
+        }
+        unsequenced.into_iter().map(|(tx, _)| tx).collect()
+    }
+}
+
+/// Whether any peer has `tx` in a decided block
+async fn sequenced_by_peers(
+    clients: &[Client<ServerError, SequencerApiVersion>],
+    tx: &Transaction,
+) -> bool {
+    let hash = tx.commit();
+    for client in clients {
+        let res = timeout(
+            PEER_TIMEOUT,
+            client
+                .get::<TransactionQueryData<SeqTypes>>(&format!(
+                    "availability/transaction/hash/{hash}"
+                ))
+                .send(),
+        )
+        .await;
+        match res {
+            Ok(Ok(_)) => return true,
+            Ok(Err(err)) => tracing::debug!(%hash, "peer does not have transaction: {err:#}"),
+            Err(_) => tracing::debug!(%hash, "timed out asking peer for transaction"),
+        }
+    }
+    false
+}
+
+/// Commitments of all the transactions in newly decided leaves
+fn decided_transactions(leaf_chain: &[LeafInfo<SeqTypes>]) -> HashSet<Commitment<Transaction>> {
+    leaf_chain
+        .iter()
+        .filter_map(|LeafInfo { leaf, .. }| {
+            let payload = leaf.block_payload()?;
+            let ns_table = leaf.block_header().metadata();
+            Some(
+                payload
+                    .transactions(ns_table)
+                    .map(|tx| tx.commit())
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect()
+}
@@ -9,10 +9,14 @@
 //! persistence which is _required_ to run a node.
 
 use async_trait::async_trait;
-use espresso_types::v0_99::ChainConfig;
+use espresso_types::v0_4::ChainConfig;
 
+use crate::state_sync::{StateDiff, StateSnapshot};
+
+pub mod embedded;
 pub mod fs;
 pub mod no_storage;
+pub mod snapshot;
 pub mod sql;
 
 #[async_trait]
@@ -20,6 +24,30 @@ pub trait ChainConfigPersistence: Sized + Send + Sync {
     async fn insert_chain_config(&mut self, chain_config: ChainConfig) -> anyhow::Result<()>;
 }
 
+#[async_trait]
+pub trait StateSnapshotPersistence: Sized + Send + Sync {
+    /// Record the accounts changed by a block.
+    async fn insert_state_diff(&mut self, diff: &StateDiff) -> anyhow::Result<()>;
+
+    /// Load the diffs of the blocks in `from..=to`, in order.
+    async fn load_state_diffs(&mut self, from: u64, to: u64) -> anyhow::Result<Vec<StateDiff>>;
+
+    /// Load the most recent snapshot, if there is one.
+    async fn load_latest_state_snapshot(&mut self) -> anyhow::Result<Option<StateSnapshot>>;
+
+    /// The height of the most recent snapshot, if there is one.
+    async fn latest_state_snapshot_height(&mut self) -> anyhow::Result<Option<u64>>;
+
+    /// Delete all snapshots and diffs.
+    async fn delete_state_snapshots(&mut self) -> anyhow::Result<()>;
+
+    /// Store a snapshot.
+    ///
+    /// Only this snapshot and the one before it are kept, along with the diffs needed to roll the
+    /// one before it forward.
+    async fn insert_state_snapshot(&mut self, snapshot: &StateSnapshot) -> anyhow::Result<()>;
+}
+
 #[cfg(any(test, feature = "testing"))]
 mod testing {
 
@@ -54,9 +82,9 @@ mod persistence_tests {
     use committable::{Commitment, Committable};
     use espresso_types::{
         traits::{EventConsumer, NullEventConsumer, PersistenceOptions},
-        v0_3::StakeTableFetcher,
-        Event, L1Client, Leaf, Leaf2, NodeState, PubKey, SeqTypes, SequencerVersions,
-        ValidatedState,
+        v0_3::{L1EventCheckpoint, StakeTableFetcher},
+        Event, L1Client, Leaf, Leaf2, NamespaceId, NodeState, PubKey, SeqTypes, SequencerVersions,
+        Transaction, ValidatedState,
     };
     use futures::{future::join_all, StreamExt, TryStreamExt};
     use hotshot::{
@@ -71,8 +99,10 @@ mod persistence_tests {
             EpochNumber, QuorumProposal2, QuorumProposalWrapper, VidCommitment, VidDisperseShare,
             ViewNumber,
         },
+        drb::DrbInput,
         event::{EventType, HotShotAction, LeafInfo},
         message::{convert_proposal, Proposal, UpgradeLock},
+        signer::Signer,
         simple_certificate::{
             NextEpochQuorumCertificate2, QuorumCertificate, QuorumCertificate2, UpgradeCertificate,
         },
@@ -93,7 +123,7 @@ mod persistence_tests {
     use tide_disco::error::ServerError;
     use vbs::version::{StaticVersion, StaticVersionType, Version};
 
-    use super::*;
+    use super::{snapshot::Snapshot, *};
     use crate::{
         api::{
             test_helpers::{TestNetwork, TestNetworkConfigBuilder},
@@ -290,6 +320,7 @@ mod persistence_tests {
                 .unwrap();
 
         let (pubkey, privkey) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+        let signer = Signer::Local(privkey.clone());
         let signature = PubKey::sign(&privkey, &[]).unwrap();
         let mut vid = VidDisperseShare2::<SeqTypes> {
             view_number: ViewNumber::new(0),
@@ -322,7 +353,7 @@ mod persistence_tests {
             _pd: Default::default(),
         };
 
-        let vid_share0 = vid.clone().to_proposal(&privkey).unwrap().clone();
+        let vid_share0 = vid.clone().to_proposal(&signer).unwrap().clone();
 
         storage.append_vid2(&vid_share0).await.unwrap();
 
@@ -333,7 +364,7 @@ mod persistence_tests {
 
         vid.view_number = ViewNumber::new(1);
 
-        let vid_share1 = vid.clone().to_proposal(&privkey).unwrap().clone();
+        let vid_share1 = vid.clone().to_proposal(&signer).unwrap().clone();
         storage.append_vid2(&vid_share1).await.unwrap();
 
         assert_eq!(
@@ -343,7 +374,7 @@ mod persistence_tests {
 
         vid.view_number = ViewNumber::new(2);
 
-        let vid_share2 = vid.clone().to_proposal(&privkey).unwrap().clone();
+        let vid_share2 = vid.clone().to_proposal(&signer).unwrap().clone();
         storage.append_vid2(&vid_share2).await.unwrap();
 
         assert_eq!(
@@ -353,7 +384,7 @@ mod persistence_tests {
 
         vid.view_number = ViewNumber::new(3);
 
-        let vid_share3 = vid.clone().to_proposal(&privkey).unwrap().clone();
+        let vid_share3 = vid.clone().to_proposal(&signer).unwrap().clone();
         storage.append_vid2(&vid_share3).await.unwrap();
 
         assert_eq!(
@@ -690,6 +721,301 @@ mod persistence_tests {
         assert_eq!(view_number, new_view_number_for_certificate);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_pending_transactions<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+        assert_eq!(storage.load_pending_transactions().await.unwrap(), vec![]);
+
+        let txs = (0..3u64)
+            .map(|i| Transaction::new(NamespaceId::from(i), vec![i as u8]))
+            .collect::<Vec<_>>();
+        for (i, tx) in txs.iter().enumerate().rev() {
+            storage
+                .add_pending_transaction(tx, 100 + i as u64)
+                .await
+                .unwrap();
+        }
+        // Adding a transaction again is idempotent.
+        storage.add_pending_transaction(&txs[0], 100).await.unwrap();
+        assert_eq!(
+            storage.load_pending_transactions().await.unwrap(),
+            vec![
+                (txs[0].clone(), 100),
+                (txs[1].clone(), 101),
+                (txs[2].clone(), 102)
+            ]
+        );
+
+        // Pending transactions survive a restart.
+        drop(storage);
+        let storage = P::connect(&tmp).await;
+        assert_eq!(storage.load_pending_transactions().await.unwrap().len(), 3);
+
+        storage
+            .remove_pending_transactions(&[txs[1].commit()])
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.load_pending_transactions().await.unwrap(),
+            vec![(txs[0].clone(), 100), (txs[2].clone(), 102)]
+        );
+
+        storage.prune_pending_transactions(101).await.unwrap();
+        assert_eq!(
+            storage.load_pending_transactions().await.unwrap(),
+            vec![(txs[2].clone(), 102)]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_notification_cursor<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+        assert_eq!(
+            storage.load_notification_cursor("nats").await.unwrap(),
+            None
+        );
+
+        storage.store_notification_cursor("nats", 5).await.unwrap();
+        storage.store_notification_cursor("kafka", 3).await.unwrap();
+        storage.store_notification_cursor("nats", 7).await.unwrap();
+        assert_eq!(
+            storage.load_notification_cursor("nats").await.unwrap(),
+            Some(7)
+        );
+
+        // Cursors survive a restart.
+        drop(storage);
+        let storage = P::connect(&tmp).await;
+        assert_eq!(
+            storage.load_notification_cursor("nats").await.unwrap(),
+            Some(7)
+        );
+        assert_eq!(
+            storage.load_notification_cursor("kafka").await.unwrap(),
+            Some(3)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_notification_queue<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+        assert_eq!(storage.load_notifications(None, 10).await.unwrap(), vec![]);
+
+        let notifications = (1..=5)
+            .map(|height| (height, vec![height as u8; 3]))
+            .collect::<Vec<_>>();
+        storage
+            .append_notifications(&notifications[..3])
+            .await
+            .unwrap();
+        // Appending overlapping notifications again, as when a decide event is redelivered, is
+        // harmless.
+        storage
+            .append_notifications(&notifications[2..])
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.load_notifications(None, 10).await.unwrap(),
+            notifications
+        );
+        assert_eq!(
+            storage.load_notifications(Some(1), 2).await.unwrap(),
+            notifications[1..3]
+        );
+        assert_eq!(
+            storage.load_notifications(Some(5), 10).await.unwrap(),
+            vec![]
+        );
+
+        // The queue survives a restart, minus what was pruned.
+        storage.prune_notifications(2).await.unwrap();
+        drop(storage);
+        let storage = P::connect(&tmp).await;
+        assert_eq!(
+            storage.load_notifications(None, 10).await.unwrap(),
+            notifications[2..]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_drb_input<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+        let epoch = EpochNumber::new(3);
+        assert_eq!(storage.load_drb_input(epoch).await.unwrap(), None);
+
+        let mut input = DrbInput::new(3, [1; 32]);
+        storage.store_drb_input(input.clone()).await.unwrap();
+        assert_eq!(
+            storage.load_drb_input(epoch).await.unwrap(),
+            Some(input.clone())
+        );
+
+        // Later progress replaces earlier progress, and survives a restart.
+        input.iterate(2);
+        storage.store_drb_input(input.clone()).await.unwrap();
+        drop(storage);
+        let storage = P::connect(&tmp).await;
+        assert_eq!(storage.load_drb_input(epoch).await.unwrap(), Some(input));
+        assert_eq!(
+            storage.load_drb_input(EpochNumber::new(4)).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_snapshot_round_trip<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+
+        let instance = NodeState::mock();
+        let validated_state = hotshot_types::traits::ValidatedState::genesis(&instance).0;
+        let leaf = Leaf2::genesis::<TestVersions>(&validated_state, &instance).await;
+        let qc = QuorumCertificate2::genesis::<TestVersions>(&validated_state, &instance).await;
+        storage
+            .append_decided_leaves(
+                leaf.view_number(),
+                [(&leaf_info(leaf.clone()), qc.clone())],
+                &NullEventConsumer,
+            )
+            .await
+            .unwrap();
+        storage
+            .record_action(ViewNumber::new(3), None, HotShotAction::Vote)
+            .await
+            .unwrap();
+        storage
+            .add_drb_result(EpochNumber::new(1), [1; 32])
+            .await
+            .unwrap();
+        storage
+            .add_epoch_root(EpochNumber::new(1), leaf.block_header().clone())
+            .await
+            .unwrap();
+        storage
+            .store_drb_input(DrbInput::new(1, [2; 32]))
+            .await
+            .unwrap();
+        let tx = Transaction::new(NamespaceId::from(1u64), vec![1, 2, 3]);
+        storage.add_pending_transaction(&tx, 100).await.unwrap();
+
+        // Round trip the snapshot through an archive.
+        let snapshot = Snapshot::export(&storage).await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.bin");
+        let manifest = snapshot.write(&path, Some(7)).unwrap();
+        assert_eq!(manifest.anchor_view, Some(leaf.view_number()));
+        assert_eq!(manifest.latest_acted_view, Some(ViewNumber::new(3)));
+        assert_eq!(manifest.query_block_height, Some(7));
+        let (_, snapshot) = Snapshot::read(&path).unwrap();
+
+        // Restore into fresh storage.
+        let tmp2 = P::tmp_storage().await;
+        let restored = P::connect(&tmp2).await;
+        snapshot.import(&restored).await.unwrap();
+        assert_eq!(
+            restored.load_anchor_leaf().await.unwrap(),
+            Some((leaf.clone(), qc))
+        );
+        assert_eq!(
+            restored.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(3))
+        );
+        assert_eq!(
+            restored.load_start_epoch_info().await.unwrap(),
+            storage.load_start_epoch_info().await.unwrap()
+        );
+        assert_eq!(
+            restored.load_drb_input(EpochNumber::new(1)).await.unwrap(),
+            Some(DrbInput::new(1, [2; 32]))
+        );
+        assert_eq!(
+            restored.load_pending_transactions().await.unwrap(),
+            vec![(tx, 100)]
+        );
+
+        // Storage which already holds consensus state is not overwritten.
+        snapshot.import(&restored).await.unwrap_err();
+
+        // A corrupted archive is rejected.
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        std::fs::write(&path, bytes).unwrap();
+        Snapshot::read(&path).unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    pub async fn test_l1_checkpoint<P: TestablePersistence>() {
+        setup_test();
+
+        let tmp = P::tmp_storage().await;
+        let storage = P::connect(&tmp).await;
+        let stake_table = alloy::primitives::Address::repeat_byte(1);
+        let fee = alloy::primitives::Address::repeat_byte(2);
+        assert_eq!(storage.load_l1_checkpoint(stake_table).await.unwrap(), None);
+
+        let checkpoint = L1EventCheckpoint {
+            l1_block: 10,
+            last_event: Some((8, 3)),
+        };
+        storage
+            .store_l1_checkpoint(stake_table, checkpoint)
+            .await
+            .unwrap();
+        storage
+            .store_l1_checkpoint(fee, L1EventCheckpoint::default())
+            .await
+            .unwrap();
+
+        // A later checkpoint replaces an earlier one, and survives a restart.
+        let checkpoint = L1EventCheckpoint {
+            l1_block: 20,
+            last_event: Some((15, 0)),
+        };
+        storage
+            .store_l1_checkpoint(stake_table, checkpoint)
+            .await
+            .unwrap();
+        drop(storage);
+        let storage = P::connect(&tmp).await;
+        assert_eq!(
+            storage.load_l1_checkpoint(stake_table).await.unwrap(),
+            Some(checkpoint)
+        );
+        assert_eq!(
+            storage.load_l1_checkpoint(fee).await.unwrap(),
+            Some(L1EventCheckpoint::default())
+        );
+
+        // Stake table events are stored along with the checkpoint of their scan.
+        storage
+            .store_events_with_checkpoint(stake_table, 30, vec![])
+            .await
+            .unwrap();
+        assert_eq!(storage.load_events().await.unwrap(), Some((30, vec![])));
+        assert_eq!(
+            storage.load_l1_checkpoint(stake_table).await.unwrap(),
+            Some(L1EventCheckpoint {
+                l1_block: 30,
+                last_event: None,
+            })
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     pub async fn test_next_epoch_quorum_certificate<P: TestablePersistence>() {
         setup_test();
@@ -798,7 +1124,7 @@ mod persistence_tests {
             target_epoch: Some(EpochNumber::new(0)),
             common: avidm_param,
         }
-        .to_proposal(&privkey)
+        .to_proposal(&Signer::Local(privkey.clone()))
         .unwrap()
         .clone();
         let mut quorum_proposal = QuorumProposalWrapper::<SeqTypes> {
@@ -1007,7 +1333,7 @@ mod persistence_tests {
             target_epoch: None,
             common: avidm_param,
         }
-        .to_proposal(&privkey)
+        .to_proposal(&Signer::Local(privkey.clone()))
         .unwrap()
         .clone();
 
@@ -1229,6 +1555,18 @@ mod persistence_tests {
         let chain_config = node_state.chain_config;
         let stake_table_contract = chain_config.stake_table_contract.unwrap();
 
+        // The scan of the contract is checkpointed along with the events.
+        let checkpoint = persistence
+            .load_l1_checkpoint(stake_table_contract)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.l1_block, latest_l1_block);
+        assert_eq!(
+            checkpoint.last_event,
+            final_persisted_events.last().map(|(key, _)| *key)
+        );
+
         // Fetch events directly from the contract and compare with persisted data.
         let contract_events = StakeTableFetcher::fetch_events_from_contract(
             l1_client.clone(),
@@ -0,0 +1,1020 @@
+//! Embedded persistence, backed by a [redb](https://docs.rs/redb) database file.
+//!
+//! This is an alternative to SQL persistence for operators running a single node, who do not want
+//! to run a separate database server. All consensus data lives in a single file. Every write is an
+//! ACID transaction which is synced to disk before it returns, so the storage is always consistent
+//! after a crash, without any further recovery.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use async_lock::Mutex;
+use async_trait::async_trait;
+use clap::Parser;
+use committable::{Commitment, Committable};
+use derivative::Derivative;
+use espresso_types::{
+    traits::MembershipPersistence,
+    v0::traits::{EventConsumer, PersistenceOptions, SequencerPersistence},
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
+    Leaf2, NetworkConfig, Payload, SeqTypes, Transaction,
+};
+use hotshot::{types::BLSPubKey, InitializerEpochInfo};
+use hotshot_types::{
+    data::{
+        vid_disperse::{ADVZDisperseShare, VidDisperseShare2},
+        DaProposal, DaProposal2, EpochNumber, QuorumProposalWrapper, VidCommitment,
+        VidDisperseShare,
+    },
+    drb::{DrbInput, DrbResult},
+    event::{Event, EventType, HotShotAction, LeafInfo},
+    message::{convert_proposal, Proposal},
+    simple_certificate::{
+        LightClientStateUpdateCertificate, NextEpochQuorumCertificate2, QuorumCertificate2,
+        UpgradeCertificate,
+    },
+    traits::{
+        block_contents::{BlockHeader, BlockPayload},
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    vote::HasViewNumber,
+};
+use indexmap::IndexMap;
+use redb::{Database, ReadableTable, TableDefinition, TableHandle};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::ViewNumber;
+
+/// Options for embedded persistence.
+#[derive(Parser, Clone, Debug)]
+pub struct Options {
+    /// Path to the database file.
+    ///
+    /// If the query module is enabled, query service data is stored in a directory next to this
+    /// file, with the extension `.query`.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_EMBEDDED_STORAGE_PATH")]
+    path: PathBuf,
+
+    /// Number of views to retain in consensus storage before data that hasn't been archived is
+    /// garbage collected.
+    ///
+    /// This setting only applies to views which never get decided (ie forks in consensus) and views
+    /// for which this node is partially offline, as most data is garbage collected as soon as it is
+    /// finalized by consensus.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CONSENSUS_VIEW_RETENTION",
+        default_value = "130000"
+    )]
+    pub(crate) consensus_view_retention: u64,
+}
+
+impl Options {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            consensus_view_retention: 130000,
+        }
+    }
+
+    /// Path to the query service storage which accompanies this database.
+    pub(crate) fn query_path(&self) -> PathBuf {
+        self.path.with_extension("query")
+    }
+}
+
+#[async_trait]
+impl PersistenceOptions for Options {
+    type Persistence = Persistence;
+
+    fn set_view_retention(&mut self, view_retention: u64) {
+        self.consensus_view_retention = view_retention;
+    }
+
+    async fn create(&mut self) -> anyhow::Result<Self::Persistence> {
+        Persistence::open(&self.path, self.consensus_view_retention)
+    }
+
+    async fn reset(self) -> anyhow::Result<()> {
+        if self.path.is_file() {
+            fs::remove_file(&self.path)
+                .context(format!("removing database {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Singleton values, keyed by name.
+const META: TableDefinition<&str, &[u8]> = TableDefinition::new("meta");
+
+const CONFIG_KEY: &str = "config";
+const VOTED_VIEW_KEY: &str = "highest_voted_view";
+const UPGRADE_CERTIFICATE_KEY: &str = "upgrade_certificate";
+const NEXT_EPOCH_QC_KEY: &str = "next_epoch_quorum_certificate";
+const STAKE_TABLE_EVENTS_KEY: &str = "stake_table_events";
+
+/// Key in the meta table of the L1 event checkpoint for `contract`.
+fn l1_checkpoint_key(contract: alloy::primitives::Address) -> String {
+    format!("l1_event_checkpoint/{contract}")
+}
+
+/// Decided leaves and their QCs, by view.
+const ANCHOR_LEAF: TableDefinition<u64, &[u8]> = TableDefinition::new("anchor_leaf2");
+/// DA proposals, by view.
+const DA_PROPOSAL: TableDefinition<u64, &[u8]> = TableDefinition::new("da_proposal2");
+/// VID shares, by view.
+const VID_SHARE: TableDefinition<u64, &[u8]> = TableDefinition::new("vid_share2");
+/// Quorum proposals, by view.
+const QUORUM_PROPOSAL: TableDefinition<u64, &[u8]> = TableDefinition::new("quorum_proposals2");
+/// Light client state update certificates which have not been decided yet, by view.
+const STATE_CERT: TableDefinition<u64, &[u8]> = TableDefinition::new("state_cert");
+/// Decided light client state update certificates, by epoch.
+const FINALIZED_STATE_CERT: TableDefinition<u64, &[u8]> =
+    TableDefinition::new("finalized_state_cert");
+/// DRB results, by epoch.
+const DRB_RESULT: TableDefinition<u64, &[u8]> = TableDefinition::new("epoch_drb_result");
+/// Progress of ongoing DRB computations, by epoch.
+const DRB_INPUT: TableDefinition<u64, &[u8]> = TableDefinition::new("drb_input");
+/// Epoch root block headers, by epoch.
+const EPOCH_ROOT: TableDefinition<u64, &[u8]> = TableDefinition::new("epoch_root_block_header");
+/// Stake tables, by epoch.
+const STAKE_TABLE: TableDefinition<u64, &[u8]> = TableDefinition::new("stake_table");
+/// Transactions submitted to this node which have not been sequenced yet, with the times they were
+/// submitted, by commitment.
+const PENDING_TRANSACTION: TableDefinition<&[u8], &[u8]> =
+    TableDefinition::new("pending_transaction");
+/// Height of the last block delivered to each notification sink, by sink name.
+const NOTIFICATION_CURSOR: TableDefinition<&str, u64> = TableDefinition::new("notification_cursor");
+/// Encoded notifications of decided blocks awaiting delivery, by block height.
+const NOTIFICATION_QUEUE: TableDefinition<u64, &[u8]> = TableDefinition::new("notification_queue");
+
+/// Tables of consensus artifacts which are garbage collected once their view has been decided.
+const VIEW_TABLES: [TableDefinition<u64, &[u8]>; 4] =
+    [DA_PROPOSAL, VID_SHARE, QUORUM_PROPOSAL, STATE_CERT];
+
+/// Embedded persistence.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct Persistence {
+    #[derivative(Debug = "ignore")]
+    db: Arc<Database>,
+    view_retention: u64,
+    /// Serializes decide processing, so that decide events are generated and garbage is collected
+    /// in order.
+    #[derivative(Debug = "ignore")]
+    decide_lock: Arc<Mutex<()>>,
+}
+
+impl Persistence {
+    fn open(path: &Path, view_retention: u64) -> anyhow::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context(format!("creating directory {}", dir.display()))?;
+        }
+        let db = Database::create(path).context(format!("opening database {}", path.display()))?;
+
+        // Create all tables up front, so that readers never have to deal with missing tables.
+        let tx = db.begin_write()?;
+        tx.open_table(META)?;
+        tx.open_table(PENDING_TRANSACTION)?;
+        tx.open_table(NOTIFICATION_CURSOR)?;
+        tx.open_table(NOTIFICATION_QUEUE)?;
+        for table in [
+            ANCHOR_LEAF,
+            DA_PROPOSAL,
+            VID_SHARE,
+            QUORUM_PROPOSAL,
+            STATE_CERT,
+            FINALIZED_STATE_CERT,
+            DRB_RESULT,
+            DRB_INPUT,
+            EPOCH_ROOT,
+            STAKE_TABLE,
+        ] {
+            tx.open_table(table)?;
+        }
+        tx.commit()?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            view_retention,
+            decide_lock: Default::default(),
+        })
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        table: TableDefinition<u64, &[u8]>,
+        key: u64,
+    ) -> anyhow::Result<Option<T>> {
+        let tx = self.db.begin_read()?;
+        let Some(bytes) = tx.open_table(table)?.get(key)? else {
+            return Ok(None);
+        };
+        let value = bincode::deserialize(bytes.value())
+            .context(format!("deserializing {} {key}", table.name()))?;
+        Ok(Some(value))
+    }
+
+    /// Store `value` at `key`.
+    ///
+    /// If there is already a value for `key`, it is only replaced if `overwrite` is set. Returns
+    /// whether the value was stored.
+    fn insert<T: Serialize>(
+        &self,
+        table: TableDefinition<u64, &[u8]>,
+        key: u64,
+        value: &T,
+        overwrite: bool,
+    ) -> anyhow::Result<bool> {
+        let bytes =
+            bincode::serialize(value).context(format!("serializing {} {key}", table.name()))?;
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(table)?;
+            if !overwrite && table.get(key)?.is_some() {
+                return Ok(false);
+            }
+            table.insert(key, bytes.as_slice())?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn get_meta(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let tx = self.db.begin_read()?;
+        let bytes = tx
+            .open_table(META)?
+            .get(key)?
+            .map(|bytes| bytes.value().to_vec());
+        Ok(bytes)
+    }
+
+    fn insert_meta(&self, key: &str, value: &[u8]) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        tx.open_table(META)?.insert(key, value)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn store_decided_leaves(
+        &self,
+        leaves: Vec<(Leaf2, QuorumCertificate2<SeqTypes>)>,
+    ) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(ANCHOR_LEAF)?;
+            for (leaf, qc) in leaves {
+                let view = leaf.view_number().u64();
+                if table.get(view)?.is_some() {
+                    // Don't overwrite an existing leaf, but warn about it as this is likely not
+                    // intended behavior from HotShot.
+                    tracing::warn!(view, "duplicate decided leaf");
+                    continue;
+                }
+                let bytes = bincode::serialize(&(leaf, qc))?;
+                table.insert(view, bytes.as_slice())?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Load decided leaves up to `view`, along with any data we have for them.
+    fn load_decided_leaves(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<BTreeMap<ViewNumber, (LeafInfo<SeqTypes>, QuorumCertificate2<SeqTypes>)>>
+    {
+        let mut leaves = BTreeMap::new();
+        let tx = self.db.begin_read()?;
+        for entry in tx.open_table(ANCHOR_LEAF)?.range(..=view.u64())? {
+            let (v, bytes) = entry?;
+            let v = ViewNumber::new(v.value());
+            let (mut leaf, qc) =
+                bincode::deserialize::<(Leaf2, QuorumCertificate2<SeqTypes>)>(bytes.value())
+                    .context(format!("parsing decided leaf {v:?}"))?;
+
+            // Include the VID share if available.
+            let vid_share = self
+                .get::<Proposal<SeqTypes, VidDisperseShare<SeqTypes>>>(VID_SHARE, v.u64())?
+                .map(|proposal| proposal.data);
+            if vid_share.is_none() {
+                tracing::debug!(?v, "VID share not available at decide");
+            }
+
+            // Fill in the full block payload using the DA proposals we had persisted.
+            if let Some(proposal) =
+                self.get::<Proposal<SeqTypes, DaProposal2<SeqTypes>>>(DA_PROPOSAL, v.u64())?
+            {
+                let payload = Payload::from_bytes(
+                    &proposal.data.encoded_transactions,
+                    &proposal.data.metadata,
+                );
+                leaf.fill_block_payload_unchecked(payload);
+            } else {
+                tracing::debug!(?v, "DA proposal not available at decide");
+            }
+
+            let state_cert = self.get(STATE_CERT, v.u64())?;
+
+            let info = LeafInfo {
+                leaf,
+                vid_share,
+                state_cert,
+                // Note: the following fields are not used in Decide event processing, and should be
+                // removed. For now, we just default them.
+                state: Default::default(),
+                delta: Default::default(),
+            };
+            leaves.insert(v, (info, qc));
+        }
+        Ok(leaves)
+    }
+
+    /// Record the decided light client state update certificate for its epoch.
+    fn finalize_state_cert(
+        &self,
+        state_cert: &LightClientStateUpdateCertificate<SeqTypes>,
+    ) -> anyhow::Result<()> {
+        self.insert(
+            FINALIZED_STATE_CERT,
+            state_cert.epoch.u64(),
+            state_cert,
+            true,
+        )?;
+        Ok(())
+    }
+
+    /// Generate events based on persisted decided leaves.
+    ///
+    /// Returns a list of closed intervals of views which can be safely deleted, as all leaves
+    /// within these view ranges have been processed by the event consumer.
+    async fn generate_decide_events(
+        &self,
+        view: ViewNumber,
+        consumer: &impl EventConsumer,
+    ) -> anyhow::Result<Vec<RangeInclusive<ViewNumber>>> {
+        // Generate a decide event for each leaf, to be processed by the event consumer. We make a
+        // separate event for each leaf because it is possible we have non-consecutive leaves in our
+        // storage, which would not be valid as a single decide with a single leaf chain.
+        let mut leaves = self.load_decided_leaves(view)?;
+
+        // The oldest leaf in storage -- if there is one -- was always included in the _previous_
+        // decide event, but not removed, because we always persist the most recent anchor leaf.
+        if let Some((oldest_view, _)) = leaves.first_key_value() {
+            // The only exception is when the oldest leaf is the genesis leaf; then there was no
+            // previous decide event.
+            if *oldest_view > ViewNumber::genesis() {
+                leaves.pop_first();
+            }
+        }
+
+        let mut intervals = vec![];
+        let mut current_interval = None;
+        for (view, (leaf, qc)) in leaves {
+            if let Some(state_cert) = &leaf.state_cert {
+                self.finalize_state_cert(state_cert)?;
+            }
+
+            let height = leaf.leaf.block_header().block_number();
+            consumer
+                .handle_event(&Event {
+                    view_number: view,
+                    event: EventType::Decide {
+                        qc: Arc::new(qc),
+                        leaf_chain: Arc::new(vec![leaf]),
+                        block_size: None,
+                    },
+                })
+                .await?;
+            if let Some((start, end, current_height)) = current_interval.as_mut() {
+                if height == *current_height + 1 {
+                    // If we have a chain of consecutive leaves, extend the current interval of
+                    // views which are safe to delete.
+                    *current_height += 1;
+                    *end = view;
+                } else {
+                    // Otherwise, end the current interval and start a new one.
+                    intervals.push(*start..=*end);
+                    current_interval = Some((view, view, height));
+                }
+            } else {
+                // Start a new interval.
+                current_interval = Some((view, view, height));
+            }
+        }
+        if let Some((start, end, _)) = current_interval {
+            intervals.push(start..=end);
+        }
+
+        Ok(intervals)
+    }
+
+    fn collect_garbage(
+        &self,
+        decided_view: ViewNumber,
+        prune_intervals: &[RangeInclusive<ViewNumber>],
+    ) -> anyhow::Result<()> {
+        let prune_view = decided_view.u64().saturating_sub(self.view_retention);
+        // Delete a view if it is time to prune it _or_ if the given intervals, which we've already
+        // successfully processed, contain it; in this case we simply don't need it anymore.
+        let prune = |view: u64| {
+            view < prune_view
+                || prune_intervals
+                    .iter()
+                    .any(|i| i.contains(&ViewNumber::new(view)))
+        };
+
+        let tx = self.db.begin_write()?;
+        for table in VIEW_TABLES {
+            tx.open_table(table)?.retain(|view, _| !prune(view))?;
+        }
+        // Save the most recent leaf as it will be our anchor point if the node restarts.
+        tx.open_table(ANCHOR_LEAF)?
+            .retain(|view, _| view == decided_view.u64() || !prune(view))?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SequencerPersistence for Persistence {
+    async fn load_config(&self) -> anyhow::Result<Option<NetworkConfig>> {
+        let Some(bytes) = self.get_meta(CONFIG_KEY)? else {
+            tracing::info!("config not found");
+            return Ok(None);
+        };
+        let config = serde_json::from_slice(&bytes).context("malformed config")?;
+        Ok(Some(config))
+    }
+
+    async fn save_config(&self, cfg: &NetworkConfig) -> anyhow::Result<()> {
+        tracing::info!("saving config");
+        let bytes = serde_json::to_vec(cfg).context("serializing config")?;
+        self.insert_meta(CONFIG_KEY, &bytes)
+    }
+
+    async fn load_latest_acted_view(&self) -> anyhow::Result<Option<ViewNumber>> {
+        let Some(bytes) = self.get_meta(VOTED_VIEW_KEY)? else {
+            return Ok(None);
+        };
+        let bytes = bytes
+            .try_into()
+            .map_err(|bytes| anyhow::anyhow!("malformed voted view: {bytes:?}"))?;
+        Ok(Some(ViewNumber::new(u64::from_le_bytes(bytes))))
+    }
+
+    async fn append_decided_leaves(
+        &self,
+        view: ViewNumber,
+        leaf_chain: impl IntoIterator<Item = (&LeafInfo<SeqTypes>, QuorumCertificate2<SeqTypes>)> + Send,
+        consumer: &impl EventConsumer,
+    ) -> anyhow::Result<()> {
+        let _guard = self.decide_lock.lock().await;
+
+        let leaves = leaf_chain
+            .into_iter()
+            .map(|(info, qc)| (info.leaf.clone(), qc))
+            .collect();
+        self.store_decided_leaves(leaves)?;
+
+        match self.generate_decide_events(view, consumer).await {
+            Err(err) => {
+                // Event processing failure is not an error, since by this point we have at least
+                // managed to persist the decided leaves successfully, and the event processing will
+                // just run again at the next decide.
+                tracing::warn!(?view, "event processing failed: {err:#}");
+            },
+            Ok(intervals) => {
+                if let Err(err) = self.collect_garbage(view, &intervals) {
+                    // Similarly, garbage collection is not an error. We have done everything we
+                    // strictly needed to do, and GC will run again at the next decide. Log the
+                    // error but do not return it.
+                    tracing::warn!(?view, "GC failed: {err:#}");
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    async fn load_anchor_leaf(
+        &self,
+    ) -> anyhow::Result<Option<(Leaf2, QuorumCertificate2<SeqTypes>)>> {
+        let tx = self.db.begin_read()?;
+        let Some((_, bytes)) = tx.open_table(ANCHOR_LEAF)?.last()? else {
+            return Ok(None);
+        };
+        let anchor = bincode::deserialize(bytes.value()).context("parsing anchor leaf")?;
+        Ok(Some(anchor))
+    }
+
+    async fn load_da_proposal(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<Option<Proposal<SeqTypes, DaProposal2<SeqTypes>>>> {
+        self.get(DA_PROPOSAL, view.u64())
+    }
+
+    async fn load_vid_share(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<Option<Proposal<SeqTypes, VidDisperseShare<SeqTypes>>>> {
+        self.get(VID_SHARE, view.u64())
+    }
+
+    async fn append_vid(
+        &self,
+        proposal: &Proposal<SeqTypes, ADVZDisperseShare<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view_number = proposal.data.view_number().u64();
+        let proposal: Proposal<SeqTypes, VidDisperseShare<SeqTypes>> =
+            convert_proposal(proposal.clone());
+        if !self.insert(VID_SHARE, view_number, &proposal, false)? {
+            // Don't overwrite an existing share, but warn about it as this is likely not intended
+            // behavior from HotShot.
+            tracing::warn!(view_number, "duplicate VID share");
+        }
+        Ok(())
+    }
+
+    async fn append_vid2(
+        &self,
+        proposal: &Proposal<SeqTypes, VidDisperseShare2<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view_number = proposal.data.view_number().u64();
+        let proposal: Proposal<SeqTypes, VidDisperseShare<SeqTypes>> =
+            convert_proposal(proposal.clone());
+        if !self.insert(VID_SHARE, view_number, &proposal, false)? {
+            // Don't overwrite an existing share, but warn about it as this is likely not intended
+            // behavior from HotShot.
+            tracing::warn!(view_number, "duplicate VID share");
+        }
+        Ok(())
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<SeqTypes, DaProposal<SeqTypes>>,
+        vid_commit: VidCommitment,
+    ) -> anyhow::Result<()> {
+        self.append_da2(&convert_proposal(proposal.clone()), vid_commit)
+            .await
+    }
+
+    async fn record_action(
+        &self,
+        view: ViewNumber,
+        _epoch: Option<EpochNumber>,
+        action: HotShotAction,
+    ) -> anyhow::Result<()> {
+        // Todo Remove this after https://github.com/EspressoSystems/espresso-sequencer/issues/1931
+        if !matches!(action, HotShotAction::Propose | HotShotAction::Vote) {
+            return Ok(());
+        }
+
+        // Read and update the saved view in a single transaction, so that the saved view only ever
+        // increases.
+        let tx = self.db.begin_write()?;
+        {
+            let mut meta = tx.open_table(META)?;
+            let saved_view = meta
+                .get(VOTED_VIEW_KEY)?
+                .map(|bytes| -> anyhow::Result<u64> {
+                    Ok(u64::from_le_bytes(bytes.value().try_into()?))
+                })
+                .transpose()
+                .context("malformed voted view")?;
+            if saved_view.is_some_and(|saved_view| saved_view >= view.u64()) {
+                return Ok(());
+            }
+            meta.insert(VOTED_VIEW_KEY, view.u64().to_le_bytes().as_slice())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn append_quorum_proposal2(
+        &self,
+        proposal: &Proposal<SeqTypes, QuorumProposalWrapper<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view_number = proposal.data.view_number().u64();
+        self.insert(QUORUM_PROPOSAL, view_number, proposal, true)?;
+        Ok(())
+    }
+
+    async fn load_quorum_proposals(
+        &self,
+    ) -> anyhow::Result<BTreeMap<ViewNumber, Proposal<SeqTypes, QuorumProposalWrapper<SeqTypes>>>>
+    {
+        let mut map = BTreeMap::new();
+        let tx = self.db.begin_read()?;
+        for entry in tx.open_table(QUORUM_PROPOSAL)?.iter()? {
+            let (view, bytes) = entry?;
+            let view = ViewNumber::new(view.value());
+            match bincode::deserialize(bytes.value()) {
+                Ok(proposal) => {
+                    map.insert(view, proposal);
+                },
+                Err(err) => {
+                    // It is better to collect as many proposals as we can rather than letting one
+                    // bad proposal cause the entire operation to fail.
+                    tracing::warn!(?view, "ignoring malformed quorum proposal: {err:#}");
+                },
+            }
+        }
+        Ok(map)
+    }
+
+    async fn load_quorum_proposal(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<Proposal<SeqTypes, QuorumProposalWrapper<SeqTypes>>> {
+        self.get(QUORUM_PROPOSAL, view.u64())?
+            .context(format!("no quorum proposal for view {view:?}"))
+    }
+
+    async fn load_upgrade_certificate(
+        &self,
+    ) -> anyhow::Result<Option<UpgradeCertificate<SeqTypes>>> {
+        let Some(bytes) = self.get_meta(UPGRADE_CERTIFICATE_KEY)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            bincode::deserialize(&bytes).context("deserialize upgrade certificate")?,
+        ))
+    }
+
+    async fn store_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let Some(certificate) = decided_upgrade_certificate else {
+            return Ok(());
+        };
+        let bytes = bincode::serialize(&certificate).context("serializing upgrade certificate")?;
+        self.insert_meta(UPGRADE_CERTIFICATE_KEY, &bytes)
+    }
+
+    async fn store_next_epoch_quorum_certificate(
+        &self,
+        high_qc: NextEpochQuorumCertificate2<SeqTypes>,
+    ) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(&high_qc).context("serializing next epoch qc")?;
+        self.insert_meta(NEXT_EPOCH_QC_KEY, &bytes)
+    }
+
+    async fn load_next_epoch_quorum_certificate(
+        &self,
+    ) -> anyhow::Result<Option<NextEpochQuorumCertificate2<SeqTypes>>> {
+        let Some(bytes) = self.get_meta(NEXT_EPOCH_QC_KEY)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            bincode::deserialize(&bytes).context("deserialize next epoch qc")?,
+        ))
+    }
+
+    async fn append_da2(
+        &self,
+        proposal: &Proposal<SeqTypes, DaProposal2<SeqTypes>>,
+        _vid_commit: VidCommitment,
+    ) -> anyhow::Result<()> {
+        let view_number = proposal.data.view_number().u64();
+        if !self.insert(DA_PROPOSAL, view_number, proposal, false)? {
+            // Don't overwrite an existing proposal, but warn about it as this is likely not
+            // intended behavior from HotShot.
+            tracing::warn!(view_number, "duplicate DA proposal");
+        }
+        Ok(())
+    }
+
+    // This storage has only ever stored the latest data formats, so there is nothing to migrate.
+    async fn migrate_anchor_leaf(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn migrate_da_proposals(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn migrate_vid_shares(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn migrate_quorum_proposals(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+    async fn migrate_quorum_certificates(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn add_drb_result(
+        &self,
+        epoch: EpochNumber,
+        drb_result: DrbResult,
+    ) -> anyhow::Result<()> {
+        self.insert(DRB_RESULT, epoch.u64(), &drb_result, true)?;
+        Ok(())
+    }
+
+    async fn store_drb_input(&self, drb_input: DrbInput) -> anyhow::Result<()> {
+        self.insert(DRB_INPUT, drb_input.epoch, &drb_input, true)?;
+        Ok(())
+    }
+
+    async fn load_drb_input(&self, epoch: EpochNumber) -> anyhow::Result<Option<DrbInput>> {
+        self.get(DRB_INPUT, epoch.u64())
+    }
+
+    async fn add_epoch_root(
+        &self,
+        epoch: EpochNumber,
+        block_header: <SeqTypes as NodeType>::BlockHeader,
+    ) -> anyhow::Result<()> {
+        self.insert(EPOCH_ROOT, epoch.u64(), &block_header, true)?;
+        Ok(())
+    }
+
+    async fn add_state_cert(
+        &self,
+        state_cert: LightClientStateUpdateCertificate<SeqTypes>,
+    ) -> anyhow::Result<()> {
+        let view = state_cert.light_client_state.view_number;
+        self.insert(STATE_CERT, view, &state_cert, true)?;
+        Ok(())
+    }
+
+    async fn load_start_epoch_info(&self) -> anyhow::Result<Vec<InitializerEpochInfo<SeqTypes>>> {
+        let mut result = Vec::new();
+        let tx = self.db.begin_read()?;
+        for entry in tx.open_table(DRB_RESULT)?.iter()? {
+            let (epoch, bytes) = entry?;
+            let epoch = EpochNumber::new(epoch.value());
+            let drb_result = bincode::deserialize::<DrbResult>(bytes.value())
+                .context(format!("parsing epoch drb result {epoch:?}"))?;
+            let block_header = self.get(EPOCH_ROOT, epoch.u64())?;
+            result.push(InitializerEpochInfo::<SeqTypes> {
+                epoch,
+                drb_result,
+                block_header,
+            });
+        }
+        Ok(result)
+    }
+
+    async fn load_state_cert(
+        &self,
+    ) -> anyhow::Result<Option<LightClientStateUpdateCertificate<SeqTypes>>> {
+        let tx = self.db.begin_read()?;
+        let Some((_, bytes)) = tx.open_table(FINALIZED_STATE_CERT)?.last()? else {
+            return Ok(None);
+        };
+        Ok(Some(bincode::deserialize(bytes.value()).context(
+            "parsing light client state update certificate",
+        )?))
+    }
+
+    async fn add_pending_transaction(
+        &self,
+        tx: &Transaction,
+        submitted: u64,
+    ) -> anyhow::Result<()> {
+        let bytes =
+            bincode::serialize(&(tx, submitted)).context("serializing pending transaction")?;
+        let db_tx = self.db.begin_write()?;
+        db_tx
+            .open_table(PENDING_TRANSACTION)?
+            .insert(tx.commit().as_ref(), bytes.as_slice())?;
+        db_tx.commit()?;
+        Ok(())
+    }
+
+    async fn remove_pending_transactions(
+        &self,
+        hashes: &[Commitment<Transaction>],
+    ) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(PENDING_TRANSACTION)?;
+            for hash in hashes {
+                table.remove(hash.as_ref())?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn prune_pending_transactions(&self, submitted_before: u64) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        tx.open_table(PENDING_TRANSACTION)?.retain(|_, bytes| {
+            // Keep anything we cannot parse, so that it surfaces as an error when loaded.
+            match bincode::deserialize::<(Transaction, u64)>(bytes) {
+                Ok((_, submitted)) => submitted >= submitted_before,
+                Err(_) => true,
+            }
+        })?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn load_pending_transactions(&self) -> anyhow::Result<Vec<(Transaction, u64)>> {
+        let tx = self.db.begin_read()?;
+        let mut result = vec![];
+        for entry in tx.open_table(PENDING_TRANSACTION)?.iter()? {
+            let (_, bytes) = entry?;
+            let pending = bincode::deserialize::<(Transaction, u64)>(bytes.value())
+                .context("parsing pending transaction")?;
+            result.push(pending);
+        }
+        result.sort_by_key(|(_, submitted)| *submitted);
+        Ok(result)
+    }
+
+    async fn store_notification_cursor(&self, sink: &str, height: u64) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        tx.open_table(NOTIFICATION_CURSOR)?.insert(sink, height)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn load_notification_cursor(&self, sink: &str) -> anyhow::Result<Option<u64>> {
+        let tx = self.db.begin_read()?;
+        Ok(tx
+            .open_table(NOTIFICATION_CURSOR)?
+            .get(sink)?
+            .map(|height| height.value()))
+    }
+
+    async fn append_notifications(&self, notifications: &[(u64, Vec<u8>)]) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(NOTIFICATION_QUEUE)?;
+            for (height, data) in notifications {
+                table.insert(*height, data.as_slice())?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn load_notifications(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<u8>)>> {
+        let tx = self.db.begin_read()?;
+        let table = tx.open_table(NOTIFICATION_QUEUE)?;
+        let range = match after {
+            Some(height) => table.range(height + 1..)?,
+            None => table.range(0..)?,
+        };
+        range
+            .take(limit)
+            .map(|entry| {
+                let (height, data) = entry?;
+                Ok((height.value(), data.value().to_vec()))
+            })
+            .collect()
+    }
+
+    async fn prune_notifications(&self, height: u64) -> anyhow::Result<()> {
+        let tx = self.db.begin_write()?;
+        tx.open_table(NOTIFICATION_QUEUE)?
+            .retain(|queued, _| queued > height)?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MembershipPersistence for Persistence {
+    async fn load_stake(
+        &self,
+        epoch: EpochNumber,
+    ) -> anyhow::Result<Option<IndexMap<alloy::primitives::Address, Validator<BLSPubKey>>>> {
+        self.get(STAKE_TABLE, epoch.u64())
+    }
+
+    async fn load_latest_stake(&self, limit: u64) -> anyhow::Result<Option<Vec<IndexedStake>>> {
+        let tx = self.db.begin_read()?;
+        let mut stakes = tx
+            .open_table(STAKE_TABLE)?
+            .iter()?
+            .rev()
+            .take(limit as usize)
+            .map(|entry| -> anyhow::Result<IndexedStake> {
+                let (epoch, bytes) = entry?;
+                let stake = bincode::deserialize(bytes.value())
+                    .context("deserialize combined stake table")?;
+                Ok((EpochNumber::new(epoch.value()), stake))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        stakes.reverse();
+        Ok(Some(stakes))
+    }
+
+    async fn store_stake(
+        &self,
+        epoch: EpochNumber,
+        stake: IndexMap<alloy::primitives::Address, Validator<BLSPubKey>>,
+    ) -> anyhow::Result<()> {
+        self.insert(STAKE_TABLE, epoch.u64(), &stake, true)?;
+        Ok(())
+    }
+
+    async fn store_events(
+        &self,
+        l1_block: u64,
+        events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(&(l1_block, events)).context("serializing events")?;
+        self.insert_meta(STAKE_TABLE_EVENTS_KEY, &bytes)
+    }
+
+    async fn load_events(&self) -> anyhow::Result<Option<(u64, Vec<(EventKey, StakeTableEvent)>)>> {
+        let Some(bytes) = self.get_meta(STAKE_TABLE_EVENTS_KEY)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            serde_json::from_slice(&bytes).context("malformed events")?,
+        ))
+    }
+
+    async fn store_events_with_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        l1_block: u64,
+        events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        let checkpoint = L1EventCheckpoint {
+            l1_block,
+            last_event: events.last().map(|(key, _)| *key),
+        };
+        let events = serde_json::to_vec(&(l1_block, events)).context("serializing events")?;
+        let checkpoint = bincode::serialize(&checkpoint).context("serializing L1 checkpoint")?;
+
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(META)?;
+            table.insert(STAKE_TABLE_EVENTS_KEY, events.as_slice())?;
+            table.insert(l1_checkpoint_key(contract).as_str(), checkpoint.as_slice())?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn store_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(&checkpoint).context("serializing L1 checkpoint")?;
+        self.insert_meta(&l1_checkpoint_key(contract), &bytes)
+    }
+
+    async fn load_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>> {
+        let Some(bytes) = self.get_meta(&l1_checkpoint_key(contract))? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            bincode::deserialize(&bytes).context("malformed L1 checkpoint")?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use tempfile::TempDir;
+
+    use super::{super::testing::TestablePersistence, *};
+
+    #[async_trait]
+    impl TestablePersistence for Persistence {
+        type Storage = TempDir;
+
+        async fn tmp_storage() -> Self::Storage {
+            TempDir::new().unwrap()
+        }
+
+        fn options(storage: &Self::Storage) -> impl PersistenceOptions<Persistence = Self> {
+            Options::new(storage.path().join("consensus.redb"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod generic_tests {
+    use super::{super::persistence_tests, Persistence};
+    // For some reason this is the only way to import the macro defined in another module of this
+    // crate.
+    use crate::*;
+
+    instantiate_persistence_tests!(Persistence);
+}
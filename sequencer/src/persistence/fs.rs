@@ -11,11 +11,12 @@ use anyhow::{anyhow, Context};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use clap::Parser;
+use committable::{Commitment, Committable};
 use espresso_types::{
     traits::MembershipPersistence,
     v0::traits::{EventConsumer, PersistenceOptions, SequencerPersistence},
-    v0_3::{EventKey, IndexedStake, StakeTableEvent, Validator},
-    Leaf, Leaf2, NetworkConfig, Payload, SeqTypes,
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
+    Leaf, Leaf2, NetworkConfig, Payload, SeqTypes, Transaction,
 };
 use hotshot::{types::BLSPubKey, InitializerEpochInfo};
 use hotshot_types::{
@@ -24,7 +25,7 @@ use hotshot_types::{
         DaProposal, DaProposal2, EpochNumber, QuorumProposal, QuorumProposal2,
         QuorumProposalWrapper, VidCommitment, VidDisperseShare,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::{Event, EventType, HotShotAction, LeafInfo},
     message::{convert_proposal, Proposal},
     simple_certificate::{
@@ -39,6 +40,7 @@ use hotshot_types::{
 };
 use indexmap::IndexMap;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::ViewNumber;
 
@@ -110,12 +112,19 @@ impl PersistenceOptions for Options {
             HashSet::new()
         };
 
+        let mut inner = Inner {
+            path,
+            migrated,
+            view_retention,
+        };
+        // Finish any consensus-critical write that was interrupted, before this node can
+        // participate in consensus.
+        inner
+            .reconcile_intents()
+            .context("failed to reconcile write-ahead log")?;
+
         Ok(Persistence {
-            inner: Arc::new(RwLock::new(Inner {
-                path,
-                migrated,
-                view_retention,
-            })),
+            inner: Arc::new(RwLock::new(inner)),
         })
     }
 
@@ -199,6 +208,10 @@ impl Inner {
         self.path.join("stake_table")
     }
 
+    fn l1_checkpoint_dir_path(&self) -> PathBuf {
+        self.path.join("l1_event_checkpoint")
+    }
+
     fn next_epoch_qc(&self) -> PathBuf {
         self.path.join("next_epoch_quorum_certificate")
     }
@@ -207,6 +220,11 @@ impl Inner {
         self.path.join("epoch_drb_result")
     }
 
+    /// Path to a directory containing the progress of ongoing DRB computations.
+    fn drb_input_dir_path(&self) -> PathBuf {
+        self.path.join("drb_input")
+    }
+
     fn epoch_root_block_header_dir_path(&self) -> PathBuf {
         self.path.join("epoch_root_block_header")
     }
@@ -219,6 +237,33 @@ impl Inner {
         self.path.join("state_cert")
     }
 
+    /// Path to a directory containing transactions submitted to this node which have not been
+    /// sequenced yet.
+    fn pending_transactions_dir_path(&self) -> PathBuf {
+        self.path.join("pending_transactions")
+    }
+
+    /// Path to a directory containing the height of the last block delivered to each notification
+    /// sink.
+    fn notification_cursor_dir_path(&self) -> PathBuf {
+        self.path.join("notification_cursor")
+    }
+
+    /// Path to a directory containing encoded notifications of decided blocks awaiting delivery,
+    /// by block height.
+    fn notification_queue_dir_path(&self) -> PathBuf {
+        self.path.join("notification_queue")
+    }
+
+    /// Path to the write-ahead log for consensus-critical writes.
+    fn wal_dir_path(&self) -> PathBuf {
+        self.path.join("wal")
+    }
+
+    fn intent_path(&self) -> PathBuf {
+        self.wal_dir_path().join("intent")
+    }
+
     fn update_migration(&mut self) -> anyhow::Result<()> {
         let path = self.migration();
         let bytes = bincode::serialize(&self.migrated)?;
@@ -277,6 +322,81 @@ impl Inner {
         Ok(())
     }
 
+    /// Crash-consistently overwrite a file if a condition is met.
+    ///
+    /// This behaves like [`replace`](Self::replace), except that `contents` produces the new
+    /// contents of the file rather than writing them. Before `path` is touched, the new contents
+    /// are recorded as an intent in the write-ahead log and synced to disk. The file is then
+    /// replaced and synced, and finally the intent is marked complete by removing it. If the node
+    /// crashes in between, the intent is replayed by [`reconcile_intents`](Self::reconcile_intents)
+    /// on the next startup, so a write is never lost or torn once it has started.
+    ///
+    /// Since all writes happen under an exclusive lock, there is at most one incomplete intent at
+    /// any time.
+    fn replace_logged(
+        &mut self,
+        path: &Path,
+        pred: impl FnOnce(File) -> anyhow::Result<bool>,
+        contents: impl FnOnce() -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<()> {
+        if path.is_file() && !pred(File::open(path)?)? {
+            return Ok(());
+        }
+
+        // Record the intent.
+        let intent = Intent {
+            path: path
+                .strip_prefix(&self.path)
+                .context("logged write outside of storage directory")?
+                .to_owned(),
+            contents: contents()?,
+        };
+        fs::create_dir_all(self.wal_dir_path()).context("failed to create WAL dir")?;
+        write_synced(
+            &self.intent_path(),
+            &bincode::serialize(&intent).context("serialize intent")?,
+        )
+        .context("failed to record intent")?;
+
+        // Act.
+        write_synced(path, &intent.contents)?;
+
+        // Mark the intent complete. If we crash before this takes effect, the write will be
+        // replayed on startup, which is harmless since it is idempotent.
+        fs::remove_file(self.intent_path()).context("failed to complete intent")?;
+        Ok(())
+    }
+
+    /// Complete any logged write which was interrupted by a crash.
+    fn reconcile_intents(&mut self) -> anyhow::Result<()> {
+        // A leftover swap file is an intent which was never fully recorded, so the corresponding
+        // write never started and there is nothing to replay.
+        let mut swap_path = self.intent_path();
+        swap_path.set_extension("swp");
+        if swap_path.is_file() {
+            fs::remove_file(&swap_path).context("failed to remove partial intent")?;
+        }
+
+        let intent_path = self.intent_path();
+        if !intent_path.is_file() {
+            return Ok(());
+        }
+        let bytes = fs::read(&intent_path).context("failed to read intent")?;
+        let intent: Intent = bincode::deserialize(&bytes).context("malformed intent")?;
+        tracing::warn!(
+            path = %intent.path.display(),
+            "replaying storage write interrupted by crash"
+        );
+
+        let path = self.path.join(&intent.path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        write_synced(&path, &intent.contents)?;
+        fs::remove_file(&intent_path).context("failed to complete intent")?;
+        Ok(())
+    }
+
     fn collect_garbage(
         &mut self,
         decided_view: ViewNumber,
@@ -690,7 +810,7 @@ impl SequencerPersistence for Persistence {
         fs::create_dir_all(dir_path.clone()).context("failed to create vid dir")?;
 
         let file_path = dir_path.join(view_number.to_string()).with_extension("txt");
-        inner.replace(
+        inner.replace_logged(
             &file_path,
             |_| {
                 // Don't overwrite an existing share, but warn about it as this is likely not intended
@@ -698,12 +818,10 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate VID share");
                 Ok(false)
             },
-            |mut file| {
+            || {
                 let proposal: Proposal<SeqTypes, VidDisperseShare<SeqTypes>> =
                     convert_proposal(proposal.clone());
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
+                bincode::serialize(&proposal).context("serialize proposal")
             },
         )
     }
@@ -720,7 +838,7 @@ impl SequencerPersistence for Persistence {
 
         let file_path = dir_path.join(view_number.to_string()).with_extension("txt");
 
-        inner.replace(
+        inner.replace_logged(
             &file_path,
             |_| {
                 // Don't overwrite an existing share, but warn about it as this is likely not intended
@@ -728,12 +846,10 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate VID share");
                 Ok(false)
             },
-            |mut file| {
+            || {
                 let proposal: Proposal<SeqTypes, VidDisperseShare<SeqTypes>> =
                     convert_proposal(proposal.clone());
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
+                bincode::serialize(&proposal).context("serialize proposal")
             },
         )
     }
@@ -749,7 +865,7 @@ impl SequencerPersistence for Persistence {
         fs::create_dir_all(dir_path.clone()).context("failed to create da dir")?;
 
         let file_path = dir_path.join(view_number.to_string()).with_extension("txt");
-        inner.replace(
+        inner.replace_logged(
             &file_path,
             |_| {
                 // Don't overwrite an existing proposal, but warn about it as this is likely not
@@ -757,11 +873,7 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate DA proposal");
                 Ok(false)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
     async fn record_action(
@@ -776,7 +888,7 @@ impl SequencerPersistence for Persistence {
         }
         let mut inner = self.inner.write().await;
         let path = &inner.voted_view_path();
-        inner.replace(
+        inner.replace_logged(
             path,
             |mut file| {
                 let mut bytes = vec![];
@@ -789,10 +901,7 @@ impl SequencerPersistence for Persistence {
                 // Overwrite the file if the saved view is older than the new view.
                 Ok(saved_view < view)
             },
-            |mut file| {
-                file.write_all(&view.u64().to_le_bytes())?;
-                Ok(())
-            },
+            || Ok(view.u64().to_le_bytes().to_vec()),
         )
     }
 
@@ -807,18 +916,13 @@ impl SequencerPersistence for Persistence {
         fs::create_dir_all(dir_path.clone()).context("failed to create proposals dir")?;
 
         let file_path = dir_path.join(view_number.to_string()).with_extension("txt");
-        inner.replace(
+        inner.replace_logged(
             &file_path,
             |_| {
                 // Always overwrite the previous file
                 Ok(true)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
     async fn load_quorum_proposals(
@@ -959,7 +1063,7 @@ impl SequencerPersistence for Persistence {
         fs::create_dir_all(dir_path.clone()).context("failed to create da dir")?;
 
         let file_path = dir_path.join(view_number.to_string()).with_extension("txt");
-        inner.replace(
+        inner.replace_logged(
             &file_path,
             |_| {
                 // Don't overwrite an existing proposal, but warn about it as this is likely not
@@ -967,11 +1071,7 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate DA proposal");
                 Ok(false)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
 
@@ -1260,6 +1360,40 @@ impl SequencerPersistence for Persistence {
         Ok(())
     }
 
+    async fn store_drb_input(&self, drb_input: DrbInput) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        let dir_path = inner.drb_input_dir_path();
+
+        fs::create_dir_all(dir_path.clone()).context("failed to create drb input dir")?;
+
+        let drb_input_bytes = bincode::serialize(&drb_input).context("serialize drb input")?;
+
+        let file_path = dir_path
+            .join(drb_input.epoch.to_string())
+            .with_extension("txt");
+        fs::write(file_path, drb_input_bytes).context(format!(
+            "writing drb input file for epoch {}",
+            drb_input.epoch
+        ))?;
+
+        Ok(())
+    }
+
+    async fn load_drb_input(&self, epoch: EpochNumber) -> anyhow::Result<Option<DrbInput>> {
+        let inner = self.inner.read().await;
+        let file_path = inner
+            .drb_input_dir_path()
+            .join(epoch.to_string())
+            .with_extension("txt");
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&file_path).context("read drb input")?;
+        let drb_input = bincode::deserialize(&bytes).context("deserialize drb input")?;
+        Ok(Some(drb_input))
+    }
+
     async fn add_epoch_root(
         &self,
         epoch: EpochNumber,
@@ -1381,6 +1515,176 @@ impl SequencerPersistence for Persistence {
 
         Ok(result)
     }
+
+    async fn add_pending_transaction(
+        &self,
+        tx: &Transaction,
+        submitted: u64,
+    ) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        let dir_path = inner.pending_transactions_dir_path();
+        fs::create_dir_all(&dir_path).context("failed to create pending transactions dir")?;
+
+        let bytes =
+            bincode::serialize(&(tx, submitted)).context("serialize pending transaction")?;
+        let file_path = dir_path.join(tx.commit().to_string()).with_extension("txt");
+        fs::write(file_path, bytes)
+            .context(format!("writing pending transaction {}", tx.commit()))?;
+
+        Ok(())
+    }
+
+    async fn remove_pending_transactions(
+        &self,
+        hashes: &[Commitment<Transaction>],
+    ) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        let dir_path = inner.pending_transactions_dir_path();
+        for hash in hashes {
+            let file_path = dir_path.join(hash.to_string()).with_extension("txt");
+            if file_path.is_file() {
+                fs::remove_file(&file_path).context(format!(
+                    "removing pending transaction {}",
+                    file_path.display()
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn prune_pending_transactions(&self, submitted_before: u64) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        for (path, (_, submitted)) in pending_transaction_files(&inner)? {
+            if submitted < submitted_before {
+                fs::remove_file(&path)
+                    .context(format!("removing pending transaction {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn load_pending_transactions(&self) -> anyhow::Result<Vec<(Transaction, u64)>> {
+        let inner = self.inner.read().await;
+        let mut result = pending_transaction_files(&inner)?
+            .into_iter()
+            .map(|(_, pending)| pending)
+            .collect::<Vec<_>>();
+        result.sort_by_key(|(_, submitted)| *submitted);
+        Ok(result)
+    }
+
+    async fn store_notification_cursor(&self, sink: &str, height: u64) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        let dir_path = inner.notification_cursor_dir_path();
+        fs::create_dir_all(&dir_path).context("failed to create notification cursor dir")?;
+
+        let file_path = dir_path.join(format!("{sink}.txt"));
+        fs::write(file_path, height.to_string())
+            .context(format!("writing notification cursor for {sink}"))?;
+        Ok(())
+    }
+
+    async fn load_notification_cursor(&self, sink: &str) -> anyhow::Result<Option<u64>> {
+        let inner = self.inner.read().await;
+        let file_path = inner
+            .notification_cursor_dir_path()
+            .join(format!("{sink}.txt"));
+        if !file_path.is_file() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(&file_path)
+            .context(format!("reading notification cursor for {sink}"))?;
+        let height = contents
+            .trim()
+            .parse()
+            .context(format!("parsing notification cursor for {sink}"))?;
+        Ok(Some(height))
+    }
+
+    async fn append_notifications(&self, notifications: &[(u64, Vec<u8>)]) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        let dir_path = inner.notification_queue_dir_path();
+        fs::create_dir_all(&dir_path).context("failed to create notification queue dir")?;
+
+        for (height, data) in notifications {
+            let file_path = dir_path.join(height.to_string()).with_extension("txt");
+            fs::write(file_path, data).context(format!("writing notification {height}"))?;
+        }
+        Ok(())
+    }
+
+    async fn load_notifications(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<u8>)>> {
+        let inner = self.inner.read().await;
+        notification_files(&inner)?
+            .into_iter()
+            .filter(|(height, _)| after.is_none_or(|after| *height > after))
+            .take(limit)
+            .map(|(height, path)| {
+                let data = fs::read(&path).context(format!("reading notification {height}"))?;
+                Ok((height, data))
+            })
+            .collect()
+    }
+
+    async fn prune_notifications(&self, height: u64) -> anyhow::Result<()> {
+        let inner = self.inner.write().await;
+        for (queued, path) in notification_files(&inner)? {
+            if queued <= height {
+                fs::remove_file(&path).context(format!("removing notification {queued}"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// All queued notifications in storage, with the files they are stored in, in order of height.
+fn notification_files(inner: &Inner) -> anyhow::Result<Vec<(u64, PathBuf)>> {
+    let dir_path = inner.notification_queue_dir_path();
+    if !dir_path.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut result = vec![];
+    for entry in fs::read_dir(&dir_path)? {
+        let path = entry?.path();
+        if path.extension() != Some("txt".as_ref()) {
+            continue;
+        }
+        let Some(height) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        else {
+            continue;
+        };
+        result.push((height, path));
+    }
+    result.sort_by_key(|(height, _)| *height);
+    Ok(result)
+}
+
+/// All pending transactions in storage, with the files they are stored in.
+fn pending_transaction_files(inner: &Inner) -> anyhow::Result<Vec<(PathBuf, (Transaction, u64))>> {
+    let dir_path = inner.pending_transactions_dir_path();
+    if !dir_path.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut result = vec![];
+    for entry in fs::read_dir(&dir_path)? {
+        let path = entry?.path();
+        if path.extension() != Some("txt".as_ref()) {
+            continue;
+        }
+        let bytes =
+            fs::read(&path).context(format!("reading pending transaction {}", path.display()))?;
+        let pending = bincode::deserialize(&bytes)
+            .context(format!("parsing pending transaction {}", path.display()))?;
+        result.push((path, pending));
+    }
+    Ok(result)
 }
 
 #[async_trait]
@@ -1489,6 +1793,62 @@ impl MembershipPersistence for Persistence {
         let (l1_block, events) = serde_json::from_reader(reader)?;
         Ok(Some((l1_block, events)))
     }
+
+    async fn store_events_with_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        l1_block: u64,
+        events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        // The file system has no transactions. The checkpoint is written after the events, so if
+        // the node stops in between, the stored events no longer match the checkpoint and are
+        // scanned again, which is slow but safe.
+        let checkpoint = L1EventCheckpoint {
+            l1_block,
+            last_event: events.last().map(|(key, _)| *key),
+        };
+        self.store_events(l1_block, events).await?;
+        self.store_l1_checkpoint(contract, checkpoint).await
+    }
+
+    async fn store_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner.write().await;
+        let dir_path = inner.l1_checkpoint_dir_path();
+        fs::create_dir_all(&dir_path).context("failed to create L1 checkpoint dir")?;
+
+        let file_path = dir_path.join(contract.to_string()).with_extension("json");
+        inner.replace(
+            &file_path,
+            |_| Ok(true),
+            |file| {
+                serde_json::to_writer(BufWriter::new(file), &checkpoint)?;
+                Ok(())
+            },
+        )
+    }
+
+    async fn load_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>> {
+        let inner = self.inner.read().await;
+        let file_path = inner
+            .l1_checkpoint_dir_path()
+            .join(contract.to_string())
+            .with_extension("json");
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(file_path).context("open L1 checkpoint")?;
+        let checkpoint =
+            serde_json::from_reader(BufReader::new(file)).context("malformed L1 checkpoint")?;
+        Ok(Some(checkpoint))
+    }
 }
 
 /// Update a `NetworkConfig` that may have originally been persisted with an old version.
@@ -1551,6 +1911,38 @@ fn migrate_network_config(
     Ok(network_config)
 }
 
+/// A write recorded in the write-ahead log before it is applied.
+#[derive(Debug, Serialize, Deserialize)]
+struct Intent {
+    /// Path of the file to write, relative to the storage directory.
+    path: PathBuf,
+    /// Full contents of the file after the write.
+    contents: Vec<u8>,
+}
+
+/// Atomically replace the contents of `path`, syncing the new contents to disk.
+///
+/// The contents are written to a swap file, which is synced before being renamed over `path`. The
+/// parent directory is then synced, so that the rename itself is durable.
+fn write_synced(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let mut swap_path = path.to_owned();
+    swap_path.set_extension("swp");
+    let mut swap = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .open(&swap_path)?;
+    swap.write_all(contents)?;
+    swap.sync_all()?;
+    drop(swap);
+
+    fs::rename(&swap_path, path)?;
+    if let Some(dir) = path.parent() {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
 /// Get all paths under `dir` whose name is of the form <view number>.txt.
 fn view_files(
     dir: impl AsRef<Path>,
@@ -1644,6 +2036,7 @@ mod test {
     use hotshot_types::{
         data::{vid_commitment, QuorumProposal2},
         light_client::LightClientState,
+        signer::Signer,
         simple_certificate::QuorumCertificate,
         simple_vote::QuorumData,
         traits::{node_implementation::Versions, EncodeBytes},
@@ -1926,7 +2319,7 @@ mod test {
 
             tracing::debug!("inserting vid for {view}");
             storage
-                .append_vid(&vid.to_proposal(&privkey).unwrap())
+                .append_vid(&vid.to_proposal(&Signer::Local(privkey.clone())).unwrap())
                 .await
                 .unwrap();
 
@@ -2004,6 +2397,44 @@ mod test {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reconcile_interrupted_write() {
+        setup_test();
+
+        let tmp = Persistence::tmp_storage().await;
+        let mut opt = Persistence::options(&tmp);
+        let storage = opt.create().await.unwrap();
+        storage
+            .record_action(ViewNumber::new(1), None, HotShotAction::Vote)
+            .await
+            .unwrap();
+
+        // Simulate a crash while voting in view 2, after the intent was recorded but while the
+        // voted view file was only partially written.
+        {
+            let inner = storage.inner.read().await;
+            let intent = Intent {
+                path: "highest_voted_view".into(),
+                contents: 2u64.to_le_bytes().to_vec(),
+            };
+            fs::write(inner.intent_path(), bincode::serialize(&intent).unwrap()).unwrap();
+            fs::write(inner.voted_view_path(), [2]).unwrap();
+
+            // Also leave behind a partially recorded intent, which should be discarded.
+            fs::write(inner.wal_dir_path().join("intent.swp"), [0; 3]).unwrap();
+        }
+        drop(storage);
+
+        // On restart, the interrupted write is completed.
+        let storage = opt.create().await.unwrap();
+        assert_eq!(
+            storage.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(2))
+        );
+        assert!(!tmp.path().join("wal/intent").exists());
+        assert!(!tmp.path().join("wal/intent.swp").exists());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_load_quorum_proposals_invalid_extension() {
         setup_test();
@@ -5,11 +5,12 @@ use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::bail;
 use async_trait::async_trait;
+use committable::Commitment;
 use espresso_types::{
     traits::MembershipPersistence,
     v0::traits::{EventConsumer, PersistenceOptions, SequencerPersistence},
-    v0_3::{EventKey, IndexedStake, StakeTableEvent, Validator},
-    Leaf2, NetworkConfig,
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
+    Leaf2, NetworkConfig, Transaction,
 };
 use hotshot::{types::BLSPubKey, InitializerEpochInfo};
 use hotshot_types::{
@@ -18,7 +19,7 @@ use hotshot_types::{
         DaProposal, DaProposal2, EpochNumber, QuorumProposalWrapper, VidCommitment,
         VidDisperseShare,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::{Event, EventType, HotShotAction, LeafInfo},
     message::Proposal,
     simple_certificate::{
@@ -220,6 +221,14 @@ impl SequencerPersistence for NoStorage {
         Ok(())
     }
 
+    async fn store_drb_input(&self, _drb_input: DrbInput) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_drb_input(&self, _epoch: EpochNumber) -> anyhow::Result<Option<DrbInput>> {
+        Ok(None)
+    }
+
     async fn add_epoch_root(
         &self,
         _epoch: EpochNumber,
@@ -244,6 +253,53 @@ impl SequencerPersistence for NoStorage {
     ) -> anyhow::Result<Option<LightClientStateUpdateCertificate<SeqTypes>>> {
         Ok(None)
     }
+
+    async fn add_pending_transaction(
+        &self,
+        _tx: &Transaction,
+        _submitted: u64,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn remove_pending_transactions(
+        &self,
+        _hashes: &[Commitment<Transaction>],
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn prune_pending_transactions(&self, _submitted_before: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_pending_transactions(&self) -> anyhow::Result<Vec<(Transaction, u64)>> {
+        Ok(vec![])
+    }
+
+    async fn store_notification_cursor(&self, _sink: &str, _height: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_notification_cursor(&self, _sink: &str) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    async fn append_notifications(&self, _notifications: &[(u64, Vec<u8>)]) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_notifications(
+        &self,
+        _after: Option<u64>,
+        _limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<u8>)>> {
+        Ok(vec![])
+    }
+
+    async fn prune_notifications(&self, _height: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -277,4 +333,28 @@ impl MembershipPersistence for NoStorage {
     async fn load_events(&self) -> anyhow::Result<Option<(u64, Vec<(EventKey, StakeTableEvent)>)>> {
         Ok(None)
     }
+
+    async fn store_events_with_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+        _l1_block: u64,
+        _events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn store_l1_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+        _checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_l1_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>> {
+        Ok(None)
+    }
 }
@@ -0,0 +1,447 @@
+//! Portable snapshots of consensus storage.
+//!
+//! A snapshot holds everything a node needs to resume consensus where it left off: the network
+//! config, the anchor leaf, the latest view the node acted in, undecided proposals, DA proposals and
+//! VID shares, certificates, epoch and stake table information, and pending transactions. Snapshots
+//! are read and written through [`SequencerPersistence`], so a snapshot taken from one storage
+//! backend can be restored into another, for example when moving a node from the file system to
+//! Postgres along with moving it to a new machine.
+//!
+//! Archives are written with a manifest describing the snapshot and a checksum of its contents,
+//! which is verified before anything is restored.
+
+use std::{
+    cmp::max,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    marker::PhantomData,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, ensure, Context};
+use espresso_types::{
+    traits::{MembershipPersistence, NullEventConsumer},
+    v0::traits::SequencerPersistence,
+    v0_3::{EventKey, IndexedStake, StakeTableEvent},
+    Header, Leaf2, NetworkConfig, SeqTypes, Transaction,
+};
+use hotshot_types::{
+    data::{
+        DaProposal2, EpochNumber, QuorumProposalWrapper, VidCommitment, VidDisperseShare,
+        ViewNumber,
+    },
+    drb::{DrbInput, DrbResult},
+    event::{HotShotAction, LeafInfo},
+    message::Proposal,
+    simple_certificate::{
+        LightClientStateUpdateCertificate, NextEpochQuorumCertificate2, QuorumCertificate2,
+        UpgradeCertificate,
+    },
+    traits::node_implementation::ConsensusTime,
+    vote::HasViewNumber,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Version of the archive format written by this module
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Number of most recent stake tables included in a snapshot
+///
+/// This matches the number of stake tables a node reloads from storage on startup.
+const STAKE_TABLE_EPOCHS: u64 = 50;
+
+/// Description of an archived snapshot, readable without restoring it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the archive format
+    pub format_version: u32,
+    /// When the snapshot was taken, in seconds since the Unix epoch
+    pub created_at: u64,
+    /// View of the anchor leaf, if the node has decided any leaves
+    pub anchor_view: Option<ViewNumber>,
+    /// Height of the anchor leaf, if the node has decided any leaves
+    pub anchor_height: Option<u64>,
+    /// Latest view in which the node voted or proposed
+    pub latest_acted_view: Option<ViewNumber>,
+    /// Height of the query service storage accompanying consensus storage, if any
+    pub query_block_height: Option<u64>,
+    /// SHA-256 digest of the archived snapshot
+    pub checksum: [u8; 32],
+}
+
+/// Epoch information needed to resume consensus
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EpochSnapshot {
+    pub epoch: EpochNumber,
+    pub drb_result: DrbResult,
+    pub root: Option<Header>,
+    pub drb_input: Option<DrbInput>,
+}
+
+/// The contents of consensus storage
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The network config, as JSON, which is how every storage backend stores it
+    pub config: Option<String>,
+    pub latest_acted_view: Option<ViewNumber>,
+    pub anchor: Option<(Leaf2, QuorumCertificate2<SeqTypes>)>,
+    pub next_epoch_high_qc: Option<NextEpochQuorumCertificate2<SeqTypes>>,
+    pub quorum_proposals: Vec<Proposal<SeqTypes, QuorumProposalWrapper<SeqTypes>>>,
+    pub da_proposals: Vec<(Proposal<SeqTypes, DaProposal2<SeqTypes>>, VidCommitment)>,
+    pub vid_shares: Vec<Proposal<SeqTypes, VidDisperseShare<SeqTypes>>>,
+    pub upgrade_certificate: Option<UpgradeCertificate<SeqTypes>>,
+    pub state_cert: Option<LightClientStateUpdateCertificate<SeqTypes>>,
+    pub epochs: Vec<EpochSnapshot>,
+    pub stake_tables: Vec<IndexedStake>,
+    pub stake_table_events: Option<(u64, Vec<(EventKey, StakeTableEvent)>)>,
+    /// Transactions submitted to this node and not yet sequenced, with their submission times
+    pub pending_transactions: Vec<(Transaction, u64)>,
+    /// The genesis file the node was started with, if it was archived along with its storage
+    pub genesis: Option<String>,
+}
+
+impl Snapshot {
+    /// Take a snapshot of `storage`.
+    ///
+    /// The node must be stopped while the snapshot is taken. As a safeguard, this fails if the
+    /// anchor leaf or latest acted view change while the snapshot is being taken.
+    pub async fn export<P>(storage: &P) -> anyhow::Result<Self>
+    where
+        P: SequencerPersistence + MembershipPersistence,
+    {
+        let latest_acted_view = storage
+            .load_latest_acted_view()
+            .await
+            .context("loading latest acted view")?;
+        let anchor = storage
+            .load_anchor_leaf()
+            .await
+            .context("loading anchor leaf")?;
+        let config = storage
+            .load_config()
+            .await
+            .context("loading network config")?
+            .map(|cfg| serde_json::to_string(&cfg))
+            .transpose()
+            .context("serializing network config")?;
+
+        let quorum_proposals = storage
+            .load_quorum_proposals()
+            .await
+            .context("loading quorum proposals")?;
+
+        // DA proposals and VID shares are only needed for views after the anchor.
+        let from = anchor
+            .as_ref()
+            .map_or(ViewNumber::genesis(), |(leaf, _)| leaf.view_number());
+        let to = max(
+            latest_acted_view.unwrap_or(from),
+            quorum_proposals.keys().last().copied().unwrap_or(from),
+        );
+        let mut da_proposals = vec![];
+        let mut vid_shares = vec![];
+        for view in from.u64()..=to.u64() {
+            let view = ViewNumber::new(view);
+            let share = storage
+                .load_vid_share(view)
+                .await
+                .with_context(|| format!("loading VID share for view {view:?}"))?;
+            if let Some(proposal) = storage
+                .load_da_proposal(view)
+                .await
+                .with_context(|| format!("loading DA proposal for view {view:?}"))?
+            {
+                // Storage does not return the VID commitment stored with a DA proposal, so recover
+                // it from the VID share or quorum proposal for the same view.
+                let vid_commit = share
+                    .as_ref()
+                    .map(|share| share.data.payload_commitment())
+                    .or_else(|| {
+                        quorum_proposals
+                            .get(&view)
+                            .map(|proposal| proposal.data.block_header().payload_commitment())
+                    });
+                match vid_commit {
+                    Some(vid_commit) => da_proposals.push((proposal, vid_commit)),
+                    None => tracing::warn!(
+                        ?view,
+                        "skipping DA proposal with no VID share or quorum proposal"
+                    ),
+                }
+            }
+            vid_shares.extend(share);
+        }
+
+        let mut epochs = vec![];
+        for info in storage
+            .load_start_epoch_info()
+            .await
+            .context("loading epoch info")?
+        {
+            let drb_input = storage
+                .load_drb_input(info.epoch)
+                .await
+                .with_context(|| format!("loading DRB input for epoch {}", info.epoch))?;
+            epochs.push(EpochSnapshot {
+                epoch: info.epoch,
+                drb_result: info.drb_result,
+                root: info.block_header,
+                drb_input,
+            });
+        }
+
+        let snapshot = Self {
+            config,
+            latest_acted_view,
+            anchor,
+            next_epoch_high_qc: storage
+                .load_next_epoch_quorum_certificate()
+                .await
+                .context("loading next epoch QC")?,
+            quorum_proposals: quorum_proposals.into_values().collect(),
+            da_proposals,
+            vid_shares,
+            upgrade_certificate: storage
+                .load_upgrade_certificate()
+                .await
+                .context("loading upgrade certificate")?,
+            state_cert: storage
+                .load_state_cert()
+                .await
+                .context("loading light client state update certificate")?,
+            epochs,
+            stake_tables: storage
+                .load_latest_stake(STAKE_TABLE_EPOCHS)
+                .await
+                .context("loading stake tables")?
+                .unwrap_or_default(),
+            stake_table_events: storage
+                .load_events()
+                .await
+                .context("loading stake table events")?,
+            pending_transactions: storage
+                .load_pending_transactions()
+                .await
+                .context("loading pending transactions")?,
+            genesis: None,
+        };
+
+        // Check that the node did not make progress while we were reading its storage, which would
+        // leave the snapshot inconsistent.
+        let anchor_view = storage
+            .load_anchor_leaf()
+            .await
+            .context("loading anchor leaf")?
+            .map(|(leaf, _)| leaf.view_number());
+        ensure!(
+            anchor_view == snapshot.anchor_view()
+                && storage.load_latest_acted_view().await? == snapshot.latest_acted_view,
+            "storage changed while the snapshot was being taken; stop the node and try again"
+        );
+
+        Ok(snapshot)
+    }
+
+    /// Restore this snapshot into `storage`, which must be empty.
+    pub async fn import<P>(&self, storage: &P) -> anyhow::Result<()>
+    where
+        P: SequencerPersistence + MembershipPersistence,
+    {
+        ensure!(
+            storage.load_anchor_leaf().await?.is_none()
+                && storage.load_latest_acted_view().await?.is_none(),
+            "target storage already holds consensus state; reset it before importing"
+        );
+
+        if let Some(config) = &self.config {
+            let config: NetworkConfig =
+                serde_json::from_str(config).context("deserializing network config")?;
+            storage
+                .save_config(&config)
+                .await
+                .context("saving network config")?;
+        }
+
+        // Restore the anchor leaf first: deciding it garbage collects older data, which must not
+        // include anything restored after it.
+        if let Some((leaf, qc)) = &self.anchor {
+            let info = LeafInfo {
+                leaf: leaf.clone(),
+                vid_share: None,
+                state: Default::default(),
+                delta: None,
+                state_cert: None,
+            };
+            storage
+                .append_decided_leaves(
+                    leaf.view_number(),
+                    [(&info, qc.clone())],
+                    &NullEventConsumer,
+                )
+                .await
+                .context("restoring anchor leaf")?;
+        }
+        if let Some(view) = self.latest_acted_view {
+            storage
+                .record_action(view, None, HotShotAction::Vote)
+                .await
+                .context("restoring latest acted view")?;
+        }
+        if let Some(qc) = &self.next_epoch_high_qc {
+            storage
+                .store_next_epoch_quorum_certificate(qc.clone())
+                .await
+                .context("restoring next epoch QC")?;
+        }
+
+        for proposal in &self.quorum_proposals {
+            storage
+                .append_quorum_proposal2(proposal)
+                .await
+                .context("restoring quorum proposal")?;
+        }
+        for (proposal, vid_commit) in &self.da_proposals {
+            storage
+                .append_da2(proposal, *vid_commit)
+                .await
+                .context("restoring DA proposal")?;
+        }
+        for share in &self.vid_shares {
+            match &share.data {
+                VidDisperseShare::V0(data) => {
+                    storage
+                        .append_vid(&Proposal {
+                            data: data.clone(),
+                            signature: share.signature.clone(),
+                            _pd: PhantomData,
+                        })
+                        .await
+                },
+                VidDisperseShare::V1(data) => {
+                    storage
+                        .append_vid2(&Proposal {
+                            data: data.clone(),
+                            signature: share.signature.clone(),
+                            _pd: PhantomData,
+                        })
+                        .await
+                },
+            }
+            .context("restoring VID share")?;
+        }
+
+        if self.upgrade_certificate.is_some() {
+            storage
+                .store_upgrade_certificate(self.upgrade_certificate.clone())
+                .await
+                .context("restoring upgrade certificate")?;
+        }
+        if let Some(cert) = &self.state_cert {
+            storage
+                .add_state_cert(cert.clone())
+                .await
+                .context("restoring light client state update certificate")?;
+        }
+
+        for epoch in &self.epochs {
+            storage
+                .add_drb_result(epoch.epoch, epoch.drb_result)
+                .await
+                .with_context(|| format!("restoring DRB result for epoch {}", epoch.epoch))?;
+            if let Some(root) = &epoch.root {
+                storage
+                    .add_epoch_root(epoch.epoch, root.clone())
+                    .await
+                    .with_context(|| format!("restoring epoch root for epoch {}", epoch.epoch))?;
+            }
+            if let Some(input) = &epoch.drb_input {
+                storage
+                    .store_drb_input(input.clone())
+                    .await
+                    .with_context(|| format!("restoring DRB input for epoch {}", epoch.epoch))?;
+            }
+        }
+        for (epoch, stake) in &self.stake_tables {
+            storage
+                .store_stake(*epoch, stake.clone())
+                .await
+                .with_context(|| format!("restoring stake table for epoch {epoch}"))?;
+        }
+        if let Some((l1_block, events)) = &self.stake_table_events {
+            storage
+                .store_events(*l1_block, events.clone())
+                .await
+                .context("restoring stake table events")?;
+        }
+
+        for (tx, submitted) in &self.pending_transactions {
+            storage
+                .add_pending_transaction(tx, *submitted)
+                .await
+                .context("restoring pending transaction")?;
+        }
+
+        Ok(())
+    }
+
+    /// View of the anchor leaf, if any.
+    pub fn anchor_view(&self) -> Option<ViewNumber> {
+        self.anchor.as_ref().map(|(leaf, _)| leaf.view_number())
+    }
+
+    /// Write this snapshot to an archive at `path`.
+    ///
+    /// `query_block_height` is recorded in the manifest, so that the query storage of the node the
+    /// archive is restored on can be checked against it.
+    pub fn write(&self, path: &Path, query_block_height: Option<u64>) -> anyhow::Result<Manifest> {
+        let contents = bincode::serialize(self).context("serializing snapshot")?;
+        let manifest = Manifest {
+            format_version: FORMAT_VERSION,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            anchor_view: self.anchor_view(),
+            anchor_height: self.anchor.as_ref().map(|(leaf, _)| leaf.height()),
+            latest_acted_view: self.latest_acted_view,
+            query_block_height,
+            checksum: Sha256::digest(&contents).into(),
+        };
+
+        // Write to a temporary file first, so that a failed export never leaves a truncated archive
+        // at `path`.
+        let tmp = path.with_extension("partial");
+        let mut file = BufWriter::new(
+            File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?,
+        );
+        bincode::serialize_into(&mut file, &(&manifest, &contents)).context("writing archive")?;
+        file.into_inner()
+            .context("writing archive")?
+            .sync_all()
+            .context("writing archive")?;
+        fs::rename(&tmp, path).with_context(|| format!("moving archive to {}", path.display()))?;
+
+        Ok(manifest)
+    }
+
+    /// Read a snapshot from the archive at `path`, verifying its checksum.
+    pub fn read(path: &Path) -> anyhow::Result<(Manifest, Self)> {
+        let file = File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        let (manifest, contents): (Manifest, Vec<u8>) =
+            bincode::deserialize_from(BufReader::new(file)).context("reading archive")?;
+        if manifest.format_version != FORMAT_VERSION {
+            bail!(
+                "archive has format version {}, but this program reads version {FORMAT_VERSION}",
+                manifest.format_version
+            );
+        }
+        ensure!(
+            <[u8; 32]>::from(Sha256::digest(&contents)) == manifest.checksum,
+            "archive is corrupt: checksum does not match"
+        );
+        let snapshot = bincode::deserialize(&contents).context("deserializing snapshot")?;
+        Ok((manifest, snapshot))
+    }
+}
@@ -3,26 +3,27 @@ use std::{collections::BTreeMap, path::PathBuf, str::FromStr, sync::Arc, time::D
 use anyhow::{bail, Context};
 use async_trait::async_trait;
 use clap::Parser;
-use committable::Committable;
+use committable::{Commitment, Committable};
 use derivative::Derivative;
 use derive_more::derive::{From, Into};
 use espresso_types::{
     parse_duration, parse_size,
     traits::MembershipPersistence,
     v0::traits::{EventConsumer, PersistenceOptions, SequencerPersistence, StateCatchup},
-    v0_3::{EventKey, IndexedStake, StakeTableEvent, Validator},
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
     BackoffParams, BlockMerkleTree, FeeMerkleTree, Leaf, Leaf2, NetworkConfig, Payload,
+    Transaction as SeqTransaction,
 };
-use futures::stream::StreamExt;
+use futures::{future::join_all, stream::StreamExt};
 use hotshot::{types::BLSPubKey, InitializerEpochInfo};
 use hotshot_query_service::{
     availability::LeafQueryData,
     data_source::{
         storage::{
-            pruning::PrunerCfg,
+            pruning::{PrunerCfg, ShareAttestor},
             sql::{
-                include_migrations, query_as, syntax_helpers::MAX_FN, Config, Db, SqlStorage,
-                Transaction, TransactionMode, Write,
+                include_migrations, query_as, syntax_helpers::MAX_FN, Config, DataMigration, Db,
+                Read, SqlStorage, Transaction, TransactionMode, Write,
             },
         },
         Transaction as _, VersionedDataSource,
@@ -38,9 +39,9 @@ use hotshot_types::{
     data::{
         vid_disperse::{ADVZDisperseShare, VidDisperseShare2},
         DaProposal, DaProposal2, EpochNumber, QuorumProposal, QuorumProposalWrapper, VidCommitment,
-        VidDisperseShare,
+        VidDisperseShare, VidShare,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::{Event, EventType, HotShotAction, LeafInfo},
     message::{convert_proposal, Proposal},
     simple_certificate::{
@@ -56,8 +57,11 @@ use hotshot_types::{
 use indexmap::IndexMap;
 use itertools::Itertools;
 use sqlx::{query, Executor, Row};
+use tide_disco::error::ServerError;
+use tokio::time::timeout;
+use url::Url;
 
-use crate::{catchup::SqlStateCatchup, NodeType, SeqTypes, ViewNumber};
+use crate::{catchup::SqlStateCatchup, NodeType, SeqTypes, SequencerApiVersion, ViewNumber};
 
 /// Options for Postgres-backed persistence.
 #[derive(Parser, Clone, Derivative)]
@@ -188,6 +192,28 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_SEQUENCER_ARCHIVE", conflicts_with = "prune")]
     pub(crate) archive: bool,
 
+    /// Fetch all missing historical leaves, blocks and VID data from peers on startup.
+    ///
+    /// Progress is reported via metrics and the `node/backfill-status` endpoint. This is useful
+    /// when standing up an archive node, and can be combined with ARCHIVE to also recover
+    /// previously pruned data.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_ARCHIVAL_BACKFILL",
+        conflicts_with = "lightweight"
+    )]
+    pub(crate) archival_backfill: bool,
+
+    /// The oldest block to fetch during archival backfill.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ARCHIVAL_BACKFILL_START")]
+    pub(crate) archival_backfill_start: Option<u64>,
+
+    /// The maximum number of missing objects to fetch per second during archival backfill.
+    ///
+    /// Set to 0 for no limit.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ARCHIVAL_BACKFILL_RATE_LIMIT")]
+    pub(crate) archival_backfill_rate_limit: Option<usize>,
+
     /// Turns on leaf only data storage
     #[clap(
         long,
@@ -239,6 +265,35 @@ pub struct Options {
     )]
     pub(crate) max_connections: u32,
 
+    /// Abort any query service database read which takes longer than this.
+    ///
+    /// This protects the database from runaway queries, such as expensive API requests. It does not
+    /// apply to consensus storage, to writes or to migrations run on startup.
+    #[cfg(not(feature = "embedded-db"))]
+    #[clap(long, env = "ESPRESSO_SEQUENCER_DATABASE_STATEMENT_TIMEOUT", value_parser = parse_duration)]
+    pub(crate) statement_timeout: Option<Duration>,
+
+    /// Database URI for a Postgres read replica.
+    ///
+    /// If set, read-only queries made by the query service are served from this replica, so that
+    /// heavy API traffic does not compete with consensus for connections to the primary database.
+    /// Consensus storage and all writes always use the primary database. The URI has the same
+    /// format as the primary database URI.
+    #[cfg(not(feature = "embedded-db"))]
+    #[clap(long, env = "ESPRESSO_SEQUENCER_DATABASE_READ_REPLICA_URI")]
+    // Hide from debug output since may contain sensitive data.
+    #[derivative(Debug = "ignore")]
+    pub(crate) read_replica_uri: Option<String>,
+
+    /// The maximum number of connections to the read replica to maintain at any time.
+    #[cfg(not(feature = "embedded-db"))]
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_DATABASE_READ_REPLICA_MAX_CONNECTIONS",
+        default_value = "25"
+    )]
+    pub(crate) read_replica_max_connections: u32,
+
     /// Sets the batch size for the types migration.
     /// Determines how many `(leaf, vid)` rows are selected from the old types table
     /// and migrated at once.
@@ -246,6 +301,17 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_SEQUENCER_DATABASE_TYPES_MIGRATION_BATCH_SIZE")]
     pub(crate) types_migration_batch_size: Option<u64>,
 
+    /// Number of blocks between snapshots of the merklized state, served to syncing nodes.
+    ///
+    /// Snapshots are only taken by nodes which have stored merklized state since genesis. Set to 0
+    /// to disable snapshots.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_STATE_SNAPSHOT_INTERVAL",
+        default_value = "10000"
+    )]
+    pub(crate) state_snapshot_interval: u64,
+
     // Keep the database connection pool when persistence is created,
     // allowing it to be reused across multiple instances instead of creating
     // a new pool each time such as for API, consensus storage etc
@@ -408,8 +474,10 @@ impl TryFrom<&Options> for Config {
             }
         }
 
+        cfg = cfg.data_migration(QuorumProposalLeafHashes);
+
         if opt.prune {
-            cfg = cfg.pruner_cfg(PrunerCfg::from(opt.pruning))?;
+            cfg = cfg.pruner_cfg(PrunerCfg::from(opt.pruning.clone()))?;
         }
         if opt.archive {
             cfg = cfg.archive();
@@ -419,8 +487,68 @@ impl TryFrom<&Options> for Config {
     }
 }
 
+/// Ensure the `leaf_hash` column is populated for all existing quorum proposals.
+///
+/// This column was added in a migration, but because it requires computing a commitment of the
+/// existing data, it is not easy to populate in the SQL migration itself. Instead, quorum proposals
+/// with a `NULL` value for this column are populated in batches, in order of view number.
+#[derive(Clone, Copy, Debug)]
+struct QuorumProposalLeafHashes;
+
+#[async_trait]
+impl DataMigration for QuorumProposalLeafHashes {
+    fn name(&self) -> &'static str {
+        "quorum_proposal_leaf_hashes"
+    }
+
+    async fn remaining(&self, tx: &mut Transaction<Read>, from: i64) -> anyhow::Result<u64> {
+        let (count,) = query_as::<(i64,)>(
+            "SELECT count(*) FROM quorum_proposals WHERE leaf_hash IS NULL AND view >= $1",
+        )
+        .bind(from)
+        .fetch_one(tx.as_mut())
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn migrate_batch(
+        &self,
+        tx: &mut Transaction<Write>,
+        from: i64,
+        batch_size: u64,
+    ) -> anyhow::Result<Option<i64>> {
+        let rows = query_as::<(i64, Vec<u8>)>(
+            "SELECT view, data FROM quorum_proposals
+              WHERE leaf_hash IS NULL AND view >= $1
+              ORDER BY view LIMIT $2",
+        )
+        .bind(from)
+        .bind(batch_size as i64)
+        .fetch_all(tx.as_mut())
+        .await?;
+        let Some((last_view, _)) = rows.last() else {
+            return Ok(None);
+        };
+        let next_key = last_view + 1;
+
+        let mut updates = vec![];
+        for (view, data) in rows {
+            let proposal: Proposal<SeqTypes, QuorumProposal<SeqTypes>> =
+                bincode::deserialize(&data)?;
+            let leaf = Leaf::from_quorum_proposal(&proposal.data);
+            let leaf_hash = Committable::commit(&leaf);
+            tracing::info!(view, %leaf_hash, "populating quorum proposal leaf hash");
+            updates.push((view, leaf_hash.to_string()));
+        }
+        tx.upsert("quorum_proposals", ["view", "leaf_hash"], ["view"], updates)
+            .await?;
+
+        Ok(Some(next_key))
+    }
+}
+
 /// Pruning parameters.
-#[derive(Parser, Clone, Copy, Debug)]
+#[derive(Parser, Clone, Debug)]
 pub struct PruningOptions {
     /// Threshold for pruning, specified in bytes.
     /// If the disk usage surpasses this threshold, pruning is initiated for data older than the specified minimum retention period.
@@ -446,6 +574,41 @@ pub struct PruningOptions {
     )]
     target_retention: Option<Duration>,
 
+    /// Number of most recent blocks to retain.
+    /// Blocks further than this from the head of the chain are pruned, even if they are younger
+    /// than the target retention period, but never if they are younger than the minimum retention
+    /// period.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_PRUNER_RETENTION_BLOCKS")]
+    retention_blocks: Option<u64>,
+
+    /// Keep headers, leaves and merklized state when pruning.
+    /// Only payloads, transactions and VID data are deleted, so the node can still serve the proofs
+    /// light clients need to verify pruned blocks.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_PRUNER_PRESERVE_HEADERS")]
+    preserve_headers: bool,
+
+    /// Retention period for VID shares.
+    /// VID shares older than this are deleted even if the rest of the block is retained, but only
+    /// once the block can be reconstructed without them.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRUNER_SHARE_RETENTION",
+        value_parser = parse_duration,
+    )]
+    share_retention: Option<Duration>,
+
+    /// Query service URLs of the peers asked for their VID shares before VID data is pruned.
+    /// Unless the full payload of a block is archived locally, its VID data is only deleted once
+    /// the shares these peers serve verify against the VID commitment of the block and carry
+    /// enough stake to recover it, so that pruning cannot make old blocks unreconstructable
+    /// network-wide. If empty, the check is disabled.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRUNER_SHARE_ATTESTATION_PEERS",
+        value_delimiter = ','
+    )]
+    share_attestation_peers: Vec<Url>,
+
     /// Batch size for pruning.
     /// This is the number of blocks data to delete in a single transaction.
     #[clap(long, env = "ESPRESSO_SEQUENCER_PRUNER_BATCH_SIZE")]
@@ -480,6 +643,22 @@ impl From<PruningOptions> for PrunerCfg {
         if let Some(target) = opt.target_retention {
             cfg = cfg.with_target_retention(target);
         }
+        if let Some(blocks) = opt.retention_blocks {
+            cfg = cfg.with_retention_blocks(blocks);
+        }
+        cfg = cfg.with_preserve_headers(opt.preserve_headers);
+        if let Some(retention) = opt.share_retention {
+            cfg = cfg.with_share_retention(retention);
+        }
+        if !opt.share_attestation_peers.is_empty() {
+            cfg = cfg.with_share_attestor(Arc::new(PeerShareAttestor {
+                peers: opt
+                    .share_attestation_peers
+                    .into_iter()
+                    .map(surf_disco::Client::new)
+                    .collect(),
+            }));
+        }
         if let Some(batch) = opt.batch_size {
             cfg = cfg.with_batch_size(batch);
         }
@@ -499,6 +678,44 @@ impl From<PruningOptions> for PrunerCfg {
     }
 }
 
+/// Collects the VID shares peers serve from their query services.
+///
+/// A peer can only serve a share it still holds, so serving it is taken as the peer's attestation.
+#[derive(Clone, Debug)]
+struct PeerShareAttestor {
+    peers: Vec<surf_disco::Client<ServerError, SequencerApiVersion>>,
+}
+
+/// How long to wait for each peer to serve its share
+const SHARE_ATTESTATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[async_trait]
+impl ShareAttestor for PeerShareAttestor {
+    async fn shares(&self, height: u64) -> Vec<VidShare> {
+        let requests = self.peers.iter().map(|client| async move {
+            let res = timeout(
+                SHARE_ATTESTATION_TIMEOUT,
+                client
+                    .get::<VidShare>(&format!("node/vid/share/{height}"))
+                    .send(),
+            )
+            .await;
+            match res {
+                Ok(Ok(share)) => Some(share),
+                Ok(Err(err)) => {
+                    tracing::debug!(height, "peer did not serve VID share: {err:#}");
+                    None
+                },
+                Err(_) => {
+                    tracing::debug!(height, "timed out fetching VID share from peer");
+                    None
+                },
+            }
+        });
+        join_all(requests).await.into_iter().flatten().collect()
+    }
+}
+
 /// Pruning parameters for ephemeral consensus storage.
 #[derive(Parser, Clone, Copy, Debug)]
 pub struct ConsensusPruningOptions {
@@ -571,7 +788,6 @@ impl PersistenceOptions for Options {
             db: SqlStorage::connect(config).await?,
             gc_opt: self.consensus_pruning,
         };
-        persistence.migrate_quorum_proposal_leaf_hashes().await?;
         self.pool = Some(persistence.db.pool());
         Ok(persistence)
     }
@@ -590,41 +806,6 @@ pub struct Persistence {
 }
 
 impl Persistence {
-    /// Ensure the `leaf_hash` column is populated for all existing quorum proposals.
-    ///
-    /// This column was added in a migration, but because it requires computing a commitment of the
-    /// existing data, it is not easy to populate in the SQL migration itself. Thus, on startup, we
-    /// check if there are any just-migrated quorum proposals with a `NULL` value for this column,
-    /// and if so we populate the column manually.
-    async fn migrate_quorum_proposal_leaf_hashes(&self) -> anyhow::Result<()> {
-        let mut tx = self.db.write().await?;
-
-        let mut proposals = tx.fetch("SELECT * FROM quorum_proposals");
-
-        let mut updates = vec![];
-        while let Some(row) = proposals.next().await {
-            let row = row?;
-
-            let hash: Option<String> = row.try_get("leaf_hash")?;
-            if hash.is_none() {
-                let view: i64 = row.try_get("view")?;
-                let data: Vec<u8> = row.try_get("data")?;
-                let proposal: Proposal<SeqTypes, QuorumProposal<SeqTypes>> =
-                    bincode::deserialize(&data)?;
-                let leaf = Leaf::from_quorum_proposal(&proposal.data);
-                let leaf_hash = Committable::commit(&leaf);
-                tracing::info!(view, %leaf_hash, "populating quorum proposal leaf hash");
-                updates.push((view, leaf_hash.to_string()));
-            }
-        }
-        drop(proposals);
-
-        tx.upsert("quorum_proposals", ["view", "leaf_hash"], ["view"], updates)
-            .await?;
-
-        tx.commit().await
-    }
-
     async fn generate_decide_events(&self, consumer: &impl EventConsumer) -> anyhow::Result<()> {
         let mut last_processed_view: Option<i64> = self
             .db
@@ -1911,6 +2092,47 @@ impl SequencerPersistence for Persistence {
         tx.commit().await
     }
 
+    async fn store_drb_input(&self, drb_input: DrbInput) -> anyhow::Result<()> {
+        let mut tx = self.db.write().await?;
+        tx.upsert(
+            "drb_input",
+            ["epoch", "iteration", "value"],
+            ["epoch"],
+            [(
+                drb_input.epoch as i64,
+                drb_input.iteration as i64,
+                drb_input.value.to_vec(),
+            )],
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn load_drb_input(&self, epoch: EpochNumber) -> anyhow::Result<Option<DrbInput>> {
+        let row = self
+            .db
+            .read()
+            .await?
+            .fetch_optional(
+                query("SELECT iteration, value FROM drb_input WHERE epoch = $1")
+                    .bind(epoch.u64() as i64),
+            )
+            .await?;
+
+        row.map(|row| {
+            let iteration: i64 = row.get("iteration");
+            let value: Vec<u8> = row.get("value");
+            Ok(DrbInput {
+                epoch: epoch.u64(),
+                iteration: iteration as u64,
+                value: value
+                    .try_into()
+                    .map_err(|_| anyhow::anyhow!("malformed drb input for epoch {epoch}"))?,
+            })
+        })
+        .transpose()
+    }
+
     async fn add_epoch_root(
         &self,
         epoch: EpochNumber,
@@ -2009,6 +2231,189 @@ impl SequencerPersistence for Persistence {
             })
             .collect()
     }
+
+    async fn add_pending_transaction(
+        &self,
+        tx: &SeqTransaction,
+        submitted: u64,
+    ) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(tx).context("serializing pending transaction")?;
+
+        let mut db_tx = self.db.write().await?;
+        db_tx
+            .upsert(
+                "pending_transaction",
+                ["hash", "data", "submitted"],
+                ["hash"],
+                [(tx.commit().to_string(), bytes, submitted as i64)],
+            )
+            .await?;
+        db_tx.commit().await
+    }
+
+    async fn remove_pending_transactions(
+        &self,
+        hashes: &[Commitment<SeqTransaction>],
+    ) -> anyhow::Result<()> {
+        if hashes.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.db.write().await?;
+        for hash in hashes {
+            tx.execute(
+                query("DELETE FROM pending_transaction WHERE hash = $1").bind(hash.to_string()),
+            )
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    async fn prune_pending_transactions(&self, submitted_before: u64) -> anyhow::Result<()> {
+        let mut tx = self.db.write().await?;
+        tx.execute(
+            query("DELETE FROM pending_transaction WHERE submitted < $1")
+                .bind(submitted_before as i64),
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn load_pending_transactions(&self) -> anyhow::Result<Vec<(SeqTransaction, u64)>> {
+        let rows = self
+            .db
+            .read()
+            .await?
+            .fetch_all("SELECT data, submitted FROM pending_transaction ORDER BY submitted ASC")
+            .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let data: Vec<u8> = row.get("data");
+                let submitted: i64 = row.get("submitted");
+                let tx =
+                    bincode::deserialize(&data).context("deserializing pending transaction")?;
+                Ok((tx, submitted as u64))
+            })
+            .collect()
+    }
+
+    async fn store_notification_cursor(&self, sink: &str, height: u64) -> anyhow::Result<()> {
+        let mut tx = self.db.write().await?;
+        tx.upsert(
+            "notification_cursor",
+            ["sink", "height"],
+            ["sink"],
+            [(sink.to_string(), height as i64)],
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn load_notification_cursor(&self, sink: &str) -> anyhow::Result<Option<u64>> {
+        let row = self
+            .db
+            .read()
+            .await?
+            .fetch_optional(
+                query("SELECT height FROM notification_cursor WHERE sink = $1").bind(sink),
+            )
+            .await?;
+        row.map(|row| {
+            let height: i64 = row.try_get("height")?;
+            Ok(height as u64)
+        })
+        .transpose()
+    }
+
+    async fn append_notifications(&self, notifications: &[(u64, Vec<u8>)]) -> anyhow::Result<()> {
+        if notifications.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.db.write().await?;
+        tx.upsert(
+            "notification_queue",
+            ["height", "data"],
+            ["height"],
+            notifications
+                .iter()
+                .map(|(height, data)| (*height as i64, data.clone())),
+        )
+        .await?;
+        tx.commit().await
+    }
+
+    async fn load_notifications(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<u8>)>> {
+        let rows = self
+            .db
+            .read()
+            .await?
+            .fetch_all(
+                query(
+                    "SELECT height, data FROM notification_queue WHERE height > $1 ORDER BY \
+                     height ASC LIMIT $2",
+                )
+                .bind(after.map(|height| height as i64).unwrap_or(-1))
+                .bind(limit as i64),
+            )
+            .await?;
+        rows.into_iter()
+            .map(|row| {
+                let height: i64 = row.try_get("height")?;
+                let data: Vec<u8> = row.try_get("data")?;
+                Ok((height as u64, data))
+            })
+            .collect()
+    }
+
+    async fn prune_notifications(&self, height: u64) -> anyhow::Result<()> {
+        let mut tx = self.db.write().await?;
+        tx.execute(query("DELETE FROM notification_queue WHERE height <= $1").bind(height as i64))
+            .await?;
+        tx.commit().await
+    }
+}
+
+async fn upsert_events(
+    tx: &mut Transaction<Write>,
+    l1_block: u64,
+    events: &[(EventKey, StakeTableEvent)],
+) -> anyhow::Result<()> {
+    let events_json = serde_json::to_value(events).context("failed to serialize events ")?;
+    tx.upsert(
+        "stake_table_events",
+        ["id", "l1_block", "data"],
+        ["id"],
+        [(0_i64, l1_block as i64, events_json)],
+    )
+    .await
+}
+
+async fn upsert_l1_checkpoint(
+    tx: &mut Transaction<Write>,
+    contract: alloy::primitives::Address,
+    checkpoint: L1EventCheckpoint,
+) -> anyhow::Result<()> {
+    tx.upsert(
+        "l1_event_checkpoint",
+        [
+            "contract",
+            "l1_block",
+            "last_event_block",
+            "last_event_index",
+        ],
+        ["contract"],
+        [(
+            contract.to_string(),
+            checkpoint.l1_block as i64,
+            checkpoint.last_event.map(|(block, _)| block as i64),
+            checkpoint.last_event.map(|(_, index)| index as i64),
+        )],
+    )
+    .await
 }
 
 #[async_trait]
@@ -2084,17 +2489,24 @@ impl MembershipPersistence for Persistence {
         l1_block: u64,
         events: Vec<(EventKey, StakeTableEvent)>,
     ) -> anyhow::Result<()> {
-        let events_json = serde_json::to_value(&events).context("failed to serialize events ")?;
-
         let mut tx = self.db.write().await?;
+        upsert_events(&mut tx, l1_block, &events).await?;
+        tx.commit().await
+    }
 
-        tx.upsert(
-            "stake_table_events",
-            ["id", "l1_block", "data"],
-            ["id"],
-            [(0_i64, l1_block as i64, events_json)],
-        )
-        .await?;
+    async fn store_events_with_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        l1_block: u64,
+        events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        let checkpoint = L1EventCheckpoint {
+            l1_block,
+            last_event: events.last().map(|(key, _)| *key),
+        };
+        let mut tx = self.db.write().await?;
+        upsert_events(&mut tx, l1_block, &events).await?;
+        upsert_l1_checkpoint(&mut tx, contract, checkpoint).await?;
         tx.commit().await
     }
 
@@ -2115,6 +2527,47 @@ impl MembershipPersistence for Persistence {
             },
         }
     }
+
+    async fn store_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()> {
+        let mut tx = self.db.write().await?;
+        upsert_l1_checkpoint(&mut tx, contract, checkpoint).await?;
+        tx.commit().await
+    }
+
+    async fn load_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>> {
+        let row = self
+            .db
+            .read()
+            .await?
+            .fetch_optional(
+                query(
+                    "SELECT l1_block, last_event_block, last_event_index FROM l1_event_checkpoint \
+                     WHERE contract = $1",
+                )
+                .bind(contract.to_string()),
+            )
+            .await?;
+
+        row.map(|row| {
+            let l1_block: i64 = row.try_get("l1_block")?;
+            let last_event_block: Option<i64> = row.try_get("last_event_block")?;
+            let last_event_index: Option<i64> = row.try_get("last_event_index")?;
+            Ok(L1EventCheckpoint {
+                l1_block: l1_block as u64,
+                last_event: last_event_block
+                    .zip(last_event_index)
+                    .map(|(block, index)| (block as u64, index as u64)),
+            })
+        })
+        .transpose()
+    }
 }
 
 #[async_trait]
@@ -2330,6 +2783,7 @@ mod test {
             QuorumProposal2,
         },
         message::convert_proposal,
+        signer::Signer,
         simple_certificate::QuorumCertificate,
         simple_vote::QuorumData,
         traits::{
@@ -2406,6 +2860,13 @@ mod test {
         tx.upsert("quorum_proposals", ["view", "data"], ["view"], params)
             .await
             .unwrap();
+        // Simulate a database created before the data migration was released, which has not run
+        // it yet.
+        tx.execute(query(
+            "DELETE FROM data_migrations WHERE name = 'quorum_proposal_leaf_hashes'",
+        ))
+        .await
+        .unwrap();
         tx.commit().await.unwrap();
 
         // Create a new persistence and ensure the commitments get populated.
@@ -2463,7 +2924,7 @@ mod test {
             target_epoch: None,
             common: avidm_param.clone(),
         }
-        .to_proposal(&privkey)
+        .to_proposal(&Signer::Local(privkey.clone()))
         .unwrap()
         .clone();
 
@@ -2609,7 +3070,7 @@ mod test {
             target_epoch: None,
             common: avidm_param,
         }
-        .to_proposal(&privkey)
+        .to_proposal(&Signer::Local(privkey.clone()))
         .unwrap()
         .clone();
         let quorum_proposal = QuorumProposalWrapper::<SeqTypes> {
@@ -2869,7 +3330,7 @@ mod test {
             };
 
             storage
-                .append_vid(&vid.to_proposal(&privkey).unwrap())
+                .append_vid(&vid.to_proposal(&Signer::Local(privkey.clone())).unwrap())
                 .await
                 .unwrap();
             storage
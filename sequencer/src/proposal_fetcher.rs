@@ -212,8 +212,10 @@ where
                 })
             ) {
                 let state = Arc::new(ValidatedState::from_header(leaf.block_header()));
-                if let Err(err) = consensus.update_leaf(leaf, state, None) {
-                    tracing::warn!("unable to update leaf: {err:#}");
+                match consensus.update_leaf(leaf, state, None) {
+                    Err(err) if err.is_stale() => tracing::debug!("not updating leaf: {err}"),
+                    Err(err) => tracing::warn!("unable to update leaf: {err}"),
+                    Ok(()) => {},
                 }
             }
 
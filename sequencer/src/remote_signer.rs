@@ -0,0 +1,1143 @@
+//! Signing by a remote signer.
+//!
+//! Rather than signing with its private keys itself, a node can delegate signing to a signer running
+//! as a separate service on a different, more tightly controlled host. The signer holds both the
+//! consensus key and the light client state key of the node, and serves a small signing protocol,
+//! either over HTTP (`POST api/sign`) or over a Unix domain socket. Consensus signs through the
+//! signer via [`hotshot_types::signer::Signer::Remote`].
+//!
+//! Every request and response is a bincode-encoded [`SignRequest`] or [`SignResponse`] wrapped in
+//! an [`Authenticated`] envelope, which carries an HMAC-SHA256 of the message under a secret shared
+//! by the node and the signer. The signer only answers requests authenticated under this secret,
+//! and the node only accepts responses authenticated under it.
+//!
+//! Each request is stamped with a random nonce and an expiry a short time ahead. The signer drops
+//! expired requests and requests whose nonce it has already seen, so a recorded request cannot be
+//! replayed, and echoes the stamp in its response, so a recorded response cannot be passed off as
+//! the answer to a later request.
+//!
+//! Over a Unix domain socket, each envelope is sent as a frame: its length as a big-endian `u32`,
+//! followed by the envelope itself.
+//!
+//! The signer keeps a high-water mark of the light client states it has signed, persisted so that
+//! it survives restarts, and refuses to sign a state older than the last one it signed, or a
+//! different state at the same height. It likewise records the votes it has signed in recent views,
+//! and refuses to sign a second, different vote of the same kind in the same view.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    future::Future,
+    io::{self, Write},
+    marker::PhantomData,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use async_lock::{Mutex, RwLock};
+use espresso_types::PubKey;
+use futures::{channel::oneshot, FutureExt};
+use hmac::{Hmac, Mac};
+use hotshot::types::{BLSPrivKey, SchnorrPubKey, SignatureKey};
+use hotshot_types::{
+    light_client::{
+        LightClientState, StakeTableState, StateKeyPair, StateSignKey, StateSignature, StateVerKey,
+    },
+    signer::VoteSlot,
+    traits::signature_key::StateSignatureKey,
+};
+use jf_signature::SignatureError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use surf_disco::Client;
+use tide_disco::{
+    api::ApiError,
+    error::ServerError,
+    method::{ReadState, WriteState},
+    Api, App, Error as _, StatusCode,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{UnixListener, UnixStream},
+    spawn,
+    sync::mpsc,
+    time::timeout,
+};
+use url::Url;
+use vbs::version::StaticVersionType;
+use zeroize::Zeroizing;
+
+use crate::keystore;
+
+/// The largest frame accepted over a Unix domain socket
+const MAX_FRAME_SIZE: u32 = 1 << 20;
+
+/// The shortest secret a node and signer may share
+const MIN_SECRET_LEN: usize = 16;
+
+/// How long after it is sent a request expires, in seconds
+const REQUEST_TTL: u64 = 30;
+
+/// How far the clocks of a node and its signer may drift apart, in seconds
+const MAX_CLOCK_SKEW: u64 = 30;
+
+/// How many of the most recent views of each kind of vote the signer remembers its votes for
+const RECORDED_VIEWS: u64 = 100;
+
+type ConsensusSignature = <PubKey as SignatureKey>::PureAssembledSignatureType;
+
+/// A request to a remote signer
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignRequest {
+    /// Get the public keys of the keys held by the signer.
+    Keys,
+    /// Sign a light client state together with the stake table state for the next block.
+    State {
+        state: LightClientState,
+        next_stake: StakeTableState,
+    },
+    /// Sign a consensus message with the consensus key.
+    ///
+    /// If the message is a vote, `vote` is the kind of the vote and the view it is cast in, and the
+    /// signer refuses to sign it if it already signed a different vote in the same slot.
+    Consensus {
+        data: Vec<u8>,
+        vote: Option<VoteSlot>,
+    },
+}
+
+/// A response from a remote signer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SignResponse {
+    Keys {
+        consensus: PubKey,
+        state: StateVerKey,
+    },
+    State(StateSignature),
+    Consensus(ConsensusSignature),
+    /// The signer refused or failed to answer the request.
+    Error(String),
+}
+
+/// A message together with its authentication code
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Authenticated {
+    pub message: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+/// The stamp identifying a request, echoed in its response
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    /// A random nonce, never used for another request
+    nonce: u128,
+    /// When the request expires, in seconds since the Unix epoch
+    expires: u64,
+}
+
+impl Stamp {
+    /// A stamp for a request sent now.
+    fn new() -> Self {
+        Self {
+            nonce: rand::random(),
+            expires: unix_now() + REQUEST_TTL,
+        }
+    }
+}
+
+/// A message together with the stamp of the request it is or answers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Stamped<T> {
+    stamp: Stamp,
+    message: T,
+}
+
+/// The current time, in seconds since the Unix epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// The nonces of the unexpired requests a signer has answered
+#[derive(Debug, Default)]
+struct ReplayGuard {
+    seen: HashMap<u128, u64>,
+}
+
+impl ReplayGuard {
+    /// Record that the request stamped with `stamp` is being answered, failing if it has expired,
+    /// expires implausibly far in the future, or has already been answered.
+    fn check(&mut self, stamp: Stamp) -> anyhow::Result<()> {
+        let now = unix_now();
+        // Expired nonces no longer need to be remembered, since their requests are refused anyway.
+        self.seen.retain(|_, expires| *expires >= now);
+        ensure!(stamp.expires >= now, "request has expired");
+        ensure!(
+            stamp.expires <= now + REQUEST_TTL + MAX_CLOCK_SKEW,
+            "request expires too far in the future"
+        );
+        ensure!(
+            self.seen.insert(stamp.nonce, stamp.expires).is_none(),
+            "request has already been answered"
+        );
+        Ok(())
+    }
+}
+
+/// Whether a message is sent by the node or by the signer
+///
+/// This is included in the authentication code, so a request cannot be passed off as a response or
+/// vice versa.
+#[derive(Clone, Copy, Debug)]
+enum Direction {
+    Request,
+    Response,
+}
+
+/// The secret shared by a node and its signer
+#[derive(Clone)]
+pub struct SignerSecret(Zeroizing<Vec<u8>>);
+
+impl SignerSecret {
+    /// Read the secret from the file at `path`, ignoring a trailing newline.
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let secret = keystore::read_passphrase(path)?;
+        ensure!(
+            secret.len() >= MIN_SECRET_LEN,
+            "remote signer secret must be at least {MIN_SECRET_LEN} bytes"
+        );
+        Ok(Self(Zeroizing::new(secret.as_bytes().to_vec())))
+    }
+
+    fn mac(&self, direction: Direction, message: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any size");
+        mac.update(match direction {
+            Direction::Request => b"request:",
+            Direction::Response => b"response:",
+        });
+        mac.update(message);
+        mac
+    }
+
+    fn seal(&self, direction: Direction, message: &impl Serialize) -> anyhow::Result<Vec<u8>> {
+        let message = bincode::serialize(message)?;
+        let mac = self
+            .mac(direction, &message)
+            .finalize()
+            .into_bytes()
+            .to_vec();
+        Ok(bincode::serialize(&Authenticated { message, mac })?)
+    }
+
+    fn open<T: DeserializeOwned>(
+        &self,
+        direction: Direction,
+        envelope: &[u8],
+    ) -> anyhow::Result<T> {
+        let Authenticated { message, mac } =
+            bincode::deserialize(envelope).context("malformed envelope")?;
+        self.mac(direction, &message)
+            .verify_slice(&mac)
+            .ok()
+            .context("message is not authenticated")?;
+        bincode::deserialize(&message).context("malformed message")
+    }
+}
+
+impl std::fmt::Debug for SignerSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SignerSecret(..)")
+    }
+}
+
+/// The last light client state a signer signed, persisted so that it never signs an older one
+///
+/// Alongside the state, this keeps a digest of each vote signed in the most recent views, so that
+/// the signer never signs two different votes in the same slot.
+#[derive(Debug)]
+struct HighWaterMark {
+    path: PathBuf,
+    last: Option<(LightClientState, StakeTableState)>,
+    votes: BTreeMap<VoteSlot, [u8; 32]>,
+}
+
+impl HighWaterMark {
+    fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let (last, votes) = match fs::read(&path) {
+            Ok(bytes) => {
+                Some(bincode::deserialize(&bytes).with_context(|| {
+                    format!("parsing signer high-water mark {}", path.display())
+                })?)
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("reading signer high-water mark {}", path.display()))
+            },
+        }
+        .unwrap_or_default();
+        Ok(Self { path, last, votes })
+    }
+
+    /// Record that `state` is about to be signed, failing if it is older than or conflicts with
+    /// the last state signed.
+    fn advance(
+        &mut self,
+        state: &LightClientState,
+        next_stake: &StakeTableState,
+    ) -> anyhow::Result<()> {
+        if let Some((last, last_next_stake)) = &self.last {
+            if state.block_height == last.block_height {
+                ensure!(
+                    state == last && next_stake == last_next_stake,
+                    "refusing to sign a second state at height {}",
+                    state.block_height
+                );
+                return Ok(());
+            }
+            ensure!(
+                state.block_height > last.block_height,
+                "refusing to sign state at height {}, already signed height {}",
+                state.block_height,
+                last.block_height
+            );
+        }
+
+        let last = Some((*state, *next_stake));
+        self.persist(&last, &self.votes)?;
+        self.last = last;
+        Ok(())
+    }
+
+    /// Record that the vote `data` is about to be signed in `slot`, failing if a different vote
+    /// was signed in the same slot.
+    fn record_vote(&mut self, slot: VoteSlot, data: &[u8]) -> anyhow::Result<()> {
+        let digest: [u8; 32] = Sha256::digest(data).into();
+        if let Some(signed) = self.votes.get(&slot) {
+            ensure!(
+                *signed == digest,
+                "refusing to sign a second {:?} vote in view {}",
+                slot.kind,
+                slot.view
+            );
+            return Ok(());
+        }
+
+        // Votes in views too old to be remembered may conflict with votes since forgotten.
+        let newest = self
+            .votes
+            .keys()
+            .filter(|signed| signed.kind == slot.kind)
+            .map(|signed| signed.view)
+            .max();
+        if let Some(newest) = newest {
+            ensure!(
+                slot.view.saturating_add(RECORDED_VIEWS) > newest,
+                "refusing to sign a {:?} vote in view {}, already voted in view {newest}",
+                slot.kind,
+                slot.view
+            );
+        }
+        let newest = newest.unwrap_or(slot.view).max(slot.view);
+
+        let mut votes = self.votes.clone();
+        votes.insert(slot, digest);
+        votes.retain(|signed, _| {
+            signed.kind != slot.kind || signed.view.saturating_add(RECORDED_VIEWS) > newest
+        });
+        self.persist(&self.last, &votes)?;
+        self.votes = votes;
+        Ok(())
+    }
+
+    /// Persist a new mark before signing, so that a crash cannot cause the signer to forget a
+    /// state or vote it signed.
+    fn persist(
+        &self,
+        last: &Option<(LightClientState, StakeTableState)>,
+        votes: &BTreeMap<VoteSlot, [u8; 32]>,
+    ) -> anyhow::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&bincode::serialize(&(last, votes))?)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// The server side of a remote signer, shared by all the transports it serves
+#[derive(Clone, Debug)]
+pub struct Signer {
+    consensus_key: BLSPrivKey,
+    state_key: StateSignKey,
+    secret: SignerSecret,
+    high_water_mark: Arc<Mutex<HighWaterMark>>,
+    replay_guard: Arc<Mutex<ReplayGuard>>,
+}
+
+impl Signer {
+    /// A signer with the given keys, which keeps its high-water mark in `high_water_mark`.
+    pub fn new(
+        consensus_key: BLSPrivKey,
+        state_key: StateSignKey,
+        secret: SignerSecret,
+        high_water_mark: PathBuf,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            consensus_key,
+            state_key,
+            secret,
+            high_water_mark: Arc::new(Mutex::new(HighWaterMark::load(high_water_mark)?)),
+            replay_guard: Default::default(),
+        })
+    }
+
+    /// Answer the authenticated request `envelope`.
+    ///
+    /// Fails without a response if the request is not authenticated, has expired or has already
+    /// been answered.
+    async fn handle(&self, envelope: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let Stamped {
+            stamp,
+            message: request,
+        } = self.secret.open(Direction::Request, envelope)?;
+        self.replay_guard.lock().await.check(stamp)?;
+        let response = match self.respond(request).await {
+            Ok(response) => response,
+            Err(err) => {
+                tracing::warn!("refusing signing request: {err:#}");
+                SignResponse::Error(format!("{err:#}"))
+            },
+        };
+        self.secret.seal(
+            Direction::Response,
+            &Stamped {
+                stamp,
+                message: response,
+            },
+        )
+    }
+
+    async fn respond(&self, request: SignRequest) -> anyhow::Result<SignResponse> {
+        match request {
+            SignRequest::Keys => Ok(SignResponse::Keys {
+                consensus: PubKey::from_private(&self.consensus_key),
+                state: StateKeyPair::from_sign_key(self.state_key.clone()).ver_key(),
+            }),
+            SignRequest::State { state, next_stake } => {
+                let mut high_water_mark = self.high_water_mark.lock().await;
+                high_water_mark.advance(&state, &next_stake)?;
+                Ok(SignResponse::State(
+                    <SchnorrPubKey as StateSignatureKey>::sign_state(
+                        &self.state_key,
+                        &state,
+                        &next_stake,
+                    )?,
+                ))
+            },
+            SignRequest::Consensus { data, vote } => {
+                if let Some(slot) = vote {
+                    self.high_water_mark.lock().await.record_vote(slot, &data)?;
+                }
+                Ok(SignResponse::Consensus(PubKey::sign(
+                    &self.consensus_key,
+                    &data,
+                )?))
+            },
+        }
+    }
+}
+
+/// Where a client reaches its signer
+#[derive(Debug)]
+enum Transport<ApiVer: StaticVersionType> {
+    Http(Client<ServerError, ApiVer>),
+    Unix {
+        path: PathBuf,
+        /// The connection to the signer, kept open between requests
+        stream: Option<UnixStream>,
+    },
+}
+
+impl<ApiVer: StaticVersionType> Transport<ApiVer> {
+    fn new(url: &Url) -> anyhow::Result<Self> {
+        match url.scheme() {
+            "http" | "https" => Ok(Self::Http(Client::new(url.clone()))),
+            "unix" => Ok(Self::Unix {
+                path: url.path().into(),
+                stream: None,
+            }),
+            scheme => bail!("unsupported remote signer scheme {scheme}"),
+        }
+    }
+
+    /// Send `envelope` to the signer and wait for its response.
+    async fn send(&mut self, envelope: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::Http(client) => Ok(client
+                .post::<Vec<u8>>("api/sign")
+                .body_binary(&envelope)?
+                .send()
+                .await?),
+            Self::Unix { path, stream } => {
+                if stream.is_none() {
+                    *stream = Some(
+                        UnixStream::connect(&*path)
+                            .await
+                            .with_context(|| format!("connecting to {}", path.display()))?,
+                    );
+                }
+                let stream = stream.as_mut().expect("connected above");
+                write_frame(stream, envelope).await?;
+                read_frame(stream)
+                    .await?
+                    .context("remote signer closed the connection")
+            },
+        }
+    }
+
+    /// Close the connection to the signer, if any, so that the next request opens a new one.
+    fn reset(&mut self) {
+        if let Self::Unix { stream, .. } = self {
+            *stream = None;
+        }
+    }
+}
+
+/// A request waiting to be sent to the signer, with where to send its response
+type PendingRequest = (SignRequest, oneshot::Sender<anyhow::Result<SignResponse>>);
+
+/// A client for a remote signer
+///
+/// Requests are sent to the signer one at a time by a thread of the client's own, which keeps its
+/// connection to the signer open between requests. This lets consensus, which signs synchronously
+/// from within its async tasks, wait for a signature without needing the runtime it blocks.
+#[derive(Debug)]
+pub struct RemoteSigner<ApiVer: StaticVersionType> {
+    requests: mpsc::UnboundedSender<PendingRequest>,
+    consensus_key: PubKey,
+    state_key: StateVerKey,
+    _version: PhantomData<ApiVer>,
+}
+
+impl<ApiVer: StaticVersionType + 'static> RemoteSigner<ApiVer> {
+    /// Connect to the remote signer at `url` and fetch its public keys.
+    ///
+    /// `url` is either an HTTP URL or `unix://` followed by the path of a Unix domain socket. Each
+    /// request to the signer, including this first one, fails if the signer has not responded
+    /// within `request_timeout`.
+    pub async fn connect(
+        url: Url,
+        secret: SignerSecret,
+        request_timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let transport = Transport::<ApiVer>::new(&url)?;
+        let (requests, pending) = mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("remote-signer-client".into())
+            .spawn(move || {
+                tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to start remote signer client runtime")
+                    .block_on(send_requests(transport, secret, request_timeout, pending))
+            })
+            .context("starting remote signer client thread")?;
+
+        let SignResponse::Keys { consensus, state } =
+            queue_request(&requests, SignRequest::Keys).await?
+        else {
+            bail!("remote signer did not return its keys");
+        };
+        tracing::info!(%url, %consensus, %state, "connected to remote signer");
+        Ok(Self {
+            requests,
+            consensus_key: consensus,
+            state_key: state,
+            _version: PhantomData,
+        })
+    }
+}
+
+impl<ApiVer: StaticVersionType> RemoteSigner<ApiVer> {
+    /// The public consensus key of the signer.
+    pub fn consensus_key(&self) -> PubKey {
+        self.consensus_key
+    }
+
+    /// The public light client state key of the signer.
+    pub fn state_key(&self) -> StateVerKey {
+        self.state_key.clone()
+    }
+
+    async fn request(&self, request: SignRequest) -> anyhow::Result<SignResponse> {
+        queue_request(&self.requests, request).await
+    }
+
+    /// Have the remote signer sign `state`.
+    ///
+    /// The signature is checked before it is returned, so a misconfigured or faulty signer cannot
+    /// cause the node to publish invalid signatures.
+    pub async fn sign_state(
+        &self,
+        state: &LightClientState,
+        next_stake: &StakeTableState,
+    ) -> anyhow::Result<StateSignature> {
+        let SignResponse::State(signature) = self
+            .request(SignRequest::State {
+                state: *state,
+                next_stake: *next_stake,
+            })
+            .await?
+        else {
+            bail!("remote signer did not return a state signature");
+        };
+        ensure!(
+            self.state_key
+                .verify_state_sig(&signature, state, next_stake),
+            "remote signer returned an invalid signature"
+        );
+        Ok(signature)
+    }
+
+    /// Have the remote signer sign `data` with the consensus key.
+    ///
+    /// As with [`sign_state`](Self::sign_state), the signature is checked before it is returned.
+    pub async fn sign_consensus(&self, data: &[u8]) -> anyhow::Result<ConsensusSignature> {
+        self.request_consensus_signature(data, None).await
+    }
+
+    /// Have the remote signer sign the vote `data` cast in `slot` with the consensus key.
+    ///
+    /// The signer refuses if it has already signed a different vote in `slot`.
+    pub async fn sign_vote(
+        &self,
+        slot: VoteSlot,
+        data: &[u8],
+    ) -> anyhow::Result<ConsensusSignature> {
+        self.request_consensus_signature(data, Some(slot)).await
+    }
+
+    async fn request_consensus_signature(
+        &self,
+        data: &[u8],
+        vote: Option<VoteSlot>,
+    ) -> anyhow::Result<ConsensusSignature> {
+        let SignResponse::Consensus(signature) = self
+            .request(SignRequest::Consensus {
+                data: data.to_vec(),
+                vote,
+            })
+            .await?
+        else {
+            bail!("remote signer did not return a consensus signature");
+        };
+        ensure!(
+            self.consensus_key.validate(&signature, data),
+            "remote signer returned an invalid signature"
+        );
+        Ok(signature)
+    }
+}
+
+/// Queue `request` for the client thread and wait for its response.
+async fn queue_request(
+    requests: &mpsc::UnboundedSender<PendingRequest>,
+    request: SignRequest,
+) -> anyhow::Result<SignResponse> {
+    let (respond, response) = oneshot::channel();
+    requests
+        .send((request, respond))
+        .ok()
+        .context("remote signer client has stopped")?;
+    response
+        .await
+        .context("remote signer client dropped the request")?
+}
+
+/// Send the requests from `pending` to the signer over `transport`, until the client is dropped.
+async fn send_requests<ApiVer: StaticVersionType>(
+    mut transport: Transport<ApiVer>,
+    secret: SignerSecret,
+    request_timeout: Duration,
+    mut pending: mpsc::UnboundedReceiver<PendingRequest>,
+) {
+    while let Some((request, respond)) = pending.recv().await {
+        let response = timeout(request_timeout, exchange(&mut transport, &secret, &request))
+            .await
+            .unwrap_or_else(|_| {
+                Err(anyhow!(
+                    "remote signer did not respond within {request_timeout:?}"
+                ))
+            });
+        if response.is_err() {
+            // A failed exchange may leave a partial frame on the connection.
+            transport.reset();
+        }
+        // The requester may have stopped waiting for the response.
+        respond.send(response).ok();
+    }
+}
+
+/// Send `request` to a signer over `transport` and wait for its response.
+async fn exchange<ApiVer: StaticVersionType>(
+    transport: &mut Transport<ApiVer>,
+    secret: &SignerSecret,
+    request: &SignRequest,
+) -> anyhow::Result<SignResponse> {
+    let stamp = Stamp::new();
+    let envelope = secret.seal(
+        Direction::Request,
+        &Stamped {
+            stamp,
+            message: request,
+        },
+    )?;
+    let response = transport.send(&envelope).await?;
+    let Stamped {
+        stamp: answered,
+        message: response,
+    } = secret.open(Direction::Response, &response)?;
+    ensure!(
+        answered == stamp,
+        "remote signer response does not answer the request"
+    );
+    match response {
+        SignResponse::Error(err) => bail!("remote signer refused: {err}"),
+        response => Ok(response),
+    }
+}
+
+/// Wait for `future` from synchronous code.
+///
+/// The future only waits for the client thread, so it completes without the help of the calling
+/// runtime, but a thread of a multi-threaded runtime must tell the runtime it is blocking.
+fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let blocking = tokio::runtime::Handle::try_current()
+        .is_ok_and(|handle| handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread);
+    if blocking {
+        tokio::task::block_in_place(|| futures::executor::block_on(future))
+    } else {
+        futures::executor::block_on(future)
+    }
+}
+
+fn signature_error(err: anyhow::Error) -> SignatureError {
+    SignatureError::ParameterError(format!("remote signer: {err:#}"))
+}
+
+impl<ApiVer: StaticVersionType + 'static> hotshot_types::signer::RemoteSigner<PubKey>
+    for RemoteSigner<ApiVer>
+{
+    fn public_key(&self) -> PubKey {
+        self.consensus_key
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<ConsensusSignature, SignatureError> {
+        block_on(self.sign_consensus(data)).map_err(signature_error)
+    }
+
+    fn sign_vote(&self, slot: VoteSlot, data: &[u8]) -> Result<ConsensusSignature, SignatureError> {
+        block_on(RemoteSigner::sign_vote(self, slot, data)).map_err(signature_error)
+    }
+}
+
+async fn read_frame(stream: &mut UnixStream) -> anyhow::Result<Option<Vec<u8>>> {
+    let len = match stream.read_u32().await {
+        Ok(len) => len,
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    ensure!(len <= MAX_FRAME_SIZE, "frame of {len} bytes is too large");
+    let mut frame = vec![0; len as usize];
+    stream.read_exact(&mut frame).await?;
+    Ok(Some(frame))
+}
+
+async fn write_frame(stream: &mut UnixStream, frame: &[u8]) -> anyhow::Result<()> {
+    stream.write_u32(frame.len().try_into()?).await?;
+    stream.write_all(frame).await?;
+    Ok(())
+}
+
+fn define_api<State, ApiVer: StaticVersionType + 'static>(
+    _: ApiVer,
+) -> Result<Api<State, ServerError, ApiVer>, ApiError>
+where
+    State: 'static + Send + Sync + ReadState<State = Signer> + WriteState,
+{
+    let toml: toml::Value =
+        toml::from_str(include_str!("../api/remote_signer.toml")).map_err(|err| {
+            ApiError::CannotReadToml {
+                reason: err.to_string(),
+            }
+        })?;
+    let mut api = Api::<State, ServerError, ApiVer>::new(toml)?;
+
+    api.post("sign", |req, signer| {
+        async move {
+            let envelope = req
+                .body_auto::<Vec<u8>, ApiVer>(ApiVer::instance())
+                .map_err(ServerError::from_request_error)?;
+            signer
+                .handle(&envelope)
+                .await
+                .map_err(|err| ServerError::catch_all(StatusCode::UNAUTHORIZED, format!("{err:#}")))
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+/// Serve `signer` over HTTP at `url`.
+pub async fn serve_http<ApiVer: StaticVersionType + 'static>(
+    url: Url,
+    signer: Signer,
+    bind_version: ApiVer,
+) -> anyhow::Result<()> {
+    let api = define_api(bind_version)?;
+    let mut app = App::<RwLock<Signer>, ServerError>::with_state(RwLock::new(signer));
+    app.register_module("api", api)?;
+
+    tracing::info!(%url, "remote signer serving over HTTP");
+    app.serve(url, bind_version).await?;
+    Ok(())
+}
+
+/// Serve `signer` on a Unix domain socket at `path`.
+///
+/// The socket is only accessible to the user running the signer.
+pub async fn serve_unix(path: PathBuf, signer: Signer) -> anyhow::Result<()> {
+    // Remove the socket left behind by a previous run, but nothing else.
+    match fs::symlink_metadata(&path) {
+        Ok(meta) if meta.file_type().is_socket() => fs::remove_file(&path)?,
+        Ok(_) => bail!("{} exists and is not a socket", path.display()),
+        Err(_) => {},
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("binding to socket {}", path.display()))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+    tracing::info!(path = %path.display(), "remote signer serving over Unix domain socket");
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let signer = signer.clone();
+        spawn(async move {
+            loop {
+                let frame = match read_frame(&mut stream).await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => return,
+                    Err(err) => {
+                        tracing::warn!("reading signing request: {err:#}");
+                        return;
+                    },
+                };
+                // Unauthenticated requests get no response at all.
+                let response = match signer.handle(&frame).await {
+                    Ok(response) => response,
+                    Err(err) => {
+                        tracing::warn!("dropping signing request: {err:#}");
+                        return;
+                    },
+                };
+                if let Err(err) = write_frame(&mut stream, &response).await {
+                    tracing::warn!("sending signing response: {err:#}");
+                    return;
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::signer::VoteKind;
+    use portpicker::pick_unused_port;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::SequencerApiVersion;
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn secret(value: &str) -> SignerSecret {
+        SignerSecret(Zeroizing::new(value.as_bytes().to_vec()))
+    }
+
+    fn test_signer(dir: &TempDir) -> (Signer, PubKey, StateKeyPair) {
+        let (consensus, consensus_key) = PubKey::generated_from_seed_indexed([1; 32], 0);
+        let state_key = StateKeyPair::generate();
+        let signer = Signer::new(
+            consensus_key,
+            state_key.sign_key(),
+            secret("correct horse battery staple"),
+            dir.path().join("high-water-mark"),
+        )
+        .unwrap();
+        (signer, consensus, state_key)
+    }
+
+    fn state(block_height: u64) -> LightClientState {
+        LightClientState {
+            block_height,
+            ..Default::default()
+        }
+    }
+
+    async fn check_signer(url: Url, consensus: PubKey, state_key: &StateKeyPair) {
+        let client = RemoteSigner::<SequencerApiVersion>::connect(
+            url.clone(),
+            secret("correct horse battery staple"),
+            REQUEST_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        assert_eq!(client.consensus_key(), consensus);
+        assert_eq!(client.state_key(), state_key.ver_key());
+
+        let next_stake = StakeTableState::default();
+        let signature = client.sign_state(&state(10), &next_stake).await.unwrap();
+        assert!(state_key
+            .ver_key()
+            .verify_state_sig(&signature, &state(10), &next_stake));
+        let signature = client.sign_consensus(b"vote").await.unwrap();
+        assert!(consensus.validate(&signature, b"vote"));
+
+        // A client with the wrong secret is refused.
+        RemoteSigner::<SequencerApiVersion>::connect(
+            url,
+            secret("incorrect horse battery"),
+            REQUEST_TIMEOUT,
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, consensus, state_key) = test_signer(&dir);
+        let port = pick_unused_port().unwrap();
+        let url: Url = format!("http://localhost:{port}").parse().unwrap();
+        spawn(serve_http(
+            url.clone(),
+            signer,
+            SequencerApiVersion::instance(),
+        ));
+        Client::<ServerError, SequencerApiVersion>::new(url.clone())
+            .connect(None)
+            .await;
+        check_signer(url, consensus, &state_key).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_unix() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, consensus, state_key) = test_signer(&dir);
+        let path = dir.path().join("signer.sock");
+        spawn(serve_unix(path.clone(), signer));
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        let url = Url::parse(&format!("unix://{}", path.display())).unwrap();
+        check_signer(url, consensus, &state_key).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_high_water_mark() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, ..) = test_signer(&dir);
+        let next_stake = StakeTableState::default();
+        let sign = |signer: Signer, state| async move {
+            signer
+                .respond(SignRequest::State { state, next_stake })
+                .await
+        };
+
+        sign(signer.clone(), state(10)).await.unwrap();
+        // The same state may be signed again...
+        sign(signer.clone(), state(10)).await.unwrap();
+        // ...but not a different state at the same height, or an older state.
+        let mut conflicting = state(10);
+        conflicting.view_number += 1;
+        sign(signer.clone(), conflicting).await.unwrap_err();
+        sign(signer.clone(), state(9)).await.unwrap_err();
+        sign(signer.clone(), state(11)).await.unwrap();
+
+        // The mark survives a restart of the signer.
+        let (restarted, ..) = test_signer(&dir);
+        sign(restarted.clone(), state(10)).await.unwrap_err();
+        sign(restarted, state(12)).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_consensus() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, consensus, _) = test_signer(&dir);
+        let path = dir.path().join("signer.sock");
+        spawn(serve_unix(path.clone(), signer));
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let url = Url::parse(&format!("unix://{}", path.display())).unwrap();
+        let client = RemoteSigner::<SequencerApiVersion>::connect(
+            url,
+            secret("correct horse battery staple"),
+            REQUEST_TIMEOUT,
+        )
+        .await
+        .unwrap();
+
+        // Consensus signs through the remote signer as it would with a local key.
+        let signer = hotshot_types::signer::Signer::Remote(Arc::new(client));
+        assert_eq!(signer.public_key(), consensus);
+        let signature = signer.sign(b"proposal").unwrap();
+        assert!(consensus.validate(&signature, b"proposal"));
+
+        // Votes are signed once per slot.
+        let slot = VoteSlot {
+            kind: VoteKind::Quorum,
+            view: 5,
+        };
+        let signature = signer.sign_vote(slot, b"vote").unwrap();
+        assert!(consensus.validate(&signature, b"vote"));
+        signer.sign_vote(slot, b"other vote").unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_votes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, ..) = test_signer(&dir);
+        let sign = |signer: Signer, kind, view, data: &'static [u8]| async move {
+            signer
+                .respond(SignRequest::Consensus {
+                    data: data.to_vec(),
+                    vote: Some(VoteSlot { kind, view }),
+                })
+                .await
+        };
+
+        sign(signer.clone(), VoteKind::Quorum, 10, b"a")
+            .await
+            .unwrap();
+        // The same vote may be signed again...
+        sign(signer.clone(), VoteKind::Quorum, 10, b"a")
+            .await
+            .unwrap();
+        // ...but not a different vote of the same kind in the same view...
+        sign(signer.clone(), VoteKind::Quorum, 10, b"b")
+            .await
+            .unwrap_err();
+        // ...while votes of other kinds or in other views are independent.
+        sign(signer.clone(), VoteKind::Timeout, 10, b"b")
+            .await
+            .unwrap();
+        sign(signer.clone(), VoteKind::Quorum, 11, b"b")
+            .await
+            .unwrap();
+
+        // A vote too old to be remembered is refused.
+        sign(signer.clone(), VoteKind::Quorum, 10 + RECORDED_VIEWS, b"c")
+            .await
+            .unwrap();
+        sign(signer.clone(), VoteKind::Quorum, 10, b"a")
+            .await
+            .unwrap_err();
+
+        // The votes survive a restart of the signer.
+        let (restarted, ..) = test_signer(&dir);
+        sign(restarted.clone(), VoteKind::Timeout, 10, b"c")
+            .await
+            .unwrap_err();
+        sign(restarted.clone(), VoteKind::Quorum, 11, b"b")
+            .await
+            .unwrap();
+        sign(restarted, VoteKind::Quorum, 11, b"c")
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let (signer, ..) = test_signer(&dir);
+        let secret = secret("correct horse battery staple");
+        let envelope = |stamp| {
+            secret
+                .seal(
+                    Direction::Request,
+                    &Stamped {
+                        stamp,
+                        message: SignRequest::Keys,
+                    },
+                )
+                .unwrap()
+        };
+
+        // A request is answered once, with its stamp...
+        let stamp = Stamp::new();
+        let request = envelope(stamp);
+        let response = signer.handle(&request).await.unwrap();
+        let Stamped {
+            stamp: answered, ..
+        } = secret
+            .open::<Stamped<SignResponse>>(Direction::Response, &response)
+            .unwrap();
+        assert_eq!(answered, stamp);
+        // ...and not when it is replayed.
+        signer.handle(&request).await.unwrap_err();
+
+        // Expired requests, and requests which would outlive the nonces the signer remembers, are
+        // refused.
+        let expired = Stamp {
+            expires: unix_now() - 1,
+            ..Stamp::new()
+        };
+        signer.handle(&envelope(expired)).await.unwrap_err();
+        let distant = Stamp {
+            expires: unix_now() + 10 * (REQUEST_TTL + MAX_CLOCK_SKEW),
+            ..Stamp::new()
+        };
+        signer.handle(&envelope(distant)).await.unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_remote_signer_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signer.sock");
+
+        // A signer which accepts connections but never answers.
+        let listener = UnixListener::bind(&path).unwrap();
+        spawn(async move {
+            let mut streams = vec![];
+            loop {
+                streams.push(listener.accept().await.unwrap().0);
+            }
+        });
+
+        let url = Url::parse(&format!("unix://{}", path.display())).unwrap();
+        let err = RemoteSigner::<SequencerApiVersion>::connect(
+            url,
+            secret("correct horse battery staple"),
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{err:#}").contains("did not respond"), "{err:#}");
+    }
+}
@@ -12,7 +12,7 @@ use cdn_marshal::{Config as MarshalConfig, Marshal};
 use clap::Parser;
 use derivative::Derivative;
 use espresso_types::{
-    eth_signature_key::EthKeyPair, traits::PersistenceOptions, v0_99::ChainConfig, FeeAccount,
+    eth_signature_key::EthKeyPair, traits::PersistenceOptions, v0_4::ChainConfig, FeeAccount,
     MockSequencerVersions, PrivKey, PubKey, SeqTypes, Transaction,
 };
 use futures::{
@@ -266,6 +266,7 @@ impl<S: TestableSequencerDataSource> TestNode<S> {
                     .iter()
                     .map(|port| format!("http://127.0.0.1:{port}").parse().unwrap())
                     .collect(),
+                vid_peers: vec![],
             });
         }
 
@@ -346,6 +347,7 @@ impl<S: TestableSequencerDataSource> TestNode<S> {
                     self.opt.clone(),
                     S::persistence_options(&self.storage),
                     MockSequencerVersions::new(),
+                    false,
                 )
                 .await
                 {
@@ -550,6 +552,8 @@ impl TestNetwork {
             accounts: [(builder_account(), 1000000000.into())]
                 .into_iter()
                 .collect(),
+            encryption: None,
+            namespace_registries: Default::default(),
         };
         genesis.to_file(&genesis_file).unwrap();
 
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use anyhow::Context;
+use async_lock::RwLock;
 use clap::Parser;
 use espresso_types::traits::SequencerPersistence;
 #[allow(unused_imports)]
@@ -9,20 +10,36 @@ use espresso_types::{
     SolverAuctionResultsProvider, V0_0,
 };
 use futures::future::FutureExt;
-use hotshot::MarketplaceConfig;
-use hotshot_types::traits::{metrics::NoMetrics, node_implementation::Versions};
+use hotshot::{types::BLSPubKey, MarketplaceConfig};
+use hotshot_query_service::fetching::provider::DaPayloadFetcher;
+use hotshot_types::traits::{
+    metrics::NoMetrics, node_implementation::Versions, signature_key::SignatureKey,
+};
+use tokio::sync::oneshot;
 use vbs::version::StaticVersionType;
 
 use super::{
     api::{self, data_source::DataSourceOptions},
+    builder_registry::{self, BuilderRegistryReloader},
     context::SequencerContext,
-    init_node, network,
-    options::{Modules, Options},
-    persistence, Genesis, L1Params, NetworkParams,
+    doctor, init_node, key_rotation, network,
+    notification::Notifier,
+    options::{Cli, Command, ConfigCommand, Modules, Options},
+    pending_transactions::PendingTransactions,
+    persistence, Genesis, L1Params, NetworkParams, SequencerApiVersion,
 };
 
 pub async fn main() -> anyhow::Result<()> {
-    let opt = Options::parse();
+    let opt = match Cli::parse() {
+        Cli {
+            command: Some(Command::Config(ConfigCommand::Doctor(opt))),
+            ..
+        } => {
+            opt.logging.init();
+            return doctor::run(opt).await;
+        },
+        Cli { options, .. } => options,
+    };
     opt.logging.init();
 
     let modules = opt.modules();
@@ -47,6 +64,31 @@ pub async fn main() -> anyhow::Result<()> {
             .await
         },
         #[cfg(feature = "pos")]
+        (espresso_types::EpochVersion::VERSION, espresso_types::BlockLimitsVersion::VERSION) => {
+            run(
+                genesis,
+                modules,
+                opt,
+                SequencerVersions::<
+                    espresso_types::EpochVersion,
+                    espresso_types::BlockLimitsVersion,
+                >::new(),
+            )
+            .await
+        },
+        #[cfg(feature = "pos")]
+        (espresso_types::BlockLimitsVersion::VERSION, _) => {
+            run(
+                genesis,
+                modules,
+                opt,
+                // Specifying V0_0 disables upgrades
+                SequencerVersions::<espresso_types::BlockLimitsVersion, espresso_types::V0_0>::new(
+                ),
+            )
+            .await
+        },
+        #[cfg(feature = "pos")]
         (espresso_types::EpochVersion::VERSION, _) => {
             run(
                 genesis,
@@ -107,6 +149,8 @@ where
         run_with_storage(genesis, modules, opt, storage, versions).await
     } else if let Some(storage) = modules.storage_sql.take() {
         run_with_storage(genesis, modules, opt, storage, versions).await
+    } else if let Some(storage) = modules.storage_embedded.take() {
+        run_with_storage(genesis, modules, opt, storage, versions).await
     } else {
         // Persistence is required. If none is provided, just use the local file system.
         run_with_storage(
@@ -131,13 +175,58 @@ where
     S: DataSourceOptions,
     V: Versions,
 {
-    let ctx = init_with_storage(genesis, modules, opt, storage_opt, versions).await?;
+    let next_key = opt
+        .next_private_keys()?
+        .map(|(staking, _)| BLSPubKey::from_private(&staking));
+    let epoch_height = genesis.epoch_height.unwrap_or_default();
 
-    // Start doing consensus.
-    ctx.start_consensus().await;
-    ctx.join().await;
+    // Whether the next keys have taken effect while this process was running.
+    let mut rotated = false;
+    loop {
+        let mut ctx = init_with_storage(
+            genesis.clone(),
+            modules.clone(),
+            opt.clone(),
+            storage_opt.clone(),
+            versions,
+            rotated,
+        )
+        .await?;
+
+        // Start doing consensus.
+        ctx.start_consensus().await;
+
+        // If this node is due to rotate its consensus keys, restart it under the new keys once
+        // they take effect.
+        let public_key = ctx.consensus().read().await.public_key();
+        let Some(next_key) = next_key.filter(|key| *key != public_key) else {
+            ctx.join().await;
+            return Ok(());
+        };
+        let (activated, activation) = oneshot::channel();
+        let events = ctx.event_stream().await;
+        let membership = ctx
+            .consensus()
+            .read()
+            .await
+            .membership_coordinator
+            .membership()
+            .clone();
+        ctx.spawn("key rotation", async move {
+            if key_rotation::wait_for_activation(events, membership, epoch_height, next_key).await {
+                activated.send(()).ok();
+            }
+        });
+        if activation.await.is_err() {
+            // Consensus ended before the new keys took effect.
+            ctx.join().await;
+            return Ok(());
+        }
 
-    Ok(())
+        tracing::warn!("restarting consensus under the next consensus keys");
+        ctx.shut_down().await;
+        rotated = true;
+    }
 }
 
 pub(crate) async fn init_with_storage<S, V>(
@@ -146,30 +235,73 @@ pub(crate) async fn init_with_storage<S, V>(
     opt: Options,
     mut storage_opt: S,
     versions: V,
+    rotated: bool,
 ) -> anyhow::Result<SequencerContext<network::Production, S::Persistence, V>>
 where
     S: DataSourceOptions,
     V: Versions,
 {
-    let (private_staking_key, private_state_key) = opt.private_keys()?;
+    let persistence = storage_opt.create().await?;
+    persistence
+        .migrate_consensus()
+        .await
+        .context("failed to migrate consensus data")?;
+
+    // Use the next keys instead of the current ones if they have already taken effect, either
+    // while this process was running or, after a restart, as of the latest decided leaf.
+    let remote_signer = opt.remote_signer().await?;
+    let (private_staking_key, private_state_key) = match opt.next_private_keys()? {
+        Some((staking, state))
+            if rotated
+                || key_rotation::key_active(
+                    &persistence,
+                    genesis.epoch_height.unwrap_or_default(),
+                    BLSPubKey::from_private(&staking),
+                )
+                .await? =>
+        {
+            tracing::warn!("next consensus keys are active, using them");
+            (staking, state)
+        },
+        _ => opt.private_keys()?,
+    };
+    let encryption_key_share = opt.encryption_key_share()?;
+    let bootstrap_document = opt.bootstrap_document()?;
     let l1_params = L1Params {
         urls: opt.l1_provider_url,
         options: opt.l1_options,
     };
 
+    let notification_sinks = opt.notifications.sinks()?;
+    for sink in &notification_sinks {
+        tracing::info!(sink = sink.name(), "publishing decided blocks");
+    }
+    let notification_storage = persistence.clone();
+    let notification_max_backlog = opt.notifications.notify_max_backlog;
+    let audit_log = opt.audit.open()?;
+
+    let pending_transaction_peers = opt.state_peers.clone();
+    let pending_transaction_retention = opt.pending_transaction_retention;
     let network_params = NetworkParams {
         cdn_endpoint: opt.cdn_endpoint,
+        cdn_metrics_urls: opt.cdn_metrics_urls,
+        cdn_metrics_interval: opt.cdn_metrics_interval,
+        network_reload_config: opt.network_reload_config,
         libp2p_advertise_address: opt.libp2p_advertise_address,
         libp2p_bind_address: opt.libp2p_bind_address,
         libp2p_bootstrap_nodes: opt.libp2p_bootstrap_nodes,
         orchestrator_url: opt.orchestrator_url,
         state_relay_server_url: opt.state_relay_server_url,
+        remote_signer,
         public_api_url: opt.public_api_url,
         private_staking_key,
         private_state_key,
+        encryption_key_share,
         state_peers: opt.state_peers,
         config_peers: opt.config_peers,
+        bootstrap_document,
         catchup_backoff: opt.catchup_backoff,
+        fast_sync: opt.fast_sync,
         libp2p_history_gossip: opt.libp2p_history_gossip,
         libp2p_history_length: opt.libp2p_history_length,
         libp2p_max_ihave_length: opt.libp2p_max_ihave_length,
@@ -191,28 +323,43 @@ where
         libp2p_heartbeat_initial_delay: opt.libp2p_heartbeat_initial_delay,
         libp2p_gossip_factor: opt.libp2p_gossip_factor,
         libp2p_gossip_lazy: opt.libp2p_gossip_lazy,
+        failover: opt.failover,
     };
 
+    let builder_registry = match &opt.builder_registry {
+        Some(path) => builder_registry::load(path)?,
+        None => Default::default(),
+    };
+    let builder_registry = Arc::new(RwLock::new(builder_registry));
+    let builder_registry_reloader = opt
+        .builder_registry
+        .map(|path| BuilderRegistryReloader::new(path, Arc::clone(&builder_registry)));
+
     let marketplace_config = MarketplaceConfig {
         auction_results_provider: Arc::new(SolverAuctionResultsProvider {
             url: opt.auction_results_solver_url,
             marketplace_path: opt.marketplace_solver_path,
             results_path: opt.auction_results_path,
+            builder_registry,
         }),
         fallback_builder_url: opt.fallback_builder_url,
     };
     let proposal_fetcher_config = opt.proposal_fetcher_config;
 
-    let persistence = storage_opt.create().await?;
-    persistence
-        .migrate_consensus()
-        .await
-        .context("failed to migrate consensus data")?;
+    // DA committee members fetch the payloads which leaders announce with payload hints from the
+    // same peers the query service fetches missing data from.
+    let da_payload_provider = modules.query.as_ref().filter(|_| opt.is_da).map(|query| {
+        api::data_source::provider::<V>(
+            query.peers.clone(),
+            query.vid_peers.clone(),
+            SequencerApiVersion::instance(),
+        )
+    });
 
     // Initialize HotShot. If the user requested the HTTP module, we must initialize the handle in
     // a special way, in order to populate the API with consensus metrics. Otherwise, we initialize
     // the handle directly, with no metrics.
-    let ctx = match modules.http {
+    let mut ctx = match modules.http {
         Some(http_opt) => {
             // Add optional API modules as requested.
             let mut http_opt = api::Options::from(http_opt);
@@ -238,46 +385,119 @@ where
             if let Some(config) = modules.config {
                 http_opt = http_opt.config(config);
             }
+            if let Some(admin) = modules.admin {
+                http_opt = http_opt.admin(admin);
+            }
+            if let Some(audit_log) = &audit_log {
+                http_opt = http_opt.audit(audit_log.clone());
+            }
 
             http_opt
                 .serve(move |metrics, consumer| {
                     async move {
-                        init_node(
+                        let notifier =
+                            Notifier::new(notification_storage, notification_sinks, consumer)
+                                .with_max_backlog(notification_max_backlog);
+                        let publishers = notifier.publishers(&*metrics);
+                        #[cfg(feature = "slashing")]
+                        let evidence_submitter =
+                            opt.slashing.connect(&l1_params.urls, &*metrics)?;
+                        let mut ctx = init_node(
                             genesis,
                             network_params,
                             &*metrics,
                             persistence,
                             l1_params,
                             versions,
-                            consumer,
+                            notifier,
                             opt.is_da,
                             opt.identity,
                             marketplace_config,
                             proposal_fetcher_config,
                         )
-                        .await
+                        .await?;
+                        for publisher in publishers {
+                            ctx.spawn(
+                                format!("notification publisher ({})", publisher.name()),
+                                publisher.run(),
+                            );
+                        }
+                        #[cfg(feature = "slashing")]
+                        if let Some(submitter) = evidence_submitter {
+                            let events = ctx.event_stream().await;
+                            ctx.spawn("double vote evidence submitter", submitter.run(events));
+                        }
+                        Ok(ctx)
                     }
                     .boxed()
                 })
                 .await?
         },
         None => {
-            init_node(
+            let notifier =
+                Notifier::new(notification_storage, notification_sinks, NullEventConsumer)
+                    .with_max_backlog(notification_max_backlog);
+            let publishers = notifier.publishers(&NoMetrics);
+            #[cfg(feature = "slashing")]
+            let evidence_submitter = opt.slashing.connect(&l1_params.urls, &NoMetrics)?;
+            let mut ctx = init_node(
                 genesis,
                 network_params,
                 &NoMetrics,
                 persistence,
                 l1_params,
                 versions,
-                NullEventConsumer,
+                notifier,
                 opt.is_da,
                 opt.identity,
                 marketplace_config,
                 proposal_fetcher_config,
             )
-            .await?
+            .await?;
+            for publisher in publishers {
+                ctx.spawn(
+                    format!("notification publisher ({})", publisher.name()),
+                    publisher.run(),
+                );
+            }
+            #[cfg(feature = "slashing")]
+            if let Some(submitter) = evidence_submitter {
+                let events = ctx.event_stream().await;
+                ctx.spawn("double vote evidence submitter", submitter.run(events));
+            }
+            ctx
         },
     };
+    if let Some(reloader) = builder_registry_reloader {
+        ctx.spawn("builder registry reloader", reloader.run());
+    }
+    if let Some(provider) = da_payload_provider {
+        ctx.consensus()
+            .read()
+            .await
+            .hotshot
+            .set_da_payload_provider(Arc::new(DaPayloadFetcher::new(provider)));
+    }
+    if let Some(audit_log) = audit_log {
+        ctx.consensus()
+            .read()
+            .await
+            .hotshot
+            .set_message_audit(Arc::new(audit_log));
+    }
+
+    let storage = ctx.consensus().read().await.storage().read().await.clone();
+    let pending_transactions = PendingTransactions::new(
+        storage,
+        pending_transaction_peers,
+        pending_transaction_retention,
+    );
+    let events = ctx.event_stream().await;
+    let consensus = ctx.consensus();
+    ctx.spawn(
+        "pending transaction recovery",
+        pending_transactions.run(events, consensus),
+    );
 
     Ok(ctx)
 }
@@ -325,6 +545,8 @@ mod test {
             upgrade_version: Version { major: 0, minor: 2 },
             epoch_height: None,
             epoch_start_block: None,
+            encryption: None,
+            namespace_registries: Default::default(),
         };
         genesis.to_file(&genesis_file).unwrap();
 
@@ -359,6 +581,7 @@ mod test {
                 opt,
                 fs::Options::new(tmp.path().into()),
                 MockSequencerVersions::new(),
+                false,
             )
             .await
             {
@@ -0,0 +1,385 @@
+//! Submission of double vote evidence to the L1 slashing contract.
+//!
+//! The vote collectors in consensus check every vote they receive against the votes already
+//! collected for the same view, and report any validator which signed two different votes as a
+//! `DoubleVote` event. The submitter follows those events, encodes the evidence for the slashing
+//! contract and sends it to L1 from a funded account: fees come from the L1 fee estimate, the
+//! nonce is pinned so that a retry replaces a stuck transaction instead of queueing behind it, and
+//! each retry bumps the fees. Evidence is only submitted once per validator and view.
+//!
+//! This module is experimental and only built with the `slashing` feature: `ISlashing` is a
+//! provisional interface with no deployed contract yet, so the evidence format may still change.
+//! Even then, submission is opt-in: nothing is sent unless a slashing contract and an account are
+//! configured, and operators can turn it off with `ESPRESSO_SEQUENCER_SLASHING_DISABLED` without
+//! removing the rest of the configuration.
+
+use std::time::Duration;
+
+use alloy::{
+    network::EthereumWallet,
+    primitives::{Address, FixedBytes},
+    providers::{Provider, ProviderBuilder},
+    sol,
+    sol_types::SolValue,
+};
+use anyhow::{ensure, Context};
+use ark_ec::CurveGroup;
+use clap::Parser;
+use derivative::Derivative;
+use espresso_types::{eth_signature_key::EthKeyPair, parse_duration, PubKey, SeqTypes};
+use futures::{
+    future,
+    stream::{Stream, StreamExt},
+};
+use hotshot::types::{Event, EventType};
+use hotshot_contract_adapter::sol_types::{G1PointSol, G2PointSol};
+use hotshot_types::{
+    traits::metrics::{Counter, Metrics},
+    vote::DoubleVoteEvidence,
+};
+use tokio::time::sleep;
+use url::Url;
+
+use crate::bounded_map::BoundedMap;
+
+/// Number of validator and view pairs remembered, to avoid submitting the same evidence twice
+const MAX_REPORTED: usize = 10_000;
+
+/// Number of pieces of evidence waiting to be submitted before new ones are dropped
+const MAX_QUEUED: usize = 100;
+
+sol! {
+    /// Evidence that a validator signed two different votes in the same view
+    struct DoubleVoteSol {
+        /// ABI encoded BLS verification key of the validator
+        bytes blsVK;
+        uint64 viewNumber;
+        bytes32 firstVoteCommitment;
+        /// ABI encoded BLS signature on the first vote
+        bytes firstSig;
+        bytes32 secondVoteCommitment;
+        /// ABI encoded BLS signature on the second vote
+        bytes secondSig;
+    }
+
+    /// Interface of the L1 contract which slashes validators for double voting
+    #[sol(rpc)]
+    interface ISlashing {
+        function submitDoubleVote(DoubleVoteSol evidence) external;
+    }
+}
+
+impl From<&DoubleVoteEvidence<SeqTypes>> for DoubleVoteSol {
+    fn from(evidence: &DoubleVoteEvidence<SeqTypes>) -> Self {
+        let bls_vk: G2PointSol = evidence.key.to_affine().into();
+        let first_sig: G1PointSol = evidence.first_signature.sigma.into_affine().into();
+        let second_sig: G1PointSol = evidence.second_signature.sigma.into_affine().into();
+        Self {
+            blsVK: bls_vk.abi_encode().into(),
+            viewNumber: *evidence.view,
+            firstVoteCommitment: FixedBytes(evidence.first_commitment),
+            firstSig: first_sig.abi_encode().into(),
+            secondVoteCommitment: FixedBytes(evidence.second_commitment),
+            secondSig: second_sig.abi_encode().into(),
+        }
+    }
+}
+
+/// Configuration for submitting double vote evidence to L1
+#[derive(Parser, Clone, Derivative)]
+#[derivative(Debug)]
+pub struct SlashingConfig {
+    /// Address of the L1 slashing contract
+    ///
+    /// Evidence is only submitted if this and SLASHING_MNEMONIC are set.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SLASHING_CONTRACT_ADDRESS")]
+    pub slashing_contract_address: Option<Address>,
+
+    /// Mnemonic phrase for the funded L1 account which submits evidence
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SLASHING_MNEMONIC")]
+    #[derivative(Debug = "ignore")]
+    pub slashing_mnemonic: Option<String>,
+
+    /// Index of the account derived from SLASHING_MNEMONIC
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SLASHING_ACCOUNT_INDEX",
+        default_value = "0"
+    )]
+    pub slashing_account_index: u32,
+
+    /// Do not submit evidence, even if a slashing contract and account are configured
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SLASHING_DISABLED")]
+    pub slashing_disabled: bool,
+
+    /// Number of attempts to get a piece of evidence confirmed before giving up
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SLASHING_MAX_ATTEMPTS",
+        default_value = "5"
+    )]
+    pub slashing_max_attempts: usize,
+
+    /// Time to wait for a submission to be confirmed before retrying with higher fees
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SLASHING_CONFIRMATION_TIMEOUT",
+        default_value = "3m",
+        value_parser = parse_duration
+    )]
+    pub slashing_confirmation_timeout: Duration,
+
+    /// Number of L1 blocks on top of a submission before it is considered confirmed
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SLASHING_CONFIRMATIONS",
+        default_value = "3"
+    )]
+    pub slashing_confirmations: u64,
+
+    /// Upper limit on the fee per gas offered for a submission, in wei
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SLASHING_MAX_FEE_PER_GAS")]
+    pub slashing_max_fee_per_gas: Option<u128>,
+}
+
+impl SlashingConfig {
+    /// Connect to the first of `l1_urls`, if submission is enabled.
+    pub fn connect(
+        self,
+        l1_urls: &[Url],
+        metrics: &dyn Metrics,
+    ) -> anyhow::Result<Option<EvidenceSubmitter<impl Provider>>> {
+        if self.slashing_disabled {
+            tracing::info!("double vote evidence submission is disabled");
+            return Ok(None);
+        }
+        let (Some(contract), Some(mnemonic), Some(l1_url)) = (
+            self.slashing_contract_address,
+            &self.slashing_mnemonic,
+            l1_urls.first(),
+        ) else {
+            return Ok(None);
+        };
+        let key_pair = EthKeyPair::from_mnemonic(mnemonic, self.slashing_account_index)
+            .context("invalid slashing mnemonic")?;
+        let provider = ProviderBuilder::new()
+            .wallet(EthereumWallet::from(key_pair.signer()))
+            .on_http(l1_url.clone());
+        tracing::info!(%contract, account = %key_pair.address(), "submitting double vote evidence");
+        Ok(Some(EvidenceSubmitter {
+            provider,
+            contract,
+            account: key_pair.address(),
+            config: self,
+            metrics: SlashingMetrics::new(metrics),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct SlashingMetrics {
+    detected: Box<dyn Counter>,
+    submitted: Box<dyn Counter>,
+    confirmed: Box<dyn Counter>,
+    failed: Box<dyn Counter>,
+}
+
+impl SlashingMetrics {
+    fn new(metrics: &dyn Metrics) -> Self {
+        let metrics = metrics.subgroup("slashing".into());
+        Self {
+            detected: metrics.create_counter("double_votes_detected".into(), None),
+            submitted: metrics.create_counter("evidence_transactions_sent".into(), None),
+            confirmed: metrics.create_counter("evidence_confirmed".into(), None),
+            failed: metrics.create_counter("evidence_failed".into(), None),
+        }
+    }
+}
+
+/// Submits double vote evidence found by consensus to the L1 slashing contract
+#[derive(Debug)]
+pub struct EvidenceSubmitter<P> {
+    provider: P,
+    contract: Address,
+    account: Address,
+    config: SlashingConfig,
+    metrics: SlashingMetrics,
+}
+
+impl<P: Provider> EvidenceSubmitter<P> {
+    /// Follow consensus events, submitting each new piece of evidence.
+    pub async fn run(self, events: impl Stream<Item = Event<SeqTypes>>) {
+        // Submissions can take several L1 blocks, so they happen alongside the event stream
+        // rather than holding it up.
+        let (sender, receiver) = async_channel::bounded(MAX_QUEUED);
+        let mut reported = BoundedMap::<(PubKey, u64), ()>::new(MAX_REPORTED);
+        let this = &self;
+        let collect = events.for_each(move |event| {
+            if let EventType::DoubleVote { evidence } = event.event {
+                let id = (evidence.key, *evidence.view);
+                if reported.get(&id).is_none() {
+                    this.metrics.detected.add(1);
+                    if sender.try_send(evidence).is_ok() {
+                        reported.insert(id, ());
+                    } else {
+                        tracing::error!(
+                            view = id.1,
+                            key = %id.0,
+                            "too much double vote evidence waiting to be submitted, dropping"
+                        );
+                        this.metrics.failed.add(1);
+                    }
+                }
+            }
+            future::ready(())
+        });
+        let submit = receiver.for_each(|evidence| async move {
+            let view = *evidence.view;
+            let key = evidence.key;
+            match this.submit(&evidence).await {
+                Ok(()) => {
+                    tracing::warn!(view, %key, "double vote evidence confirmed on L1");
+                    this.metrics.confirmed.add(1);
+                },
+                Err(err) => {
+                    tracing::error!(view, %key, "failed to submit double vote evidence: {err:#}");
+                    this.metrics.failed.add(1);
+                },
+            }
+        });
+        future::join(collect, submit).await;
+    }
+
+    /// Submit a piece of evidence and wait for it to be confirmed, retrying with higher fees.
+    async fn submit(&self, evidence: &DoubleVoteEvidence<SeqTypes>) -> anyhow::Result<()> {
+        let contract = ISlashing::new(self.contract, &self.provider);
+        let call = contract.submitDoubleVote(evidence.into());
+
+        // Every attempt uses the same nonce, so that each one replaces the last.
+        let nonce = self
+            .provider
+            .get_transaction_count(self.account)
+            .pending()
+            .await
+            .context("fetching account nonce")?;
+        let gas = call.estimate_gas().await.context("estimating gas")?;
+
+        let mut last_err = None;
+        for attempt in 0..self.config.slashing_max_attempts {
+            let fees = self
+                .provider
+                .estimate_eip1559_fees()
+                .await
+                .context("estimating fees")?;
+            let (max_fee, priority_fee) = bump_fees(
+                fees.max_fee_per_gas,
+                fees.max_priority_fee_per_gas,
+                attempt,
+                self.config.slashing_max_fee_per_gas,
+            );
+            tracing::info!(
+                view = *evidence.view,
+                attempt,
+                nonce,
+                max_fee,
+                priority_fee,
+                "sending double vote evidence"
+            );
+            let pending = match call
+                .clone()
+                .nonce(nonce)
+                .gas(gas + gas / 5)
+                .max_fee_per_gas(max_fee)
+                .max_priority_fee_per_gas(priority_fee)
+                .send()
+                .await
+            {
+                Ok(pending) => pending,
+                Err(err) => {
+                    tracing::warn!(attempt, "error sending double vote evidence: {err:#}");
+                    last_err = Some(anyhow::Error::from(err));
+                    sleep(self.config.slashing_confirmation_timeout / 10).await;
+                    continue;
+                },
+            };
+            self.metrics.submitted.add(1);
+
+            match pending
+                .with_required_confirmations(self.config.slashing_confirmations)
+                .with_timeout(Some(self.config.slashing_confirmation_timeout))
+                .get_receipt()
+                .await
+            {
+                Ok(receipt) => {
+                    ensure!(
+                        receipt.inner.is_success(),
+                        "evidence transaction {} reverted",
+                        receipt.transaction_hash
+                    );
+                    return Ok(());
+                },
+                Err(err) => {
+                    tracing::warn!(attempt, "double vote evidence not confirmed: {err:#}");
+                    last_err = Some(err.into());
+                },
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no attempts configured")))
+    }
+}
+
+/// Fees for the given attempt, raised by an eighth per attempt so each attempt can replace the
+/// last, and capped at `max_fee_per_gas`.
+fn bump_fees(
+    max_fee: u128,
+    priority_fee: u128,
+    attempt: usize,
+    max_fee_per_gas: Option<u128>,
+) -> (u128, u128) {
+    let bump = |fee: u128| (0..attempt).fold(fee, |fee, _| fee.saturating_add(fee.div_ceil(8)));
+    let cap = max_fee_per_gas.unwrap_or(u128::MAX);
+    let max_fee = bump(max_fee).min(cap);
+    (max_fee, bump(priority_fee).min(max_fee))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::sol_types::SolValue;
+    use hotshot::types::SignatureKey;
+    use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+    use super::*;
+
+    #[test]
+    fn test_encode_double_vote() {
+        let (key, priv_key) = PubKey::generated_from_seed_indexed([0; 32], 0);
+        let sign = |msg: &[u8]| PubKey::sign(&priv_key, msg).unwrap();
+        let evidence = DoubleVoteEvidence::<SeqTypes> {
+            view: ViewNumber::new(7),
+            key,
+            first_commitment: [1; 32],
+            first_signature: sign(&[1; 32]),
+            second_commitment: [2; 32],
+            second_signature: sign(&[2; 32]),
+        };
+
+        let sol = DoubleVoteSol::from(&evidence);
+        assert_eq!(sol.viewNumber, 7);
+        assert_eq!(sol.firstVoteCommitment, FixedBytes([1; 32]));
+        assert_eq!(sol.secondVoteCommitment, FixedBytes([2; 32]));
+        let vk = G2PointSol::abi_decode(&sol.blsVK, true).unwrap();
+        assert_eq!(PubKey::from(vk), key);
+        assert_ne!(sol.firstSig, sol.secondSig);
+    }
+
+    #[test]
+    fn test_bump_fees() {
+        assert_eq!(bump_fees(80, 8, 0, None), (80, 8));
+        assert_eq!(bump_fees(80, 8, 1, None), (90, 9));
+        assert_eq!(bump_fees(80, 8, 2, None), (102, 11));
+
+        // Fees never exceed the cap, and the priority fee never exceeds the max fee.
+        assert_eq!(bump_fees(80, 80, 3, Some(100)), (100, 100));
+        assert_eq!(bump_fees(200, 8, 0, Some(100)), (100, 8));
+    }
+}
@@ -5,7 +5,7 @@ use anyhow::{bail, ensure, Context};
 use espresso_types::{
     traits::StateCatchup,
     v0_1::{RewardAccount, RewardMerkleTree},
-    v0_99::ChainConfig,
+    v0_4::ChainConfig,
     BlockMerkleTree, Delta, FeeAccount, FeeMerkleTree, Leaf2, ValidatedState,
 };
 use futures::{future::Future, StreamExt};
@@ -22,10 +22,14 @@ use tokio::time::sleep;
 
 use crate::{
     catchup::{CatchupStorage, SqlStateCatchup},
-    persistence::ChainConfigPersistence,
+    persistence::{ChainConfigPersistence, StateSnapshotPersistence},
+    state_sync::{blocks_frontier, StateDiff, StateSnapshot},
     NodeState, SeqTypes,
 };
 
+/// Number of snapshot intervals the latest state snapshot may fall behind before it is dropped.
+const STATE_DIFF_RETENTION: u64 = 3;
+
 pub(crate) async fn compute_state_update(
     state: &ValidatedState,
     instance: &NodeState,
@@ -187,6 +191,7 @@ async fn update_state_storage<T>(
     peers: &impl StateCatchup,
     parent_leaf: &LeafQueryData<SeqTypes>,
     proposed_leaf: &LeafQueryData<SeqTypes>,
+    snapshot_interval: u64,
 ) -> anyhow::Result<ValidatedState>
 where
     T: SequencerStateDataSource,
@@ -204,6 +209,10 @@ where
     .await
     .context("computing state update")?;
 
+    let diff = (snapshot_interval > 0)
+        .then(|| StateDiff::new(proposed_leaf.height(), &state, &delta))
+        .transpose()?;
+
     tracing::debug!("storing state update");
     let mut tx = storage
         .write()
@@ -211,6 +220,9 @@ where
         .context("opening transaction for state update")?;
 
     store_state_update(&mut tx, proposed_leaf.height(), &state, delta).await?;
+    if let Some(diff) = diff {
+        store_state_diff(&mut tx, &diff, snapshot_interval).await?;
+    }
 
     if parent_chain_config != state.chain_config {
         let cf = state
@@ -222,9 +234,89 @@ where
     }
 
     tx.commit().await?;
+
+    // Snapshots are only an optimization for syncing nodes, so failing to take one must not hold
+    // up the state update.
+    let height = proposed_leaf.height();
+    if snapshot_interval > 0 && height % snapshot_interval == 0 {
+        if let Err(err) = store_state_snapshot(storage, height, &state).await {
+            tracing::warn!(height, "failed to take state snapshot: {err:#}");
+        }
+    }
+
     Ok(state)
 }
 
+/// Record the diff of a block, as long as there is a snapshot it can be applied to.
+///
+/// Diffs are only kept until the next snapshot is taken. If snapshots keep failing, the latest one
+/// falls further and further behind; once it is more than [`STATE_DIFF_RETENTION`] intervals old,
+/// it is dropped along with its diffs, so that they do not grow without bound.
+async fn store_state_diff(
+    tx: &mut impl SequencerStateUpdate,
+    diff: &StateDiff,
+    snapshot_interval: u64,
+) -> anyhow::Result<()> {
+    let Some(snapshot_height) = tx.latest_state_snapshot_height().await? else {
+        // Without a snapshot to build on, the diff would never be used.
+        return Ok(());
+    };
+    if diff.height.saturating_sub(snapshot_height) > STATE_DIFF_RETENTION * snapshot_interval {
+        tracing::warn!(
+            height = diff.height,
+            snapshot_height,
+            "latest state snapshot is too old to roll forward, dropping it"
+        );
+        return tx
+            .delete_state_snapshots()
+            .await
+            .context("deleting stale state snapshots");
+    }
+    tx.insert_state_diff(diff)
+        .await
+        .context("storing state diff")
+}
+
+/// Roll the latest state snapshot forward to `height`, using the diffs recorded since.
+async fn store_state_snapshot<T>(
+    storage: &Arc<T>,
+    height: u64,
+    state: &ValidatedState,
+) -> anyhow::Result<()>
+where
+    T: SequencerStateDataSource,
+    for<'a> T::Transaction<'a>: SequencerStateUpdate,
+{
+    let mut tx = storage
+        .write()
+        .await
+        .context("opening transaction for state snapshot")?;
+    let mut snapshot = tx
+        .load_latest_state_snapshot()
+        .await?
+        .context("no earlier snapshot to build on")?;
+    if snapshot.height >= height {
+        return Ok(());
+    }
+    for diff in tx.load_state_diffs(snapshot.height + 1, height).await? {
+        snapshot.apply(&diff)?;
+    }
+    ensure!(
+        snapshot.height == height,
+        "state diffs after height {} are missing",
+        snapshot.height
+    );
+    snapshot.blocks_frontier = blocks_frontier(&state.block_merkle_tree, height)?;
+    snapshot.chain_config = state
+        .chain_config
+        .resolve()
+        .context("chain config is not available")?;
+
+    tracing::info!(height, "storing state snapshot");
+    tx.insert_state_snapshot(&snapshot).await?;
+    tx.commit().await
+}
+
 async fn store_genesis_state<T>(
     mut tx: T,
     chain_config: ChainConfig,
@@ -258,6 +350,9 @@ where
         .context("failed to store fee merkle nodes")?;
     }
 
+    tx.insert_state_snapshot(&StateSnapshot::genesis(chain_config, state))
+        .await
+        .context("storing genesis state snapshot")?;
     tx.insert_chain_config(chain_config).await?;
 
     tx.commit().await?;
@@ -268,6 +363,7 @@ where
 pub(crate) async fn update_state_storage_loop<T>(
     storage: Arc<T>,
     instance: impl Future<Output = NodeState>,
+    snapshot_interval: u64,
 ) -> anyhow::Result<()>
 where
     T: SequencerStateDataSource,
@@ -336,6 +432,7 @@ where
                 &peers,
                 &parent_leaf,
                 &leaf,
+                snapshot_interval,
             )
             .await
             {
@@ -386,6 +483,7 @@ pub(crate) trait SequencerStateUpdate:
     + UpdateStateData<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>
     + UpdateStateData<SeqTypes, RewardMerkleTree, { RewardMerkleTree::ARITY }>
     + ChainConfigPersistence
+    + StateSnapshotPersistence
 {
 }
 
@@ -395,5 +493,53 @@ impl<T> SequencerStateUpdate for T where
         + UpdateStateData<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>
         + UpdateStateData<SeqTypes, RewardMerkleTree, { RewardMerkleTree::ARITY }>
         + ChainConfigPersistence
+        + StateSnapshotPersistence
 {
 }
+
+#[cfg(test)]
+mod test {
+    use sequencer_utils::test_utils::setup_test;
+
+    use super::*;
+    use crate::api::{
+        data_source::{testing::TestableSequencerDataSource, SequencerDataSource},
+        sql::DataSource,
+    };
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_state_diff_retention() {
+        setup_test();
+
+        let storage = DataSource::create_storage().await;
+        let ds = DataSource::create(
+            DataSource::persistence_options(&storage),
+            Default::default(),
+            false,
+        )
+        .await
+        .unwrap();
+        let state = ValidatedState::default();
+        let diff = |height| StateDiff::new(height, &state, &Delta::default()).unwrap();
+
+        // Without a snapshot, diffs are not stored.
+        let mut tx = ds.write().await.unwrap();
+        store_state_diff(&mut tx, &diff(1), 10).await.unwrap();
+        assert_eq!(tx.load_state_diffs(1, 1).await.unwrap(), vec![]);
+
+        // Once there is a snapshot, they are.
+        tx.insert_state_snapshot(&StateSnapshot::genesis(ChainConfig::default(), &state))
+            .await
+            .unwrap();
+        for height in 1..=30 {
+            store_state_diff(&mut tx, &diff(height), 10).await.unwrap();
+        }
+        assert_eq!(tx.load_state_diffs(1, 30).await.unwrap().len(), 30);
+
+        // Once the snapshot falls too far behind, it is dropped along with its diffs.
+        store_state_diff(&mut tx, &diff(31), 10).await.unwrap();
+        assert_eq!(tx.latest_state_snapshot_height().await.unwrap(), None);
+        assert_eq!(tx.load_state_diffs(1, 31).await.unwrap(), vec![]);
+        tx.commit().await.unwrap();
+    }
+}
@@ -26,7 +26,7 @@ use surf_disco::{Client, Url};
 use tide_disco::error::ServerError;
 use vbs::version::StaticVersionType;
 
-use crate::{context::Consensus, SeqTypes};
+use crate::{context::Consensus, remote_signer::RemoteSigner, SeqTypes};
 
 /// A relay server that's collecting and serving the light client state signatures
 pub mod relay_server;
@@ -56,6 +56,9 @@ pub struct StateSigner<ApiVer: StaticVersionType> {
 
     /// The state relay server url
     relay_server_client: Option<Client<ServerError, ApiVer>>,
+
+    /// Remote signer to sign light client states with, instead of `sign_key`
+    remote_signer: Option<Arc<RemoteSigner<ApiVer>>>,
 }
 
 impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
@@ -74,6 +77,7 @@ impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
             stake_table_capacity,
             signatures: Default::default(),
             relay_server_client: Default::default(),
+            remote_signer: Default::default(),
         }
     }
 
@@ -83,6 +87,12 @@ impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
         self
     }
 
+    /// Sign light client states with the given remote signer.
+    pub fn with_remote_signer(mut self, signer: Arc<RemoteSigner<ApiVer>>) -> Self {
+        self.remote_signer = Some(signer);
+        self
+    }
+
     pub(super) async fn handle_event<N, P, V>(
         &mut self,
         event: &Event<SeqTypes>,
@@ -139,7 +149,10 @@ impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
                     );
                 }
 
-                let signature = self.sign_new_state(&state, self.voting_stake_table).await;
+                let Some(signature) = self.sign_new_state(&state, self.voting_stake_table).await
+                else {
+                    return;
+                };
 
                 if let Some(client) = &self.relay_server_client {
                     let request_body = StateSignatureRequestBody {
@@ -176,13 +189,25 @@ impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
         &self,
         state: &LightClientState,
         next_stake_table: StakeTableState,
-    ) -> StateSignature {
-        let signature = <SchnorrPubKey as StateSignatureKey>::sign_state(
-            &self.sign_key,
-            state,
-            &next_stake_table,
-        )
-        .unwrap();
+    ) -> Option<StateSignature> {
+        let signature = match &self.remote_signer {
+            Some(signer) => match signer.sign_state(state, &next_stake_table).await {
+                Ok(signature) => signature,
+                Err(err) => {
+                    tracing::error!(
+                        height = state.block_height,
+                        "remote signer failed to sign light client state: {err:#}"
+                    );
+                    return None;
+                },
+            },
+            None => <SchnorrPubKey as StateSignatureKey>::sign_state(
+                &self.sign_key,
+                state,
+                &next_stake_table,
+            )
+            .unwrap(),
+        };
         let mut pool_guard = self.signatures.write().await;
         pool_guard.push(
             state.block_height,
@@ -197,7 +222,7 @@ impl<ApiVer: StaticVersionType> StateSigner<ApiVer> {
             "New signature added for block height {}",
             state.block_height
         );
-        signature
+        Some(signature)
     }
 }
 
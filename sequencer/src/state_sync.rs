@@ -0,0 +1,295 @@
+//! Merklized state snapshots for syncing new nodes.
+//!
+//! Alongside the merklized state itself, nodes storing merklized state record a [`StateDiff`] for
+//! every block, holding the fee and reward accounts changed by that block. Every so often, the
+//! latest [`StateSnapshot`] is rolled forward through the diffs recorded since to produce a new
+//! snapshot. A new node can download the latest snapshot in chunks, check it against the header at
+//! the snapshot height, and replay the blocks (or diffs) after it, rather than replaying the whole
+//! chain from genesis.
+
+use std::collections::BTreeMap;
+
+use anyhow::{ensure, Context};
+use espresso_types::{
+    v0_1::{
+        RewardAccount, RewardAmount, RewardMerkleTree, FEE_MERKLE_TREE_HEIGHT,
+        REWARD_MERKLE_TREE_HEIGHT,
+    },
+    v0_4::{ChainConfig, ResolvableChainConfig},
+    BlockMerkleTree, Delta, FeeAccount, FeeAmount, FeeMerkleTree, Header, ValidatedState,
+};
+use hotshot::traits::ValidatedState as _;
+use jf_merkle_tree::{
+    prelude::MerkleNode, ForgetableMerkleTreeScheme, LookupResult, MerkleTreeScheme,
+    UniversalMerkleTreeScheme,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::api::BlocksFrontier;
+
+/// Size of the chunks snapshots are stored and served in, in bytes
+pub const STATE_SNAPSHOT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Maximum number of diffs served in a single request
+pub const MAX_STATE_DIFFS_PER_REQUEST: u64 = 1000;
+
+/// The accounts changed by the block at `height`
+///
+/// An account which is absent from the state after the block maps to `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff {
+    pub height: u64,
+    pub fee_accounts: Vec<(FeeAccount, Option<FeeAmount>)>,
+    pub reward_accounts: Vec<(RewardAccount, Option<RewardAmount>)>,
+}
+
+impl StateDiff {
+    /// The diff made by the block at `height`, which took the state to `state` and changed the
+    /// accounts in `delta`
+    pub fn new(height: u64, state: &ValidatedState, delta: &Delta) -> anyhow::Result<Self> {
+        let fee_accounts = delta
+            .fees_delta
+            .iter()
+            .map(|account| match state.fee_merkle_tree.lookup(*account) {
+                LookupResult::Ok(amount, _) => Ok((*account, Some(*amount))),
+                LookupResult::NotFound(_) => Ok((*account, None)),
+                LookupResult::NotInMemory => {
+                    anyhow::bail!("missing merkle path for fee account {account}")
+                },
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let reward_accounts = delta
+            .rewards_delta
+            .iter()
+            .map(|account| match state.reward_merkle_tree.lookup(*account) {
+                LookupResult::Ok(amount, _) => Ok((*account, Some(*amount))),
+                LookupResult::NotFound(_) => Ok((*account, None)),
+                LookupResult::NotInMemory => {
+                    anyhow::bail!("missing merkle path for reward account {account}")
+                },
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            height,
+            fee_accounts,
+            reward_accounts,
+        })
+    }
+}
+
+/// Summary of a stored snapshot, telling clients how to download it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshotInfo {
+    pub height: u64,
+    pub num_chunks: u64,
+}
+
+/// The complete merklized state after the block at `height`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub height: u64,
+    pub chain_config: ChainConfig,
+    /// Path to the most recent block in the blocks tree, or `None` if the tree is empty
+    pub blocks_frontier: Option<BlocksFrontier>,
+    pub fee_accounts: BTreeMap<FeeAccount, FeeAmount>,
+    pub reward_accounts: BTreeMap<RewardAccount, RewardAmount>,
+}
+
+impl StateSnapshot {
+    /// The snapshot of the genesis state, which must be fully in memory
+    pub fn genesis(chain_config: ChainConfig, state: &ValidatedState) -> Self {
+        Self {
+            height: 0,
+            chain_config,
+            blocks_frontier: None,
+            fee_accounts: state
+                .fee_merkle_tree
+                .iter()
+                .map(|(account, amount)| (*account, *amount))
+                .collect(),
+            reward_accounts: state
+                .reward_merkle_tree
+                .iter()
+                .map(|(account, amount)| (*account, *amount))
+                .collect(),
+        }
+    }
+
+    /// Roll this snapshot forward by one block
+    pub fn apply(&mut self, diff: &StateDiff) -> anyhow::Result<()> {
+        ensure!(
+            diff.height == self.height + 1,
+            "cannot apply diff for height {} to snapshot at height {}",
+            diff.height,
+            self.height
+        );
+        for (account, amount) in &diff.fee_accounts {
+            match amount {
+                Some(amount) => self.fee_accounts.insert(*account, *amount),
+                None => self.fee_accounts.remove(account),
+            };
+        }
+        for (account, amount) in &diff.reward_accounts {
+            match amount {
+                Some(amount) => self.reward_accounts.insert(*account, *amount),
+                None => self.reward_accounts.remove(account),
+            };
+        }
+        self.height = diff.height;
+        Ok(())
+    }
+
+    /// Build the state this snapshot describes, checking it against `header`
+    pub fn into_state(self, header: &Header) -> anyhow::Result<ValidatedState> {
+        ensure!(
+            header.height() == self.height,
+            "snapshot at height {} does not match header at height {}",
+            self.height,
+            header.height()
+        );
+        let chain_config = ResolvableChainConfig::from(self.chain_config);
+        ensure!(
+            header.chain_config().commit() == chain_config.commit(),
+            "snapshot chain config does not match header"
+        );
+
+        let fee_merkle_tree = FeeMerkleTree::from_kv_set(FEE_MERKLE_TREE_HEIGHT, self.fee_accounts)
+            .context("building fee merkle tree")?;
+        ensure!(
+            fee_merkle_tree.commitment() == header.fee_merkle_tree_root(),
+            "snapshot fee state does not match header"
+        );
+        let reward_merkle_tree =
+            RewardMerkleTree::from_kv_set(REWARD_MERKLE_TREE_HEIGHT, self.reward_accounts)
+                .context("building reward merkle tree")?;
+        ensure!(
+            reward_merkle_tree.commitment() == header.reward_merkle_tree_root(),
+            "snapshot reward state does not match header"
+        );
+
+        let mut state = ValidatedState::from_header(header);
+        if let Some(frontier) = self.blocks_frontier {
+            let Some(MerkleNode::Leaf { pos, elem, .. }) = frontier.proof.first() else {
+                anyhow::bail!("invalid blocks frontier");
+            };
+            ensure!(
+                *pos + 1 == state.block_merkle_tree.num_leaves(),
+                "blocks frontier is not for the most recent block"
+            );
+            state
+                .block_merkle_tree
+                .remember(pos, elem, &frontier)
+                .context("snapshot blocks frontier does not match header")?;
+        }
+        state.fee_merkle_tree = fee_merkle_tree;
+        state.reward_merkle_tree = reward_merkle_tree;
+        state.chain_config = chain_config;
+        Ok(state)
+    }
+
+    /// Serialize this snapshot and split it into chunks of at most `chunk_size` bytes
+    pub fn into_chunks(&self, chunk_size: usize) -> anyhow::Result<Vec<Vec<u8>>> {
+        let bytes = bincode::serialize(self).context("serializing state snapshot")?;
+        Ok(bytes.chunks(chunk_size).map(<[u8]>::to_vec).collect())
+    }
+
+    /// Reassemble a snapshot from its chunks, in order
+    pub fn from_chunks(chunks: impl IntoIterator<Item = Vec<u8>>) -> anyhow::Result<Self> {
+        let bytes = chunks.into_iter().flatten().collect::<Vec<_>>();
+        bincode::deserialize(&bytes).context("deserializing state snapshot")
+    }
+}
+
+/// The blocks frontier of `state` after the block at `height`, if any blocks precede it
+pub(crate) fn blocks_frontier(
+    state: &BlockMerkleTree,
+    height: u64,
+) -> anyhow::Result<Option<BlocksFrontier>> {
+    if height == 0 {
+        return Ok(None);
+    }
+    let (_, proof) = state
+        .lookup(height - 1)
+        .expect_ok()
+        .context("getting blocks frontier")?;
+    Ok(Some(proof))
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::primitives::Address;
+    use espresso_types::{Leaf2, NodeState};
+    use hotshot_example_types::node_types::TestVersions;
+
+    use super::*;
+
+    #[test]
+    fn test_state_snapshot_apply_diffs() {
+        let mut state = ValidatedState::default();
+        let alice = FeeAccount::test_key_pair().fee_account();
+        state.prefund_account(alice, FeeAmount::from(100));
+        let mut snapshot = StateSnapshot::genesis(ChainConfig::default(), &state);
+        assert_eq!(snapshot.fee_accounts[&alice], FeeAmount::from(100));
+
+        // Change a balance and check the snapshot follows the state.
+        state
+            .fee_merkle_tree
+            .update(alice, FeeAmount::from(50))
+            .unwrap();
+        let delta = Delta {
+            fees_delta: [alice].into_iter().collect(),
+            rewards_delta: Default::default(),
+        };
+        let diff = StateDiff::new(1, &state, &delta).unwrap();
+        assert_eq!(diff.fee_accounts, vec![(alice, Some(FeeAmount::from(50)))]);
+        snapshot.apply(&diff).unwrap();
+        assert_eq!(snapshot.height, 1);
+        assert_eq!(snapshot.fee_accounts[&alice], FeeAmount::from(50));
+
+        // Diffs must be applied in order.
+        snapshot.apply(&diff).unwrap_err();
+
+        let rebuilt =
+            FeeMerkleTree::from_kv_set(FEE_MERKLE_TREE_HEIGHT, snapshot.fee_accounts.clone())
+                .unwrap();
+        assert_eq!(rebuilt.commitment(), state.fee_merkle_tree.commitment());
+    }
+
+    #[test]
+    fn test_state_snapshot_chunks() {
+        let mut state = ValidatedState::default();
+        for i in 1..=100u8 {
+            state.prefund_account(Address::repeat_byte(i).into(), FeeAmount::from(i as u64));
+        }
+        let snapshot = StateSnapshot::genesis(ChainConfig::default(), &state);
+
+        let chunks = snapshot.into_chunks(64).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 64));
+        let reassembled = StateSnapshot::from_chunks(chunks).unwrap();
+        assert_eq!(reassembled.fee_accounts, snapshot.fee_accounts);
+        assert_eq!(reassembled.chain_config, snapshot.chain_config);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_state_snapshot_into_state() {
+        let instance = NodeState::mock();
+        let leaf = Leaf2::genesis::<TestVersions>(&instance.genesis_state, &instance).await;
+        let genesis = leaf.block_header();
+        let snapshot = StateSnapshot::genesis(instance.chain_config, &instance.genesis_state);
+
+        let state = snapshot.clone().into_state(genesis).unwrap();
+        assert_eq!(
+            state.fee_merkle_tree.commitment(),
+            genesis.fee_merkle_tree_root()
+        );
+
+        // A snapshot which does not match the header is rejected.
+        let mut bad = snapshot;
+        bad.fee_accounts.insert(
+            FeeAccount::test_key_pair().fee_account(),
+            FeeAmount::from(1),
+        );
+        bad.into_state(genesis).unwrap_err();
+    }
+}
@@ -0,0 +1,286 @@
+//! Tracking of transactions through consensus.
+//!
+//! The tracker follows consensus events and records the furthest stage each recent transaction has
+//! reached: received by this node, proposed to the DA committee, certified by the DA committee, and
+//! decided. The submit API serves these statuses, and streams them to clients as they change.
+
+use std::sync::Arc;
+
+use async_broadcast::{broadcast, InactiveReceiver, RecvError, Sender};
+use async_lock::RwLock;
+use committable::{Commitment, Committable};
+use espresso_types::{Payload, SeqTypes, Transaction, TransactionStatus};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use hotshot::types::{Event, EventType};
+use hotshot_types::{
+    event::LeafInfo,
+    traits::{block_contents::BlockHeader, BlockPayload},
+    vote::HasViewNumber,
+};
+
+use crate::bounded_map::BoundedMap;
+
+/// Maximum number of transactions whose status is kept
+const MAX_TRANSACTIONS: usize = 100_000;
+
+/// Maximum number of proposed blocks remembered while waiting for their DA certificates
+const MAX_PROPOSALS: usize = 100;
+
+/// Capacity of the channel of status updates
+const CHANNEL_CAPACITY: usize = 1_000;
+
+/// Status update for a single transaction
+type Update = (Commitment<Transaction>, TransactionStatus);
+
+/// Tracks the progress of recent transactions through consensus
+#[derive(Debug)]
+pub struct TransactionStatusTracker {
+    state: Arc<RwLock<TrackerState>>,
+    sender: Sender<Update>,
+    // Keeps the channel open while there are no subscribers.
+    _receiver: InactiveReceiver<Update>,
+}
+
+#[derive(Debug)]
+struct TrackerState {
+    /// The furthest status each transaction has reached
+    statuses: BoundedMap<Commitment<Transaction>, TransactionStatus>,
+    /// Transactions in blocks proposed to the DA committee, by view
+    proposals: BoundedMap<u64, Vec<Commitment<Transaction>>>,
+}
+
+impl Default for TransactionStatusTracker {
+    fn default() -> Self {
+        let (mut sender, receiver) = broadcast(CHANNEL_CAPACITY);
+        // Slow subscribers lose old updates rather than holding up consensus. They catch up from
+        // the recorded statuses instead.
+        sender.set_overflow(true);
+        Self {
+            state: Arc::new(RwLock::new(TrackerState {
+                statuses: BoundedMap::new(MAX_TRANSACTIONS),
+                proposals: BoundedMap::new(MAX_PROPOSALS),
+            })),
+            sender,
+            _receiver: receiver.deactivate(),
+        }
+    }
+}
+
+impl TransactionStatusTracker {
+    /// The current status of the transaction with commitment `hash`, if it is known
+    pub async fn status(&self, hash: Commitment<Transaction>) -> Option<TransactionStatus> {
+        self.state.read().await.statuses.get(&hash).copied()
+    }
+
+    /// Follow the status of the transaction with commitment `hash`.
+    ///
+    /// The stream yields the current status, if it is known, and then each change of status, ending
+    /// once the transaction is decided.
+    pub async fn subscribe(
+        &self,
+        hash: Commitment<Transaction>,
+    ) -> BoxStream<'static, TransactionStatus> {
+        // Subscribe before reading the current status, so no update can be missed in between.
+        let updates = self.sender.new_receiver();
+        let current = self.status(hash).await;
+        let state = self.state.clone();
+        stream::unfold(
+            (updates, current, None::<TransactionStatus>),
+            move |(mut updates, mut next, latest)| {
+                let state = state.clone();
+                async move {
+                    if latest.is_some_and(|status| status.is_final()) {
+                        return None;
+                    }
+                    let supersedes_latest = |status: &TransactionStatus| {
+                        latest.is_none_or(|latest| status.supersedes(&latest))
+                    };
+                    let status = match next.take() {
+                        Some(status) => status,
+                        None => loop {
+                            match updates.recv().await {
+                                Ok((updated, status)) => {
+                                    if updated == hash && supersedes_latest(&status) {
+                                        break status;
+                                    }
+                                },
+                                Err(RecvError::Overflowed(_)) => {
+                                    // Updates were lost, possibly including this transaction's,
+                                    // so catch up from its recorded status.
+                                    let status = state.read().await.statuses.get(&hash).copied();
+                                    if let Some(status) =
+                                        status.filter(|status| supersedes_latest(status))
+                                    {
+                                        break status;
+                                    }
+                                },
+                                Err(RecvError::Closed) => return None,
+                            }
+                        },
+                    };
+                    Some((status, (updates, next, Some(status))))
+                }
+            },
+        )
+        .boxed()
+    }
+
+    /// Record that this node has received a transaction.
+    pub async fn received(&self, hash: Commitment<Transaction>) {
+        let mut state = self.state.write().await;
+        self.update(&mut state, hash, TransactionStatus::Received);
+    }
+
+    /// Follow consensus events, updating the status of the transactions they concern.
+    pub async fn run(self: Arc<Self>, mut events: impl Stream<Item = Event<SeqTypes>> + Unpin) {
+        while let Some(event) = events.next().await {
+            match event.event {
+                EventType::DaProposal { proposal, .. } => {
+                    let view = proposal.data.view_number().u64();
+                    let payload = Payload::from_bytes(
+                        &proposal.data.encoded_transactions,
+                        &proposal.data.metadata,
+                    );
+                    let hashes = payload
+                        .transactions(&proposal.data.metadata)
+                        .map(|tx| tx.commit())
+                        .collect::<Vec<_>>();
+                    self.proposed(view, hashes).await;
+                },
+                EventType::DaCertificate { certificate } => {
+                    self.certified(certificate.view_number().u64()).await;
+                },
+                EventType::Decide { leaf_chain, .. } => {
+                    self.decided(&leaf_chain).await;
+                },
+                _ => {},
+            }
+        }
+    }
+
+    async fn proposed(&self, view: u64, hashes: Vec<Commitment<Transaction>>) {
+        let mut state = self.state.write().await;
+        for hash in &hashes {
+            self.update(&mut state, *hash, TransactionStatus::Proposed { view });
+        }
+        state.proposals.insert(view, hashes);
+    }
+
+    async fn certified(&self, view: u64) {
+        let mut state = self.state.write().await;
+        let Some(hashes) = state.proposals.remove(&view) else {
+            return;
+        };
+        for hash in hashes {
+            self.update(&mut state, hash, TransactionStatus::DaCertified { view });
+        }
+    }
+
+    async fn decided(&self, leaf_chain: &[LeafInfo<SeqTypes>]) {
+        let mut state = self.state.write().await;
+        for LeafInfo { leaf, .. } in leaf_chain.iter().rev() {
+            let view = leaf.view_number().u64();
+            state.proposals.remove(&view);
+            let Some(payload) = leaf.block_payload() else {
+                continue;
+            };
+            let status = TransactionStatus::Decided {
+                view,
+                height: leaf.height(),
+            };
+            for tx in payload.transactions(leaf.block_header().metadata()) {
+                self.update(&mut state, tx.commit(), status);
+            }
+        }
+    }
+
+    /// Move a transaction to `status`, unless it has already made it further.
+    fn update(
+        &self,
+        state: &mut TrackerState,
+        hash: Commitment<Transaction>,
+        status: TransactionStatus,
+    ) {
+        if state
+            .statuses
+            .get(&hash)
+            .is_some_and(|current| !status.supersedes(current))
+        {
+            return;
+        }
+        state.statuses.insert(hash, status);
+        // Only fails if there are no subscribers, in which case there is nobody to notify.
+        self.sender.try_broadcast((hash, status)).ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::NamespaceId;
+    use futures::FutureExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_transaction_status() {
+        let tracker = TransactionStatusTracker::default();
+        let tx = Transaction::new(NamespaceId::from(1u64), vec![1, 2, 3]);
+        let hash = tx.commit();
+        assert_eq!(tracker.status(hash).await, None);
+
+        tracker.received(hash).await;
+        let mut updates = tracker.subscribe(hash).await;
+        assert_eq!(updates.next().await, Some(TransactionStatus::Received));
+
+        // Unrelated proposals do not affect the transaction.
+        tracker.proposed(1, vec![]).await;
+        tracker.certified(1).await;
+        assert!(updates.next().now_or_never().is_none());
+
+        tracker.proposed(2, vec![hash]).await;
+        tracker.certified(2).await;
+        assert_eq!(
+            updates.next().await,
+            Some(TransactionStatus::Proposed { view: 2 })
+        );
+        assert_eq!(
+            updates.next().await,
+            Some(TransactionStatus::DaCertified { view: 2 })
+        );
+        assert_eq!(
+            tracker.status(hash).await,
+            Some(TransactionStatus::DaCertified { view: 2 })
+        );
+
+        // A stale update does not move the status backwards.
+        tracker.received(hash).await;
+        assert_eq!(
+            tracker.status(hash).await,
+            Some(TransactionStatus::DaCertified { view: 2 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_overflow() {
+        let tracker = TransactionStatusTracker::default();
+        let hash = Transaction::new(NamespaceId::from(1u64), vec![0]).commit();
+        tracker.received(hash).await;
+        let mut updates = tracker.subscribe(hash).await;
+        assert_eq!(updates.next().await, Some(TransactionStatus::Received));
+
+        // The transaction is decided, but the update is pushed out of the channel by updates for
+        // other transactions before the subscriber reads it.
+        let decided = TransactionStatus::Decided { view: 3, height: 2 };
+        {
+            let mut state = tracker.state.write().await;
+            tracker.update(&mut state, hash, decided);
+        }
+        for i in 0..2 * CHANNEL_CAPACITY as u64 {
+            let other = Transaction::new(NamespaceId::from(2u64), i.to_le_bytes().to_vec());
+            tracker.received(other.commit()).await;
+        }
+
+        assert_eq!(updates.next().await, Some(decided));
+        assert_eq!(updates.next().await, None);
+    }
+}
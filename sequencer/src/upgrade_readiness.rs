@@ -0,0 +1,248 @@
+//! Reporting of the protocol versions nodes support.
+//!
+//! Every node periodically broadcasts the version it is configured to upgrade to, signed with its
+//! staking key, and records the versions broadcast by the other nodes in the stake table in the
+//! consensus state. Before proposing an upgrade, the leader checks that the nodes supporting it
+//! hold enough stake to certify it, and reports the result in an
+//! [`EventType::UpgradeReadiness`] event, which this module logs and exposes as metrics.
+//!
+//! Nodes running versions before [`MarketplaceVersion`] cannot deserialize these reports, so they
+//! are only sent once the network has upgraded to it. Until then no node reports, and the network
+//! is assumed ready for any upgrade, as it was before readiness was tracked.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{ensure, Context};
+use async_lock::RwLock;
+use espresso_types::{
+    v0::traits::SequencerPersistence, MarketplaceVersion, PrivKey, PubKey, SeqTypes,
+};
+use futures::stream::{Stream, StreamExt};
+use hotshot::types::{Event, EventType};
+use hotshot_types::{
+    message::RecipientList,
+    traits::{
+        metrics::{Counter, Gauge, Metrics},
+        network::ConnectedNetwork,
+        node_implementation::Versions,
+        signature_key::SignatureKey,
+    },
+    upgrade_readiness::UpgradeReadinessReport,
+};
+use serde::{Deserialize, Serialize};
+use vbs::version::{StaticVersionType, Version};
+
+use crate::{context::Consensus, external_event_handler::ExternalMessage};
+
+/// How often this node broadcasts the version it supports
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The protocol version a node supports, signed with its staking key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedSupportedVersion {
+    pub key: PubKey,
+    pub version: Version,
+    pub signature: <PubKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl SignedSupportedVersion {
+    fn sign(key: PubKey, private_key: &PrivKey, version: Version) -> anyhow::Result<Self> {
+        let bytes = bincode::serialize(&version)?;
+        let signature = PubKey::sign(private_key, &bytes).context("signing supported version")?;
+        Ok(Self {
+            key,
+            version,
+            signature,
+        })
+    }
+
+    fn verify(&self) -> bool {
+        bincode::serialize(&self.version)
+            .is_ok_and(|bytes| self.key.validate(&self.signature, &bytes))
+    }
+}
+
+struct ReadinessMetrics {
+    /// Reports of supported versions received from other nodes
+    reports_received: Box<dyn Counter>,
+    /// Reports rejected because of an invalid signature or a reporter outside the stake table
+    reports_rejected: Box<dyn Counter>,
+    /// Whether the network was ready for the upgrade, the last time the leader checked
+    ready: Box<dyn Gauge>,
+}
+
+impl ReadinessMetrics {
+    fn new(metrics: &dyn Metrics) -> Self {
+        Self {
+            reports_received: metrics.create_counter("reports_received".into(), None),
+            reports_rejected: metrics.create_counter("reports_rejected".into(), None),
+            ready: metrics.create_gauge("ready".into(), None),
+        }
+    }
+}
+
+/// Broadcast the version this node supports, and record the versions supported by other nodes.
+pub async fn run<N, P, V>(
+    mut events: impl Stream<Item = Event<SeqTypes>> + Unpin,
+    consensus: Arc<RwLock<Consensus<N, P, V>>>,
+    private_key: PrivKey,
+    metrics: Box<dyn Metrics>,
+) where
+    N: ConnectedNetwork<PubKey>,
+    P: SequencerPersistence,
+    V: Versions,
+{
+    let metrics = ReadinessMetrics::new(&*metrics);
+    let version = V::Upgrade::version();
+    let mut interval = tokio::time::interval(REPORT_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(err) = report(&consensus, &private_key, version).await {
+                    tracing::warn!("failed to report supported version: {err:#}");
+                }
+            },
+            event = events.next() => {
+                let Some(event) = event else {
+                    break;
+                };
+                match event.event {
+                    EventType::ExternalMessageReceived { data, .. } => {
+                        if let Ok(ExternalMessage::SupportedVersion(report)) =
+                            bincode::deserialize(&data)
+                        {
+                            metrics.reports_received.add(1);
+                            if let Err(err) = record(&consensus, report).await {
+                                tracing::debug!("rejected supported version: {err:#}");
+                                metrics.reports_rejected.add(1);
+                            }
+                        }
+                    },
+                    EventType::UpgradeReadiness { report } => {
+                        log_readiness(&report);
+                        metrics.ready.set(report.is_ready() as usize);
+                    },
+                    _ => {},
+                }
+            },
+        }
+    }
+}
+
+/// Broadcast the version this node supports, once every node can deserialize the report.
+async fn report<N, P, V>(
+    consensus: &Arc<RwLock<Consensus<N, P, V>>>,
+    private_key: &PrivKey,
+    version: Version,
+) -> anyhow::Result<()>
+where
+    N: ConnectedNetwork<PubKey>,
+    P: SequencerPersistence,
+    V: Versions,
+{
+    let handle = consensus.read().await;
+    let current = handle
+        .hotshot
+        .upgrade_lock
+        .version_infallible(handle.cur_view().await)
+        .await;
+    if current < MarketplaceVersion::version() {
+        return Ok(());
+    }
+
+    let report = SignedSupportedVersion::sign(handle.public_key(), private_key, version)?;
+    let message = bincode::serialize(&ExternalMessage::SupportedVersion(report))?;
+    // Broadcasts do not reach their sender, so record our own support directly.
+    handle
+        .consensus()
+        .write()
+        .await
+        .upgrade_readiness
+        .report(handle.public_key(), version);
+    handle
+        .send_external_message(message, RecipientList::Broadcast)
+        .await
+        .context("broadcasting supported version")
+}
+
+/// Record the version supported by another node, if it is signed by a node in the stake table.
+async fn record<N, P, V>(
+    consensus: &Arc<RwLock<Consensus<N, P, V>>>,
+    report: SignedSupportedVersion,
+) -> anyhow::Result<()>
+where
+    N: ConnectedNetwork<PubKey>,
+    P: SequencerPersistence,
+    V: Versions,
+{
+    ensure!(report.verify(), "invalid signature from {}", report.key);
+    let handle = consensus.read().await;
+    let epoch = handle.cur_epoch().await;
+    let membership = handle
+        .membership_coordinator
+        .stake_table_for_epoch(epoch)
+        .await
+        .context("getting stake table")?;
+    ensure!(
+        membership.stake(&report.key).await.is_some(),
+        "{} is not in the stake table for epoch {epoch:?}",
+        report.key
+    );
+
+    tracing::debug!(key = %report.key, version = %report.version, "received supported version");
+    handle
+        .consensus()
+        .write()
+        .await
+        .upgrade_readiness
+        .report(report.key, report.version);
+    Ok(())
+}
+
+fn log_readiness(report: &UpgradeReadinessReport) {
+    if report.is_ready() {
+        tracing::info!(
+            version = %report.version,
+            supporting_stake = %report.supporting_stake,
+            threshold = %report.threshold,
+            "network is ready to upgrade"
+        );
+    } else {
+        tracing::warn!(
+            version = %report.version,
+            supporting_stake = %report.supporting_stake,
+            threshold = %report.threshold,
+            "network is not ready to upgrade; not enough stake supports the new version"
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_signed_supported_version() {
+        let (key, private_key) = PubKey::generated_from_seed_indexed([0; 32], 0);
+        let (other, _) = PubKey::generated_from_seed_indexed([0; 32], 1);
+        let version = Version {
+            major: 0,
+            minor: 99,
+        };
+
+        let report = SignedSupportedVersion::sign(key, &private_key, version).unwrap();
+        assert!(report.verify());
+
+        // A report cannot be attributed to another node, nor changed to another version.
+        assert!(!SignedSupportedVersion {
+            key: other,
+            ..report.clone()
+        }
+        .verify());
+        assert!(!SignedSupportedVersion {
+            version: Version { major: 0, minor: 3 },
+            ..report
+        }
+        .verify());
+    }
+}
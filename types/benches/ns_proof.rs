@@ -0,0 +1,71 @@
+//! Benchmark of namespace proof generation
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use espresso_types::{NamespaceId, NodeState, NsProof, Payload, Transaction, ValidatedState};
+use hotshot_query_service::VidCommon;
+use hotshot_types::{
+    traits::{BlockPayload, EncodeBytes},
+    vid::{advz::advz_scheme, avidm::AvidMParam},
+};
+use jf_vid::VidScheme;
+use rand::RngCore;
+
+const NUM_NAMESPACES: u64 = 10;
+const TX_SIZE: usize = 1024;
+const NUM_STORAGE_NODES: usize = 100;
+const RECOVERY_THRESHOLD: usize = 34;
+
+/// A payload of `byte_len` bytes, split evenly among [`NUM_NAMESPACES`] namespaces.
+fn payload(byte_len: usize) -> Payload {
+    let mut rng = jf_utils::test_rng();
+    let txs = (0..byte_len / TX_SIZE)
+        .map(|i| {
+            let mut tx = vec![0; TX_SIZE];
+            rng.fill_bytes(&mut tx);
+            Transaction::new(NamespaceId::from(i as u64 % NUM_NAMESPACES), tx)
+        })
+        .collect::<Vec<_>>();
+
+    // Raise the block size limit so the payload is not truncated.
+    let mut instance = NodeState::mock();
+    instance.chain_config.max_block_size = (2 * byte_len as u64).into();
+    let state = ValidatedState {
+        chain_config: instance.chain_config.into(),
+        ..Default::default()
+    };
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(Payload::from_transactions(txs, &state, &instance))
+        .unwrap()
+        .0
+}
+
+fn ns_proof_benchmark(c: &mut Criterion) {
+    let payload_bytes_len_list = [1024 * 1024, 8 * 1024 * 1024];
+
+    let mut group = c.benchmark_group("NsProof");
+    group.sample_size(10);
+    for payload_bytes_len in payload_bytes_len_list {
+        let payload = payload(payload_bytes_len);
+        let index = payload.ns_table().iter().next().unwrap();
+        group.throughput(Throughput::Bytes(payload_bytes_len as u64));
+
+        let advz = VidCommon::V0(
+            advz_scheme(NUM_STORAGE_NODES)
+                .disperse(payload.encode())
+                .unwrap()
+                .common,
+        );
+        let avidm = VidCommon::V1(AvidMParam::new(RECOVERY_THRESHOLD, NUM_STORAGE_NODES).unwrap());
+        for (name, common) in [("ADVZ", advz), ("AvidM", avidm)] {
+            group.bench_with_input(
+                BenchmarkId::new(name, payload_bytes_len),
+                &common,
+                |b, common| b.iter(|| NsProof::new(&payload, &index, common).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, ns_proof_benchmark);
+criterion_main!(benches);
@@ -24,7 +24,7 @@
 use std::{fmt::Debug, path::Path, str::FromStr};
 
 use alloy::primitives::U256;
-use committable::Committable;
+use committable::{Commitment, Committable};
 use hotshot_query_service::{availability::QueryablePayload, testing::mocks::MockVersions};
 use hotshot_types::{
     data::vid_commitment,
@@ -43,13 +43,14 @@ use vbs::{
 };
 
 use crate::{
-    v0_1, v0_2, v0_3, FeeAccount, FeeInfo, Header, L1BlockInfo, NamespaceId, NsTable, Payload,
+    v0_1, v0_2, v0_3, v0_4, v0_99, FeeAccount, FeeInfo, Header, L1BlockInfo, NamespaceId, NsTable, Payload,
     SeqTypes, Transaction, ValidatedState,
 };
 
 type V1Serializer = vbs::Serializer<StaticVersion<0, 1>>;
 type V2Serializer = vbs::Serializer<StaticVersion<0, 2>>;
 type V3Serializer = vbs::Serializer<StaticVersion<0, 3>>;
+type V4Serializer = vbs::Serializer<StaticVersion<0, 4>>;
 type V99Serializer = vbs::Serializer<StaticVersion<0, 99>>;
 
 async fn reference_payload() -> Payload {
@@ -95,8 +96,8 @@ fn reference_l1_block() -> L1BlockInfo {
 
 const REFERENCE_L1_BLOCK_COMMITMENT: &str = "L1BLOCK~4HpzluLK2Isz3RdPNvNrDAyQcWOF2c9JeLZzVNLmfpQ9";
 
-fn reference_chain_config() -> crate::v0_99::ChainConfig {
-    crate::v0_99::ChainConfig {
+fn reference_chain_config() -> v0_4::ChainConfig {
+    v0_4::ChainConfig {
         chain_id: 0x8a19.into(),
         max_block_size: 10240.into(),
         base_fee: 0.into(),
@@ -104,6 +105,21 @@ fn reference_chain_config() -> crate::v0_99::ChainConfig {
         fee_recipient: Default::default(),
         bid_recipient: Some(Default::default()),
         stake_table_contract: Some(Default::default()),
+        max_block_transactions: None,
+        max_namespace_size: None,
+        namespace_registry: None,
+    }
+}
+
+/// A chain config which sets the fields added in v0.4.
+///
+/// The namespace registry is given by an arbitrary commitment, as only its encoding is of interest.
+fn reference_chain_config_with_registry() -> v0_4::ChainConfig {
+    v0_4::ChainConfig {
+        max_block_transactions: Some(1000),
+        max_namespace_size: Some(1024.into()),
+        namespace_registry: Some(Commitment::from_raw([42; 32])),
+        ..reference_chain_config()
     }
 }
 
@@ -116,6 +132,9 @@ const REFERENCE_V2_CHAIN_CONFIG_COMMITMENT: &str =
 const REFERENCE_V3_CHAIN_CONFIG_COMMITMENT: &str =
     "CHAIN_CONFIG~eGc90bEB8zFN4GTo2nForM7pox7r4OiHd2LrtgotiNMO";
 
+const REFERENCE_V4_CHAIN_CONFIG_COMMITMENT: &str =
+    "CHAIN_CONFIG~ucfYQZSMbWCUHdtwYMc6vsw-4jDmlu3hi2lGDBxCRpI-";
+
 const REFERENCE_V99_CHAIN_CONFIG_COMMITMENT: &str =
     "CHAIN_CONFIG~ucfYQZSMbWCUHdtwYMc6vsw-4jDmlu3hi2lGDBxCRpI-";
 
@@ -240,6 +259,7 @@ change in the serialization of this data structure.
         "v1" => V1Serializer::serialize(&reference).unwrap(),
         "v2" => V2Serializer::serialize(&reference).unwrap(),
         "v3" => V3Serializer::serialize(&reference).unwrap(),
+        "v4" => V4Serializer::serialize(&reference).unwrap(),
         "v99" => V99Serializer::serialize(&reference).unwrap(),
         _ => panic!("invalid version"),
     };
@@ -270,6 +290,7 @@ change in the serialization of this data structure.
         "v1" => V1Serializer::deserialize(&expected).unwrap(),
         "v2" => V2Serializer::deserialize(&expected).unwrap(),
         "v3" => V3Serializer::deserialize(&expected).unwrap(),
+        "v4" => V4Serializer::deserialize(&expected).unwrap(),
         "v99" => V99Serializer::deserialize(&expected).unwrap(),
         _ => panic!("invalid version"),
     };
@@ -376,12 +397,31 @@ fn test_reference_v3_chain_config() {
     );
 }
 
+#[test]
+fn test_reference_v4_chain_config() {
+    reference_test(
+        "v4",
+        "chain_config",
+        reference_chain_config(),
+        REFERENCE_V4_CHAIN_CONFIG_COMMITMENT,
+    );
+}
+
+#[test]
+fn test_reference_v4_chain_config_with_registry() {
+    let chain_config = reference_chain_config_with_registry();
+    reference_test_without_committable("v4", "chain_config_with_registry", &chain_config);
+
+    // Unlike the fields which are unset, the fields which are set are committed to.
+    assert_ne!(chain_config.commit(), reference_chain_config().commit());
+}
+
 #[test]
 fn test_reference_v99_chain_config() {
     reference_test(
         "v99",
         "chain_config",
-        reference_chain_config(),
+        v0_99::ChainConfig::from(reference_chain_config()),
         REFERENCE_V99_CHAIN_CONFIG_COMMITMENT,
     );
 }
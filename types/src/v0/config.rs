@@ -1,9 +1,14 @@
 use std::{num::NonZeroUsize, time::Duration};
 
 use hotshot_types::{
+    bandwidth::BandwidthConfig,
+    compression::CompressionConfig,
+    local_builder::LocalBuilderConfig,
     network::{
         BuilderType, CombinedNetworkConfig, Libp2pConfig, NetworkConfig, RandomBuilderConfig,
     },
+    transaction_dedup::TransactionDedupConfig,
+    view_timeout::AdaptiveViewTimeoutConfig,
     HotShotConfig, PeerConfig, ValidatorConfig,
 };
 use serde::{Deserialize, Serialize};
@@ -73,6 +78,20 @@ pub struct PublicHotShotConfig {
     stop_voting_time: u64,
     epoch_height: u64,
     epoch_start_block: u64,
+    #[serde(default)]
+    da_payload_hint_threshold: Option<usize>,
+    #[serde(default)]
+    da_payload_hint_urls: Vec<Url>,
+    #[serde(default)]
+    compression: Option<CompressionConfig>,
+    #[serde(default)]
+    bandwidth: BandwidthConfig,
+    #[serde(default)]
+    local_builder: Option<LocalBuilderConfig>,
+    #[serde(default)]
+    adaptive_view_timeout: Option<AdaptiveViewTimeoutConfig>,
+    #[serde(default)]
+    transaction_dedup: Option<TransactionDedupConfig>,
 }
 
 impl From<HotShotConfig<SeqTypes>> for PublicHotShotConfig {
@@ -103,6 +122,13 @@ impl From<HotShotConfig<SeqTypes>> for PublicHotShotConfig {
             stop_voting_time,
             epoch_height,
             epoch_start_block,
+            da_payload_hint_threshold,
+            da_payload_hint_urls,
+            compression,
+            bandwidth,
+            local_builder,
+            adaptive_view_timeout,
+            transaction_dedup,
         } = v;
 
         Self {
@@ -128,6 +154,13 @@ impl From<HotShotConfig<SeqTypes>> for PublicHotShotConfig {
             stop_voting_time,
             epoch_height,
             epoch_start_block,
+            da_payload_hint_threshold,
+            da_payload_hint_urls,
+            compression,
+            bandwidth,
+            local_builder,
+            adaptive_view_timeout,
+            transaction_dedup,
         }
     }
 }
@@ -157,6 +190,13 @@ impl PublicHotShotConfig {
             stop_voting_time: self.stop_voting_time,
             epoch_height: self.epoch_height,
             epoch_start_block: self.epoch_start_block,
+            da_payload_hint_threshold: self.da_payload_hint_threshold,
+            da_payload_hint_urls: self.da_payload_hint_urls,
+            compression: self.compression,
+            bandwidth: self.bandwidth,
+            local_builder: self.local_builder,
+            adaptive_view_timeout: self.adaptive_view_timeout,
+            transaction_dedup: self.transaction_dedup,
         }
     }
 
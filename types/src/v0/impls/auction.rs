@@ -1,13 +1,14 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use anyhow::Context;
+use async_lock::RwLock;
 use async_trait::async_trait;
 use committable::{Commitment, Committable};
 use hotshot_types::{
-    data::ViewNumber,
+    data::{EpochNumber, ViewNumber},
     traits::{
         auction_results_provider::AuctionResultsProvider,
-        node_implementation::{ConsensusTime, HasUrls, NodeType},
+        node_implementation::{ConsensusTime, HasUrls},
         signature_key::BuilderSignatureKey,
     },
 };
@@ -15,11 +16,11 @@ use thiserror::Error;
 use tide_disco::error::ServerError;
 use url::Url;
 
-use super::{state::ValidatedState, MarketplaceVersion};
+use super::{builder_registry::BuilderRegistry, state::ValidatedState, MarketplaceVersion};
 use crate::{
     eth_signature_key::{EthKeyPair, SigningError},
     v0_99::{BidTx, BidTxBody, FullNetworkTx, SolverAuctionResults},
-    FeeAccount, FeeAmount, FeeError, FeeInfo, NamespaceId,
+    FeeAccount, FeeAmount, FeeError, FeeInfo, NamespaceId, SeqTypes,
 };
 
 impl FullNetworkTx {
@@ -220,7 +221,7 @@ impl BidTx {
         Ok(())
     }
     /// Cryptographic signature verification
-    fn verify(&self) -> Result<(), ExecutionError> {
+    pub fn verify(&self) -> Result<(), ExecutionError> {
         self.body
             .account
             .validate_builder_signature(&self.signature, self.body.commit().as_ref())
@@ -251,6 +252,10 @@ impl BidTx {
     pub fn url(&self) -> Url {
         self.body.url()
     }
+    /// get the namespaces bid for
+    pub fn namespaces(&self) -> &[NamespaceId] {
+        &self.body.namespaces
+    }
 }
 
 impl Committable for SolverAuctionResults {
@@ -343,12 +348,14 @@ impl HasUrls for SolverAuctionResults {
 
 type SurfClient = surf_disco::Client<ServerError, MarketplaceVersion>;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone)]
 /// Auction Results provider holding the Url of the solver in order to fetch auction results.
 pub struct SolverAuctionResultsProvider {
     pub url: Url,
     pub marketplace_path: String,
     pub results_path: String,
+    /// Builders whose bundles the node accepts, applied to the results from the solver
+    pub builder_registry: Arc<RwLock<BuilderRegistry>>,
 }
 
 impl Default for SolverAuctionResultsProvider {
@@ -357,26 +364,28 @@ impl Default for SolverAuctionResultsProvider {
             url: Url::from_str("http://localhost:25000").unwrap(),
             marketplace_path: "marketplace-solver/".into(),
             results_path: "auction_results/".into(),
+            builder_registry: Default::default(),
         }
     }
 }
 
 #[async_trait]
-impl<TYPES: NodeType> AuctionResultsProvider<TYPES> for SolverAuctionResultsProvider {
-    /// Fetch the auction results from the solver.
+impl AuctionResultsProvider<SeqTypes> for SolverAuctionResultsProvider {
+    /// Fetch the auction results from the solver, restricted to the registered builders.
     async fn fetch_auction_result(
         &self,
-        view_number: TYPES::View,
-    ) -> anyhow::Result<TYPES::AuctionResult> {
+        view_number: ViewNumber,
+        epoch: Option<EpochNumber>,
+    ) -> anyhow::Result<SolverAuctionResults> {
         let resp = SurfClient::new(
             self.url
                 .join(&self.marketplace_path)
                 .context("Malformed solver URL")?,
         )
-        .get::<TYPES::AuctionResult>(&format!("{}{}", self.results_path, *view_number))
+        .get::<SolverAuctionResults>(&format!("{}{}", self.results_path, *view_number))
         .send()
         .await?;
-        Ok(resp)
+        Ok(self.builder_registry.read().await.apply(resp, epoch))
     }
 }
 
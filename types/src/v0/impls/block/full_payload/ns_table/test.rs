@@ -130,6 +130,27 @@ async fn payload_byte_len() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn payload_is_well_formed() {
+    setup_test();
+    let test_case = vec![vec![5, 8, 8], vec![7, 9, 11]];
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(test_case, &mut rng);
+    let (mut block, ns_table) =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap();
+    assert!(block.is_well_formed(&ns_table));
+
+    // metadata for a different payload
+    let (_, empty_ns_table) = Payload::empty();
+    assert!(!block.is_well_formed(&empty_ns_table));
+
+    // namespace table inconsistent with the payload
+    *block.ns_table_mut() = empty_ns_table.clone();
+    assert!(!block.is_well_formed(&empty_ns_table));
+}
+
 #[test]
 fn monotonic_increase() {
     setup_test();
@@ -5,7 +5,7 @@ use committable::Committable;
 use hotshot_query_service::availability::QueryablePayload;
 use hotshot_types::{
     data::ViewNumber,
-    traits::{BlockPayload, EncodeBytes},
+    traits::{block_contents::BlockLimits, BlockPayload, EncodeBytes},
     utils::BuilderCommitment,
     vid::advz::{ADVZCommon, ADVZScheme},
 };
@@ -15,7 +15,7 @@ use thiserror::Error;
 
 use crate::{
     v0::impls::{NodeState, ValidatedState},
-    v0_1::ChainConfig,
+    v0_4::ChainConfig,
     Index, Iter, NamespaceId, NsIndex, NsPayload, NsPayloadBuilder, NsPayloadRange, NsTable,
     NsTableBuilder, Payload, PayloadByteLen, SeqTypes, Transaction, TxProof,
 };
@@ -34,6 +34,8 @@ pub enum BlockBuildingError {
     UnexpectedGenesis,
     #[error("ChainConfig is not available")]
     MissingChainConfig(String),
+    #[error("Namespace registry is not available: {0}")]
+    MissingNamespaceRegistry(String),
 }
 
 impl Payload {
@@ -69,21 +71,52 @@ impl Payload {
 
     // PRIVATE HELPERS START HERE
 
+    /// The chain config in effect after `validated_state`, fetching it from peers if necessary.
+    async fn chain_config(
+        validated_state: &ValidatedState,
+        instance_state: &NodeState,
+    ) -> Result<ChainConfig, BlockBuildingError> {
+        let validated_state_cf = validated_state.chain_config;
+        let instance_state_cf = instance_state.chain_config;
+
+        if validated_state_cf.commit() == instance_state_cf.commit() {
+            return Ok(instance_state_cf);
+        }
+        match validated_state_cf.resolve() {
+            Some(cf) => Ok(cf),
+            None => instance_state
+                .peers
+                .as_ref()
+                .fetch_chain_config(validated_state_cf.commit())
+                .await
+                .map_err(|err| BlockBuildingError::MissingChainConfig(format!("{err:#}"))),
+        }
+    }
+
     /// Need a sync version of [`BlockPayload::from_transactions`] in order to impl [`BlockPayload::empty`].
     fn from_transactions_sync(
         transactions: impl IntoIterator<Item = <Self as BlockPayload<SeqTypes>>::Transaction> + Send,
-        chain_config: ChainConfig,
+        limits: &BlockLimits,
     ) -> Result<
         (Self, <Self as BlockPayload<SeqTypes>>::Metadata),
         <Self as BlockPayload<SeqTypes>>::Error,
     > {
         // accounting for block byte length limit
-        let max_block_byte_len = u64::from(chain_config.max_block_size);
+        let max_block_byte_len = limits.max_block_size.unwrap_or(u64::MAX);
         let mut block_byte_len = NsTableBuilder::header_byte_len() as u64;
+        let mut num_txs = 0;
 
         // add each tx to its namespace
         let mut ns_builders = BTreeMap::<NamespaceId, NsPayloadBuilder>::new();
+        let mut ns_byte_lens = BTreeMap::<NamespaceId, u64>::new();
         for tx in transactions.into_iter() {
+            if limits.max_transactions.is_some_and(|max| num_txs >= max) {
+                tracing::warn!(
+                    "transactions truncated to fit in maximum number of transactions {num_txs}"
+                );
+                break;
+            }
+
             let tx_size = tx.size_in_block(!ns_builders.contains_key(&tx.namespace()));
 
             if tx_size > max_block_byte_len {
@@ -94,6 +127,23 @@ impl Payload {
                 continue;
             }
 
+            // accounting for namespace byte length limit
+            let ns_byte_len = ns_byte_lens
+                .get(&tx.namespace())
+                .copied()
+                .unwrap_or(NsPayloadBuilder::tx_table_header_byte_len() as u64)
+                + (tx.payload().len() + NsPayloadBuilder::tx_table_entry_byte_len()) as u64;
+            if let Some(max_ns_byte_len) = limits.max_group_size {
+                if ns_byte_len > max_ns_byte_len {
+                    // skip this transaction; transactions in other namespaces may still fit
+                    tracing::warn!(
+                        "skip the transaction to fit in maximum namespace byte length {max_ns_byte_len}, namespace {}",
+                        tx.namespace()
+                    );
+                    continue;
+                }
+            }
+
             // accounting for block byte length limit
             block_byte_len += tx_size;
             if block_byte_len > max_block_byte_len {
@@ -101,6 +151,8 @@ impl Payload {
                 break;
             }
 
+            ns_byte_lens.insert(tx.namespace(), ns_byte_len);
+            num_txs += 1;
             let ns_builder = ns_builders.entry(tx.namespace()).or_default();
             ns_builder.append_tx(tx);
         }
@@ -139,24 +191,28 @@ impl BlockPayload<SeqTypes> for Payload {
         validated_state: &Self::ValidatedState,
         instance_state: &Self::Instance,
     ) -> Result<(Self, Self::Metadata), Self::Error> {
-        let validated_state_cf = validated_state.chain_config;
-        let instance_state_cf = instance_state.chain_config;
-
-        let chain_config = if validated_state_cf.commit() == instance_state_cf.commit() {
-            instance_state_cf
-        } else {
-            match validated_state_cf.resolve() {
-                Some(cf) => cf,
-                None => instance_state
-                    .peers
-                    .as_ref()
-                    .fetch_chain_config(validated_state_cf.commit())
-                    .await
-                    .map_err(|err| BlockBuildingError::MissingChainConfig(format!("{err:#}")))?,
-            }
-        };
-
-        Self::from_transactions_sync(transactions, ChainConfig::from(chain_config))
+        let chain_config = Self::chain_config(validated_state, instance_state).await?;
+        let registry = instance_state
+            .namespace_registry(&chain_config)
+            .map_err(|err| BlockBuildingError::MissingNamespaceRegistry(err.to_string()))?;
+
+        // Leave out transactions in namespaces the registry would get the block rejected for.
+        let transactions = transactions
+            .into_iter()
+            .filter(|tx| {
+                let Some(registry) = &registry else {
+                    return true;
+                };
+                match registry.admit(tx.namespace()) {
+                    Ok(_) => true,
+                    Err(err) => {
+                        tracing::warn!("skip the transaction: {err}");
+                        false
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        Self::from_transactions_sync(transactions, &chain_config.block_limits())
     }
 
     // TODO avoid cloning the entire payload here?
@@ -168,7 +224,7 @@ impl BlockPayload<SeqTypes> for Payload {
     }
 
     fn empty() -> (Self, Self::Metadata) {
-        let payload = Self::from_transactions_sync(vec![], Default::default())
+        let payload = Self::from_transactions_sync(vec![], &BlockLimits::default())
             .unwrap()
             .0;
 
@@ -195,6 +251,54 @@ impl BlockPayload<SeqTypes> for Payload {
         BuilderCommitment::from_raw_digest(digest.finalize())
     }
 
+    fn is_well_formed(&self, metadata: &Self::Metadata) -> bool {
+        self.ns_table == *metadata && metadata.validate(&self.byte_len()).is_ok()
+    }
+
+    async fn limits(
+        validated_state: &Self::ValidatedState,
+        instance_state: &Self::Instance,
+    ) -> Result<BlockLimits, Self::Error> {
+        Ok(Self::chain_config(validated_state, instance_state)
+            .await?
+            .block_limits())
+    }
+
+    async fn requires_fee(
+        validated_state: &Self::ValidatedState,
+        instance_state: &Self::Instance,
+    ) -> Result<bool, Self::Error> {
+        Ok(Self::chain_config(validated_state, instance_state)
+            .await?
+            .base_fee
+            > 0.into())
+    }
+
+    fn within_limits(&self, ns_table: &Self::Metadata, limits: &BlockLimits) -> bool {
+        // Same accounting as `from_transactions`: the block size includes the namespace table.
+        let byte_len = self.byte_len();
+        let block_byte_len = (byte_len.as_usize() + ns_table.encode().len()) as u64;
+        if limits
+            .max_block_size
+            .is_some_and(|max| block_byte_len > max)
+        {
+            return false;
+        }
+
+        let mut num_txs = 0;
+        for index in ns_table.iter() {
+            let range = ns_table.ns_range(&index, &byte_len);
+            if limits
+                .max_group_size
+                .is_some_and(|max| range.as_block_range().len() as u64 > max)
+            {
+                return false;
+            }
+            num_txs += self.read_ns_payload(&range).iter().count() as u64;
+        }
+        limits.max_transactions.is_none_or(|max| num_txs <= max)
+    }
+
     fn transactions<'a>(
         &'a self,
         metadata: &'a Self::Metadata,
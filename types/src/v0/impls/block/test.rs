@@ -9,7 +9,7 @@ use rand::RngCore;
 use sequencer_utils::test_utils::setup_test;
 
 use crate::{
-    v0_1::ADVZNsProof, v0_99::ChainConfig, BlockSize, NamespaceId, NodeState, Payload, Transaction,
+    v0_1::ADVZNsProof, v0_4::ChainConfig, BlockSize, NamespaceId, NodeState, Payload, Transaction,
     TxProof, ValidatedState,
 };
 
@@ -163,6 +163,83 @@ async fn enforce_max_block_size() {
     assert_eq!(block.len(block.ns_table()), tx_count_expected - 1);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn enforce_block_limits() {
+    setup_test();
+    // Namespace payload byte lengths are 37, 43 and 39, including the tx tables.
+    let test_case = vec![vec![5, 8, 8], vec![7, 9, 11], vec![10, 5, 8]];
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(test_case, &mut rng);
+    let tx_count = test.all_txs().len();
+
+    let build = |chain_config: ChainConfig| {
+        let instance_state = NodeState::default().with_chain_config(chain_config);
+        let validated_state = ValidatedState {
+            chain_config: chain_config.into(),
+            ..Default::default()
+        };
+        let txs = test.all_txs();
+        async move {
+            let limits = Payload::limits(&validated_state, &instance_state)
+                .await
+                .unwrap();
+            let (block, ns_table) =
+                Payload::from_transactions(txs, &validated_state, &instance_state)
+                    .await
+                    .unwrap();
+            assert!(block.within_limits(&ns_table, &limits));
+            (block, limits)
+        }
+    };
+
+    // test: no limits beyond the block size
+    let (full_block, _) = build(ChainConfig::default()).await;
+    assert_eq!(full_block.len(full_block.ns_table()), tx_count);
+
+    // test: one transaction in the 43-byte namespace is skipped, the others all fit
+    let (block, limits) = build(ChainConfig {
+        max_namespace_size: Some(BlockSize::from(39)),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(block.len(block.ns_table()), tx_count - 1);
+    assert!(!full_block.within_limits(full_block.ns_table(), &limits));
+
+    // test: the block is truncated to the maximum number of transactions
+    let (block, limits) = build(ChainConfig {
+        max_block_transactions: Some(4),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(block.len(block.ns_table()), 4);
+    assert!(!full_block.within_limits(full_block.ns_table(), &limits));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requires_fee_with_base_fee() {
+    setup_test();
+    let requires_fee = |chain_config: ChainConfig| async move {
+        let instance_state = NodeState::default().with_chain_config(chain_config);
+        let validated_state = ValidatedState {
+            chain_config: chain_config.into(),
+            ..Default::default()
+        };
+        Payload::requires_fee(&validated_state, &instance_state)
+            .await
+            .unwrap()
+    };
+
+    // Blocks built by the node itself pay no fee, so they are only valid without a base fee.
+    assert!(!requires_fee(ChainConfig::default()).await);
+    assert!(
+        requires_fee(ChainConfig {
+            base_fee: 1.into(),
+            ..Default::default()
+        })
+        .await
+    );
+}
+
 // TODO lots of infra here that could be reused in other tests.
 pub struct ValidTest {
     pub nss: BTreeMap<NamespaceId, Vec<Transaction>>,
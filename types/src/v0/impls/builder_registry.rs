@@ -0,0 +1,253 @@
+//! Registry of the builders a node accepts marketplace bundles from.
+
+use std::collections::HashMap;
+
+use hotshot_types::data::EpochNumber;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{v0_99::SolverAuctionResults, FeeAccount, NamespaceId};
+
+/// A builder registered with the node
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisteredBuilder {
+    /// Account whose key signs the builder's bids and bundles
+    pub account: FeeAccount,
+    /// URL the leader requests bundles from
+    pub url: Url,
+    /// Namespaces the builder serves
+    pub namespaces: Vec<NamespaceId>,
+    /// Whether the builder takes turns, one epoch at a time, holding the reserve slot of the
+    /// namespaces it serves
+    #[serde(default)]
+    pub reserve: bool,
+    /// Maximum number of namespaces the builder may win in a single auction, unlimited if absent
+    #[serde(default)]
+    pub quota: Option<usize>,
+}
+
+/// Registry of the builders the node accepts marketplace bundles from.
+///
+/// An empty registry places no restrictions on the auction results from the solver.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuilderRegistry {
+    /// The registered builders
+    #[serde(default)]
+    pub builders: Vec<RegisteredBuilder>,
+}
+
+impl BuilderRegistry {
+    /// Whether no builders are registered
+    pub fn is_empty(&self) -> bool {
+        self.builders.is_empty()
+    }
+
+    /// The registered builder with the given account
+    pub fn builder(&self, account: FeeAccount) -> Option<&RegisteredBuilder> {
+        self.builders
+            .iter()
+            .find(|builder| builder.account == account)
+    }
+
+    /// The builder holding the reserve slot for `namespace` in `epoch`.
+    ///
+    /// The reserve builders serving a namespace take turns holding its reserve slot, in the order
+    /// they are registered, for an epoch each. Before epochs, the first of them holds it.
+    pub fn reserve_builder(
+        &self,
+        namespace: NamespaceId,
+        epoch: Option<EpochNumber>,
+    ) -> Option<&RegisteredBuilder> {
+        let candidates = self
+            .builders
+            .iter()
+            .filter(|builder| builder.reserve && builder.namespaces.contains(&namespace))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None;
+        }
+        let turn = epoch.map_or(0, |epoch| *epoch % (candidates.len() as u64));
+        Some(candidates[turn as usize])
+    }
+
+    /// Restrict auction results for a view in `epoch` to the registered builders.
+    ///
+    /// Winning bids are dropped if the builder is not registered, if they cover namespaces the
+    /// builder does not serve, or if they would take the builder over its quota. The namespaces of
+    /// dropped bids fall back to their reserve slot. Each reserve slot goes to the reserve builder
+    /// whose turn it is, or else stays with the reserve builder chosen by the solver.
+    pub fn apply(
+        &self,
+        results: SolverAuctionResults,
+        epoch: Option<EpochNumber>,
+    ) -> SolverAuctionResults {
+        if self.is_empty() {
+            return results;
+        }
+        let view = results.view_number;
+
+        let mut won = HashMap::<FeeAccount, usize>::new();
+        let mut winning_bids = vec![];
+        let mut reserve_slots = results
+            .reserve_bids
+            .into_iter()
+            .map(|(namespace, url)| (namespace, Some(url)))
+            .collect::<Vec<_>>();
+        for bid in results.winning_bids {
+            let account = bid.account();
+            let namespaces = bid.namespaces();
+            let accepted = self.builder(account).is_some_and(|builder| {
+                let won = won.entry(account).or_default();
+                let serves = namespaces
+                    .iter()
+                    .all(|namespace| builder.namespaces.contains(namespace));
+                let within_quota = builder
+                    .quota
+                    .is_none_or(|quota| *won + namespaces.len() <= quota);
+                if serves && within_quota {
+                    *won += namespaces.len();
+                }
+                serves && within_quota
+            });
+            if accepted {
+                winning_bids.push(bid);
+            } else {
+                tracing::info!(%account, ?namespaces, "winning bid rejected by builder registry");
+                reserve_slots.extend(namespaces.iter().map(|namespace| (*namespace, None)));
+            }
+        }
+
+        let reserve_bids = reserve_slots
+            .into_iter()
+            .filter_map(|(namespace, url)| {
+                let url = self
+                    .reserve_builder(namespace, epoch)
+                    .map(|builder| builder.url.clone())
+                    .or(url)?;
+                Some((namespace, url))
+            })
+            .collect();
+
+        SolverAuctionResults::new(view, winning_bids, reserve_bids)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::{
+        data::ViewNumber,
+        traits::{node_implementation::ConsensusTime, signature_key::BuilderSignatureKey},
+    };
+
+    use super::*;
+    use crate::{v0_99::BidTxBody, FeeAmount};
+
+    fn url(s: &str) -> Url {
+        s.parse().unwrap()
+    }
+
+    fn builder(index: u64, namespaces: &[u64]) -> RegisteredBuilder {
+        RegisteredBuilder {
+            account: FeeAccount::generated_from_seed_indexed([1; 32], index).0,
+            url: url(&format!("http://builder{index}")),
+            namespaces: namespaces.iter().copied().map(NamespaceId::from).collect(),
+            reserve: false,
+            quota: None,
+        }
+    }
+
+    fn bid(index: u64, namespaces: &[u64]) -> crate::v0_99::BidTx {
+        let (account, key) = FeeAccount::generated_from_seed_indexed([1; 32], index);
+        BidTxBody::new(
+            account,
+            FeeAmount::from(1u64),
+            ViewNumber::new(1),
+            namespaces.iter().copied().map(NamespaceId::from).collect(),
+            url(&format!("http://builder{index}")),
+            FeeAmount::default(),
+        )
+        .signed(&key)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reserve_rotation() {
+        let registry = BuilderRegistry {
+            builders: vec![
+                RegisteredBuilder {
+                    reserve: true,
+                    ..builder(0, &[1, 2])
+                },
+                builder(1, &[1]),
+                RegisteredBuilder {
+                    reserve: true,
+                    ..builder(2, &[1])
+                },
+            ],
+        };
+        let reserve = |namespace: u64, epoch: Option<u64>| {
+            registry
+                .reserve_builder(NamespaceId::from(namespace), epoch.map(EpochNumber::new))
+                .map(|builder| builder.url.clone())
+        };
+
+        assert_eq!(reserve(1, None), Some(url("http://builder0")));
+        assert_eq!(reserve(1, Some(0)), Some(url("http://builder0")));
+        assert_eq!(reserve(1, Some(1)), Some(url("http://builder2")));
+        assert_eq!(reserve(1, Some(2)), Some(url("http://builder0")));
+        assert_eq!(reserve(2, Some(1)), Some(url("http://builder0")));
+        assert_eq!(reserve(3, Some(0)), None);
+    }
+
+    #[test]
+    fn test_apply_registry() {
+        let registry = BuilderRegistry {
+            builders: vec![
+                RegisteredBuilder {
+                    quota: Some(2),
+                    ..builder(0, &[1, 2, 3])
+                },
+                builder(1, &[4]),
+                RegisteredBuilder {
+                    reserve: true,
+                    ..builder(2, &[3, 4, 5])
+                },
+            ],
+        };
+        let results = SolverAuctionResults::new(
+            ViewNumber::new(1),
+            vec![
+                // accepted
+                bid(0, &[1, 2]),
+                // over quota
+                bid(0, &[3]),
+                // namespace not served
+                bid(1, &[5]),
+                // not registered
+                bid(3, &[6]),
+            ],
+            vec![
+                (NamespaceId::from(4u64), url("http://solver-reserve")),
+                (NamespaceId::from(7u64), url("http://solver-reserve")),
+            ],
+        );
+
+        let results = registry.apply(results, Some(EpochNumber::new(1)));
+        assert_eq!(results.winning_bids(), &[bid(0, &[1, 2])]);
+        assert_eq!(
+            results.reserve_bids(),
+            &[
+                (NamespaceId::from(4u64), url("http://builder2")),
+                (NamespaceId::from(7u64), url("http://solver-reserve")),
+                (NamespaceId::from(3u64), url("http://builder2")),
+                (NamespaceId::from(5u64), url("http://builder2")),
+            ]
+        );
+
+        // An empty registry changes nothing.
+        let unrestricted = BuilderRegistry::default().apply(results.clone(), None);
+        assert_eq!(unrestricted, results);
+    }
+}
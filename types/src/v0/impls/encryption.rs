@@ -0,0 +1,741 @@
+//! Threshold encryption of transactions.
+//!
+//! To protect rollup users from front-running, a transaction can be submitted encrypted to a key
+//! held jointly by the DA committee, so that its contents stay hidden until after it has been
+//! sequenced. The namespace of an encrypted transaction is public, and its payload is an encoded
+//! [`EncryptedPayload`] in place of the rollup's own data.
+//!
+//! The scheme is TDH2 (Shoup and Gennaro, "Securing threshold cryptosystems against chosen
+//! ciphertext attack") over the BN254 G1 group, used as a hybrid scheme. The secret key is
+//! Shamir-shared among the committee, and any [`threshold`](ThresholdEncryptionKey::threshold) of
+//! the shares suffice to decrypt. Every ciphertext carries a proof that its author knows the
+//! ephemeral secret, bound to the ciphertext and to its namespace as the label, so a ciphertext
+//! cannot be mauled into another one, or replayed into another namespace, which the committee
+//! would then decrypt. Committee members only publish a [`DecryptionShare`] for a valid ciphertext,
+//! and each share carries a proof that it was computed with the key share of its index, so nobody
+//! has to trust the node a share came from.
+
+use std::{collections::BTreeMap, fmt, str::FromStr, sync::OnceLock};
+
+use anyhow::{ensure, Context};
+use ark_bn254::{Fq, Fr, G1Affine, G1Projective};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, One, PrimeField, UniformRand, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use itertools::Itertools;
+use jf_utils::canonical;
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use tagged_base64::TaggedBase64;
+use thiserror::Error;
+
+use crate::{NamespaceId, PubKey};
+
+/// Prefix distinguishing the payload of an encrypted transaction from a plaintext payload
+pub const ENCRYPTED_PAYLOAD_PREFIX: &[u8] = b"ESPRESSO-ENCRYPTED-V2:";
+
+/// Tag of the string encoding of a [`KeyShare`]
+const KEY_SHARE_TAG: &str = "ENCSHARE";
+
+/// Context for deriving the second generator of the group
+const GENERATOR_CONTEXT: &str = "espresso-network threshold encryption 2025 second generator";
+
+/// Context for deriving the encryption key from the shared secret
+const ENCRYPTION_KEY_CONTEXT: &str = "espresso-network threshold encryption 2025 encryption key";
+
+/// Context for the challenge of the proof carried by a ciphertext
+const CIPHERTEXT_CONTEXT: &str = "espresso-network threshold encryption 2025 ciphertext proof";
+
+/// Context for the challenge of the proof carried by a decryption share
+const SHARE_CONTEXT: &str = "espresso-network threshold encryption 2025 decryption share proof";
+
+/// Why an encrypted payload could not be decrypted
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum DecryptionError {
+    #[error("{got} distinct valid decryption shares given, {threshold} needed")]
+    NotEnoughShares { got: usize, threshold: usize },
+    #[error("invalid ciphertext")]
+    InvalidCiphertext,
+}
+
+/// The public parameters of the committee's threshold encryption key
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdEncryptionKey {
+    /// Number of decryption shares needed to decrypt a payload
+    pub threshold: usize,
+    /// The key transactions are encrypted to
+    #[serde(with = "canonical")]
+    pub public_key: G1Affine,
+    /// Public key shares, used to verify decryption shares.
+    ///
+    /// The `i`th public key share belongs to the key share with index `i + 1`.
+    #[serde(with = "canonical")]
+    pub public_shares: Vec<G1Affine>,
+    /// Staking keys of the nodes holding the key shares, in the same order as `public_shares`.
+    ///
+    /// Decryption shares are only accepted from the holder of the key share they were computed
+    /// with.
+    pub holders: Vec<PubKey>,
+}
+
+impl ThresholdEncryptionKey {
+    /// Generate a key and split it into a share for each of `holders`, any `threshold` of which
+    /// can decrypt.
+    ///
+    /// This is a trusted dealer setup: whoever runs it learns the whole secret key, and must
+    /// discard it after handing out the shares.
+    ///
+    /// # Panics
+    /// If `threshold` is zero or greater than the number of holders.
+    pub fn generate(
+        threshold: usize,
+        holders: Vec<PubKey>,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> (Self, Vec<KeyShare>) {
+        let num_shares = holders.len();
+        assert!(
+            threshold > 0 && threshold <= num_shares,
+            "threshold {threshold} out of range for {num_shares} shares"
+        );
+
+        // The secret key is the constant term of a random polynomial of degree `threshold - 1`,
+        // and each share is the polynomial evaluated at the share's index.
+        let coefficients = (0..threshold).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+        let shares = (1..=num_shares as u32)
+            .map(|index| KeyShare {
+                index,
+                secret: evaluate(&coefficients, Fr::from(index)),
+            })
+            .collect::<Vec<_>>();
+
+        let key = Self {
+            threshold,
+            public_key: (G1Affine::generator() * coefficients[0]).into_affine(),
+            public_shares: shares
+                .iter()
+                .map(|share| (G1Affine::generator() * share.secret).into_affine())
+                .collect(),
+            holders,
+        };
+        (key, shares)
+    }
+
+    /// Check that the public key shares are shares of the public key, with this threshold.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let num_shares = self.public_shares.len();
+        ensure!(
+            self.threshold > 0 && self.threshold <= num_shares,
+            "threshold {} out of range for {num_shares} shares",
+            self.threshold
+        );
+        ensure!(
+            self.holders.len() == num_shares,
+            "{} holders given for {num_shares} key shares",
+            self.holders.len()
+        );
+        ensure!(
+            self.holders.iter().all_unique(),
+            "a node holds more than one key share"
+        );
+
+        // Any `threshold` public key shares determine the polynomial in the exponent. Every other
+        // share must lie on it, and at 0 it must give the public key.
+        let basis = (1..=self.threshold as u32).collect::<Vec<_>>();
+        let interpolate = |at: Fr| -> G1Projective {
+            basis
+                .iter()
+                .zip(lagrange_coefficients(&basis, at))
+                .map(|(index, coefficient)| self.public_shares[*index as usize - 1] * coefficient)
+                .sum()
+        };
+        for index in self.threshold + 1..=num_shares {
+            ensure!(
+                interpolate(Fr::from(index as u64)).into_affine() == self.public_shares[index - 1],
+                "public key share {index} is inconsistent with the others"
+            );
+        }
+        ensure!(
+            interpolate(Fr::zero()).into_affine() == self.public_key,
+            "public key does not match the public key shares"
+        );
+        Ok(())
+    }
+
+    /// The index of the key share held by the node with staking key `holder`, if any
+    pub fn holder_index(&self, holder: &PubKey) -> Option<u32> {
+        let i = self.holders.iter().position(|key| key == holder)?;
+        Some(i as u32 + 1)
+    }
+
+    /// Check that `share` is a valid decryption share of `payload`.
+    pub fn verify_share(&self, payload: &EncryptedPayload, share: &DecryptionShare) -> bool {
+        let Some(public_share) = (share.index as usize)
+            .checked_sub(1)
+            .and_then(|i| self.public_shares.get(i))
+        else {
+            return false;
+        };
+
+        // Recompute the commitments of the proof that the share and the public key share have the
+        // same discrete logarithm, with respect to the ephemeral key and the generator.
+        let share_commitment = payload.ephemeral * share.response - share.share * share.challenge;
+        let key_commitment =
+            G1Affine::generator() * share.response - *public_share * share.challenge;
+        share.challenge
+            == share_challenge(
+                share.index,
+                &payload.ephemeral,
+                &share.share,
+                &share_commitment.into_affine(),
+                &key_commitment.into_affine(),
+            )
+    }
+
+    /// Decrypt a payload submitted in `namespace`, using the first `threshold` distinct valid
+    /// shares.
+    ///
+    /// # Errors
+    /// If the ciphertext is invalid, or there are not enough valid shares.
+    pub fn decrypt(
+        &self,
+        namespace: NamespaceId,
+        payload: &EncryptedPayload,
+        shares: &[DecryptionShare],
+    ) -> Result<Vec<u8>, DecryptionError> {
+        if !payload.verify(namespace) {
+            return Err(DecryptionError::InvalidCiphertext);
+        }
+        let shares = shares
+            .iter()
+            .filter(|share| self.verify_share(payload, share))
+            .map(|share| (share.index, share.share))
+            .collect::<BTreeMap<_, _>>();
+        if shares.len() < self.threshold {
+            return Err(DecryptionError::NotEnoughShares {
+                got: shares.len(),
+                threshold: self.threshold,
+            });
+        }
+        let (indices, shares): (Vec<_>, Vec<_>) = shares.into_iter().take(self.threshold).unzip();
+        let secret: G1Projective = shares
+            .into_iter()
+            .zip(lagrange_coefficients(&indices, Fr::zero()))
+            .map(|(share, coefficient)| share * coefficient)
+            .sum();
+        Ok(payload.open(&secret.into_affine()))
+    }
+}
+
+/// A committee member's share of the secret key
+#[derive(Clone, PartialEq, Eq)]
+pub struct KeyShare {
+    index: u32,
+    secret: Fr,
+}
+
+impl KeyShare {
+    /// The index of this share, starting from 1
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// This share's contribution to decrypting `payload`, submitted in `namespace`.
+    ///
+    /// Returns `None` if the ciphertext is invalid, in which case it must not be decrypted.
+    pub fn decryption_share(
+        &self,
+        namespace: NamespaceId,
+        payload: &EncryptedPayload,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Option<DecryptionShare> {
+        if !payload.verify(namespace) {
+            return None;
+        }
+
+        let share = (payload.ephemeral * self.secret).into_affine();
+        let nonce = Fr::rand(rng);
+        let challenge = share_challenge(
+            self.index,
+            &payload.ephemeral,
+            &share,
+            &(payload.ephemeral * nonce).into_affine(),
+            &(G1Affine::generator() * nonce).into_affine(),
+        );
+        Some(DecryptionShare {
+            index: self.index,
+            share,
+            challenge,
+            response: nonce + self.secret * challenge,
+        })
+    }
+}
+
+impl fmt::Debug for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyShare")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for KeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut bytes = self.index.to_le_bytes().to_vec();
+        self.secret
+            .serialize_compressed(&mut bytes)
+            .map_err(|_| fmt::Error)?;
+        let tb64 = TaggedBase64::new(KEY_SHARE_TAG, &bytes).map_err(|_| fmt::Error)?;
+        write!(f, "{tb64}")
+    }
+}
+
+impl FromStr for KeyShare {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let tb64 = TaggedBase64::parse(s)?;
+        ensure!(
+            tb64.tag() == KEY_SHARE_TAG,
+            "wrong tag {} for key share",
+            tb64.tag()
+        );
+        let bytes = tb64.value();
+        let (index, secret) = bytes
+            .split_first_chunk::<4>()
+            .context("key share too short")?;
+        let index = u32::from_le_bytes(*index);
+        ensure!(index > 0, "key share index must be positive");
+        let secret = Fr::deserialize_compressed(secret).context("malformed key share")?;
+        Ok(Self { index, secret })
+    }
+}
+
+/// A committee member's contribution to decrypting a payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecryptionShare {
+    /// The index of the key share this was computed with
+    pub index: u32,
+    #[serde(with = "canonical")]
+    pub share: G1Affine,
+    /// Challenge of the proof that the share was computed with the key share of `index`
+    #[serde(with = "canonical")]
+    pub challenge: Fr,
+    /// Response of the proof that the share was computed with the key share of `index`
+    #[serde(with = "canonical")]
+    pub response: Fr,
+}
+
+/// The payload of an encrypted transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedPayload {
+    /// `g^r` for the ephemeral secret `r`
+    #[serde(with = "canonical")]
+    ephemeral: G1Affine,
+    /// `ḡ^r`, for the second generator `ḡ`
+    #[serde(with = "canonical")]
+    ephemeral_bar: G1Affine,
+    /// Challenge of the proof that `ephemeral` and `ephemeral_bar` have the same discrete
+    /// logarithm, which binds the ciphertext and its namespace
+    #[serde(with = "canonical")]
+    challenge: Fr,
+    /// Response of the proof
+    #[serde(with = "canonical")]
+    response: Fr,
+    ciphertext: Vec<u8>,
+}
+
+impl EncryptedPayload {
+    /// Encrypt `plaintext` for submission in `namespace`.
+    ///
+    /// The namespace is the label of the ciphertext, so the payload cannot be replayed into a
+    /// different namespace.
+    pub fn encrypt(
+        key: &ThresholdEncryptionKey,
+        namespace: NamespaceId,
+        plaintext: &[u8],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Self {
+        let r = Fr::rand(rng);
+        let s = Fr::rand(rng);
+        let ephemeral = (G1Affine::generator() * r).into_affine();
+        let ephemeral_bar = (second_generator() * r).into_affine();
+        let secret = (key.public_key * r).into_affine();
+
+        let mut ciphertext = plaintext.to_vec();
+        apply_keystream(&derive_key(&ephemeral, &secret), &mut ciphertext);
+        let challenge = ciphertext_challenge(
+            namespace,
+            &ciphertext,
+            &ephemeral,
+            &(G1Affine::generator() * s).into_affine(),
+            &ephemeral_bar,
+            &(second_generator() * s).into_affine(),
+        );
+        Self {
+            ephemeral,
+            ephemeral_bar,
+            challenge,
+            response: s + r * challenge,
+            ciphertext,
+        }
+    }
+
+    /// Check that this is a well-formed ciphertext submitted in `namespace`.
+    ///
+    /// Only valid ciphertexts may be decrypted: anyone can take the ephemeral key of another
+    /// ciphertext, but only its author can prove knowledge of its secret for a new ciphertext.
+    pub fn verify(&self, namespace: NamespaceId) -> bool {
+        let commitment = G1Affine::generator() * self.response - self.ephemeral * self.challenge;
+        let commitment_bar =
+            second_generator() * self.response - self.ephemeral_bar * self.challenge;
+        self.challenge
+            == ciphertext_challenge(
+                namespace,
+                &self.ciphertext,
+                &self.ephemeral,
+                &commitment.into_affine(),
+                &self.ephemeral_bar,
+                &commitment_bar.into_affine(),
+            )
+    }
+
+    /// Decrypt with the shared secret recovered from the decryption shares
+    fn open(&self, secret: &G1Affine) -> Vec<u8> {
+        let mut plaintext = self.ciphertext.clone();
+        apply_keystream(&derive_key(&self.ephemeral, secret), &mut plaintext);
+        plaintext
+    }
+
+    /// Encode as a transaction payload
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = ENCRYPTED_PAYLOAD_PREFIX.to_vec();
+        bytes.extend(bincode::serialize(self).expect("encrypted payload is serializable"));
+        bytes
+    }
+
+    /// Decode a transaction payload, if it is an encrypted payload
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let bytes = payload.strip_prefix(ENCRYPTED_PAYLOAD_PREFIX)?;
+        bincode::deserialize(bytes).ok()
+    }
+}
+
+/// The second generator of G1 used by ciphertext proofs, whose discrete logarithm with respect to
+/// the standard generator is unknown.
+fn second_generator() -> G1Affine {
+    static GENERATOR: OnceLock<G1Affine> = OnceLock::new();
+    *GENERATOR.get_or_init(|| {
+        // Hash to a point by try-and-increment. G1 has cofactor 1, so any point on the curve is in
+        // the group.
+        (0u64..)
+            .find_map(|counter| {
+                let mut bytes = [0; 64];
+                blake3::Hasher::new_derive_key(GENERATOR_CONTEXT)
+                    .update(&counter.to_le_bytes())
+                    .finalize_xof()
+                    .fill(&mut bytes);
+                G1Affine::get_point_from_x_unchecked(Fq::from_le_bytes_mod_order(&bytes), false)
+            })
+            .expect("half of all x coordinates are on the curve")
+    })
+}
+
+/// A hash of group elements and byte strings to a scalar, for Fiat-Shamir challenges
+struct Transcript(blake3::Hasher);
+
+impl Transcript {
+    fn new(context: &str) -> Self {
+        Self(blake3::Hasher::new_derive_key(context))
+    }
+
+    fn bytes(mut self, bytes: &[u8]) -> Self {
+        self.0.update(&(bytes.len() as u64).to_le_bytes());
+        self.0.update(bytes);
+        self
+    }
+
+    fn point(self, point: &G1Affine) -> Self {
+        let mut bytes = vec![];
+        point
+            .serialize_compressed(&mut bytes)
+            .expect("serializing to a vector cannot fail");
+        self.bytes(&bytes)
+    }
+
+    fn challenge(self) -> Fr {
+        let mut bytes = [0; 64];
+        self.0.finalize_xof().fill(&mut bytes);
+        Fr::from_le_bytes_mod_order(&bytes)
+    }
+}
+
+/// Challenge of the proof carried by a ciphertext, binding the ciphertext and its label
+fn ciphertext_challenge(
+    namespace: NamespaceId,
+    ciphertext: &[u8],
+    ephemeral: &G1Affine,
+    commitment: &G1Affine,
+    ephemeral_bar: &G1Affine,
+    commitment_bar: &G1Affine,
+) -> Fr {
+    Transcript::new(CIPHERTEXT_CONTEXT)
+        .bytes(ciphertext)
+        .bytes(&u64::from(namespace).to_le_bytes())
+        .point(ephemeral)
+        .point(commitment)
+        .point(ephemeral_bar)
+        .point(commitment_bar)
+        .challenge()
+}
+
+/// Challenge of the proof carried by a decryption share
+fn share_challenge(
+    index: u32,
+    ephemeral: &G1Affine,
+    share: &G1Affine,
+    share_commitment: &G1Affine,
+    key_commitment: &G1Affine,
+) -> Fr {
+    Transcript::new(SHARE_CONTEXT)
+        .bytes(&index.to_le_bytes())
+        .point(ephemeral)
+        .point(share)
+        .point(share_commitment)
+        .point(key_commitment)
+        .challenge()
+}
+
+/// Derive the encryption key from the shared secret
+fn derive_key(ephemeral: &G1Affine, secret: &G1Affine) -> [u8; 32] {
+    let mut material = vec![];
+    ephemeral
+        .serialize_compressed(&mut material)
+        .expect("serializing to a vector cannot fail");
+    secret
+        .serialize_compressed(&mut material)
+        .expect("serializing to a vector cannot fail");
+    blake3::derive_key(ENCRYPTION_KEY_CONTEXT, &material)
+}
+
+/// XOR `data` with the keystream for `key`.
+///
+/// Each key is derived from a fresh ephemeral secret, so is used for a single message and needs no
+/// nonce.
+fn apply_keystream(key: &[u8; 32], data: &mut [u8]) {
+    let mut keystream = vec![0; data.len()];
+    blake3::Hasher::new_keyed(key)
+        .finalize_xof()
+        .fill(&mut keystream);
+    for (byte, key_byte) in data.iter_mut().zip(keystream) {
+        *byte ^= key_byte;
+    }
+}
+
+/// Evaluate the polynomial with the given coefficients, constant term first, at `x`
+fn evaluate(coefficients: &[Fr], x: Fr) -> Fr {
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fr::zero(), |acc, coefficient| acc * x + coefficient)
+}
+
+/// Coefficients for interpolating at `at` a polynomial known at the distinct nonzero `indices`
+fn lagrange_coefficients(indices: &[u32], at: Fr) -> Vec<Fr> {
+    indices
+        .iter()
+        .map(|&i| {
+            let xi = Fr::from(i);
+            let (numerator, denominator) = indices.iter().filter(|&&j| j != i).fold(
+                (Fr::one(), Fr::one()),
+                |(numerator, denominator), &j| {
+                    let xj = Fr::from(j);
+                    (numerator * (at - xj), denominator * (xi - xj))
+                },
+            );
+            numerator
+                * denominator
+                    .inverse()
+                    .expect("indices are distinct, so the denominator is nonzero")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::traits::signature_key::SignatureKey;
+    use jf_utils::test_rng;
+
+    use super::*;
+
+    fn holders(n: u64) -> Vec<PubKey> {
+        (0..n)
+            .map(|i| PubKey::generated_from_seed_indexed([0; 32], i).0)
+            .collect()
+    }
+
+    #[test]
+    fn test_threshold_decryption() {
+        let mut rng = test_rng();
+        let (key, key_shares) = ThresholdEncryptionKey::generate(3, holders(5), &mut rng);
+        key.validate().unwrap();
+
+        let namespace = NamespaceId::from(42u64);
+        let plaintext = b"transfer 10 to bob".to_vec();
+        let payload = EncryptedPayload::encrypt(&key, namespace, &plaintext, &mut rng);
+        assert!(payload.verify(namespace));
+        assert_eq!(
+            EncryptedPayload::decode(&payload.encode()).as_ref(),
+            Some(&payload)
+        );
+        assert_eq!(EncryptedPayload::decode(&plaintext), None);
+
+        let shares = key_shares
+            .iter()
+            .map(|key_share| {
+                key_share
+                    .decryption_share(namespace, &payload, &mut rng)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for share in &shares {
+            assert!(key.verify_share(&payload, share));
+        }
+
+        // Any `threshold` shares decrypt.
+        let some_shares = [shares[4], shares[0], shares[2]];
+        assert_eq!(
+            key.decrypt(namespace, &payload, &some_shares).unwrap(),
+            plaintext
+        );
+        assert_eq!(
+            key.decrypt(namespace, &payload, &shares).unwrap(),
+            plaintext
+        );
+
+        // Fewer do not, even if some are repeated.
+        assert_eq!(
+            key.decrypt(namespace, &payload, &[shares[0], shares[1], shares[1]]),
+            Err(DecryptionError::NotEnoughShares {
+                got: 2,
+                threshold: 3
+            })
+        );
+
+        // The payload is bound to its namespace.
+        let other_namespace = NamespaceId::from(43u64);
+        assert!(!payload.verify(other_namespace));
+        assert!(key_shares[0]
+            .decryption_share(other_namespace, &payload, &mut rng)
+            .is_none());
+        assert_eq!(
+            key.decrypt(other_namespace, &payload, &some_shares),
+            Err(DecryptionError::InvalidCiphertext)
+        );
+
+        // A share of another payload, or a tampered share, is detected and not used.
+        let other = EncryptedPayload::encrypt(&key, namespace, &plaintext, &mut rng);
+        let bad_share = key_shares[1]
+            .decryption_share(namespace, &other, &mut rng)
+            .unwrap();
+        assert!(!key.verify_share(&payload, &bad_share));
+        let mut tampered = shares[1];
+        tampered.share = (tampered.share * Fr::from(2u64)).into_affine();
+        assert!(!key.verify_share(&payload, &tampered));
+        let mut wrong_index = shares[1];
+        wrong_index.index = 4;
+        assert!(!key.verify_share(&payload, &wrong_index));
+        assert_eq!(
+            key.decrypt(
+                namespace,
+                &payload,
+                &[shares[0], bad_share, tampered, shares[2]]
+            ),
+            Err(DecryptionError::NotEnoughShares {
+                got: 2,
+                threshold: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_mauled_ciphertext() {
+        let mut rng = test_rng();
+        let (key, key_shares) = ThresholdEncryptionKey::generate(2, holders(3), &mut rng);
+        let namespace = NamespaceId::from(42u64);
+        let victim = EncryptedPayload::encrypt(&key, namespace, b"victim's secret", &mut rng);
+
+        // Flipping bits of the ciphertext invalidates it.
+        let mut flipped = victim.clone();
+        flipped.ciphertext[0] ^= 1;
+        assert!(!flipped.verify(namespace));
+
+        // So does reusing the victim's ephemeral key in a ciphertext of the attacker's own, even
+        // with a valid proof for the rest of it.
+        let mut mauled = EncryptedPayload::encrypt(&key, namespace, b"attacker", &mut rng);
+        mauled.ephemeral = victim.ephemeral;
+        assert!(!mauled.verify(namespace));
+
+        // The committee refuses to help decrypt it.
+        for key_share in &key_shares {
+            assert!(key_share
+                .decryption_share(namespace, &mauled, &mut rng)
+                .is_none());
+            assert!(key_share
+                .decryption_share(namespace, &flipped, &mut rng)
+                .is_none());
+        }
+        let victim_shares = key_shares
+            .iter()
+            .map(|key_share| {
+                key_share
+                    .decryption_share(namespace, &victim, &mut rng)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            key.decrypt(namespace, &mauled, &victim_shares),
+            Err(DecryptionError::InvalidCiphertext)
+        );
+    }
+
+    #[test]
+    fn test_validate_key() {
+        let mut rng = test_rng();
+        let (key, _) = ThresholdEncryptionKey::generate(2, holders(4), &mut rng);
+        key.validate().unwrap();
+        assert_eq!(key.holder_index(&key.holders[2]), Some(3));
+        assert_eq!(key.holder_index(&holders(5)[4]), None);
+
+        let (other, _) = ThresholdEncryptionKey::generate(2, holders(4), &mut rng);
+        let mut bad = key.clone();
+        bad.public_shares[3] = other.public_shares[3];
+        bad.validate().unwrap_err();
+
+        let mut bad = key.clone();
+        bad.public_key = other.public_key;
+        bad.validate().unwrap_err();
+
+        let mut bad = key.clone();
+        bad.holders.pop();
+        bad.validate().unwrap_err();
+
+        let mut bad = key.clone();
+        bad.holders[1] = bad.holders[0];
+        bad.validate().unwrap_err();
+
+        let mut bad = key;
+        bad.threshold = 5;
+        bad.validate().unwrap_err();
+    }
+
+    #[test]
+    fn test_key_share_encoding() {
+        let (_, key_shares) = ThresholdEncryptionKey::generate(1, holders(2), &mut test_rng());
+        for key_share in key_shares {
+            let encoded = key_share.to_string();
+            assert_eq!(encoded.parse::<KeyShare>().unwrap(), key_share);
+        }
+        "ENCSHARE~AAAA".parse::<KeyShare>().unwrap_err();
+    }
+}
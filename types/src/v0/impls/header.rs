@@ -40,7 +40,8 @@ use crate::{
         MarketplaceVersion,
     },
     v0_1, v0_2, v0_3,
-    v0_99::{self, ChainConfig, IterableFeeInfo, SolverAuctionResults},
+    v0_4::{self, ChainConfig},
+    v0_99::{self, IterableFeeInfo, SolverAuctionResults},
     BlockMerkleCommitment, EpochVersion, FeeAccount, FeeAmount, FeeInfo, FeeMerkleCommitment,
     Header, L1BlockInfo, L1Snapshot, Leaf2, NamespaceId, NsTable, SeqTypes, UpgradeType,
 };
@@ -88,6 +89,11 @@ impl Committable for Header {
                 .u64_field("version_minor", 3)
                 .field("fields", fields.commit())
                 .finalize(),
+            Self::V4(fields) => RawCommitmentBuilder::new(&Self::tag())
+                .u64_field("version_major", 0)
+                .u64_field("version_minor", 4)
+                .field("fields", fields.commit())
+                .finalize(),
             Self::V99(fields) => RawCommitmentBuilder::new(&Self::tag())
                 .u64_field("version_major", 0)
                 .u64_field("version_minor", 3)
@@ -120,6 +126,11 @@ impl Serialize for Header {
                 fields: fields.clone(),
             }
             .serialize(serializer),
+            Self::V4(fields) => VersionedHeader {
+                version: EitherOrVersion::Version(Version { major: 0, minor: 4 }),
+                fields: fields.clone(),
+            }
+            .serialize(serializer),
             Self::V99(fields) => VersionedHeader {
                 version: EitherOrVersion::Version(Version {
                     major: 0,
@@ -173,6 +184,10 @@ impl<'de> Deserialize<'de> for Header {
                         seq.next_element()?
                             .ok_or_else(|| de::Error::missing_field("fields"))?,
                     )),
+                    EitherOrVersion::Version(Version { major: 0, minor: 4 }) => Ok(Header::V4(
+                        seq.next_element()?
+                            .ok_or_else(|| de::Error::missing_field("fields"))?,
+                    )),
                     EitherOrVersion::Version(Version {
                         major: 0,
                         minor: 99,
@@ -211,6 +226,9 @@ impl<'de> Deserialize<'de> for Header {
                         EitherOrVersion::Version(Version { major: 0, minor: 3 }) => Ok(Header::V3(
                             serde_json::from_value(fields.clone()).map_err(de::Error::custom)?,
                         )),
+                        EitherOrVersion::Version(Version { major: 0, minor: 4 }) => Ok(Header::V4(
+                            serde_json::from_value(fields.clone()).map_err(de::Error::custom)?,
+                        )),
                         EitherOrVersion::Version(Version {
                             major: 0,
                             minor: 99,
@@ -274,6 +292,7 @@ impl Header {
             Self::V1(_) => Version { major: 0, minor: 1 },
             Self::V2(_) => Version { major: 0, minor: 2 },
             Self::V3(_) => Version { major: 0, minor: 3 },
+            Self::V4(_) => Version { major: 0, minor: 4 },
             Self::V99(_) => Version {
                 major: 0,
                 minor: 99,
@@ -354,9 +373,26 @@ impl Header {
                 builder_signature: builder_signature.first().copied(),
                 reward_merkle_tree_root: reward_merkle_tree_root.unwrap(),
             }),
+            4 => Self::V4(v0_4::Header {
+                chain_config: v0_4::ResolvableChainConfig::from(chain_config),
+                height,
+                timestamp,
+                l1_head,
+                l1_finalized,
+                payload_commitment,
+                builder_commitment,
+                ns_table,
+                block_merkle_tree_root,
+                fee_merkle_tree_root,
+                fee_info: fee_info[0], // NOTE this is asserted to exist above
+                builder_signature: builder_signature.first().copied(),
+                reward_merkle_tree_root: reward_merkle_tree_root.unwrap(),
+            }),
 
             99 => Self::V99(v0_99::Header {
-                chain_config: v0_99::ResolvableChainConfig::from(chain_config),
+                chain_config: v0_99::ResolvableChainConfig::from(v0_99::ChainConfig::from(
+                    chain_config,
+                )),
                 height,
                 timestamp,
                 l1_head,
@@ -385,6 +421,7 @@ macro_rules! field {
             Self::V1(data) => &data.$name,
             Self::V2(data) => &data.$name,
             Self::V3(data) => &data.$name,
+            Self::V4(data) => &data.$name,
             Self::V99(data) => &data.$name,
         }
     };
@@ -396,6 +433,7 @@ macro_rules! field_mut {
             Self::V1(data) => &mut data.$name,
             Self::V2(data) => &mut data.$name,
             Self::V3(data) => &mut data.$name,
+            Self::V4(data) => &mut data.$name,
             Self::V99(data) => &mut data.$name,
         }
     };
@@ -588,7 +626,7 @@ impl Header {
                 fee_info: fee_info[0],
                 builder_signature: builder_signature.first().copied(),
             }),
-            99 => Self::V99(v0_99::Header {
+            4 => Self::V4(v0_4::Header {
                 chain_config: chain_config.into(),
                 height,
                 timestamp,
@@ -599,6 +637,21 @@ impl Header {
                 ns_table,
                 block_merkle_tree_root,
                 fee_merkle_tree_root,
+                reward_merkle_tree_root: state.reward_merkle_tree.commitment(),
+                fee_info: fee_info[0],
+                builder_signature: builder_signature.first().copied(),
+            }),
+            99 => Self::V99(v0_99::Header {
+                chain_config: v0_99::ChainConfig::from(chain_config).into(),
+                height,
+                timestamp,
+                l1_head: l1.head,
+                l1_finalized: l1.finalized,
+                payload_commitment,
+                builder_commitment,
+                ns_table,
+                block_merkle_tree_root,
+                fee_merkle_tree_root,
                 fee_info,
                 builder_signature,
                 auction_results: auction_results.unwrap(),
@@ -639,12 +692,13 @@ impl Header {
 
 impl Header {
     /// A commitment to a ChainConfig or a full ChainConfig.
-    pub fn chain_config(&self) -> v0_99::ResolvableChainConfig {
+    pub fn chain_config(&self) -> v0_4::ResolvableChainConfig {
         match self {
-            Self::V1(fields) => v0_99::ResolvableChainConfig::from(&fields.chain_config),
-            Self::V2(fields) => v0_99::ResolvableChainConfig::from(&fields.chain_config),
-            Self::V3(fields) => v0_99::ResolvableChainConfig::from(&fields.chain_config),
-            Self::V99(fields) => fields.chain_config,
+            Self::V1(fields) => v0_4::ResolvableChainConfig::from(&fields.chain_config),
+            Self::V2(fields) => v0_4::ResolvableChainConfig::from(&fields.chain_config),
+            Self::V3(fields) => v0_4::ResolvableChainConfig::from(&fields.chain_config),
+            Self::V4(fields) => fields.chain_config,
+            Self::V99(fields) => v0_4::ResolvableChainConfig::from(&fields.chain_config),
         }
     }
 
@@ -762,6 +816,7 @@ impl Header {
             Self::V1(fields) => vec![fields.fee_info],
             Self::V2(fields) => vec![fields.fee_info],
             Self::V3(fields) => vec![fields.fee_info],
+            Self::V4(fields) => vec![fields.fee_info],
             Self::V99(fields) => fields.fee_info.clone(),
         }
     }
@@ -773,6 +828,7 @@ impl Header {
             Self::V1(_) => empty_reward_merkle_tree.commitment(),
             Self::V2(_) => empty_reward_merkle_tree.commitment(),
             Self::V3(fields) => fields.reward_merkle_tree_root,
+            Self::V4(fields) => fields.reward_merkle_tree_root,
             // TODO: add reward commitment to v99
             Self::V99(_) => empty_reward_merkle_tree.commitment(),
         }
@@ -795,6 +851,7 @@ impl Header {
             Self::V1(fields) => fields.builder_signature.as_slice().to_vec(),
             Self::V2(fields) => fields.builder_signature.as_slice().to_vec(),
             Self::V3(fields) => fields.builder_signature.as_slice().to_vec(),
+            Self::V4(fields) => fields.builder_signature.as_slice().to_vec(),
             Self::V99(fields) => fields.builder_signature.clone(),
         }
     }
@@ -826,6 +883,7 @@ impl BlockHeader<SeqTypes> for Header {
             Self::V1(_) => None,
             Self::V2(_) => None,
             Self::V3(_) => None,
+            Self::V4(_) => None,
             Self::V99(fields) => Some(fields.auction_results.clone()),
         }
     }
@@ -892,8 +950,7 @@ impl BlockHeader<SeqTypes> for Header {
             (chain_config.fee_contract, l1_snapshot.finalized)
         {
             instance_state
-                .l1_client
-                .get_finalized_deposits(
+                .finalized_deposits(
                     addr,
                     parent_leaf
                         .block_header()
@@ -1011,6 +1068,7 @@ impl BlockHeader<SeqTypes> for Header {
                 Some(upgrade) => match upgrade.upgrade_type {
                     UpgradeType::Fee { chain_config } => chain_config,
                     UpgradeType::Epoch { chain_config } => chain_config,
+                    UpgradeType::BlockLimits { chain_config } => chain_config,
                     _ => Header::get_chain_config(&validated_state, instance_state).await?,
                 },
                 None => Header::get_chain_config(&validated_state, instance_state).await?,
@@ -1028,8 +1086,7 @@ impl BlockHeader<SeqTypes> for Header {
             (chain_config.fee_contract, l1_snapshot.finalized)
         {
             instance_state
-                .l1_client
-                .get_finalized_deposits(
+                .finalized_deposits(
                     addr,
                     parent_leaf
                         .block_header()
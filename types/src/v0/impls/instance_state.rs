@@ -1,8 +1,12 @@
-use std::{collections::BTreeMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 #[cfg(any(test, feature = "testing"))]
 use async_lock::RwLock;
 use async_trait::async_trait;
+use committable::{Commitment, Committable};
 use hotshot::types::BLSPubKey;
 use hotshot_types::{
     data::EpochNumber, epoch_membership::EpochMembershipCoordinator, traits::states::InstanceState,
@@ -17,15 +21,15 @@ use super::{
     state::ValidatedState,
     traits::MembershipPersistence,
     v0_1::NoStorage,
-    v0_3::{EventKey, IndexedStake, StakeTableEvent, Validator},
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
     SeqTypes,
 };
 use crate::v0::{
-    traits::StateCatchup, v0_99::ChainConfig, GenesisHeader, L1BlockInfo, L1Client, Timestamp,
-    Upgrade, UpgradeMode,
+    traits::StateCatchup, v0_4::ChainConfig, GenesisHeader, L1BlockInfo, L1Client,
+    NamespaceRegistry, NamespaceRegistryError, Timestamp, Upgrade, UpgradeMode,
 };
 #[cfg(any(test, feature = "testing"))]
-use crate::EpochCommittees;
+use crate::{EpochCommittees, FeeInfo};
 
 /// Represents the immutable state of a node.
 ///
@@ -33,7 +37,7 @@ use crate::EpochCommittees;
 #[derive(derive_more::Debug, Clone)]
 pub struct NodeState {
     pub node_id: u64,
-    pub chain_config: crate::v0_99::ChainConfig,
+    pub chain_config: crate::v0_4::ChainConfig,
     pub l1_client: L1Client,
     #[debug("{}", peers.name())]
     pub peers: Arc<dyn StateCatchup>,
@@ -60,6 +64,8 @@ pub struct NodeState {
     /// to use in functions such as genesis.
     /// (example: genesis returns V2 Header if version is 0.2)
     pub current_version: Version,
+    /// The namespace registries chain configs may commit to, by commitment.
+    pub namespace_registries: HashMap<Commitment<NamespaceRegistry>, Arc<NamespaceRegistry>>,
 }
 
 #[async_trait]
@@ -93,9 +99,115 @@ impl MembershipPersistence for NoStorage {
     async fn load_events(&self) -> anyhow::Result<Option<(u64, Vec<(EventKey, StakeTableEvent)>)>> {
         Ok(None)
     }
+
+    async fn store_events_with_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+        _l1_block: u64,
+        _events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn store_l1_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+        _checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn load_l1_checkpoint(
+        &self,
+        _contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>> {
+        Ok(None)
+    }
 }
 
 impl NodeState {
+    /// Get the fee deposits finalized on L1 after `prev_finalized`, up to `new_finalized`.
+    ///
+    /// How far the fee contract has been scanned is checkpointed, as long as the scan is ahead of
+    /// the last checkpoint. Deposits are always scanned between the L1 blocks finalized by
+    /// consecutive headers, so the checkpoint is not used to skip any.
+    pub async fn finalized_deposits(
+        &self,
+        fee_contract: alloy::primitives::Address,
+        prev_finalized: Option<u64>,
+        new_finalized: u64,
+    ) -> Vec<FeeInfo> {
+        let (deposits, checkpoint) = self
+            .l1_client
+            .scan_finalized_deposits(fee_contract, prev_finalized, new_finalized)
+            .await;
+        if let Some(checkpoint) = checkpoint {
+            let persistence = self
+                .coordinator
+                .membership()
+                .read()
+                .await
+                .fetcher()
+                .persistence
+                .clone();
+            let res = async {
+                let last = persistence.load_l1_checkpoint(fee_contract).await?;
+                if last.is_none_or(|last| last.l1_block < checkpoint.l1_block) {
+                    persistence
+                        .store_l1_checkpoint(fee_contract, checkpoint)
+                        .await?;
+                }
+                anyhow::Ok(())
+            };
+            if let Err(err) = res.await {
+                tracing::warn!(%fee_contract, "failed to store fee contract checkpoint: {err:#}");
+            }
+        }
+        deposits
+    }
+
+    /// Roll back the state scanned from L1 blocks which are replaced by reorgs, as they happen.
+    pub async fn roll_back_l1_reorgs(self) {
+        loop {
+            let fork = self.l1_client.wait_for_reorg().await;
+            self.roll_back_l1(fork).await;
+        }
+    }
+
+    /// Roll back the stake table events and fee deposit checkpoint scanned from L1 blocks at or
+    /// above `fork`, so that they are scanned again from the new chain.
+    pub async fn roll_back_l1(&self, fork: u64) {
+        let fetcher = self.coordinator.membership().read().await.fetcher().clone();
+        if let Some(contract) = self.chain_config.stake_table_contract {
+            if let Err(err) = fetcher.roll_back_events(contract, fork).await {
+                tracing::warn!(%contract, fork, "failed to roll back stake table events: {err:#}");
+            }
+        }
+        if let Some(fee_contract) = self.chain_config.fee_contract {
+            let res = async {
+                let Some(checkpoint) = fetcher.persistence.load_l1_checkpoint(fee_contract).await?
+                else {
+                    return anyhow::Ok(());
+                };
+                if checkpoint.l1_block >= fork {
+                    tracing::warn!(%fee_contract, fork, ?checkpoint, "rolling back fee deposits");
+                    let checkpoint = L1EventCheckpoint {
+                        l1_block: fork.saturating_sub(1),
+                        last_event: checkpoint.last_event.filter(|(block, _)| *block < fork),
+                    };
+                    fetcher
+                        .persistence
+                        .store_l1_checkpoint(fee_contract, checkpoint)
+                        .await?;
+                }
+                Ok(())
+            };
+            if let Err(err) = res.await {
+                tracing::warn!(%fee_contract, fork, "failed to roll back fee deposits: {err:#}");
+            }
+        }
+    }
+
     pub fn new(
         node_id: u64,
         chain_config: ChainConfig,
@@ -119,6 +231,7 @@ impl NodeState {
             current_version,
             epoch_height: None,
             coordinator,
+            namespace_registries: Default::default(),
         }
     }
 
@@ -262,6 +375,30 @@ impl NodeState {
         self.epoch_height = Some(epoch_height);
         self
     }
+
+    pub fn with_namespace_registry(mut self, registry: NamespaceRegistry) -> Self {
+        self.namespace_registries
+            .insert(registry.commit(), Arc::new(registry));
+        self
+    }
+
+    /// The namespace registry `chain_config` commits to, if any.
+    ///
+    /// # Errors
+    /// If `chain_config` commits to a registry this node is not configured with.
+    pub fn namespace_registry(
+        &self,
+        chain_config: &ChainConfig,
+    ) -> Result<Option<Arc<NamespaceRegistry>>, NamespaceRegistryError> {
+        let Some(commitment) = chain_config.namespace_registry else {
+            return Ok(None);
+        };
+        self.namespace_registries
+            .get(&commitment)
+            .cloned()
+            .map(Some)
+            .ok_or(NamespaceRegistryError::Unknown(commitment))
+    }
 }
 
 // This allows us to turn on `Default` on InstanceState trait
@@ -15,7 +15,7 @@ use alloy::{
     rpc::{
         client::RpcClient,
         json_rpc::{RequestPacket, ResponsePacket},
-        types::Block,
+        types::{Block, Header},
     },
     transports::{http::Http, RpcError, TransportErrorKind},
 };
@@ -25,7 +25,7 @@ use clap::Parser;
 use committable::{Commitment, Committable, RawCommitmentBuilder};
 use futures::{
     future::{Future, TryFuture, TryFutureExt},
-    stream::{self, StreamExt},
+    stream::{self, FuturesUnordered, StreamExt},
 };
 use hotshot_contract_adapter::sol_types::FeeContract;
 use hotshot_types::traits::metrics::Metrics;
@@ -41,8 +41,12 @@ use tracing::Instrument;
 use url::Url;
 
 use super::{
-    v0_1::{L1BlockInfoWithParent, SingleTransport, SingleTransportStatus, SwitchingTransport},
-    L1BlockInfo, L1ClientMetrics, L1State, L1UpdateTask,
+    v0_1::{
+        L1BlockInfoWithParent, ProviderHealth, SingleTransport, SingleTransportStatus,
+        SwitchingTransport,
+    },
+    v0_3::L1EventCheckpoint,
+    L1BlockInfo, L1ClientMetrics, L1ReadMode, L1State, L1UpdateTask,
 };
 use crate::{FeeInfo, L1Client, L1ClientOptions, L1Event, L1Snapshot};
 
@@ -77,6 +81,19 @@ impl From<&Block> for L1BlockInfoWithParent {
     }
 }
 
+impl From<&Header> for L1BlockInfoWithParent {
+    fn from(header: &Header) -> Self {
+        Self {
+            info: L1BlockInfo {
+                number: header.number,
+                timestamp: U256::from(header.timestamp),
+                hash: header.hash,
+            },
+            parent_hash: header.parent_hash,
+        }
+    }
+}
+
 impl Committable for L1BlockInfo {
     fn commit(&self) -> Commitment<Self> {
         let timestamp: [u8; 32] = self.timestamp.to_le_bytes();
@@ -156,14 +173,36 @@ impl L1ClientMetrics {
             failure_metrics.push(failures.create(vec![url_index.to_string()]));
         }
 
+        let requests = metrics.counter_family("requests".into(), vec!["provider".into()]);
+        let request_metrics = (0..num_urls)
+            .map(|url_index| requests.create(vec![url_index.to_string()]))
+            .collect();
+        let health = metrics.gauge_family("provider_health".into(), vec!["provider".into()]);
+        let health_metrics = (0..num_urls)
+            .map(|url_index| health.create(vec![url_index.to_string()]))
+            .collect();
+
         Self {
             head: metrics.create_gauge("head".into(), None).into(),
             finalized: metrics.create_gauge("finalized".into(), None).into(),
             reconnects: metrics
                 .create_counter("stream_reconnects".into(), None)
                 .into(),
+            reorgs: metrics.create_counter("reorgs".into(), None).into(),
+            reorg_depth: metrics.create_gauge("last_reorg_depth".into(), None).into(),
             failovers: metrics.create_counter("failovers".into(), None).into(),
             failures: Arc::new(failure_metrics),
+            requests: Arc::new(request_metrics),
+            health: Arc::new(health_metrics),
+            stake_table_scan_lag: metrics
+                .create_gauge("stake_table_scan_lag".into(), Some("blocks".into()))
+                .into(),
+            fee_scan_lag: metrics
+                .create_gauge("fee_scan_lag".into(), Some("blocks".into()))
+                .into(),
+            quorum_failures: metrics
+                .create_counter("quorum_failures".into(), None)
+                .into(),
         }
     }
 }
@@ -182,15 +221,217 @@ impl SwitchingTransport {
         // Create a new `SingleTransport` for the first URL
         let first_transport = Arc::new(RwLock::new(SingleTransport::new(&first_url, 0)));
 
+        let providers = urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| SingleTransport::new(url, index))
+            .collect();
+        let health = urls
+            .iter()
+            .map(|_| RwLock::new(ProviderHealth::default()))
+            .collect();
+
         Ok(Self {
             urls: Arc::new(urls),
             current_transport: first_transport,
             opt: Arc::new(opt),
             metrics,
             switch_notify: Arc::new(Notify::new()),
+            providers: Arc::new(providers),
+            health: Arc::new(health),
         })
     }
 
+    /// Record the outcome of a request to the provider at `index`.
+    fn log_outcome(&self, index: usize, success: bool) {
+        let score = {
+            let mut health = self.health[index].write();
+            health.record(success);
+            health.score
+        };
+        self.metrics.requests[index].add(1);
+        self.metrics.health[index].set((score * 100.0) as usize);
+    }
+
+    /// The indices of the `n` healthiest providers, healthiest first.
+    fn healthiest(&self, n: usize) -> Vec<usize> {
+        let scores = self
+            .health
+            .iter()
+            .map(|health| health.read().score)
+            .collect::<Vec<_>>();
+        let mut indices = (0..self.urls.len()).collect::<Vec<_>>();
+        // Stable sort, so ties go to the providers listed first.
+        indices.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+        indices.truncate(n.max(1));
+        indices
+    }
+
+    /// Send a read to several providers at once, responding according to the read mode.
+    async fn fan_out(
+        self,
+        req: RequestPacket,
+    ) -> Result<ResponsePacket, RpcError<TransportErrorKind>> {
+        let indices = self.healthiest(self.opt.l1_read_fanout);
+        let quorum = if self.opt.l1_read_mode == L1ReadMode::Quorum && is_pinned_read(&req) {
+            self.opt
+                .l1_read_quorum
+                .unwrap_or(indices.len() / 2 + 1)
+                .min(indices.len())
+        } else {
+            1
+        };
+
+        // The current provider is called through the current transport, so that its failures
+        // count towards failing over to the next one.
+        let current = self.current_transport.read().clone();
+        let current_index = current.generation % self.urls.len();
+        let mut responses = indices
+            .into_iter()
+            .map(|index| {
+                let transport = if index == current_index {
+                    current.clone()
+                } else {
+                    self.providers[index].clone()
+                };
+                let this = self.clone();
+                let req = req.clone();
+                async move { (index, this.call_transport(transport, req).await) }
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        // Distinct successful results, and how many providers gave each one.
+        let mut tallies: Vec<(Vec<String>, usize)> = vec![];
+        let mut fallback = None;
+        while let Some((index, res)) = responses.next().await {
+            let res = match res {
+                Ok(res) => res,
+                Err(err) => {
+                    tracing::debug!(provider = index, "L1 provider did not respond to read");
+                    fallback.get_or_insert(Err(err));
+                    continue;
+                },
+            };
+            if quorum == 1 {
+                return Ok(res);
+            }
+
+            // Error responses never count towards a quorum, but are returned if nothing does.
+            let Some(results) = response_results(&res) else {
+                fallback = Some(Ok(res));
+                continue;
+            };
+            let count = match tallies.iter_mut().find(|(seen, _)| *seen == results) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                },
+                None => {
+                    tallies.push((results, 1));
+                    1
+                },
+            };
+            if count >= quorum {
+                return Ok(res);
+            }
+        }
+
+        self.metrics.quorum_failures.add(1);
+        if tallies.is_empty() {
+            if let Some(res) = fallback {
+                return res;
+            }
+        }
+        tracing::warn!(quorum, responses = tallies.len(), "L1 providers disagree");
+        Err(RpcError::Transport(TransportErrorKind::Custom(
+            "L1 providers did not reach a quorum".into(),
+        )))
+    }
+
+    /// Send `req` through `transport`, honoring its rate limit and recording the outcome.
+    ///
+    /// Fails over to the next provider if `transport` is the current one and has failed too often.
+    async fn call_transport(
+        self,
+        mut transport: SingleTransport,
+        req: RequestPacket,
+    ) -> Result<ResponsePacket, RpcError<TransportErrorKind>> {
+        let index = transport.generation % self.urls.len();
+
+        // If we've been rate limited, back off until the limit (hopefully) expires.
+        if let Some(t) = transport.status.read().rate_limited_until {
+            if t > Instant::now() {
+                // Return an error with a non-standard code to indicate client-side rate limit.
+                return Err(RpcError::Transport(TransportErrorKind::Custom(
+                    "Rate limit exceeded".into(),
+                )));
+            } else {
+                // Reset the rate limit if we are passed it so we don't check every time
+                transport.status.write().rate_limited_until = None;
+            }
+        }
+
+        // Call the inner client, match on the result
+        match transport.client.call(req).await {
+            Ok(res) => {
+                // If it's okay, log the success to the status
+                transport.status.write().log_success();
+                self.log_outcome(index, true);
+                Ok(res)
+            },
+            Err(err) => {
+                self.log_outcome(index, false);
+
+                // Increment the failure metric
+                if let Some(f) = self.metrics.failures.get(index) {
+                    f.add(1);
+                }
+
+                // Treat rate limited errors specially; these should not cause failover, but instead
+                // should only cause us to temporarily back off on making requests to the RPC
+                // server.
+                if let RpcError::ErrorResp(e) = &err {
+                    // 429 == Too Many Requests
+                    if e.code == 429 {
+                        transport.status.write().rate_limited_until =
+                            Some(Instant::now() + self.opt.rate_limit_delay());
+                        return Err(err);
+                    }
+                }
+
+                // Log the error and indicate a failure
+                tracing::warn!(provider = index, ?err, "L1 client error");
+
+                // If the transport should switch, do so. We don't need to worry about
+                // race conditions here, since it will only return true once. Only the current
+                // transport is switched; others are just read from alongside it.
+                let is_current =
+                    Arc::ptr_eq(&transport.status, &self.current_transport.read().status);
+                if is_current && transport.status.write().log_failure(&self.opt) {
+                    // Increment the failovers metric
+                    self.metrics.failovers.add(1);
+
+                    // Calculate the next URL index
+                    let next_gen = transport.generation + 1;
+                    let next_index = next_gen % self.urls.len();
+                    let url = self.urls[next_index].clone();
+                    tracing::info!(%url, "failing over to next L1 transport");
+
+                    // Create a new transport from the next URL and index
+                    let new_transport = SingleTransport::new(&url, next_gen);
+
+                    // Switch to the next URL
+                    *self.current_transport.write() = new_transport;
+
+                    // Notify the transport that it has been switched
+                    self.switch_notify.notify_waiters();
+                }
+
+                Err(err)
+            },
+        }
+    }
+
     /// Returns when the transport has been switched
     async fn wait_switch(&self) {
         self.switch_notify.notified().await;
@@ -205,6 +446,61 @@ impl SwitchingTransport {
     }
 }
 
+/// Whether a request can be sent to any number of providers.
+///
+/// Filters are kept by the provider which created them, and transactions should only be sent once.
+fn is_stateless_read(req: &RequestPacket) -> bool {
+    req.method_names()
+        .all(|method| !method.starts_with("eth_send") && !method.contains("Filter"))
+}
+
+/// Whether a read is pinned to specific blocks, so that all providers should agree on the result.
+///
+/// Reads of the head of the chain, or of current fees, can legitimately differ between providers
+/// which are a few moments apart.
+fn is_pinned_read(req: &RequestPacket) -> bool {
+    const TAGS: [&str; 4] = ["\"latest\"", "\"pending\"", "\"safe\"", "\"finalized\""];
+    const MOVING: [&str; 5] = [
+        "eth_blockNumber",
+        "eth_gasPrice",
+        "eth_maxPriorityFeePerGas",
+        "eth_feeHistory",
+        "eth_blobBaseFee",
+    ];
+    req.requests().iter().all(|req| {
+        let params = req.params().map(|params| params.get()).unwrap_or_default();
+        !MOVING.contains(&req.method()) && !TAGS.iter().any(|tag| params.contains(tag))
+    })
+}
+
+/// The results of each request in a response, if they are all successful.
+fn response_results(res: &ResponsePacket) -> Option<Vec<String>> {
+    let responses = match res {
+        ResponsePacket::Single(res) => std::slice::from_ref(res),
+        ResponsePacket::Batch(res) => res.as_slice(),
+    };
+    responses
+        .iter()
+        .map(|res| res.payload.as_success().map(|raw| raw.get().to_string()))
+        .collect()
+}
+
+impl Default for ProviderHealth {
+    fn default() -> Self {
+        Self { score: 1.0 }
+    }
+}
+
+impl ProviderHealth {
+    /// Weight of the latest request in the moving average.
+    const WEIGHT: f64 = 0.1;
+
+    fn record(&mut self, success: bool) {
+        let outcome = if success { 1.0 } else { 0.0 };
+        self.score += Self::WEIGHT * (outcome - self.score);
+    }
+}
+
 impl SingleTransportStatus {
     /// Log a successful call to the inner transport
     fn log_success(&mut self) {
@@ -286,85 +582,18 @@ impl Service<RequestPacket> for SwitchingTransport {
         // Clone ourselves
         let self_clone = self.clone();
 
+        if self.opt.l1_read_mode != L1ReadMode::Failover
+            && self.urls.len() > 1
+            && is_stateless_read(&req)
+        {
+            return Box::pin(self_clone.fan_out(req));
+        }
+
         // Pin and box, which turns this into a future
         Box::pin(async move {
             // Clone the current transport
-            let mut current_transport = self_clone.current_transport.read().clone();
-
-            // If we've been rate limited, back off until the limit (hopefully) expires.
-            if let Some(t) = current_transport.status.read().rate_limited_until {
-                if t > Instant::now() {
-                    // Return an error with a non-standard code to indicate client-side rate limit.
-                    return Err(RpcError::Transport(TransportErrorKind::Custom(
-                        "Rate limit exceeded".into(),
-                    )));
-                } else {
-                    // Reset the rate limit if we are passed it so we don't check every time
-                    current_transport.status.write().rate_limited_until = None;
-                }
-            }
-
-            // Call the inner client, match on the result
-            match current_transport.client.call(req).await {
-                Ok(res) => {
-                    // If it's okay, log the success to the status
-                    current_transport.status.write().log_success();
-                    Ok(res)
-                },
-                Err(err) => {
-                    // Increment the failure metric
-                    if let Some(f) = self_clone
-                        .metrics
-                        .failures
-                        .get(current_transport.generation % self_clone.urls.len())
-                    {
-                        f.add(1);
-                    }
-
-                    // Treat rate limited errors specially; these should not cause failover, but instead
-                    // should only cause us to temporarily back off on making requests to the RPC
-                    // server.
-                    if let RpcError::ErrorResp(e) = &err {
-                        // 429 == Too Many Requests
-                        if e.code == 429 {
-                            current_transport.status.write().rate_limited_until =
-                                Some(Instant::now() + self_clone.opt.rate_limit_delay());
-                            return Err(err);
-                        }
-                    }
-
-                    // Log the error and indicate a failure
-                    tracing::warn!(?err, "L1 client error");
-
-                    // If the transport should switch, do so. We don't need to worry about
-                    // race conditions here, since it will only return true once.
-                    if current_transport
-                        .status
-                        .write()
-                        .log_failure(&self_clone.opt)
-                    {
-                        // Increment the failovers metric
-                        self_clone.metrics.failovers.add(1);
-
-                        // Calculate the next URL index
-                        let next_gen = current_transport.generation + 1;
-                        let next_index = next_gen % self_clone.urls.len();
-                        let url = self_clone.urls[next_index].clone();
-                        tracing::info!(%url, "failing over to next L1 transport");
-
-                        // Create a new transport from the next URL and index
-                        let new_transport = SingleTransport::new(&url, next_gen);
-
-                        // Switch to the next URL
-                        *self_clone.current_transport.write() = new_transport;
-
-                        // Notify the transport that it has been switched
-                        self_clone.switch_notify.notify_waiters();
-                    }
-
-                    Err(err)
-                },
-            }
+            let current_transport = self_clone.current_transport.read().clone();
+            self_clone.call_transport(current_transport, req).await
         })
     }
 }
@@ -530,9 +759,32 @@ impl L1Client {
                     match block_timeout {
                         // We got a block
                         Ok(Some(head)) => {
+                            let block = L1BlockInfoWithParent::from(&head);
                             let head = head.number;
                             tracing::debug!(head, "Received L1 block");
 
+                            // Check the new head against the recent chain, to catch reorgs.
+                            let chain = fetch_new_chain(&rpc, &state, block, retry_delay).await;
+                            let reorg = {
+                                let mut state = state.lock().await;
+                                let reorg = state.put_head(&chain);
+                                if reorg.is_some() {
+                                    // The new chain may be shorter than the one it replaces.
+                                    state.snapshot.head = head;
+                                    metrics.head.set(head as usize);
+                                }
+                                reorg
+                            };
+                            if let Some((fork, depth)) = reorg {
+                                tracing::warn!(fork, depth, ?block, "L1 reorg detected");
+                                metrics.reorgs.add(1);
+                                metrics.reorg_depth.set(depth as usize);
+                                sender
+                                    .broadcast_direct(L1Event::Reorg { fork, depth, head: block.info })
+                                    .await
+                                    .ok();
+                            }
+
                             // A new block has been produced. This happens fairly rarely, so it is now ok to
                             // poll to see if a new block has been finalized.
                             let finalized = loop {
@@ -599,6 +851,25 @@ impl L1Client {
         self.state.lock().await.snapshot
     }
 
+    /// Wait for the next L1 reorg, returning the lowest height it replaced.
+    ///
+    /// Anything derived from L1 blocks at or above this height may no longer reflect the canonical
+    /// chain, and should be rolled back and derived again.
+    pub async fn wait_for_reorg(&self) -> u64 {
+        loop {
+            let mut events = self.receiver.activate_cloned();
+            while let Some(event) = events.next().await {
+                if let L1Event::Reorg { fork, .. } = event {
+                    return fork;
+                }
+            }
+
+            // This should not happen: the event stream ended. All we can do is try again.
+            tracing::warn!("L1 event stream ended unexpectedly; retry");
+            self.retry_delay().await;
+        }
+    }
+
     /// Wait until the highest L1 block number reaches at least `number`.
     ///
     /// This function does not return any information about the block, since the block is not
@@ -827,10 +1098,23 @@ impl L1Client {
         prev_finalized: Option<u64>,
         new_finalized: u64,
     ) -> Vec<FeeInfo> {
+        self.scan_finalized_deposits(fee_contract_address, prev_finalized, new_finalized)
+            .await
+            .0
+    }
+
+    /// Get fee info for each `Deposit` occurring between `prev` and `new`, along with the
+    /// checkpoint of the scan, if any blocks were scanned.
+    pub async fn scan_finalized_deposits(
+        &self,
+        fee_contract_address: Address,
+        prev_finalized: Option<u64>,
+        new_finalized: u64,
+    ) -> (Vec<FeeInfo>, Option<L1EventCheckpoint>) {
         // No new blocks have been finalized, therefore there are no
         // new deposits.
         if prev_finalized >= Some(new_finalized) {
-            return vec![];
+            return (vec![], None);
         }
 
         let opt = self.options();
@@ -881,11 +1165,21 @@ impl L1Client {
                 }
             }
         });
-        events
-            .flatten()
+        let events = events.flatten().collect::<Vec<_>>().await;
+        let checkpoint = L1EventCheckpoint {
+            l1_block: new_finalized,
+            last_event: events
+                .last()
+                .and_then(|(_, log)| Some((log.block_number?, log.log_index?))),
+        };
+        let deposits = events
+            .into_iter()
             .map(|(deposit, _)| FeeInfo::from(deposit))
-            .collect()
-            .await
+            .collect();
+        self.metrics()
+            .fee_scan_lag
+            .set(self.scan_lag(new_finalized).await);
+        (deposits, Some(checkpoint))
     }
 
     /// Check if the given address is a proxy contract.
@@ -935,10 +1229,15 @@ impl L1Client {
         self.transport.options()
     }
 
-    fn metrics(&self) -> &L1ClientMetrics {
+    pub(crate) fn metrics(&self) -> &L1ClientMetrics {
         self.transport.metrics()
     }
 
+    /// How many blocks behind the L1 head a scan for events up to `l1_block` is.
+    pub(crate) async fn scan_lag(&self, l1_block: u64) -> usize {
+        self.snapshot().await.head.saturating_sub(l1_block) as usize
+    }
+
     async fn retry_delay(&self) {
         sleep(self.options().l1_retry_delay).await;
     }
@@ -949,9 +1248,92 @@ impl L1State {
         Self {
             snapshot: Default::default(),
             finalized: LruCache::new(cache_size),
+            recent: Default::default(),
+            recent_capacity: cache_size.get(),
         }
     }
 
+    /// Whether we have seen a block at the height below `block` which is not its parent.
+    fn parent_conflicts(&self, block: &L1BlockInfoWithParent) -> bool {
+        block.info.number > 0
+            && self
+                .recent
+                .get(&(block.info.number - 1))
+                .is_some_and(|parent| parent.info.hash != block.parent_hash)
+    }
+
+    /// Record a new L1 head, along with the ancestors it was fetched with.
+    ///
+    /// `chain` is ordered from oldest to newest, ending with the new head. Any blocks we have seen
+    /// at the height of the oldest block in `chain` or above which are not part of `chain` have
+    /// been reorged out. If the oldest block in `chain` does not extend the blocks we have seen
+    /// either, the fork is deeper than `chain`, and all the blocks we have seen are replaced. If
+    /// there are any replaced blocks, they are forgotten, along with anything we derived from
+    /// them, and the height of the first one and the number of blocks replaced are returned.
+    fn put_head(&mut self, chain: &[L1BlockInfoWithParent]) -> Option<(u64, u64)> {
+        let head = chain.last()?;
+        if self.recent.get(&head.info.number) == Some(head) {
+            // We have seen this block already, e.g. when reconnecting to the block stream.
+            return None;
+        }
+        let oldest = chain.first()?;
+        let first = if self.parent_conflicts(oldest) {
+            self.recent
+                .first_key_value()
+                .map_or(oldest.info.number, |(number, _)| {
+                    min(*number, oldest.info.number)
+                })
+        } else {
+            oldest.info.number
+        };
+        let replaced = self
+            .recent
+            .range(first..)
+            .filter(|(number, seen)| {
+                chain
+                    .iter()
+                    .find(|block| block.info.number == **number)
+                    .is_none_or(|block| block.info.hash != seen.info.hash)
+            })
+            .map(|(number, _)| *number)
+            .collect::<Vec<_>>();
+
+        self.recent.retain(|number, _| *number < first);
+        self.recent
+            .extend(chain.iter().map(|block| (block.info.number, *block)));
+        while self.recent.len() > self.recent_capacity {
+            self.recent.pop_first();
+        }
+
+        let fork = *replaced.first()?;
+        let depth = replaced.last()? - fork + 1;
+
+        // Finalized blocks should never be reorged. If they are, the cached ones can no longer be
+        // trusted.
+        if self
+            .snapshot
+            .finalized
+            .is_some_and(|finalized| finalized.number >= fork)
+        {
+            tracing::error!(
+                fork,
+                depth,
+                finalized = ?self.snapshot.finalized,
+                "L1 reorg replaced finalized blocks; something has gone very wrong with the L1",
+            );
+            let stale = self
+                .finalized
+                .iter()
+                .filter(|(number, _)| **number >= fork)
+                .map(|(number, _)| *number)
+                .collect::<Vec<_>>();
+            for number in stale {
+                self.finalized.pop(&number);
+            }
+        }
+        Some((fork, depth))
+    }
+
     fn put_finalized(&mut self, block: L1BlockInfoWithParent) {
         assert!(
             self.snapshot.finalized.is_some()
@@ -972,6 +1354,54 @@ impl L1State {
     }
 }
 
+/// Number of times to try fetching an ancestor of a new L1 head before giving up on it.
+const L1_REORG_FETCH_ATTEMPTS: usize = 3;
+
+/// Fetch the ancestors of a new L1 head which replace blocks we have recently seen.
+///
+/// Returns the chain of blocks from the earliest ancestor which does not conflict with the blocks
+/// we have seen, up to and including `head`. If `head` simply extends the chain we have seen, this
+/// is just `head`. The search stops early, with an ancestor which still conflicts, if the fork is
+/// deeper than the blocks we keep, or if an ancestor cannot be fetched after a few attempts, e.g.
+/// because it has been reorged out in turn.
+async fn fetch_new_chain(
+    rpc: &impl Provider,
+    state: &Mutex<L1State>,
+    head: L1BlockInfoWithParent,
+    retry_delay: Duration,
+) -> Vec<L1BlockInfoWithParent> {
+    let max_depth = state.lock().await.recent_capacity;
+    let mut chain = vec![head];
+    while chain.len() < max_depth {
+        let oldest = chain.last().unwrap();
+        if !state.lock().await.parent_conflicts(oldest) {
+            break;
+        }
+        let mut parent = None;
+        for _ in 0..L1_REORG_FETCH_ATTEMPTS {
+            match rpc.get_block(oldest.parent_hash.into()).await {
+                Ok(Some(block)) => {
+                    parent = Some((&block).into());
+                    break;
+                },
+                Ok(None) => {
+                    tracing::warn!(?oldest, "parent of L1 block not available");
+                },
+                Err(err) => {
+                    tracing::warn!(?oldest, "failed to get parent of L1 block: {err:#}");
+                },
+            }
+            sleep(retry_delay).await;
+        }
+        let Some(parent) = parent else {
+            break;
+        };
+        chain.push(parent);
+    }
+    chain.reverse();
+    chain
+}
+
 async fn fetch_finalized_block_from_rpc(
     rpc: &impl Provider,
 ) -> anyhow::Result<Option<L1BlockInfoWithParent>> {
@@ -1495,4 +1925,78 @@ mod test {
         }
         panic!("L1 state of L1Client not initialized");
     }
+
+    #[test]
+    fn test_reorg_detection() {
+        // A block at `number` on the fork identified by `fork`, whose parent is on `parent_fork`.
+        fn block(number: u64, fork: u8, parent_fork: u8) -> L1BlockInfoWithParent {
+            fn hash(number: u64, fork: u8) -> B256 {
+                let mut hash = B256::with_last_byte(fork);
+                hash[..8].copy_from_slice(&number.to_le_bytes());
+                hash
+            }
+            L1BlockInfoWithParent {
+                info: L1BlockInfo {
+                    number,
+                    timestamp: U256::from(number),
+                    hash: hash(number, fork),
+                },
+                parent_hash: hash(number - 1, parent_fork),
+            }
+        }
+
+        let mut state = L1State::new(NonZeroUsize::new(5).unwrap());
+        for number in 1..=6 {
+            assert_eq!(state.put_head(&[block(number, 0, 0)]), None);
+        }
+        assert_eq!(state.recent.len(), 5);
+
+        // Seeing the same head again is not a reorg.
+        assert_eq!(state.put_head(&[block(6, 0, 0)]), None);
+        assert!(!state.parent_conflicts(&block(7, 0, 0)));
+
+        // A new head whose parent is not the block we saw replaces blocks 5 and 6.
+        let head = block(7, 1, 1);
+        assert!(state.parent_conflicts(&head));
+        assert!(state.parent_conflicts(&block(6, 1, 1)));
+        assert!(!state.parent_conflicts(&block(5, 1, 0)));
+        let chain = [block(5, 1, 0), block(6, 1, 1), head];
+        assert_eq!(state.put_head(&chain), Some((5, 2)));
+        assert_eq!(state.recent[&5], chain[0]);
+
+        // A competing block at the current height is a reorg of depth 1.
+        assert_eq!(state.put_head(&[block(7, 2, 1)]), Some((7, 1)));
+
+        // A fork deeper than the blocks we fetched replaces all the blocks we have seen.
+        assert_eq!(state.put_head(&[block(8, 3, 3)]), Some((3, 5)));
+        assert_eq!(state.recent.len(), 1);
+    }
+
+    #[test]
+    fn test_provider_health() {
+        let urls = (0..3)
+            .map(|i| format!("http://localhost:{}", 8545 + i).parse().unwrap())
+            .collect();
+        let transport = SwitchingTransport::new(
+            L1ClientOptions {
+                l1_read_mode: L1ReadMode::Quorum,
+                ..Default::default()
+            },
+            urls,
+        )
+        .unwrap();
+        assert_eq!(transport.healthiest(2), vec![0, 1]);
+
+        // A provider which fails drops behind the others, until it recovers.
+        transport.log_outcome(0, false);
+        assert_eq!(transport.healthiest(2), vec![1, 2]);
+        assert_eq!(transport.healthiest(5), vec![1, 2, 0]);
+        transport.log_outcome(1, false);
+        transport.log_outcome(1, false);
+        assert_eq!(transport.healthiest(3), vec![2, 0, 1]);
+        for _ in 0..100 {
+            transport.log_outcome(1, true);
+        }
+        assert_eq!(transport.healthiest(3), vec![2, 1, 0]);
+    }
 }
@@ -0,0 +1,316 @@
+use alloy::primitives::U256;
+use anyhow::{ensure, Context};
+use committable::Committable;
+#[cfg(any(test, feature = "testing"))]
+use hotshot_types::{
+    data::{QuorumProposal2, QuorumProposalWrapper, ViewNumber},
+    signer::Signer,
+    simple_vote::{QuorumData2, QuorumVote2, VersionedVoteData},
+    traits::signature_key::SignatureKey,
+    vote::{Certificate, Vote},
+    ValidatorConfig,
+};
+use hotshot_types::{
+    message::UpgradeLock, simple_certificate::QuorumCertificate2,
+    traits::node_implementation::Versions, vote::HasViewNumber, PeerConfig, StakeTableEntries,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(test, feature = "testing"))]
+use crate::PubKey;
+use crate::{Leaf2, SeqTypes};
+
+/// Proof that a leaf has been decided, verifiable without running a node.
+///
+/// The proof consists of the leaf, the QC certifying it, and the chain of descendants of the leaf
+/// up to a decided anchor. Each leaf in the chain is justified by a QC on the one before it, so the
+/// chain commits to the proven leaf. The last three leaves of the chain satisfy the decide rule: the
+/// second to last directly extends the third to last, in the next view, and is itself certified by
+/// the last. The third to last leaf is therefore decided, and so are all of its ancestors.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafProof {
+    /// The leaf being proven.
+    pub leaf: Leaf2,
+    /// The QC certifying `leaf`.
+    pub qc: QuorumCertificate2<SeqTypes>,
+    /// Descendants of `leaf`, oldest first, up to and including the leaf which decides the anchor.
+    pub chain: Vec<Leaf2>,
+}
+
+impl LeafProof {
+    /// Build a proof from a leaf chain, as served by the `catchup/:height/leafchain` endpoint.
+    ///
+    /// `leaves` must start with the leaf being proven, followed by its descendants in order.
+    pub fn from_leaf_chain(mut leaves: Vec<Leaf2>) -> anyhow::Result<Self> {
+        ensure!(
+            leaves.len() >= 3,
+            "leaf chain of length {} is too short to prove a decide",
+            leaves.len()
+        );
+        let chain = leaves.split_off(1);
+        let leaf = leaves.pop().unwrap();
+        let qc = chain[0].justify_qc();
+        Ok(Self { leaf, qc, chain })
+    }
+
+    /// The height of the proven leaf.
+    pub fn height(&self) -> u64 {
+        self.leaf.height()
+    }
+
+    /// The decided anchor, which the proven leaf is an ancestor of (or equal to).
+    ///
+    /// This is the leaf three from the end of the chain, counting the proven leaf itself.
+    pub fn anchor(&self) -> Option<&Leaf2> {
+        match self.chain.len() {
+            0 | 1 => None,
+            2 => Some(&self.leaf),
+            n => Some(&self.chain[n - 3]),
+        }
+    }
+}
+
+/// Verifies [`LeafProof`]s against a known stake table.
+///
+/// This performs the same checks as a node catching up on the chain, so that external verifiers
+/// such as bridges need not reimplement them.
+#[derive(Clone, Debug)]
+pub struct LeafProofVerifier<V: Versions> {
+    stake_table: Vec<PeerConfig<SeqTypes>>,
+    success_threshold: U256,
+    upgrade_lock: UpgradeLock<SeqTypes, V>,
+}
+
+impl<V: Versions> LeafProofVerifier<V> {
+    /// A verifier accepting QCs signed by `success_threshold` stake from `stake_table`.
+    pub fn new(stake_table: Vec<PeerConfig<SeqTypes>>, success_threshold: U256) -> Self {
+        Self {
+            stake_table,
+            success_threshold,
+            upgrade_lock: UpgradeLock::new(),
+        }
+    }
+
+    /// Use `upgrade_lock` to determine the protocol version in effect for each QC.
+    ///
+    /// By default, every QC is checked under the base version of `V`.
+    pub fn with_upgrade_lock(mut self, upgrade_lock: UpgradeLock<SeqTypes, V>) -> Self {
+        self.upgrade_lock = upgrade_lock;
+        self
+    }
+
+    /// Check that `proof` shows its leaf to be decided.
+    ///
+    /// On success, returns the proven leaf.
+    pub async fn verify<'a>(&self, proof: &'a LeafProof) -> anyhow::Result<&'a Leaf2> {
+        ensure!(
+            proof.chain.len() >= 2,
+            "leaf chain of length {} is too short to prove a decide",
+            proof.chain.len() + 1
+        );
+        ensure!(
+            proof.chain[0].justify_qc() == proof.qc,
+            "QC does not justify the first descendant"
+        );
+
+        // Check that each leaf is certified by the QC in its child.
+        let mut parent = &proof.leaf;
+        for child in &proof.chain {
+            let qc = child.justify_qc();
+            ensure!(
+                qc.view_number() == parent.view_number() && qc.data.leaf_commit == parent.commit(),
+                "leaf in view {:?} is not justified by a QC for its parent",
+                child.view_number()
+            );
+            self.verify_qc(&qc)
+                .await
+                .with_context(|| format!("invalid QC for view {:?}", qc.view_number()))?;
+            parent = child;
+        }
+
+        // Check the decide rule for the anchor at the end of the chain.
+        let anchor = proof.anchor().context("leaf chain has no anchor")?;
+        let anchor_child = &proof.chain[proof.chain.len() - 2];
+        ensure!(
+            anchor_child.view_number() == anchor.view_number() + 1,
+            "leaf chain does not end in a decide"
+        );
+
+        Ok(&proof.leaf)
+    }
+
+    async fn verify_qc(&self, qc: &QuorumCertificate2<SeqTypes>) -> anyhow::Result<()> {
+        qc.verify_untrusted(
+            StakeTableEntries::<SeqTypes>::from(self.stake_table.clone()).0,
+            self.success_threshold,
+            &self.upgrade_lock,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Certify `data` in `view` with the signatures of all of `validators`.
+///
+/// The QC verifies against the stake table formed by `validators` with the given `threshold`.
+#[cfg(any(test, feature = "testing"))]
+pub async fn sign_qc<V: Versions>(
+    validators: &[ValidatorConfig<SeqTypes>],
+    threshold: U256,
+    data: QuorumData2<SeqTypes>,
+    view: ViewNumber,
+    upgrade_lock: &UpgradeLock<SeqTypes, V>,
+) -> QuorumCertificate2<SeqTypes> {
+    let peers = validators
+        .iter()
+        .map(ValidatorConfig::public_config)
+        .collect::<Vec<_>>();
+    let params = PubKey::public_parameter(StakeTableEntries::<SeqTypes>::from(peers).0, threshold);
+
+    let mut signatures = vec![];
+    for validator in validators {
+        let vote = QuorumVote2::<SeqTypes>::create_signed_vote(
+            data.clone(),
+            view,
+            &validator.public_key,
+            &Signer::Local(validator.private_key.clone()),
+            upgrade_lock,
+        )
+        .await
+        .unwrap();
+        signatures.push(vote.signature());
+    }
+    let signature = PubKey::assemble(
+        &params,
+        bitvec::bitvec![1; validators.len()].as_bitslice(),
+        &signatures,
+    );
+
+    let commit = VersionedVoteData::new(data.clone(), view, upgrade_lock)
+        .await
+        .unwrap()
+        .commit();
+    QuorumCertificate2::create_signed_certificate(commit, data, signature, view)
+}
+
+/// A leaf in `view` extending the leaf certified by `justify_qc`, whose header is `parent_header`
+/// with the next height.
+#[cfg(any(test, feature = "testing"))]
+pub fn child_leaf(
+    parent_header: &crate::Header,
+    view: ViewNumber,
+    justify_qc: QuorumCertificate2<SeqTypes>,
+) -> Leaf2 {
+    let mut block_header = parent_header.clone();
+    *block_header.height_mut() += 1;
+    Leaf2::from_quorum_proposal(&QuorumProposalWrapper {
+        proposal: QuorumProposal2 {
+            block_header,
+            view_number: view,
+            epoch: justify_qc.data.epoch,
+            justify_qc,
+            next_epoch_justify_qc: None,
+            upgrade_certificate: None,
+            view_change_evidence: None,
+            next_drb_result: None,
+            state_cert: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_query_service::testing::mocks::MockVersions;
+    use hotshot_types::{data::Leaf, traits::node_implementation::ConsensusTime};
+    use sequencer_utils::test_utils::setup_test;
+
+    use super::*;
+    use crate::{NodeState, ValidatedState};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_leaf_proof_structure() {
+        setup_test();
+
+        let instance_state = NodeState::mock();
+        let validated_state = ValidatedState::genesis(&instance_state).0;
+        let genesis: Leaf2 = Leaf::genesis::<MockVersions>(&validated_state, &instance_state)
+            .await
+            .into();
+        let verifier = LeafProofVerifier::<MockVersions>::new(vec![], U256::ZERO);
+
+        // A chain must have enough descendants to show a decide.
+        LeafProof::from_leaf_chain(vec![genesis.clone(), genesis.clone()]).unwrap_err();
+
+        // A chain which does not advance the view does not satisfy the decide rule.
+        let proof =
+            LeafProof::from_leaf_chain(vec![genesis.clone(), genesis.clone(), genesis.clone()])
+                .unwrap();
+        assert_eq!(proof.height(), 0);
+        assert_eq!(proof.qc, genesis.justify_qc());
+        assert_eq!(proof.anchor(), Some(&genesis));
+        verifier.verify(&proof).await.unwrap_err();
+
+        // Truncated proofs are rejected.
+        let mut truncated = proof.clone();
+        truncated.chain.pop();
+        verifier.verify(&truncated).await.unwrap_err();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_leaf_proof_signed_chain() {
+        setup_test();
+
+        let instance_state = NodeState::mock();
+        let validated_state = ValidatedState::genesis(&instance_state).0;
+        let genesis: Leaf2 = Leaf::genesis::<MockVersions>(&validated_state, &instance_state)
+            .await
+            .into();
+        let validators = (0..4)
+            .map(|i| ValidatorConfig::generated_from_seed_indexed([0; 32], i, U256::from(1), true))
+            .collect::<Vec<_>>();
+        let threshold = U256::from(validators.len());
+        let upgrade_lock = UpgradeLock::<SeqTypes, MockVersions>::new();
+
+        // Build a chain of leaves in consecutive views, each certified by the next.
+        let mut leaves = vec![genesis];
+        for view in 1..4 {
+            let parent = leaves.last().unwrap();
+            let qc = sign_qc(
+                &validators,
+                threshold,
+                QuorumData2 {
+                    leaf_commit: parent.commit(),
+                    epoch: None,
+                    block_number: Some(parent.height()),
+                },
+                parent.view_number(),
+                &upgrade_lock,
+            )
+            .await;
+            leaves.push(child_leaf(parent.block_header(), ViewNumber::new(view), qc));
+        }
+        let stake_table = validators
+            .iter()
+            .map(ValidatorConfig::public_config)
+            .collect::<Vec<_>>();
+        let verifier = LeafProofVerifier::<MockVersions>::new(stake_table, threshold);
+
+        // The leaf in view 1 is decided by its two descendants.
+        let proof = LeafProof::from_leaf_chain(leaves[1..].to_vec()).unwrap();
+        assert_eq!(verifier.verify(&proof).await.unwrap(), &leaves[1]);
+
+        // A proof starting at genesis relies on the unsigned genesis QC, which is not trusted.
+        let proof = LeafProof::from_leaf_chain(leaves[..3].to_vec()).unwrap();
+        verifier.verify(&proof).await.unwrap_err();
+
+        // A QC with its signatures stripped is rejected rather than panicking.
+        let mut unsigned = LeafProof::from_leaf_chain(leaves[1..].to_vec()).unwrap();
+        unsigned.qc.signatures = None;
+        unsigned.chain[0] = child_leaf(
+            leaves[1].block_header(),
+            leaves[2].view_number(),
+            unsigned.qc.clone(),
+        );
+        verifier.verify(&unsigned).await.unwrap_err();
+    }
+}
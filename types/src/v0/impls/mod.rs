@@ -2,24 +2,48 @@ pub use super::*;
 
 mod auction;
 mod block;
+mod builder_registry;
 mod chain_config;
+mod encryption;
 mod fee_info;
 mod header;
 mod instance_state;
 mod l1;
+mod leaf_proof;
+mod namespace_registry;
 mod reward;
+mod reward_distribution;
+#[cfg(any(test, feature = "testing"))]
+mod simulated_l1;
 mod solver;
 mod stake_table;
 mod state;
 mod transaction;
+mod transaction_status;
 
 pub use auction::SolverAuctionResultsProvider;
+pub use builder_registry::{BuilderRegistry, RegisteredBuilder};
+pub use encryption::{
+    DecryptionError, DecryptionShare, EncryptedPayload, KeyShare, ThresholdEncryptionKey,
+    ENCRYPTED_PAYLOAD_PREFIX,
+};
 pub use fee_info::{retain_accounts, FeeError};
 #[cfg(any(test, feature = "testing"))]
 pub use instance_state::mock;
 pub use instance_state::NodeState;
+#[cfg(any(test, feature = "testing"))]
+pub use leaf_proof::{child_leaf, sign_qc};
+pub use leaf_proof::{LeafProof, LeafProofVerifier};
+pub use namespace_registry::{
+    CollisionPolicy, NamespaceAdmission, NamespaceRegistry, NamespaceRegistryConfig,
+    NamespaceRegistryError, RegisteredNamespace, UnregisteredNamespacePolicy,
+};
+pub use reward_distribution::{RewardClaim, RewardDistribution, RewardDistributionPage};
+#[cfg(any(test, feature = "testing"))]
+pub use simulated_l1::SimulatedL1;
 pub use stake_table::*;
 pub use state::{
     get_l1_deposits, BuilderValidationError, ProposalValidationError, StateValidationError,
     ValidatedState,
 };
+pub use transaction_status::{SubmissionReceipt, TransactionStatus};
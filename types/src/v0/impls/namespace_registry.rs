@@ -0,0 +1,397 @@
+//! Registry of the rollups which own each namespace.
+//!
+//! The registry in force is part of the chain: the chain config commits to it (see
+//! [`ChainConfig::namespace_registry`](crate::v0_4::ChainConfig::namespace_registry)), so changing
+//! it takes a chain config upgrade, and every node validates proposed blocks against it. Only v0.4
+//! headers carry a chain config with a registry. Nodes are configured with the contents of each
+//! registry a chain config may commit to, and look them up by commitment in
+//! [`NodeState::namespace_registry`](crate::NodeState::namespace_registry).
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{FeeAccount, NamespaceId};
+
+/// What the node does with transactions submitted to a namespace no rollup has registered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnregisteredNamespacePolicy {
+    /// Accept them like any other transaction
+    #[default]
+    Allow,
+    /// Accept them, but log each submission so operators can follow up
+    Flag,
+    /// Refuse them, and reject blocks which contain them
+    Reject,
+}
+
+/// What the registry does when more than one rollup registers the same namespace
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollisionPolicy {
+    /// The first registration owns the namespace; later ones are kept as collisions
+    #[default]
+    FirstWins,
+    /// The registry fails to load, so the collision must be resolved before the node starts
+    Reject,
+}
+
+/// A namespace registered by a rollup
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisteredNamespace {
+    pub namespace: NamespaceId,
+    /// Name of the rollup which owns the namespace
+    pub rollup: String,
+    /// Account of the rollup operator, if any.
+    ///
+    /// If set, nodes only accept transactions submitted to the namespace with a signature by this
+    /// account (see [`NamespaceRegistry::authorize_submission`]).
+    #[serde(default)]
+    pub owner: Option<FeeAccount>,
+}
+
+/// Configuration of the namespace registry, as given in the genesis file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceRegistryConfig {
+    #[serde(default)]
+    pub unregistered: UnregisteredNamespacePolicy,
+    #[serde(default)]
+    pub collisions: CollisionPolicy,
+    /// Registrations, in order of precedence
+    #[serde(default)]
+    pub namespaces: Vec<RegisteredNamespace>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum NamespaceRegistryError {
+    #[error("namespace {namespace} is registered by both {owner} and {claimant}")]
+    Collision {
+        namespace: NamespaceId,
+        owner: String,
+        claimant: String,
+    },
+    #[error("namespace {0} is not registered to any rollup")]
+    Unregistered(NamespaceId),
+    #[error("namespace {namespace} only accepts submissions signed by its owner {owner}")]
+    NotOwner {
+        namespace: NamespaceId,
+        owner: FeeAccount,
+    },
+    #[error(
+        "chain config commits to namespace registry {0}, which this node is not configured with"
+    )]
+    Unknown(Commitment<NamespaceRegistry>),
+}
+
+/// Whether a transaction submitted to a namespace is accepted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NamespaceAdmission<'a> {
+    /// The namespace is registered
+    Registered(&'a RegisteredNamespace),
+    /// The namespace is not registered, and unregistered namespaces are allowed
+    Unregistered,
+    /// The namespace is not registered, and the submission should be flagged
+    Flagged,
+}
+
+/// Registry of the rollups which own each namespace.
+///
+/// The default registry has no registrations and allows every namespace.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NamespaceRegistry {
+    unregistered: UnregisteredNamespacePolicy,
+    /// Registrations owning their namespace, by namespace
+    namespaces: Vec<RegisteredNamespace>,
+    /// Registrations of namespaces already owned by an earlier registration
+    collisions: Vec<RegisteredNamespace>,
+}
+
+impl NamespaceRegistry {
+    /// Build the registry from `config`.
+    ///
+    /// # Errors
+    /// If two registrations claim the same namespace and the collision policy is
+    /// [`Reject`](CollisionPolicy::Reject).
+    pub fn new(config: NamespaceRegistryConfig) -> Result<Self, NamespaceRegistryError> {
+        let mut namespaces = Vec::<RegisteredNamespace>::new();
+        let mut collisions = vec![];
+        for registration in config.namespaces {
+            match namespaces.binary_search_by_key(&registration.namespace, |r| r.namespace) {
+                Ok(i) => {
+                    let owner = &namespaces[i];
+                    if config.collisions == CollisionPolicy::Reject {
+                        return Err(NamespaceRegistryError::Collision {
+                            namespace: registration.namespace,
+                            owner: owner.rollup.clone(),
+                            claimant: registration.rollup,
+                        });
+                    }
+                    tracing::warn!(
+                        namespace = %registration.namespace,
+                        owner = %owner.rollup,
+                        claimant = %registration.rollup,
+                        "namespace registered more than once, keeping the first registration"
+                    );
+                    collisions.push(registration);
+                },
+                Err(i) => namespaces.insert(i, registration),
+            }
+        }
+        Ok(Self {
+            unregistered: config.unregistered,
+            namespaces,
+            collisions,
+        })
+    }
+
+    /// What happens to transactions submitted to unregistered namespaces
+    pub fn unregistered_policy(&self) -> UnregisteredNamespacePolicy {
+        self.unregistered
+    }
+
+    /// The registration owning `namespace`, if any
+    pub fn get(&self, namespace: NamespaceId) -> Option<&RegisteredNamespace> {
+        self.namespaces
+            .binary_search_by_key(&namespace, |r| r.namespace)
+            .ok()
+            .map(|i| &self.namespaces[i])
+    }
+
+    /// The registrations owning their namespace, in namespace order
+    pub fn namespaces(&self) -> &[RegisteredNamespace] {
+        &self.namespaces
+    }
+
+    /// The registrations which lost their namespace to an earlier registration
+    pub fn collisions(&self) -> &[RegisteredNamespace] {
+        &self.collisions
+    }
+
+    /// The namespaces registered to `rollup`
+    pub fn namespaces_of<'a>(
+        &'a self,
+        rollup: &'a str,
+    ) -> impl Iterator<Item = &'a RegisteredNamespace> + 'a {
+        self.namespaces
+            .iter()
+            .filter(move |registration| registration.rollup == rollup)
+    }
+
+    /// Check whether a transaction in `namespace` may be included in a block.
+    ///
+    /// # Errors
+    /// If the namespace is not registered and unregistered namespaces are rejected.
+    pub fn admit(
+        &self,
+        namespace: NamespaceId,
+    ) -> Result<NamespaceAdmission<'_>, NamespaceRegistryError> {
+        if let Some(registration) = self.get(namespace) {
+            return Ok(NamespaceAdmission::Registered(registration));
+        }
+        match self.unregistered {
+            UnregisteredNamespacePolicy::Allow => Ok(NamespaceAdmission::Unregistered),
+            UnregisteredNamespacePolicy::Flag => Ok(NamespaceAdmission::Flagged),
+            UnregisteredNamespacePolicy::Reject => {
+                Err(NamespaceRegistryError::Unregistered(namespace))
+            },
+        }
+    }
+
+    /// Check whether a transaction submitted to `namespace` by `signer` is accepted.
+    ///
+    /// On top of [`admit`](Self::admit), a namespace with an owner only accepts submissions signed
+    /// by the owner. The signer of a transaction is not part of the block, so this is only checked
+    /// where transactions are submitted, not in block validation.
+    ///
+    /// # Errors
+    /// If the transaction is not admitted, or the namespace has an owner other than `signer`.
+    pub fn authorize_submission(
+        &self,
+        namespace: NamespaceId,
+        signer: Option<FeeAccount>,
+    ) -> Result<NamespaceAdmission<'_>, NamespaceRegistryError> {
+        let admission = self.admit(namespace)?;
+        if let NamespaceAdmission::Registered(RegisteredNamespace {
+            owner: Some(owner), ..
+        }) = admission
+        {
+            if signer != Some(*owner) {
+                return Err(NamespaceRegistryError::NotOwner {
+                    namespace,
+                    owner: *owner,
+                });
+            }
+        }
+        Ok(admission)
+    }
+}
+
+impl Committable for NamespaceRegistry {
+    fn tag() -> String {
+        "NAMESPACE_REGISTRY".to_string()
+    }
+
+    /// Commit to the policy and the registrations owning their namespace.
+    ///
+    /// Collisions are left out, as they do not affect which transactions are accepted.
+    fn commit(&self) -> Commitment<Self> {
+        let unregistered = match self.unregistered {
+            UnregisteredNamespacePolicy::Allow => 0,
+            UnregisteredNamespacePolicy::Flag => 1,
+            UnregisteredNamespacePolicy::Reject => 2,
+        };
+        let mut comm = RawCommitmentBuilder::new(&Self::tag())
+            .u64_field("unregistered", unregistered)
+            .u64_field("namespaces", self.namespaces.len() as u64);
+        for registration in &self.namespaces {
+            comm = comm
+                .u64_field("namespace", registration.namespace.0)
+                .var_size_field("rollup", registration.rollup.as_bytes());
+            comm = match registration.owner {
+                Some(owner) => comm
+                    .u64_field("owner", 1)
+                    .fixed_size_bytes(&owner.to_fixed_bytes()),
+                None => comm.u64_field("owner", 0),
+            };
+        }
+        comm.finalize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn registration(namespace: u64, rollup: &str) -> RegisteredNamespace {
+        RegisteredNamespace {
+            namespace: NamespaceId::from(namespace),
+            rollup: rollup.into(),
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_namespace_registry() {
+        let config = NamespaceRegistryConfig {
+            unregistered: UnregisteredNamespacePolicy::Flag,
+            collisions: CollisionPolicy::FirstWins,
+            namespaces: vec![
+                registration(7, "alpha"),
+                registration(3, "beta"),
+                registration(7, "gamma"),
+                registration(5, "alpha"),
+            ],
+        };
+
+        let registry = NamespaceRegistry::new(config.clone()).unwrap();
+        assert_eq!(
+            registry.namespaces(),
+            [
+                registration(3, "beta"),
+                registration(5, "alpha"),
+                registration(7, "alpha")
+            ]
+        );
+        assert_eq!(registry.collisions(), [registration(7, "gamma")]);
+        assert_eq!(
+            registry.namespaces_of("alpha").cloned().collect::<Vec<_>>(),
+            [registration(5, "alpha"), registration(7, "alpha")]
+        );
+
+        assert_eq!(
+            registry.admit(NamespaceId::from(7u64)),
+            Ok(NamespaceAdmission::Registered(&registration(7, "alpha")))
+        );
+        assert_eq!(
+            registry.admit(NamespaceId::from(8u64)),
+            Ok(NamespaceAdmission::Flagged)
+        );
+
+        // Collisions can be refused outright.
+        let err = NamespaceRegistry::new(NamespaceRegistryConfig {
+            collisions: CollisionPolicy::Reject,
+            ..config.clone()
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            NamespaceRegistryError::Collision {
+                namespace: NamespaceId::from(7u64),
+                owner: "alpha".into(),
+                claimant: "gamma".into(),
+            }
+        );
+
+        // So can unregistered namespaces.
+        let registry = NamespaceRegistry::new(NamespaceRegistryConfig {
+            unregistered: UnregisteredNamespacePolicy::Reject,
+            collisions: CollisionPolicy::FirstWins,
+            namespaces: vec![registration(3, "beta")],
+        })
+        .unwrap();
+        assert_eq!(
+            registry.admit(NamespaceId::from(4u64)),
+            Err(NamespaceRegistryError::Unregistered(NamespaceId::from(
+                4u64
+            )))
+        );
+
+        // The default registry allows everything.
+        assert_eq!(
+            NamespaceRegistry::default().admit(NamespaceId::from(4u64)),
+            Ok(NamespaceAdmission::Unregistered)
+        );
+    }
+
+    #[test]
+    fn test_namespace_registry_owner() {
+        let owner = FeeAccount::generated_from_seed_indexed([0; 32], 0).0;
+        let other = FeeAccount::generated_from_seed_indexed([0; 32], 1).0;
+        let owned = RegisteredNamespace {
+            owner: Some(owner),
+            ..registration(3, "beta")
+        };
+        let registry = NamespaceRegistry::new(NamespaceRegistryConfig {
+            namespaces: vec![owned.clone(), registration(5, "alpha")],
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Namespaces with an owner only accept submissions signed by the owner.
+        assert_eq!(
+            registry.authorize_submission(NamespaceId::from(3u64), Some(owner)),
+            Ok(NamespaceAdmission::Registered(&owned))
+        );
+        for signer in [None, Some(other)] {
+            assert_eq!(
+                registry.authorize_submission(NamespaceId::from(3u64), signer),
+                Err(NamespaceRegistryError::NotOwner {
+                    namespace: NamespaceId::from(3u64),
+                    owner,
+                })
+            );
+        }
+        // Blocks may still contain transactions in the namespace, as the signer is not part of
+        // the block.
+        registry.admit(NamespaceId::from(3u64)).unwrap();
+
+        // Other namespaces accept submissions from anyone.
+        registry
+            .authorize_submission(NamespaceId::from(5u64), None)
+            .unwrap();
+        registry
+            .authorize_submission(NamespaceId::from(6u64), Some(other))
+            .unwrap();
+
+        // The commitment covers the owners.
+        let unowned = NamespaceRegistry::new(NamespaceRegistryConfig {
+            namespaces: vec![registration(3, "beta"), registration(5, "alpha")],
+            ..Default::default()
+        })
+        .unwrap();
+        assert_ne!(registry.commit(), unowned.commit());
+    }
+}
@@ -0,0 +1,312 @@
+//! Per-epoch reward distributions.
+//!
+//! Consensus credits the reward for each block to the leader that proposed it and its delegators
+//! in the [`RewardMerkleTree`]. At the end of each epoch, the rewards credited during the epoch are
+//! read back from the tree and committed to in a Merkle tree whose root can be posted to a
+//! reward-claim contract, against which each account claims its reward by presenting its Merkle
+//! proof.
+//!
+//! The tree uses keccak256 throughout so that proofs can be verified on chain. A leaf is the double
+//! hash of the ABI encoding of `(address account, uint256 amount)`, and each internal node is the
+//! hash of its two children in sorted order, so that a proof is just the list of sibling hashes.
+
+use std::collections::BTreeMap;
+
+use alloy::primitives::{keccak256, Address, B256};
+use anyhow::{ensure, Context};
+use hotshot_types::data::EpochNumber;
+use serde::{Deserialize, Serialize};
+
+use super::v0_1::{RewardAccountProof, RewardAmount, RewardMerkleTree};
+
+/// The rewards earned by each account in an epoch
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardDistribution {
+    pub epoch: EpochNumber,
+    /// Root of the Merkle tree of rewards, to be posted to the reward-claim contract
+    pub root: B256,
+    /// Total reward distributed
+    pub total: RewardAmount,
+    /// Reward earned by each account
+    pub rewards: BTreeMap<Address, RewardAmount>,
+}
+
+impl RewardDistribution {
+    /// The rewards credited to `accounts` during `epoch`.
+    ///
+    /// `start` is the reward state at the end of the previous epoch and `end` the reward state at
+    /// the end of `epoch`; both must contain `accounts` in memory. The reward of each account is
+    /// the increase of its balance between the two, and accounts whose balance did not change are
+    /// left out.
+    pub fn from_balances(
+        epoch: EpochNumber,
+        start: &RewardMerkleTree,
+        end: &RewardMerkleTree,
+        accounts: impl IntoIterator<Item = Address>,
+    ) -> anyhow::Result<Self> {
+        let mut rewards = BTreeMap::new();
+        for account in accounts {
+            let (_, before) = RewardAccountProof::prove(start, account)
+                .with_context(|| format!("{account} missing from reward state at epoch start"))?;
+            let (_, after) = RewardAccountProof::prove(end, account)
+                .with_context(|| format!("{account} missing from reward state at epoch end"))?;
+            let reward = after
+                .checked_sub(before)
+                .with_context(|| format!("reward balance of {account} decreased"))?;
+            if !reward.is_zero() {
+                rewards.insert(account, RewardAmount(reward));
+            }
+        }
+        Ok(Self::new(epoch, rewards))
+    }
+
+    fn new(epoch: EpochNumber, rewards: BTreeMap<Address, RewardAmount>) -> Self {
+        let total = RewardAmount(rewards.values().map(|amount| amount.0).sum());
+        let root = RewardClaimTree::new(&rewards).root();
+        Self {
+            epoch,
+            root,
+            total,
+            rewards,
+        }
+    }
+
+    /// The summary of this distribution with the rewards of at most `limit` accounts, starting
+    /// from the `offset`th account in address order.
+    pub fn page(&self, offset: usize, limit: usize) -> RewardDistributionPage {
+        RewardDistributionPage {
+            epoch: self.epoch,
+            root: self.root,
+            total: self.total,
+            accounts: self.rewards.len(),
+            rewards: self
+                .rewards
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|(account, amount)| (*account, *amount))
+                .collect(),
+        }
+    }
+
+    /// The data `account` needs to claim its reward for this epoch, if it earned any.
+    pub fn claim(&self, account: Address) -> Option<RewardClaim> {
+        let amount = *self.rewards.get(&account)?;
+        let tree = RewardClaimTree::new(&self.rewards);
+        let proof = tree.prove(claim_leaf(account, amount))?;
+        Some(RewardClaim {
+            epoch: self.epoch,
+            account,
+            amount,
+            proof,
+        })
+    }
+}
+
+/// A page of the rewards of a [`RewardDistribution`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardDistributionPage {
+    pub epoch: EpochNumber,
+    /// Root of the Merkle tree of all rewards in the epoch
+    pub root: B256,
+    /// Total reward distributed in the epoch
+    pub total: RewardAmount,
+    /// Number of accounts which earned a reward in the epoch
+    pub accounts: usize,
+    /// Reward earned by each account in this page
+    pub rewards: BTreeMap<Address, RewardAmount>,
+}
+
+/// Proof that an account earned a reward in an epoch
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardClaim {
+    pub epoch: EpochNumber,
+    pub account: Address,
+    pub amount: RewardAmount,
+    /// Sibling hashes from the leaf to the root
+    pub proof: Vec<B256>,
+}
+
+impl RewardClaim {
+    /// Check this claim against the root of a reward distribution.
+    pub fn verify(&self, root: B256) -> anyhow::Result<()> {
+        let computed = self
+            .proof
+            .iter()
+            .fold(claim_leaf(self.account, self.amount), |node, sibling| {
+                hash_pair(node, *sibling)
+            });
+        ensure!(computed == root, "reward claim does not match root {root}");
+        Ok(())
+    }
+}
+
+/// Merkle tree over the rewards of an epoch
+struct RewardClaimTree {
+    /// Each level of the tree, from the sorted leaves up to the root
+    levels: Vec<Vec<B256>>,
+}
+
+impl RewardClaimTree {
+    fn new(rewards: &BTreeMap<Address, RewardAmount>) -> Self {
+        let mut leaves = rewards
+            .iter()
+            .map(|(account, amount)| claim_leaf(*account, *amount))
+            .collect::<Vec<_>>();
+        leaves.sort();
+
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(*left, *right),
+                    // An odd node out is promoted to the next level unchanged.
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// The root of the tree, or zero if there are no rewards.
+    fn root(&self) -> B256 {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn prove(&self, leaf: B256) -> Option<Vec<B256>> {
+        let mut index = self.levels[0].binary_search(&leaf).ok()?;
+        let mut proof = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// The leaf committing to `amount` earned by `account`.
+fn claim_leaf(account: Address, amount: RewardAmount) -> B256 {
+    let mut encoded = [0; 64];
+    encoded[12..32].copy_from_slice(account.as_slice());
+    encoded[32..].copy_from_slice(&amount.0.to_be_bytes::<32>());
+    keccak256(keccak256(encoded))
+}
+
+fn hash_pair(a: B256, b: B256) -> B256 {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    keccak256([left.as_slice(), right.as_slice()].concat())
+}
+
+#[cfg(test)]
+mod test {
+    use alloy::primitives::U256;
+    use jf_merkle_tree::UniversalMerkleTreeScheme;
+
+    use super::{
+        super::v0_1::{RewardAccount, REWARD_MERKLE_TREE_HEIGHT},
+        *,
+    };
+
+    #[test]
+    fn test_reward_distribution() {
+        let accounts = (0..5).map(|_| Address::random()).collect::<Vec<_>>();
+        let mut start = RewardMerkleTree::new(REWARD_MERKLE_TREE_HEIGHT);
+        let mut end = RewardMerkleTree::new(REWARD_MERKLE_TREE_HEIGHT);
+        // The first account has a balance from earlier epochs but earns nothing, the second earns
+        // its first reward, the next two add to earlier rewards and the last is never credited.
+        let balances: [(Option<u64>, u64); 4] =
+            [(Some(10), 10), (None, 12), (Some(20), 23), (Some(30), 34)];
+        for (account, (before, after)) in accounts.iter().zip(balances) {
+            if let Some(before) = before {
+                start
+                    .update(RewardAccount(*account), RewardAmount::from(before))
+                    .unwrap();
+            }
+            end.update(RewardAccount(*account), RewardAmount::from(after))
+                .unwrap();
+        }
+
+        let distribution = RewardDistribution::from_balances(
+            EpochNumber::new(5),
+            &start,
+            &end,
+            accounts.iter().copied(),
+        )
+        .unwrap();
+        assert_eq!(
+            distribution.rewards,
+            [
+                (accounts[1], RewardAmount::from(12u64)),
+                (accounts[2], RewardAmount::from(3u64)),
+                (accounts[3], RewardAmount::from(4u64)),
+            ]
+            .into_iter()
+            .collect()
+        );
+        assert_eq!(distribution.total, RewardAmount::from(19u64));
+        assert!(distribution.claim(accounts[0]).is_none());
+        assert!(distribution.claim(accounts[4]).is_none());
+
+        // Every account with a reward can claim it, and claims cannot be altered.
+        for (account, amount) in &distribution.rewards {
+            let claim = distribution.claim(*account).unwrap();
+            assert_eq!(claim.amount, *amount);
+            claim.verify(distribution.root).unwrap();
+
+            let mut forged = claim.clone();
+            forged.amount.0 += U256::from(1);
+            forged.verify(distribution.root).unwrap_err();
+        }
+
+        // Pages cover every reward exactly once.
+        let pages = (0..3)
+            .map(|i| distribution.page(2 * i, 2))
+            .collect::<Vec<_>>();
+        assert!(pages.iter().all(|page| page.accounts == 3));
+        assert!(pages.iter().all(|page| page.root == distribution.root));
+        assert_eq!(
+            pages
+                .into_iter()
+                .flat_map(|page| page.rewards)
+                .collect::<BTreeMap<_, _>>(),
+            distribution.rewards
+        );
+
+        // A balance can never decrease within an epoch.
+        RewardDistribution::from_balances(
+            EpochNumber::new(5),
+            &end,
+            &start,
+            accounts[2..3].to_vec(),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_reward_claim_tree_shapes() {
+        for n in 1..=9 {
+            let rewards = (1..=n)
+                .map(|i| (Address::random(), RewardAmount::from(i)))
+                .collect::<BTreeMap<_, _>>();
+            let distribution = RewardDistribution::new(EpochNumber::new(1), rewards.clone());
+            for account in rewards.keys() {
+                distribution
+                    .claim(*account)
+                    .unwrap()
+                    .verify(distribution.root)
+                    .unwrap();
+            }
+        }
+    }
+}
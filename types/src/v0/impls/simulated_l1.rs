@@ -0,0 +1,637 @@
+//! A simulated L1 for tests.
+//!
+//! [`SimulatedL1`] serves the parts of the Ethereum JSON-RPC API used by the [`L1Client`] and the
+//! stake table fetcher from an in-memory chain which is entirely under the control of the test:
+//! blocks are only produced, finalized and reorged when the test says so, and the only contract
+//! events are the ones the test emits. L1-dependent tests can script the L1 deterministically,
+//! without running an anvil process.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{keccak256, Address, Bloom, Bytes, LogData, B256, B64, U256},
+    rpc::types::{Filter, FilterBlockOption},
+    sol_types::{SolCall, SolEvent},
+};
+use hotshot_contract_adapter::sol_types::StakeTable;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    spawn,
+    task::JoinHandle,
+    time::sleep,
+};
+use url::Url;
+
+use crate::{L1Client, L1ClientOptions};
+
+/// Seconds between the timestamps of consecutive blocks
+const BLOCK_TIME: u64 = 12;
+
+/// How often clients created by [`SimulatedL1::client`] poll for changes
+const POLLING_INTERVAL: Duration = Duration::from_millis(10);
+
+/// JSON-RPC error code for malformed requests
+const PARSE_ERROR: i64 = -32700;
+/// JSON-RPC error code for methods the simulated L1 does not support
+const METHOD_NOT_FOUND: i64 = -32601;
+/// JSON-RPC error code for malformed parameters
+const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for requests which fail, such as reverted calls
+const SERVER_ERROR: i64 = -32000;
+
+/// A JSON-RPC error code and message
+type RpcError = (i64, String);
+
+/// An L1 chain, served over JSON-RPC, whose blocks, finality, reorgs and contract events are
+/// scripted by the test.
+///
+/// The chain starts out with just a genesis block. Blocks are produced by [`mine`](Self::mine),
+/// and contain the events [emitted](Self::emit) since the previous block. Nothing is finalized
+/// until the test [finalizes](Self::finalize) it, and [`reorg`](Self::reorg) replaces the most
+/// recent blocks, and the events in them, with new ones.
+///
+/// Clients only learn of changes to the chain when they next poll it, so as with a real L1, a
+/// client only sees the new finalized block once a block is produced after it is finalized.
+#[derive(Debug)]
+pub struct SimulatedL1 {
+    url: Url,
+    chain: Arc<Mutex<Chain>>,
+    server: JoinHandle<()>,
+}
+
+impl Drop for SimulatedL1 {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+impl SimulatedL1 {
+    /// Start serving a new chain, consisting of a genesis block.
+    pub async fn spawn() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?).parse()?;
+        let chain = Arc::new(Mutex::new(Chain::new()));
+        let server = spawn(serve(listener, chain.clone()));
+        Ok(Self { url, chain, server })
+    }
+
+    /// The URL of the JSON-RPC API of the chain.
+    pub fn url(&self) -> Url {
+        self.url.clone()
+    }
+
+    /// An L1 client for the chain, which polls it often enough that tests need not wait long for
+    /// the client to see changes.
+    pub fn client(&self) -> anyhow::Result<L1Client> {
+        L1ClientOptions {
+            l1_polling_interval: POLLING_INTERVAL,
+            l1_retry_delay: POLLING_INTERVAL,
+            ..Default::default()
+        }
+        .connect(vec![self.url()])
+    }
+
+    /// Wait until some client is watching the chain for new blocks.
+    ///
+    /// Clients watch for blocks once their [tasks](L1Client::spawn_tasks) are running. A client
+    /// does not see blocks which are produced before it starts watching, other than the head of the
+    /// chain at the time, so tests which depend on the client seeing every block should wait for
+    /// this first.
+    pub async fn wait_for_block_watcher(&self) {
+        while self.chain.lock().block_filters.is_empty() {
+            sleep(POLLING_INTERVAL).await;
+        }
+    }
+
+    /// The number of the latest block.
+    pub fn head(&self) -> u64 {
+        self.chain.lock().head().number
+    }
+
+    /// The number of the latest finalized block, if any.
+    pub fn finalized(&self) -> Option<u64> {
+        self.chain.lock().finalized
+    }
+
+    /// The hash of the canonical block `number`, if there is one.
+    pub fn block_hash(&self, number: u64) -> Option<B256> {
+        self.chain
+            .lock()
+            .canonical
+            .get(number as usize)
+            .map(|block| block.hash)
+    }
+
+    /// Produce a block containing the events emitted since the last block, and return its number.
+    pub fn mine(&self) -> u64 {
+        self.chain.lock().mine()
+    }
+
+    /// Produce `n` blocks, and return the number of the last one.
+    pub fn mine_blocks(&self, n: u64) -> u64 {
+        let mut chain = self.chain.lock();
+        for _ in 0..n {
+            chain.mine();
+        }
+        chain.head().number
+    }
+
+    /// Finalize the chain up to block `number`.
+    ///
+    /// # Panics
+    ///
+    /// If block `number` has not been produced yet, or a later block is already finalized.
+    pub fn finalize(&self, number: u64) {
+        let mut chain = self.chain.lock();
+        let head = chain.head().number;
+        assert!(
+            number <= head,
+            "cannot finalize block {number}, the head is {head}"
+        );
+        assert!(
+            chain.finalized <= Some(number),
+            "cannot finalize block {number}, block {:?} is already finalized",
+            chain.finalized,
+        );
+        chain.finalized = Some(number);
+    }
+
+    /// Replace the latest `depth` blocks with as many new blocks, and return the number of the
+    /// first block replaced.
+    ///
+    /// Events in the replaced blocks are dropped; events emitted since the last block are included
+    /// in the first new block.
+    ///
+    /// # Panics
+    ///
+    /// If the reorg would replace the genesis block or a finalized block.
+    pub fn reorg(&self, depth: u64) -> u64 {
+        let mut chain = self.chain.lock();
+        let head = chain.head().number;
+        assert!(
+            depth > 0 && depth <= head,
+            "cannot reorg {depth} blocks with head {head}"
+        );
+        let fork = head - depth + 1;
+        assert!(
+            chain.finalized.is_none_or(|finalized| finalized < fork),
+            "cannot reorg from block {fork}, block {:?} is finalized",
+            chain.finalized,
+        );
+
+        chain.canonical.truncate(fork as usize);
+        for _ in 0..depth {
+            chain.mine();
+        }
+        tracing::info!(fork, depth, "simulated L1 reorg");
+        fork
+    }
+
+    /// Deploy a stake table contract, initialized in the next block, and return its address.
+    ///
+    /// The contract only answers `initializedAtBlock`; its events are the ones
+    /// [emitted](Self::emit) from its address.
+    pub fn deploy_stake_table(&self) -> Address {
+        let address = Address::random();
+        let mut chain = self.chain.lock();
+        let initialized_at = chain.head().number + 1;
+        chain.stake_tables.insert(address, initialized_at);
+        address
+    }
+
+    /// Emit `event` from `contract` in the next block.
+    pub fn emit(&self, contract: Address, event: &impl SolEvent) {
+        self.chain
+            .lock()
+            .pending_logs
+            .push((contract, event.encode_log_data()));
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Block {
+    number: u64,
+    hash: B256,
+    parent_hash: B256,
+    timestamp: u64,
+    logs: Vec<(Address, LogData)>,
+}
+
+#[derive(Debug)]
+struct Chain {
+    /// Canonical blocks, indexed by number
+    canonical: Vec<Block>,
+    /// Every block produced, including blocks which have since been reorged out
+    by_hash: HashMap<B256, Block>,
+    finalized: Option<u64>,
+    /// Events to include in the next block
+    pending_logs: Vec<(Address, LogData)>,
+    /// The block at which each stake table contract was initialized
+    stake_tables: HashMap<Address, u64>,
+    /// Hashes of the canonical blocks produced since each block filter was last polled
+    block_filters: HashMap<U256, Vec<B256>>,
+    next_filter_id: U256,
+    /// Distinguishes blocks which replace others at the same height
+    nonce: u64,
+}
+
+impl Chain {
+    fn new() -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let genesis = Block {
+            number: 0,
+            hash: keccak256(timestamp.to_be_bytes()),
+            parent_hash: B256::ZERO,
+            timestamp,
+            logs: vec![],
+        };
+        Self {
+            canonical: vec![genesis.clone()],
+            by_hash: [(genesis.hash, genesis)].into(),
+            finalized: None,
+            pending_logs: vec![],
+            stake_tables: Default::default(),
+            block_filters: Default::default(),
+            next_filter_id: U256::ZERO,
+            nonce: 0,
+        }
+    }
+
+    fn head(&self) -> &Block {
+        self.canonical.last().unwrap()
+    }
+
+    fn mine(&mut self) -> u64 {
+        let parent = self.head();
+        let (number, parent_hash, timestamp) = (
+            parent.number + 1,
+            parent.hash,
+            parent.timestamp + BLOCK_TIME,
+        );
+        self.nonce += 1;
+        let block = Block {
+            number,
+            hash: keccak256(
+                [
+                    &number.to_be_bytes()[..],
+                    parent_hash.as_slice(),
+                    &self.nonce.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            parent_hash,
+            timestamp,
+            logs: std::mem::take(&mut self.pending_logs),
+        };
+
+        for changes in self.block_filters.values_mut() {
+            changes.push(block.hash);
+        }
+        self.by_hash.insert(block.hash, block.clone());
+        self.canonical.push(block);
+        number
+    }
+
+    /// The number of the block `tag` refers to.
+    fn resolve(&self, tag: BlockNumberOrTag) -> u64 {
+        match tag {
+            BlockNumberOrTag::Number(number) => number,
+            BlockNumberOrTag::Earliest => 0,
+            BlockNumberOrTag::Latest | BlockNumberOrTag::Pending => self.head().number,
+            BlockNumberOrTag::Safe | BlockNumberOrTag::Finalized => {
+                self.finalized.unwrap_or_default()
+            },
+        }
+    }
+
+    fn block(&self, tag: BlockNumberOrTag) -> Option<&Block> {
+        if matches!(tag, BlockNumberOrTag::Safe | BlockNumberOrTag::Finalized)
+            && self.finalized.is_none()
+        {
+            return None;
+        }
+        self.canonical.get(self.resolve(tag) as usize)
+    }
+
+    fn handle(&mut self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        let param = |i: usize| params.get(i).cloned().unwrap_or_default();
+        match method {
+            "eth_chainId" => Ok(quantity(1)),
+            "eth_blockNumber" => Ok(quantity(self.head().number)),
+            "eth_getBlockByNumber" => {
+                let tag = parse(param(0))?;
+                Ok(self.block(tag).map_or(Value::Null, block_json))
+            },
+            "eth_getBlockByHash" => {
+                let hash = parse(param(0))?;
+                Ok(self.by_hash.get(&hash).map_or(Value::Null, block_json))
+            },
+            "eth_newBlockFilter" => {
+                self.next_filter_id += U256::from(1);
+                self.block_filters.insert(self.next_filter_id, vec![]);
+                Ok(json!(self.next_filter_id))
+            },
+            "eth_getFilterChanges" => {
+                let id = parse(param(0))?;
+                let changes = self
+                    .block_filters
+                    .get_mut(&id)
+                    .ok_or((SERVER_ERROR, "filter not found".to_string()))?;
+                Ok(json!(std::mem::take(changes)))
+            },
+            "eth_uninstallFilter" => {
+                let id = parse(param(0))?;
+                Ok(json!(self.block_filters.remove(&id).is_some()))
+            },
+            "eth_getLogs" => Ok(Value::Array(self.logs(&parse(param(0))?))),
+            "eth_call" => self.call(&param(0)),
+            // No contract on the simulated L1 is a proxy.
+            "eth_getStorageAt" => Ok(json!(B256::ZERO)),
+            _ => Err((
+                METHOD_NOT_FOUND,
+                format!("{method} is not supported by the simulated L1"),
+            )),
+        }
+    }
+
+    fn logs(&self, filter: &Filter) -> Vec<Value> {
+        let blocks: Vec<&Block> = match &filter.block_option {
+            FilterBlockOption::AtBlockHash(hash) => self.by_hash.get(hash).into_iter().collect(),
+            FilterBlockOption::Range {
+                from_block,
+                to_block,
+            } => {
+                let from = from_block.map_or(0, |tag| self.resolve(tag)) as usize;
+                let to = to_block.map_or(self.head().number, |tag| self.resolve(tag)) as usize;
+                self.canonical
+                    .iter()
+                    .skip(from)
+                    .take((to + 1).saturating_sub(from))
+                    .collect()
+            },
+        };
+
+        let mut logs = vec![];
+        for block in blocks {
+            for (index, (address, data)) in block.logs.iter().enumerate() {
+                let matches = filter.address.matches(address)
+                    && filter.topics.iter().enumerate().all(|(i, topic)| {
+                        topic.is_empty() || data.topics().get(i).is_some_and(|t| topic.matches(t))
+                    });
+                if matches {
+                    logs.push(log_json(block, index as u64, *address, data));
+                }
+            }
+        }
+        logs
+    }
+
+    fn call(&self, tx: &Value) -> Result<Value, RpcError> {
+        let to: Address = parse(tx["to"].clone())?;
+        let input: Bytes = parse(
+            tx.get("input")
+                .or_else(|| tx.get("data"))
+                .cloned()
+                .unwrap_or_default(),
+        )?;
+        match self.stake_tables.get(&to) {
+            Some(initialized_at)
+                if input.starts_with(&StakeTable::initializedAtBlockCall::SELECTOR) =>
+            {
+                let output = U256::from(*initialized_at).to_be_bytes::<32>();
+                Ok(json!(Bytes::from(output.to_vec())))
+            },
+            _ => Err((SERVER_ERROR, "execution reverted".to_string())),
+        }
+    }
+}
+
+fn quantity(n: u64) -> Value {
+    json!(format!("{n:#x}"))
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    serde_json::from_value(value).map_err(|err| (INVALID_PARAMS, err.to_string()))
+}
+
+fn block_json(block: &Block) -> Value {
+    json!({
+        "hash": block.hash,
+        "parentHash": block.parent_hash,
+        "sha3Uncles": B256::ZERO,
+        "miner": Address::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::default(),
+        "difficulty": quantity(0),
+        "number": quantity(block.number),
+        "gasLimit": quantity(30_000_000),
+        "gasUsed": quantity(0),
+        "timestamp": quantity(block.timestamp),
+        "extraData": Bytes::new(),
+        "mixHash": B256::ZERO,
+        "nonce": B64::ZERO,
+        "transactions": [],
+        "uncles": [],
+    })
+}
+
+fn log_json(block: &Block, index: u64, address: Address, data: &LogData) -> Value {
+    json!({
+        "address": address,
+        "topics": data.topics(),
+        "data": data.data,
+        "blockHash": block.hash,
+        "blockNumber": quantity(block.number),
+        "transactionHash": keccak256([block.hash.as_slice(), &index.to_be_bytes()].concat()),
+        "transactionIndex": quantity(index),
+        "logIndex": quantity(index),
+        "removed": false,
+    })
+}
+
+fn respond(chain: &Mutex<Chain>, request: Value) -> Value {
+    let id = request["id"].clone();
+    let method = request["method"].as_str().unwrap_or_default();
+    let params: &[Value] = match &request["params"] {
+        Value::Array(params) => params.as_slice(),
+        _ => &[],
+    };
+    match chain.lock().handle(method, params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => {
+            tracing::debug!(method, code, error = %message, "simulated L1 request failed");
+            json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+        },
+    }
+}
+
+async fn serve(listener: TcpListener, chain: Arc<Mutex<Chain>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let chain = chain.clone();
+                spawn(async move {
+                    if let Err(err) = serve_connection(stream, chain).await {
+                        tracing::debug!("simulated L1 connection closed: {err:#}");
+                    }
+                });
+            },
+            Err(err) => tracing::warn!("simulated L1 failed to accept connection: {err:#}"),
+        }
+    }
+}
+
+/// Answer JSON-RPC requests over a keep-alive HTTP connection, until the client closes it.
+async fn serve_connection(stream: TcpStream, chain: Arc<Mutex<Chain>>) -> io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    loop {
+        // Of the headers, which end with an empty line, we only need the length of the body.
+        let mut content_length = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stream.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value
+                        .trim()
+                        .parse()
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await?;
+
+        let response = match serde_json::from_slice(&body) {
+            Ok(Value::Array(batch)) => batch
+                .into_iter()
+                .map(|request| respond(&chain, request))
+                .collect(),
+            Ok(request) => respond(&chain, request),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": err.to_string() },
+            }),
+        }
+        .to_string();
+        let header = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+            response.len()
+        );
+        let stream = stream.get_mut();
+        stream.write_all(header.as_bytes()).await?;
+        stream.write_all(response.as_bytes()).await?;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{pin_mut, poll};
+    use hotshot_contract_adapter::sol_types::StakeTable::{Delegated, ValidatorRegistered};
+    use sequencer_utils::test_utils::setup_test;
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{v0::impls::testing::TestValidator, v0_3::StakeTableFetcher};
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_simulated_l1_blocks_and_reorgs() {
+        setup_test();
+
+        let l1 = SimulatedL1::spawn().await.unwrap();
+        let client = l1.client().unwrap();
+        client.spawn_tasks().await;
+        l1.wait_for_block_watcher().await;
+
+        // Blocks are only produced and finalized when the test says so.
+        assert_eq!(l1.mine_blocks(3), 3);
+        client.wait_for_block(3).await;
+        assert_eq!(client.snapshot().await.finalized, None);
+        l1.finalize(1);
+        l1.mine();
+        let finalized = client.wait_for_finalized_block(1).await;
+        assert_eq!(finalized.hash(), l1.block_hash(1).unwrap());
+
+        // The client notices when blocks it has seen are replaced.
+        let reorg = client.wait_for_reorg();
+        pin_mut!(reorg);
+        // Start listening for events before the reorg happens.
+        assert!(poll!(&mut reorg).is_pending());
+        let old_head = l1.block_hash(4).unwrap();
+        assert_eq!(l1.reorg(2), 3);
+        assert_eq!(l1.head(), 4);
+        assert_ne!(l1.block_hash(4).unwrap(), old_head);
+        let reorged_from = timeout(Duration::from_secs(10), reorg).await.unwrap();
+        assert_eq!(reorged_from, 3);
+
+        client.shut_down_tasks().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_simulated_l1_stake_table_events() {
+        setup_test();
+
+        let l1 = SimulatedL1::spawn().await.unwrap();
+        let client = l1.client().unwrap();
+        l1.mine_blocks(2);
+        let stake_table = l1.deploy_stake_table();
+
+        let validator = TestValidator::random();
+        l1.emit(
+            stake_table,
+            &ValidatorRegistered {
+                account: validator.account,
+                blsVk: validator.bls_vk.clone().into(),
+                schnorrVk: validator.schnorr_vk.clone().into(),
+                commission: validator.commission,
+            },
+        );
+        let delegated = Delegated {
+            delegator: Address::random(),
+            validator: validator.account,
+            amount: U256::from(10),
+        };
+        l1.emit(stake_table, &delegated);
+        l1.mine();
+        // Events of other contracts are not stake table events.
+        l1.emit(Address::random(), &delegated);
+        l1.mine();
+
+        let validators =
+            StakeTableFetcher::fetch_all_validators(client.clone(), stake_table, l1.head())
+                .await
+                .unwrap();
+        assert_eq!(validators.len(), 1);
+        assert_eq!(validators[&validator.account].stake, U256::from(10));
+
+        // Events in blocks which are reorged out are gone.
+        l1.reorg(2);
+        let validators = StakeTableFetcher::fetch_all_validators(client, stake_table, l1.head())
+            .await
+            .unwrap();
+        assert!(validators.is_empty());
+    }
+}
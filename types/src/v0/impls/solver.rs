@@ -1,8 +1,18 @@
 use committable::{Commitment, Committable};
 use hotshot::types::SignatureKey;
-
-use super::v0_99::{RollupRegistrationBody, RollupUpdatebody};
-use crate::v0::utils::{Update, Update::Set};
+use hotshot_types::traits::{
+    node_implementation::ConsensusTime, signature_key::BuilderSignatureKey,
+};
+
+use super::v0_99::{
+    BuilderRegistration, BuilderRegistrationBody, BundleReservation, BundleReservationBody,
+    RollupRegistrationBody, RollupUpdatebody,
+};
+use crate::{
+    eth_signature_key::{EthKeyPair, SigningError},
+    v0::utils::{Update, Update::Set},
+    FeeAccount,
+};
 
 impl Committable for RollupRegistrationBody {
     fn tag() -> String {
@@ -93,3 +103,75 @@ impl Committable for RollupUpdatebody {
         comm.finalize()
     }
 }
+
+impl Committable for BuilderRegistrationBody {
+    fn tag() -> String {
+        "BUILDER_REGISTRATION".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        committable::RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("account", &self.account.to_fixed_bytes())
+            .var_size_field("url", self.url.as_str().as_ref())
+            .finalize()
+    }
+}
+
+impl BuilderRegistrationBody {
+    /// Sign the registration with the key of the builder account.
+    pub fn signed(self, key: &EthKeyPair) -> Result<BuilderRegistration, SigningError> {
+        let signature = FeeAccount::sign_builder_message(key, self.commit().as_ref())?;
+        Ok(BuilderRegistration {
+            body: self,
+            signature,
+        })
+    }
+}
+
+impl BuilderRegistration {
+    /// Check that the registration is signed by the key of the builder account.
+    pub fn verify(&self) -> bool {
+        self.body
+            .account
+            .validate_builder_signature(&self.signature, self.body.commit().as_ref())
+    }
+}
+
+impl Committable for BundleReservationBody {
+    fn tag() -> String {
+        "BUNDLE_RESERVATION".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        let mut comm = committable::RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("account", &self.account.to_fixed_bytes())
+            .u64_field("view", self.view.u64())
+            .constant_str("namespaces");
+
+        for ns in &self.namespaces {
+            comm = comm.u64(u64::from(*ns));
+        }
+
+        comm.finalize()
+    }
+}
+
+impl BundleReservationBody {
+    /// Sign the reservation with the key of the builder account.
+    pub fn signed(self, key: &EthKeyPair) -> Result<BundleReservation, SigningError> {
+        let signature = FeeAccount::sign_builder_message(key, self.commit().as_ref())?;
+        Ok(BundleReservation {
+            body: self,
+            signature,
+        })
+    }
+}
+
+impl BundleReservation {
+    /// Check that the reservation is signed by the key of the builder account.
+    pub fn verify(&self) -> bool {
+        self.body
+            .account
+            .validate_builder_signature(&self.signature, self.body.commit().as_ref())
+    }
+}
@@ -22,6 +22,7 @@ use hotshot_types::{
         election::{generate_stake_cdf, select_randomized_leader, RandomizedCommittee},
         DrbResult,
     },
+    leader_schedule::LeaderSchedule,
     message::UpgradeLock,
     stake_table::StakeTableEntry,
     traits::{
@@ -40,8 +41,8 @@ use tokio::time::sleep;
 use super::v0_3::DAMembers;
 use super::{
     traits::{MembershipPersistence, StateCatchup},
-    v0_3::{EventKey, StakeTableEvent, StakeTableFetcher, Validator},
-    v0_99::ChainConfig,
+    v0_3::{EventKey, IndexedStake, KeyRotation, StakeTableEvent, StakeTableFetcher, Validator},
+    v0_4::ChainConfig,
     Header, L1Client, Leaf2, PubKey, SeqTypes,
 };
 use crate::{EpochVersion, SequencerVersions};
@@ -358,7 +359,22 @@ impl StakeTableFetcher {
         contract: Address,
         to_block: u64,
     ) -> anyhow::Result<Vec<(EventKey, StakeTableEvent)>> {
-        let res = self.persistence.load_events().await?;
+        let mut res = self.persistence.load_events().await?;
+
+        // Only resume from stored events which were scanned from this contract. Events stored
+        // before checkpoints were recorded have none, and are resumed from as they always were.
+        let checkpoint = self.persistence.load_l1_checkpoint(contract).await?;
+        if let (Some((block, _)), Some(checkpoint)) = (&res, checkpoint) {
+            if checkpoint.l1_block != *block {
+                tracing::warn!(
+                    %contract,
+                    block,
+                    ?checkpoint,
+                    "stored stake table events do not match the checkpoint of this contract",
+                );
+                res = None;
+            }
+        }
 
         let from_block = res.as_ref().map(|(block, _)| block + 1);
 
@@ -589,13 +605,31 @@ impl StakeTableFetcher {
 
         tracing::info!("storing events in storage to_block={to_block:?}");
         self.persistence
-            .store_events(to_block, events.clone())
+            .store_events_with_checkpoint(contract, to_block, events.clone())
             .await
             .inspect_err(|e| tracing::error!("failed to store events. err={e}"))?;
+        let lag = self.l1_client.scan_lag(to_block).await;
+        self.l1_client.metrics().stake_table_scan_lag.set(lag);
 
         active_validator_set_from_l1_events(events.into_iter().map(|(_, e)| e))
     }
 
+    /// Forget the stored events of the contract at `contract` from L1 blocks at or above `fork`,
+    /// which have been replaced by a reorg, so that they are fetched again from the new chain.
+    pub async fn roll_back_events(&self, contract: Address, fork: u64) -> anyhow::Result<()> {
+        let Some((block, mut events)) = self.persistence.load_events().await? else {
+            return Ok(());
+        };
+        if block < fork {
+            return Ok(());
+        }
+        tracing::warn!(%contract, fork, block, "rolling back stake table events after L1 reorg");
+        events.retain(|((event_block, _), _)| *event_block < fork);
+        self.persistence
+            .store_events_with_checkpoint(contract, fork.saturating_sub(1), events)
+            .await
+    }
+
     // Only used by staking CLI which doesn't have persistence
     pub async fn fetch_all_validators(
         l1_client: L1Client,
@@ -608,6 +642,18 @@ impl StakeTableFetcher {
         validators_from_l1_events(sorted.into_iter().map(|(_, e)| e))
     }
 
+    // Used to bootstrap a node without persistence, before it has joined the network
+    pub async fn fetch_active_validators(
+        l1_client: L1Client,
+        contract: Address,
+        to_block: u64,
+    ) -> anyhow::Result<IndexMap<Address, Validator<BLSPubKey>>> {
+        let events = Self::fetch_events_from_contract(l1_client, contract, None, to_block).await?;
+        let sorted = events.sort_events()?;
+        // Process the sorted events and return the active validator set.
+        active_validator_set_from_l1_events(sorted.into_iter().map(|(_, e)| e))
+    }
+
     pub async fn fetch(
         &self,
         epoch: Epoch,
@@ -694,6 +740,23 @@ pub struct EpochCommittee {
     stake_table: IndexMap<PubKey, PeerConfig<SeqTypes>>,
     validators: IndexMap<Address, Validator<BLSPubKey>>,
     address_mapping: HashMap<BLSPubKey, Address>,
+    /// Validators whose consensus key changed from the previous epoch.
+    key_rotations: Vec<KeyRotation>,
+    /// Entries for the keys of validators which rotated their consensus key at the start or end of
+    /// this epoch, other than the key in `stake_table`.
+    key_aliases: HashMap<PubKey, PeerConfig<SeqTypes>>,
+}
+
+impl EpochCommittee {
+    /// Let `key` identify the validator `account` in this epoch, with stake table entry `config`.
+    fn add_alias(&mut self, key: BLSPubKey, account: Address, config: PeerConfig<SeqTypes>) {
+        if self.stake_table.contains_key(&key) {
+            // The key belongs to another validator in this epoch.
+            return;
+        }
+        self.address_mapping.entry(key).or_insert(account);
+        self.key_aliases.entry(key).or_insert(config);
+    }
 }
 
 impl EpochCommittees {
@@ -720,16 +783,7 @@ impl EpochCommittees {
             .values()
             .map(|v| {
                 address_mapping.insert(v.stake_table_key, v.account);
-                (
-                    v.stake_table_key,
-                    PeerConfig {
-                        stake_table_entry: BLSPubKey::stake_table_entry(
-                            &v.stake_table_key,
-                            v.stake,
-                        ),
-                        state_ver_key: v.state_ver_key.clone(),
-                    },
-                )
+                (v.stake_table_key, peer_config(v))
             })
             .collect();
 
@@ -743,8 +797,127 @@ impl EpochCommittees {
                 stake_table,
                 validators,
                 address_mapping,
+                key_rotations: vec![],
+                key_aliases: HashMap::new(),
             },
         );
+
+        // Stake tables may be loaded in any order, for instance from storage after a restart, so
+        // link this epoch with whichever of its neighbours is already known.
+        self.link_key_rotations(epoch);
+        self.link_key_rotations(epoch + 1);
+    }
+
+    /// Update the stake table for `epoch`, and for the epoch before if it is given and not yet
+    /// known.
+    fn update_stake_tables(
+        &mut self,
+        previous: Option<IndexedStake>,
+        epoch: EpochNumber,
+        validators: IndexMap<Address, Validator<BLSPubKey>>,
+    ) {
+        if let Some((previous_epoch, previous)) = previous {
+            if !self.state.contains_key(&previous_epoch) {
+                self.update_stake_table(previous_epoch, previous);
+            }
+        }
+        self.update_stake_table(epoch, validators);
+    }
+
+    /// The stake table for the epoch before `epoch` from storage, if it is not held in memory.
+    async fn load_previous_stake_table(&self, epoch: EpochNumber) -> Option<IndexedStake> {
+        let previous = EpochNumber::new(epoch.checked_sub(1)?);
+        if self.state.contains_key(&previous) {
+            return None;
+        }
+        match self.fetcher.persistence.load_stake(previous).await {
+            Ok(validators) => validators.map(|validators| (previous, validators)),
+            Err(err) => {
+                tracing::warn!(?err, %previous, "error loading previous stake table");
+                None
+            },
+        }
+    }
+
+    /// Record the validators which rotated their consensus key between the epoch before `epoch`
+    /// and `epoch`, if the stake tables of both are known.
+    ///
+    /// A validator which registered a new consensus key on the L1 uses it from the first epoch
+    /// whose stake table includes it. Around the transition, the validator may still be acting
+    /// under its old key in `epoch`, or already under its new key in the epoch before, so both keys
+    /// identify the validator, with its stake, in both epochs. The stake tables themselves, which
+    /// determine the total stake and the signers of certificates, are left unchanged.
+    fn link_key_rotations(&mut self, epoch: EpochNumber) {
+        let Some(previous_epoch) = epoch.checked_sub(1).map(EpochNumber::new) else {
+            return;
+        };
+        let (Some(previous), Some(next)) =
+            (self.state.get(&previous_epoch), self.state.get(&epoch))
+        else {
+            return;
+        };
+        let key_rotations = key_rotations(&previous.validators, &next.validators);
+        let aliases = key_rotations
+            .iter()
+            .map(|rotation| {
+                tracing::info!(
+                    %epoch,
+                    account = %rotation.account,
+                    old_key = %rotation.old_key,
+                    new_key = %rotation.new_key,
+                    "validator rotated its consensus key",
+                );
+                let previous = &previous.validators[&rotation.account];
+                let next = &next.validators[&rotation.account];
+                (
+                    rotation.clone(),
+                    alias_config(previous, rotation.new_key),
+                    alias_config(next, rotation.old_key),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (rotation, previous_config, next_config) in aliases {
+            if let Some(previous) = self.state.get_mut(&previous_epoch) {
+                previous.add_alias(rotation.new_key, rotation.account, previous_config);
+            }
+            if let Some(next) = self.state.get_mut(&epoch) {
+                next.add_alias(rotation.old_key, rotation.account, next_config);
+            }
+        }
+        if let Some(next) = self.state.get_mut(&epoch) {
+            next.key_rotations = key_rotations;
+        }
+    }
+
+    /// The validators whose consensus key changed at `epoch`.
+    ///
+    /// Only known if the stake tables for both `epoch` and the epoch before are loaded.
+    pub fn key_rotations(&self, epoch: &Epoch) -> anyhow::Result<Vec<KeyRotation>> {
+        Ok(self
+            .state
+            .get(epoch)
+            .context("state for found")?
+            .key_rotations
+            .clone())
+    }
+
+    /// The leader schedule of `epoch`, or before epochs if `epoch` is `None`.
+    ///
+    /// Returns `None` if the DRB result for the epoch is not known yet.
+    pub fn leader_schedule(&self, epoch: Option<Epoch>) -> Option<LeaderSchedule<SeqTypes>> {
+        let Some(epoch) = epoch else {
+            return Some(LeaderSchedule::round_robin(
+                self.non_epoch_committee.eligible_leaders.clone(),
+            ));
+        };
+        let drb_result = self.randomized_committees.get(&epoch)?.drb();
+        let committee = self.state.get(&epoch)?;
+        Some(LeaderSchedule::randomized(
+            epoch,
+            committee.eligible_leaders.clone(),
+            drb_result,
+        ))
     }
 
     pub fn validators(
@@ -851,6 +1024,8 @@ impl EpochCommittees {
                 .collect(),
             validators: Default::default(),
             address_mapping: HashMap::new(),
+            key_rotations: vec![],
+            key_aliases: HashMap::new(),
         };
         map.insert(Epoch::genesis(), epoch_committee.clone());
         // TODO: remove this, workaround for hotshot asking for stake tables from epoch 1
@@ -889,6 +1064,33 @@ impl EpochCommittees {
         }
     }
 
+    /// Get the stake table for `epoch`, including epochs no longer held in memory.
+    ///
+    /// The stake table of every epoch is recorded in persistence when its epoch root is decided, so
+    /// historical stake tables can be recovered to verify certificates from old epochs. Returns
+    /// `None` if the stake table for `epoch` is not known.
+    pub async fn stake_table_at(
+        membership: &RwLock<Self>,
+        epoch: Epoch,
+    ) -> anyhow::Result<Option<Vec<PeerConfig<SeqTypes>>>> {
+        let persistence = {
+            let membership = membership.read().await;
+            if let Some(stake_table) = membership.get_stake_table(&Some(epoch)) {
+                return Ok(Some(stake_table));
+            }
+            membership.fetcher.persistence.clone()
+        };
+
+        let Some(validators) = persistence
+            .load_stake(epoch)
+            .await
+            .context(format!("loading stake table for epoch {epoch}"))?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(validators.values().map(peer_config).collect()))
+    }
+
     fn get_stake_table(&self, epoch: &Option<Epoch>) -> Option<Vec<PeerConfig<SeqTypes>>> {
         if let Some(epoch) = epoch {
             self.state
@@ -900,6 +1102,63 @@ impl EpochCommittees {
     }
 }
 
+/// Validators in `next` whose consensus key differs from their key in `previous`
+fn key_rotations(
+    previous: &IndexMap<Address, Validator<BLSPubKey>>,
+    next: &IndexMap<Address, Validator<BLSPubKey>>,
+) -> Vec<KeyRotation> {
+    next.values()
+        .filter_map(|validator| {
+            let old_key = previous.get(&validator.account)?.stake_table_key;
+            (old_key != validator.stake_table_key).then(|| KeyRotation {
+                account: validator.account,
+                old_key,
+                new_key: validator.stake_table_key,
+            })
+        })
+        .collect()
+}
+
+/// The stake table entry of `validator` under `key`, another consensus key it is known by
+fn alias_config(validator: &Validator<BLSPubKey>, key: BLSPubKey) -> PeerConfig<SeqTypes> {
+    PeerConfig {
+        stake_table_entry: BLSPubKey::stake_table_entry(&key, validator.stake),
+        state_ver_key: validator.state_ver_key.clone(),
+    }
+}
+
+/// The voting success threshold for a committee with `stake_table`
+pub fn success_threshold_of(stake_table: &[PeerConfig<SeqTypes>]) -> U256 {
+    two_thirds_threshold(
+        stake_table
+            .iter()
+            .fold(U256::ZERO, |acc, peer| acc + peer.stake_table_entry.stake()),
+    )
+}
+
+/// Stake strictly greater than two thirds of `total_stake`
+fn two_thirds_threshold(total_stake: U256) -> U256 {
+    let one = U256::ONE;
+    let two = U256::from(2);
+    let three = U256::from(3);
+    if total_stake < U256::MAX / two {
+        ((total_stake * two) / three) + one
+    } else {
+        ((total_stake / three) * two) + two
+    }
+}
+
+/// The stake table entry of a validator
+fn peer_config(validator: &Validator<BLSPubKey>) -> PeerConfig<SeqTypes> {
+    PeerConfig {
+        stake_table_entry: BLSPubKey::stake_table_entry(
+            &validator.stake_table_key,
+            validator.stake,
+        ),
+        state_ver_key: validator.state_ver_key.clone(),
+    }
+}
+
 #[derive(Error, Debug)]
 /// Error representing fail cases for retrieving the stake table.
 enum GetStakeTablesError {
@@ -965,7 +1224,11 @@ impl Membership<SeqTypes> for EpochCommittees {
         if let Some(epoch) = epoch {
             self.state
                 .get(&epoch)
-                .and_then(|h| h.stake_table.get(pub_key))
+                .and_then(|h| {
+                    h.stake_table
+                        .get(pub_key)
+                        .or_else(|| h.key_aliases.get(pub_key))
+                })
                 .cloned()
         } else {
             self.non_epoch_committee
@@ -1038,29 +1301,12 @@ impl Membership<SeqTypes> for EpochCommittees {
 
     /// Get the voting success threshold for the committee
     fn success_threshold(&self, epoch: Option<Epoch>) -> U256 {
-        let total_stake = self.total_stake(epoch);
-        let one = U256::ONE;
-        let two = U256::from(2);
-        let three = U256::from(3);
-        if total_stake < U256::MAX / two {
-            ((total_stake * two) / three) + one
-        } else {
-            ((total_stake / three) * two) + two
-        }
+        two_thirds_threshold(self.total_stake(epoch))
     }
 
     /// Get the voting success threshold for the committee
     fn da_success_threshold(&self, epoch: Option<Epoch>) -> U256 {
-        let total_stake = self.total_da_stake(epoch);
-        let one = U256::ONE;
-        let two = U256::from(2);
-        let three = U256::from(3);
-
-        if total_stake < U256::MAX / two {
-            ((total_stake * two) / three) + one
-        } else {
-            ((total_stake / three) * two) + two
-        }
+        two_thirds_threshold(self.total_da_stake(epoch))
     }
 
     /// Get the voting failure threshold for the committee
@@ -1102,6 +1348,23 @@ impl Membership<SeqTypes> for EpochCommittees {
             return None;
         }
 
+        // Consensus keys rotated at the start of this epoch are only recognized alongside the stake
+        // table of the previous epoch, which a restarted node may not have loaded yet.
+        let previous = self.load_previous_stake_table(epoch).await;
+
+        // If the stake table for this epoch was recorded before, there is no need to fetch it from
+        // the L1 again.
+        match self.fetcher.persistence.load_stake(epoch).await {
+            Ok(Some(stake_tables)) => {
+                tracing::info!("Loaded stake table for epoch {epoch} from storage");
+                return Some(Box::new(move |committee: &mut Self| {
+                    committee.update_stake_tables(previous, epoch, stake_tables);
+                }));
+            },
+            Ok(None) => {},
+            Err(e) => tracing::warn!(?e, "`add_epoch_root`, error loading stake table"),
+        }
+
         let stake_tables = self.fetcher.fetch(epoch, block_header).await?;
 
         if let Err(e) = self
@@ -1114,7 +1377,7 @@ impl Membership<SeqTypes> for EpochCommittees {
         }
 
         Some(Box::new(move |committee: &mut Self| {
-            committee.update_stake_table(epoch, stake_tables);
+            committee.update_stake_tables(previous, epoch, stake_tables);
         }))
     }
 
@@ -1141,8 +1404,10 @@ impl Membership<SeqTypes> for EpochCommittees {
         epoch: Epoch,
     ) -> anyhow::Result<Leaf2> {
         let peers = membership.read().await.fetcher.peers.clone();
-        let stake_table = membership.read().await.stake_table(Some(epoch)).clone();
-        let success_threshold = membership.read().await.success_threshold(Some(epoch));
+        let stake_table = Self::stake_table_at(&membership, epoch)
+            .await?
+            .with_context(|| format!("no stake table known for epoch {epoch}"))?;
+        let success_threshold = success_threshold_of(&stake_table);
         // Fetch leaves from peers
         let leaf: Leaf2 = peers
             .fetch_leaf(block_height, stake_table.clone(), success_threshold)
@@ -1157,8 +1422,10 @@ impl Membership<SeqTypes> for EpochCommittees {
         epoch: Epoch,
     ) -> anyhow::Result<DrbResult> {
         let peers = membership.read().await.fetcher.peers.clone();
-        let stake_table = membership.read().await.stake_table(Some(epoch)).clone();
-        let success_threshold = membership.read().await.success_threshold(Some(epoch));
+        let stake_table = Self::stake_table_at(&membership, epoch)
+            .await?
+            .with_context(|| format!("no stake table known for epoch {epoch}"))?;
+        let success_threshold = success_threshold_of(&stake_table);
 
         tracing::debug!(
             "Getting DRB for epoch {:?}, block height {:?}",
@@ -1316,7 +1583,7 @@ mod tests {
     use sequencer_utils::test_utils::setup_test;
 
     use super::*;
-    use crate::v0::impls::testing::*;
+    use crate::v0::impls::{mock::MockStateCatchup, testing::*, v0_1::NoStorage};
 
     #[test]
     fn test_from_l1_events() -> anyhow::Result<()> {
@@ -1477,4 +1744,87 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_key_rotations() {
+        let unchanged = Validator::mock();
+        let rotated = Validator::mock();
+        let previous: IndexMap<_, _> = [
+            (unchanged.account, unchanged.clone()),
+            (rotated.account, rotated.clone()),
+        ]
+        .into_iter()
+        .collect();
+
+        let new_key = BLSPubKey::generated_from_seed_indexed([3; 32], 0).0;
+        let mut next = previous.clone();
+        next[&rotated.account].stake_table_key = new_key;
+        // Validators which join in the next epoch have not rotated anything.
+        let joined = Validator::mock();
+        next.insert(joined.account, joined);
+
+        assert_eq!(
+            key_rotations(&previous, &next),
+            vec![KeyRotation {
+                account: rotated.account,
+                old_key: rotated.stake_table_key,
+                new_key,
+            }]
+        );
+        assert_eq!(key_rotations(&previous, &previous), vec![]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_key_rotation_aliases() {
+        let unchanged = Validator::mock();
+        let rotated = Validator::mock();
+        let previous: IndexMap<_, _> = [
+            (unchanged.account, unchanged.clone()),
+            (rotated.account, rotated.clone()),
+        ]
+        .into_iter()
+        .collect();
+        let new_key = BLSPubKey::generated_from_seed_indexed([3; 32], 0).0;
+        let mut next = previous.clone();
+        next[&rotated.account].stake_table_key = new_key;
+
+        let l1 = L1Client::new(vec!["http://localhost:3331".parse().unwrap()]).unwrap();
+        let mut committees = EpochCommittees::new_stake(
+            vec![],
+            vec![],
+            l1,
+            ChainConfig::default(),
+            Arc::new(MockStateCatchup::default()),
+            NoStorage,
+        );
+
+        // Load the later epoch first, as a restarted node may.
+        let (epoch, previous_epoch) = (EpochNumber::new(6), EpochNumber::new(5));
+        committees.update_stake_table(epoch, next);
+        assert_eq!(committees.key_rotations(&epoch).unwrap(), vec![]);
+        committees.update_stake_table(previous_epoch, previous);
+        assert_eq!(
+            committees.key_rotations(&epoch).unwrap(),
+            vec![KeyRotation {
+                account: rotated.account,
+                old_key: rotated.stake_table_key,
+                new_key,
+            }]
+        );
+
+        // Both keys identify the validator, with its stake, in both epochs.
+        for e in [previous_epoch, epoch] {
+            for key in [rotated.stake_table_key, new_key] {
+                let entry = committees.stake(&key, Some(e)).unwrap();
+                assert_eq!(entry.stake_table_entry.stake(), rotated.stake);
+                assert_eq!(committees.address(&e, key).unwrap(), rotated.account);
+            }
+            // The stake table itself still counts the validator once.
+            assert_eq!(committees.stake_table(Some(e)).len(), 2);
+            assert_eq!(
+                committees.total_stake(Some(e)),
+                unchanged.stake + rotated.stake
+            );
+        }
+    }
 }
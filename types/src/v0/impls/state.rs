@@ -40,10 +40,11 @@ use super::{
 };
 use crate::{
     traits::StateCatchup,
-    v0_99::{ChainConfig, FullNetworkTx, IterableFeeInfo, ResolvableChainConfig},
+    v0_4::{ChainConfig, ResolvableChainConfig},
+    v0_99::{FullNetworkTx, IterableFeeInfo},
     BlockMerkleTree, Delta, FeeAccount, FeeAmount, FeeInfo, FeeMerkleTree, Header, Leaf2,
-    NsTableValidationError, PayloadByteLen, SeqTypes, UpgradeType, BLOCK_MERKLE_TREE_HEIGHT,
-    FEE_MERKLE_TREE_HEIGHT,
+    NamespaceRegistryError, NsTableValidationError, PayloadByteLen, SeqTypes, UpgradeType,
+    BLOCK_MERKLE_TREE_HEIGHT, FEE_MERKLE_TREE_HEIGHT,
 };
 
 /// This enum is not used in code but functions as an index of
@@ -109,6 +110,8 @@ pub enum ProposalValidationError {
     },
     #[error("Invalid namespace table: {0}")]
     InvalidNsTable(NsTableValidationError),
+    #[error("Namespace not admitted by the registry: {0}")]
+    NamespaceRegistry(NamespaceRegistryError),
     #[error("Some fee amount or their sum total out of range")]
     SomeFeeAmountOutOfRange,
     #[error("Invalid timestamp: proposal={proposal_timestamp}, parent={parent_timestamp}")]
@@ -449,6 +452,7 @@ impl<'a> Proposal<'a> {
 pub(crate) struct ValidatedTransition<'a> {
     state: ValidatedState,
     expected_chain_config: ChainConfig,
+    instance: &'a NodeState,
     parent: &'a Header,
     proposal: Proposal<'a>,
     view_number: u64,
@@ -457,6 +461,7 @@ pub(crate) struct ValidatedTransition<'a> {
 impl<'a> ValidatedTransition<'a> {
     pub(crate) fn new(
         state: ValidatedState,
+        instance: &'a NodeState,
         parent: &'a Header,
         proposal: Proposal<'a>,
         view_number: u64,
@@ -468,6 +473,7 @@ impl<'a> ValidatedTransition<'a> {
         Self {
             state,
             expected_chain_config,
+            instance,
             parent,
             proposal,
             view_number,
@@ -488,6 +494,7 @@ impl<'a> ValidatedTransition<'a> {
     /// self.validate_l1_finalized()?;
     /// self.validate_l1_head()?;
     /// self.validate_namespace_table()?;
+    /// self.validate_namespace_registry()?;
     /// ```
     pub(crate) fn validate(self) -> Result<Self, ProposalValidationError> {
         self.validate_timestamp()?;
@@ -502,6 +509,7 @@ impl<'a> ValidatedTransition<'a> {
         self.validate_l1_finalized()?;
         self.validate_l1_head()?;
         self.validate_namespace_table()?;
+        self.validate_namespace_registry()?;
 
         Ok(self)
     }
@@ -683,6 +691,23 @@ impl<'a> ValidatedTransition<'a> {
             .validate(&PayloadByteLen(self.proposal.block_size as usize))
             .map_err(ProposalValidationError::from)
     }
+    /// Validate that every namespace in the proposal is admitted by the namespace registry the
+    /// chain config commits to, if any.
+    fn validate_namespace_registry(&self) -> Result<(), ProposalValidationError> {
+        let Some(registry) = self
+            .instance
+            .namespace_registry(&self.expected_chain_config)?
+        else {
+            return Ok(());
+        };
+        let ns_table = self.proposal.header.ns_table();
+        for index in ns_table.iter() {
+            if let Some(namespace) = ns_table.read_ns_id(&index) {
+                registry.admit(namespace)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(any(test, feature = "testing"))]
@@ -707,6 +732,12 @@ impl From<NsTableValidationError> for ProposalValidationError {
     }
 }
 
+impl From<NamespaceRegistryError> for ProposalValidationError {
+    fn from(err: NamespaceRegistryError) -> Self {
+        Self::NamespaceRegistry(err)
+    }
+}
+
 impl From<ProposalValidationError> for BlockError {
     fn from(err: ProposalValidationError) -> Self {
         tracing::error!("Invalid Block Header: {err:#}");
@@ -911,6 +942,7 @@ impl ValidatedState {
             UpgradeType::Fee { chain_config } => chain_config,
             UpgradeType::Marketplace { chain_config } => chain_config,
             UpgradeType::Epoch { chain_config } => chain_config,
+            UpgradeType::BlockLimits { chain_config } => chain_config,
         };
 
         self.chain_config = cf.into();
@@ -962,8 +994,7 @@ pub async fn get_l1_deposits(
 ) -> Vec<FeeInfo> {
     if let (Some(addr), Some(block_info)) = (fee_contract_address, header.l1_finalized()) {
         instance
-            .l1_client
-            .get_finalized_deposits(
+            .finalized_deposits(
                 addr,
                 parent_leaf
                     .block_header()
@@ -1020,6 +1051,7 @@ impl HotShotState<SeqTypes> for ValidatedState {
         // Validate the proposal.
         let validated_state = ValidatedTransition::new(
             validated_state,
+            instance,
             parent_leaf.block_header(),
             Proposal::new(proposed_header, payload_byte_len),
             view_number,
@@ -1195,7 +1227,7 @@ mod test {
     use hotshot::{helpers::initialize_logging, traits::BlockPayload};
     use hotshot_query_service::{testing::mocks::MockVersions, Resolvable};
     use hotshot_types::{
-        data::vid_commitment,
+        data::{null_block, vid_commitment},
         traits::{node_implementation::Versions, signature_key::BuilderSignatureKey, EncodeBytes},
     };
     use sequencer_utils::ser::FromStringOrInteger;
@@ -1205,9 +1237,11 @@ mod test {
     use super::*;
     use crate::{
         eth_signature_key::{BuilderSignature, EthKeyPair},
-        v0_1, v0_2, v0_3,
+        v0_1, v0_2, v0_3, v0_4,
         v0_99::{self, BidTx},
-        BlockSize, FeeAccountProof, FeeMerkleProof, Leaf, Payload, Transaction,
+        BlockSize, FeeAccountProof, FeeMerkleProof, Leaf, NamespaceId, NamespaceRegistry,
+        NamespaceRegistryConfig, Payload, RegisteredNamespace, Transaction,
+        UnregisteredNamespacePolicy,
     };
 
     impl Transaction {
@@ -1251,6 +1285,11 @@ mod test {
                     timestamp: OffsetDateTime::now_utc().unix_timestamp() as u64,
                     ..parent.clone()
                 }),
+                Header::V4(parent) => Header::V4(v0_4::Header {
+                    height: parent.height + 1,
+                    timestamp: OffsetDateTime::now_utc().unix_timestamp() as u64,
+                    ..parent.clone()
+                }),
                 Header::V99(_) => {
                     panic!("You called `Header.next()` on unimplemented version (v3)")
                 },
@@ -1280,6 +1319,11 @@ mod test {
                     builder_signature: Some(sig),
                     ..header.clone()
                 }),
+                Header::V4(header) => Header::V4(v0_4::Header {
+                    fee_info,
+                    builder_signature: Some(sig),
+                    ..header.clone()
+                }),
                 Header::V99(_) => {
                     panic!("You called `Header.sign()` on unimplemented version (v3)")
                 },
@@ -1313,6 +1357,11 @@ mod test {
                     builder_signature: Some(sig),
                     ..parent.clone()
                 }),
+                Header::V4(parent) => Header::V4(v0_4::Header {
+                    fee_info,
+                    builder_signature: Some(sig),
+                    ..parent.clone()
+                }),
                 Header::V99(_) => panic!(
                     "You called `Header.invalid_builder_signature()` on unimplemented version (v3)"
                 ),
@@ -1321,12 +1370,13 @@ mod test {
     }
 
     impl<'a> ValidatedTransition<'a> {
-        fn mock(instance: NodeState, parent: &'a Header, proposal: Proposal<'a>) -> Self {
+        fn mock(instance: &'a NodeState, parent: &'a Header, proposal: Proposal<'a>) -> Self {
             let expected_chain_config = instance.chain_config;
 
             Self {
-                state: instance.genesis_state,
+                state: instance.genesis_state.clone(),
                 expected_chain_config,
+                instance,
                 parent,
                 proposal,
                 view_number: 1,
@@ -1412,7 +1462,7 @@ mod test {
         let proposal = Proposal::new(&header, block_size);
         // Note we are using the same header for parent and proposal,
         // this may be OK depending on what we are testing.
-        ValidatedTransition::mock(NodeState::mock_v2(), &header, proposal)
+        ValidatedTransition::mock(&NodeState::mock_v2(), &header, proposal)
             .validate_l1_head()
             .unwrap();
 
@@ -1433,14 +1483,14 @@ mod test {
 
         // Success Case
         let proposal = Proposal::new(&header, block_size);
-        ValidatedTransition::mock(instance.clone(), &header, proposal)
+        ValidatedTransition::mock(&instance, &header, proposal)
             .validate_builder_fee()
             .unwrap();
 
         // Error Case
         let header = header.invalid_builder_signature();
         let proposal = Proposal::new(&header, block_size);
-        let err = ValidatedTransition::mock(instance, &header, proposal)
+        let err = ValidatedTransition::mock(&instance, &header, proposal)
             .validate_builder_fee()
             .unwrap_err();
 
@@ -1464,7 +1514,7 @@ mod test {
 
         // Success Case
         let proposal = Proposal::new(&header, block_size);
-        ValidatedTransition::mock(instance.clone(), &header, proposal)
+        ValidatedTransition::mock(&instance, &header, proposal)
             .validate_chain_config()
             .unwrap();
 
@@ -1506,7 +1556,7 @@ mod test {
 
         // Error Case
         let proposal = Proposal::new(&header, block_size);
-        let err = ValidatedTransition::mock(instance.clone(), &header, proposal)
+        let err = ValidatedTransition::mock(&instance, &header, proposal)
             .validate_block_size()
             .unwrap_err();
 
@@ -1521,7 +1571,7 @@ mod test {
 
         // Success Case
         let proposal = Proposal::new(&header, 1);
-        ValidatedTransition::mock(instance, &header, proposal)
+        ValidatedTransition::mock(&instance, &header, proposal)
             .validate_block_size()
             .unwrap()
     }
@@ -1539,7 +1589,7 @@ mod test {
         });
 
         let proposal = Proposal::new(&header, block_size);
-        let err = ValidatedTransition::mock(instance.clone(), &header, proposal)
+        let err = ValidatedTransition::mock(&instance, &header, proposal)
             .validate_fee()
             .unwrap_err();
 
@@ -1555,6 +1605,37 @@ mod test {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_validation_null_builder_fee() {
+        initialize_logging();
+        // Setup
+        let tx = Transaction::of_size(20);
+        let (header, block_size) = tx.into_mock_header().await;
+        let Header::V2(header) = header else {
+            panic!("expected a v2 header");
+        };
+        // A block signed with the well-known null block key gets no fee waiver.
+        let null_builder = null_block::builder_key::<SeqTypes>().0;
+        let header = Header::V2(v0_2::Header {
+            fee_info: FeeInfo::new(null_builder, 0),
+            ..header
+        });
+        let state = ValidatedState::default();
+        let instance = NodeState::mock_v2().with_chain_config(ChainConfig {
+            base_fee: 1000.into(), // High expected base fee
+            ..state.chain_config.resolve().unwrap()
+        });
+
+        let proposal = Proposal::new(&header, block_size);
+        let err = ValidatedTransition::mock(&instance, &header, proposal)
+            .validate_fee()
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ProposalValidationError::InsufficientFee { .. }
+        ));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_validation_height() {
         initialize_logging();
@@ -1564,7 +1645,7 @@ mod test {
         let (parent, block_size) = tx.into_mock_header().await;
 
         let proposal = Proposal::new(&parent, block_size);
-        let err = ValidatedTransition::mock(instance.clone(), &parent, proposal)
+        let err = ValidatedTransition::mock(&instance, &parent, proposal)
             .validate_height()
             .unwrap_err();
 
@@ -1583,7 +1664,7 @@ mod test {
         *header.height_mut() += 1;
         let proposal = Proposal::new(&header, block_size);
 
-        ValidatedTransition::mock(instance, &parent, proposal)
+        ValidatedTransition::mock(&instance, &parent, proposal)
             .validate_height()
             .unwrap();
     }
@@ -1628,7 +1709,7 @@ mod test {
 
         let mock_time = OffsetDateTime::now_utc().unix_timestamp() as u64;
         // TODO
-        let err = ValidatedTransition::mock(instance.clone(), &parent, proposal)
+        let err = ValidatedTransition::mock(&instance, &parent, proposal)
             .validate_timestamp()
             .unwrap_err();
 
@@ -1682,7 +1763,7 @@ mod test {
 
         // Success case.
         let proposal = Proposal::new(&header, block_size);
-        ValidatedTransition::mock(instance.clone(), &header, proposal)
+        ValidatedTransition::mock(&instance, &header, proposal)
             .validate_fee_merkle_tree()
             .unwrap();
 
@@ -1717,7 +1798,7 @@ mod test {
 
         // Success case.
         let proposal = Proposal::new(&header, block_size);
-        ValidatedTransition::mock(instance.clone(), &header, proposal)
+        ValidatedTransition::mock(&instance, &header, proposal)
             .validate_block_merkle_tree()
             .unwrap();
 
@@ -1754,13 +1835,13 @@ mod test {
 
         // Success case.
         let proposal = Proposal::new(&header, block_size);
-        ValidatedTransition::mock(NodeState::mock_v2(), &header, proposal)
+        ValidatedTransition::mock(&NodeState::mock_v2(), &header, proposal)
             .validate_namespace_table()
             .unwrap();
 
         // Error case
         let proposal = Proposal::new(&header, 40);
-        let err = ValidatedTransition::mock(NodeState::mock_v2(), &header, proposal)
+        let err = ValidatedTransition::mock(&NodeState::mock_v2(), &header, proposal)
             .validate_namespace_table()
             .unwrap_err();
         tracing::info!(%err, "task failed successfully");
@@ -1772,6 +1853,80 @@ mod test {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_validation_namespace_registry() {
+        initialize_logging();
+        // Setup.
+        let tx = Transaction::of_size(10);
+        let (header, block_size) = tx.into_mock_header().await;
+        let registry = |unregistered| {
+            NamespaceRegistry::new(NamespaceRegistryConfig {
+                unregistered,
+                collisions: Default::default(),
+                namespaces: vec![RegisteredNamespace {
+                    namespace: NamespaceId::from(2u64),
+                    rollup: "alpha".into(),
+                    owner: None,
+                }],
+            })
+            .unwrap()
+        };
+        let with_registry = |registry: NamespaceRegistry| {
+            let instance = NodeState::mock_v2();
+            let chain_config = ChainConfig {
+                namespace_registry: Some(registry.commit()),
+                ..instance.chain_config
+            };
+            instance
+                .with_chain_config(chain_config)
+                .with_namespace_registry(registry)
+        };
+
+        // Without a registry, every namespace is admitted.
+        ValidatedTransition::mock(
+            &NodeState::mock_v2(),
+            &header,
+            Proposal::new(&header, block_size),
+        )
+        .validate_namespace_registry()
+        .unwrap();
+
+        // The transaction's namespace is not registered, which is fine unless the registry rejects
+        // unregistered namespaces.
+        let instance = with_registry(registry(UnregisteredNamespacePolicy::Flag));
+        ValidatedTransition::mock(&instance, &header, Proposal::new(&header, block_size))
+            .validate_namespace_registry()
+            .unwrap();
+
+        let instance = with_registry(registry(UnregisteredNamespacePolicy::Reject));
+        let err = ValidatedTransition::mock(&instance, &header, Proposal::new(&header, block_size))
+            .validate_namespace_registry()
+            .unwrap_err();
+        tracing::info!(%err, "task failed successfully");
+        assert_eq!(
+            ProposalValidationError::NamespaceRegistry(NamespaceRegistryError::Unregistered(
+                NamespaceId::from(1u64)
+            )),
+            err
+        );
+
+        // A node which does not know the registry the chain config commits to cannot validate.
+        let registry = registry(UnregisteredNamespacePolicy::Allow);
+        let instance = NodeState::mock_v2().with_chain_config(ChainConfig {
+            namespace_registry: Some(registry.commit()),
+            ..NodeState::mock_v2().chain_config
+        });
+        let err = ValidatedTransition::mock(&instance, &header, Proposal::new(&header, block_size))
+            .validate_namespace_registry()
+            .unwrap_err();
+        assert_eq!(
+            ProposalValidationError::NamespaceRegistry(NamespaceRegistryError::Unknown(
+                registry.commit()
+            )),
+            err
+        );
+    }
+
     #[test]
     fn test_charge_fee() {
         initialize_logging();
@@ -1927,6 +2082,11 @@ mod test {
                 fee_info: FeeInfo::new(account, data),
                 ..header
             }),
+            Header::V4(header) => Header::V4(v0_4::Header {
+                builder_signature: Some(sig),
+                fee_info: FeeInfo::new(account, data),
+                ..header
+            }),
             Header::V99(header) => Header::V99(v0_99::Header {
                 builder_signature: vec![sig],
                 fee_info: vec![FeeInfo::new(account, data)],
@@ -1988,6 +2148,11 @@ mod test {
                 fee_info: FeeInfo::new(account, data),
                 ..header
             }),
+            Header::V4(header) => Header::V4(v0_4::Header {
+                builder_signature: Some(sig),
+                fee_info: FeeInfo::new(account, data),
+                ..header
+            }),
             Header::V99(header) => Header::V99(v0_99::Header {
                 builder_signature: vec![sig],
                 fee_info: vec![FeeInfo::new(account, data)],
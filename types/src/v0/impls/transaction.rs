@@ -1,7 +1,9 @@
 use committable::{Commitment, Committable};
 use hotshot_query_service::explorer::ExplorerTransaction;
-use hotshot_types::traits::block_contents::Transaction as HotShotTransaction;
-use serde::{de::Error, Deserialize, Deserializer};
+use hotshot_types::traits::block_contents::{
+    Transaction as HotShotTransaction, TransactionPriority,
+};
+use serde::{de::Error, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 
 use super::{NsPayloadBuilder, NsTableBuilder};
 use crate::{NamespaceId, Transaction};
@@ -46,7 +48,25 @@ impl NamespaceId {
 
 impl Transaction {
     pub fn new(namespace: NamespaceId, payload: Vec<u8>) -> Self {
-        Self { namespace, payload }
+        Self {
+            namespace,
+            payload,
+            priority_fee: 0,
+            replacement_key: None,
+        }
+    }
+
+    /// Offer `fee` for the local builder to include this transaction ahead of others
+    pub fn with_priority_fee(mut self, fee: u64) -> Self {
+        self.priority_fee = fee;
+        self
+    }
+
+    /// Replace the pending transaction submitted with the same `key`, if this one offers a higher
+    /// priority fee
+    pub fn with_replacement_key(mut self, key: Vec<u8>) -> Self {
+        self.replacement_key = Some(key);
+        self
     }
 
     pub fn namespace(&self) -> NamespaceId {
@@ -98,6 +118,99 @@ impl HotShotTransaction for Transaction {
             + NsPayloadBuilder::tx_table_header_byte_len();
         len as u64
     }
+
+    fn fairness_group(&self) -> u64 {
+        self.namespace.0
+    }
+
+    fn priority_fee(&self) -> u64 {
+        self.priority_fee
+    }
+
+    fn replacement_key(&self) -> Option<Vec<u8>> {
+        self.replacement_key.clone()
+    }
+
+    fn with_priority(self, priority: TransactionPriority) -> Self {
+        Self {
+            priority_fee: priority.fee,
+            replacement_key: priority.replacement_key,
+            ..self
+        }
+    }
+}
+
+/// Payload or replacement key, encoded in base 64 in JSON
+#[derive(Serialize, Deserialize)]
+struct Bytes(#[serde(with = "base64_bytes")] Vec<u8>);
+
+/// Serializes a borrowed payload the same way as [`Bytes`]
+struct BytesRef<'a>(&'a Vec<u8>);
+
+impl Serialize for BytesRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        base64_bytes::serialize(self.0, serializer)
+    }
+}
+
+/// Fields of a transaction in the binary encoding, which leaves out the priority
+#[derive(Deserialize)]
+#[serde(rename = "Transaction")]
+struct SequencedTransaction {
+    namespace: NamespaceId,
+    payload: Bytes,
+}
+
+/// Fields of a transaction in the JSON encoding, where the priority is optional
+#[derive(Deserialize)]
+#[serde(rename = "Transaction")]
+struct SubmittedTransaction {
+    namespace: NamespaceId,
+    payload: Bytes,
+    #[serde(default)]
+    priority_fee: u64,
+    #[serde(default)]
+    replacement_key: Option<Bytes>,
+}
+
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // The priority is not part of what gets sequenced, so it stays out of the binary encoding,
+        // which must match the transactions reconstructed from block payloads.
+        let with_fee = serializer.is_human_readable() && self.priority_fee != 0;
+        let with_key = serializer.is_human_readable() && self.replacement_key.is_some();
+        let mut state = serializer
+            .serialize_struct("Transaction", 2 + with_fee as usize + with_key as usize)?;
+        state.serialize_field("namespace", &self.namespace)?;
+        state.serialize_field("payload", &BytesRef(&self.payload))?;
+        if with_fee {
+            state.serialize_field("priority_fee", &self.priority_fee)?;
+        }
+        if let Some(key) = self.replacement_key.as_ref().filter(|_| with_key) {
+            state.serialize_field("replacement_key", &BytesRef(key))?;
+        }
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Transaction {
+    fn deserialize<D>(deserializer: D) -> Result<Transaction, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let tx = SubmittedTransaction::deserialize(deserializer)?;
+            Ok(Self {
+                namespace: tx.namespace,
+                payload: tx.payload.0,
+                priority_fee: tx.priority_fee,
+                replacement_key: tx.replacement_key.map(|key| key.0),
+            })
+        } else {
+            let tx = SequencedTransaction::deserialize(deserializer)?;
+            Ok(Self::new(tx.namespace, tx.payload.0))
+        }
+    }
 }
 
 impl Committable for Transaction {
@@ -123,3 +236,35 @@ impl ExplorerTransaction for Transaction {
         self.payload.len() as u64
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_transaction_priority_serialization() {
+        let tx = Transaction::new(NamespaceId(1), vec![1, 2, 3]);
+        let prioritized = tx
+            .clone()
+            .with_priority_fee(5)
+            .with_replacement_key(vec![7]);
+        assert_eq!(prioritized.commit(), tx.commit());
+
+        // The priority is optional in JSON, so existing clients are unaffected.
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "namespace": 1, "payload": "AQID" })
+        );
+        let json = serde_json::to_string(&prioritized).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Transaction>(&json).unwrap(),
+            prioritized
+        );
+
+        // The binary encoding is what gets sequenced, and leaves the priority out.
+        let bytes = bincode::serialize(&prioritized).unwrap();
+        assert_eq!(bytes, bincode::serialize(&tx).unwrap());
+        assert_eq!(bincode::deserialize::<Transaction>(&bytes).unwrap(), tx);
+    }
+}
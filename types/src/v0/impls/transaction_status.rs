@@ -0,0 +1,100 @@
+//! Progress of a submitted transaction through consensus.
+
+use committable::Commitment;
+use serde::{Deserialize, Serialize};
+
+use crate::Transaction;
+
+/// How far a transaction has made it through consensus
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// The node has received the transaction, but has not seen it in a proposed block yet
+    Received,
+    /// The transaction is in the block proposed to the DA committee for `view`
+    Proposed { view: u64 },
+    /// The DA committee has certified the block containing the transaction, proposed for `view`
+    DaCertified { view: u64 },
+    /// The block containing the transaction has been decided at `height`
+    Decided { view: u64, height: u64 },
+}
+
+impl TransactionStatus {
+    /// Position of this status in the progression from received to decided
+    fn stage(&self) -> u8 {
+        match self {
+            Self::Received => 0,
+            Self::Proposed { .. } => 1,
+            Self::DaCertified { .. } => 2,
+            Self::Decided { .. } => 3,
+        }
+    }
+
+    /// The view of the block containing the transaction, once it has been proposed
+    pub fn view(&self) -> Option<u64> {
+        match self {
+            Self::Received => None,
+            Self::Proposed { view } | Self::DaCertified { view } | Self::Decided { view, .. } => {
+                Some(*view)
+            },
+        }
+    }
+
+    /// Whether `self` is further along than `other`.
+    ///
+    /// A transaction may be proposed again in a later view if its first block is never decided, so
+    /// a proposal in a later view replaces one in an earlier view, whatever stage it reached.
+    pub fn supersedes(&self, other: &Self) -> bool {
+        if other.is_final() {
+            return false;
+        }
+        if self.is_final() {
+            return true;
+        }
+        match (self.view(), other.view()) {
+            (Some(view), Some(old)) if view != old => view > old,
+            _ => self.stage() > other.stage(),
+        }
+    }
+
+    /// Whether the transaction will make no further progress
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Decided { .. })
+    }
+}
+
+/// Receipt for a submitted transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SubmissionReceipt {
+    /// The commitment identifying the transaction, used to query its status
+    pub hash: Commitment<Transaction>,
+    /// The status of the transaction when the receipt was issued
+    pub status: TransactionStatus,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_status_progression() {
+        use TransactionStatus::*;
+
+        assert!(Proposed { view: 1 }.supersedes(&Received));
+        assert!(DaCertified { view: 1 }.supersedes(&Proposed { view: 1 }));
+        assert!(Decided { view: 1, height: 1 }.supersedes(&DaCertified { view: 1 }));
+
+        // A block proposed in a later view replaces one which was not decided.
+        assert!(Proposed { view: 2 }.supersedes(&Proposed { view: 1 }));
+        assert!(Proposed { view: 2 }.supersedes(&DaCertified { view: 1 }));
+        assert!(!Proposed { view: 1 }.supersedes(&DaCertified { view: 2 }));
+        assert!(!Received.supersedes(&Proposed { view: 1 }));
+        assert!(Decided { view: 1, height: 1 }.supersedes(&Proposed { view: 2 }));
+        assert!(!Decided { view: 2, height: 2 }.supersedes(&Decided { view: 1, height: 1 }));
+
+        assert_eq!(
+            serde_json::to_value(Decided { view: 3, height: 2 }).unwrap(),
+            serde_json::json!({ "status": "decided", "view": 3, "height": 2 })
+        );
+    }
+}
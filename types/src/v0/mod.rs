@@ -18,10 +18,15 @@ pub mod traits;
 mod utils;
 pub use header::Header;
 #[cfg(any(test, feature = "testing"))]
-pub use impls::mock;
+pub use impls::{child_leaf, mock, sign_qc, SimulatedL1};
 pub use impls::{
-    get_l1_deposits, retain_accounts, BuilderValidationError, EpochCommittees, FeeError,
-    ProposalValidationError, StateValidationError,
+    get_l1_deposits, retain_accounts, BuilderRegistry, BuilderValidationError, CollisionPolicy,
+    DecryptionError, DecryptionShare, EncryptedPayload, EpochCommittees, FeeError, KeyShare,
+    LeafProof, LeafProofVerifier, NamespaceAdmission, NamespaceRegistry, NamespaceRegistryConfig,
+    NamespaceRegistryError, ProposalValidationError, RegisteredBuilder, RegisteredNamespace,
+    RewardClaim, RewardDistribution, RewardDistributionPage, StateValidationError,
+    SubmissionReceipt, ThresholdEncryptionKey, TransactionStatus, UnregisteredNamespacePolicy,
+    ENCRYPTED_PAYLOAD_PREFIX,
 };
 pub use nsproof::NsProof;
 pub use utils::*;
@@ -37,7 +42,7 @@ use vbs::version::{StaticVersion, StaticVersionType};
 // instead we write `with_minor_versions!(some_macro!(args))`.
 macro_rules! with_minor_versions {
     ($m:ident!($($arg:tt),*)) => {
-        $m!($($arg,)* v0_1, v0_2, v0_3, v0_99);
+        $m!($($arg,)* v0_1, v0_2, v0_3, v0_4, v0_99);
     };
 }
 
@@ -90,6 +95,7 @@ reexport_unchanged_types!(
     L1BlockInfo,
     L1Client,
     L1ClientOptions,
+    L1ReadMode,
     L1Snapshot,
     NamespaceId,
     NsIndex,
@@ -179,6 +185,7 @@ pub type V0_0 = StaticVersion<0, 0>;
 pub type V0_1 = StaticVersion<0, 1>;
 pub type FeeVersion = StaticVersion<0, 2>;
 pub type EpochVersion = StaticVersion<0, 3>;
+pub type BlockLimitsVersion = StaticVersion<0, 4>;
 pub type MarketplaceVersion = StaticVersion<0, 99>;
 
 pub type Leaf = hotshot_types::data::Leaf<SeqTypes>;
@@ -196,4 +203,4 @@ pub use crate::v0_1::{
     BLOCK_MERKLE_TREE_HEIGHT, FEE_MERKLE_TREE_HEIGHT, NS_ID_BYTE_LEN, NS_OFFSET_BYTE_LEN,
     NUM_NSS_BYTE_LEN, NUM_TXS_BYTE_LEN, TX_OFFSET_BYTE_LEN,
 };
-use crate::v0_99::{ChainConfig, SolverAuctionResults};
+use crate::{v0_4::ChainConfig, v0_99::SolverAuctionResults};
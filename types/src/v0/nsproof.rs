@@ -48,3 +48,44 @@ impl NsProof {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use hotshot::traits::BlockPayload;
+    use hotshot_types::vid::avidm::AvidMParam;
+    use sequencer_utils::test_utils::setup_test;
+
+    use super::*;
+
+    /// Prove every namespace of a block. `benches/ns_proof.rs` tracks the cost of doing so.
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ns_proof_all_namespaces() {
+        setup_test();
+
+        // Fill most of a block with 10 namespaces.
+        let txs = (0..25u64)
+            .map(|i| Transaction::new(NamespaceId::from(i % 10), vec![i as u8; 1000]))
+            .collect::<Vec<_>>();
+        let payload = Payload::from_transactions(txs, &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+        let common = VidCommon::V1(AvidMParam::new(34, 100).unwrap());
+
+        let mut proved = 0;
+        for index in payload.ns_table().iter() {
+            let ns_id = payload.ns_table().read_ns_id(&index).unwrap();
+            let proof = NsProof::new(&payload, &index, &common).unwrap();
+            assert_eq!(
+                proof.export_all_txs(&ns_id).len(),
+                if ns_id < NamespaceId::from(5u64) {
+                    3
+                } else {
+                    2
+                }
+            );
+            proved += 1;
+        }
+        assert_eq!(proved, 10);
+    }
+}
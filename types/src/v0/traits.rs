@@ -17,7 +17,7 @@ use hotshot_types::{
         DaProposal, DaProposal2, EpochNumber, QuorumProposal, QuorumProposal2,
         QuorumProposalWrapper, VidCommitment, VidDisperseShare, ViewNumber,
     },
-    drb::DrbResult,
+    drb::{DrbInput, DrbResult},
     event::{HotShotAction, LeafInfo},
     message::{convert_proposal, Proposal, UpgradeLock},
     simple_certificate::{
@@ -40,12 +40,13 @@ use super::{
     impls::NodeState,
     utils::BackoffParams,
     v0_1::{RewardAccount, RewardAccountProof, RewardMerkleCommitment, RewardMerkleTree},
-    v0_3::{EventKey, IndexedStake, StakeTableEvent, Validator},
+    v0_3::{EventKey, IndexedStake, L1EventCheckpoint, StakeTableEvent, Validator},
     EpochVersion, SequencerVersions,
 };
 use crate::{
-    v0::impls::ValidatedState, v0_99::ChainConfig, BlockMerkleTree, Event, FeeAccount,
+    v0::impls::ValidatedState, v0_4::ChainConfig, BlockMerkleTree, Event, FeeAccount,
     FeeAccountProof, FeeMerkleCommitment, FeeMerkleTree, Leaf2, NetworkConfig, SeqTypes,
+    Transaction,
 };
 
 #[async_trait]
@@ -682,6 +683,28 @@ pub trait MembershipPersistence: Send + Sync + 'static {
         events: Vec<(EventKey, StakeTableEvent)>,
     ) -> anyhow::Result<()>;
     async fn load_events(&self) -> anyhow::Result<Option<(u64, Vec<(EventKey, StakeTableEvent)>)>>;
+
+    /// Store the stake table events scanned from the L1 contract at `contract` up to `l1_block`,
+    /// along with the checkpoint of the scan, in the same transaction.
+    async fn store_events_with_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        l1_block: u64,
+        events: Vec<(EventKey, StakeTableEvent)>,
+    ) -> anyhow::Result<()>;
+
+    /// Store how far the events of the L1 contract at `contract` have been scanned.
+    async fn store_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+        checkpoint: L1EventCheckpoint,
+    ) -> anyhow::Result<()>;
+
+    /// Load how far the events of the L1 contract at `contract` have been scanned, if at all.
+    async fn load_l1_checkpoint(
+        &self,
+        contract: alloy::primitives::Address,
+    ) -> anyhow::Result<Option<L1EventCheckpoint>>;
 }
 
 #[async_trait]
@@ -1020,6 +1043,15 @@ pub trait SequencerPersistence: Sized + Send + Sync + Clone + 'static {
         epoch: <SeqTypes as NodeType>::Epoch,
         drb_result: DrbResult,
     ) -> anyhow::Result<()>;
+    /// Store the progress of an ongoing DRB computation.
+    ///
+    /// Only the latest progress for each epoch is kept.
+    async fn store_drb_input(&self, drb_input: DrbInput) -> anyhow::Result<()>;
+    /// Load the latest progress stored for the DRB computation for `epoch`.
+    async fn load_drb_input(
+        &self,
+        epoch: <SeqTypes as NodeType>::Epoch,
+    ) -> anyhow::Result<Option<DrbInput>>;
     async fn add_epoch_root(
         &self,
         epoch: <SeqTypes as NodeType>::Epoch,
@@ -1029,6 +1061,48 @@ pub trait SequencerPersistence: Sized + Send + Sync + Clone + 'static {
         &self,
         state_cert: LightClientStateUpdateCertificate<SeqTypes>,
     ) -> anyhow::Result<()>;
+
+    /// Record a transaction submitted to this node which has not been sequenced yet.
+    ///
+    /// `submitted` is the time the transaction was submitted, in seconds since the Unix epoch.
+    async fn add_pending_transaction(&self, tx: &Transaction, submitted: u64)
+        -> anyhow::Result<()>;
+
+    /// Forget pending transactions, once they have been sequenced.
+    async fn remove_pending_transactions(
+        &self,
+        hashes: &[Commitment<Transaction>],
+    ) -> anyhow::Result<()>;
+
+    /// Forget pending transactions submitted before `submitted_before` (in seconds since the Unix
+    /// epoch).
+    async fn prune_pending_transactions(&self, submitted_before: u64) -> anyhow::Result<()>;
+
+    /// Load all pending transactions, with the times they were submitted.
+    async fn load_pending_transactions(&self) -> anyhow::Result<Vec<(Transaction, u64)>>;
+
+    /// Record that every block up to `height` has been delivered to the notification sink `sink`.
+    async fn store_notification_cursor(&self, sink: &str, height: u64) -> anyhow::Result<()>;
+
+    /// The height of the last block delivered to the notification sink `sink`, if any.
+    async fn load_notification_cursor(&self, sink: &str) -> anyhow::Result<Option<u64>>;
+
+    /// Queue encoded notifications of decided blocks, by block height, for delivery to
+    /// notification sinks.
+    ///
+    /// Notifications which are already queued are replaced.
+    async fn append_notifications(&self, notifications: &[(u64, Vec<u8>)]) -> anyhow::Result<()>;
+
+    /// Load at most `limit` queued notifications, in order of height, starting after `after` if
+    /// given.
+    async fn load_notifications(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(u64, Vec<u8>)>>;
+
+    /// Forget queued notifications for blocks up to `height`.
+    async fn prune_notifications(&self, height: u64) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -1140,6 +1214,17 @@ impl<P: SequencerPersistence> Storage<SeqTypes> for Arc<P> {
         (**self).add_drb_result(epoch, drb_result).await
     }
 
+    async fn store_drb_input(&self, drb_input: DrbInput) -> anyhow::Result<()> {
+        (**self).store_drb_input(drb_input).await
+    }
+
+    async fn load_drb_input(
+        &self,
+        epoch: <SeqTypes as NodeType>::Epoch,
+    ) -> anyhow::Result<Option<DrbInput>> {
+        (**self).load_drb_input(epoch).await
+    }
+
     async fn add_epoch_root(
         &self,
         epoch: <SeqTypes as NodeType>::Epoch,
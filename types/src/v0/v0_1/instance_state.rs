@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-use crate::{v0::utils::Timestamp, v0_99::ChainConfig};
+use crate::{v0::utils::Timestamp, v0_4::ChainConfig};
 
 /// Represents the specific type of upgrade.
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -10,6 +10,7 @@ pub enum UpgradeType {
     Fee { chain_config: ChainConfig },
     Marketplace { chain_config: ChainConfig },
     Epoch { chain_config: ChainConfig },
+    BlockLimits { chain_config: ChainConfig },
 }
 
 impl UpgradeType {
@@ -20,6 +21,7 @@ impl UpgradeType {
             UpgradeType::Fee { chain_config } => Some(*chain_config),
             UpgradeType::Marketplace { chain_config } => Some(*chain_config),
             UpgradeType::Epoch { chain_config } => Some(*chain_config),
+            UpgradeType::BlockLimits { chain_config } => Some(*chain_config),
         }
     }
 }
@@ -1,3 +1,10 @@
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use alloy::{
     network::Ethereum,
     primitives::{B256, U256},
@@ -9,17 +16,12 @@ use alloy::{
 };
 use alloy_compat::ethers_serde;
 use async_broadcast::{InactiveReceiver, Sender};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use derive_more::Deref;
 use hotshot_types::traits::metrics::{Counter, Gauge, Metrics, NoMetrics};
 use lru::LruCache;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::{
-    num::NonZeroUsize,
-    sync::Arc,
-    time::{Duration, Instant},
-};
 use tokio::{
     sync::{Mutex, Notify},
     task::JoinHandle,
@@ -146,6 +148,26 @@ pub struct L1ClientOptions {
     )]
     pub l1_rate_limit_delay: Option<Duration>,
 
+    /// How reads are served when multiple L1 providers are configured.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_READ_MODE",
+        default_value = "failover"
+    )]
+    pub l1_read_mode: L1ReadMode,
+
+    /// Number of providers to send each read to in the `fastest` and `quorum` read modes.
+    ///
+    /// The healthiest providers are chosen, based on their recent success rates.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_L1_READ_FANOUT", default_value = "3")]
+    pub l1_read_fanout: usize,
+
+    /// Number of providers which must agree on the response to a read in the `quorum` read mode.
+    ///
+    /// If not set, a majority of the providers the read is sent to is required.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_L1_READ_QUORUM")]
+    pub l1_read_quorum: Option<usize>,
+
     /// Separate provider to use for subscription feeds.
     ///
     /// Typically this would be a WebSockets endpoint while the main provider uses HTTP.
@@ -156,6 +178,19 @@ pub struct L1ClientOptions {
     pub metrics: Arc<Box<dyn Metrics>>,
 }
 
+/// How an L1 client with multiple providers serves reads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum L1ReadMode {
+    /// Use one provider at a time, failing over to the next when it becomes unhealthy.
+    #[default]
+    Failover,
+    /// Send each read to several providers at once, and use the first successful response.
+    Fastest,
+    /// Send each read to several providers at once, and use the response once enough of them
+    /// agree on it.
+    Quorum,
+}
+
 /// Type alias for alloy provider
 pub type L1Provider = FillProvider<
     JoinFill<Identity, <Ethereum as RecommendedFillers>::RecommendedFillers>,
@@ -192,12 +227,25 @@ pub struct L1Client {
 pub(crate) struct L1State {
     pub(crate) snapshot: L1Snapshot,
     pub(crate) finalized: LruCache<u64, L1BlockInfoWithParent>,
+    /// Recent blocks in the canonical chain, by height, used to detect reorgs.
+    pub(crate) recent: BTreeMap<u64, L1BlockInfoWithParent>,
+    /// Maximum number of blocks kept in `recent`.
+    pub(crate) recent_capacity: usize,
 }
 
 #[derive(Clone, Debug)]
 pub(crate) enum L1Event {
-    NewHead { head: u64 },
-    NewFinalized { finalized: L1BlockInfoWithParent },
+    NewHead {
+        head: u64,
+    },
+    NewFinalized {
+        finalized: L1BlockInfoWithParent,
+    },
+    Reorg {
+        fork: u64,
+        depth: u64,
+        head: L1BlockInfo,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -208,8 +256,17 @@ pub(crate) struct L1ClientMetrics {
     pub(crate) head: Arc<dyn Gauge>,
     pub(crate) finalized: Arc<dyn Gauge>,
     pub(crate) reconnects: Arc<dyn Counter>,
+    pub(crate) reorgs: Arc<dyn Counter>,
+    pub(crate) reorg_depth: Arc<dyn Gauge>,
     pub(crate) failovers: Arc<dyn Counter>,
     pub(crate) failures: Arc<Vec<Box<dyn Counter>>>,
+    pub(crate) requests: Arc<Vec<Box<dyn Counter>>>,
+    pub(crate) health: Arc<Vec<Box<dyn Gauge>>>,
+    pub(crate) quorum_failures: Arc<dyn Counter>,
+    /// How far behind the L1 head the last scan of stake table events was.
+    pub(crate) stake_table_scan_lag: Arc<dyn Gauge>,
+    /// How far behind the L1 head the last scan of fee deposits was.
+    pub(crate) fee_scan_lag: Arc<dyn Gauge>,
 }
 
 /// An RPC client with multiple remote (HTTP) providers.
@@ -225,6 +282,17 @@ pub struct SwitchingTransport {
     pub(crate) opt: Arc<L1ClientOptions>,
     pub(crate) metrics: L1ClientMetrics,
     pub(crate) switch_notify: Arc<Notify>,
+    /// A transport for each of `urls`, used to send reads to several providers at once
+    pub(crate) providers: Arc<Vec<SingleTransport>>,
+    /// The recent health of each of `urls`
+    pub(crate) health: Arc<Vec<RwLock<ProviderHealth>>>,
+}
+
+/// Recent reliability of an L1 provider.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ProviderHealth {
+    /// Moving average of the success rate of recent requests, between 0 and 1
+    pub(crate) score: f64,
 }
 
 /// The state of the current provider being used by a [`SwitchingTransport`].
@@ -244,4 +312,4 @@ pub(crate) struct SingleTransportStatus {
     pub(crate) rate_limited_until: Option<Instant>,
     /// Whether or not this current transport is being shut down (switching to the next transport)
     pub(crate) shutting_down: bool,
-}
\ No newline at end of file
+}
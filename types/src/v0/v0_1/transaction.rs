@@ -1,23 +1,19 @@
 use derive_more::{Display, From, Into};
 
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
-#[derive(
-    Clone,
-    Serialize,
-    Deserialize,
-    Debug,
-    PartialEq,
-    Eq,
-    Hash,
-    CanonicalSerialize,
-    CanonicalDeserialize,
-)]
+/// A transaction, along with the priority chosen by its submitter
+///
+/// Only the namespace and payload are sequenced: they make up the commitment and the binary
+/// encoding. The priority fee and replacement key are only meaningful to the local builder of the
+/// node the transaction is submitted to, and appear in the JSON encoding when set.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CanonicalSerialize, CanonicalDeserialize)]
 pub struct Transaction {
     pub(crate) namespace: NamespaceId,
-    #[serde(with = "base64_bytes")]
     pub(crate) payload: Vec<u8>,
+    pub(crate) priority_fee: u64,
+    pub(crate) replacement_key: Option<Vec<u8>>,
 }
 
 #[derive(
@@ -1,4 +1,4 @@
-use crate::{v0_1, v0_99, BlockSize, ChainId, FeeAccount, FeeAmount};
+use crate::{v0_1, v0_4, v0_99, BlockSize, ChainId, FeeAccount, FeeAmount};
 use alloy::primitives::{Address, U256};
 use alloy_compat::ethers_serde;
 use committable::{Commitment, Committable};
@@ -167,6 +167,29 @@ impl From<v0_99::ChainConfig> for ChainConfig {
     }
 }
 
+impl From<v0_4::ChainConfig> for ChainConfig {
+    fn from(chain_config: v0_4::ChainConfig) -> ChainConfig {
+        let v0_4::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            ..
+        } = chain_config;
+
+        ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+        }
+    }
+}
+
 impl Default for ChainConfig {
     fn default() -> Self {
         Self {
@@ -1,18 +1,24 @@
 use std::{collections::HashMap, sync::Arc};
 
-use crate::{traits::{MembershipPersistence, StateCatchup}, SeqTypes};
 use alloy::primitives::{Address, U256};
 use derive_more::derive::{From, Into};
 use hotshot::types::{BLSPubKey, SignatureKey};
-use hotshot_contract_adapter::sol_types::StakeTable::{ConsensusKeysUpdated, Delegated, Undelegated, ValidatorExit, ValidatorRegistered};
+use hotshot_contract_adapter::sol_types::StakeTable::{
+    ConsensusKeysUpdated, Delegated, Undelegated, ValidatorExit, ValidatorRegistered,
+};
 use hotshot_types::{
     data::EpochNumber, light_client::StateVerKey, network::PeerConfigKeys,
     traits::node_implementation::NodeType, PeerConfig,
 };
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use crate::v0::ChainConfig;
+
 use super::L1Client;
+use crate::{
+    traits::{MembershipPersistence, StateCatchup},
+    v0::ChainConfig,
+    SeqTypes,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, From)]
 #[serde(bound = "TYPES: NodeType")]
@@ -54,24 +60,32 @@ pub struct Delegator {
     pub stake: U256,
 }
 
+/// A change of a validator's consensus key from one epoch to the next.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct KeyRotation {
+    pub account: Address,
+    /// The key used up to the epoch before the rotation
+    pub old_key: BLSPubKey,
+    /// The key used from the epoch of the rotation
+    pub new_key: BLSPubKey,
+}
+
 /// Type for holding result sets matching epochs to stake tables.
 pub type IndexedStake = (
     EpochNumber,
     IndexMap<alloy::primitives::Address, Validator<BLSPubKey>>,
 );
 
-
-
 #[derive(Clone, derive_more::derive::Debug)]
 pub struct StakeTableFetcher {
     /// Peers for catching up the stake table
     #[debug(skip)]
-    pub(crate) peers: Arc<dyn StateCatchup>, 
+    pub(crate) peers: Arc<dyn StateCatchup>,
     /// Methods for stake table persistence.
     #[debug(skip)]
-    pub(crate)  persistence: Arc<dyn MembershipPersistence>,
+    pub(crate) persistence: Arc<dyn MembershipPersistence>,
     /// L1 provider
-    pub(crate)  l1_client: L1Client,
+    pub(crate) l1_client: L1Client,
     /// Verifiable `ChainConfig` holding contract address
     pub(crate) chain_config: ChainConfig,
 }
@@ -79,6 +93,15 @@ pub struct StakeTableFetcher {
 // (log block number, log index)
 pub type EventKey = (u64, u64);
 
+/// How far the events of an L1 contract have been scanned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1EventCheckpoint {
+    /// The last L1 block scanned for events.
+    pub l1_block: u64,
+    /// The last event processed, if any.
+    pub last_event: Option<EventKey>,
+}
+
 #[derive(Clone, derive_more::From, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum StakeTableEvent {
     Register(ValidatorRegistered),
@@ -86,4 +109,4 @@ pub enum StakeTableEvent {
     Delegate(Delegated),
     Undelegate(Undelegated),
     KeyUpdate(ConsensusKeysUpdated),
-}
\ No newline at end of file
+}
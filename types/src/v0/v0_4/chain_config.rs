@@ -0,0 +1,427 @@
+use alloy::primitives::{Address, U256};
+use alloy_compat::ethers_serde;
+use committable::{Commitment, Committable};
+use hotshot_types::traits::block_contents::BlockLimits;
+use itertools::Either;
+use serde::{Deserialize, Serialize};
+use vbs::version::{StaticVersionType, Version};
+
+use crate::{
+    v0_1, v0_3, v0_99, BlockLimitsVersion, BlockSize, ChainId, FeeAccount, FeeAmount,
+    NamespaceRegistry,
+};
+
+/// Global variables for an Espresso blockchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChainConfig {
+    /// Espresso chain ID
+    pub chain_id: ChainId,
+
+    /// Maximum size in bytes of a block
+    pub max_block_size: BlockSize,
+
+    /// Minimum fee in WEI per byte of payload
+    pub base_fee: FeeAmount,
+
+    /// Fee contract address on L1.
+    ///
+    /// This is optional so that fees can easily be toggled on/off, with no need to deploy a
+    /// contract when they are off. In a future release, after fees are switched on and thoroughly
+    /// tested, this may be made mandatory.
+    #[serde(with = "ethers_serde::option_address")]
+    pub fee_contract: Option<Address>,
+
+    /// Account that receives sequencing fees.
+    ///
+    /// This account in the Espresso fee ledger will always receive every fee paid in Espresso,
+    /// regardless of whether or not their is a `fee_contract` deployed. Once deployed, the fee
+    /// contract can decide what to do with tokens locked in this account in Espresso.
+    pub fee_recipient: FeeAccount,
+
+    /// `StakeTable `(proxy) contract address on L1.
+    ///
+    /// This is optional so that stake can easily be toggled on/off, with no need to deploy a
+    /// contract when they are off. In a future release, after PoS is switched on and thoroughly
+    /// tested, this may be made mandatory.
+    #[serde(with = "ethers_serde::option_address")]
+    pub stake_table_contract: Option<Address>,
+
+    /// Account that receives sequencing bids.
+    pub bid_recipient: Option<FeeAccount>,
+
+    /// Maximum number of transactions in a block, unlimited if absent.
+    pub max_block_transactions: Option<u64>,
+
+    /// Maximum size in bytes of the payload of any one namespace in a block, unlimited if absent.
+    pub max_namespace_size: Option<BlockSize>,
+
+    /// Commitment to the registry of the rollups owning each namespace, if any.
+    ///
+    /// Blocks are validated against the registry, which nodes are given in their genesis file.
+    pub namespace_registry: Option<Commitment<NamespaceRegistry>>,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Deserialize, Serialize, Eq, Hash)]
+/// A commitment to a ChainConfig or a full ChainConfig.
+pub struct ResolvableChainConfig {
+    pub(crate) chain_config: Either<ChainConfig, Commitment<ChainConfig>>,
+}
+
+impl Committable for ChainConfig {
+    fn tag() -> String {
+        "CHAIN_CONFIG".to_string()
+    }
+
+    fn commit(&self) -> Commitment<Self> {
+        let comm = committable::RawCommitmentBuilder::new(&Self::tag())
+            .fixed_size_field("chain_id", &self.chain_id.to_fixed_bytes())
+            .u64_field("max_block_size", *self.max_block_size)
+            .fixed_size_field("base_fee", &self.base_fee.to_fixed_bytes())
+            .fixed_size_field("fee_recipient", &self.fee_recipient.to_fixed_bytes());
+        let comm = if let Some(addr) = self.fee_contract {
+            comm.u64_field("fee_contract", 1).fixed_size_bytes(&addr.0)
+        } else {
+            comm.u64_field("fee_contract", 0)
+        };
+
+        let comm = if let Some(addr) = self.stake_table_contract {
+            comm.u64_field("stake_table_contract", 1)
+                .fixed_size_bytes(&addr.0)
+        } else {
+            comm
+        };
+
+        let comm = if let Some(bid_recipient) = self.bid_recipient {
+            comm.fixed_size_field("bid_recipient", &bid_recipient.to_fixed_bytes())
+        } else {
+            comm
+        };
+
+        // With `ChainConfig` upgrades we want commitments w/out
+        // fields added >= v0_4 to have the same commitment as < v0_4
+        // commitment. Therefore `None` values are simply ignored.
+        let comm = if let Some(max) = self.max_block_transactions {
+            comm.u64_field("max_block_transactions", max)
+        } else {
+            comm
+        };
+        let comm = if let Some(max) = self.max_namespace_size {
+            comm.u64_field("max_namespace_size", *max)
+        } else {
+            comm
+        };
+        let comm = if let Some(registry) = self.namespace_registry {
+            comm.field("namespace_registry", registry)
+        } else {
+            comm
+        };
+
+        comm.finalize()
+    }
+}
+
+impl ChainConfig {
+    /// Limits on the contents of blocks under this configuration.
+    pub fn block_limits(&self) -> BlockLimits {
+        BlockLimits {
+            max_block_size: Some(*self.max_block_size),
+            max_transactions: self.max_block_transactions,
+            max_group_size: self.max_namespace_size.map(|max| *max),
+        }
+    }
+
+    /// Whether headers of `version` can carry this configuration.
+    ///
+    /// Only v0.4 headers carry the fields added in v0.4. Headers of other versions drop them, so
+    /// a configuration which sets any of them cannot be committed to by those headers.
+    pub fn supported_in(&self, version: Version) -> bool {
+        version == BlockLimitsVersion::version()
+            || (self.max_block_transactions.is_none()
+                && self.max_namespace_size.is_none()
+                && self.namespace_registry.is_none())
+    }
+}
+
+impl ResolvableChainConfig {
+    pub fn commit(&self) -> Commitment<ChainConfig> {
+        match self.chain_config {
+            Either::Left(config) => config.commit(),
+            Either::Right(commitment) => commitment,
+        }
+    }
+    pub fn resolve(self) -> Option<ChainConfig> {
+        match self.chain_config {
+            Either::Left(config) => Some(config),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+impl From<Commitment<ChainConfig>> for ResolvableChainConfig {
+    fn from(value: Commitment<ChainConfig>) -> Self {
+        Self {
+            chain_config: Either::Right(value),
+        }
+    }
+}
+
+impl From<ChainConfig> for ResolvableChainConfig {
+    fn from(value: ChainConfig) -> Self {
+        Self {
+            chain_config: Either::Left(value),
+        }
+    }
+}
+
+impl From<&v0_1::ResolvableChainConfig> for ResolvableChainConfig {
+    fn from(
+        &v0_1::ResolvableChainConfig { chain_config }: &v0_1::ResolvableChainConfig,
+    ) -> ResolvableChainConfig {
+        match chain_config {
+            Either::Left(chain_config) => ResolvableChainConfig {
+                chain_config: Either::Left(ChainConfig::from(chain_config)),
+            },
+            Either::Right(c) => ResolvableChainConfig {
+                chain_config: Either::Right(Commitment::from_raw(*c.as_ref())),
+            },
+        }
+    }
+}
+
+impl From<&v0_3::ResolvableChainConfig> for ResolvableChainConfig {
+    fn from(
+        &v0_3::ResolvableChainConfig { chain_config }: &v0_3::ResolvableChainConfig,
+    ) -> ResolvableChainConfig {
+        match chain_config {
+            Either::Left(chain_config) => ResolvableChainConfig {
+                chain_config: Either::Left(ChainConfig::from(chain_config)),
+            },
+            Either::Right(c) => ResolvableChainConfig {
+                chain_config: Either::Right(Commitment::from_raw(*c.as_ref())),
+            },
+        }
+    }
+}
+
+impl From<&v0_99::ResolvableChainConfig> for ResolvableChainConfig {
+    fn from(
+        &v0_99::ResolvableChainConfig { chain_config }: &v0_99::ResolvableChainConfig,
+    ) -> ResolvableChainConfig {
+        match chain_config {
+            Either::Left(chain_config) => ResolvableChainConfig {
+                chain_config: Either::Left(ChainConfig::from(chain_config)),
+            },
+            Either::Right(c) => ResolvableChainConfig {
+                chain_config: Either::Right(Commitment::from_raw(*c.as_ref())),
+            },
+        }
+    }
+}
+
+impl From<v0_1::ChainConfig> for ChainConfig {
+    fn from(chain_config: v0_1::ChainConfig) -> ChainConfig {
+        let v0_1::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            ..
+        } = chain_config;
+
+        ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract: None,
+            bid_recipient: None,
+            max_block_transactions: None,
+            max_namespace_size: None,
+            namespace_registry: None,
+        }
+    }
+}
+
+impl From<v0_3::ChainConfig> for ChainConfig {
+    fn from(chain_config: v0_3::ChainConfig) -> ChainConfig {
+        let v0_3::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            ..
+        } = chain_config;
+
+        ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            bid_recipient: None,
+            max_block_transactions: None,
+            max_namespace_size: None,
+            namespace_registry: None,
+        }
+    }
+}
+
+impl From<v0_99::ChainConfig> for ChainConfig {
+    fn from(chain_config: v0_99::ChainConfig) -> ChainConfig {
+        let v0_99::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            bid_recipient,
+        } = chain_config;
+
+        ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            bid_recipient,
+            max_block_transactions: None,
+            max_namespace_size: None,
+            namespace_registry: None,
+        }
+    }
+}
+
+impl From<ChainConfig> for v0_1::ChainConfig {
+    fn from(chain_config: ChainConfig) -> v0_1::ChainConfig {
+        let ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            ..
+        } = chain_config;
+
+        v0_1::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+        }
+    }
+}
+
+impl From<ChainConfig> for v0_99::ChainConfig {
+    fn from(chain_config: ChainConfig) -> v0_99::ChainConfig {
+        let ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            bid_recipient,
+            ..
+        } = chain_config;
+
+        v0_99::ChainConfig {
+            chain_id,
+            max_block_size,
+            base_fee,
+            fee_contract,
+            fee_recipient,
+            stake_table_contract,
+            bid_recipient,
+        }
+    }
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: U256::from(35353).into(), // arbitrarily chosen chain ID
+            max_block_size: 30720.into(),
+            base_fee: 0.into(),
+            fee_contract: None,
+            fee_recipient: Default::default(),
+            stake_table_contract: None,
+            bid_recipient: None,
+            max_block_transactions: None,
+            max_namespace_size: None,
+            namespace_registry: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{EpochVersion, MarketplaceVersion};
+
+    #[test]
+    fn test_upgrade_chain_config_v4_resolvable_chain_config_from_v1() {
+        let expectation: ResolvableChainConfig = ChainConfig::default().into();
+        let v1_resolvable: v0_1::ResolvableChainConfig = v0_1::ChainConfig::default().into();
+        let v4_resolvable: ResolvableChainConfig = ResolvableChainConfig::from(&v1_resolvable);
+        assert_eq!(expectation, v4_resolvable);
+        let expectation: ResolvableChainConfig = ChainConfig::default().commit().into();
+        let v1_resolvable: v0_1::ResolvableChainConfig =
+            v0_1::ChainConfig::default().commit().into();
+        let v4_resolvable: ResolvableChainConfig = ResolvableChainConfig::from(&v1_resolvable);
+        assert_eq!(expectation, v4_resolvable);
+    }
+
+    #[test]
+    fn test_upgrade_chain_config_v4_resolvable_chain_config_from_v99() {
+        let expectation: ResolvableChainConfig = ChainConfig::default().into();
+        let v99_resolvable: v0_99::ResolvableChainConfig = v0_99::ChainConfig::default().into();
+        let v4_resolvable: ResolvableChainConfig = ResolvableChainConfig::from(&v99_resolvable);
+        assert_eq!(expectation, v4_resolvable);
+        let expectation: ResolvableChainConfig = ChainConfig::default().commit().into();
+        let v99_resolvable: v0_99::ResolvableChainConfig =
+            v0_99::ChainConfig::default().commit().into();
+        let v4_resolvable: ResolvableChainConfig = ResolvableChainConfig::from(&v99_resolvable);
+        assert_eq!(expectation, v4_resolvable);
+    }
+
+    #[test]
+    fn test_upgrade_chain_config_v3_chain_config_from_v4() {
+        let expectation = v0_3::ChainConfig::default();
+        let v4_chain_config = ChainConfig::default();
+        let v3_chain_config = v0_3::ChainConfig::from(v4_chain_config);
+        assert_eq!(expectation, v3_chain_config);
+    }
+
+    #[test]
+    fn test_chain_config_limits_require_v4() {
+        let unlimited = ChainConfig::default();
+        let limited = ChainConfig {
+            max_block_transactions: Some(10),
+            max_namespace_size: Some(1000.into()),
+            namespace_registry: Some(NamespaceRegistry::default().commit()),
+            ..Default::default()
+        };
+
+        // Configurations without limits commit as they did before v0.4.
+        assert_eq!(
+            unlimited.commit(),
+            v0_99::ChainConfig::from(unlimited).commit()
+        );
+        assert_ne!(unlimited.commit(), limited.commit());
+
+        for version in [EpochVersion::version(), MarketplaceVersion::version()] {
+            assert!(unlimited.supported_in(version));
+            assert!(!limited.supported_in(version));
+        }
+        assert!(limited.supported_in(BlockLimitsVersion::version()));
+    }
+}
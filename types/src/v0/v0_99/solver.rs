@@ -1,9 +1,13 @@
-use crate::{v0::utils::Update, FeeAmount, NamespaceId, SeqTypes};
 use hotshot::types::SignatureKey;
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{data::ViewNumber, traits::node_implementation::NodeType};
 use serde::{Deserialize, Serialize};
 use tide_disco::Url;
 
+use crate::{
+    eth_signature_key::BuilderSignature, v0::utils::Update, FeeAccount, FeeAmount, NamespaceId,
+    SeqTypes,
+};
+
 #[derive(PartialEq, Serialize, Deserialize, Debug, Clone)]
 pub struct RollupRegistration {
     pub body: RollupRegistrationBody,
@@ -56,3 +60,42 @@ pub struct RollupUpdatebody {
     // Optional field for human readable information
     pub text: Update<String>,
 }
+
+/// Registration of a builder key with the solver.
+///
+/// Only bids and bundle reservations signed by registered builder keys are accepted by the solver.
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct BuilderRegistration {
+    pub body: BuilderRegistrationBody,
+    // signature over the above data, by the key of `account`
+    pub signature: BuilderSignature,
+}
+
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct BuilderRegistrationBody {
+    // The builder account, whose key signs bids and reservations
+    pub account: FeeAccount,
+    // URL the HotShot leader will use to request bundles from this builder
+    pub url: Url,
+}
+
+/// A reservation of bundle slots for an upcoming view.
+///
+/// A builder holding a reservation for a namespace is asked for the bundle for that namespace if
+/// no bid for the namespace wins the auction, in place of the rollup's reserve builder.
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct BundleReservation {
+    pub body: BundleReservationBody,
+    // signature over the above data, by the key of `account`
+    pub signature: BuilderSignature,
+}
+
+#[derive(PartialEq, Eq, Serialize, Deserialize, Debug, Clone)]
+pub struct BundleReservationBody {
+    // The registered builder account making the reservation
+    pub account: FeeAccount,
+    // The view to reserve bundle slots in
+    pub view: ViewNumber,
+    // The namespaces to reserve bundle slots for
+    pub namespaces: Vec<NamespaceId>,
+}
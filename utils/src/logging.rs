@@ -1,6 +1,21 @@
+#[cfg(feature = "otlp")]
+use anyhow::Context;
 use clap::{Parser, ValueEnum};
-use hotshot::helpers::initialize_logging;
+use hotshot::helpers::initialize_logging_with_layer;
+#[cfg(feature = "otlp")]
+use hotshot_types::telemetry;
 use log_panics::BacktraceMode;
+#[cfg(feature = "otlp")]
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+#[cfg(feature = "otlp")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(feature = "otlp")]
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+#[cfg(feature = "otlp")]
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{registry::Registry, Layer};
+#[cfg(feature = "otlp")]
+use url::Url;
 
 /// Controls how backtraces are logged on panic.
 ///
@@ -12,12 +27,15 @@ use log_panics::BacktraceMode;
 /// * `json`: output the panic message and stack trace as a tracing event. This in turn works with
 ///   the behavior of the tracing subscriber with `RUST_LOG_FORMAT=json` to output the event in a
 ///   machine-parseable, JSON format.
+/// * `structured`: like `json`, but log events are output in a JSON format with a stable schema
+///   (see [`StructuredJsonLayer`](hotshot::helpers::StructuredJsonLayer)).
 #[derive(Clone, Copy, Debug, Default, ValueEnum)]
 enum BacktraceLoggingMode {
     #[default]
     Full,
     Compact,
     Json,
+    Structured,
 }
 
 /// Logging configuration.
@@ -25,6 +43,34 @@ enum BacktraceLoggingMode {
 pub struct Config {
     #[clap(long, env = "RUST_LOG_FORMAT")]
     backtrace_mode: Option<BacktraceLoggingMode>,
+
+    /// OTLP (gRPC) endpoint to export tracing spans to.
+    ///
+    /// If not provided, spans are not exported and tracing is log-only. Only available with the
+    /// `otlp` feature.
+    #[cfg(feature = "otlp")]
+    #[clap(long, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<Url>,
+
+    /// Service name to report to the OTLP collector.
+    #[cfg(feature = "otlp")]
+    #[clap(long, env = "OTEL_SERVICE_NAME", default_value = "espresso-sequencer")]
+    otlp_service_name: String,
+
+    /// Filter for spans exported to the OTLP collector, in `RUST_LOG` syntax.
+    #[cfg(feature = "otlp")]
+    #[clap(long, env = "ESPRESSO_OTLP_FILTER", default_value = "info")]
+    otlp_filter: String,
+
+    /// Namespace for deriving per-view trace IDs.
+    ///
+    /// Nodes configured with the same namespace use the same trace ID for each view, so that a
+    /// collector receiving spans from all of them shows a single trace per view, spanning all
+    /// nodes. If not provided, each node uses a random namespace, and traces are correlated only
+    /// across the tasks of a single node.
+    #[cfg(feature = "otlp")]
+    #[clap(long, env = "ESPRESSO_OTLP_TRACE_NAMESPACE")]
+    otlp_trace_namespace: Option<String>,
 }
 
 impl Config {
@@ -34,13 +80,76 @@ impl Config {
     }
 
     /// Initialize logging and panic handlers based on this configuration.
+    ///
+    /// If OTLP export is enabled, this must be called from within a Tokio runtime, which is used to
+    /// export spans in the background. The same goes for the `tokio-console` server, if built with
+    /// the `tokio-console` feature.
     pub fn init(&self) {
-        initialize_logging();
+        #[cfg(feature = "otlp")]
+        let otlp = self.otlp_endpoint.as_ref().and_then(|endpoint| {
+            self.otlp_layer(endpoint)
+                .inspect_err(|err| eprintln!("failed to initialize OTLP export: {err:#}"))
+                .ok()
+        });
+        #[cfg(not(feature = "otlp"))]
+        let otlp = None::<Box<dyn Layer<Registry> + Send + Sync>>;
+        #[cfg(feature = "otlp")]
+        let exporting = otlp.is_some();
+
+        // The console server listens on `TOKIO_CONSOLE_BIND` (127.0.0.1:6669 by default). It only
+        // sees tasks if the runtime was built with `--cfg tokio_unstable`.
+        #[cfg(feature = "tokio-console")]
+        let console = Some(
+            console_subscriber::ConsoleLayer::builder()
+                .with_default_env()
+                .spawn()
+                .boxed(),
+        );
+        #[cfg(not(feature = "tokio-console"))]
+        let console = None;
+
+        let layers = otlp.into_iter().chain(console).collect::<Vec<_>>();
+        initialize_logging_with_layer((!layers.is_empty()).then_some(layers));
+        #[cfg(feature = "otlp")]
+        if exporting {
+            tracing::info!(endpoint = ?self.otlp_endpoint, "exporting spans via OTLP");
+        }
 
-        if let BacktraceLoggingMode::Json = self.backtrace_mode.unwrap_or_default() {
+        if let BacktraceLoggingMode::Json | BacktraceLoggingMode::Structured =
+            self.backtrace_mode.unwrap_or_default()
+        {
             log_panics::Config::new()
                 .backtrace_mode(BacktraceMode::Resolved)
                 .install_panic_hook();
         }
     }
+
+    #[cfg(feature = "otlp")]
+    fn otlp_layer(&self, endpoint: &Url) -> anyhow::Result<Box<dyn Layer<Registry> + Send + Sync>> {
+        let exporter = SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint.to_string())
+            .build()
+            .context("building OTLP exporter")?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                self.otlp_service_name.clone(),
+            )]))
+            .build();
+        let tracer = provider.tracer("espresso");
+        opentelemetry::global::set_tracer_provider(provider);
+
+        if let Some(namespace) = &self.otlp_trace_namespace {
+            telemetry::set_trace_namespace(namespace);
+        }
+        telemetry::enable_task_spans();
+
+        let filter = EnvFilter::try_new(&self.otlp_filter).context("parsing OTLP filter")?;
+        Ok(tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(filter)
+            .boxed())
+    }
 }
@@ -45,6 +45,12 @@ impl NsAvidMShare {
     pub fn payload_byte_len(&self) -> usize {
         self.ns_lens.iter().sum()
     }
+
+    /// Return, for each namespace, the range of the encoded namespace held by this share. The
+    /// length of a range is the weight of the share in that namespace.
+    pub fn ns_ranges(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.content.iter().map(|content| content.range.clone())
+    }
 }
 
 impl NsAvidMScheme {
@@ -82,12 +88,44 @@ impl NsAvidMScheme {
         payload: &[u8],
         ns_table: impl IntoIterator<Item = Range<usize>>,
     ) -> VidResult<(NsAvidMCommit, Vec<NsAvidMShare>)> {
+        Self::ns_disperse_cancellable(param, distribution, payload, ns_table, || false)
+    }
+
+    /// Same as [`ns_disperse`](Self::ns_disperse), but gives up with [`VidError::Cancelled`] as
+    /// soon as `is_cancelled` returns true.
+    ///
+    /// `is_cancelled` is checked before encoding each namespace and before distributing its
+    /// shares among the storage nodes.
+    pub fn ns_disperse_cancellable(
+        param: &NsAvidMParam,
+        distribution: &[u32],
+        payload: &[u8],
+        ns_table: impl IntoIterator<Item = Range<usize>>,
+        is_cancelled: impl Fn() -> bool,
+    ) -> VidResult<(NsAvidMCommit, Vec<NsAvidMShare>)> {
+        let check_cancelled = || {
+            if is_cancelled() {
+                Err(VidError::Cancelled)
+            } else {
+                Ok(())
+            }
+        };
         let mut ns_commits = vec![];
         let mut disperses = vec![];
         let mut ns_lens = vec![];
         for ns_range in ns_table {
             ns_lens.push(ns_range.len());
-            let (commit, shares) = AvidMScheme::disperse(param, distribution, &payload[ns_range])?;
+            check_cancelled()?;
+            let ns_payload = &payload[ns_range];
+            let (mt, raw_shares) = AvidMScheme::pad_and_encode(param, ns_payload)?;
+            check_cancelled()?;
+            let (commit, shares) = AvidMScheme::distribute_shares(
+                param,
+                distribution,
+                mt,
+                raw_shares,
+                ns_payload.len(),
+            )?;
             ns_commits.push(commit.commit);
             disperses.push(shares);
         }
@@ -181,9 +219,11 @@ impl NsAvidMScheme {
 /// Unit tests
 #[cfg(test)]
 pub mod tests {
+    use std::cell::Cell;
+
     use rand::{seq::SliceRandom, RngCore};
 
-    use crate::avid_m::namespaced::NsAvidMScheme;
+    use crate::{avid_m::namespaced::NsAvidMScheme, VidError};
 
     #[test]
     fn round_trip() {
@@ -249,4 +289,40 @@ pub mod tests {
         let payload_recovered = NsAvidMScheme::recover(&params, &shares[..cut_index]).unwrap();
         assert_eq!(payload_recovered, payload);
     }
+
+    #[test]
+    fn cancelled_disperse() {
+        let weights = [1u32, 2, 3];
+        let params = NsAvidMScheme::setup(2, 6).unwrap();
+        let payload = vec![7u8; 48];
+        let ns_table = [(0usize..15), (15..48)];
+
+        // Cancelling after the first namespace is encoded stops before the second one.
+        let checks = Cell::new(0);
+        let result = NsAvidMScheme::ns_disperse_cancellable(
+            &params,
+            &weights,
+            &payload,
+            ns_table.iter().cloned(),
+            || {
+                checks.set(checks.get() + 1);
+                checks.get() > 2
+            },
+        );
+        assert!(matches!(result, Err(VidError::Cancelled)));
+        assert_eq!(checks.get(), 3);
+
+        let (commit, _) = NsAvidMScheme::ns_disperse_cancellable(
+            &params,
+            &weights,
+            &payload,
+            ns_table.iter().cloned(),
+            || false,
+        )
+        .unwrap();
+        assert_eq!(
+            commit,
+            NsAvidMScheme::commit(&params, &payload, ns_table.iter().cloned()).unwrap()
+        );
+    }
 }
@@ -37,6 +37,8 @@ pub enum VidError {
     InvalidParam,
     /// Invalid VID share
     InvalidShare,
+    /// Dispersal cancelled
+    Cancelled,
 }
 
 impl From<Poseidon2Error> for VidError {